@@ -597,10 +597,11 @@ pub fn invalid_session_client_test<T>(
 
     // Read the variable and expect that to fail
     let read_nodes = vec![ReadValueId::from(v1_node_id())];
-    let status_code = session
+    let err = session
         .read(&read_nodes, TimestampsToReturn::Both, 1.0)
         .unwrap_err();
-    assert_eq!(status_code, StatusCode::BadSessionNotActivated);
+    assert!(matches!(err, ServiceError::SessionInvalid(_)));
+    assert_eq!(err.status_code(), StatusCode::BadSessionNotActivated);
 
     session.disconnect();
 }
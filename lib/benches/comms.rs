@@ -0,0 +1,129 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Benchmarks of the comms layer, i.e. turning messages into chunks and back again, and applying
+//! and removing the security of a chunk with each security policy.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use opcua::core::{
+    comms::{chunker::Chunker, secure_channel::SecureChannel, tcp_types::MIN_CHUNK_SIZE},
+    supported_message::SupportedMessage,
+};
+use opcua::crypto::SecurityPolicy;
+use opcua::types::*;
+
+/// A read response with enough values to be split into many chunks
+fn make_read_response(value_count: u32) -> SupportedMessage {
+    let results = (0..value_count).map(DataValue::new_now).collect();
+    ReadResponse {
+        response_header: ResponseHeader::null(),
+        results: Some(results),
+        diagnostic_infos: None,
+    }
+    .into()
+}
+
+fn decoding_options() -> DecodingOptions {
+    DecodingOptions {
+        max_chunk_count: 0,
+        max_array_length: 100000,
+        ..Default::default()
+    }
+}
+
+fn chunk_assembly(c: &mut Criterion) {
+    let mut secure_channel = SecureChannel::new_no_certificate_store();
+    secure_channel.set_decoding_options(decoding_options());
+
+    let mut group = c.benchmark_group("chunk_assembly");
+    for value_count in [100, 10000] {
+        let message = make_read_response(value_count);
+        group.throughput(Throughput::Bytes(message.byte_len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("encode", value_count),
+            &message,
+            |b, message| {
+                b.iter(|| {
+                    Chunker::encode(1, 1, 0, MIN_CHUNK_SIZE, &secure_channel, message).unwrap()
+                })
+            },
+        );
+        let chunks = Chunker::encode(1, 1, 0, MIN_CHUNK_SIZE, &secure_channel, &message).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("decode", value_count),
+            &chunks,
+            |b, chunks| b.iter(|| Chunker::decode(chunks, &secure_channel, None).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+/// Makes a secure channel with the nonces of both ends and the keys derived from them
+fn make_secure_channel(
+    security_mode: MessageSecurityMode,
+    security_policy: SecurityPolicy,
+    local_nonce: &[u8],
+    remote_nonce: &[u8],
+) -> SecureChannel {
+    let mut secure_channel = SecureChannel::new_no_certificate_store();
+    secure_channel.set_security_mode(security_mode);
+    secure_channel.set_security_policy(security_policy);
+    secure_channel.set_local_nonce(local_nonce);
+    secure_channel.set_remote_nonce(remote_nonce);
+    secure_channel.derive_keys();
+    secure_channel
+}
+
+fn symmetric_security(c: &mut Criterion) {
+    let security_policies = [
+        SecurityPolicy::Basic128Rsa15,
+        SecurityPolicy::Basic256,
+        SecurityPolicy::Basic256Sha256,
+        SecurityPolicy::Aes128Sha256RsaOaep,
+        SecurityPolicy::Aes256Sha256RsaPss,
+    ];
+    let security_modes = [
+        MessageSecurityMode::Sign,
+        MessageSecurityMode::SignAndEncrypt,
+    ];
+    let message = make_read_response(400);
+
+    let mut group = c.benchmark_group("symmetric_security");
+    for security_policy in security_policies {
+        let nonce_length = security_policy.secure_channel_nonce_length();
+        let local_nonce: Vec<u8> = (0..nonce_length).map(|i| i as u8).collect();
+        let remote_nonce: Vec<u8> = (0..nonce_length).map(|i| (i + 100) as u8).collect();
+        for security_mode in security_modes {
+            let sender =
+                make_secure_channel(security_mode, security_policy, &local_nonce, &remote_nonce);
+            let mut receiver =
+                make_secure_channel(security_mode, security_policy, &remote_nonce, &local_nonce);
+            receiver.set_decoding_options(decoding_options());
+
+            let chunks = Chunker::encode(1, 1, 0, 0, &sender, &message).unwrap();
+            let chunk = &chunks[0];
+            let mut secured = vec![0u8; chunk.data.len() + 4096];
+            let secured_size = sender.apply_security(chunk, &mut secured).unwrap();
+            let name = format!("{}/{:?}", security_policy, security_mode);
+
+            group.throughput(Throughput::Bytes(chunk.data.len() as u64));
+            group.bench_function(BenchmarkId::new("apply", &name), |b| {
+                let mut dst = vec![0u8; secured.len()];
+                b.iter(|| sender.apply_security(chunk, &mut dst).unwrap())
+            });
+            group.bench_function(BenchmarkId::new("verify_and_remove", &name), |b| {
+                b.iter(|| {
+                    receiver
+                        .verify_and_remove_security(&secured[..secured_size])
+                        .unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, chunk_assembly, symmetric_security);
+criterion_main!(benches);
@@ -0,0 +1,235 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Benchmark of the end to end throughput of data change notifications from a server to a
+//! client in the same process over the loopback transport.
+//!
+//! Each iteration changes the values of all the monitored variables and waits until the client
+//! has received a notification for each of them. The server only publishes on the ticks of its
+//! subscription timer, so the time includes waiting for the next tick and is most useful to
+//! compare with the time of another run.
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tempdir::TempDir;
+use tokio::task::JoinHandle;
+
+use opcua::client::prelude::*;
+use opcua::server::{
+    address_space::variable::VariableBuilder, builder::ServerBuilder, server::Server,
+};
+use opcua::sync::RwLock;
+
+const LOOPBACK_HOST: &str = "publish-bench";
+const LOOPBACK_PORT: u16 = 4855;
+
+/// The number of variables that the client monitors
+const VARIABLE_COUNT: usize = 500;
+
+/// How long to wait for the notifications before giving up
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn variable_id(idx: usize) -> NodeId {
+    NodeId::new(2, format!("v{:04}", idx))
+}
+
+/// Counts the data change notifications that the client receives
+#[derive(Default)]
+struct Notifications {
+    count: Mutex<usize>,
+    changed: Condvar,
+}
+
+impl Notifications {
+    fn add(&self, count: usize) {
+        *self.count.lock().unwrap() += count;
+        self.changed.notify_all();
+    }
+
+    /// Waits until the client has received at least this many notifications in total
+    fn wait_for(&self, count: usize) {
+        let (received, timeout) = self
+            .changed
+            .wait_timeout_while(
+                self.count.lock().unwrap(),
+                NOTIFICATION_TIMEOUT,
+                |received| *received < count,
+            )
+            .unwrap();
+        if timeout.timed_out() {
+            panic!("Received {} of {} notifications", *received, count);
+        }
+    }
+
+    fn count(&self) -> usize {
+        *self.count.lock().unwrap()
+    }
+}
+
+/// A server and a client that is subscribed to the variables of the server
+struct PublishBench {
+    _pki_dir: TempDir,
+    runtime: tokio::runtime::Runtime,
+    server: Arc<RwLock<Server>>,
+    server_task: JoinHandle<()>,
+    session: Arc<RwLock<Session>>,
+    _session_command: tokio::sync::oneshot::Sender<SessionCommand>,
+    notifications: Arc<Notifications>,
+    value: i32,
+}
+
+impl PublishBench {
+    fn start() -> PublishBench {
+        let pki_dir = TempDir::new("publish-bench").unwrap();
+
+        let server = ServerBuilder::new_anonymous("Publish Bench Server")
+            .application_uri("urn:publish-bench-server")
+            .host_and_port(LOOPBACK_HOST, LOOPBACK_PORT)
+            .pki_dir(pki_dir.path().join("server"))
+            .create_sample_keypair(true)
+            .discovery_server_url(None)
+            .server()
+            .unwrap();
+        {
+            let address_space = server.address_space();
+            let mut address_space = address_space.write();
+            let folder_id = address_space
+                .add_folder("Bench", "Bench", &NodeId::objects_folder_id())
+                .unwrap();
+            for idx in 0..VARIABLE_COUNT {
+                let name = format!("v{:04}", idx);
+                VariableBuilder::new(&variable_id(idx), &name, &name)
+                    .data_type(DataTypeId::Int32)
+                    .value(0i32)
+                    .organized_by(&folder_id)
+                    .insert(&mut address_space);
+            }
+        }
+        let server = Arc::new(RwLock::new(server));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server_task = runtime.spawn(Server::new_loopback_server_task(server.clone()));
+
+        // Wait for the server to bind its listener
+        let endpoint_url = format!("opc.tcp://{}:{}/", LOOPBACK_HOST, LOOPBACK_PORT);
+        while !opcua::core::comms::loopback::is_bound(&endpoint_url, LOOPBACK_PORT) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut client = ClientBuilder::new()
+            .application_name("Publish Bench Client")
+            .application_uri("urn:publish-bench-client")
+            .pki_dir(pki_dir.path().join("client"))
+            .create_sample_keypair(false)
+            .trust_server_certs(true)
+            .session_retry_limit(0)
+            .client()
+            .unwrap();
+        let session = client
+            .connect_to_endpoint(
+                (
+                    endpoint_url.as_ref(),
+                    SecurityPolicy::None.to_str(),
+                    MessageSecurityMode::None,
+                    UserTokenPolicy::anonymous(),
+                ),
+                IdentityToken::Anonymous,
+            )
+            .unwrap();
+
+        let notifications = Arc::new(Notifications::default());
+        {
+            let notifications = notifications.clone();
+            let session = session.read();
+            let subscription_id = session
+                .create_subscription(
+                    100f64,
+                    100,
+                    30,
+                    0,
+                    0,
+                    true,
+                    DataChangeCallback::new(move |changed_monitored_items| {
+                        notifications.add(changed_monitored_items.len());
+                    }),
+                )
+                .unwrap();
+            let items_to_create: Vec<MonitoredItemCreateRequest> = (0..VARIABLE_COUNT)
+                .map(|idx| variable_id(idx).into())
+                .collect();
+            let results = session
+                .create_monitored_items(
+                    subscription_id,
+                    TimestampsToReturn::Neither,
+                    &items_to_create,
+                )
+                .unwrap();
+            assert!(results.iter().all(|r| r.status_code.is_good()));
+        }
+        let session_command = Session::run_async(session.clone());
+
+        // The initial values are notified before any of them change
+        notifications.wait_for(VARIABLE_COUNT);
+
+        PublishBench {
+            _pki_dir: pki_dir,
+            runtime,
+            server,
+            server_task,
+            session,
+            _session_command: session_command,
+            notifications,
+            value: 0,
+        }
+    }
+
+    /// Changes the values of all the variables and waits for the client to be notified of them
+    fn change_values(&mut self) {
+        let notified = self.notifications.count() + VARIABLE_COUNT;
+        self.value += 1;
+        {
+            let server = self.server.read();
+            let address_space = server.address_space();
+            let mut address_space = address_space.write();
+            let now = DateTime::now();
+            for idx in 0..VARIABLE_COUNT {
+                address_space.set_variable_value(variable_id(idx), self.value, &now, &now);
+            }
+        }
+        self.notifications.wait_for(notified);
+    }
+
+    fn stop(self) {
+        self.session.read().disconnect();
+        self.server.write().abort();
+        self.runtime.block_on(self.server_task).unwrap();
+    }
+}
+
+fn publish_throughput(c: &mut Criterion) {
+    let mut bench = PublishBench::start();
+
+    let mut group = c.benchmark_group("publish");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(VARIABLE_COUNT as u64));
+    group.bench_function("data_change_notifications", |b| {
+        b.iter_custom(|iters| {
+            let started = Instant::now();
+            for _ in 0..iters {
+                bench.change_values();
+            }
+            started.elapsed()
+        })
+    });
+    group.finish();
+
+    bench.stop();
+}
+
+criterion_group!(benches, publish_throughput);
+criterion_main!(benches);
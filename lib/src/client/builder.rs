@@ -4,8 +4,9 @@
 
 use std::path::PathBuf;
 
-use crate::client::{client::Client, config::*};
-use crate::core::config::Config;
+use crate::client::{client::Client, config::*, failover::FailoverMode};
+use crate::core::{comms::socket_options::SocketOptions, config::Config};
+use crate::crypto::SystemCertificateStore;
 
 /// The `ClientBuilder` is a builder for producing a [`Client`]. It is an alternative to constructing
 /// a [`ClientConfig`] from file or from scratch.
@@ -147,6 +148,17 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the stores of the operating system that the client certificate and private key, and
+    /// trusted server certificates, are read from instead of the PKI directory. Rejected server
+    /// certificates are still stored in the PKI directory.
+    pub fn system_certificate_store(
+        mut self,
+        system_certificate_store: SystemCertificateStore,
+    ) -> Self {
+        self.config.system_certificate_store = Some(system_certificate_store);
+        self
+    }
+
     /// Sets whether the client should automatically trust servers. If this is not set then
     /// the client will reject the server upon first connect and the server's certificate
     /// must be manually moved from pki's `/rejected` folder to the `/trusted` folder. If it is
@@ -165,6 +177,14 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets whether the client trusts a server cert that replaces a trusted one, i.e. one that has
+    /// the same subject key identifier and public key as a cert in the `/trusted` folder. The
+    /// renewed cert is stored in the `/trusted` folder too.
+    pub fn match_subject_key_identifier(mut self, match_subject_key_identifier: bool) -> Self {
+        self.config.match_subject_key_identifier = match_subject_key_identifier;
+        self
+    }
+
     /// Sets the pki directory where client's own key pair is stored and where `/trusted` and
     /// `/rejected` server certificates are stored.
     pub fn pki_dir<T>(mut self, pki_dir: T) -> Self
@@ -245,6 +265,79 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets how long in milliseconds the client waits for the response to a request before it
+    /// times out.
+    pub fn request_timeout(mut self, request_timeout: u32) -> Self {
+        self.config.request_timeout = request_timeout;
+        self
+    }
+
+    /// Sets how many requests a session may have outstanding at once when it is called from
+    /// several threads. 0 means no limit.
+    pub fn max_inflight_requests(mut self, max_inflight_requests: usize) -> Self {
+        self.config.max_inflight_requests = max_inflight_requests;
+        self
+    }
+
+    /// Sets how often in milliseconds a session reads the state of the server to check that it
+    /// is still running. 0 means the session does not check.
+    pub fn keep_alive_interval(mut self, keep_alive_interval: u32) -> Self {
+        self.config.keep_alive_interval = keep_alive_interval;
+        self
+    }
+
+    /// Adds the endpoint url of a redundant server that sessions fail over to if they cannot
+    /// reconnect to the server they were connected to.
+    pub fn redundant_server<T>(mut self, server_url: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.config.redundant_servers.push(server_url.into());
+        self
+    }
+
+    /// Sets how the redundant servers back each other up, which decides how much of a session
+    /// is kept when it fails over.
+    pub fn failover_mode(mut self, failover_mode: FailoverMode) -> Self {
+        self.config.failover_mode = failover_mode;
+        self
+    }
+
+    /// Sets a SOCKS5 or HTTP proxy that connections to servers are tunnelled through.
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets how long in milliseconds to wait for a TCP connection to a server or proxy. 0 waits as
+    /// long as the operating system does.
+    pub fn connect_timeout(mut self, connect_timeout: u32) -> Self {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Sets the options on the TCP socket of each connection, e.g. keepalive to notice servers
+    /// that have gone without closing their connections.
+    pub fn socket_options(mut self, socket_options: SocketOptions) -> Self {
+        self.config.socket_options = socket_options;
+        self
+    }
+
+    /// Sets whether the client refuses to create sessions over secure channels with security
+    /// policy None. The endpoints of a server are still got with security policy None.
+    pub fn secure_sessions_only(mut self, secure_sessions_only: bool) -> Self {
+        self.config.secure_sessions_only = secure_sessions_only;
+        self
+    }
+
+    /// Sets whether the client connects to servers in this process that accept loopback
+    /// connections over in-memory streams instead of TCP. This is meant for integration tests,
+    /// where the server is run with `Server::new_loopback_server_task`.
+    pub fn loopback(mut self, loopback: bool) -> Self {
+        self.config.loopback = loopback;
+        self
+    }
+
     /// Sets whether the client should ignore clock skew so the client can make a successful
     /// connection to the server, even when the client and server clocks are out of sync.
     pub fn ignore_clock_skew(mut self) -> Self {
@@ -307,6 +400,12 @@ fn client_builder() {
         .session_retry_interval(1234)
         .session_retry_limit(999)
         .session_timeout(777)
+        .request_timeout(4321)
+        .max_inflight_requests(3)
+        .keep_alive_interval(5000)
+        .redundant_server("opc.tcp://backup1:4855/")
+        .redundant_server("opc.tcp://backup2:4855/")
+        .failover_mode(FailoverMode::Hot)
         .ignore_clock_skew()
         .single_threaded_executor()
         .session_name("SessionName")
@@ -332,6 +431,14 @@ fn client_builder() {
     assert_eq!(c.session_retry_interval, 1234);
     assert_eq!(c.session_retry_limit, 999);
     assert_eq!(c.session_timeout, 777);
+    assert_eq!(c.request_timeout, 4321);
+    assert_eq!(c.max_inflight_requests, 3);
+    assert_eq!(c.keep_alive_interval, 5000);
+    assert_eq!(
+        c.redundant_servers,
+        vec!["opc.tcp://backup1:4855/", "opc.tcp://backup2:4855/"]
+    );
+    assert_eq!(c.failover_mode, FailoverMode::Hot);
     assert_eq!(c.performance.ignore_clock_skew, true);
     assert_eq!(c.performance.single_threaded_executor, true);
     assert_eq!(c.session_name, "SessionName");
@@ -6,14 +6,20 @@
 //! with the client api.
 //!
 //! For example, the client must supply an [`OnSubscriptionNotification`] implementation when it calls `Session::create_subscription`.
-//! It could implement this trait for itself, or it can use the concrete implementations in [`DataChangeCallback`] and [`EventCallback`].
+//! It could implement this trait for itself, or it can use the concrete implementations in [`DataChangeCallback`], [`EventCallback`]
+//! and [`EventFieldsCallback`].
 //!
 //! [`DataChangeCallback`]: ./struct.DataChangeCallback.html
 //! [`EventCallback`]: ./struct.EventCallback.html
+//! [`EventFieldsCallback`]: ./struct.EventFieldsCallback.html
 
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
-use crate::types::{service_types::EventNotificationList, status_code::StatusCode};
+use crate::types::{
+    service_types::{EventFilter, EventNotificationList, ServerState},
+    status_code::StatusCode,
+    Variant,
+};
 
 use super::subscription::MonitoredItem;
 
@@ -24,10 +30,12 @@ use super::subscription::MonitoredItem;
 /// you probably only need to implement either `data_change()`, or `event()` and leave the default,
 /// no-op implementation for the other.
 ///
-/// There are concrete implementations of this trait in [`DataChangeCallback`] and [`EventCallback`].
+/// There are concrete implementations of this trait in [`DataChangeCallback`], [`EventCallback`]
+/// and [`EventFieldsCallback`].
 ///
 /// [`DataChangeCallback`]: ./struct.DataChangeCallback.html
 /// [`EventCallback`]: ./struct.EventCallback.html
+/// [`EventFieldsCallback`]: ./struct.EventFieldsCallback.html
 ///
 pub trait OnSubscriptionNotification {
     /// Called by the subscription after a `DataChangeNotification`. The default implementation
@@ -38,6 +46,12 @@ pub trait OnSubscriptionNotification {
     /// are individual `EventFieldList` structs filled from the select clause criteria from when the
     /// event was constructed. The default implementation does nothing.
     fn on_event(&mut self, _events: &EventNotificationList) {}
+
+    /// Called by the subscription after a `StatusChangeNotification`. The status tells why the
+    /// subscription changed, e.g. `BadTimeout` when its lifetime expired, `GoodSubscriptionTransferred`
+    /// when it was transferred to another session or `BadShutdown` when the server is shutting down.
+    /// The default implementation does nothing.
+    fn on_status_change(&mut self, _status: StatusCode) {}
 }
 
 /// The `OnConnectionStatusChange` trait can be used to register on the session to be notified
@@ -60,6 +74,33 @@ pub trait OnSessionClosed {
     fn on_session_closed(&mut self, status_code: StatusCode);
 }
 
+/// The reason that the keep-alive of a session found the server to be unavailable.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KeepAliveFailure {
+    /// The server did not answer the keep-alive read of its state in time, or answered it with
+    /// the status code of an error
+    Unresponsive(StatusCode),
+    /// The server reported a state other than `Running`, e.g. `Shutdown`
+    ServerState(ServerState),
+}
+
+/// The `OnKeepAliveFailure` trait can be used to register on a session to be notified when its
+/// keep-alive finds that the server has become unresponsive or is no longer running.
+pub trait OnKeepAliveFailure {
+    /// Called when the keep-alive fails, just before the session drops its connection so that
+    /// the session retry policy can reconnect.
+    fn on_keep_alive_failure(&mut self, failure: KeepAliveFailure);
+}
+
+/// The `OnFailover` trait can be used to register on a session to be notified when it has failed
+/// over to a redundant server.
+pub trait OnFailover {
+    /// Called when the session has reconnected to a redundant server because it could not
+    /// reconnect to the server it was connected to. The session and its subscriptions have been
+    /// activated, transferred or created again on the new server by the time this is called.
+    fn on_failover(&mut self, from_server_url: &str, to_server_url: &str);
+}
+
 /// This is a concrete implementation of [`OnSubscriptionNotification`] that calls a function when
 /// a data change occurs.
 pub struct DataChangeCallback {
@@ -106,6 +147,44 @@ impl EventCallback {
     }
 }
 
+/// The function that [`EventFieldsCallback`] calls with a client handle and the event fields
+type EventFieldsFn = dyn Fn(u32, &BTreeMap<String, Variant>) + Send + Sync + 'static;
+
+/// This is a concrete implementation of [`OnSubscriptionNotification`] that calls a function
+/// for each event with the client handle of its monitored item and its fields keyed by the field
+/// names of the select clauses of an event filter, e.g. `"Message"` or `"SourceNode"`. It suits a
+/// subscription whose monitored items for events were all created with that filter.
+pub struct EventFieldsCallback {
+    /// The filter that the events were selected with
+    event_filter: EventFilter,
+    /// The actual call back
+    cb: Box<EventFieldsFn>,
+}
+
+impl OnSubscriptionNotification for EventFieldsCallback {
+    fn on_event(&mut self, events: &EventNotificationList) {
+        if let Some(ref events) = events.events {
+            events.iter().for_each(|event| {
+                let event_fields = self.event_filter.event_fields(event);
+                (self.cb)(event.client_handle, &event_fields);
+            });
+        }
+    }
+}
+
+impl EventFieldsCallback {
+    /// Constructs a callback from the event filter of the monitored items and the supplied function
+    pub fn new<CB>(event_filter: &EventFilter, cb: CB) -> Self
+    where
+        CB: Fn(u32, &BTreeMap<String, Variant>) + Send + Sync + 'static,
+    {
+        Self {
+            event_filter: event_filter.clone(),
+            cb: Box::new(cb),
+        }
+    }
+}
+
 /// This is a concrete implementation of [`OnConnectionStatusChange`] that calls the supplied function.
 pub struct ConnectionStatusCallback {
     cb: Box<dyn FnMut(bool) + Send + Sync + 'static>,
@@ -159,3 +238,48 @@ impl SessionClosedCallback {
         Self { cb: Box::new(cb) }
     }
 }
+
+/// This is a concrete implementation of [`OnKeepAliveFailure`] that calls the supplied function.
+pub struct KeepAliveCallback {
+    cb: Box<dyn FnMut(KeepAliveFailure) + Send + Sync + 'static>,
+}
+
+impl OnKeepAliveFailure for KeepAliveCallback {
+    fn on_keep_alive_failure(&mut self, failure: KeepAliveFailure) {
+        (self.cb)(failure);
+    }
+}
+
+impl KeepAliveCallback {
+    // Constructor
+    pub fn new<CB>(cb: CB) -> Self
+    where
+        CB: FnMut(KeepAliveFailure) + Send + Sync + 'static,
+    {
+        Self { cb: Box::new(cb) }
+    }
+}
+
+/// The function that [`FailoverCallback`] calls with the urls of the old and new servers
+type FailoverFn = dyn FnMut(&str, &str) + Send + Sync + 'static;
+
+/// This is a concrete implementation of [`OnFailover`] that calls the supplied function.
+pub struct FailoverCallback {
+    cb: Box<FailoverFn>,
+}
+
+impl OnFailover for FailoverCallback {
+    fn on_failover(&mut self, from_server_url: &str, to_server_url: &str) {
+        (self.cb)(from_server_url, to_server_url);
+    }
+}
+
+impl FailoverCallback {
+    // Constructor
+    pub fn new<CB>(cb: CB) -> Self
+    where
+        CB: FnMut(&str, &str) + Send + Sync + 'static,
+    {
+        Self { cb: Box::new(cb) }
+    }
+}
@@ -10,6 +10,7 @@ use chrono::Duration;
 
 use super::{
     config::{ClientConfig, ClientEndpoint, ANONYMOUS_USER_TOKEN_ID},
+    failover::FailoverPolicy,
     session::{
         services::*,
         session::{Session, SessionInfo},
@@ -20,14 +21,12 @@ use super::{
 use crate::{
     core::{
         comms::url::{
-            hostname_from_url, is_opc_ua_binary_url, is_valid_opc_ua_url,
-            server_url_from_endpoint_url, url_matches_except_host, url_with_replaced_hostname,
+            is_opc_ua_binary_url, is_valid_opc_ua_url, server_url_from_endpoint_url, EndpointUrl,
         },
         config::Config,
     },
     crypto::{CertificateStore, SecurityPolicy},
     sync::RwLock,
-    trace_read_lock,
     types::{
         service_types::{ApplicationDescription, EndpointDescription, RegisteredServer},
         status_code::StatusCode,
@@ -125,13 +124,20 @@ impl Client {
         };
 
         let (mut certificate_store, client_certificate, client_pkey) =
-            CertificateStore::new_with_x509_data(
-                &config.pki_dir,
-                false,
-                config.certificate_path.as_deref(),
-                config.private_key_path.as_deref(),
-                application_description,
-            );
+            if let Some(ref system_certificate_store) = config.system_certificate_store {
+                CertificateStore::new_with_system_store(
+                    &config.pki_dir,
+                    system_certificate_store.clone(),
+                )
+            } else {
+                CertificateStore::new_with_x509_data(
+                    &config.pki_dir,
+                    false,
+                    config.certificate_path.as_deref(),
+                    config.private_key_path.as_deref(),
+                    application_description,
+                )
+            };
         if client_certificate.is_none() || client_pkey.is_none() {
             error!("Client is missing its application instance certificate and/or its private key. Encrypted endpoints will not function correctly.")
         }
@@ -142,6 +148,9 @@ impl Client {
         // Clients may choose to auto trust servers to save some messing around with rejected certs
         certificate_store.set_trust_unknown_certs(config.trust_server_certs);
 
+        // Clients may trust servers whose certs have been renewed with the same key
+        certificate_store.set_match_subject_key_identifier(config.match_subject_key_identifier);
+
         let session_timeout = config.session_timeout as f64;
 
         // The session retry policy dictates how many times to retry if connection to the server goes down
@@ -174,6 +183,12 @@ impl Client {
         self.config.application_description()
     }
 
+    /// Returns the `CertificateStore` for the client, e.g. to list the server certificates that
+    /// were rejected and to trust them.
+    pub fn certificate_store(&self) -> Arc<RwLock<CertificateStore>> {
+        self.certificate_store.clone()
+    }
+
     /// Connects to a named endpoint that you have defined in the `ClientConfig`
     /// and creates / activates a [`Session`] for that endpoint. Note that `GetEndpoints` is first
     /// called on the server and it is expected to support the endpoint you intend to connect to.
@@ -386,7 +401,7 @@ impl Client {
                 session_info.endpoint.endpoint_url
             ))
         } else {
-            let session = Arc::new(RwLock::new(Session::new(
+            let mut session = Session::new(
                 self.application_description(),
                 self.config.session_name.clone(),
                 self.certificate_store.clone(),
@@ -395,8 +410,22 @@ impl Client {
                 self.decoding_options(),
                 self.config.performance.ignore_clock_skew,
                 self.config.performance.single_threaded_executor,
-            )));
-            Ok(session)
+            );
+            session.set_request_timeout(self.config.request_timeout);
+            session.set_max_inflight_requests(self.config.max_inflight_requests);
+            session.set_keep_alive_interval(self.config.keep_alive_interval);
+            session.set_proxy(self.config.proxy.clone());
+            session.set_socket_options(self.config.socket_options.clone());
+            session.set_connect_timeout(self.config.connect_timeout);
+            session.set_loopback(self.config.loopback);
+            session.set_secure_sessions_only(self.config.secure_sessions_only);
+            if !self.config.redundant_servers.is_empty() {
+                session.set_failover_policy(FailoverPolicy::new(
+                    self.config.failover_mode,
+                    self.config.redundant_servers.clone(),
+                ));
+            }
+            Ok(Arc::new(RwLock::new(session)))
         }
     }
 
@@ -476,7 +505,7 @@ impl Client {
                 user_identity_token: IdentityToken::Anonymous,
                 preferred_locales,
             };
-            let session = Session::new(
+            let mut session = Session::new(
                 self.application_description(),
                 self.config.session_name.clone(),
                 self.certificate_store.clone(),
@@ -486,6 +515,12 @@ impl Client {
                 self.config.performance.ignore_clock_skew,
                 self.config.performance.single_threaded_executor,
             );
+            session.set_request_timeout(self.config.request_timeout);
+            session.set_max_inflight_requests(self.config.max_inflight_requests);
+            session.set_proxy(self.config.proxy.clone());
+            session.set_socket_options(self.config.socket_options.clone());
+            session.set_connect_timeout(self.config.connect_timeout);
+            session.set_loopback(self.config.loopback);
             session.connect()?;
             let result = session.get_endpoints()?;
             session.disconnect();
@@ -522,7 +557,7 @@ impl Client {
                             "Cannot find servers on discovery server {} - check this error - {:?}",
                             discovery_endpoint_url, err
                         );
-                        err
+                        err.status_code()
                     });
                 session.disconnect();
                 result
@@ -597,7 +632,7 @@ impl Client {
                                 // Register with the server
                                 let result = session.register_server(server);
                                 session.disconnect();
-                                result
+                                result.map_err(StatusCode::from)
                             }
                             Err(result) => {
                                 error!(
@@ -670,34 +705,25 @@ impl Client {
             panic!("Cannot match against unknown security policy");
         }
 
-        let matching_endpoint = endpoints
-            .iter()
-            .find(|e| {
-                // Endpoint matches if the security mode, policy and url match
-                security_mode == e.security_mode
-                    && security_policy == SecurityPolicy::from_uri(e.security_policy_uri.as_ref())
-                    && url_matches_except_host(endpoint_url, e.endpoint_url.as_ref())
-            })
-            .cloned();
-
-        // Issue #16, #17 - the server may advertise an endpoint whose hostname is inaccessible
-        // to the client so substitute the advertised hostname with the one the client supplied.
-        if let Some(mut matching_endpoint) = matching_endpoint {
-            if let Ok(hostname) = hostname_from_url(endpoint_url) {
-                if let Ok(new_endpoint_url) =
-                    url_with_replaced_hostname(matching_endpoint.endpoint_url.as_ref(), &hostname)
-                {
-                    matching_endpoint.endpoint_url = new_endpoint_url.into();
-                    Some(matching_endpoint)
-                } else {
-                    None
-                }
+        let endpoint_url = EndpointUrl::from_str(endpoint_url).ok()?;
+        let (mut matching_endpoint, matching_url) = endpoints.iter().find_map(|e| {
+            // Endpoint matches if the security mode, policy and url match
+            let url = EndpointUrl::from_str(e.endpoint_url.as_ref()).ok()?;
+            if security_mode == e.security_mode
+                && security_policy == SecurityPolicy::from_uri(e.security_policy_uri.as_ref())
+                && url.matches_except_host(&endpoint_url)
+            {
+                Some((e.clone(), url))
             } else {
                 None
             }
-        } else {
-            None
-        }
+        })?;
+
+        // Issue #16, #17 - the server may advertise an endpoint whose hostname is inaccessible
+        // to the client so substitute the advertised hostname with the one the client supplied.
+        let new_endpoint_url = matching_url.with_host(endpoint_url.host()).ok()?;
+        matching_endpoint.endpoint_url = new_endpoint_url.to_string().into();
+        Some(matching_endpoint)
     }
 
     /// Creates a [`SessionInfo`](SessionInfo) information from the supplied client endpoint.
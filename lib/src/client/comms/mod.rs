@@ -4,6 +4,5 @@
 
 //! Client side communications
 
-mod transport;
-
+pub(crate) mod proxy;
 pub(crate) mod tcp_transport;
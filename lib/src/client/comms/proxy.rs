@@ -0,0 +1,211 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Tunnels the TCP connection to a server through a SOCKS5 proxy (RFC 1928, with the user name
+//! and password authentication of RFC 1929) or an HTTP proxy that supports the CONNECT method.
+//! Once the tunnel is open the stream carries UA TCP exactly as a direct connection does.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::types::status_code::StatusCode;
+
+use crate::client::config::{ProxyConfig, ProxyKind};
+
+/// The most bytes of response headers an HTTP proxy may send
+const MAX_HTTP_RESPONSE_LEN: usize = 8192;
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_NO_AUTHENTICATION: u8 = 0;
+const SOCKS_USER_PASSWORD: u8 = 2;
+const SOCKS_CONNECT: u8 = 1;
+const SOCKS_IPV4: u8 = 1;
+const SOCKS_DOMAIN_NAME: u8 = 3;
+const SOCKS_IPV6: u8 = 4;
+
+/// Connects to the proxy and has it open a tunnel to the host and port. The host may be a name,
+/// which the proxy resolves, or an IP address with brackets around an IPv6 address.
+pub(crate) async fn connect(
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, StatusCode> {
+    let mut stream = TcpStream::connect(&proxy.address).await.map_err(|err| {
+        error!("Could not connect to proxy {}, {:?}", proxy.address, err);
+        StatusCode::BadCommunicationError
+    })?;
+    match proxy.kind {
+        ProxyKind::Socks5 => socks5_handshake(&mut stream, proxy, host, port).await?,
+        ProxyKind::Http => http_connect(&mut stream, proxy, host, port).await?,
+    }
+    debug!(
+        "Proxy {} opened a tunnel to {}:{}",
+        proxy.address, host, port
+    );
+    Ok(stream)
+}
+
+/// Logs an IO error with the proxy
+fn io_error(err: std::io::Error) -> StatusCode {
+    error!("Communication with the proxy failed, {:?}", err);
+    StatusCode::BadCommunicationError
+}
+
+/// Opens a tunnel with the SOCKS5 protocol
+pub(crate) async fn socks5_handshake<S>(
+    stream: &mut S,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), StatusCode>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // Offer to authenticate with a user name and password if there are credentials
+    let credentials = proxy.user.as_ref().map(|user| {
+        (
+            user.as_bytes(),
+            proxy.password.as_deref().unwrap_or_default().as_bytes(),
+        )
+    });
+    let greeting: &[u8] = if credentials.is_some() {
+        &[
+            SOCKS_VERSION,
+            2,
+            SOCKS_NO_AUTHENTICATION,
+            SOCKS_USER_PASSWORD,
+        ]
+    } else {
+        &[SOCKS_VERSION, 1, SOCKS_NO_AUTHENTICATION]
+    };
+    stream.write_all(greeting).await.map_err(io_error)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await.map_err(io_error)?;
+    if reply[0] != SOCKS_VERSION {
+        error!("Proxy is not a SOCKS5 proxy");
+        return Err(StatusCode::BadCommunicationError);
+    }
+    match (reply[1], credentials) {
+        (SOCKS_NO_AUTHENTICATION, _) => {}
+        (SOCKS_USER_PASSWORD, Some((user, password))) => {
+            let mut request = Vec::with_capacity(3 + user.len() + password.len());
+            request.push(1);
+            request.push(user.len() as u8);
+            request.extend_from_slice(user);
+            request.push(password.len() as u8);
+            request.extend_from_slice(password);
+            stream.write_all(&request).await.map_err(io_error)?;
+            stream.read_exact(&mut reply).await.map_err(io_error)?;
+            if reply[1] != 0 {
+                error!("Proxy rejected the user name and password");
+                return Err(StatusCode::BadConnectionRejected);
+            }
+        }
+        _ => {
+            error!("Proxy requires an authentication method that is not supported");
+            return Err(StatusCode::BadConnectionRejected);
+        }
+    }
+
+    // Ask for a connection to the host
+    let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+    let unbracketed = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(address) = host.parse::<Ipv4Addr>() {
+        request.push(SOCKS_IPV4);
+        request.extend_from_slice(&address.octets());
+    } else if let Ok(address) = unbracketed.parse::<Ipv6Addr>() {
+        request.push(SOCKS_IPV6);
+        request.extend_from_slice(&address.octets());
+    } else if host.len() <= 255 {
+        request.push(SOCKS_DOMAIN_NAME);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+    } else {
+        error!("Host name {} is too long for the proxy", host);
+        return Err(StatusCode::BadTcpEndpointUrlInvalid);
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await.map_err(io_error)?;
+
+    // The reply ends with the address that the proxy connected from, which is skipped
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await.map_err(io_error)?;
+    if reply[1] != 0 {
+        error!(
+            "Proxy could not connect to {}:{}, SOCKS error {}",
+            host, port, reply[1]
+        );
+        return Err(StatusCode::BadConnectionRejected);
+    }
+    let address_len = match reply[3] {
+        SOCKS_IPV4 => 4,
+        SOCKS_IPV6 => 16,
+        SOCKS_DOMAIN_NAME => stream.read_u8().await.map_err(io_error)? as usize,
+        address_type => {
+            error!("Proxy replied with unknown address type {}", address_type);
+            return Err(StatusCode::BadCommunicationError);
+        }
+    };
+    let mut bound_address = vec![0u8; address_len + 2];
+    stream
+        .read_exact(&mut bound_address)
+        .await
+        .map_err(io_error)?;
+    Ok(())
+}
+
+/// Opens a tunnel with the HTTP CONNECT method
+pub(crate) async fn http_connect<S>(
+    stream: &mut S,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), StatusCode>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let authority = format!("{}:{}", host, port);
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some(ref user) = proxy.user {
+        let credentials = format!("{}:{}", user, proxy.password.as_deref().unwrap_or_default());
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            base64::encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(io_error)?;
+
+    // The response is read a byte at a time so nothing after its headers is consumed
+    let mut response = Vec::with_capacity(256);
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE_LEN {
+            error!("Proxy sent a response that is too long");
+            return Err(StatusCode::BadCommunicationError);
+        }
+        response.push(stream.read_u8().await.map_err(io_error)?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1);
+    if !status_line.starts_with("HTTP/1.") || status.is_none() {
+        error!("Proxy sent an invalid response \"{}\"", status_line);
+        Err(StatusCode::BadCommunicationError)
+    } else if status != Some("200") {
+        error!(
+            "Proxy could not connect to {}, \"{}\"",
+            authority, status_line
+        );
+        Err(StatusCode::BadConnectionRejected)
+    } else {
+        Ok(())
+    }
+}
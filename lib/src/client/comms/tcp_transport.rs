@@ -9,6 +9,7 @@
 //! session state.
 use std::{
     collections::HashMap,
+    future::Future,
     net::{SocketAddr, ToSocketAddrs},
     result::Result,
     sync::Arc,
@@ -18,20 +19,23 @@ use std::{
 use futures::StreamExt;
 use tokio::{
     self,
-    io::{AsyncWriteExt, ReadHalf, WriteHalf},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     net::TcpStream,
     sync::mpsc::UnboundedReceiver,
-    time::Duration,
+    time::{timeout, Duration},
 };
 use tokio_util::codec::FramedRead;
 
 use crate::core::{
     comms::{
+        capture::{capture, CaptureDirection, CaptureForm},
+        local_socket, loopback,
         message_chunk_info::ChunkInfo,
         message_writer::MessageWriter,
+        socket_options::SocketOptions,
         tcp_codec::{Message, TcpCodec},
-        tcp_types::HelloMessage,
-        url::hostname_port_from_url,
+        tcp_types::{HelloMessage, ReceiveLimits},
+        url::EndpointUrl,
     },
     prelude::*,
 };
@@ -40,7 +44,8 @@ use crate::types::status_code::StatusCode;
 
 use crate::client::{
     callbacks::OnSessionClosed,
-    comms::transport::Transport,
+    comms::proxy,
+    config::ProxyConfig,
     message_queue::{self, MessageQueue},
     session::session_state::{ConnectionState, ConnectionStateMgr, SessionState},
 };
@@ -56,11 +61,12 @@ struct ReadState {
     pub state: ConnectionStateMgr,
     pub secure_channel: Arc<RwLock<SecureChannel>>,
     pub message_queue: Arc<RwLock<MessageQueue>>,
-    pub max_chunk_count: usize,
+    /// Limits of the chunks and messages received from the server, as sent in the HELLO
+    pub receive_limits: ReceiveLimits,
     /// Last decoded sequence number
     last_received_sequence_number: u32,
     chunks: HashMap<u32, Vec<MessageChunkWithChunkInfo>>,
-    pub framed_read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
+    pub framed_read: FramedRead<Box<dyn AsyncRead + Unpin + Send>, TcpCodec>,
 }
 
 impl Drop for ReadState {
@@ -75,12 +81,16 @@ impl ReadState {
         secure_channel: Arc<RwLock<SecureChannel>>,
         message_queue: Arc<RwLock<MessageQueue>>,
         session_state: &SessionState,
-        framed_read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
+        framed_read: FramedRead<Box<dyn AsyncRead + Unpin + Send>, TcpCodec>,
     ) -> Self {
         ReadState {
             secure_channel,
             state: connection_state,
-            max_chunk_count: session_state.max_chunk_count(),
+            receive_limits: ReceiveLimits {
+                receive_buffer_size: session_state.receive_buffer_size(),
+                max_message_size: session_state.max_message_size(),
+                max_chunk_count: session_state.max_chunk_count(),
+            },
             last_received_sequence_number: 0,
             message_queue,
             chunks: HashMap::new(),
@@ -107,10 +117,17 @@ impl ReadState {
         chunk: MessageChunk,
     ) -> Result<Option<SupportedMessage>, StatusCode> {
         // trace!("Got a chunk {:?}", chunk);
+        capture(CaptureDirection::Received, CaptureForm::Raw, &chunk.data);
+        self.receive_limits.check_chunk_size(chunk.data.len())?;
         let chunk = {
             let mut secure_channel = trace_write_lock!(self.secure_channel);
             secure_channel.verify_and_remove_security(&chunk.data)?
         };
+        capture(
+            CaptureDirection::Received,
+            CaptureForm::Decrypted,
+            &chunk.data,
+        );
 
         let secure_channel = trace_read_lock!(self.secure_channel);
         let chunk_info = chunk.chunk_info(&secure_channel)?;
@@ -129,20 +146,39 @@ impl ReadState {
                     header: chunk_info,
                     data_with_header: chunk.data,
                 });
-                let chunks_len = self.chunks.len();
-                if self.max_chunk_count > 0 && chunks_len > self.max_chunk_count {
-                    error!("too many chunks {}> {}", chunks_len, self.max_chunk_count);
-                    // TODO this code should return an error to be safe
-                    //remove first
-                    let first_req_id = *self.chunks.iter().next().unwrap().0;
-                    self.chunks.remove(&first_req_id);
-                }
+                Self::check_message_limits(&self.receive_limits, chunks)?;
                 return Ok(None);
             }
             MessageIsFinalType::FinalError => {
-                info!("Discarding chunk marked in as final error");
-                self.chunks.remove(&chunk_info.sequence_header.request_id);
-                return Ok(None);
+                // The server has abandoned the response, which fails the request while the
+                // connection stays open. The sequence numbers of the chunks are still validated.
+                let mut chunks = self.chunks.remove(&req_id).unwrap_or_default();
+                let discarded = chunks.len();
+                chunks.push(MessageChunkWithChunkInfo {
+                    header: chunk_info,
+                    data_with_header: chunk.data,
+                });
+                let chunks = Self::merge_chunks(chunks)?;
+                let (status_code, reason) = {
+                    let secure_channel = trace_read_lock!(self.secure_channel);
+                    self.last_received_sequence_number = Chunker::validate_chunks(
+                        self.last_received_sequence_number + 1,
+                        &secure_channel,
+                        &chunks,
+                    )?;
+                    Chunker::decode_abort(&chunks[chunks.len() - 1], &secure_channel)?
+                };
+                info!(
+                    "Discarding {} chunks of request {} after the server aborted it with {}, reason {}",
+                    discarded, req_id, status_code, reason
+                );
+                let request_handle = {
+                    let message_queue = trace_read_lock!(self.message_queue);
+                    message_queue.request_handle(req_id)
+                };
+                return Ok(
+                    request_handle.map(|request_handle| service_fault(request_handle, status_code))
+                );
             }
             _ => {
                 // Drop through
@@ -154,12 +190,22 @@ impl ReadState {
             header: chunk_info,
             data_with_header: chunk.data,
         });
+        Self::check_message_limits(&self.receive_limits, chunks)?;
         let in_chunks = Self::merge_chunks(self.chunks.remove(&req_id).unwrap())?;
         let message = self.turn_received_chunks_into_message(&in_chunks)?;
 
         Ok(Some(message))
     }
 
+    /// Checks the chunks received so far for a message against the limits of what is received
+    fn check_message_limits(
+        receive_limits: &ReceiveLimits,
+        chunks: &[MessageChunkWithChunkInfo],
+    ) -> Result<(), StatusCode> {
+        let message_size = chunks.iter().map(|c| c.header.body_length).sum();
+        receive_limits.check_message(chunks.len(), message_size)
+    }
+
     fn merge_chunks(
         mut chunks: Vec<MessageChunkWithChunkInfo>,
     ) -> Result<Vec<MessageChunk>, StatusCode> {
@@ -199,11 +245,27 @@ impl ReadState {
     }
 }
 
+/// Makes the service fault that a request fails with when it is not sent or its response is
+/// aborted
+fn service_fault(request_handle: u32, service_result: StatusCode) -> SupportedMessage {
+    ServiceFault {
+        response_header: ResponseHeader {
+            timestamp: DateTime::now(),
+            request_handle,
+            service_result,
+            service_diagnostics: DiagnosticInfo::default(),
+            string_table: None,
+            additional_header: ExtensionObject::null(),
+        },
+    }
+    .into()
+}
+
 struct WriteState {
     /// The url to connect to
     pub secure_channel: Arc<RwLock<SecureChannel>>,
     pub message_queue: Arc<RwLock<MessageQueue>>,
-    pub writer: WriteHalf<TcpStream>,
+    pub writer: Box<dyn AsyncWrite + Unpin + Send>,
     /// The send buffer
     pub send_buffer: MessageWriter,
     pub receiver: UnboundedReceiver<message_queue::Message>,
@@ -219,7 +281,7 @@ impl WriteState {
     fn new(
         secure_channel: Arc<RwLock<SecureChannel>>,
         message_queue: Arc<RwLock<MessageQueue>>,
-        writer: WriteHalf<TcpStream>,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
         session_state: &SessionState,
     ) -> Self {
         let receiver = {
@@ -248,6 +310,19 @@ impl WriteState {
     }
 }
 
+/// Where the transport connects to
+#[derive(Debug)]
+enum ConnectTo {
+    /// A server listening on a TCP socket
+    Tcp(SocketAddr),
+    /// A server in this process accepting loopback connections
+    Loopback,
+    /// A server on this host listening on a Unix domain socket or named pipe
+    Local(String),
+    /// A server reached through a proxy, which resolves the host name itself
+    Proxy(ProxyConfig, String, u16),
+}
+
 /// This is the OPC UA TCP client transport layer
 ///
 /// At its heart it is a tokio task that runs continuously reading and writing data from the connected
@@ -265,6 +340,16 @@ pub(crate) struct TcpTransport {
     message_queue: Arc<RwLock<MessageQueue>>,
     /// Tokio runtime
     runtime: Arc<Mutex<tokio::runtime::Runtime>>,
+    /// Proxy that TCP connections go through
+    proxy: Option<ProxyConfig>,
+    /// Options that are set on TCP sockets
+    socket_options: SocketOptions,
+    /// How long in milliseconds to wait for a TCP connection, 0 meaning as long as the
+    /// operating system waits
+    connect_timeout: u32,
+    /// Whether servers in this process that accept loopback connections are connected to in
+    /// memory
+    loopback: bool,
 }
 
 impl Drop for TcpTransport {
@@ -273,8 +358,6 @@ impl Drop for TcpTransport {
     }
 }
 
-impl Transport for TcpTransport {}
-
 impl TcpTransport {
     const WAIT_POLLING_TIMEOUT: u64 = 100;
 
@@ -310,20 +393,76 @@ impl TcpTransport {
             connection_state,
             message_queue,
             runtime: Arc::new(Mutex::new(runtime)),
+            proxy: None,
+            socket_options: SocketOptions::default(),
+            connect_timeout: 0,
+            loopback: false,
         }
     }
 
+    /// Sets the proxy that the transport connects to servers through. Loopback servers in this
+    /// process and servers on local sockets are still connected to directly.
+    pub fn set_proxy(&mut self, proxy: Option<ProxyConfig>) {
+        self.proxy = proxy;
+    }
+
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    /// Sets the options that are set on the TCP socket of each connection, including the socket
+    /// connected to a proxy
+    pub fn set_socket_options(&mut self, socket_options: SocketOptions) {
+        self.socket_options = socket_options;
+    }
+
+    pub fn socket_options(&self) -> &SocketOptions {
+        &self.socket_options
+    }
+
+    /// Sets how long in milliseconds to wait for a TCP connection, including the handshake with a
+    /// proxy, 0 meaning as long as the operating system waits
+    pub fn set_connect_timeout(&mut self, connect_timeout: u32) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    pub fn connect_timeout(&self) -> u32 {
+        self.connect_timeout
+    }
+
+    /// Sets whether servers in this process that accept loopback connections are connected to
+    /// over in-memory streams instead of TCP
+    pub fn set_loopback(&mut self, loopback: bool) {
+        self.loopback = loopback;
+    }
+
+    pub fn loopback(&self) -> bool {
+        self.loopback
+    }
+
     /// Connects the stream to the specified endpoint
     pub fn connect(&self, endpoint_url: &str) -> Result<(), StatusCode> {
         debug_assert!(!self.is_connected(), "Should not try to connect when already connected");
-        let (host, port) =
-            hostname_port_from_url(endpoint_url, crate::core::constants::DEFAULT_OPC_UA_SERVER_PORT)?;
-
-        // Resolve the host name into a socket address
-        let addr = {
-            let addr = format!("{}:{}", host, port);
+        let default_port = crate::core::constants::DEFAULT_OPC_UA_SERVER_PORT;
+        let url = endpoint_url.parse::<EndpointUrl>()?;
+        if !url.is_opc_tcp() && !url.is_opc_unix() {
+            return Err(StatusCode::BadTcpEndpointUrlInvalid);
+        }
+        let port = url.port();
+
+        // A server in this process that accepts loopback connections is connected to in memory if
+        // the transport is asked to, a proxy is left to resolve the host name, otherwise resolve
+        // the host name into a socket address
+        let addr = if let Some(socket_path) = url.socket_path() {
+            ConnectTo::Local(socket_path)
+        } else if self.loopback && loopback::is_bound(endpoint_url, default_port) {
+            ConnectTo::Loopback
+        } else if let Some(ref proxy) = self.proxy {
+            ConnectTo::Proxy(proxy.clone(), url.host().to_string(), port)
+        } else {
+            let addr = url.host_and_port();
             let addrs = addr.to_socket_addrs();
-            if let Ok(mut addrs) = addrs {
+            let addr = if let Ok(mut addrs) = addrs {
                 // Take the first resolved ip addr for the hostname
                 if let Some(addr) = addrs.next() {
                     addr
@@ -338,9 +477,10 @@ impl TcpTransport {
                     addrs.unwrap_err()
                 );
                 return Err(StatusCode::BadTcpEndpointUrlInvalid);
-            }
+            };
+            assert_eq!(addr.port(), port);
+            ConnectTo::Tcp(addr)
         };
-        assert_eq!(addr.port(), port);
         let endpoint_url = endpoint_url.to_string();
 
         let (connection_state,
@@ -356,6 +496,8 @@ impl TcpTransport {
             connection_status_receiver) = std::sync::mpsc::channel();
         let conn_task = Self::connection_task(
             addr,
+            self.socket_options.clone(),
+            self.connect_timeout,
             connection_state.clone(),
             endpoint_url,
             session_state.clone(),
@@ -378,6 +520,18 @@ impl TcpTransport {
         connection_status_receiver.recv().expect("channel should never be dropped here")
     }
 
+    /// Replaces the session state that the transport takes requests from and gives responses to,
+    /// e.g. after the session has reset its state to reconnect. It must not be called while the
+    /// transport is connected.
+    pub fn set_session_state(&mut self, session_state: Arc<RwLock<SessionState>>) {
+        {
+            let session_state = trace_read_lock!(session_state);
+            self.connection_state = session_state.connection_state();
+            self.message_queue = session_state.message_queue.clone();
+        }
+        self.session_state = session_state;
+    }
+
     /// Disconnects the stream from the server (if it is connected)
     pub fn wait_for_disconnect(&self) {
         debug!("Waiting for a disconnect");
@@ -396,9 +550,44 @@ impl TcpTransport {
         self.connection_state.is_connected()
     }
 
+    /// Waits for the future that connects a socket, failing if it takes longer than the connect
+    /// timeout in milliseconds, 0 meaning it is not limited
+    pub(crate) async fn connect_within<F>(
+        connect_timeout: u32,
+        connect: F,
+    ) -> Result<TcpStream, StatusCode>
+    where
+        F: Future<Output = Result<TcpStream, StatusCode>>,
+    {
+        if connect_timeout == 0 {
+            connect.await
+        } else {
+            timeout(Duration::from_millis(connect_timeout as u64), connect)
+                .await
+                .unwrap_or_else(|_| {
+                    error!("Could not connect within {} ms", connect_timeout);
+                    Err(StatusCode::BadTimeout)
+                })
+        }
+    }
+
+    /// Sets the options on a connected socket. A connection whose options cannot all be set is
+    /// still used.
+    fn apply_socket_options(socket: &TcpStream, socket_options: &SocketOptions) {
+        if let Err(err) = socket_options.apply(socket) {
+            warn!(
+                "Could not set the options of socket {:?}, {:?}",
+                socket, err
+            );
+        }
+    }
+
     /// This is the main connection task for a connection.
+    #[allow(clippy::too_many_arguments)]
     async fn connection_task(
-        addr: SocketAddr,
+        addr: ConnectTo,
+        socket_options: SocketOptions,
+        connect_timeout: u32,
         connection_state: ConnectionStateMgr,
         endpoint_url: String,
         session_state: Arc<RwLock<SessionState>>,
@@ -406,17 +595,53 @@ impl TcpTransport {
         message_queue: Arc<RwLock<MessageQueue>>,
     ) -> Result<(ReadState, WriteState), StatusCode> {
         debug!(
-            "Creating a connection task to connect to {} with url {}",
+            "Creating a connection task to connect to {:?} with url {}",
             addr, endpoint_url
         );
 
         connection_state.set_state(ConnectionState::Connecting);
-        let socket = TcpStream::connect(&addr).await.map_err(|err| {
-            error!("Could not connect to host {}, {:?}", addr, err);
-            StatusCode::BadCommunicationError
-        })?;
+        let (reader, writer): (
+            Box<dyn AsyncRead + Unpin + Send>,
+            Box<dyn AsyncWrite + Unpin + Send>,
+        ) = match addr {
+            ConnectTo::Tcp(addr) => {
+                let socket = Self::connect_within(connect_timeout, async {
+                    TcpStream::connect(&addr).await.map_err(|err| {
+                        error!("Could not connect to host {}, {:?}", addr, err);
+                        StatusCode::BadCommunicationError
+                    })
+                })
+                .await?;
+                Self::apply_socket_options(&socket, &socket_options);
+                let (reader, writer) = tokio::io::split(socket);
+                (Box::new(reader), Box::new(writer))
+            }
+            ConnectTo::Loopback => {
+                let stream = loopback::connect(
+                    &endpoint_url,
+                    crate::core::constants::DEFAULT_OPC_UA_SERVER_PORT,
+                )?;
+                let (reader, writer) = tokio::io::split(stream);
+                (Box::new(reader), Box::new(writer))
+            }
+            ConnectTo::Local(socket_path) => {
+                let stream = local_socket::connect(&socket_path).await.map_err(|err| {
+                    error!("Could not connect to local socket {}, {:?}", socket_path, err);
+                    StatusCode::BadCommunicationError
+                })?;
+                let (reader, writer) = tokio::io::split(stream);
+                (Box::new(reader), Box::new(writer))
+            }
+            ConnectTo::Proxy(proxy, host, port) => {
+                let socket =
+                    Self::connect_within(connect_timeout, proxy::connect(&proxy, &host, port))
+                        .await?;
+                Self::apply_socket_options(&socket, &socket_options);
+                let (reader, writer) = tokio::io::split(socket);
+                (Box::new(reader), Box::new(writer))
+            }
+        };
         connection_state.set_state(ConnectionState::Connected);
-        let (reader, writer) = tokio::io::split(socket);
 
         let (hello, mut read_state, mut write_state) = {
             let session_state = trace_read_lock!(session_state);
@@ -452,8 +677,29 @@ impl TcpTransport {
         connection_state.set_state(ConnectionState::WaitingForAck);
         match read_state.framed_read.next().await {
             Some(Ok(Message::Acknowledge(ack))) => {
-                // TODO revise our sizes and other things according to the ACK
-                log::trace!("Received acknowledgement: {:?}", ack)
+                log::trace!("Received acknowledgement: {:?}", ack);
+                if !ack.is_valid_buffer_sizes() {
+                    error!("ACK buffer sizes are invalid");
+                    return Err(StatusCode::BadCommunicationError);
+                }
+                // Chunks that are sent must fit the server's receive buffer, and requests must
+                // not exceed what it receives
+                let send_buffer_size = {
+                    let session_state = trace_read_lock!(session_state);
+                    session_state.send_buffer_size()
+                };
+                write_state.send_buffer.set_limits(
+                    send_buffer_size.min(ack.receive_buffer_size as usize),
+                    ack.max_message_size as usize,
+                    ack.max_chunk_count as usize,
+                );
+            }
+            Some(Ok(Message::Error(error))) => {
+                // The server refused the connection, e.g. because it is too busy
+                let status_code = StatusCode::from_u32(error.error)
+                    .unwrap_or(StatusCode::BadUnexpectedError);
+                error!("Server sent an error instead of an ACK, {}", status_code);
+                return Err(status_code);
             }
             other => {
                 error!("Unexpected error while waiting for server ACK. Expected ACK, got {:?}", other);
@@ -468,6 +714,7 @@ impl TcpTransport {
         write_state: &mut WriteState,
     ) -> Result<(), StatusCode> {
         let bytes_to_write = write_state.send_buffer.bytes_to_write();
+        capture(CaptureDirection::Sent, CaptureForm::Raw, &bytes_to_write);
         write_state.writer.write_all(&bytes_to_write).await.map_err(|e| {
             error!("write bytes task failed: {}", e);
             StatusCode::BadCommunicationError
@@ -551,12 +798,22 @@ impl TcpTransport {
 
                     // Write it to the outgoing buffer
                     let request_handle = request.request_handle();
-                    write_state.send_request(request)?;
+                    let request_id = match write_state.send_request(request) {
+                        Err(StatusCode::BadRequestTooLarge) => {
+                            // The request is failed without sending it, and the connection is kept
+                            let fault =
+                                service_fault(request_handle, StatusCode::BadRequestTooLarge);
+                            let mut message_queue = trace_write_lock!(write_state.message_queue);
+                            message_queue.store_response(fault);
+                            continue;
+                        }
+                        result => result?,
+                    };
                     // Indicate the request was processed
                     {
                         let mut message_queue =
                             trace_write_lock!(write_state.message_queue);
-                        message_queue.request_was_processed(request_handle);
+                        message_queue.request_was_processed(request_handle, request_id);
                     }
                     Self::write_bytes_task(&mut write_state).await?;
                     if close_connection {
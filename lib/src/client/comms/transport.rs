@@ -1,8 +0,0 @@
-// OPCUA for Rust
-// SPDX-License-Identifier: MPL-2.0
-// Copyright (C) 2017-2022 Adam Lock
-
-/// A trait common to all transport implementations
-pub(crate) trait Transport {
-    // Common functions will go here
-}
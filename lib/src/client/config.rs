@@ -12,12 +12,12 @@ use std::{
 };
 
 use crate::{
-    core::config::Config,
-    crypto::SecurityPolicy,
+    core::{comms::socket_options::SocketOptions, config::Config},
+    crypto::{SecurityPolicy, SystemCertificateStore},
     types::{ApplicationType, MessageSecurityMode, UAString},
 };
 
-use super::session_retry_policy::SessionRetryPolicy;
+use super::{failover::FailoverMode, session_retry_policy::SessionRetryPolicy};
 
 pub const ANONYMOUS_USER_TOKEN_ID: &str = "ANONYMOUS";
 
@@ -159,6 +159,73 @@ pub struct Performance {
     pub single_threaded_executor: bool,
 }
 
+/// The protocol of a proxy that connections are tunnelled through
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProxyKind {
+    /// A SOCKS5 proxy
+    Socks5,
+    /// An HTTP proxy that tunnels connections with the CONNECT method
+    Http,
+}
+
+/// A proxy that a client connects to servers through, e.g. because a DMZ blocks direct outbound
+/// TCP connections. The proxy is given the host name of a server, which it resolves itself.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ProxyConfig {
+    /// The protocol of the proxy
+    pub kind: ProxyKind,
+    /// The host and port of the proxy, e.g. `proxy.example.com:1080`
+    pub address: String,
+    /// User name to authenticate to the proxy with, if it requires it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Password of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    pub fn new<T>(kind: ProxyKind, address: T) -> ProxyConfig
+    where
+        T: Into<String>,
+    {
+        ProxyConfig {
+            kind,
+            address: address.into(),
+            user: None,
+            password: None,
+        }
+    }
+
+    /// Sets the credentials to authenticate to the proxy with
+    pub fn credentials<U, P>(mut self, user: U, password: P) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.user = Some(user.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Tests if the proxy has an address with a port and no more than 255 bytes of credentials,
+    /// which is the most that SOCKS5 can send
+    pub fn is_valid(&self) -> bool {
+        let has_port = self
+            .address
+            .rsplit_once(':')
+            .map(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+            .unwrap_or(false);
+        let credential_fits = |credential: &Option<String>| {
+            credential
+                .as_ref()
+                .map(|credential| credential.len() <= 255)
+                .unwrap_or(true)
+        };
+        has_port && credential_fits(&self.user) && credential_fits(&self.password)
+    }
+}
+
 /// Client OPC UA configuration
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ClientConfig {
@@ -175,12 +242,20 @@ pub struct ClientConfig {
     pub certificate_path: Option<PathBuf>,
     /// Custom private key path, to be used instead of the default private key path
     pub private_key_path: Option<PathBuf>,
+    /// Reads the certificate and private key, and trusted certificates, from the certificate
+    /// store of the operating system instead of the PKI folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_certificate_store: Option<SystemCertificateStore>,
     /// Auto trusts server certificates. For testing/samples only unless you're sure what you're
     /// doing.
     pub trust_server_certs: bool,
     /// Verify server certificates. For testing/samples only unless you're sure what you're
     /// doing.
     pub verify_server_certs: bool,
+    /// Trusts a server certificate that is not in the trusted folder if one there has the same
+    /// subject key identifier and public key, e.g. because it was renewed with the same key
+    #[serde(default)]
+    pub match_subject_key_identifier: bool,
     /// PKI folder, either absolute or relative to executable
     pub pki_dir: PathBuf,
     /// Preferred locales
@@ -199,6 +274,46 @@ pub struct ClientConfig {
     pub session_retry_interval: u32,
     /// Session timeout period in milliseconds
     pub session_timeout: u32,
+    /// How long in milliseconds to wait for the response to a request before it times out. It is
+    /// sent to the server as the timeout hint of each request.
+    #[serde(default = "ClientConfig::default_request_timeout")]
+    pub request_timeout: u32,
+    /// How many requests a session may have outstanding at once when it is called from several
+    /// threads. Requests beyond that wait for an earlier one to be answered. 0 means no limit.
+    #[serde(default = "ClientConfig::default_max_inflight_requests")]
+    pub max_inflight_requests: usize,
+    /// How often in milliseconds a session reads the state of the server to check that it is
+    /// still running. 0, the default, means the session does not check.
+    #[serde(default)]
+    pub keep_alive_interval: u32,
+    /// Endpoint urls of redundant servers that a session fails over to if it cannot reconnect to
+    /// the server it was connected to. Empty, the default, means the session does not fail over.
+    #[serde(default)]
+    pub redundant_servers: Vec<String>,
+    /// How the redundant servers back each other up
+    #[serde(default)]
+    pub failover_mode: FailoverMode,
+    /// A proxy that connections to servers are tunnelled through. None, the default, means
+    /// servers are connected to directly.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// How long in milliseconds to wait for a TCP connection to a server or proxy. 0, the
+    /// default, waits as long as the operating system does.
+    #[serde(default)]
+    pub connect_timeout: u32,
+    /// Options that are set on the TCP socket of each connection
+    #[serde(default)]
+    pub socket_options: SocketOptions,
+    /// Refuses to create sessions over secure channels with security policy None, so that a
+    /// misconfigured endpoint cannot silently send credentials and data in the clear. Getting
+    /// the endpoints of a server still uses security policy None. False, the default, allows it.
+    #[serde(default)]
+    pub secure_sessions_only: bool,
+    /// Connects to servers in this process that accept loopback connections over in-memory
+    /// streams instead of TCP, e.g. in integration tests. False, the default, always connects to
+    /// servers over the network.
+    #[serde(default)]
+    pub loopback: bool,
     /// Client performance settings
     pub performance: Performance,
     /// Session name
@@ -218,6 +333,12 @@ impl Config for ClientConfig {
             error!("Application uri is empty");
             valid = false;
         }
+        if let Some(ref system_certificate_store) = self.system_certificate_store {
+            if !system_certificate_store.is_valid() {
+                error!("System certificate store is invalid");
+                valid = false;
+            }
+        }
         if self.user_tokens.contains_key(ANONYMOUS_USER_TOKEN_ID) {
             error!(
                 "User tokens contains the reserved \"{}\" id",
@@ -273,6 +394,12 @@ impl Config for ClientConfig {
                 }
             });
         }
+        if let Some(ref proxy) = self.proxy {
+            if !proxy.is_valid() {
+                error!("Proxy {} is invalid", proxy.address);
+                valid = false;
+            }
+        }
         if self.session_retry_limit < 0 && self.session_retry_limit != -1 {
             error!("Session retry limit of {} is invalid - must be -1 (infinite), 0 (never) or a positive value", self.session_retry_limit);
             valid = false;
@@ -306,6 +433,18 @@ impl Default for ClientConfig {
 impl ClientConfig {
     /// The default PKI directory
     pub const PKI_DIR: &'static str = "pki";
+    /// The default request timeout in milliseconds
+    pub const DEFAULT_REQUEST_TIMEOUT: u32 = 10 * 1000;
+    /// The default number of requests that a session may have outstanding at once
+    pub const DEFAULT_MAX_INFLIGHT_REQUESTS: usize = 16;
+
+    fn default_request_timeout() -> u32 {
+        Self::DEFAULT_REQUEST_TIMEOUT
+    }
+
+    fn default_max_inflight_requests() -> usize {
+        Self::DEFAULT_MAX_INFLIGHT_REQUESTS
+    }
 
     pub fn new<T>(application_name: T, application_uri: T) -> Self
     where
@@ -321,8 +460,10 @@ impl ClientConfig {
             create_sample_keypair: false,
             certificate_path: None,
             private_key_path: None,
+            system_certificate_store: None,
             trust_server_certs: false,
             verify_server_certs: true,
+            match_subject_key_identifier: false,
             product_uri: String::new(),
             pki_dir,
             preferred_locales: Vec::new(),
@@ -332,6 +473,16 @@ impl ClientConfig {
             session_retry_limit: SessionRetryPolicy::DEFAULT_RETRY_LIMIT as i32,
             session_retry_interval: SessionRetryPolicy::DEFAULT_RETRY_INTERVAL_MS,
             session_timeout: 0,
+            request_timeout: Self::DEFAULT_REQUEST_TIMEOUT,
+            max_inflight_requests: Self::DEFAULT_MAX_INFLIGHT_REQUESTS,
+            keep_alive_interval: 0,
+            redundant_servers: Vec::new(),
+            failover_mode: FailoverMode::default(),
+            proxy: None,
+            connect_timeout: 0,
+            socket_options: SocketOptions::default(),
+            secure_sessions_only: false,
+            loopback: false,
             decoding_options: DecodingOptions {
                 max_array_length: decoding_options.max_array_length,
                 max_string_length: decoding_options.max_string_length,
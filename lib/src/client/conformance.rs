@@ -0,0 +1,542 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains a self-test harness that exercises a running server through a connected session
+//! against a set of assertions taken from the OPC UA specification, and produces a report.
+//!
+//! The checks are grouped by the smallest server profile that requires them, i.e. nano, micro
+//! and embedded. They cover session handling, read and browse correctness and subscription
+//! timing. Passing them is no substitute for the OPC Foundation's Compliance Test Tool (CTT)
+//! but it catches common problems before a server is put in front of it.
+//!
+//! ```no_run
+//! use opcua::client::prelude::*;
+//!
+//! # fn run(session: std::sync::Arc<opcua::sync::RwLock<Session>>) {
+//! let report = ConformanceTest::new(session, ConformanceProfile::Micro).run();
+//! println!("{}", report);
+//! assert!(report.passed());
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::sync::*;
+use crate::types::{service_types::*, status_code::StatusCode, *};
+
+use crate::client::{
+    callbacks::DataChangeCallback,
+    error::ServiceError,
+    session::{
+        services::{AttributeService, MonitoredItemService, SubscriptionService, ViewService},
+        session::{Session, SessionCommand},
+    },
+};
+
+/// The namespace that must be the first entry of a server's namespace array
+const OPC_UA_NAMESPACE: &str = "http://opcfoundation.org/UA/";
+
+/// The publishing interval requested by the subscription timing check
+const PUBLISHING_INTERVAL_MS: f64 = 100.0;
+
+/// The minimum number of publishing intervals the subscription timing check observes
+const OBSERVED_PUBLISHING_INTERVALS: u32 = 10;
+
+/// The server profiles that checks are grouped by. Each profile includes the checks of the
+/// profiles before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConformanceProfile {
+    /// Nano Embedded Device Server, sessions, read and browse without subscriptions
+    Nano,
+    /// Micro Embedded Device Server, adds subscriptions and monitored items
+    Micro,
+    /// Embedded UA Server, adds browse path translation and multiple subscriptions
+    Embedded,
+}
+
+impl fmt::Display for ConformanceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConformanceProfile::Nano => "Nano",
+            ConformanceProfile::Micro => "Micro",
+            ConformanceProfile::Embedded => "Embedded",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The outcome of a single check
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    Passed,
+    /// The check failed for the reason given
+    Failed(String),
+}
+
+/// The result of a single check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// The smallest profile that requires the check
+    pub profile: ConformanceProfile,
+    /// The area of functionality the check covers, e.g. "Session" or "Browse"
+    pub group: &'static str,
+    /// What the check asserts
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == CheckOutcome::Passed
+    }
+}
+
+/// The results of running the checks of a profile against a server
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// The profile that was tested
+    pub profile: ConformanceProfile,
+    pub results: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    /// Tests if every check passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|r| r.passed())
+    }
+
+    /// Returns the checks that failed
+    pub fn failures(&self) -> Vec<&CheckResult> {
+        self.results.iter().filter(|r| !r.passed()).collect()
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self.failures().len();
+        writeln!(
+            f,
+            "Conformance report for the {} profile, {} passed, {} failed",
+            self.profile,
+            self.results.len() - failed,
+            failed
+        )?;
+        for result in &self.results {
+            match result.outcome {
+                CheckOutcome::Passed => writeln!(
+                    f,
+                    "[PASS] {} / {} - {}",
+                    result.profile, result.group, result.name
+                )?,
+                CheckOutcome::Failed(ref reason) => writeln!(
+                    f,
+                    "[FAIL] {} / {} - {}: {}",
+                    result.profile, result.group, result.name, reason
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+type CheckFn = fn(&Arc<RwLock<Session>>) -> Result<(), String>;
+
+/// A check and the profile that requires it
+struct Check {
+    profile: ConformanceProfile,
+    group: &'static str,
+    name: &'static str,
+    run: CheckFn,
+}
+
+const CHECKS: &[Check] = &[
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Session",
+        name: "Server state is Running",
+        run: check_server_state,
+    },
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Session",
+        name: "Namespace array starts with the OPC UA namespace",
+        run: check_namespace_array,
+    },
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Read",
+        name: "Reading an unknown node returns BadNodeIdUnknown",
+        run: check_read_unknown_node,
+    },
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Read",
+        name: "Reading the value of an object returns BadAttributeIdInvalid",
+        run: check_read_invalid_attribute,
+    },
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Browse",
+        name: "Objects folder references the Server object",
+        run: check_browse_objects_folder,
+    },
+    Check {
+        profile: ConformanceProfile::Nano,
+        group: "Browse",
+        name: "Browsing an unknown node returns BadNodeIdUnknown",
+        run: check_browse_unknown_node,
+    },
+    Check {
+        profile: ConformanceProfile::Micro,
+        group: "Subscription",
+        name: "Data changes arrive at the revised publishing interval",
+        run: check_subscription_timing,
+    },
+    Check {
+        profile: ConformanceProfile::Micro,
+        group: "Subscription",
+        name: "Monitoring an unknown node returns BadNodeIdUnknown",
+        run: check_monitor_unknown_node,
+    },
+    Check {
+        profile: ConformanceProfile::Embedded,
+        group: "Browse",
+        name: "Browse path Objects/Server translates to the Server object",
+        run: check_translate_browse_path,
+    },
+    Check {
+        profile: ConformanceProfile::Embedded,
+        group: "Subscription",
+        name: "A session can hold several subscriptions",
+        run: check_multiple_subscriptions,
+    },
+];
+
+/// Runs the checks of a profile against the server of a connected session.
+pub struct ConformanceTest {
+    session: Arc<RwLock<Session>>,
+    profile: ConformanceProfile,
+}
+
+impl ConformanceTest {
+    /// Creates a test of the profile. The session must already be connected and activated.
+    pub fn new(session: Arc<RwLock<Session>>, profile: ConformanceProfile) -> ConformanceTest {
+        ConformanceTest { session, profile }
+    }
+
+    /// Runs every check up to and including the profile and reports the results. The
+    /// subscription checks briefly run the session to receive notifications so the caller
+    /// must not be running it.
+    pub fn run(&self) -> ConformanceReport {
+        let results = CHECKS
+            .iter()
+            .filter(|check| check.profile <= self.profile)
+            .map(|check| {
+                info!("Running conformance check \"{}\"", check.name);
+                let outcome = match (check.run)(&self.session) {
+                    Ok(_) => CheckOutcome::Passed,
+                    Err(reason) => {
+                        error!("Conformance check \"{}\" failed, {}", check.name, reason);
+                        CheckOutcome::Failed(reason)
+                    }
+                };
+                CheckResult {
+                    profile: check.profile,
+                    group: check.group,
+                    name: check.name,
+                    outcome,
+                }
+            })
+            .collect();
+        ConformanceReport {
+            profile: self.profile,
+            results,
+        }
+    }
+}
+
+/// Turns a failed service call into the reason a check failed
+fn service_failed(service: &str, error: ServiceError) -> String {
+    format!("{} failed with {}", service, error)
+}
+
+fn expect_status(actual: Option<StatusCode>, expected: StatusCode) -> Result<(), String> {
+    let actual = actual.unwrap_or(StatusCode::Good);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {} but got {}", expected, actual))
+    }
+}
+
+fn read_one(
+    session: &Arc<RwLock<Session>>,
+    node_to_read: ReadValueId,
+) -> Result<DataValue, String> {
+    let session = trace_read_lock!(session);
+    let mut results = session
+        .read(&[node_to_read], TimestampsToReturn::Neither, 0f64)
+        .map_err(|err| service_failed("Read", err))?;
+    if results.len() == 1 {
+        Ok(results.remove(0))
+    } else {
+        Err(format!(
+            "Read returned {} results for 1 node",
+            results.len()
+        ))
+    }
+}
+
+fn browse_one(session: &Arc<RwLock<Session>>, node_id: NodeId) -> Result<BrowseResult, String> {
+    let session = trace_read_lock!(session);
+    let browse_description = BrowseDescription {
+        node_id,
+        browse_direction: BrowseDirection::Forward,
+        reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+        include_subtypes: true,
+        node_class_mask: 0,
+        result_mask: BrowseDescriptionResultMask::all().bits(),
+    };
+    let mut results = session
+        .browse(&[browse_description])
+        .map_err(|err| service_failed("Browse", err))?
+        .unwrap_or_default();
+    if results.len() == 1 {
+        Ok(results.remove(0))
+    } else {
+        Err(format!(
+            "Browse returned {} results for 1 node",
+            results.len()
+        ))
+    }
+}
+
+fn check_server_state(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let value = read_one(
+        session,
+        NodeId::from(&VariableId::Server_ServerStatus_State).into(),
+    )?;
+    match value.value {
+        Some(Variant::Int32(state)) if state == ServerState::Running as i32 => Ok(()),
+        value => Err(format!("server state is {:?}", value)),
+    }
+}
+
+fn check_namespace_array(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let value = read_one(
+        session,
+        NodeId::from(&VariableId::Server_NamespaceArray).into(),
+    )?;
+    if let Some(Variant::Array(array)) = value.value {
+        match array.values.first() {
+            Some(Variant::String(namespace)) if namespace.as_ref() == OPC_UA_NAMESPACE => Ok(()),
+            namespace => Err(format!("first namespace is {:?}", namespace)),
+        }
+    } else {
+        Err(format!("namespace array is {:?}", value.value))
+    }
+}
+
+fn check_read_unknown_node(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let value = read_one(session, NodeId::new(0, "ConformanceUnknownNode").into())?;
+    expect_status(value.status, StatusCode::BadNodeIdUnknown)
+}
+
+fn check_read_invalid_attribute(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let value = read_one(session, NodeId::objects_folder_id().into())?;
+    expect_status(value.status, StatusCode::BadAttributeIdInvalid)
+}
+
+fn check_browse_objects_folder(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let result = browse_one(session, NodeId::objects_folder_id())?;
+    expect_status(Some(result.status_code), StatusCode::Good)?;
+    let server_id: NodeId = ObjectId::Server.into();
+    let references = result.references.unwrap_or_default();
+    if references.iter().any(|r| r.node_id.node_id == server_id) {
+        Ok(())
+    } else {
+        Err(format!(
+            "the {} references of the Objects folder do not include the Server object",
+            references.len()
+        ))
+    }
+}
+
+fn check_browse_unknown_node(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let result = browse_one(session, NodeId::new(0, "ConformanceUnknownNode"))?;
+    expect_status(Some(result.status_code), StatusCode::BadNodeIdUnknown)
+}
+
+fn check_subscription_timing(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let notifications = Arc::new(Mutex::new(Vec::new()));
+    let (subscription_id, publishing_interval) = {
+        let session = trace_read_lock!(session);
+        let callback_notifications = notifications.clone();
+        let subscription_id = session
+            .create_subscription(
+                PUBLISHING_INTERVAL_MS,
+                30,
+                10,
+                0,
+                0,
+                true,
+                DataChangeCallback::new(move |_| {
+                    trace_lock!(callback_notifications).push(Instant::now());
+                }),
+            )
+            .map_err(|err| service_failed("CreateSubscription", err))?;
+        let publishing_interval = {
+            let subscription_state = session.subscription_state();
+            let subscription_state = trace_read_lock!(subscription_state);
+            subscription_state
+                .get(subscription_id)
+                .map(|s| s.publishing_interval())
+                .unwrap_or(PUBLISHING_INTERVAL_MS)
+        };
+        let results = session
+            .create_monitored_items(
+                subscription_id,
+                TimestampsToReturn::Both,
+                &[NodeId::from(&VariableId::Server_ServerStatus_CurrentTime).into()],
+            )
+            .map_err(|err| service_failed("CreateMonitoredItems", err))?;
+        if let Some(result) = results.first() {
+            expect_status(Some(result.status_code), StatusCode::Good)?;
+        }
+        (subscription_id, publishing_interval)
+    };
+
+    if publishing_interval <= 0.0 {
+        return Err(format!(
+            "revised publishing interval is {}",
+            publishing_interval
+        ));
+    }
+
+    // Run the session so it receives publish responses for a number of publishing intervals
+    let observe_for =
+        Duration::from_millis((publishing_interval * OBSERVED_PUBLISHING_INTERVALS as f64) as u64);
+    let session_tx = Session::run_async(session.clone());
+    thread::sleep(observe_for);
+    let _ = session_tx.send(SessionCommand::Stop);
+
+    {
+        let session = trace_read_lock!(session);
+        let _ = session.delete_subscription(subscription_id);
+    }
+
+    let notifications = trace_lock!(notifications);
+    // The first notification includes the time taken to sample the item so allow for a few
+    // intervals to go missing
+    let minimum = (OBSERVED_PUBLISHING_INTERVALS / 2) as usize;
+    if notifications.len() < minimum {
+        Err(format!(
+            "only {} data changes arrived in {} ms with a publishing interval of {} ms",
+            notifications.len(),
+            observe_for.as_millis(),
+            publishing_interval
+        ))
+    } else {
+        let first = notifications.first().unwrap();
+        let last = notifications.last().unwrap();
+        let average_interval =
+            (*last - *first).as_millis() as f64 / (notifications.len() - 1) as f64;
+        if average_interval > publishing_interval * 2.0 {
+            Err(format!(
+                "data changes arrived every {} ms on average with a publishing interval of {} ms",
+                average_interval, publishing_interval
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn check_monitor_unknown_node(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let session = trace_read_lock!(session);
+    let subscription_id = session
+        .create_subscription(
+            PUBLISHING_INTERVAL_MS,
+            30,
+            10,
+            0,
+            0,
+            true,
+            DataChangeCallback::new(|_| {}),
+        )
+        .map_err(|err| service_failed("CreateSubscription", err))?;
+    let results = session.create_monitored_items(
+        subscription_id,
+        TimestampsToReturn::Both,
+        &[NodeId::new(0, "ConformanceUnknownNode").into()],
+    );
+    let _ = session.delete_subscription(subscription_id);
+    let results =
+        results.map_err(|err| service_failed("CreateMonitoredItems", err))?;
+    expect_status(
+        results.first().map(|r| r.status_code),
+        StatusCode::BadNodeIdUnknown,
+    )
+}
+
+fn check_translate_browse_path(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let session = trace_read_lock!(session);
+    let relative_path =
+        RelativePath::from_str("/0:Server", &RelativePathElement::default_node_resolver)
+            .map_err(|_| "cannot parse the relative path".to_string())?;
+    let results = session
+        .translate_browse_paths_to_node_ids(&[BrowsePath {
+            starting_node: NodeId::objects_folder_id(),
+            relative_path,
+        }])
+        .map_err(|err| service_failed("TranslateBrowsePathsToNodeIds", err))?;
+    let result = results
+        .first()
+        .ok_or_else(|| "no result was returned".to_string())?;
+    expect_status(Some(result.status_code), StatusCode::Good)?;
+    let server_id: NodeId = ObjectId::Server.into();
+    match result.targets {
+        Some(ref targets) if targets.iter().any(|t| t.target_id.node_id == server_id) => Ok(()),
+        ref targets => Err(format!("targets are {:?}", targets)),
+    }
+}
+
+fn check_multiple_subscriptions(session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    const SUBSCRIPTION_COUNT: usize = 2;
+    let session = trace_read_lock!(session);
+    let mut subscription_ids = Vec::with_capacity(SUBSCRIPTION_COUNT);
+    let mut result = Ok(());
+    for _ in 0..SUBSCRIPTION_COUNT {
+        match session.create_subscription(
+            PUBLISHING_INTERVAL_MS,
+            30,
+            10,
+            0,
+            0,
+            true,
+            DataChangeCallback::new(|_| {}),
+        ) {
+            Ok(subscription_id) => subscription_ids.push(subscription_id),
+            Err(err) => {
+                result = Err(service_failed("CreateSubscription", err));
+                break;
+            }
+        }
+    }
+    if result.is_ok() {
+        subscription_ids.dedup();
+        if subscription_ids.len() != SUBSCRIPTION_COUNT {
+            result = Err("the server returned the same subscription id twice".to_string());
+        }
+    }
+    if !subscription_ids.is_empty() {
+        let _ = session.delete_subscriptions(&subscription_ids);
+    }
+    result
+}
@@ -0,0 +1,138 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`ServiceError`] returned by the service calls of a session.
+//!
+//! [`ServiceError`]: ./enum.ServiceError.html
+
+use std::fmt;
+
+use crate::types::{
+    diagnostic_info::ResponseDiagnostics, response_header::ResponseHeader, status_code::StatusCode,
+};
+
+/// The details of a bad service result, either from a service fault or from the header of a
+/// response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceFaultInfo {
+    /// The service result of the response header.
+    pub status_code: StatusCode,
+    /// The handle of the request that failed, as set in its request header.
+    pub request_handle: u32,
+    /// The service diagnostics of the response, if the session asked for them with
+    /// `Session::set_return_diagnostics()` and the server returned any.
+    pub diagnostics: Option<Box<ResponseDiagnostics>>,
+}
+
+impl ServiceFaultInfo {
+    pub(crate) fn new(response_header: &ResponseHeader) -> ServiceFaultInfo {
+        ServiceFaultInfo {
+            status_code: response_header.service_result,
+            request_handle: response_header.request_handle,
+            diagnostics: ResponseDiagnostics::new(response_header, None).map(Box::new),
+        }
+    }
+}
+
+/// The error returned by a service call of a session. Bad service results from the server are
+/// mapped to a variant by their status code so callers can branch on the common faults.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceError {
+    /// The call failed on the client without a response from the server, e.g. because the session
+    /// is not connected, the request was invalid or `BadTimeout` when no response arrived in time.
+    Client(StatusCode),
+    /// The server does not know the session, e.g. because it timed out, was closed or has not been
+    /// activated.
+    SessionInvalid(ServiceFaultInfo),
+    /// The user of the session is not allowed to make the request or their identity was rejected.
+    AccessDenied(ServiceFaultInfo),
+    /// The server does not support the service.
+    ServiceUnsupported(ServiceFaultInfo),
+    /// The request holds more operations than the server allows.
+    TooManyOperations(ServiceFaultInfo),
+    /// The server is too busy or short of resources to process the request.
+    ServerBusy(ServiceFaultInfo),
+    /// The server gave up on the request because it could not process it in time.
+    Timeout(ServiceFaultInfo),
+    /// Any other bad service result.
+    Fault(ServiceFaultInfo),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fault() {
+            Some(fault) => write!(
+                f,
+                "{} returned for request {}",
+                fault.status_code, fault.request_handle
+            ),
+            None => write!(f, "{}", self.status_code()),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl From<StatusCode> for ServiceError {
+    fn from(status_code: StatusCode) -> Self {
+        ServiceError::Client(status_code)
+    }
+}
+
+impl From<ServiceFaultInfo> for ServiceError {
+    fn from(fault: ServiceFaultInfo) -> Self {
+        match fault.status_code.status() {
+            StatusCode::BadSessionIdInvalid
+            | StatusCode::BadSessionClosed
+            | StatusCode::BadSessionNotActivated => ServiceError::SessionInvalid(fault),
+            StatusCode::BadUserAccessDenied
+            | StatusCode::BadIdentityTokenInvalid
+            | StatusCode::BadIdentityTokenRejected => ServiceError::AccessDenied(fault),
+            StatusCode::BadServiceUnsupported => ServiceError::ServiceUnsupported(fault),
+            StatusCode::BadTooManyOperations => ServiceError::TooManyOperations(fault),
+            StatusCode::BadTcpServerTooBusy
+            | StatusCode::BadTooManySessions
+            | StatusCode::BadResourceUnavailable => ServiceError::ServerBusy(fault),
+            StatusCode::BadTimeout | StatusCode::BadRequestTimeout => ServiceError::Timeout(fault),
+            _ => ServiceError::Fault(fault),
+        }
+    }
+}
+
+impl From<ServiceError> for StatusCode {
+    fn from(error: ServiceError) -> Self {
+        error.status_code()
+    }
+}
+
+impl ServiceError {
+    /// Returns the status code of the error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ServiceError::Client(status_code) => *status_code,
+            ServiceError::SessionInvalid(fault)
+            | ServiceError::AccessDenied(fault)
+            | ServiceError::ServiceUnsupported(fault)
+            | ServiceError::TooManyOperations(fault)
+            | ServiceError::ServerBusy(fault)
+            | ServiceError::Timeout(fault)
+            | ServiceError::Fault(fault) => fault.status_code,
+        }
+    }
+
+    /// Returns the details of the bad service result from the server, or `None` if the call
+    /// failed on the client.
+    pub fn fault(&self) -> Option<&ServiceFaultInfo> {
+        match self {
+            ServiceError::Client(_) => None,
+            ServiceError::SessionInvalid(fault)
+            | ServiceError::AccessDenied(fault)
+            | ServiceError::ServiceUnsupported(fault)
+            | ServiceError::TooManyOperations(fault)
+            | ServiceError::ServerBusy(fault)
+            | ServiceError::Timeout(fault)
+            | ServiceError::Fault(fault) => Some(fault),
+        }
+    }
+}
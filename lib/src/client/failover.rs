@@ -0,0 +1,100 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`FailoverPolicy`] that lets a session move to a redundant server when it cannot
+//! reconnect to the one it was connected to.
+//!
+//! [`FailoverPolicy`]: ./struct.FailoverPolicy.html
+
+/// How the servers of a redundant set back each other up, following the redundancy models of
+/// Part 4. It decides how much of the session a client tries to keep when it fails over.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum FailoverMode {
+    /// Backup servers know nothing of the session, so the client creates a new session and
+    /// creates its subscriptions again. This is the default.
+    #[default]
+    Cold,
+    /// Backup servers are running but do not share the session, so the client creates a new
+    /// session, tries to transfer its subscriptions and creates those that cannot be transferred
+    Warm,
+    /// Backup servers are running and may share subscriptions, which is handled the same way as
+    /// `Warm`
+    Hot,
+    /// Backup servers mirror sessions and subscriptions, so the client first tries to activate
+    /// its existing session on the backup and otherwise handles it the same way as `Hot`
+    HotAndMirrored,
+}
+
+impl FailoverMode {
+    /// Tests if the existing session is activated on a server it fails over to
+    pub(crate) fn reactivates_session(self) -> bool {
+        self == FailoverMode::HotAndMirrored
+    }
+
+    /// Tests if subscriptions are transferred to a server it fails over to, instead of only
+    /// being created again
+    pub(crate) fn transfers_subscriptions(self) -> bool {
+        self != FailoverMode::Cold
+    }
+}
+
+/// The redundant servers that a session fails over to. When the session cannot reconnect to the
+/// server it was connected to, it tries each of the other servers of the redundant set in turn
+/// and stays on the first that it reconnects to, until that one fails too.
+///
+/// The session reaches a redundant server with the security policy, security mode and user
+/// identity of its own endpoint. If the security policy is not `None` it first asks the server
+/// for its endpoints to find the certificate of the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverPolicy {
+    mode: FailoverMode,
+    /// The endpoint urls of every server of the redundant set
+    server_urls: Vec<String>,
+}
+
+impl FailoverPolicy {
+    /// Creates a policy from the endpoint urls of the redundant servers. The server that the
+    /// session connects to is part of the redundant set whether it is in the list or not.
+    pub fn new<T>(mode: FailoverMode, server_urls: Vec<T>) -> FailoverPolicy
+    where
+        T: Into<String>,
+    {
+        FailoverPolicy {
+            mode,
+            server_urls: server_urls.into_iter().map(|url| url.into()).collect(),
+        }
+    }
+
+    pub fn mode(&self) -> FailoverMode {
+        self.mode
+    }
+
+    pub fn server_urls(&self) -> &[String] {
+        &self.server_urls
+    }
+
+    /// Adds the server to the front of the redundant set if it is not already part of it
+    pub(crate) fn include_server(&mut self, server_url: &str) {
+        if !self.server_urls.iter().any(|url| url == server_url) {
+            self.server_urls.insert(0, server_url.to_string());
+        }
+    }
+
+    /// Returns the servers to fail over to from the current server, in the order they are tried.
+    /// They are the servers after the current one in the list, followed by those before it, so
+    /// a session that keeps failing goes round every server.
+    pub(crate) fn failover_order(&self, current_url: &str) -> Vec<String> {
+        let position = self.server_urls.iter().position(|url| url == current_url);
+        let (after, before) = match position {
+            Some(position) => (position + 1, position),
+            None => (0, self.server_urls.len()),
+        };
+        self.server_urls[after..]
+            .iter()
+            .chain(self.server_urls[..before].iter())
+            .filter(|url| url.as_str() != current_url)
+            .cloned()
+            .collect()
+    }
+}
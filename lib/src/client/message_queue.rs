@@ -2,17 +2,32 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::{collections::HashMap, sync::mpsc::SyncSender};
+use std::{
+    collections::HashMap,
+    sync::mpsc::SyncSender,
+    time::{Duration, Instant},
+};
 
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use crate::core::supported_message::SupportedMessage;
 
+/// A request that has been sent and is waiting for its response
+#[derive(Debug)]
+struct InflightRequest {
+    /// Notified with the response for synchronous requests
+    sender: Option<SyncSender<SupportedMessage>>,
+    /// When the request times out, from the timeout hint of the request
+    timeout_at: Option<Instant>,
+    /// The request id of the chunks it was sent in, once it has been sent
+    request_id: Option<u32>,
+}
+
 pub(crate) struct MessageQueue {
     /// The requests that are in-flight, defined by their request handle and optionally a sender that will be notified with the response.
     /// Basically, the sent requests reside here until the response returns at which point the entry is removed.
     /// If a response is received for which there is no entry, the response will be discarded.
-    inflight_requests: HashMap<u32, Option<SyncSender<SupportedMessage>>>,
+    inflight_requests: HashMap<u32, InflightRequest>,
     /// A map of incoming responses waiting to be processed
     responses: HashMap<u32, SupportedMessage>,
     /// This is the queue that messages will be sent onto the transport for sending
@@ -48,8 +63,19 @@ impl MessageQueue {
         rx
     }
 
-    pub(crate) fn request_was_processed(&mut self, request_handle: u32) {
+    pub(crate) fn request_was_processed(&mut self, request_handle: u32, request_id: u32) {
         debug!("Request {} was processed by the server", request_handle);
+        if let Some(inflight_request) = self.inflight_requests.get_mut(&request_handle) {
+            inflight_request.request_id = Some(request_id);
+        }
+    }
+
+    /// Returns the handle of the in-flight request that was sent in chunks with the request id
+    pub(crate) fn request_handle(&self, request_id: u32) -> Option<u32> {
+        self.inflight_requests
+            .iter()
+            .find(|(_, inflight_request)| inflight_request.request_id == Some(request_id))
+            .map(|(request_handle, _)| *request_handle)
     }
 
     fn send_message(&self, message: Message) -> bool {
@@ -75,8 +101,21 @@ impl MessageQueue {
         sender: Option<SyncSender<SupportedMessage>>,
     ) {
         let request_handle = request.request_handle();
+        let timeout_hint = request.request_header().timeout_hint;
+        let timeout_at = if timeout_hint > 0 {
+            Some(Instant::now() + Duration::from_millis(timeout_hint as u64))
+        } else {
+            None
+        };
         trace!("Sending request {:?} to be sent", request);
-        self.inflight_requests.insert(request_handle, sender);
+        self.inflight_requests.insert(
+            request_handle,
+            InflightRequest {
+                sender,
+                timeout_at,
+                request_id: None,
+            },
+        );
         let _ = self.send_message(Message::SupportedMessage(request));
     }
 
@@ -95,6 +134,29 @@ impl MessageQueue {
         let _ = self.inflight_requests.remove(&request_handle);
     }
 
+    /// Removes the requests whose timeout hint has elapsed without a response, so any response
+    /// that arrives for them is ignored. Returns the request handles of the removed requests.
+    pub(crate) fn remove_timed_out_requests(&mut self, now: Instant) -> Vec<u32> {
+        let timed_out = self
+            .inflight_requests
+            .iter()
+            .filter(|(_, r)| matches!(r.timeout_at, Some(timeout_at) if timeout_at <= now))
+            .map(|(request_handle, _)| *request_handle)
+            .collect::<Vec<_>>();
+        timed_out.iter().for_each(|request_handle| {
+            self.request_has_timed_out(*request_handle);
+        });
+        timed_out
+    }
+
+    /// Returns the request handles of the requests that are waiting for a response, oldest first
+    /// (except if handles wrap).
+    pub(crate) fn inflight_request_handles(&self) -> Vec<u32> {
+        let mut request_handles = self.inflight_requests.keys().copied().collect::<Vec<_>>();
+        request_handles.sort();
+        request_handles
+    }
+
     /// Called by the connection to store a response for the consumption of the session.
     pub(crate) fn store_response(&mut self, response: SupportedMessage) {
         // Remove corresponding request handle from inflight queue, add to responses
@@ -103,8 +165,8 @@ impl MessageQueue {
         debug!("Response to Request {} has been stored", request_handle);
         // Remove the inflight request
         // This true / false is slightly clunky.
-        if let Some(sender) = self.inflight_requests.remove(&request_handle) {
-            if let Some(sender) = sender {
+        if let Some(inflight_request) = self.inflight_requests.remove(&request_handle) {
+            if let Some(sender) = inflight_request.sender {
                 // Synchronous request
                 if let Err(e) = sender.send(response) {
                     error!(
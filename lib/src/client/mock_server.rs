@@ -0,0 +1,378 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`MockServer`], a scriptable stand in for a real server that client applications
+//! can use to unit test their handling of service results and errors.
+//!
+//! The mock server listens on the in-process loopback transport so a client in the same process
+//! that is built with `ClientBuilder::loopback(true)` and connects to its endpoint url talks to it
+//! without touching the network. It only supports
+//! the `None` security policy. Out of the box it answers the services a client needs to connect,
+//! i.e. GetEndpoints, OpenSecureChannel, CreateSession, ActivateSession and CloseSession, and
+//! answers any other request with `BadServiceUnsupported`. A [`MockServerBuilder`] scripts the
+//! responses to individual services, replacing the defaults.
+//!
+//! ```no_run
+//! use opcua::client::prelude::*;
+//!
+//! let server = MockServerBuilder::new("opc.tcp://mock-server:4855/")
+//!     .fault("ActivateSessionRequest", StatusCode::BadIdentityTokenRejected)
+//!     .server()
+//!     .unwrap();
+//! // ... connect a client built with loopback(true) to server.endpoint_url() and test it
+//! // handles the error
+//! ```
+//!
+//! [`MockServer`]: ./struct.MockServer.html
+//! [`MockServerBuilder`]: ./struct.MockServerBuilder.html
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use futures::StreamExt;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+use tokio_util::codec::FramedRead;
+
+use crate::core::{
+    comms::{
+        loopback::LoopbackListener,
+        message_writer::MessageWriter,
+        tcp_codec::{Message, TcpCodec},
+        url::hostname_port_from_url,
+    },
+    constants,
+    prelude::*,
+};
+use crate::crypto::{random, SecurityPolicy};
+use crate::sync::*;
+
+/// The size of the buffers that the mock server advertises and writes messages into
+const BUFFER_SIZE: usize = 65535;
+
+/// A function that produces the response to a request
+type ResponseFn = dyn Fn(&SupportedMessage) -> SupportedMessage + Send + Sync;
+
+/// Scripts the responses of a [`MockServer`] and starts it.
+///
+/// Responses are scripted by the name of the request they answer, e.g. `"ReadRequest"`, which is
+/// the name returned by `SupportedMessage::name()`.
+///
+/// [`MockServer`]: ./struct.MockServer.html
+pub struct MockServerBuilder {
+    endpoint_url: String,
+    responses: HashMap<String, Arc<ResponseFn>>,
+    aborts: HashMap<String, StatusCode>,
+}
+
+impl MockServerBuilder {
+    /// Creates a builder for a mock server that listens on the host and port of the endpoint url.
+    pub fn new<T>(endpoint_url: T) -> MockServerBuilder
+    where
+        T: Into<String>,
+    {
+        MockServerBuilder {
+            endpoint_url: endpoint_url.into(),
+            responses: HashMap::new(),
+            aborts: HashMap::new(),
+        }
+    }
+
+    /// Answers the named request with the response returned by the function, which is handed
+    /// the request.
+    pub fn respond_to<T, F>(mut self, request_name: T, response: F) -> Self
+    where
+        T: Into<String>,
+        F: Fn(&SupportedMessage) -> SupportedMessage + Send + Sync + 'static,
+    {
+        self.responses
+            .insert(request_name.into(), Arc::new(response));
+        self
+    }
+
+    /// Answers the named request with a service fault holding the status code.
+    pub fn fault<T>(self, request_name: T, status_code: StatusCode) -> Self
+    where
+        T: Into<String>,
+    {
+        self.respond_to(request_name, move |request| {
+            ServiceFault::new(request.request_header(), status_code).into()
+        })
+    }
+
+    /// Aborts the response to the named request with an abort chunk holding the status code,
+    /// instead of answering it. The secure channel stays open.
+    pub fn abort<T>(mut self, request_name: T, status_code: StatusCode) -> Self
+    where
+        T: Into<String>,
+    {
+        self.aborts.insert(request_name.into(), status_code);
+        self
+    }
+
+    /// Answers every Read with the supplied values.
+    pub fn read_response(self, values: Vec<DataValue>) -> Self {
+        self.respond_to("ReadRequest", move |request| {
+            ReadResponse {
+                response_header: ResponseHeader::new_good(request.request_header()),
+                results: Some(values.clone()),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+    }
+
+    /// Starts the mock server. Fails if the endpoint url is invalid or another server is already
+    /// listening on its host and port.
+    pub fn server(self) -> Result<MockServer, StatusCode> {
+        let (host, port) =
+            hostname_port_from_url(&self.endpoint_url, constants::DEFAULT_OPC_UA_SERVER_PORT)?;
+        let mut listener = LoopbackListener::bind(&host, port)?;
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|err| {
+                error!("Cannot create a runtime for the mock server, {}", err);
+                StatusCode::BadUnexpectedError
+            })?;
+
+        let state = Arc::new(MockServerState {
+            endpoint_url: self.endpoint_url.clone(),
+            responses: self.responses,
+            aborts: self.aborts,
+            requests: Mutex::new(Vec::new()),
+            last_id: AtomicU32::new(0),
+        });
+
+        let connection_state = state.clone();
+        runtime.spawn(async move {
+            while let Some(stream) = listener.accept().await {
+                let state = connection_state.clone();
+                tokio::spawn(async move {
+                    if let Err(status_code) = state.handle_connection(stream).await {
+                        error!("Mock server connection failed, {}", status_code);
+                    }
+                });
+            }
+        });
+
+        Ok(MockServer {
+            state,
+            runtime: Some(runtime),
+        })
+    }
+}
+
+/// A scriptable server that client applications can test against. It stops listening when it is
+/// dropped.
+pub struct MockServer {
+    state: Arc<MockServerState>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl MockServer {
+    /// Returns the endpoint url that clients connect to.
+    pub fn endpoint_url(&self) -> &str {
+        &self.state.endpoint_url
+    }
+
+    /// Returns the requests the server has received so far, in the order they arrived.
+    pub fn requests(&self) -> Vec<SupportedMessage> {
+        trace_lock!(self.state.requests).clone()
+    }
+}
+
+/// State shared by the connections of a mock server
+struct MockServerState {
+    endpoint_url: String,
+    responses: HashMap<String, Arc<ResponseFn>>,
+    aborts: HashMap<String, StatusCode>,
+    requests: Mutex<Vec<SupportedMessage>>,
+    /// Last id handed out to a secure channel or session
+    last_id: AtomicU32,
+}
+
+impl MockServerState {
+    fn next_id(&self) -> u32 {
+        self.last_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    async fn handle_connection(&self, stream: DuplexStream) -> Result<(), StatusCode> {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let decoding_options = DecodingOptions::default();
+        let mut framed_read = FramedRead::new(reader, TcpCodec::new(decoding_options.clone()));
+        let mut secure_channel = SecureChannel::new_no_certificate_store();
+        let mut message_writer = MessageWriter::new(BUFFER_SIZE, 0, 0);
+        let mut pending_chunks = Vec::new();
+
+        while let Some(message) = framed_read.next().await {
+            match message {
+                Ok(Message::Hello(_)) => {
+                    let mut acknowledge = AcknowledgeMessage {
+                        message_header: MessageHeader::new(MessageType::Acknowledge),
+                        protocol_version: 0,
+                        receive_buffer_size: BUFFER_SIZE as u32,
+                        send_buffer_size: BUFFER_SIZE as u32,
+                        max_message_size: decoding_options.max_message_size as u32,
+                        max_chunk_count: decoding_options.max_chunk_count as u32,
+                    };
+                    acknowledge.message_header.message_size = acknowledge.byte_len() as u32;
+                    message_writer.write_ack(&acknowledge)?;
+                }
+                Ok(Message::Chunk(chunk)) => {
+                    let chunk = secure_channel.verify_and_remove_security(&chunk.data)?;
+                    let chunk_info = chunk.chunk_info(&secure_channel)?;
+                    match chunk_info.message_header.is_final {
+                        MessageIsFinalType::Intermediate => {
+                            pending_chunks.push(chunk);
+                            continue;
+                        }
+                        MessageIsFinalType::FinalError => {
+                            pending_chunks.clear();
+                            continue;
+                        }
+                        MessageIsFinalType::Final => pending_chunks.push(chunk),
+                    }
+                    let chunks = std::mem::take(&mut pending_chunks);
+                    let request = Chunker::decode(&chunks, &secure_channel, None)?;
+                    trace_lock!(self.requests).push(request.clone());
+                    if let SupportedMessage::CloseSecureChannelRequest(_) = request {
+                        break;
+                    }
+                    let request_id = chunk_info.sequence_header.request_id;
+                    if let Some(status_code) = self.aborts.get(request.name()) {
+                        message_writer.write_abort(
+                            request_id,
+                            MessageChunkType::Message,
+                            *status_code,
+                            &secure_channel,
+                        )?;
+                    } else {
+                        let response = self.respond(&request, &mut secure_channel);
+                        message_writer.write(request_id, response, &secure_channel)?;
+                    }
+                }
+                Ok(message) => {
+                    error!("Mock server received an unexpected message {:?}", message);
+                    break;
+                }
+                Err(err) => {
+                    error!("Mock server could not read from the stream, {}", err);
+                    break;
+                }
+            }
+
+            let bytes = message_writer.bytes_to_write();
+            writer.write_all(&bytes).await.map_err(|err| {
+                error!("Mock server could not write to the stream, {}", err);
+                StatusCode::BadCommunicationError
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Produces the response to a request, either the scripted one or the default.
+    fn respond(
+        &self,
+        request: &SupportedMessage,
+        secure_channel: &mut SecureChannel,
+    ) -> SupportedMessage {
+        if let SupportedMessage::OpenSecureChannelRequest(_) = request {
+            if secure_channel.secure_channel_id() == 0 {
+                secure_channel.set_secure_channel_id(self.next_id());
+            }
+            secure_channel.set_token_id(secure_channel.token_id() + 1);
+        }
+        if let Some(response) = self.responses.get(request.name()) {
+            response(request)
+        } else {
+            self.default_response(request, secure_channel)
+        }
+    }
+
+    fn default_response(
+        &self,
+        request: &SupportedMessage,
+        secure_channel: &SecureChannel,
+    ) -> SupportedMessage {
+        match request {
+            SupportedMessage::OpenSecureChannelRequest(request) => OpenSecureChannelResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                server_protocol_version: 0,
+                security_token: ChannelSecurityToken {
+                    channel_id: secure_channel.secure_channel_id(),
+                    token_id: secure_channel.token_id(),
+                    created_at: DateTime::now(),
+                    revised_lifetime: request.requested_lifetime,
+                },
+                server_nonce: ByteString::null(),
+            }
+            .into(),
+            SupportedMessage::GetEndpointsRequest(request) => GetEndpointsResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                endpoints: Some(vec![self.endpoint()]),
+            }
+            .into(),
+            SupportedMessage::CreateSessionRequest(request) => CreateSessionResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                session_id: NodeId::new(1, self.next_id()),
+                authentication_token: NodeId::new(0, random::byte_string(32)),
+                revised_session_timeout: request.requested_session_timeout,
+                server_nonce: random::byte_string(32),
+                server_certificate: ByteString::null(),
+                server_endpoints: Some(vec![self.endpoint()]),
+                server_software_certificates: None,
+                server_signature: SignatureData::null(),
+                max_request_message_size: 0,
+            }
+            .into(),
+            SupportedMessage::ActivateSessionRequest(request) => ActivateSessionResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                server_nonce: random::byte_string(32),
+                results: None,
+                diagnostic_infos: None,
+            }
+            .into(),
+            SupportedMessage::CloseSessionRequest(request) => CloseSessionResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+            }
+            .into(),
+            request => {
+                ServiceFault::new(request.request_header(), StatusCode::BadServiceUnsupported)
+                    .into()
+            }
+        }
+    }
+
+    /// The endpoint the mock server advertises, accepting anonymous and user name identities
+    fn endpoint(&self) -> EndpointDescription {
+        let user_name = UserTokenPolicy {
+            policy_id: UAString::from("userpass"),
+            token_type: UserTokenType::UserName,
+            issued_token_type: UAString::null(),
+            issuer_endpoint_url: UAString::null(),
+            security_policy_uri: UAString::null(),
+        };
+        EndpointDescription::from((
+            self.endpoint_url.as_ref(),
+            SecurityPolicy::None.to_uri(),
+            MessageSecurityMode::None,
+            vec![UserTokenPolicy::anonymous(), user_name],
+        ))
+    }
+}
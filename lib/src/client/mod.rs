@@ -27,7 +27,8 @@
 //! Functionality is synchronous and housekeeping such as renewing the active session and sending publish requests is
 //! handled automatically.
 //!
-//! Data change and event notifications are via asynchronous callbacks.
+//! Data change and event notifications are via asynchronous callbacks. Alternatively the session
+//! can maintain a [`MonitoredItemCache`] of the latest value of every monitored item.
 //!
 //! # Example
 //!
@@ -84,7 +85,7 @@
 //!    if let Some(ref value) = data_value.value {
 //!        println!("Item \"{}\", Value = {:?}", node_id, value);
 //!    } else {
-//!        println!("Item \"{}\", Value not found, error: {}", node_id, data_value.status.as_ref().unwrap());
+//!        println!("Item \"{}\", Value not found, error: {}", node_id, data_value.status());
 //!    }
 //!}
 //! ```
@@ -93,12 +94,17 @@
 //! [`ClientConfig`]: ./config/struct.ClientConfig.html
 //! [`ClientBuilder`]: ./client_builder/struct.ClientBuilder.html
 //! [`Session`]: ./session/struct.Session.html
+//! [`MonitoredItemCache`]: ./monitored_item_cache/struct.MonitoredItemCache.html
 
 use crate::core::supported_message::SupportedMessage;
 use crate::types::{response_header::ResponseHeader, status_code::StatusCode};
 
+use self::error::{ServiceError, ServiceFaultInfo};
+
 mod comms;
 mod message_queue;
+mod monitored_item_cache;
+mod request_window;
 mod subscription;
 mod subscription_state;
 
@@ -106,36 +112,41 @@ mod subscription_state;
 mod builder;
 mod callbacks;
 mod client;
+mod conformance;
 mod config;
+mod error;
+mod failover;
+mod mock_server;
+mod pool;
 mod session;
 mod session_retry_policy;
 
 /// Process the service result, i.e. where the request "succeeded" but the response
 /// contains a failure status code.
-pub(crate) fn process_service_result(response_header: &ResponseHeader) -> Result<(), StatusCode> {
+pub(crate) fn process_service_result(response_header: &ResponseHeader) -> Result<(), ServiceError> {
     if response_header.service_result.is_bad() {
         info!(
             "Received a bad service result {} from the request",
             response_header.service_result
         );
-        Err(response_header.service_result)
+        Err(ServiceFaultInfo::new(response_header).into())
     } else {
         Ok(())
     }
 }
 
-pub(crate) fn process_unexpected_response(response: SupportedMessage) -> StatusCode {
+pub(crate) fn process_unexpected_response(response: SupportedMessage) -> ServiceError {
     match response {
         SupportedMessage::ServiceFault(service_fault) => {
             error!(
                 "Received a service fault of {} for the request",
                 service_fault.response_header.service_result
             );
-            service_fault.response_header.service_result
+            ServiceFaultInfo::new(&service_fault.response_header).into()
         }
         _ => {
             error!("Received an unexpected response to the request");
-            StatusCode::BadUnknownResponse
+            StatusCode::BadUnknownResponse.into()
         }
     }
 }
@@ -144,7 +155,8 @@ pub mod prelude {
     pub use crate::{
         core::prelude::*,
         crypto::*,
-        types::{service_types::*, status_code::StatusCode},
+        // The encoding's DecodingOptions, not the config::DecodingOptions section of a config
+        types::{service_types::*, status_code::StatusCode, DecodingOptions},
     };
 
     pub use crate::client::{
@@ -152,9 +164,21 @@ pub mod prelude {
         callbacks::*,
         client::*,
         config::*,
-        session::{services::*, session::*},
+        conformance::*,
+        error::*,
+        failover::*,
+        mock_server::*,
+        monitored_item_cache::MonitoredItemCache,
+        pool::*,
+        session::{
+            node_attributes::*, pending_response::*, services::*, session::*, shared_session::*,
+            write_result::*,
+        },
         subscription::MonitoredItem,
     };
+
+    #[cfg(feature = "history")]
+    pub use crate::client::session::history_read::*;
 }
 
 #[cfg(test)]
@@ -0,0 +1,133 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Provides an optional client side cache of the latest value received for each monitored item.
+//!
+//! Applications such as HMIs and gateways usually want to know the current value of everything
+//! they monitor without having to track data change callbacks themselves. When the cache is enabled
+//! on a session, every data change notification updates the cache, which can then be queried by
+//! node id, snapshotted in its entirety, or watched for changes through a `tokio::sync::watch`
+//! channel.
+
+use std::collections::HashMap;
+
+use tokio::sync::watch;
+
+use crate::types::{attribute::AttributeId, DataValue, NodeId};
+
+/// The key of a cached value is the node id and the attribute id of the monitored item.
+type CacheKey = (NodeId, u32);
+
+/// Holds the most recently received value of every monitored item on a session. The cache is
+/// obtained from [`Session::enable_monitored_item_cache`].
+///
+/// [`Session::enable_monitored_item_cache`]: ../session/struct.Session.html#method.enable_monitored_item_cache
+#[derive(Default)]
+pub struct MonitoredItemCache {
+    /// The most recent value of every monitored item that has received one
+    values: HashMap<CacheKey, DataValue>,
+    /// Senders for anybody watching a value. These are kept apart from the values so that
+    /// watching a node which has not received a value does not add it to the cache.
+    senders: HashMap<CacheKey, watch::Sender<DataValue>>,
+}
+
+impl MonitoredItemCache {
+    pub fn new() -> MonitoredItemCache {
+        MonitoredItemCache {
+            values: HashMap::new(),
+            senders: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of values held in the cache
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Tests if the cache holds no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the latest value of the `Value` attribute of the node, if it is monitored.
+    pub fn value(&self, node_id: &NodeId) -> Option<DataValue> {
+        self.attribute_value(node_id, AttributeId::Value)
+    }
+
+    /// Returns the latest value of the specified attribute of the node, if it is monitored.
+    pub fn attribute_value(
+        &self,
+        node_id: &NodeId,
+        attribute_id: AttributeId,
+    ) -> Option<DataValue> {
+        self.values
+            .get(&(node_id.clone(), attribute_id as u32))
+            .cloned()
+    }
+
+    /// Returns a copy of every value in the cache, keyed by node id and attribute id.
+    pub fn snapshot(&self) -> HashMap<(NodeId, u32), DataValue> {
+        self.values
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Returns a snapshot of the `Value` attributes of the supplied nodes. Nodes which are not
+    /// monitored are omitted from the result.
+    pub fn values(&self, node_ids: &[NodeId]) -> HashMap<NodeId, DataValue> {
+        node_ids
+            .iter()
+            .filter_map(|node_id| self.value(node_id).map(|v| (node_id.clone(), v)))
+            .collect()
+    }
+
+    /// Returns a receiver that is notified every time the `Value` attribute of the node changes.
+    /// The receiver initially holds the current cached value, or a null value if nothing has been
+    /// received yet. The channel closes if the monitored item is deleted.
+    pub fn watch(&mut self, node_id: &NodeId) -> watch::Receiver<DataValue> {
+        self.watch_attribute(node_id, AttributeId::Value)
+    }
+
+    /// Returns a receiver that is notified every time the specified attribute of the node changes.
+    pub fn watch_attribute(
+        &mut self,
+        node_id: &NodeId,
+        attribute_id: AttributeId,
+    ) -> watch::Receiver<DataValue> {
+        let key = (node_id.clone(), attribute_id as u32);
+        if let Some(sender) = self.senders.get(&key) {
+            sender.subscribe()
+        } else {
+            let value = self
+                .values
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(DataValue::null);
+            let (sender, receiver) = watch::channel(value);
+            self.senders.insert(key, sender);
+            receiver
+        }
+    }
+
+    /// Stores the new value for the node / attribute and notifies anybody watching it.
+    pub(crate) fn update(&mut self, node_id: &NodeId, attribute_id: u32, value: &DataValue) {
+        let key = (node_id.clone(), attribute_id);
+        if let Some(sender) = self.senders.get(&key) {
+            // An error just means nobody is listening any more
+            if sender.send(value.clone()).is_err() {
+                self.senders.remove(&key);
+            }
+        }
+        self.values.insert(key, value.clone());
+    }
+
+    /// Removes the value for the node / attribute. Dropping the sender closes the channel of
+    /// anybody watching it.
+    pub(crate) fn remove(&mut self, node_id: &NodeId, attribute_id: u32) {
+        let key = (node_id.clone(), attribute_id);
+        self.values.remove(&key);
+        self.senders.remove(&key);
+    }
+}
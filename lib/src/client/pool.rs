@@ -0,0 +1,368 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`ClientPool`], which holds many sessions on behalf of an application, such as a
+//! gateway that aggregates the data of many servers.
+//!
+//! Each session in the pool is identified by a [`SessionHandle`]. The pool runs every session,
+//! checks its health on request and reconnects sessions that have dropped, and routes the data
+//! changes of subscriptions to a callback that is told which session they came from.
+//!
+//! [`ClientPool`]: ./struct.ClientPool.html
+//! [`SessionHandle`]: ./struct.SessionHandle.html
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use tokio::sync::oneshot;
+
+use crate::core::prelude::*;
+use crate::sync::*;
+
+use crate::client::{
+    callbacks::DataChangeCallback,
+    client::{Client, IdentityToken},
+    session::{
+        services::{AttributeService, SubscriptionService},
+        session::{Session, SessionCommand},
+    },
+    subscription::MonitoredItem,
+};
+
+/// The timeout in milliseconds of the read that checks the health of a session
+const HEALTH_CHECK_TIMEOUT: u32 = 5000;
+
+/// Identifies a session in a [`ClientPool`]. Handles are never reused by the pool that issued
+/// them, so a handle to a removed session does not refer to another session later on.
+///
+/// [`ClientPool`]: ./struct.ClientPool.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SessionHandle(u32);
+
+impl fmt::Display for SessionHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The health of a session, as found by the last health check
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SessionHealth {
+    /// The session is connected and the server reports that it is running
+    Healthy,
+    /// The session is connected but the server did not answer, or reported a state other than
+    /// running. The status code says why.
+    Unhealthy(StatusCode),
+    /// The session is not connected and could not be reconnected
+    Disconnected,
+}
+
+impl SessionHealth {
+    /// Tests if the session is healthy
+    pub fn is_healthy(&self) -> bool {
+        *self == SessionHealth::Healthy
+    }
+}
+
+/// A session in the pool and what is needed to run it
+struct PooledSession {
+    session: Arc<RwLock<Session>>,
+    /// Sender that stops the run loop of the session, if it is running
+    run_tx: Option<oneshot::Sender<SessionCommand>>,
+    health: SessionHealth,
+    subscription_ids: Vec<u32>,
+}
+
+impl PooledSession {
+    fn is_running(&self) -> bool {
+        self.run_tx.as_ref().is_some_and(|tx| !tx.is_closed())
+    }
+
+    fn stop(&mut self) {
+        if let Some(tx) = self.run_tx.take() {
+            let _ = tx.send(SessionCommand::Stop);
+        }
+    }
+}
+
+/// Manages the sessions that a client holds to one or more servers. Sessions are added to the
+/// pool with an endpoint and an identity, and are afterwards used through the handle that the
+/// pool returns.
+///
+/// The pool runs each session on its own thread, in the way of `Session::run_async()`, so the
+/// subscriptions of every session receive their notifications. Sessions are disconnected when they
+/// are removed or the pool is dropped.
+///
+/// ```no_run
+/// use opcua::client::prelude::*;
+///
+/// let client = ClientBuilder::new()
+///     .application_name("Gateway")
+///     .application_uri("urn:Gateway")
+///     .pki_dir("./pki")
+///     .client()
+///     .unwrap();
+/// let mut pool = ClientPool::new(client);
+/// let plc1 = pool
+///     .add_session(("opc.tcp://plc1:4840/", "None", MessageSecurityMode::None, UserTokenPolicy::anonymous()), IdentityToken::Anonymous)
+///     .unwrap();
+/// let plc2 = pool
+///     .add_session(("opc.tcp://plc2:4840/", "None", MessageSecurityMode::None, UserTokenPolicy::anonymous()), IdentityToken::Anonymous)
+///     .unwrap();
+/// pool.create_subscription(plc1, 1000.0, |handle, items| {
+///     println!("{} items changed on session {}", items.len(), handle);
+/// }).unwrap();
+/// let value = pool.with_session(plc2, |session| {
+///     session.read(&[NodeId::new(2, "v1").into()], TimestampsToReturn::Both, 0.0)
+/// });
+/// ```
+pub struct ClientPool {
+    client: Client,
+    sessions: BTreeMap<SessionHandle, PooledSession>,
+    last_handle: u32,
+}
+
+impl Drop for ClientPool {
+    fn drop(&mut self) {
+        let handles = self.handles();
+        handles.into_iter().for_each(|handle| {
+            self.remove_session(handle);
+        });
+    }
+}
+
+impl ClientPool {
+    /// Creates a pool whose sessions are made by the client, and so share its configuration,
+    /// certificate store and retry policy.
+    pub fn new(client: Client) -> ClientPool {
+        ClientPool {
+            client,
+            sessions: BTreeMap::new(),
+            last_handle: 0,
+        }
+    }
+
+    /// Connects a session to the endpoint, activates it with the identity and starts running it.
+    /// Several sessions may be added for the same endpoint.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SessionHandle)` - the handle of the new session
+    /// * `Err(StatusCode)` - reason why the session could not be connected
+    ///
+    pub fn add_session<T>(
+        &mut self,
+        endpoint: T,
+        user_identity_token: IdentityToken,
+    ) -> Result<SessionHandle, StatusCode>
+    where
+        T: Into<EndpointDescription>,
+    {
+        let session = self
+            .client
+            .connect_to_endpoint(endpoint, user_identity_token)?;
+        let run_tx = Session::run_async(session.clone());
+        self.last_handle += 1;
+        let handle = SessionHandle(self.last_handle);
+        self.sessions.insert(
+            handle,
+            PooledSession {
+                session,
+                run_tx: Some(run_tx),
+                health: SessionHealth::Healthy,
+                subscription_ids: Vec::new(),
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Disconnects the session and removes it from the pool. Returns `false` if there is no
+    /// session with the handle.
+    pub fn remove_session(&mut self, handle: SessionHandle) -> bool {
+        if let Some(mut pooled) = self.sessions.remove(&handle) {
+            pooled.stop();
+            let session = trace_read_lock!(pooled.session);
+            session.disconnect();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the handles of the sessions in the pool, in the order they were added
+    pub fn handles(&self) -> Vec<SessionHandle> {
+        self.sessions.keys().cloned().collect()
+    }
+
+    /// Returns the number of sessions in the pool
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Tests if the pool has no sessions
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Returns the session with the handle, or `None` if there is none
+    pub fn session(&self, handle: SessionHandle) -> Option<Arc<RwLock<Session>>> {
+        self.sessions
+            .get(&handle)
+            .map(|pooled| pooled.session.clone())
+    }
+
+    /// Calls the function with the session that has the handle, holding a read lock on the
+    /// session for the duration of the call. Fails with `BadSessionIdInvalid` if there is no
+    /// session with the handle.
+    pub fn with_session<F, R>(&self, handle: SessionHandle, f: F) -> Result<R, StatusCode>
+    where
+        F: FnOnce(&Session) -> R,
+    {
+        let pooled = self
+            .sessions
+            .get(&handle)
+            .ok_or(StatusCode::BadSessionIdInvalid)?;
+        let session = trace_read_lock!(pooled.session);
+        Ok(f(&session))
+    }
+
+    /// Returns the health of the session found by the last health check, or `None` if there is
+    /// no session with the handle. A session is healthy until it is first checked.
+    pub fn health(&self, handle: SessionHandle) -> Option<SessionHealth> {
+        self.sessions.get(&handle).map(|pooled| pooled.health)
+    }
+
+    /// Checks the health of every session in the pool and returns it.
+    ///
+    /// A connected session is healthy if the server answers a read of its `ServerStatus.State`
+    /// with `Running`. A session that is not connected is reconnected and reactivated, and is
+    /// started again if its run loop has ended, e.g. because it gave up retrying.
+    pub fn check_health(&mut self) -> Vec<(SessionHandle, SessionHealth)> {
+        self.sessions
+            .iter_mut()
+            .map(|(handle, pooled)| {
+                pooled.health = Self::session_health(pooled);
+                (*handle, pooled.health)
+            })
+            .collect()
+    }
+
+    fn session_health(pooled: &mut PooledSession) -> SessionHealth {
+        let connected = {
+            let session = trace_read_lock!(pooled.session);
+            session.is_connected()
+        };
+        if !connected {
+            let reconnected = {
+                let mut session = trace_write_lock!(pooled.session);
+                session.reconnect_and_activate()
+            };
+            if let Err(status_code) = reconnected {
+                debug!("Session did not reconnect, error = {}", status_code);
+                return SessionHealth::Disconnected;
+            }
+        }
+        if !pooled.is_running() {
+            pooled.stop();
+            pooled.run_tx = Some(Session::run_async(pooled.session.clone()));
+        }
+
+        let session = trace_read_lock!(pooled.session);
+        let state = session.with_request_timeout(HEALTH_CHECK_TIMEOUT, |session| {
+            session.read(
+                &[NodeId::from(&VariableId::Server_ServerStatus_State).into()],
+                TimestampsToReturn::Neither,
+                0.0,
+            )
+        });
+        match state {
+            Ok(values) => match values.first() {
+                Some(DataValue {
+                    value: Some(Variant::Int32(state)),
+                    ..
+                }) if *state == ServerState::Running as i32 => SessionHealth::Healthy,
+                Some(DataValue {
+                    status: Some(status_code),
+                    ..
+                }) if status_code.is_bad() => SessionHealth::Unhealthy(*status_code),
+                _ => SessionHealth::Unhealthy(StatusCode::BadServerHalted),
+            },
+            Err(err) => SessionHealth::Unhealthy(err.status_code()),
+        }
+    }
+
+    /// Creates a subscription on the session with the handle. The callback receives the data
+    /// changes of the subscription together with the handle of the session, so one callback can
+    /// serve the subscriptions of many sessions. Monitored items are added to the subscription
+    /// through the session in the usual way.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u32)` - the id of the subscription
+    /// * `Err(StatusCode)` - `BadSessionIdInvalid` if there is no session with the handle, or
+    ///   the reason why the server did not create the subscription
+    ///
+    pub fn create_subscription<CB>(
+        &mut self,
+        handle: SessionHandle,
+        publishing_interval: f64,
+        callback: CB,
+    ) -> Result<u32, StatusCode>
+    where
+        CB: Fn(SessionHandle, &[&MonitoredItem]) + Send + Sync + 'static,
+    {
+        let pooled = self
+            .sessions
+            .get_mut(&handle)
+            .ok_or(StatusCode::BadSessionIdInvalid)?;
+        let subscription_id = {
+            let session = trace_read_lock!(pooled.session);
+            session.create_subscription(
+                publishing_interval,
+                30,
+                10,
+                0,
+                0,
+                true,
+                DataChangeCallback::new(move |items| callback(handle, items)),
+            )?
+        };
+        pooled.subscription_ids.push(subscription_id);
+        Ok(subscription_id)
+    }
+
+    /// Deletes a subscription that was created through the pool. Fails with
+    /// `BadSubscriptionIdInvalid` if the session has no such subscription.
+    pub fn delete_subscription(
+        &mut self,
+        handle: SessionHandle,
+        subscription_id: u32,
+    ) -> Result<(), StatusCode> {
+        let pooled = self
+            .sessions
+            .get_mut(&handle)
+            .ok_or(StatusCode::BadSessionIdInvalid)?;
+        let idx = pooled
+            .subscription_ids
+            .iter()
+            .position(|id| *id == subscription_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        {
+            let session = trace_read_lock!(pooled.session);
+            let status_code = session.delete_subscription(subscription_id)?;
+            if status_code.is_bad() {
+                return Err(status_code);
+            }
+        }
+        pooled.subscription_ids.remove(idx);
+        Ok(())
+    }
+
+    /// Returns the ids of the subscriptions created through the pool on the session with the
+    /// handle, or `None` if there is no session with the handle.
+    pub fn subscriptions(&self, handle: SessionHandle) -> Option<&[u32]> {
+        self.sessions
+            .get(&handle)
+            .map(|pooled| pooled.subscription_ids.as_slice())
+    }
+}
@@ -0,0 +1,65 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Limits how many synchronous requests a session has outstanding at once.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Condvar;
+
+use crate::sync::*;
+use crate::types::status_code::StatusCode;
+
+/// A window of requests that may be outstanding at the same time. A thread takes a slot before
+/// it sends a request and gives it back when the response arrives, so once the window is full
+/// other threads wait for a slot to free up.
+pub(crate) struct RequestWindow {
+    /// The size of the window, 0 meaning no limit
+    size: usize,
+    /// The number of slots that are taken
+    taken: Mutex<usize>,
+    freed: Condvar,
+}
+
+/// A slot in a request window, which is given back when it is dropped
+pub(crate) struct RequestSlot {
+    window: Arc<RequestWindow>,
+}
+
+impl Drop for RequestSlot {
+    fn drop(&mut self) {
+        let mut taken = trace_lock!(self.window.taken);
+        *taken -= 1;
+        self.window.freed.notify_one();
+    }
+}
+
+impl RequestWindow {
+    pub fn new(size: usize) -> RequestWindow {
+        RequestWindow {
+            size,
+            taken: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Takes a slot, waiting up to the timeout in milliseconds for one to free up. Fails with
+    /// `BadTimeout` if none did.
+    pub fn acquire(self: Arc<Self>, timeout: u32) -> Result<RequestSlot, StatusCode> {
+        let deadline = Instant::now() + Duration::from_millis(timeout as u64);
+        let mut taken = trace_lock!(self.taken);
+        while self.size > 0 && *taken >= self.size {
+            if self.freed.wait_until(&mut taken, deadline).timed_out() && *taken >= self.size {
+                info!("Timeout waiting for a slot in the request window");
+                return Err(StatusCode::BadTimeout);
+            }
+        }
+        *taken += 1;
+        drop(taken);
+        Ok(RequestSlot { window: self })
+    }
+}
@@ -0,0 +1,176 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains [`HistoryValues`], the values of a node as read by `Session::history_read_raw()`,
+//! `Session::history_read_processed()` and `Session::history_read_at_time()`.
+//!
+//! [`HistoryValues`]: ./struct.HistoryValues.html
+
+use std::collections::VecDeque;
+
+use crate::client::{
+    error::ServiceError,
+    session::{
+        services::{AttributeService, HistoryReadAction},
+        session::Session,
+        session_debug, session_error,
+    },
+};
+use crate::types::{status_code::StatusCode, *};
+
+/// The options of a history read
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryReadOptions {
+    /// The timestamps that the values have
+    pub timestamps_to_return: TimestampsToReturn,
+    /// The most values that the server returns in each response of a raw read, or 0 to let the
+    /// server decide. The values are still read until the end of the time range.
+    pub num_values_per_node: u32,
+    /// Whether a raw read also returns the values at or around the start and end of the time
+    /// range
+    pub return_bounds: bool,
+    /// Whether a read at time uses simple bounds, i.e. the values before and after a requested
+    /// time, to interpolate a value that was not recorded at that time
+    pub use_simple_bounds: bool,
+}
+
+impl Default for HistoryReadOptions {
+    fn default() -> Self {
+        HistoryReadOptions {
+            timestamps_to_return: TimestampsToReturn::Both,
+            num_values_per_node: 0,
+            return_bounds: false,
+            use_simple_bounds: true,
+        }
+    }
+}
+
+/// An iterator over the historical values of a node. It reads the values from the server as it
+/// goes, following the continuation point of each response until the server has returned all of
+/// them. Each item is a value, or the reason that the values could not be read, after which the
+/// iterator ends.
+///
+/// A continuation point that is left when the iterator is dropped before its end is released, so
+/// the server does not have to hold on to it.
+pub struct HistoryValues<'a> {
+    session: &'a Session,
+    details: HistoryReadAction,
+    timestamps_to_return: TimestampsToReturn,
+    node_id: NodeId,
+    continuation_point: ByteString,
+    values: VecDeque<DataValue>,
+    finished: bool,
+}
+
+impl<'a> HistoryValues<'a> {
+    pub(crate) fn new(
+        session: &'a Session,
+        node_id: NodeId,
+        details: HistoryReadAction,
+        timestamps_to_return: TimestampsToReturn,
+    ) -> HistoryValues<'a> {
+        HistoryValues {
+            session,
+            details,
+            timestamps_to_return,
+            node_id,
+            continuation_point: ByteString::null(),
+            values: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Reads the next values, returning the reason if they could not be read
+    fn read_next(
+        &mut self,
+        release_continuation_point: bool,
+    ) -> Result<Vec<DataValue>, ServiceError> {
+        let node_to_read = HistoryReadValueId {
+            node_id: self.node_id.clone(),
+            index_range: UAString::null(),
+            data_encoding: QualifiedName::null(),
+            continuation_point: std::mem::take(&mut self.continuation_point),
+        };
+        let mut results = self.session.history_read(
+            self.details.clone(),
+            self.timestamps_to_return,
+            release_continuation_point,
+            std::slice::from_ref(&node_to_read),
+        )?;
+        if results.len() != 1 {
+            session_error!(
+                self.session,
+                "history_read() returned {} results for one node",
+                results.len()
+            );
+            return Err(StatusCode::BadUnexpectedError.into());
+        }
+        let result = results.remove(0);
+        if result.status_code.is_bad() {
+            return Err(result.status_code.into());
+        }
+        self.continuation_point = result.continuation_point;
+        if release_continuation_point || result.history_data.is_null() {
+            return Ok(Vec::new());
+        }
+        let history_data = result
+            .history_data
+            .decode_inner::<HistoryData>(&self.session.decoding_options())
+            .inspect_err(|status| {
+                session_error!(
+                    self.session,
+                    "Cannot decode the history data of {}, {}",
+                    self.node_id,
+                    status
+                );
+            })?;
+        Ok(history_data.data_values.unwrap_or_default())
+    }
+}
+
+impl<'a> Iterator for HistoryValues<'a> {
+    type Item = Result<DataValue, ServiceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.values.pop_front() {
+                return Some(Ok(value));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.read_next(false) {
+                Ok(values) => {
+                    self.values.extend(values);
+                    self.finished = self.continuation_point.is_null_or_empty();
+                }
+                Err(err) => {
+                    self.finished = true;
+                    self.continuation_point = ByteString::null();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Drop for HistoryValues<'a> {
+    fn drop(&mut self) {
+        if !self.continuation_point.is_null_or_empty() {
+            session_debug!(
+                self.session,
+                "Releasing the history continuation point of {}",
+                self.node_id
+            );
+            if let Err(err) = self.read_next(true) {
+                session_debug!(
+                    self.session,
+                    "Cannot release the history continuation point of {}, {}",
+                    self.node_id,
+                    err
+                );
+            }
+        }
+    }
+}
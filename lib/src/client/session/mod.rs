@@ -1,6 +1,12 @@
+#[cfg(feature = "history")]
+pub mod history_read;
+pub mod node_attributes;
+pub mod pending_response;
 pub mod services;
 pub mod session;
 pub mod session_state;
+pub mod shared_session;
+pub mod write_result;
 
 macro_rules! session_warn {
     ($session: expr, $($arg:tt)*) =>  {
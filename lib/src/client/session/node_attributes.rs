@@ -0,0 +1,248 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains [`NodeAttributeValues`], the attributes of a node as read by
+//! `Session::read_node_attributes()`.
+//!
+//! [`NodeAttributeValues`]: ./struct.NodeAttributeValues.html
+
+use std::convert::TryFrom;
+
+use crate::types::{status_code::StatusCode, *};
+
+/// The attributes that `Session::read_node_attributes()` reads, in the order of their ids
+pub(crate) const NODE_ATTRIBUTE_IDS: [AttributeId; 27] = [
+    AttributeId::NodeId,
+    AttributeId::NodeClass,
+    AttributeId::BrowseName,
+    AttributeId::DisplayName,
+    AttributeId::Description,
+    AttributeId::WriteMask,
+    AttributeId::UserWriteMask,
+    AttributeId::IsAbstract,
+    AttributeId::Symmetric,
+    AttributeId::InverseName,
+    AttributeId::ContainsNoLoops,
+    AttributeId::EventNotifier,
+    AttributeId::Value,
+    AttributeId::DataType,
+    AttributeId::ValueRank,
+    AttributeId::ArrayDimensions,
+    AttributeId::AccessLevel,
+    AttributeId::UserAccessLevel,
+    AttributeId::MinimumSamplingInterval,
+    AttributeId::Historizing,
+    AttributeId::Executable,
+    AttributeId::UserExecutable,
+    AttributeId::DataTypeDefinition,
+    AttributeId::RolePermissions,
+    AttributeId::UserRolePermissions,
+    AttributeId::AccessRestrictions,
+    AttributeId::AccessLevelEx,
+];
+
+/// The attributes of a node. The attributes that every node has are always set. The others are
+/// `None` if the class of the node does not have them, the node does not set them or the server
+/// could not read them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeAttributeValues {
+    pub node_id: NodeId,
+    pub node_class: NodeClass,
+    pub browse_name: QualifiedName,
+    pub display_name: LocalizedText,
+    pub description: Option<LocalizedText>,
+    pub write_mask: Option<u32>,
+    pub user_write_mask: Option<u32>,
+    pub is_abstract: Option<bool>,
+    pub symmetric: Option<bool>,
+    pub inverse_name: Option<LocalizedText>,
+    pub contains_no_loops: Option<bool>,
+    pub event_notifier: Option<u8>,
+    /// The value of a variable or variable type, with its status and timestamps
+    pub value: Option<DataValue>,
+    pub data_type: Option<NodeId>,
+    pub value_rank: Option<i32>,
+    pub array_dimensions: Option<Vec<u32>>,
+    pub access_level: Option<u8>,
+    pub user_access_level: Option<u8>,
+    pub minimum_sampling_interval: Option<f64>,
+    pub historizing: Option<bool>,
+    pub executable: Option<bool>,
+    pub user_executable: Option<bool>,
+    /// The definition of a data type, a `StructureDefinition` or an `EnumDefinition`
+    pub data_type_definition: Option<ExtensionObject>,
+    pub role_permissions: Option<Vec<RolePermissionType>>,
+    pub user_role_permissions: Option<Vec<RolePermissionType>>,
+    pub access_restrictions: Option<u16>,
+    pub access_level_ex: Option<u32>,
+}
+
+impl NodeAttributeValues {
+    /// Makes the attributes from the results of reading `NODE_ATTRIBUTE_IDS`. Fails with the
+    /// status of the node id if the node could not be read, e.g. `BadNodeIdUnknown`, or with
+    /// `BadTypeMismatch` if an attribute that every node has is missing or of the wrong type.
+    pub(crate) fn from_data_values(
+        data_values: Vec<DataValue>,
+        decoding_options: &DecodingOptions,
+    ) -> Result<NodeAttributeValues, StatusCode> {
+        if data_values.len() != NODE_ATTRIBUTE_IDS.len() {
+            error!(
+                "Expected {} attributes and got {}",
+                NODE_ATTRIBUTE_IDS.len(),
+                data_values.len()
+            );
+            return Err(StatusCode::BadUnexpectedError);
+        }
+        if let Some(status) = data_values[0].status {
+            if status.is_bad() {
+                return Err(status);
+            }
+        }
+
+        // The value is kept with its status and timestamps, the others are taken from a good
+        // result
+        let mut data_values = data_values;
+        let value = std::mem::take(&mut data_values[AttributeId::Value as usize - 1]);
+        let value = match value.status {
+            Some(status) if status == StatusCode::BadAttributeIdInvalid => None,
+            _ => Some(value),
+        };
+        let mut values = data_values
+            .into_iter()
+            .map(Self::good_value)
+            .collect::<Vec<_>>();
+        let mut attribute = |attribute_id: AttributeId| values[attribute_id as usize - 1].take();
+
+        let node_id = match attribute(AttributeId::NodeId) {
+            Some(Variant::NodeId(v)) => *v,
+            _ => return Err(StatusCode::BadTypeMismatch),
+        };
+        let node_class = match attribute(AttributeId::NodeClass) {
+            Some(Variant::Int32(v)) => Self::node_class(v).ok_or(StatusCode::BadTypeMismatch)?,
+            _ => return Err(StatusCode::BadTypeMismatch),
+        };
+        let browse_name = match attribute(AttributeId::BrowseName) {
+            Some(Variant::QualifiedName(v)) => *v,
+            _ => return Err(StatusCode::BadTypeMismatch),
+        };
+        let display_name = match attribute(AttributeId::DisplayName) {
+            Some(Variant::LocalizedText(v)) => *v,
+            _ => return Err(StatusCode::BadTypeMismatch),
+        };
+        Ok(NodeAttributeValues {
+            node_id,
+            node_class,
+            browse_name,
+            display_name,
+            description: attribute(AttributeId::Description).and_then(Self::localized_text),
+            write_mask: attribute(AttributeId::WriteMask).and_then(Self::u32),
+            user_write_mask: attribute(AttributeId::UserWriteMask).and_then(Self::u32),
+            is_abstract: attribute(AttributeId::IsAbstract).and_then(Self::bool),
+            symmetric: attribute(AttributeId::Symmetric).and_then(Self::bool),
+            inverse_name: attribute(AttributeId::InverseName).and_then(Self::localized_text),
+            contains_no_loops: attribute(AttributeId::ContainsNoLoops).and_then(Self::bool),
+            event_notifier: attribute(AttributeId::EventNotifier).and_then(Self::u8),
+            value,
+            data_type: match attribute(AttributeId::DataType) {
+                Some(Variant::NodeId(v)) => Some(*v),
+                _ => None,
+            },
+            value_rank: match attribute(AttributeId::ValueRank) {
+                Some(Variant::Int32(v)) => Some(v),
+                _ => None,
+            },
+            array_dimensions: attribute(AttributeId::ArrayDimensions)
+                .and_then(|v| <Vec<u32>>::try_from(&v).ok()),
+            access_level: attribute(AttributeId::AccessLevel).and_then(Self::u8),
+            user_access_level: attribute(AttributeId::UserAccessLevel).and_then(Self::u8),
+            minimum_sampling_interval: match attribute(AttributeId::MinimumSamplingInterval) {
+                Some(Variant::Double(v)) => Some(v),
+                _ => None,
+            },
+            historizing: attribute(AttributeId::Historizing).and_then(Self::bool),
+            executable: attribute(AttributeId::Executable).and_then(Self::bool),
+            user_executable: attribute(AttributeId::UserExecutable).and_then(Self::bool),
+            data_type_definition: match attribute(AttributeId::DataTypeDefinition) {
+                Some(Variant::ExtensionObject(v)) => Some(*v),
+                _ => None,
+            },
+            role_permissions: attribute(AttributeId::RolePermissions)
+                .and_then(|v| Self::role_permissions(v, decoding_options)),
+            user_role_permissions: attribute(AttributeId::UserRolePermissions)
+                .and_then(|v| Self::role_permissions(v, decoding_options)),
+            access_restrictions: match attribute(AttributeId::AccessRestrictions) {
+                Some(Variant::UInt16(v)) => Some(v),
+                _ => None,
+            },
+            access_level_ex: attribute(AttributeId::AccessLevelEx).and_then(Self::u32),
+        })
+    }
+
+    /// Returns the value of a result that is not bad
+    fn good_value(data_value: DataValue) -> Option<Variant> {
+        match data_value.status {
+            Some(status) if status.is_bad() => None,
+            _ => data_value.value,
+        }
+    }
+
+    fn node_class(value: i32) -> Option<NodeClass> {
+        match value {
+            1 => Some(NodeClass::Object),
+            2 => Some(NodeClass::Variable),
+            4 => Some(NodeClass::Method),
+            8 => Some(NodeClass::ObjectType),
+            16 => Some(NodeClass::VariableType),
+            32 => Some(NodeClass::ReferenceType),
+            64 => Some(NodeClass::DataType),
+            128 => Some(NodeClass::View),
+            _ => None,
+        }
+    }
+
+    fn localized_text(value: Variant) -> Option<LocalizedText> {
+        match value {
+            Variant::LocalizedText(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn bool(value: Variant) -> Option<bool> {
+        match value {
+            Variant::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn u8(value: Variant) -> Option<u8> {
+        match value {
+            Variant::Byte(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn u32(value: Variant) -> Option<u32> {
+        match value {
+            Variant::UInt32(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn role_permissions(
+        value: Variant,
+        decoding_options: &DecodingOptions,
+    ) -> Option<Vec<RolePermissionType>> {
+        match value {
+            Variant::Array(array) => array
+                .values
+                .iter()
+                .map(|v| match v {
+                    Variant::ExtensionObject(v) => v.decode_inner(decoding_options).ok(),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`PendingResponse`] of a request that has been sent without waiting for its
+//! response.
+//!
+//! [`PendingResponse`]: ./struct.PendingResponse.html
+
+use std::{
+    sync::{mpsc::Receiver, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::core::supported_message::SupportedMessage;
+use crate::sync::*;
+use crate::types::status_code::StatusCode;
+
+use crate::client::{request_window::RequestSlot, session::session_state::SessionState};
+
+/// The response to a request that is in flight. A session can have many requests in flight on
+/// its secure channel at once, up to its request window, and their responses may arrive in any
+/// order. Each is handed to the `PendingResponse` of the request it answers.
+///
+/// A pending response holds its slot in the request window until it is waited for or dropped.
+/// Dropping it without waiting abandons the response.
+pub struct PendingResponse {
+    request_handle: u32,
+    /// When the request times out, from its timeout hint or the request timeout of the session
+    deadline: Instant,
+    receiver: Receiver<SupportedMessage>,
+    session_state: Arc<RwLock<SessionState>>,
+    _request_slot: RequestSlot,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+impl PendingResponse {
+    pub(crate) fn new(
+        request_handle: u32,
+        request_timeout: u32,
+        receiver: Receiver<SupportedMessage>,
+        session_state: Arc<RwLock<SessionState>>,
+        request_slot: RequestSlot,
+        #[cfg(feature = "tracing")] span: tracing::Span,
+    ) -> PendingResponse {
+        let now = Instant::now();
+        PendingResponse {
+            request_handle,
+            deadline: now + Duration::from_millis(request_timeout as u64),
+            receiver,
+            session_state,
+            _request_slot: request_slot,
+            #[cfg(feature = "tracing")]
+            span,
+            #[cfg(feature = "tracing")]
+            started: now,
+        }
+    }
+
+    /// Returns the request handle of the request, e.g. to cancel it
+    pub fn request_handle(&self) -> u32 {
+        self.request_handle
+    }
+
+    /// Waits for the response to the request. Fails with `BadTimeout` if the response has not
+    /// arrived by the time the request times out.
+    pub fn wait(self) -> Result<SupportedMessage, StatusCode> {
+        let timeout = self.deadline.saturating_duration_since(Instant::now());
+        let response = self.receiver.recv_timeout(timeout).map_err(|_| {
+            info!("Timeout waiting for response from server");
+            let session_state = trace_read_lock!(self.session_state);
+            session_state.request_has_timed_out(self.request_handle);
+            StatusCode::BadTimeout
+        });
+
+        {
+            let mut session_state = trace_write_lock!(self.session_state);
+            session_state.set_last_response(&response);
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            let status = match response {
+                Ok(ref response) if response.is_response() => {
+                    response.response_header().service_result
+                }
+                Ok(_) => StatusCode::Good,
+                Err(status) => status,
+            };
+            self.span.record("status", tracing::field::display(status));
+            self.span
+                .record("duration_us", self.started.elapsed().as_micros() as u64);
+        }
+
+        response
+    }
+}
@@ -5,7 +5,7 @@
 use std::{convert::TryFrom, sync::mpsc::SyncSender};
 
 use crate::{
-    client::callbacks::OnSubscriptionNotification,
+    client::{callbacks::OnSubscriptionNotification, error::ServiceError},
     core::supported_message::SupportedMessage,
     types::{
         node_ids::{MethodId, ObjectId},
@@ -14,7 +14,12 @@ use crate::{
     },
 };
 
+/// The results of the links added and removed by Session::set_triggering()
+pub type SetTriggeringResults = (Option<Vec<StatusCode>>, Option<Vec<StatusCode>>);
+
 /// Enumeration used with Session::history_read()
+#[cfg(feature = "history")]
+#[derive(Clone)]
 pub enum HistoryReadAction {
     ReadEventDetails(ReadEventDetails),
     ReadRawModifiedDetails(ReadRawModifiedDetails),
@@ -22,6 +27,7 @@ pub enum HistoryReadAction {
     ReadAtTimeDetails(ReadAtTimeDetails),
 }
 
+#[cfg(feature = "history")]
 impl From<HistoryReadAction> for ExtensionObject {
     fn from(action: HistoryReadAction) -> Self {
         match action {
@@ -42,6 +48,7 @@ impl From<HistoryReadAction> for ExtensionObject {
 }
 
 /// Enumeration used with Session::history_update()
+#[cfg(feature = "history")]
 pub enum HistoryUpdateAction {
     UpdateDataDetails(UpdateDataDetails),
     UpdateStructureDataDetails(UpdateStructureDataDetails),
@@ -51,6 +58,7 @@ pub enum HistoryUpdateAction {
     DeleteEventDetails(DeleteEventDetails),
 }
 
+#[cfg(feature = "history")]
 impl From<&HistoryUpdateAction> for ExtensionObject {
     fn from(action: &HistoryUpdateAction) -> Self {
         match action {
@@ -108,12 +116,12 @@ pub trait DiscoveryService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<ApplicationDescription>)` - A list of [`ApplicationDescription`] that meet criteria specified in the request.
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`FindServersRequest`]: ./struct.FindServersRequest.html
     /// [`ApplicationDescription`]: ./struct.ApplicationDescription.html
     ///
-    fn find_servers<T>(&self, endpoint_url: T) -> Result<Vec<ApplicationDescription>, StatusCode>
+    fn find_servers<T>(&self, endpoint_url: T) -> Result<Vec<ApplicationDescription>, ServiceError>
     where
         T: Into<UAString>;
 
@@ -124,11 +132,11 @@ pub trait DiscoveryService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<EndpointDescription>)` - A list of endpoints supported by the server
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`GetEndpointsRequest`]: ./struct.GetEndpointsRequest.html
     ///
-    fn get_endpoints(&self) -> Result<Vec<EndpointDescription>, StatusCode>;
+    fn get_endpoints(&self) -> Result<Vec<EndpointDescription>, ServiceError>;
 
     /// This function is used by servers that wish to register themselves with a discovery server.
     /// i.e. one server is the client to another server. The server sends a [`RegisterServerRequest`]
@@ -144,11 +152,11 @@ pub trait DiscoveryService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Success
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`RegisterServerRequest`]: ./struct.RegisterServerRequest.html
     ///
-    fn register_server(&self, server: RegisteredServer) -> Result<(), StatusCode>;
+    fn register_server(&self, server: RegisteredServer) -> Result<(), ServiceError>;
 }
 
 /// SecureChannel Service set
@@ -160,11 +168,11 @@ pub trait SecureChannelService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Success
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`OpenSecureChannelRequest`]: ./struct.OpenSecureChannelRequest.html
     ///
-    fn open_secure_channel(&self) -> Result<(), StatusCode>;
+    fn open_secure_channel(&self) -> Result<(), ServiceError>;
 
     /// Sends a [`CloseSecureChannelRequest`] to the server which will cause the server to drop
     /// the connection.
@@ -174,11 +182,11 @@ pub trait SecureChannelService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Success
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`CloseSecureChannelRequest`]: ./struct.CloseSecureChannelRequest.html
     ///
-    fn close_secure_channel(&self) -> Result<(), StatusCode>;
+    fn close_secure_channel(&self) -> Result<(), ServiceError>;
 }
 
 /// Session Service set
@@ -192,11 +200,11 @@ pub trait SessionService: Service {
     /// # Returns
     ///
     /// * `Ok(NodeId)` - Success, session id
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`CreateSessionRequest`]: ./struct.CreateSessionRequest.html
     ///
-    fn create_session(&self) -> Result<NodeId, StatusCode>;
+    fn create_session(&self) -> Result<NodeId, ServiceError>;
 
     /// Sends an [`ActivateSessionRequest`] to the server to activate this session
     ///
@@ -205,11 +213,11 @@ pub trait SessionService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Success
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`ActivateSessionRequest`]: ./struct.ActivateSessionRequest.html
     ///
-    fn activate_session(&self) -> Result<(), StatusCode>;
+    fn activate_session(&self) -> Result<(), ServiceError>;
 
     /// Cancels an outstanding service request by sending a [`CancelRequest`] to the server.
     ///
@@ -222,15 +230,16 @@ pub trait SessionService: Service {
     /// # Returns
     ///
     /// * `Ok(u32)` - Success, number of cancelled requests
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`CancelRequest`]: ./struct.CancelRequest.html
     ///
-    fn cancel(&self, request_handle: IntegerId) -> Result<u32, StatusCode>;
+    fn cancel(&self, request_handle: IntegerId) -> Result<u32, ServiceError>;
 }
 
 /// NodeManagement Service set
 
+#[cfg(feature = "node-management")]
 pub trait NodeManagementService: Service {
     /// Add nodes by sending a [`AddNodesRequest`] to the server.
     ///
@@ -243,13 +252,13 @@ pub trait NodeManagementService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<AddNodesResult>)` - A list of [`AddNodesResult`] corresponding to each add node operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`AddNodesRequest`]: ./struct.AddNodesRequest.html
     /// [`AddNodesItem`]: ./struct.AddNodesItem.html
     /// [`AddNodesResult`]: ./struct.AddNodesResult.html
     ///
-    fn add_nodes(&self, nodes_to_add: &[AddNodesItem]) -> Result<Vec<AddNodesResult>, StatusCode>;
+    fn add_nodes(&self, nodes_to_add: &[AddNodesItem]) -> Result<Vec<AddNodesResult>, ServiceError>;
 
     /// Add references by sending a [`AddReferencesRequest`] to the server.
     ///
@@ -262,7 +271,7 @@ pub trait NodeManagementService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - A list of `StatusCode` corresponding to each add reference operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`AddReferencesRequest`]: ./struct.AddReferencesRequest.html
     /// [`AddReferencesItem`]: ./struct.AddReferencesItem.html
@@ -270,7 +279,7 @@ pub trait NodeManagementService: Service {
     fn add_references(
         &self,
         references_to_add: &[AddReferencesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 
     /// Delete nodes by sending a [`DeleteNodesRequest`] to the server.
     ///
@@ -283,7 +292,7 @@ pub trait NodeManagementService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - A list of `StatusCode` corresponding to each delete node operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`DeleteNodesRequest`]: ./struct.DeleteNodesRequest.html
     /// [`DeleteNodesItem`]: ./struct.DeleteNodesItem.html
@@ -291,7 +300,7 @@ pub trait NodeManagementService: Service {
     fn delete_nodes(
         &self,
         nodes_to_delete: &[DeleteNodesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 
     /// Delete references by sending a [`DeleteReferencesRequest`] to the server.
     ///
@@ -304,7 +313,7 @@ pub trait NodeManagementService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - A list of `StatusCode` corresponding to each delete node operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`DeleteReferencesRequest`]: ./struct.DeleteReferencesRequest.html
     /// [`DeleteReferencesItem`]: ./struct.DeleteReferencesItem.html
@@ -312,7 +321,7 @@ pub trait NodeManagementService: Service {
     fn delete_references(
         &self,
         references_to_delete: &[DeleteReferencesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 }
 
 /// View Service set
@@ -329,7 +338,7 @@ pub trait ViewService: Service {
     ///
     /// * `Ok(Option<Vec<BrowseResult>)` - A list [`BrowseResult`] corresponding to each node to browse. A browse result
     ///                                    may contain a continuation point, for use with `browse_next()`.
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`BrowseRequest`]: ./struct.BrowseRequest.html
     /// [`BrowseDescription`]: ./struct.BrowseDescription.html
@@ -338,7 +347,7 @@ pub trait ViewService: Service {
     fn browse(
         &self,
         nodes_to_browse: &[BrowseDescription],
-    ) -> Result<Option<Vec<BrowseResult>>, StatusCode>;
+    ) -> Result<Option<Vec<BrowseResult>>, ServiceError>;
 
     /// Continue to discover references to nodes by sending continuation points in a [`BrowseNextRequest`]
     /// to the server. This function may have to be called repeatedly to process the initial query.
@@ -354,7 +363,7 @@ pub trait ViewService: Service {
     ///
     /// * `Ok(Option<Vec<BrowseResult>)` - A list [`BrowseResult`] corresponding to each node to browse. A browse result
     ///                                    may contain a continuation point, for use with `browse_next()`.
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`BrowseRequest`]: ./struct.BrowseRequest.html
     /// [`BrowseNextRequest`]: ./struct.BrowseNextRequest.html
@@ -364,7 +373,7 @@ pub trait ViewService: Service {
         &self,
         release_continuation_points: bool,
         continuation_points: &[ByteString],
-    ) -> Result<Option<Vec<BrowseResult>>, StatusCode>;
+    ) -> Result<Option<Vec<BrowseResult>>, ServiceError>;
 
     /// Translate browse paths to NodeIds by sending a [`TranslateBrowsePathsToNodeIdsRequest`] request to the Server
     /// Each [`BrowsePath`] is constructed of a starting node and a `RelativePath`. The specified starting node
@@ -382,7 +391,7 @@ pub trait ViewService: Service {
     /// * `Ok(Vec<BrowsePathResult>>)` - List of [`BrowsePathResult`] for the list of browse
     ///                       paths. The size and order of the list matches the size and order of the `browse_paths`
     ///                       parameter.
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`TranslateBrowsePathsToNodeIdsRequest`]: ./struct.TranslateBrowsePathsToNodeIdsRequest.html
     /// [`BrowsePath`]: ./struct.BrowsePath.html
@@ -390,7 +399,7 @@ pub trait ViewService: Service {
     fn translate_browse_paths_to_node_ids(
         &self,
         browse_paths: &[BrowsePath],
-    ) -> Result<Vec<BrowsePathResult>, StatusCode>;
+    ) -> Result<Vec<BrowsePathResult>, ServiceError>;
 
     /// Register nodes on the server by sending a [`RegisterNodesRequest`]. The purpose of this
     /// call is server-dependent but allows a client to ask a server to create nodes which are
@@ -406,11 +415,11 @@ pub trait ViewService: Service {
     ///
     /// * `Ok(Vec<NodeId>)` - A list of [`NodeId`] corresponding to size and order of the input. The
     ///                       server may return an alias for the input `NodeId`
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`RegisterNodesRequest`]: ./struct.RegisterNodesRequest.html
     /// [`NodeId`]: ./struct.NodeId.html
-    fn register_nodes(&self, nodes_to_register: &[NodeId]) -> Result<Vec<NodeId>, StatusCode>;
+    fn register_nodes(&self, nodes_to_register: &[NodeId]) -> Result<Vec<NodeId>, ServiceError>;
 
     /// Unregister nodes on the server by sending a [`UnregisterNodesRequest`]. This indicates to
     /// the server that the client relinquishes any need for these nodes. The server will ignore
@@ -425,12 +434,12 @@ pub trait ViewService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Request succeeded, server ignores invalid nodes
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`UnregisterNodesRequest`]: ./struct.UnregisterNodesRequest.html
     /// [`NodeId`]: ./struct.NodeId.html
     ///
-    fn unregister_nodes(&self, nodes_to_unregister: &[NodeId]) -> Result<(), StatusCode>;
+    fn unregister_nodes(&self, nodes_to_unregister: &[NodeId]) -> Result<(), ServiceError>;
 }
 
 /// Attribute Service set
@@ -451,7 +460,7 @@ pub trait AttributeService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<DataValue>)` - A list of [`DataValue`] corresponding to each read operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`ReadRequest`]: ./struct.ReadRequest.html
     /// [`ReadValueId`]: ./struct.ReadValueId.html
@@ -462,7 +471,7 @@ pub trait AttributeService: Service {
         nodes_to_read: &[ReadValueId],
         timestamps_to_return: TimestampsToReturn,
         max_age: f64,
-    ) -> Result<Vec<DataValue>, StatusCode>;
+    ) -> Result<Vec<DataValue>, ServiceError>;
 
     /// Reads historical values or events of one or more nodes. The caller is expected to provide
     /// a HistoryReadAction enum which must be one of the following:
@@ -484,15 +493,16 @@ pub trait AttributeService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<HistoryReadResult>)` - A list of `HistoryReadResult` results corresponding to history read operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
+    #[cfg(feature = "history")]
     fn history_read(
         &self,
         history_read_details: HistoryReadAction,
         timestamps_to_return: TimestampsToReturn,
         release_continuation_points: bool,
         nodes_to_read: &[HistoryReadValueId],
-    ) -> Result<Vec<HistoryReadResult>, StatusCode>;
+    ) -> Result<Vec<HistoryReadResult>, ServiceError>;
 
     /// Writes values to nodes by sending a [`WriteRequest`] to the server. Note that some servers may reject DataValues
     /// containing source or server timestamps.
@@ -506,12 +516,12 @@ pub trait AttributeService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - A list of `StatusCode` results corresponding to each write operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`WriteRequest`]: ./struct.WriteRequest.html
     /// [`WriteValue`]: ./struct.WriteValue.html
     ///
-    fn write(&self, nodes_to_write: &[WriteValue]) -> Result<Vec<StatusCode>, StatusCode>;
+    fn write(&self, nodes_to_write: &[WriteValue]) -> Result<Vec<StatusCode>, ServiceError>;
 
     /// Updates historical values. The caller is expected to provide one or more history update operations
     /// in a slice of HistoryUpdateAction enums which are one of the following:
@@ -532,12 +542,13 @@ pub trait AttributeService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<ClientHistoryUpdateResult>)` - A list of `ClientHistoryUpdateResult` results corresponding to history update operation.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
+    #[cfg(feature = "history")]
     fn history_update(
         &self,
         history_update_details: &[HistoryUpdateAction],
-    ) -> Result<Vec<HistoryUpdateResult>, StatusCode>;
+    ) -> Result<Vec<HistoryUpdateResult>, ServiceError>;
 }
 
 /// Method Service set
@@ -555,13 +566,13 @@ pub trait MethodService: Service {
     /// # Returns
     ///
     /// * `Ok(CallMethodResult)` - A `[CallMethodResult]` for the Method call.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`CallRequest`]: ./struct.CallRequest.html
     /// [`CallMethodRequest`]: ./struct.CallMethodRequest.html
     /// [`CallMethodResult`]: ./struct.CallMethodResult.html
     ///
-    fn call<T>(&self, method: T) -> Result<CallMethodResult, StatusCode>
+    fn call<T>(&self, method: T) -> Result<CallMethodResult, ServiceError>
     where
         T: Into<CallMethodRequest>;
 
@@ -574,12 +585,12 @@ pub trait MethodService: Service {
     /// # Returns
     ///
     /// * `Ok((Vec<u32>, Vec<u32>))` - Result for call, consisting a list of (monitored_item_id, client_handle)
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     fn call_get_monitored_items(
         &self,
         subscription_id: u32,
-    ) -> Result<(Vec<u32>, Vec<u32>), StatusCode> {
+    ) -> Result<(Vec<u32>, Vec<u32>), ServiceError> {
         let args = Some(vec![Variant::from(subscription_id)]);
         let object_id: NodeId = ObjectId::Server.into();
         let method_id: NodeId = MethodId::Server_GetMonitoredItems.into();
@@ -594,11 +605,11 @@ pub trait MethodService: Service {
                 Ok((server_handles, client_handles))
             } else {
                 error!("Expected a result with 2 args and didn't get it.");
-                Err(StatusCode::BadUnexpectedError)
+                Err(StatusCode::BadUnexpectedError.into())
             }
         } else {
             error!("Expected a result and didn't get it.");
-            Err(StatusCode::BadUnexpectedError)
+            Err(StatusCode::BadUnexpectedError.into())
         }
     }
 }
@@ -621,7 +632,7 @@ pub trait MonitoredItemService: Service {
     ///
     /// * `Ok(Vec<MonitoredItemCreateResult>)` - A list of [`MonitoredItemCreateResult`] corresponding to the items to create.
     ///    The size and order of the list matches the size and order of the `items_to_create` request parameter.
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`CreateMonitoredItemsRequest`]: ./struct.CreateMonitoredItemsRequest.html
     /// [`MonitoredItemCreateRequest`]: ./struct.MonitoredItemCreateRequest.html
@@ -632,7 +643,7 @@ pub trait MonitoredItemService: Service {
         subscription_id: u32,
         timestamps_to_return: TimestampsToReturn,
         items_to_create: &[MonitoredItemCreateRequest],
-    ) -> Result<Vec<MonitoredItemCreateResult>, StatusCode>;
+    ) -> Result<Vec<MonitoredItemCreateResult>, ServiceError>;
 
     /// Modifies monitored items on a subscription by sending a [`ModifyMonitoredItemsRequest`] to the server.
     ///
@@ -648,7 +659,7 @@ pub trait MonitoredItemService: Service {
     ///
     /// * `Ok(Vec<MonitoredItemModifyResult>)` - A list of [`MonitoredItemModifyResult`] corresponding to the MonitoredItems to modify.
     ///    The size and order of the list matches the size and order of the `items_to_modify` request parameter.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`ModifyMonitoredItemsRequest`]: ./struct.ModifyMonitoredItemsRequest.html
     /// [`MonitoredItemModifyRequest`]: ./struct.MonitoredItemModifyRequest.html
@@ -659,7 +670,7 @@ pub trait MonitoredItemService: Service {
         subscription_id: u32,
         timestamps_to_return: TimestampsToReturn,
         items_to_modify: &[MonitoredItemModifyRequest],
-    ) -> Result<Vec<MonitoredItemModifyResult>, StatusCode>;
+    ) -> Result<Vec<MonitoredItemModifyResult>, ServiceError>;
 
     /// Sets the monitoring mode on one or more monitored items by sending a [`SetMonitoringModeRequest`]
     /// to the server.
@@ -675,7 +686,7 @@ pub trait MonitoredItemService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - Individual result for each monitored item.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`SetMonitoringModeRequest`]: ./struct.SetMonitoringModeRequest.html
     ///
@@ -684,7 +695,7 @@ pub trait MonitoredItemService: Service {
         subscription_id: u32,
         monitoring_mode: MonitoringMode,
         monitored_item_ids: &[u32],
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 
     /// Sets a monitored item so it becomes the trigger that causes other monitored items to send
     /// change events in the same update. Sends a [`SetTriggeringRequest`] to the server.
@@ -702,7 +713,7 @@ pub trait MonitoredItemService: Service {
     /// # Returns
     ///
     /// * `Ok((Option<Vec<StatusCode>>, Option<Vec<StatusCode>>))` - Individual result for each item added / removed for the SetTriggering call.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`SetTriggeringRequest`]: ./struct.SetTriggeringRequest.html
     ///
@@ -712,7 +723,7 @@ pub trait MonitoredItemService: Service {
         triggering_item_id: u32,
         links_to_add: &[u32],
         links_to_remove: &[u32],
-    ) -> Result<(Option<Vec<StatusCode>>, Option<Vec<StatusCode>>), StatusCode>;
+    ) -> Result<SetTriggeringResults, ServiceError>;
 
     /// Deletes monitored items from a subscription by sending a [`DeleteMonitoredItemsRequest`] to the server.
     ///
@@ -727,7 +738,7 @@ pub trait MonitoredItemService: Service {
     ///
     /// * `Ok(Vec<StatusCode>)` - List of StatusCodes for the MonitoredItems to delete. The size and
     ///   order of the list matches the size and order of the `items_to_delete` request parameter.
-    /// * `Err(StatusCode)` - Status code reason for failure.
+    /// * `Err(ServiceError)` - Reason for failure.
     ///
     /// [`DeleteMonitoredItemsRequest`]: ./struct.DeleteMonitoredItemsRequest.html
     ///
@@ -735,7 +746,7 @@ pub trait MonitoredItemService: Service {
         &self,
         subscription_id: u32,
         items_to_delete: &[u32],
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////
@@ -781,7 +792,7 @@ pub trait SubscriptionService: Service {
     /// # Returns
     ///
     /// * `Ok(u32)` - identifier for new subscription
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`CreateSubscriptionRequest`]: ./struct.CreateSubscriptionRequest.html
     ///
@@ -794,7 +805,7 @@ pub trait SubscriptionService: Service {
         priority: u8,
         publishing_enabled: bool,
         callback: CB,
-    ) -> Result<u32, StatusCode>
+    ) -> Result<u32, ServiceError>
     where
         CB: OnSubscriptionNotification + Send + Sync + 'static;
 
@@ -811,7 +822,7 @@ pub trait SubscriptionService: Service {
     /// # Returns
     ///
     /// * `Ok(())` - Success
-    /// * `Err(StatusCode)` - Request failed, status code is the reason for failure
+    /// * `Err(ServiceError)` - Request failed, the error holds the reason for failure
     ///
     /// [`ModifySubscriptionRequest`]: ./struct.ModifySubscriptionRequest.html
     ///
@@ -823,7 +834,7 @@ pub trait SubscriptionService: Service {
         max_keep_alive_count: u32,
         max_notifications_per_publish: u32,
         priority: u8,
-    ) -> Result<(), StatusCode>;
+    ) -> Result<(), ServiceError>;
 
     /// Changes the publishing mode of subscriptions by sending a [`SetPublishingModeRequest`] to the server.
     ///
@@ -838,7 +849,7 @@ pub trait SubscriptionService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<StatusCode>)` - Service return code for the  action for each id, `Good` or `BadSubscriptionIdInvalid`
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`SetPublishingModeRequest`]: ./struct.SetPublishingModeRequest.html
     ///
@@ -846,7 +857,7 @@ pub trait SubscriptionService: Service {
         &self,
         subscription_ids: &[u32],
         publishing_enabled: bool,
-    ) -> Result<Vec<StatusCode>, StatusCode>;
+    ) -> Result<Vec<StatusCode>, ServiceError>;
 
     /// Transfers Subscriptions and their MonitoredItems from one Session to another. For example,
     /// a Client may need to reopen a Session and then transfer its Subscriptions to that Session.
@@ -864,7 +875,7 @@ pub trait SubscriptionService: Service {
     /// # Returns
     ///
     /// * `Ok(Vec<TransferResult>)` - The [`TransferResult`] for each transfer subscription.
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`TransferSubscriptionsRequest`]: ./struct.TransferSubscriptionsRequest.html
     /// [`TransferResult`]: ./struct.TransferResult.html
@@ -873,7 +884,7 @@ pub trait SubscriptionService: Service {
         &self,
         subscription_ids: &[u32],
         send_initial_values: bool,
-    ) -> Result<Vec<TransferResult>, StatusCode>;
+    ) -> Result<Vec<TransferResult>, ServiceError>;
 
     /// Deletes a subscription by sending a [`DeleteSubscriptionsRequest`] to the server.
     ///
@@ -886,11 +897,11 @@ pub trait SubscriptionService: Service {
     /// # Returns
     ///
     /// * `Ok(StatusCode)` - Service return code for the delete action, `Good` or `BadSubscriptionIdInvalid`
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`DeleteSubscriptionsRequest`]: ./struct.DeleteSubscriptionsRequest.html
     ///
-    fn delete_subscription(&self, subscription_id: u32) -> Result<StatusCode, StatusCode>;
+    fn delete_subscription(&self, subscription_id: u32) -> Result<StatusCode, ServiceError>;
 
     /// Deletes subscriptions by sending a [`DeleteSubscriptionsRequest`] to the server with the list
     /// of subscriptions to delete.
@@ -905,10 +916,10 @@ pub trait SubscriptionService: Service {
     ///
     /// * `Ok(Vec<StatusCode>)` - List of result for delete action on each id, `Good` or `BadSubscriptionIdInvalid`
     ///   The size and order of the list matches the size and order of the input.
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Err(ServiceError)` - Reason for failure
     ///
     /// [`DeleteSubscriptionsRequest`]: ./struct.DeleteSubscriptionsRequest.html
     ///
     fn delete_subscriptions(&self, subscription_ids: &[u32])
-        -> Result<Vec<StatusCode>, StatusCode>;
+        -> Result<Vec<StatusCode>, ServiceError>;
 }
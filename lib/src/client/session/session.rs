@@ -9,10 +9,13 @@
 //! and events.
 use std::{
     cmp,
-    collections::HashSet,
+    collections::{HashSet, VecDeque},
     result::Result,
     str::FromStr,
-    sync::{mpsc::SyncSender, Arc},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+        Arc,
+    },
     thread,
 };
 
@@ -23,15 +26,25 @@ use tokio::{
 
 use crate::{
     client::{
-        callbacks::{OnConnectionStatusChange, OnSessionClosed, OnSubscriptionNotification},
-        client::IdentityToken,
+        callbacks::{
+            KeepAliveFailure, OnConnectionStatusChange, OnFailover, OnKeepAliveFailure,
+            OnSessionClosed, OnSubscriptionNotification,
+        },
+        client::{Client, IdentityToken},
         comms::tcp_transport::TcpTransport,
+        config::{ClientConfig, ProxyConfig},
+        error::ServiceError,
+        failover::FailoverPolicy,
+        monitored_item_cache::MonitoredItemCache,
         process_service_result, process_unexpected_response,
         session::{
+            node_attributes::{NodeAttributeValues, NODE_ATTRIBUTE_IDS},
+            pending_response::PendingResponse,
             services::*,
             session_debug, session_error,
-            session_state::{ConnectionState, SessionState},
+            session_state::{self, ConnectionState, SessionState},
             session_trace, session_warn,
+            write_result::{WriteStatus, WriteValuesResult},
         },
         session_retry_policy::{Answer, SessionRetryPolicy},
         subscription::{self, Subscription},
@@ -40,6 +53,7 @@ use crate::{
     core::{
         comms::{
             secure_channel::{Role, SecureChannel},
+            socket_options::SocketOptions,
             url::*,
         },
         supported_message::SupportedMessage,
@@ -51,9 +65,16 @@ use crate::{
     },
     deregister_runtime_component, register_runtime_component,
     sync::*,
-    types::{node_ids::ObjectId, status_code::StatusCode, *},
+    types::{
+        node_ids::{ObjectId, VariableId},
+        status_code::StatusCode,
+        *,
+    },
 };
 
+#[cfg(feature = "history")]
+use crate::client::session::history_read::{HistoryReadOptions, HistoryValues};
+
 /// Information about the server endpoint, security policy, security mode and user identity that the session will
 /// will use to establish a connection.
 #[derive(Debug)]
@@ -116,12 +137,32 @@ pub struct Session {
     secure_channel: Arc<RwLock<SecureChannel>>,
     /// Session retry policy.
     session_retry_policy: Arc<Mutex<SessionRetryPolicy>>,
+    /// How long in milliseconds to wait for the response to a request.
+    request_timeout: u32,
+    /// How many synchronous requests may be outstanding at once.
+    max_inflight_requests: usize,
+    /// How often in milliseconds the state of the server is read to check it is running, 0 for never.
+    keep_alive_interval: u32,
+    /// Refuses to create a session over a secure channel with security policy None.
+    secure_sessions_only: bool,
+    /// Called when the keep-alive finds the server unavailable. It is kept by the session rather
+    /// than its state so that it is still registered after a reconnect.
+    keep_alive_callback: Arc<Mutex<Option<Box<dyn OnKeepAliveFailure + Send + Sync + 'static>>>>,
+    /// The redundant servers that the session fails over to, if any.
+    failover_policy: Option<FailoverPolicy>,
+    /// Called when the session has failed over to a redundant server.
+    failover_callback: Option<Box<dyn OnFailover + Send + Sync + 'static>>,
+    /// The diagnostics that the server is asked to return with each response.
+    return_diagnostics: DiagnosticBits,
     /// Ignore clock skew between the client and the server.
     ignore_clock_skew: bool,
-    /// Single threaded executor flag (for TCP transport). Unused.
+    /// Single threaded executor flag (for TCP transport).
     single_threaded_executor: bool,
     /// Tokio runtime
     runtime: Arc<Mutex<tokio::runtime::Runtime>>,
+    /// Handle of the runtime, which spawns tasks without locking the runtime, because the run
+    /// loop holds that lock while the session reconnects.
+    runtime_handle: tokio::runtime::Handle,
 }
 
 impl Drop for Session {
@@ -155,8 +196,8 @@ impl Session {
         ignore_clock_skew: bool,
         single_threaded_executor: bool,
     ) -> Session
-        where
-            T: Into<UAString>,
+    where
+        T: Into<UAString>,
     {
         let session_name = session_name.into();
 
@@ -196,8 +237,17 @@ impl Session {
             transport,
             secure_channel,
             session_retry_policy: Arc::new(Mutex::new(session_retry_policy)),
+            request_timeout: ClientConfig::DEFAULT_REQUEST_TIMEOUT,
+            max_inflight_requests: ClientConfig::DEFAULT_MAX_INFLIGHT_REQUESTS,
+            keep_alive_interval: 0,
+            secure_sessions_only: false,
+            keep_alive_callback: Arc::new(Mutex::new(None)),
+            failover_policy: None,
+            failover_callback: None,
+            return_diagnostics: DiagnosticBits::empty(),
             ignore_clock_skew,
             single_threaded_executor,
+            runtime_handle: runtime.handle().clone(),
             runtime: Arc::new(Mutex::new(runtime)),
         }
     }
@@ -210,13 +260,23 @@ impl Session {
         }
 
         // Create a new session state
-        self.session_state = Arc::new(RwLock::new(SessionState::new(
+        let mut session_state = SessionState::new(
             self.ignore_clock_skew,
             self.secure_channel.clone(),
             self.subscription_state.clone(),
-        )));
+        );
+        session_state.set_request_timeout(self.request_timeout);
+        session_state.set_max_inflight_requests(self.max_inflight_requests);
+        session_state.set_return_diagnostics(self.return_diagnostics);
+        {
+            let mut old_session_state = trace_write_lock!(self.session_state);
+            session_state.take_session_from(&mut old_session_state);
+        }
+        self.session_state = Arc::new(RwLock::new(session_state));
 
-        // Keep the existing transport, we should never drop a tokio runtime from a sync function
+        // Keep the existing transport, we should never drop a tokio runtime from a sync function,
+        // but have it use the new state
+        self.transport.set_session_state(self.session_state.clone());
     }
 
     /// Connects to the server, creates and activates a session. If there
@@ -247,6 +307,312 @@ impl Session {
         self.session_retry_policy = Arc::new(Mutex::new(session_retry_policy));
     }
 
+    /// Sets how long in milliseconds the session waits for the response to a request before the
+    /// request fails with `BadTimeout`. The timeout is also sent to the server as the timeout
+    /// hint of each request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_timeout` - the request timeout in milliseconds
+    ///
+    pub fn set_request_timeout(&mut self, request_timeout: u32) {
+        self.request_timeout = request_timeout;
+        let mut session_state = trace_write_lock!(self.session_state);
+        session_state.set_request_timeout(request_timeout);
+    }
+
+    /// Sets how many requests the session may have outstanding at once when it is called from
+    /// several threads. A request made while the window is full waits for an earlier request to
+    /// be answered, or fails with `BadTimeout` if none is within the request timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_inflight_requests` - the number of outstanding requests, 0 meaning no limit
+    ///
+    pub fn set_max_inflight_requests(&mut self, max_inflight_requests: usize) {
+        self.max_inflight_requests = max_inflight_requests;
+        let mut session_state = trace_write_lock!(self.session_state);
+        session_state.set_max_inflight_requests(max_inflight_requests);
+    }
+
+    /// Returns how many requests the session may have outstanding at once, 0 meaning no limit
+    pub fn max_inflight_requests(&self) -> usize {
+        self.max_inflight_requests
+    }
+
+    /// Sets how often the session reads the state of the server to check that it is still
+    /// running. If the server does not answer a read before the next is due, or reports a state
+    /// other than `Running`, the keep-alive callback is called and the session drops its
+    /// connection, so that the session retry policy reconnects it. The interval takes effect
+    /// when the session is next activated, and the server is only checked while the session is
+    /// run with `run()` or `run_async()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_alive_interval` - the interval in milliseconds, 0 meaning the server is not checked
+    ///
+    pub fn set_keep_alive_interval(&mut self, keep_alive_interval: u32) {
+        self.keep_alive_interval = keep_alive_interval;
+    }
+
+    /// Returns how often in milliseconds the session checks the state of the server, 0 meaning
+    /// it does not check
+    pub fn keep_alive_interval(&self) -> u32 {
+        self.keep_alive_interval
+    }
+
+    /// Sets that the session refuses to create a session with the server over a secure channel
+    /// with security policy None, which fails with `BadSecurityPolicyRejected` before anything
+    /// is sent. The session can still connect with security policy None to get the endpoints of
+    /// the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `secure_sessions_only` - true to refuse sessions over unsecured channels
+    ///
+    pub fn set_secure_sessions_only(&mut self, secure_sessions_only: bool) {
+        self.secure_sessions_only = secure_sessions_only;
+    }
+
+    /// Returns true if the session refuses to create a session over an unsecured channel
+    pub fn secure_sessions_only(&self) -> bool {
+        self.secure_sessions_only
+    }
+
+    /// Sets the redundant servers that the session fails over to when it cannot reconnect to the
+    /// server it was connected to. Failing over happens when the session is polled, i.e. while
+    /// it is run, and only after the session has been connected to its own server.
+    ///
+    /// # Arguments
+    ///
+    /// * `failover_policy` - the redundant servers and how they back each other up
+    ///
+    pub fn set_failover_policy(&mut self, mut failover_policy: FailoverPolicy) {
+        failover_policy.include_server(self.session_info.endpoint.endpoint_url.as_ref());
+        self.failover_policy = Some(failover_policy);
+    }
+
+    /// Sets the SOCKS5 or HTTP proxy that the session connects to its server through, or `None` to
+    /// connect directly. It takes effect the next time the session connects.
+    ///
+    /// # Arguments
+    ///
+    /// * `proxy` - the proxy, which must be valid
+    ///
+    pub fn set_proxy(&mut self, proxy: Option<ProxyConfig>) {
+        self.transport.set_proxy(proxy);
+    }
+
+    /// Sets the options on the TCP socket of each connection that the session makes. They take
+    /// effect the next time the session connects.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_options` - the socket options
+    ///
+    pub fn set_socket_options(&mut self, socket_options: SocketOptions) {
+        self.transport.set_socket_options(socket_options);
+    }
+
+    /// Sets how long the session waits for a TCP connection to its server, or its proxy. It
+    /// takes effect the next time the session connects.
+    ///
+    /// # Arguments
+    ///
+    /// * `connect_timeout` - the timeout in milliseconds, 0 meaning as long as the operating
+    ///   system waits
+    ///
+    pub fn set_connect_timeout(&mut self, connect_timeout: u32) {
+        self.transport.set_connect_timeout(connect_timeout);
+    }
+
+    /// Sets whether the session connects to a server in this process that accepts loopback
+    /// connections over an in-memory stream instead of TCP. It takes effect the next time the
+    /// session connects.
+    ///
+    /// # Arguments
+    ///
+    /// * `loopback` - true to connect to loopback servers in this process
+    ///
+    pub fn set_loopback(&mut self, loopback: bool) {
+        self.transport.set_loopback(loopback);
+    }
+
+    /// Returns the endpoint url of the server that the session connects to, which changes when
+    /// the session fails over to a redundant server
+    pub fn server_url(&self) -> &str {
+        self.session_info.endpoint.endpoint_url.as_ref()
+    }
+
+    /// Calls the function with a request timeout that replaces the session's for the requests
+    /// made by the function on this thread, e.g. to give a single slow call longer to complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_timeout` - the request timeout in milliseconds
+    /// * `f` - the function that makes the requests
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use opcua::client::prelude::*;
+    /// # fn example(session: &Session, nodes_to_read: &[ReadValueId]) -> Result<(), StatusCode> {
+    /// let values = session.with_request_timeout(60000, |session| {
+    ///     session.read(nodes_to_read, TimestampsToReturn::Both, 0.0)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    pub fn with_request_timeout<F, R>(&self, request_timeout: u32, f: F) -> R
+    where
+        F: FnOnce(&Session) -> R,
+    {
+        session_state::with_request_timeout(request_timeout, || f(self))
+    }
+
+    /// Sets the diagnostics that the server is asked to return with the response to each request.
+    /// The diagnostics of the last response can be obtained from `last_response_diagnostics()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `return_diagnostics` - the service and operation level diagnostics to return
+    ///
+    pub fn set_return_diagnostics(&mut self, return_diagnostics: DiagnosticBits) {
+        self.return_diagnostics = return_diagnostics;
+        let mut session_state = trace_write_lock!(self.session_state);
+        session_state.set_return_diagnostics(return_diagnostics);
+    }
+
+    /// Returns the diagnostics that the server returned with the response to the last request,
+    /// with the strings they refer to looked up in the string table of the response. There are
+    /// only diagnostics if they were asked for with `set_return_diagnostics()` and the server
+    /// supplied some.
+    pub fn last_response_diagnostics(&self) -> Option<ResponseDiagnostics> {
+        let session_state = trace_read_lock!(self.session_state);
+        session_state.last_response_diagnostics()
+    }
+
+    /// Sends a request without waiting for its response, so a thread can have many requests in
+    /// flight at once. Requests made this way count towards the request window, and this waits for
+    /// a slot if the window is full. Each response is received through the returned
+    /// `PendingResponse`, in whatever order the server answers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use opcua::client::prelude::*;
+    /// # fn example(session: &Session, requests: Vec<ReadRequest>) -> Result<(), StatusCode> {
+    /// let pending = requests
+    ///     .into_iter()
+    ///     .map(|request| session.begin_request(request))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// for pending in pending {
+    ///     let response = pending.wait()?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    pub fn begin_request<T>(&self, request: T) -> Result<PendingResponse, StatusCode>
+    where
+        T: Into<SupportedMessage>,
+    {
+        SessionState::begin_request(&self.session_state, request)
+    }
+
+    /// Reads the nodes with as many read requests in flight at once as the request window
+    /// allows, each request reading up to `max_nodes_per_read` of them. Reading many nodes
+    /// this way takes little more than one round trip to the server per window of requests.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes_to_read` - the nodes to read, as for `read()`
+    /// * `max_nodes_per_read` - the most nodes to read in one request, 0 meaning all of them
+    /// * `timestamps_to_return` - the timestamps to return with the values
+    /// * `max_age` - the maximum age of the values to read, as for `read()`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DataValue>)` - the values of the nodes, in the order of `nodes_to_read`
+    /// * `Err(ServiceError)` - the reason the first failing request failed
+    ///
+    pub fn read_pipelined(
+        &self,
+        nodes_to_read: &[ReadValueId],
+        max_nodes_per_read: usize,
+        timestamps_to_return: TimestampsToReturn,
+        max_age: f64,
+    ) -> Result<Vec<DataValue>, ServiceError> {
+        if nodes_to_read.is_empty() {
+            session_error!(
+                self,
+                "read_pipelined(), was not supplied with any nodes to read"
+            );
+            return Err(StatusCode::BadNothingToDo.into());
+        }
+        let max_nodes_per_read = if max_nodes_per_read == 0 {
+            nodes_to_read.len()
+        } else {
+            max_nodes_per_read
+        };
+
+        // The requests are kept within the window, so this thread does not wait for a slot that
+        // only its own responses would free up
+        let mut pending = VecDeque::new();
+        let mut results = Vec::with_capacity(nodes_to_read.len());
+        for nodes_to_read in nodes_to_read.chunks(max_nodes_per_read) {
+            if self.max_inflight_requests > 0 && pending.len() >= self.max_inflight_requests {
+                let (pending, expected) = pending.pop_front().unwrap();
+                results.extend(Self::read_pipelined_response(pending, expected)?);
+            }
+            let request = ReadRequest {
+                request_header: self.make_request_header(),
+                max_age,
+                timestamps_to_return,
+                nodes_to_read: Some(nodes_to_read.to_vec()),
+            };
+            pending.push_back((self.begin_request(request)?, nodes_to_read.len()));
+        }
+        while let Some((pending, expected)) = pending.pop_front() {
+            results.extend(Self::read_pipelined_response(pending, expected)?);
+        }
+        session_debug!(self, "read_pipelined(), read {} values", results.len());
+        Ok(results)
+    }
+
+    fn read_pipelined_response(
+        pending: PendingResponse,
+        expected: usize,
+    ) -> Result<Vec<DataValue>, ServiceError> {
+        let response = pending.wait()?;
+        if let SupportedMessage::ReadResponse(response) = response {
+            process_service_result(&response.response_header)?;
+            let results = response.results.unwrap_or_default();
+            if results.len() != expected {
+                error!(
+                    "read_pipelined(), server returned {} values for {} nodes",
+                    results.len(),
+                    expected
+                );
+                Err(StatusCode::BadUnexpectedError.into())
+            } else {
+                Ok(results)
+            }
+        } else {
+            Err(process_unexpected_response(response))
+        }
+    }
+
+    /// Returns the request handles of the requests that have been sent and are waiting for a
+    /// response. A request that takes too long can be cancelled by calling `cancel()` with its
+    /// handle from another thread.
+    pub fn inflight_request_handles(&self) -> Vec<u32> {
+        let session_state = trace_read_lock!(self.session_state);
+        let message_queue = trace_read_lock!(session_state.message_queue);
+        message_queue.inflight_request_handles()
+    }
+
     /// Register a callback to be notified when the session has been closed.
     ///
     /// # Arguments
@@ -254,8 +620,8 @@ impl Session {
     /// * `session_closed_callback` - the session closed callback
     ///
     pub fn set_session_closed_callback<CB>(&mut self, session_closed_callback: CB)
-        where
-            CB: OnSessionClosed + Send + Sync + 'static,
+    where
+        CB: OnSessionClosed + Send + Sync + 'static,
     {
         let mut session_state = trace_write_lock!(self.session_state);
         session_state.set_session_closed_callback(session_closed_callback);
@@ -269,13 +635,42 @@ impl Session {
     /// * `connection_status_callback` - the connection status callback.
     ///
     pub fn set_connection_status_callback<CB>(&mut self, connection_status_callback: CB)
-        where
-            CB: OnConnectionStatusChange + Send + Sync + 'static,
+    where
+        CB: OnConnectionStatusChange + Send + Sync + 'static,
     {
         let mut session_state = trace_write_lock!(self.session_state);
         session_state.set_connection_status_callback(connection_status_callback);
     }
 
+    /// Registers a callback to be notified when the keep-alive of the session finds that the
+    /// server has become unresponsive or is no longer running. See `set_keep_alive_interval()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_alive_callback` - the keep-alive failure callback.
+    ///
+    pub fn set_keep_alive_callback<CB>(&mut self, keep_alive_callback: CB)
+    where
+        CB: OnKeepAliveFailure + Send + Sync + 'static,
+    {
+        let mut callback = trace_lock!(self.keep_alive_callback);
+        *callback = Some(Box::new(keep_alive_callback));
+    }
+
+    /// Registers a callback to be notified when the session has failed over to a redundant
+    /// server. See `set_failover_policy()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `failover_callback` - the failover callback.
+    ///
+    pub fn set_failover_callback<CB>(&mut self, failover_callback: CB)
+    where
+        CB: OnFailover + Send + Sync + 'static,
+    {
+        self.failover_callback = Some(Box::new(failover_callback));
+    }
+
     /// Reconnects to the server and tries to activate the existing session. If there
     /// is a failure, it will be communicated by the status code in the result. You should not
     /// call this if there is a session retry policy associated with the session.
@@ -297,41 +692,173 @@ impl Session {
             );
             Err(StatusCode::BadUnexpectedError)
         } else {
-            // Reset the session state
-            self.reset();
+            self.reconnect(true, true)
+        }
+    }
+
+    /// Reconnects to the server of the session endpoint. The existing session is activated if
+    /// `reactivate` is set and the server still knows it, otherwise a new session is created.
+    /// Subscriptions of the old session are transferred if `transfer` is set, and those that are
+    /// not transferred are created again.
+    fn reconnect(&mut self, reactivate: bool, transfer: bool) -> Result<(), StatusCode> {
+        // Reset the session state
+        self.reset();
 
-            // Connect to server (again)
-            self.connect_no_retry()?;
+        // Connect to server (again)
+        self.connect_no_retry()?;
 
-            // Attempt to reactivate the existing session
+        // Attempt to reactivate the existing session
+        let activated = if reactivate {
             match self.activate_session() {
                 Err(status_code) => {
-                    // Activation didn't work, so create a new session
                     info!("Session activation failed on reconnect, error = {}, so creating a new session", status_code);
-                    {
-                        let mut session_state = trace_write_lock!(self.session_state);
-                        session_state.reset();
-                    }
-
-                    session_debug!(self, "create_session");
-                    self.create_session()?;
-                    session_debug!(self, "activate_session");
-                    self.activate_session()?;
-                    session_debug!(self, "reconnect should be complete");
+                    false
                 }
                 Ok(_) => {
                     info!("Activation succeeded");
+                    true
                 }
             }
-            session_debug!(self, "transfer_subscriptions_from_old_session");
-            self.transfer_subscriptions_from_old_session()?;
-            Ok(())
+        } else {
+            false
+        };
+        if !activated {
+            // Activation didn't work, so create a new session
+            {
+                let mut session_state = trace_write_lock!(self.session_state);
+                session_state.reset();
+            }
+
+            session_debug!(self, "create_session");
+            self.create_session()?;
+            session_debug!(self, "activate_session");
+            self.activate_session()?;
+            session_debug!(self, "reconnect should be complete");
+        }
+        session_debug!(self, "transfer_subscriptions_from_old_session");
+        self.transfer_subscriptions_from_old_session(transfer)?;
+        Ok(())
+    }
+
+    /// Fails over to the first of the redundant servers that the session reconnects to. Returns
+    /// `false` if there are no redundant servers or the session could reconnect to none of them,
+    /// in which case it is left with the endpoint of the server it was connected to.
+    fn fail_over(&mut self) -> bool {
+        let (mode, server_urls) = match self.failover_policy {
+            Some(ref failover_policy) => (
+                failover_policy.mode(),
+                failover_policy.failover_order(self.server_url()),
+            ),
+            None => return false,
+        };
+        let original_endpoint = self.session_info.endpoint.clone();
+        for server_url in server_urls {
+            info!("Failing over to redundant server {}", server_url);
+            let endpoint = match self.redundant_endpoint(&original_endpoint, &server_url) {
+                Ok(endpoint) => endpoint,
+                Err(status_code) => {
+                    session_warn!(
+                        self,
+                        "Cannot get the endpoint of redundant server {}, error = {}",
+                        server_url,
+                        status_code
+                    );
+                    continue;
+                }
+            };
+            self.session_info.endpoint = endpoint;
+            match self.reconnect(mode.reactivates_session(), mode.transfers_subscriptions()) {
+                Ok(_) => {
+                    info!("Failed over to redundant server {}", server_url);
+                    if let Some(ref mut failover_callback) = self.failover_callback {
+                        failover_callback
+                            .on_failover(original_endpoint.endpoint_url.as_ref(), &server_url);
+                    }
+                    return true;
+                }
+                Err(status_code) => {
+                    session_warn!(
+                        self,
+                        "Failing over to redundant server {} was unsuccessful, error = {}",
+                        server_url,
+                        status_code
+                    );
+                    // Drop the connection without closing the session, so the subscriptions
+                    // are kept for the next server
+                    self.drop_connection();
+                }
+            }
+        }
+        self.session_info.endpoint = original_endpoint;
+        false
+    }
+
+    /// Returns the endpoint of a redundant server, which has the security policy and mode of the
+    /// session's endpoint. A secure endpoint is looked up on the server for its certificate.
+    fn redundant_endpoint(
+        &self,
+        endpoint: &EndpointDescription,
+        server_url: &str,
+    ) -> Result<EndpointDescription, StatusCode> {
+        let security_policy = SecurityPolicy::from_uri(endpoint.security_policy_uri.as_ref());
+        if security_policy == SecurityPolicy::None {
+            let mut endpoint = endpoint.clone();
+            endpoint.endpoint_url = server_url.into();
+            endpoint.server_certificate = ByteString::null();
+            Ok(endpoint)
+        } else {
+            let decoding_options = {
+                let secure_channel = trace_read_lock!(self.secure_channel);
+                secure_channel.decoding_options()
+            };
+            let mut session = Session::new(
+                self.application_description.clone(),
+                self.session_name.clone(),
+                self.certificate_store.clone(),
+                EndpointDescription::from(server_url).into(),
+                SessionRetryPolicy::new(SessionRetryPolicy::DEFAULT_SESSION_TIMEOUT_MS, 0, 0),
+                decoding_options,
+                self.ignore_clock_skew,
+                self.single_threaded_executor,
+            );
+            session.set_request_timeout(self.request_timeout);
+            session.set_proxy(self.transport.proxy().cloned());
+            session.set_socket_options(self.transport.socket_options().clone());
+            session.set_connect_timeout(self.transport.connect_timeout());
+            session.set_loopback(self.transport.loopback());
+            session.connect_no_retry()?;
+            let endpoints = session.get_endpoints();
+            session.disconnect();
+            Client::find_matching_endpoint(
+                &endpoints?,
+                server_url,
+                security_policy,
+                endpoint.security_mode,
+            )
+            .ok_or(StatusCode::BadSecurityPolicyRejected)
+        }
+    }
+
+    /// Drops the connection to the server without closing the session
+    fn drop_connection(&self) {
+        if self.is_connected() {
+            let _ = self.close_secure_channel();
+            {
+                let session_state = trace_read_lock!(self.session_state);
+                session_state.quit();
+            }
+            self.transport.wait_for_disconnect();
+            self.on_connection_status_change(false);
         }
     }
 
     /// This code attempts to take the existing subscriptions created by a previous session and
-    /// either transfer them to this session, or construct them from scratch.
-    fn transfer_subscriptions_from_old_session(&mut self) -> Result<(), StatusCode> {
+    /// either transfer them to this session, or construct them from scratch. Transferring is not
+    /// tried unless `try_transfer` is set.
+    fn transfer_subscriptions_from_old_session(
+        &mut self,
+        try_transfer: bool,
+    ) -> Result<(), StatusCode> {
         let subscription_state = self.subscription_state.clone();
 
         let subscription_ids = {
@@ -345,7 +872,12 @@ impl Session {
             // works then there is nothing else to do.
             let mut subscription_ids_to_recreate =
                 subscription_ids.iter().copied().collect::<HashSet<u32>>();
-            if let Ok(transfer_results) = self.transfer_subscriptions(&subscription_ids, true) {
+            let transfer_results = if try_transfer {
+                self.transfer_subscriptions(&subscription_ids, true).ok()
+            } else {
+                None
+            };
+            if let Some(transfer_results) = transfer_results {
                 session_debug!(self, "transfer_results = {:?}", transfer_results);
                 transfer_results.iter().enumerate().for_each(|(i, r)| {
                     if r.status_code.is_good() {
@@ -368,7 +900,7 @@ impl Session {
                     // Remove the subscription data, create it again from scratch
                     let deleted_subscription = {
                         let mut subscription_state = trace_write_lock!(subscription_state);
-                        subscription_state.delete_subscription(*subscription_id)
+                        subscription_state.take_subscription(*subscription_id)
                     };
 
                     if let Some(subscription) = deleted_subscription {
@@ -505,15 +1037,21 @@ impl Session {
             );
             Err(StatusCode::BadSecurityPolicyRejected)
         } else {
-            let (cert, key) = {
+            let (cert, issuer_certs, key) = {
                 let certificate_store = trace_write_lock!(self.certificate_store);
-                certificate_store.read_own_cert_and_pkey_optional()
+                let (cert, key) = certificate_store.read_own_cert_and_pkey_optional();
+                let issuer_certs = cert
+                    .as_ref()
+                    .map(|cert| certificate_store.issuer_certs(cert))
+                    .unwrap_or_default();
+                (cert, issuer_certs, key)
             };
 
             {
                 let mut secure_channel = trace_write_lock!(self.secure_channel);
                 secure_channel.set_private_key(key);
                 secure_channel.set_cert(cert);
+                secure_channel.set_issuer_certs(issuer_certs);
                 secure_channel.set_security_policy(security_policy);
                 secure_channel.set_security_mode(self.session_info.endpoint.security_mode);
                 let _ = secure_channel.set_remote_cert_from_byte_string(
@@ -626,10 +1164,25 @@ impl Session {
             _ = async {
                 let mut timer = interval(Duration::from_millis(sleep_interval));
                 loop {
-                    // Poll the session.
+                    // Poll the session. Only reconnecting needs the session to itself, so a
+                    // connected session is polled with a read lock that does not wait for other
+                    // threads to finish their calls
                     let poll_result = {
-                        let mut session = session.write();
-                        session.poll().await
+                        let responses = {
+                            let session = session.read();
+                            if session.is_connected() {
+                                Some(session.poll_responses())
+                            } else {
+                                None
+                            }
+                        };
+                        match responses {
+                            Some(did_something) => Ok(did_something),
+                            None => {
+                                let mut session = session.write();
+                                session.poll().await
+                            }
+                        }
                     };
                     match poll_result {
                         Ok(did_something) => {
@@ -703,8 +1256,7 @@ impl Session {
     ///
     pub async fn poll(&mut self) -> Result<bool, ()> {
         let did_something = if self.is_connected() {
-            let mut session_state = trace_write_lock!(self.session_state);
-            session_state.handle_publish_responses()
+            self.poll_responses()
         } else {
             let should_retry_connect = {
                 let session_retry_policy = trace_lock!(self.session_retry_policy);
@@ -726,7 +1278,21 @@ impl Session {
                         let mut session_retry_policy = trace_lock!(self.session_retry_policy);
                         session_retry_policy.set_last_attempt(DateTime::now());
                     }
-                    if self.reconnect_and_activate().is_ok() {
+                    let reconnected = match self.reconnect_and_activate() {
+                        Ok(_) => true,
+                        Err(status_code) if self.failover_policy.is_some() => {
+                            // Try the redundant servers before waiting to retry
+                            session_warn!(
+                                self,
+                                "Reconnect was unsuccessful, error = {}, so failing over",
+                                status_code
+                            );
+                            self.drop_connection();
+                            self.fail_over()
+                        }
+                        Err(_) => false,
+                    };
+                    if reconnected {
                         info!("Retry to connect was successful");
                         let mut session_retry_policy = trace_lock!(self.session_retry_policy);
                         session_retry_policy.reset_retry_count();
@@ -753,6 +1319,15 @@ impl Session {
         Ok(did_something)
     }
 
+    /// Handles the asynchronous responses that have arrived and stops waiting for requests that
+    /// have timed out. Unlike `poll()` it only needs shared access to the session, so it does not
+    /// hold up the threads that are calling the session.
+    fn poll_responses(&self) -> bool {
+        let mut session_state = trace_write_lock!(self.session_state);
+        session_state.remove_timed_out_requests();
+        session_state.handle_publish_responses()
+    }
+
     /// Start a task that will periodically "ping" the server to keep the session alive. The ping rate
     /// will be 3/4 the session timeout rate.
     ///
@@ -781,8 +1356,7 @@ impl Session {
         );
 
         let id = format!("session-activity-thread-{:?}", thread::current().id());
-        let runtime = trace_lock!(self.runtime);
-        runtime.spawn(async move {
+        self.runtime_handle.spawn(async move {
             register_runtime_component!(&id);
             // The timer runs at a higher frequency timer loop to terminate as soon after the session
             // state has terminated. Each time it runs it will test if the interval has elapsed or not.
@@ -831,46 +1405,172 @@ impl Session {
         });
     }
 
-    /// Start a task that will periodically send a publish request to keep the subscriptions alive.
-    /// The request rate will be 3/4 of the shortest (revised publishing interval * the revised keep
-    /// alive count) of all subscriptions that belong to a single session.
-    fn spawn_subscription_activity_task(&self) {
-        session_debug!(self, "spawn_subscription_activity_task",);
+    /// Start a task that will read the state of the server every keep-alive interval, if there is
+    /// one. A read that is not answered by the time the next one is due, or a state other than
+    /// `Running`, calls the keep-alive callback and drops the connection so the session retry
+    /// policy can reconnect.
+    fn spawn_keep_alive_task(&self) {
+        if self.keep_alive_interval == 0 {
+            return;
+        }
+        session_debug!(self, "spawn_keep_alive_task({})", self.keep_alive_interval);
 
         let connection_state = {
             let session_state = trace_read_lock!(self.session_state);
             session_state.connection_state()
         };
-
-        const MIN_SUBSCRIPTION_ACTIVITY_MS: u64 = 1000;
         let session_state = self.session_state.clone();
-        let subscription_state = self.subscription_state.clone();
+        let keep_alive_callback = self.keep_alive_callback.clone();
+        let keep_alive_interval = Duration::from_millis(self.keep_alive_interval as u64);
 
-        let id = format!("subscription-activity-thread-{:?}", thread::current().id());
-        let runtime = trace_lock!(self.runtime);
-        runtime.spawn(async move {
+        let id = format!("keep-alive-thread-{:?}", thread::current().id());
+        self.runtime_handle.spawn(async move {
             register_runtime_component!(&id);
+            let mut timer = interval(keep_alive_interval);
+            // The first tick of the interval is immediate
+            timer.tick().await;
 
-            // The timer runs at a higher frequency timer loop to terminate as soon after the session
-            // state has terminated. Each time it runs it will test if the interval has elapsed or not.
-            let mut timer = interval(Duration::from_millis(MIN_SUBSCRIPTION_ACTIVITY_MS));
-
-            let mut last_timeout: Instant;
-            let mut subscription_activity_interval: Duration;
-
+            let mut pending: Option<(u32, Receiver<SupportedMessage>)> = None;
             loop {
                 timer.tick().await;
 
                 if connection_state.is_finished() {
-                    info!("Session activity timer is terminating");
                     break;
                 }
 
-                if let (Some(keep_alive_timeout), last_publish_request) = {
-                    let subscription_state = trace_read_lock!(subscription_state);
-                    (
-                        subscription_state.keep_alive_timeout(),
-                        subscription_state.last_publish_request(),
+                // Check the answer to the last read of the server state
+                let failure = pending
+                    .take()
+                    .and_then(|(request_handle, receiver)| match receiver.try_recv() {
+                        Ok(response) => Self::keep_alive_failure(response),
+                        Err(TryRecvError::Empty) => {
+                            let session_state = trace_read_lock!(session_state);
+                            session_state.request_has_timed_out(request_handle);
+                            Some(KeepAliveFailure::Unresponsive(StatusCode::BadTimeout))
+                        }
+                        Err(TryRecvError::Disconnected) => Some(KeepAliveFailure::Unresponsive(
+                            StatusCode::BadConnectionClosed,
+                        )),
+                    });
+                if let Some(failure) = failure {
+                    warn!("Session keep-alive failed, {:?}", failure);
+                    {
+                        let mut keep_alive_callback = trace_lock!(keep_alive_callback);
+                        if let Some(ref mut keep_alive_callback) = *keep_alive_callback {
+                            keep_alive_callback.on_keep_alive_failure(failure);
+                        }
+                    }
+                    let session_state = trace_read_lock!(session_state);
+                    session_state.quit();
+                    break;
+                }
+
+                if let ConnectionState::Processing = connection_state.state() {
+                    let (sender, receiver) = mpsc::sync_channel(1);
+                    let mut session_state = trace_write_lock!(session_state);
+                    let request = ReadRequest {
+                        request_header: session_state.make_request_header(),
+                        max_age: 0f64,
+                        timestamps_to_return: TimestampsToReturn::Neither,
+                        nodes_to_read: Some(vec![ReadValueId::from(NodeId::from(
+                            &VariableId::Server_ServerStatus_State,
+                        ))]),
+                    };
+                    if let Ok(request_handle) =
+                        session_state.async_send_request(request, Some(sender))
+                    {
+                        pending = Some((request_handle, receiver));
+                    }
+                }
+            }
+
+            info!("Session keep-alive task is finished");
+            deregister_runtime_component!(&id);
+        });
+    }
+
+    /// Returns why the response to a keep-alive read shows the server to be unavailable, or
+    /// `None` if it is running
+    fn keep_alive_failure(response: SupportedMessage) -> Option<KeepAliveFailure> {
+        match response {
+            SupportedMessage::ReadResponse(response) => {
+                let service_result = response.response_header.service_result;
+                if service_result.is_bad() {
+                    return Some(KeepAliveFailure::Unresponsive(service_result));
+                }
+                let state = response
+                    .results
+                    .as_ref()
+                    .and_then(|results| results.first())
+                    .and_then(|result| result.value.as_ref());
+                match state {
+                    Some(Variant::Int32(state)) => {
+                        let state = match *state {
+                            0 => ServerState::Running,
+                            1 => ServerState::Failed,
+                            2 => ServerState::NoConfiguration,
+                            3 => ServerState::Suspended,
+                            4 => ServerState::Shutdown,
+                            5 => ServerState::Test,
+                            6 => ServerState::CommunicationFault,
+                            _ => ServerState::Unknown,
+                        };
+                        if state == ServerState::Running {
+                            None
+                        } else {
+                            Some(KeepAliveFailure::ServerState(state))
+                        }
+                    }
+                    // A server that answers without its state is still responsive
+                    _ => None,
+                }
+            }
+            SupportedMessage::ServiceFault(fault) => Some(KeepAliveFailure::Unresponsive(
+                fault.response_header.service_result,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Start a task that will periodically send a publish request to keep the subscriptions alive.
+    /// The request rate will be 3/4 of the shortest (revised publishing interval * the revised keep
+    /// alive count) of all subscriptions that belong to a single session.
+    fn spawn_subscription_activity_task(&self) {
+        session_debug!(self, "spawn_subscription_activity_task",);
+
+        let connection_state = {
+            let session_state = trace_read_lock!(self.session_state);
+            session_state.connection_state()
+        };
+
+        const MIN_SUBSCRIPTION_ACTIVITY_MS: u64 = 1000;
+        let session_state = self.session_state.clone();
+        let subscription_state = self.subscription_state.clone();
+
+        let id = format!("subscription-activity-thread-{:?}", thread::current().id());
+        self.runtime_handle.spawn(async move {
+            register_runtime_component!(&id);
+
+            // The timer runs at a higher frequency timer loop to terminate as soon after the session
+            // state has terminated. Each time it runs it will test if the interval has elapsed or not.
+            let mut timer = interval(Duration::from_millis(MIN_SUBSCRIPTION_ACTIVITY_MS));
+
+            let mut last_timeout: Instant;
+            let mut subscription_activity_interval: Duration;
+
+            loop {
+                timer.tick().await;
+
+                if connection_state.is_finished() {
+                    info!("Session activity timer is terminating");
+                    break;
+                }
+
+                if let (Some(keep_alive_timeout), last_publish_request) = {
+                    let subscription_state = trace_read_lock!(subscription_state);
+                    (
+                        subscription_state.keep_alive_timeout(),
+                        subscription_state.last_publish_request(),
                     )
                 } {
                     subscription_activity_interval =
@@ -889,142 +1589,857 @@ impl Session {
                 }
             }
 
-            info!("Subscription activity timer task is finished");
-            deregister_runtime_component!(&id);
-        });
+            info!("Subscription activity timer task is finished");
+            deregister_runtime_component!(&id);
+        });
+    }
+
+    /// This is the internal handler for create subscription that receives the callback wrapped up and reference counted.
+    fn create_subscription_inner(
+        &self,
+        publishing_interval: f64,
+        lifetime_count: u32,
+        max_keep_alive_count: u32,
+        max_notifications_per_publish: u32,
+        priority: u8,
+        publishing_enabled: bool,
+        callback: Arc<Mutex<dyn OnSubscriptionNotification + Send + Sync + 'static>>,
+    ) -> Result<u32, ServiceError> {
+        let request = CreateSubscriptionRequest {
+            request_header: self.make_request_header(),
+            requested_publishing_interval: publishing_interval,
+            requested_lifetime_count: lifetime_count,
+            requested_max_keep_alive_count: max_keep_alive_count,
+            max_notifications_per_publish,
+            publishing_enabled,
+            priority,
+        };
+        let response = self.send_request(request)?;
+        if let SupportedMessage::CreateSubscriptionResponse(response) = response {
+            process_service_result(&response.response_header)?;
+            let subscription = Subscription::new(
+                response.subscription_id,
+                response.revised_publishing_interval,
+                response.revised_lifetime_count,
+                response.revised_max_keep_alive_count,
+                max_notifications_per_publish,
+                publishing_enabled,
+                priority,
+                callback,
+            );
+
+            // Add the new subscription to the subscription state
+            {
+                let mut subscription_state = trace_write_lock!(self.subscription_state);
+                subscription_state.add_subscription(subscription);
+            }
+
+            // Send an async publish request for this new subscription
+            {
+                let mut session_state = trace_write_lock!(self.session_state);
+                let _ = session_state.async_publish();
+            }
+
+            session_debug!(
+                self,
+                "create_subscription, created a subscription with id {}",
+                response.subscription_id
+            );
+            Ok(response.subscription_id)
+        } else {
+            session_error!(self, "create_subscription failed {:?}", response);
+            Err(process_unexpected_response(response))
+        }
+    }
+
+    /// Deletes all subscriptions by sending a [`DeleteSubscriptionsRequest`] to the server with
+    /// ids for all subscriptions.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(u32, StatusCode)>)` - List of (id, status code) result for delete action on each id, `Good` or `BadSubscriptionIdInvalid`
+    /// * `Err(StatusCode)` - Status code reason for failure
+    ///
+    /// [`DeleteSubscriptionsRequest`]: ./struct.DeleteSubscriptionsRequest.html
+    ///
+    pub fn delete_all_subscriptions(&self) -> Result<Vec<(u32, StatusCode)>, StatusCode> {
+        let subscription_ids = {
+            let subscription_state = trace_read_lock!(self.subscription_state);
+            subscription_state.subscription_ids()
+        };
+        if let Some(ref subscription_ids) = subscription_ids {
+            let status_codes = self.delete_subscriptions(subscription_ids.as_slice())?;
+            // Return a list of (id, status_code) for each subscription
+            Ok(subscription_ids
+                .iter()
+                .zip(status_codes)
+                .map(|(id, status_code)| (*id, status_code))
+                .collect())
+        } else {
+            // No subscriptions
+            session_trace!(
+                self,
+                "delete_all_subscriptions, called when there are no subscriptions"
+            );
+            Err(StatusCode::BadNothingToDo)
+        }
+    }
+
+    /// Closes the session and deletes all subscriptions
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - if the session was closed
+    /// * `Err(StatusCode)` - Status code reason for failure
+    ///
+    /// [`CloseSessionRequest`]: ./struct.CloseSessionRequest.html
+    ///
+    pub fn close_session_and_delete_subscriptions(&self) -> Result<(), StatusCode> {
+        if !self.is_connected() {
+            return Err(StatusCode::BadNotConnected);
+        }
+        // for some operations like enumerating endpoints, there is no session equivalent
+        // on the server and it's a local helper object, only. In that case: nothing to do.
+        if trace_read_lock!(self.session_state).session_id().identifier == Identifier::Numeric(0) {
+            return Ok(());
+        }
+        let request = CloseSessionRequest {
+            delete_subscriptions: true,
+            request_header: self.make_request_header(),
+        };
+        let response = self.send_request(request)?;
+        if let SupportedMessage::CloseSessionResponse(_) = response {
+            let mut subscription_state = trace_write_lock!(self.subscription_state);
+            if let Some(subscription_ids) = subscription_state.subscription_ids() {
+                for subscription_id in subscription_ids {
+                    subscription_state.delete_subscription(subscription_id);
+                }
+            }
+            Ok(())
+        } else {
+            session_error!(self, "close_session failed {:?}", response);
+            Err(process_unexpected_response(response).into())
+        }
+    }
+
+    /// Returns the subscription state object
+    pub fn subscription_state(&self) -> Arc<RwLock<SubscriptionState>> {
+        self.subscription_state.clone()
+    }
+
+    /// Enables the client side cache of monitored item values and returns it. Once enabled, every
+    /// data change notification received by the session updates the cache, so the application can
+    /// query or watch the latest value of any monitored item without implementing a callback of its
+    /// own. Calling this more than once returns the same cache.
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<RwLock<MonitoredItemCache>>` - the cache of monitored item values
+    ///
+    pub fn enable_monitored_item_cache(&self) -> Arc<RwLock<MonitoredItemCache>> {
+        let mut subscription_state = trace_write_lock!(self.subscription_state);
+        subscription_state.enable_monitored_item_cache()
+    }
+
+    /// Returns the monitored item cache if it has been enabled with `enable_monitored_item_cache()`.
+    pub fn monitored_item_cache(&self) -> Option<Arc<RwLock<MonitoredItemCache>>> {
+        let subscription_state = trace_read_lock!(self.subscription_state);
+        subscription_state.monitored_item_cache()
+    }
+
+    /// Returns the namespace table of the server, reading its `NamespaceArray` the first time
+    /// it is called. The table is kept for the life of the session and is read again after the
+    /// session reconnects, so use it rather than hardcoding namespace indices, which a server may
+    /// assign differently each time it starts.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NamespaceTable)` - the namespaces of the server
+    /// * `Err(ServiceError)` - the reason the namespace array could not be read
+    ///
+    pub fn namespaces(&self) -> Result<NamespaceTable, ServiceError> {
+        {
+            let session_state = trace_read_lock!(self.session_state);
+            if let Some(namespaces) = session_state.namespaces() {
+                return Ok(namespaces.clone());
+            }
+        }
+        self.refresh_namespaces()
+    }
+
+    /// Reads the `NamespaceArray` of the server again, e.g. because the server has registered a
+    /// new namespace, and returns the new table.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NamespaceTable)` - the namespaces of the server
+    /// * `Err(ServiceError)` - the reason the namespace array could not be read
+    ///
+    pub fn refresh_namespaces(&self) -> Result<NamespaceTable, ServiceError> {
+        let value = self.read_uri_array(VariableId::Server_NamespaceArray)?;
+        if let Some(namespaces) = NamespaceTable::from_variant(&value) {
+            {
+                // Extension objects are decoded against the namespaces of the server
+                let mut secure_channel = trace_write_lock!(self.secure_channel);
+                secure_channel.set_namespaces(namespaces.clone());
+            }
+            let mut session_state = trace_write_lock!(self.session_state);
+            session_state.set_namespaces(Some(namespaces.clone()));
+            Ok(namespaces)
+        } else {
+            session_error!(
+                self,
+                "Server returned a namespace array that is not strings"
+            );
+            Err(StatusCode::BadTypeMismatch.into())
+        }
+    }
+
+    /// Returns the server table of the server, reading its `ServerArray` the first time it is
+    /// called. The table maps the server index of an `ExpandedNodeId` to the URI of the server
+    /// that holds the node. Like the namespace table it is read again after the session
+    /// reconnects.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerTable)` - the servers known to the server
+    /// * `Err(ServiceError)` - the reason the server array could not be read
+    ///
+    pub fn servers(&self) -> Result<ServerTable, ServiceError> {
+        {
+            let session_state = trace_read_lock!(self.session_state);
+            if let Some(servers) = session_state.servers() {
+                return Ok(servers.clone());
+            }
+        }
+        self.refresh_servers()
+    }
+
+    /// Reads the `ServerArray` of the server again and returns the new table.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerTable)` - the servers known to the server
+    /// * `Err(ServiceError)` - the reason the server array could not be read
+    ///
+    pub fn refresh_servers(&self) -> Result<ServerTable, ServiceError> {
+        let value = self.read_uri_array(VariableId::Server_ServerArray)?;
+        if let Some(servers) = ServerTable::from_variant(&value) {
+            let mut session_state = trace_write_lock!(self.session_state);
+            session_state.set_servers(Some(servers.clone()));
+            Ok(servers)
+        } else {
+            session_error!(self, "Server returned a server array that is not strings");
+            Err(StatusCode::BadTypeMismatch.into())
+        }
+    }
+
+    /// Reads the value of the namespace or server array.
+    fn read_uri_array(&self, variable_id: VariableId) -> Result<Variant, ServiceError> {
+        let node_id: NodeId = variable_id.into();
+        let mut results = self.read(&[node_id.into()], TimestampsToReturn::Neither, 0f64)?;
+        if results.len() == 1 {
+            let data_value = results.remove(0);
+            match data_value.status {
+                Some(status) if status.is_bad() => Err(status.into()),
+                _ => Ok(data_value.value.unwrap_or(Variant::Empty)),
+            }
+        } else {
+            Ok(Variant::Empty)
+        }
+    }
+
+    /// Finds the index that the server uses for a namespace URI.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(u16))` - the index of the namespace
+    /// * `Ok(None)` - the server does not have the namespace
+    /// * `Err(ServiceError)` - the reason the namespace array could not be read
+    ///
+    pub fn namespace_index(&self, namespace_uri: &str) -> Result<Option<u16>, ServiceError> {
+        Ok(self.namespaces()?.index_of(namespace_uri))
+    }
+
+    /// Makes a node id in a namespace identified by its URI rather than its index.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NodeId)` - the node id with the index the server uses for the namespace
+    /// * `Err(ServiceError)` - `BadNodeIdUnknown` if the server does not have the namespace, or
+    ///   the reason the namespace array could not be read
+    ///
+    pub fn node_id_in_namespace<T>(
+        &self,
+        namespace_uri: &str,
+        identifier: T,
+    ) -> Result<NodeId, ServiceError>
+    where
+        T: Into<Identifier>,
+    {
+        let node_id = ExpandedNodeId::with_namespace_uri(namespace_uri, identifier);
+        self.namespaces()?
+            .resolve(&node_id)
+            .ok_or_else(|| StatusCode::BadNodeIdUnknown.into())
+    }
+
+    /// Resolves an expanded node id returned by the server, e.g. the target of a reference,
+    /// against the server's `ServerArray` and `NamespaceArray`. The arrays are only read if the
+    /// node id has a server index or a namespace URI.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ResolvedNodeId::Local)` - the node id of a node on this server
+    /// * `Ok(ResolvedNodeId::Remote)` - the URI of the server that holds the node and the node id
+    ///   to use on that server
+    /// * `Err(ServiceError)` - `BadNodeIdUnknown` if the server index or namespace URI is not
+    ///   known to the server, or the reason the arrays could not be read
+    ///
+    pub fn resolve_node_id(
+        &self,
+        node_id: &ExpandedNodeId,
+    ) -> Result<ResolvedNodeId, ServiceError> {
+        if let Some(node_id) = node_id.as_local_node_id() {
+            return Ok(ResolvedNodeId::Local(node_id.clone()));
+        }
+        let resolved = if node_id.is_local() {
+            self.namespaces()?
+                .resolve(node_id)
+                .map(ResolvedNodeId::Local)
+        } else {
+            // The namespaces of a node on another server belong to that server
+            self.servers()?.resolve(node_id, &NamespaceTable::new())
+        };
+        resolved.ok_or_else(|| StatusCode::BadNodeIdUnknown.into())
+    }
+
+    /// Turns a node id into an expanded node id that holds the namespace URI instead of the
+    /// index, e.g. to store it somewhere that outlives the session.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ExpandedNodeId)` - the node id with the namespace URI
+    /// * `Err(ServiceError)` - `BadNodeIdUnknown` if the server does not have the namespace, or
+    ///   the reason the namespace array could not be read
+    ///
+    pub fn expand_node_id(&self, node_id: &NodeId) -> Result<ExpandedNodeId, ServiceError> {
+        self.namespaces()?
+            .expand(node_id)
+            .ok_or_else(|| StatusCode::BadNodeIdUnknown.into())
+    }
+
+    /// Reads the value of a node, a shorthand for [`read`] of its `Value` attribute with both
+    /// timestamps and a max age of 0.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DataValue)` - the value of the node. Its status is bad if the server could not read
+    ///   the value, e.g. `BadNodeIdUnknown`
+    /// * `Err(ServiceError)` - the reason the read failed
+    ///
+    /// [`read`]: ./trait.AttributeService.html#tymethod.read
+    pub fn read_value<T>(&self, node_id: T) -> Result<DataValue, ServiceError>
+    where
+        T: Into<NodeId>,
+    {
+        let mut results = self.read_values(&[node_id.into()])?;
+        Ok(results.remove(0))
+    }
+
+    /// Reads the values of nodes in one request, like [`read_value`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DataValue>)` - the values of the nodes, in the order of `node_ids`
+    /// * `Err(ServiceError)` - the reason the read failed
+    ///
+    /// [`read_value`]: #method.read_value
+    pub fn read_values(&self, node_ids: &[NodeId]) -> Result<Vec<DataValue>, ServiceError> {
+        let nodes_to_read = node_ids.iter().map(ReadValueId::from).collect::<Vec<_>>();
+        let results = self.read(&nodes_to_read, TimestampsToReturn::Both, 0f64)?;
+        if results.len() == node_ids.len() {
+            Ok(results)
+        } else {
+            session_error!(
+                self,
+                "Server returned {} values for {} nodes",
+                results.len(),
+                node_ids.len()
+            );
+            Err(StatusCode::BadUnexpectedError.into())
+        }
+    }
+
+    /// Reads the value of a node that holds a large array or byte string, e.g. an image or a
+    /// waveform, in chunks of `chunk_size` elements or bytes, so that no response exceeds the
+    /// message or array size limits. Each chunk is read with an index range and the chunks are
+    /// joined. An array with several dimensions is joined as an array with one dimension. A
+    /// value that is neither an array nor a byte string is read in one piece.
+    ///
+    /// The chunks are read one after the other, so a value that changes while it is read may be
+    /// joined from chunks of different values. The source timestamp and status of the result
+    /// are those of the first chunk.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DataValue)` - the value of the node. Its status is bad if the server could not read
+    ///   a chunk
+    /// * `Err(ServiceError)` - `BadInvalidArgument` if `chunk_size` is 0, `BadTypeMismatch` if the
+    ///   value changes type while it is read, or the reason a read failed
+    ///
+    pub fn read_value_in_chunks<T>(
+        &self,
+        node_id: T,
+        chunk_size: u32,
+    ) -> Result<DataValue, ServiceError>
+    where
+        T: Into<NodeId>,
+    {
+        if chunk_size == 0 {
+            session_error!(
+                self,
+                "read_value_in_chunks() was called with a chunk size of 0"
+            );
+            return Err(StatusCode::BadInvalidArgument.into());
+        }
+        let node_id = node_id.into();
+        let mut joined: Option<DataValue> = None;
+        let mut offset = 0u32;
+        loop {
+            // A range must span at least 2 elements, so a chunk of 1 is read by its index
+            let last = offset.saturating_add(chunk_size - 1);
+            let index_range = if last == offset {
+                NumericRange::Index(offset)
+            } else {
+                NumericRange::Range(offset, last)
+            };
+            let node_to_read = ReadValueId {
+                index_range: index_range.as_string().into(),
+                ..ReadValueId::from(&node_id)
+            };
+            let chunk = self
+                .read(&[node_to_read], TimestampsToReturn::Both, 0f64)?
+                .pop()
+                .ok_or(StatusCode::BadUnexpectedError)?;
+            match chunk.status {
+                Some(status) if status == StatusCode::BadIndexRangeNoData => {
+                    // A scalar or an empty array has no data in any range, otherwise the length
+                    // of the value is a multiple of the chunk size
+                    return match joined {
+                        Some(joined) => Ok(joined),
+                        None => self.read_value(node_id),
+                    };
+                }
+                Some(status) if status.is_bad() => return Ok(chunk),
+                _ => {}
+            }
+
+            match joined {
+                // A value that is not an array or a byte string, e.g. a string, is read in one
+                // piece
+                None if Self::chunked_value_len(&chunk.value).is_none() => {
+                    return self.read_value(node_id)
+                }
+                None => joined = Some(chunk),
+                Some(ref mut joined) => match (joined.value.as_mut(), chunk.value) {
+                    (Some(Variant::Array(joined)), Some(Variant::Array(chunk))) => {
+                        joined.values.extend(chunk.values)
+                    }
+                    (Some(Variant::ByteString(joined)), Some(Variant::ByteString(chunk))) => {
+                        let mut bytes = joined.value.take().unwrap_or_default();
+                        bytes.extend(chunk.value.unwrap_or_default());
+                        joined.value = Some(bytes);
+                    }
+                    _ => {
+                        session_error!(
+                            self,
+                            "The value of {} changed type while it was read",
+                            node_id
+                        );
+                        return Err(StatusCode::BadTypeMismatch.into());
+                    }
+                },
+            }
+
+            // A chunk that is short of the chunk size is the last
+            let len = joined
+                .as_ref()
+                .and_then(|joined| Self::chunked_value_len(&joined.value))
+                .unwrap_or_default();
+            if len <= last as usize || last == u32::MAX {
+                return Ok(joined.unwrap());
+            }
+            offset = last + 1;
+        }
+    }
+
+    /// Returns the number of elements or bytes of a value that can be read in chunks, or `None`
+    /// if it is not an array or a byte string.
+    fn chunked_value_len(value: &Option<Variant>) -> Option<usize> {
+        match value {
+            Some(Variant::Array(array)) => Some(array.values.len()),
+            Some(Variant::ByteString(bytes)) => Some(bytes.as_ref().len()),
+            _ => None,
+        }
     }
 
-    /// This is the internal handler for create subscription that receives the callback wrapped up and reference counted.
-    fn create_subscription_inner(
+    /// Reads all the attributes of a node in one request.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NodeAttributeValues)` - the attributes of the node. Those that the node does not
+    ///   have are `None`
+    /// * `Err(ServiceError)` - the reason the read failed, e.g. `BadNodeIdUnknown`
+    ///
+    pub fn read_node_attributes<T>(&self, node_id: T) -> Result<NodeAttributeValues, ServiceError>
+    where
+        T: Into<NodeId>,
+    {
+        let node_id = node_id.into();
+        let nodes_to_read = NODE_ATTRIBUTE_IDS
+            .iter()
+            .map(|attribute_id| ReadValueId {
+                attribute_id: *attribute_id as u32,
+                ..ReadValueId::from(&node_id)
+            })
+            .collect::<Vec<_>>();
+        let results = self.read(&nodes_to_read, TimestampsToReturn::Both, 0f64)?;
+        NodeAttributeValues::from_data_values(results, &self.decoding_options()).map_err(|status| {
+            session_error!(
+                self,
+                "Cannot read the attributes of {}, {}",
+                node_id,
+                status
+            );
+            status.into()
+        })
+    }
+
+    /// Reads the raw historical values of a node between two times. The values are read as the
+    /// returned iterator goes, following continuation points until the server has returned all
+    /// of them. The end time may be before the start time to read the values backwards.
+    ///
+    /// # Returns
+    ///
+    /// * `HistoryValues` - an iterator over the values, or over the reason they could not be
+    ///   read, e.g. `BadHistoryOperationUnsupported`
+    ///
+    #[cfg(feature = "history")]
+    pub fn history_read_raw<T>(
         &self,
-        publishing_interval: f64,
-        lifetime_count: u32,
-        max_keep_alive_count: u32,
-        max_notifications_per_publish: u32,
-        priority: u8,
-        publishing_enabled: bool,
-        callback: Arc<Mutex<dyn OnSubscriptionNotification + Send + Sync + 'static>>,
-    ) -> Result<u32, StatusCode> {
-        let request = CreateSubscriptionRequest {
-            request_header: self.make_request_header(),
-            requested_publishing_interval: publishing_interval,
-            requested_lifetime_count: lifetime_count,
-            requested_max_keep_alive_count: max_keep_alive_count,
-            max_notifications_per_publish,
-            publishing_enabled,
-            priority,
+        node_id: T,
+        start_time: DateTime,
+        end_time: DateTime,
+        options: &HistoryReadOptions,
+    ) -> HistoryValues<'_>
+    where
+        T: Into<NodeId>,
+    {
+        let details = ReadRawModifiedDetails {
+            is_read_modified: false,
+            start_time,
+            end_time,
+            num_values_per_node: options.num_values_per_node,
+            return_bounds: options.return_bounds,
         };
-        let response = self.send_request(request)?;
-        if let SupportedMessage::CreateSubscriptionResponse(response) = response {
-            process_service_result(&response.response_header)?;
-            let subscription = Subscription::new(
-                response.subscription_id,
-                response.revised_publishing_interval,
-                response.revised_lifetime_count,
-                response.revised_max_keep_alive_count,
-                max_notifications_per_publish,
-                publishing_enabled,
-                priority,
-                callback,
-            );
+        HistoryValues::new(
+            self,
+            node_id.into(),
+            HistoryReadAction::ReadRawModifiedDetails(details),
+            options.timestamps_to_return,
+        )
+    }
 
-            // Add the new subscription to the subscription state
-            {
-                let mut subscription_state = trace_write_lock!(self.subscription_state);
-                subscription_state.add_subscription(subscription);
-            }
+    /// Reads the values of an aggregate, such as `ObjectId::AggregateFunction_Average`, that the
+    /// server computes from the historical values of a node over each processing interval
+    /// between two times. The server computes them with its default aggregate configuration.
+    ///
+    /// # Returns
+    ///
+    /// * `HistoryValues` - an iterator over the values, one for each interval, or over the
+    ///   reason they could not be read, e.g. `BadAggregateNotSupported`
+    ///
+    #[cfg(feature = "history")]
+    pub fn history_read_processed<T, A>(
+        &self,
+        node_id: T,
+        start_time: DateTime,
+        end_time: DateTime,
+        processing_interval: f64,
+        aggregate_type: A,
+        options: &HistoryReadOptions,
+    ) -> HistoryValues<'_>
+    where
+        T: Into<NodeId>,
+        A: Into<NodeId>,
+    {
+        let details = ReadProcessedDetails {
+            start_time,
+            end_time,
+            processing_interval,
+            aggregate_type: Some(vec![aggregate_type.into()]),
+            aggregate_configuration: AggregateConfiguration {
+                use_server_capabilities_defaults: true,
+                treat_uncertain_as_bad: true,
+                percent_data_bad: 100,
+                percent_data_good: 100,
+                use_sloped_extrapolation: false,
+            },
+        };
+        HistoryValues::new(
+            self,
+            node_id.into(),
+            HistoryReadAction::ReadProcessedDetails(details),
+            options.timestamps_to_return,
+        )
+    }
 
-            // Send an async publish request for this new subscription
-            {
-                let mut session_state = trace_write_lock!(self.session_state);
-                let _ = session_state.async_publish();
-            }
+    /// Reads the historical values of a node at the times, with one value for each time that the
+    /// server interpolates if it recorded no value at that time.
+    ///
+    /// # Returns
+    ///
+    /// * `HistoryValues` - an iterator over the values, in the order of the times, or over the
+    ///   reason they could not be read
+    ///
+    #[cfg(feature = "history")]
+    pub fn history_read_at_time<T>(
+        &self,
+        node_id: T,
+        times: &[DateTime],
+        options: &HistoryReadOptions,
+    ) -> HistoryValues<'_>
+    where
+        T: Into<NodeId>,
+    {
+        let details = ReadAtTimeDetails {
+            req_times: Some(times.to_vec()),
+            use_simple_bounds: options.use_simple_bounds,
+        };
+        HistoryValues::new(
+            self,
+            node_id.into(),
+            HistoryReadAction::ReadAtTimeDetails(details),
+            options.timestamps_to_return,
+        )
+    }
 
-            session_debug!(
+    /// Reads the value of the node that a browse path leads to, translating the path to a node
+    /// id and reading its value like [`read_value`]. The relative path of the browse path can be
+    /// made from a string with `RelativePath::from_str()`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DataValue)` - the value of the node
+    /// * `Err(ServiceError)` - the reason the path could not be translated, e.g. `BadNoMatch` if
+    ///   it leads to no node on this server, or the reason the read failed
+    ///
+    /// [`read_value`]: #method.read_value
+    pub fn read_value_by_browse_path(
+        &self,
+        browse_path: &BrowsePath,
+    ) -> Result<DataValue, ServiceError> {
+        let mut results =
+            self.translate_browse_paths_to_node_ids(std::slice::from_ref(browse_path))?;
+        if results.len() != 1 {
+            session_error!(
                 self,
-                "create_subscription, created a subscription with id {}",
-                response.subscription_id
+                "Server returned {} results for a browse path",
+                results.len()
             );
-            Ok(response.subscription_id)
-        } else {
-            session_error!(self, "create_subscription failed {:?}", response);
-            Err(process_unexpected_response(response))
+            return Err(StatusCode::BadUnexpectedError.into());
+        }
+        let result = results.remove(0);
+        if result.status_code.is_bad() {
+            return Err(result.status_code.into());
         }
+        // A target that the path leads to in part is on another server
+        for target in result.targets.unwrap_or_default() {
+            if target.remaining_path_index == u32::MAX {
+                if let ResolvedNodeId::Local(node_id) = self.resolve_node_id(&target.target_id)? {
+                    return self.read_value(node_id);
+                }
+            }
+        }
+        Err(StatusCode::BadNoMatch.into())
     }
 
-    /// Deletes all subscriptions by sending a [`DeleteSubscriptionsRequest`] to the server with
-    /// ids for all subscriptions.
+    /// Reads the enumeration of a node from its `EnumStrings` or `EnumValues` property, which a
+    /// multi state variable or an enumeration data type has. The enumeration maps the integer
+    /// values read from the variable, or from variables of the data type, back to their text with
+    /// `Enumeration::text_of()`.
     ///
     /// # Returns
     ///
-    /// * `Ok(Vec<(u32, StatusCode)>)` - List of (id, status code) result for delete action on each id, `Good` or `BadSubscriptionIdInvalid`
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Ok(Enumeration)` - the values of the enumeration with their text
+    /// * `Err(ServiceError)` - the reason the enumeration could not be read, e.g. `BadNoMatch` if
+    ///   the node has neither property or `BadTypeMismatch` if the property holds something else
+    pub fn read_enumeration<T>(&self, node_id: T) -> Result<Enumeration, ServiceError>
+    where
+        T: Into<NodeId>,
+    {
+        let node_id = node_id.into();
+        let property_path = |browse_name: &str| BrowsePath {
+            starting_node: node_id.clone(),
+            relative_path: RelativePath {
+                elements: Some(vec![RelativePathElement {
+                    reference_type_id: ReferenceTypeId::HasProperty.into(),
+                    is_inverse: false,
+                    include_subtypes: true,
+                    target_name: QualifiedName::new(0, browse_name),
+                }]),
+            },
+        };
+        let value = match self.read_value_by_browse_path(&property_path("EnumStrings")) {
+            Err(err) if err.status_code() == StatusCode::BadNoMatch => {
+                self.read_value_by_browse_path(&property_path("EnumValues"))?
+            }
+            result => result?,
+        };
+        if value.status().is_bad() {
+            return Err(value.status().into());
+        }
+        value
+            .value
+            .as_ref()
+            .and_then(Enumeration::from_property_value)
+            .ok_or_else(|| StatusCode::BadTypeMismatch.into())
+    }
+
+    /// Writes the value of a node, a shorthand for [`write`] of its `Value` attribute. The value
+    /// is written without timestamps and its type must match the data type of the node, e.g. a
+    /// `Variant::Double` for a `Double` node.
     ///
-    /// [`DeleteSubscriptionsRequest`]: ./struct.DeleteSubscriptionsRequest.html
+    /// # Returns
     ///
-    pub fn delete_all_subscriptions(&self) -> Result<Vec<(u32, StatusCode)>, StatusCode> {
-        let subscription_ids = {
-            let subscription_state = trace_read_lock!(self.subscription_state);
-            subscription_state.subscription_ids()
-        };
-        if let Some(ref subscription_ids) = subscription_ids {
-            let status_codes = self.delete_subscriptions(subscription_ids.as_slice())?;
-            // Return a list of (id, status_code) for each subscription
-            Ok(subscription_ids
-                .iter()
-                .zip(status_codes)
-                .map(|(id, status_code)| (*id, status_code))
-                .collect())
+    /// * `Ok(StatusCode)` - the status of the write. It is bad if the server did not write the
+    ///   value, e.g. `BadTypeMismatch` or `BadUserAccessDenied`
+    /// * `Err(ServiceError)` - the reason the write failed
+    ///
+    /// [`write`]: ./trait.AttributeService.html#tymethod.write
+    pub fn write_value<T, V>(&self, node_id: T, value: V) -> Result<StatusCode, ServiceError>
+    where
+        T: Into<NodeId>,
+        V: Into<Variant>,
+    {
+        let result = self.write_values(vec![(node_id, value)])?;
+        Ok(result.statuses()[0].status_code)
+    }
+
+    /// Writes the values of nodes in one request, like [`write_value`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(WriteValuesResult)` - whether all, some or none of the values were written, with
+    ///   the status of each write in the order of `values`
+    /// * `Err(ServiceError)` - the reason the write failed
+    ///
+    /// [`write_value`]: #method.write_value
+    pub fn write_values<I, T, V>(&self, values: I) -> Result<WriteValuesResult, ServiceError>
+    where
+        I: IntoIterator<Item = (T, V)>,
+        T: Into<NodeId>,
+        V: Into<Variant>,
+    {
+        let nodes_to_write = values
+            .into_iter()
+            .map(|(node_id, value)| WriteValue {
+                node_id: node_id.into(),
+                attribute_id: AttributeId::Value as u32,
+                index_range: UAString::null(),
+                value: DataValue::value_only(value.into()),
+            })
+            .collect::<Vec<_>>();
+        let results = self.write(&nodes_to_write)?;
+        if results.len() == nodes_to_write.len() {
+            let statuses = nodes_to_write
+                .into_iter()
+                .zip(results)
+                .map(|(node_to_write, status_code)| WriteStatus {
+                    node_id: node_to_write.node_id,
+                    status_code,
+                })
+                .collect();
+            Ok(WriteValuesResult::new(statuses))
         } else {
-            // No subscriptions
-            session_trace!(
+            session_error!(
                 self,
-                "delete_all_subscriptions, called when there are no subscriptions"
+                "Server returned {} results for {} writes",
+                results.len(),
+                nodes_to_write.len()
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadUnexpectedError.into())
         }
     }
 
-    /// Closes the session and deletes all subscriptions
+    /// Links monitored items to a triggering item of the same subscription, a shorthand for
+    /// [`set_triggering`]. A linked item in sampling mode reports its queued values, or its last
+    /// value the first time, whenever the triggering item reports a change. A linked item in
+    /// reporting mode is unaffected.
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - if the session was closed
-    /// * `Err(StatusCode)` - Status code reason for failure
+    /// * `Ok(Vec<StatusCode>)` - the status of each link in the order of `triggered_item_ids`. It
+    ///   is `BadMonitoredItemIdInvalid` if the item is unknown or is the triggering item
+    /// * `Err(ServiceError)` - the reason the links were not added, e.g.
+    ///   `BadMonitoredItemIdInvalid` if the triggering item is unknown
     ///
-    /// [`CloseSessionRequest`]: ./struct.CloseSessionRequest.html
+    /// [`set_triggering`]: ./trait.MonitoredItemService.html#tymethod.set_triggering
+    pub fn add_triggered_items(
+        &self,
+        subscription_id: u32,
+        triggering_item_id: u32,
+        triggered_item_ids: &[u32],
+    ) -> Result<Vec<StatusCode>, ServiceError> {
+        let (add_results, _) =
+            self.set_triggering(subscription_id, triggering_item_id, triggered_item_ids, &[])?;
+        Ok(add_results.unwrap_or_default())
+    }
+
+    /// Removes the links from a triggering item to monitored items, which were added with
+    /// [`add_triggered_items`].
     ///
-    pub fn close_session_and_delete_subscriptions(&self) -> Result<(), StatusCode> {
-        if !self.is_connected() {
-            return Err(StatusCode::BadNotConnected);
-        }
-        // for some operations like enumerating endpoints, there is no session equivalent
-        // on the server and it's a local helper object, only. In that case: nothing to do.
-        if trace_read_lock!(self.session_state).session_id().identifier == Identifier::Numeric(0) {
-            return Ok(());
-        }
-        let request = CloseSessionRequest {
-            delete_subscriptions: true,
-            request_header: self.make_request_header(),
-        };
-        let response = self.send_request(request)?;
-        if let SupportedMessage::CloseSessionResponse(_) = response {
-            let mut subscription_state = trace_write_lock!(self.subscription_state);
-            if let Some(subscription_ids) = subscription_state.subscription_ids() {
-                for subscription_id in subscription_ids {
-                    subscription_state.delete_subscription(subscription_id);
-                }
-            }
-            Ok(())
-        } else {
-            session_error!(self, "close_session failed {:?}", response);
-            Err(process_unexpected_response(response))
-        }
+    /// # Returns
+    ///
+    /// * `Ok(Vec<StatusCode>)` - the status of removing each link in the order of
+    ///   `triggered_item_ids`
+    /// * `Err(ServiceError)` - the reason the links were not removed
+    ///
+    /// [`add_triggered_items`]: #method.add_triggered_items
+    pub fn remove_triggered_items(
+        &self,
+        subscription_id: u32,
+        triggering_item_id: u32,
+        triggered_item_ids: &[u32],
+    ) -> Result<Vec<StatusCode>, ServiceError> {
+        let (_, remove_results) =
+            self.set_triggering(subscription_id, triggering_item_id, &[], triggered_item_ids)?;
+        Ok(remove_results.unwrap_or_default())
     }
 
-    /// Returns the subscription state object
-    pub fn subscription_state(&self) -> Arc<RwLock<SubscriptionState>> {
-        self.subscription_state.clone()
+    /// Registers a custom type so that extension objects holding it can be decoded with
+    /// `ExtensionObject::decode_registered()` and the decoding options of the session. The type
+    /// identifies its binary encoding by namespace URI, so the namespace table must be read with
+    /// [`namespaces`] before such extension objects can be recognised.
+    ///
+    /// [`namespaces`]: #method.namespaces
+    pub fn register_type<T>(&self)
+    where
+        T: ExtensionObjectBody + Send + Sync + 'static,
+    {
+        let mut secure_channel = trace_write_lock!(self.secure_channel);
+        let mut type_registry = (*secure_channel.decoding_options().type_registry).clone();
+        type_registry.register::<T>();
+        secure_channel.set_type_registry(type_registry);
+    }
+
+    /// Returns the decoding options of the session. They hold the namespace table of the server
+    /// once it has been read and the registered custom types, which is what
+    /// `ExtensionObject::try_decode()` and `ExtensionObject::decode_registered()` need to decode
+    /// the extension objects the server returns.
+    pub fn decoding_options(&self) -> DecodingOptions {
+        let secure_channel = trace_read_lock!(self.secure_channel);
+        secure_channel.decoding_options()
     }
 
     /// Returns a string identifier for the session
@@ -1082,8 +2497,8 @@ impl Session {
             }
             Some(policy) => {
                 let security_policy = if policy.security_policy_uri.is_null() {
-                    // Assume None
-                    SecurityPolicy::None
+                    // Null means the security policy of the endpoint
+                    self.security_policy()
                 } else {
                     SecurityPolicy::from_uri(policy.security_policy_uri.as_ref())
                 };
@@ -1107,10 +2522,11 @@ impl Session {
                             Ok((identity_token, SignatureData::null()))
                         }
                         IdentityToken::UserName(ref user, ref pass) => {
-                            let secure_channel = trace_read_lock!(self.secure_channel);
-                            let identity_token = self.make_user_name_identity_token(
-                                &secure_channel,
+                            let identity_token = make_user_name_identity_token(
+                                self.security_policy(),
                                 policy,
+                                server_nonce,
+                                server_cert,
                                 user,
                                 pass,
                             )?;
@@ -1173,28 +2589,6 @@ impl Session {
             }
         }
     }
-
-    /// Create a filled in UserNameIdentityToken by using the endpoint's token policy, the current
-    /// secure channel information and the user name and password.
-    fn make_user_name_identity_token(
-        &self,
-        secure_channel: &SecureChannel,
-        user_token_policy: &UserTokenPolicy,
-        user: &str,
-        pass: &str,
-    ) -> Result<UserNameIdentityToken, StatusCode> {
-        let channel_security_policy = secure_channel.security_policy();
-        let nonce = secure_channel.remote_nonce();
-        let cert = secure_channel.remote_cert();
-        make_user_name_identity_token(
-            channel_security_policy,
-            user_token_policy,
-            nonce,
-            &cert,
-            user,
-            pass,
-        )
-    }
 }
 
 impl Service for Session {
@@ -1210,8 +2604,7 @@ impl Service for Session {
     where
         T: Into<SupportedMessage>,
     {
-        let mut session_state = trace_write_lock!(self.session_state);
-        session_state.send_request(request)
+        SessionState::send_request(&self.session_state, request)
     }
 
     // Asynchronously sends a request. The return value is the request handle of the request
@@ -1229,7 +2622,7 @@ impl Service for Session {
 }
 
 impl DiscoveryService for Session {
-    fn find_servers<T>(&self, endpoint_url: T) -> Result<Vec<ApplicationDescription>, StatusCode>
+    fn find_servers<T>(&self, endpoint_url: T) -> Result<Vec<ApplicationDescription>, ServiceError>
     where
         T: Into<UAString>,
     {
@@ -1253,7 +2646,7 @@ impl DiscoveryService for Session {
         }
     }
 
-    fn get_endpoints(&self) -> Result<Vec<EndpointDescription>, StatusCode> {
+    fn get_endpoints(&self) -> Result<Vec<EndpointDescription>, ServiceError> {
         session_debug!(self, "get_endpoints");
         let endpoint_url = self.session_info.endpoint.endpoint_url.clone();
 
@@ -1283,7 +2676,7 @@ impl DiscoveryService for Session {
         }
     }
 
-    fn register_server(&self, server: RegisteredServer) -> Result<(), StatusCode> {
+    fn register_server(&self, server: RegisteredServer) -> Result<(), ServiceError> {
         let request = RegisterServerRequest {
             request_header: self.make_request_header(),
             server,
@@ -1299,24 +2692,37 @@ impl DiscoveryService for Session {
 }
 
 impl SecureChannelService for Session {
-    fn open_secure_channel(&self) -> Result<(), StatusCode> {
+    fn open_secure_channel(&self) -> Result<(), ServiceError> {
         session_debug!(self, "open_secure_channel");
         let mut session_state = trace_write_lock!(self.session_state);
         session_state.issue_or_renew_secure_channel(SecurityTokenRequestType::Issue)
     }
 
-    fn close_secure_channel(&self) -> Result<(), StatusCode> {
+    fn close_secure_channel(&self) -> Result<(), ServiceError> {
         let request = CloseSecureChannelRequest {
             request_header: self.make_request_header(),
         };
         // We do not wait for a response because there may not be one. Just return
         let _ = self.async_send_request(request, None);
+        #[cfg(feature = "tracing")]
+        tracing::info!("secure channel closed");
         Ok(())
     }
 }
 
 impl SessionService for Session {
-    fn create_session(&self) -> Result<NodeId, StatusCode> {
+    fn create_session(&self) -> Result<NodeId, ServiceError> {
+        if self.secure_sessions_only {
+            let security_policy = trace_read_lock!(self.secure_channel).security_policy();
+            if security_policy == SecurityPolicy::None {
+                session_error!(
+                    self,
+                    "Session is not created because its secure channel has security policy None"
+                );
+                return Err(StatusCode::BadSecurityPolicyRejected.into());
+            }
+        }
+
         // Get some state stuff
         let endpoint_url = self.session_info.endpoint.endpoint_url.clone();
 
@@ -1328,16 +2734,18 @@ impl SessionService for Session {
         let server_uri = UAString::null();
         let session_name = self.session_name.clone();
 
-        let (client_certificate, _) = {
+        // Security, the certificate is followed by the certificates of the CAs that issued it
+        let client_certificate = {
             let certificate_store = trace_write_lock!(self.certificate_store);
-            certificate_store.read_own_cert_and_pkey_optional()
-        };
-
-        // Security
-        let client_certificate = if let Some(ref client_certificate) = client_certificate {
-            client_certificate.as_byte_string()
-        } else {
-            ByteString::null()
+            let (client_certificate, _) = certificate_store.read_own_cert_and_pkey_optional();
+            if let Some(client_certificate) = client_certificate {
+                let issuer_certs = certificate_store.issuer_certs(&client_certificate);
+                let mut client_certificate_chain = vec![client_certificate];
+                client_certificate_chain.extend(issuer_certs);
+                crypto::X509::chain_as_byte_string(&client_certificate_chain)
+            } else {
+                ByteString::null()
+            }
         };
 
         // Requested session timeout should be larger than your expected subscription rate.
@@ -1368,10 +2776,9 @@ impl SessionService for Session {
                 let mut session_state = trace_write_lock!(self.session_state);
                 session_state.set_session_id(response.session_id.clone());
                 session_state.set_authentication_token(response.authentication_token.clone());
+                session_state.set_server_nonce(response.server_nonce.clone());
                 {
                     let mut secure_channel = trace_write_lock!(self.secure_channel);
-                    let _ =
-                        secure_channel.set_remote_nonce_from_byte_string(&response.server_nonce);
                     let _ = secure_channel
                         .set_remote_cert_from_byte_string(&response.server_certificate);
                 }
@@ -1390,23 +2797,31 @@ impl SessionService for Session {
             // The server certificate is validated if the policy requires it
             let security_policy = self.security_policy();
             let cert_status_code = if security_policy != SecurityPolicy::None {
-                if let Ok(server_certificate) =
-                    crypto::X509::from_byte_string(&response.server_certificate)
+                if let Ok(server_certificate_chain) =
+                    crypto::X509::chain_from_byte_string(&response.server_certificate)
                 {
                     // Validate server certificate against hostname and application_uri
-                    let hostname =
-                        hostname_from_url(self.session_info.endpoint.endpoint_url.as_ref())
+                    let endpoint_url =
+                        EndpointUrl::from_str(self.session_info.endpoint.endpoint_url.as_ref())
                             .map_err(|_| StatusCode::BadUnexpectedError)?;
+                    // The host of a local socket url is the path of the socket, not a hostname
+                    let hostname = if endpoint_url.is_opc_unix() {
+                        None
+                    } else {
+                        Some(endpoint_url.host())
+                    };
                     let application_uri =
                         self.session_info.endpoint.server.application_uri.as_ref();
 
                     let certificate_store = trace_write_lock!(self.certificate_store);
-                    let result = certificate_store.validate_or_reject_application_instance_cert(
-                        &server_certificate,
-                        security_policy,
-                        Some(&hostname),
-                        Some(application_uri),
-                    );
+                    let result = certificate_store
+                        .validate_or_reject_application_instance_cert_chain(
+                            &server_certificate_chain,
+                            security_policy,
+                            hostname,
+                            Some(application_uri),
+                            Some(self.session_info.endpoint.endpoint_url.as_ref()),
+                        );
                     if result.is_bad() {
                         result
                     } else {
@@ -1422,7 +2837,7 @@ impl SessionService for Session {
 
             if !cert_status_code.is_good() {
                 session_error!(self, "Server's certificate was rejected");
-                Err(cert_status_code)
+                Err(cert_status_code.into())
             } else {
                 // Spawn a task to ping the server to keep the connection alive before the session
                 // timeout period.
@@ -1443,11 +2858,18 @@ impl SessionService for Session {
         }
     }
 
-    fn activate_session(&self) -> Result<(), StatusCode> {
-        let (user_identity_token, user_token_signature) = {
+    fn activate_session(&self) -> Result<(), ServiceError> {
+        // The server nonce is the one from the last time the session was created or activated
+        let server_nonce = {
+            let session_state = trace_read_lock!(self.session_state);
+            session_state.server_nonce().clone()
+        };
+        let server_cert = {
             let secure_channel = trace_read_lock!(self.secure_channel);
-            self.user_identity_token(&secure_channel.remote_cert(), secure_channel.remote_nonce())?
+            secure_channel.remote_cert()
         };
+        let (user_identity_token, user_token_signature) =
+            self.user_identity_token(&server_cert, server_nonce.as_ref())?;
 
         let locale_ids = if self.session_info.preferred_locales.is_empty() {
             None
@@ -1465,10 +2887,6 @@ impl SessionService for Session {
         let client_signature = match security_policy {
             SecurityPolicy::None => SignatureData::null(),
             _ => {
-                let secure_channel = trace_read_lock!(self.secure_channel);
-                let server_cert = secure_channel.remote_cert();
-                let server_nonce = secure_channel.remote_nonce();
-
                 let (_, client_pkey) = {
                     let certificate_store = trace_write_lock!(self.certificate_store);
                     certificate_store.read_own_cert_and_pkey_optional()
@@ -1477,27 +2895,22 @@ impl SessionService for Session {
                 // Create a signature data
                 if client_pkey.is_none() {
                     session_error!(self, "Cannot create client signature - no pkey!");
-                    return Err(StatusCode::BadUnexpectedError);
+                    return Err(StatusCode::BadUnexpectedError.into());
                 } else if server_cert.is_none() {
                     session_error!(
                         self,
                         "Cannot sign server certificate because server cert is null"
                     );
-                    return Err(StatusCode::BadUnexpectedError);
-                } else if server_nonce.is_empty() {
+                    return Err(StatusCode::BadUnexpectedError.into());
+                } else if server_nonce.is_null_or_empty() {
                     session_error!(
                         self,
                         "Cannot sign server certificate because server nonce is empty"
                     );
-                    return Err(StatusCode::BadUnexpectedError);
+                    return Err(StatusCode::BadUnexpectedError.into());
                 }
 
-                let server_cert = secure_channel
-                    .remote_cert()
-                    .as_ref()
-                    .unwrap()
-                    .as_byte_string();
-                let server_nonce = ByteString::from(secure_channel.remote_nonce());
+                let server_cert = server_cert.as_ref().unwrap().as_byte_string();
                 let signing_key = client_pkey.as_ref().unwrap();
                 crypto::create_signature_data(
                     signing_key,
@@ -1525,13 +2938,18 @@ impl SessionService for Session {
         if let SupportedMessage::ActivateSessionResponse(response) = response {
             // trace!("ActivateSessionResponse = {:#?}", response);
             process_service_result(&response.response_header)?;
+            {
+                let mut session_state = trace_write_lock!(self.session_state);
+                session_state.set_server_nonce(response.server_nonce.clone());
+            }
+            self.spawn_keep_alive_task();
             Ok(())
         } else {
             Err(process_unexpected_response(response))
         }
     }
 
-    fn cancel(&self, request_handle: IntegerId) -> Result<u32, StatusCode> {
+    fn cancel(&self, request_handle: IntegerId) -> Result<u32, ServiceError> {
         let request = CancelRequest {
             request_header: self.make_request_header(),
             request_handle,
@@ -1556,7 +2974,7 @@ impl SubscriptionService for Session {
         priority: u8,
         publishing_enabled: bool,
         callback: CB,
-    ) -> Result<u32, StatusCode>
+    ) -> Result<u32, ServiceError>
     where
         CB: OnSubscriptionNotification + Send + Sync + 'static,
     {
@@ -1579,13 +2997,13 @@ impl SubscriptionService for Session {
         max_keep_alive_count: u32,
         max_notifications_per_publish: u32,
         priority: u8,
-    ) -> Result<(), StatusCode> {
+    ) -> Result<(), ServiceError> {
         if subscription_id == 0 {
             session_error!(self, "modify_subscription, subscription id must be non-zero, or the subscription is considered invalid");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if !self.subscription_exists(subscription_id) {
             session_error!(self, "modify_subscription, subscription id does not exist");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else {
             let request = ModifySubscriptionRequest {
                 request_header: self.make_request_header(),
@@ -1621,7 +3039,7 @@ impl SubscriptionService for Session {
         &self,
         subscription_ids: &[u32],
         publishing_enabled: bool,
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         session_debug!(
             self,
             "set_publishing_mode, for subscriptions {:?}, publishing enabled {}",
@@ -1634,7 +3052,7 @@ impl SubscriptionService for Session {
                 self,
                 "set_publishing_mode, no subscription ids were provided"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = SetPublishingModeRequest {
                 request_header: self.make_request_header(),
@@ -1662,14 +3080,14 @@ impl SubscriptionService for Session {
         &self,
         subscription_ids: &[u32],
         send_initial_values: bool,
-    ) -> Result<Vec<TransferResult>, StatusCode> {
+    ) -> Result<Vec<TransferResult>, ServiceError> {
         if subscription_ids.is_empty() {
             // No subscriptions
             session_error!(
                 self,
                 "set_publishing_mode, no subscription ids were provided"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = TransferSubscriptionsRequest {
                 request_header: self.make_request_header(),
@@ -1688,17 +3106,17 @@ impl SubscriptionService for Session {
         }
     }
 
-    fn delete_subscription(&self, subscription_id: u32) -> Result<StatusCode, StatusCode> {
+    fn delete_subscription(&self, subscription_id: u32) -> Result<StatusCode, ServiceError> {
         if subscription_id == 0 {
             session_error!(self, "delete_subscription, subscription id 0 is invalid");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if !self.subscription_exists(subscription_id) {
             session_error!(
                 self,
                 "delete_subscription, subscription id {} does not exist",
                 subscription_id
             );
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else {
             let result = self.delete_subscriptions(&[subscription_id][..])?;
             Ok(result[0])
@@ -1708,11 +3126,11 @@ impl SubscriptionService for Session {
     fn delete_subscriptions(
         &self,
         subscription_ids: &[u32],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         if subscription_ids.is_empty() {
             // No subscriptions
             session_trace!(self, "delete_subscriptions with no subscriptions");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             // Send a delete request holding all the subscription ides that we wish to delete
             let request = DeleteSubscriptionsRequest {
@@ -1739,11 +3157,15 @@ impl SubscriptionService for Session {
     }
 }
 
+#[cfg(feature = "node-management")]
 impl NodeManagementService for Session {
-    fn add_nodes(&self, nodes_to_add: &[AddNodesItem]) -> Result<Vec<AddNodesResult>, StatusCode> {
+    fn add_nodes(
+        &self,
+        nodes_to_add: &[AddNodesItem],
+    ) -> Result<Vec<AddNodesResult>, ServiceError> {
         if nodes_to_add.is_empty() {
             session_error!(self, "add_nodes, called with no nodes to add");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = AddNodesRequest {
                 request_header: self.make_request_header(),
@@ -1761,10 +3183,10 @@ impl NodeManagementService for Session {
     fn add_references(
         &self,
         references_to_add: &[AddReferencesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         if references_to_add.is_empty() {
             session_error!(self, "add_references, called with no references to add");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = AddReferencesRequest {
                 request_header: self.make_request_header(),
@@ -1782,10 +3204,10 @@ impl NodeManagementService for Session {
     fn delete_nodes(
         &self,
         nodes_to_delete: &[DeleteNodesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         if nodes_to_delete.is_empty() {
             session_error!(self, "delete_nodes, called with no nodes to delete");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = DeleteNodesRequest {
                 request_header: self.make_request_header(),
@@ -1803,13 +3225,13 @@ impl NodeManagementService for Session {
     fn delete_references(
         &self,
         references_to_delete: &[DeleteReferencesItem],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         if references_to_delete.is_empty() {
             session_error!(
                 self,
                 "delete_references, called with no references to delete"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = DeleteReferencesRequest {
                 request_header: self.make_request_header(),
@@ -1831,7 +3253,7 @@ impl MonitoredItemService for Session {
         subscription_id: u32,
         timestamps_to_return: TimestampsToReturn,
         items_to_create: &[MonitoredItemCreateRequest],
-    ) -> Result<Vec<MonitoredItemCreateResult>, StatusCode> {
+    ) -> Result<Vec<MonitoredItemCreateResult>, ServiceError> {
         session_debug!(
             self,
             "create_monitored_items, for subscription {}, {} items",
@@ -1840,20 +3262,20 @@ impl MonitoredItemService for Session {
         );
         if subscription_id == 0 {
             session_error!(self, "create_monitored_items, subscription id 0 is invalid");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if !self.subscription_exists(subscription_id) {
             session_error!(
                 self,
                 "create_monitored_items, subscription id {} does not exist",
                 subscription_id
             );
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if items_to_create.is_empty() {
             session_error!(
                 self,
                 "create_monitored_items, called with no items to create"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             // Assign each item a unique client handle
             let mut items_to_create = items_to_create.to_vec();
@@ -1921,7 +3343,7 @@ impl MonitoredItemService for Session {
         subscription_id: u32,
         timestamps_to_return: TimestampsToReturn,
         items_to_modify: &[MonitoredItemModifyRequest],
-    ) -> Result<Vec<MonitoredItemModifyResult>, StatusCode> {
+    ) -> Result<Vec<MonitoredItemModifyResult>, ServiceError> {
         session_debug!(
             self,
             "modify_monitored_items, for subscription {}, {} items",
@@ -1930,20 +3352,20 @@ impl MonitoredItemService for Session {
         );
         if subscription_id == 0 {
             session_error!(self, "modify_monitored_items, subscription id 0 is invalid");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if !self.subscription_exists(subscription_id) {
             session_error!(
                 self,
                 "modify_monitored_items, subscription id {} does not exist",
                 subscription_id
             );
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if items_to_modify.is_empty() {
             session_error!(
                 self,
                 "modify_monitored_items, called with no items to modify"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let monitored_item_ids = items_to_modify
                 .iter()
@@ -1989,10 +3411,10 @@ impl MonitoredItemService for Session {
         subscription_id: u32,
         monitoring_mode: MonitoringMode,
         monitored_item_ids: &[u32],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         if monitored_item_ids.is_empty() {
             session_error!(self, "set_monitoring_mode, called with nothing to do");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = {
                 let monitored_item_ids = Some(monitored_item_ids.to_vec());
@@ -2019,10 +3441,10 @@ impl MonitoredItemService for Session {
         triggering_item_id: u32,
         links_to_add: &[u32],
         links_to_remove: &[u32],
-    ) -> Result<(Option<Vec<StatusCode>>, Option<Vec<StatusCode>>), StatusCode> {
+    ) -> Result<SetTriggeringResults, ServiceError> {
         if links_to_add.is_empty() && links_to_remove.is_empty() {
             session_error!(self, "set_triggering, called with nothing to add or remove");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = {
                 let links_to_add = if links_to_add.is_empty() {
@@ -2045,12 +3467,22 @@ impl MonitoredItemService for Session {
             };
             let response = self.send_request(request)?;
             if let SupportedMessage::SetTriggeringResponse(response) = response {
-                // Update client side state
+                // Update client side state with the links that the server added, so they are
+                // recreated on reconnect
+                let links_added = match response.add_results {
+                    Some(ref add_results) => links_to_add
+                        .iter()
+                        .zip(add_results.iter())
+                        .filter(|(_, status_code)| status_code.is_good())
+                        .map(|(link, _)| *link)
+                        .collect::<Vec<u32>>(),
+                    None => Vec::new(),
+                };
                 let mut subscription_state = trace_write_lock!(self.subscription_state);
                 subscription_state.set_triggering(
                     subscription_id,
                     triggering_item_id,
-                    links_to_add,
+                    &links_added,
                     links_to_remove,
                 );
                 Ok((response.add_results, response.remove_results))
@@ -2065,7 +3497,7 @@ impl MonitoredItemService for Session {
         &self,
         subscription_id: u32,
         items_to_delete: &[u32],
-    ) -> Result<Vec<StatusCode>, StatusCode> {
+    ) -> Result<Vec<StatusCode>, ServiceError> {
         session_debug!(
             self,
             "delete_monitored_items, subscription {} for {} items",
@@ -2074,20 +3506,20 @@ impl MonitoredItemService for Session {
         );
         if subscription_id == 0 {
             session_error!(self, "delete_monitored_items, subscription id 0 is invalid");
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if !self.subscription_exists(subscription_id) {
             session_error!(
                 self,
                 "delete_monitored_items, subscription id {} does not exist",
                 subscription_id
             );
-            Err(StatusCode::BadInvalidArgument)
+            Err(StatusCode::BadInvalidArgument.into())
         } else if items_to_delete.is_empty() {
             session_error!(
                 self,
                 "delete_monitored_items, called with no items to delete"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = DeleteMonitoredItemsRequest {
                 request_header: self.make_request_header(),
@@ -2115,10 +3547,10 @@ impl ViewService for Session {
     fn browse(
         &self,
         nodes_to_browse: &[BrowseDescription],
-    ) -> Result<Option<Vec<BrowseResult>>, StatusCode> {
+    ) -> Result<Option<Vec<BrowseResult>>, ServiceError> {
         if nodes_to_browse.is_empty() {
             session_error!(self, "browse, was not supplied with any nodes to browse");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = BrowseRequest {
                 request_header: self.make_request_header(),
@@ -2146,9 +3578,9 @@ impl ViewService for Session {
         &self,
         release_continuation_points: bool,
         continuation_points: &[ByteString],
-    ) -> Result<Option<Vec<BrowseResult>>, StatusCode> {
+    ) -> Result<Option<Vec<BrowseResult>>, ServiceError> {
         if continuation_points.is_empty() {
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = BrowseNextRequest {
                 request_header: self.make_request_header(),
@@ -2170,13 +3602,13 @@ impl ViewService for Session {
     fn translate_browse_paths_to_node_ids(
         &self,
         browse_paths: &[BrowsePath],
-    ) -> Result<Vec<BrowsePathResult>, StatusCode> {
+    ) -> Result<Vec<BrowsePathResult>, ServiceError> {
         if browse_paths.is_empty() {
             session_error!(
                 self,
                 "translate_browse_paths_to_node_ids, was not supplied with any browse paths"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = TranslateBrowsePathsToNodeIdsRequest {
                 request_header: self.make_request_header(),
@@ -2198,13 +3630,13 @@ impl ViewService for Session {
         }
     }
 
-    fn register_nodes(&self, nodes_to_register: &[NodeId]) -> Result<Vec<NodeId>, StatusCode> {
+    fn register_nodes(&self, nodes_to_register: &[NodeId]) -> Result<Vec<NodeId>, ServiceError> {
         if nodes_to_register.is_empty() {
             session_error!(
                 self,
                 "register_nodes, was not supplied with any nodes to register"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = RegisterNodesRequest {
                 request_header: self.make_request_header(),
@@ -2222,13 +3654,13 @@ impl ViewService for Session {
         }
     }
 
-    fn unregister_nodes(&self, nodes_to_unregister: &[NodeId]) -> Result<(), StatusCode> {
+    fn unregister_nodes(&self, nodes_to_unregister: &[NodeId]) -> Result<(), ServiceError> {
         if nodes_to_unregister.is_empty() {
             session_error!(
                 self,
                 "unregister_nodes, was not supplied with any nodes to unregister"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = UnregisterNodesRequest {
                 request_header: self.make_request_header(),
@@ -2248,7 +3680,7 @@ impl ViewService for Session {
 }
 
 impl MethodService for Session {
-    fn call<T>(&self, method: T) -> Result<CallMethodResult, StatusCode>
+    fn call<T>(&self, method: T) -> Result<CallMethodResult, ServiceError>
     where
         T: Into<CallMethodRequest>,
     {
@@ -2267,7 +3699,7 @@ impl MethodService for Session {
                         "call(), expecting a result from the call to the server, got {} results",
                         results.len()
                     );
-                    Err(StatusCode::BadUnexpectedError)
+                    Err(StatusCode::BadUnexpectedError.into())
                 } else {
                     Ok(results.remove(0))
                 }
@@ -2276,7 +3708,7 @@ impl MethodService for Session {
                     self,
                     "call(), expecting a result from the call to the server, got nothing"
                 );
-                Err(StatusCode::BadUnexpectedError)
+                Err(StatusCode::BadUnexpectedError.into())
             }
         } else {
             Err(process_unexpected_response(response))
@@ -2290,11 +3722,11 @@ impl AttributeService for Session {
         nodes_to_read: &[ReadValueId],
         timestamps_to_return: TimestampsToReturn,
         max_age: f64,
-    ) -> Result<Vec<DataValue>, StatusCode> {
+    ) -> Result<Vec<DataValue>, ServiceError> {
         if nodes_to_read.is_empty() {
             // No subscriptions
             session_error!(self, "read(), was not supplied with any nodes to read");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             session_debug!(self, "read() requested to read nodes {:?}", nodes_to_read);
             let request = ReadRequest {
@@ -2320,13 +3752,14 @@ impl AttributeService for Session {
         }
     }
 
+    #[cfg(feature = "history")]
     fn history_read(
         &self,
         history_read_details: HistoryReadAction,
         timestamps_to_return: TimestampsToReturn,
         release_continuation_points: bool,
         nodes_to_read: &[HistoryReadValueId],
-    ) -> Result<Vec<HistoryReadResult>, StatusCode> {
+    ) -> Result<Vec<HistoryReadResult>, ServiceError> {
         // Turn the enum into an extension object
         let history_read_details = ExtensionObject::from(history_read_details);
         let request = HistoryReadRequest {
@@ -2361,11 +3794,11 @@ impl AttributeService for Session {
         }
     }
 
-    fn write(&self, nodes_to_write: &[WriteValue]) -> Result<Vec<StatusCode>, StatusCode> {
+    fn write(&self, nodes_to_write: &[WriteValue]) -> Result<Vec<StatusCode>, ServiceError> {
         if nodes_to_write.is_empty() {
             // No subscriptions
             session_error!(self, "write() was not supplied with any nodes to write");
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             let request = WriteRequest {
                 request_header: self.make_request_header(),
@@ -2383,22 +3816,23 @@ impl AttributeService for Session {
         }
     }
 
+    #[cfg(feature = "history")]
     fn history_update(
         &self,
         history_update_details: &[HistoryUpdateAction],
-    ) -> Result<Vec<HistoryUpdateResult>, StatusCode> {
+    ) -> Result<Vec<HistoryUpdateResult>, ServiceError> {
         if history_update_details.is_empty() {
             // No subscriptions
             session_error!(
                 self,
                 "history_update(), was not supplied with any detail to update"
             );
-            Err(StatusCode::BadNothingToDo)
+            Err(StatusCode::BadNothingToDo.into())
         } else {
             // Turn the enums into ExtensionObjects
             let history_update_details = history_update_details
                 .iter()
-                .map(|action|ExtensionObject::from(action))
+                .map(ExtensionObject::from)
                 .collect::<Vec<ExtensionObject>>();
 
             let request = HistoryUpdateRequest {
@@ -3,9 +3,10 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 use std::{
+    cell::Cell,
     sync::{
         atomic::{AtomicU32, Ordering},
-        mpsc::{self, Receiver, SyncSender},
+        mpsc::{self, SyncSender},
         Arc,
     },
     u32,
@@ -17,9 +18,12 @@ use tokio::time::Instant;
 use crate::{
     client::{
         callbacks::{OnConnectionStatusChange, OnSessionClosed},
+        config::ClientConfig,
+        error::ServiceError,
         message_queue::MessageQueue,
         process_unexpected_response,
-        session::{session_debug, session_trace},
+        request_window::RequestWindow,
+        session::{pending_response::PendingResponse, session_debug, session_trace},
         subscription_state::SubscriptionState,
     },
     core::{
@@ -30,6 +34,67 @@ use crate::{
     types::{status_code::StatusCode, *},
 };
 
+thread_local! {
+    /// A request timeout that replaces the one of the session for requests made on this thread
+    static REQUEST_TIMEOUT_OVERRIDE: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Calls the function with the request timeout replacing the one of the session for any request
+/// sent by this thread while the function runs.
+pub(crate) fn with_request_timeout<F, R>(request_timeout: u32, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    /// Restores the previous override when dropped, even if the function panics
+    struct RestoreOverride(Option<u32>);
+
+    impl Drop for RestoreOverride {
+        fn drop(&mut self) {
+            REQUEST_TIMEOUT_OVERRIDE.with(|o| o.set(self.0));
+        }
+    }
+
+    let _restore =
+        RestoreOverride(REQUEST_TIMEOUT_OVERRIDE.with(|o| o.replace(Some(request_timeout))));
+    f()
+}
+
+/// Returns the operation level diagnostic infos of a response
+fn operation_diagnostic_infos(response: &SupportedMessage) -> Option<&[DiagnosticInfo]> {
+    let diagnostic_infos = match response {
+        SupportedMessage::ActivateSessionResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "node-management")]
+        SupportedMessage::AddNodesResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "node-management")]
+        SupportedMessage::AddReferencesResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "node-management")]
+        SupportedMessage::DeleteNodesResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "node-management")]
+        SupportedMessage::DeleteReferencesResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::BrowseResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::BrowseNextResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::TranslateBrowsePathsToNodeIdsResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::ReadResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "history")]
+        SupportedMessage::HistoryReadResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::WriteResponse(r) => &r.diagnostic_infos,
+        #[cfg(feature = "history")]
+        SupportedMessage::HistoryUpdateResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::CallResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::CreateMonitoredItemsResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::ModifyMonitoredItemsResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::SetMonitoringModeResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::DeleteMonitoredItemsResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::SetPublishingModeResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::DeleteSubscriptionsResponse(r) => &r.diagnostic_infos,
+        SupportedMessage::TransferSubscriptionsResponse(r) => &r.diagnostic_infos,
+        // Only the diagnostics of the links that are added
+        SupportedMessage::SetTriggeringResponse(r) => &r.add_diagnostic_infos,
+        _ => return None,
+    };
+    diagnostic_infos.as_deref()
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum ConnectionState {
     /// No connect has been made yet
@@ -108,6 +173,16 @@ pub(crate) struct SessionState {
     /// The request timeout is how long the session will wait from sending a request expecting a response
     /// if no response is received the client will terminate.
     request_timeout: u32,
+    /// Limits how many synchronous requests are outstanding at once
+    request_window: Arc<RequestWindow>,
+    /// The diagnostics that the server is asked to return with each response
+    return_diagnostics: DiagnosticBits,
+    /// The diagnostics returned with the last response
+    last_response_diagnostics: Option<ResponseDiagnostics>,
+    /// The namespace table of the server, read the first time it is needed
+    namespaces: Option<NamespaceTable>,
+    /// The server table of the server, read the first time it is needed
+    servers: Option<ServerTable>,
     /// Size of the send buffer
     send_buffer_size: usize,
     /// Size of the
@@ -120,6 +195,9 @@ pub(crate) struct SessionState {
     session_id: NodeId,
     /// The session authentication token, used for session activation
     authentication_token: NodeId,
+    /// The nonce that the server last returned from creating or activating the session, which
+    /// the next activation signs and encrypts the user's password with
+    server_nonce: ByteString,
     /// The next handle to assign to a request
     request_handle: Handle,
     /// Next monitored item client side handle
@@ -155,10 +233,8 @@ impl SessionState {
     const FIRST_REQUEST_HANDLE: u32 = 1;
     const FIRST_MONITORED_ITEM_HANDLE: u32 = 1000;
 
-    const DEFAULT_REQUEST_TIMEOUT: u32 = 10 * 1000;
     const SEND_BUFFER_SIZE: usize = 65535;
     const RECEIVE_BUFFER_SIZE: usize = 65535;
-    const MAX_BUFFER_SIZE: usize = 65535;
 
     pub fn new(
         ignore_clock_skew: bool,
@@ -166,20 +242,30 @@ impl SessionState {
         subscription_state: Arc<RwLock<SubscriptionState>>,
     ) -> SessionState {
         let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+        // The hello asks the server for messages no larger than the client decodes
+        let decoding_options = trace_read_lock!(secure_channel).decoding_options();
         SessionState {
             id,
             client_offset: Duration::zero(),
             ignore_clock_skew,
             secure_channel,
             connection_state: ConnectionStateMgr::new(),
-            request_timeout: Self::DEFAULT_REQUEST_TIMEOUT,
+            request_timeout: ClientConfig::DEFAULT_REQUEST_TIMEOUT,
+            request_window: Arc::new(RequestWindow::new(
+                ClientConfig::DEFAULT_MAX_INFLIGHT_REQUESTS,
+            )),
+            return_diagnostics: DiagnosticBits::empty(),
+            last_response_diagnostics: None,
+            namespaces: None,
+            servers: None,
             send_buffer_size: Self::SEND_BUFFER_SIZE,
             receive_buffer_size: Self::RECEIVE_BUFFER_SIZE,
-            max_message_size: Self::MAX_BUFFER_SIZE,
-            max_chunk_count: constants::MAX_CHUNK_COUNT,
+            max_message_size: decoding_options.max_message_size,
+            max_chunk_count: decoding_options.max_chunk_count,
             request_handle: Handle::new(Self::FIRST_REQUEST_HANDLE),
             session_id: NodeId::null(),
             authentication_token: NodeId::null(),
+            server_nonce: ByteString::null(),
             monitored_item_handle: Handle::new(Self::FIRST_MONITORED_ITEM_HANDLE),
             subscription_acknowledgements: Vec::new(),
             subscription_state,
@@ -218,8 +304,46 @@ impl SessionState {
         self.max_chunk_count
     }
 
+    /// Returns the request timeout for requests made by this thread, which is the session's
+    /// unless it is overridden.
     pub fn request_timeout(&self) -> u32 {
-        self.request_timeout
+        REQUEST_TIMEOUT_OVERRIDE
+            .with(|o| o.get())
+            .unwrap_or(self.request_timeout)
+    }
+
+    pub fn set_request_timeout(&mut self, request_timeout: u32) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Sets how many synchronous requests may be outstanding at once, 0 meaning no limit.
+    /// Requests that are already outstanding keep their slots in the previous window.
+    pub fn set_max_inflight_requests(&mut self, max_inflight_requests: usize) {
+        self.request_window = Arc::new(RequestWindow::new(max_inflight_requests));
+    }
+
+    pub fn set_return_diagnostics(&mut self, return_diagnostics: DiagnosticBits) {
+        self.return_diagnostics = return_diagnostics;
+    }
+
+    pub fn last_response_diagnostics(&self) -> Option<ResponseDiagnostics> {
+        self.last_response_diagnostics.clone()
+    }
+
+    pub(crate) fn namespaces(&self) -> Option<&NamespaceTable> {
+        self.namespaces.as_ref()
+    }
+
+    pub(crate) fn set_namespaces(&mut self, namespaces: Option<NamespaceTable>) {
+        self.namespaces = namespaces;
+    }
+
+    pub(crate) fn servers(&self) -> Option<&ServerTable> {
+        self.servers.as_ref()
+    }
+
+    pub(crate) fn set_servers(&mut self, servers: Option<ServerTable>) {
+        self.servers = servers;
     }
 
     pub fn send_buffer_size(&self) -> usize {
@@ -238,6 +362,24 @@ impl SessionState {
         self.authentication_token = authentication_token;
     }
 
+    pub(crate) fn set_server_nonce(&mut self, server_nonce: ByteString) {
+        self.server_nonce = server_nonce;
+    }
+
+    pub(crate) fn server_nonce(&self) -> &ByteString {
+        &self.server_nonce
+    }
+
+    /// Takes the session and the callbacks of the state that this one replaces, so the session
+    /// can be activated again on a new connection and the callbacks are still called
+    pub(crate) fn take_session_from(&mut self, old: &mut SessionState) {
+        self.session_id = old.session_id.clone();
+        self.authentication_token = old.authentication_token.clone();
+        self.server_nonce = old.server_nonce.clone();
+        self.session_closed_callback = old.session_closed_callback.take();
+        self.connection_status_callback = old.connection_status_callback.take();
+    }
+
     pub fn set_session_closed_callback<CB>(&mut self, session_closed_callback: CB)
     where
         CB: OnSessionClosed + Send + Sync + 'static,
@@ -269,8 +411,8 @@ impl SessionState {
             authentication_token: self.authentication_token.clone(),
             timestamp: DateTime::now_with_offset(self.client_offset),
             request_handle: self.request_handle.next(),
-            return_diagnostics: DiagnosticBits::empty(),
-            timeout_hint: self.request_timeout,
+            return_diagnostics: self.return_diagnostics,
+            timeout_hint: self.request_timeout(),
             ..Default::default()
         }
     }
@@ -295,8 +437,19 @@ impl SessionState {
             }
             Some(subscription_acknowledgements)
         };
+        // The server may hold on to a publish request until a subscription has something to
+        // send, so it must not time out before the subscription would expire
+        let mut request_header = self.make_request_header();
+        let keep_alive_timeout = {
+            let subscription_state = trace_read_lock!(self.subscription_state);
+            subscription_state.keep_alive_timeout()
+        };
+        if let Some(keep_alive_timeout) = keep_alive_timeout {
+            let keep_alive_timeout = keep_alive_timeout.min(u32::MAX as u64) as u32;
+            request_header.timeout_hint = request_header.timeout_hint.max(keep_alive_timeout);
+        }
         let request = PublishRequest {
-            request_header: self.make_request_header(),
+            request_header,
             subscription_acknowledgements,
         };
         let request_handle = self.async_send_request(request, None)?;
@@ -310,26 +463,130 @@ impl SessionState {
         Ok(request_handle)
     }
 
-    /// Synchronously sends a request. The return value is the response to the request
-    pub(crate) fn send_request<T>(&mut self, request: T) -> Result<SupportedMessage, StatusCode>
+    /// Synchronously sends a request. The return value is the response to the request.
+    ///
+    /// The session state is only locked while the request is queued and while the response is
+    /// recorded, so other threads can send requests of their own while this one waits.
+    pub(crate) fn send_request<T>(
+        session_state: &Arc<RwLock<SessionState>>,
+        request: T,
+    ) -> Result<SupportedMessage, StatusCode>
+    where
+        T: Into<SupportedMessage>,
+    {
+        Self::begin_request(session_state, request)?.wait()
+    }
+
+    /// Sends a request without waiting for its response, which is received through the returned
+    /// pending response. The responses are matched to their requests by request handle. A request
+    /// first waits for a slot in the request window, which limits how many requests are
+    /// outstanding at once.
+    pub(crate) fn begin_request<T>(
+        session_state: &Arc<RwLock<SessionState>>,
+        request: T,
+    ) -> Result<PendingResponse, StatusCode>
     where
         T: Into<SupportedMessage>,
     {
+        let request = request.into();
+
+        // The response can be waited for as long as the timeout hint of the request
+        let (request_timeout, request_window) = {
+            let session_state = trace_read_lock!(session_state);
+            let request_timeout = match request.request_header().timeout_hint {
+                0 => session_state.request_timeout(),
+                timeout_hint => timeout_hint,
+            };
+            (request_timeout, session_state.request_window.clone())
+        };
+
+        // The status and duration are recorded on the span when the response arrives
+        #[cfg(feature = "tracing")]
+        let span = {
+            let session_state = trace_read_lock!(session_state);
+            tracing::info_span!(
+                "service_request",
+                service = request.name(),
+                request_handle = request.request_handle(),
+                session_id = %session_state.session_id,
+                status = tracing::field::Empty,
+                duration_us = tracing::field::Empty,
+            )
+        };
+        #[cfg(feature = "tracing")]
+        let entered = span.enter();
+
+        let request_slot = request_window.acquire(request_timeout)?;
+
         // A channel is created to receive the response
         let (sender, receiver) = mpsc::sync_channel(1);
         // Send the request
-        let request_handle = self.async_send_request(request, Some(sender))?;
-        // Wait for the response
+        let request_handle = {
+            let mut session_state = trace_write_lock!(session_state);
+            session_state.async_send_request(request, Some(sender))?
+        };
+
+        #[cfg(feature = "tracing")]
+        drop(entered);
+
+        Ok(PendingResponse::new(
+            request_handle,
+            request_timeout,
+            receiver,
+            session_state.clone(),
+            request_slot,
+            #[cfg(feature = "tracing")]
+            span,
+        ))
+    }
+
+    /// Records the diagnostics of the response to a synchronous request
+    pub(crate) fn set_last_response(&mut self, response: &Result<SupportedMessage, StatusCode>) {
+        if !self.return_diagnostics.is_empty() {
+            self.last_response_diagnostics = match response {
+                Ok(ref response) if response.is_response() => ResponseDiagnostics::new(
+                    response.response_header(),
+                    operation_diagnostic_infos(response),
+                ),
+                _ => None,
+            };
+        }
+    }
+
+    /// Sends a request to open or renew the secure channel and waits for the response with the
+    /// session state locked, so no other request is sent on a channel whose token is changing.
+    /// It is not limited by the request window, which may be full of the requests that this
+    /// one holds up.
+    fn send_secure_channel_request<T>(&mut self, request: T) -> Result<SupportedMessage, StatusCode>
+    where
+        T: Into<SupportedMessage>,
+    {
         let request_timeout = self.request_timeout();
-        self.wait_for_sync_response(request_handle, request_timeout, receiver)
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let request_handle = self.async_send_request(request, Some(sender))?;
+        receiver
+            .recv_timeout(std::time::Duration::from_millis(request_timeout as u64))
+            .map_err(|_| {
+                info!("Timeout waiting for secure channel response from server");
+                self.request_has_timed_out(request_handle);
+                StatusCode::BadTimeout
+            })
     }
 
     pub(crate) fn reset(&mut self) {
         // Clear tokens, ids etc.
         self.session_id = NodeId::null();
         self.authentication_token = NodeId::null();
+        self.server_nonce = ByteString::null();
         self.request_handle.reset();
         self.monitored_item_handle.reset();
+        // The server may have different namespaces and servers when it comes back
+        self.namespaces = None;
+        self.servers = None;
+        {
+            let mut secure_channel = trace_write_lock!(self.secure_channel);
+            secure_channel.set_namespaces(NamespaceTable::new());
+        }
 
         // Clear the message queue
         {
@@ -371,30 +628,17 @@ impl SessionState {
         message_queue.quit();
     }
 
-    /// Wait for a response with a matching request handle. If request handle is 0 then no match
-    /// is performed and in fact the function is expected to receive no messages except asynchronous
-    /// and housekeeping events from the server. A 0 handle will cause the wait to process at most
-    /// one async message before returning.
-    fn wait_for_sync_response(
-        &mut self,
-        request_handle: u32,
-        request_timeout: u32,
-        receiver: Receiver<SupportedMessage>,
-    ) -> Result<SupportedMessage, StatusCode> {
-        if request_handle == 0 {
-            panic!("Request handle must be non zero");
+    /// Stops waiting for asynchronous requests, e.g. publish requests, whose timeout hint has
+    /// elapsed without a response.
+    pub(crate) fn remove_timed_out_requests(&self) {
+        let mut message_queue = trace_write_lock!(self.message_queue);
+        let timed_out = message_queue.remove_timed_out_requests(std::time::Instant::now());
+        if !timed_out.is_empty() {
+            debug!("Requests {:?} timed out without a response", timed_out);
         }
-        // Receive messages until the one expected comes back. Publish responses will be consumed
-        // silently.
-        let request_timeout = std::time::Duration::from_millis(request_timeout as u64);
-        receiver.recv_timeout(request_timeout).map_err(|_| {
-            info!("Timeout waiting for response from server");
-            self.request_has_timed_out(request_handle);
-            StatusCode::BadTimeout
-        })
     }
 
-    fn request_has_timed_out(&self, request_handle: u32) {
+    pub(crate) fn request_has_timed_out(&self, request_handle: u32) {
         let mut message_queue = trace_write_lock!(self.message_queue);
         message_queue.request_has_timed_out(request_handle)
     }
@@ -416,6 +660,7 @@ impl SessionState {
         };
         if should_renew_security_token {
             self.issue_or_renew_secure_channel(SecurityTokenRequestType::Renew)
+                .map_err(StatusCode::from)
         } else {
             Ok(())
         }
@@ -424,7 +669,7 @@ impl SessionState {
     pub(crate) fn issue_or_renew_secure_channel(
         &mut self,
         request_type: SecurityTokenRequestType,
-    ) -> Result<(), StatusCode> {
+    ) -> Result<(), ServiceError> {
         trace!("issue_or_renew_secure_channel({:?})", request_type);
 
         const REQUESTED_LIFETIME: u32 = 60000; // TODO
@@ -440,9 +685,12 @@ impl SessionState {
             )
         };
 
-        info!("Making secure channel request");
-        info!("security_mode = {:?}", security_mode);
-        info!("security_policy = {:?}", security_policy);
+        #[cfg(not(feature = "tracing"))]
+        {
+            info!("Making secure channel request");
+            info!("security_mode = {:?}", security_mode);
+            info!("security_policy = {:?}", security_policy);
+        }
 
         let requested_lifetime = REQUESTED_LIFETIME;
         let request = OpenSecureChannelRequest {
@@ -453,7 +701,7 @@ impl SessionState {
             client_nonce,
             requested_lifetime,
         };
-        let response = self.send_request(request)?;
+        let response = self.send_secure_channel_request(request)?;
         if let SupportedMessage::OpenSecureChannelResponse(response) = response {
             // Extract the security token from the response.
             let mut security_token = response.security_token.clone();
@@ -465,13 +713,24 @@ impl SessionState {
             if self.ignore_clock_skew && !response.response_header.timestamp.is_null() {
                 let offset = response.response_header.timestamp - DateTime::now();
                 // Make sure to apply the offset to the security token in the current response.
-                security_token.created_at = security_token.created_at - offset;
+                security_token.created_at -= offset;
                 // Update the client offset by adding the new offset. When the secure channel is
                 // renewed its already using the client offset calculated when issuing the secure
                 // channel and only needs to be updated to accommodate any additional clock skew.
                 self.set_client_offset(offset);
             }
 
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                secure_channel_id = security_token.channel_id,
+                token_id = security_token.token_id,
+                request_type = ?request_type,
+                security_policy = %security_policy,
+                security_mode = ?security_mode,
+                revised_lifetime = security_token.revised_lifetime,
+                "secure channel opened"
+            );
+
             debug!("Setting transport's security token");
             {
                 let mut secure_channel = trace_write_lock!(self.secure_channel);
@@ -559,6 +818,20 @@ impl SessionState {
                     }
                 }
 
+                // Process a status change of the subscription
+                if let Some(status_change) =
+                    notification_message.status_change_notification(&decoding_options)
+                {
+                    session_debug!(
+                        self,
+                        "Received status change {} for subscription {}",
+                        status_change.status,
+                        subscription_id
+                    );
+                    let mut subscription_state = trace_write_lock!(self.subscription_state);
+                    subscription_state.on_status_change(subscription_id, status_change.status);
+                }
+
                 // Send another publish request
                 let _ = self.async_publish();
             }
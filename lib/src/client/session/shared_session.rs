@@ -0,0 +1,86 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`SharedSession`], a handle that lets many threads call one session at once.
+//!
+//! [`SharedSession`]: ./struct.SharedSession.html
+
+use std::sync::Arc;
+
+use tokio::sync::oneshot;
+
+use crate::sync::*;
+
+use crate::client::session::session::{Session, SessionCommand};
+
+/// A clonable handle to a session that threads can call concurrently.
+///
+/// Every clone refers to the same session and the same secure channel. Calls from different
+/// threads do not wait for each other's responses. Their requests are written to the secure
+/// channel one after another, and each response is handed to the thread that sent the matching
+/// request. How many requests may be outstanding at once is set by
+/// `Session::set_max_inflight_requests()`, after which further calls wait for a response.
+///
+/// ```no_run
+/// use std::thread;
+/// use opcua::client::prelude::*;
+///
+/// # fn example(session: SharedSession) {
+/// let handles = (0..4).map(|i| {
+///     let session = session.clone();
+///     thread::spawn(move || {
+///         session.with(|session| {
+///             session.read(&[NodeId::new(2, i).into()], TimestampsToReturn::Both, 0.0)
+///         })
+///     })
+/// }).collect::<Vec<_>>();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SharedSession {
+    session: Arc<RwLock<Session>>,
+}
+
+impl From<Arc<RwLock<Session>>> for SharedSession {
+    fn from(session: Arc<RwLock<Session>>) -> Self {
+        Self::new(session)
+    }
+}
+
+impl SharedSession {
+    /// Creates a handle to the session, e.g. the one returned by `Client::connect_to_endpoint()`
+    pub fn new(session: Arc<RwLock<Session>>) -> SharedSession {
+        SharedSession { session }
+    }
+
+    /// Returns the session that the handle refers to
+    pub fn session(&self) -> &Arc<RwLock<Session>> {
+        &self.session
+    }
+
+    /// Calls the function with the session. Other threads may call the session at the same time,
+    /// so this is how services are called through the handle.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Session) -> R,
+    {
+        let session = trace_read_lock!(self.session);
+        f(&session)
+    }
+
+    /// Calls the function with exclusive access to the session, e.g. to change its settings. It
+    /// waits for the calls that other threads are making to finish.
+    pub fn with_mut<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Session) -> R,
+    {
+        let mut session = trace_write_lock!(self.session);
+        f(&mut session)
+    }
+
+    /// Runs the session on its own thread, see `Session::run_async()`
+    pub fn run_async(&self) -> oneshot::Sender<SessionCommand> {
+        Session::run_async(self.session.clone())
+    }
+}
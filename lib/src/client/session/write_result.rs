@@ -0,0 +1,68 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains [`WriteValuesResult`], the outcome of `Session::write_values()`.
+//!
+//! [`WriteValuesResult`]: ./enum.WriteValuesResult.html
+
+use crate::types::{node_id::NodeId, status_code::StatusCode};
+
+/// The status that the server returned for writing the value of a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteStatus {
+    /// The node that the value was written to
+    pub node_id: NodeId,
+    /// The status of the write, bad if the value was not written
+    pub status_code: StatusCode,
+}
+
+/// The outcome of writing the values of several nodes in one request. Each variant holds the
+/// status of every write, in the order of the values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteValuesResult {
+    /// Every value was written
+    Good(Vec<WriteStatus>),
+    /// Some values were written and some were not
+    PartialFailure(Vec<WriteStatus>),
+    /// No value was written
+    Failure(Vec<WriteStatus>),
+}
+
+impl WriteValuesResult {
+    pub(crate) fn new(statuses: Vec<WriteStatus>) -> WriteValuesResult {
+        let failures = statuses
+            .iter()
+            .filter(|status| status.status_code.is_bad())
+            .count();
+        if failures == 0 {
+            WriteValuesResult::Good(statuses)
+        } else if failures < statuses.len() {
+            WriteValuesResult::PartialFailure(statuses)
+        } else {
+            WriteValuesResult::Failure(statuses)
+        }
+    }
+
+    /// Tests if every value was written
+    pub fn is_good(&self) -> bool {
+        matches!(self, WriteValuesResult::Good(_))
+    }
+
+    /// Returns the status of every write, in the order of the values
+    pub fn statuses(&self) -> &[WriteStatus] {
+        match self {
+            WriteValuesResult::Good(statuses)
+            | WriteValuesResult::PartialFailure(statuses)
+            | WriteValuesResult::Failure(statuses) => statuses,
+        }
+    }
+
+    /// Returns the writes that failed, in the order of the values
+    pub fn failures(&self) -> Vec<&WriteStatus> {
+        self.statuses()
+            .iter()
+            .filter(|status| status.status_code.is_bad())
+            .collect()
+    }
+}
@@ -340,6 +340,16 @@ impl Subscription {
         self.client_handles.get(&client_handle).copied()
     }
 
+    pub(crate) fn monitored_item_from_handle(&self, client_handle: u32) -> Option<&MonitoredItem> {
+        self.monitored_item_id_from_handle(client_handle)
+            .and_then(|id| self.monitored_items.get(&id))
+    }
+
+    pub(crate) fn on_status_change(&mut self, status: StatusCode) {
+        let mut cb = trace_lock!(self.notification_callback);
+        cb.on_status_change(status);
+    }
+
     pub(crate) fn on_event(&mut self, events: &[EventNotificationList]) {
         let mut cb = trace_lock!(self.notification_callback);
         events.iter().for_each(|event| {
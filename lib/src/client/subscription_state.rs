@@ -2,13 +2,17 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use tokio::time::Instant;
 
-use crate::types::service_types::{DataChangeNotification, EventNotificationList};
+use crate::sync::*;
+use crate::types::{
+    service_types::{DataChangeNotification, EventNotificationList},
+    status_code::StatusCode,
+};
 
-use super::subscription::*;
+use super::{monitored_item_cache::MonitoredItemCache, subscription::*};
 
 /// Holds the live subscription state
 pub struct SubscriptionState {
@@ -18,6 +22,8 @@ pub struct SubscriptionState {
     last_publish_request: Instant,
     /// Subscriptions (key = subscription_id)
     subscriptions: HashMap<u32, Subscription>,
+    /// Optional cache of the latest value of each monitored item
+    monitored_item_cache: Option<Arc<RwLock<MonitoredItemCache>>>,
 }
 
 impl SubscriptionState {
@@ -26,9 +32,44 @@ impl SubscriptionState {
             keep_alive_timeout: None,
             last_publish_request: Instant::now(),
             subscriptions: HashMap::new(),
+            monitored_item_cache: None,
         }
     }
 
+    /// Enables the monitored item cache if it isn't already enabled and returns it. The cache is
+    /// seeded with the last value of any existing monitored items.
+    pub fn enable_monitored_item_cache(&mut self) -> Arc<RwLock<MonitoredItemCache>> {
+        if let Some(ref monitored_item_cache) = self.monitored_item_cache {
+            monitored_item_cache.clone()
+        } else {
+            let mut cache = MonitoredItemCache::new();
+            self.subscriptions.values().for_each(|s| {
+                s.monitored_items().values().for_each(|item| {
+                    let item_to_monitor = item.item_to_monitor();
+                    cache.update(
+                        &item_to_monitor.node_id,
+                        item_to_monitor.attribute_id,
+                        item.last_value(),
+                    );
+                });
+            });
+            let cache = Arc::new(RwLock::new(cache));
+            self.monitored_item_cache = Some(cache.clone());
+            cache
+        }
+    }
+
+    /// Disables the monitored item cache. Anybody watching values in the cache will find their
+    /// channels closed.
+    pub fn disable_monitored_item_cache(&mut self) {
+        self.monitored_item_cache = None;
+    }
+
+    /// Returns the monitored item cache, if it is enabled.
+    pub fn monitored_item_cache(&self) -> Option<Arc<RwLock<MonitoredItemCache>>> {
+        self.monitored_item_cache.clone()
+    }
+
     pub fn subscription_ids(&self) -> Option<Vec<u32>> {
         if self.subscriptions.is_empty() {
             None
@@ -71,6 +112,22 @@ impl SubscriptionState {
     }
 
     pub(crate) fn delete_subscription(&mut self, subscription_id: u32) -> Option<Subscription> {
+        let subscription = self.subscriptions.remove(&subscription_id);
+        if let Some(ref subscription) = subscription {
+            let monitored_item_ids = subscription
+                .monitored_items()
+                .keys()
+                .copied()
+                .collect::<Vec<u32>>();
+            self.remove_from_monitored_item_cache(subscription, &monitored_item_ids);
+        }
+        self.set_keep_alive_timeout();
+        subscription
+    }
+
+    /// Removes the subscription but leaves the values of its monitored items in the monitored item
+    /// cache. This is used when the subscription is about to be recreated on a new session.
+    pub(crate) fn take_subscription(&mut self, subscription_id: u32) -> Option<Subscription> {
         let subscription = self.subscriptions.remove(&subscription_id);
         self.set_keep_alive_timeout();
         subscription
@@ -95,6 +152,24 @@ impl SubscriptionState {
     ) {
         if let Some(ref mut subscription) = self.subscriptions.get_mut(&subscription_id) {
             subscription.on_data_change(data_change_notifications);
+            if let Some(ref monitored_item_cache) = self.monitored_item_cache {
+                let mut monitored_item_cache = trace_write_lock!(monitored_item_cache);
+                data_change_notifications
+                    .iter()
+                    .filter_map(|n| n.monitored_items.as_ref())
+                    .flatten()
+                    .for_each(|i| {
+                        if let Some(item) = subscription.monitored_item_from_handle(i.client_handle)
+                        {
+                            let item_to_monitor = item.item_to_monitor();
+                            monitored_item_cache.update(
+                                &item_to_monitor.node_id,
+                                item_to_monitor.attribute_id,
+                                &i.value,
+                            );
+                        }
+                    });
+            }
         }
     }
 
@@ -104,6 +179,19 @@ impl SubscriptionState {
         }
     }
 
+    /// Passes the status change of a subscription to its callback. A subscription which timed out
+    /// or was transferred to another session no longer exists on this session so it is deleted.
+    pub(crate) fn on_status_change(&mut self, subscription_id: u32, status: StatusCode) {
+        let subscription = match self.subscriptions.get_mut(&subscription_id) {
+            Some(subscription) => subscription,
+            None => return,
+        };
+        subscription.on_status_change(status);
+        if status == StatusCode::BadTimeout || status == StatusCode::GoodSubscriptionTransferred {
+            let _ = self.delete_subscription(subscription_id);
+        }
+    }
+
     pub(crate) fn insert_monitored_items(
         &mut self,
         subscription_id: u32,
@@ -125,8 +213,43 @@ impl SubscriptionState {
     }
 
     pub(crate) fn delete_monitored_items(&mut self, subscription_id: u32, items_to_delete: &[u32]) {
-        if let Some(ref mut subscription) = self.subscriptions.get_mut(&subscription_id) {
+        if let Some(mut subscription) = self.subscriptions.remove(&subscription_id) {
+            self.remove_from_monitored_item_cache(&subscription, items_to_delete);
             subscription.delete_monitored_items(items_to_delete);
+            self.subscriptions.insert(subscription_id, subscription);
+        }
+    }
+
+    /// Removes the cached values of the monitored items from the cache, unless some other monitored
+    /// item still monitors the same node and attribute. The subscription must not be in the map of
+    /// subscriptions when this is called.
+    fn remove_from_monitored_item_cache(&self, subscription: &Subscription, items_to_delete: &[u32]) {
+        if let Some(ref monitored_item_cache) = self.monitored_item_cache {
+            let mut monitored_item_cache = trace_write_lock!(monitored_item_cache);
+            items_to_delete
+                .iter()
+                .filter_map(|id| subscription.monitored_items().get(id))
+                .for_each(|item| {
+                    let item_to_monitor = item.item_to_monitor();
+                    // Other subscriptions and the subscription's remaining items might monitor
+                    // the same thing
+                    let other_items = self
+                        .subscriptions
+                        .values()
+                        .flat_map(|s| s.monitored_items().values());
+                    let remaining_items = subscription
+                        .monitored_items()
+                        .values()
+                        .filter(|i| !items_to_delete.contains(&i.id()));
+                    let still_monitored = other_items.chain(remaining_items).any(|i| {
+                        i.item_to_monitor().node_id == item_to_monitor.node_id
+                            && i.item_to_monitor().attribute_id == item_to_monitor.attribute_id
+                    });
+                    if !still_monitored {
+                        monitored_item_cache
+                            .remove(&item_to_monitor.node_id, item_to_monitor.attribute_id);
+                    }
+                });
         }
     }
 
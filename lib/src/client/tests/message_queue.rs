@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+use crate::client::message_queue::MessageQueue;
+use crate::core::supported_message::SupportedMessage;
+use crate::types::*;
+
+fn read_request(request_handle: u32, timeout_hint: u32) -> SupportedMessage {
+    ReadRequest {
+        request_header: RequestHeader {
+            request_handle,
+            timeout_hint,
+            ..RequestHeader::dummy()
+        },
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: None,
+    }
+    .into()
+}
+
+#[test]
+fn remove_timed_out_requests() {
+    let mut message_queue = MessageQueue::new();
+    let _rx = message_queue.make_request_channel();
+
+    message_queue.add_request(read_request(1, 1000), None);
+    message_queue.add_request(read_request(2, 60000), None);
+    message_queue.add_request(read_request(3, 0), None);
+    assert_eq!(message_queue.inflight_request_handles(), vec![1, 2, 3]);
+
+    // Nothing has timed out yet
+    assert!(message_queue
+        .remove_timed_out_requests(Instant::now())
+        .is_empty());
+
+    // Only the request with the shortest hint times out, a zero hint never does
+    let now = Instant::now() + Duration::from_secs(5);
+    assert_eq!(message_queue.remove_timed_out_requests(now), vec![1]);
+    assert_eq!(message_queue.inflight_request_handles(), vec![2, 3]);
+}
@@ -0,0 +1,1667 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tempdir::TempDir;
+
+use crate::client::prelude::*;
+use crate::sync::*;
+
+fn mock_client(pki_dir: &TempDir) -> Client {
+    ClientBuilder::new()
+        .loopback(true)
+        .application_name("Mock Server Test Client")
+        .application_uri("urn:mock-server-test-client")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .client()
+        .unwrap()
+}
+
+fn connect(client: &mut Client, endpoint_url: &str) -> Result<Arc<RwLock<Session>>, StatusCode> {
+    client.connect_to_endpoint(
+        (
+            endpoint_url,
+            SecurityPolicy::None.to_str(),
+            MessageSecurityMode::None,
+            UserTokenPolicy::anonymous(),
+        ),
+        IdentityToken::Anonymous,
+    )
+}
+
+#[test]
+fn mock_server_scripted_read() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-read:4855/")
+        .read_response(vec![DataValue::new_now(123i32)])
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        let results = session
+            .read(
+                &[ReadValueId::from(NodeId::new(2, "v1"))],
+                TimestampsToReturn::Neither,
+                0f64,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].value, Some(Variant::from(123i32)));
+
+        // Services that are not scripted are unsupported
+        let result = session.write(&[WriteValue {
+            node_id: NodeId::new(2, "v1"),
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            value: DataValue::new_now(1i32),
+        }]);
+        match result.unwrap_err() {
+            ServiceError::ServiceUnsupported(fault) => {
+                assert_eq!(fault.status_code, StatusCode::BadServiceUnsupported)
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+        session.disconnect();
+    }
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .any(|r| matches!(r, SupportedMessage::ReadRequest(_))));
+    assert!(requests
+        .iter()
+        .any(|r| matches!(r, SupportedMessage::CloseSessionRequest(_))));
+}
+
+#[test]
+fn loopback_needs_opt_in() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-no-loopback:4855/")
+        .server()
+        .unwrap();
+
+    // A client that does not opt in connects over TCP, where there is no such server
+    let mut client = ClientBuilder::new()
+        .application_name("Mock Server Test Client")
+        .application_uri("urn:mock-server-test-client")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .client()
+        .unwrap();
+    assert!(connect(&mut client, server.endpoint_url()).is_err());
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn mock_server_activate_session_fault() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-fault:4855/")
+        .fault(
+            "ActivateSessionRequest",
+            StatusCode::BadIdentityTokenRejected,
+        )
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let result = connect(&mut client, server.endpoint_url());
+    assert_eq!(result.err(), Some(StatusCode::BadIdentityTokenRejected));
+}
+
+#[test]
+fn secure_sessions_only_refuses_security_policy_none() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-secure-only:4855/")
+        .server()
+        .unwrap();
+
+    let mut client = ClientBuilder::new()
+        .loopback(true)
+        .application_name("Mock Server Test Client")
+        .application_uri("urn:mock-server-test-client")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .secure_sessions_only(true)
+        .client()
+        .unwrap();
+    let result = connect(&mut client, server.endpoint_url());
+    assert_eq!(result.err(), Some(StatusCode::BadSecurityPolicyRejected));
+
+    // The endpoints were got over security policy None, but no session was asked for
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .any(|r| matches!(r, SupportedMessage::GetEndpointsRequest(_))));
+    assert!(!requests
+        .iter()
+        .any(|r| matches!(r, SupportedMessage::CreateSessionRequest(_))));
+}
+
+#[test]
+fn mock_server_typed_service_faults() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-typed-fault:4855/")
+        .fault("ReadRequest", StatusCode::BadTooManyOperations)
+        .fault("WriteRequest", StatusCode::BadSessionIdInvalid)
+        .fault("BrowseRequest", StatusCode::BadNodeIdUnknown)
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let session = trace_read_lock!(session);
+
+    let err = session
+        .read(
+            &[ReadValueId::from(NodeId::new(2, "v1"))],
+            TimestampsToReturn::Neither,
+            0f64,
+        )
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::TooManyOperations(_)));
+    assert_eq!(err.status_code(), StatusCode::BadTooManyOperations);
+    let fault = err.fault().unwrap();
+    assert_ne!(fault.request_handle, 0);
+    assert!(fault.diagnostics.is_none());
+
+    // The request handle identifies the request that failed
+    let requests = server.requests();
+    let read_request = requests
+        .iter()
+        .find(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+        .unwrap();
+    assert_eq!(fault.request_handle, read_request.request_handle());
+
+    let err = session
+        .write(&[WriteValue {
+            node_id: NodeId::new(2, "v1"),
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            value: DataValue::new_now(1i32),
+        }])
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::SessionInvalid(_)));
+
+    let err = session
+        .browse(&[BrowseDescription {
+            node_id: ObjectId::ObjectsFolder.into(),
+            browse_direction: BrowseDirection::Forward,
+            reference_type_id: ReferenceTypeId::Organizes.into(),
+            include_subtypes: true,
+            node_class_mask: 0,
+            result_mask: 0,
+        }])
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::Fault(_)));
+    assert_eq!(StatusCode::from(err), StatusCode::BadNodeIdUnknown);
+
+    session.disconnect();
+}
+
+#[test]
+fn service_error_from_status_code() {
+    // Errors raised on the client are not faults from the server
+    let err = ServiceError::from(StatusCode::BadNotConnected);
+    assert_eq!(err, ServiceError::Client(StatusCode::BadNotConnected));
+    assert_eq!(err.status_code(), StatusCode::BadNotConnected);
+    assert!(err.fault().is_none());
+}
+
+#[test]
+fn mock_server_address_in_use() {
+    let _server = MockServerBuilder::new("opc.tcp://mock-server-in-use:4855/")
+        .server()
+        .unwrap();
+    let result = MockServerBuilder::new("opc.tcp://mock-server-in-use:4855/").server();
+    assert_eq!(result.err(), Some(StatusCode::BadResourceUnavailable));
+}
+
+#[test]
+fn mock_server_namespaces() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let namespace_array = Variant::from(vec![
+        "http://opcfoundation.org/UA/".to_string(),
+        "urn:mock-server".to_string(),
+        "urn:machines".to_string(),
+    ]);
+    let server = MockServerBuilder::new("opc.tcp://mock-server-namespaces:4855/")
+        .read_response(vec![DataValue::new_now(namespace_array)])
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        assert_eq!(
+            *session.decoding_options().namespaces,
+            NamespaceTable::new()
+        );
+        assert_eq!(session.namespace_index("urn:machines").unwrap(), Some(2));
+        // Extension objects are decoded against the namespaces of the server
+        assert_eq!(
+            session.decoding_options().namespaces.uris()[2],
+            "urn:machines"
+        );
+        session.register_type::<ReadValueId>();
+        assert_eq!(session.decoding_options().type_registry.len(), 1);
+        assert_eq!(session.namespace_index("urn:unknown").unwrap(), None);
+        assert_eq!(
+            session
+                .node_id_in_namespace("urn:machines", "Pump1")
+                .unwrap(),
+            NodeId::new(2, "Pump1")
+        );
+        assert_eq!(
+            session
+                .node_id_in_namespace("urn:unknown", "Pump1")
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNodeIdUnknown
+        );
+        session.disconnect();
+    }
+
+    // The namespace array is only read once
+    let reads = server
+        .requests()
+        .iter()
+        .filter(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+        .count();
+    assert_eq!(reads, 1);
+}
+
+#[test]
+fn mock_server_resolve_node_id() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-resolve:4855/")
+        .respond_to("ReadRequest", |request| {
+            let request = match request {
+                SupportedMessage::ReadRequest(request) => request,
+                _ => panic!(),
+            };
+            let node_id = &request.nodes_to_read.as_ref().unwrap()[0].node_id;
+            let uris = if *node_id == VariableId::Server_ServerArray.into() {
+                vec!["urn:mock-server", "urn:remote-server"]
+            } else {
+                vec!["http://opcfoundation.org/UA/", "urn:machines"]
+            };
+            ReadResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(vec![DataValue::new_now(Variant::from(
+                    uris.into_iter().map(String::from).collect::<Vec<String>>(),
+                ))]),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+
+        // A node id with an index needs nothing from the server
+        let node_id = ExpandedNodeId::new(NodeId::new(2, "Pump1"));
+        assert_eq!(
+            session.resolve_node_id(&node_id).unwrap(),
+            ResolvedNodeId::Local(NodeId::new(2, "Pump1"))
+        );
+        assert!(server
+            .requests()
+            .iter()
+            .all(|r| !matches!(r, SupportedMessage::ReadRequest(_))));
+
+        let node_id = ExpandedNodeId::with_namespace_uri("urn:machines", "Pump1");
+        assert_eq!(
+            session.resolve_node_id(&node_id).unwrap(),
+            ResolvedNodeId::Local(NodeId::new(1, "Pump1"))
+        );
+        assert_eq!(
+            session.expand_node_id(&NodeId::new(1, "Pump1")).unwrap(),
+            node_id
+        );
+
+        let remote = ExpandedNodeId {
+            server_index: 1,
+            ..node_id.clone()
+        };
+        assert_eq!(
+            session.resolve_node_id(&remote).unwrap(),
+            ResolvedNodeId::Remote {
+                server_uri: "urn:remote-server".to_string(),
+                node_id,
+            }
+        );
+
+        let unknown = ExpandedNodeId {
+            server_index: 5,
+            ..remote
+        };
+        assert_eq!(
+            session.resolve_node_id(&unknown).unwrap_err().status_code(),
+            StatusCode::BadNodeIdUnknown
+        );
+        session.disconnect();
+    }
+}
+
+/// A mock server with one variable, `ns=2;s=Pump1`, that can be found by the browse path
+/// `ObjectsFolder/2:Pump1`
+fn pump_server(endpoint_url: &str) -> MockServer {
+    MockServerBuilder::new(endpoint_url)
+        .respond_to("ReadRequest", |request| {
+            let request = match request {
+                SupportedMessage::ReadRequest(request) => request,
+                _ => panic!(),
+            };
+            let pump_id = NodeId::new(2, "Pump1");
+            let results = request
+                .nodes_to_read
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|n| {
+                    let value = if n.node_id != pump_id {
+                        return DataValue {
+                            status: Some(StatusCode::BadNodeIdUnknown),
+                            ..DataValue::null()
+                        };
+                    } else {
+                        match AttributeId::from_u32(n.attribute_id).unwrap() {
+                            AttributeId::NodeId => Variant::from(pump_id.clone()),
+                            AttributeId::NodeClass => Variant::from(NodeClass::Variable as i32),
+                            AttributeId::BrowseName => {
+                                Variant::from(QualifiedName::new(2, "Pump1"))
+                            }
+                            AttributeId::DisplayName => {
+                                Variant::from(LocalizedText::from("Pump 1"))
+                            }
+                            AttributeId::Value => Variant::from(42.5f64),
+                            AttributeId::DataType => {
+                                Variant::from(NodeId::new(0, DataTypeId::Double as u32))
+                            }
+                            AttributeId::ValueRank => Variant::from(-1i32),
+                            AttributeId::AccessLevel => Variant::from(1u8),
+                            _ => {
+                                return DataValue {
+                                    status: Some(StatusCode::BadAttributeIdInvalid),
+                                    ..DataValue::null()
+                                }
+                            }
+                        }
+                    };
+                    DataValue::value_only(value)
+                })
+                .collect();
+            ReadResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(results),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .respond_to("TranslateBrowsePathsToNodeIdsRequest", |request| {
+            let request = match request {
+                SupportedMessage::TranslateBrowsePathsToNodeIdsRequest(request) => request,
+                _ => panic!(),
+            };
+            let results = request
+                .browse_paths
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|browse_path| {
+                    let elements = browse_path.relative_path.elements.as_ref().unwrap();
+                    if elements[0].target_name == QualifiedName::new(2, "Pump1") {
+                        BrowsePathResult {
+                            status_code: StatusCode::Good,
+                            targets: Some(vec![BrowsePathTarget {
+                                target_id: NodeId::new(2, "Pump1").into(),
+                                remaining_path_index: u32::MAX,
+                            }]),
+                        }
+                    } else {
+                        BrowsePathResult {
+                            status_code: StatusCode::BadNoMatch,
+                            targets: None,
+                        }
+                    }
+                })
+                .collect();
+            TranslateBrowsePathsToNodeIdsResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(results),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap()
+}
+
+#[test]
+fn read_helpers() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = pump_server("opc.tcp://mock-server-read-helpers:4855/");
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+
+        let value = session.read_value(NodeId::new(2, "Pump1")).unwrap();
+        assert_eq!(value.value, Some(Variant::from(42.5f64)));
+
+        let values = session
+            .read_values(&[NodeId::new(2, "Pump1"), NodeId::new(2, "Pump2")])
+            .unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].value, Some(Variant::from(42.5f64)));
+        assert_eq!(values[1].status, Some(StatusCode::BadNodeIdUnknown));
+
+        let attributes = session
+            .read_node_attributes(NodeId::new(2, "Pump1"))
+            .unwrap();
+        assert_eq!(attributes.node_id, NodeId::new(2, "Pump1"));
+        assert_eq!(attributes.node_class, NodeClass::Variable);
+        assert_eq!(attributes.browse_name, QualifiedName::new(2, "Pump1"));
+        assert_eq!(attributes.display_name.text, UAString::from("Pump 1"));
+        assert_eq!(
+            attributes.value.unwrap().value,
+            Some(Variant::from(42.5f64))
+        );
+        assert_eq!(attributes.data_type, Some(DataTypeId::Double.into()));
+        assert_eq!(attributes.value_rank, Some(-1));
+        assert_eq!(attributes.access_level, Some(1));
+        assert_eq!(attributes.description, None);
+        assert_eq!(attributes.executable, None);
+        assert_eq!(
+            session
+                .read_node_attributes(NodeId::new(2, "Pump2"))
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNodeIdUnknown
+        );
+
+        let relative_path =
+            RelativePath::from_str("/2:Pump1", &RelativePathElement::default_node_resolver)
+                .unwrap();
+        let browse_path = BrowsePath {
+            starting_node: ObjectId::ObjectsFolder.into(),
+            relative_path,
+        };
+        let value = session.read_value_by_browse_path(&browse_path).unwrap();
+        assert_eq!(value.value, Some(Variant::from(42.5f64)));
+        let relative_path =
+            RelativePath::from_str("/2:Pump2", &RelativePathElement::default_node_resolver)
+                .unwrap();
+        let browse_path = BrowsePath {
+            starting_node: ObjectId::ObjectsFolder.into(),
+            relative_path,
+        };
+        assert_eq!(
+            session
+                .read_value_by_browse_path(&browse_path)
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNoMatch
+        );
+        session.disconnect();
+    }
+}
+
+#[test]
+fn read_enumeration() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    // The pump mode has enum values but no enum strings
+    let server = MockServerBuilder::new("opc.tcp://mock-server-read-enumeration:4855/")
+        .respond_to("TranslateBrowsePathsToNodeIdsRequest", |request| {
+            let request = match request {
+                SupportedMessage::TranslateBrowsePathsToNodeIdsRequest(request) => request,
+                _ => panic!(),
+            };
+            let results = request
+                .browse_paths
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|browse_path| {
+                    let elements = browse_path.relative_path.elements.as_ref().unwrap();
+                    if browse_path.starting_node == NodeId::new(2, "PumpMode")
+                        && elements[0].target_name == QualifiedName::new(0, "EnumValues")
+                    {
+                        BrowsePathResult {
+                            status_code: StatusCode::Good,
+                            targets: Some(vec![BrowsePathTarget {
+                                target_id: NodeId::new(2, "PumpMode.EnumValues").into(),
+                                remaining_path_index: u32::MAX,
+                            }]),
+                        }
+                    } else {
+                        BrowsePathResult {
+                            status_code: StatusCode::BadNoMatch,
+                            targets: None,
+                        }
+                    }
+                })
+                .collect();
+            TranslateBrowsePathsToNodeIdsResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(results),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .respond_to("ReadRequest", |request| {
+            let request = match request {
+                SupportedMessage::ReadRequest(request) => request,
+                _ => panic!(),
+            };
+            let enum_values = Enumeration::from_enum_values(vec![
+                EnumValueType {
+                    value: 1,
+                    display_name: LocalizedText::from("Manual"),
+                    description: LocalizedText::null(),
+                },
+                EnumValueType {
+                    value: 5,
+                    display_name: LocalizedText::from("Automatic"),
+                    description: LocalizedText::null(),
+                },
+            ]);
+            ReadResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(vec![DataValue::value_only(
+                    enum_values.enum_values_variant(),
+                )]),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        let enumeration = session
+            .read_enumeration(NodeId::new(2, "PumpMode"))
+            .unwrap();
+        assert_eq!(
+            enumeration.text_of(&Variant::from(5i32)).unwrap().text,
+            UAString::from("Automatic")
+        );
+        assert_eq!(enumeration.value_of("Manual"), Some(1));
+        assert_eq!(
+            session
+                .read_enumeration(NodeId::new(2, "Pump1"))
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNoMatch
+        );
+        session.disconnect();
+    }
+}
+
+#[test]
+fn write_helpers() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    // Nodes named "ReadOnly..." are not writable
+    let server = MockServerBuilder::new("opc.tcp://mock-server-write-helpers:4855/")
+        .respond_to("WriteRequest", |request| {
+            let request = match request {
+                SupportedMessage::WriteRequest(request) => request,
+                _ => panic!(),
+            };
+            let results = request
+                .nodes_to_write
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|n| match n.node_id.identifier {
+                    Identifier::String(ref name) if name.as_ref().starts_with("ReadOnly") => {
+                        StatusCode::BadNotWritable
+                    }
+                    _ => StatusCode::Good,
+                })
+                .collect();
+            WriteResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(results),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+
+        assert_eq!(
+            session.write_value(NodeId::new(2, "v1"), 1i32).unwrap(),
+            StatusCode::Good
+        );
+        assert_eq!(
+            session
+                .write_value(NodeId::new(2, "ReadOnly1"), 1i32)
+                .unwrap(),
+            StatusCode::BadNotWritable
+        );
+
+        let result = session
+            .write_values(vec![
+                (NodeId::new(2, "v1"), Variant::from(1i32)),
+                (NodeId::new(2, "v2"), Variant::from("two")),
+            ])
+            .unwrap();
+        assert!(result.is_good());
+        assert!(result.failures().is_empty());
+
+        let result = session
+            .write_values(vec![
+                (NodeId::new(2, "v1"), 1.5f64),
+                (NodeId::new(2, "ReadOnly1"), 2.5f64),
+            ])
+            .unwrap();
+        assert!(matches!(result, WriteValuesResult::PartialFailure(_)));
+        assert_eq!(
+            result.failures(),
+            vec![&WriteStatus {
+                node_id: NodeId::new(2, "ReadOnly1"),
+                status_code: StatusCode::BadNotWritable,
+            }]
+        );
+        assert_eq!(result.statuses()[0].status_code, StatusCode::Good);
+
+        let result = session
+            .write_values(vec![(NodeId::new(2, "ReadOnly1"), true)])
+            .unwrap();
+        assert!(matches!(result, WriteValuesResult::Failure(_)));
+
+        assert_eq!(
+            session
+                .write_values(Vec::<(NodeId, i32)>::new())
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNothingToDo
+        );
+        session.disconnect();
+    }
+
+    // The values are written without timestamps
+    let requests = server.requests();
+    let write = requests
+        .iter()
+        .find_map(|r| match r {
+            SupportedMessage::WriteRequest(request) => Some(request),
+            _ => None,
+        })
+        .unwrap();
+    let node_to_write = &write.nodes_to_write.as_ref().unwrap()[0];
+    assert_eq!(node_to_write.attribute_id, AttributeId::Value as u32);
+    assert_eq!(node_to_write.value, DataValue::value_only(1i32));
+}
+
+#[test]
+fn read_value_in_chunks() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    // An array of 10 values, a byte string of 10 bytes and a scalar, which are read in ranges
+    let server = MockServerBuilder::new("opc.tcp://mock-server-read-chunks:4855/")
+        .respond_to("ReadRequest", |request| {
+            let request = match request {
+                SupportedMessage::ReadRequest(request) => request,
+                _ => panic!(),
+            };
+            let results = request
+                .nodes_to_read
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|n| {
+                    let value = match n.node_id.identifier {
+                        Identifier::String(ref name) if name.as_ref() == "Array" => {
+                            Variant::from((0..10i32).collect::<Vec<_>>())
+                        }
+                        Identifier::String(ref name) if name.as_ref() == "Bytes" => {
+                            Variant::from(ByteString::from((0..10u8).collect::<Vec<_>>()))
+                        }
+                        _ => Variant::from(5i32),
+                    };
+                    let index_range = n.index_range.as_ref().parse::<NumericRange>().unwrap();
+                    match value.range_of(index_range) {
+                        Ok(value) => DataValue::value_only(value),
+                        Err(status) => DataValue {
+                            status: Some(status),
+                            ..DataValue::null()
+                        },
+                    }
+                })
+                .collect();
+            ReadResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(results),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let reads = || {
+        server
+            .requests()
+            .iter()
+            .filter(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+            .count()
+    };
+    {
+        let session = trace_read_lock!(session);
+
+        let array = Variant::from((0..10i32).collect::<Vec<_>>());
+        [(1, 11), (3, 4), (5, 3), (10, 2), (100, 1)]
+            .iter()
+            .for_each(|(chunk_size, expected_reads)| {
+                let before = reads();
+                let value = session
+                    .read_value_in_chunks(NodeId::new(2, "Array"), *chunk_size)
+                    .unwrap();
+                assert_eq!(value.value.as_ref(), Some(&array));
+                assert_eq!(reads() - before, *expected_reads);
+            });
+
+        let value = session
+            .read_value_in_chunks(NodeId::new(2, "Bytes"), 4)
+            .unwrap();
+        assert_eq!(
+            value.value,
+            Some(Variant::from(ByteString::from(
+                (0..10u8).collect::<Vec<_>>()
+            )))
+        );
+
+        // A scalar is read in one piece
+        let value = session
+            .read_value_in_chunks(NodeId::new(2, "Scalar"), 4)
+            .unwrap();
+        assert_eq!(value.value, Some(Variant::from(5i32)));
+
+        assert_eq!(
+            session
+                .read_value_in_chunks(NodeId::new(2, "Array"), 0)
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadInvalidArgument
+        );
+        session.disconnect();
+    }
+}
+
+#[test]
+fn triggered_item_helpers() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    // Monitored item 99 is unknown to the server
+    let server = MockServerBuilder::new("opc.tcp://mock-server-triggering:4855/")
+        .respond_to("SetTriggeringRequest", |request| {
+            let request = match request {
+                SupportedMessage::SetTriggeringRequest(request) => request,
+                _ => panic!(),
+            };
+            let results = |links: &Option<Vec<u32>>| {
+                links.as_ref().map(|links| {
+                    links
+                        .iter()
+                        .map(|link| {
+                            if *link == 99 {
+                                StatusCode::BadMonitoredItemIdInvalid
+                            } else {
+                                StatusCode::Good
+                            }
+                        })
+                        .collect()
+                })
+            };
+            SetTriggeringResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                add_results: results(&request.links_to_add),
+                add_diagnostic_infos: None,
+                remove_results: results(&request.links_to_remove),
+                remove_diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        assert_eq!(
+            session.add_triggered_items(1, 10, &[11, 99]).unwrap(),
+            vec![StatusCode::Good, StatusCode::BadMonitoredItemIdInvalid]
+        );
+        assert_eq!(
+            session.remove_triggered_items(1, 10, &[11]).unwrap(),
+            vec![StatusCode::Good]
+        );
+        assert_eq!(
+            session
+                .add_triggered_items(1, 10, &[])
+                .unwrap_err()
+                .status_code(),
+            StatusCode::BadNothingToDo
+        );
+        session.disconnect();
+    }
+
+    let requests = server.requests();
+    let requests = requests
+        .iter()
+        .filter_map(|r| match r {
+            SupportedMessage::SetTriggeringRequest(request) => Some(request),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].subscription_id, 1);
+    assert_eq!(requests[0].triggering_item_id, 10);
+    assert_eq!(requests[0].links_to_add, Some(vec![11, 99]));
+    assert!(requests[0].links_to_remove.is_none());
+    assert!(requests[1].links_to_add.is_none());
+    assert_eq!(requests[1].links_to_remove, Some(vec![11]));
+}
+
+/// Records the status changes of a subscription
+struct StatusChangeCallback {
+    statuses: Arc<Mutex<Vec<StatusCode>>>,
+}
+
+impl OnSubscriptionNotification for StatusChangeCallback {
+    fn on_status_change(&mut self, status: StatusCode) {
+        trace_lock!(self.statuses).push(status);
+    }
+}
+
+#[test]
+fn subscription_status_change() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    // The first publish request is answered with a status change saying the subscription timed
+    // out, any after that with a fault that stops the client publishing
+    let published = Arc::new(AtomicBool::new(false));
+    let server = MockServerBuilder::new("opc.tcp://mock-server-status-change:4855/")
+        .respond_to("CreateSubscriptionRequest", |request| {
+            CreateSubscriptionResponse {
+                response_header: ResponseHeader::new_good(request.request_header()),
+                subscription_id: 7,
+                revised_publishing_interval: 1000.0,
+                revised_lifetime_count: 30,
+                revised_max_keep_alive_count: 10,
+            }
+            .into()
+        })
+        .respond_to("PublishRequest", move |request| {
+            if published.swap(true, Ordering::SeqCst) {
+                ServiceFault::new(
+                    request.request_header(),
+                    StatusCode::BadTooManyPublishRequests,
+                )
+                .into()
+            } else {
+                PublishResponse {
+                    response_header: ResponseHeader::new_good(request.request_header()),
+                    subscription_id: 7,
+                    available_sequence_numbers: None,
+                    more_notifications: false,
+                    notification_message: NotificationMessage::status_change(
+                        1,
+                        DateTime::now(),
+                        StatusCode::BadTimeout,
+                    ),
+                    results: None,
+                    diagnostic_infos: None,
+                }
+                .into()
+            }
+        })
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let _run_tx = Session::run_async(session.clone());
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let subscription_state = {
+        let session = trace_read_lock!(session);
+        let subscription_id = session
+            .create_subscription(
+                1000.0,
+                30,
+                10,
+                0,
+                0,
+                true,
+                StatusChangeCallback {
+                    statuses: statuses.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(subscription_id, 7);
+        session.subscription_state()
+    };
+
+    // The status change reaches the callback and the subscription that timed out is deleted
+    let started = std::time::Instant::now();
+    while trace_lock!(statuses).is_empty() && started.elapsed() < std::time::Duration::from_secs(5)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(*trace_lock!(statuses), vec![StatusCode::BadTimeout]);
+    assert!(!trace_read_lock!(subscription_state).subscription_exists(7));
+    trace_read_lock!(session).disconnect();
+}
+
+#[test]
+fn client_pool_sessions() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let running = MockServerBuilder::new("opc.tcp://mock-pool-running:4855/")
+        .read_response(vec![DataValue::value_only(ServerState::Running as i32)])
+        .respond_to("CreateSubscriptionRequest", |request| {
+            CreateSubscriptionResponse {
+                response_header: ResponseHeader::new_good(request.request_header()),
+                subscription_id: 7,
+                revised_publishing_interval: 1000.0,
+                revised_lifetime_count: 30,
+                revised_max_keep_alive_count: 10,
+            }
+            .into()
+        })
+        .server()
+        .unwrap();
+    let unreadable = MockServerBuilder::new("opc.tcp://mock-pool-unreadable:4855/")
+        .server()
+        .unwrap();
+
+    let mut pool = ClientPool::new(mock_client(&pki_dir));
+    let endpoint = |url: &str| -> EndpointDescription {
+        (
+            url,
+            SecurityPolicy::None.to_str(),
+            MessageSecurityMode::None,
+            UserTokenPolicy::anonymous(),
+        )
+            .into()
+    };
+    let h1 = pool
+        .add_session(endpoint(running.endpoint_url()), IdentityToken::Anonymous)
+        .unwrap();
+    let h2 = pool
+        .add_session(
+            endpoint(unreadable.endpoint_url()),
+            IdentityToken::Anonymous,
+        )
+        .unwrap();
+    // Two sessions to the same server
+    let h3 = pool
+        .add_session(endpoint(running.endpoint_url()), IdentityToken::Anonymous)
+        .unwrap();
+    assert_ne!(h1, h3);
+    assert_eq!(pool.handles(), vec![h1, h2, h3]);
+
+    assert_eq!(
+        pool.check_health(),
+        vec![
+            (h1, SessionHealth::Healthy),
+            (
+                h2,
+                SessionHealth::Unhealthy(StatusCode::BadServiceUnsupported)
+            ),
+            (h3, SessionHealth::Healthy),
+        ]
+    );
+    assert_eq!(
+        pool.health(h2),
+        Some(SessionHealth::Unhealthy(StatusCode::BadServiceUnsupported))
+    );
+
+    // Subscriptions are recorded against the session that they were created on
+    let subscription_id = pool
+        .create_subscription(h1, 1000.0, |_handle, _items| {})
+        .unwrap();
+    assert_eq!(subscription_id, 7);
+    assert_eq!(pool.subscriptions(h1), Some(&[7u32][..]));
+    assert_eq!(pool.subscriptions(h3), Some(&[][..]));
+    assert_eq!(
+        pool.create_subscription(h2, 1000.0, |_handle, _items| {}),
+        Err(StatusCode::BadServiceUnsupported)
+    );
+    assert_eq!(
+        pool.delete_subscription(h3, 7),
+        Err(StatusCode::BadSubscriptionIdInvalid)
+    );
+
+    // Removed sessions are closed and their handles are not reused
+    assert!(pool.remove_session(h2));
+    assert!(!pool.remove_session(h2));
+    assert!(unreadable
+        .requests()
+        .iter()
+        .any(|r| matches!(r, SupportedMessage::CloseSessionRequest(_))));
+    assert_eq!(pool.health(h2), None);
+    assert_eq!(
+        pool.with_session(h2, |session| session.is_connected()),
+        Err(StatusCode::BadSessionIdInvalid)
+    );
+    assert_eq!(
+        pool.create_subscription(h2, 1000.0, |_handle, _items| {}),
+        Err(StatusCode::BadSessionIdInvalid)
+    );
+    let h4 = pool
+        .add_session(
+            endpoint(unreadable.endpoint_url()),
+            IdentityToken::Anonymous,
+        )
+        .unwrap();
+    assert!(h4 > h3);
+    assert_eq!(pool.len(), 3);
+}
+
+/// A mock server answering each read with the numeric identifiers of the nodes it reads, so a
+/// client can tell that it received the answer to its own request
+fn echo_read_server(endpoint_url: &str) -> MockServer {
+    MockServerBuilder::new(endpoint_url)
+        .respond_to("ReadRequest", |request| {
+            let results = if let SupportedMessage::ReadRequest(request) = request {
+                request.nodes_to_read.as_ref().map(|nodes_to_read| {
+                    nodes_to_read
+                        .iter()
+                        .map(|n| match n.node_id.identifier {
+                            Identifier::Numeric(id) => DataValue::value_only(id),
+                            _ => DataValue::null(),
+                        })
+                        .collect()
+                })
+            } else {
+                None
+            };
+            ReadResponse {
+                response_header: ResponseHeader::new_good(request.request_header()),
+                results,
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap()
+}
+
+#[test]
+fn shared_session_concurrent_requests() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = echo_read_server("opc.tcp://mock-server-shared:4855/");
+    let mut client = mock_client(&pki_dir);
+    let session = SharedSession::new(connect(&mut client, server.endpoint_url()).unwrap());
+    session.with_mut(|session| session.set_max_inflight_requests(4));
+    let _run_tx = session.run_async();
+
+    let threads = (0..8u32)
+        .map(|t| {
+            let session = session.clone();
+            std::thread::spawn(move || {
+                (0..20u32).for_each(|i| {
+                    let id = t * 1000 + i;
+                    let results = session
+                        .with(|session| {
+                            session.read(
+                                &[ReadValueId::from(NodeId::new(2, id))],
+                                TimestampsToReturn::Neither,
+                                0f64,
+                            )
+                        })
+                        .unwrap();
+                    assert_eq!(results[0].value, Some(Variant::from(id)));
+                });
+            })
+        })
+        .collect::<Vec<_>>();
+    threads.into_iter().for_each(|t| t.join().unwrap());
+
+    let reads = server
+        .requests()
+        .iter()
+        .filter(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+        .count();
+    assert_eq!(reads, 160);
+    session.with(|session| session.disconnect());
+}
+
+#[test]
+fn pipelined_requests() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = echo_read_server("opc.tcp://mock-server-pipelined:4855/");
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let mut session = trace_write_lock!(session);
+    session.set_max_inflight_requests(3);
+
+    let read_request = |session: &Session, id: u32| ReadRequest {
+        request_header: session.make_request_header(),
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Neither,
+        nodes_to_read: Some(vec![ReadValueId::from(NodeId::new(2, id))]),
+    };
+    let value = |response: SupportedMessage| match response {
+        SupportedMessage::ReadResponse(response) => response.results.unwrap()[0].value.clone(),
+        response => panic!("Unexpected response {:?}", response),
+    };
+
+    // Several requests are in flight at once and are waited for in any order
+    let pending = (1..=3)
+        .map(|id| session.begin_request(read_request(&session, id)).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(session.inflight_request_handles().len(), 3);
+    let values = pending
+        .into_iter()
+        .rev()
+        .map(|pending| value(pending.wait().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            Some(Variant::from(3u32)),
+            Some(Variant::from(2u32)),
+            Some(Variant::from(1u32))
+        ]
+    );
+
+    // A pipelined read splits the nodes into requests and returns the values in order
+    let nodes_to_read = (0..100u32)
+        .map(|id| ReadValueId::from(NodeId::new(2, id)))
+        .collect::<Vec<_>>();
+    let values = session
+        .read_pipelined(&nodes_to_read, 7, TimestampsToReturn::Neither, 0.0)
+        .unwrap();
+    assert_eq!(values.len(), 100);
+    values.iter().enumerate().for_each(|(id, value)| {
+        assert_eq!(value.value, Some(Variant::from(id as u32)));
+    });
+    let reads = server
+        .requests()
+        .iter()
+        .filter(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+        .count();
+    assert_eq!(reads, 3 + 15);
+
+    assert_eq!(
+        session
+            .read_pipelined(&[], 7, TimestampsToReturn::Neither, 0.0)
+            .unwrap_err()
+            .status_code(),
+        StatusCode::BadNothingToDo
+    );
+    session.disconnect();
+}
+
+type KeepAliveFailures = Arc<Mutex<Vec<KeepAliveFailure>>>;
+
+/// Connects to the mock server with a keep-alive and runs the session, returning it, the sender
+/// that keeps it running and the keep-alive failures it reports
+fn keep_alive_session(
+    server: &MockServer,
+    pki_dir: &TempDir,
+) -> (
+    Arc<RwLock<Session>>,
+    tokio::sync::oneshot::Sender<SessionCommand>,
+    KeepAliveFailures,
+) {
+    let mut client = ClientBuilder::new()
+        .loopback(true)
+        .application_name("Mock Server Test Client")
+        .application_uri("urn:mock-server-test-client")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .keep_alive_interval(100)
+        .client()
+        .unwrap();
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    {
+        let failures = failures.clone();
+        let mut session = trace_write_lock!(session);
+        assert_eq!(session.keep_alive_interval(), 100);
+        session.set_keep_alive_callback(KeepAliveCallback::new(move |failure| {
+            trace_lock!(failures).push(failure);
+        }));
+    }
+    let run_tx = Session::run_async(session.clone());
+    (session, run_tx, failures)
+}
+
+/// Waits up to a few seconds for the session to lose its connection
+fn wait_for_disconnect(session: &Arc<RwLock<Session>>) {
+    let start = std::time::Instant::now();
+    while trace_read_lock!(session).is_connected() {
+        assert!(start.elapsed().as_secs() < 5, "Session did not disconnect");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn keep_alive_running_server() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-keep-alive:4855/")
+        .read_response(vec![DataValue::value_only(ServerState::Running as i32)])
+        .server()
+        .unwrap();
+    let (session, _run_tx, failures) = keep_alive_session(&server, &pki_dir);
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(trace_read_lock!(session).is_connected());
+    assert!(trace_lock!(failures).is_empty());
+    let reads = server
+        .requests()
+        .iter()
+        .filter(|r| matches!(r, SupportedMessage::ReadRequest(_)))
+        .count();
+    assert!(reads >= 2);
+    trace_read_lock!(session).disconnect();
+}
+
+#[test]
+fn keep_alive_server_shutdown() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-keep-alive-shutdown:4855/")
+        .read_response(vec![DataValue::value_only(ServerState::Shutdown as i32)])
+        .server()
+        .unwrap();
+    let (session, _run_tx, failures) = keep_alive_session(&server, &pki_dir);
+
+    wait_for_disconnect(&session);
+    assert_eq!(
+        *trace_lock!(failures),
+        vec![KeepAliveFailure::ServerState(ServerState::Shutdown)]
+    );
+}
+
+#[test]
+fn keep_alive_server_fault() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-keep-alive-fault:4855/")
+        .fault("ReadRequest", StatusCode::BadServerHalted)
+        .server()
+        .unwrap();
+    let (session, _run_tx, failures) = keep_alive_session(&server, &pki_dir);
+
+    wait_for_disconnect(&session);
+    assert_eq!(
+        *trace_lock!(failures),
+        vec![KeepAliveFailure::Unresponsive(StatusCode::BadServerHalted)]
+    );
+}
+
+#[test]
+fn failover_order() {
+    let mut policy = FailoverPolicy::new(
+        FailoverMode::Warm,
+        vec![
+            "opc.tcp://a:4855/",
+            "opc.tcp://b:4855/",
+            "opc.tcp://c:4855/",
+        ],
+    );
+    assert_eq!(
+        policy.failover_order("opc.tcp://b:4855/"),
+        vec!["opc.tcp://c:4855/", "opc.tcp://a:4855/"]
+    );
+    assert_eq!(
+        policy.failover_order("opc.tcp://c:4855/"),
+        vec!["opc.tcp://a:4855/", "opc.tcp://b:4855/"]
+    );
+
+    // The server the session connects to joins the redundant set
+    policy.include_server("opc.tcp://primary:4855/");
+    policy.include_server("opc.tcp://b:4855/");
+    assert_eq!(policy.server_urls().len(), 4);
+    assert_eq!(
+        policy.failover_order("opc.tcp://primary:4855/"),
+        vec![
+            "opc.tcp://a:4855/",
+            "opc.tcp://b:4855/",
+            "opc.tcp://c:4855/"
+        ]
+    );
+    assert_eq!(
+        policy.failover_order("opc.tcp://c:4855/"),
+        vec![
+            "opc.tcp://primary:4855/",
+            "opc.tcp://a:4855/",
+            "opc.tcp://b:4855/"
+        ]
+    );
+}
+
+/// Connects to a primary mock server that goes away, so the session fails over to a backup in the
+/// mode. Returns the requests that the backup received.
+fn fail_over(name: &str, mode: FailoverMode) -> Vec<SupportedMessage> {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let primary_url = format!("opc.tcp://mock-server-{}-primary:4855/", name);
+    let primary = MockServerBuilder::new(primary_url.as_str())
+        .server()
+        .unwrap();
+    let backup = MockServerBuilder::new(format!("opc.tcp://mock-server-{}-backup:4855/", name))
+        .server()
+        .unwrap();
+    let mut client = ClientBuilder::new()
+        .loopback(true)
+        .application_name("Mock Server Test Client")
+        .application_uri("urn:mock-server-test-client")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(3)
+        .session_retry_interval(500)
+        .redundant_server(format!("opc.tcp://mock-server-{}-unreachable:4855/", name))
+        .redundant_server(backup.endpoint_url())
+        .failover_mode(mode)
+        .client()
+        .unwrap();
+    let session = connect(&mut client, primary.endpoint_url()).unwrap();
+    let failovers = Arc::new(Mutex::new(Vec::new()));
+    {
+        let failovers = failovers.clone();
+        let mut session = trace_write_lock!(session);
+        session.set_failover_callback(FailoverCallback::new(move |from, to| {
+            trace_lock!(failovers).push((from.to_string(), to.to_string()));
+        }));
+    }
+    let _run_tx = Session::run_async(session.clone());
+
+    // The session fails over when the primary server goes away, passing over the unreachable one
+    drop(primary);
+    let start = std::time::Instant::now();
+    while trace_lock!(failovers).is_empty() {
+        assert!(start.elapsed().as_secs() < 10, "Session did not fail over");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(
+        *trace_lock!(failovers),
+        vec![(primary_url, backup.endpoint_url().to_string())]
+    );
+    {
+        let session = trace_read_lock!(session);
+        assert!(session.is_connected());
+        assert_eq!(session.server_url(), backup.endpoint_url());
+        session.disconnect();
+    }
+    backup.requests()
+}
+
+#[test]
+fn failover_cold() {
+    // A cold backup is given a new session
+    let requests = fail_over("cold", FailoverMode::Cold);
+    let names = requests.iter().map(|r| r.name()).collect::<Vec<_>>();
+    assert!(names.contains(&"CreateSessionRequest"));
+    assert!(names.contains(&"ActivateSessionRequest"));
+}
+
+#[test]
+fn failover_hot_and_mirrored() {
+    // A mirrored backup activates the existing session
+    let requests = fail_over("mirrored", FailoverMode::HotAndMirrored);
+    let names = requests.iter().map(|r| r.name()).collect::<Vec<_>>();
+    assert!(!names.contains(&"CreateSessionRequest"));
+    let activate = requests
+        .iter()
+        .find(|r| matches!(r, SupportedMessage::ActivateSessionRequest(_)))
+        .unwrap();
+    assert!(!activate.request_header().authentication_token.is_null());
+}
+
+#[test]
+fn mock_server_aborted_response() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-abort:4855/")
+        .abort("ReadRequest", StatusCode::BadEncodingLimitsExceeded)
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    let session = trace_read_lock!(session);
+
+    // The request fails with the status of the abort chunk
+    let err = session
+        .read(
+            &[ReadValueId::from(NodeId::new(2, "v1"))],
+            TimestampsToReturn::Neither,
+            0f64,
+        )
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BadEncodingLimitsExceeded);
+
+    // The secure channel is still open for further requests
+    let err = session
+        .write(&[WriteValue {
+            node_id: NodeId::new(2, "v1"),
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            value: DataValue::new_now(1i32),
+        }])
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::ServiceUnsupported(_)));
+    assert!(session.is_connected());
+    session.disconnect();
+}
+
+/// A server whose history of a node is returned in two parts, the first with a continuation
+/// point to the second
+fn history_server(endpoint_url: &str) -> MockServer {
+    MockServerBuilder::new(endpoint_url)
+        .respond_to("HistoryReadRequest", |request| {
+            let request = match request {
+                SupportedMessage::HistoryReadRequest(request) => request,
+                _ => panic!(),
+            };
+            let node_to_read = &request.nodes_to_read.as_ref().unwrap()[0];
+            let result = if node_to_read.node_id != NodeId::new(2, "Level") {
+                HistoryReadResult {
+                    status_code: StatusCode::BadNodeIdUnknown,
+                    continuation_point: ByteString::null(),
+                    history_data: ExtensionObject::null(),
+                }
+            } else if request.release_continuation_points {
+                HistoryReadResult {
+                    status_code: StatusCode::Good,
+                    continuation_point: ByteString::null(),
+                    history_data: ExtensionObject::null(),
+                }
+            } else {
+                let (values, continuation_point) = if node_to_read.continuation_point.is_null() {
+                    (vec![1f64, 2f64], ByteString::from(b"part2"))
+                } else {
+                    (vec![3f64], ByteString::null())
+                };
+                let history_data = HistoryData {
+                    data_values: Some(values.into_iter().map(DataValue::new_now).collect()),
+                };
+                HistoryReadResult {
+                    status_code: StatusCode::Good,
+                    continuation_point,
+                    history_data: ExtensionObject::from_encodable(
+                        ObjectId::HistoryData_Encoding_DefaultBinary,
+                        &history_data,
+                    ),
+                }
+            };
+            HistoryReadResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                results: Some(vec![result]),
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .server()
+        .unwrap()
+}
+
+fn history_read_requests(server: &MockServer) -> Vec<HistoryReadRequest> {
+    server
+        .requests()
+        .into_iter()
+        .filter_map(|r| match r {
+            SupportedMessage::HistoryReadRequest(request) => Some(*request),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn history_read_helpers() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = history_server("opc.tcp://mock-server-history-read:4855/");
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        let start_time = DateTime::now() - chrono::Duration::hours(1);
+        let end_time = DateTime::now();
+        let options = HistoryReadOptions::default();
+
+        // The values of both parts are read by following the continuation point
+        let values = session
+            .history_read_raw(NodeId::new(2, "Level"), start_time, end_time, &options)
+            .map(|value| value.unwrap().value.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Variant::from(1f64),
+                Variant::from(2f64),
+                Variant::from(3f64)
+            ]
+        );
+        let requests = history_read_requests(&server);
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].nodes_to_read.as_ref().unwrap()[0]
+            .continuation_point
+            .is_null());
+        assert_eq!(
+            requests[1].nodes_to_read.as_ref().unwrap()[0].continuation_point,
+            ByteString::from(b"part2")
+        );
+        assert!(!requests[1].release_continuation_points);
+
+        // A continuation point that is left when the values are dropped is released
+        {
+            let mut values = session.history_read_processed(
+                NodeId::new(2, "Level"),
+                start_time,
+                end_time,
+                60000f64,
+                ObjectId::AggregateFunction_Average,
+                &options,
+            );
+            assert_eq!(
+                values.next().unwrap().unwrap().value,
+                Some(Variant::from(1f64))
+            );
+        }
+        let requests = history_read_requests(&server);
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[2].history_read_details.node_id,
+            ObjectId::ReadProcessedDetails_Encoding_DefaultBinary.into()
+        );
+        assert!(requests[3].release_continuation_points);
+        assert_eq!(
+            requests[3].nodes_to_read.as_ref().unwrap()[0].continuation_point,
+            ByteString::from(b"part2")
+        );
+
+        // The values end with the reason that they could not be read
+        let mut values =
+            session.history_read_at_time(NodeId::new(2, "Pump1"), &[end_time], &options);
+        assert_eq!(
+            values.next().unwrap().unwrap_err().status_code(),
+            StatusCode::BadNodeIdUnknown
+        );
+        assert!(values.next().is_none());
+        drop(values);
+        let requests = history_read_requests(&server);
+        assert_eq!(requests.len(), 5);
+        assert_eq!(
+            requests[4].history_read_details.node_id,
+            ObjectId::ReadAtTimeDetails_Encoding_DefaultBinary.into()
+        );
+        session.disconnect();
+    }
+}
+
+#[test]
+fn request_timeout_restored_after_panic() {
+    let pki_dir = TempDir::new("mock_server").unwrap();
+    let server = MockServerBuilder::new("opc.tcp://mock-server-request-timeout:4855/")
+        .read_response(vec![DataValue::new_now(123i32)])
+        .server()
+        .unwrap();
+
+    let mut client = mock_client(&pki_dir);
+    let session = connect(&mut client, server.endpoint_url()).unwrap();
+    {
+        let session = trace_read_lock!(session);
+        let read = || {
+            session
+                .read(
+                    &[ReadValueId::from(NodeId::new(2, "v1"))],
+                    TimestampsToReturn::Neither,
+                    0f64,
+                )
+                .unwrap();
+        };
+        read();
+
+        // The override is gone once the function panics
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            session.with_request_timeout(1234, |_| panic!("Function panicked"))
+        }));
+        assert!(result.is_err());
+        read();
+        session.disconnect();
+    }
+
+    let timeout_hints = server
+        .requests()
+        .into_iter()
+        .filter_map(|r| match r {
+            SupportedMessage::ReadRequest(r) => Some(r.request_header.timeout_hint),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(timeout_hints.len(), 2);
+    assert_eq!(timeout_hints[0], timeout_hints[1]);
+    assert_ne!(timeout_hints[1], 1234);
+}
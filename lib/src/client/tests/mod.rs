@@ -1,14 +1,26 @@
-use std::{self, collections::BTreeMap, path::PathBuf};
+use std::{
+    self,
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 use crate::core::config::Config;
-use crate::crypto::SecurityPolicy;
+use crate::crypto::{SecurityPolicy, SystemCertificateStore, SystemStoreLocation};
 use crate::types::*;
 
 use crate::client::{
     builder::ClientBuilder,
+    callbacks::{EventFieldsCallback, OnSubscriptionNotification},
     config::{ClientConfig, ClientEndpoint, ClientUserToken, ANONYMOUS_USER_TOKEN_ID},
 };
 
+mod message_queue;
+mod mock_server;
+mod monitored_item_cache;
+mod proxy;
+mod request_window;
+
 fn make_test_file(filename: &str) -> PathBuf {
     let mut path = std::env::temp_dir();
     path.push(filename);
@@ -143,6 +155,30 @@ fn client_invalid_security_mode_config() {
     assert!(!config.is_valid());
 }
 
+#[test]
+fn client_system_certificate_store_config() {
+    let system_certificate_store = SystemCertificateStore {
+        location: SystemStoreLocation::CurrentUser,
+        own_store: "My".to_string(),
+        own_thumbprint: "F5 BA A2 ED 4C 1B 0E 1F B4 D1 2F 6A 58 7A 3B 1D 21 59 3E 7C".to_string(),
+        trusted_store: "TrustedPeople".to_string(),
+    };
+    // The store of the operating system is only supported on Windows
+    let config = sample_builder()
+        .system_certificate_store(system_certificate_store.clone())
+        .config();
+    assert_eq!(config.is_valid(), SystemCertificateStore::is_supported());
+
+    // The thumbprint must be a SHA-1 thumbprint
+    let config = sample_builder()
+        .system_certificate_store(SystemCertificateStore {
+            own_thumbprint: "F5 BA A2 ED".to_string(),
+            ..system_certificate_store
+        })
+        .config();
+    assert!(!config.is_valid());
+}
+
 #[test]
 fn client_anonymous_user_tokens_id() {
     let mut config = default_sample_config();
@@ -159,3 +195,83 @@ fn client_anonymous_user_tokens_id() {
     );
     assert!(!config.is_valid());
 }
+
+#[test]
+fn event_fields_callback() {
+    let event_filter = EventFilterBuilder::new()
+        .select(ObjectTypeId::BaseEventType, "Message")
+        .select(ObjectTypeId::BaseEventType, r"Name\/With\/Slashes")
+        .select_operand(
+            SimpleAttributeOperandBuilder::new()
+                .browse_name(QualifiedName::new(2, "Sensor"))
+                .browse_name(QualifiedName::new(2, "Reading"))
+                .build(),
+        )
+        .select_condition_id()
+        .where_clause(
+            ContentFilterBuilder::new()
+                .of_type(ObjectTypeId::BaseEventType)
+                .build(),
+        )
+        .build();
+
+    let select_clauses = event_filter.select_clauses.as_ref().unwrap();
+    assert_eq!(select_clauses.len(), 4);
+    assert_eq!(
+        select_clauses[2].browse_path,
+        Some(vec![
+            QualifiedName::new(2, "Sensor"),
+            QualifiedName::new(2, "Reading")
+        ])
+    );
+    assert_eq!(select_clauses[3].attribute_id, AttributeId::NodeId as u32);
+    assert_eq!(
+        event_filter.where_clause.elements.as_ref().unwrap().len(),
+        1
+    );
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let mut callback = {
+        let received = received.clone();
+        EventFieldsCallback::new(&event_filter, move |client_handle, event_fields| {
+            received
+                .lock()
+                .unwrap()
+                .push((client_handle, event_fields.clone()));
+        })
+    };
+
+    // The last event has fewer fields than there are select clauses
+    let condition_id = NodeId::new(2, "Condition");
+    callback.on_event(&EventNotificationList {
+        events: Some(vec![
+            EventFieldList {
+                client_handle: 10,
+                event_fields: Some(vec![
+                    Variant::from("Hello"),
+                    Variant::from(1),
+                    Variant::from(2.5),
+                    Variant::from(condition_id.clone()),
+                ]),
+            },
+            EventFieldList {
+                client_handle: 11,
+                event_fields: Some(vec![Variant::from("World")]),
+            },
+        ]),
+    });
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    let (client_handle, ref event_fields) = received[0];
+    assert_eq!(client_handle, 10);
+    assert_eq!(event_fields.len(), 4);
+    assert_eq!(event_fields["Message"], Variant::from("Hello"));
+    assert_eq!(event_fields[r"Name\/With\/Slashes"], Variant::from(1));
+    assert_eq!(event_fields["Sensor/Reading"], Variant::from(2.5));
+    assert_eq!(event_fields["ConditionId"], Variant::from(condition_id));
+    let (client_handle, ref event_fields) = received[1];
+    assert_eq!(client_handle, 11);
+    assert_eq!(event_fields.len(), 1);
+    assert_eq!(event_fields["Message"], Variant::from("World"));
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::sync::*;
+use crate::types::{service_types::*, *};
+
+use crate::client::{
+    callbacks::DataChangeCallback,
+    subscription::{CreateMonitoredItem, Subscription},
+    subscription_state::SubscriptionState,
+};
+
+const SUBSCRIPTION_ID: u32 = 1;
+
+fn make_subscription_state(node_ids: &[NodeId]) -> SubscriptionState {
+    let mut subscription_state = SubscriptionState::new();
+    subscription_state.add_subscription(Subscription::new(
+        SUBSCRIPTION_ID,
+        1000.0,
+        30,
+        10,
+        0,
+        true,
+        0,
+        Arc::new(Mutex::new(DataChangeCallback::new(|_| {}))),
+    ));
+    let items_to_create = node_ids
+        .iter()
+        .enumerate()
+        .map(|(i, node_id)| CreateMonitoredItem {
+            id: i as u32 + 1,
+            client_handle: i as u32 + 100,
+            item_to_monitor: node_id.clone().into(),
+            monitoring_mode: MonitoringMode::Reporting,
+            queue_size: 1,
+            discard_oldest: true,
+            sampling_interval: 100.0,
+        })
+        .collect::<Vec<_>>();
+    subscription_state.insert_monitored_items(SUBSCRIPTION_ID, &items_to_create);
+    subscription_state
+}
+
+fn data_change(client_handle: u32, value: i32) -> DataChangeNotification {
+    DataChangeNotification {
+        monitored_items: Some(vec![MonitoredItemNotification {
+            client_handle,
+            value: DataValue::from(value),
+        }]),
+        diagnostic_infos: None,
+    }
+}
+
+#[test]
+fn cache_disabled_by_default() {
+    let subscription_state = make_subscription_state(&[NodeId::new(2, "v1")]);
+    assert!(subscription_state.monitored_item_cache().is_none());
+}
+
+#[test]
+fn cache_updates_on_data_change() {
+    let v1 = NodeId::new(2, "v1");
+    let v2 = NodeId::new(2, "v2");
+    let mut subscription_state = make_subscription_state(&[v1.clone(), v2.clone()]);
+    let cache = subscription_state.enable_monitored_item_cache();
+
+    // Seeded with null values from the existing monitored items
+    assert_eq!(trace_read_lock!(cache).len(), 2);
+    assert_eq!(
+        trace_read_lock!(cache).value(&v1).unwrap(),
+        DataValue::null()
+    );
+
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(100, 10)]);
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(101, 20)]);
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(100, 11)]);
+
+    let cache = trace_read_lock!(cache);
+    assert_eq!(cache.value(&v1).unwrap().value, Some(Variant::Int32(11)));
+    assert_eq!(cache.value(&v2).unwrap().value, Some(Variant::Int32(20)));
+    assert!(cache.value(&NodeId::new(2, "v3")).is_none());
+
+    let snapshot = cache.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(
+        snapshot[&(v2.clone(), AttributeId::Value as u32)].value,
+        Some(Variant::Int32(20))
+    );
+
+    let values = cache.values(&[v2.clone(), NodeId::new(2, "v3")]);
+    assert_eq!(values.len(), 1);
+    assert!(values.contains_key(&v2));
+}
+
+#[test]
+fn cache_watch() {
+    let v1 = NodeId::new(2, "v1");
+    let mut subscription_state = make_subscription_state(std::slice::from_ref(&v1));
+    let cache = subscription_state.enable_monitored_item_cache();
+    let mut receiver = trace_write_lock!(cache).watch(&v1);
+
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(100, 5)]);
+    assert!(receiver.has_changed().unwrap());
+    assert_eq!(receiver.borrow_and_update().value, Some(Variant::Int32(5)));
+    assert!(!receiver.has_changed().unwrap());
+
+    // Deleting the monitored item removes it from the cache and closes the channel
+    subscription_state.delete_monitored_items(SUBSCRIPTION_ID, &[1]);
+    assert!(trace_read_lock!(cache).value(&v1).is_none());
+    assert!(receiver.has_changed().is_err());
+}
+
+#[test]
+fn cache_watch_unmonitored() {
+    let v1 = NodeId::new(2, "v1");
+    let v2 = NodeId::new(2, "v2");
+    let mut subscription_state = make_subscription_state(std::slice::from_ref(&v1));
+    let cache = subscription_state.enable_monitored_item_cache();
+
+    // Watching a node that is not monitored does not add it to the cache
+    let receiver = trace_write_lock!(cache).watch(&v2);
+    assert!(receiver.borrow().value.is_none());
+
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(100, 5)]);
+    let cache = trace_read_lock!(cache);
+    assert_eq!(cache.len(), 1);
+    assert!(cache.value(&v2).is_none());
+    let snapshot = cache.snapshot();
+    assert_eq!(snapshot.len(), 1);
+    assert!(snapshot.contains_key(&(v1.clone(), AttributeId::Value as u32)));
+}
+
+#[test]
+fn cache_retains_values_monitored_elsewhere() {
+    let v1 = NodeId::new(2, "v1");
+    let mut subscription_state = make_subscription_state(&[v1.clone(), v1.clone()]);
+    let cache = subscription_state.enable_monitored_item_cache();
+    subscription_state.on_data_change(SUBSCRIPTION_ID, &[data_change(101, 7)]);
+
+    // Item 2 still monitors v1 so the value stays
+    subscription_state.delete_monitored_items(SUBSCRIPTION_ID, &[1]);
+    assert_eq!(
+        trace_read_lock!(cache).value(&v1).unwrap().value,
+        Some(Variant::Int32(7))
+    );
+
+    subscription_state.delete_subscription(SUBSCRIPTION_ID);
+    assert!(trace_read_lock!(cache).is_empty());
+}
@@ -0,0 +1,38 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use crate::client::request_window::RequestWindow;
+use crate::types::status_code::StatusCode;
+
+#[test]
+fn request_window_limits_slots() {
+    let window = Arc::new(RequestWindow::new(2));
+    let slot1 = window.clone().acquire(0).unwrap();
+    let _slot2 = window.clone().acquire(0).unwrap();
+
+    // The window is full
+    assert_eq!(
+        window.clone().acquire(50).err(),
+        Some(StatusCode::BadTimeout)
+    );
+
+    // A slot that is given back from another thread lets a waiting request through
+    let releaser = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(slot1);
+    });
+    let _slot3 = window.clone().acquire(5000).unwrap();
+    releaser.join().unwrap();
+    assert_eq!(
+        window.clone().acquire(0).err(),
+        Some(StatusCode::BadTimeout)
+    );
+}
+
+#[test]
+fn request_window_unlimited() {
+    let window = Arc::new(RequestWindow::new(0));
+    let slots = (0..100)
+        .map(|_| window.clone().acquire(0).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(slots.len(), 100);
+}
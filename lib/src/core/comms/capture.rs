@@ -0,0 +1,422 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Wire level capture and replay of the chunks that pass over a transport.
+//!
+//! A capture is enabled for the whole process by calling [`set_capture`] with a [`CaptureWriter`].
+//! From then on the client and server transports record every chunk they send and receive in
+//! its raw on-the-wire form. If the writer was created with `include_decrypted` set, chunks are
+//! also recorded without their security, i.e. sent chunks before they are signed and encrypted
+//! and received chunks after their security has been verified and removed. That form exposes
+//! the plaintext of encrypted traffic, including secrets such as passwords, so it should only be
+//! enabled when debugging on a system that is permitted to observe it.
+//!
+//! A capture file can be read back with a [`CaptureReader`] and its chunks fed back into the
+//! decoder with [`replay`], which is how interop issues can be debugged offline. The
+//! `opcua-capture-replay` tool does this from the command line.
+//!
+//! [`set_capture`]: ./fn.set_capture.html
+//! [`CaptureWriter`]: ./struct.CaptureWriter.html
+//! [`CaptureReader`]: ./struct.CaptureReader.html
+//! [`replay`]: ./fn.replay.html
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Cursor, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::core::{
+    comms::{
+        chunker::Chunker,
+        message_chunk::{MessageChunk, MessageIsFinalType},
+        secure_channel::SecureChannel,
+        tcp_types::{CHUNK_MESSAGE, CLOSE_SECURE_CHANNEL_MESSAGE, OPEN_SECURE_CHANNEL_MESSAGE},
+    },
+    supported_message::SupportedMessage,
+};
+use crate::sync::*;
+use crate::types::{encoding::*, io, status_code::StatusCode, DateTime};
+
+/// The first bytes of every capture file
+const CAPTURE_MAGIC: &[u8; 8] = b"OPCUACAP";
+/// The version of the capture file format
+const CAPTURE_VERSION: u32 = 1;
+
+/// Whether a captured chunk was sent or received by this process
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CaptureDirection {
+    Received = 0,
+    Sent = 1,
+}
+
+/// The form of a captured chunk
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaptureForm {
+    /// The bytes exactly as they were on the wire
+    Raw = 0,
+    /// The chunk without its security, i.e. before it was signed and encrypted when sent, or after
+    /// its signature was verified and it was decrypted when received
+    Decrypted = 1,
+}
+
+/// A single captured chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRecord {
+    /// When the chunk was captured
+    pub timestamp: DateTime,
+    pub direction: CaptureDirection,
+    pub form: CaptureForm,
+    /// The data. Raw sent data may hold several chunks and other messages such as an acknowledge.
+    pub data: Vec<u8>,
+}
+
+impl BinaryEncoder<CaptureRecord> for CaptureRecord {
+    fn byte_len(&self) -> usize {
+        self.timestamp.byte_len() + 1 + 1 + 4 + self.data.len()
+    }
+
+    fn encode<S: io::Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        let mut size = self.timestamp.encode(stream)?;
+        size += write_u8(stream, self.direction as u8)?;
+        size += write_u8(stream, self.form as u8)?;
+        size += write_u32(stream, self.data.len() as u32)?;
+        size += process_encode_io_result(stream.write(&self.data))?;
+        Ok(size)
+    }
+
+    fn decode<S: io::Read>(
+        stream: &mut S,
+        decoding_options: &DecodingOptions,
+    ) -> EncodingResult<Self> {
+        let timestamp = DateTime::decode(stream, decoding_options)?;
+        let direction = match read_u8(stream)? {
+            0 => CaptureDirection::Received,
+            1 => CaptureDirection::Sent,
+            v => {
+                error!("Invalid capture direction {}", v);
+                return Err(StatusCode::BadDecodingError);
+            }
+        };
+        let form = match read_u8(stream)? {
+            0 => CaptureForm::Raw,
+            1 => CaptureForm::Decrypted,
+            v => {
+                error!("Invalid capture form {}", v);
+                return Err(StatusCode::BadDecodingError);
+            }
+        };
+        let len = read_u32(stream)? as usize;
+        if decoding_options.max_message_size > 0 && len > decoding_options.max_message_size {
+            error!(
+                "Captured record of {} bytes exceeds the max message size",
+                len
+            );
+            return Err(StatusCode::BadDecodingError);
+        }
+        let mut data = vec![0u8; len];
+        read_bytes(stream, &mut data)?;
+        Ok(CaptureRecord {
+            timestamp,
+            direction,
+            form,
+            data,
+        })
+    }
+}
+
+/// Writes capture records to a file or any other stream.
+pub struct CaptureWriter {
+    stream: Box<dyn Write + Send + Sync>,
+    include_decrypted: bool,
+}
+
+impl CaptureWriter {
+    /// Creates a writer over the stream and writes the capture header to it. If `include_decrypted`
+    /// is true then received chunks are also recorded after their security is removed.
+    pub fn new(
+        mut stream: Box<dyn Write + Send + Sync>,
+        include_decrypted: bool,
+    ) -> Result<CaptureWriter, StatusCode> {
+        process_encode_io_result(io::Write::write(&mut stream, CAPTURE_MAGIC))?;
+        write_u32(&mut stream, CAPTURE_VERSION)?;
+        Ok(CaptureWriter {
+            stream,
+            include_decrypted,
+        })
+    }
+
+    /// Creates a capture file at the path, replacing any file that is already there.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        include_decrypted: bool,
+    ) -> Result<CaptureWriter, StatusCode> {
+        let file = File::create(path.as_ref()).map_err(|err| {
+            error!(
+                "Cannot create capture file {}, error = {}",
+                path.as_ref().display(),
+                err
+            );
+            StatusCode::BadUnexpectedError
+        })?;
+        Self::new(Box::new(BufWriter::new(file)), include_decrypted)
+    }
+
+    /// Tests if decrypted chunks are recorded
+    pub fn include_decrypted(&self) -> bool {
+        self.include_decrypted
+    }
+
+    /// Writes a record to the capture and flushes it so nothing is lost if the process dies.
+    pub fn write_record(&mut self, record: &CaptureRecord) -> Result<(), StatusCode> {
+        record.encode(&mut self.stream)?;
+        self.stream
+            .flush()
+            .map_err(|_| StatusCode::BadEncodingError)
+    }
+}
+
+/// Reads capture records back from a file or any other stream.
+pub struct CaptureReader<R: BufRead> {
+    stream: R,
+    decoding_options: DecodingOptions,
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Opens a capture file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StatusCode> {
+        let file = File::open(path.as_ref()).map_err(|err| {
+            error!(
+                "Cannot open capture file {}, error = {}",
+                path.as_ref().display(),
+                err
+            );
+            StatusCode::BadUnexpectedError
+        })?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> CaptureReader<R> {
+    /// Creates a reader over the stream, checking that it starts with a capture header.
+    pub fn new(mut stream: R) -> Result<Self, StatusCode> {
+        let mut magic = [0u8; 8];
+        read_bytes(&mut stream, &mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            error!("Stream is not an OPC UA capture");
+            return Err(StatusCode::BadDecodingError);
+        }
+        let version = read_u32(&mut stream)?;
+        if version != CAPTURE_VERSION {
+            error!("Capture version {} is not supported", version);
+            return Err(StatusCode::BadDecodingError);
+        }
+        Ok(CaptureReader {
+            stream,
+            decoding_options: DecodingOptions::default(),
+        })
+    }
+
+    /// Reads the next record, returning `None` at the end of the capture.
+    pub fn read_record(&mut self) -> Result<Option<CaptureRecord>, StatusCode> {
+        let at_end = self
+            .stream
+            .fill_buf()
+            .map_err(|_| StatusCode::BadDecodingError)?
+            .is_empty();
+        if at_end {
+            Ok(None)
+        } else {
+            CaptureRecord::decode(&mut self.stream, &self.decoding_options).map(Some)
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord, StatusCode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+lazy_static! {
+    static ref CAPTURE: Mutex<Option<CaptureWriter>> = Mutex::new(None);
+}
+
+/// Set while a capture is set, so chunks can be passed over without taking the lock of the
+/// capture when nothing is capturing them.
+static CAPTURE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Starts capturing the chunks of every transport in the process into the writer, replacing
+/// any previous capture.
+pub fn set_capture(writer: CaptureWriter) {
+    let mut capture = trace_lock!(CAPTURE);
+    *capture = Some(writer);
+    CAPTURE_ENABLED.store(true, Ordering::Release);
+}
+
+/// Stops capturing, closing the writer.
+pub fn clear_capture() {
+    let mut capture = trace_lock!(CAPTURE);
+    *capture = None;
+    CAPTURE_ENABLED.store(false, Ordering::Release);
+}
+
+/// Records a chunk if a capture is set. A capture that fails to write is stopped.
+pub(crate) fn capture(direction: CaptureDirection, form: CaptureForm, data: &[u8]) {
+    if !CAPTURE_ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    let mut capture = trace_lock!(CAPTURE);
+    if let Some(ref mut writer) = *capture {
+        if form == CaptureForm::Decrypted && !writer.include_decrypted {
+            return;
+        }
+        let record = CaptureRecord {
+            timestamp: DateTime::now(),
+            direction,
+            form,
+            data: data.to_vec(),
+        };
+        if let Err(err) = writer.write_record(&record) {
+            error!(
+                "Capture has been stopped because it cannot be written, error = {}",
+                err
+            );
+            *capture = None;
+            CAPTURE_ENABLED.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// A message reconstructed from a capture by [`replay`].
+///
+/// [`replay`]: ./fn.replay.html
+#[derive(Debug)]
+pub struct ReplayedMessage {
+    /// The timestamp of the final chunk of the message
+    pub timestamp: DateTime,
+    pub direction: CaptureDirection,
+    pub secure_channel_id: u32,
+    pub request_id: u32,
+    /// The decoded message, or the reason it could not be decoded
+    pub message: Result<SupportedMessage, StatusCode>,
+}
+
+/// Feeds captured chunks back into the decoder and returns the messages they make up.
+///
+/// Each direction is replayed from its decrypted chunks if the capture holds any, since raw chunks
+/// of a secure channel cannot be decoded without its keys. Otherwise the raw chunks of the
+/// direction are replayed, which works for channels with a security policy of None. Records which
+/// are not chunks, e.g. a hello or acknowledge, are skipped.
+pub fn replay<I>(records: I, decoding_options: DecodingOptions) -> Vec<ReplayedMessage>
+where
+    I: IntoIterator<Item = CaptureRecord>,
+{
+    let records: Vec<CaptureRecord> = records.into_iter().collect();
+    let form = |direction: CaptureDirection| {
+        if records
+            .iter()
+            .any(|r| r.direction == direction && r.form == CaptureForm::Decrypted)
+        {
+            CaptureForm::Decrypted
+        } else {
+            CaptureForm::Raw
+        }
+    };
+    let received_form = form(CaptureDirection::Received);
+    let sent_form = form(CaptureDirection::Sent);
+
+    let mut secure_channel = SecureChannel::new_no_certificate_store();
+    secure_channel.set_decoding_options(decoding_options.clone());
+
+    // Chunks are accumulated per direction, channel and request until the final chunk arrives
+    let mut pending: HashMap<(CaptureDirection, u32, u32), Vec<MessageChunk>> = HashMap::new();
+    let mut messages = Vec::new();
+
+    records
+        .iter()
+        .filter(|r| {
+            r.form
+                == match r.direction {
+                    CaptureDirection::Received => received_form,
+                    CaptureDirection::Sent => sent_form,
+                }
+        })
+        .for_each(|record| {
+            split_chunks(&record.data, &decoding_options)
+                .into_iter()
+                .for_each(|chunk| {
+                    let chunk_info = match chunk.chunk_info(&secure_channel) {
+                        Ok(chunk_info) => chunk_info,
+                        Err(err) => {
+                            messages.push(ReplayedMessage {
+                                timestamp: record.timestamp,
+                                direction: record.direction,
+                                secure_channel_id: 0,
+                                request_id: 0,
+                                message: Err(err),
+                            });
+                            return;
+                        }
+                    };
+                    let secure_channel_id = chunk_info.message_header.secure_channel_id;
+                    let request_id = chunk_info.sequence_header.request_id;
+                    let key = (record.direction, secure_channel_id, request_id);
+                    match chunk_info.message_header.is_final {
+                        MessageIsFinalType::Intermediate => {
+                            pending.entry(key).or_default().push(chunk);
+                        }
+                        MessageIsFinalType::FinalError => {
+                            pending.remove(&key);
+                        }
+                        MessageIsFinalType::Final => {
+                            let mut chunks = pending.remove(&key).unwrap_or_default();
+                            chunks.push(chunk);
+                            messages.push(ReplayedMessage {
+                                timestamp: record.timestamp,
+                                direction: record.direction,
+                                secure_channel_id,
+                                request_id,
+                                message: Chunker::decode(&chunks, &secure_channel, None),
+                            });
+                        }
+                    }
+                });
+        });
+    messages
+}
+
+/// Splits captured data into the chunks it contains, skipping any other kinds of message.
+fn split_chunks(data: &[u8], decoding_options: &DecodingOptions) -> Vec<MessageChunk> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    // Every message starts with a 3 byte type, a 1 byte final flag and a 4 byte size
+    while data.len() - offset >= 8 {
+        let message_type = &data[offset..offset + 3];
+        let message_size = {
+            let mut stream = Cursor::new(&data[offset + 4..offset + 8]);
+            read_u32(&mut stream).unwrap_or(0) as usize
+        };
+        if message_size < 8 || offset + message_size > data.len() {
+            error!("Captured data contains a truncated message");
+            break;
+        }
+        if message_type == CHUNK_MESSAGE
+            || message_type == OPEN_SECURE_CHANNEL_MESSAGE
+            || message_type == CLOSE_SECURE_CHANNEL_MESSAGE
+        {
+            let mut stream = Cursor::new(&data[offset..offset + message_size]);
+            match MessageChunk::decode(&mut stream, decoding_options) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(err) => error!("Cannot decode captured chunk, error = {}", err),
+            }
+        }
+        offset += message_size;
+    }
+    chunks
+}
@@ -16,7 +16,8 @@ use crate::{
     },
     crypto::SecurityPolicy,
     types::{
-        encoding::BinaryEncoder, node_id::NodeId, node_ids::ObjectId, status_code::StatusCode,
+        encoding::BinaryEncoder, node_id::NodeId, node_ids::ObjectId,
+        request_header::RequestHeader, status_code::StatusCode, string::UAString,
     },
 };
 
@@ -25,7 +26,7 @@ pub struct Chunker;
 
 impl Chunker {
     /// Tests what kind of chunk type is used for the supported message.
-    fn message_type(message: &SupportedMessage) -> MessageChunkType {
+    pub fn message_type(message: &SupportedMessage) -> MessageChunkType {
         match message {
             SupportedMessage::OpenSecureChannelRequest(_)
             | SupportedMessage::OpenSecureChannelResponse(_) => MessageChunkType::OpenSecureChannel,
@@ -194,6 +195,50 @@ impl Chunker {
         }
     }
 
+    /// Creates the abort chunk that ends a message whose other chunks have been sent, when the rest
+    /// of it cannot be. It tells the other end to abandon the message with the error, while the
+    /// secure channel stays open.
+    pub fn encode_abort(
+        sequence_number: u32,
+        request_id: u32,
+        message_type: MessageChunkType,
+        secure_channel: &SecureChannel,
+        status_code: StatusCode,
+    ) -> std::result::Result<MessageChunk, StatusCode> {
+        // The body is the error followed by a reason
+        let reason = UAString::from(status_code.description());
+        let mut stream = Cursor::new(Vec::new());
+        let _ = status_code.encode(&mut stream)?;
+        let _ = reason.encode(&mut stream)?;
+        MessageChunk::new(
+            sequence_number,
+            request_id,
+            message_type,
+            MessageIsFinalType::FinalError,
+            secure_channel,
+            &stream.into_inner(),
+        )
+    }
+
+    /// Decodes the error and reason in the body of an abort chunk. The chunk must be decrypted /
+    /// verified by now.
+    pub fn decode_abort(
+        chunk: &MessageChunk,
+        secure_channel: &SecureChannel,
+    ) -> std::result::Result<(StatusCode, UAString), StatusCode> {
+        let chunk_info = chunk.chunk_info(secure_channel)?;
+        if chunk_info.message_header.is_final != MessageIsFinalType::FinalError {
+            return Err(StatusCode::BadDecodingError);
+        }
+        let body_start = chunk_info.body_offset;
+        let body_end = body_start + chunk_info.body_length;
+        let mut stream = Cursor::new(&chunk.data[body_start..body_end]);
+        let decoding_options = secure_channel.decoding_options();
+        let status_code = StatusCode::decode(&mut stream, &decoding_options)?;
+        let reason = UAString::decode(&mut stream, &decoding_options)?;
+        Ok((status_code, reason))
+    }
+
     /// Decodes a series of chunks to create a message. The message must be of a `SupportedMessage`
     /// type otherwise an error will occur.
     pub fn decode(
@@ -201,6 +246,56 @@ impl Chunker {
         secure_channel: &SecureChannel,
         expected_node_id: Option<NodeId>,
     ) -> std::result::Result<SupportedMessage, StatusCode> {
+        let mut data = Self::message_body(chunks, secure_channel)?;
+
+        // The extension object prefix is just the node id. A point the spec rather unhelpfully doesn't
+        // elaborate on. Probably because people enjoy debugging why the stream pos is out by 1 byte
+        // for hours.
+
+        let decoding_options = secure_channel.decoding_options();
+
+        // Read node id from stream
+        let node_id = NodeId::decode(&mut data, &decoding_options)?;
+        let object_id = Self::object_id_from_node_id(node_id, expected_node_id)?;
+
+        // Now decode the payload using the node id.
+        match SupportedMessage::decode_by_object_id(&mut data, object_id, &decoding_options) {
+            Ok(decoded_message) => {
+                if let SupportedMessage::Invalid(_) = decoded_message {
+                    debug!("Message {:?} is unsupported", object_id);
+                    Err(StatusCode::BadServiceUnsupported)
+                } else {
+                    // debug!("Returning decoded msg {:?}", decoded_message);
+                    Ok(decoded_message)
+                }
+            }
+            Err(err) => {
+                debug!("Cannot decode message {:?}, err = {:?}", object_id, err);
+                Err(StatusCode::BadServiceUnsupported)
+            }
+        }
+    }
+
+    /// Decodes the request header of a request from a series of chunks without decoding the rest
+    /// of the request. A request whose message is unsupported, e.g. because its service set
+    /// is not compiled in, can still be answered with a service fault this way.
+    pub fn decode_request_header(
+        chunks: &[MessageChunk],
+        secure_channel: &SecureChannel,
+    ) -> std::result::Result<RequestHeader, StatusCode> {
+        let mut data = Self::message_body(chunks, secure_channel)?;
+        let decoding_options = secure_channel.decoding_options();
+        let node_id = NodeId::decode(&mut data, &decoding_options)?;
+        let _ = Self::object_id_from_node_id(node_id, None)?;
+        // Every request starts with its request header
+        RequestHeader::decode(&mut data, &decoding_options)
+    }
+
+    /// Reads the body of a series of chunks into a stream
+    fn message_body(
+        chunks: &[MessageChunk],
+        secure_channel: &SecureChannel,
+    ) -> std::result::Result<Cursor<Vec<u8>>, StatusCode> {
         // Calculate the size of data held in all chunks
         let mut data_size: usize = 0;
         for (i, chunk) in chunks.iter().enumerate() {
@@ -233,34 +328,7 @@ impl Chunker {
         }
 
         // Make a stream around the data
-        let mut data = Cursor::new(data);
-
-        // The extension object prefix is just the node id. A point the spec rather unhelpfully doesn't
-        // elaborate on. Probably because people enjoy debugging why the stream pos is out by 1 byte
-        // for hours.
-
-        let decoding_options = secure_channel.decoding_options();
-
-        // Read node id from stream
-        let node_id = NodeId::decode(&mut data, &decoding_options)?;
-        let object_id = Self::object_id_from_node_id(node_id, expected_node_id)?;
-
-        // Now decode the payload using the node id.
-        match SupportedMessage::decode_by_object_id(&mut data, object_id, &decoding_options) {
-            Ok(decoded_message) => {
-                if let SupportedMessage::Invalid(_) = decoded_message {
-                    debug!("Message {:?} is unsupported", object_id);
-                    Err(StatusCode::BadServiceUnsupported)
-                } else {
-                    // debug!("Returning decoded msg {:?}", decoded_message);
-                    Ok(decoded_message)
-                }
-            }
-            Err(err) => {
-                debug!("Cannot decode message {:?}, err = {:?}", object_id, err);
-                Err(StatusCode::BadServiceUnsupported)
-            }
-        }
+        Ok(Cursor::new(data))
     }
 
     fn object_id_from_node_id(
@@ -0,0 +1,143 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! A transport that connects a client to a server on the same host over a Unix domain socket, or
+//! a named pipe on Windows, instead of TCP. Messages are framed exactly as they are over TCP, but
+//! there is no TCP stack in between and no port to open in a firewall.
+//!
+//! An endpoint url with the `opc.unix` scheme holds the path of the socket, percent-encoded, as
+//! its host, e.g. `opc.unix://%2Frun%2Fopcua.sock/` for the socket `/run/opcua.sock`, or
+//! `opc.unix://%5C%5C.%5Cpipe%5Copcua/` for the pipe `\\.\pipe\opcua`. A server that is
+//! configured with a local socket accepts connections on a [`LocalListener`] bound to it as well
+//! as on its TCP port, and advertises its endpoints with `opc.unix` urls to clients that
+//! connected through it.
+//!
+//! [`LocalListener`]: ./struct.LocalListener.html
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A connected local socket or named pipe
+pub trait LocalStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T> LocalStream for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
+/// Accepts connections on a Unix domain socket, or a named pipe on Windows
+pub struct LocalListener {
+    path: String,
+    #[cfg(unix)]
+    listener: tokio::net::UnixListener,
+    /// The instance of the pipe that the next client connects to
+    #[cfg(windows)]
+    next_instance: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+#[cfg(unix)]
+impl Drop for LocalListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl LocalListener {
+    /// Binds a listener to the socket path, which must be called on a tokio runtime. On Unix, a
+    /// socket that an earlier listener left behind is replaced, but another kind of file at the
+    /// path is an error. The socket is removed when the listener is dropped.
+    #[cfg(unix)]
+    pub fn bind(path: &str) -> io::Result<LocalListener> {
+        use std::os::unix::fs::FileTypeExt;
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                debug!("Removing the socket {} that was left behind", path);
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = tokio::net::UnixListener::bind(path)?;
+        Ok(LocalListener {
+            path: path.to_string(),
+            listener,
+        })
+    }
+
+    /// Binds a listener to the pipe name, which must be called on a tokio runtime. Fails if
+    /// another listener has created the pipe.
+    #[cfg(windows)]
+    pub fn bind(path: &str) -> io::Result<LocalListener> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        let next_instance = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(path)?;
+        Ok(LocalListener {
+            path: path.to_string(),
+            next_instance,
+        })
+    }
+
+    /// Fails because there are no local sockets on this platform
+    #[cfg(not(any(unix, windows)))]
+    pub fn bind(path: &str) -> io::Result<LocalListener> {
+        error!("Cannot bind to {}, local sockets are unsupported", path);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Returns the path of the socket or pipe
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Waits for the next connection
+    #[cfg(unix)]
+    pub async fn accept(&mut self) -> io::Result<Box<dyn LocalStream>> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(Box::new(stream))
+    }
+
+    /// Waits for the next connection
+    #[cfg(windows)]
+    pub async fn accept(&mut self) -> io::Result<Box<dyn LocalStream>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        self.next_instance.connect().await?;
+        // Each client is connected to an instance of its own, so a new one waits for the next
+        let next_instance = ServerOptions::new().create(&self.path)?;
+        let connected = std::mem::replace(&mut self.next_instance, next_instance);
+        Ok(Box::new(connected))
+    }
+
+    /// Waits for the next connection
+    #[cfg(not(any(unix, windows)))]
+    pub async fn accept(&mut self) -> io::Result<Box<dyn LocalStream>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Connects to a listener on the socket path, which must be called on a tokio runtime
+#[cfg(unix)]
+pub async fn connect(path: &str) -> io::Result<Box<dyn LocalStream>> {
+    let stream = tokio::net::UnixStream::connect(path).await?;
+    Ok(Box::new(stream))
+}
+
+/// Connects to a listener on the pipe name, waiting while every instance of the pipe is busy
+#[cfg(windows)]
+pub async fn connect(path: &str) -> io::Result<Box<dyn LocalStream>> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+    /// The error of opening a pipe whose instances are all connected to other clients
+    const ERROR_PIPE_BUSY: i32 = 231;
+    loop {
+        match ClientOptions::new().open(path) {
+            Ok(client) => return Ok(Box::new(client)),
+            Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {}
+            Err(err) => return Err(err),
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+/// Fails because there are no local sockets on this platform
+#[cfg(not(any(unix, windows)))]
+pub async fn connect(path: &str) -> io::Result<Box<dyn LocalStream>> {
+    error!("Cannot connect to {}, local sockets are unsupported", path);
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,109 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! An in-process loopback transport that connects a client to a server over in-memory duplex
+//! streams instead of TCP.
+//!
+//! A server run with `Server::new_loopback_server_task` binds a [`LoopbackListener`] to the host
+//! and port of its configuration instead of a TCP socket. A client in the same process that opts
+//! in with `ClientBuilder::loopback(true)` and connects to an endpoint url with that host and port
+//! is then given an in-memory stream to the server instead of opening a socket. This allows integration tests of address spaces
+//! and client logic to run quickly without touching the network.
+//!
+//! [`LoopbackListener`]: ./struct.LoopbackListener.html
+
+use std::collections::HashMap;
+
+use tokio::{
+    io::DuplexStream,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::sync::*;
+use crate::types::status_code::StatusCode;
+
+use super::url::hostname_port_from_url;
+
+/// The number of bytes that may be buffered in each direction of a loopback stream before the
+/// writer has to wait for the reader.
+const LOOPBACK_BUFFER_SIZE: usize = 65536;
+
+lazy_static! {
+    static ref LISTENERS: Mutex<HashMap<String, UnboundedSender<DuplexStream>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Produces the key that listeners are registered under
+fn listener_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host.to_lowercase(), port)
+}
+
+/// Accepts loopback connections made to a host and port. The listener is unregistered when it
+/// is dropped.
+pub struct LoopbackListener {
+    key: String,
+    receiver: UnboundedReceiver<DuplexStream>,
+}
+
+impl Drop for LoopbackListener {
+    fn drop(&mut self) {
+        let mut listeners = trace_lock!(LISTENERS);
+        listeners.remove(&self.key);
+    }
+}
+
+impl LoopbackListener {
+    /// Binds a listener to the host and port. Fails with `BadResourceUnavailable` if another
+    /// listener is already bound to them.
+    pub fn bind(host: &str, port: u16) -> Result<LoopbackListener, StatusCode> {
+        let key = listener_key(host, port);
+        let mut listeners = trace_lock!(LISTENERS);
+        if listeners.contains_key(&key) {
+            error!("A loopback listener is already bound to {}", key);
+            Err(StatusCode::BadResourceUnavailable)
+        } else {
+            let (sender, receiver) = unbounded_channel();
+            listeners.insert(key.clone(), sender);
+            Ok(LoopbackListener { key, receiver })
+        }
+    }
+
+    /// Waits for the next connection. Returns `None` if the listener can accept no more
+    /// connections.
+    pub async fn accept(&mut self) -> Option<DuplexStream> {
+        self.receiver.recv().await
+    }
+}
+
+/// Tests if a loopback listener is bound to the host and port of the endpoint url.
+pub fn is_bound(endpoint_url: &str, default_port: u16) -> bool {
+    if let Ok((host, port)) = hostname_port_from_url(endpoint_url, default_port) {
+        let listeners = trace_lock!(LISTENERS);
+        listeners.contains_key(&listener_key(&host, port))
+    } else {
+        false
+    }
+}
+
+/// Connects to the loopback listener bound to the host and port of the endpoint url, returning
+/// the client end of the stream. Fails with `BadNotConnected` if no listener is bound.
+pub fn connect(endpoint_url: &str, default_port: u16) -> Result<DuplexStream, StatusCode> {
+    let (host, port) = hostname_port_from_url(endpoint_url, default_port)?;
+    let key = listener_key(&host, port);
+    let listeners = trace_lock!(LISTENERS);
+    if let Some(sender) = listeners.get(&key) {
+        let (client, server) = tokio::io::duplex(LOOPBACK_BUFFER_SIZE);
+        sender.send(server).map_err(|_| {
+            error!(
+                "Loopback listener {} is no longer accepting connections",
+                key
+            );
+            StatusCode::BadNotConnected
+        })?;
+        Ok(client)
+    } else {
+        error!("No loopback listener is bound to {}", key);
+        Err(StatusCode::BadNotConnected)
+    }
+}
@@ -5,9 +5,13 @@
 //! A message chunk is a message or a portion of a message, optionally encrypted & signed, which
 //! has been split for transmission.
 
-use std::io::{Cursor, Read, Write};
+use std::io::Cursor;
 
-use crate::types::{status_code::StatusCode, *};
+use crate::types::{
+    io::{Read, Write},
+    status_code::StatusCode,
+    *,
+};
 
 use super::{
     message_chunk_info::ChunkInfo,
@@ -6,7 +6,13 @@ use std::io::{Cursor, Write};
 
 use crate::types::{status_code::StatusCode, BinaryEncoder, EncodingResult};
 
-use super::{chunker::Chunker, secure_channel::SecureChannel, tcp_types::AcknowledgeMessage};
+use super::{
+    capture::{capture, CaptureDirection, CaptureForm},
+    chunker::Chunker,
+    message_chunk::MessageChunkType,
+    secure_channel::SecureChannel,
+    tcp_types::{AcknowledgeMessage, ErrorMessage},
+};
 
 use crate::core::supported_message::SupportedMessage;
 
@@ -18,14 +24,19 @@ const DEFAULT_SENT_SEQUENCE_NUMBER: u32 = 0;
 pub struct MessageWriter {
     /// The send buffer
     buffer: Cursor<Vec<u8>>,
+    /// The buffer that each chunk is secured into before it is written to the send buffer. It is
+    /// allocated once, rather than for every message.
+    chunk_buffer: Vec<u8>,
     /// The last request id
     last_request_id: u32,
     /// Last sent sequence number
     last_sent_sequence_number: u32,
     /// Maximum size of a message, total. Use 0 for no limit
     max_message_size: usize,
-    /// Maximum size of a chunk. Use 0 for no limit
+    /// Maximum number of chunks in a message. Use 0 for no limit
     max_chunk_count: usize,
+    /// Maximum size of a chunk. Use 0 for no limit
+    max_chunk_size: usize,
 }
 
 impl MessageWriter {
@@ -36,19 +47,47 @@ impl MessageWriter {
     ) -> MessageWriter {
         MessageWriter {
             buffer: Cursor::new(vec![0u8; buffer_size]),
+            // A secured chunk is slightly larger than the chunk, by its header, padding and
+            // signature (up to 1024 bytes)
+            chunk_buffer: vec![0u8; buffer_size + 1024],
             last_request_id: DEFAULT_REQUEST_ID,
             last_sent_sequence_number: DEFAULT_SENT_SEQUENCE_NUMBER,
             max_message_size,
             max_chunk_count,
+            max_chunk_size: 0,
         }
     }
 
+    /// Revises the limits of the messages that are written to those that the other end said it
+    /// receives in the hello / acknowledge handshake. Messages are split into chunks that fit
+    /// the receive buffer of the other end. Use 0 for no limit.
+    pub fn set_limits(
+        &mut self,
+        max_chunk_size: usize,
+        max_message_size: usize,
+        max_chunk_count: usize,
+    ) {
+        self.max_chunk_size = max_chunk_size;
+        self.max_message_size = max_message_size;
+        self.max_chunk_count = max_chunk_count;
+    }
+
     pub fn write_ack(&mut self, ack: &AcknowledgeMessage) -> EncodingResult<usize> {
         ack.encode(&mut self.buffer)
     }
 
+    pub fn write_error(&mut self, error: &ErrorMessage) -> EncodingResult<usize> {
+        error.encode(&mut self.buffer)
+    }
+
     /// Encodes the message into a series of chunks, encrypts those chunks and writes the
     /// result into the buffer ready to be sent.
+    ///
+    /// A message that is too large for the other end fails with `BadRequestTooLarge` from a client
+    /// or `BadResponseTooLarge` from a server. If it is a service message, the chunks that fit are
+    /// written and the message ends with an abort chunk carrying the error in place of the rest,
+    /// which tells the other end to abandon it. A message whose chunk cannot be secured after others
+    /// have been written also ends with an abort chunk.
     pub fn write(
         &mut self,
         request_id: u32,
@@ -56,47 +95,101 @@ impl MessageWriter {
         secure_channel: &SecureChannel,
     ) -> Result<u32, StatusCode> {
         trace!("Writing request to buffer");
+        // Client stack should report a BadRequestTooLarge, server BadResponseTooLarge
+        let too_large = if secure_channel.is_client_role() {
+            StatusCode::BadRequestTooLarge
+        } else {
+            StatusCode::BadResponseTooLarge
+        };
+        let message_type = Chunker::message_type(&message);
+        let can_abort = message_type == MessageChunkType::Message;
+
         // Turn message to chunk(s)
-        let chunks = Chunker::encode(
+        let chunks = match Chunker::encode(
             self.last_sent_sequence_number + 1,
             request_id,
             self.max_message_size,
-            0,
+            self.max_chunk_size,
             secure_channel,
             &message,
-        )?;
+        ) {
+            Err(status_code) if status_code == too_large && can_abort => {
+                // None of the message is written so the abort is all there is of it
+                self.write_abort(request_id, message_type, status_code, secure_channel)?;
+                return Err(status_code);
+            }
+            result => result?,
+        };
 
-        if self.max_chunk_count > 0 && chunks.len() > self.max_chunk_count {
+        // The number of chunks that are written before the message is aborted
+        let abort_after = if self.max_chunk_count > 0 && chunks.len() > self.max_chunk_count {
             error!(
                 "Cannot write message since {} chunks exceeds {} chunk limit",
                 chunks.len(),
                 self.max_chunk_count
             );
-            Err(StatusCode::BadCommunicationError)
+            if !can_abort {
+                return Err(too_large);
+            }
+            Some(self.max_chunk_count - 1)
         } else {
-            // Sequence number monotonically increases per chunk
-            self.last_sent_sequence_number += chunks.len() as u32;
-
-            // Send chunks
-
-            // This max chunk size allows the message to be encoded to a chunk with header + encoding
-            // which is just slightly larger in size (up to 1024 bytes).
-            let data_buffer_size = self.buffer.get_ref().len() + 1024;
-            let mut data = vec![0u8; data_buffer_size];
-            for chunk in chunks {
-                trace!("Sending chunk {:?}", chunk);
-                let size = secure_channel.apply_security(&chunk, &mut data)?;
-                self.buffer.write(&data[..size]).map_err(|error| {
-                    error!(
-                        "Error while writing bytes to stream, connection broken, check error {:?}",
-                        error
-                    );
-                    StatusCode::BadCommunicationError
-                })?;
+            None
+        };
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            if abort_after == Some(i) {
+                self.write_abort(request_id, message_type, too_large, secure_channel)?;
+                return Err(too_large);
             }
-            trace!("Message written");
-            Ok(request_id)
+            trace!("Sending chunk {:?}", chunk);
+            capture(CaptureDirection::Sent, CaptureForm::Decrypted, &chunk.data);
+            let result = secure_channel
+                .apply_security(chunk, &mut self.chunk_buffer)
+                .and_then(|size| Self::write_bytes(&mut self.buffer, &self.chunk_buffer[..size]));
+            if let Err(status_code) = result {
+                if i > 0 && can_abort {
+                    self.write_abort(request_id, message_type, status_code, secure_channel)?;
+                }
+                return Err(status_code);
+            }
+            // Sequence number monotonically increases per chunk
+            self.last_sent_sequence_number += 1;
         }
+        trace!("Message written");
+        Ok(request_id)
+    }
+
+    /// Writes an abort chunk into the buffer, which tells the other end to abandon a message of
+    /// the request whose other chunks have been written, e.g. when the rest of it cannot be.
+    pub fn write_abort(
+        &mut self,
+        request_id: u32,
+        message_type: MessageChunkType,
+        status_code: StatusCode,
+        secure_channel: &SecureChannel,
+    ) -> Result<(), StatusCode> {
+        let chunk = Chunker::encode_abort(
+            self.last_sent_sequence_number + 1,
+            request_id,
+            message_type,
+            secure_channel,
+            status_code,
+        )?;
+        self.last_sent_sequence_number += 1;
+        capture(CaptureDirection::Sent, CaptureForm::Decrypted, &chunk.data);
+        let size = secure_channel.apply_security(&chunk, &mut self.chunk_buffer)?;
+        Self::write_bytes(&mut self.buffer, &self.chunk_buffer[..size])
+    }
+
+    /// Writes the bytes of a secured chunk into the buffer
+    fn write_bytes(buffer: &mut Cursor<Vec<u8>>, data: &[u8]) -> Result<(), StatusCode> {
+        buffer.write(data).map(|_| ()).map_err(|error| {
+            error!(
+                "Error while writing bytes to stream, connection broken, check error {:?}",
+                error
+            );
+            StatusCode::BadCommunicationError
+        })
     }
 
     pub fn next_request_id(&mut self) -> u32 {
@@ -5,12 +5,16 @@
 //! Contains all code related to sending / receiving messages from a transport
 //! and turning those messages into and out of chunks.
 
+pub mod capture;
 pub mod chunker;
+pub mod local_socket;
+pub mod loopback;
 pub mod message_chunk;
 pub mod message_chunk_info;
 pub mod message_writer;
 pub mod secure_channel;
 pub mod security_header;
+pub mod socket_options;
 pub mod tcp_codec;
 pub mod tcp_types;
 pub mod url;
@@ -20,6 +24,7 @@ pub mod prelude {
     pub use super::message_chunk::*;
     pub use super::secure_channel::*;
     pub use super::security_header::*;
+    pub use super::socket_options::*;
     pub use super::tcp_codec::*;
     pub use super::tcp_types::*;
     pub use super::url::*;
@@ -14,13 +14,15 @@ use crate::crypto::{
     aeskey::AesKey,
     pkey::{KeySize, PrivateKey, PublicKey},
     random,
+    thumbprint::ThumbprintAlgorithm,
     x509::X509,
     CertificateStore, SecurityPolicy,
 };
 use crate::sync::*;
 use crate::types::{
     service_types::ChannelSecurityToken, status_code::StatusCode, write_bytes, write_u8,
-    BinaryEncoder, ByteString, DateTime, DecodingOptions, MessageSecurityMode,
+    BinaryEncoder, ByteString, DateTime, DecodingOptions, MessageSecurityMode, NamespaceTable,
+    TypeRegistry,
 };
 
 use super::{
@@ -54,6 +56,8 @@ pub struct SecureChannel {
     token_id: u32,
     /// Our certificate
     cert: Option<X509>,
+    /// The certificates of the CAs that issued our certificate, which are sent after it
+    issuer_certs: Vec<X509>,
     /// Our private key
     private_key: Option<PrivateKey>,
     /// Their certificate
@@ -71,8 +75,8 @@ pub struct SecureChannel {
 }
 
 impl SecureChannel {
-    /// For testing purposes only
-    #[cfg(test)]
+    /// Creates a secure channel without certificates and a security policy of None, used by tests
+    /// and to decode captured chunks.
     pub fn new_no_certificate_store() -> SecureChannel {
         SecureChannel {
             role: Role::Unknown,
@@ -85,6 +89,7 @@ impl SecureChannel {
             local_nonce: Vec::new(),
             remote_nonce: Vec::new(),
             cert: None,
+            issuer_certs: Vec::new(),
             private_key: None,
             remote_cert: None,
             local_keys: None,
@@ -98,13 +103,14 @@ impl SecureChannel {
         role: Role,
         decoding_options: DecodingOptions,
     ) -> SecureChannel {
-        let (cert, private_key) = {
+        let (cert, issuer_certs, private_key) = {
             let certificate_store = certificate_store.read();
             if let Ok((cert, pkey)) = certificate_store.read_own_cert_and_pkey() {
-                (Some(cert), Some(pkey))
+                let issuer_certs = certificate_store.issuer_certs(&cert);
+                (Some(cert), issuer_certs, Some(pkey))
             } else {
                 error!("Cannot read our own certificate and private key. Check paths. Crypto won't work");
-                (None, None)
+                (None, Vec::new(), None)
             }
         };
         SecureChannel {
@@ -118,6 +124,7 @@ impl SecureChannel {
             local_nonce: Vec::new(),
             remote_nonce: Vec::new(),
             cert,
+            issuer_certs,
             private_key,
             remote_cert: None,
             local_keys: None,
@@ -138,6 +145,11 @@ impl SecureChannel {
         self.cert.clone()
     }
 
+    /// Sets the certificates of the CAs that issued our certificate, starting with its issuer
+    pub fn set_issuer_certs(&mut self, issuer_certs: Vec<X509>) {
+        self.issuer_certs = issuer_certs;
+    }
+
     pub fn set_remote_cert(&mut self, remote_cert: Option<X509>) {
         self.remote_cert = remote_cert;
     }
@@ -208,6 +220,14 @@ impl SecureChannel {
         self.decoding_options.client_offset = client_offset;
     }
 
+    pub fn set_namespaces(&mut self, namespaces: NamespaceTable) {
+        self.decoding_options.namespaces = Arc::new(namespaces);
+    }
+
+    pub fn set_type_registry(&mut self, type_registry: TypeRegistry) {
+        self.decoding_options.type_registry = Arc::new(type_registry);
+    }
+
     pub fn set_decoding_options(&mut self, decoding_options: DecodingOptions) {
         self.decoding_options = DecodingOptions {
             client_offset: self.decoding_options.client_offset,
@@ -243,13 +263,18 @@ impl SecureChannel {
                 } else {
                     let receiver_certificate_thumbprint =
                         if let Some(ref remote_cert) = self.remote_cert {
-                            remote_cert.thumbprint().as_byte_string()
+                            remote_cert
+                                .thumbprint_with_algorithm(
+                                    self.security_policy.certificate_thumbprint_algorithm(),
+                                )
+                                .as_byte_string()
                         } else {
                             ByteString::null()
                         };
                     AsymmetricSecurityHeader::new(
                         self.security_policy,
                         self.cert.as_ref().unwrap(),
+                        &self.issuer_certs,
                         receiver_certificate_thumbprint,
                     )
                 };
@@ -925,11 +950,13 @@ impl SecureChannel {
         // The sender_certificate is is the cert used to sign the message, i.e. the client's cert
         //
         // The receiver certificate thumbprint identifies which of our certs was used by the client
-        // to encrypt the message. We have to work out from the thumbprint which cert to use
+        // to encrypt the message. We have to work out from the thumbprint which cert to use. The
+        // thumbprint may be SHA-1 or SHA-256, which is told apart by its length.
 
         let our_cert = self.cert.as_ref().unwrap();
-        let our_thumbprint = our_cert.thumbprint();
-        if our_thumbprint.value() != receiver_thumbprint.as_ref() {
+        let our_thumbprint = ThumbprintAlgorithm::from_size(receiver_thumbprint.as_ref().len())
+            .map(|algorithm| our_cert.thumbprint_with_algorithm(algorithm));
+        if our_thumbprint.as_ref().map(|t| t.value()) != Some(receiver_thumbprint.as_ref()) {
             error!("Supplied thumbprint does not match application certificate's thumbprint");
             Err(StatusCode::BadNoValidCertificates)
         } else {
@@ -2,11 +2,14 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::io::{Read, Write};
+use crate::types::{
+    constants,
+    io::{Read, Write},
+    status_code::StatusCode,
+    *,
+};
 
-use crate::types::{constants, status_code::StatusCode, *};
-
-use crate::crypto::{SecurityPolicy, Thumbprint, X509};
+use crate::crypto::{SecurityPolicy, ThumbprintAlgorithm, X509};
 
 /// Holds the security header associated with the chunk. Secure channel requests use an asymmetric
 /// security header, regular messages use a symmetric security header.
@@ -93,7 +96,7 @@ impl BinaryEncoder<AsymmetricSecurityHeader> for AsymmetricSecurityHeader {
             error!("Sender certificate exceeds max certificate size");
             Err(StatusCode::BadDecodingError)
         } else {
-            // validate receiver_certificate_thumbprint_length == 20
+            // validate receiver_certificate_thumbprint_length is that of a SHA-1 or SHA-256 digest
             let thumbprint_len = if receiver_certificate_thumbprint.value.is_some() {
                 receiver_certificate_thumbprint
                     .value
@@ -103,14 +106,10 @@ impl BinaryEncoder<AsymmetricSecurityHeader> for AsymmetricSecurityHeader {
             } else {
                 0
             };
-            if thumbprint_len > 0 && thumbprint_len != Thumbprint::THUMBPRINT_SIZE {
+            if thumbprint_len > 0 && ThumbprintAlgorithm::from_size(thumbprint_len).is_none() {
                 error!(
-                    "Receiver certificate thumbprint is not 20 bytes long, {} bytes",
-                    receiver_certificate_thumbprint
-                        .value
-                        .as_ref()
-                        .unwrap()
-                        .len()
+                    "Receiver certificate thumbprint is not 20 or 32 bytes long, {} bytes",
+                    thumbprint_len
                 );
                 Err(StatusCode::BadDecodingError)
             } else {
@@ -133,14 +132,19 @@ impl AsymmetricSecurityHeader {
         }
     }
 
+    /// Creates a header with the sender certificate, which is sent followed by the certificates
+    /// of the CAs that issued it
     pub fn new(
         security_policy: SecurityPolicy,
         sender_certificate: &X509,
+        issuer_certificates: &[X509],
         receiver_certificate_thumbprint: ByteString,
     ) -> AsymmetricSecurityHeader {
+        let mut sender_certificate_chain = vec![sender_certificate.clone()];
+        sender_certificate_chain.extend_from_slice(issuer_certificates);
         AsymmetricSecurityHeader {
             security_policy_uri: UAString::from(security_policy.to_uri()),
-            sender_certificate: sender_certificate.as_byte_string(),
+            sender_certificate: X509::chain_as_byte_string(&sender_certificate_chain),
             receiver_certificate_thumbprint,
         }
     }
@@ -0,0 +1,105 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`SocketOptions`] that tune the TCP sockets of clients and servers.
+//!
+//! [`SocketOptions`]: ./struct.SocketOptions.html
+
+use std::{io, time::Duration};
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+
+/// Options that are set on each TCP socket that a client connects or a server accepts. Every
+/// option is left at the default of the operating system unless it is set.
+///
+/// The defaults of most systems only notice that the peer of an idle connection has gone, e.g.
+/// because a cable was pulled, after two hours or more. Turning keepalive on with a short time
+/// and interval notices it within seconds.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct SocketOptions {
+    /// Sends small messages straight away instead of waiting to coalesce them, i.e. sets
+    /// `TCP_NODELAY`, which lowers the latency of requests and responses
+    #[serde(default)]
+    pub nodelay: bool,
+    /// How long in milliseconds a connection is idle before keepalive probes are sent. 0, the
+    /// default, leaves keepalive off.
+    #[serde(default)]
+    pub keepalive_time: u32,
+    /// How long in milliseconds to wait between keepalive probes that are not answered. 0 leaves
+    /// the default of the operating system. Ignored on platforms that cannot set it.
+    #[serde(default)]
+    pub keepalive_interval: u32,
+    /// How many keepalive probes go unanswered before the connection is dropped. 0 leaves the
+    /// default of the operating system. Ignored on platforms that cannot set it.
+    #[serde(default)]
+    pub keepalive_retries: u32,
+    /// How long in milliseconds closing the socket waits to send data that is still queued, i.e.
+    /// sets `SO_LINGER`. 0 discards it and resets the connection. `None`, the default, closes the
+    /// socket in the background.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linger: Option<u32>,
+    /// The size in bytes of the receive buffer of the socket. 0 leaves the default of the
+    /// operating system.
+    #[serde(default)]
+    pub receive_buffer_size: usize,
+    /// The size in bytes of the send buffer of the socket. 0 leaves the default of the operating
+    /// system.
+    #[serde(default)]
+    pub send_buffer_size: usize,
+}
+
+impl SocketOptions {
+    /// Sets the options on the connected socket
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        let socket = SockRef::from(stream);
+        if self.keepalive_time > 0 {
+            socket.set_tcp_keepalive(&self.keepalive())?;
+        }
+        if let Some(linger) = self.linger {
+            socket.set_linger(Some(Duration::from_millis(linger as u64)))?;
+        }
+        if self.receive_buffer_size > 0 {
+            socket.set_recv_buffer_size(self.receive_buffer_size)?;
+        }
+        if self.send_buffer_size > 0 {
+            socket.set_send_buffer_size(self.send_buffer_size)?;
+        }
+        Ok(())
+    }
+
+    fn keepalive(&self) -> TcpKeepalive {
+        let keepalive =
+            TcpKeepalive::new().with_time(Duration::from_millis(self.keepalive_time as u64));
+        #[cfg(any(
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "linux",
+            target_os = "netbsd",
+            target_vendor = "apple",
+            windows,
+        ))]
+        let keepalive = if self.keepalive_interval > 0 {
+            keepalive.with_interval(Duration::from_millis(self.keepalive_interval as u64))
+        } else {
+            keepalive
+        };
+        #[cfg(any(
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "linux",
+            target_os = "netbsd",
+            target_vendor = "apple",
+        ))]
+        let keepalive = if self.keepalive_retries > 0 {
+            keepalive.with_retries(self.keepalive_retries)
+        } else {
+            keepalive
+        };
+        keepalive
+    }
+}
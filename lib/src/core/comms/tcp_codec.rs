@@ -42,6 +42,7 @@ pub enum Message {
 /// messages so there is still some buffers within message chunks, but not at the raw socket level.
 pub struct TcpCodec {
     decoding_options: DecodingOptions,
+    max_frame_size: usize,
 }
 
 impl Decoder for TcpCodec {
@@ -62,6 +63,13 @@ impl Decoder for TcpCodec {
             // the message. The buffer needs to have at least that amount of bytes in it for the
             // whole message to be extracted.
             let message_size = message_header.message_size as usize;
+            if self.max_frame_size > 0 && message_size > self.max_frame_size {
+                error!(
+                    "Codec got a message of {} bytes, which is larger than the limit of {} bytes",
+                    message_size, self.max_frame_size
+                );
+                return Err(io::Error::from(StatusCode::BadTcpMessageTooLarge));
+            }
             if buf.len() >= message_size {
                 // Extract the message bytes from the buffer & decode them into a message
                 let mut buf = buf.split_to(message_size);
@@ -73,7 +81,9 @@ impl Decoder for TcpCodec {
                         })?;
                 Ok(Some(message))
             } else {
-                // Not enough bytes
+                // Not enough bytes, so make room for the rest of the message at once rather
+                // than growing the buffer as they arrive
+                buf.reserve(message_size - buf.len());
                 Ok(None)
             }
         } else {
@@ -101,9 +111,17 @@ impl TcpCodec {
     pub fn new(decoding_options: DecodingOptions) -> TcpCodec {
         TcpCodec {
             decoding_options,
+            max_frame_size: 0,
         }
     }
 
+    /// Sets the largest message in bytes that the codec decodes, 0 for no limit. A larger message
+    /// is an error, so the buffer never has to grow beyond this size to hold one.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> TcpCodec {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
     // Writes the encodable thing into the buffer.
     fn write<T>(&self, msg: T, buf: &mut BytesMut) -> Result<(), io::Error>
     where
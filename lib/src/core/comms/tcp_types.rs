@@ -4,10 +4,14 @@
 
 //! Contains the implementation of various UA over TCP types.
 
-use std::io::{Cursor, Error, ErrorKind, Read, Result, Write};
+use std::io::{Cursor, Error, ErrorKind, Result};
 
 use crate::types::{
-    encoding::*, service_types::EndpointDescription, status_code::StatusCode, string::UAString,
+    encoding::*,
+    io::{Read, Write},
+    service_types::EndpointDescription,
+    status_code::StatusCode,
+    string::UAString,
 };
 
 use super::url::url_matches_except_host;
@@ -108,6 +112,16 @@ impl MessageHeader {
             return Err(Error::new(ErrorKind::Other, "Cannot decode message_size"));
         }
         let message_size = message_size.unwrap();
+        if (message_size as usize) < MESSAGE_HEADER_LEN {
+            return Err(Error::other(
+                "Message size is smaller than the message header",
+            ));
+        }
+        if decoding_options.max_message_size > 0
+            && message_size as usize > decoding_options.max_message_size
+        {
+            return Err(Error::other("Message size exceeds the max message size"));
+        }
 
         // Write header to stream
         let mut out = Cursor::new(Vec::with_capacity(message_size as usize));
@@ -324,6 +338,87 @@ impl BinaryEncoder<AcknowledgeMessage> for AcknowledgeMessage {
     }
 }
 
+impl AcknowledgeMessage {
+    /// Tests if the buffer sizes are no smaller than the minimum of the specification
+    pub fn is_valid_buffer_sizes(&self) -> bool {
+        self.receive_buffer_size >= MIN_CHUNK_SIZE as u32
+            && self.send_buffer_size >= MIN_CHUNK_SIZE as u32
+    }
+
+    /// Creates the ACK that answers a HEL. The buffer sizes are revised so that neither end sends
+    /// chunks larger than the other end receives, and the message size and chunk count are the
+    /// limits of what the server receives.
+    pub fn new(
+        hello: &HelloMessage,
+        receive_buffer_size: usize,
+        send_buffer_size: usize,
+        max_message_size: usize,
+        max_chunk_count: usize,
+    ) -> AcknowledgeMessage {
+        let mut msg = AcknowledgeMessage {
+            message_header: MessageHeader::new(MessageType::Acknowledge),
+            protocol_version: 0,
+            receive_buffer_size: (receive_buffer_size as u32).min(hello.send_buffer_size),
+            send_buffer_size: (send_buffer_size as u32).min(hello.receive_buffer_size),
+            max_message_size: max_message_size as u32,
+            max_chunk_count: max_chunk_count as u32,
+        };
+        msg.message_header.message_size = msg.byte_len() as u32;
+        msg
+    }
+}
+
+/// The limits on the chunks and messages that one end of a connection receives, as they were
+/// agreed in the HEL / ACK handshake. A message size or chunk count of 0 means there is no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReceiveLimits {
+    /// The largest chunk in bytes that is received
+    pub receive_buffer_size: usize,
+    /// The largest message body in bytes that is received
+    pub max_message_size: usize,
+    /// The most chunks that a message is received in
+    pub max_chunk_count: usize,
+}
+
+impl ReceiveLimits {
+    /// Checks the size of a chunk that has been received against the receive buffer size
+    pub fn check_chunk_size(&self, chunk_size: usize) -> std::result::Result<(), StatusCode> {
+        if self.receive_buffer_size > 0 && chunk_size > self.receive_buffer_size {
+            error!(
+                "Chunk of {} bytes exceeds the receive buffer size of {}",
+                chunk_size, self.receive_buffer_size
+            );
+            Err(StatusCode::BadTcpMessageTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks the message that chunks are being received for, from the number of its chunks and
+    /// the size of their bodies so far
+    pub fn check_message(
+        &self,
+        chunk_count: usize,
+        message_size: usize,
+    ) -> std::result::Result<(), StatusCode> {
+        if self.max_chunk_count > 0 && chunk_count > self.max_chunk_count {
+            error!(
+                "Message of {} chunks exceeds the limit of {} chunks",
+                chunk_count, self.max_chunk_count
+            );
+            Err(StatusCode::BadTcpMessageTooLarge)
+        } else if self.max_message_size > 0 && message_size > self.max_message_size {
+            error!(
+                "Message of {} bytes exceeds the max message size of {}",
+                message_size, self.max_message_size
+            );
+            Err(StatusCode::BadTcpMessageTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// Implementation of the ERR message in OPC UA
 #[derive(Debug, Clone, PartialEq)]
 pub struct ErrorMessage {
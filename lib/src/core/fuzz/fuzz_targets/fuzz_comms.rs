@@ -1,23 +1,22 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
 
-use std::sync::Arc;
-
 use bytes::BytesMut;
 use tokio_util::codec::Decoder;
 
-use crate::core::prelude::*;
+use opcua::core::comms::tcp_codec::{Message, TcpCodec};
+use opcua::types::DecodingOptions;
 
 pub fn decode(buf: &mut BytesMut, codec: &mut TcpCodec) -> Result<Option<Message>, std::io::Error> {
     codec.decode(buf)
 }
 
 fuzz_target!(|data: &[u8]| {
-    crate::console_logging::init();
     // With some random data, just try and deserialize it
     let decoding_options = DecodingOptions::default();
-    let abort = Arc::new(RwLock::new(false));
-    let mut codec = TcpCodec::new(abort, decoding_options);
+    // The frame size is limited so a made up message size does not reserve a huge buffer
+    let max_frame_size = decoding_options.max_message_size;
+    let mut codec = TcpCodec::new(decoding_options).with_max_frame_size(max_frame_size);
     let mut buf = BytesMut::from(data);
     let _ = decode(&mut buf, &mut codec);
 });
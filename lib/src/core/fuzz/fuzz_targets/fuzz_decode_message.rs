@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use opcua::core::fuzzing;
+use opcua::types::DecodingOptions;
+
+fuzz_target!(|data: &[u8]| {
+    let decoding_options = DecodingOptions::default();
+    // The data is decoded as the message that its node id says it is, and then as each of the
+    // messages in turn. Each decode should either return the message or an error. It shouldn't
+    // panic.
+    let _ = fuzzing::decode_message(data, &decoding_options);
+    for (_, _) in fuzzing::decode_each_message(data, &decoding_options) {}
+});
@@ -0,0 +1,53 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Entry points for fuzz targets, e.g. the ones of `cargo fuzz`, that decode messages from raw
+//! bytes. Decoding never panics, whatever the bytes are. It either returns the message or an
+//! error.
+
+use std::io::Cursor;
+
+use crate::core::supported_message::SupportedMessage;
+use crate::types::{
+    encoding::{BinaryEncoder, DecodingOptions, EncodingResult},
+    node_id::NodeId,
+    node_ids::ObjectId,
+    status_code::StatusCode,
+};
+
+/// Decodes a message from bytes that start with the node id of the binary encoding of the
+/// message, like the body of a chunk. Bytes of a message that is unsupported, or left out by a
+/// feature, return `BadServiceUnsupported`.
+pub fn decode_message(
+    data: &[u8],
+    decoding_options: &DecodingOptions,
+) -> EncodingResult<SupportedMessage> {
+    let mut stream = Cursor::new(data);
+    let node_id = NodeId::decode(&mut stream, decoding_options)?;
+    let object_id = if node_id.namespace == 0 {
+        node_id
+            .as_object_id()
+            .map_err(|_| StatusCode::BadDecodingError)?
+    } else {
+        return Err(StatusCode::BadDecodingError);
+    };
+    match SupportedMessage::decode_by_object_id(&mut stream, object_id, decoding_options)? {
+        SupportedMessage::Invalid(_) => Err(StatusCode::BadServiceUnsupported),
+        message => Ok(message),
+    }
+}
+
+/// Decodes the bytes as each type of message in turn, so a fuzzer does not have to find the
+/// node ids of the messages to reach their decoders.
+pub fn decode_each_message<'a>(
+    data: &'a [u8],
+    decoding_options: &'a DecodingOptions,
+) -> impl Iterator<Item = (ObjectId, EncodingResult<SupportedMessage>)> + 'a {
+    SupportedMessage::OBJECT_IDS.iter().map(move |object_id| {
+        let mut stream = Cursor::new(data);
+        let message =
+            SupportedMessage::decode_by_object_id(&mut stream, *object_id, decoding_options);
+        (*object_id, message)
+    })
+}
@@ -0,0 +1,455 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Provides hooks for instrumenting the server so that operators can collect health metrics
+//! into a monitoring system such as Prometheus / Grafana. This module is only available with
+//! the `metrics` feature.
+//!
+//! The server calls the installed [`MetricsRecorder`] as sessions are created and closed, secure
+//! channels are opened, service calls complete, publish requests are queued, bytes are transferred,
+//! messages fail to decode, monitored items coalesce or discard samples and the session reaper
+//! frees resources. An application installs a recorder with [`set_recorder`]. It can implement
+//! the trait itself to forward metrics to whatever library it uses, or use the supplied
+//! [`PrometheusMetrics`] which accumulates values in memory and renders them in the Prometheus
+//! text exposition format.
+//!
+//! [`MetricsRecorder`]: ./trait.MetricsRecorder.html
+//! [`set_recorder`]: ./fn.set_recorder.html
+//! [`PrometheusMetrics`]: ./struct.PrometheusMetrics.html
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::sync::*;
+use crate::types::status_code::StatusCode;
+
+/// The `MetricsRecorder` trait is implemented by anything that wishes to receive metrics from
+/// the server. Every function has a default implementation that does nothing, so an implementation
+/// only has to override the metrics it is interested in.
+pub trait MetricsRecorder {
+    /// Called when a session is created.
+    fn on_session_created(&self) {}
+
+    /// Called when a session is closed, either explicitly or because it timed out or its
+    /// connection dropped.
+    fn on_session_closed(&self) {}
+
+    /// Called when a new secure channel is issued to a client. Renewals are not counted.
+    fn on_secure_channel_opened(&self) {}
+
+    /// Called when a service call has been processed. The service is the name of the request,
+    /// e.g. `ReadRequest`. The status is `Good` unless the request produced a service fault.
+    fn on_service_call(&self, _service: &'static str, _duration: Duration, _status: StatusCode) {}
+
+    /// Called when a publish request is queued, with the number of publish requests that are
+    /// now waiting on the session.
+    fn on_publish_queue_depth(&self, _depth: usize) {}
+
+    /// Called when bytes have been received from a client.
+    fn on_bytes_received(&self, _bytes: usize) {}
+
+    /// Called when bytes have been sent to a client.
+    fn on_bytes_sent(&self, _bytes: usize) {}
+
+    /// Called when a message from a client cannot be decoded.
+    fn on_decode_error(&self) {}
+
+    /// Called when a monitored item samples a value that was updated more than once since its
+    /// last sample, with the number of updates that were replaced before they were sampled.
+    fn on_updates_coalesced(&self, _updates: usize) {}
+
+    /// Called when notifications of a monitored item are discarded because its queue is full.
+    fn on_notifications_discarded(&self, _notifications: usize) {}
+
+    /// Called when the session reaper has closed sessions that timed out or deleted parked
+    /// subscriptions, with the number of each resource that it freed. Subscriptions of the
+    /// sessions that were parked for transfer are counted separately from those deleted.
+    fn on_resources_reaped(
+        &self,
+        _sessions: usize,
+        _publish_requests: usize,
+        _continuation_points: usize,
+        _subscriptions_deleted: usize,
+        _subscriptions_parked: usize,
+    ) {
+    }
+}
+
+lazy_static! {
+    static ref RECORDER: RwLock<Option<Arc<dyn MetricsRecorder + Send + Sync>>> = RwLock::new(None);
+}
+
+/// Installs the recorder that receives metrics, replacing any previous recorder.
+pub fn set_recorder(recorder: Arc<dyn MetricsRecorder + Send + Sync>) {
+    let mut r = trace_write_lock!(RECORDER);
+    *r = Some(recorder);
+}
+
+/// Removes the installed recorder, if any.
+pub fn clear_recorder() {
+    let mut r = trace_write_lock!(RECORDER);
+    *r = None;
+}
+
+/// Calls the supplied function with the installed recorder. Nothing happens if there is no
+/// recorder.
+pub(crate) fn with_recorder<F>(f: F)
+where
+    F: FnOnce(&(dyn MetricsRecorder + Send + Sync)),
+{
+    // Clone the recorder out so the lock is not held while the recorder does its work
+    let recorder = {
+        let r = trace_read_lock!(RECORDER);
+        r.clone()
+    };
+    if let Some(recorder) = recorder {
+        f(recorder.as_ref());
+    }
+}
+
+/// A histogram with fixed buckets. Bucket bounds are in seconds, following Prometheus convention.
+struct Histogram {
+    /// Cumulative count of observations less than or equal to each bucket's bound
+    buckets: Vec<u64>,
+    /// Sum of all observations
+    sum: f64,
+    /// Number of observations
+    count: u64,
+}
+
+impl Histogram {
+    fn new() -> Histogram {
+        Histogram {
+            buckets: vec![0; PrometheusMetrics::LATENCY_BUCKETS.len()],
+            sum: 0f64,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        PrometheusMetrics::LATENCY_BUCKETS
+            .iter()
+            .zip(self.buckets.iter_mut())
+            .filter(|(bound, _)| value <= **bound)
+            .for_each(|(_, bucket)| *bucket += 1);
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// An implementation of [`MetricsRecorder`] which accumulates metrics in memory and renders
+/// them in the Prometheus text exposition format, e.g. to serve from an HTTP endpoint that
+/// Prometheus scrapes.
+///
+/// [`MetricsRecorder`]: ./trait.MetricsRecorder.html
+pub struct PrometheusMetrics {
+    sessions_current: AtomicI64,
+    sessions_total: AtomicU64,
+    secure_channels_opened_total: AtomicU64,
+    publish_queue_depth: AtomicU64,
+    bytes_received_total: AtomicU64,
+    bytes_sent_total: AtomicU64,
+    decode_errors_total: AtomicU64,
+    coalesced_updates_total: AtomicU64,
+    discarded_notifications_total: AtomicU64,
+    reaped_sessions_total: AtomicU64,
+    reaped_publish_requests_total: AtomicU64,
+    reaped_continuation_points_total: AtomicU64,
+    reaped_subscriptions_deleted_total: AtomicU64,
+    reaped_subscriptions_parked_total: AtomicU64,
+    /// Service call counts keyed by (service name, good / bad)
+    service_calls: Mutex<BTreeMap<(&'static str, bool), u64>>,
+    /// Service call latencies keyed by service name.
+    service_latencies: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self {
+            sessions_current: AtomicI64::new(0),
+            sessions_total: AtomicU64::new(0),
+            secure_channels_opened_total: AtomicU64::new(0),
+            publish_queue_depth: AtomicU64::new(0),
+            bytes_received_total: AtomicU64::new(0),
+            bytes_sent_total: AtomicU64::new(0),
+            decode_errors_total: AtomicU64::new(0),
+            coalesced_updates_total: AtomicU64::new(0),
+            discarded_notifications_total: AtomicU64::new(0),
+            reaped_sessions_total: AtomicU64::new(0),
+            reaped_publish_requests_total: AtomicU64::new(0),
+            reaped_continuation_points_total: AtomicU64::new(0),
+            reaped_subscriptions_deleted_total: AtomicU64::new(0),
+            reaped_subscriptions_parked_total: AtomicU64::new(0),
+            service_calls: Mutex::new(BTreeMap::new()),
+            service_latencies: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl MetricsRecorder for PrometheusMetrics {
+    fn on_session_created(&self) {
+        self.sessions_current.fetch_add(1, Ordering::Relaxed);
+        self.sessions_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_session_closed(&self) {
+        self.sessions_current.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn on_secure_channel_opened(&self) {
+        self.secure_channels_opened_total
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_service_call(&self, service: &'static str, duration: Duration, status: StatusCode) {
+        {
+            let mut service_calls = trace_lock!(self.service_calls);
+            *service_calls
+                .entry((service, status.is_good()))
+                .or_insert(0) += 1;
+        }
+        let mut service_latencies = trace_lock!(self.service_latencies);
+        service_latencies
+            .entry(service)
+            .or_insert_with(Histogram::new)
+            .observe(duration.as_secs_f64());
+    }
+
+    fn on_publish_queue_depth(&self, depth: usize) {
+        self.publish_queue_depth
+            .store(depth as u64, Ordering::Relaxed);
+    }
+
+    fn on_bytes_received(&self, bytes: usize) {
+        self.bytes_received_total
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn on_bytes_sent(&self, bytes: usize) {
+        self.bytes_sent_total
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn on_decode_error(&self) {
+        self.decode_errors_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_updates_coalesced(&self, updates: usize) {
+        self.coalesced_updates_total
+            .fetch_add(updates as u64, Ordering::Relaxed);
+    }
+
+    fn on_notifications_discarded(&self, notifications: usize) {
+        self.discarded_notifications_total
+            .fetch_add(notifications as u64, Ordering::Relaxed);
+    }
+
+    fn on_resources_reaped(
+        &self,
+        sessions: usize,
+        publish_requests: usize,
+        continuation_points: usize,
+        subscriptions_deleted: usize,
+        subscriptions_parked: usize,
+    ) {
+        self.reaped_sessions_total
+            .fetch_add(sessions as u64, Ordering::Relaxed);
+        self.reaped_publish_requests_total
+            .fetch_add(publish_requests as u64, Ordering::Relaxed);
+        self.reaped_continuation_points_total
+            .fetch_add(continuation_points as u64, Ordering::Relaxed);
+        self.reaped_subscriptions_deleted_total
+            .fetch_add(subscriptions_deleted as u64, Ordering::Relaxed);
+        self.reaped_subscriptions_parked_total
+            .fetch_add(subscriptions_parked as u64, Ordering::Relaxed);
+    }
+}
+
+impl PrometheusMetrics {
+    /// Upper bounds of the service call latency histogram buckets in seconds
+    pub const LATENCY_BUCKETS: [f64; 10] = [
+        0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+    ];
+
+    pub fn new() -> PrometheusMetrics {
+        Self::default()
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::with_capacity(2048);
+        Self::render_metric(
+            &mut out,
+            "opcua_sessions_current",
+            "gauge",
+            "Number of sessions currently established",
+            self.sessions_current.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_sessions_total",
+            "counter",
+            "Number of sessions created since the server started",
+            self.sessions_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_secure_channels_opened_total",
+            "counter",
+            "Number of secure channels issued since the server started",
+            self.secure_channels_opened_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_publish_queue_depth",
+            "gauge",
+            "Number of publish requests queued on the most recently active session",
+            self.publish_queue_depth.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_bytes_received_total",
+            "counter",
+            "Number of bytes received from clients",
+            self.bytes_received_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_bytes_sent_total",
+            "counter",
+            "Number of bytes sent to clients",
+            self.bytes_sent_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_decode_errors_total",
+            "counter",
+            "Number of messages from clients that could not be decoded",
+            self.decode_errors_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_coalesced_updates_total",
+            "counter",
+            "Number of value updates replaced by a later update before a monitored item sampled them",
+            self.coalesced_updates_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_discarded_notifications_total",
+            "counter",
+            "Number of monitored item notifications discarded because the queue was full",
+            self.discarded_notifications_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_reaped_sessions_total",
+            "counter",
+            "Number of sessions closed by the session reaper because they timed out",
+            self.reaped_sessions_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_reaped_publish_requests_total",
+            "counter",
+            "Number of publish requests of reaped sessions answered with BadSessionClosed",
+            self.reaped_publish_requests_total.load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_reaped_continuation_points_total",
+            "counter",
+            "Number of continuation points of reaped sessions that were released",
+            self.reaped_continuation_points_total
+                .load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_reaped_subscriptions_deleted_total",
+            "counter",
+            "Number of subscriptions deleted by the session reaper",
+            self.reaped_subscriptions_deleted_total
+                .load(Ordering::Relaxed),
+        );
+        Self::render_metric(
+            &mut out,
+            "opcua_reaped_subscriptions_parked_total",
+            "counter",
+            "Number of subscriptions of reaped sessions parked for transfer",
+            self.reaped_subscriptions_parked_total
+                .load(Ordering::Relaxed),
+        );
+
+        {
+            let service_calls = trace_lock!(self.service_calls);
+            let _ = writeln!(
+                out,
+                "# HELP opcua_service_calls_total Number of service calls processed"
+            );
+            let _ = writeln!(out, "# TYPE opcua_service_calls_total counter");
+            service_calls.iter().for_each(|((service, good), count)| {
+                let _ = writeln!(
+                    out,
+                    "opcua_service_calls_total{{service=\"{}\",result=\"{}\"}} {}",
+                    service,
+                    if *good { "good" } else { "bad" },
+                    count
+                );
+            });
+        }
+
+        {
+            let service_latencies = trace_lock!(self.service_latencies);
+            let _ = writeln!(
+                out,
+                "# HELP opcua_service_call_duration_seconds Time taken to process service calls"
+            );
+            let _ = writeln!(out, "# TYPE opcua_service_call_duration_seconds histogram");
+            service_latencies.iter().for_each(|(service, histogram)| {
+                Self::LATENCY_BUCKETS
+                    .iter()
+                    .zip(histogram.buckets.iter())
+                    .for_each(|(bound, count)| {
+                        let _ = writeln!(
+                            out,
+                            "opcua_service_call_duration_seconds_bucket{{service=\"{}\",le=\"{}\"}} {}",
+                            service, bound, count
+                        );
+                    });
+                let _ = writeln!(
+                    out,
+                    "opcua_service_call_duration_seconds_bucket{{service=\"{}\",le=\"+Inf\"}} {}",
+                    service, histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "opcua_service_call_duration_seconds_sum{{service=\"{}\"}} {}",
+                    service, histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "opcua_service_call_duration_seconds_count{{service=\"{}\"}} {}",
+                    service, histogram.count
+                );
+            });
+        }
+        out
+    }
+
+    fn render_metric<T>(out: &mut String, name: &str, metric_type: &str, help: &str, value: T)
+    where
+        T: std::fmt::Display,
+    {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+}
@@ -48,6 +48,23 @@ macro_rules! deregister_runtime_component {
     };
 }
 
+/// Records a metric with the installed [`MetricsRecorder`], e.g. `record_metric!(on_bytes_sent(n))`.
+/// The macro expands to nothing unless the `metrics` feature is enabled so the arguments are not
+/// evaluated when metrics are not compiled in.
+///
+/// [`MetricsRecorder`]: ./metrics/trait.MetricsRecorder.html
+#[cfg(feature = "server-core")]
+macro_rules! record_metric {
+    ( $hook:ident ( $( $arg:expr ),* ) ) => {
+        #[cfg(feature = "metrics")]
+        {
+            $crate::core::metrics::with_recorder(|recorder| recorder.$hook($( $arg ),*));
+        }
+    };
+}
+#[cfg(feature = "server-core")]
+pub(crate) use record_metric;
+
 /// Contains debugging utility helper functions
 pub mod debug {
     /// Prints out the content of a slice in hex and visible char format to aid debugging. Format
@@ -102,7 +119,10 @@ pub mod constants {
 
 pub mod comms;
 pub mod config;
+pub mod fuzzing;
 pub mod handle;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod runtime;
 #[rustfmt::skip]
 pub mod supported_message;
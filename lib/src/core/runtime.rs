@@ -5,7 +5,6 @@
 use std::{collections::BTreeSet, sync::Arc};
 
 use crate::sync::*;
-use crate::trace_lock;
 
 /// The `Runtime` is for debugging / diagnostics purposes and tracks which substantial system objects
 /// components are in existence. It can be used to detect if something has shutdown or not.
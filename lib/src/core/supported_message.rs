@@ -6,10 +6,8 @@
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 
-use std::io::{Read, Write};
-
 use crate::types::{
-    encoding::*, node_id::NodeId, node_ids::ObjectId, request_header::RequestHeader,
+    encoding::*, io::{Read, Write}, node_id::NodeId, node_ids::ObjectId, request_header::RequestHeader,
     response_header::ResponseHeader, service_types::*,
 };
 
@@ -19,8 +17,8 @@ pub use crate::core::comms::tcp_types::AcknowledgeMessage;
 /// The first form just handles the trailing comma after the last entry to save some pointless
 /// editing when new messages are added to the list.
 macro_rules! supported_messages_enum {
-    [ $( $x:ident, ) * ] => (supported_messages_enum![ $( $x ),* ];);
-    [ $( $x:ident ), * ] => {
+    [ $( $(#[$meta:meta])* $x:ident, ) * ] => (supported_messages_enum![ $( $(#[$meta])* $x ),* ];);
+    [ $( $(#[$meta:meta])* $x:ident ), * ] => {
         #[derive(Debug, PartialEq, Clone)]
         pub enum SupportedMessage {
             /// An invalid request / response of some form
@@ -28,7 +26,7 @@ macro_rules! supported_messages_enum {
             /// Acknowledge message
             AcknowledgeMessage(Box<AcknowledgeMessage>),
             /// Other messages
-            $( $x(Box<$x>), )*
+            $( $(#[$meta])* $x(Box<$x>), )*
         }
 
         impl BinaryEncoder <SupportedMessage> for SupportedMessage {
@@ -38,7 +36,7 @@ macro_rules! supported_messages_enum {
                         panic!("Unsupported message byte_len {:?}", object_id);
                     },
                     SupportedMessage::AcknowledgeMessage(value) => value.byte_len(),
-                    $( SupportedMessage::$x(value) => value.byte_len(), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.byte_len(), )*
                 }
             }
 
@@ -48,7 +46,7 @@ macro_rules! supported_messages_enum {
                         panic!("Unsupported message encode {:?}", object_id);
                     },
                     SupportedMessage::AcknowledgeMessage(value) => value.encode(stream),
-                    $( SupportedMessage::$x(value) => value.encode(stream), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.encode(stream), )*
                 }
             }
 
@@ -63,6 +61,7 @@ macro_rules! supported_messages_enum {
         }
 
         $(
+        $(#[$meta])*
         impl Into<SupportedMessage> for $x {
             fn into(self) -> SupportedMessage { SupportedMessage::$x(Box::new(self)) }
         }
@@ -77,7 +76,16 @@ macro_rules! supported_messages_enum {
                     SupportedMessage::AcknowledgeMessage(value) => {
                         panic!("Unsupported message node_id {:?}", value);
                     },
-                    $( SupportedMessage::$x(value) => value.object_id().into(), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.object_id().into(), )*
+                }
+            }
+
+            /// Returns the name of the message, e.g. "ReadRequest"
+            pub fn name(&self) -> &'static str {
+                match self {
+                    SupportedMessage::Invalid(_) => "Invalid",
+                    SupportedMessage::AcknowledgeMessage(_) => "AcknowledgeMessage",
+                    $( $(#[$meta])* SupportedMessage::$x(_) => stringify!($x), )*
                 }
             }
         }
@@ -107,9 +115,13 @@ impl SupportedMessage {
             SupportedMessage::CloseSessionRequest(_) => true,
             SupportedMessage::CancelRequest(_) => true,
             SupportedMessage::ActivateSessionRequest(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddNodesRequest(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddReferencesRequest(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteNodesRequest(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteReferencesRequest(_) => true,
             SupportedMessage::CreateMonitoredItemsRequest(_) => true,
             SupportedMessage::ModifyMonitoredItemsRequest(_) => true,
@@ -121,7 +133,9 @@ impl SupportedMessage {
             SupportedMessage::DeleteSubscriptionsRequest(_) => true,
             SupportedMessage::TransferSubscriptionsRequest(_) => true,
             SupportedMessage::SetPublishingModeRequest(_) => true,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryFirstRequest(_) => true,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryNextRequest(_) => true,
             SupportedMessage::BrowseRequest(_) => true,
             SupportedMessage::BrowseNextRequest(_) => true,
@@ -131,8 +145,10 @@ impl SupportedMessage {
             SupportedMessage::RegisterNodesRequest(_) => true,
             SupportedMessage::UnregisterNodesRequest(_) => true,
             SupportedMessage::ReadRequest(_) => true,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryReadRequest(_) => true,
             SupportedMessage::WriteRequest(_) => true,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryUpdateRequest(_) => true,
             SupportedMessage::CallRequest(_) => true,
             _ => false,
@@ -151,9 +167,13 @@ impl SupportedMessage {
             SupportedMessage::CloseSessionRequest(r) => &r.request_header,
             SupportedMessage::CancelRequest(r) => &r.request_header,
             SupportedMessage::ActivateSessionRequest(r) => &r.request_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddNodesRequest(r) => &r.request_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddReferencesRequest(r) => &r.request_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteNodesRequest(r) => &r.request_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteReferencesRequest(r) => &r.request_header,
             SupportedMessage::CreateMonitoredItemsRequest(r) => &r.request_header,
             SupportedMessage::ModifyMonitoredItemsRequest(r) => &r.request_header,
@@ -165,7 +185,9 @@ impl SupportedMessage {
             SupportedMessage::DeleteSubscriptionsRequest(r) => &r.request_header,
             SupportedMessage::TransferSubscriptionsRequest(r) => &r.request_header,
             SupportedMessage::SetPublishingModeRequest(r) => &r.request_header,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryFirstRequest(r) => &r.request_header,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryNextRequest(r) => &r.request_header,
             SupportedMessage::BrowseRequest(r) => &r.request_header,
             SupportedMessage::BrowseNextRequest(r) => &r.request_header,
@@ -175,8 +197,10 @@ impl SupportedMessage {
             SupportedMessage::RegisterNodesRequest(r) => &r.request_header,
             SupportedMessage::UnregisterNodesRequest(r) => &r.request_header,
             SupportedMessage::ReadRequest(r) => &r.request_header,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryReadRequest(r) => &r.request_header,
             SupportedMessage::WriteRequest(r) => &r.request_header,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryUpdateRequest(r) => &r.request_header,
             SupportedMessage::CallRequest(r) => &r.request_header,
             _ => panic!(),
@@ -196,9 +220,13 @@ impl SupportedMessage {
             SupportedMessage::CloseSessionResponse(_) => true,
             SupportedMessage::CancelResponse(_) => true,
             SupportedMessage::ActivateSessionResponse(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddNodesResponse(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddReferencesResponse(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteNodesResponse(_) => true,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteReferencesResponse(_) => true,
             SupportedMessage::CreateMonitoredItemsResponse(_) => true,
             SupportedMessage::ModifyMonitoredItemsResponse(_) => true,
@@ -210,7 +238,9 @@ impl SupportedMessage {
             SupportedMessage::DeleteSubscriptionsResponse(_) => true,
             SupportedMessage::TransferSubscriptionsResponse(_) => true,
             SupportedMessage::SetPublishingModeResponse(_) => true,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryFirstResponse(_) => true,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryNextResponse(_) => true,
             SupportedMessage::BrowseResponse(_) => true,
             SupportedMessage::BrowseNextResponse(_) => true,
@@ -220,8 +250,10 @@ impl SupportedMessage {
             SupportedMessage::RegisterNodesResponse(_) => true,
             SupportedMessage::UnregisterNodesResponse(_) => true,
             SupportedMessage::ReadResponse(_) => true,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryReadResponse(_) => true,
             SupportedMessage::WriteResponse(_) => true,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryUpdateResponse(_) => true,
             SupportedMessage::CallResponse(_) => true,
             _ => false,
@@ -241,9 +273,13 @@ impl SupportedMessage {
             SupportedMessage::CloseSessionResponse(r) => &r.response_header,
             SupportedMessage::CancelResponse(r) => &r.response_header,
             SupportedMessage::ActivateSessionResponse(r) => &r.response_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddNodesResponse(r) => &r.response_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddReferencesResponse(r) => &r.response_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteNodesResponse(r) => &r.response_header,
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteReferencesResponse(r) => &r.response_header,
             SupportedMessage::CreateMonitoredItemsResponse(r) => &r.response_header,
             SupportedMessage::ModifyMonitoredItemsResponse(r) => &r.response_header,
@@ -255,7 +291,9 @@ impl SupportedMessage {
             SupportedMessage::DeleteSubscriptionsResponse(r) => &r.response_header,
             SupportedMessage::TransferSubscriptionsResponse(r) => &r.response_header,
             SupportedMessage::SetPublishingModeResponse(r) => &r.response_header,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryFirstResponse(r) => &r.response_header,
+            #[cfg(feature = "query")]
             SupportedMessage::QueryNextResponse(r) => &r.response_header,
             SupportedMessage::BrowseResponse(r) => &r.response_header,
             SupportedMessage::BrowseNextResponse(r) => &r.response_header,
@@ -265,14 +303,113 @@ impl SupportedMessage {
             SupportedMessage::RegisterNodesResponse(r) => &r.response_header,
             SupportedMessage::UnregisterNodesResponse(r) => &r.response_header,
             SupportedMessage::ReadResponse(r) => &r.response_header,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryReadResponse(r) => &r.response_header,
             SupportedMessage::WriteResponse(r) => &r.response_header,
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryUpdateResponse(r) => &r.response_header,
             SupportedMessage::CallResponse(r) => &r.response_header,
             _ => panic!(),
         }
     }
 
+    /// The object ids of the binary encodings of the messages that `decode_by_object_id()` decodes
+    pub const OBJECT_IDS: &[ObjectId] = &[
+        ObjectId::ServiceFault_Encoding_DefaultBinary,
+        ObjectId::OpenSecureChannelRequest_Encoding_DefaultBinary,
+        ObjectId::OpenSecureChannelResponse_Encoding_DefaultBinary,
+        ObjectId::CloseSecureChannelRequest_Encoding_DefaultBinary,
+        ObjectId::CloseSecureChannelResponse_Encoding_DefaultBinary,
+        ObjectId::GetEndpointsRequest_Encoding_DefaultBinary,
+        ObjectId::GetEndpointsResponse_Encoding_DefaultBinary,
+        ObjectId::FindServersRequest_Encoding_DefaultBinary,
+        ObjectId::FindServersResponse_Encoding_DefaultBinary,
+        ObjectId::RegisterServerRequest_Encoding_DefaultBinary,
+        ObjectId::RegisterServerResponse_Encoding_DefaultBinary,
+        ObjectId::RegisterServer2Request_Encoding_DefaultBinary,
+        ObjectId::RegisterServer2Response_Encoding_DefaultBinary,
+        ObjectId::CreateSessionRequest_Encoding_DefaultBinary,
+        ObjectId::CreateSessionResponse_Encoding_DefaultBinary,
+        ObjectId::CloseSessionRequest_Encoding_DefaultBinary,
+        ObjectId::CloseSessionResponse_Encoding_DefaultBinary,
+        ObjectId::CancelRequest_Encoding_DefaultBinary,
+        ObjectId::CancelResponse_Encoding_DefaultBinary,
+        ObjectId::ActivateSessionRequest_Encoding_DefaultBinary,
+        ObjectId::ActivateSessionResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::AddNodesRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::AddNodesResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::AddReferencesRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::AddReferencesResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::DeleteNodesRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::DeleteNodesResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::DeleteReferencesRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "node-management")]
+        ObjectId::DeleteReferencesResponse_Encoding_DefaultBinary,
+        ObjectId::CreateMonitoredItemsRequest_Encoding_DefaultBinary,
+        ObjectId::CreateMonitoredItemsResponse_Encoding_DefaultBinary,
+        ObjectId::ModifyMonitoredItemsRequest_Encoding_DefaultBinary,
+        ObjectId::ModifyMonitoredItemsResponse_Encoding_DefaultBinary,
+        ObjectId::DeleteMonitoredItemsRequest_Encoding_DefaultBinary,
+        ObjectId::DeleteMonitoredItemsResponse_Encoding_DefaultBinary,
+        ObjectId::SetMonitoringModeRequest_Encoding_DefaultBinary,
+        ObjectId::SetMonitoringModeResponse_Encoding_DefaultBinary,
+        ObjectId::SetTriggeringRequest_Encoding_DefaultBinary,
+        ObjectId::SetTriggeringResponse_Encoding_DefaultBinary,
+        ObjectId::CreateSubscriptionRequest_Encoding_DefaultBinary,
+        ObjectId::CreateSubscriptionResponse_Encoding_DefaultBinary,
+        ObjectId::ModifySubscriptionRequest_Encoding_DefaultBinary,
+        ObjectId::ModifySubscriptionResponse_Encoding_DefaultBinary,
+        ObjectId::DeleteSubscriptionsRequest_Encoding_DefaultBinary,
+        ObjectId::DeleteSubscriptionsResponse_Encoding_DefaultBinary,
+        ObjectId::TransferSubscriptionsRequest_Encoding_DefaultBinary,
+        ObjectId::TransferSubscriptionsResponse_Encoding_DefaultBinary,
+        ObjectId::SetPublishingModeRequest_Encoding_DefaultBinary,
+        ObjectId::SetPublishingModeResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "query")]
+        ObjectId::QueryFirstRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "query")]
+        ObjectId::QueryFirstResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "query")]
+        ObjectId::QueryNextRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "query")]
+        ObjectId::QueryNextResponse_Encoding_DefaultBinary,
+        ObjectId::BrowseRequest_Encoding_DefaultBinary,
+        ObjectId::BrowseResponse_Encoding_DefaultBinary,
+        ObjectId::BrowseNextRequest_Encoding_DefaultBinary,
+        ObjectId::BrowseNextResponse_Encoding_DefaultBinary,
+        ObjectId::PublishRequest_Encoding_DefaultBinary,
+        ObjectId::PublishResponse_Encoding_DefaultBinary,
+        ObjectId::RepublishRequest_Encoding_DefaultBinary,
+        ObjectId::RepublishResponse_Encoding_DefaultBinary,
+        ObjectId::TranslateBrowsePathsToNodeIdsRequest_Encoding_DefaultBinary,
+        ObjectId::TranslateBrowsePathsToNodeIdsResponse_Encoding_DefaultBinary,
+        ObjectId::RegisterNodesRequest_Encoding_DefaultBinary,
+        ObjectId::RegisterNodesResponse_Encoding_DefaultBinary,
+        ObjectId::UnregisterNodesRequest_Encoding_DefaultBinary,
+        ObjectId::UnregisterNodesResponse_Encoding_DefaultBinary,
+        ObjectId::ReadRequest_Encoding_DefaultBinary,
+        ObjectId::ReadResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "history")]
+        ObjectId::HistoryReadRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "history")]
+        ObjectId::HistoryReadResponse_Encoding_DefaultBinary,
+        ObjectId::WriteRequest_Encoding_DefaultBinary,
+        ObjectId::WriteResponse_Encoding_DefaultBinary,
+        #[cfg(feature = "history")]
+        ObjectId::HistoryUpdateRequest_Encoding_DefaultBinary,
+        #[cfg(feature = "history")]
+        ObjectId::HistoryUpdateResponse_Encoding_DefaultBinary,
+        ObjectId::CallRequest_Encoding_DefaultBinary,
+        ObjectId::CallResponse_Encoding_DefaultBinary,
+    ];
+
     pub fn decode_by_object_id<S: Read>(
         stream: &mut S,
         object_id: ObjectId,
@@ -343,27 +480,35 @@ impl SupportedMessage {
             ObjectId::ActivateSessionResponse_Encoding_DefaultBinary => {
                 ActivateSessionResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::AddNodesRequest_Encoding_DefaultBinary => {
                 AddNodesRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::AddNodesResponse_Encoding_DefaultBinary => {
                 AddNodesResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::AddReferencesRequest_Encoding_DefaultBinary => {
                 AddReferencesRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::AddReferencesResponse_Encoding_DefaultBinary => {
                 AddReferencesResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::DeleteNodesRequest_Encoding_DefaultBinary => {
                 DeleteNodesRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::DeleteNodesResponse_Encoding_DefaultBinary => {
                 DeleteNodesResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::DeleteReferencesRequest_Encoding_DefaultBinary => {
                 DeleteReferencesRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "node-management")]
             ObjectId::DeleteReferencesResponse_Encoding_DefaultBinary => {
                 DeleteReferencesResponse::decode(stream, decoding_options)?.into()
             }
@@ -427,15 +572,19 @@ impl SupportedMessage {
             ObjectId::SetPublishingModeResponse_Encoding_DefaultBinary => {
                 SetPublishingModeResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "query")]
             ObjectId::QueryFirstRequest_Encoding_DefaultBinary => {
                 QueryFirstRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "query")]
             ObjectId::QueryFirstResponse_Encoding_DefaultBinary => {
                 QueryFirstResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "query")]
             ObjectId::QueryNextRequest_Encoding_DefaultBinary => {
                 QueryNextRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "query")]
             ObjectId::QueryNextResponse_Encoding_DefaultBinary => {
                 QueryNextResponse::decode(stream, decoding_options)?.into()
             }
@@ -487,9 +636,11 @@ impl SupportedMessage {
             ObjectId::ReadResponse_Encoding_DefaultBinary => {
                 ReadResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "history")]
             ObjectId::HistoryReadRequest_Encoding_DefaultBinary => {
                 HistoryReadRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "history")]
             ObjectId::HistoryReadResponse_Encoding_DefaultBinary => {
                 HistoryReadResponse::decode(stream, decoding_options)?.into()
             }
@@ -499,9 +650,11 @@ impl SupportedMessage {
             ObjectId::WriteResponse_Encoding_DefaultBinary => {
                 WriteResponse::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "history")]
             ObjectId::HistoryUpdateRequest_Encoding_DefaultBinary => {
                 HistoryUpdateRequest::decode(stream, decoding_options)?.into()
             }
+            #[cfg(feature = "history")]
             ObjectId::HistoryUpdateResponse_Encoding_DefaultBinary => {
                 HistoryUpdateResponse::decode(stream, decoding_options)?.into()
             }
@@ -543,13 +696,21 @@ supported_messages_enum![
     CancelResponse,
     ActivateSessionRequest,
     ActivateSessionResponse,
+    #[cfg(feature = "node-management")]
     AddNodesRequest,
+    #[cfg(feature = "node-management")]
     AddNodesResponse,
+    #[cfg(feature = "node-management")]
     AddReferencesRequest,
+    #[cfg(feature = "node-management")]
     AddReferencesResponse,
+    #[cfg(feature = "node-management")]
     DeleteNodesRequest,
+    #[cfg(feature = "node-management")]
     DeleteNodesResponse,
+    #[cfg(feature = "node-management")]
     DeleteReferencesRequest,
+    #[cfg(feature = "node-management")]
     DeleteReferencesResponse,
     CreateMonitoredItemsRequest,
     CreateMonitoredItemsResponse,
@@ -571,9 +732,13 @@ supported_messages_enum![
     TransferSubscriptionsResponse,
     SetPublishingModeRequest,
     SetPublishingModeResponse,
+    #[cfg(feature = "query")]
     QueryFirstRequest,
+    #[cfg(feature = "query")]
     QueryFirstResponse,
+    #[cfg(feature = "query")]
     QueryNextRequest,
+    #[cfg(feature = "query")]
     QueryNextResponse,
     BrowseRequest,
     BrowseResponse,
@@ -591,11 +756,15 @@ supported_messages_enum![
     UnregisterNodesResponse,
     ReadRequest,
     ReadResponse,
+    #[cfg(feature = "history")]
     HistoryReadRequest,
+    #[cfg(feature = "history")]
     HistoryReadResponse,
     WriteRequest,
     WriteResponse,
+    #[cfg(feature = "history")]
     HistoryUpdateRequest,
+    #[cfg(feature = "history")]
     HistoryUpdateResponse,
     CallRequest,
     CallResponse,
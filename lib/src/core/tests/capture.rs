@@ -0,0 +1,179 @@
+use tempdir::TempDir;
+
+use crate::core::{
+    comms::{capture::*, chunker::Chunker, secure_channel::SecureChannel},
+    tests::*,
+};
+use crate::crypto::SecurityPolicy;
+
+fn sample_chunk_data(secure_channel: &SecureChannel, message: &SupportedMessage) -> Vec<u8> {
+    Chunker::encode(1, 10, 0, 0, secure_channel, message)
+        .unwrap()
+        .into_iter()
+        .flat_map(|chunk| chunk.data)
+        .collect()
+}
+
+#[test]
+fn capture_write_and_read() {
+    let tmp_dir = TempDir::new("capture").unwrap();
+    let path = tmp_dir.path().join("test.cap");
+
+    let records = vec![
+        CaptureRecord {
+            timestamp: DateTime::now(),
+            direction: CaptureDirection::Received,
+            form: CaptureForm::Raw,
+            data: vec![1, 2, 3, 4],
+        },
+        CaptureRecord {
+            timestamp: DateTime::now(),
+            direction: CaptureDirection::Sent,
+            form: CaptureForm::Decrypted,
+            data: vec![],
+        },
+    ];
+
+    {
+        let mut writer = CaptureWriter::create(&path, true).unwrap();
+        assert!(writer.include_decrypted());
+        records.iter().for_each(|r| writer.write_record(r).unwrap());
+    }
+
+    let read_records: Vec<CaptureRecord> = CaptureReader::open(&path)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(read_records, records);
+}
+
+#[test]
+fn capture_reader_rejects_other_files() {
+    let data = b"NOTACAPTURE0000".to_vec();
+    assert!(CaptureReader::new(&data[..]).is_err());
+}
+
+#[test]
+fn capture_replay() {
+    let secure_channel = SecureChannel::new_no_certificate_store();
+    let message = make_sample_message();
+
+    // The acknowledge at the front is not a chunk and should be skipped
+    let mut data = vec![b'A', b'C', b'K', b'F', 8, 0, 0, 0];
+    data.extend(sample_chunk_data(&secure_channel, &message));
+
+    let records = vec![CaptureRecord {
+        timestamp: DateTime::now(),
+        direction: CaptureDirection::Sent,
+        form: CaptureForm::Raw,
+        data,
+    }];
+
+    let replayed = replay(records, DecodingOptions::test());
+    assert_eq!(replayed.len(), 1);
+    let replayed = &replayed[0];
+    assert_eq!(replayed.direction, CaptureDirection::Sent);
+    assert_eq!(replayed.request_id, 10);
+    assert_eq!(*replayed.message.as_ref().unwrap(), message);
+}
+
+/// Makes the raw and the decrypted records of a message that is sent over one secure channel
+/// and received over the other, like a capture made with decrypted chunks included.
+fn secure_records(
+    direction: CaptureDirection,
+    sender: &SecureChannel,
+    receiver: &mut SecureChannel,
+    request_id: u32,
+    message: &SupportedMessage,
+) -> Vec<CaptureRecord> {
+    let mut records = Vec::new();
+    Chunker::encode(1, request_id, 0, 0, sender, message)
+        .unwrap()
+        .into_iter()
+        .for_each(|chunk| {
+            let mut data = vec![0u8; chunk.data.len() + 1024];
+            let size = sender.apply_security(&chunk, &mut data).unwrap();
+            data.truncate(size);
+            let decrypted = match direction {
+                CaptureDirection::Sent => chunk.data,
+                CaptureDirection::Received => {
+                    receiver.verify_and_remove_security(&data).unwrap().data
+                }
+            };
+            records.push(CaptureRecord {
+                timestamp: DateTime::now(),
+                direction,
+                form: CaptureForm::Raw,
+                data,
+            });
+            records.push(CaptureRecord {
+                timestamp: DateTime::now(),
+                direction,
+                form: CaptureForm::Decrypted,
+                data: decrypted,
+            });
+        });
+    records
+}
+
+#[test]
+fn capture_replay_sign_and_encrypt() {
+    let (mut local, mut remote) = make_secure_channels(
+        MessageSecurityMode::SignAndEncrypt,
+        SecurityPolicy::Basic256Sha256,
+    );
+    let message = make_sample_message();
+
+    // Both directions of the capture are only readable from their decrypted chunks
+    let mut records = secure_records(CaptureDirection::Sent, &local, &mut remote, 10, &message);
+    records.extend(secure_records(
+        CaptureDirection::Received,
+        &remote,
+        &mut local,
+        11,
+        &message,
+    ));
+
+    let replayed = replay(records, DecodingOptions::test());
+    assert_eq!(replayed.len(), 2);
+    assert_eq!(replayed[0].direction, CaptureDirection::Sent);
+    assert_eq!(replayed[0].request_id, 10);
+    assert_eq!(*replayed[0].message.as_ref().unwrap(), message);
+    assert_eq!(replayed[1].direction, CaptureDirection::Received);
+    assert_eq!(replayed[1].request_id, 11);
+    assert_eq!(*replayed[1].message.as_ref().unwrap(), message);
+}
+
+#[test]
+fn capture_replay_raw_fallback_per_direction() {
+    let (mut local, remote) = make_secure_channels(
+        MessageSecurityMode::SignAndEncrypt,
+        SecurityPolicy::Basic256Sha256,
+    );
+    let message = make_sample_message();
+
+    // The received direction has decrypted chunks, the sent direction only has raw chunks of a
+    // channel without security
+    let mut records = secure_records(
+        CaptureDirection::Received,
+        &remote,
+        &mut local,
+        11,
+        &message,
+    );
+    records.push(CaptureRecord {
+        timestamp: DateTime::now(),
+        direction: CaptureDirection::Sent,
+        form: CaptureForm::Raw,
+        data: sample_chunk_data(&SecureChannel::new_no_certificate_store(), &message),
+    });
+
+    let replayed = replay(records, DecodingOptions::test());
+    assert_eq!(replayed.len(), 2);
+    assert_eq!(replayed[0].direction, CaptureDirection::Received);
+    assert_eq!(replayed[0].request_id, 11);
+    assert_eq!(*replayed[0].message.as_ref().unwrap(), message);
+    assert_eq!(replayed[1].direction, CaptureDirection::Sent);
+    assert_eq!(replayed[1].request_id, 10);
+    assert_eq!(*replayed[1].message.as_ref().unwrap(), message);
+}
@@ -6,7 +6,10 @@ use crate::crypto::{x509::X509, SecurityPolicy};
 use crate::types::DecodingOptions;
 
 use crate::core::{
-    comms::{chunker::*, message_chunk::*, secure_channel::*, tcp_types::MIN_CHUNK_SIZE},
+    comms::{
+        chunker::*, message_chunk::*, message_writer::MessageWriter, secure_channel::*,
+        tcp_types::MIN_CHUNK_SIZE,
+    },
     supported_message::SupportedMessage,
     tests::*,
 };
@@ -80,7 +83,16 @@ fn set_chunk_request_id(
 }
 
 fn make_large_read_response() -> SupportedMessage {
-    let results = (0..10000).map(|i| DataValue::new_now(i as u32)).collect();
+    let now = DateTime::now();
+    let results = (0..10000)
+        .map(|i| {
+            DataValue::builder()
+                .value(i as u32)
+                .source_timestamp(now)
+                .server_timestamp(now)
+                .build()
+        })
+        .collect();
     ReadResponse {
         response_header: ResponseHeader::null(),
         results: Some(results),
@@ -203,6 +215,172 @@ fn max_message_size() {
     assert_eq!(err, StatusCode::BadResponseTooLarge);
 }
 
+/// Splits the bytes written by a message writer into the chunks they hold
+fn written_chunks(bytes: &[u8]) -> Vec<MessageChunk> {
+    let decoding_options = DecodingOptions::test();
+    let mut stream = Cursor::new(bytes);
+    let mut chunks = Vec::new();
+    while (stream.position() as usize) < bytes.len() {
+        chunks.push(MessageChunk::decode(&mut stream, &decoding_options).unwrap());
+    }
+    chunks
+}
+
+/// Write a large message with the limits of the other end and ensure it is split into chunks that
+/// fit its receive buffer, or is aborted if it takes more chunks than the other end receives
+#[test]
+fn message_writer_limits() {
+    let _ = Test::setup();
+
+    let secure_channel = SecureChannel::new_no_certificate_store();
+    let response = make_large_read_response();
+
+    let mut message_writer = MessageWriter::new(65535, 0, 0);
+    message_writer.set_limits(MIN_CHUNK_SIZE, 0, 0);
+    message_writer
+        .write(100, response.clone(), &secure_channel)
+        .unwrap();
+    let chunks = written_chunks(&message_writer.bytes_to_write());
+    chunks
+        .iter()
+        .for_each(|chunk| assert!(chunk.data.len() <= MIN_CHUNK_SIZE));
+    let chunk_count = chunks.len();
+    assert!(chunk_count > 2);
+    let last_sequence_number = Chunker::validate_chunks(1, &secure_channel, &chunks).unwrap();
+
+    // Expect this to fail, with the chunks that fit followed by an abort in place of the rest
+    message_writer.set_limits(MIN_CHUNK_SIZE, 0, chunk_count - 1);
+    let err = message_writer
+        .write(101, response, &secure_channel)
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadResponseTooLarge);
+    let chunks = written_chunks(&message_writer.bytes_to_write());
+    assert_eq!(chunks.len(), chunk_count - 1);
+    assert_eq!(
+        Chunker::validate_chunks(last_sequence_number + 1, &secure_channel, &chunks).unwrap(),
+        last_sequence_number + chunk_count as u32 - 1
+    );
+    let (status_code, _) =
+        Chunker::decode_abort(&chunks[chunks.len() - 1], &secure_channel).unwrap();
+    assert_eq!(status_code, StatusCode::BadResponseTooLarge);
+    chunks[..chunks.len() - 1].iter().for_each(|chunk| {
+        let message_header = chunk.message_header(&DecodingOptions::test()).unwrap();
+        assert_eq!(message_header.is_final, MessageIsFinalType::Intermediate);
+        assert_eq!(
+            chunk
+                .chunk_info(&secure_channel)
+                .unwrap()
+                .sequence_header
+                .request_id,
+            101
+        );
+    });
+
+    // A message that exceeds the message size of the other end is just an abort
+    message_writer.set_limits(MIN_CHUNK_SIZE, 100, 0);
+    let err = message_writer
+        .write(102, make_large_read_response(), &secure_channel)
+        .unwrap_err();
+    assert_eq!(err, StatusCode::BadResponseTooLarge);
+    let chunks = written_chunks(&message_writer.bytes_to_write());
+    assert_eq!(chunks.len(), 1);
+    let (status_code, _) = Chunker::decode_abort(&chunks[0], &secure_channel).unwrap();
+    assert_eq!(status_code, StatusCode::BadResponseTooLarge);
+}
+
+/// Abort a large message after its intermediate chunks and ensure the abort chunk carries the
+/// error and follows on from their sequence numbers
+#[test]
+fn abort_chunk() {
+    let _ = Test::setup();
+
+    let secure_channel = SecureChannel::new_no_certificate_store();
+    let response = make_large_read_response();
+
+    let sequence_number = 1000;
+    let request_id = 100;
+    let mut chunks = Chunker::encode(
+        sequence_number,
+        request_id,
+        0,
+        MIN_CHUNK_SIZE,
+        &secure_channel,
+        &response,
+    )
+    .unwrap();
+    assert!(chunks.len() > 2);
+
+    // Replace the final chunk with an abort
+    let _ = chunks.pop();
+    let abort = Chunker::encode_abort(
+        sequence_number + chunks.len() as u32,
+        request_id,
+        MessageChunkType::Message,
+        &secure_channel,
+        StatusCode::BadEncodingLimitsExceeded,
+    )
+    .unwrap();
+    let decoding_options = DecodingOptions::test();
+    let message_header = abort.message_header(&decoding_options).unwrap();
+    assert_eq!(message_header.is_final, MessageIsFinalType::FinalError);
+
+    let (status_code, reason) = Chunker::decode_abort(&abort, &secure_channel).unwrap();
+    assert_eq!(status_code, StatusCode::BadEncodingLimitsExceeded);
+    assert_eq!(
+        reason.as_ref(),
+        StatusCode::BadEncodingLimitsExceeded.description()
+    );
+
+    // The abort is part of the series of chunks but they do not make a message
+    chunks.push(abort);
+    assert_eq!(
+        Chunker::validate_chunks(sequence_number, &secure_channel, &chunks).unwrap(),
+        sequence_number + chunks.len() as u32 - 1
+    );
+    assert_eq!(
+        Chunker::decode(&chunks, &secure_channel, None).unwrap_err(),
+        StatusCode::BadDecodingError
+    );
+
+    // Only an abort chunk has an error to decode
+    assert_eq!(
+        Chunker::decode_abort(&chunks[0], &secure_channel).unwrap_err(),
+        StatusCode::BadDecodingError
+    );
+}
+
+/// Make a request unsupported by changing its type id and ensure that its request header still
+/// decodes, so the request can be answered with a service fault
+#[test]
+fn decode_request_header_of_unsupported_request() {
+    let _ = Test::setup();
+
+    let secure_channel = SecureChannel::new_no_certificate_store();
+    let mut request_header = RequestHeader::dummy();
+    request_header.request_handle = 1234;
+    let request: SupportedMessage = ReadRequest {
+        request_header,
+        max_age: 0f64,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: None,
+    }
+    .into();
+    let mut chunks = Chunker::encode(1, 1, 0, 0, &secure_channel, &request).unwrap();
+    assert_eq!(chunks.len(), 1);
+
+    // The body starts with the type id of the request in its four byte form
+    let body_offset = chunks[0].chunk_info(&secure_channel).unwrap().body_offset;
+    let type_id = ObjectId::ReadValueId_Encoding_DefaultBinary as u16;
+    chunks[0].data[body_offset + 2..body_offset + 4].copy_from_slice(&type_id.to_le_bytes());
+
+    assert_eq!(
+        Chunker::decode(&chunks, &secure_channel, None).unwrap_err(),
+        StatusCode::BadServiceUnsupported
+    );
+    let request_header = Chunker::decode_request_header(&chunks, &secure_channel).unwrap();
+    assert_eq!(request_header.request_handle, 1234);
+}
+
 /// Encode a large message and then ensure verification throws error for secure channel id mismatch
 #[test]
 fn validate_chunks_secure_channel_id() {
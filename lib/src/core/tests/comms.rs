@@ -1,9 +1,17 @@
 use std::io::*;
 
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
 use crate::crypto::SecurityPolicy;
 use crate::types::*;
 
-use crate::core::comms::{secure_channel::*, tcp_types::*};
+use crate::core::comms::{
+    secure_channel::*,
+    security_header::AsymmetricSecurityHeader,
+    tcp_codec::{Message, TcpCodec},
+    tcp_types::*,
+};
 
 fn hello_data() -> Vec<u8> {
     vec![
@@ -40,6 +48,47 @@ pub fn hello() {
     );
 }
 
+#[test]
+pub fn codec_max_frame_size() {
+    // A message that fits is decoded, even when it arrives in parts
+    let hello_data = hello_data();
+    let mut codec = TcpCodec::new(DecodingOptions::test()).with_max_frame_size(57);
+    let mut buf = BytesMut::from(&hello_data[..20]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    assert!(buf.capacity() >= 57);
+    buf.extend_from_slice(&hello_data[20..]);
+    match codec.decode(&mut buf).unwrap() {
+        Some(Message::Hello(hello)) => assert_eq!(hello.message_header.message_size, 57),
+        message => panic!("Unexpected message {:?}", message),
+    }
+
+    // A larger message is an error as soon as its header arrives
+    let mut codec = TcpCodec::new(DecodingOptions::test()).with_max_frame_size(56);
+    let mut buf = BytesMut::from(&hello_data[..20]);
+    assert!(codec.decode(&mut buf).is_err());
+}
+
+#[test]
+pub fn message_header_read_bytes() {
+    let hello_data = hello_data();
+    let decoding_options = DecodingOptions::test();
+    let bytes =
+        MessageHeader::read_bytes(&mut Cursor::new(&hello_data), &decoding_options).unwrap();
+    assert_eq!(bytes, hello_data);
+
+    // A message size smaller than the header itself is an error
+    let mut data = hello_data.clone();
+    data[4..8].copy_from_slice(&4u32.to_le_bytes());
+    assert!(MessageHeader::read_bytes(&mut Cursor::new(&data), &decoding_options).is_err());
+
+    // So is a message size beyond the max message size
+    let decoding_options = DecodingOptions {
+        max_message_size: 56,
+        ..DecodingOptions::test()
+    };
+    assert!(MessageHeader::read_bytes(&mut Cursor::new(&hello_data), &decoding_options).is_err());
+}
+
 #[test]
 pub fn acknowledge() {
     let mut stream = Cursor::new(ack_data());
@@ -134,3 +183,23 @@ pub fn secure_channel_nonce_none() {
         .set_remote_nonce_from_byte_string(&ByteString::from(b""))
         .is_ok());
 }
+
+#[test]
+pub fn asymmetric_security_header_thumbprint_length() {
+    let decoding_options = DecodingOptions::test();
+    // SHA-1 and SHA-256 thumbprints decode, other lengths do not
+    for (len, valid) in [(0, true), (20, true), (32, true), (16, false)] {
+        let header = AsymmetricSecurityHeader {
+            security_policy_uri: UAString::from(SecurityPolicy::Basic256Sha256.to_uri()),
+            sender_certificate: ByteString::null(),
+            receiver_certificate_thumbprint: if len > 0 {
+                ByteString::from(vec![1u8; len])
+            } else {
+                ByteString::null()
+            },
+        };
+        let mut stream = Cursor::new(header.encode_to_vec());
+        let result = AsymmetricSecurityHeader::decode(&mut stream, &decoding_options);
+        assert_eq!(result.is_ok(), valid);
+    }
+}
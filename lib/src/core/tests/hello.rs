@@ -1,10 +1,14 @@
 use crate::types::{
     byte_string::ByteString,
+    encoding::BinaryEncoder,
     service_types::{ApplicationDescription, EndpointDescription, MessageSecurityMode},
+    status_code::StatusCode,
     string::UAString,
 };
 
-use crate::core::comms::tcp_types::{HelloMessage, MessageHeader, MessageType};
+use crate::core::comms::tcp_types::{
+    AcknowledgeMessage, HelloMessage, MessageHeader, MessageType, ReceiveLimits,
+};
 
 #[test]
 fn endpoint_url() {
@@ -78,3 +82,50 @@ fn valid_buffer_sizes() {
     h.send_buffer_size = 8196;
     assert!(h.is_valid_buffer_sizes());
 }
+
+#[test]
+fn acknowledge_buffer_sizes() {
+    // Test that the acknowledge revises the buffer sizes to those of the smaller end
+    let h = HelloMessage::new("opc.tcp://foo/", 16384, 32768, 0, 0);
+
+    let ack = AcknowledgeMessage::new(&h, 65535, 65535, 1000000, 10);
+    assert_eq!(ack.receive_buffer_size, 16384);
+    assert_eq!(ack.send_buffer_size, 32768);
+    assert_eq!(ack.max_message_size, 1000000);
+    assert_eq!(ack.max_chunk_count, 10);
+    assert!(ack.is_valid_buffer_sizes());
+
+    let ack = AcknowledgeMessage::new(&h, 8196, 8196, 0, 0);
+    assert_eq!(ack.receive_buffer_size, 8196);
+    assert_eq!(ack.send_buffer_size, 8196);
+    assert_eq!(ack.message_header.message_size as usize, ack.byte_len());
+}
+
+#[test]
+fn receive_limits() {
+    // Test that chunks and messages exceeding the limits are rejected
+    let limits = ReceiveLimits {
+        receive_buffer_size: 8196,
+        max_message_size: 20000,
+        max_chunk_count: 3,
+    };
+    assert!(limits.check_chunk_size(8196).is_ok());
+    assert_eq!(
+        limits.check_chunk_size(8197).unwrap_err(),
+        StatusCode::BadTcpMessageTooLarge
+    );
+    assert!(limits.check_message(3, 20000).is_ok());
+    assert_eq!(
+        limits.check_message(4, 20000).unwrap_err(),
+        StatusCode::BadTcpMessageTooLarge
+    );
+    assert_eq!(
+        limits.check_message(3, 20001).unwrap_err(),
+        StatusCode::BadTcpMessageTooLarge
+    );
+
+    // No limits
+    let limits = ReceiveLimits::default();
+    assert!(limits.check_chunk_size(1000000).is_ok());
+    assert!(limits.check_message(1000, 100000000).is_ok());
+}
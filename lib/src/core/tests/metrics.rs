@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use crate::types::status_code::StatusCode;
+
+use crate::core::metrics::{MetricsRecorder, PrometheusMetrics};
+
+#[test]
+fn prometheus_counters() {
+    let metrics = PrometheusMetrics::new();
+    metrics.on_session_created();
+    metrics.on_session_created();
+    metrics.on_session_closed();
+    metrics.on_secure_channel_opened();
+    metrics.on_bytes_received(100);
+    metrics.on_bytes_received(20);
+    metrics.on_bytes_sent(64);
+    metrics.on_decode_error();
+    metrics.on_updates_coalesced(5);
+    metrics.on_updates_coalesced(2);
+    metrics.on_notifications_discarded(3);
+    metrics.on_publish_queue_depth(3);
+    metrics.on_resources_reaped(1, 2, 3, 1, 4);
+    metrics.on_resources_reaped(0, 0, 0, 2, 0);
+
+    let out = metrics.render();
+    println!("{}", out);
+    assert!(out.contains("# TYPE opcua_sessions_current gauge\nopcua_sessions_current 1\n"));
+    assert!(out.contains("\nopcua_sessions_total 2\n"));
+    assert!(out.contains("\nopcua_secure_channels_opened_total 1\n"));
+    assert!(out.contains("\nopcua_bytes_received_total 120\n"));
+    assert!(out.contains("\nopcua_bytes_sent_total 64\n"));
+    assert!(out.contains("\nopcua_decode_errors_total 1\n"));
+    assert!(out.contains("\nopcua_coalesced_updates_total 7\n"));
+    assert!(out.contains("\nopcua_discarded_notifications_total 3\n"));
+    assert!(out.contains("\nopcua_publish_queue_depth 3\n"));
+    assert!(out.contains("\nopcua_reaped_sessions_total 1\n"));
+    assert!(out.contains("\nopcua_reaped_publish_requests_total 2\n"));
+    assert!(out.contains("\nopcua_reaped_continuation_points_total 3\n"));
+    assert!(out.contains("\nopcua_reaped_subscriptions_deleted_total 3\n"));
+    assert!(out.contains("\nopcua_reaped_subscriptions_parked_total 4\n"));
+}
+
+#[test]
+fn prometheus_service_calls() {
+    let metrics = PrometheusMetrics::new();
+    metrics.on_service_call("ReadRequest", Duration::from_micros(800), StatusCode::Good);
+    metrics.on_service_call("ReadRequest", Duration::from_millis(20), StatusCode::Good);
+    metrics.on_service_call(
+        "WriteRequest",
+        Duration::from_millis(2),
+        StatusCode::BadNodeIdUnknown,
+    );
+
+    let out = metrics.render();
+    println!("{}", out);
+    assert!(out.contains("opcua_service_calls_total{service=\"ReadRequest\",result=\"good\"} 2\n"));
+    assert!(out.contains("opcua_service_calls_total{service=\"WriteRequest\",result=\"bad\"} 1\n"));
+    // Buckets are cumulative
+    assert!(out.contains(
+        "opcua_service_call_duration_seconds_bucket{service=\"ReadRequest\",le=\"0.0005\"} 0\n"
+    ));
+    assert!(out.contains(
+        "opcua_service_call_duration_seconds_bucket{service=\"ReadRequest\",le=\"0.001\"} 1\n"
+    ));
+    assert!(out.contains(
+        "opcua_service_call_duration_seconds_bucket{service=\"ReadRequest\",le=\"0.025\"} 2\n"
+    ));
+    assert!(out.contains(
+        "opcua_service_call_duration_seconds_bucket{service=\"ReadRequest\",le=\"+Inf\"} 2\n"
+    ));
+    assert!(out.contains("opcua_service_call_duration_seconds_count{service=\"WriteRequest\"} 1\n"));
+}
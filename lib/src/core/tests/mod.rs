@@ -169,9 +169,13 @@ impl Test {
     }
 }
 
+mod capture;
 mod chunk;
 mod comms;
 mod hello;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod secure_channel;
 mod services;
+mod socket_options;
 mod supported_message;
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use socket2::SockRef;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::core::comms::socket_options::SocketOptions;
+
+/// Connects a socket to a listener on the loopback interface
+async fn connected_socket() -> (TcpStream, TcpListener) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let socket = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    (socket, listener)
+}
+
+#[test]
+fn socket_options_are_applied() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let (socket, _listener) = connected_socket().await;
+        let socket_options = SocketOptions {
+            nodelay: true,
+            keepalive_time: 5000,
+            keepalive_interval: 1000,
+            keepalive_retries: 3,
+            linger: Some(2000),
+            receive_buffer_size: 128 * 1024,
+            send_buffer_size: 128 * 1024,
+        };
+        socket_options.apply(&socket).unwrap();
+
+        let socket = SockRef::from(&socket);
+        assert!(socket.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.linger().unwrap(), Some(Duration::from_secs(2)));
+        // The operating system may round the buffer sizes up
+        assert!(socket.recv_buffer_size().unwrap() >= 128 * 1024);
+        assert!(socket.send_buffer_size().unwrap() >= 128 * 1024);
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(5));
+            assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(1));
+            assert_eq!(socket.keepalive_retries().unwrap(), 3);
+        }
+    });
+}
+
+#[test]
+fn default_socket_options_change_nothing() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let (socket, _listener) = connected_socket().await;
+        let before = {
+            let socket = SockRef::from(&socket);
+            (
+                socket.nodelay().unwrap(),
+                socket.keepalive().unwrap(),
+                socket.linger().unwrap(),
+                socket.recv_buffer_size().unwrap(),
+            )
+        };
+        SocketOptions::default().apply(&socket).unwrap();
+        let socket = SockRef::from(&socket);
+        assert_eq!(
+            (
+                socket.nodelay().unwrap(),
+                socket.keepalive().unwrap(),
+                socket.linger().unwrap(),
+                socket.recv_buffer_size().unwrap(),
+            ),
+            before
+        );
+    });
+}
@@ -8,3 +8,69 @@ fn size() {
     println!("SupportedMessage size = {}", size);
     assert!(size <= 16);
 }
+
+#[test]
+fn name() {
+    use crate::types::service_types::ReadRequest;
+    let message: SupportedMessage = ReadRequest {
+        request_header: Default::default(),
+        max_age: 0f64,
+        timestamps_to_return: crate::types::TimestampsToReturn::Both,
+        nodes_to_read: None,
+    }
+    .into();
+    assert_eq!(message.name(), "ReadRequest");
+}
+
+#[test]
+fn fuzzing_decode_message() {
+    use crate::core::fuzzing;
+    use crate::types::{
+        encoding::{BinaryEncoder, DecodingOptions},
+        node_id::NodeId,
+        service_types::ReadRequest,
+        status_code::StatusCode,
+    };
+
+    let decoding_options = DecodingOptions::test();
+    let message: SupportedMessage = ReadRequest {
+        request_header: Default::default(),
+        max_age: 0f64,
+        timestamps_to_return: crate::types::TimestampsToReturn::Both,
+        nodes_to_read: None,
+    }
+    .into();
+    let mut data = message.node_id().encode_to_vec();
+    data.extend(message.encode_to_vec());
+    assert_eq!(
+        fuzzing::decode_message(&data, &decoding_options).unwrap(),
+        message
+    );
+
+    // The node id of something that is not a message
+    let data = NodeId::new(0, 85).encode_to_vec();
+    assert_eq!(
+        fuzzing::decode_message(&data, &decoding_options).unwrap_err(),
+        StatusCode::BadServiceUnsupported
+    );
+
+    // A node id from another namespace
+    let data = NodeId::new(1, 631).encode_to_vec();
+    assert_eq!(
+        fuzzing::decode_message(&data, &decoding_options).unwrap_err(),
+        StatusCode::BadDecodingError
+    );
+}
+
+#[test]
+fn fuzzing_decode_each_message() {
+    use crate::core::fuzzing;
+    use crate::types::encoding::DecodingOptions;
+
+    // Bytes that are not a valid encoding of anything either decode or fail, but never panic
+    let decoding_options = DecodingOptions::test();
+    for data in [vec![], vec![0xffu8; 64], (0..=255u8).collect::<Vec<u8>>()] {
+        let count = fuzzing::decode_each_message(&data, &decoding_options).count();
+        assert_eq!(count, SupportedMessage::OBJECT_IDS.len());
+    }
+}
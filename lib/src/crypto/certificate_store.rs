@@ -9,6 +9,7 @@ use std::fs::{metadata, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
 use openssl::{pkey, x509};
 
 use crate::types::status_code::StatusCode;
@@ -16,6 +17,8 @@ use crate::types::status_code::StatusCode;
 use super::{
     pkey::PrivateKey,
     security_policy::SecurityPolicy,
+    system_store::SystemCertificateStore,
+    thumbprint::{Thumbprint, ThumbprintAlgorithm},
     x509::{X509Data, X509},
 };
 
@@ -27,6 +30,37 @@ const OWN_PRIVATE_KEY_PATH: &str = "private/private.pem";
 const TRUSTED_CERTS_DIR: &str = "trusted";
 /// The directory holding rejected certificates
 const REJECTED_CERTS_DIR: &str = "rejected";
+/// The directory holding the certificates of CAs that are needed to build a certificate chain
+/// but are not trusted themselves
+const ISSUER_CERTS_DIR: &str = "issuer";
+/// The most certificates that a chain may hold, to stop a chain of CAs that issue each other
+const MAX_CHAIN_LENGTH: usize = 10;
+
+/// The extension of the file next to a rejected certificate that holds its `RejectionInfo`
+const REJECTION_INFO_EXTENSION: &str = "yaml";
+
+/// Says why and when a certificate was rejected, and which peer presented it. It is stored next
+/// to the certificate in the rejected folder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionInfo {
+    /// The status code that validating the certificate failed with
+    pub reason: StatusCode,
+    /// When the certificate was rejected
+    pub rejected_at: DateTime<Utc>,
+    /// The address of the peer that presented the certificate, if it is known
+    pub peer_address: Option<String>,
+}
+
+/// A certificate in the rejected folder
+#[derive(Debug, Clone)]
+pub struct RejectedCertificate {
+    /// The certificate
+    pub cert: X509,
+    /// The path of the certificate in the rejected folder
+    pub path: PathBuf,
+    /// Why the certificate was rejected, unless that was not recorded
+    pub rejection_info: Option<RejectionInfo>,
+}
 
 /// The certificate store manages the storage of a server/client's own certificate & private key
 /// and the trust / rejection of certificates from the other end.
@@ -48,6 +82,14 @@ pub struct CertificateStore {
     /// into the trusted folder if this flag is set. Certs in the trusted folder must still pass
     /// validity checks.
     trust_unknown_certs: bool,
+    /// A cert that is not in the trusted folder is trusted if a cert there has the same subject
+    /// key identifier and public key, e.g. because the cert was renewed with the same key. The
+    /// renewed cert is stored into the trusted folder and must still pass validity checks.
+    match_subject_key_identifier: bool,
+    /// The stores of the operating system that the own certificate and private key are read
+    /// from instead of the pki directory, and that trusted certs are read from as well as from
+    /// the trusted folder.
+    system_store: Option<SystemCertificateStore>,
 }
 
 impl CertificateStore {
@@ -62,9 +104,36 @@ impl CertificateStore {
             check_time: true,
             skip_verify_certs: false,
             trust_unknown_certs: false,
+            match_subject_key_identifier: false,
+            system_store: None,
         }
     }
 
+    /// Sets up the certificate store to read its own certificate and private key, and trusted
+    /// certificates, from the stores of the operating system. The PKI directory still holds the
+    /// rejected certificates and may hold more trusted certificates.
+    pub fn new_with_system_store(
+        pki_path: &Path,
+        system_store: SystemCertificateStore,
+    ) -> (CertificateStore, Option<X509>, Option<PrivateKey>) {
+        let mut certificate_store = CertificateStore::new(pki_path);
+        certificate_store.system_store = Some(system_store);
+        if certificate_store.ensure_pki_path().is_err() {
+            error!("Folder for storing certificates cannot be examined so rejected certificates cannot be stored.");
+        }
+        let (cert, pkey) = match certificate_store.read_own_cert_and_pkey() {
+            Ok((cert, pkey)) => (Some(cert), Some(pkey)),
+            Err(err) => {
+                error!(
+                    "Application instance certificate and private key could not be read from the store of the operating system - {}",
+                    err
+                );
+                (None, None)
+            }
+        };
+        (certificate_store, cert, pkey)
+    }
+
     pub fn new_with_x509_data<X>(
         pki_path: &Path,
         overwrite: bool,
@@ -122,6 +191,10 @@ impl CertificateStore {
         self.check_time = check_time;
     }
 
+    pub fn set_match_subject_key_identifier(&mut self, match_subject_key_identifier: bool) {
+        self.match_subject_key_identifier = match_subject_key_identifier;
+    }
+
     /// Reads a private key from a path on disk.
     pub fn read_pkey(path: &Path) -> Result<PrivateKey, String> {
         if let Ok(pkey_info) = metadata(path) {
@@ -139,7 +212,9 @@ impl CertificateStore {
 
     /// Reads the store's own certificate and private key
     pub fn read_own_cert_and_pkey(&self) -> Result<(X509, PrivateKey), String> {
-        if let Ok(cert) = CertificateStore::read_cert(&self.own_certificate_path()) {
+        if let Some(ref system_store) = self.system_store {
+            system_store.read_own_cert_and_pkey()
+        } else if let Ok(cert) = CertificateStore::read_cert(&self.own_certificate_path()) {
             CertificateStore::read_pkey(&self.own_private_key_path())
                 .map(|pkey| (cert, pkey))
                 .map_err(|_| {
@@ -215,8 +290,41 @@ impl CertificateStore {
         hostname: Option<&str>,
         application_uri: Option<&str>,
     ) -> StatusCode {
-        let result = self.validate_application_instance_cert(
-            cert,
+        self.validate_or_reject_application_instance_cert_chain(
+            std::slice::from_ref(cert),
+            security_policy,
+            hostname,
+            application_uri,
+            None,
+        )
+    }
+
+    /// Validates the certificate chain, i.e. the leaf certificate followed by the certificates
+    /// of the CAs that issued it, like `validate_or_reject_application_instance_cert`. The leaf
+    /// certificate is the one that is stored in the rejected folder, along with why it was
+    /// rejected and the address of the peer that presented it.
+    ///
+    /// # Errors
+    ///
+    /// A non `Good` status code indicates a failure in the cert or in some action required in
+    /// order to validate it.
+    ///
+    pub fn validate_or_reject_application_instance_cert_chain(
+        &self,
+        chain: &[X509],
+        security_policy: SecurityPolicy,
+        hostname: Option<&str>,
+        application_uri: Option<&str>,
+        peer_address: Option<&str>,
+    ) -> StatusCode {
+        let cert = if let Some(cert) = chain.first() {
+            cert
+        } else {
+            error!("Certificate chain is empty");
+            return StatusCode::BadCertificateInvalid;
+        };
+        let result = self.validate_application_instance_cert_chain(
+            chain,
             security_policy,
             hostname,
             application_uri,
@@ -228,8 +336,16 @@ impl CertificateStore {
                 }
                 _ => {
                     // Store result in rejected folder
-                    // TODO this appears to be redundant if cert is already in rejected dir
-                    let _ = self.store_rejected_cert(cert);
+                    let rejection_info = RejectionInfo {
+                        reason: result,
+                        rejected_at: Utc::now(),
+                        peer_address: peer_address.map(|peer_address| peer_address.to_string()),
+                    };
+                    if let Err(err) =
+                        self.store_rejected_cert_with_rejection_info(cert, &rejection_info)
+                    {
+                        error!("Rejected certificate cannot be stored, {}", err);
+                    }
                 }
             }
         }
@@ -279,13 +395,44 @@ impl CertificateStore {
         hostname: Option<&str>,
         application_uri: Option<&str>,
     ) -> StatusCode {
+        self.validate_application_instance_cert_chain(
+            std::slice::from_ref(cert),
+            security_policy,
+            hostname,
+            application_uri,
+        )
+    }
+
+    /// Validates the certificate chain, i.e. the leaf certificate followed by the certificates
+    /// of the CAs that issued it, like `validate_application_instance_cert`. A leaf certificate
+    /// that is not in the trusted folder is trusted if it was issued by a certificate in the
+    /// trusted folder, either directly or through the CAs of the chain or of the issuer folder.
+    ///
+    /// # Errors
+    ///
+    /// A non `Good` status code indicates a failure in the cert or in some action required in
+    /// order to validate it.
+    ///
+    pub fn validate_application_instance_cert_chain(
+        &self,
+        chain: &[X509],
+        security_policy: SecurityPolicy,
+        hostname: Option<&str>,
+        application_uri: Option<&str>,
+    ) -> StatusCode {
+        let (cert, issuer_certs) = if let Some((cert, issuer_certs)) = chain.split_first() {
+            (cert, issuer_certs)
+        } else {
+            error!("Certificate chain is empty");
+            return StatusCode::BadCertificateInvalid;
+        };
         let cert_file_name = CertificateStore::cert_file_name(cert);
         debug!("Validating cert with name on disk {}", cert_file_name);
 
         // Look for the cert in the rejected folder. If it's rejected there is no purpose going
         // any further
         {
-            let mut cert_path = self.rejected_certs_dir();
+            let cert_path = self.rejected_certs_dir();
             if !cert_path.exists() {
                 error!(
                     "Path for rejected certificates {} does not exist",
@@ -293,8 +440,7 @@ impl CertificateStore {
                 );
                 return StatusCode::BadUnexpectedError;
             }
-            cert_path.push(&cert_file_name);
-            if cert_path.exists() {
+            if Self::find_cert_path(&cert_path, cert).is_some() {
                 warn!(
                     "Certificate {} is untrusted because it resides in the rejected directory",
                     cert_file_name
@@ -307,19 +453,48 @@ impl CertificateStore {
         // trusted
         {
             // Check the trusted folder
-            let mut cert_path = self.trusted_certs_dir();
-            if !cert_path.exists() {
+            let trusted_certs_dir = self.trusted_certs_dir();
+            if !trusted_certs_dir.exists() {
                 error!(
                     "Path for rejected certificates {} does not exist",
-                    cert_path.display()
+                    trusted_certs_dir.display()
                 );
                 return StatusCode::BadUnexpectedError;
             }
-            cert_path.push(&cert_file_name);
 
-            // Check if cert is in the trusted folder
-            if !cert_path.exists() {
-                // ... trust checks based on ca could be added here to add cert straight to trust folder
+            // Check if cert is in the trusted folder. A cert that is trusted because of its issuer
+            // or because it is in the trusted store of the operating system has no file to
+            // compare to.
+            let cert_path = if let Some(cert_path) = Self::find_cert_path(&trusted_certs_dir, cert)
+            {
+                Some(cert_path)
+            } else if self.is_in_system_trusted_store(cert) {
+                debug!(
+                    "Certificate {} is in the trusted store of the operating system",
+                    cert_file_name
+                );
+                None
+            } else if let Some(renewed_cert_path) = self.find_renewed_cert_path(cert) {
+                // The cert replaces one that is trusted, so it is trusted too
+                info!(
+                    "Certificate {} is unknown but has the subject key identifier and key of trusted certificate {}, so it will be stored in the trusted directory",
+                    cert_file_name,
+                    renewed_cert_path.display()
+                );
+                match self.store_trusted_cert(cert) {
+                    Ok(cert_path) => Some(cert_path),
+                    Err(err) => {
+                        error!("Certificate {} cannot be stored, {}", cert_file_name, err);
+                        return StatusCode::BadUnexpectedError;
+                    }
+                }
+            } else if self.is_issued_by_trusted_cert(cert, issuer_certs) {
+                info!(
+                    "Certificate {} is unknown but was issued by a trusted certificate",
+                    cert_file_name
+                );
+                None
+            } else {
                 if self.trust_unknown_certs {
                     // Put the unknown cert into the trusted folder
                     warn!("Certificate {} is unknown but policy will store it into the trusted directory", cert_file_name);
@@ -330,12 +505,15 @@ impl CertificateStore {
                     let _ = self.store_rejected_cert(cert);
                     return StatusCode::BadCertificateUntrusted;
                 }
-            }
+                Some(trusted_certs_dir.join(&cert_file_name))
+            };
 
             // Read the cert from the trusted folder to make sure it matches the one supplied
-            if !CertificateStore::ensure_cert_and_file_are_the_same(cert, &cert_path) {
-                error!("Certificate in memory does not match the one on disk {} so cert will automatically be treated as untrusted", cert_path.display());
-                return StatusCode::BadUnexpectedError;
+            if let Some(cert_path) = cert_path {
+                if !CertificateStore::ensure_cert_and_file_are_the_same(cert, &cert_path) {
+                    error!("Certificate in memory does not match the one on disk {} so cert will automatically be treated as untrusted", cert_path.display());
+                    return StatusCode::BadUnexpectedError;
+                }
             }
 
             // Check that the certificate is the right length for the security policy
@@ -402,23 +580,145 @@ impl CertificateStore {
             }
 
             // Other tests that we might do with trust lists
-            // ... issuer
-            // ... trust (self-signed, ca etc.)
             // ... revocation
         }
         StatusCode::Good
     }
 
+    /// Finds the file of the cert in the directory. The file is named after the SHA-1 thumbprint of
+    /// the cert, which is what certs are stored as, or after its SHA-256 thumbprint.
+    fn find_cert_path(dir: &Path, cert: &X509) -> Option<PathBuf> {
+        [ThumbprintAlgorithm::Sha1, ThumbprintAlgorithm::Sha256]
+            .iter()
+            .map(|algorithm| dir.join(Self::cert_file_name_with_algorithm(cert, *algorithm)))
+            .find(|cert_path| cert_path.exists())
+    }
+
+    /// Finds a cert in the trusted folder that the cert is a renewal of, i.e. one with the same
+    /// subject key identifier and public key, if the store matches certs that way.
+    fn find_renewed_cert_path(&self, cert: &X509) -> Option<PathBuf> {
+        if !self.match_subject_key_identifier {
+            return None;
+        }
+        let subject_key_identifier = cert.subject_key_identifier()?;
+        let entries = std::fs::read_dir(self.trusted_certs_dir()).ok()?;
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|cert_path| {
+                // The key is compared too, because anybody can put any identifier into a cert
+                CertificateStore::read_cert(cert_path)
+                    .map(|trusted_cert| {
+                        trusted_cert.subject_key_identifier().as_ref()
+                            == Some(&subject_key_identifier)
+                            && trusted_cert.has_same_public_key(cert)
+                    })
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Tests if the cert was issued by a cert in the trusted folder, either directly or through
+    /// the issuer certs, which were sent with the cert, and the certs in the issuer folder.
+    /// A self-signed cert is never trusted this way.
+    fn is_issued_by_trusted_cert(&self, cert: &X509, issuer_certs: &[X509]) -> bool {
+        if cert.is_self_signed() {
+            return false;
+        }
+        let trusted_certs = self.trusted_certs();
+        if trusted_certs.is_empty() {
+            return false;
+        }
+        let mut intermediate_certs = issuer_certs.to_vec();
+        intermediate_certs.extend(Self::read_certs(&self.issuer_certs_dir()));
+        // The time of the issuer certs is checked along with the cert unless the store skips it
+        let check_time = self.check_time && !self.skip_verify_certs;
+        match cert.verify_issuer(&trusted_certs, &intermediate_certs, check_time) {
+            Ok(_) => true,
+            Err(err) => {
+                debug!(
+                    "Certificate {} was not issued by a trusted certificate, {}",
+                    CertificateStore::cert_file_name(cert),
+                    err
+                );
+                false
+            }
+        }
+    }
+
+    /// Finds the certs of the CAs that issued the cert in the issuer and trusted folders,
+    /// starting with the cert's own issuer, e.g. to send them after the application's own cert.
+    /// The chain ends at a self-signed cert or at a cert whose issuer cannot be found, so a
+    /// self-signed cert has no issuer certs.
+    pub fn issuer_certs(&self, cert: &X509) -> Vec<X509> {
+        let mut certs = Self::read_certs(&self.issuer_certs_dir());
+        certs.extend(self.trusted_certs());
+
+        let mut issuer_certs: Vec<X509> = Vec::new();
+        let mut subject = cert.clone();
+        while !subject.is_self_signed() && issuer_certs.len() < MAX_CHAIN_LENGTH {
+            if let Some(issuer) = certs.iter().find(|issuer| subject.is_issued_by(issuer)) {
+                subject = issuer.clone();
+                issuer_certs.push(subject.clone());
+            } else {
+                break;
+            }
+        }
+        issuer_certs
+    }
+
+    /// Reads the certs of the trusted folder and of the trusted store of the operating system
+    fn trusted_certs(&self) -> Vec<X509> {
+        let mut trusted_certs = Self::read_certs(&self.trusted_certs_dir());
+        trusted_certs.extend(self.system_trusted_certs());
+        trusted_certs
+    }
+
+    /// Reads the certs of the trusted store of the operating system, if the store uses one
+    fn system_trusted_certs(&self) -> Vec<X509> {
+        if let Some(ref system_store) = self.system_store {
+            system_store.read_trusted_certs().unwrap_or_else(|err| {
+                error!("Cannot read trusted certificates, {}", err);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Tests if the cert is in the trusted store of the operating system
+    fn is_in_system_trusted_store(&self, cert: &X509) -> bool {
+        let der = cert.to_der().ok();
+        self.system_trusted_certs()
+            .iter()
+            .any(|trusted_cert| trusted_cert.to_der().ok() == der)
+    }
+
+    /// Reads every cert in the directory, skipping files that are not certs
+    fn read_certs(dir: &Path) -> Vec<X509> {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter_map(|cert_path| CertificateStore::read_cert(&cert_path).ok())
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
     /// Returns a certificate file name from the cert's issuer and thumbprint fields.
     /// File name is either "prefix - [thumbprint].der" or "thumbprint.der" depending on
     /// the cert's common name being empty or not
     pub fn cert_file_name(cert: &X509) -> String {
+        Self::cert_file_name_with_algorithm(cert, ThumbprintAlgorithm::Sha1)
+    }
+
+    /// Returns a certificate file name like `cert_file_name` with the thumbprint of the algorithm
+    pub fn cert_file_name_with_algorithm(cert: &X509, algorithm: ThumbprintAlgorithm) -> String {
         let prefix = if let Ok(common_name) = cert.common_name() {
             common_name.trim().to_string().replace('/', "")
         } else {
             String::new()
         };
-        let thumbprint = cert.thumbprint().as_hex_string();
+        let thumbprint = cert.thumbprint_with_algorithm(algorithm).as_hex_string();
 
         if !prefix.is_empty() {
             format!("{} [{}].der", prefix, thumbprint)
@@ -435,7 +735,7 @@ impl CertificateStore {
     ///
     pub fn ensure_pki_path(&self) -> Result<(), String> {
         let mut path = self.pki_path.clone();
-        let subdirs = [TRUSTED_CERTS_DIR, REJECTED_CERTS_DIR];
+        let subdirs = [TRUSTED_CERTS_DIR, REJECTED_CERTS_DIR, ISSUER_CERTS_DIR];
         for subdir in &subdirs {
             path.push(subdir);
             CertificateStore::ensure_dir(&path)?;
@@ -491,6 +791,13 @@ impl CertificateStore {
         path
     }
 
+    /// Get the path to the issuer certs dir
+    pub fn issuer_certs_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(ISSUER_CERTS_DIR);
+        path
+    }
+
     /// Write a cert to the rejected directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
@@ -507,6 +814,100 @@ impl CertificateStore {
         Ok(cert_path)
     }
 
+    /// Writes a cert to the rejected directory like `store_rejected_cert`, along with why it was
+    /// rejected. If the write succeeds, the function returns a path to the written cert.
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure
+    ///
+    pub fn store_rejected_cert_with_rejection_info(
+        &self,
+        cert: &X509,
+        rejection_info: &RejectionInfo,
+    ) -> Result<PathBuf, String> {
+        let cert_path = self.store_rejected_cert(cert)?;
+        let rejection_info = serde_yaml::to_string(rejection_info).map_err(|err| {
+            format!(
+                "Cannot serialize rejection info of {}, {}",
+                cert_path.display(),
+                err
+            )
+        })?;
+        let _ = CertificateStore::write_to_file(
+            rejection_info.as_bytes(),
+            &cert_path.with_extension(REJECTION_INFO_EXTENSION),
+            true,
+        )?;
+        Ok(cert_path)
+    }
+
+    /// Lists the certs in the rejected directory along with why they were rejected, e.g. so an
+    /// administrator can decide which to trust
+    pub fn rejected_certs(&self) -> Vec<RejectedCertificate> {
+        let entries = if let Ok(entries) = std::fs::read_dir(self.rejected_certs_dir()) {
+            entries
+        } else {
+            return Vec::new();
+        };
+        let mut rejected_certs = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|e| e == "der").unwrap_or(false))
+            .filter_map(|path| {
+                let cert = CertificateStore::read_cert(&path).ok()?;
+                let rejection_info =
+                    std::fs::read_to_string(path.with_extension(REJECTION_INFO_EXTENSION))
+                        .ok()
+                        .and_then(|rejection_info| serde_yaml::from_str(&rejection_info).ok());
+                Some(RejectedCertificate {
+                    cert,
+                    path,
+                    rejection_info,
+                })
+            })
+            .collect::<Vec<_>>();
+        rejected_certs.sort_by(|a, b| a.path.cmp(&b.path));
+        rejected_certs
+    }
+
+    /// Trusts the rejected cert with the thumbprint, by moving it from the rejected directory to
+    /// the trusted directory. Its rejection info is removed. If the move succeeds, the function
+    /// returns the path to the trusted cert.
+    ///
+    /// # Errors
+    ///
+    /// A string description of any failure, e.g. that no rejected cert has the thumbprint
+    ///
+    pub fn trust_rejected_cert(&self, thumbprint: &Thumbprint) -> Result<PathBuf, String> {
+        let rejected_cert = self
+            .rejected_certs()
+            .into_iter()
+            .find(|rejected_cert| {
+                rejected_cert
+                    .cert
+                    .thumbprint_with_algorithm(thumbprint.algorithm())
+                    == *thumbprint
+            })
+            .ok_or_else(|| {
+                format!(
+                    "There is no rejected certificate with thumbprint {}",
+                    thumbprint.as_hex_string()
+                )
+            })?;
+        let trusted_cert_path = self.store_trusted_cert(&rejected_cert.cert)?;
+        std::fs::remove_file(&rejected_cert.path)
+            .map_err(|err| format!("Cannot remove {}, {}", rejected_cert.path.display(), err))?;
+        let rejection_info_path = rejected_cert.path.with_extension(REJECTION_INFO_EXTENSION);
+        if rejection_info_path.exists() {
+            let _ = std::fs::remove_file(&rejection_info_path);
+        }
+        info!(
+            "Rejected certificate {} is now trusted",
+            trusted_cert_path.display()
+        );
+        Ok(trusted_cert_path)
+    }
+
     /// Writes a cert to the trusted directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
@@ -10,8 +10,8 @@ use std::fmt;
 
 use crate::types::{service_types::SignatureData, status_code::StatusCode, ByteString, UAString};
 pub use {
-    aeskey::*, certificate_store::*, hash::*, pkey::*, security_policy::*, thumbprint::*,
-    user_identity::*, x509::*,
+    aeskey::*, certificate_store::*, hash::*, pkey::*, security_policy::*, system_store::*,
+    thumbprint::*, user_identity::*, x509::*,
 };
 
 #[cfg(test)]
@@ -23,6 +23,7 @@ pub mod hash;
 pub mod pkey;
 pub mod random;
 pub mod security_policy;
+pub mod system_store;
 pub mod thumbprint;
 pub mod user_identity;
 pub mod x509;
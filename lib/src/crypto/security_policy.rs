@@ -15,7 +15,9 @@ use super::{
     aeskey::AesKey,
     hash,
     pkey::{KeySize, PrivateKey, PublicKey, RsaPadding},
-    random, SHA1_SIZE, SHA256_SIZE,
+    random,
+    thumbprint::ThumbprintAlgorithm,
+    SHA1_SIZE, SHA256_SIZE,
 };
 
 // These are constants that govern the different encryption / signing modes for OPC UA. In some
@@ -24,8 +26,8 @@ use super::{
 
 /// Aes128-Sha256-RsaOaep security policy
 ///
-///   AsymmetricEncryptionAlgorithm_RSA-PKCS15-SHA2-256
-///   AsymmetricSignatureAlgorithm_RSA-OAEP-SHA1
+///   AsymmetricEncryptionAlgorithm_RSA-OAEP-SHA1
+///   AsymmetricSignatureAlgorithm_RSA-PKCS15-SHA2-256
 ///   CertificateSignatureAlgorithm_RSA-PKCS15-SHA2-256
 ///   KeyDerivationAlgorithm_P-SHA2-256
 ///   SymmetricEncryptionAlgorithm_AES128-CBC
@@ -45,7 +47,7 @@ mod aes_128_sha_256_rsa_oaep {
 
     pub const SYMMETRIC_SIGNATURE_ALGORITHM: &str = DSIG_HMAC_SHA256;
     pub const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = DSIG_RSA_SHA256;
-    pub const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = ENC_RSA_15;
+    pub const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = ENC_RSA_OAEP;
     pub const DERIVED_SIGNATURE_KEY_LENGTH: usize = 256;
     pub const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (2048, 4096);
 }
@@ -73,7 +75,7 @@ mod aes_256_sha_256_rsa_pss {
 
     pub const SYMMETRIC_SIGNATURE_ALGORITHM: &str = DSIG_HMAC_SHA256;
     pub const ASYMMETRIC_SIGNATURE_ALGORITHM: &str = DSIG_RSA_PSS_SHA2_256;
-    pub const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = ENC_RSA_OAEP;
+    pub const ASYMMETRIC_ENCRYPTION_ALGORITHM: &str = ENC_RSA_OAEP_SHA256;
     pub const DERIVED_SIGNATURE_KEY_LENGTH: usize = 256;
     pub const ASYMMETRIC_KEY_LENGTH: (usize, usize) = (2048, 4096);
 }
@@ -402,6 +404,20 @@ impl SecurityPolicy {
         }
     }
 
+    /// The hash algorithm of the receiver certificate thumbprint in the asymmetric security
+    /// header. Every RSA policy of the spec uses SHA-1 for it.
+    pub fn certificate_thumbprint_algorithm(&self) -> ThumbprintAlgorithm {
+        match self {
+            SecurityPolicy::None
+            | SecurityPolicy::Unknown
+            | SecurityPolicy::Basic128Rsa15
+            | SecurityPolicy::Basic256
+            | SecurityPolicy::Basic256Sha256
+            | SecurityPolicy::Aes128Sha256RsaOaep
+            | SecurityPolicy::Aes256Sha256RsaPss => ThumbprintAlgorithm::Sha1,
+        }
+    }
+
     pub fn from_uri(uri: &str) -> SecurityPolicy {
         match uri {
             constants::SECURITY_POLICY_NONE_URI => SecurityPolicy::None,
@@ -0,0 +1,429 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Reads the application instance certificate, its private key and the trusted certificates from
+//! the certificate store of the operating system, for sites whose PKI tooling manages the stores
+//! of the operating system rather than a pki directory.
+//!
+//! Only the Windows certificate store is supported. On other platforms a configuration that uses
+//! the store of the operating system is invalid.
+
+use super::{pkey::PrivateKey, x509::X509};
+
+/// Says whether the stores of the current user or the stores of the local machine are opened
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SystemStoreLocation {
+    CurrentUser,
+    LocalMachine,
+}
+
+/// Names the stores of the operating system that the certificate store reads its own
+/// certificate and the trusted certificates from, instead of the pki directory. Rejected
+/// certificates are still written to the pki directory, and its trusted folder is used as well.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemCertificateStore {
+    /// Whether the stores of the current user or of the local machine are opened
+    pub location: SystemStoreLocation,
+    /// The store that holds the application instance certificate and its private key, e.g. "My"
+    pub own_store: String,
+    /// The SHA-1 thumbprint of the application instance certificate in hex, which is how the
+    /// certificate tools of the operating system show it. Spaces are ignored.
+    pub own_thumbprint: String,
+    /// The store that holds the certificates of trusted peers and CAs, e.g. "TrustedPeople"
+    pub trusted_store: String,
+}
+
+impl SystemCertificateStore {
+    /// Tests if the operating system has a certificate store that is supported
+    pub fn is_supported() -> bool {
+        cfg!(windows)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let mut valid = true;
+        if !Self::is_supported() {
+            error!("The certificate store of the operating system is only supported on Windows");
+            valid = false;
+        }
+        if self.own_store.is_empty() || self.trusted_store.is_empty() {
+            error!("The names of the own and trusted stores of the operating system must be set");
+            valid = false;
+        }
+        let own_thumbprint = self.own_thumbprint();
+        if own_thumbprint.len() != 40 || !own_thumbprint.chars().all(|c| c.is_ascii_hexdigit()) {
+            error!(
+                "Own certificate thumbprint \"{}\" is not a SHA-1 thumbprint in hex",
+                self.own_thumbprint
+            );
+            valid = false;
+        }
+        valid
+    }
+
+    /// The thumbprint of the own certificate in lower case hex without spaces
+    fn own_thumbprint(&self) -> String {
+        self.own_thumbprint
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase()
+    }
+
+    /// Reads the application instance certificate and its private key from the own store. The
+    /// private key must be exportable.
+    pub fn read_own_cert_and_pkey(&self) -> Result<(X509, PrivateKey), String> {
+        let own_thumbprint = self.own_thumbprint();
+        imp::read_cert_and_pkey(self.location, &self.own_store, |cert| {
+            cert.thumbprint().as_hex_string() == own_thumbprint
+        })
+        .and_then(|cert_and_pkey| {
+            cert_and_pkey.ok_or_else(|| {
+                format!(
+                    "Cannot find certificate with thumbprint {} in store {}",
+                    own_thumbprint, self.own_store
+                )
+            })
+        })
+    }
+
+    /// Reads the certificates of the trusted store
+    pub fn read_trusted_certs(&self) -> Result<Vec<X509>, String> {
+        imp::read_certs(self.location, &self.trusted_store)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    //! The Windows certificate store, which is read through crypt32 and the private key is
+    //! exported through ncrypt.
+
+    use std::{ffi::c_void, ptr};
+
+    use openssl::pkey;
+
+    use crate::crypto::{pkey::PrivateKey, random, x509::X509};
+
+    use super::SystemStoreLocation;
+
+    type Handle = *mut c_void;
+    type NCryptKeyHandle = usize;
+
+    /// CERT_CONTEXT
+    #[repr(C)]
+    struct CertContext {
+        cert_encoding_type: u32,
+        cert_encoded: *const u8,
+        cert_encoded_len: u32,
+        cert_info: *mut c_void,
+        cert_store: Handle,
+    }
+
+    /// NCryptBuffer
+    #[repr(C)]
+    struct NCryptBuffer {
+        buffer_len: u32,
+        buffer_type: u32,
+        buffer: *mut c_void,
+    }
+
+    /// NCryptBufferDesc
+    #[repr(C)]
+    struct NCryptBufferDesc {
+        version: u32,
+        buffers_len: u32,
+        buffers: *mut NCryptBuffer,
+    }
+
+    /// CRYPT_PKCS12_PBE_PARAMS followed by the salt
+    #[repr(C)]
+    struct PbeParams {
+        iterations: i32,
+        salt_len: u32,
+        salt: [u8; 8],
+    }
+
+    const CERT_STORE_PROV_SYSTEM_W: usize = 10;
+    const CERT_SYSTEM_STORE_CURRENT_USER: u32 = 1 << 16;
+    const CERT_SYSTEM_STORE_LOCAL_MACHINE: u32 = 2 << 16;
+    const CERT_STORE_OPEN_EXISTING_FLAG: u32 = 0x0000_4000;
+    const CERT_STORE_READONLY_FLAG: u32 = 0x0000_8000;
+    const CRYPT_ACQUIRE_SILENT_FLAG: u32 = 0x0000_0040;
+    const CRYPT_ACQUIRE_ONLY_NCRYPT_KEY_FLAG: u32 = 0x0004_0000;
+    const CERT_NCRYPT_KEY_SPEC: u32 = 0xFFFF_FFFF;
+    const NCRYPT_SILENT_FLAG: u32 = 0x0000_0040;
+    const NCRYPTBUFFER_PKCS_ALG_OID: u32 = 41;
+    const NCRYPTBUFFER_PKCS_ALG_PARAM: u32 = 42;
+    const NCRYPTBUFFER_PKCS_SECRET: u32 = 46;
+    /// pbeWithSHAAnd3-KeyTripleDES-CBC, which is what Windows encrypts an exported key with
+    const PBE_WITH_SHA_AND_3DES_OID: &[u8] = b"1.2.840.113549.1.12.1.3\0";
+
+    #[link(name = "crypt32")]
+    extern "system" {
+        fn CertOpenStore(
+            store_provider: usize,
+            encoding_type: u32,
+            crypt_prov: usize,
+            flags: u32,
+            para: *const c_void,
+        ) -> Handle;
+        fn CertCloseStore(cert_store: Handle, flags: u32) -> i32;
+        fn CertEnumCertificatesInStore(
+            cert_store: Handle,
+            prev_cert_context: *const CertContext,
+        ) -> *const CertContext;
+        fn CertFreeCertificateContext(cert_context: *const CertContext) -> i32;
+        fn CryptAcquireCertificatePrivateKey(
+            cert: *const CertContext,
+            flags: u32,
+            parameters: *const c_void,
+            key: *mut NCryptKeyHandle,
+            key_spec: *mut u32,
+            caller_free: *mut i32,
+        ) -> i32;
+    }
+
+    #[link(name = "ncrypt")]
+    extern "system" {
+        fn NCryptExportKey(
+            key: NCryptKeyHandle,
+            export_key: NCryptKeyHandle,
+            blob_type: *const u16,
+            parameter_list: *const NCryptBufferDesc,
+            output: *mut u8,
+            output_len: u32,
+            result_len: *mut u32,
+            flags: u32,
+        ) -> i32;
+        fn NCryptFreeObject(object: NCryptKeyHandle) -> i32;
+    }
+
+    fn wide_string(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// An open store that is closed when it is dropped
+    struct Store(Handle);
+
+    impl Store {
+        fn open(location: SystemStoreLocation, name: &str) -> Result<Store, String> {
+            let location_flag = match location {
+                SystemStoreLocation::CurrentUser => CERT_SYSTEM_STORE_CURRENT_USER,
+                SystemStoreLocation::LocalMachine => CERT_SYSTEM_STORE_LOCAL_MACHINE,
+            };
+            let wide_name = wide_string(name);
+            let store = unsafe {
+                CertOpenStore(
+                    CERT_STORE_PROV_SYSTEM_W,
+                    0,
+                    0,
+                    location_flag | CERT_STORE_OPEN_EXISTING_FLAG | CERT_STORE_READONLY_FLAG,
+                    wide_name.as_ptr() as *const c_void,
+                )
+            };
+            if store.is_null() {
+                Err(format!(
+                    "Cannot open certificate store {} of {:?}",
+                    name, location
+                ))
+            } else {
+                Ok(Store(store))
+            }
+        }
+
+        /// Calls the function with each certificate of the store until it returns true, and
+        /// returns the certificate that it returned true for. The context of that certificate
+        /// is only valid until the function returns.
+        fn find<F>(&self, mut f: F) -> Option<X509>
+        where
+            F: FnMut(&X509, *const CertContext) -> bool,
+        {
+            let mut context: *const CertContext = ptr::null();
+            loop {
+                // The previous context is freed by the call
+                context = unsafe { CertEnumCertificatesInStore(self.0, context) };
+                if context.is_null() {
+                    return None;
+                }
+                let der = unsafe {
+                    std::slice::from_raw_parts(
+                        (*context).cert_encoded,
+                        (*context).cert_encoded_len as usize,
+                    )
+                };
+                if let Ok(cert) = X509::from_der(der) {
+                    if f(&cert, context) {
+                        unsafe { CertFreeCertificateContext(context) };
+                        return Some(cert);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for Store {
+        fn drop(&mut self) {
+            unsafe { CertCloseStore(self.0, 0) };
+        }
+    }
+
+    pub(super) fn read_certs(
+        location: SystemStoreLocation,
+        store_name: &str,
+    ) -> Result<Vec<X509>, String> {
+        let store = Store::open(location, store_name)?;
+        let mut certs = Vec::new();
+        let _ = store.find(|cert, _| {
+            certs.push(cert.clone());
+            false
+        });
+        Ok(certs)
+    }
+
+    pub(super) fn read_cert_and_pkey<F>(
+        location: SystemStoreLocation,
+        store_name: &str,
+        is_own_cert: F,
+    ) -> Result<Option<(X509, PrivateKey)>, String>
+    where
+        F: Fn(&X509) -> bool,
+    {
+        let store = Store::open(location, store_name)?;
+        let mut pkey = None;
+        let cert = store.find(|cert, context| {
+            if is_own_cert(cert) {
+                pkey = Some(export_private_key(context));
+                true
+            } else {
+                false
+            }
+        });
+        match (cert, pkey) {
+            (Some(cert), Some(pkey)) => pkey.map(|pkey| Some((cert, pkey))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Exports the private key of the certificate. Windows only exports keys encrypted, so the
+    /// key is exported encrypted with a random password and decrypted again.
+    fn export_private_key(context: *const CertContext) -> Result<PrivateKey, String> {
+        let mut key: NCryptKeyHandle = 0;
+        let mut key_spec = 0u32;
+        let mut caller_free = 0i32;
+        let acquired = unsafe {
+            CryptAcquireCertificatePrivateKey(
+                context,
+                CRYPT_ACQUIRE_SILENT_FLAG | CRYPT_ACQUIRE_ONLY_NCRYPT_KEY_FLAG,
+                ptr::null(),
+                &mut key,
+                &mut key_spec,
+                &mut caller_free,
+            )
+        };
+        if acquired == 0 || key_spec != CERT_NCRYPT_KEY_SPEC {
+            return Err("Cannot acquire the private key of the certificate".to_string());
+        }
+
+        let password = random::byte_string(16).as_base64();
+        let mut wide_password = wide_string(&password);
+        let mut oid = PBE_WITH_SHA_AND_3DES_OID.to_vec();
+        let mut pbe_params = PbeParams {
+            iterations: 2000,
+            salt_len: 8,
+            salt: [0u8; 8],
+        };
+        random::bytes(&mut pbe_params.salt);
+        let mut buffers = [
+            NCryptBuffer {
+                buffer_len: (wide_password.len() * 2) as u32,
+                buffer_type: NCRYPTBUFFER_PKCS_SECRET,
+                buffer: wide_password.as_mut_ptr() as *mut c_void,
+            },
+            NCryptBuffer {
+                buffer_len: oid.len() as u32,
+                buffer_type: NCRYPTBUFFER_PKCS_ALG_OID,
+                buffer: oid.as_mut_ptr() as *mut c_void,
+            },
+            NCryptBuffer {
+                buffer_len: std::mem::size_of::<PbeParams>() as u32,
+                buffer_type: NCRYPTBUFFER_PKCS_ALG_PARAM,
+                buffer: &mut pbe_params as *mut PbeParams as *mut c_void,
+            },
+        ];
+        let parameter_list = NCryptBufferDesc {
+            version: 0,
+            buffers_len: buffers.len() as u32,
+            buffers: buffers.as_mut_ptr(),
+        };
+        let blob_type = wide_string("PKCS8_PRIVATEKEY");
+
+        let der = unsafe {
+            let mut der_len = 0u32;
+            let mut result = NCryptExportKey(
+                key,
+                0,
+                blob_type.as_ptr(),
+                &parameter_list,
+                ptr::null_mut(),
+                0,
+                &mut der_len,
+                NCRYPT_SILENT_FLAG,
+            );
+            let mut der = vec![0u8; der_len as usize];
+            if result == 0 {
+                result = NCryptExportKey(
+                    key,
+                    0,
+                    blob_type.as_ptr(),
+                    &parameter_list,
+                    der.as_mut_ptr(),
+                    der_len,
+                    &mut der_len,
+                    NCRYPT_SILENT_FLAG,
+                );
+                der.truncate(der_len as usize);
+            }
+            if caller_free != 0 {
+                NCryptFreeObject(key);
+            }
+            if result != 0 {
+                return Err(format!(
+                    "Cannot export the private key of the certificate, error {:#x}. Is the key exportable?",
+                    result
+                ));
+            }
+            der
+        };
+        pkey::PKey::private_key_from_pkcs8_passphrase(&der, password.as_bytes())
+            .map(PrivateKey::wrap_private_key)
+            .map_err(|err| format!("Cannot read the exported private key, {}", err))
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use crate::crypto::{pkey::PrivateKey, x509::X509};
+
+    use super::SystemStoreLocation;
+
+    const NOT_SUPPORTED: &str =
+        "The certificate store of the operating system is only supported on Windows";
+
+    pub(super) fn read_certs(
+        _location: SystemStoreLocation,
+        _store_name: &str,
+    ) -> Result<Vec<X509>, String> {
+        Err(NOT_SUPPORTED.to_string())
+    }
+
+    pub(super) fn read_cert_and_pkey<F>(
+        _location: SystemStoreLocation,
+        _store_name: &str,
+        _is_own_cert: F,
+    ) -> Result<Option<(X509, PrivateKey)>, String>
+    where
+        F: Fn(&X509) -> bool,
+    {
+        Err(NOT_SUPPORTED.to_string())
+    }
+}
@@ -184,4 +184,117 @@ fn user_name_identity_token_encrypted() {
     assert!(token.encryption_algorithm.is_empty());
     let password1 = decrypt_user_identity_token_password(&token, nonce.as_ref(), &pkey).unwrap();
     assert_eq!(password, password1);
+
+    // #8 This should be Rsa-OAEP since channel security policy is none, token policy is
+    // Aes128-Sha256-RsaOaep
+    user_token_policy.security_policy_uri =
+        UAString::from(SecurityPolicy::Aes128Sha256RsaOaep.to_uri());
+    let token = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        nonce.as_ref(),
+        &cert,
+        "user1",
+        &password,
+    )
+    .unwrap();
+    assert_eq!(
+        token.encryption_algorithm.as_ref(),
+        crypto::algorithms::ENC_RSA_OAEP
+    );
+    let password1 = decrypt_user_identity_token_password(&token, nonce.as_ref(), &pkey).unwrap();
+    assert_eq!(password, password1);
+
+    // #9 This should be Rsa-OAEP-Sha256 since channel security policy is none, token policy is
+    // Aes256-Sha256-RsaPss
+    user_token_policy.security_policy_uri =
+        UAString::from(SecurityPolicy::Aes256Sha256RsaPss.to_uri());
+    let token = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        nonce.as_ref(),
+        &cert,
+        "user1",
+        &password,
+    )
+    .unwrap();
+    assert_eq!(
+        token.encryption_algorithm.as_ref(),
+        crypto::algorithms::ENC_RSA_OAEP_SHA256
+    );
+    let password1 = decrypt_user_identity_token_password(&token, nonce.as_ref(), &pkey).unwrap();
+    assert_eq!(password, password1);
+}
+
+#[test]
+fn user_name_identity_token_encryption_errors() {
+    let password = String::from("abcdef123456");
+    let nonce = random::byte_string(32);
+    let (cert, pkey) = make_test_cert_1024();
+    let cert = Some(cert);
+
+    let mut user_token_policy = crate::types::service_types::UserTokenPolicy {
+        policy_id: UAString::from("x"),
+        token_type: UserTokenType::UserName,
+        issued_token_type: UAString::null(),
+        issuer_endpoint_url: UAString::null(),
+        security_policy_uri: UAString::from("http://unknown"),
+    };
+
+    // An unknown token policy is not taken as a reason to send the password in plain text
+    let result = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        nonce.as_ref(),
+        &cert,
+        "user1",
+        &password,
+    );
+    assert_eq!(result.unwrap_err(), StatusCode::BadSecurityPolicyRejected);
+
+    // Encrypting needs the certificate and nonce of the server
+    user_token_policy.security_policy_uri = UAString::from(SecurityPolicy::Basic256Sha256.to_uri());
+    let result = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        nonce.as_ref(),
+        &None,
+        "user1",
+        &password,
+    );
+    assert_eq!(result.unwrap_err(), StatusCode::BadCertificateInvalid);
+    let result = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        &[],
+        &cert,
+        "user1",
+        &password,
+    );
+    assert_eq!(result.unwrap_err(), StatusCode::BadNonceInvalid);
+
+    // The password only decrypts with the nonce it was encrypted with
+    let token = make_user_name_identity_token(
+        SecurityPolicy::None,
+        &user_token_policy,
+        nonce.as_ref(),
+        &cert,
+        "user1",
+        &password,
+    )
+    .unwrap();
+    let other_nonce = random::byte_string(32);
+    let result = decrypt_user_identity_token_password(&token, other_nonce.as_ref(), &pkey);
+    assert_eq!(result.unwrap_err(), StatusCode::BadDecodingError);
+
+    // A nonce longer than what was encrypted is an error rather than a panic
+    let long_nonce = random::byte_string(200);
+    let result = decrypt_user_identity_token_password(&token, long_nonce.as_ref(), &pkey);
+    assert_eq!(result.unwrap_err(), StatusCode::BadDecodingError);
+
+    // An algorithm that is not supported is rejected
+    let mut token = token;
+    token.encryption_algorithm = UAString::from("http://unknown");
+    let result = decrypt_user_identity_token_password(&token, nonce.as_ref(), &pkey);
+    assert_eq!(result.unwrap_err(), StatusCode::BadIdentityTokenInvalid);
 }
@@ -3,7 +3,7 @@ extern crate rustc_serialize as serialize;
 use std::fs::File;
 use std::io::Write;
 
-use crate::types::status_code::StatusCode;
+use crate::types::{status_code::StatusCode, ByteString};
 
 use crate::crypto::tests::{
     make_certificate_store, make_test_cert_1024, make_test_cert_2048, APPLICATION_HOSTNAME,
@@ -14,6 +14,7 @@ use crate::crypto::{
     certificate_store::*,
     pkey::{KeySize, PrivateKey, RsaPadding},
     random,
+    thumbprint::{Thumbprint, ThumbprintAlgorithm},
     user_identity::{legacy_password_decrypt, legacy_password_encrypt},
     x509::{X509Data, X509},
     SecurityPolicy, SHA1_SIZE, SHA256_SIZE,
@@ -73,7 +74,7 @@ fn create_cert() {
 fn ensure_pki_path() {
     let (tmp_dir, cert_store) = make_certificate_store();
     let pki = cert_store.pki_path.clone();
-    for dirname in ["rejected", "trusted"].iter() {
+    for dirname in ["rejected", "trusted", "issuer"].iter() {
         let mut subdir = pki.to_path_buf();
         subdir.push(dirname);
         assert!(subdir.exists());
@@ -138,6 +139,86 @@ fn test_and_reject_application_instance_cert() {
     drop(tmp_dir);
 }
 
+#[test]
+fn list_rejected_certs_with_rejection_info() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+    assert!(cert_store.rejected_certs().is_empty());
+
+    let (cert, _) = make_test_cert_1024();
+    let result = cert_store.validate_or_reject_application_instance_cert_chain(
+        std::slice::from_ref(&cert),
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+        Some("127.0.0.1:4855"),
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+
+    // A cert that is stored without rejection info is listed too
+    let (other_cert, _) = make_test_cert_2048();
+    cert_store.store_rejected_cert(&other_cert).unwrap();
+
+    let rejected_certs = cert_store.rejected_certs();
+    assert_eq!(rejected_certs.len(), 2);
+    let rejected_cert = rejected_certs
+        .iter()
+        .find(|rejected_cert| rejected_cert.cert.thumbprint() == cert.thumbprint())
+        .unwrap();
+    let rejection_info = rejected_cert.rejection_info.as_ref().unwrap();
+    assert_eq!(rejection_info.reason, StatusCode::BadCertificateUntrusted);
+    assert_eq!(
+        rejection_info.peer_address.as_deref(),
+        Some("127.0.0.1:4855")
+    );
+    assert!(rejection_info.rejected_at <= chrono::Utc::now());
+    let other_rejected_cert = rejected_certs
+        .iter()
+        .find(|rejected_cert| rejected_cert.cert.thumbprint() == other_cert.thumbprint())
+        .unwrap();
+    assert!(other_rejected_cert.rejection_info.is_none());
+
+    drop(tmp_dir);
+}
+
+#[test]
+fn trust_rejected_cert() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+
+    let (cert, _) = make_test_cert_1024();
+    let result = cert_store.validate_or_reject_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+
+    // The thumbprint must match a rejected cert
+    let (other_cert, _) = make_test_cert_2048();
+    assert!(cert_store
+        .trust_rejected_cert(&other_cert.thumbprint())
+        .is_err());
+
+    // Trusting the cert moves it out of the rejected folder, so it is accepted next time
+    let rejected_path = cert_store.rejected_certs()[0].path.clone();
+    let trusted_path = cert_store
+        .trust_rejected_cert(&cert.thumbprint_with_algorithm(ThumbprintAlgorithm::Sha256))
+        .unwrap();
+    assert!(trusted_path.exists());
+    assert!(!rejected_path.exists());
+    assert!(!rejected_path.with_extension("yaml").exists());
+    assert!(cert_store.rejected_certs().is_empty());
+    let result = cert_store.validate_or_reject_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert!(result.is_good());
+
+    drop(tmp_dir);
+}
+
 #[test]
 fn test_and_trust_application_instance_cert() {
     let (tmp_dir, cert_store) = make_certificate_store();
@@ -197,6 +278,394 @@ fn test_and_reject_thumbprint_mismatch() {
     drop(tmp_dir);
 }
 
+#[test]
+fn thumbprints() {
+    let (cert, _) = make_test_cert_1024();
+    let thumbprint = cert.thumbprint();
+    assert_eq!(thumbprint.algorithm(), ThumbprintAlgorithm::Sha1);
+    assert_eq!(thumbprint.value().len(), Thumbprint::THUMBPRINT_SIZE);
+    let thumbprint = cert.thumbprint_with_algorithm(ThumbprintAlgorithm::Sha256);
+    assert_eq!(thumbprint.algorithm(), ThumbprintAlgorithm::Sha256);
+    assert_eq!(thumbprint.value().len(), Thumbprint::THUMBPRINT_SHA256_SIZE);
+    assert_eq!(
+        ThumbprintAlgorithm::from_size(Thumbprint::THUMBPRINT_SHA256_SIZE),
+        Some(ThumbprintAlgorithm::Sha256)
+    );
+    assert!(ThumbprintAlgorithm::from_size(16).is_none());
+}
+
+#[test]
+fn test_and_trust_cert_named_by_sha256_thumbprint() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+
+    // Simulate user/admin copying cert to the trusted folder under its SHA-256 thumbprint
+    let (cert, _) = make_test_cert_1024();
+    let mut cert_trusted_path = cert_store.trusted_certs_dir();
+    cert_trusted_path.push(CertificateStore::cert_file_name_with_algorithm(
+        &cert,
+        ThumbprintAlgorithm::Sha256,
+    ));
+    {
+        let mut file = File::create(cert_trusted_path).unwrap();
+        assert!(file.write(&cert.to_der().unwrap()).is_ok());
+    }
+
+    let result = cert_store.validate_or_reject_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert!(result.is_good());
+
+    drop(tmp_dir);
+}
+
+/// Makes a new cert for the key of a cert, as happens when a cert is renewed
+fn renew_test_cert(pkey: &PrivateKey) -> X509 {
+    let args = X509Data {
+        key_size: 1024,
+        common_name: "x".to_string(),
+        organization: "x.org".to_string(),
+        organizational_unit: "x.org ops".to_string(),
+        country: "EN".to_string(),
+        state: "London".to_string(),
+        alt_host_names: vec![
+            APPLICATION_URI.to_string(),
+            APPLICATION_HOSTNAME.to_string(),
+        ],
+        certificate_duration_days: 60,
+    };
+    X509::from_pkey(pkey, &args).unwrap()
+}
+
+#[test]
+fn test_and_trust_renewed_cert_by_subject_key_identifier() {
+    let (tmp_dir, mut cert_store) = make_certificate_store();
+
+    // Trust a cert, then renew it with the same key
+    let (cert, pkey) = make_test_cert_1024();
+    let mut cert_trusted_path = cert_store.trusted_certs_dir();
+    cert_trusted_path.push(CertificateStore::cert_file_name(&cert));
+    {
+        let mut file = File::create(cert_trusted_path).unwrap();
+        assert!(file.write(&cert.to_der().unwrap()).is_ok());
+    }
+    let renewed_cert = renew_test_cert(&pkey);
+    assert_ne!(renewed_cert.thumbprint(), cert.thumbprint());
+    assert!(renewed_cert.subject_key_identifier().is_some());
+    assert_eq!(
+        renewed_cert.subject_key_identifier(),
+        cert.subject_key_identifier()
+    );
+
+    // A cert with another key is never trusted
+    let (other_cert, _) = make_test_cert_1024();
+    assert!(!other_cert.has_same_public_key(&cert));
+    cert_store.set_match_subject_key_identifier(true);
+    let result = cert_store.validate_application_instance_cert(
+        &other_cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+
+    // The renewed cert is only trusted when certs are matched by subject key identifier
+    cert_store.set_match_subject_key_identifier(false);
+    let mut rejected_path = cert_store.rejected_certs_dir();
+    rejected_path.push(CertificateStore::cert_file_name(&renewed_cert));
+    let result = cert_store.validate_application_instance_cert(
+        &renewed_cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+    std::fs::remove_file(&rejected_path).unwrap();
+
+    cert_store.set_match_subject_key_identifier(true);
+    let result = cert_store.validate_application_instance_cert(
+        &renewed_cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert!(result.is_good());
+    let mut renewed_trusted_path = cert_store.trusted_certs_dir();
+    renewed_trusted_path.push(CertificateStore::cert_file_name(&renewed_cert));
+    assert!(renewed_trusted_path.exists());
+
+    drop(tmp_dir);
+}
+
+/// Makes a cert that is issued by the issuer, or a self-signed one without an issuer. A CA cert
+/// may issue certs, the others are application instance certs.
+fn issue_test_cert(
+    common_name: &str,
+    ca: bool,
+    issuer: Option<(&X509, &PrivateKey)>,
+) -> (X509, PrivateKey) {
+    use openssl::{
+        asn1::Asn1Time,
+        bn::{BigNum, MsbOption},
+        hash::MessageDigest,
+        x509::{self, extension::*},
+    };
+
+    let pkey = PrivateKey::new(1024);
+    let name = {
+        let mut name = x509::X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        name.build()
+    };
+    let issuer_cert = issuer
+        .map(|(issuer_cert, _)| x509::X509::from_der(&issuer_cert.to_der().unwrap()).unwrap());
+
+    let mut builder = x509::X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_issuer_name(
+            issuer_cert
+                .as_ref()
+                .map(|issuer_cert| issuer_cert.subject_name())
+                .unwrap_or(&name),
+        )
+        .unwrap();
+    builder.set_pubkey(&pkey.value).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(60).unwrap())
+        .unwrap();
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(128, MsbOption::MAYBE_ZERO, false).unwrap();
+    builder
+        .set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    if ca {
+        let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+        builder.append_extension(basic_constraints).unwrap();
+        let key_usage = KeyUsage::new().key_cert_sign().crl_sign().build().unwrap();
+        builder.append_extension(key_usage).unwrap();
+    } else {
+        let subject_alternative_name = SubjectAlternativeName::new()
+            .uri(APPLICATION_URI)
+            .dns(APPLICATION_HOSTNAME)
+            .build(&builder.x509v3_context(issuer_cert.as_deref(), None))
+            .unwrap();
+        builder.append_extension(subject_alternative_name).unwrap();
+    }
+    let signing_key = issuer.map(|(_, issuer_pkey)| issuer_pkey).unwrap_or(&pkey);
+    builder
+        .sign(&signing_key.value, MessageDigest::sha256())
+        .unwrap();
+    (X509::from(builder.build()), pkey)
+}
+
+/// Writes a cert into the directory under its file name
+fn write_test_cert(dir: &std::path::Path, cert: &X509) {
+    let mut file = File::create(dir.join(CertificateStore::cert_file_name(cert))).unwrap();
+    assert!(file.write(&cert.to_der().unwrap()).is_ok());
+}
+
+#[test]
+fn certificate_chain_to_and_from_byte_string() {
+    let (root_cert, root_pkey) = issue_test_cert("root", true, None);
+    let (cert, _) = issue_test_cert("x", false, Some((&root_cert, &root_pkey)));
+    assert!(root_cert.is_self_signed());
+    assert!(!cert.is_self_signed());
+    assert!(cert.is_issued_by(&root_cert));
+    assert!(!root_cert.is_issued_by(&cert));
+
+    let chain = X509::chain_as_byte_string(&[cert.clone(), root_cert.clone()]);
+    let certs = X509::chain_from_byte_string(&chain).unwrap();
+    assert_eq!(certs.len(), 2);
+    assert_eq!(certs[0].thumbprint(), cert.thumbprint());
+    assert_eq!(certs[1].thumbprint(), root_cert.thumbprint());
+    // The leaf is the cert of the chain
+    assert_eq!(
+        X509::from_byte_string(&chain).unwrap().thumbprint(),
+        cert.thumbprint()
+    );
+    assert_eq!(
+        X509::chain_from_byte_string(&cert.as_byte_string())
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // A chain that is cut short or followed by junk is invalid
+    let mut der = chain.as_ref().to_vec();
+    der.truncate(der.len() - 1);
+    assert_eq!(
+        X509::chain_from_byte_string(&ByteString::from(der)).unwrap_err(),
+        StatusCode::BadCertificateInvalid
+    );
+    let mut der = chain.as_ref().to_vec();
+    der.push(0);
+    assert_eq!(
+        X509::chain_from_byte_string(&ByteString::from(der)).unwrap_err(),
+        StatusCode::BadCertificateInvalid
+    );
+
+    // An empty chain is invalid
+    assert_eq!(
+        X509::chain_from_byte_string(&ByteString::from(vec![])).unwrap_err(),
+        StatusCode::BadCertificateInvalid
+    );
+    assert_eq!(
+        X509::from_byte_string(&ByteString::from(vec![])).unwrap_err(),
+        StatusCode::BadCertificateInvalid
+    );
+}
+
+#[test]
+fn test_and_trust_cert_issued_by_trusted_ca() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+
+    let (ca_cert, ca_pkey) = issue_test_cert("ca", true, None);
+    let (cert, _) = issue_test_cert("x", false, Some((&ca_cert, &ca_pkey)));
+
+    // The issuer is not trusted
+    let result = cert_store.validate_or_reject_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        Some(APPLICATION_HOSTNAME),
+        Some(APPLICATION_URI),
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+    let mut rejected_path = cert_store.rejected_certs_dir();
+    rejected_path.push(CertificateStore::cert_file_name(&cert));
+    std::fs::remove_file(&rejected_path).unwrap();
+
+    // Once the CA is trusted, so are the certs it issues, without storing them
+    write_test_cert(&cert_store.trusted_certs_dir(), &ca_cert);
+    let result = cert_store.validate_or_reject_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        Some(APPLICATION_HOSTNAME),
+        Some(APPLICATION_URI),
+    );
+    assert!(result.is_good());
+    let mut trusted_path = cert_store.trusted_certs_dir();
+    trusted_path.push(CertificateStore::cert_file_name(&cert));
+    assert!(!trusted_path.exists());
+
+    // A cert with the name of the CA that another key signed is not trusted
+    let (other_ca_cert, other_ca_pkey) = issue_test_cert("ca", true, None);
+    let (other_cert, _) = issue_test_cert("x", false, Some((&other_ca_cert, &other_ca_pkey)));
+    let result = cert_store.validate_application_instance_cert(
+        &other_cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+
+    // The checks of the cert itself still apply
+    let result = cert_store.validate_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        Some("otherhost"),
+        None,
+    );
+    assert!(result.is_bad());
+
+    drop(tmp_dir);
+}
+
+#[test]
+fn test_and_trust_cert_issued_by_intermediate_ca() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+
+    let (root_cert, root_pkey) = issue_test_cert("root", true, None);
+    let (intermediate_cert, intermediate_pkey) =
+        issue_test_cert("intermediate", true, Some((&root_cert, &root_pkey)));
+    let (cert, _) = issue_test_cert("x", false, Some((&intermediate_cert, &intermediate_pkey)));
+    write_test_cert(&cert_store.trusted_certs_dir(), &root_cert);
+
+    // Without the intermediate CA the chain does not reach the trusted root
+    let result = cert_store.validate_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+    std::fs::remove_file(
+        cert_store
+            .rejected_certs_dir()
+            .join(CertificateStore::cert_file_name(&cert)),
+    )
+    .unwrap();
+
+    // The intermediate CA may be sent along with the cert
+    let result = cert_store.validate_application_instance_cert_chain(
+        &[cert.clone(), intermediate_cert.clone()],
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert!(result.is_good());
+
+    // Or it can be in the issuer folder
+    write_test_cert(&cert_store.issuer_certs_dir(), &intermediate_cert);
+    let result = cert_store.validate_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert!(result.is_good());
+
+    // A cert in the issuer folder is not trusted itself, nor are the certs it issues when the
+    // root is not trusted
+    std::fs::remove_file(
+        cert_store
+            .trusted_certs_dir()
+            .join(CertificateStore::cert_file_name(&root_cert)),
+    )
+    .unwrap();
+    let result = cert_store.validate_application_instance_cert(
+        &cert,
+        SecurityPolicy::Basic128Rsa15,
+        None,
+        None,
+    );
+    assert_eq!(result, StatusCode::BadCertificateUntrusted);
+
+    drop(tmp_dir);
+}
+
+#[test]
+fn issuer_certs_of_own_cert() {
+    let (tmp_dir, cert_store) = make_certificate_store();
+
+    let (root_cert, root_pkey) = issue_test_cert("root", true, None);
+    let (intermediate_cert, intermediate_pkey) =
+        issue_test_cert("intermediate", true, Some((&root_cert, &root_pkey)));
+    let (cert, _) = issue_test_cert("x", false, Some((&intermediate_cert, &intermediate_pkey)));
+
+    // The issuers are found in the issuer and trusted folders, up to the root
+    assert!(cert_store.issuer_certs(&cert).is_empty());
+    write_test_cert(&cert_store.issuer_certs_dir(), &intermediate_cert);
+    assert_eq!(cert_store.issuer_certs(&cert).len(), 1);
+    write_test_cert(&cert_store.trusted_certs_dir(), &root_cert);
+    let issuer_certs = cert_store.issuer_certs(&cert);
+    assert_eq!(issuer_certs.len(), 2);
+    assert_eq!(issuer_certs[0].thumbprint(), intermediate_cert.thumbprint());
+    assert_eq!(issuer_certs[1].thumbprint(), root_cert.thumbprint());
+
+    // A self-signed cert has no issuers
+    let (cert, _) = make_test_cert_1024();
+    assert!(cert_store.issuer_certs(&cert).is_empty());
+
+    drop(tmp_dir);
+}
+
 fn test_asymmetric_encrypt_and_decrypt(
     cert: &X509,
     key: &PrivateKey,
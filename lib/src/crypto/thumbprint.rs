@@ -5,33 +5,66 @@
 //! Functionality for holding a message digest.
 use crate::types::ByteString;
 
-/// The thumbprint holds a 20 byte representation of a certificate that can be used as a hash,
-/// handshake comparison, a filename hint or similar purpose where a shortened representation
-/// of a cert is required. Thumbprint size is dictated by the OPC UA spec
+/// The hash algorithm that a thumbprint is the digest of
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ThumbprintAlgorithm {
+    /// SHA-1, which the security policies of the OPC UA spec use in their security headers
+    Sha1,
+    /// SHA-256
+    Sha256,
+}
+
+impl ThumbprintAlgorithm {
+    /// The length in bytes of a thumbprint that is made with the algorithm
+    pub fn size(&self) -> usize {
+        match self {
+            ThumbprintAlgorithm::Sha1 => Thumbprint::THUMBPRINT_SIZE,
+            ThumbprintAlgorithm::Sha256 => Thumbprint::THUMBPRINT_SHA256_SIZE,
+        }
+    }
+
+    /// Returns the algorithm whose thumbprints have the length, if there is one
+    pub fn from_size(size: usize) -> Option<ThumbprintAlgorithm> {
+        match size {
+            Thumbprint::THUMBPRINT_SIZE => Some(ThumbprintAlgorithm::Sha1),
+            Thumbprint::THUMBPRINT_SHA256_SIZE => Some(ThumbprintAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+}
+
+/// The thumbprint holds a 20 byte SHA-1 or 32 byte SHA-256 representation of a certificate that
+/// can be used as a hash, handshake comparison, a filename hint or similar purpose where a
+/// shortened representation of a cert is required. Thumbprint size is dictated by the OPC UA spec
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Thumbprint {
-    /// Thumbprint is relatively small and fixed size, so use array to hold value instead of a vec
-    /// just to save heap
-    value: [u8; Thumbprint::THUMBPRINT_SIZE],
+    value: Vec<u8>,
 }
 
 impl Into<ByteString> for Thumbprint {
     fn into(self) -> ByteString {
-        ByteString::from(&self.value)
+        ByteString::from(self.value)
     }
 }
 
 impl Thumbprint {
     pub const THUMBPRINT_SIZE: usize = 20;
+    pub const THUMBPRINT_SHA256_SIZE: usize = 32;
 
     /// Constructs a thumbprint from a message digest which is expected to be the proper length
+    /// for SHA-1 or SHA-256
     pub fn new(digest: &[u8]) -> Thumbprint {
-        if digest.len() != Thumbprint::THUMBPRINT_SIZE {
+        if ThumbprintAlgorithm::from_size(digest.len()).is_none() {
             panic!("Thumbprint is the wrong length, {}", digest.len());
         }
-        let mut value = [0u8; Thumbprint::THUMBPRINT_SIZE];
-        value.clone_from_slice(digest);
-        Thumbprint { value }
+        Thumbprint {
+            value: digest.to_vec(),
+        }
+    }
+
+    /// Returns the hash algorithm of the thumbprint
+    pub fn algorithm(&self) -> ThumbprintAlgorithm {
+        ThumbprintAlgorithm::from_size(self.value.len()).unwrap()
     }
 
     pub fn as_byte_string(&self) -> ByteString {
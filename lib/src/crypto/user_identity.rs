@@ -45,10 +45,14 @@ pub fn make_user_name_identity_token(
         let security_policy =
             SecurityPolicy::from_str(user_token_policy.security_policy_uri.as_ref()).unwrap();
         if security_policy == SecurityPolicy::Unknown {
-            SecurityPolicy::None
-        } else {
-            security_policy
+            // Sending the password in plain text instead would leak it to anyone listening
+            error!(
+                "User token policy has an unsupported security policy {}",
+                user_token_policy.security_policy_uri
+            );
+            return Err(StatusCode::BadSecurityPolicyRejected);
         }
+        security_policy
     };
 
     // Now it should be a matter of using the policy (or lack thereof) to encrypt the password
@@ -66,11 +70,20 @@ pub fn make_user_name_identity_token(
             panic!("Don't know how to make the token for this server");
         }
         security_policy => {
-            // Create a password which is encrypted using the secure channel info and the user token policy for the endpoint
+            // The password is encrypted with the server's certificate and nonce, which it has
+            // even when the secure channel has no security
+            let cert = cert.as_ref().ok_or_else(|| {
+                error!("Cannot encrypt the password because the server has no certificate");
+                StatusCode::BadCertificateInvalid
+            })?;
+            if nonce.is_empty() {
+                error!("Cannot encrypt the password because the server nonce is empty");
+                return Err(StatusCode::BadNonceInvalid);
+            }
             let password = legacy_password_encrypt(
                 pass,
                 nonce,
-                cert.as_ref().unwrap(),
+                cert,
                 security_policy.asymmetric_encryption_padding(),
             )?;
             let encryption_algorithm =
@@ -162,19 +175,22 @@ pub fn legacy_password_decrypt(
         Err(StatusCode::BadDecodingError)
     } else {
         // Decrypt the message
-        let src = secret.value.as_ref().unwrap();
+        let src = secret.as_ref();
         let mut dst = vec![0u8; src.len()];
         let actual_size = server_key
             .private_decrypt(src, &mut dst, padding)
             .map_err(|_| StatusCode::BadEncodingError)?;
 
+        let nonce_len = server_nonce.len();
+        if actual_size < 4 + nonce_len {
+            return Err(StatusCode::BadDecodingError);
+        }
         let mut dst = Cursor::new(dst);
         let plaintext_size = read_u32(&mut dst)? as usize;
         if plaintext_size + 4 != actual_size {
             Err(StatusCode::BadDecodingError)
         } else {
             let dst = dst.into_inner();
-            let nonce_len = server_nonce.len();
             let nonce_begin = actual_size - nonce_len;
             let nonce = &dst[nonce_begin..(nonce_begin + nonce_len)];
             if nonce != server_nonce {
@@ -27,7 +27,7 @@ use crate::types::{service_types::ApplicationDescription, status_code::StatusCod
 use super::{
     hostname,
     pkey::{PrivateKey, PublicKey},
-    thumbprint::Thumbprint,
+    thumbprint::{Thumbprint, ThumbprintAlgorithm},
 };
 
 const DEFAULT_KEYSIZE: u32 = 2048;
@@ -294,6 +294,12 @@ impl X509 {
             .unwrap();
         builder.set_pubkey(&pkey.value).unwrap();
 
+        // The subject key identifier stays the same when the cert is renewed with the same key
+        let subject_key_identifier = SubjectKeyIdentifier::new()
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(subject_key_identifier).unwrap();
+
         // Random serial number
         {
             use openssl::bn::BigNum;
@@ -342,22 +348,83 @@ impl X509 {
         Ok(X509::from(builder.build()))
     }
 
+    /// Makes a certificate from the DER form of the certificate. The bytes may hold a
+    /// certificate chain in which case the first certificate, i.e. the leaf, is returned.
     pub fn from_byte_string(data: &ByteString) -> Result<X509, StatusCode> {
+        Self::chain_from_byte_string(data)?
+            .into_iter()
+            .next()
+            .ok_or(StatusCode::BadCertificateInvalid)
+    }
+
+    /// Makes the certificates of a chain, which is the DER form of each certificate one after
+    /// another, starting with the leaf certificate and followed by the certificates of the CAs
+    /// that issued it. A chain holds at least one certificate.
+    pub fn chain_from_byte_string(data: &ByteString) -> Result<Vec<X509>, StatusCode> {
         if data.is_null() {
             error!("Cannot make certificate from null bytestring");
-            Err(StatusCode::BadCertificateInvalid)
-        } else if let Ok(cert) = x509::X509::from_der(data.value.as_ref().unwrap()) {
-            Ok(X509::from(cert))
+            return Err(StatusCode::BadCertificateInvalid);
+        } else if data.is_empty() {
+            error!("Cannot make certificate from empty bytestring");
+            return Err(StatusCode::BadCertificateInvalid);
+        }
+        let mut der = data.as_ref();
+        let mut chain = Vec::new();
+        while !der.is_empty() {
+            let cert = Self::der_length(der)
+                .filter(|length| *length <= der.len())
+                .and_then(|length| {
+                    let cert = x509::X509::from_der(&der[..length]).ok()?;
+                    der = &der[length..];
+                    Some(cert)
+                });
+            if let Some(cert) = cert {
+                chain.push(X509::from(cert));
+            } else {
+                error!("Cannot make certificate, does bytestring contain .der?");
+                return Err(StatusCode::BadCertificateInvalid);
+            }
+        }
+        Ok(chain)
+    }
+
+    /// Returns the length of the DER encoded value that starts the bytes, including its tag and
+    /// length, if the bytes start with a sequence as a certificate does.
+    fn der_length(der: &[u8]) -> Option<usize> {
+        const SEQUENCE: u8 = 0x30;
+        if der.len() < 2 || der[0] != SEQUENCE {
+            return None;
+        }
+        if der[1] & 0x80 == 0 {
+            // Short form, the byte is the length
+            Some(2 + der[1] as usize)
         } else {
-            error!("Cannot make certificate, does bytestring contain .der?");
-            Err(StatusCode::BadCertificateInvalid)
+            // Long form, the byte says how many bytes the length takes
+            let length_size = (der[1] & 0x7f) as usize;
+            if length_size == 0 || length_size > 4 || der.len() < 2 + length_size {
+                return None;
+            }
+            let length = der[2..2 + length_size]
+                .iter()
+                .fold(0usize, |length, b| (length << 8) | *b as usize);
+            Some(2 + length_size + length)
         }
     }
 
     /// Returns a ByteString representation of the cert which is DER encoded form of X509v3
     pub fn as_byte_string(&self) -> ByteString {
         let der = self.value.to_der().unwrap();
-        ByteString::from(&der)
+        ByteString::from(der)
+    }
+
+    /// Returns a ByteString representation of a certificate chain, which is the DER encoded form
+    /// of each certificate one after another
+    pub fn chain_as_byte_string(chain: &[X509]) -> ByteString {
+        let der = chain
+            .iter()
+            .flat_map(|cert| cert.value.to_der().unwrap())
+            .collect::<Vec<u8>>();
+        ByteString::from(der)
     }
 
     pub fn public_key(&self) -> Result<PublicKey, StatusCode> {
@@ -557,12 +624,116 @@ impl X509 {
     ///
     /// The thumbprint might be used by the server / client for look-up purposes.
     pub fn thumbprint(&self) -> Thumbprint {
+        self.thumbprint_with_algorithm(ThumbprintAlgorithm::Sha1)
+    }
+
+    /// The thumbprint that is the digest of the DER form of the certificate with the algorithm
+    pub fn thumbprint_with_algorithm(&self, algorithm: ThumbprintAlgorithm) -> Thumbprint {
         use openssl::hash::{hash, MessageDigest};
         let der = self.value.to_der().unwrap();
-        let digest = hash(MessageDigest::sha1(), &der).unwrap();
+        let message_digest = match algorithm {
+            ThumbprintAlgorithm::Sha1 => MessageDigest::sha1(),
+            ThumbprintAlgorithm::Sha256 => MessageDigest::sha256(),
+        };
+        let digest = hash(message_digest, &der).unwrap();
         Thumbprint::new(&digest)
     }
 
+    /// Returns the value of the subject key identifier extension, if the certificate has one.
+    /// Certificates that are renewed with the same key normally keep their identifier.
+    pub fn subject_key_identifier(&self) -> Option<Vec<u8>> {
+        use foreign_types::ForeignType;
+        use openssl_sys::*;
+        unsafe {
+            let subject_key_identifier = X509_get_ext_d2i(
+                self.value.as_ptr(),
+                NID_subject_key_identifier,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            ) as *mut ASN1_STRING;
+            if subject_key_identifier.is_null() {
+                None
+            } else {
+                let data = ASN1_STRING_get0_data(subject_key_identifier);
+                let len = ASN1_STRING_length(subject_key_identifier) as usize;
+                let value = std::slice::from_raw_parts(data, len).to_vec();
+                ASN1_STRING_free(subject_key_identifier);
+                Some(value)
+            }
+        }
+    }
+
+    /// Tests if the certificate holds the same public key as the other
+    pub fn has_same_public_key(&self, other: &X509) -> bool {
+        match (self.value.public_key(), other.value.public_key()) {
+            (Ok(public_key), Ok(other_public_key)) => public_key.public_eq(&other_public_key),
+            _ => false,
+        }
+    }
+
+    /// Tests if the certificate was signed by the issuer, i.e. the issuer name of the
+    /// certificate is the subject name of the issuer and the signature verifies with its key
+    pub fn is_issued_by(&self, issuer: &X509) -> bool {
+        if issuer.value.issued(&self.value) != x509::X509VerifyResult::OK {
+            false
+        } else if let Ok(issuer_public_key) = issuer.value.public_key() {
+            self.value.verify(&issuer_public_key).unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// Tests if the certificate is self-signed, i.e. it is its own issuer
+    pub fn is_self_signed(&self) -> bool {
+        self.is_issued_by(self)
+    }
+
+    /// Verifies that the certificate was issued by one of the trusted certificates, either
+    /// directly or through the intermediate certificates. The intermediate certificates are
+    /// not trusted themselves, but a trusted certificate need not be a root CA. If the time is
+    /// checked, every certificate of the chain must be valid now.
+    pub fn verify_issuer(
+        &self,
+        trusted: &[X509],
+        intermediates: &[X509],
+        check_time: bool,
+    ) -> Result<(), String> {
+        use openssl::{
+            stack::Stack,
+            x509::{
+                store::X509StoreBuilder, verify::X509VerifyFlags, X509StoreContext,
+                X509VerifyResult,
+            },
+        };
+        let result = (|| {
+            let mut store = X509StoreBuilder::new()?;
+            for cert in trusted {
+                store.add_cert(cert.value.clone())?;
+            }
+            let mut flags = X509VerifyFlags::PARTIAL_CHAIN;
+            if !check_time {
+                flags |= X509VerifyFlags::NO_CHECK_TIME;
+            }
+            store.set_flags(flags)?;
+            let store = store.build();
+
+            let mut chain = Stack::new()?;
+            for cert in intermediates {
+                chain.push(cert.value.clone())?;
+            }
+            let mut context = X509StoreContext::new()?;
+            context.init(&store, &self.value, &chain, |context| {
+                context.verify_cert()?;
+                Ok(context.error())
+            })
+        })();
+        match result {
+            Ok(result) if result == X509VerifyResult::OK => Ok(()),
+            Ok(result) => Err(result.error_string().to_string()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
     /// Turn the Asn1 values into useful portable types
     pub fn not_before(&self) -> Result<DateTime<Utc>, X509Error> {
         let date = self.value.not_before().to_string();
@@ -4,6 +4,7 @@ extern crate lazy_static;
 extern crate log;
 #[cfg(test)]
 extern crate tempdir;
+#[cfg(feature = "server-core")]
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
@@ -12,6 +13,7 @@ extern crate serde_derive;
 extern crate actix_web;
 #[cfg(test)]
 extern crate serde_json;
+#[cfg(feature = "server-core")]
 #[macro_use]
 extern crate derivative;
 
@@ -65,20 +67,13 @@ macro_rules! trace_write_lock {
     }
 }
 
-#[cfg(feature = "client")]
+#[cfg(feature = "client-core")]
 pub mod client;
 #[cfg(feature = "console-logging")]
 pub mod console_logging;
 pub mod core;
 pub mod crypto;
-#[cfg(feature = "server")]
+#[cfg(feature = "server-core")]
 pub mod server;
-pub mod types;
+pub use opcua_types as types;
 
-mod prelude {
-    #[cfg(feature = "client")]
-    pub use crate::client::prelude::*;
-    pub use crate::core::prelude::*;
-    #[cfg(feature = "server")]
-    pub use crate::server::prelude::*;
-}
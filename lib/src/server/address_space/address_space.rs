@@ -3,33 +3,64 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 //! Implementation of `AddressSpace`.
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 use chrono::Utc;
 
 use crate::sync::*;
+#[cfg(feature = "events")]
+use crate::types::service_types::ModelChangeStructureVerbMask;
 use crate::types::{
     node_ids::VariableId::*,
-    service_types::{BrowseDirection, CallMethodRequest, CallMethodResult, NodeClass},
+    service_types::{
+        BrowseDirection, CallMethodRequest, CallMethodResult, ModelChangeStructureDataType,
+        NodeClass,
+    },
     status_code::StatusCode,
     *,
 };
 
+#[cfg(all(feature = "generated-address-space", feature = "diagnostics"))]
+use crate::server::diagnostics::ServerDiagnostics;
+#[cfg(feature = "history")]
+use crate::server::historical::{
+    data_collection::{self, HistoryCollector},
+    HistoricalDataConfiguration, HistoryServerCapabilities,
+};
+#[cfg(feature = "events")]
+use crate::server::{
+    address_space::method_calls::AlarmMethodCall,
+    events::{
+        alarm::{Alarm, ShelvedState},
+        event::Event,
+        model_change_event::GeneralModelChangeEventType,
+    },
+};
+#[cfg(feature = "generated-address-space")]
+use crate::server::{address_space::AttrFnGetter, config::Redundancy, constants};
 use crate::server::{
     address_space::{
+        data_access, instantiate,
+        localization::Localization,
+        method_calls,
+        modelling_rule::ModellingRule,
         node::{HasNodeId, NodeType},
         object::{Object, ObjectBuilder},
         references::{Reference, ReferenceDirection, References},
-        variable::Variable,
-        AttrFnGetter,
+        state_machine::StateMachine,
+        variable::{Variable, VariableBuilder},
+        view::ViewBuilder,
     },
-    callbacks, constants,
-    diagnostics::ServerDiagnostics,
-    historical::HistoryServerCapabilities,
-    session::SessionManager,
+    callbacks,
+    profiles::ServerProfiles,
+    session::{Session, SessionManager},
     state::ServerState,
 };
+#[cfg(feature = "generated-address-space")]
+use crate::types::service_types::{
+    EndpointUrlListDataType, NetworkGroupDataType, RedundancySupport,
+};
 
 /// Finds a node in the address space and coerces it into a reference of the expected node type.
 macro_rules! find_node {
@@ -99,6 +130,7 @@ macro_rules! is_method {
 }
 
 /// Gets a field from the live diagnostics table.
+#[cfg(all(feature = "generated-address-space", feature = "diagnostics"))]
 macro_rules! server_diagnostics_summary {
     ($address_space: expr, $variable_id: expr, $field: ident) => {
         let server_diagnostics = $address_space.server_diagnostics.as_ref().unwrap().clone();
@@ -127,12 +159,6 @@ pub(crate) type MethodCallback = Box<dyn callbacks::Method + Send + Sync>;
 
 const OPCUA_INTERNAL_NAMESPACE_IDX: u16 = 1;
 
-#[derive(PartialEq, Eq, Clone, Debug, Hash)]
-struct MethodKey {
-    object_id: NodeId,
-    method_id: NodeId,
-}
-
 /// The `AddressSpace` describes all of the nodes managed by the server and the references between
 /// them. Usually it will be populated with the default OPC UA node set plus any that have been
 /// added by the server.
@@ -166,6 +192,7 @@ pub struct AddressSpace {
     /// This is the last time that nodes or references to nodes were added or removed from the address space.
     last_modified: DateTimeUtc,
     /// Access to server diagnostics
+    #[cfg(all(feature = "generated-address-space", feature = "diagnostics"))]
     server_diagnostics: Option<Arc<RwLock<ServerDiagnostics>>>,
     /// The namespace to create sequential node ids
     default_namespace: u16,
@@ -173,8 +200,27 @@ pub struct AddressSpace {
     audit_namespace: u16,
     /// The namespace to generate sequential internal node ids
     internal_namespace: u16,
-    /// The list of all registered namespaces.
-    namespaces: Vec<String>,
+    /// The table of all registered namespaces, which is the value of the `NamespaceArray`
+    namespaces: NamespaceTable,
+    /// Translations of the human readable text of nodes and events
+    localization: Localization,
+    /// Raise model change events when nodes or references are added or removed
+    #[cfg(feature = "events")]
+    model_change_events: bool,
+    /// The profiles and facets reported in the `ServerProfileArray`
+    server_profiles: ServerProfiles,
+    /// Hooks called before a client writes the value of a variable
+    before_write_hooks: Vec<Box<callbacks::BeforeWriteHook>>,
+    /// Hooks called after the value of a variable has changed
+    value_changed_hooks: Vec<Box<callbacks::ValueChangedHook>>,
+    /// Alarms that are evaluated when the value of their input changes
+    #[cfg(feature = "events")]
+    alarms: Vec<Alarm>,
+    /// State machines that take transitions, including programs whose methods clients call
+    state_machines: Vec<StateMachine>,
+    /// Collects the values of historizing variables for the historical data provider
+    #[cfg(feature = "history")]
+    history_collector: HistoryCollector,
 }
 
 impl Default for AddressSpace {
@@ -183,6 +229,7 @@ impl Default for AddressSpace {
             node_map: HashMap::new(),
             references: References::default(),
             last_modified: Utc::now(),
+            #[cfg(all(feature = "generated-address-space", feature = "diagnostics"))]
             server_diagnostics: None,
             default_namespace: OPCUA_INTERNAL_NAMESPACE_IDX,
             audit_namespace: OPCUA_INTERNAL_NAMESPACE_IDX,
@@ -190,7 +237,18 @@ impl Default for AddressSpace {
             // By default, there will be two standard namespaces. The first is the default
             // OPC UA namespace for its standard nodes. The second is the internal namespace used
             // by this implementation.
-            namespaces: vec!["http://opcfoundation.org/UA/".to_string()],
+            namespaces: NamespaceTable::new(),
+            localization: Localization::default(),
+            #[cfg(feature = "events")]
+            model_change_events: false,
+            server_profiles: ServerProfiles::default(),
+            before_write_hooks: Vec::new(),
+            value_changed_hooks: Vec::new(),
+            #[cfg(feature = "events")]
+            alarms: Vec::new(),
+            state_machines: Vec::new(),
+            #[cfg(feature = "history")]
+            history_collector: HistoryCollector::default(),
         }
     }
 }
@@ -205,50 +263,86 @@ impl AddressSpace {
         address_space
     }
 
+    /// Returns the translations of the human readable text of nodes and events.
+    pub fn localization(&self) -> &Localization {
+        &self.localization
+    }
+
+    /// Returns the translations of the human readable text of nodes and events for modification.
+    pub fn localization_mut(&mut self) -> &mut Localization {
+        &mut self.localization
+    }
+
     /// Returns the last modified date for the address space
     pub fn last_modified(&self) -> DateTimeUtc {
         self.last_modified
     }
 
+    /// Tests if model change events are raised when nodes or references are added or removed.
+    #[cfg(feature = "events")]
+    pub fn model_change_events(&self) -> bool {
+        self.model_change_events
+    }
+
+    /// Sets whether a `GeneralModelChangeEventType` event is raised each time nodes or references
+    /// are added or removed, and the `NodeVersion` property of the affected nodes is updated. The
+    /// server turns this on when it starts running so that building the address space beforehand
+    /// does not raise events. Changes to the nodes of events never raise events.
+    #[cfg(feature = "events")]
+    pub fn set_model_change_events(&mut self, model_change_events: bool) {
+        self.model_change_events = model_change_events;
+    }
+
     /// Registers a namespace described by a uri with address space. The return code is the index
     /// of the newly added namespace / index. The index is used with `NodeId`. Registering a
     /// namespace that is already registered will return the index to the previous instance.
     /// The last registered namespace becomes the default namespace unless you explcitly call
     /// `set_default_namespace()` after this.
     pub fn register_namespace(&mut self, namespace: &str) -> Result<u16, ()> {
-        use std::u16;
-        let now = DateTime::now();
-        if namespace.is_empty() || self.namespaces.len() == u16::MAX as usize {
-            Err(())
+        // Check if namespace already exists or not
+        if let Some(i) = self.namespace_index(namespace) {
+            // Existing namespace index
+            Ok(i)
         } else {
-            // Check if namespace already exists or not
-            if let Some(i) = self.namespace_index(namespace) {
-                // Existing namespace index
-                Ok(i)
-            } else {
-                // Add and register new namespace
-                self.namespaces.push(namespace.into());
-                self.set_namespaces(&now);
-                // New namespace index
-                let ns = (self.namespaces.len() - 1) as u16;
-                // Make this the new default namespace
-                self.default_namespace = ns;
-                Ok(ns)
-            }
+            // Add and register new namespace
+            let ns = self.namespaces.register(namespace).map_err(|_| ())?;
+            self.set_namespaces(&DateTime::now());
+            // Make this the new default namespace
+            self.default_namespace = ns;
+            Ok(ns)
         }
     }
 
+    /// Registers the namespaces of a node set that is being imported and returns the map that
+    /// translates the namespace indices used by the node set into the indices of this address
+    /// space. Namespaces that are already registered keep their index and the default namespace
+    /// is left alone.
+    pub fn import_namespaces(
+        &mut self,
+        namespaces: &NamespaceTable,
+    ) -> Result<NamespaceMap, StatusCode> {
+        let map = self.namespaces.import(namespaces)?;
+        self.set_namespaces(&DateTime::now());
+        Ok(map)
+    }
+
     /// Finds the namespace index of a given namespace
     pub fn namespace_index(&self, namespace: &str) -> Option<u16> {
-        self.namespaces
-            .iter()
-            .position(|ns| {
-                let ns: &str = ns.as_ref();
-                ns == namespace
-            })
-            .map(|i| i as u16)
+        self.namespaces.index_of(namespace)
     }
 
+    /// Finds the namespace of a given namespace index
+    pub fn namespace_uri(&self, namespace_index: u16) -> Option<&str> {
+        self.namespaces.uri(namespace_index)
+    }
+
+    /// Returns the table of registered namespaces. The position of a namespace in the table is its
+    /// index and never changes once registered.
+    pub fn namespaces(&self) -> &NamespaceTable {
+        &self.namespaces
+    }
+
+    #[cfg(feature = "generated-address-space")]
     fn set_servers(&mut self, server_state: Arc<RwLock<ServerState>>, now: &DateTime) {
         let server_state = trace_read_lock!(server_state);
         if let Some(ref mut v) = self.find_variable_mut(Server_ServerArray) {
@@ -261,6 +355,118 @@ impl AddressSpace {
         }
     }
 
+    /// Sets the `ServerRedundancy` object from the redundancy of the server. A server that is
+    /// part of a redundant set gets the non-transparent redundancy type, with the uris of the
+    /// servers of the set and their network paths if there are any.
+    #[cfg(feature = "generated-address-space")]
+    fn set_server_redundancy(
+        &mut self,
+        redundancy: &Redundancy,
+        application_uri: &str,
+        now: &DateTime,
+    ) {
+        let redundancy_support = RedundancySupport::from(redundancy.mode);
+        self.set_variable_value(
+            Server_ServerRedundancy_RedundancySupport,
+            redundancy_support as i32,
+            now,
+            now,
+        );
+        if redundancy_support == RedundancySupport::None {
+            return;
+        }
+
+        let server_redundancy_id: NodeId = ObjectId::Server_ServerRedundancy.into();
+        let redundancy_type = if redundancy.server_network_groups.is_empty() {
+            ObjectTypeId::NonTransparentRedundancyType
+        } else {
+            ObjectTypeId::NonTransparentNetworkRedundancyType
+        };
+        let _ = self.delete_reference(
+            &server_redundancy_id,
+            &ObjectTypeId::ServerRedundancyType.into(),
+            ReferenceTypeId::HasTypeDefinition,
+        );
+        self.set_node_type(&server_redundancy_id, redundancy_type);
+
+        let mut server_uris = redundancy.server_uris.clone();
+        if !application_uri.is_empty() && !server_uris.iter().any(|uri| uri == application_uri) {
+            server_uris.insert(0, application_uri.to_string());
+        }
+        let server_uris = server_uris
+            .iter()
+            .map(|uri| UAString::from(uri).into())
+            .collect::<Vec<Variant>>();
+        self.insert_redundancy_property(
+            Server_ServerRedundancy_ServerUriArray,
+            "ServerUriArray",
+            DataTypeId::String,
+            (VariantTypeId::String, server_uris),
+            now,
+        );
+
+        if !redundancy.server_network_groups.is_empty() {
+            let server_network_groups = redundancy
+                .server_network_groups
+                .iter()
+                .map(|group| {
+                    let network_group = NetworkGroupDataType {
+                        server_uri: UAString::from(&group.server_uri),
+                        network_paths: Some(
+                            group
+                                .network_paths
+                                .iter()
+                                .map(|endpoint_urls| EndpointUrlListDataType {
+                                    endpoint_url_list: Some(
+                                        endpoint_urls.iter().map(UAString::from).collect(),
+                                    ),
+                                })
+                                .collect(),
+                        ),
+                    };
+                    ExtensionObject::from_encodable(
+                        ObjectId::NetworkGroupDataType_Encoding_DefaultBinary,
+                        &network_group,
+                    )
+                    .into()
+                })
+                .collect::<Vec<Variant>>();
+            self.insert_redundancy_property(
+                Server_ServerRedundancy_ServerNetworkGroups,
+                "ServerNetworkGroups",
+                DataTypeId::NetworkGroupDataType,
+                (VariantTypeId::ExtensionObject, server_network_groups),
+                now,
+            );
+        }
+    }
+
+    /// Sets the array value of a property of the `ServerRedundancy` object, inserting the
+    /// property first since the properties of the redundancy subtypes are not in the generated
+    /// address space
+    #[cfg(feature = "generated-address-space")]
+    fn insert_redundancy_property<V>(
+        &mut self,
+        property_id: VariableId,
+        browse_name: &str,
+        data_type: DataTypeId,
+        value: V,
+        now: &DateTime,
+    ) where
+        V: Into<Variant>,
+    {
+        let property_id: NodeId = property_id.into();
+        if !self.node_exists(&property_id) {
+            VariableBuilder::new(&property_id, browse_name, browse_name)
+                .property_of(ObjectId::Server_ServerRedundancy)
+                .has_type_definition(VariableTypeId::PropertyType)
+                .data_type(data_type)
+                .value_rank(1)
+                .insert(self);
+        }
+        self.set_variable_value(property_id, value, now, now);
+    }
+
     fn set_namespaces(&mut self, now: &DateTime) {
         let value = Variant::from(&self.namespaces);
         if let Some(ref mut v) = self.find_variable_mut(Server_NamespaceArray) {
@@ -276,6 +482,8 @@ impl AddressSpace {
     /// Sets values for nodes representing the server.
     pub fn set_server_state(&mut self, server_state: Arc<RwLock<ServerState>>) {
         // Server state requires the generated address space, otherwise nothing
+        #[cfg(not(feature = "generated-address-space"))]
+        let _ = server_state;
         #[cfg(feature = "generated-address-space")]
         {
             let now = DateTime::now();
@@ -288,6 +496,9 @@ impl AddressSpace {
                 let server_state = trace_read_lock!(server_state);
                 let server_config = trace_read_lock!(server_state.config);
                 let _ = self.register_namespace(&server_config.application_uri);
+                if let Some(locale_id) = server_config.locale_ids.first() {
+                    self.localization.set_default_locale(locale_id.as_str());
+                }
             }
 
             // ServerCapabilities
@@ -332,7 +543,7 @@ impl AddressSpace {
                 );
                 self.set_variable_value(
                     Server_ServerCapabilities_MinSupportedSampleRate,
-                    constants::MIN_SAMPLING_INTERVAL as f64,
+                    server_state.min_sampling_interval_ms,
                     &now,
                     &now,
                 );
@@ -419,102 +630,18 @@ impl AddressSpace {
             }
 
             // Server_ServerCapabilities_ServerProfileArray
-            if let Some(ref mut v) =
-                self.find_variable_mut(Server_ServerCapabilities_ServerProfileArray)
             {
-                // Declares what the server implements. Subitems are implied by the profile. A subitem
-                // marked - is optional to the spec
-                let server_profiles = [
-                    // Base server behaviour
-                    //  SecurityPolicy - None
-                    //  User Token - User Name Password Server Facet
-                    //  Address Space Base
-                    //  AttributeRead
-                    //  -Attribute Write Index
-                    //  -Attribute Write Values
-                    //  Base Info Core Structure
-                    //  -Base Info OptionSet
-                    //  -Base Info Placeholder Modelling Rules
-                    //  -Base Info ValueAsText
-                    //  Discovery Find Servers Self
-                    //  Discovery Get Endpoints
-                    //  -Security - No Application Authentications
-                    //  -Security - Security Administration
-                    //   Session Base
-                    //  Session General Service Behaviour
-                    //  Session Minimum 1
-                    //  View Basic
-                    //  View Minimum Continuation Point 01
-                    //  View RegisterNodes
-                    //  View TranslateBrowsePath
-                    "http://opcfoundation.org/UA-Profile/Server/Behaviour",
-                    // Embedded UA server
-                    //   SecurityPolicy - Basic128Rsa15
-                    //     Security
-                    //       - Security Certificate Validation
-                    //       - Security Basic 128Rsa15
-                    //       - Security Encryption Required
-                    //       - Security Signing Required
-                    //   Standard DataChange Subscription Server Facet
-                    //     Base Information
-                    //       - Base Info GetMonitoredItems Method
-                    //     Monitored Item Services
-                    //       - Monitored Items Deadband Filter
-                    //       - Monitor Items 10
-                    //       - Monitor Items 100
-                    //       - Monitor MinQueueSize_02
-                    //       - Monitor Triggering
-                    //     Subscription Services
-                    //       - Subscription Minimum 02
-                    //       - Subscription Publish Min 05
-                    //     Method Services
-                    //       - Method call
-                    //   User Token - X509 Certificate Server Facet
-                    //       - Security User X509 - Server supports public / private key pair for user identity
-                    //   Micro Embedded Device Server Profile
-                    // Base Information
-                    //   - Base Info Type System - Exposes a Type system with DataTypes, ReferenceTypes, ObjectTypes and VariableTypes
-                    //     including all of OPC UA namespace (namespace 0) types that are used by the Server as defined in Part 6.
-                    //   - Base Info Placeholder Modelling Rules - The server supports defining cusom Object or Variables that include the use of OptionalPlaceholder
-                    //     or MandatoryPlaceholder modelling rules
-                    //   - Base Info Engineering Units - The server supports defining Variables that include the Engineering Units property
-                    // Security
-                    //  Security Default ApplicationInstanceCertificate - has a default ApplicationInstanceCertificate that is valid
-                    "http://opcfoundation.org/UA-Profile/Server/EmbeddedUA",
-                    // TODO server profile
-                    // Standard UA Server Profile
-                    //   Enhanced DataChange Subscription Server Facet
-                    //     Monitored Item Services
-                    //       - Monitor Items 500 - Support at least 500 MonitoredItems per Subscription
-                    //       - Monitor MinQueueSize_05 - Support at least 5 queue entries
-                    //     Subscription Services
-                    //       - Subscription Minimum 05 - Support at least 5 subscriptions per Session
-                    //       - Subscription Publish Min 10 - Support at least Publish service requests per session
-                    //   Embedded UA Server Profile
-                    // Base Information
-                    //   - Base Info Diagnostics
-                    // Discovery Services
-                    //   - Discovery Register (be able to call RegisterServer)
-                    //   - Discovery Register2 (be able to call RegisterServer2)
-                    // Session Services
-                    //   - Session Change User - Support use of ActivateSession to change the Session user
-                    //   - Session Cancel - Support the Cancel Service to cancel outstanding requests
-                    //   - Session Minimum 50 Parallel - Support minimum 50 parallel Sessions
-                    //
-                    // "http://opcfoundation.org/UA-Profile/Server/StandardUA",
-                ];
-                let _ = v.set_value_direct(
-                    Variant::from((VariantTypeId::String, &server_profiles[..])),
-                    StatusCode::Good,
-                    &now,
-                    &now,
-                );
+                let server_state = trace_read_lock!(server_state);
+                let server_config = trace_read_lock!(server_state.config);
+                self.server_profiles.set_config(&server_config);
             }
+            self.set_server_profile_array();
 
             // Server_ServerDiagnostics_ServerDiagnosticsSummary
             // Server_ServerDiagnostics_SamplingIntervalDiagnosticsArray
             // Server_ServerDiagnostics_SubscriptionDiagnosticsArray
             // Server_ServerDiagnostics_EnabledFlag
+            #[cfg(feature = "diagnostics")]
             {
                 let server_state = trace_read_lock!(server_state);
                 self.server_diagnostics = Some(server_state.diagnostics.clone());
@@ -586,7 +713,17 @@ impl AddressSpace {
             // Auditing - var
             // ServerDiagnostics
             // VendorServiceInfo
+
             // ServerRedundancy
+            {
+                let server_state = trace_read_lock!(server_state);
+                let server_config = trace_read_lock!(server_state.config);
+                self.set_server_redundancy(
+                    &server_config.redundancy,
+                    &server_config.application_uri,
+                    &now,
+                );
+            }
 
             // Server_ServerStatus_StartTime
             self.set_variable_value(Server_ServerStatus_StartTime, now, &now, &now);
@@ -635,11 +772,363 @@ impl AddressSpace {
                 MethodId::Server_GetMonitoredItems,
                 Box::new(method_impls::ServerGetMonitoredItemsMethod),
             );
+            let max_lifetime_in_hours = {
+                let server_state = trace_read_lock!(server_state);
+                let server_config = trace_read_lock!(server_state.config);
+                server_config.limits.max_durable_subscription_lifetime_hours
+            };
+            self.register_method_handler(
+                MethodId::Server_SetSubscriptionDurable,
+                Box::new(method_impls::ServerSetSubscriptionDurableMethod {
+                    max_lifetime_in_hours,
+                }),
+            );
+            #[cfg(feature = "events")]
+            self.register_method_handler(
+                MethodId::ConditionType_ConditionRefresh,
+                Box::new(method_impls::ConditionRefreshMethod),
+            );
+            #[cfg(feature = "events")]
+            self.register_method_handler(
+                MethodId::ConditionType_ConditionRefresh2,
+                Box::new(method_impls::ConditionRefresh2Method),
+            );
         }
     }
 
-    /// Sets the history server capabilities based on the supplied flags
+    /// Returns the profiles and facets that the server reports it implements
+    pub fn server_profiles(&self) -> &ServerProfiles {
+        &self.server_profiles
+    }
+
+    /// Adds a profile or facet to those that the server reports it implements, for a facet that
+    /// the server implementation supports by itself such as a companion specification. Profiles
+    /// implied by the configuration and the history capabilities are added automatically.
+    pub fn add_server_profile(&mut self, profile_uri: &str) {
+        if self.server_profiles.add(profile_uri) {
+            self.set_server_profile_array();
+        }
+    }
+
+    /// Adds a hook that is called before a client writes the value of any variable, to validate or
+    /// transform the value according to the rules of the application. Hooks are called in the
+    /// order they were added, each with the value returned by the one before, and the first one
+    /// to return an error rejects the write. The value that they return is then validated
+    /// against the data type of the variable as usual.
+    ///
+    /// ```no_run
+    /// # use opcua::server::prelude::*;
+    /// # fn example(address_space: &mut AddressSpace) {
+    /// let setpoint = NodeId::new(2, "Setpoint");
+    /// address_space.add_before_write_hook(move |node_id, value, _session| {
+    ///     match value {
+    ///         Variant::Double(v) if *node_id == setpoint && !(0.0..=100.0).contains(&v) => {
+    ///             Err(StatusCode::BadOutOfRange)
+    ///         }
+    ///         value => Ok(value),
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn add_before_write_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&NodeId, Variant, &Session) -> Result<Variant, StatusCode> + Send + Sync + 'static,
+    {
+        self.before_write_hooks.push(Box::new(hook));
+    }
+
+    /// Adds a hook that is called after the value of any variable has changed, either by a
+    /// client's write or by `set_variable_value()`, so the application can react to it. The hook
+    /// is not called when setting a value leaves it and its status as they were. Values that are
+    /// set on a variable directly, e.g. through `find_variable_mut()`, are not seen by the hook.
+    pub fn add_value_changed_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&NodeId, &DataValue, &DataValue) + Send + Sync + 'static,
+    {
+        self.value_changed_hooks.push(Box::new(hook));
+    }
+
+    /// Runs the before write hooks on a value that a client writes to a variable
+    pub(crate) fn before_write(
+        &mut self,
+        node_id: &NodeId,
+        value: Variant,
+        session: &Session,
+    ) -> Result<Variant, StatusCode> {
+        self.before_write_hooks
+            .iter_mut()
+            .try_fold(value, |value, hook| hook(node_id, value, session))
+    }
+
+    pub(crate) fn has_value_changed_hooks(&self) -> bool {
+        let has_hooks = !self.value_changed_hooks.is_empty();
+        #[cfg(feature = "events")]
+        let has_hooks = has_hooks || !self.alarms.is_empty();
+        #[cfg(feature = "history")]
+        let has_hooks = has_hooks || self.history_collector.is_enabled();
+        has_hooks
+    }
+
+    /// Adds an alarm that was raised into the address space
+    #[cfg(feature = "events")]
+    pub(crate) fn add_alarm(&mut self, alarm: Alarm) {
+        self.alarms.push(alarm);
+    }
+
+    /// Suppresses the alarm with the node id, or ends its suppression. A suppressed alarm is not
+    /// reported when it becomes active or inactive, e.g. while the equipment it monitors is out
+    /// of service. The function returns false if there is no such alarm.
+    #[cfg(feature = "events")]
+    pub fn set_alarm_suppressed(&mut self, alarm_id: &NodeId, suppressed: bool) -> bool {
+        let mut found = false;
+        self.update_alarms(
+            |alarm| alarm.node_id() == alarm_id,
+            |alarm, address_space| {
+                alarm.state_mut().set_suppressed(address_space, suppressed);
+                found = true;
+            },
+        );
+        found
+    }
+
+    /// Shelves the alarm with the node id, or unshelves it, as a client does by calling the
+    /// methods of its `ShelvingState`. A shelved alarm is not reported when it becomes active or
+    /// inactive. The function returns `BadNodeIdUnknown` if there is no such alarm.
+    #[cfg(feature = "events")]
+    pub fn set_alarm_shelved_state(
+        &mut self,
+        alarm_id: &NodeId,
+        shelved_state: ShelvedState,
+    ) -> Result<(), StatusCode> {
+        let mut result = Err(StatusCode::BadNodeIdUnknown);
+        self.update_alarms(
+            |alarm| alarm.node_id() == alarm_id,
+            |alarm, address_space| {
+                result = alarm
+                    .state_mut()
+                    .set_shelved_state(address_space, shelved_state);
+            },
+        );
+        result
+    }
+
+    /// Unshelves the alarms whose shelving time has elapsed, and updates the time that remains
+    /// for the other alarms that are shelved for a time
+    #[cfg(feature = "events")]
+    pub(crate) fn update_shelved_alarms(&mut self) {
+        let now = Utc::now();
+        self.update_alarms(
+            |alarm| alarm.state().has_unshelve_time(),
+            |alarm, address_space| alarm.state_mut().update_unshelve_time(address_space, &now),
+        );
+    }
+
+    #[cfg(feature = "events")]
+    pub(crate) fn has_shelved_alarms_with_unshelve_time(&self) -> bool {
+        self.alarms
+            .iter()
+            .any(|alarm| alarm.state().has_unshelve_time())
+    }
+
+    /// Adds a state machine that was inserted into the address space
+    pub(crate) fn add_state_machine(&mut self, state_machine: StateMachine) {
+        self.state_machines.push(state_machine);
+    }
+
+    /// Takes the transition of the state machine with the node id, which updates its
+    /// `CurrentState` and `LastTransition` variables and fires a transition event. The function
+    /// returns `BadNodeIdUnknown` if there is no such state machine, `BadInvalidArgument` if the
+    /// transition is not one of its transitions, or `BadInvalidState` if the transition does not
+    /// leave its current state.
+    pub fn fire_state_machine_transition<T>(
+        &mut self,
+        state_machine_id: &NodeId,
+        transition_id: T,
+    ) -> Result<(), StatusCode>
+    where
+        T: Into<NodeId>,
+    {
+        let transition_id = transition_id.into();
+        self.update_state_machine(state_machine_id, |state_machine, address_space| {
+            state_machine.fire_transition(address_space, &transition_id)
+        })
+        .unwrap_or(Err(StatusCode::BadNodeIdUnknown))
+    }
+
+    /// Returns the node id of the current state of the state machine with the node id, or `None`
+    /// if there is no such state machine.
+    pub fn state_machine_current_state(&self, state_machine_id: &NodeId) -> Option<NodeId> {
+        self.state_machines
+            .iter()
+            .find(|state_machine| state_machine.node_id() == state_machine_id)
+            .map(|state_machine| state_machine.current_state().clone())
+    }
+
+    /// Calls the function on the state machine with the node id, which is taken out of the
+    /// address space meanwhile. Returns `None` if there is no such state machine.
+    fn update_state_machine<F, T>(&mut self, state_machine_id: &NodeId, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut StateMachine, &mut AddressSpace) -> T,
+    {
+        let index = self
+            .state_machines
+            .iter()
+            .position(|state_machine| state_machine.node_id() == state_machine_id)?;
+        let mut state_machine = self.state_machines.remove(index);
+        let result = f(&mut state_machine, self);
+        self.state_machines.insert(index, state_machine);
+        Some(result)
+    }
+
+    /// Calls the function on the alarms that match the predicate. The alarms are taken out of
+    /// the address space while the function runs so it can update their conditions.
+    #[cfg(feature = "events")]
+    fn update_alarms<P, F>(&mut self, predicate: P, mut f: F)
+    where
+        P: Fn(&Alarm) -> bool,
+        F: FnMut(&mut Alarm, &mut AddressSpace),
+    {
+        if self.alarms.iter().any(&predicate) {
+            let mut alarms = std::mem::take(&mut self.alarms);
+            alarms
+                .iter_mut()
+                .filter(|alarm| predicate(alarm))
+                .for_each(|alarm| f(alarm, self));
+            // Alarms may have been added in the meantime
+            alarms.append(&mut self.alarms);
+            self.alarms = alarms;
+        }
+    }
+
+    /// Returns the value that a variable has, for the value changed hooks
+    pub(crate) fn current_value(variable: &Variable) -> DataValue {
+        variable.value(
+            TimestampsToReturn::Both,
+            NumericRange::None,
+            &QualifiedName::null(),
+            0.0,
+        )
+    }
+
+    /// Runs the value changed hooks if the value or the status of a variable has changed
+    pub(crate) fn value_changed(
+        &mut self,
+        node_id: &NodeId,
+        old_value: &DataValue,
+        new_value: &DataValue,
+    ) {
+        if old_value.value != new_value.value || old_value.status != new_value.status {
+            self.value_changed_hooks
+                .iter_mut()
+                .for_each(|hook| hook(node_id, old_value, new_value));
+            #[cfg(feature = "events")]
+            self.evaluate_alarms(node_id);
+            #[cfg(feature = "history")]
+            if self.history_collector.is_enabled() && self.is_historizing(node_id) {
+                self.history_collector
+                    .value_changed(node_id, new_value, &Utc::now());
+            }
+        }
+    }
+
+    #[cfg(feature = "history")]
+    fn is_historizing(&self, node_id: &NodeId) -> bool {
+        self.find_variable_by_ref(node_id)
+            .is_some_and(|variable| variable.historizing())
+    }
+
+    /// Sets the settings that the values of a variable are collected into the historian with
+    /// while its `Historizing` attribute is true, and inserts or updates its `HA Configuration`
+    /// object. Values are only collected while the server has a historical data provider. The
+    /// function returns false if there is no such variable.
+    #[cfg(feature = "history")]
+    pub fn set_historical_data_configuration(
+        &mut self,
+        node_id: &NodeId,
+        configuration: HistoricalDataConfiguration,
+    ) -> bool {
+        if self.find_variable_by_ref(node_id).is_none() {
+            return false;
+        }
+        let aggregate_functions = self.history_collector.aggregate_functions().to_vec();
+        data_collection::insert_configuration(self, node_id, &configuration, &aggregate_functions);
+        self.history_collector
+            .set_configuration(node_id, configuration);
+        true
+    }
+
+    /// Starts or stops collecting the values of historizing variables, which the server does
+    /// while it has a historical data provider
+    #[cfg(feature = "history")]
+    pub(crate) fn set_history_collection(&mut self, enabled: bool) {
+        self.history_collector.set_enabled(enabled);
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn is_history_collection_enabled(&self) -> bool {
+        self.history_collector.is_enabled()
+    }
+
+    /// Tests if there are collected values, or variables whose current value is due to be
+    /// collected again
+    #[cfg(feature = "history")]
+    pub(crate) fn has_history_to_collect(&self, now: &DateTimeUtc) -> bool {
+        self.history_collector.has_values()
+            || !self.history_collector.due_for_sampling(now).is_empty()
+    }
+
+    /// Collects the current value of the historizing variables whose value has not been collected
+    /// for their maximum time interval, and returns the values that were collected
+    #[cfg(feature = "history")]
+    pub(crate) fn collect_history(&mut self, now: &DateTimeUtc) -> Vec<(NodeId, DataValue)> {
+        let server_timestamp = DateTime::from(*now);
+        for node_id in self.history_collector.due_for_sampling(now) {
+            if let Some(variable) = self.find_variable_by_ref(&node_id) {
+                if variable.historizing() {
+                    let mut data_value = Self::current_value(variable);
+                    data_value.server_timestamp = Some(server_timestamp);
+                    self.history_collector.collect(&node_id, data_value, now);
+                }
+            }
+        }
+        self.history_collector.take_values()
+    }
+
+    /// Evaluates the alarms that watch the node. An alarm that updates its condition sets the
+    /// values of the condition's variables, which the alarms don't watch.
+    #[cfg(feature = "events")]
+    fn evaluate_alarms(&mut self, node_id: &NodeId) {
+        self.update_alarms(
+            |alarm| alarm.watches(node_id),
+            |alarm, address_space| alarm.evaluate(address_space),
+        );
+    }
+
+    fn set_server_profile_array(&mut self) {
+        let now = DateTime::now();
+        let server_profiles = self
+            .server_profiles
+            .uris()
+            .into_iter()
+            .map(|uri| UAString::from(uri).into())
+            .collect::<Vec<Variant>>();
+        self.set_variable_value(
+            Server_ServerCapabilities_ServerProfileArray,
+            (VariantTypeId::String, server_profiles),
+            &now,
+            &now,
+        );
+    }
+
+    /// Sets the history server capabilities based on the supplied flags. The history facets in
+    /// the `ServerProfileArray` follow the capabilities, and the `AggregateFunctions` folder of
+    /// the `HistoryServerCapabilities` object organizes the aggregate functions. When the server
+    /// can access history data, every historizing variable gets an `HA Configuration` object
+    /// whose `AggregateFunctions` folder organizes them too.
+    #[cfg(feature = "history")]
     pub fn set_history_server_capabilities(&mut self, capabilities: &HistoryServerCapabilities) {
+        self.server_profiles.set_history(capabilities);
+        self.set_server_profile_array();
         let now = DateTime::now();
         self.set_variable_value(
             HistoryServerCapabilities_AccessHistoryDataCapability,
@@ -719,6 +1208,32 @@ impl AddressSpace {
             &now,
             &now,
         );
+        data_collection::organize_aggregate_functions(
+            self,
+            &ObjectId::HistoryServerCapabilities_AggregateFunctions.into(),
+            &capabilities.aggregate_functions,
+        );
+        self.history_collector
+            .set_aggregate_functions(capabilities.aggregate_functions.clone());
+        if capabilities.access_history_data {
+            let historizing_variables: Vec<NodeId> = self
+                .node_map
+                .iter()
+                .filter(|(_, node)| {
+                    matches!(node, NodeType::Variable(variable) if variable.historizing())
+                })
+                .map(|(node_id, _)| node_id.clone())
+                .collect();
+            for node_id in historizing_variables {
+                let configuration = self.history_collector.configuration(&node_id);
+                data_collection::insert_configuration(
+                    self,
+                    &node_id,
+                    &configuration,
+                    &capabilities.aggregate_functions,
+                );
+            }
+        }
     }
 
     /// Returns the root folder
@@ -794,6 +1309,20 @@ impl AddressSpace {
                 self.references.insert(&node_id, references);
             }
             self.update_last_modified();
+            #[cfg(feature = "events")]
+            if self.model_change_events && !self.is_event_node(&node_id) {
+                let mut changes =
+                    vec![self.model_change(&node_id, ModelChangeStructureVerbMask::NodeAdded)];
+                if let Some(references) = references {
+                    references.iter().for_each(|(target_node_id, _, _)| {
+                        changes.push(self.model_change(
+                            target_node_id,
+                            ModelChangeStructureVerbMask::ReferenceAdded,
+                        ));
+                    });
+                }
+                self.raise_model_change_event(changes);
+            }
             true
         }
     }
@@ -819,6 +1348,18 @@ impl AddressSpace {
     {
         self.references.insert_references(references);
         self.update_last_modified();
+        #[cfg(feature = "events")]
+        if self.model_change_events {
+            let changes = references
+                .iter()
+                .flat_map(|(node_id, target_node_id, _)| [*node_id, *target_node_id])
+                .filter(|node_id| !self.is_event_node(node_id))
+                .map(|node_id| {
+                    self.model_change(node_id, ModelChangeStructureVerbMask::ReferenceAdded)
+                })
+                .collect();
+            self.raise_model_change_event(changes);
+        }
     }
 
     /// Inserts a single reference between two nodes in the address space
@@ -833,6 +1374,14 @@ impl AddressSpace {
         self.references
             .insert_reference(node_id, target_node_id, &reference_type_id);
         self.update_last_modified();
+        #[cfg(feature = "events")]
+        if self.model_change_events {
+            self.raise_reference_changes(
+                node_id,
+                target_node_id,
+                ModelChangeStructureVerbMask::ReferenceAdded,
+            );
+        }
     }
 
     pub fn set_node_type<T>(&mut self, node_id: &NodeId, node_type: T)
@@ -912,16 +1461,263 @@ impl AddressSpace {
         result
     }
 
+    /// Creates an instance of an object type or variable type under the parent, and returns the
+    /// node id of the instance. The instance gets a copy of each mandatory instance declaration of
+    /// the type and its supertypes, that is each component or property with a `Mandatory`
+    /// modelling rule, and of the mandatory declarations within those copies in turn. Methods are
+    /// referenced by the instance rather than copied.
+    ///
+    /// An object is organized by its parent if the parent is a folder, otherwise it is a
+    /// component of the parent. The function returns `BadParentNodeIdInvalid` if there is no such
+    /// parent, `BadBrowseNameDuplicated` if the parent already has a child with the browse name,
+    /// or `BadTypeDefinitionInvalid` if the type is not an object type or variable type, or is
+    /// abstract.
+    pub fn instantiate<R>(
+        &mut self,
+        type_id: &NodeId,
+        browse_name: R,
+        parent_id: &NodeId,
+    ) -> Result<NodeId, StatusCode>
+    where
+        R: Into<QualifiedName>,
+    {
+        instantiate::instantiate(self, type_id, browse_name.into(), parent_id, &[])
+    }
+
+    /// Creates an instance of an object type or variable type under the parent like
+    /// `instantiate()`, but also copies the optional instance declarations of the type with the
+    /// browse names, e.g. `Model` to give a device its optional `Model` property.
+    pub fn instantiate_with_optional<R>(
+        &mut self,
+        type_id: &NodeId,
+        browse_name: R,
+        parent_id: &NodeId,
+        optional: &[QualifiedName],
+    ) -> Result<NodeId, StatusCode>
+    where
+        R: Into<QualifiedName>,
+    {
+        instantiate::instantiate(self, type_id, browse_name.into(), parent_id, optional)
+    }
+
+    /// Returns the modelling rule of a node, or `None` if it has none. A node with a modelling
+    /// rule is an instance declaration of a type, while the nodes of instances have none.
+    pub fn modelling_rule(&self, node_id: &NodeId) -> Option<ModellingRule> {
+        instantiate::modelling_rule(self, node_id)
+    }
+
+    /// Tests if the node is an instance declaration of a type, i.e. if it has a modelling rule.
+    pub fn is_instance_declaration(&self, node_id: &NodeId) -> bool {
+        self.modelling_rule(node_id).is_some()
+    }
+
+    /// Finds the instance declarations of a type and its supertypes, with their modelling rules.
+    /// A declaration of a type hides a declaration of a supertype with the same browse name.
+    pub fn find_instance_declarations(&self, type_id: &NodeId) -> Vec<(NodeId, ModellingRule)> {
+        instantiate::instance_declarations(self, None, type_id)
+            .into_iter()
+            .map(|declaration| (declaration.node_id, declaration.modelling_rule))
+            .collect()
+    }
+
+    /// Adds a view with a specified id to the Views folder. The view has a `ViewVersion` property
+    /// which is incremented whenever nodes are added to or removed from the view.
+    pub fn add_view_with_id<R, S>(
+        &mut self,
+        node_id: &NodeId,
+        browse_name: R,
+        display_name: S,
+    ) -> bool
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        self.assert_namespace(node_id);
+        let inserted = ViewBuilder::new(node_id, browse_name, display_name)
+            .organized_by(ObjectId::ViewsFolder)
+            .insert(self);
+        if inserted {
+            VariableBuilder::new(
+                &NodeId::next_numeric(node_id.namespace),
+                "ViewVersion",
+                "ViewVersion",
+            )
+            .data_type(DataTypeId::UInt32)
+            .value(0u32)
+            .has_type_definition(VariableTypeId::PropertyType)
+            .property_of(node_id.clone())
+            .insert(self);
+        }
+        inserted
+    }
+
+    /// Adds a view using a generated node id. The function returns `BadNodeIdExists` if a node
+    /// already has the generated id.
+    pub fn add_view<R, S>(&mut self, browse_name: R, display_name: S) -> Result<NodeId, StatusCode>
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        let node_id = NodeId::next_numeric(self.default_namespace);
+        if self.add_view_with_id(&node_id, browse_name, display_name) {
+            Ok(node_id)
+        } else {
+            Err(StatusCode::BadNodeIdExists)
+        }
+    }
+
+    /// Adds a node to a view by organizing it from the view. The node and the nodes beneath it in
+    /// the hierarchy become part of the view. Returns false if the view or the node does not exist.
+    pub fn add_to_view(&mut self, view_id: &NodeId, node_id: &NodeId) -> bool {
+        if !is_node!(self, view_id, View) || !self.node_exists(node_id) {
+            false
+        } else {
+            self.insert_reference(view_id, node_id, ReferenceTypeId::Organizes);
+            self.increment_view_version(view_id);
+            true
+        }
+    }
+
+    /// Removes a node that was added to a view. Returns false if the node was not in the view.
+    pub fn remove_from_view(&mut self, view_id: &NodeId, node_id: &NodeId) -> bool {
+        if self.delete_reference(view_id, node_id, ReferenceTypeId::Organizes) {
+            self.update_last_modified();
+            self.increment_view_version(view_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the `ViewVersion` of a view, or `None` if the node is not a view with a version.
+    pub fn view_version(&self, view_id: &NodeId) -> Option<u32> {
+        let view_version_id = self.view_version_id(view_id)?;
+        match self
+            .find_variable_by_ref(&view_version_id)?
+            .value(
+                TimestampsToReturn::Neither,
+                NumericRange::None,
+                &QualifiedName::null(),
+                0.0,
+            )
+            .value
+        {
+            Some(Variant::UInt32(view_version)) => Some(view_version),
+            _ => None,
+        }
+    }
+
+    /// Returns the nodes in a view, i.e. the view itself and every node that can be reached from it
+    /// by forward hierarchical references. Returns `None` if the node is not a view.
+    pub fn nodes_in_view(&self, view_id: &NodeId) -> Option<HashSet<NodeId>> {
+        if !is_node!(self, view_id, View) {
+            return None;
+        }
+        let mut nodes = HashSet::new();
+        let mut to_visit = VecDeque::new();
+        nodes.insert(view_id.clone());
+        to_visit.push_back(view_id.clone());
+        while let Some(node_id) = to_visit.pop_front() {
+            if let Some(children) = self.find_hierarchical_references(&node_id) {
+                children.into_iter().for_each(|child| {
+                    if self.node_exists(&child) && nodes.insert(child.clone()) {
+                        to_visit.push_back(child);
+                    }
+                });
+            }
+        }
+        Some(nodes)
+    }
+
+    fn view_version_id(&self, view_id: &NodeId) -> Option<NodeId> {
+        if !is_node!(self, view_id, View) {
+            return None;
+        }
+        let view_version = QualifiedName::from("ViewVersion");
+        self.find_references(view_id, Some((ReferenceTypeId::HasProperty, false)))?
+            .into_iter()
+            .map(|r| r.target_node)
+            .find(|node_id| {
+                self.find_node(node_id)
+                    .map(|node| node.as_node().browse_name() == view_version)
+                    .unwrap_or(false)
+            })
+    }
+
+    fn increment_view_version(&mut self, view_id: &NodeId) {
+        if let (Some(view_version_id), Some(view_version)) =
+            (self.view_version_id(view_id), self.view_version(view_id))
+        {
+            let now = DateTime::now();
+            let _ = self.set_variable_value_by_ref(
+                &view_version_id,
+                view_version.wrapping_add(1),
+                &now,
+                &now,
+            );
+        }
+    }
+
     /// Deletes a node by its node id, and all of its properties and optionally any references to or from it it in the
     /// address space.
     pub fn delete(&mut self, node_id: &NodeId, delete_target_references: bool) -> bool {
+        #[cfg(feature = "events")]
+        let mut changes = if self.model_change_events && !self.is_event_node(node_id) {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        #[cfg(not(feature = "events"))]
+        let mut changes = None;
+        let deleted = self.delete_node(node_id, delete_target_references, &mut changes);
+        if deleted {
+            #[cfg(feature = "events")]
+            self.alarms.retain(|alarm| alarm.node_id() != node_id);
+            self.state_machines
+                .retain(|state_machine| state_machine.node_id() != node_id);
+            #[cfg(feature = "history")]
+            self.history_collector.remove(node_id);
+        }
+        #[cfg(feature = "events")]
+        if let Some(changes) = changes {
+            self.raise_model_change_event(changes);
+        }
+        deleted
+    }
+
+    /// Deletes the node and its children, noting the model changes if they are wanted.
+    fn delete_node(
+        &mut self,
+        node_id: &NodeId,
+        delete_target_references: bool,
+        changes: &mut Option<Vec<ModelChangeStructureDataType>>,
+    ) -> bool {
         // Delete any children recursively
         if let Some(child_nodes) = self.find_aggregates_of(node_id) {
             child_nodes.into_iter().for_each(|node_id| {
                 debug!("Deleting child node {}", node_id);
-                let _ = self.delete(&node_id, delete_target_references);
+                let _ = self.delete_node(&node_id, delete_target_references, changes);
             });
         }
+        #[cfg(feature = "events")]
+        if let Some(ref mut changes) = changes {
+            if self.node_exists(node_id) {
+                changes.push(self.model_change(node_id, ModelChangeStructureVerbMask::NodeDeleted));
+            }
+            if delete_target_references {
+                let (references, _) = self.find_references_by_direction::<NodeId>(
+                    node_id,
+                    BrowseDirection::Both,
+                    None,
+                );
+                references.iter().for_each(|r| {
+                    changes.push(self.model_change(
+                        &r.target_node,
+                        ModelChangeStructureVerbMask::ReferenceDeleted,
+                    ));
+                });
+            }
+        }
         // Remove the node
         let removed_node = self.node_map.remove(node_id);
         // Remove references
@@ -943,8 +1739,142 @@ impl AddressSpace {
     where
         T: Into<NodeId>,
     {
-        self.references
-            .delete_reference(node_id, target_node_id, reference_type_id)
+        let deleted = self
+            .references
+            .delete_reference(node_id, target_node_id, reference_type_id);
+        #[cfg(feature = "events")]
+        if deleted && self.model_change_events {
+            self.raise_reference_changes(
+                node_id,
+                target_node_id,
+                ModelChangeStructureVerbMask::ReferenceDeleted,
+            );
+        }
+        deleted
+    }
+
+    /// Tests if the node is an event or a property of an event. Changes to events and their
+    /// properties are not model changes, otherwise raising a model change event would be one.
+    #[cfg(feature = "events")]
+    fn is_event_node(&self, node_id: &NodeId) -> bool {
+        let is_event = |node_id: &NodeId| {
+            self.find_references(node_id, Some((ReferenceTypeId::HasTypeDefinition, false)))
+                .map(|references| {
+                    references.iter().any(|r| {
+                        self.is_subtype_of(&r.target_node, &ObjectTypeId::BaseEventType.into())
+                    })
+                })
+                .unwrap_or(false)
+        };
+        is_event(node_id)
+            || self
+                .find_inverse_references(node_id, Some((ReferenceTypeId::HasProperty, false)))
+                .map(|references| references.iter().any(|r| is_event(&r.target_node)))
+                .unwrap_or(false)
+    }
+
+    /// Describes a change to a node for a model change event. The type of the node is its type
+    /// definition, if it has one.
+    #[cfg(feature = "events")]
+    fn model_change(
+        &self,
+        node_id: &NodeId,
+        verb: ModelChangeStructureVerbMask,
+    ) -> ModelChangeStructureDataType {
+        let affected_type = self
+            .find_references(node_id, Some((ReferenceTypeId::HasTypeDefinition, false)))
+            .and_then(|references| references.into_iter().next())
+            .map(|r| r.target_node)
+            .unwrap_or_else(NodeId::null);
+        ModelChangeStructureDataType {
+            affected: node_id.clone(),
+            affected_type,
+            verb: verb as u8,
+        }
+    }
+
+    /// Raises a model change event for a reference that has been added or removed between two
+    /// nodes, unless either of them is an event.
+    #[cfg(feature = "events")]
+    fn raise_reference_changes(
+        &mut self,
+        node_id: &NodeId,
+        target_node_id: &NodeId,
+        verb: ModelChangeStructureVerbMask,
+    ) {
+        if !self.is_event_node(node_id) && !self.is_event_node(target_node_id) {
+            let changes = vec![
+                self.model_change(node_id, verb),
+                self.model_change(target_node_id, verb),
+            ];
+            self.raise_model_change_event(changes);
+        }
+    }
+
+    /// Raises a model change event for the changes and bumps the `NodeVersion` property of
+    /// every node whose references changed.
+    #[cfg(feature = "events")]
+    fn raise_model_change_event(&mut self, changes: Vec<ModelChangeStructureDataType>) {
+        if changes.is_empty() {
+            return;
+        }
+        let reference_changed = ModelChangeStructureVerbMask::ReferenceAdded as u8
+            | ModelChangeStructureVerbMask::ReferenceDeleted as u8;
+        let mut versioned = HashSet::new();
+        changes
+            .iter()
+            .filter(|change| change.verb & reference_changed != 0)
+            .for_each(|change| {
+                if versioned.insert(change.affected.clone()) {
+                    self.increment_node_version(&change.affected);
+                }
+            });
+        let event_id = NodeId::next_numeric(self.internal_namespace);
+        let mut event = GeneralModelChangeEventType::new(event_id, DateTime::now(), changes);
+        if event.raise(self).is_err() {
+            error!("Cannot raise a model change event");
+        }
+    }
+
+    /// Increments the `NodeVersion` property of the node, if it has one. The version is a string
+    /// but the server treats it as a number.
+    #[cfg(feature = "events")]
+    fn increment_node_version(&mut self, node_id: &NodeId) {
+        let node_version_id = self
+            .find_references(node_id, Some((ReferenceTypeId::HasProperty, false)))
+            .and_then(|references| {
+                references.into_iter().find(|r| {
+                    self.find_node(&r.target_node)
+                        .map(|node| {
+                            node.as_node().browse_name() == QualifiedName::from("NodeVersion")
+                        })
+                        .unwrap_or(false)
+                })
+            })
+            .map(|r| r.target_node);
+        if let Some(node_version_id) = node_version_id {
+            if let Some(variable) = self.find_variable_mut(&node_version_id) {
+                let version = match variable
+                    .value(
+                        TimestampsToReturn::Neither,
+                        NumericRange::None,
+                        &QualifiedName::null(),
+                        0.0,
+                    )
+                    .value
+                {
+                    Some(Variant::String(version)) => version.as_ref().parse::<u32>().unwrap_or(0),
+                    _ => 0,
+                };
+                let now = DateTime::now();
+                let _ = variable.set_value_direct(
+                    UAString::from(format!("{}", version.wrapping_add(1))),
+                    StatusCode::Good,
+                    &now,
+                    &now,
+                );
+            }
+        }
     }
 
     /// Find node by something that can be turned into a node id and return a reference to it.
@@ -1031,10 +1961,56 @@ impl AddressSpace {
     where
         V: Into<Variant>,
     {
+        let notify_value_changed = self.has_value_changed_hooks();
         if let Some(ref mut variable) = self.find_variable_mut_by_ref(node_id) {
+            let old_value = if notify_value_changed {
+                Some(AddressSpace::current_value(variable))
+            } else {
+                None
+            };
             let _ = variable.set_value_direct(
                 value,
                 StatusCode::Good,
+                server_timestamp,
+                source_timestamp,
+            );
+            if let Some(old_value) = old_value {
+                let new_value = AddressSpace::current_value(variable);
+                self.value_changed(node_id, &old_value, &new_value);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set the value of a `MultiStateValueDiscreteType` variable from its NodeId, along with its
+    /// `ValueAsText` property, which is set to the text of the value from its `EnumValues`
+    /// property. The function will return false if the variable does not exist, or the node is not
+    /// a variable.
+    pub fn set_multi_state_value<N, V>(
+        &mut self,
+        node_id: N,
+        value: V,
+        source_timestamp: &DateTime,
+        server_timestamp: &DateTime,
+    ) -> bool
+    where
+        N: Into<NodeId>,
+        V: Into<Variant>,
+    {
+        let node_id = node_id.into();
+        let value = value.into();
+        if self.set_variable_value_by_ref(
+            &node_id,
+            value.clone(),
+            source_timestamp,
+            server_timestamp,
+        ) {
+            data_access::update_value_as_text(
+                self,
+                &node_id,
+                &value,
                 source_timestamp,
                 server_timestamp,
             );
@@ -1044,6 +2020,45 @@ impl AddressSpace {
         }
     }
 
+    /// Set a variable value from its NodeId to a data value, e.g. one that an application has
+    /// sampled with a status and a source timestamp of its own. A data value without a source
+    /// timestamp is given the server timestamp, and one without a server timestamp or status is
+    /// given the current time and a status of `Good`. The function will return false if the
+    /// variable does not exist, or the node is not a variable.
+    pub fn set_variable_data_value<N>(&mut self, node_id: N, data_value: DataValue) -> bool
+    where
+        N: Into<NodeId>,
+    {
+        let node_id = node_id.into();
+        let notify_value_changed = self.has_value_changed_hooks();
+        if let Some(ref mut variable) = self.find_variable_mut_by_ref(&node_id) {
+            let old_value = if notify_value_changed {
+                Some(AddressSpace::current_value(variable))
+            } else {
+                None
+            };
+            let mut data_value = data_value;
+            if data_value.server_timestamp.is_none() {
+                data_value.set_server_time(Utc::now());
+            }
+            if data_value.source_timestamp.is_none() {
+                data_value.source_timestamp = data_value.server_timestamp;
+                data_value.source_picoseconds = data_value.server_picoseconds;
+            }
+            if data_value.status.is_none() {
+                data_value.status = Some(StatusCode::Good);
+            }
+            variable.set_data_value(data_value);
+            if let Some(old_value) = old_value {
+                let new_value = AddressSpace::current_value(variable);
+                self.value_changed(&node_id, &old_value, &new_value);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
     /// Gets a variable value with the supplied NodeId. The function will return Err if the
     /// NodeId does not exist or is not a variable.
     pub fn get_variable_value<N>(&self, node_id: N) -> Result<DataValue, ()>
@@ -1146,6 +2161,71 @@ impl AddressSpace {
         }
     }
 
+    /// Calls a method of an alarm, or of its `ShelvingState`, that changes the state of the
+    /// alarm. Returns `None` if the object is not an alarm or the method is not one of those.
+    #[cfg(feature = "events")]
+    fn call_alarm_method(
+        &mut self,
+        request: &CallMethodRequest,
+    ) -> Option<Result<CallMethodResult, StatusCode>> {
+        let object_id = &request.object_id;
+        let alarm_id = self
+            .alarms
+            .iter()
+            .find(|alarm| {
+                alarm.node_id() == object_id || alarm.state().shelving_state_id() == object_id
+            })
+            .map(|alarm| alarm.node_id().clone())?;
+        let result = match method_calls::alarm_method_call(request)? {
+            Ok(AlarmMethodCall::Suppress(suppressed)) => {
+                let _ = self.set_alarm_suppressed(&alarm_id, suppressed);
+                Ok(())
+            }
+            Ok(AlarmMethodCall::Shelve(shelved_state)) => {
+                self.set_alarm_shelved_state(&alarm_id, shelved_state)
+            }
+            Err(status_code) => Err(status_code),
+        };
+        Some(result.map(|_| Self::call_method_result(request)))
+    }
+
+    #[cfg(not(feature = "events"))]
+    fn call_alarm_method(
+        &mut self,
+        _request: &CallMethodRequest,
+    ) -> Option<Result<CallMethodResult, StatusCode>> {
+        None
+    }
+
+    /// Calls a method of a program state machine, which is passed on to its program. Returns
+    /// `None` if the object is not a program or the method is not one of those.
+    fn call_program_method(
+        &mut self,
+        request: &CallMethodRequest,
+    ) -> Option<Result<CallMethodResult, StatusCode>> {
+        let method_call = method_calls::program_method_call(request)?;
+        let input_arguments = request.input_arguments.clone().unwrap_or_default();
+        let result = self
+            .update_state_machine(&request.object_id, |state_machine, address_space| {
+                state_machine.call_program(address_space, method_call, &input_arguments)
+            })
+            .flatten()?;
+        Some(result.map(|_| Self::call_method_result(request)))
+    }
+
+    /// Returns the good result of a method call that has no output arguments
+    fn call_method_result(request: &CallMethodRequest) -> CallMethodResult {
+        CallMethodResult {
+            status_code: StatusCode::Good,
+            input_argument_results: request
+                .input_arguments
+                .as_ref()
+                .map(|arguments| vec![StatusCode::Good; arguments.len()]),
+            input_argument_diagnostic_infos: None,
+            output_arguments: None,
+        }
+    }
+
     /// Calls a method node with the supplied request and expecting a result.
     ///
     /// Calls require a registered handler to handle the method. If there is no handler, or if
@@ -1159,7 +2239,10 @@ impl AddressSpace {
     ) -> Result<CallMethodResult, StatusCode> {
         let (object_id, method_id) = (&request.object_id, &request.method_id);
         // Handle the call
-        if !is_object!(self, object_id) {
+        // Methods such as ConditionRefresh are called on an object type rather than an object
+        if !is_object!(self, object_id)
+            && !matches!(self.find_node(object_id), Some(NodeType::ObjectType(_)))
+        {
             error!(
                 "Method call to {:?} on {:?} but the node id is not recognized!",
                 method_id, object_id
@@ -1177,6 +2260,12 @@ impl AddressSpace {
                 method_id, object_id
             );
             Err(StatusCode::BadMethodInvalid)
+        } else if let Some(result) = self.call_alarm_method(request) {
+            // Suppressing and shelving change the state of an alarm, which a handler can't do
+            result
+        } else if let Some(result) = self.call_program_method(request) {
+            // Nor can a handler take the transitions of a program
+            result
         } else if let Some(method) = self.find_mut(method_id) {
             // TODO check security - session / user may not have permission to call methods
             match method {
@@ -1188,23 +2277,31 @@ impl AddressSpace {
         }
     }
 
-    /// Recursive function tries to find if a type is a subtype of another type by looking at its
-    /// references. Function will positively match a type against itself.
+    /// Tests if a type is a subtype of another type. Function will positively match a type against
+    /// itself.
     pub fn is_subtype(&self, subtype_id: &NodeId, base_type_id: &NodeId) -> bool {
-        subtype_id == base_type_id || {
-            // Apply same test to all children of the base type
-            if let Some(references) =
-                self.find_references(base_type_id, Some((ReferenceTypeId::HasSubtype, false)))
-            {
-                // Each child will test if it is the parent / match for the subtype
-                references
-                    .iter()
-                    .any(|r| self.is_subtype(subtype_id, &r.target_node))
-            } else {
-                false
-            }
-        }
+        self.is_subtype_of(subtype_id, base_type_id)
+    }
+
+    /// Tests if a type is a subtype of another type. The address space keeps the supertype of
+    /// every type up to date as `HasSubtype` references are added and removed, so the test
+    /// walks up from the subtype rather than searching down every branch of the base type.
+    /// Function will positively match a type against itself.
+    pub fn is_subtype_of(&self, subtype_id: &NodeId, base_type_id: &NodeId) -> bool {
+        self.references.is_subtype_of(subtype_id, base_type_id)
+    }
+
+    /// Finds the supertypes of a type, starting with its direct supertype and ending with the
+    /// root of its type hierarchy, e.g. `BaseObjectType`.
+    pub fn find_supertypes(&self, type_id: &NodeId) -> Vec<NodeId> {
+        self.references.find_supertypes(type_id)
+    }
+
+    /// Finds every subtype of a type, direct or indirect, not including the type itself.
+    pub fn find_subtypes(&self, type_id: &NodeId) -> Vec<NodeId> {
+        self.references.find_subtypes(type_id)
     }
+
     /// Finds objects by a specified type.
     fn find_nodes_by_type<T>(
         &self,
@@ -1216,6 +2313,11 @@ impl AddressSpace {
         T: Into<NodeId>,
     {
         let node_type_id = node_type_id.into();
+        let node_class = if node_type_class == NodeClass::VariableType {
+            NodeClass::Variable
+        } else {
+            NodeClass::Object
+        };
         // Ensure the node type is of the right class
         if let Some(node) = self.node_map.get(&node_type_id) {
             if node.node_class() == node_type_class {
@@ -1223,7 +2325,7 @@ impl AddressSpace {
                 let nodes = self
                     .node_map
                     .iter()
-                    .filter(|(_, v)| v.node_class() == NodeClass::Object)
+                    .filter(|(_, v)| v.node_class() == node_class)
                     .filter(move |(k, _)| {
                         // Node has to have a type definition reference to the type
                         if let Some(type_refs) = self
@@ -1231,8 +2333,9 @@ impl AddressSpace {
                         {
                             // Type definition must find the sought after type
                             type_refs.iter().any(|r| {
-                                include_subtypes && self.is_subtype(&node_type_id, &r.target_node)
-                                    || r.target_node == node_type_id
+                                r.target_node == node_type_id
+                                    || include_subtypes
+                                        && self.is_subtype_of(&r.target_node, &node_type_id)
                             })
                         } else {
                             false
@@ -1360,6 +2463,7 @@ impl AddressSpace {
     }
 
     /// Sets the getter for a variable node
+    #[cfg(feature = "generated-address-space")]
     fn set_variable_getter<N, F>(&mut self, variable_id: N, getter: F)
     where
         N: Into<NodeId>,
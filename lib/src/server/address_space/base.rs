@@ -2,7 +2,11 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use crate::types::{status_code::StatusCode, *};
+use crate::types::{
+    service_types::{AccessRestrictionType, RolePermissionType},
+    status_code::StatusCode,
+    *,
+};
 
 use super::node::{Node, NodeBase};
 
@@ -23,6 +27,44 @@ pub(crate) struct Base {
     write_mask: Option<u32>,
     /// User write mask bits (optional)
     user_write_mask: Option<u32>,
+    /// The permissions that roles have on the node (optional)
+    role_permissions: Option<Vec<RolePermissionType>>,
+    /// The permissions that the roles of the current user have on the node (optional)
+    user_role_permissions: Option<Vec<RolePermissionType>>,
+    /// Access restriction bits (optional)
+    access_restrictions: Option<u16>,
+}
+
+/// Makes the value of a role permissions attribute, an array of `RolePermissionType`.
+fn role_permissions_to_variant(role_permissions: &[RolePermissionType]) -> Variant {
+    let values = role_permissions
+        .iter()
+        .map(|v| {
+            Variant::from(ExtensionObject::from_encodable(
+                ObjectId::RolePermissionType_Encoding_DefaultBinary,
+                v,
+            ))
+        })
+        .collect::<Vec<_>>();
+    Variant::from((VariantTypeId::ExtensionObject, values))
+}
+
+/// Reads the value of a role permissions attribute, an array of `RolePermissionType`.
+fn role_permissions_from_variant(value: Variant) -> Result<Vec<RolePermissionType>, StatusCode> {
+    let decoding_options = DecodingOptions::default();
+    match value {
+        Variant::Array(array) => array
+            .values
+            .iter()
+            .map(|v| match v {
+                Variant::ExtensionObject(v) => v
+                    .decode_inner::<RolePermissionType>(&decoding_options)
+                    .map_err(|_| StatusCode::BadTypeMismatch),
+                _ => Err(StatusCode::BadTypeMismatch),
+            })
+            .collect(),
+        _ => Err(StatusCode::BadTypeMismatch),
+    }
 }
 
 impl NodeBase for Base {
@@ -69,6 +111,31 @@ impl NodeBase for Base {
     fn set_user_write_mask(&mut self, user_write_mask: WriteMask) {
         self.user_write_mask = Some(user_write_mask.bits());
     }
+
+    fn role_permissions(&self) -> Option<Vec<RolePermissionType>> {
+        self.role_permissions.clone()
+    }
+
+    fn set_role_permissions(&mut self, role_permissions: Vec<RolePermissionType>) {
+        self.role_permissions = Some(role_permissions);
+    }
+
+    fn user_role_permissions(&self) -> Option<Vec<RolePermissionType>> {
+        self.user_role_permissions.clone()
+    }
+
+    fn set_user_role_permissions(&mut self, user_role_permissions: Vec<RolePermissionType>) {
+        self.user_role_permissions = Some(user_role_permissions);
+    }
+
+    fn access_restrictions(&self) -> Option<AccessRestrictionType> {
+        self.access_restrictions
+            .map(|v| AccessRestrictionType::from_bits_truncate(v as i16))
+    }
+
+    fn set_access_restrictions(&mut self, access_restrictions: AccessRestrictionType) {
+        self.access_restrictions = Some(access_restrictions.bits() as u16);
+    }
 }
 
 impl Node for Base {
@@ -88,6 +155,15 @@ impl Node for Base {
             AttributeId::Description => self.description().map(|description| description.into()),
             AttributeId::WriteMask => self.write_mask.map(|v| v.into()),
             AttributeId::UserWriteMask => self.user_write_mask.map(|v| v.into()),
+            AttributeId::RolePermissions => self
+                .role_permissions
+                .as_ref()
+                .map(|v| role_permissions_to_variant(v).into()),
+            AttributeId::UserRolePermissions => self
+                .user_role_permissions
+                .as_ref()
+                .map(|v| role_permissions_to_variant(v).into()),
+            AttributeId::AccessRestrictions => self.access_restrictions.map(|v| v.into()),
             _ => None,
         }
     }
@@ -168,6 +244,22 @@ impl Node for Base {
                     Err(StatusCode::BadTypeMismatch)
                 }
             }
+            AttributeId::RolePermissions => {
+                self.role_permissions = Some(role_permissions_from_variant(value)?);
+                Ok(())
+            }
+            AttributeId::UserRolePermissions => {
+                self.user_role_permissions = Some(role_permissions_from_variant(value)?);
+                Ok(())
+            }
+            AttributeId::AccessRestrictions => {
+                if let Variant::UInt16(v) = value {
+                    self.access_restrictions = Some(v);
+                    Ok(())
+                } else {
+                    Err(StatusCode::BadTypeMismatch)
+                }
+            }
             _ => Err(StatusCode::BadAttributeIdInvalid),
         }
     }
@@ -192,6 +284,9 @@ impl Base {
             description: None,
             write_mask: None,
             user_write_mask: None,
+            role_permissions: None,
+            user_role_permissions: None,
+            access_restrictions: None,
         }
     }
 
@@ -0,0 +1,656 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains builders for the variable types of OPC UA Part 8 DataAccess, i.e. `AnalogItemType`,
+//! `TwoStateDiscreteType`, `MultiStateDiscreteType` and `MultiStateValueDiscreteType`. Each
+//! builder makes the variable with the type definition and inserts the properties that the type
+//! mandates, as well as any optional properties that were supplied.
+
+use std::sync::Arc;
+
+use crate::sync::*;
+use crate::types::{
+    service_types::{EUInformation, EnumValueType, Range},
+    *,
+};
+
+use crate::server::{
+    address_space::{address_space::AddressSpace, variable::VariableBuilder},
+    callbacks::{AttributeGetter, AttributeSetter},
+};
+
+/// Implements the functions of the data access builders that are passed on to the builder of the
+/// variable.
+macro_rules! data_access_builder_impl {
+    ( $builder_ty:ident ) => {
+        impl $builder_ty {
+            /// Returns the node id of the variable.
+            pub fn get_node_id(&self) -> NodeId {
+                self.variable.get_node_id()
+            }
+
+            /// Sets the description of the variable.
+            pub fn description<V>(mut self, description: V) -> Self
+            where
+                V: Into<LocalizedText>,
+            {
+                self.variable = self.variable.description(description);
+                self
+            }
+
+            /// Sets the value of the variable.
+            pub fn value<V>(mut self, value: V) -> Self
+            where
+                V: Into<Variant>,
+            {
+                self.variable = self.variable.value(value);
+                self
+            }
+
+            /// Makes the variable writable (by default it isn't)
+            pub fn writable(mut self) -> Self {
+                self.variable = self.variable.writable();
+                self
+            }
+
+            /// Sets the minimum sampling interval for the variable.
+            pub fn minimum_sampling_interval(mut self, minimum_sampling_interval: f64) -> Self {
+                self.variable = self
+                    .variable
+                    .minimum_sampling_interval(minimum_sampling_interval);
+                self
+            }
+
+            /// Sets a value getter function for the variable.
+            pub fn value_getter(mut self, getter: Arc<Mutex<dyn AttributeGetter + Send>>) -> Self {
+                self.variable = self.variable.value_getter(getter);
+                self
+            }
+
+            /// Sets a value setter function for the variable.
+            pub fn value_setter(mut self, setter: Arc<Mutex<dyn AttributeSetter + Send>>) -> Self {
+                self.variable = self.variable.value_setter(setter);
+                self
+            }
+
+            /// Makes the variable organized by the node, e.g. a folder.
+            pub fn organized_by<T>(mut self, organized_by_id: T) -> Self
+            where
+                T: Into<NodeId>,
+            {
+                self.variable = self.variable.organized_by(organized_by_id);
+                self
+            }
+
+            /// Makes the variable a component of the node.
+            pub fn component_of<T>(mut self, component_of_id: T) -> Self
+            where
+                T: Into<NodeId>,
+            {
+                self.variable = self.variable.component_of(component_of_id);
+                self
+            }
+
+            /// Applies any other settings to the builder of the variable.
+            pub fn variable<F>(mut self, f: F) -> Self
+            where
+                F: FnOnce(VariableBuilder) -> VariableBuilder,
+            {
+                self.variable = f(self.variable);
+                self
+            }
+        }
+    };
+}
+
+/// Inserts a property of a data access variable. The property has a standard browse name.
+fn insert_property<V>(
+    address_space: &mut AddressSpace,
+    variable_id: &NodeId,
+    browse_name: &str,
+    data_type: DataTypeId,
+    value: V,
+) where
+    V: Into<Variant>,
+{
+    let value = value.into();
+    let property_id = NodeId::next_numeric(variable_id.namespace);
+    let builder = VariableBuilder::new(&property_id, browse_name, browse_name)
+        .property_of(variable_id.clone())
+        .has_type_definition(VariableTypeId::PropertyType)
+        .data_type(data_type);
+    let builder = if let Variant::Array(ref array) = value {
+        builder
+            .value_rank(1)
+            .array_dimensions(&[array.values.len() as u32])
+    } else {
+        builder
+    };
+    builder.value(value).insert(address_space);
+}
+
+fn range_to_variant(range: &Range) -> Variant {
+    ExtensionObject::from_encodable(ObjectId::Range_Encoding_DefaultBinary, range).into()
+}
+
+/// Inserts a variable, panicking with a message naming the missing mandatory property if it
+/// was not supplied.
+fn insert_variable(
+    variable: VariableBuilder,
+    type_definition: VariableTypeId,
+    missing_property: Option<&str>,
+    address_space: &mut AddressSpace,
+) -> NodeId {
+    let node_id = variable.get_node_id();
+    if let Some(missing_property) = missing_property {
+        panic!(
+            "The {:?} variable {:?} has no {} property",
+            type_definition, node_id, missing_property
+        );
+    }
+    variable
+        .has_type_definition(type_definition)
+        .insert(address_space);
+    node_id
+}
+
+/// Builds a variable of `AnalogItemType`, which holds a measured or calculated analog value. The
+/// `EURange` property is mandatory while `EngineeringUnits` and `InstrumentRange` are optional.
+///
+/// ```no_run
+/// use opcua::server::prelude::*;
+///
+/// # fn example(address_space: &mut AddressSpace) {
+/// let node_id = NodeId::new(2, "Temperature");
+/// AnalogItemBuilder::new(&node_id, "Temperature", "Temperature")
+///     .data_type(DataTypeId::Double)
+///     .value(21.5)
+///     .eu_range(-40.0, 120.0)
+///     .engineering_units(EUInformation {
+///         namespace_uri: UAString::from("http://www.opcfoundation.org/UA/units/un/cefact"),
+///         unit_id: 4408652,
+///         display_name: LocalizedText::from("°C"),
+///         description: LocalizedText::from("degree Celsius"),
+///     })
+///     .organized_by(ObjectId::ObjectsFolder)
+///     .insert(address_space);
+/// # }
+/// ```
+pub struct AnalogItemBuilder {
+    variable: VariableBuilder,
+    eu_range: Option<Range>,
+    engineering_units: Option<EUInformation>,
+    instrument_range: Option<Range>,
+}
+
+data_access_builder_impl!(AnalogItemBuilder);
+
+impl AnalogItemBuilder {
+    pub fn new<R, S>(node_id: &NodeId, browse_name: R, display_name: S) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        AnalogItemBuilder {
+            variable: VariableBuilder::new(node_id, browse_name, display_name),
+            eu_range: None,
+            engineering_units: None,
+            instrument_range: None,
+        }
+    }
+
+    /// Sets the data type of the variable, which should be a number.
+    pub fn data_type<T>(mut self, data_type: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.variable = self.variable.data_type(data_type);
+        self
+    }
+
+    /// Sets the range that the value is expected to be within in normal operation.
+    pub fn eu_range(mut self, low: f64, high: f64) -> Self {
+        self.eu_range = Some(Range { low, high });
+        self
+    }
+
+    /// Sets the engineering units of the value.
+    pub fn engineering_units(mut self, engineering_units: EUInformation) -> Self {
+        self.engineering_units = Some(engineering_units);
+        self
+    }
+
+    /// Sets the range of values that the instrument can return.
+    pub fn instrument_range(mut self, low: f64, high: f64) -> Self {
+        self.instrument_range = Some(Range { low, high });
+        self
+    }
+
+    /// Inserts the variable and its properties into the address space and returns the node id
+    /// of the variable. Panics if the EURange was not set.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        let missing_property = if self.eu_range.is_none() {
+            Some("EURange")
+        } else {
+            None
+        };
+        let node_id = insert_variable(
+            self.variable,
+            VariableTypeId::AnalogItemType,
+            missing_property,
+            address_space,
+        );
+        if let Some(ref eu_range) = self.eu_range {
+            insert_property(
+                address_space,
+                &node_id,
+                "EURange",
+                DataTypeId::Range,
+                range_to_variant(eu_range),
+            );
+        }
+        if let Some(ref engineering_units) = self.engineering_units {
+            let value = ExtensionObject::from_encodable(
+                ObjectId::EUInformation_Encoding_DefaultBinary,
+                engineering_units,
+            );
+            insert_property(
+                address_space,
+                &node_id,
+                "EngineeringUnits",
+                DataTypeId::EUInformation,
+                value,
+            );
+        }
+        if let Some(ref instrument_range) = self.instrument_range {
+            insert_property(
+                address_space,
+                &node_id,
+                "InstrumentRange",
+                DataTypeId::Range,
+                range_to_variant(instrument_range),
+            );
+        }
+        node_id
+    }
+}
+
+/// Builds a variable of `TwoStateDiscreteType`, which holds a boolean value with the text of its
+/// `TrueState` and `FalseState` properties, both of which are mandatory.
+pub struct TwoStateDiscreteBuilder {
+    variable: VariableBuilder,
+    true_state: Option<LocalizedText>,
+    false_state: Option<LocalizedText>,
+}
+
+data_access_builder_impl!(TwoStateDiscreteBuilder);
+
+impl TwoStateDiscreteBuilder {
+    pub fn new<R, S>(node_id: &NodeId, browse_name: R, display_name: S) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        TwoStateDiscreteBuilder {
+            variable: VariableBuilder::new(node_id, browse_name, display_name)
+                .data_type(DataTypeId::Boolean),
+            true_state: None,
+            false_state: None,
+        }
+    }
+
+    /// Sets the text of the true state, e.g. "On".
+    pub fn true_state<T>(mut self, true_state: T) -> Self
+    where
+        T: Into<LocalizedText>,
+    {
+        self.true_state = Some(true_state.into());
+        self
+    }
+
+    /// Sets the text of the false state, e.g. "Off".
+    pub fn false_state<T>(mut self, false_state: T) -> Self
+    where
+        T: Into<LocalizedText>,
+    {
+        self.false_state = Some(false_state.into());
+        self
+    }
+
+    /// Inserts the variable and its properties into the address space and returns the node id
+    /// of the variable. Panics if the true or false state was not set.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        let missing_property = if self.true_state.is_none() {
+            Some("TrueState")
+        } else if self.false_state.is_none() {
+            Some("FalseState")
+        } else {
+            None
+        };
+        let node_id = insert_variable(
+            self.variable,
+            VariableTypeId::TwoStateDiscreteType,
+            missing_property,
+            address_space,
+        );
+        if let (Some(true_state), Some(false_state)) = (self.true_state, self.false_state) {
+            insert_property(
+                address_space,
+                &node_id,
+                "TrueState",
+                DataTypeId::LocalizedText,
+                true_state,
+            );
+            insert_property(
+                address_space,
+                &node_id,
+                "FalseState",
+                DataTypeId::LocalizedText,
+                false_state,
+            );
+        }
+        node_id
+    }
+}
+
+/// Builds a variable of `MultiStateDiscreteType`, which holds an unsigned integer that indexes
+/// the text of its mandatory `EnumStrings` property.
+pub struct MultiStateDiscreteBuilder {
+    variable: VariableBuilder,
+    enum_strings: Vec<LocalizedText>,
+}
+
+data_access_builder_impl!(MultiStateDiscreteBuilder);
+
+impl MultiStateDiscreteBuilder {
+    pub fn new<R, S>(node_id: &NodeId, browse_name: R, display_name: S) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        MultiStateDiscreteBuilder {
+            variable: VariableBuilder::new(node_id, browse_name, display_name)
+                .data_type(DataTypeId::UInt32),
+            enum_strings: Vec::new(),
+        }
+    }
+
+    /// Sets the data type of the variable, which must be an unsigned integer. The default is
+    /// `UInt32`.
+    pub fn data_type<T>(mut self, data_type: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.variable = self.variable.data_type(data_type);
+        self
+    }
+
+    /// Sets the text of each state, where the first is the text of the value 0.
+    pub fn enum_strings<T>(mut self, enum_strings: Vec<T>) -> Self
+    where
+        T: Into<LocalizedText>,
+    {
+        self.enum_strings = enum_strings.into_iter().map(|s| s.into()).collect();
+        self
+    }
+
+    /// Sets the text of each state from the variants of a Rust enum. Panics if the values of the
+    /// enum do not run from 0 upwards without gaps, since `EnumStrings` cannot express that. Use a
+    /// `MultiStateValueDiscreteBuilder` for such an enum.
+    pub fn enum_type<E>(mut self) -> Self
+    where
+        E: UaEnum,
+    {
+        self.enum_strings = Enumeration::from_enum::<E>()
+            .enum_strings()
+            .expect("The values of the enum do not run from 0 upwards without gaps");
+        self
+    }
+
+    /// Inserts the variable and its properties into the address space and returns the node id
+    /// of the variable. Panics if there are no enum strings.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        let missing_property = if self.enum_strings.is_empty() {
+            Some("EnumStrings")
+        } else {
+            None
+        };
+        let node_id = insert_variable(
+            self.variable,
+            VariableTypeId::MultiStateDiscreteType,
+            missing_property,
+            address_space,
+        );
+        let enum_strings = self
+            .enum_strings
+            .into_iter()
+            .map(Variant::from)
+            .collect::<Vec<_>>();
+        insert_property(
+            address_space,
+            &node_id,
+            "EnumStrings",
+            DataTypeId::LocalizedText,
+            (VariantTypeId::LocalizedText, enum_strings),
+        );
+        node_id
+    }
+}
+
+/// Builds a variable of `MultiStateValueDiscreteType`, which holds a number whose text is
+/// looked up from its mandatory `EnumValues` property. The text of the current value is in its
+/// mandatory `ValueAsText` property, which `AddressSpace::set_multi_state_value()` keeps up to
+/// date.
+pub struct MultiStateValueDiscreteBuilder {
+    variable: VariableBuilder,
+    enum_values: Vec<EnumValueType>,
+}
+
+data_access_builder_impl!(MultiStateValueDiscreteBuilder);
+
+impl MultiStateValueDiscreteBuilder {
+    pub fn new<R, S>(node_id: &NodeId, browse_name: R, display_name: S) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        MultiStateValueDiscreteBuilder {
+            variable: VariableBuilder::new(node_id, browse_name, display_name)
+                .data_type(DataTypeId::Int32),
+            enum_values: Vec::new(),
+        }
+    }
+
+    /// Sets the data type of the variable, which must be a number. The default is `Int32`.
+    pub fn data_type<T>(mut self, data_type: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.variable = self.variable.data_type(data_type);
+        self
+    }
+
+    /// Adds a value with its text.
+    pub fn enum_value<T>(mut self, value: i64, display_name: T) -> Self
+    where
+        T: Into<LocalizedText>,
+    {
+        self.enum_values.push(EnumValueType {
+            value,
+            display_name: display_name.into(),
+            description: LocalizedText::null(),
+        });
+        self
+    }
+
+    /// Sets the values with their text and description.
+    pub fn enum_values(mut self, enum_values: Vec<EnumValueType>) -> Self {
+        self.enum_values = enum_values;
+        self
+    }
+
+    /// Sets the values with their text from the variants of a Rust enum.
+    pub fn enum_type<E>(mut self) -> Self
+    where
+        E: UaEnum,
+    {
+        self.enum_values = Enumeration::from_enum::<E>().enum_values().to_vec();
+        self
+    }
+
+    /// Inserts the variable and its properties into the address space and returns the node id
+    /// of the variable. Panics if there are no enum values.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        let MultiStateValueDiscreteBuilder {
+            variable,
+            enum_values,
+        } = self;
+        let missing_property = if enum_values.is_empty() {
+            Some("EnumValues")
+        } else {
+            None
+        };
+        let node_id = insert_variable(
+            variable,
+            VariableTypeId::MultiStateValueDiscreteType,
+            missing_property,
+            address_space,
+        );
+        let enumeration = Enumeration::from_enum_values(enum_values);
+        insert_property(
+            address_space,
+            &node_id,
+            "EnumValues",
+            DataTypeId::EnumValueType,
+            enumeration.enum_values_variant(),
+        );
+        let value_as_text = address_space
+            .find_variable_by_ref(&node_id)
+            .and_then(|v| {
+                v.value(
+                    TimestampsToReturn::Neither,
+                    NumericRange::None,
+                    &QualifiedName::null(),
+                    0.0,
+                )
+                .value
+            })
+            .and_then(|value| enumeration.text_of(&value).cloned())
+            .unwrap_or_else(LocalizedText::null);
+        insert_property(
+            address_space,
+            &node_id,
+            "ValueAsText",
+            DataTypeId::LocalizedText,
+            value_as_text,
+        );
+        node_id
+    }
+}
+
+/// Finds the property of a node by its browse name.
+fn find_property(
+    address_space: &AddressSpace,
+    node_id: &NodeId,
+    browse_name: &str,
+) -> Option<NodeId> {
+    address_space
+        .find_references(node_id, Some((ReferenceTypeId::HasProperty, false)))?
+        .into_iter()
+        .map(|r| r.target_node)
+        .find(|property_id| {
+            address_space
+                .find_node(property_id)
+                .map(|n| n.as_node().browse_name().name.as_ref() == browse_name)
+                .unwrap_or(false)
+        })
+}
+
+/// Reads the enumeration of a node from its `EnumStrings` or `EnumValues` property.
+fn find_enumeration(address_space: &AddressSpace, node_id: &NodeId) -> Option<Enumeration> {
+    find_property(address_space, node_id, "EnumStrings")
+        .or_else(|| find_property(address_space, node_id, "EnumValues"))
+        .and_then(|id| address_space.get_variable_value(id).ok())
+        .and_then(|value| value.value)
+        .and_then(|value| Enumeration::from_property_value(&value))
+}
+
+/// Validates a value to write to an enumerated variable against the values that it allows. The
+/// values are those of the `EnumStrings` or `EnumValues` property of the variable, as a multi
+/// state variable has, or else of its data type if that is an enumeration. Fails with
+/// `BadOutOfRange` if the value, or an element of it, is not one of them.
+pub(crate) fn validate_enum_value(
+    address_space: &AddressSpace,
+    variable_id: &NodeId,
+    data_type: &NodeId,
+    value: &Variant,
+) -> Result<(), StatusCode> {
+    let enumeration = find_enumeration(address_space, variable_id).or_else(|| {
+        if address_space.is_subtype_of(data_type, &DataTypeId::Enumeration.into()) {
+            find_enumeration(address_space, data_type)
+        } else {
+            None
+        }
+    });
+    let enumeration = if let Some(enumeration) = enumeration {
+        enumeration
+    } else {
+        return Ok(());
+    };
+    let is_allowed = |value: &Variant| match value {
+        Variant::Empty => true,
+        value => enumeration.text_of(value).is_some(),
+    };
+    let is_allowed = match value {
+        Variant::Array(array) => array.values.iter().all(is_allowed),
+        value => is_allowed(value),
+    };
+    if is_allowed {
+        Ok(())
+    } else {
+        debug!(
+            "Value {:?} is not one of the enum values of var {}",
+            value, variable_id
+        );
+        Err(StatusCode::BadOutOfRange)
+    }
+}
+
+/// Sets the `ValueAsText` property of a multi state value discrete variable to the text of the
+/// value. Does nothing if the variable has no such property.
+pub(crate) fn update_value_as_text(
+    address_space: &mut AddressSpace,
+    node_id: &NodeId,
+    value: &Variant,
+    source_timestamp: &DateTime,
+    server_timestamp: &DateTime,
+) {
+    if let Some(property_id) = find_property(address_space, node_id, "ValueAsText") {
+        let value_as_text = find_enumeration(address_space, node_id)
+            .and_then(|enumeration| enumeration.text_of(value).cloned())
+            .unwrap_or_else(LocalizedText::null);
+        address_space.set_variable_value_by_ref(
+            &property_id,
+            value_as_text,
+            source_timestamp,
+            server_timestamp,
+        );
+    }
+}
+
+/// Updates the `ValueAsText` property of a multi state value discrete variable after its value
+/// was written by a client.
+pub(crate) fn value_written(address_space: &mut AddressSpace, node_id: &NodeId) {
+    if find_property(address_space, node_id, "ValueAsText").is_none() {
+        return;
+    }
+    if let Some(value) = address_space
+        .get_variable_value(node_id)
+        .ok()
+        .and_then(|value| value.value)
+    {
+        let now = DateTime::now();
+        update_value_as_text(address_space, node_id, &value, &now, &now);
+    }
+}
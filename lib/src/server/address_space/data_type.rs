@@ -2,19 +2,91 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-//! Contains the implementation of `Method` and `MethodBuilder`.
+//! Contains the implementation of `DataType` and `DataTypeBuilder`.
 
-use crate::types::service_types::DataTypeAttributes;
+use std::convert::TryFrom;
+
+use crate::types::service_types::{DataTypeAttributes, EnumDefinition, StructureDefinition};
 
 use super::{base::Base, node::Node, node::NodeBase};
 
 node_builder_impl!(DataTypeBuilder, DataType);
+node_builder_impl_subtype!(DataTypeBuilder);
+
+impl DataTypeBuilder {
+    /// Sets whether the data type is abstract.
+    pub fn is_abstract(mut self, is_abstract: bool) -> Self {
+        self.node.set_is_abstract(is_abstract);
+        self
+    }
+
+    /// Sets the definition of the data type, i.e. the fields of a structure or enumeration.
+    pub fn data_type_definition<T>(mut self, data_type_definition: T) -> Self
+    where
+        T: Into<DataTypeDefinition>,
+    {
+        self.node.set_data_type_definition(data_type_definition);
+        self
+    }
+}
+
+/// The definition of a data type, served as its `DataTypeDefinition` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataTypeDefinition {
+    Structure(StructureDefinition),
+    Enum(EnumDefinition),
+}
+
+impl From<StructureDefinition> for DataTypeDefinition {
+    fn from(v: StructureDefinition) -> Self {
+        DataTypeDefinition::Structure(v)
+    }
+}
+
+impl From<EnumDefinition> for DataTypeDefinition {
+    fn from(v: EnumDefinition) -> Self {
+        DataTypeDefinition::Enum(v)
+    }
+}
+
+impl From<&DataTypeDefinition> for ExtensionObject {
+    fn from(v: &DataTypeDefinition) -> Self {
+        match v {
+            DataTypeDefinition::Structure(v) => ExtensionObject::from_encodable(
+                ObjectId::StructureDefinition_Encoding_DefaultBinary,
+                v,
+            ),
+            DataTypeDefinition::Enum(v) => {
+                ExtensionObject::from_encodable(ObjectId::EnumDefinition_Encoding_DefaultBinary, v)
+            }
+        }
+    }
+}
+
+impl TryFrom<&ExtensionObject> for DataTypeDefinition {
+    type Error = StatusCode;
+
+    fn try_from(v: &ExtensionObject) -> Result<Self, Self::Error> {
+        let decoding_options = DecodingOptions::default();
+        match v.object_id().map_err(|_| StatusCode::BadTypeMismatch)? {
+            ObjectId::StructureDefinition_Encoding_DefaultBinary => v
+                .decode_inner::<StructureDefinition>(&decoding_options)
+                .map(DataTypeDefinition::Structure),
+            ObjectId::EnumDefinition_Encoding_DefaultBinary => v
+                .decode_inner::<EnumDefinition>(&decoding_options)
+                .map(DataTypeDefinition::Enum),
+            _ => return Err(StatusCode::BadTypeMismatch),
+        }
+        .map_err(|_| StatusCode::BadTypeMismatch)
+    }
+}
 
 /// A `DataType` is a type of node within the `AddressSpace`.
 #[derive(Debug)]
 pub struct DataType {
     base: Base,
     is_abstract: bool,
+    data_type_definition: Option<DataTypeDefinition>,
 }
 
 impl Default for DataType {
@@ -22,6 +94,7 @@ impl Default for DataType {
         Self {
             base: Base::new(NodeClass::DataType, &NodeId::null(), "", ""),
             is_abstract: false,
+            data_type_definition: None,
         }
     }
 }
@@ -39,6 +112,10 @@ impl Node for DataType {
     ) -> Option<DataValue> {
         match attribute_id {
             AttributeId::IsAbstract => Some(self.is_abstract().into()),
+            AttributeId::DataTypeDefinition => self
+                .data_type_definition
+                .as_ref()
+                .map(|v| Variant::from(ExtensionObject::from(v)).into()),
             _ => self.base.get_attribute_max_age(
                 timestamps_to_return,
                 attribute_id,
@@ -63,6 +140,14 @@ impl Node for DataType {
                     Err(StatusCode::BadTypeMismatch)
                 }
             }
+            AttributeId::DataTypeDefinition => {
+                if let Variant::ExtensionObject(v) = value {
+                    self.data_type_definition = Some(DataTypeDefinition::try_from(v.as_ref())?);
+                    Ok(())
+                } else {
+                    Err(StatusCode::BadTypeMismatch)
+                }
+            }
             _ => self.base.set_attribute(attribute_id, value),
         }
     }
@@ -82,6 +167,7 @@ impl DataType {
         DataType {
             base: Base::new(NodeClass::DataType, node_id, browse_name, display_name),
             is_abstract,
+            data_type_definition: None,
         }
     }
 
@@ -128,4 +214,17 @@ impl DataType {
     pub fn set_is_abstract(&mut self, is_abstract: bool) {
         self.is_abstract = is_abstract;
     }
+
+    /// Returns the definition of the data type, if it has one.
+    pub fn data_type_definition(&self) -> Option<&DataTypeDefinition> {
+        self.data_type_definition.as_ref()
+    }
+
+    /// Sets the definition of the data type.
+    pub fn set_data_type_definition<T>(&mut self, data_type_definition: T)
+    where
+        T: Into<DataTypeDefinition>,
+    {
+        self.data_type_definition = Some(data_type_definition.into());
+    }
 }
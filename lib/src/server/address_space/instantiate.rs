@@ -0,0 +1,297 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Creates instances of object types and variable types, with copies of the instance
+//! declarations of the type according to their modelling rules.
+
+use std::{collections::HashSet, convert::TryFrom};
+
+use crate::types::{
+    service_types::TimestampsToReturn, status_code::StatusCode, DataTypeId, NodeId, NumericRange,
+    ObjectTypeId, QualifiedName, ReferenceTypeId, VariableTypeId,
+};
+
+use super::{
+    address_space::AddressSpace,
+    modelling_rule::ModellingRule,
+    node::{HasNodeId, NodeBase, NodeType},
+    object::ObjectBuilder,
+    references::ReferenceDirection,
+    variable::VariableBuilder,
+};
+
+/// An instance declaration of a type, found by `instance_declarations()`.
+pub(crate) struct InstanceDeclaration {
+    /// The type of the reference from the parent of the declaration
+    pub reference_type: NodeId,
+    pub node_id: NodeId,
+    pub browse_name: QualifiedName,
+    pub modelling_rule: ModellingRule,
+}
+
+/// Creates an instance of the object type or variable type under the parent, copying the optional
+/// instance declarations of the type with the browse names as well as the mandatory ones. See
+/// `AddressSpace::instantiate()`.
+pub(crate) fn instantiate(
+    address_space: &mut AddressSpace,
+    type_id: &NodeId,
+    browse_name: QualifiedName,
+    parent_id: &NodeId,
+    optional: &[QualifiedName],
+) -> Result<NodeId, StatusCode> {
+    if !address_space.node_exists(parent_id) {
+        return Err(StatusCode::BadParentNodeIdInvalid);
+    }
+    let is_duplicate = address_space
+        .find_hierarchical_references(parent_id)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|node_id| address_space.find_node(node_id))
+        .any(|node| node.as_node().browse_name() == browse_name);
+    if is_duplicate {
+        return Err(StatusCode::BadBrowseNameDuplicated);
+    }
+
+    let node_id = NodeId::next_numeric(address_space.default_namespace());
+    let display_name = browse_name.name.as_ref().to_string();
+    let (node, reference_type): (NodeType, NodeId) = match address_space.find_node(type_id) {
+        Some(NodeType::ObjectType(object_type)) if !object_type.is_abstract() => {
+            let node = ObjectBuilder::new(&node_id, browse_name, display_name).build();
+            // Objects are organized by a folder, otherwise they are a component of their parent
+            let is_folder = type_definition_of(address_space, parent_id)
+                .map(|parent_type_id| {
+                    address_space.is_subtype(&parent_type_id, &ObjectTypeId::FolderType.into())
+                })
+                .unwrap_or(false);
+            let reference_type = if is_folder {
+                ReferenceTypeId::Organizes
+            } else {
+                ReferenceTypeId::HasComponent
+            };
+            (node.into(), reference_type.into())
+        }
+        Some(NodeType::VariableType(variable_type)) if !variable_type.is_abstract() => {
+            // A variable always has a data type, even if its type leaves it open
+            let data_type = variable_type.data_type();
+            let data_type = if data_type.is_null() {
+                DataTypeId::BaseDataType.into()
+            } else {
+                data_type
+            };
+            let mut builder = VariableBuilder::new(&node_id, browse_name, display_name)
+                .data_type(data_type)
+                .value_rank(variable_type.value_rank());
+            if let Some(array_dimensions) = variable_type.array_dimensions() {
+                builder = builder.array_dimensions(&array_dimensions);
+            }
+            if let Some(value) = variable_type.value().and_then(|v| v.value) {
+                builder = builder.value(value);
+            }
+            let reference_type =
+                if address_space.is_subtype(type_id, &VariableTypeId::PropertyType.into()) {
+                    ReferenceTypeId::HasProperty
+                } else {
+                    ReferenceTypeId::HasComponent
+                };
+            (builder.build().into(), reference_type.into())
+        }
+        _ => return Err(StatusCode::BadTypeDefinitionInvalid),
+    };
+    insert_instance(address_space, node, type_id, parent_id, &reference_type);
+    copy_instance_declarations(
+        address_space,
+        None,
+        type_id,
+        &node_id,
+        optional,
+        &mut Vec::new(),
+    );
+    Ok(node_id)
+}
+
+/// Returns the modelling rule of a node, or `None` if it has none and so is not an instance
+/// declaration.
+pub(crate) fn modelling_rule(
+    address_space: &AddressSpace,
+    node_id: &NodeId,
+) -> Option<ModellingRule> {
+    address_space
+        .find_references(node_id, Some((ReferenceTypeId::HasModellingRule, false)))
+        .unwrap_or_default()
+        .iter()
+        .find_map(|r| ModellingRule::try_from(&r.target_node).ok())
+}
+
+/// Returns the type definition of an object or variable.
+fn type_definition_of(address_space: &AddressSpace, node_id: &NodeId) -> Option<NodeId> {
+    address_space
+        .find_references(node_id, Some((ReferenceTypeId::HasTypeDefinition, false)))
+        .and_then(|references| references.first().map(|r| r.target_node.clone()))
+}
+
+/// Finds the instance declarations of an instance. The declarations are the children of the
+/// instance declaration the instance is a copy of, if there is one, followed by those of its type
+/// definition and each of its supertypes. A declaration hides any that come after it with the
+/// same browse name.
+pub(crate) fn instance_declarations(
+    address_space: &AddressSpace,
+    declaration_id: Option<&NodeId>,
+    type_id: &NodeId,
+) -> Vec<InstanceDeclaration> {
+    let mut sources = declaration_id.into_iter().cloned().collect::<Vec<_>>();
+    sources.push(type_id.clone());
+    sources.extend(address_space.find_supertypes(type_id));
+
+    let mut browse_names = HashSet::new();
+    let mut declarations = Vec::new();
+    sources.iter().for_each(|source_id| {
+        address_space
+            .find_references(source_id, Some((ReferenceTypeId::Aggregates, true)))
+            .unwrap_or_default()
+            .into_iter()
+            .for_each(|r| {
+                let modelling_rule = modelling_rule(address_space, &r.target_node);
+                let node = address_space.find_node(&r.target_node);
+                if let (Some(modelling_rule), Some(node)) = (modelling_rule, node) {
+                    let browse_name = node.as_node().browse_name();
+                    if browse_names.insert(browse_name.clone()) {
+                        declarations.push(InstanceDeclaration {
+                            reference_type: r.reference_type,
+                            node_id: r.target_node,
+                            browse_name,
+                            modelling_rule,
+                        });
+                    }
+                }
+            });
+    });
+    declarations
+}
+
+/// Copies the mandatory instance declarations into the instance, and the declarations within
+/// them. Optional declarations are only copied into the instance itself, if their browse name is
+/// one of the optional ones. Placeholders are never copied, since their instances have browse
+/// names of their own. The path holds the declarations being copied, to stop a declaration from
+/// being copied into itself.
+fn copy_instance_declarations(
+    address_space: &mut AddressSpace,
+    parent_declaration_id: Option<&NodeId>,
+    type_id: &NodeId,
+    instance_id: &NodeId,
+    optional: &[QualifiedName],
+    path: &mut Vec<NodeId>,
+) {
+    for declaration in instance_declarations(address_space, parent_declaration_id, type_id) {
+        let is_copied = match declaration.modelling_rule {
+            ModellingRule::Mandatory => true,
+            ModellingRule::Optional => {
+                path.is_empty() && optional.contains(&declaration.browse_name)
+            }
+            _ => false,
+        };
+        let InstanceDeclaration {
+            reference_type,
+            node_id: declaration_id,
+            ..
+        } = declaration;
+        if !is_copied || path.contains(&declaration_id) {
+            continue;
+        }
+        let copy =
+            copy_instance_declaration(address_space, &declaration_id, instance_id, &reference_type);
+        if let Some((copy_id, type_id)) = copy {
+            path.push(declaration_id.clone());
+            copy_instance_declarations(
+                address_space,
+                Some(&declaration_id),
+                &type_id,
+                &copy_id,
+                &[],
+                path,
+            );
+            path.pop();
+        }
+    }
+}
+
+/// Copies an instance declaration into the instance. Methods are not copied, the instance
+/// references the method of the declaration instead. Returns the node id and type definition of
+/// the copy, or `None` if nothing was copied.
+fn copy_instance_declaration(
+    address_space: &mut AddressSpace,
+    declaration_id: &NodeId,
+    instance_id: &NodeId,
+    reference_type: &NodeId,
+) -> Option<(NodeId, NodeId)> {
+    let node_id = NodeId::next_numeric(instance_id.namespace);
+    let type_id = type_definition_of(address_space, declaration_id);
+    let (node, type_id): (NodeType, NodeId) = match address_space.find_node(declaration_id)? {
+        NodeType::Object(object) => {
+            let mut builder =
+                ObjectBuilder::new(&node_id, object.browse_name(), object.display_name())
+                    .event_notifier(object.event_notifier());
+            if let Some(description) = object.description() {
+                builder = builder.description(description);
+            }
+            let type_id = type_id.unwrap_or_else(|| ObjectTypeId::BaseObjectType.into());
+            (builder.build().into(), type_id)
+        }
+        NodeType::Variable(variable) => {
+            let mut builder =
+                VariableBuilder::new(&node_id, variable.browse_name(), variable.display_name())
+                    .data_type(variable.data_type())
+                    .value_rank(variable.value_rank())
+                    .access_level(variable.access_level())
+                    .user_access_level(variable.user_access_level())
+                    .historizing(variable.historizing());
+            if let Some(description) = variable.description() {
+                builder = builder.description(description);
+            }
+            if let Some(array_dimensions) = variable.array_dimensions() {
+                builder = builder.array_dimensions(&array_dimensions);
+            }
+            if let Some(minimum_sampling_interval) = variable.minimum_sampling_interval() {
+                builder = builder.minimum_sampling_interval(minimum_sampling_interval);
+            }
+            let value = variable.value(
+                TimestampsToReturn::Neither,
+                NumericRange::None,
+                &QualifiedName::null(),
+                0.0,
+            );
+            if let Some(value) = value.value {
+                builder = builder.value(value);
+            }
+            let type_id = type_id.unwrap_or_else(|| VariableTypeId::BaseDataVariableType.into());
+            (builder.build().into(), type_id)
+        }
+        NodeType::Method(_) => {
+            address_space.insert_reference(instance_id, declaration_id, reference_type.clone());
+            return None;
+        }
+        _ => return None,
+    };
+    insert_instance(address_space, node, &type_id, instance_id, reference_type);
+    Some((node_id, type_id))
+}
+
+/// Inserts the instance with a reference to its type definition and from its parent.
+fn insert_instance(
+    address_space: &mut AddressSpace,
+    node: NodeType,
+    type_id: &NodeId,
+    parent_id: &NodeId,
+    reference_type: &NodeId,
+) {
+    let node_id = node.node_id();
+    address_space.insert(
+        node,
+        Some(&[(
+            type_id,
+            &ReferenceTypeId::HasTypeDefinition,
+            ReferenceDirection::Forward,
+        )]),
+    );
+    address_space.insert_reference(parent_id, &node_id, reference_type.clone());
+}
@@ -0,0 +1,163 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`Localization`] store that translates human readable text into the locales
+//! requested by a session.
+//!
+//! [`Localization`]: ./struct.Localization.html
+
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "events")]
+use crate::types::EventFieldList;
+use crate::types::{
+    locale_language as language, LocalizedText, MonitoredItemNotification, UAString, Variant,
+};
+
+/// Holds translations of the human readable text of the address space, such as the
+/// `DisplayName` and `Description` attributes of nodes, the values of `EnumStrings` properties
+/// and the messages of events.
+///
+/// A translation is keyed by the text it translates, so the same text is translated the same way
+/// wherever it appears. Text is returned in the first of the session's locale ids, passed in
+/// ActivateSession, that the store can satisfy. For each locale id the rules are:
+///
+/// 1. A text already in the locale, or a translation to it, is used
+/// 2. Otherwise a text or translation in the language of the locale, e.g. `de` or `de-AT` for
+///    `de-CH`, is used
+///
+/// If no locale id can be satisfied the text is returned as it is. Text without a locale is taken
+/// to be in the default locale, which is the first locale id of the server configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Localization {
+    /// The locale of text that has no locale
+    default_locale: String,
+    /// Translations keyed by the text they translate, then by their locale id
+    translations: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl Localization {
+    pub fn new() -> Localization {
+        Localization::default()
+    }
+
+    /// Returns true if there are no translations.
+    pub fn is_empty(&self) -> bool {
+        self.translations.is_empty()
+    }
+
+    /// Returns the locale of text that has no locale.
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Sets the locale of text that has no locale.
+    pub fn set_default_locale<T>(&mut self, default_locale: T)
+    where
+        T: Into<String>,
+    {
+        self.default_locale = default_locale.into();
+    }
+
+    /// Adds a translation of the text into the locale, replacing any previous translation.
+    pub fn add_translation<S, L, T>(&mut self, text: S, locale: L, translation: T)
+    where
+        S: Into<String>,
+        L: Into<String>,
+        T: Into<String>,
+    {
+        self.translations
+            .entry(text.into())
+            .or_default()
+            .insert(locale.into(), translation.into());
+    }
+
+    /// Adds the translations of the text into several locales, each a pair of locale id and
+    /// translation.
+    pub fn add_translations<S, L, T>(&mut self, text: S, translations: Vec<(L, T)>)
+    where
+        S: Into<String>,
+        L: Into<String>,
+        T: Into<String>,
+    {
+        let text = text.into();
+        translations.into_iter().for_each(|(locale, translation)| {
+            self.add_translation(text.clone(), locale, translation)
+        });
+    }
+
+    /// Returns the text translated into the preferred locale that the store can satisfy, or
+    /// `None` if the text should be returned as it is.
+    pub fn localize(&self, text: &LocalizedText, locale_ids: &[UAString]) -> Option<LocalizedText> {
+        let translations = self.translations.get(text.text.as_ref())?;
+        let text_locale = if text.locale.is_empty() {
+            self.default_locale.as_str()
+        } else {
+            text.locale.as_ref()
+        };
+        for locale_id in locale_ids.iter().filter(|l| !l.is_empty()) {
+            let locale_id = locale_id.as_ref();
+            if text_locale.eq_ignore_ascii_case(locale_id) {
+                return None;
+            }
+            if let Some((locale, translation)) = translations
+                .iter()
+                .find(|(locale, _)| locale.eq_ignore_ascii_case(locale_id))
+            {
+                return Some(LocalizedText::new(locale, translation));
+            }
+            let locale_language = language(locale_id);
+            if language(text_locale).eq_ignore_ascii_case(locale_language) {
+                return None;
+            }
+            if let Some((locale, translation)) = translations
+                .iter()
+                .find(|(locale, _)| language(locale).eq_ignore_ascii_case(locale_language))
+            {
+                return Some(LocalizedText::new(locale, translation));
+            }
+        }
+        None
+    }
+
+    /// Localizes a value in place if it is a localized text or an array of them.
+    pub fn localize_variant(&self, value: &mut Variant, locale_ids: &[UAString]) {
+        if self.is_empty() || locale_ids.is_empty() {
+            return;
+        }
+        match value {
+            Variant::LocalizedText(text) => {
+                if let Some(localized) = self.localize(text, locale_ids) {
+                    **text = localized;
+                }
+            }
+            Variant::Array(array) => array
+                .values
+                .iter_mut()
+                .for_each(|value| self.localize_variant(value, locale_ids)),
+            _ => {}
+        }
+    }
+
+    /// Localizes the value of a data change notification.
+    pub(crate) fn localize_data_change(
+        &self,
+        notification: &mut MonitoredItemNotification,
+        locale_ids: &[UAString],
+    ) {
+        if let Some(ref mut value) = notification.value.value {
+            self.localize_variant(value, locale_ids);
+        }
+    }
+
+    /// Localizes the fields of an event notification, e.g. its message.
+    #[cfg(feature = "events")]
+    pub(crate) fn localize_event(&self, event: &mut EventFieldList, locale_ids: &[UAString]) {
+        if let Some(ref mut event_fields) = event.event_fields {
+            event_fields
+                .iter_mut()
+                .for_each(|value| self.localize_variant(value, locale_ids));
+        }
+    }
+}
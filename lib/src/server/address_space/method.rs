@@ -37,6 +37,18 @@ impl MethodBuilder {
         self
     }
 
+    /// Add a reference to the node indicating it has a modelling rule of another node.
+    pub fn has_modelling_rule<T>(self, type_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.reference(
+            type_id,
+            ReferenceTypeId::HasModellingRule,
+            ReferenceDirection::Forward,
+        )
+    }
+
     pub fn callback(mut self, callback: MethodCallback) -> Self {
         self.node.set_callback(callback);
         self
@@ -0,0 +1,174 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Parsing of the calls to methods that are made by the address space itself, as well as the
+//! argument helpers that are shared with the method handlers.
+
+use crate::types::{service_types::CallMethodRequest, *};
+
+#[cfg(feature = "events")]
+use crate::server::events::alarm::ShelvedState;
+
+/// Count the number of provided input arguments, comparing them to the expected number.
+#[cfg(any(feature = "events", feature = "generated-address-space"))]
+pub(super) fn ensure_input_argument_count(
+    request: &CallMethodRequest,
+    expected: usize,
+) -> Result<(), StatusCode> {
+    if let Some(ref input_arguments) = request.input_arguments {
+        let actual = input_arguments.len();
+        if actual == expected {
+            Ok(())
+        } else if actual < expected {
+            debug!("Method call fails BadArgumentsMissing");
+            Err(StatusCode::BadArgumentsMissing)
+        } else {
+            debug!("Method call fails BadTooManyArguments");
+            Err(StatusCode::BadTooManyArguments)
+        }
+    } else if expected == 0 {
+        Ok(())
+    } else {
+        debug!("Method call fails BadArgumentsMissing");
+        Err(StatusCode::BadArgumentsMissing)
+    }
+}
+
+/// Gets the input argument value, expecting it to the specified variant type. If it fails,
+/// it returns an error
+#[cfg(any(feature = "events", feature = "generated-address-space"))]
+macro_rules! get_input_argument {
+    ( $request:expr, $index: expr, $variant_type: ident ) => {{
+        let input_arguments = $request.input_arguments.as_ref().unwrap();
+        let arg = input_arguments.get($index).unwrap();
+        if let Variant::$variant_type(value) = arg {
+            Ok(value)
+        } else {
+            // Argument is not the expected type
+            Err(StatusCode::BadInvalidArgument)
+        }
+    }};
+}
+
+/// A call to a method that changes the state of an alarm. The address space makes these calls
+/// rather than a handler.
+#[cfg(feature = "events")]
+pub(crate) enum AlarmMethodCall {
+    Suppress(bool),
+    Shelve(ShelvedState),
+}
+
+/// Gets the call to a method of an alarm, or of its `ShelvingState`, from the request. Returns
+/// `None` if the method is not one of them.
+#[cfg(feature = "events")]
+pub(crate) fn alarm_method_call(
+    request: &CallMethodRequest,
+) -> Option<Result<AlarmMethodCall, StatusCode>> {
+    // OPC UA part 9 - Suppress(); Unsuppress();
+    //
+    // Return codes
+    //
+    // BadUserAccessDenied
+    //
+    // OPC UA part 9 - Unshelve(); OneShotShelve(); TimedShelve([in] Duration shelvingTime);
+    //
+    // shelvingTime - Time in milliseconds that the alarm is shelved for
+    //
+    // Return codes
+    //
+    // BadConditionNotShelved
+    // BadConditionAlreadyShelved
+    // BadShelvingTimeOutOfRange
+    // BadUserAccessDenied
+
+    // The methods of the shelving state are called by the ids of the type or of the alarm type
+    let is_method = |method_ids: &[MethodId]| {
+        method_ids
+            .iter()
+            .any(|method_id| request.method_id == (*method_id).into())
+    };
+    if is_method(&[MethodId::AlarmConditionType_Suppress]) {
+        Some(ensure_input_argument_count(request, 0).map(|_| AlarmMethodCall::Suppress(true)))
+    } else if is_method(&[MethodId::AlarmConditionType_Unsuppress]) {
+        Some(ensure_input_argument_count(request, 0).map(|_| AlarmMethodCall::Suppress(false)))
+    } else if is_method(&[
+        MethodId::ShelvedStateMachineType_Unshelve,
+        MethodId::AlarmConditionType_ShelvingState_Unshelve,
+    ]) {
+        Some(
+            ensure_input_argument_count(request, 0)
+                .map(|_| AlarmMethodCall::Shelve(ShelvedState::Unshelved)),
+        )
+    } else if is_method(&[
+        MethodId::ShelvedStateMachineType_OneShotShelve,
+        MethodId::AlarmConditionType_ShelvingState_OneShotShelve,
+    ]) {
+        Some(
+            ensure_input_argument_count(request, 0)
+                .map(|_| AlarmMethodCall::Shelve(ShelvedState::OneShotShelved)),
+        )
+    } else if is_method(&[
+        MethodId::ShelvedStateMachineType_TimedShelve,
+        MethodId::AlarmConditionType_ShelvingState_TimedShelve,
+    ]) {
+        Some(
+            ensure_input_argument_count(request, 1)
+                .and_then(|_| get_input_argument!(request, 0, Double))
+                .map(|shelving_time| {
+                    AlarmMethodCall::Shelve(ShelvedState::TimedShelved(*shelving_time))
+                }),
+        )
+    } else {
+        None
+    }
+}
+
+/// A call to a method of a program state machine. The address space makes these calls rather
+/// than a handler, and passes them on to the program.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ProgramMethodCall {
+    Start,
+    Suspend,
+    Resume,
+    Halt,
+    Reset,
+}
+
+/// Gets the call to a method of a program state machine from the request. Returns `None` if the
+/// method is not one of them.
+pub(crate) fn program_method_call(request: &CallMethodRequest) -> Option<ProgramMethodCall> {
+    // OPC UA part 10 - Start(); Suspend(); Resume(); Halt(); Reset();
+    //
+    // The input arguments of the methods are defined by the program
+    //
+    // Return codes
+    //
+    // BadInvalidState
+    // BadUserAccessDenied
+    [
+        (
+            MethodId::ProgramStateMachineType_Start,
+            ProgramMethodCall::Start,
+        ),
+        (
+            MethodId::ProgramStateMachineType_Suspend,
+            ProgramMethodCall::Suspend,
+        ),
+        (
+            MethodId::ProgramStateMachineType_Resume,
+            ProgramMethodCall::Resume,
+        ),
+        (
+            MethodId::ProgramStateMachineType_Halt,
+            ProgramMethodCall::Halt,
+        ),
+        (
+            MethodId::ProgramStateMachineType_Reset,
+            ProgramMethodCall::Reset,
+        ),
+    ]
+    .iter()
+    .find(|(method_id, _)| request.method_id == (*method_id).into())
+    .map(|(_, method_call)| *method_call)
+}
@@ -13,44 +13,7 @@ use crate::types::{
 
 use crate::server::{callbacks::Method, session::SessionManager};
 
-/// Count the number of provided input arguments, comparing them to the expected number.
-fn ensure_input_argument_count(
-    request: &CallMethodRequest,
-    expected: usize,
-) -> Result<(), StatusCode> {
-    if let Some(ref input_arguments) = request.input_arguments {
-        let actual = input_arguments.len();
-        if actual == expected {
-            Ok(())
-        } else if actual < expected {
-            debug!("Method call fails BadArgumentsMissing");
-            Err(StatusCode::BadArgumentsMissing)
-        } else {
-            debug!("Method call fails BadTooManyArguments");
-            Err(StatusCode::BadTooManyArguments)
-        }
-    } else if expected == 0 {
-        Ok(())
-    } else {
-        debug!("Method call fails BadArgumentsMissing");
-        Err(StatusCode::BadArgumentsMissing)
-    }
-}
-
-/// Gets the input argument value, expecting it to the specified variant type. If it fails,
-/// it returns an error
-macro_rules! get_input_argument {
-    ( $request:expr, $index: expr, $variant_type: ident ) => {{
-        let input_arguments = $request.input_arguments.as_ref().unwrap();
-        let arg = input_arguments.get($index).unwrap();
-        if let Variant::$variant_type(value) = arg {
-            Ok(value)
-        } else {
-            // Argument is not the expected type
-            Err(StatusCode::BadInvalidArgument)
-        }
-    }};
-}
+use super::method_calls::ensure_input_argument_count;
 
 /// Search all sessions in the session map except the specified one for a matching subscription id
 fn subscription_exists_on_other_session(
@@ -181,3 +144,183 @@ impl Method for ServerGetMonitoredItemsMethod {
         }
     }
 }
+
+/// This is the handler for the Server.SetSubscriptionDurable method call.
+pub struct ServerSetSubscriptionDurableMethod {
+    /// The longest lifetime in hours that a durable subscription may have, 0 if subscriptions
+    /// cannot be made durable
+    pub max_lifetime_in_hours: u32,
+}
+
+impl Method for ServerSetSubscriptionDurableMethod {
+    fn call(
+        &mut self,
+        session_id: &NodeId,
+        session_manager: Arc<RwLock<SessionManager>>,
+        request: &CallMethodRequest,
+    ) -> Result<CallMethodResult, StatusCode> {
+        debug!("Method handler for SetSubscriptionDurable");
+
+        // OPC UA part 5 - SetSubscriptionDurable([in] UInt32 subscriptionId, [in] UInt32 lifetimeInHours, [out] UInt32 revisedLifetimeInHours);
+        //
+        // subscriptionId - Identifier of the subscription
+        // lifetimeInHours - The requested lifetime in hours of the durable subscription
+        // revisedLifetimeInHours - The lifetime in hours that the server gave the subscription
+        //
+        // Return codes
+        //
+        // BadSubscriptionIdInvalid
+        // BadInvalidState - The subscription already has monitored items
+        // BadUserAccessDenied
+
+        ensure_input_argument_count(request, 2)?;
+
+        let subscription_id = get_input_argument!(request, 0, UInt32)?;
+        let lifetime_in_hours = get_input_argument!(request, 1, UInt32)?;
+
+        if self.max_lifetime_in_hours == 0 {
+            debug!("Method handler for SetSubscriptionDurable returns BadNotSupported");
+            return Err(StatusCode::BadNotSupported);
+        }
+        // The server chooses the lifetime if the client asks for none
+        let revised_lifetime_in_hours =
+            if *lifetime_in_hours == 0 || *lifetime_in_hours > self.max_lifetime_in_hours {
+                self.max_lifetime_in_hours
+            } else {
+                *lifetime_in_hours
+            };
+
+        {
+            let session_manager = trace_read_lock!(session_manager);
+            if let Some(session) = session_manager.find_session_by_id(session_id) {
+                let mut session = trace_write_lock!(session);
+                if let Some(subscription) = session.subscriptions_mut().get_mut(*subscription_id) {
+                    subscription.set_durable(revised_lifetime_in_hours)?;
+                    return Ok(CallMethodResult {
+                        status_code: StatusCode::Good,
+                        input_argument_results: Some(vec![StatusCode::Good, StatusCode::Good]),
+                        input_argument_diagnostic_infos: None,
+                        output_arguments: Some(vec![Variant::from(revised_lifetime_in_hours)]),
+                    });
+                };
+            } else {
+                return Err(StatusCode::BadSessionIdInvalid);
+            }
+        }
+
+        if subscription_exists_on_other_session(session_id, session_manager, *subscription_id) {
+            Err(StatusCode::BadUserAccessDenied)
+        } else {
+            Err(StatusCode::BadSubscriptionIdInvalid)
+        }
+    }
+}
+
+/// Refreshes the retained conditions of a subscription on the session supplied, or of a single
+/// monitored item of it
+#[cfg(feature = "events")]
+fn condition_refresh(
+    session_id: &NodeId,
+    session_manager: Arc<RwLock<SessionManager>>,
+    subscription_id: u32,
+    monitored_item_id: Option<u32>,
+) -> Result<CallMethodResult, StatusCode> {
+    {
+        let session_manager = trace_read_lock!(session_manager);
+        if let Some(session) = session_manager.find_session_by_id(session_id) {
+            let mut session = trace_write_lock!(session);
+            if let Some(subscription) = session.subscriptions_mut().get_mut(subscription_id) {
+                subscription.set_condition_refresh(monitored_item_id)?;
+                let input_argument_count = if monitored_item_id.is_some() { 2 } else { 1 };
+                return Ok(CallMethodResult {
+                    status_code: StatusCode::Good,
+                    input_argument_results: Some(vec![StatusCode::Good; input_argument_count]),
+                    input_argument_diagnostic_infos: None,
+                    output_arguments: None,
+                });
+            };
+        } else {
+            return Err(StatusCode::BadSessionIdInvalid);
+        }
+    }
+
+    if subscription_exists_on_other_session(session_id, session_manager, subscription_id) {
+        Err(StatusCode::BadUserAccessDenied)
+    } else {
+        Err(StatusCode::BadSubscriptionIdInvalid)
+    }
+}
+
+/// This is the handler for the ConditionType.ConditionRefresh method call.
+#[cfg(feature = "events")]
+pub struct ConditionRefreshMethod;
+
+#[cfg(feature = "events")]
+impl Method for ConditionRefreshMethod {
+    fn call(
+        &mut self,
+        session_id: &NodeId,
+        session_manager: Arc<RwLock<SessionManager>>,
+        request: &CallMethodRequest,
+    ) -> Result<CallMethodResult, StatusCode> {
+        debug!("Method handler for ConditionRefresh");
+
+        // OPC UA part 9 - ConditionRefresh([in] IntegerId subscriptionId);
+        //
+        // subscriptionId - Identifier of the subscription whose monitored items for events get
+        //                  their retained conditions replayed
+        //
+        // Return codes
+        //
+        // BadSubscriptionIdInvalid
+        // BadRefreshInProgress
+        // BadUserAccessDenied
+
+        ensure_input_argument_count(request, 1)?;
+
+        let subscription_id = get_input_argument!(request, 0, UInt32)?;
+
+        condition_refresh(session_id, session_manager, *subscription_id, None)
+    }
+}
+
+/// This is the handler for the ConditionType.ConditionRefresh2 method call.
+#[cfg(feature = "events")]
+pub struct ConditionRefresh2Method;
+
+#[cfg(feature = "events")]
+impl Method for ConditionRefresh2Method {
+    fn call(
+        &mut self,
+        session_id: &NodeId,
+        session_manager: Arc<RwLock<SessionManager>>,
+        request: &CallMethodRequest,
+    ) -> Result<CallMethodResult, StatusCode> {
+        debug!("Method handler for ConditionRefresh2");
+
+        // OPC UA part 9 - ConditionRefresh2([in] IntegerId subscriptionId, [in] IntegerId monitoredItemId);
+        //
+        // subscriptionId - Identifier of the subscription that holds the monitored item
+        // monitoredItemId - Identifier of the monitored item for events that gets its retained
+        //                   conditions replayed
+        //
+        // Return codes
+        //
+        // BadSubscriptionIdInvalid
+        // BadMonitoredItemIdInvalid
+        // BadRefreshInProgress
+        // BadUserAccessDenied
+
+        ensure_input_argument_count(request, 2)?;
+
+        let subscription_id = get_input_argument!(request, 0, UInt32)?;
+        let monitored_item_id = get_input_argument!(request, 1, UInt32)?;
+
+        condition_refresh(
+            session_id,
+            session_manager,
+            *subscription_id,
+            Some(*monitored_item_id),
+        )
+    }
+}
@@ -13,7 +13,7 @@ use crate::types::{
     AttributeId, DataValue, NodeId, NumericRange, QualifiedName, TimestampsToReturn,
 };
 
-use super::callbacks::{AttributeGetter, AttributeSetter};
+use super::callbacks::{AttributeGetter, AttributeSetter, ValueSource};
 
 pub use self::address_space::AddressSpace;
 
@@ -31,6 +31,7 @@ where
         + Send,
 {
     getter: F,
+    value_source: ValueSource,
 }
 
 impl<F> AttributeGetter for AttrFnGetter<F>
@@ -63,6 +64,10 @@ where
             max_age,
         )
     }
+
+    fn value_source(&self) -> ValueSource {
+        self.value_source
+    }
 }
 
 impl<F> AttrFnGetter<F>
@@ -78,12 +83,28 @@ where
         + Send,
 {
     pub fn new(getter: F) -> AttrFnGetter<F> {
-        AttrFnGetter { getter }
+        AttrFnGetter {
+            getter,
+            value_source: ValueSource::Poll,
+        }
     }
 
     pub fn new_boxed(getter: F) -> Arc<Mutex<AttrFnGetter<F>>> {
         Arc::new(Mutex::new(Self::new(getter)))
     }
+
+    /// Creates a getter whose value is pushed by the application, so monitored items never call
+    /// it. See `ValueSource::Push`.
+    pub fn new_push(getter: F) -> AttrFnGetter<F> {
+        AttrFnGetter {
+            getter,
+            value_source: ValueSource::Push,
+        }
+    }
+
+    pub fn new_push_boxed(getter: F) -> Arc<Mutex<AttrFnGetter<F>>> {
+        Arc::new(Mutex::new(Self::new_push(getter)))
+    }
 }
 
 /// An implementation of attribute setter that can be easily constructed using a mutable function
@@ -193,6 +214,37 @@ macro_rules! node_builder_impl {
                 self
             }
 
+            /// Sets the write mask of the node, which says which of its attributes may be written
+            pub fn write_mask(mut self, write_mask: WriteMask) -> Self {
+                self.node.set_write_mask(write_mask);
+                self
+            }
+
+            /// Sets the user write mask of the node, which says which of its attributes the users
+            /// of the server may write
+            pub fn user_write_mask(mut self, user_write_mask: WriteMask) -> Self {
+                self.node.set_user_write_mask(user_write_mask);
+                self
+            }
+
+            /// Sets the permissions that roles have on the node
+            pub fn role_permissions(
+                mut self,
+                role_permissions: Vec<$crate::types::service_types::RolePermissionType>,
+            ) -> Self {
+                self.node.set_role_permissions(role_permissions);
+                self
+            }
+
+            /// Sets the restrictions on access to the node
+            pub fn access_restrictions(
+                mut self,
+                access_restrictions: $crate::types::service_types::AccessRestrictionType,
+            ) -> Self {
+                self.node.set_access_restrictions(access_restrictions);
+                self
+            }
+
             /// Adds a reference to the node
             pub fn reference<T>(
                 mut self,
@@ -434,20 +486,64 @@ macro_rules! node_base_impl {
             fn set_user_write_mask(&mut self, user_write_mask: WriteMask) {
                 self.base.set_user_write_mask(user_write_mask)
             }
+
+            fn role_permissions(
+                &self,
+            ) -> Option<Vec<crate::types::service_types::RolePermissionType>> {
+                self.base.role_permissions()
+            }
+
+            fn set_role_permissions(
+                &mut self,
+                role_permissions: Vec<crate::types::service_types::RolePermissionType>,
+            ) {
+                self.base.set_role_permissions(role_permissions)
+            }
+
+            fn user_role_permissions(
+                &self,
+            ) -> Option<Vec<crate::types::service_types::RolePermissionType>> {
+                self.base.user_role_permissions()
+            }
+
+            fn set_user_role_permissions(
+                &mut self,
+                user_role_permissions: Vec<crate::types::service_types::RolePermissionType>,
+            ) {
+                self.base.set_user_role_permissions(user_role_permissions)
+            }
+
+            fn access_restrictions(
+                &self,
+            ) -> Option<crate::types::service_types::AccessRestrictionType> {
+                self.base.access_restrictions()
+            }
+
+            fn set_access_restrictions(
+                &mut self,
+                access_restrictions: crate::types::service_types::AccessRestrictionType,
+            ) {
+                self.base.set_access_restrictions(access_restrictions)
+            }
         }
     };
 }
 
 pub mod address_space;
 pub mod base;
+pub mod data_access;
 pub mod data_type;
+mod instantiate;
+pub mod localization;
 pub mod method;
+pub mod modelling_rule;
 pub mod node;
 pub mod object;
 pub mod object_type;
 pub mod reference_type;
 pub mod references;
 pub mod relative_path;
+pub mod state_machine;
 pub mod variable;
 pub mod variable_type;
 pub mod view;
@@ -455,6 +551,8 @@ pub mod view;
 #[rustfmt::skip]
 #[cfg(feature = "generated-address-space")]
 mod generated;
+#[macro_use]
+mod method_calls;
 #[cfg(feature = "generated-address-space")]
 mod method_impls;
 
@@ -493,13 +591,20 @@ bitflags! {
 
 pub mod types {
     pub use super::address_space::AddressSpace;
-    pub use super::data_type::{DataType, DataTypeBuilder};
+    pub use super::data_access::{
+        AnalogItemBuilder, MultiStateDiscreteBuilder, MultiStateValueDiscreteBuilder,
+        TwoStateDiscreteBuilder,
+    };
+    pub use super::data_type::{DataType, DataTypeBuilder, DataTypeDefinition};
+    pub use super::localization::Localization;
     pub use super::method::{Method, MethodBuilder};
+    pub use super::modelling_rule::ModellingRule;
     pub use super::node::{NodeBase, NodeType};
     pub use super::object::{Object, ObjectBuilder};
     pub use super::object_type::{ObjectType, ObjectTypeBuilder};
     pub use super::reference_type::{ReferenceType, ReferenceTypeBuilder};
     pub use super::references::ReferenceDirection;
+    pub use super::state_machine::{FiniteStateMachineBuilder, ProgramStateMachineBuilder};
     pub use super::variable::{Variable, VariableBuilder};
     pub use super::variable_type::{VariableType, VariableTypeBuilder};
     pub use super::view::{View, ViewBuilder};
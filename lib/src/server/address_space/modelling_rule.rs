@@ -0,0 +1,78 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains `ModellingRule`, the rule that makes a node of a type an instance declaration.
+
+use std::convert::TryFrom;
+
+use crate::types::{NodeId, ObjectId};
+
+/// The modelling rule of an instance declaration, i.e. a component or property of a type that
+/// says what instances of the type hold. Nodes of instances have no modelling rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModellingRule {
+    /// Each instance has a copy of the declaration.
+    Mandatory,
+    /// An instance may have a copy of the declaration.
+    Optional,
+    /// An instance has one or more nodes in place of the declaration, with their own browse names.
+    MandatoryPlaceholder,
+    /// An instance has any number of nodes in place of the declaration, with their own browse
+    /// names.
+    OptionalPlaceholder,
+    /// The declaration describes each element of the array value of its parent variable.
+    ExposesItsArray,
+}
+
+impl ModellingRule {
+    /// Tests if each instance has a copy of the declaration.
+    pub fn is_mandatory(&self) -> bool {
+        matches!(self, ModellingRule::Mandatory)
+    }
+
+    /// Tests if the declaration stands for nodes with their own browse names.
+    pub fn is_placeholder(&self) -> bool {
+        matches!(
+            self,
+            ModellingRule::MandatoryPlaceholder | ModellingRule::OptionalPlaceholder
+        )
+    }
+}
+
+impl From<ModellingRule> for ObjectId {
+    fn from(modelling_rule: ModellingRule) -> Self {
+        match modelling_rule {
+            ModellingRule::Mandatory => ObjectId::ModellingRule_Mandatory,
+            ModellingRule::Optional => ObjectId::ModellingRule_Optional,
+            ModellingRule::MandatoryPlaceholder => ObjectId::ModellingRule_MandatoryPlaceholder,
+            ModellingRule::OptionalPlaceholder => ObjectId::ModellingRule_OptionalPlaceholder,
+            ModellingRule::ExposesItsArray => ObjectId::ModellingRule_ExposesItsArray,
+        }
+    }
+}
+
+impl From<ModellingRule> for NodeId {
+    fn from(modelling_rule: ModellingRule) -> Self {
+        ObjectId::from(modelling_rule).into()
+    }
+}
+
+impl TryFrom<&NodeId> for ModellingRule {
+    type Error = ();
+
+    fn try_from(node_id: &NodeId) -> Result<Self, ()> {
+        match node_id.as_object_id() {
+            Ok(ObjectId::ModellingRule_Mandatory) => Ok(ModellingRule::Mandatory),
+            Ok(ObjectId::ModellingRule_Optional) => Ok(ModellingRule::Optional),
+            Ok(ObjectId::ModellingRule_MandatoryPlaceholder) => {
+                Ok(ModellingRule::MandatoryPlaceholder)
+            }
+            Ok(ObjectId::ModellingRule_OptionalPlaceholder) => {
+                Ok(ModellingRule::OptionalPlaceholder)
+            }
+            Ok(ObjectId::ModellingRule_ExposesItsArray) => Ok(ModellingRule::ExposesItsArray),
+            _ => Err(()),
+        }
+    }
+}
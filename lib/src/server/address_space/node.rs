@@ -3,8 +3,10 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 use crate::types::{
-    service_types::NodeClass, status_code::StatusCode, AttributeId, DataValue, LocalizedText,
-    NodeId, NumericRange, QualifiedName, TimestampsToReturn, Variant, WriteMask,
+    service_types::{AccessRestrictionType, NodeClass, RolePermissionType},
+    status_code::StatusCode,
+    AttributeId, DataValue, LocalizedText, NodeId, NumericRange, QualifiedName, TimestampsToReturn,
+    Variant, WriteMask,
 };
 
 use super::types::{
@@ -105,6 +107,21 @@ pub trait NodeBase {
     fn user_write_mask(&self) -> Option<WriteMask>;
 
     fn set_user_write_mask(&mut self, write_mask: WriteMask);
+
+    /// Returns the permissions that roles have on the node
+    fn role_permissions(&self) -> Option<Vec<RolePermissionType>>;
+
+    fn set_role_permissions(&mut self, role_permissions: Vec<RolePermissionType>);
+
+    /// Returns the permissions that the roles of the current user have on the node
+    fn user_role_permissions(&self) -> Option<Vec<RolePermissionType>>;
+
+    fn set_user_role_permissions(&mut self, user_role_permissions: Vec<RolePermissionType>);
+
+    /// Returns the restrictions on access to the node, e.g. that it requires encryption
+    fn access_restrictions(&self) -> Option<AccessRestrictionType>;
+
+    fn set_access_restrictions(&mut self, access_restrictions: AccessRestrictionType);
 }
 
 /// Implemented by each node type's to provide a generic way to set or get attributes, e.g.
@@ -33,6 +33,18 @@ impl ObjectBuilder {
         )
     }
 
+    /// Add a reference to the node indicating it has a modelling rule of another node.
+    pub fn has_modelling_rule<T>(self, type_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.reference(
+            type_id,
+            ReferenceTypeId::HasModellingRule,
+            ReferenceDirection::Forward,
+        )
+    }
+
     pub fn has_event_source<T>(self, source_id: T) -> Self
     where
         T: Into<NodeId>,
@@ -40,6 +40,10 @@ pub struct References {
     /// by. It is not the same as an inverse reference. A node may be referenced one or more
     /// times by the other node.
     referenced_by_map: HashMap<NodeId, HashSet<NodeId>>,
+    /// The supertype of each type, i.e. the source of the `HasSubtype` reference to it. A type
+    /// has at most one supertype so the type hierarchy can be tested by walking up this map
+    /// instead of searching down through the references of every subtype.
+    supertypes: HashMap<NodeId, NodeId>,
 }
 
 impl Default for References {
@@ -47,10 +51,16 @@ impl Default for References {
         Self {
             references_map: HashMap::with_capacity(2000),
             referenced_by_map: HashMap::with_capacity(2000),
+            supertypes: HashMap::with_capacity(1000),
         }
     }
 }
 
+fn is_has_subtype(reference_type: &NodeId) -> bool {
+    reference_type.namespace == 0
+        && reference_type.identifier == Identifier::Numeric(ReferenceTypeId::HasSubtype as u32)
+}
+
 impl References {
     /// Inserts a single reference into the map.
     pub fn insert<T>(
@@ -135,6 +145,10 @@ impl References {
         }
 
         let reference_type: NodeId = reference_type.clone().into();
+        if is_has_subtype(&reference_type) {
+            self.supertypes
+                .insert(target_node.clone(), source_node.clone());
+        }
         let reference = Reference::new(reference_type, target_node.clone());
 
         if let Some(ref mut references) = self.references_map.get_mut(source_node) {
@@ -178,13 +192,24 @@ impl References {
     ) {
         nodes_to_check.into_iter().for_each(|node_to_check| {
             // Removes any references that refer from the node to check back to the node to remove
+            let mut removed_subtype = false;
             let remove_entry =
                 if let Some(ref mut references) = self.references_map.get_mut(&node_to_check) {
-                    references.retain(|r| r.target_node != *node_to_remove);
+                    references.retain(|r| {
+                        if r.target_node == *node_to_remove {
+                            removed_subtype |= is_has_subtype(&r.reference_type);
+                            false
+                        } else {
+                            true
+                        }
+                    });
                     references.is_empty()
                 } else {
                     false
                 };
+            if removed_subtype {
+                self.remove_supertype(node_to_remove, &node_to_check);
+            }
             if remove_entry {
                 self.references_map.remove(&node_to_check);
             }
@@ -256,10 +281,20 @@ impl References {
         if remove_entry {
             self.references_map.remove(source_node);
         }
+        if deleted && is_has_subtype(&reference_type) {
+            self.remove_supertype(target_node, source_node);
+        }
 
         deleted
     }
 
+    /// Forgets the supertype of a type if it is the one given.
+    fn remove_supertype(&mut self, subtype: &NodeId, supertype: &NodeId) {
+        if self.supertypes.get(subtype) == Some(supertype) {
+            self.supertypes.remove(subtype);
+        }
+    }
+
     /// Deletes all references to the node.
     pub fn delete_node_references(&mut self, source_node: &NodeId) -> bool {
         let deleted_references = if let Some(references) = self.references_map.remove(source_node) {
@@ -268,6 +303,10 @@ impl References {
                 .iter()
                 .map(|r| r.target_node.clone())
                 .collect::<HashSet<NodeId>>();
+            references
+                .iter()
+                .filter(|r| is_has_subtype(&r.reference_type))
+                .for_each(|r| self.remove_supertype(&r.target_node, source_node));
             self.remove_node_from_referenced_nodes(nodes_referenced, source_node);
             true
         } else {
@@ -457,37 +496,56 @@ impl References {
         if ref_type == ref_subtype {
             true
         } else if include_subtypes {
-            let has_subtype: NodeId = ReferenceTypeId::HasSubtype.into();
-
-            let mut stack = Vec::with_capacity(20);
-            stack.push(ref_type.clone());
-
-            // Search every type and subtype until exhausted
-            let mut found = false;
-            while let Some(current) = stack.pop() {
-                // Get all references to subtypes
-                if *ref_subtype == current {
-                    found = true;
-                    break;
-                } else if let Some(references) = self.references_map.get(&current) {
-                    let mut subtypes = references
-                        .iter()
-                        .filter(|r| r.reference_type == has_subtype)
-                        .map(|r| r.target_node.clone())
-                        .collect::<Vec<NodeId>>();
-                    if subtypes.contains(ref_subtype) {
-                        found = true;
-                        break;
-                    }
-                    stack.append(&mut subtypes);
-                }
-            }
-            found
+            self.is_subtype_of(ref_subtype, ref_type)
         } else {
             false
         }
     }
 
+    /// Tests if a type is a subtype of a base type by walking up the supertypes of the type. A
+    /// type positively matches itself.
+    pub fn is_subtype_of(&self, subtype_id: &NodeId, base_type_id: &NodeId) -> bool {
+        subtype_id == base_type_id || self.supertypes_of(subtype_id).any(|t| t == base_type_id)
+    }
+
+    /// Finds the supertypes of a type, starting with its direct supertype and ending with the
+    /// root of its type hierarchy.
+    pub fn find_supertypes(&self, type_id: &NodeId) -> Vec<NodeId> {
+        self.supertypes_of(type_id).cloned().collect()
+    }
+
+    /// Finds every subtype of a type, direct or indirect, not including the type itself.
+    pub fn find_subtypes(&self, type_id: &NodeId) -> Vec<NodeId> {
+        let mut subtypes = Vec::new();
+        let mut stack = vec![type_id.clone()];
+        while let Some(current) = stack.pop() {
+            if let Some(references) = self.references_map.get(&current) {
+                references
+                    .iter()
+                    .filter(|r| is_has_subtype(&r.reference_type))
+                    .for_each(|r| {
+                        if r.target_node != *type_id && !subtypes.contains(&r.target_node) {
+                            subtypes.push(r.target_node.clone());
+                            stack.push(r.target_node.clone());
+                        }
+                    });
+            }
+        }
+        subtypes
+    }
+
+    /// Iterates the supertypes of a type. The hierarchy should not have loops but the iteration
+    /// is bounded in case a bad nodeset introduced one.
+    fn supertypes_of<'a>(&'a self, type_id: &'a NodeId) -> impl Iterator<Item = &'a NodeId> {
+        let mut current = type_id;
+        std::iter::from_fn(move || {
+            let supertype = self.supertypes.get(current)?;
+            current = supertype;
+            Some(supertype)
+        })
+        .take(self.supertypes.len())
+    }
+
     pub fn get_type_id(&self, node: &NodeId) -> Option<NodeId> {
         if let Some(references) = self.references_map.get(node) {
             let has_type_definition_id = ReferenceTypeId::HasTypeDefinition.into();
@@ -8,7 +8,7 @@ use crate::types::{
     node_id::NodeId,
     service_types::{RelativePath, RelativePathElement},
     status_code::StatusCode,
-    QualifiedName,
+    DateTime, QualifiedName, Variant,
 };
 
 use super::{node::NodeType, AddressSpace};
@@ -17,7 +17,7 @@ use super::{node::NodeType, AddressSpace};
 /// This function is a simplified use case for event filters and such like where a browse path
 /// is defined as an array and doesn't need to be parsed out of a relative path. All nodes in the
 /// path must be objects or variables.
-pub(crate) fn find_node_from_browse_path<'a>(
+pub fn find_node_from_browse_path<'a>(
     address_space: &'a AddressSpace,
     parent_node_id: &NodeId,
     browse_path: &[QualifiedName],
@@ -64,6 +64,30 @@ pub(crate) fn find_node_from_browse_path<'a>(
     }
 }
 
+/// Sets the value of the variable at the browse path from a node, such as a property of an event
+/// or a state variable of a state machine. Returns false if there is no such variable.
+pub(crate) fn set_variable_value_from_browse_path<V>(
+    address_space: &mut AddressSpace,
+    node_id: &NodeId,
+    browse_path: &[&str],
+    value: V,
+) -> bool
+where
+    V: Into<Variant>,
+{
+    let browse_path = browse_path
+        .iter()
+        .map(|name| QualifiedName::from(*name))
+        .collect::<Vec<_>>();
+    if let Ok(node) = find_node_from_browse_path(address_space, node_id, &browse_path) {
+        let variable_id = node.as_node().node_id();
+        let now = DateTime::now();
+        address_space.set_variable_value_by_ref(&variable_id, value, &now, &now)
+    } else {
+        false
+    }
+}
+
 /// Given a path as a string, find all the nodes that match against it. Note this function
 /// uses a default path resolver based on common browse names. If you need something else use
 /// `find_nodes_relative_path()` after you have created a relative path.
@@ -0,0 +1,828 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains builders for state machines, i.e. the `FiniteStateMachineType` of OPC UA Part 16 and
+//! the `ProgramStateMachineType` of OPC UA Part 10, and the state machines that the address space
+//! keeps so it can take their transitions. Each builder makes the object with its `CurrentState`
+//! and `LastTransition` variables, and the address space updates them on every transition.
+
+use crate::types::{status_code::StatusCode, *};
+
+#[cfg(feature = "events")]
+use crate::server::events::{event::Event, transition_event::TransitionEventType};
+use crate::server::{
+    address_space::{
+        address_space::AddressSpace,
+        method_calls::ProgramMethodCall,
+        object::ObjectBuilder,
+        references::ReferenceDirection,
+        relative_path::{find_node_from_browse_path, set_variable_value_from_browse_path},
+        variable::{add_property, good_value, VariableBuilder},
+    },
+    callbacks::Program,
+};
+
+/// Adds the `CurrentState` variable of a state machine, with its `Id` property, and returns the
+/// node id of the variable
+pub(crate) fn add_current_state_variable<T, U>(
+    state_machine_id: &NodeId,
+    current_state: T,
+    current_state_id: U,
+    address_space: &mut AddressSpace,
+) -> NodeId
+where
+    T: Into<LocalizedText>,
+    U: Into<NodeId>,
+{
+    let variable_id = NodeId::next_numeric(state_machine_id.namespace);
+    VariableBuilder::new(&variable_id, "CurrentState", "CurrentState")
+        .component_of(state_machine_id.clone())
+        .has_type_definition(VariableTypeId::FiniteStateVariableType)
+        .data_type(DataTypeId::LocalizedText)
+        .value(current_state.into())
+        .insert(address_space);
+    add_property(
+        &variable_id,
+        "Id",
+        DataTypeId::NodeId,
+        current_state_id.into(),
+        address_space,
+    );
+    variable_id
+}
+
+/// The states of a program with their names and numbers
+const PROGRAM_STATES: [(ObjectId, &str, u32); 4] = [
+    (ObjectId::ProgramStateMachineType_Halted, "Halted", 11),
+    (ObjectId::ProgramStateMachineType_Ready, "Ready", 12),
+    (ObjectId::ProgramStateMachineType_Running, "Running", 13),
+    (ObjectId::ProgramStateMachineType_Suspended, "Suspended", 14),
+];
+
+/// The transitions of a program with their names, numbers and the states they are between
+const PROGRAM_TRANSITIONS: [(ObjectId, &str, u32, ObjectId, ObjectId); 9] = [
+    (
+        ObjectId::ProgramStateMachineType_HaltedToReady,
+        "HaltedToReady",
+        1,
+        ObjectId::ProgramStateMachineType_Halted,
+        ObjectId::ProgramStateMachineType_Ready,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_ReadyToRunning,
+        "ReadyToRunning",
+        2,
+        ObjectId::ProgramStateMachineType_Ready,
+        ObjectId::ProgramStateMachineType_Running,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_RunningToHalted,
+        "RunningToHalted",
+        3,
+        ObjectId::ProgramStateMachineType_Running,
+        ObjectId::ProgramStateMachineType_Halted,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_RunningToReady,
+        "RunningToReady",
+        4,
+        ObjectId::ProgramStateMachineType_Running,
+        ObjectId::ProgramStateMachineType_Ready,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_RunningToSuspended,
+        "RunningToSuspended",
+        5,
+        ObjectId::ProgramStateMachineType_Running,
+        ObjectId::ProgramStateMachineType_Suspended,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_SuspendedToRunning,
+        "SuspendedToRunning",
+        6,
+        ObjectId::ProgramStateMachineType_Suspended,
+        ObjectId::ProgramStateMachineType_Running,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_SuspendedToHalted,
+        "SuspendedToHalted",
+        7,
+        ObjectId::ProgramStateMachineType_Suspended,
+        ObjectId::ProgramStateMachineType_Halted,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_SuspendedToReady,
+        "SuspendedToReady",
+        8,
+        ObjectId::ProgramStateMachineType_Suspended,
+        ObjectId::ProgramStateMachineType_Ready,
+    ),
+    (
+        ObjectId::ProgramStateMachineType_ReadyToHalted,
+        "ReadyToHalted",
+        9,
+        ObjectId::ProgramStateMachineType_Ready,
+        ObjectId::ProgramStateMachineType_Halted,
+    ),
+];
+
+/// The transition that each method of a program takes from a state
+const PROGRAM_METHOD_TRANSITIONS: [(ProgramMethodCall, ObjectId, ObjectId); 7] = [
+    (
+        ProgramMethodCall::Start,
+        ObjectId::ProgramStateMachineType_Ready,
+        ObjectId::ProgramStateMachineType_ReadyToRunning,
+    ),
+    (
+        ProgramMethodCall::Suspend,
+        ObjectId::ProgramStateMachineType_Running,
+        ObjectId::ProgramStateMachineType_RunningToSuspended,
+    ),
+    (
+        ProgramMethodCall::Resume,
+        ObjectId::ProgramStateMachineType_Suspended,
+        ObjectId::ProgramStateMachineType_SuspendedToRunning,
+    ),
+    (
+        ProgramMethodCall::Halt,
+        ObjectId::ProgramStateMachineType_Ready,
+        ObjectId::ProgramStateMachineType_ReadyToHalted,
+    ),
+    (
+        ProgramMethodCall::Halt,
+        ObjectId::ProgramStateMachineType_Running,
+        ObjectId::ProgramStateMachineType_RunningToHalted,
+    ),
+    (
+        ProgramMethodCall::Halt,
+        ObjectId::ProgramStateMachineType_Suspended,
+        ObjectId::ProgramStateMachineType_SuspendedToHalted,
+    ),
+    (
+        ProgramMethodCall::Reset,
+        ObjectId::ProgramStateMachineType_Halted,
+        ObjectId::ProgramStateMachineType_HaltedToReady,
+    ),
+];
+
+/// The methods of a program
+const PROGRAM_METHODS: [MethodId; 5] = [
+    MethodId::ProgramStateMachineType_Start,
+    MethodId::ProgramStateMachineType_Suspend,
+    MethodId::ProgramStateMachineType_Resume,
+    MethodId::ProgramStateMachineType_Halt,
+    MethodId::ProgramStateMachineType_Reset,
+];
+
+/// A state of a state machine
+#[derive(Debug, Clone)]
+struct State {
+    node_id: NodeId,
+    name: LocalizedText,
+    number: u32,
+}
+
+/// A transition of a state machine from one state to another
+#[derive(Debug, Clone)]
+struct Transition {
+    node_id: NodeId,
+    name: LocalizedText,
+    number: u32,
+    from_state: NodeId,
+    to_state: NodeId,
+}
+
+/// Builds an object of `FiniteStateMachineType`, or of one of its subtypes, with its states and
+/// the transitions between them. The state machine starts in its initial state, which is its
+/// first state unless another is set.
+///
+/// States and transitions are usually those of the type definition, e.g. the states of a
+/// companion specification's state machine type. A state or transition that is not already in
+/// the address space is inserted as a component of the state machine.
+///
+/// The application takes a transition with `AddressSpace::fire_state_machine_transition()`,
+/// which fires a `TransitionEventType` event unless transition events are turned off or the
+/// server is built without the `events` feature.
+///
+/// ```no_run
+/// use opcua::server::prelude::*;
+///
+/// # fn example(address_space: &mut AddressSpace) {
+/// let node_id = NodeId::new(2, "Door");
+/// let (closed, open) = (NodeId::new(2, "Closed"), NodeId::new(2, "Open"));
+/// FiniteStateMachineBuilder::new(&node_id, "Door", "Door")
+///     .state(closed.clone(), "Closed", 1)
+///     .state(open.clone(), "Open", 2)
+///     .transition(NodeId::new(2, "Opening"), "Opening", 1, closed.clone(), open.clone())
+///     .transition(NodeId::new(2, "Closing"), "Closing", 2, open, closed)
+///     .organized_by(ObjectId::ObjectsFolder)
+///     .insert(address_space);
+/// address_space
+///     .fire_state_machine_transition(&node_id, NodeId::new(2, "Opening"))
+///     .unwrap();
+/// # }
+/// ```
+pub struct FiniteStateMachineBuilder {
+    object: ObjectBuilder,
+    type_definition: NodeId,
+    #[cfg(feature = "events")]
+    event_type_id: ObjectTypeId,
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    initial_state: Option<NodeId>,
+    #[cfg(feature = "events")]
+    event_source: Option<NodeId>,
+    #[cfg(feature = "events")]
+    transition_events: bool,
+}
+
+impl FiniteStateMachineBuilder {
+    pub fn new<R, S>(node_id: &NodeId, browse_name: R, display_name: S) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        FiniteStateMachineBuilder {
+            object: ObjectBuilder::new(node_id, browse_name, display_name),
+            type_definition: ObjectTypeId::FiniteStateMachineType.into(),
+            #[cfg(feature = "events")]
+            event_type_id: ObjectTypeId::TransitionEventType,
+            states: Vec::new(),
+            transitions: Vec::new(),
+            initial_state: None,
+            #[cfg(feature = "events")]
+            event_source: None,
+            #[cfg(feature = "events")]
+            transition_events: true,
+        }
+    }
+
+    /// Returns the node id of the state machine.
+    pub fn get_node_id(&self) -> NodeId {
+        self.object.get_node_id()
+    }
+
+    /// Sets the description of the state machine.
+    pub fn description<V>(mut self, description: V) -> Self
+    where
+        V: Into<LocalizedText>,
+    {
+        self.object = self.object.description(description);
+        self
+    }
+
+    /// Makes the state machine organized by the node, e.g. a folder.
+    pub fn organized_by<T>(mut self, organized_by_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.object = self.object.organized_by(organized_by_id);
+        self
+    }
+
+    /// Makes the state machine a component of the node, e.g. of the object whose state it is.
+    pub fn component_of<T>(mut self, component_of_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.object = self.object.component_of(component_of_id);
+        self
+    }
+
+    /// Applies any other settings to the builder of the object.
+    pub fn object<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(ObjectBuilder) -> ObjectBuilder,
+    {
+        self.object = f(self.object);
+        self
+    }
+
+    /// Sets the type definition of the state machine, which is a subtype of
+    /// `FiniteStateMachineType`. The default is `FiniteStateMachineType`.
+    pub fn has_type_definition<T>(mut self, type_definition: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.type_definition = type_definition.into();
+        self
+    }
+
+    /// Adds a state with its name and number.
+    pub fn state<T, S>(mut self, state_id: T, name: S, number: u32) -> Self
+    where
+        T: Into<NodeId>,
+        S: Into<LocalizedText>,
+    {
+        self.states.push(State {
+            node_id: state_id.into(),
+            name: name.into(),
+            number,
+        });
+        self
+    }
+
+    /// Adds a transition with its name and number from one state to another.
+    pub fn transition<T, S, U, V>(
+        mut self,
+        transition_id: T,
+        name: S,
+        number: u32,
+        from_state: U,
+        to_state: V,
+    ) -> Self
+    where
+        T: Into<NodeId>,
+        S: Into<LocalizedText>,
+        U: Into<NodeId>,
+        V: Into<NodeId>,
+    {
+        self.transitions.push(Transition {
+            node_id: transition_id.into(),
+            name: name.into(),
+            number,
+            from_state: from_state.into(),
+            to_state: to_state.into(),
+        });
+        self
+    }
+
+    /// Sets the state that the state machine starts in.
+    pub fn initial_state<T>(mut self, state_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.initial_state = Some(state_id.into());
+        self
+    }
+
+    /// Sets the source node of the transition events, e.g. the object that owns the state
+    /// machine. The default is the state machine itself.
+    #[cfg(feature = "events")]
+    pub fn event_source<T>(mut self, event_source: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.event_source = Some(event_source.into());
+        self
+    }
+
+    /// Sets whether the state machine fires a transition event for each transition it takes.
+    /// The default is that it does.
+    #[cfg(feature = "events")]
+    pub fn transition_events(mut self, transition_events: bool) -> Self {
+        self.transition_events = transition_events;
+        self
+    }
+
+    /// Inserts the state machine and its variables into the address space and returns the node
+    /// id of the state machine. Panics if the state machine has no states or a transition is
+    /// between states that it does not have.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        self.insert_state_machine(address_space, None)
+    }
+
+    fn has_state(&self, state_id: &NodeId) -> bool {
+        self.states.iter().any(|state| state.node_id == *state_id)
+    }
+
+    fn insert_state_machine(
+        self,
+        address_space: &mut AddressSpace,
+        program: Option<Box<dyn Program + Send + Sync>>,
+    ) -> NodeId {
+        let node_id = self.object.get_node_id();
+        let initial_state = self
+            .initial_state
+            .as_ref()
+            .or_else(|| self.states.first().map(|state| &state.node_id))
+            .and_then(|state_id| self.states.iter().find(|state| state.node_id == *state_id))
+            .cloned()
+            .unwrap_or_else(|| panic!("The state machine {:?} has no initial state", node_id));
+        if let Some(transition) = self.transitions.iter().find(|transition| {
+            !self.has_state(&transition.from_state) || !self.has_state(&transition.to_state)
+        }) {
+            panic!(
+                "The transition {:?} of the state machine {:?} is not between its states",
+                transition.node_id, node_id
+            );
+        }
+
+        self.object
+            .has_type_definition(self.type_definition)
+            .insert(address_space);
+
+        // States and transitions that are not those of the type are components of the machine
+        let ns = node_id.namespace;
+        for state in &self.states {
+            if address_space.find_node(&state.node_id).is_some() {
+                continue;
+            }
+            let type_definition = if state.node_id == initial_state.node_id {
+                ObjectTypeId::InitialStateType
+            } else {
+                ObjectTypeId::StateType
+            };
+            let browse_name = QualifiedName::new(ns, state.name.text.as_ref());
+            ObjectBuilder::new(&state.node_id, browse_name, state.name.clone())
+                .component_of(node_id.clone())
+                .has_type_definition(type_definition)
+                .insert(address_space);
+            add_property(
+                &state.node_id,
+                "StateNumber",
+                DataTypeId::UInt32,
+                state.number,
+                address_space,
+            );
+        }
+        for transition in &self.transitions {
+            if address_space.find_node(&transition.node_id).is_some() {
+                continue;
+            }
+            let browse_name = QualifiedName::new(ns, transition.name.text.as_ref());
+            ObjectBuilder::new(&transition.node_id, browse_name, transition.name.clone())
+                .component_of(node_id.clone())
+                .has_type_definition(ObjectTypeId::TransitionType)
+                .reference(
+                    transition.from_state.clone(),
+                    ReferenceTypeId::FromState,
+                    ReferenceDirection::Forward,
+                )
+                .reference(
+                    transition.to_state.clone(),
+                    ReferenceTypeId::ToState,
+                    ReferenceDirection::Forward,
+                )
+                .insert(address_space);
+            add_property(
+                &transition.node_id,
+                "TransitionNumber",
+                DataTypeId::UInt32,
+                transition.number,
+                address_space,
+            );
+        }
+
+        let current_state_id = add_current_state_variable(
+            &node_id,
+            initial_state.name.clone(),
+            initial_state.node_id.clone(),
+            address_space,
+        );
+        add_property(
+            &current_state_id,
+            "Number",
+            DataTypeId::UInt32,
+            initial_state.number,
+            address_space,
+        );
+        let last_transition_id = NodeId::next_numeric(ns);
+        VariableBuilder::new(&last_transition_id, "LastTransition", "LastTransition")
+            .component_of(node_id.clone())
+            .has_type_definition(VariableTypeId::FiniteTransitionVariableType)
+            .data_type(DataTypeId::LocalizedText)
+            .value(LocalizedText::null())
+            .insert(address_space);
+        add_property(
+            &last_transition_id,
+            "Id",
+            DataTypeId::NodeId,
+            NodeId::null(),
+            address_space,
+        );
+        add_property(
+            &last_transition_id,
+            "Number",
+            DataTypeId::UInt32,
+            0u32,
+            address_space,
+        );
+        add_property(
+            &last_transition_id,
+            "TransitionTime",
+            DataTypeId::UtcTime,
+            DateTime::null(),
+            address_space,
+        );
+
+        #[cfg(feature = "events")]
+        let event_source = if self.transition_events {
+            Some(self.event_source.unwrap_or_else(|| node_id.clone()))
+        } else {
+            None
+        };
+        address_space.add_state_machine(StateMachine {
+            node_id: node_id.clone(),
+            #[cfg(feature = "events")]
+            event_type_id: self.event_type_id,
+            #[cfg(feature = "events")]
+            event_source,
+            current_state: initial_state.node_id,
+            states: self.states,
+            transitions: self.transitions,
+            program,
+        });
+        node_id
+    }
+}
+
+/// Builds an object of `ProgramStateMachineType`, the state machine of a program that clients
+/// control with its `Start`, `Suspend`, `Resume`, `Halt` and `Reset` methods. The address space
+/// passes each call on to the program, and takes the transition of the method unless the program
+/// refuses it. The program starts in the `Ready` state.
+///
+/// ```no_run
+/// use opcua::server::prelude::*;
+///
+/// struct Batch;
+///
+/// impl Program for Batch {
+///     fn start(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+///         // Start the batch
+///         Ok(())
+///     }
+/// }
+///
+/// # fn example(address_space: &mut AddressSpace) {
+/// let node_id = NodeId::new(2, "Batch");
+/// ProgramStateMachineBuilder::new(&node_id, "Batch", "Batch", Box::new(Batch))
+///     .organized_by(ObjectId::ObjectsFolder)
+///     .insert(address_space);
+/// # }
+/// ```
+pub struct ProgramStateMachineBuilder {
+    state_machine: FiniteStateMachineBuilder,
+    program: Box<dyn Program + Send + Sync>,
+}
+
+impl ProgramStateMachineBuilder {
+    pub fn new<R, S>(
+        node_id: &NodeId,
+        browse_name: R,
+        display_name: S,
+        program: Box<dyn Program + Send + Sync>,
+    ) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        let state_machine = FiniteStateMachineBuilder::new(node_id, browse_name, display_name)
+            .has_type_definition(ObjectTypeId::ProgramStateMachineType)
+            .initial_state(ObjectId::ProgramStateMachineType_Ready);
+        #[cfg(feature = "events")]
+        let state_machine = FiniteStateMachineBuilder {
+            event_type_id: ObjectTypeId::ProgramTransitionEventType,
+            ..state_machine
+        };
+        let state_machine =
+            PROGRAM_STATES
+                .iter()
+                .fold(state_machine, |state_machine, (state_id, name, number)| {
+                    state_machine.state(*state_id, *name, *number)
+                });
+        let state_machine = PROGRAM_TRANSITIONS.iter().fold(
+            state_machine,
+            |state_machine, (transition_id, name, number, from_state, to_state)| {
+                state_machine.transition(*transition_id, *name, *number, *from_state, *to_state)
+            },
+        );
+        ProgramStateMachineBuilder {
+            state_machine,
+            program,
+        }
+    }
+
+    /// Returns the node id of the program.
+    pub fn get_node_id(&self) -> NodeId {
+        self.state_machine.get_node_id()
+    }
+
+    /// Makes the program organized by the node, e.g. a folder.
+    pub fn organized_by<T>(mut self, organized_by_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.state_machine = self.state_machine.organized_by(organized_by_id);
+        self
+    }
+
+    /// Makes the program a component of the node, e.g. of the object that runs it.
+    pub fn component_of<T>(mut self, component_of_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.state_machine = self.state_machine.component_of(component_of_id);
+        self
+    }
+
+    /// Applies any other settings to the builder of the state machine, e.g. the type definition
+    /// of a subtype of `ProgramStateMachineType` or the source of its transition events.
+    pub fn state_machine<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(FiniteStateMachineBuilder) -> FiniteStateMachineBuilder,
+    {
+        self.state_machine = f(self.state_machine);
+        self
+    }
+
+    /// Inserts the program, its variables and its properties into the address space and returns
+    /// the node id of the program.
+    pub fn insert(self, address_space: &mut AddressSpace) -> NodeId {
+        let node_id = self
+            .state_machine
+            .insert_state_machine(address_space, Some(self.program));
+        ["Creatable", "Deletable", "AutoDelete"]
+            .iter()
+            .for_each(|name| {
+                add_property(&node_id, name, DataTypeId::Boolean, false, address_space);
+            });
+        add_property(
+            &node_id,
+            "RecycleCount",
+            DataTypeId::Int32,
+            0i32,
+            address_space,
+        );
+        PROGRAM_METHODS.iter().for_each(|method_id| {
+            address_space.insert_reference(
+                &node_id,
+                &(*method_id).into(),
+                ReferenceTypeId::HasComponent,
+            );
+        });
+        node_id
+    }
+}
+
+/// A state machine that the address space takes the transitions of, which updates its variables
+/// and fires its transition events.
+pub(crate) struct StateMachine {
+    node_id: NodeId,
+    #[cfg(feature = "events")]
+    event_type_id: ObjectTypeId,
+    /// The source node of the transition events, if the state machine fires them
+    #[cfg(feature = "events")]
+    event_source: Option<NodeId>,
+    current_state: NodeId,
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    /// The program that the methods of a program state machine are passed on to
+    program: Option<Box<dyn Program + Send + Sync>>,
+}
+
+impl StateMachine {
+    pub(crate) fn node_id(&self) -> &NodeId {
+        &self.node_id
+    }
+
+    pub(crate) fn current_state(&self) -> &NodeId {
+        &self.current_state
+    }
+
+    /// Takes the transition, which must leave the current state. Returns `BadInvalidArgument`
+    /// if the transition is not one of the state machine, or `BadInvalidState` if it leaves
+    /// another state.
+    pub(crate) fn fire_transition(
+        &mut self,
+        address_space: &mut AddressSpace,
+        transition_id: &NodeId,
+    ) -> Result<(), StatusCode> {
+        let transition = self
+            .transitions
+            .iter()
+            .find(|transition| transition.node_id == *transition_id)
+            .cloned()
+            .ok_or(StatusCode::BadInvalidArgument)?;
+        if transition.from_state != self.current_state {
+            Err(StatusCode::BadInvalidState)
+        } else {
+            self.take_transition(address_space, &transition);
+            Ok(())
+        }
+    }
+
+    /// Passes the call of a method on to the program and takes the transition of the method
+    /// unless the program refuses it. Returns `BadInvalidState` if the method does not leave the
+    /// current state, or `None` if the state machine is not a program.
+    pub(crate) fn call_program(
+        &mut self,
+        address_space: &mut AddressSpace,
+        method_call: ProgramMethodCall,
+        input_arguments: &[Variant],
+    ) -> Option<Result<(), StatusCode>> {
+        let current_state = self.current_state.clone();
+        let program = self.program.as_mut()?;
+        let transition_id = PROGRAM_METHOD_TRANSITIONS
+            .iter()
+            .find(|(method, from_state, _)| {
+                *method == method_call && current_state == (*from_state).into()
+            })
+            .map(|(_, _, transition_id)| NodeId::from(transition_id));
+        let result = if let Some(transition_id) = transition_id {
+            match method_call {
+                ProgramMethodCall::Start => program.start(input_arguments),
+                ProgramMethodCall::Suspend => program.suspend(input_arguments),
+                ProgramMethodCall::Resume => program.resume(input_arguments),
+                ProgramMethodCall::Halt => program.halt(input_arguments),
+                ProgramMethodCall::Reset => program.reset(input_arguments),
+            }
+            .and_then(|_| self.fire_transition(address_space, &transition_id))
+        } else {
+            Err(StatusCode::BadInvalidState)
+        };
+        if result.is_ok() && method_call == ProgramMethodCall::Reset {
+            self.increment_recycle_count(address_space);
+        }
+        Some(result)
+    }
+
+    fn take_transition(&mut self, address_space: &mut AddressSpace, transition: &Transition) {
+        let to_state = self.state(&transition.to_state);
+        self.current_state = to_state.node_id.clone();
+
+        self.set_variable(address_space, &["CurrentState"], to_state.name.clone());
+        self.set_variable(
+            address_space,
+            &["CurrentState", "Id"],
+            to_state.node_id.clone(),
+        );
+        self.set_variable(address_space, &["CurrentState", "Number"], to_state.number);
+        self.set_variable(address_space, &["LastTransition"], transition.name.clone());
+        self.set_variable(
+            address_space,
+            &["LastTransition", "Id"],
+            transition.node_id.clone(),
+        );
+        self.set_variable(
+            address_space,
+            &["LastTransition", "Number"],
+            transition.number,
+        );
+        self.set_variable(
+            address_space,
+            &["LastTransition", "TransitionTime"],
+            DateTime::now(),
+        );
+
+        #[cfg(feature = "events")]
+        if let Some(ref event_source) = self.event_source {
+            let from_state = self.state(&transition.from_state);
+            let source_name = address_space
+                .find_node(event_source)
+                .map(|node| node.as_node().browse_name().name)
+                .unwrap_or_default();
+            let event_id = NodeId::next_numeric(self.node_id.namespace);
+            let mut event =
+                TransitionEventType::new(event_id, self.event_type_id, event_source.clone())
+                    .source_name(source_name)
+                    .message(transition.name.clone())
+                    .transition(transition.name.clone(), transition.node_id.clone())
+                    .from_state(from_state.name, from_state.node_id)
+                    .to_state(to_state.name, to_state.node_id);
+            if event.raise(address_space).is_err() {
+                error!("Cannot raise a transition event for {}", self.node_id);
+            }
+        }
+    }
+
+    fn state(&self, state_id: &NodeId) -> State {
+        // The builder ensures that transitions are between the states of the machine
+        self.states
+            .iter()
+            .find(|state| state.node_id == *state_id)
+            .cloned()
+            .unwrap()
+    }
+
+    fn increment_recycle_count(&self, address_space: &mut AddressSpace) {
+        let recycle_count =
+            find_node_from_browse_path(address_space, &self.node_id, &["RecycleCount".into()])
+                .ok()
+                .and_then(|node| good_value(address_space, &node.as_node().node_id()))
+                .and_then(|value| match value {
+                    Variant::Int32(recycle_count) => Some(recycle_count),
+                    _ => None,
+                })
+                .unwrap_or(0);
+        self.set_variable(
+            address_space,
+            &["RecycleCount"],
+            recycle_count.wrapping_add(1),
+        );
+    }
+
+    /// Sets the value of a variable of the state machine by its browse path from the machine
+    fn set_variable<V>(&self, address_space: &mut AddressSpace, browse_path: &[&str], value: V)
+    where
+        V: Into<Variant>,
+    {
+        if !set_variable_value_from_browse_path(address_space, &self.node_id, browse_path, value) {
+            error!(
+                "State machine {} has no variable {:?}",
+                self.node_id, browse_path
+            );
+        }
+    }
+}
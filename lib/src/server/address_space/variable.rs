@@ -8,7 +8,7 @@ use std::convert::{Into, TryFrom};
 use std::sync::Arc;
 
 use crate::sync::*;
-use crate::types::service_types::VariableAttributes;
+use crate::types::service_types::{AccessLevelExType, VariableAttributes};
 
 use crate::server::{
     address_space::{
@@ -16,7 +16,7 @@ use crate::server::{
         node::{Node, NodeBase},
         AccessLevel, UserAccessLevel,
     },
-    callbacks::{AttributeGetter, AttributeSetter},
+    callbacks::{AttributeGetter, AttributeSetter, ValueSource},
 };
 
 // This is a builder object for constructing variable nodes programmatically.
@@ -62,6 +62,12 @@ impl VariableBuilder {
         self
     }
 
+    /// Sets the extended access level for the variable. The low 8 bits are the access level.
+    pub fn access_level_ex(mut self, access_level_ex: AccessLevelExType) -> Self {
+        self.node.set_access_level_ex(access_level_ex);
+        self
+    }
+
     /// Sets the value rank for the variable.
     pub fn value_rank(mut self, value_rank: i32) -> Self {
         self.node.set_value_rank(value_rank);
@@ -162,8 +168,12 @@ pub struct Variable {
     value: DataValue,
     access_level: u8,
     user_access_level: u8,
+    /// The bits of the extended access level above those of the access level
+    access_level_ex: u32,
     array_dimensions: Option<Vec<u32>>,
     minimum_sampling_interval: Option<f64>,
+    /// The number of times the value has been set
+    value_updates: u64,
     #[derivative(Debug = "ignore")]
     value_setter: Option<Arc<Mutex<dyn AttributeSetter + Send>>>,
     #[derivative(Debug = "ignore")]
@@ -180,8 +190,10 @@ impl Default for Variable {
             value: Variant::Empty.into(),
             access_level: UserAccessLevel::CURRENT_READ.bits(),
             user_access_level: AccessLevel::CURRENT_READ.bits(),
+            access_level_ex: 0,
             array_dimensions: None,
             minimum_sampling_interval: None,
+            value_updates: 0,
             value_getter: None,
             value_setter: None,
         }
@@ -213,6 +225,7 @@ impl Node for Variable {
             AttributeId::AccessLevel => Some(self.access_level().bits().into()),
             AttributeId::UserAccessLevel => Some(self.user_access_level().bits().into()),
             // Optional attributes
+            AttributeId::AccessLevelEx => Some((self.access_level_ex().bits() as u32).into()),
             AttributeId::ArrayDimensions => {
                 self.array_dimensions().map(|v| Variant::from(v).into())
             }
@@ -279,6 +292,14 @@ impl Node for Variable {
                     Err(StatusCode::BadTypeMismatch)
                 }
             }
+            AttributeId::AccessLevelEx => {
+                if let Variant::UInt32(v) = value {
+                    self.set_access_level_ex(AccessLevelExType::from_bits_truncate(v as i32));
+                    Ok(())
+                } else {
+                    Err(StatusCode::BadTypeMismatch)
+                }
+            }
             AttributeId::ArrayDimensions => {
                 let array_dimensions = <Vec<u32>>::try_from(&value);
                 if let Ok(array_dimensions) = array_dimensions {
@@ -461,8 +482,6 @@ impl Variable {
         data_encoding: &QualifiedName,
         max_age: f64,
     ) -> DataValue {
-        use std::i32;
-
         if let Some(ref value_getter) = self.value_getter {
             let mut value_getter = value_getter.lock();
             value_getter
@@ -481,34 +500,62 @@ impl Variable {
                 })
                 .unwrap_or_default()
         } else {
-            let data_value = &self.value;
-            let mut result = DataValue {
-                server_picoseconds: data_value.server_picoseconds,
-                server_timestamp: data_value.server_timestamp,
-                source_picoseconds: data_value.source_picoseconds,
-                source_timestamp: data_value.source_timestamp,
-                value: None,
-                status: None,
-            };
-
-            // Get the value
-            if let Some(ref value) = data_value.value {
-                match value.range_of(index_range) {
-                    Ok(value) => {
-                        result.value = Some(value);
-                        result.status = data_value.status;
-                    }
-                    Err(err) => {
-                        result.status = Some(err);
-                    }
+            self.stored_value(index_range, max_age)
+        }
+    }
+
+    /// Returns the value for a monitored item to sample. A getter that polls is called for the
+    /// value, while a variable whose value is pushed gives the value that was last set.
+    pub fn sample_value(&self) -> DataValue {
+        match self.value_source() {
+            ValueSource::Poll => self.value(
+                TimestampsToReturn::Both,
+                NumericRange::None,
+                &QualifiedName::null(),
+                0.0,
+            ),
+            ValueSource::Push => self.stored_value(NumericRange::None, 0.0),
+        }
+    }
+
+    /// Returns how the value is provided to monitored items. The value of a variable without a
+    /// getter is pushed, i.e. set by the application.
+    pub fn value_source(&self) -> ValueSource {
+        self.value_getter
+            .as_ref()
+            .map(|value_getter| value_getter.lock().value_source())
+            .unwrap_or(ValueSource::Push)
+    }
+
+    /// Returns the value that was last set, ignoring any getter.
+    fn stored_value(&self, index_range: NumericRange, max_age: f64) -> DataValue {
+        let data_value = &self.value;
+        let mut result = DataValue {
+            server_picoseconds: data_value.server_picoseconds,
+            server_timestamp: data_value.server_timestamp,
+            source_picoseconds: data_value.source_picoseconds,
+            source_timestamp: data_value.source_timestamp,
+            value: None,
+            status: None,
+        };
+
+        // Get the value
+        if let Some(ref value) = data_value.value {
+            match value.range_of(index_range) {
+                Ok(value) => {
+                    result.value = Some(value);
+                    result.status = data_value.status;
+                }
+                Err(err) => {
+                    result.status = Some(err);
                 }
             }
-            if max_age > 0.0 && max_age <= i32::MAX as f64 {
-                // Update the server timestamp to now as a "best effort" attempt to get the latest value
-                result.server_timestamp = Some(DateTime::now());
-            }
-            result
         }
+        if max_age > 0.0 && max_age <= i32::MAX as f64 {
+            // Update the server timestamp to now as a "best effort" attempt to get the latest value
+            result.server_timestamp = Some(DateTime::now());
+        }
+        result
     }
 
     /// Sets the variable's `Variant` value. The timestamps for the change are updated to now.
@@ -564,6 +611,7 @@ impl Variable {
             Some(ref mut full_value) => {
                 // Overwrite a partial section of the value
                 full_value.set_range_of(index_range, &value)?;
+                self.value_updates += 1;
                 self.value.status = Some(status_code);
                 self.value.server_timestamp = Some(*server_timestamp);
                 self.value.source_timestamp = Some(*source_timestamp);
@@ -585,12 +633,26 @@ impl Variable {
         V: Into<Variant>,
     {
         self.value.value = Some(value.into());
+        self.value_updates += 1;
         self.value.status = Some(status_code);
         self.value.server_timestamp = Some(*server_timestamp);
         self.value.source_timestamp = Some(*source_timestamp);
         Ok(())
     }
 
+    /// Sets the variable's `DataValue` as it is, with its status and timestamps
+    pub fn set_data_value(&mut self, data_value: DataValue) {
+        self.value = data_value;
+        self.value_updates += 1;
+    }
+
+    /// Returns the number of times the value has been set since the variable was created. A
+    /// monitored item compares the number between samples to count the updates that it coalesced,
+    /// i.e. that were replaced by a later value before it sampled them.
+    pub fn value_updates(&self) -> u64 {
+        self.value_updates
+    }
+
     /// Sets a getter function that will be called to get the value of this variable.
     pub fn set_value_getter(&mut self, value_getter: Arc<Mutex<dyn AttributeGetter + Send>>) {
         self.value_getter = Some(value_getter);
@@ -648,6 +710,21 @@ impl Variable {
         self.access_level = access_level.bits();
     }
 
+    /// Returns the extended access level of the variable, whose low 8 bits are the access level.
+    pub fn access_level_ex(&self) -> AccessLevelExType {
+        AccessLevelExType::from_bits_truncate(
+            (self.access_level_ex | self.access_level as u32) as i32,
+        )
+    }
+
+    /// Sets the extended access level of the variable. This also sets the access level from its
+    /// low 8 bits.
+    pub fn set_access_level_ex(&mut self, access_level_ex: AccessLevelExType) {
+        let bits = access_level_ex.bits() as u32;
+        self.access_level = AccessLevel::from_bits_truncate(bits as u8).bits();
+        self.access_level_ex = bits & !0xff;
+    }
+
     /// Test if the variable is user readable.
     pub fn is_user_readable(&self) -> bool {
         self.user_access_level()
@@ -705,3 +782,31 @@ impl Variable {
         self.data_type = data_type.into();
     }
 }
+
+/// Returns the value of a variable, if it is a good one
+pub(crate) fn good_value(address_space: &AddressSpace, node_id: &NodeId) -> Option<Variant> {
+    address_space
+        .find_variable_by_ref(node_id)
+        .map(AddressSpace::current_value)
+        .filter(|value| !value.status.is_some_and(|status| status.is_bad()))
+        .and_then(|value| value.value)
+}
+
+/// Adds a property to the node
+pub(crate) fn add_property<R, V>(
+    parent_id: &NodeId,
+    name: &str,
+    data_type: R,
+    value: V,
+    address_space: &mut AddressSpace,
+) where
+    R: Into<NodeId>,
+    V: Into<Variant>,
+{
+    VariableBuilder::new(&NodeId::next_numeric(parent_id.namespace), name, name)
+        .property_of(parent_id.clone())
+        .has_type_definition(VariableTypeId::PropertyType)
+        .data_type(data_type)
+        .value(value)
+        .insert(address_space);
+}
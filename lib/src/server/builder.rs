@@ -4,10 +4,14 @@
 
 use std::path::PathBuf;
 
-use crate::core::config::Config;
+use crate::core::{comms::socket_options::SocketOptions, config::Config};
+use crate::crypto::SystemCertificateStore;
 
 use super::{
-    config::{ServerConfig, ServerEndpoint, ServerUserToken, ANONYMOUS_USER_TOKEN_ID},
+    config::{
+        Limits, QueueOverflowPolicy, Redundancy, ServerConfig, ServerEndpoint, ServerUserToken,
+        ANONYMOUS_USER_TOKEN_ID,
+    },
     constants,
     server::Server,
 };
@@ -229,6 +233,17 @@ impl ServerBuilder {
         self
     }
 
+    /// Sets the stores of the operating system that the server certificate and private key, and
+    /// trusted client certificates, are read from instead of the PKI directory. Rejected client
+    /// certificates are still stored in the PKI directory.
+    pub fn system_certificate_store(
+        mut self,
+        system_certificate_store: SystemCertificateStore,
+    ) -> Self {
+        self.config.system_certificate_store = Some(system_certificate_store);
+        self
+    }
+
     /// Sets the pki directory where client's own key pair is stored and where `/trusted` and
     /// `/rejected` server certificates are stored.
     pub fn pki_dir<T>(mut self, pki_dir: T) -> Self
@@ -286,6 +301,41 @@ impl ServerBuilder {
         self
     }
 
+    /// Sets the path of a Unix domain socket, or the name of a named pipe on Windows, that the
+    /// server also listens on for clients on the same host. Clients connect to it with
+    /// `opc.unix` endpoint urls.
+    pub fn local_socket<T>(mut self, local_socket: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.config.tcp_config.local_socket = Some(local_socket.into());
+        self
+    }
+
+    /// Sets the options on the TCP socket of each connection, e.g. keepalive to notice clients
+    /// that have gone without closing their connections.
+    pub fn socket_options(mut self, socket_options: SocketOptions) -> Self {
+        self.config.tcp_config.socket_options = socket_options;
+        self
+    }
+
+    /// Sets the most TCP connections that one IP address may open per minute, 0 for no limit.
+    pub fn max_connection_rate(mut self, max_connection_rate: usize) -> Self {
+        self.config.tcp_config.max_connection_rate = max_connection_rate;
+        self
+    }
+
+    /// Sets the most TCP connections that may be open without an activated session, 0 for no
+    /// limit. It stops clients that connect and never authenticate, or never say anything, from
+    /// using up the connections of the server.
+    pub fn max_unauthenticated_connections(
+        mut self,
+        max_unauthenticated_connections: usize,
+    ) -> Self {
+        self.config.tcp_config.max_unauthenticated_connections = max_unauthenticated_connections;
+        self
+    }
+
     /// Discovery endpoint urls - the urls of this server used by clients to get endpoints.
     /// If the url is relative, e.g. "/" then the code will make a url for you using the port/host
     /// settings as they are at the time this function is executed.
@@ -307,6 +357,12 @@ impl ServerBuilder {
         self
     }
 
+    /// Set the maximum number of sessions in the server, 0 for no limit
+    pub fn max_sessions(mut self, max_sessions: usize) -> Self {
+        self.config.limits.max_sessions = max_sessions;
+        self
+    }
+
     /// Set the maximum number of subscriptions in a session
     pub fn max_subscriptions(mut self, max_subscriptions: usize) -> Self {
         self.config.limits.max_subscriptions = max_subscriptions;
@@ -319,6 +375,20 @@ impl ServerBuilder {
         self
     }
 
+    /// Set the minimum sampling interval in seconds. Monitored items that ask to be sampled
+    /// faster, or as fast as possible, are revised to this interval.
+    pub fn min_sampling_interval(mut self, min_sampling_interval: f64) -> Self {
+        self.config.limits.min_sampling_interval = min_sampling_interval;
+        self
+    }
+
+    /// Set the minimum publishing interval in seconds. Subscriptions that ask to publish faster
+    /// are revised to this interval.
+    pub fn min_publishing_interval(mut self, min_publishing_interval: f64) -> Self {
+        self.config.limits.min_publishing_interval = min_publishing_interval;
+        self
+    }
+
     /// Set the max array length in elements
     pub fn max_array_length(mut self, max_array_length: usize) -> Self {
         self.config.limits.max_array_length = max_array_length;
@@ -362,6 +432,59 @@ impl ServerBuilder {
         self
     }
 
+    /// Set the maximum number of responses on a connection that may be waiting to be written
+    /// before the server sheds further requests on it with `BadTcpServerTooBusy`, 0 for no limit
+    pub fn max_queued_responses(mut self, max_queued_responses: usize) -> Self {
+        self.config.limits.max_queued_responses = max_queued_responses;
+        self
+    }
+
+    /// Set what the server does with a connection once `max_queued_responses` responses are
+    /// waiting to be written on it, instead of shedding further requests
+    pub fn queue_overflow_policy(mut self, queue_overflow_policy: QueueOverflowPolicy) -> Self {
+        self.config.limits.queue_overflow_policy = queue_overflow_policy;
+        self
+    }
+
+    /// Set that the subscriptions of sessions that time out or are closed without deleting them
+    /// are deleted. By default, they are parked until their lifetime ends so that another session
+    /// of the same user can transfer them.
+    pub fn delete_orphaned_subscriptions(mut self) -> Self {
+        self.config.limits.delete_orphaned_subscriptions = true;
+        self
+    }
+
+    /// Set the longest lifetime in hours that a client may give a durable subscription, or 0 if
+    /// clients cannot make subscriptions durable. Durable subscriptions are parked when their
+    /// session goes away, even if orphaned subscriptions are deleted.
+    pub fn max_durable_subscription_lifetime_hours(
+        mut self,
+        max_durable_subscription_lifetime_hours: u32,
+    ) -> Self {
+        self.config.limits.max_durable_subscription_lifetime_hours =
+            max_durable_subscription_lifetime_hours;
+        self
+    }
+
+    /// Set the maximum number of notification messages that a late subscription holds while it
+    /// waits for publish requests, 0 for no limit. The oldest are discarded beyond this.
+    pub fn max_queued_notifications(mut self, max_queued_notifications: usize) -> Self {
+        self.config.limits.max_queued_notifications = max_queued_notifications;
+        self
+    }
+
+    /// Set the limits of `Limits::bounded_memory()`, which bound the memory that the server
+    /// allocates for its clients on constrained devices. Whether clients can modify the address
+    /// space is kept, and any of the limits can still be set after this.
+    pub fn bounded_memory(mut self) -> Self {
+        let clients_can_modify_address_space = self.config.limits.clients_can_modify_address_space;
+        self.config.limits = Limits {
+            clients_can_modify_address_space,
+            ..Limits::bounded_memory()
+        };
+        self
+    }
+
     /// Sets the server to automatically trust client certs. This subverts the
     /// authentication during handshake, so only do this if you understand the risks.
     pub fn trust_client_certs(mut self) -> Self {
@@ -369,6 +492,15 @@ impl ServerBuilder {
         self
     }
 
+    /// Sets the server to trust a client cert that replaces a trusted one, i.e. one that has the
+    /// same subject key identifier and public key as a cert in the trusted folder.
+    pub fn match_subject_key_identifier(mut self) -> Self {
+        self.config
+            .certificate_validation
+            .match_subject_key_identifier = true;
+        self
+    }
+
     /// Set that clients can modify the address space, i.e. they can add or remove nodes through
     /// the node management service. By default, they cannot.
     pub fn clients_can_modify_address_space(mut self) -> Self {
@@ -388,4 +520,19 @@ impl ServerBuilder {
         self.config.performance.single_threaded_executor = false;
         self
     }
+
+    /// Sets whether the server only creates sessions over secure channels that are signed or
+    /// encrypted. Secure channels with security policy None then only serve discovery, i.e.
+    /// `GetEndpoints` and `FindServers`.
+    pub fn secure_sessions_only(mut self, secure_sessions_only: bool) -> Self {
+        self.config.secure_sessions_only = secure_sessions_only;
+        self
+    }
+
+    /// Sets the redundancy that the server advertises in its `ServerRedundancy` object, i.e. its
+    /// failover model and the other servers of its redundant set.
+    pub fn redundancy(mut self, redundancy: Redundancy) -> Self {
+        self.config.redundancy = redundancy;
+        self
+    }
 }
@@ -10,10 +10,26 @@ use crate::sync::*;
 use crate::types::{
     service_types::{CallMethodRequest, CallMethodResult, TimestampsToReturn},
     status_code::StatusCode,
-    AttributeId, DataValue, NodeId, NumericRange, QualifiedName,
+    AttributeId, DataValue, NodeId, NumericRange, QualifiedName, Variant, WriteMask,
 };
 
-use super::session::{Session, SessionManager};
+use super::{
+    address_space::UserAccessLevel,
+    session::{Session, SessionManager},
+};
+
+/// How a getter provides the value of a variable to the monitored items that sample it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// The server calls the getter each time a monitored item samples the value, at the sampling
+    /// interval of the item. A getter is only called for items that are due to sample, so a value
+    /// that nobody monitors is never polled.
+    Poll,
+    /// The application sets the value, e.g. with `AddressSpace::set_variable_value()`, whenever it
+    /// changes. Monitored items sample the value that was last set and never call the getter,
+    /// which is only called when a client reads the value.
+    Push,
+}
 
 /// An attribute getter trait is used to obtain the data value associated with the particular attribute id
 /// This allows server implementations to supply a value on demand, usually in response to a polling action
@@ -21,6 +37,9 @@ use super::session::{Session, SessionManager};
 ///
 /// `node_id` is the node to which the node belongs
 /// `attribute_id` is the attribute of the node to fetch a value for
+/// `index_range` is the part of an array or byte string value to return, e.g. to a client that reads
+/// a large value in chunks. Return only that part, e.g. with `Variant::range_of()`, rather than the
+/// whole value.
 ///
 /// Use `max_age` according to the OPC UA Part 4, Table 52 specification to determine how to return
 /// a value:
@@ -40,6 +59,11 @@ pub trait AttributeGetter {
         data_encoding: &QualifiedName,
         max_age: f64,
     ) -> Result<Option<DataValue>, StatusCode>;
+
+    /// Returns how the getter provides the value to monitored items. The default is to poll.
+    fn value_source(&self) -> ValueSource {
+        ValueSource::Poll
+    }
 }
 
 // An attribute setter. Sets the value on the specified attribute
@@ -55,6 +79,23 @@ pub trait AttributeSetter {
 }
 
 /// Called by RegisterNodes service
+/// A hook that the address space calls before a client writes the value of a variable, with the
+/// node id of the variable, the value to write and the session of the client. It returns the
+/// value to write, which may be the one it was given or a transformed one, or a status code such
+/// as `BadOutOfRange` that rejects the write. With an index range the value is the part of the
+/// value being written.
+///
+/// The hook is called with the address space locked so it must not try to access it.
+pub type BeforeWriteHook =
+    dyn FnMut(&NodeId, Variant, &Session) -> Result<Variant, StatusCode> + Send + Sync;
+
+/// A hook that the address space calls after the value of a variable has changed, either by
+/// a client's write or by `AddressSpace::set_variable_value()`, with the node id of the variable
+/// and its old and new values.
+///
+/// The hook is called with the address space locked so it must not try to access it.
+pub type ValueChangedHook = dyn FnMut(&NodeId, &DataValue, &DataValue) + Send + Sync;
+
 pub trait RegisterNodes {
     /// Called when a client calls the RegisterNodes service. This implementation should return a list
     /// of the same size and order containing node ids corresponding to the input, or aliases. The implementation
@@ -87,6 +128,41 @@ pub trait UnregisterNodes {
     ) -> Result<(), StatusCode>;
 }
 
+/// Gives the users of the server their own access to variables and the attributes of nodes.
+/// Called by the Read, Write and HistoryRead services, and when the `UserAccessLevel` or
+/// `UserWriteMask` attributes are read, for each node accessed by the user of an activated
+/// session.
+pub trait UserAccessLevels {
+    /// Returns the user access level of the variable for the user identified by the id of the
+    /// server user token that authenticated them, e.g. `ANONYMOUS_USER_TOKEN_ID`. The
+    /// `user_access_level` is the attribute of the variable, which can be returned as it is for
+    /// users that need no override.
+    ///
+    /// The user access level is further limited by the access level of the variable, so an
+    /// implementation cannot give a user access that the variable does not permit.
+    fn user_access_level(
+        &self,
+        user_token_id: &str,
+        node_id: &NodeId,
+        user_access_level: UserAccessLevel,
+    ) -> UserAccessLevel;
+
+    /// Returns the user write mask of the node for the user, which says which of its attributes
+    /// other than the value of a variable they may write. The `user_write_mask` is the attribute
+    /// of the node, or its write mask if it has no user write mask. The default implementation
+    /// returns it as it is.
+    ///
+    /// The user write mask is further limited by the write mask of the node.
+    fn user_write_mask(
+        &self,
+        _user_token_id: &str,
+        _node_id: &NodeId,
+        user_write_mask: WriteMask,
+    ) -> WriteMask {
+        user_write_mask
+    }
+}
+
 /// Called by the Method service when it invokes a method
 pub trait Method {
     /// A method is registered via the address space to a method id and optionally an object id.
@@ -99,3 +175,38 @@ pub trait Method {
         request: &CallMethodRequest,
     ) -> Result<CallMethodResult, StatusCode>;
 }
+
+/// Implements the program of a `ProgramStateMachineType` state machine. The address space calls
+/// the program when a client calls the `Start`, `Suspend`, `Resume`, `Halt` or `Reset` method of
+/// the state machine and the program is in a state that the method leaves. A status code refuses
+/// the call and leaves the program in its state, otherwise the state machine takes the transition.
+///
+/// The program is called with the address space locked so it must not try to access it. A program
+/// that completes or fails by itself takes the transition with
+/// `AddressSpace::fire_state_machine_transition()`.
+pub trait Program {
+    /// Called to start the program, with the input arguments of the `Start` method
+    fn start(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Ok(())
+    }
+
+    /// Called to suspend the program while it runs
+    fn suspend(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Ok(())
+    }
+
+    /// Called to resume the program after it was suspended
+    fn resume(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Ok(())
+    }
+
+    /// Called to halt the program while it is ready, running or suspended
+    fn halt(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Ok(())
+    }
+
+    /// Called to reset the program after it halted so it is ready to start again
+    fn reset(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`ConnectionLimiter`] that decides which TCP connections the server accepts
+//! before they have said a word, so a port scan or a misbehaving client that keeps connecting
+//! cannot use up the connections of the server.
+//!
+//! [`ConnectionLimiter`]: ./struct.ConnectionLimiter.html
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+};
+
+use tokio::time::{Duration, Instant};
+
+use crate::types::status_code::StatusCode;
+
+/// The period that the connection rate of an address is counted over
+const RATE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Addresses are forgotten once they have not connected for a period, but only when more than
+/// this many are tracked, so an idle server does not sweep them for nothing
+const MAX_TRACKED_ADDRESSES: usize = 1024;
+
+/// Admits or rejects each connection that the server accepts, following the connection limits
+/// of the TCP configuration
+pub(crate) struct ConnectionLimiter {
+    /// Most connections that one address may open per minute, 0 for no limit
+    max_connection_rate: usize,
+    /// Most connections that may be open without an activated session, 0 for no limit
+    max_unauthenticated_connections: usize,
+    /// When each address opened the connections that count towards its rate
+    recent_connections: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(
+        max_connection_rate: usize,
+        max_unauthenticated_connections: usize,
+    ) -> ConnectionLimiter {
+        ConnectionLimiter {
+            max_connection_rate,
+            max_unauthenticated_connections,
+            recent_connections: HashMap::new(),
+        }
+    }
+
+    /// Decides if a connection from the address is accepted, given how many connections are
+    /// open without an activated session. A connection that is accepted counts towards the rate
+    /// of its address, and one that is rejected does not.
+    pub fn admit(
+        &mut self,
+        address: IpAddr,
+        unauthenticated_connections: usize,
+        now: Instant,
+    ) -> Result<(), StatusCode> {
+        if self.max_unauthenticated_connections > 0
+            && unauthenticated_connections >= self.max_unauthenticated_connections
+        {
+            warn!(
+                "Connection from {} is rejected because {} connections have not activated a session",
+                address, unauthenticated_connections
+            );
+            return Err(StatusCode::BadTcpServerTooBusy);
+        }
+        if self.max_connection_rate > 0 {
+            if self.recent_connections.len() > MAX_TRACKED_ADDRESSES {
+                self.forget_idle_addresses(now);
+            }
+            let recent_connections = self.recent_connections.entry(address).or_default();
+            while let Some(opened) = recent_connections.front() {
+                if now.duration_since(*opened) < RATE_PERIOD {
+                    break;
+                }
+                recent_connections.pop_front();
+            }
+            if recent_connections.len() >= self.max_connection_rate {
+                warn!(
+                    "Connection from {} is rejected because it opened {} connections in the last minute",
+                    address,
+                    recent_connections.len()
+                );
+                return Err(StatusCode::BadTcpServerTooBusy);
+            }
+            recent_connections.push_back(now);
+        }
+        Ok(())
+    }
+
+    /// Stops tracking the addresses that have opened no connections within the period
+    fn forget_idle_addresses(&mut self, now: Instant) {
+        self.recent_connections.retain(|_, recent_connections| {
+            recent_connections
+                .back()
+                .map(|opened| now.duration_since(*opened) < RATE_PERIOD)
+                .unwrap_or(false)
+        });
+    }
+}
@@ -5,6 +5,7 @@
 //! Provides communication services for the server such as the transport layer and secure
 //! channel implementation
 
+pub(crate) mod connection_limiter;
 mod secure_channel_service;
 
 pub mod tcp_transport;
@@ -4,7 +4,7 @@
 
 use std::result::Result;
 
-use crate::core::{comms::prelude::*, supported_message::SupportedMessage};
+use crate::core::{comms::prelude::*, record_metric, supported_message::SupportedMessage};
 
 use crate::crypto::SecurityPolicy;
 use crate::types::{status_code::StatusCode, *};
@@ -98,6 +98,7 @@ impl SecureChannelService {
         // Test the request type
         let secure_channel_id = match request.request_type {
             SecurityTokenRequestType::Issue => {
+                #[cfg(not(feature = "tracing"))]
                 trace!("Request type == Issue");
                 // check to see if renew has been called before or not
                 if self.secure_channel_state.renew_count > 0 {
@@ -106,6 +107,7 @@ impl SecureChannelService {
                 self.secure_channel_state.create_secure_channel_id()
             }
             SecurityTokenRequestType::Renew => {
+                #[cfg(not(feature = "tracing"))]
                 trace!("Request type == Renew");
 
                 // Check for a duplicate nonce. It is invalid for the renew to use the same nonce
@@ -132,6 +134,7 @@ impl SecureChannelService {
         };
 
         // Check the requested security mode
+        #[cfg(not(feature = "tracing"))]
         debug!("Message security mode == {:?}", request.security_mode);
         match request.security_mode {
             MessageSecurityMode::None
@@ -186,6 +189,19 @@ impl SecureChannelService {
             },
             server_nonce: secure_channel.local_nonce_as_byte_string(),
         };
+        if request.request_type == SecurityTokenRequestType::Issue {
+            record_metric!(on_secure_channel_opened());
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            secure_channel_id = response.security_token.channel_id,
+            token_id = response.security_token.token_id,
+            request_type = ?request.request_type,
+            security_policy = %security_policy,
+            security_mode = ?security_mode,
+            revised_lifetime = response.security_token.revised_lifetime,
+            "secure channel opened"
+        );
         Ok(response.into())
     }
 
@@ -193,7 +209,10 @@ impl SecureChannelService {
         &mut self,
         _: &SupportedMessage,
     ) -> Result<SupportedMessage, StatusCode> {
+        #[cfg(not(feature = "tracing"))]
         info!("CloseSecureChannelRequest received, session closing");
+        #[cfg(feature = "tracing")]
+        tracing::info!("secure channel closed");
         Err(StatusCode::BadConnectionClosed)
     }
 }
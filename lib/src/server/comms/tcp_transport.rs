@@ -11,63 +11,148 @@
 //! left to asynchronous event handlers.
 use chrono::{self, Utc};
 use futures::StreamExt;
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 use tokio::{
     self,
-    io::AsyncWriteExt,
-    net::{
-        tcp::{OwnedReadHalf, OwnedWriteHalf},
-        TcpStream,
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        Notify,
     },
-    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     time::{interval_at, Duration, Instant},
 };
 
-use tokio::time::timeout;
+use tokio::time::timeout_at;
 use tokio_util::codec::FramedRead;
 
 use crate::core::{
     comms::{
+        capture::{capture, CaptureDirection, CaptureForm},
         message_writer::MessageWriter,
         secure_channel::SecureChannel,
         tcp_codec::{self, TcpCodec},
+        tcp_types::{ErrorMessage, ReceiveLimits},
     },
     prelude::*,
+    record_metric,
 };
 use crate::crypto::CertificateStore;
 use crate::sync::*;
-use crate::types::status_code::StatusCode;
+use crate::types::{clock, status_code::StatusCode, DateTimeUtc};
 
 use crate::server::{
     address_space::types::AddressSpace,
     comms::{secure_channel_service::SecureChannelService, transport::*},
+    config::QueueOverflowPolicy,
     services::message_handler::MessageHandler,
     session::SessionManager,
     state::ServerState,
     subscriptions::subscription::TickReason,
 };
 
+/// How long in milliseconds the writer is given to send an error message before the connection
+/// is closed without it
+const ERROR_MESSAGE_TIMEOUT_MS: u64 = 1000;
+
 /// Messages that may be sent to the writer.
 #[derive(Debug)]
-enum Message {
-    // Message for writer to quit right now.
-    Quit,
-    // A supported message with a request id
-    Message(u32, SupportedMessage),
+pub(crate) enum Message {
+    // Message for writer to quit right now, after sending the client an error message with the
+    // status if there is one
+    Quit(Option<StatusCode>),
+    // A supported message with a request id and optionally the time after which the client has
+    // stopped waiting for it
+    Message(u32, SupportedMessage, Option<DateTimeUtc>),
 }
 
 #[derive(Clone)]
 pub struct MessageSender {
     sender: UnboundedSender<Message>,
+    /// Number of messages sent to the writer that it has yet to write
+    queue_depth: Arc<AtomicUsize>,
+    /// Notified when the writer takes a message from the queue
+    message_taken: Arc<Notify>,
 }
 
 impl MessageSender {
+    pub(crate) fn new(sender: UnboundedSender<Message>) -> MessageSender {
+        MessageSender {
+            sender,
+            queue_depth: Arc::new(AtomicUsize::new(0)),
+            message_taken: Arc::new(Notify::new()),
+        }
+    }
+
     pub fn send_quit(&self) {
-        let _ = self.sender.send(Message::Quit);
+        let _ = self.sender.send(Message::Quit(None));
+    }
+
+    /// Sends the client an error message with the status, after which the connection is closed
+    pub fn send_error(&self, status_code: StatusCode) {
+        let _ = self.sender.send(Message::Quit(Some(status_code)));
     }
 
     pub fn send_message(&self, request_id: u32, message: SupportedMessage) {
-        let _ = self.sender.send(Message::Message(request_id, message));
+        self.send_response(request_id, message, None);
+    }
+
+    /// Sends a response that the client stops waiting for at the deadline. If the writer only
+    /// gets to the response after the deadline, it writes a `BadTimeout` service fault instead.
+    pub fn send_response(
+        &self,
+        request_id: u32,
+        message: SupportedMessage,
+        deadline: Option<DateTimeUtc>,
+    ) {
+        self.queue_depth.fetch_add(1, Ordering::Relaxed);
+        if self
+            .sender
+            .send(Message::Message(request_id, message, deadline))
+            .is_err()
+        {
+            self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of messages waiting to be written
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.load(Ordering::Relaxed)
+    }
+
+    /// Waits until fewer messages than the limit are waiting to be written
+    pub(crate) async fn wait_for_room(&self, max_queued_responses: usize) {
+        while self.queue_depth() >= max_queued_responses && !self.sender.is_closed() {
+            self.message_taken.notified().await;
+        }
+    }
+
+    /// Called by the writer for each message it takes from the queue
+    fn message_taken(&self) {
+        self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        self.message_taken.notify_one();
+    }
+}
+
+/// Limits the responses that may be waiting to be written on a connection, so that a client which
+/// stops reading them cannot make the server hold on to a growing queue
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct QueueLimits {
+    /// Number of waiting responses at which the connection overflows, 0 for no limit
+    max_queued_responses: usize,
+    /// What happens while the connection overflows
+    overflow_policy: QueueOverflowPolicy,
+}
+
+impl QueueLimits {
+    fn is_overflowing(&self, sender: &MessageSender) -> bool {
+        self.max_queued_responses > 0 && sender.queue_depth() >= self.max_queued_responses
     }
 }
 
@@ -75,11 +160,15 @@ struct ReadState {
     /// The associated connection
     pub transport: Arc<RwLock<TcpTransport>>,
     /// Sender of responses
-    pub sender: UnboundedSender<Message>,
-    /// Time to wait for a HELLO from the client
+    pub sender: MessageSender,
+    /// Time in seconds to wait for a HELLO, and then the first message, from the client
     pub hello_timeout: u32,
     /// Reader from which messages will be decoded
-    pub reader: OwnedReadHalf,
+    pub reader: Box<dyn AsyncRead + Unpin + Send>,
+    /// Write buffer, whose limits are revised to those of the client by its HELLO
+    pub send_buffer: Arc<Mutex<MessageWriter>>,
+    /// Limits of the responses waiting to be written
+    pub queue_limits: QueueLimits,
 }
 
 struct WriteState {
@@ -88,7 +177,7 @@ struct WriteState {
     /// Secure channel state
     pub secure_channel: Arc<RwLock<SecureChannel>>,
     /// Writing portion of socket
-    pub writer: OwnedWriteHalf,
+    pub writer: Box<dyn AsyncWrite + Unpin + Send>,
     /// Write buffer (protected since it might be accessed by publish response / event activity)
     pub send_buffer: Arc<Mutex<MessageWriter>>,
 }
@@ -116,8 +205,14 @@ pub struct TcpTransport {
     client_protocol_version: u32,
     /// Last decoded sequence number
     last_received_sequence_number: u32,
-    /// A message may consist of one or more chunks which are stored here until complete.
+    /// A message may consist of one or more chunks which are stored here until complete. The
+    /// vector is reused for every message, so it is allocated once when the number of chunks in a
+    /// message is limited.
     pending_chunks: Vec<MessageChunk>,
+    /// Size of the bodies of the pending chunks
+    pending_message_size: usize,
+    /// Limits of the chunks and messages received from the client, agreed during HELLO
+    receive_limits: ReceiveLimits,
     /// Sessions associated with this connection. Normally there would be one, but potentially there could be more
     session_manager: Arc<RwLock<SessionManager>>,
 }
@@ -164,6 +259,12 @@ impl TcpTransport {
             let config = trace_read_lock!(server_state.config);
             config.decoding_options()
         };
+        // Room for the chunks of the largest message, and the abort chunk that may end it
+        let pending_chunks_capacity = if decoding_options.max_chunk_count > 0 {
+            decoding_options.max_chunk_count + 1
+        } else {
+            2
+        };
         let secure_channel = Arc::new(RwLock::new(SecureChannel::new(
             certificate_store.clone(),
             Role::Server,
@@ -192,7 +293,9 @@ impl TcpTransport {
             secure_channel_service,
             client_protocol_version: 0,
             last_received_sequence_number: 0,
-            pending_chunks: Vec::with_capacity(2),
+            pending_chunks: Vec::with_capacity(pending_chunks_capacity),
+            pending_message_size: 0,
+            receive_limits: ReceiveLimits::default(),
             session_manager,
         }
     }
@@ -217,10 +320,27 @@ impl TcpTransport {
             }
         );
 
+        let client_address = socket.peer_addr().ok();
+        Self::run_stream(connection, socket, client_address, looping_interval_ms);
+    }
+
+    /// Runs the session over any stream, e.g. the in-memory stream of a loopback connection.
+    /// Like `run()`, this function spawns tokio tasks and returns immediately.
+    pub fn run_stream<S>(
+        connection: Arc<RwLock<TcpTransport>>,
+        stream: S,
+        client_address: Option<SocketAddr>,
+        looping_interval_ms: f64,
+    ) where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
         // Store the address of the client
         let (send_buffer_size, receive_buffer_size) = {
             let mut connection = trace_write_lock!(connection);
-            connection.client_address = Some(socket.peer_addr().unwrap());
+            connection.client_address = client_address;
+            connection
+                .message_handler
+                .set_client_address(client_address);
             connection.transport_state = TransportState::WaitingHello;
             let server_state = trace_read_lock!(connection.server_state);
             (
@@ -229,14 +349,28 @@ impl TcpTransport {
             )
         };
 
+        // Everything that happens on the connection is recorded inside a span for it
+        #[cfg(feature = "tracing")]
+        let span = {
+            let connection = trace_read_lock!(connection);
+            tracing::info_span!(
+                "connection",
+                transport_id = %connection.transport_id,
+                client_address = ?connection.client_address,
+            )
+        };
+
         // Spawn the tasks we need to run
-        tokio::spawn(Self::spawn_session_handler_task(
+        let task = Self::spawn_session_handler_task(
             connection,
-            socket,
+            stream,
             looping_interval_ms,
             send_buffer_size,
             receive_buffer_size,
-        ));
+        );
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, span);
+        tokio::spawn(task);
     }
 
     async fn write_bytes_task(mut write_state: WriteState) -> WriteState {
@@ -244,28 +378,40 @@ impl TcpTransport {
             let mut send_buffer = trace_lock!(write_state.send_buffer);
             send_buffer.bytes_to_write()
         };
-        let result = write_state.writer.write_all(&bytes_to_write).await;
-        if let Err(err) = result {
-            error!("Write IO error {:?}", err);
-            let mut transport = trace_write_lock!(write_state.transport);
-            transport.finish(StatusCode::BadCommunicationError);
+        capture(CaptureDirection::Sent, CaptureForm::Raw, &bytes_to_write);
+        match write_state.writer.write_all(&bytes_to_write).await {
+            Ok(()) => {
+                record_metric!(on_bytes_sent(bytes_to_write.len()));
+            }
+            Err(err) => {
+                error!("Write IO error {:?}", err);
+                let mut transport = trace_write_lock!(write_state.transport);
+                transport.finish(StatusCode::BadCommunicationError);
+            }
         }
         write_state
     }
 
-    async fn spawn_session_handler_task(
+    async fn spawn_session_handler_task<S>(
         transport: Arc<RwLock<TcpTransport>>,
-        socket: TcpStream,
+        stream: S,
         looping_interval_ms: f64,
         send_buffer_size: usize,
         receive_buffer_size: usize,
-    ) {
+    ) where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
         // The reader task will send responses, the writer task will receive responses
         let (tx, rx) = unbounded_channel();
+        let tx = MessageSender::new(tx);
         let send_buffer = Arc::new(Mutex::new(MessageWriter::new(send_buffer_size, 0, 0)));
 
-        let (reader, writer) = socket.into_split();
-        let (hello_timeout, secure_channel) = {
+        let (reader, writer) = tokio::io::split(stream);
+        let (reader, writer): (
+            Box<dyn AsyncRead + Unpin + Send>,
+            Box<dyn AsyncWrite + Unpin + Send>,
+        ) = (Box::new(reader), Box::new(writer));
+        let (hello_timeout, secure_channel, queue_limits) = {
             let transport = trace_read_lock!(transport);
             let server_state = trace_read_lock!(transport.server_state);
             let server_config = trace_read_lock!(server_state.config);
@@ -277,6 +423,10 @@ impl TcpTransport {
             (
                 server_config.tcp_config.hello_timeout,
                 transport.secure_channel.clone(),
+                QueueLimits {
+                    max_queued_responses: server_config.limits.max_queued_responses,
+                    overflow_policy: server_config.limits.queue_overflow_policy,
+                },
             )
         };
 
@@ -285,16 +435,18 @@ impl TcpTransport {
             hello_timeout,
             transport: transport.clone(),
             sender: tx.clone(),
+            send_buffer: send_buffer.clone(),
+            queue_limits,
         };
 
         // Spawn all the tasks that monitor the session - the subscriptions, finished state,
         // reading and writing.
         let final_status = tokio::select! {
-            _ = Self::spawn_subscriptions_task(transport.clone(), tx.clone(), looping_interval_ms) => {
+            status = Self::spawn_subscriptions_task(transport.clone(), tx.clone(), queue_limits, looping_interval_ms) => {
                 log::trace!("Closing connection because the subscription task failed");
-                Ok(())
+                status
             }
-            status = Self::spawn_writing_loop_task(writer, rx, secure_channel, transport.clone(), send_buffer) => {
+            status = Self::spawn_writing_loop_task(writer, rx, tx.clone(), secure_channel, transport.clone(), send_buffer) => {
                 log::trace!("Closing connection after the write task ended");
                 status
             }
@@ -304,7 +456,10 @@ impl TcpTransport {
             }
         }.err().unwrap_or(StatusCode::Good);
 
+        #[cfg(not(feature = "tracing"))]
         log::info!("Closing connection with status {}", final_status);
+        #[cfg(feature = "tracing")]
+        tracing::info!(status = %final_status, "connection closed");
         // Both the read and write halves of the tcp stream are dropped at this point,
         // and the connection is closed
         let mut transport = trace_write_lock!(transport);
@@ -314,8 +469,9 @@ impl TcpTransport {
     /// Spawns the writing loop task. The writing loop takes messages to send off of a queue
     /// and sends them to the stream.
     async fn spawn_writing_loop_task(
-        writer: OwnedWriteHalf,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
         mut receiver: UnboundedReceiver<Message>,
+        sender: MessageSender,
         secure_channel: Arc<RwLock<SecureChannel>>,
         transport: Arc<RwLock<TcpTransport>>,
         send_buffer: Arc<Mutex<MessageWriter>>,
@@ -331,16 +487,42 @@ impl TcpTransport {
         while let Some(message) = receiver.recv().await {
             trace!("Writing loop received message: {:?}", message);
             let (request_id, response) = match message {
-                Message::Quit => {
+                Message::Quit(None) => {
                     debug!("Server writer received a quit so it will quit");
                     return Ok(());
                 }
-                Message::Message(request_id, response) => {
+                Message::Quit(Some(status_code)) => {
+                    debug!(
+                        "Server writer is sending error {} and will quit",
+                        status_code
+                    );
+                    let error = ErrorMessage::from_status_code(status_code);
+                    trace_lock!(write_state.send_buffer).write_error(&error)?;
+                    write_state = Self::write_bytes_task(write_state).await;
+                    let _ = write_state.writer.flush().await;
+                    return Err(status_code);
+                }
+                Message::Message(request_id, response, deadline) => {
+                    sender.message_taken();
                     if let SupportedMessage::Invalid(_) = response {
                         error!("Writer terminating - received an invalid message");
                         return Err(StatusCode::BadCommunicationError);
                     }
-                    (request_id, response)
+                    match deadline {
+                        Some(deadline) if clock::now() > deadline => {
+                            // The client has given up waiting, so don't waste time sending it
+                            debug!(
+                                "Response to request {} is past its timeout hint and is replaced with a timeout",
+                                request_id
+                            );
+                            let request_handle = response.request_handle();
+                            (
+                                request_id,
+                                Self::service_fault(request_handle, StatusCode::BadTimeout),
+                            )
+                        }
+                        _ => (request_id, response),
+                    }
                 }
             };
 
@@ -352,7 +534,31 @@ impl TcpTransport {
                         send_buffer.write_ack(&ack)?;
                     }
                     msg => {
-                        send_buffer.write(request_id, msg, &secure_channel)?;
+                        let request_handle = msg.request_handle();
+                        let message_type = Chunker::message_type(&msg);
+                        match send_buffer.write(request_id, msg, &secure_channel) {
+                            Err(StatusCode::BadResponseTooLarge)
+                                if message_type == MessageChunkType::Message =>
+                            {
+                                // The response has been ended with an abort chunk, which tells
+                                // the client that it is too large to receive
+                                debug!(
+                                    "Response to request {} is too large and has been aborted",
+                                    request_id
+                                );
+                            }
+                            Err(StatusCode::BadResponseTooLarge) => {
+                                // The client is told the response is too large to receive
+                                let fault = Self::service_fault(
+                                    request_handle,
+                                    StatusCode::BadResponseTooLarge,
+                                );
+                                send_buffer.write(request_id, fault, &secure_channel)?;
+                            }
+                            result => {
+                                result?;
+                            }
+                        }
                     }
                 }
             }
@@ -361,12 +567,26 @@ impl TcpTransport {
         Ok(())
     }
 
+    /// Makes the service fault that is sent in place of a response that is too late or too large
+    fn service_fault(request_handle: u32, service_result: StatusCode) -> SupportedMessage {
+        ServiceFault {
+            response_header: ResponseHeader {
+                timestamp: DateTime::now(),
+                request_handle,
+                service_result,
+                service_diagnostics: DiagnosticInfo::default(),
+                string_table: None,
+                additional_header: ExtensionObject::null(),
+            },
+        }
+        .into()
+    }
+
     async fn wait_for_hello(
-        reader: &mut FramedRead<OwnedReadHalf, TcpCodec>,
-        hello_timeout: u32,
+        reader: &mut FramedRead<Box<dyn AsyncRead + Unpin + Send>, TcpCodec>,
+        handshake_deadline: Instant,
     ) -> Result<HelloMessage, StatusCode> {
-        let duration = Duration::from_secs(u64::from(hello_timeout));
-        match timeout(duration, reader.next()).await {
+        match timeout_at(handshake_deadline, reader.next()).await {
             // We process a timeout(stream_element(tcp_message))
             Err(_timeout) => {
                 warn!("Session has been waiting for a hello for more than the timeout period and will now close");
@@ -375,7 +595,7 @@ impl TcpTransport {
             Ok(Some(Ok(tcp_codec::Message::Hello(hello)))) => Ok(hello),
             Ok(Some(Ok(bad_msg))) => {
                 log::error!("Expected a hello message, got {:?} instead", bad_msg);
-                Err(StatusCode::BadCommunicationError)
+                Err(StatusCode::BadTcpMessageTypeInvalid)
             }
             Ok(Some(Err(communication_err))) => {
                 error!(
@@ -388,6 +608,15 @@ impl TcpTransport {
         }
     }
 
+    /// Has the writer send the client an error message and close the connection, which the
+    /// reading loop waits for, as closing the connection first would drop the message. If the
+    /// writer does not close it soon, the reading loop does.
+    async fn reject(sender: &MessageSender, status_code: StatusCode) -> Result<(), StatusCode> {
+        sender.send_error(status_code);
+        tokio::time::sleep(Duration::from_millis(ERROR_MESSAGE_TIMEOUT_MS)).await;
+        Err(status_code)
+    }
+
     /// Spawns the reading loop where a reader task continuously reads messages, chunks from the
     /// input and process them. The reading task will terminate upon error.
     async fn spawn_reading_loop_task(
@@ -395,7 +624,7 @@ impl TcpTransport {
         send_buffer_size: usize,
         receive_buffer_size: usize,
     ) -> Result<(), StatusCode> {
-        let (transport, mut sender) = { (read_state.transport.clone(), read_state.sender.clone()) };
+        let (transport, sender) = { (read_state.transport.clone(), read_state.sender.clone()) };
 
         let decoding_options = {
             let transport = trace_read_lock!(transport);
@@ -403,25 +632,72 @@ impl TcpTransport {
             secure_channel.decoding_options()
         };
 
-        // The reader reads frames from the codec, which are messages
+        // The reader reads frames from the codec, which are messages. A chunk cannot be larger
+        // than the receive buffer, so the read buffer is allocated once at that size.
+        let codec =
+            TcpCodec::new(decoding_options.clone()).with_max_frame_size(receive_buffer_size);
         let mut framed_read =
-            FramedRead::new(read_state.reader, TcpCodec::new(decoding_options.clone()));
-
-        let hello = Self::wait_for_hello(&mut framed_read, read_state.hello_timeout).await?;
-        trace_write_lock!(transport).process_hello(
-            hello,
-            &mut sender,
-            &decoding_options,
-            send_buffer_size,
-            receive_buffer_size,
-        )?;
+            FramedRead::with_capacity(read_state.reader, codec, receive_buffer_size);
+
+        // The client must send its hello, and the first message after it, before the deadline,
+        // so a connection that never says anything, or says it a byte at a time, is closed
+        let handshake_deadline =
+            Instant::now() + Duration::from_secs(u64::from(read_state.hello_timeout));
+        let send_buffer = read_state.send_buffer;
+        let hello = Self::wait_for_hello(&mut framed_read, handshake_deadline).await;
+        let hello_result = hello.and_then(|hello| {
+            trace_write_lock!(transport).process_hello(
+                hello,
+                &sender,
+                &send_buffer,
+                &decoding_options,
+                send_buffer_size,
+                receive_buffer_size,
+            )
+        });
+        match hello_result {
+            Err(StatusCode::BadConnectionClosed) => return Err(StatusCode::BadConnectionClosed),
+            Err(status_code) => return Self::reject(&sender, status_code).await,
+            Ok(()) => {}
+        }
+        let mut handshake_deadline = Some(handshake_deadline);
 
-        while let Some(next_msg) = framed_read.next().await {
+        let queue_limits = read_state.queue_limits;
+        loop {
+            // A client that is not reading its responses is not read from either, until the
+            // writer has caught up
+            if queue_limits.overflow_policy == QueueOverflowPolicy::Block
+                && queue_limits.is_overflowing(&sender)
+            {
+                debug!(
+                    "Reading is paused because {} responses are waiting to be written",
+                    sender.queue_depth()
+                );
+                sender
+                    .wait_for_room(queue_limits.max_queued_responses)
+                    .await;
+            }
+            let next_msg = match handshake_deadline.take() {
+                Some(handshake_deadline) => {
+                    match timeout_at(handshake_deadline, framed_read.next()).await {
+                        Ok(next_msg) => next_msg,
+                        Err(_) => {
+                            warn!("Connection did not open a secure channel within the hello timeout and will now close");
+                            return Self::reject(&sender, StatusCode::BadTimeout).await;
+                        }
+                    }
+                }
+                None => framed_read.next().await,
+            };
+            let next_msg = match next_msg {
+                Some(next_msg) => next_msg,
+                None => break,
+            };
             match next_msg {
                 Ok(tcp_codec::Message::Chunk(chunk)) => {
                     log::trace!("Received message chunk: {:?}", chunk);
                     let mut transport = trace_write_lock!(transport);
-                    transport.process_chunk(chunk, &mut sender)?
+                    transport.process_chunk(chunk, &sender)?
                 }
                 Ok(unexpected) => {
                     log::error!("Received unexpected message: {:?}", unexpected);
@@ -439,7 +715,8 @@ impl TcpTransport {
     /// Start the subscription timer to service subscriptions
     async fn spawn_subscriptions_task(
         transport: Arc<RwLock<TcpTransport>>,
-        sender: UnboundedSender<Message>,
+        sender: MessageSender,
+        queue_limits: QueueLimits,
         looping_interval_ms: f64,
     ) -> Result<(), StatusCode> {
         // Subscription events are passed sent from the monitor task to the receiver
@@ -460,7 +737,7 @@ impl TcpTransport {
 
             for (_node_id, session) in session_manager.sessions.iter() {
                 let mut session = trace_write_lock!(session);
-                let now = Utc::now();
+                let now = clock::now();
 
                 // Request queue might contain stale publish requests
                 session.expire_stale_publish_requests(&now);
@@ -472,28 +749,54 @@ impl TcpTransport {
                 if let Some(publish_responses) =
                     session.subscriptions_mut().take_publish_responses()
                 {
-                    for publish_response in publish_responses {
+                    for mut publish_response in publish_responses {
+                        if queue_limits.is_overflowing(&sender) {
+                            match queue_limits.overflow_policy {
+                                QueueOverflowPolicy::DropNotifications => {
+                                    debug!(
+                                        "Notifications of publish response {} are dropped because {} responses are waiting to be written",
+                                        publish_response.request_id,
+                                        sender.queue_depth()
+                                    );
+                                    publish_response.drop_notifications();
+                                }
+                                QueueOverflowPolicy::Disconnect => {
+                                    warn!(
+                                        "Connection is closed because {} responses are waiting to be written",
+                                        sender.queue_depth()
+                                    );
+                                    return Err(StatusCode::BadTcpServerTooBusy);
+                                }
+                                QueueOverflowPolicy::Reject | QueueOverflowPolicy::Block => {}
+                            }
+                        }
                         trace!(
                             "<-- Sending a Publish Response{}, {:?}",
                             publish_response.request_id,
                             &publish_response.response
                         );
                         // Messages will be sent by the writing task
-                        sender
-                            .send(Message::Message(
-                                publish_response.request_id,
-                                publish_response.response,
-                            ))
-                            .map_err(|e| {
-                                error!("Unable to send publish response to writer task: {}", e);
-                                StatusCode::BadUnexpectedError
-                            })?;
+                        if sender.sender.is_closed() {
+                            error!("Unable to send publish response to writer task");
+                            return Err(StatusCode::BadUnexpectedError);
+                        }
+                        sender.send_message(publish_response.request_id, publish_response.response);
                     }
                 }
             }
         }
     }
 
+    /// Tests if a session on the connection has been activated, i.e. the client has
+    /// authenticated
+    pub fn has_activated_session(&self) -> bool {
+        let session_manager = trace_read_lock!(self.session_manager);
+        session_manager
+            .sessions
+            .values()
+            .any(|session| trace_read_lock!(session).is_activated())
+    }
+
     /// Test if the connection should abort
     pub fn is_server_abort(&self) -> bool {
         let server_state = trace_read_lock!(self.server_state);
@@ -503,7 +806,8 @@ impl TcpTransport {
     fn process_hello(
         &mut self,
         hello: HelloMessage,
-        sender: &mut UnboundedSender<Message>,
+        sender: &MessageSender,
+        send_buffer: &Mutex<MessageWriter>,
         decoding_options: &DecodingOptions,
         send_buffer_size: usize,
         receive_buffer_size: usize,
@@ -532,24 +836,44 @@ impl TcpTransport {
 
         let client_protocol_version = hello.protocol_version;
 
-        // Send acknowledge
-        let mut acknowledge = AcknowledgeMessage {
-            message_header: MessageHeader::new(MessageType::Acknowledge),
-            protocol_version: server_protocol_version,
-            receive_buffer_size: receive_buffer_size as u32,
-            send_buffer_size: send_buffer_size as u32,
-            max_message_size: decoding_options.max_message_size as u32,
-            max_chunk_count: decoding_options.max_chunk_count as u32,
+        // Send acknowledge, with buffer sizes that suit both ends
+        let acknowledge = AcknowledgeMessage::new(
+            &hello,
+            receive_buffer_size,
+            send_buffer_size,
+            decoding_options.max_message_size,
+            decoding_options.max_chunk_count,
+        );
+
+        // Chunks that are sent must fit the client's receive buffer, and responses must not
+        // exceed what it receives
+        trace_lock!(send_buffer).set_limits(
+            acknowledge.send_buffer_size as usize,
+            hello.max_message_size as usize,
+            hello.max_chunk_count as usize,
+        );
+        self.receive_limits = ReceiveLimits {
+            receive_buffer_size: acknowledge.receive_buffer_size as usize,
+            max_message_size: decoding_options.max_message_size,
+            max_chunk_count: decoding_options.max_chunk_count,
         };
-        acknowledge.message_header.message_size = acknowledge.byte_len() as u32;
-        let acknowledge: SupportedMessage = acknowledge.into();
 
         // New state
         self.transport_state = TransportState::ProcessMessages;
         self.client_protocol_version = client_protocol_version;
 
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            endpoint_url = %hello.endpoint_url,
+            protocol_version = client_protocol_version,
+            receive_buffer_size = acknowledge.receive_buffer_size,
+            send_buffer_size = acknowledge.send_buffer_size,
+            "hello received, sending acknowledge"
+        );
+
+        #[cfg(not(feature = "tracing"))]
         debug!("Sending ACK");
-        let _ = sender.send(Message::Message(0, acknowledge));
+        sender.send_message(0, acknowledge.into());
         Ok(())
     }
 
@@ -571,28 +895,41 @@ impl TcpTransport {
     fn process_chunk(
         &mut self,
         chunk: MessageChunk,
-        sender: &mut UnboundedSender<Message>,
+        sender: &MessageSender,
     ) -> std::result::Result<(), StatusCode> {
         let decoding_options = {
             let secure_channel = trace_read_lock!(self.secure_channel);
             secure_channel.decoding_options()
         };
 
+        record_metric!(on_bytes_received(chunk.data.len()));
+        capture(CaptureDirection::Received, CaptureForm::Raw, &chunk.data);
+        self.receive_limits.check_chunk_size(chunk.data.len())?;
         let message_header = chunk.message_header(&decoding_options)?;
 
+        // Decrypt / verify chunk if necessary
+        let chunk = {
+            let mut secure_channel = trace_write_lock!(self.secure_channel);
+            secure_channel.verify_and_remove_security(&chunk.data)?
+        };
+        capture(
+            CaptureDirection::Received,
+            CaptureForm::Decrypted,
+            &chunk.data,
+        );
+
         if message_header.is_final == MessageIsFinalType::FinalError {
-            info!("Discarding chunks as after receiving one marked as final error");
-            self.pending_chunks.clear();
-            Ok(())
+            self.process_abort_chunk(chunk)
         } else {
-            // Decrypt / verify chunk if necessary
-            let chunk = {
-                let mut secure_channel = trace_write_lock!(self.secure_channel);
-                secure_channel.verify_and_remove_security(&chunk.data)?
+            // Drop the connection if the message exceeds the number of chunks or the size that
+            // is received
+            let body_length = {
+                let secure_channel = trace_read_lock!(self.secure_channel);
+                chunk.chunk_info(&secure_channel)?.body_length
             };
-
-            // TODO check how many chunks are pending, produce error and drop connection if it exceeds
-            //  supported chunk limit
+            self.pending_message_size += body_length;
+            self.receive_limits
+                .check_message(self.pending_chunks.len() + 1, self.pending_message_size)?;
 
             // Put the chunk on the list
             self.pending_chunks.push(chunk);
@@ -606,24 +943,102 @@ impl TcpTransport {
         }
     }
 
+    /// Abandons the pending chunks of a message that the client has aborted. The secure channel
+    /// stays open, and the sequence numbers of the chunks are still validated.
+    fn process_abort_chunk(&mut self, chunk: MessageChunk) -> Result<(), StatusCode> {
+        let mut chunks = std::mem::take(&mut self.pending_chunks);
+        self.pending_message_size = 0;
+        chunks.push(chunk);
+        let result = self.discard_aborted_chunks(&chunks);
+        // The emptied vector is kept for the chunks of the next message
+        chunks.clear();
+        self.pending_chunks = chunks;
+        result
+    }
+
+    fn discard_aborted_chunks(&mut self, chunks: &[MessageChunk]) -> Result<(), StatusCode> {
+        let secure_channel = trace_read_lock!(self.secure_channel);
+        self.last_received_sequence_number = Chunker::validate_chunks(
+            self.last_received_sequence_number + 1,
+            &secure_channel,
+            chunks,
+        )?;
+        let request_id = chunks[0]
+            .chunk_info(&secure_channel)?
+            .sequence_header
+            .request_id;
+        let (status_code, reason) =
+            Chunker::decode_abort(&chunks[chunks.len() - 1], &secure_channel)?;
+        info!(
+            "Discarding {} chunks of request {} after the client aborted it with {}, reason {}",
+            chunks.len() - 1,
+            request_id,
+            status_code,
+            reason
+        );
+        Ok(())
+    }
+
     fn process_final_chunk(
         &mut self,
         message_header: &MessageChunkHeader,
-        sender: &mut UnboundedSender<Message>,
+        sender: &MessageSender,
+    ) -> Result<(), StatusCode> {
+        // Take the pending chunks and turn them into a message
+        let mut chunks = std::mem::take(&mut self.pending_chunks);
+        self.pending_message_size = 0;
+        let result = self.process_message_chunks(&chunks, message_header, sender);
+        // The emptied vector is kept for the chunks of the next message
+        chunks.clear();
+        self.pending_chunks = chunks;
+        result
+    }
+
+    fn process_message_chunks(
+        &mut self,
+        chunks: &[MessageChunk],
+        message_header: &MessageChunkHeader,
+        sender: &MessageSender,
     ) -> Result<(), StatusCode> {
-        // Drain pending chunks and turn them into a message
-        let chunks: Vec<MessageChunk> = self.pending_chunks.drain(..).collect();
         let chunk_info = {
             let secure_channel = trace_read_lock!(self.secure_channel);
             chunks[0].chunk_info(&secure_channel)?
         };
 
         // Handle the request, and then send the response back to the caller
-        let request = self.turn_received_chunks_into_message(&chunks)?;
         let request_id = chunk_info.sequence_header.request_id;
-
-        let sender = MessageSender {
-            sender: sender.clone(),
+        let request = match self.turn_received_chunks_into_message(chunks) {
+            Ok(request) => request,
+            Err(StatusCode::BadServiceUnsupported)
+                if message_header.message_type == MessageChunkType::Message =>
+            {
+                // A request for a service that the server does not support, e.g. one whose
+                // service set is not compiled in, is answered with a fault if its header decodes
+                let request_header = {
+                    let secure_channel = trace_read_lock!(self.secure_channel);
+                    Chunker::decode_request_header(chunks, &secure_channel)
+                };
+                return match request_header {
+                    Ok(request_header) => {
+                        debug!(
+                            "Request {} is for an unsupported service, sending a service fault",
+                            request_header.request_handle
+                        );
+                        let fault =
+                            ServiceFault::new(&request_header, StatusCode::BadServiceUnsupported);
+                        sender.send_message(request_id, fault.into());
+                        Ok(())
+                    }
+                    Err(status_code) => {
+                        record_metric!(on_decode_error());
+                        Err(status_code)
+                    }
+                };
+            }
+            Err(status_code) => {
+                record_metric!(on_decode_error());
+                return Err(status_code);
+            }
         };
 
         match message_header.message_type {
@@ -631,12 +1046,12 @@ impl TcpTransport {
                 request_id,
                 &request,
                 &chunk_info.security_header,
-                &sender,
+                sender,
             ),
             MessageChunkType::CloseSecureChannel => {
-                self.process_close_secure_channel(request_id, &request, &sender)
+                self.process_close_secure_channel(request_id, &request, sender)
             }
-            MessageChunkType::Message => self.process_message(request_id, &request, &sender),
+            MessageChunkType::Message => self.process_message(request_id, &request, sender),
         }
     }
 
@@ -10,9 +10,19 @@ use std::{
 };
 
 use crate::{
-    core::{comms::url::url_matches_except_host, config::Config},
-    crypto::{CertificateStore, SecurityPolicy, Thumbprint},
-    types::{service_types::ApplicationType, DecodingOptions, MessageSecurityMode, UAString},
+    core::{
+        comms::{
+            socket_options::SocketOptions,
+            tcp_types::MIN_CHUNK_SIZE,
+            url::{local_socket_url, url_matches_except_host, EndpointUrl},
+        },
+        config::Config,
+    },
+    crypto::{CertificateStore, SecurityPolicy, SystemCertificateStore, Thumbprint},
+    types::{
+        service_types::{ApplicationType, RedundancySupport},
+        DecodingOptions, MessageSecurityMode, UAString,
+    },
 };
 
 use super::constants;
@@ -24,12 +34,28 @@ const SEND_BUFFER_SIZE: usize = std::u16::MAX as usize;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct TcpConfig {
-    /// Timeout for hello on a session in seconds
+    /// Timeout in seconds for a new connection to send its hello and then the first message that
+    /// opens its secure channel. Connections that take longer are closed.
     pub hello_timeout: u32,
     /// The hostname to supply in the endpoints
     pub host: String,
     /// The port number of the service
     pub port: u16,
+    /// The path of a Unix domain socket, or the name of a named pipe on Windows, that the server
+    /// also accepts connections on from clients on the same host, e.g. `/run/opcua.sock`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_socket: Option<String>,
+    /// Options that are set on the TCP socket of each connection that is accepted
+    #[serde(default)]
+    pub socket_options: SocketOptions,
+    /// Most TCP connections that one IP address may open per minute, 0 for no limit. Further
+    /// connections are rejected with `BadTcpServerTooBusy`.
+    #[serde(default)]
+    pub max_connection_rate: usize,
+    /// Most TCP connections that may be open without an activated session, 0 for no limit.
+    /// Further connections are rejected with `BadTcpServerTooBusy`.
+    #[serde(default)]
+    pub max_unauthenticated_connections: usize,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -133,6 +159,9 @@ pub struct Limits {
     /// set. This is a very broad flag and is likely to require more fine grained per user control
     /// in a later revision. By default, this value is `false`
     pub clients_can_modify_address_space: bool,
+    /// Maximum number of sessions in the server across all connections, 0 for no limit
+    #[serde(default)]
+    pub max_sessions: usize,
     /// Maximum number of subscriptions in a session, 0 for no limit
     pub max_subscriptions: usize,
     /// Maximum number of monitored items per subscription, 0 for no limit
@@ -153,10 +182,33 @@ pub struct Limits {
     pub max_message_size: usize,
     /// Maximum chunk count
     pub max_chunk_count: usize,
-    /// Send buffer size in bytes
+    /// Send buffer size in bytes, which is the largest chunk that is sent unless the client
+    /// receives smaller ones
     pub send_buffer_size: usize,
-    /// Receive buffer size in bytes
+    /// Receive buffer size in bytes, which is the largest chunk that is received unless the
+    /// client sends smaller ones
     pub receive_buffer_size: usize,
+    /// Maximum number of responses on a connection that may be waiting to be written before
+    /// the connection is treated as overflowing, 0 for no limit
+    #[serde(default)]
+    pub max_queued_responses: usize,
+    /// What the server does with a connection while `max_queued_responses` responses are waiting
+    /// to be written on it
+    #[serde(default)]
+    pub queue_overflow_policy: QueueOverflowPolicy,
+    /// Deletes the subscriptions of sessions that time out, or that are closed without deleting
+    /// them, instead of parking them until their lifetime ends so that another session of the
+    /// same user can transfer them. By default, they are parked.
+    #[serde(default)]
+    pub delete_orphaned_subscriptions: bool,
+    /// Longest lifetime in hours that a client may give a subscription it makes durable through
+    /// the `SetSubscriptionDurable` method, 0 if subscriptions cannot be made durable
+    #[serde(default = "Limits::default_max_durable_subscription_lifetime_hours")]
+    pub max_durable_subscription_lifetime_hours: u32,
+    /// Maximum number of notification messages that a late subscription holds while it waits for
+    /// publish requests, 0 for no limit. The oldest are discarded to make room for new ones.
+    #[serde(default = "Limits::default_max_queued_notifications")]
+    pub max_queued_notifications: usize,
 }
 
 impl Default for Limits {
@@ -166,6 +218,7 @@ impl Default for Limits {
             max_array_length: decoding_options.max_array_length,
             max_string_length: decoding_options.max_string_length,
             max_byte_string_length: decoding_options.max_byte_string_length,
+            max_sessions: 0,
             max_subscriptions: constants::DEFAULT_MAX_SUBSCRIPTIONS,
             max_monitored_items_per_sub: constants::DEFAULT_MAX_MONITORED_ITEMS_PER_SUB,
             max_monitored_item_queue_size: constants::MAX_DATA_CHANGE_QUEUE_SIZE,
@@ -176,10 +229,71 @@ impl Default for Limits {
             min_publishing_interval: constants::MIN_PUBLISHING_INTERVAL,
             send_buffer_size: SEND_BUFFER_SIZE,
             receive_buffer_size: RECEIVE_BUFFER_SIZE,
+            max_queued_responses: 0,
+            queue_overflow_policy: QueueOverflowPolicy::default(),
+            delete_orphaned_subscriptions: false,
+            max_durable_subscription_lifetime_hours:
+                Self::default_max_durable_subscription_lifetime_hours(),
+            max_queued_notifications: Self::default_max_queued_notifications(),
         }
     }
 }
 
+impl Limits {
+    fn default_max_durable_subscription_lifetime_hours() -> u32 {
+        constants::DEFAULT_MAX_DURABLE_SUBSCRIPTION_LIFETIME_HOURS
+    }
+
+    fn default_max_queued_notifications() -> usize {
+        constants::DEFAULT_MAX_QUEUED_NOTIFICATIONS
+    }
+
+    /// Returns limits for constrained devices such as small gateways, which bound the memory that
+    /// the server allocates for its clients. Few sessions, subscriptions and monitored items are
+    /// allowed, chunks are small and a connection is not read from while responses are waiting to
+    /// be written on it, so the queues and buffers of the server cannot grow beyond these sizes.
+    /// The room for the sessions, subscriptions, monitored items and chunks that these limits
+    /// allow is allocated up front, when the server, session, subscription or connection is made.
+    pub fn bounded_memory() -> Self {
+        Self {
+            max_sessions: 4,
+            max_subscriptions: 4,
+            max_monitored_items_per_sub: 100,
+            max_monitored_item_queue_size: 10,
+            max_queued_notifications: 10,
+            max_array_length: 1000,
+            max_string_length: 4096,
+            max_byte_string_length: 4096,
+            max_message_size: 65536,
+            max_chunk_count: 8,
+            send_buffer_size: MIN_CHUNK_SIZE,
+            receive_buffer_size: MIN_CHUNK_SIZE,
+            max_queued_responses: 8,
+            queue_overflow_policy: QueueOverflowPolicy::Block,
+            ..Default::default()
+        }
+    }
+}
+
+/// What the server does with a connection whose client is not reading the responses that are
+/// written to it, once `max_queued_responses` of them are waiting to be written
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum QueueOverflowPolicy {
+    /// Further requests are answered with `BadTcpServerTooBusy`, except for CloseSession and
+    /// Cancel requests. This is the default.
+    #[default]
+    Reject,
+    /// No further requests are read from the connection until some of the responses have been
+    /// written, so the client cannot send more than the network buffers hold
+    Block,
+    /// Requests are answered as for `Reject`, and publish responses are sent without their
+    /// notifications and with a `GoodOverload` service result. The notifications that are dropped
+    /// can still be republished until the client acknowledges them.
+    DropNotifications,
+    /// The connection is closed with `BadTcpServerTooBusy`
+    Disconnect,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct CertificateValidation {
     /// Auto trusts client certificates. For testing/samples only unless you're sure what you're
@@ -187,6 +301,10 @@ pub struct CertificateValidation {
     pub trust_client_certs: bool,
     /// Check the valid from/to fields of a certificate
     pub check_time: bool,
+    /// Trusts a client certificate that is not in the trusted folder if one there has the same
+    /// subject key identifier and public key, e.g. because it was renewed with the same key
+    #[serde(default)]
+    pub match_subject_key_identifier: bool,
 }
 
 impl Default for CertificateValidation {
@@ -194,6 +312,7 @@ impl Default for CertificateValidation {
         Self {
             trust_client_certs: false,
             check_time: true,
+            match_subject_key_identifier: false,
         }
     }
 }
@@ -210,6 +329,9 @@ pub struct ServerEndpoint {
     pub security_level: u8,
     /// Password security policy when a client supplies a user name identity token
     pub password_security_policy: Option<String>,
+    /// Security policy that a client signs with when it supplies an X509 identity token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x509_security_policy: Option<String>,
     /// User tokens
     pub user_token_ids: BTreeSet<String>,
 }
@@ -223,6 +345,7 @@ impl<'a> From<(&'a str, SecurityPolicy, MessageSecurityMode, &'a [&'a str])> for
             security_mode: v.2.to_string(),
             security_level: Self::security_level(v.1, v.2),
             password_security_policy: None,
+            x509_security_policy: None,
             user_token_ids: v.3.iter().map(|id| id.to_string()).collect(),
         }
     }
@@ -244,6 +367,7 @@ impl ServerEndpoint {
             security_mode: security_mode.to_string(),
             security_level: Self::security_level(security_policy, security_mode),
             password_security_policy: None,
+            x509_security_policy: None,
             user_token_ids: user_token_ids.iter().cloned().collect(),
         }
     }
@@ -421,6 +545,16 @@ impl ServerEndpoint {
             }
         }
 
+        if let Some(ref x509_security_policy) = self.x509_security_policy {
+            match SecurityPolicy::from_str(x509_security_policy).unwrap() {
+                SecurityPolicy::Unknown | SecurityPolicy::None => {
+                    error!("Endpoint {} is invalid. X509 security policy \"{}\" is invalid. Valid values are Basic128Rsa15, Basic256, Basic256Sha256, Aes128Sha256RsaOaep, Aes256Sha256RsaPss", id, x509_security_policy);
+                    valid = false;
+                }
+                _ => {}
+            }
+        }
+
         // Validate the security policy and mode
         let security_policy = SecurityPolicy::from_str(&self.security_policy).unwrap();
         let security_mode = MessageSecurityMode::from(self.security_mode.as_ref());
@@ -478,6 +612,26 @@ impl ServerEndpoint {
         password_security_policy
     }
 
+    /// Returns the effective security policy that X509 identity tokens are signed with for the
+    /// endpoint. This is the explicitly set X509 security policy, or the regular security policy.
+    /// As a token cannot be signed without security, an endpoint with security policy None falls
+    /// back to Basic128Rsa15.
+    pub fn x509_security_policy(&self) -> SecurityPolicy {
+        if let Some(ref security_policy) = self.x509_security_policy {
+            match SecurityPolicy::from_str(security_policy).unwrap() {
+                SecurityPolicy::Unknown | SecurityPolicy::None => {
+                    panic!("X509 security policy {} is unrecognized", security_policy);
+                }
+                security_policy => security_policy,
+            }
+        } else {
+            match self.security_policy() {
+                SecurityPolicy::None => SecurityPolicy::Basic128Rsa15,
+                security_policy => security_policy,
+            }
+        }
+    }
+
     /// Test if the endpoint supports anonymous users
     pub fn supports_anonymous(&self) -> bool {
         self.supports_user_token_id(ANONYMOUS_USER_TOKEN_ID)
@@ -525,6 +679,60 @@ pub struct Performance {
     pub single_threaded_executor: bool,
 }
 
+/// The failover model of the redundant set of servers that a server belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum RedundancyMode {
+    /// The server is not part of a redundant set. This is the default.
+    #[default]
+    None,
+    /// Backup servers are started when the active server fails
+    Cold,
+    /// Backup servers are running but cannot share sessions or subscriptions with the active one
+    Warm,
+    /// Backup servers are running and may share subscriptions with the active one
+    Hot,
+    /// Backup servers mirror the sessions and subscriptions of the active one
+    HotAndMirrored,
+}
+
+impl From<RedundancyMode> for RedundancySupport {
+    fn from(mode: RedundancyMode) -> Self {
+        match mode {
+            RedundancyMode::None => RedundancySupport::None,
+            RedundancyMode::Cold => RedundancySupport::Cold,
+            RedundancyMode::Warm => RedundancySupport::Warm,
+            RedundancyMode::Hot => RedundancySupport::Hot,
+            RedundancyMode::HotAndMirrored => RedundancySupport::HotAndMirrored,
+        }
+    }
+}
+
+/// The network paths that a server of a redundant set can be reached through
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ServerNetworkGroup {
+    /// The application uri of the server
+    pub server_uri: String,
+    /// The endpoint urls of the server on each network path, one list per path
+    pub network_paths: Vec<Vec<String>>,
+}
+
+/// The non-transparent redundancy of a server, which it advertises to clients through the
+/// `ServerRedundancy` object so they know which servers they can fail over to and how.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Redundancy {
+    /// The failover model of the redundant set
+    #[serde(default)]
+    pub mode: RedundancyMode,
+    /// The application uris of the servers of the redundant set. The server itself is part of
+    /// the set whether it is in the list or not.
+    #[serde(default)]
+    pub server_uris: Vec<String>,
+    /// The network paths to the servers of the redundant set, if they can be reached through
+    /// more than one network
+    #[serde(default)]
+    pub server_network_groups: Vec<ServerNetworkGroup>,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     /// An id for this server
@@ -540,6 +748,10 @@ pub struct ServerConfig {
     pub certificate_path: Option<PathBuf>,
     /// Path to a custom private key, to be used instead of the default private key
     pub private_key_path: Option<PathBuf>,
+    /// Reads the certificate and private key, and trusted certificates, from the certificate
+    /// store of the operating system instead of the PKI folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_certificate_store: Option<SystemCertificateStore>,
     /// Checks the certificate's time validity
     pub certificate_validation: CertificateValidation,
     /// PKI folder, either absolute or relative to executable
@@ -563,6 +775,16 @@ pub struct ServerConfig {
     pub default_endpoint: Option<String>,
     /// Endpoints supported by the server
     pub endpoints: BTreeMap<String, ServerEndpoint>,
+    /// Redundancy of the server, by default it is not part of a redundant set
+    #[serde(default)]
+    pub redundancy: Redundancy,
+    /// Only creates sessions over secure channels that are signed or encrypted. Clients can
+    /// still open a secure channel with security policy None to get the endpoints or find the
+    /// servers, but any other request over it is rejected with `BadSecurityPolicyRejected` and
+    /// endpoints with security policy None are not advertised. False, the default, serves every
+    /// endpoint that is configured.
+    #[serde(default)]
+    pub secure_sessions_only: bool,
 }
 
 impl Config for ServerConfig {
@@ -608,10 +830,49 @@ impl Config for ServerConfig {
             error!("Server configuration is invalid. Max byte string length is invalid");
             valid = false;
         }
+        // The subscription timer ticks at the shorter of the two intervals, so neither can be zero
+        if !(self.limits.min_sampling_interval > 0.0 && self.limits.min_publishing_interval > 0.0) {
+            error!("Server configuration is invalid. Min sampling and publishing intervals must be greater than zero");
+            valid = false;
+        }
+        if self.limits.send_buffer_size < MIN_CHUNK_SIZE
+            || self.limits.receive_buffer_size < MIN_CHUNK_SIZE
+        {
+            error!(
+                "Server configuration is invalid. Send and receive buffer sizes must be at least {} bytes",
+                MIN_CHUNK_SIZE
+            );
+            valid = false;
+        }
         if self.discovery_urls.is_empty() {
             error!("Server configuration is invalid. Discovery urls not set");
             valid = false;
         }
+        if let Some(ref system_certificate_store) = self.system_certificate_store {
+            if !system_certificate_store.is_valid() {
+                error!("Server configuration is invalid. System certificate store is invalid");
+                valid = false;
+            }
+        }
+        if self.secure_sessions_only {
+            if self.endpoints.values().all(|e| !self.is_advertised(e)) {
+                error!("Server configuration is invalid. It only creates secure sessions but defines no secure endpoints");
+                valid = false;
+            }
+            for (id, endpoint) in &self.endpoints {
+                if !self.is_advertised(endpoint) {
+                    warn!("Endpoint {} is not advertised because it has security policy None and the server only creates secure sessions", id);
+                }
+            }
+        }
+        if self.redundancy.mode == RedundancyMode::None
+            && (!self.redundancy.server_uris.is_empty()
+                || !self.redundancy.server_network_groups.is_empty())
+        {
+            warn!(
+                "Redundant servers are configured but are not advertised with redundancy mode None"
+            );
+        }
         valid
     }
 
@@ -650,6 +911,7 @@ impl Default for ServerConfig {
             create_sample_keypair: false,
             certificate_path: None,
             private_key_path: None,
+            system_certificate_store: None,
             pki_dir,
             certificate_validation: CertificateValidation::default(),
             discovery_server_url: None,
@@ -657,6 +919,10 @@ impl Default for ServerConfig {
                 host: "127.0.0.1".to_string(),
                 port: constants::DEFAULT_RUST_OPC_UA_SERVER_PORT,
                 hello_timeout: constants::DEFAULT_HELLO_TIMEOUT_SECONDS,
+                local_socket: None,
+                socket_options: SocketOptions::default(),
+                max_connection_rate: 0,
+                max_unauthenticated_connections: 0,
             },
             limits: Limits::default(),
             user_tokens: BTreeMap::new(),
@@ -667,6 +933,8 @@ impl Default for ServerConfig {
             performance: Performance {
                 single_threaded_executor: false,
             },
+            redundancy: Redundancy::default(),
+            secure_sessions_only: false,
         }
     }
 }
@@ -703,9 +971,11 @@ impl ServerConfig {
             create_sample_keypair: false,
             certificate_path: None,
             private_key_path: None,
+            system_certificate_store: None,
             certificate_validation: CertificateValidation {
                 trust_client_certs: false,
                 check_time: true,
+                match_subject_key_identifier: false,
             },
             pki_dir,
             discovery_server_url,
@@ -713,6 +983,10 @@ impl ServerConfig {
                 host,
                 port,
                 hello_timeout: constants::DEFAULT_HELLO_TIMEOUT_SECONDS,
+                local_socket: None,
+                socket_options: SocketOptions::default(),
+                max_connection_rate: 0,
+                max_unauthenticated_connections: 0,
             },
             limits: Limits::default(),
             locale_ids,
@@ -723,6 +997,8 @@ impl ServerConfig {
             performance: Performance {
                 single_threaded_executor: false,
             },
+            redundancy: Redundancy::default(),
+            secure_sessions_only: false,
         }
     }
 
@@ -756,6 +1032,22 @@ impl ServerConfig {
         )
     }
 
+    /// Returns the url that paths can be appended onto for endpoints that are reached through the
+    /// supplied url. It is the `opc.unix` url of the local socket for an `opc.unix` url if the
+    /// server has a local socket, otherwise the opc.tcp://server:port url.
+    pub fn base_endpoint_url_for(&self, endpoint_url: &str) -> String {
+        match self.tcp_config.local_socket {
+            Some(ref local_socket)
+                if EndpointUrl::from_str(endpoint_url)
+                    .map(|url| url.is_opc_unix())
+                    .unwrap_or(false) =>
+            {
+                local_socket_url(local_socket)
+            }
+            _ => self.base_endpoint_url(),
+        }
+    }
+
     /// Find the default endpoint
     pub fn default_endpoint(&self) -> Option<&ServerEndpoint> {
         if let Some(ref default_endpoint) = self.default_endpoint {
@@ -765,6 +1057,12 @@ impl ServerConfig {
         }
     }
 
+    /// Tests if the endpoint is advertised to clients, which it is unless it has security policy
+    /// None and the server only creates secure sessions
+    pub fn is_advertised(&self, endpoint: &ServerEndpoint) -> bool {
+        !self.secure_sessions_only || endpoint.security_policy() != SecurityPolicy::None
+    }
+
     /// Find the first endpoint that matches the specified url, security policy and message
     /// security mode.
     pub fn find_endpoint(
@@ -773,7 +1071,7 @@ impl ServerConfig {
         security_policy: SecurityPolicy,
         security_mode: MessageSecurityMode,
     ) -> Option<&ServerEndpoint> {
-        let base_endpoint_url = self.base_endpoint_url();
+        let base_endpoint_url = self.base_endpoint_url_for(endpoint_url);
         let endpoint = self.endpoints.iter().find(|&(_, e)| {
             // Test end point's security_policy_uri and matching url
             if url_matches_except_host(&e.endpoint_url(&base_endpoint_url), endpoint_url) {
@@ -2,18 +2,162 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-//! Provides a browse continuation point type for tracking a browse operation initiated by a client.
+//! Provides browse, query and history read continuation point types for tracking the operations
+//! initiated by a client, and `ContinuationPoints`, the registry of the continuation points of
+//! one kind that a session holds.
 
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
+use crate::crypto::random;
 use crate::sync::*;
-use crate::types::{service_types::ReferenceDescription, ByteString, DateTimeUtc};
+use crate::types::{
+    service_types::{QueryDataSet, ReferenceDescription},
+    status_code::StatusCode,
+    ByteString, DateTimeUtc, NodeId,
+};
 
-use crate::prelude::AddressSpace;
+use crate::server::address_space::AddressSpace;
+
+/// The continuation points of one kind that a session holds for its client, oldest first. The
+/// registry makes the id of each continuation point, which is random and unique within the
+/// registry. A continuation point is forgotten when the client resumes or releases it, when it
+/// expires after the timeout, or when the session releases all of its continuation points.
+///
+/// The registry is held by the session and only changed under the lock of the session, so a
+/// continuation point is resumed by one request at most.
+#[derive(Debug)]
+pub struct ContinuationPoints<T> {
+    /// The most continuation points that may be held at once, 0 for no limit
+    max_continuation_points: usize,
+    /// How long a continuation point lives for
+    timeout: chrono::Duration,
+    continuation_points: VecDeque<Entry<T>>,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    id: ByteString,
+    created_at: DateTimeUtc,
+    continuation_point: T,
+}
+
+impl<T> ContinuationPoints<T> {
+    /// Creates a registry that holds up to the maximum number of continuation points, or any
+    /// number for 0, each for the timeout in milliseconds.
+    pub fn new(max_continuation_points: usize, timeout_ms: u64) -> ContinuationPoints<T> {
+        ContinuationPoints {
+            max_continuation_points,
+            timeout: chrono::Duration::milliseconds(timeout_ms as i64),
+            continuation_points: VecDeque::with_capacity(max_continuation_points),
+        }
+    }
+
+    /// Returns the number of continuation points that are held.
+    pub fn len(&self) -> usize {
+        self.continuation_points.len()
+    }
+
+    /// Tests if no continuation points are held.
+    pub fn is_empty(&self) -> bool {
+        self.continuation_points.is_empty()
+    }
+
+    /// Adds a continuation point and returns its id for the client. Fails with
+    /// `BadNoContinuationPoints` if all the continuation points are in use, after those that
+    /// expired are removed.
+    pub fn add(
+        &mut self,
+        now: &DateTimeUtc,
+        continuation_point: T,
+    ) -> Result<ByteString, StatusCode> {
+        self.remove_expired(now);
+        if self.max_continuation_points > 0
+            && self.continuation_points.len() >= self.max_continuation_points
+        {
+            debug!(
+                "All {} continuation points are in use",
+                self.max_continuation_points
+            );
+            return Err(StatusCode::BadNoContinuationPoints);
+        }
+        let id = loop {
+            let id = random::byte_string(6);
+            if self.position(&id).is_none() {
+                break id;
+            }
+        };
+        self.continuation_points.push_back(Entry {
+            id: id.clone(),
+            created_at: *now,
+            continuation_point,
+        });
+        Ok(id)
+    }
+
+    /// Removes the continuation point with the id for the client to resume the operation. Fails
+    /// with `BadContinuationPointInvalid` if there is no such continuation point or it expired.
+    pub fn take(&mut self, now: &DateTimeUtc, id: &ByteString) -> Result<T, StatusCode> {
+        let entry = self
+            .position(id)
+            .and_then(|idx| self.continuation_points.remove(idx))
+            .filter(|entry| !self.is_expired(entry, now));
+        match entry {
+            Some(entry) => Ok(entry.continuation_point),
+            None => {
+                debug!("Continuation point {} is invalid", id.as_base64());
+                Err(StatusCode::BadContinuationPointInvalid)
+            }
+        }
+    }
+
+    /// Releases the continuation point with the id and returns it, or fails with
+    /// `BadContinuationPointInvalid` if there is no such continuation point.
+    pub fn release(&mut self, id: &ByteString) -> Result<T, StatusCode> {
+        self.position(id)
+            .and_then(|idx| self.continuation_points.remove(idx))
+            .map(|entry| entry.continuation_point)
+            .ok_or(StatusCode::BadContinuationPointInvalid)
+    }
+
+    /// Removes the continuation points that fail the test, e.g. because they refer to an address
+    /// space that has since been modified.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.continuation_points
+            .retain(|entry| f(&entry.continuation_point));
+    }
+
+    /// Removes the continuation points that have expired, returning how many there were.
+    pub fn remove_expired(&mut self, now: &DateTimeUtc) -> usize {
+        let timeout = self.timeout;
+        let len = self.continuation_points.len();
+        self.continuation_points
+            .retain(|entry| now.signed_duration_since(entry.created_at) < timeout);
+        len - self.continuation_points.len()
+    }
+
+    /// Removes every continuation point, returning how many there were.
+    pub fn clear(&mut self) -> usize {
+        let len = self.continuation_points.len();
+        self.continuation_points.clear();
+        len
+    }
+
+    fn position(&self, id: &ByteString) -> Option<usize> {
+        self.continuation_points
+            .iter()
+            .position(|entry| entry.id == *id)
+    }
+
+    fn is_expired(&self, entry: &Entry<T>, now: &DateTimeUtc) -> bool {
+        now.signed_duration_since(entry.created_at) >= self.timeout
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct BrowseContinuationPoint {
-    pub id: ByteString,
     pub address_space_last_modified: DateTimeUtc,
     pub max_references_per_node: usize,
     pub starting_index: usize,
@@ -27,3 +171,30 @@ impl BrowseContinuationPoint {
         self.address_space_last_modified >= address_space.last_modified()
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct QueryContinuationPoint {
+    pub address_space_last_modified: DateTimeUtc,
+    pub max_data_sets_to_return: usize,
+    /// The data sets that remain to be returned
+    pub query_data_sets: Vec<QueryDataSet>,
+}
+
+impl QueryContinuationPoint {
+    /// Test if the continuation point valid which is only true if address space has not been
+    /// modified since the point was made.
+    pub fn is_valid_query_continuation_point(&self, address_space: &AddressSpace) -> bool {
+        self.address_space_last_modified >= address_space.last_modified()
+    }
+}
+
+/// A continuation point of a history read. The historical data or event provider makes its own
+/// continuation point, which the client never sees. The client is given the id of this one
+/// instead, so the provider is only passed continuation points that it made for the same node.
+#[derive(Clone, Debug)]
+pub struct HistoryContinuationPoint {
+    /// The node that was read
+    pub node_id: NodeId,
+    /// The continuation point that the provider returned
+    pub continuation_point: ByteString,
+}
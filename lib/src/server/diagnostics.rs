@@ -8,7 +8,7 @@
 use crate::types::service_types::ServerDiagnosticsSummaryDataType;
 use crate::{deregister_runtime_component, register_runtime_component};
 
-use crate::core::RUNTIME;
+use crate::core::{record_metric, RUNTIME};
 
 use super::{session::Session, subscriptions::subscription::Subscription};
 
@@ -62,6 +62,7 @@ impl ServerDiagnostics {
     pub(crate) fn on_create_session(&mut self, _session: &Session) {
         self.server_diagnostics_summary.current_session_count += 1;
         self.server_diagnostics_summary.cumulated_session_count += 1;
+        record_metric!(on_session_created());
         debug!(
             "Incrementing current session count to {}",
             self.server_diagnostics_summary.current_session_count
@@ -71,6 +72,7 @@ impl ServerDiagnostics {
     /// Decrement the number of client sessions currently established in the server.
     pub(crate) fn on_destroy_session(&mut self, _session: &Session) {
         self.server_diagnostics_summary.current_session_count -= 1;
+        record_metric!(on_session_closed());
         debug!(
             "Decrementing current session count to {}",
             self.server_diagnostics_summary.current_session_count
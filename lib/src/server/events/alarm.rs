@@ -0,0 +1,424 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the state that the alarms of the address space have in common, i.e. whether they are
+//! active, suppressed or shelved, and the variables of the condition that reflect it.
+use chrono::Utc;
+
+use crate::types::{status_code::StatusCode, *};
+
+use crate::server::{
+    address_space::{
+        object::ObjectBuilder,
+        relative_path::{find_node_from_browse_path, set_variable_value_from_browse_path},
+        state_machine::add_current_state_variable,
+        variable::{add_property, good_value, VariableBuilder},
+        AddressSpace,
+    },
+    events::{limit_alarm::LimitAlarm, off_normal_alarm::OffNormalAlarm},
+};
+
+/// The shelving of an alarm. An operator shelves an alarm to stop it from being reported for a
+/// while, e.g. when it is known to be faulty.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShelvedState {
+    /// The alarm is reported
+    Unshelved,
+    /// The alarm is shelved until it next becomes inactive
+    OneShotShelved,
+    /// The alarm is shelved for the duration in milliseconds
+    TimedShelved(f64),
+}
+
+impl ShelvedState {
+    fn name(&self) -> &'static str {
+        match self {
+            ShelvedState::Unshelved => "Unshelved",
+            ShelvedState::OneShotShelved => "OneShotShelved",
+            ShelvedState::TimedShelved(_) => "TimedShelved",
+        }
+    }
+
+    fn state_id(&self) -> ObjectId {
+        match self {
+            ShelvedState::Unshelved => ObjectId::ShelvedStateMachineType_Unshelved,
+            ShelvedState::OneShotShelved => ObjectId::ShelvedStateMachineType_OneShotShelved,
+            ShelvedState::TimedShelved(_) => ObjectId::ShelvedStateMachineType_TimedShelved,
+        }
+    }
+}
+
+/// An alarm that the address space evaluates when the value of a node that it watches changes.
+pub(crate) enum Alarm {
+    Limit(LimitAlarm),
+    OffNormal(OffNormalAlarm),
+}
+
+impl Alarm {
+    pub(crate) fn node_id(&self) -> &NodeId {
+        &self.state().node_id
+    }
+
+    /// Tests if the alarm is evaluated when the value of the node changes
+    pub(crate) fn watches(&self, node_id: &NodeId) -> bool {
+        match self {
+            Alarm::Limit(alarm) => alarm.watches(node_id),
+            Alarm::OffNormal(alarm) => alarm.watches(node_id),
+        }
+    }
+
+    pub(crate) fn evaluate(&mut self, address_space: &mut AddressSpace) {
+        match self {
+            Alarm::Limit(alarm) => alarm.evaluate(address_space, false),
+            Alarm::OffNormal(alarm) => alarm.evaluate(address_space, false),
+        }
+    }
+
+    pub(crate) fn state(&self) -> &AlarmState {
+        match self {
+            Alarm::Limit(alarm) => &alarm.state,
+            Alarm::OffNormal(alarm) => &alarm.state,
+        }
+    }
+
+    pub(crate) fn state_mut(&mut self) -> &mut AlarmState {
+        match self {
+            Alarm::Limit(alarm) => &mut alarm.state,
+            Alarm::OffNormal(alarm) => &mut alarm.state,
+        }
+    }
+}
+
+/// The active, suppressed and shelved states of an alarm. The alarm fires an event each time its
+/// state changes, except that it is not reported while it is suppressed or shelved.
+pub(crate) struct AlarmState {
+    node_id: NodeId,
+    shelving_state_id: NodeId,
+    suppressed: bool,
+    shelved_state: ShelvedState,
+    /// The longest time in milliseconds that the alarm may be shelved for
+    max_time_shelved: Option<f64>,
+    /// The time that a shelved alarm is unshelved, if it is shelved for a time
+    unshelve_time: Option<chrono::DateTime<Utc>>,
+}
+
+impl AlarmState {
+    /// Inserts the variables that the alarm conditions have in common into the alarm that was
+    /// raised, and makes the alarm a condition of its source node.
+    pub(crate) fn raise(
+        node_id: &NodeId,
+        input_node: &NodeId,
+        max_time_shelved: Option<f64>,
+        address_space: &mut AddressSpace,
+    ) -> AlarmState {
+        let ns = node_id.namespace;
+        if let Some(source_node) = source_node_id(node_id, address_space) {
+            address_space.insert_reference(&source_node, node_id, ReferenceTypeId::HasCondition);
+        }
+
+        let condition_name = address_space
+            .find_node(node_id)
+            .map(|node| node.as_node().browse_name().name)
+            .unwrap_or_default();
+        add_property(
+            node_id,
+            "ConditionName",
+            DataTypeId::String,
+            condition_name,
+            address_space,
+        );
+        add_property(node_id, "Retain", DataTypeId::Boolean, false, address_space);
+        add_property(
+            node_id,
+            "InputNode",
+            DataTypeId::NodeId,
+            input_node.clone(),
+            address_space,
+        );
+        add_property(
+            node_id,
+            "SuppressedOrShelved",
+            DataTypeId::Boolean,
+            false,
+            address_space,
+        );
+        add_two_state_variable(node_id, "ActiveState", address_space);
+        add_two_state_variable(node_id, "SuppressedState", address_space);
+        if let Some(max_time_shelved) = max_time_shelved {
+            add_property(
+                node_id,
+                "MaxTimeShelved",
+                DataTypeId::Duration,
+                max_time_shelved,
+                address_space,
+            );
+        }
+
+        // The shelving state machine. The address space calls its methods, and those of the
+        // alarm, because they change the state of the alarm.
+        let shelving_state_id = NodeId::next_numeric(ns);
+        ObjectBuilder::new(&shelving_state_id, "ShelvingState", "ShelvingState")
+            .component_of(node_id.clone())
+            .has_type_definition(ObjectTypeId::ShelvedStateMachineType)
+            .insert(address_space);
+        add_current_state_variable(
+            &shelving_state_id,
+            ShelvedState::Unshelved.name(),
+            ShelvedState::Unshelved.state_id(),
+            address_space,
+        );
+        add_property(
+            &shelving_state_id,
+            "UnshelveTime",
+            DataTypeId::Duration,
+            0f64,
+            address_space,
+        );
+        address_space.insert_references(&[
+            (
+                &shelving_state_id,
+                &MethodId::AlarmConditionType_ShelvingState_Unshelve.into(),
+                &ReferenceTypeId::HasComponent,
+            ),
+            (
+                &shelving_state_id,
+                &MethodId::AlarmConditionType_ShelvingState_OneShotShelve.into(),
+                &ReferenceTypeId::HasComponent,
+            ),
+            (
+                &shelving_state_id,
+                &MethodId::AlarmConditionType_ShelvingState_TimedShelve.into(),
+                &ReferenceTypeId::HasComponent,
+            ),
+            (
+                node_id,
+                &MethodId::AlarmConditionType_Suppress.into(),
+                &ReferenceTypeId::HasComponent,
+            ),
+            (
+                node_id,
+                &MethodId::AlarmConditionType_Unsuppress.into(),
+                &ReferenceTypeId::HasComponent,
+            ),
+        ]);
+
+        AlarmState {
+            node_id: node_id.clone(),
+            shelving_state_id,
+            suppressed: false,
+            shelved_state: ShelvedState::Unshelved,
+            max_time_shelved,
+            unshelve_time: None,
+        }
+    }
+
+    pub(crate) fn shelving_state_id(&self) -> &NodeId {
+        &self.shelving_state_id
+    }
+
+    pub(crate) fn has_unshelve_time(&self) -> bool {
+        self.unshelve_time.is_some()
+    }
+
+    /// Updates the condition to whether the alarm is active, and fires an event with the message
+    /// unless the alarm is suppressed or shelved. A one shot shelved alarm is unshelved when it
+    /// becomes inactive.
+    pub(crate) fn update(&mut self, address_space: &mut AddressSpace, active: bool, message: &str) {
+        self.set_variable(address_space, &["Retain"], active);
+        self.set_variable(address_space, &["ActiveState"], two_state(active));
+        self.set_variable(address_space, &["ActiveState", "Id"], active);
+        if !active && self.shelved_state == ShelvedState::OneShotShelved {
+            self.set_shelving(address_space, ShelvedState::Unshelved);
+        }
+        if !self.suppressed && self.shelved_state == ShelvedState::Unshelved {
+            self.fire(address_space, message);
+        }
+    }
+
+    /// Suppresses the alarm or ends its suppression, firing an event if that changes it
+    pub(crate) fn set_suppressed(&mut self, address_space: &mut AddressSpace, suppressed: bool) {
+        if self.suppressed != suppressed {
+            self.suppressed = suppressed;
+            self.set_variable(address_space, &["SuppressedState"], two_state(suppressed));
+            self.set_variable(address_space, &["SuppressedState", "Id"], suppressed);
+            self.set_suppressed_or_shelved(address_space);
+            self.fire(
+                address_space,
+                if suppressed {
+                    "The alarm is suppressed"
+                } else {
+                    "The alarm is no longer suppressed"
+                },
+            );
+        }
+    }
+
+    /// Shelves or unshelves the alarm, firing an event. Unshelving an alarm that is not shelved
+    /// or shelving a one shot shelved alarm again is an error, whereas shelving a timed shelved
+    /// alarm for a time restarts its timer. The time must not exceed the `MaxTimeShelved` of the
+    /// alarm, which also limits how long it is one shot shelved for.
+    pub(crate) fn set_shelved_state(
+        &mut self,
+        address_space: &mut AddressSpace,
+        shelved_state: ShelvedState,
+    ) -> Result<(), StatusCode> {
+        match shelved_state {
+            ShelvedState::Unshelved if self.shelved_state == ShelvedState::Unshelved => {
+                Err(StatusCode::BadConditionNotShelved)
+            }
+            ShelvedState::OneShotShelved if self.shelved_state == ShelvedState::OneShotShelved => {
+                Err(StatusCode::BadConditionAlreadyShelved)
+            }
+            ShelvedState::TimedShelved(shelving_time)
+                if !shelving_time.is_finite()
+                    || shelving_time <= 0f64
+                    || self
+                        .max_time_shelved
+                        .is_some_and(|max_time_shelved| shelving_time > max_time_shelved) =>
+            {
+                Err(StatusCode::BadShelvingTimeOutOfRange)
+            }
+            shelved_state => {
+                self.set_shelving(address_space, shelved_state);
+                self.fire(
+                    address_space,
+                    &format!("The alarm is {}", shelved_state.name()),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Unshelves a shelved alarm if its shelving time has elapsed, or else updates the time that
+    /// remains in its `UnshelveTime`
+    pub(crate) fn update_unshelve_time(
+        &mut self,
+        address_space: &mut AddressSpace,
+        now: &chrono::DateTime<Utc>,
+    ) {
+        if let Some(unshelve_time) = self.unshelve_time {
+            if unshelve_time <= *now {
+                let _ = self.set_shelved_state(address_space, ShelvedState::Unshelved);
+            } else {
+                let remaining_time = (unshelve_time - *now).num_milliseconds() as f64;
+                let shelving_state_id = self.shelving_state_id.clone();
+                set_variable(
+                    address_space,
+                    &shelving_state_id,
+                    &["UnshelveTime"],
+                    remaining_time,
+                );
+            }
+        }
+    }
+
+    fn set_shelving(&mut self, address_space: &mut AddressSpace, shelved_state: ShelvedState) {
+        self.shelved_state = shelved_state;
+        let shelving_time = match shelved_state {
+            ShelvedState::Unshelved => None,
+            ShelvedState::OneShotShelved => self.max_time_shelved,
+            ShelvedState::TimedShelved(shelving_time) => Some(shelving_time),
+        };
+        self.unshelve_time = shelving_time
+            .map(|shelving_time| Utc::now() + chrono::Duration::milliseconds(shelving_time as i64));
+        let unshelve_time = shelving_time.unwrap_or(0f64);
+        let shelving_state_id = self.shelving_state_id.clone();
+        set_variable(
+            address_space,
+            &shelving_state_id,
+            &["CurrentState"],
+            LocalizedText::from(shelved_state.name()),
+        );
+        set_variable(
+            address_space,
+            &shelving_state_id,
+            &["CurrentState", "Id"],
+            NodeId::from(&shelved_state.state_id()),
+        );
+        set_variable(
+            address_space,
+            &shelving_state_id,
+            &["UnshelveTime"],
+            unshelve_time,
+        );
+        self.set_suppressed_or_shelved(address_space);
+    }
+
+    fn set_suppressed_or_shelved(&self, address_space: &mut AddressSpace) {
+        let suppressed_or_shelved =
+            self.suppressed || self.shelved_state != ShelvedState::Unshelved;
+        self.set_variable(
+            address_space,
+            &["SuppressedOrShelved"],
+            suppressed_or_shelved,
+        );
+    }
+
+    /// Fires an event for the condition by giving it a new event id and time
+    fn fire(&self, address_space: &mut AddressSpace, message: &str) {
+        let now = DateTime::now();
+        self.set_variable(address_space, &["EventId"], ByteString::from(Guid::new()));
+        self.set_variable(address_space, &["Time"], now);
+        self.set_variable(address_space, &["ReceiveTime"], now);
+        self.set_variable(address_space, &["Message"], LocalizedText::from(message));
+    }
+
+    /// Sets the value of a variable of the alarm by its browse path from the alarm
+    pub(crate) fn set_variable<V>(
+        &self,
+        address_space: &mut AddressSpace,
+        browse_path: &[&str],
+        value: V,
+    ) where
+        V: Into<Variant>,
+    {
+        set_variable(address_space, &self.node_id, browse_path, value);
+    }
+}
+
+/// Returns the text of a two state variable
+pub(crate) fn two_state(state: bool) -> LocalizedText {
+    LocalizedText::from(if state { "Active" } else { "Inactive" })
+}
+
+/// Adds a two state variable, which is inactive, with its `Id` property
+pub(crate) fn add_two_state_variable(
+    parent_id: &NodeId,
+    name: &str,
+    address_space: &mut AddressSpace,
+) {
+    let state_id = NodeId::next_numeric(parent_id.namespace);
+    VariableBuilder::new(&state_id, name, name)
+        .component_of(parent_id.clone())
+        .has_type_definition(VariableTypeId::TwoStateVariableType)
+        .data_type(DataTypeId::LocalizedText)
+        .value(two_state(false))
+        .insert(address_space);
+    add_property(&state_id, "Id", DataTypeId::Boolean, false, address_space);
+}
+
+fn set_variable<V>(
+    address_space: &mut AddressSpace,
+    node_id: &NodeId,
+    browse_path: &[&str],
+    value: V,
+) where
+    V: Into<Variant>,
+{
+    if !set_variable_value_from_browse_path(address_space, node_id, browse_path, value) {
+        error!("Alarm {} has no variable {:?}", node_id, browse_path);
+    }
+}
+
+/// Returns the source node of an event, unless it has none
+fn source_node_id(node_id: &NodeId, address_space: &AddressSpace) -> Option<NodeId> {
+    find_node_from_browse_path(address_space, node_id, &["SourceNode".into()])
+        .ok()
+        .and_then(|node| good_value(address_space, &node.as_node().node_id()))
+        .and_then(|value| match value {
+            Variant::NodeId(node_id) => Some(*node_id),
+            _ => None,
+        })
+        .filter(|node_id| !node_id.is_null())
+}
@@ -4,9 +4,9 @@
 
 //! Contains functions for generating events and adding them to the address space of the server.
 use crate::types::{
-    service_types::TimeZoneDataType, AttributeId, ByteString, DataTypeId, DateTime, DateTimeUtc,
-    ExtensionObject, Guid, LocalizedText, NodeId, NumericRange, ObjectId, ObjectTypeId,
-    QualifiedName, TimestampsToReturn, UAString, VariableTypeId, Variant,
+    service_types::TimeZoneDataType, AttributeId, ByteString, DataTypeId, DataValue, DateTime,
+    DateTimeUtc, ExtensionObject, Guid, LocalizedText, NodeId, NumericRange, ObjectId,
+    ObjectTypeId, QualifiedName, TimestampsToReturn, UAString, VariableTypeId, Variant,
 };
 
 use crate::server::address_space::{
@@ -489,6 +489,27 @@ fn event_time(event_id: &NodeId, address_space: &AddressSpace) -> Option<DateTim
     }
 }
 
+fn event_retain(event_id: &NodeId, address_space: &AddressSpace) -> bool {
+    // Find the Retain variable under the condition to tell if it is still of interest
+    if let Ok(retain_node) = find_node_from_browse_path(address_space, event_id, &["Retain".into()])
+    {
+        matches!(
+            retain_node.as_node().get_attribute(
+                TimestampsToReturn::Neither,
+                AttributeId::Value,
+                NumericRange::None,
+                &QualifiedName::null(),
+            ),
+            Some(DataValue {
+                value: Some(Variant::Boolean(true)),
+                ..
+            })
+        )
+    } else {
+        false
+    }
+}
+
 /// Attempts to find events that were emitted by the source object based upon a time predicate
 pub fn filter_events<T, R, F>(
     source_object_id: T,
@@ -579,6 +600,38 @@ where
     )
 }
 
+/// Searches for the conditions of the source object whose `Retain` property is true, i.e. the
+/// conditions that a client must know of, such as alarms that are active or unacknowledged. The
+/// conditions are ordered by their time.
+pub fn retained_conditions_for_object<T>(
+    source_object_id: T,
+    address_space: &AddressSpace,
+) -> Option<Vec<NodeId>>
+where
+    T: Into<NodeId>,
+{
+    filter_events(
+        source_object_id,
+        ObjectTypeId::ConditionType,
+        address_space,
+        |_| true,
+    )
+    .and_then(|conditions| {
+        let mut conditions = conditions
+            .into_iter()
+            .filter(|condition_id| event_retain(condition_id, address_space))
+            .collect::<Vec<NodeId>>();
+        if conditions.is_empty() {
+            None
+        } else {
+            conditions.sort_by_key(|condition_id| {
+                event_time(condition_id, address_space).map(|time| time.as_chrono())
+            });
+            Some(conditions)
+        }
+    })
+}
+
 #[test]
 fn test_event_source_node() {
     let mut address_space = AddressSpace::new();
@@ -11,12 +11,13 @@ use crate::types::{
         EventFilter, EventFilterResult, FilterOperator, SimpleAttributeOperand,
     },
     status_code::StatusCode,
-    AttributeId, DateTimeUtc, NodeId, Variant,
+    AttributeId, ByteString, DateTime, DateTimeUtc, Guid, LocalizedText, NodeId, ObjectId,
+    ObjectTypeId, UAString, Variant,
 };
 
 use crate::server::{
     address_space::{address_space::AddressSpace, node::NodeType, relative_path::*},
-    events::event::events_for_object,
+    events::event::{events_for_object, retained_conditions_for_object},
     events::operator,
 };
 
@@ -47,39 +48,155 @@ pub fn evaluate(
     happened_since: &DateTimeUtc,
     client_handle: u32,
 ) -> Option<Vec<EventFieldList>> {
-    if let Some(events) = events_for_object(object_id, address_space, happened_since) {
-        let event_fields = events
-            .iter()
-            .filter(|event_id| {
-                if let Ok(result) =
-                    evaluate_where_clause(event_id, &event_filter.where_clause, address_space)
-                {
-                    result == Variant::Boolean(true)
-                } else {
-                    false
-                }
-            })
-            .map(|event_id| {
-                // Produce an event notification list from the select clauses.
-                let event_fields = event_filter.select_clauses.as_ref().map(|select_clauses| {
-                    select_clauses
-                        .iter()
-                        .map(|v| operator::value_of_simple_attribute(event_id, v, address_space))
-                        .collect()
-                });
-                EventFieldList {
-                    client_handle,
-                    event_fields,
-                }
-            })
-            .collect::<Vec<EventFieldList>>();
-        if event_fields.is_empty() {
-            None
-        } else {
-            Some(event_fields)
-        }
-    } else {
+    events_for_object(object_id, address_space, happened_since)
+        .and_then(|events| evaluate_events(&events, event_filter, address_space, client_handle))
+}
+
+/// Evaluate the event filter against the retained conditions of the object, i.e. the conditions
+/// that a `ConditionRefresh` replays.
+pub(crate) fn evaluate_retained_conditions(
+    object_id: &NodeId,
+    event_filter: &EventFilter,
+    address_space: &AddressSpace,
+    client_handle: u32,
+) -> Option<Vec<EventFieldList>> {
+    retained_conditions_for_object(object_id, address_space).and_then(|conditions| {
+        evaluate_events(&conditions, event_filter, address_space, client_handle)
+    })
+}
+
+fn evaluate_events(
+    events: &[NodeId],
+    event_filter: &EventFilter,
+    address_space: &AddressSpace,
+    client_handle: u32,
+) -> Option<Vec<EventFieldList>> {
+    let event_fields = events
+        .iter()
+        .filter(|event_id| {
+            if let Ok(result) =
+                evaluate_where_clause(event_id, &event_filter.where_clause, address_space)
+            {
+                result == Variant::Boolean(true)
+            } else {
+                false
+            }
+        })
+        .map(|event_id| {
+            // Produce an event notification list from the select clauses.
+            let event_fields = event_filter.select_clauses.as_ref().map(|select_clauses| {
+                select_clauses
+                    .iter()
+                    .map(|v| operator::value_of_simple_attribute(event_id, v, address_space))
+                    .collect()
+            });
+            EventFieldList {
+                client_handle,
+                event_fields,
+            }
+        })
+        .collect::<Vec<EventFieldList>>();
+    if event_fields.is_empty() {
         None
+    } else {
+        Some(event_fields)
+    }
+}
+
+/// Produces the fields of an `EventQueueOverflowEventType` event from the select clauses of the
+/// filter. The server inserts the event into the queue of a monitored item when the queue
+/// overflows, so it is not an object in the address space and only the fields of a
+/// `BaseEventType` have values.
+pub(crate) fn overflow_event(
+    event_filter: &EventFilter,
+    client_handle: u32,
+    now: &DateTime,
+) -> EventFieldList {
+    server_event(
+        event_filter,
+        client_handle,
+        ObjectTypeId::EventQueueOverflowEventType,
+        "Event queue overflow",
+        now,
+    )
+}
+
+/// Produces the fields of the `RefreshStartEventType` event that precedes the retained conditions
+/// replayed by a `ConditionRefresh`. Like the overflow event it is not in the address space.
+pub(crate) fn refresh_start_event(
+    event_filter: &EventFilter,
+    client_handle: u32,
+    now: &DateTime,
+) -> EventFieldList {
+    server_event(
+        event_filter,
+        client_handle,
+        ObjectTypeId::RefreshStartEventType,
+        "Condition refresh start",
+        now,
+    )
+}
+
+/// Produces the fields of the `RefreshEndEventType` event that follows the retained conditions
+/// replayed by a `ConditionRefresh`.
+pub(crate) fn refresh_end_event(
+    event_filter: &EventFilter,
+    client_handle: u32,
+    now: &DateTime,
+) -> EventFieldList {
+    server_event(
+        event_filter,
+        client_handle,
+        ObjectTypeId::RefreshEndEventType,
+        "Condition refresh end",
+        now,
+    )
+}
+
+fn server_event(
+    event_filter: &EventFilter,
+    client_handle: u32,
+    event_type_id: ObjectTypeId,
+    message: &str,
+    now: &DateTime,
+) -> EventFieldList {
+    let event_type_id: NodeId = event_type_id.into();
+    let event_fields = event_filter.select_clauses.as_ref().map(|select_clauses| {
+        select_clauses
+            .iter()
+            .map(|clause| server_event_field(clause, &event_type_id, message, now))
+            .collect()
+    });
+    EventFieldList {
+        client_handle,
+        event_fields,
+    }
+}
+
+fn server_event_field(
+    clause: &SimpleAttributeOperand,
+    event_type_id: &NodeId,
+    message: &str,
+    now: &DateTime,
+) -> Variant {
+    if (clause.type_definition_id != ObjectTypeId::BaseEventType.into()
+        && clause.type_definition_id != *event_type_id)
+        || clause.attribute_id != AttributeId::Value as u32
+    {
+        return Variant::Empty;
+    }
+    match clause.browse_path.as_deref() {
+        Some([field]) if field.namespace_index == 0 => match field.name.as_ref() {
+            "EventId" => ByteString::from(Guid::new()).into(),
+            "EventType" => event_type_id.clone().into(),
+            "SourceNode" => NodeId::from(&ObjectId::Server).into(),
+            "SourceName" => UAString::from("Server").into(),
+            "Time" | "ReceiveTime" => (*now).into(),
+            "Message" => LocalizedText::from(message).into(),
+            "Severity" => 1u16.into(),
+            _ => Variant::Empty,
+        },
+        _ => Variant::Empty,
     }
 }
 
@@ -167,7 +284,7 @@ fn validate_select_clause(
     }
 }
 
-fn validate_where_clause(
+pub(crate) fn validate_where_clause(
     where_clause: &ContentFilter,
     address_space: &AddressSpace,
 ) -> Result<ContentFilterResult, StatusCode> {
@@ -209,6 +326,7 @@ fn validate_where_clause(
                     FilterOperator::Cast => filter_operands.len() < 2,
                     FilterOperator::BitwiseAnd => filter_operands.len() < 2,
                     FilterOperator::BitwiseOr => filter_operands.len() < 2,
+                    FilterOperator::OfType => filter_operands.is_empty(),
                     _ => true,
                 };
 
@@ -358,6 +476,7 @@ fn validate_where_clause_test() {
                 )),
                 ContentFilterElement::from((FilterOperator::BitwiseOr, vec![Operand::literal(10)])),
                 ContentFilterElement::from((FilterOperator::Like, vec![Operand::literal(10)])),
+                ContentFilterElement::from((FilterOperator::OfType, vec![])),
             ]),
         };
         // Check for less than required number of operands
@@ -0,0 +1,383 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the limit alarms, the conditions that watch the value of an analog variable and are
+//! active while it lies beyond the limits that are configured for them.
+use crate::types::*;
+
+use crate::server::{
+    address_space::{
+        object::ObjectBuilder,
+        state_machine::add_current_state_variable,
+        variable::{add_property, good_value},
+        AddressSpace,
+    },
+    events::{
+        alarm::{add_two_state_variable, two_state, Alarm, AlarmState},
+        event::{BaseEventType, Event},
+    },
+};
+
+/// The kinds of limit alarm. A deviation alarm compares the limits with the difference between
+/// the value of its input and the value of a setpoint variable rather than with the value itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitAlarmKind {
+    /// An `ExclusiveLimitAlarmType`, which is in at most one limit state at a time
+    ExclusiveLimit,
+    /// A `NonExclusiveLimitAlarmType`, which is in every limit state that the value lies beyond
+    NonExclusiveLimit,
+    /// An `ExclusiveDeviationAlarmType` with the node id of its setpoint variable
+    ExclusiveDeviation(NodeId),
+    /// A `NonExclusiveDeviationAlarmType` with the node id of its setpoint variable
+    NonExclusiveDeviation(NodeId),
+}
+
+impl LimitAlarmKind {
+    fn event_type_id(&self) -> ObjectTypeId {
+        match self {
+            LimitAlarmKind::ExclusiveLimit => ObjectTypeId::ExclusiveLimitAlarmType,
+            LimitAlarmKind::NonExclusiveLimit => ObjectTypeId::NonExclusiveLimitAlarmType,
+            LimitAlarmKind::ExclusiveDeviation(_) => ObjectTypeId::ExclusiveDeviationAlarmType,
+            LimitAlarmKind::NonExclusiveDeviation(_) => {
+                ObjectTypeId::NonExclusiveDeviationAlarmType
+            }
+        }
+    }
+
+    fn is_exclusive(&self) -> bool {
+        matches!(
+            self,
+            LimitAlarmKind::ExclusiveLimit | LimitAlarmKind::ExclusiveDeviation(_)
+        )
+    }
+
+    fn setpoint_node(&self) -> Option<&NodeId> {
+        match self {
+            LimitAlarmKind::ExclusiveDeviation(setpoint_node)
+            | LimitAlarmKind::NonExclusiveDeviation(setpoint_node) => Some(setpoint_node),
+            _ => None,
+        }
+    }
+}
+
+/// The limit states of a limit alarm, from the highest to the lowest
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LimitState {
+    HighHigh,
+    High,
+    Low,
+    LowLow,
+}
+
+impl LimitState {
+    fn name(&self) -> &'static str {
+        match self {
+            LimitState::HighHigh => "HighHigh",
+            LimitState::High => "High",
+            LimitState::Low => "Low",
+            LimitState::LowLow => "LowLow",
+        }
+    }
+
+    /// The state of the `LimitState` state machine of an exclusive alarm
+    fn state_id(&self) -> ObjectId {
+        match self {
+            LimitState::HighHigh => ObjectId::ExclusiveLimitStateMachineType_HighHigh,
+            LimitState::High => ObjectId::ExclusiveLimitStateMachineType_High,
+            LimitState::Low => ObjectId::ExclusiveLimitStateMachineType_Low,
+            LimitState::LowLow => ObjectId::ExclusiveLimitStateMachineType_LowLow,
+        }
+    }
+
+    /// The browse name of the two state variable of a non-exclusive alarm
+    fn two_state_name(&self) -> String {
+        format!("{}State", self.name())
+    }
+
+    fn limit_name(&self) -> String {
+        format!("{}Limit", self.name())
+    }
+
+    fn is_high(&self) -> bool {
+        matches!(self, LimitState::HighHigh | LimitState::High)
+    }
+}
+
+/// This corresponds to the limit alarm types of OPC UA Part 9, i.e. `ExclusiveLimitAlarmType`,
+/// `NonExclusiveLimitAlarmType` and their deviation alarm subtypes. Raising the alarm inserts the
+/// condition into the address space, after which the address space evaluates it each time the
+/// value of its input, or of its setpoint, is set through the address space or written by a client.
+///
+/// Whenever the limit states of the alarm change, the condition is updated and fires an event, so
+/// the alarm is reported to the monitored items for the events of its source node. The condition
+/// is retained while it is active so a `ConditionRefresh` replays it. The alarm is not reported
+/// while it is suppressed or shelved.
+pub struct LimitAlarmType {
+    base: BaseEventType,
+    kind: LimitAlarmKind,
+    input_node: NodeId,
+    limits: Vec<(LimitState, f64)>,
+    max_time_shelved: Option<f64>,
+}
+
+impl Event for LimitAlarmType {
+    type Err = ();
+
+    fn is_valid(&self) -> bool {
+        // Limits must be in the order of their states.
+        self.base.is_valid()
+            && !self.limits.is_empty()
+            && self
+                .limits
+                .windows(2)
+                .all(|limits| limits[0].1 > limits[1].1)
+    }
+
+    fn raise(&mut self, address_space: &mut AddressSpace) -> Result<NodeId, Self::Err> {
+        if self.is_valid() {
+            let node_id = self.base.raise(address_space)?;
+            let state = AlarmState::raise(
+                &node_id,
+                &self.input_node,
+                self.max_time_shelved,
+                address_space,
+            );
+
+            if let Some(setpoint_node) = self.kind.setpoint_node() {
+                add_property(
+                    &node_id,
+                    "SetpointNode",
+                    DataTypeId::NodeId,
+                    setpoint_node.clone(),
+                    address_space,
+                );
+            }
+            self.limits.iter().for_each(|(state, limit)| {
+                add_property(
+                    &node_id,
+                    &state.limit_name(),
+                    DataTypeId::Double,
+                    *limit,
+                    address_space,
+                );
+            });
+
+            // The limit states of the alarm
+            if self.kind.is_exclusive() {
+                let limit_state_id = NodeId::next_numeric(node_id.namespace);
+                ObjectBuilder::new(&limit_state_id, "LimitState", "LimitState")
+                    .component_of(node_id.clone())
+                    .has_type_definition(ObjectTypeId::ExclusiveLimitStateMachineType)
+                    .insert(address_space);
+                add_current_state_variable(
+                    &limit_state_id,
+                    LocalizedText::null(),
+                    NodeId::null(),
+                    address_space,
+                );
+            } else {
+                self.limits.iter().for_each(|(state, _)| {
+                    add_two_state_variable(&node_id, &state.two_state_name(), address_space);
+                });
+            }
+
+            let mut limit_alarm = LimitAlarm {
+                state,
+                input_node: self.input_node.clone(),
+                setpoint_node: self.kind.setpoint_node().cloned(),
+                exclusive: self.kind.is_exclusive(),
+                limits: self.limits.clone(),
+                states: Vec::new(),
+            };
+            limit_alarm.evaluate(address_space, true);
+            address_space.add_alarm(Alarm::Limit(limit_alarm));
+
+            Ok(node_id)
+        } else {
+            error!("Limit alarm is invalid and will not be inserted");
+            Err(())
+        }
+    }
+}
+
+base_event_impl!(LimitAlarmType, base);
+
+impl LimitAlarmType {
+    /// Creates a limit alarm of the kind for the input variable. The source node of the alarm is
+    /// the `Server` unless it is set to another object that clients subscribe to for events.
+    pub fn new<R, S, T, U>(
+        node_id: R,
+        browse_name: S,
+        display_name: T,
+        input_node: U,
+        kind: LimitAlarmKind,
+    ) -> Self
+    where
+        R: Into<NodeId>,
+        S: Into<QualifiedName>,
+        T: Into<LocalizedText>,
+        U: Into<NodeId>,
+    {
+        let base = BaseEventType::new_now(
+            node_id,
+            kind.event_type_id(),
+            browse_name,
+            display_name,
+            NodeId::null(),
+        )
+        .source_node(ObjectId::Server)
+        .source_name("Server")
+        .severity(500);
+        Self {
+            base,
+            kind,
+            input_node: input_node.into(),
+            limits: Vec::new(),
+            max_time_shelved: None,
+        }
+    }
+
+    /// Sets the longest time in milliseconds that the alarm may be shelved for, which is also how
+    /// long it is one shot shelved for at most
+    pub fn max_time_shelved(mut self, max_time_shelved: f64) -> Self {
+        self.max_time_shelved = Some(max_time_shelved);
+        self
+    }
+
+    pub fn high_high_limit(self, limit: f64) -> Self {
+        self.limit(LimitState::HighHigh, limit)
+    }
+
+    pub fn high_limit(self, limit: f64) -> Self {
+        self.limit(LimitState::High, limit)
+    }
+
+    pub fn low_limit(self, limit: f64) -> Self {
+        self.limit(LimitState::Low, limit)
+    }
+
+    pub fn low_low_limit(self, limit: f64) -> Self {
+        self.limit(LimitState::LowLow, limit)
+    }
+
+    fn limit(mut self, state: LimitState, limit: f64) -> Self {
+        self.limits.retain(|(s, _)| *s != state);
+        self.limits.push((state, limit));
+        self.limits.sort_by_key(|(state, _)| *state as u8);
+        self
+    }
+}
+
+/// The state of a limit alarm that the address space evaluates when the value of its input or
+/// setpoint changes.
+pub(crate) struct LimitAlarm {
+    pub(crate) state: AlarmState,
+    input_node: NodeId,
+    setpoint_node: Option<NodeId>,
+    exclusive: bool,
+    limits: Vec<(LimitState, f64)>,
+    /// The limit states that the alarm is in, empty while it is inactive
+    states: Vec<LimitState>,
+}
+
+impl LimitAlarm {
+    /// Tests if the alarm is evaluated when the value of the node changes
+    pub(crate) fn watches(&self, node_id: &NodeId) -> bool {
+        self.input_node == *node_id || self.setpoint_node.as_ref() == Some(node_id)
+    }
+
+    /// Evaluates the alarm against the current value of its input. When its limit states change,
+    /// or when it is forced to, the condition is updated to the new states and fires an event.
+    pub(crate) fn evaluate(&mut self, address_space: &mut AddressSpace, force: bool) {
+        let states = match self.input(address_space) {
+            Some(value) => self.states_of(value),
+            // A value that is bad or not a number leaves the alarm as it is
+            None => self.states.clone(),
+        };
+        if force || states != self.states {
+            self.states = states;
+            self.update(address_space);
+        }
+    }
+
+    /// Returns the value of the input, less the setpoint for a deviation alarm
+    fn input(&self, address_space: &AddressSpace) -> Option<f64> {
+        let value_of =
+            |node_id: &NodeId| good_value(address_space, node_id).and_then(|value| value.as_f64());
+        match self.setpoint_node {
+            Some(ref setpoint_node) => Some(value_of(&self.input_node)? - value_of(setpoint_node)?),
+            None => value_of(&self.input_node),
+        }
+    }
+
+    /// Returns the limit states that the value lies beyond. An exclusive alarm is only in the
+    /// state of the furthest limit.
+    fn states_of(&self, value: f64) -> Vec<LimitState> {
+        let states = self
+            .limits
+            .iter()
+            .filter(|(state, limit)| {
+                if state.is_high() {
+                    value >= *limit
+                } else {
+                    value <= *limit
+                }
+            })
+            .map(|(state, _)| *state)
+            .collect::<Vec<_>>();
+        if self.exclusive {
+            let furthest = if states.first().is_some_and(|state| state.is_high()) {
+                states.first()
+            } else {
+                states.last()
+            };
+            furthest.into_iter().cloned().collect()
+        } else {
+            states
+        }
+    }
+
+    /// Updates the condition to the states of the alarm, which fires an event for it
+    fn update(&mut self, address_space: &mut AddressSpace) {
+        let active = !self.states.is_empty();
+        let message = if active {
+            let states = self
+                .states
+                .iter()
+                .map(|state| state.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("The value is beyond the {} limit", states)
+        } else {
+            "The value is within its limits".to_string()
+        };
+
+        if self.exclusive {
+            let (current_state, current_state_id) = match self.states.first() {
+                Some(state) => (LocalizedText::from(state.name()), state.state_id().into()),
+                None => (LocalizedText::null(), NodeId::null()),
+            };
+            self.state.set_variable(
+                address_space,
+                &["LimitState", "CurrentState"],
+                current_state,
+            );
+            self.state.set_variable(
+                address_space,
+                &["LimitState", "CurrentState", "Id"],
+                current_state_id,
+            );
+        } else {
+            self.limits.iter().for_each(|(state, _)| {
+                let name = state.two_state_name();
+                let active = self.states.contains(state);
+                self.state
+                    .set_variable(address_space, &[&name], two_state(active));
+                self.state
+                    .set_variable(address_space, &[&name, "Id"], active);
+            });
+        }
+        self.state.update(address_space, active, &message);
+    }
+}
@@ -2,9 +2,14 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
+pub mod alarm;
 pub mod event_filter;
 pub(crate) mod operator;
 #[macro_use]
 pub mod event;
 #[macro_use]
 pub mod audit;
+pub mod limit_alarm;
+pub mod model_change_event;
+pub mod off_normal_alarm;
+pub mod transition_event;
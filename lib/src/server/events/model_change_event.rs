@@ -0,0 +1,85 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the model change event that tells clients which nodes and references have been added
+//! to or removed from the address space.
+use crate::types::{service_types::ModelChangeStructureDataType, *};
+
+use crate::server::{
+    address_space::AddressSpace,
+    events::event::{BaseEventType, Event},
+};
+
+/// This corresponds to GeneralModelChangeEventType definition in OPC UA Part 5. The event is
+/// raised by the `Server` object and its `Changes` property describes each affected node and what
+/// happened to it.
+pub struct GeneralModelChangeEventType {
+    base: BaseEventType,
+    changes: Vec<ModelChangeStructureDataType>,
+}
+
+impl Event for GeneralModelChangeEventType {
+    type Err = ();
+
+    fn is_valid(&self) -> bool {
+        self.base.is_valid() && !self.changes.is_empty()
+    }
+
+    fn raise(&mut self, address_space: &mut AddressSpace) -> Result<NodeId, Self::Err> {
+        if self.is_valid() {
+            let node_id = self.base.raise(address_space)?;
+            let ns = node_id.namespace;
+            let changes = self
+                .changes
+                .iter()
+                .map(|change| {
+                    ExtensionObject::from_encodable(
+                        ObjectId::ModelChangeStructureDataType_Encoding_DefaultBinary,
+                        change,
+                    )
+                    .into()
+                })
+                .collect::<Vec<Variant>>();
+            self.add_property(
+                &node_id,
+                NodeId::next_numeric(ns),
+                "Changes",
+                "Changes",
+                DataTypeId::ModelChangeStructureDataType,
+                Variant::from((VariantTypeId::ExtensionObject, changes)),
+                address_space,
+            );
+            Ok(node_id)
+        } else {
+            error!("Model change event is invalid and will not be inserted");
+            Err(())
+        }
+    }
+}
+
+base_event_impl!(GeneralModelChangeEventType, base);
+
+impl GeneralModelChangeEventType {
+    pub fn new<R>(node_id: R, time: DateTime, changes: Vec<ModelChangeStructureDataType>) -> Self
+    where
+        R: Into<NodeId>,
+    {
+        let base = BaseEventType::new(
+            node_id,
+            ObjectTypeId::GeneralModelChangeEventType,
+            "GeneralModelChangeEvent",
+            "GeneralModelChangeEvent",
+            NodeId::null(),
+            time,
+        )
+        .source_node(ObjectId::Server)
+        .source_name("Server")
+        .message(LocalizedText::from("The address space has changed"));
+        Self { base, changes }
+    }
+
+    pub fn changes(&self) -> &[ModelChangeStructureDataType] {
+        &self.changes
+    }
+}
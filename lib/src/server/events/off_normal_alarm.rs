@@ -0,0 +1,168 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the off normal alarm, the discrete alarm that is active while a boolean or enumerated
+//! variable has a value other than its normal one.
+use crate::types::*;
+
+use crate::server::{
+    address_space::{
+        variable::{add_property, good_value},
+        AddressSpace,
+    },
+    events::{
+        alarm::{Alarm, AlarmState},
+        event::{BaseEventType, Event},
+    },
+};
+
+/// This corresponds to the `OffNormalAlarmType` of OPC UA Part 9. The normal state of the alarm is
+/// a variable that holds the normal value of its input. Raising the alarm inserts the condition
+/// into the address space, after which the address space evaluates it each time the value of its
+/// input, or its normal state, is set through the address space or written by a client.
+///
+/// The alarm is active while the values differ and fires an event whenever it becomes active or
+/// inactive, unless it is suppressed or shelved. The condition is retained while it is active.
+pub struct OffNormalAlarmType {
+    base: BaseEventType,
+    input_node: NodeId,
+    normal_state: NodeId,
+    max_time_shelved: Option<f64>,
+}
+
+impl Event for OffNormalAlarmType {
+    type Err = ();
+
+    fn is_valid(&self) -> bool {
+        self.base.is_valid() && !self.input_node.is_null() && !self.normal_state.is_null()
+    }
+
+    fn raise(&mut self, address_space: &mut AddressSpace) -> Result<NodeId, Self::Err> {
+        if self.is_valid() {
+            let node_id = self.base.raise(address_space)?;
+            let state = AlarmState::raise(
+                &node_id,
+                &self.input_node,
+                self.max_time_shelved,
+                address_space,
+            );
+            add_property(
+                &node_id,
+                "NormalState",
+                DataTypeId::NodeId,
+                self.normal_state.clone(),
+                address_space,
+            );
+
+            let mut off_normal_alarm = OffNormalAlarm {
+                state,
+                input_node: self.input_node.clone(),
+                normal_state: self.normal_state.clone(),
+                active: false,
+            };
+            off_normal_alarm.evaluate(address_space, true);
+            address_space.add_alarm(Alarm::OffNormal(off_normal_alarm));
+
+            Ok(node_id)
+        } else {
+            error!("Off normal alarm is invalid and will not be inserted");
+            Err(())
+        }
+    }
+}
+
+base_event_impl!(OffNormalAlarmType, base);
+
+impl OffNormalAlarmType {
+    /// Creates an off normal alarm for the input variable, and the variable that holds its normal
+    /// value. The source node of the alarm is the `Server` unless it is set to another object that
+    /// clients subscribe to for events.
+    pub fn new<R, S, T, U, V>(
+        node_id: R,
+        browse_name: S,
+        display_name: T,
+        input_node: U,
+        normal_state: V,
+    ) -> Self
+    where
+        R: Into<NodeId>,
+        S: Into<QualifiedName>,
+        T: Into<LocalizedText>,
+        U: Into<NodeId>,
+        V: Into<NodeId>,
+    {
+        let base = BaseEventType::new_now(
+            node_id,
+            ObjectTypeId::OffNormalAlarmType,
+            browse_name,
+            display_name,
+            NodeId::null(),
+        )
+        .source_node(ObjectId::Server)
+        .source_name("Server")
+        .severity(500);
+        Self {
+            base,
+            input_node: input_node.into(),
+            normal_state: normal_state.into(),
+            max_time_shelved: None,
+        }
+    }
+
+    /// Sets the longest time in milliseconds that the alarm may be shelved for, which is also how
+    /// long it is one shot shelved for at most
+    pub fn max_time_shelved(mut self, max_time_shelved: f64) -> Self {
+        self.max_time_shelved = Some(max_time_shelved);
+        self
+    }
+}
+
+/// The state of an off normal alarm that the address space evaluates when the value of its input
+/// or normal state changes.
+pub(crate) struct OffNormalAlarm {
+    pub(crate) state: AlarmState,
+    input_node: NodeId,
+    normal_state: NodeId,
+    active: bool,
+}
+
+impl OffNormalAlarm {
+    /// Tests if the alarm is evaluated when the value of the node changes
+    pub(crate) fn watches(&self, node_id: &NodeId) -> bool {
+        self.input_node == *node_id || self.normal_state == *node_id
+    }
+
+    /// Evaluates the alarm against the current value of its input. When it becomes active or
+    /// inactive, or when it is forced to, the condition is updated and fires an event.
+    pub(crate) fn evaluate(&mut self, address_space: &mut AddressSpace, force: bool) {
+        let values = (
+            good_value(address_space, &self.input_node),
+            good_value(address_space, &self.normal_state),
+        );
+        // A value that is bad or missing leaves the alarm as it is
+        let active = if let (Some(value), Some(normal_value)) = values {
+            !Self::is_normal(&value, &normal_value)
+        } else {
+            self.active
+        };
+        if force || active != self.active {
+            self.active = active;
+            let message = if active {
+                "The value is off normal"
+            } else {
+                "The value is normal"
+            };
+            self.state.update(address_space, active, message);
+        }
+    }
+
+    /// Compares the value with the normal value. Numbers are compared by value so an enumeration
+    /// that is held as an `Int32` matches a normal value of another integer type.
+    fn is_normal(value: &Variant, normal_value: &Variant) -> bool {
+        match (value.as_f64(), normal_value.as_f64()) {
+            (Some(value), Some(normal_value)) => value == normal_value,
+            _ => value == normal_value,
+        }
+    }
+}
@@ -12,8 +12,8 @@ use crate::types::{
     operand::Operand,
     service_types::{ContentFilterElement, FilterOperator, SimpleAttributeOperand},
     status_code::StatusCode,
-    AttributeId, ExtensionObject, NodeId, NumericRange, QualifiedName, TimestampsToReturn, Variant,
-    VariantTypeId,
+    AttributeId, ExtensionObject, NodeId, NumericRange, QualifiedName, ReferenceTypeId,
+    TimestampsToReturn, Variant, VariantTypeId,
 };
 
 use crate::server::address_space::{
@@ -161,6 +161,13 @@ pub(crate) fn evaluate(
                     elements,
                     address_space,
                 ),
+                FilterOperator::OfType => of_type(
+                    object_id,
+                    &operands[..],
+                    used_elements,
+                    elements,
+                    address_space,
+                ),
                 _ => Err(StatusCode::BadFilterOperatorUnsupported),
             }
         } else {
@@ -319,6 +326,38 @@ pub(crate) fn is_null(
     Ok((Variant::Empty == v1).into())
 }
 
+// Tests if the node is an instance of the type in operand[0], or of a subtype of it. TRUE if the
+// type definition of the node is the type or a subtype. Operand[0] must resolve to a NodeId.
+pub(crate) fn of_type(
+    object_id: &NodeId,
+    operands: &[Operand],
+    used_elements: &mut HashSet<u32>,
+    elements: &[ContentFilterElement],
+    address_space: &AddressSpace,
+) -> Result<Variant, StatusCode> {
+    let v1 = value_of(
+        object_id,
+        &operands[0],
+        used_elements,
+        elements,
+        address_space,
+    )?;
+    if let Variant::NodeId(type_id) = v1 {
+        let result = address_space
+            .find_references(object_id, Some((ReferenceTypeId::HasTypeDefinition, false)))
+            .map(|references| {
+                references
+                    .iter()
+                    .any(|r| address_space.is_subtype_of(&r.target_node, &type_id))
+            })
+            .unwrap_or(false);
+        Ok(result.into())
+    } else {
+        error!("of_type, operand must be a node id, got {:?}", v1);
+        Err(StatusCode::BadFilterOperandInvalid)
+    }
+}
+
 #[derive(PartialEq)]
 enum ComparisonResult {
     // Value 1 is less than value 2
@@ -0,0 +1,132 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the transition event that a state machine fires when it takes a transition from one
+//! state to another.
+use crate::types::*;
+
+use crate::server::{
+    address_space::{variable::add_property, AddressSpace},
+    events::event::{BaseEventType, Event},
+};
+
+/// This corresponds to the `TransitionEventType` of OPC UA Part 16, or to its
+/// `ProgramTransitionEventType` subtype of OPC UA Part 10 for the transitions of a program. The
+/// `Transition`, `FromState` and `ToState` properties of the event hold the names of the
+/// transition and of its states, and each has an `Id` property with the node id of the
+/// transition or state.
+pub struct TransitionEventType {
+    base: BaseEventType,
+    event_type_id: ObjectTypeId,
+    transition: (LocalizedText, NodeId),
+    from_state: (LocalizedText, NodeId),
+    to_state: (LocalizedText, NodeId),
+}
+
+impl Event for TransitionEventType {
+    type Err = ();
+
+    fn is_valid(&self) -> bool {
+        self.base.is_valid() && !self.transition.1.is_null() && !self.to_state.1.is_null()
+    }
+
+    fn raise(&mut self, address_space: &mut AddressSpace) -> Result<NodeId, Self::Err> {
+        if self.is_valid() {
+            let node_id = self.base.raise(address_space)?;
+            let ns = node_id.namespace;
+            let properties = [
+                ("Transition", self.transition.clone()),
+                ("FromState", self.from_state.clone()),
+                ("ToState", self.to_state.clone()),
+            ];
+            for (name, (text, id)) in properties {
+                let property_id = NodeId::next_numeric(ns);
+                self.add_property(
+                    &node_id,
+                    property_id.clone(),
+                    name,
+                    name,
+                    DataTypeId::LocalizedText,
+                    text,
+                    address_space,
+                );
+                add_property(&property_id, "Id", DataTypeId::NodeId, id, address_space);
+            }
+            if self.event_type_id == ObjectTypeId::ProgramTransitionEventType {
+                self.add_property(
+                    &node_id,
+                    NodeId::next_numeric(ns),
+                    "IntermediateResult",
+                    "IntermediateResult",
+                    DataTypeId::BaseDataType,
+                    Variant::Empty,
+                    address_space,
+                );
+            }
+            Ok(node_id)
+        } else {
+            error!("Transition event is invalid and will not be inserted");
+            Err(())
+        }
+    }
+}
+
+base_event_impl!(TransitionEventType, base);
+
+impl TransitionEventType {
+    /// Creates a transition event of the event type, which is `TransitionEventType` or one of its
+    /// subtypes, for the source node, which is usually the state machine or the object that owns
+    /// it.
+    pub fn new<R, S>(node_id: R, event_type_id: ObjectTypeId, source_node: S) -> Self
+    where
+        R: Into<NodeId>,
+        S: Into<NodeId>,
+    {
+        let base = BaseEventType::new_now(
+            node_id,
+            event_type_id,
+            "TransitionEvent",
+            "TransitionEvent",
+            NodeId::null(),
+        )
+        .source_node(source_node);
+        Self {
+            base,
+            event_type_id,
+            transition: (LocalizedText::null(), NodeId::null()),
+            from_state: (LocalizedText::null(), NodeId::null()),
+            to_state: (LocalizedText::null(), NodeId::null()),
+        }
+    }
+
+    /// Sets the name and node id of the transition
+    pub fn transition<T, U>(mut self, name: T, transition_id: U) -> Self
+    where
+        T: Into<LocalizedText>,
+        U: Into<NodeId>,
+    {
+        self.transition = (name.into(), transition_id.into());
+        self
+    }
+
+    /// Sets the name and node id of the state that the transition leaves
+    pub fn from_state<T, U>(mut self, name: T, state_id: U) -> Self
+    where
+        T: Into<LocalizedText>,
+        U: Into<NodeId>,
+    {
+        self.from_state = (name.into(), state_id.into());
+        self
+    }
+
+    /// Sets the name and node id of the state that the transition enters
+    pub fn to_state<T, U>(mut self, name: T, state_id: U) -> Self
+    where
+        T: Into<LocalizedText>,
+        U: Into<NodeId>,
+    {
+        self.to_state = (name.into(), state_id.into());
+        self
+    }
+}
@@ -0,0 +1,217 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Provides the [`ServerHandle`], which threads and tasks outside of the server use to push value
+//! updates and events into its address space.
+//!
+//! [`ServerHandle`]: ./struct.ServerHandle.html
+
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use crate::sync::*;
+use crate::types::{DataValue, DateTime, NodeId, Variant};
+
+use crate::server::address_space::AddressSpace;
+#[cfg(feature = "events")]
+use crate::server::events::event::Event;
+
+/// The most updates that are applied under one lock of the address space, so that a burst of
+/// updates does not hold the lock for long
+const MAX_UPDATES_PER_LOCK: usize = 1000;
+
+/// An update to apply to the address space
+pub(crate) enum Update {
+    Value {
+        node_id: NodeId,
+        value: Variant,
+        source_timestamp: DateTime,
+    },
+    DataValue {
+        node_id: NodeId,
+        data_value: DataValue,
+    },
+    #[cfg(feature = "events")]
+    Event(Box<dyn FnOnce(&mut AddressSpace) + Send>),
+}
+
+/// A cheap, clonable handle that any thread or task can use to update the values of variables and
+/// raise events in the address space of a server.
+///
+/// Calls on the handle do not lock the address space or wait for the server. They queue the update,
+/// and the server applies queued updates on its runtime in batches, taking the lock of the address
+/// space once per batch. Updates are applied in the order they were queued, and those that are
+/// queued before the server runs are applied when it starts running.
+///
+/// ```no_run
+/// use std::thread;
+/// use opcua::server::prelude::*;
+///
+/// # fn example(server: &Server) {
+/// let handle = server.handle();
+/// thread::spawn(move || loop {
+///     let temperature = 21.5f64; // read from a device
+///     handle.set_value(NodeId::new(2, "Temperature"), temperature, &DateTime::now());
+///     thread::sleep(std::time::Duration::from_millis(100));
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ServerHandle {
+    updates: UnboundedSender<Update>,
+}
+
+impl ServerHandle {
+    /// Queues the value of a variable to be set, with the time that the value was sampled as its
+    /// source timestamp. Returns `false` if the server has stopped and will not set it.
+    pub fn set_value<N, V>(&self, node_id: N, value: V, source_timestamp: &DateTime) -> bool
+    where
+        N: Into<NodeId>,
+        V: Into<Variant>,
+    {
+        self.queue(Update::Value {
+            node_id: node_id.into(),
+            value: value.into(),
+            source_timestamp: *source_timestamp,
+        })
+    }
+
+    /// Queues the value of a variable to be set to a data value, e.g. to give it a status or the
+    /// picoseconds of its source timestamp. See `AddressSpace::set_variable_data_value()` for the
+    /// timestamps and status that the value is given if it has none. Returns `false` if the
+    /// server has stopped and will not set it.
+    pub fn set_data_value<N>(&self, node_id: N, data_value: DataValue) -> bool
+    where
+        N: Into<NodeId>,
+    {
+        self.queue(Update::DataValue {
+            node_id: node_id.into(),
+            data_value,
+        })
+    }
+
+    /// Queues an event to be raised. Returns `false` if the server has stopped and will not raise
+    /// it.
+    #[cfg(feature = "events")]
+    pub fn raise_event<E>(&self, mut event: E) -> bool
+    where
+        E: Event + Send + 'static,
+    {
+        self.queue(Update::Event(Box::new(move |address_space| {
+            if event.raise(address_space).is_err() {
+                error!("Event queued through a server handle could not be raised");
+            }
+        })))
+    }
+
+    /// Tests if the server has stopped, so updates are no longer applied
+    pub fn is_closed(&self) -> bool {
+        self.updates.is_closed()
+    }
+
+    fn queue(&self, update: Update) -> bool {
+        if self.updates.send(update).is_err() {
+            debug!("Update was not queued because the server has stopped");
+            false
+        } else {
+            true
+        }
+    }
+}
+
+/// The queue of updates that server handles have made, which the server applies to its address
+/// space
+pub(crate) struct UpdateQueue {
+    receiver: UnboundedReceiver<Update>,
+}
+
+impl UpdateQueue {
+    /// Creates a queue and the handle that updates go into it through
+    pub fn new() -> (ServerHandle, UpdateQueue) {
+        let (updates, receiver) = mpsc::unbounded_channel();
+        (ServerHandle { updates }, UpdateQueue { receiver })
+    }
+
+    /// Applies the updates that are queued, after the first if there is one, under one lock of
+    /// the address space and without waiting for more. Returns the number of updates that were
+    /// applied.
+    pub fn apply_queued(
+        &mut self,
+        address_space: &Arc<RwLock<AddressSpace>>,
+        first: Option<Update>,
+    ) -> usize {
+        let mut address_space = trace_write_lock!(address_space);
+        let mut applied = 0;
+        if let Some(first) = first {
+            Self::apply(&mut address_space, first);
+            applied += 1;
+        }
+        while applied < MAX_UPDATES_PER_LOCK {
+            match self.receiver.try_recv() {
+                Ok(update) => {
+                    Self::apply(&mut address_space, update);
+                    applied += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        applied
+    }
+
+    fn apply(address_space: &mut AddressSpace, update: Update) {
+        match update {
+            Update::Value {
+                node_id,
+                value,
+                source_timestamp,
+            } => {
+                let now = DateTime::now();
+                if !address_space.set_variable_value_by_ref(
+                    &node_id,
+                    value,
+                    &source_timestamp,
+                    &now,
+                ) {
+                    debug!(
+                        "Value queued through a server handle is for {} which is not a variable",
+                        node_id
+                    );
+                }
+            }
+            Update::DataValue {
+                node_id,
+                data_value,
+            } => {
+                if !address_space.set_variable_data_value(node_id.clone(), data_value) {
+                    debug!(
+                        "Value queued through a server handle is for {} which is not a variable",
+                        node_id
+                    );
+                }
+            }
+            #[cfg(feature = "events")]
+            Update::Event(raise) => raise(address_space),
+        }
+    }
+
+    /// Applies updates as they are queued until the token is cancelled, e.g. because the server
+    /// is aborting
+    pub async fn run(mut self, address_space: Arc<RwLock<AddressSpace>>, token: CancellationToken) {
+        loop {
+            let first = tokio::select! {
+                update = self.receiver.recv() => update,
+                _ = token.cancelled() => None,
+            };
+            match first {
+                Some(first) => {
+                    let _ = self.apply_queued(&address_space, Some(first));
+                }
+                None => break,
+            }
+        }
+        debug!("Server handle update task is finished");
+    }
+}
@@ -0,0 +1,372 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the automatic collection of the values of historizing variables, which the server
+//! passes on to the historical data provider.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::sync::*;
+use crate::types::*;
+
+use crate::server::{
+    address_space::{
+        object::ObjectBuilder,
+        relative_path::{find_node_from_browse_path, set_variable_value_from_browse_path},
+        variable::add_property,
+        AddressSpace,
+    },
+    state::ServerState,
+};
+
+/// The settings that the server collects the values of a historizing variable with. They are
+/// the properties of the `HistoricalDataConfigurationType` of OPC UA Part 11, which the address
+/// space inserts as the `HA Configuration` object of the variable.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HistoricalDataConfiguration {
+    /// Whether the values are stepped, i.e. a value holds until the next one, rather than sloped
+    pub stepped: bool,
+    /// The shortest time in milliseconds between two values that are collected, or 0 to collect
+    /// every change of the value
+    pub min_time_interval: f64,
+    /// The longest time in milliseconds between two values that are collected, after which the
+    /// current value is collected again even if it has not changed, or 0 to only collect changes
+    pub max_time_interval: f64,
+    /// How much a number must differ from the last value that was collected for it to be
+    /// collected, or 0 to collect every change of the value
+    pub exception_deviation: f64,
+}
+
+/// The last value that was collected for a variable
+struct CollectedValue {
+    time: DateTimeUtc,
+    value: Option<f64>,
+}
+
+/// Collects the values of the variables of the address space whose `Historizing` attribute is
+/// true while the server has a historical data provider. A variable without a configuration of
+/// its own has every change of its value collected.
+#[derive(Default)]
+pub(crate) struct HistoryCollector {
+    enabled: bool,
+    configurations: HashMap<NodeId, HistoricalDataConfiguration>,
+    aggregate_functions: Vec<NodeId>,
+    last_collected: HashMap<NodeId, CollectedValue>,
+    values: Vec<(NodeId, DataValue)>,
+}
+
+impl HistoryCollector {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts or stops collecting values. Values that were collected are dropped when it stops.
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.last_collected.clear();
+            self.values.clear();
+        }
+    }
+
+    pub(crate) fn set_configuration(
+        &mut self,
+        node_id: &NodeId,
+        configuration: HistoricalDataConfiguration,
+    ) {
+        self.configurations.insert(node_id.clone(), configuration);
+    }
+
+    /// Returns the configuration of a variable, or the default one if it has none of its own
+    pub(crate) fn configuration(&self, node_id: &NodeId) -> HistoricalDataConfiguration {
+        self.configurations
+            .get(node_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the aggregate functions that the history server capabilities declare, which the
+    /// `HA Configuration` objects organize
+    pub(crate) fn aggregate_functions(&self) -> &[NodeId] {
+        &self.aggregate_functions
+    }
+
+    pub(crate) fn set_aggregate_functions(&mut self, aggregate_functions: Vec<NodeId>) {
+        self.aggregate_functions = aggregate_functions;
+    }
+
+    pub(crate) fn remove(&mut self, node_id: &NodeId) {
+        self.configurations.remove(node_id);
+        self.last_collected.remove(node_id);
+    }
+
+    /// Collects the new value of a historizing variable, unless the last value was collected
+    /// too recently or the new value does not deviate enough from it.
+    pub(crate) fn value_changed(
+        &mut self,
+        node_id: &NodeId,
+        data_value: &DataValue,
+        now: &DateTimeUtc,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let configuration = self.configuration(node_id);
+        if let Some(last_collected) = self.last_collected.get(node_id) {
+            let since_last = (*now - last_collected.time).num_milliseconds() as f64;
+            if since_last < configuration.min_time_interval {
+                return;
+            }
+            let value = data_value.value.as_ref().and_then(|value| value.as_f64());
+            if let (Some(value), Some(last_value)) = (value, last_collected.value) {
+                if (value - last_value).abs() < configuration.exception_deviation {
+                    return;
+                }
+            }
+        }
+        self.collect(node_id, data_value.clone(), now);
+    }
+
+    /// Returns the variables whose last value was collected longer ago than their maximum
+    /// time interval, so their current value should be collected again
+    pub(crate) fn due_for_sampling(&self, now: &DateTimeUtc) -> Vec<NodeId> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.configurations
+            .iter()
+            .filter(|(_, configuration)| configuration.max_time_interval > 0f64)
+            .filter(|(node_id, configuration)| {
+                self.last_collected
+                    .get(*node_id)
+                    .map(|last_collected| {
+                        (*now - last_collected.time).num_milliseconds() as f64
+                            >= configuration.max_time_interval
+                    })
+                    .unwrap_or(true)
+            })
+            .map(|(node_id, _)| node_id.clone())
+            .collect()
+    }
+
+    /// Collects the value of a variable
+    pub(crate) fn collect(&mut self, node_id: &NodeId, data_value: DataValue, now: &DateTimeUtc) {
+        let value = data_value.value.as_ref().and_then(|value| value.as_f64());
+        self.last_collected
+            .insert(node_id.clone(), CollectedValue { time: *now, value });
+        self.values.push((node_id.clone(), data_value));
+    }
+
+    pub(crate) fn has_values(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    /// Takes the values that were collected, in the order they were collected
+    pub(crate) fn take_values(&mut self) -> Vec<(NodeId, DataValue)> {
+        std::mem::take(&mut self.values)
+    }
+}
+
+/// Inserts the `HA Configuration` object of a variable with the properties of the configuration
+/// and an `AggregateFunctions` folder that organizes the aggregate functions, or updates them if
+/// the variable already has one.
+pub(crate) fn insert_configuration(
+    address_space: &mut AddressSpace,
+    node_id: &NodeId,
+    configuration: &HistoricalDataConfiguration,
+    aggregate_functions: &[NodeId],
+) {
+    let properties: [(&str, DataTypeId, Variant); 4] = [
+        ("Stepped", DataTypeId::Boolean, configuration.stepped.into()),
+        (
+            "MinTimeInterval",
+            DataTypeId::Duration,
+            configuration.min_time_interval.into(),
+        ),
+        (
+            "MaxTimeInterval",
+            DataTypeId::Duration,
+            configuration.max_time_interval.into(),
+        ),
+        (
+            "ExceptionDeviation",
+            DataTypeId::Double,
+            configuration.exception_deviation.into(),
+        ),
+    ];
+    if let Some(configuration_id) = configuration_id(address_space, node_id) {
+        for (name, _, value) in properties {
+            let _ = set_variable_value_from_browse_path(
+                address_space,
+                &configuration_id,
+                &[name],
+                value,
+            );
+        }
+        if let Ok(folder) = find_node_from_browse_path(
+            address_space,
+            &configuration_id,
+            &["AggregateFunctions".into()],
+        ) {
+            let folder_id = folder.as_node().node_id();
+            organize_aggregate_functions(address_space, &folder_id, aggregate_functions);
+        }
+        return;
+    }
+
+    let ns = node_id.namespace;
+    let configuration_id = NodeId::next_numeric(ns);
+    ObjectBuilder::new(&configuration_id, "HA Configuration", "HA Configuration")
+        .has_type_definition(ObjectTypeId::HistoricalDataConfigurationType)
+        .insert(address_space);
+    address_space.insert_reference(
+        node_id,
+        &configuration_id,
+        ReferenceTypeId::HasHistoricalConfiguration,
+    );
+    for (name, data_type, value) in properties {
+        add_property(&configuration_id, name, data_type, value, address_space);
+    }
+    add_property(
+        &configuration_id,
+        "ExceptionDeviationFormat",
+        DataTypeId::ExceptionDeviationFormat,
+        ExceptionDeviationFormat::AbsoluteValue as i32,
+        address_space,
+    );
+    add_property(
+        &configuration_id,
+        "ServerTimestampSupported",
+        DataTypeId::Boolean,
+        true,
+        address_space,
+    );
+
+    // The aggregates that are computed from the values use the default settings of Part 13
+    let aggregate_configuration_id = NodeId::next_numeric(ns);
+    ObjectBuilder::new(
+        &aggregate_configuration_id,
+        "AggregateConfiguration",
+        "AggregateConfiguration",
+    )
+    .component_of(configuration_id.clone())
+    .has_type_definition(ObjectTypeId::AggregateConfigurationType)
+    .insert(address_space);
+    let aggregate_properties: [(&str, DataTypeId, Variant); 4] = [
+        ("TreatUncertainAsBad", DataTypeId::Boolean, true.into()),
+        ("PercentDataBad", DataTypeId::Byte, 100u8.into()),
+        ("PercentDataGood", DataTypeId::Byte, 100u8.into()),
+        ("UseSlopedExtrapolation", DataTypeId::Boolean, false.into()),
+    ];
+    for (name, data_type, value) in aggregate_properties {
+        add_property(
+            &aggregate_configuration_id,
+            name,
+            data_type,
+            value,
+            address_space,
+        );
+    }
+
+    let folder_id = NodeId::next_numeric(ns);
+    ObjectBuilder::new(&folder_id, "AggregateFunctions", "AggregateFunctions")
+        .component_of(configuration_id)
+        .is_folder()
+        .insert(address_space);
+    organize_aggregate_functions(address_space, &folder_id, aggregate_functions);
+}
+
+/// Makes an `AggregateFunctions` folder organize the aggregate functions in place of the ones
+/// that it organized before
+pub(crate) fn organize_aggregate_functions(
+    address_space: &mut AddressSpace,
+    folder_id: &NodeId,
+    aggregate_functions: &[NodeId],
+) {
+    let organized: Vec<NodeId> = address_space
+        .find_references(folder_id, Some((ReferenceTypeId::Organizes, false)))
+        .map(|references| references.iter().map(|r| r.target_node.clone()).collect())
+        .unwrap_or_default();
+    for target_node_id in &organized {
+        if !aggregate_functions.contains(target_node_id) {
+            address_space.delete_reference(folder_id, target_node_id, ReferenceTypeId::Organizes);
+        }
+    }
+    for aggregate_function in aggregate_functions {
+        if !organized.contains(aggregate_function) {
+            address_space.insert_reference(
+                folder_id,
+                aggregate_function,
+                ReferenceTypeId::Organizes,
+            );
+        }
+    }
+}
+
+/// Returns the `HA Configuration` object of a variable, if it has one
+fn configuration_id(address_space: &AddressSpace, node_id: &NodeId) -> Option<NodeId> {
+    address_space
+        .find_references(
+            node_id,
+            Some((ReferenceTypeId::HasHistoricalConfiguration, false)),
+        )
+        .and_then(|references| references.first().map(|r| r.target_node.clone()))
+}
+
+/// Passes the values that the address space collected since it was last called on to the
+/// historical data provider. The address space collects values while the server has a provider.
+pub(crate) fn collect_history(
+    server_state: &Arc<RwLock<ServerState>>,
+    address_space: &Arc<RwLock<AddressSpace>>,
+) {
+    let has_provider = {
+        let server_state = trace_read_lock!(server_state);
+        server_state.historical_data_provider.is_some()
+    };
+    let now = Utc::now();
+    let (provider_changed, needs_collection) = {
+        let address_space = trace_read_lock!(address_space);
+        let provider_changed = address_space.is_history_collection_enabled() != has_provider;
+        (
+            provider_changed,
+            provider_changed || address_space.has_history_to_collect(&now),
+        )
+    };
+    if !needs_collection {
+        return;
+    }
+    // A provider that was just set has the capabilities that it declares set in the address space
+    let capabilities = if provider_changed && has_provider {
+        let server_state = trace_read_lock!(server_state);
+        server_state
+            .historical_data_provider
+            .as_ref()
+            .and_then(|historical_data_provider| historical_data_provider.capabilities())
+    } else {
+        None
+    };
+    let values = {
+        let mut address_space = trace_write_lock!(address_space);
+        if let Some(ref capabilities) = capabilities {
+            address_space.set_history_server_capabilities(capabilities);
+        }
+        address_space.set_history_collection(has_provider);
+        address_space.collect_history(&now)
+    };
+    if !values.is_empty() {
+        let server_state = trace_read_lock!(server_state);
+        if let Some(ref historical_data_provider) = server_state.historical_data_provider {
+            if let Err(status_code) = historical_data_provider.collect_values(&values) {
+                debug!(
+                    "Historical data provider did not collect {} values, {}",
+                    values.len(),
+                    status_code
+                );
+            }
+        }
+    }
+}
@@ -10,8 +10,14 @@ use crate::types::*;
 
 use crate::server::address_space::AddressSpace;
 
+pub use self::data_collection::HistoricalDataConfiguration;
+
+pub(crate) mod data_collection;
+
 /// Values that should be set in the address space via `AddressSpace::set_history_server_capabilities()`
-/// to denote to clients what history capabilities the server has.
+/// to denote to clients what history capabilities the server has. A `HistoricalDataProvider` may
+/// also declare them through `HistoricalDataProvider::capabilities()`.
+#[derive(Debug, Clone, Default)]
 pub struct HistoryServerCapabilities {
     pub access_history_data: bool,
     pub access_history_events: bool,
@@ -27,6 +33,10 @@ pub struct HistoryServerCapabilities {
     pub update_event: bool,
     pub delete_event: bool,
     pub insert_annotation: bool,
+    /// The aggregate functions, such as `ObjectId::AggregateFunction_Average`, that the server
+    /// can compute when reading processed values. The `AggregateFunctions` folders of the
+    /// `HistoryServerCapabilities` object and of each `HA Configuration` object organize them.
+    pub aggregate_functions: Vec<NodeId>,
 }
 
 /// The `HistoricalEventProvider` trait provides the function stubs that a server will call
@@ -156,4 +166,23 @@ pub trait HistoricalDataProvider {
         info!("Unimplemented delete_at_time_details");
         Err(StatusCode::BadHistoryOperationUnsupported)
     }
+
+    /// Called with the values of historizing variables that the server collected, in the order
+    /// they were collected, for the provider to store. The server collects the values of a
+    /// variable while its `Historizing` attribute is true, according to the
+    /// `HistoricalDataConfiguration` that is set for it with
+    /// `AddressSpace::set_historical_data_configuration()`, or else every change of its value.
+    fn collect_values(&self, _values: &[(NodeId, DataValue)]) -> Result<(), StatusCode> {
+        info!("Unimplemented collect_values");
+        Err(StatusCode::BadHistoryOperationUnsupported)
+    }
+
+    /// Declares the history capabilities of the provider. The server sets them in the address
+    /// space, as it would with `AddressSpace::set_history_server_capabilities()`, when the
+    /// provider is set, and generates an `HA Configuration` object for each historizing
+    /// variable. The default implementation declares nothing and leaves the capabilities as
+    /// they are.
+    fn capabilities(&self) -> Option<HistoryServerCapabilities> {
+        None
+    }
 }
@@ -8,7 +8,10 @@ pub(crate) const POLICY_ID_ANONYMOUS: &str = "anonymous";
 pub(crate) const POLICY_ID_USER_PASS_NONE: &str = "userpass_none";
 pub(crate) const POLICY_ID_USER_PASS_RSA_15: &str = "userpass_rsa_15";
 pub(crate) const POLICY_ID_USER_PASS_RSA_OAEP: &str = "userpass_rsa_oaep";
-pub(crate) const POLICY_ID_X509: &str = "x509";
+pub(crate) const POLICY_ID_USER_PASS_RSA_OAEP_SHA256: &str = "userpass_rsa_oaep_sha256";
+pub(crate) const POLICY_ID_X509_RSA_SHA1: &str = "x509_rsa_sha1";
+pub(crate) const POLICY_ID_X509_RSA_SHA256: &str = "x509_rsa_sha256";
+pub(crate) const POLICY_ID_X509_RSA_PSS_SHA256: &str = "x509_rsa_pss_sha256";
 
 pub enum IdentityToken {
     None,
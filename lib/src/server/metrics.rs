@@ -9,11 +9,11 @@
 use crate::runtime_components;
 use crate::types::DateTime;
 
+#[cfg(feature = "diagnostics")]
+use crate::server::diagnostics::ServerDiagnostics;
 use crate::server::{
     comms::transport::{Transport, TransportState},
-    config,
-    diagnostics::ServerDiagnostics,
-    server,
+    config, server,
     state::ServerState,
     subscriptions::subscriptions,
 };
@@ -21,6 +21,7 @@ use crate::server::{
 #[derive(Serialize)]
 pub struct ServerMetrics {
     pub server: Server,
+    #[cfg(feature = "diagnostics")]
     pub diagnostics: ServerDiagnostics,
     pub config: Option<config::ServerConfig>,
     pub connections: Vec<Connection>,
@@ -59,6 +60,7 @@ impl Default for ServerMetrics {
                 start_time: String::new(),
                 uptime_ms: 0,
             },
+            #[cfg(feature = "diagnostics")]
             diagnostics: ServerDiagnostics::default(),
             config: None,
             connections: Vec::new(),
@@ -104,6 +106,7 @@ impl ServerMetrics {
         self.server.start_time = start_time.as_chrono().to_rfc3339();
 
         // Take a snapshot of the diagnostics
+        #[cfg(feature = "diagnostics")]
         {
             let diagnostics = trace_read_lock!(server_state.diagnostics);
             self.diagnostics = diagnostics.clone();
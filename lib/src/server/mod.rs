@@ -53,13 +53,20 @@ pub mod callbacks;
 pub mod comms;
 pub mod config;
 pub mod continuation_point;
+#[cfg(feature = "diagnostics")]
 pub mod diagnostics;
+#[cfg(feature = "events")]
 #[macro_use]
 pub mod events;
+pub mod handle;
+#[cfg(feature = "history")]
 pub mod historical;
 pub mod metrics;
+pub mod profiles;
+pub mod scheduler;
 pub mod server;
 pub mod session;
+pub mod simulation;
 pub mod state;
 pub mod subscriptions;
 pub mod util;
@@ -69,21 +76,29 @@ pub mod prelude {
     //! single use statement.
     pub use crate::core::prelude::*;
     pub use crate::crypto::*;
+    #[cfg(feature = "events")]
+    pub use crate::server::events::{
+        alarm::ShelvedState, event::*, limit_alarm::*, off_normal_alarm::OffNormalAlarmType,
+        transition_event::TransitionEventType,
+    };
+    #[cfg(feature = "history")]
+    pub use crate::server::historical::*;
     pub use crate::server::{
+        // The node, not the callbacks::Method trait that handles calls to it
+        address_space::types::Method,
         address_space::types::*,
         address_space::{AccessLevel, EventNotifier, UserAccessLevel},
         builder::*,
         callbacks::*,
         config::*,
-        events::event::*,
-        historical::*,
+        handle::ServerHandle,
+        scheduler::TaskHandle,
         server::*,
+        simulation::*,
         subscriptions::*,
         util::*,
     };
-    pub use crate::types::service_types::*;
     pub use crate::types::status_code::StatusCode;
-    pub use crate::types::*;
 }
 
 pub mod constants {
@@ -94,6 +109,11 @@ pub mod constants {
     pub const DEFAULT_RUST_OPC_UA_SERVER_PORT: u16 = 4855;
     /// Default maximum number of subscriptions in a session
     pub const DEFAULT_MAX_SUBSCRIPTIONS: usize = 100;
+    /// Default longest lifetime in hours that a client may give a durable subscription
+    pub const DEFAULT_MAX_DURABLE_SUBSCRIPTION_LIFETIME_HOURS: u32 = 24;
+    /// Default maximum number of notification messages that a late subscription holds while it
+    /// waits for publish requests
+    pub const DEFAULT_MAX_QUEUED_NOTIFICATIONS: usize = 100;
     /// Default maximum number of monitored items per subscription
     pub const DEFAULT_MAX_MONITORED_ITEMS_PER_SUB: usize = 1000;
     /// Default, well known address for TCP discovery server
@@ -116,6 +136,16 @@ pub mod constants {
     /// Interval to check for HELLO timeout in millis. This can be fairly coarse because it's not
     /// something that requires huge accuracy.
     pub const HELLO_TIMEOUT_POLL_MS: u64 = 500;
+    /// Interval in millis at which sessions that have timed out are closed and their resources
+    /// freed. Like the hello timeout this does not need to be very accurate.
+    pub const SESSION_REAPER_INTERVAL_MS: u64 = 1000;
+    /// Interval in millis at which shelved alarms are checked for their shelving time having
+    /// elapsed, which is also how often their remaining shelving time is updated.
+    pub const ALARM_UNSHELVE_INTERVAL_MS: u64 = 1000;
+    /// Interval in millis at which the values collected from historizing variables are passed on
+    /// to the historical data provider, which is also the resolution of their maximum time
+    /// interval.
+    pub const HISTORY_COLLECTION_INTERVAL_MS: u64 = 100;
     /// Maximum time in MS that a session can be inactive before a timeout
     pub const MAX_SESSION_TIMEOUT: f64 = 60000f64;
     /// Maximum size in bytes that a request message is allowed to be
@@ -130,6 +160,9 @@ pub mod constants {
     pub const MAX_HISTORY_CONTINUATION_POINTS: usize = 10;
     /// Maximum query continuation points
     pub const MAX_QUERY_CONTINUATION_POINTS: usize = 10;
+    /// Time in MS that a continuation point lives for before it expires, if the client neither
+    /// resumes nor releases it
+    pub const CONTINUATION_POINT_TIMEOUT_MS: u64 = 60000;
 
     /// Maximum number of nodes in a TranslateBrowsePathsToNodeIdsRequest
     pub const MAX_NODES_PER_TRANSLATE_BROWSE_PATHS_TO_NODE_IDS: usize = 10;
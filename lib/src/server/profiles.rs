@@ -0,0 +1,156 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Works out which of the profiles and facets in OPC UA Part 7 the server implements. They are
+//! reported to clients in the `ServerProfileArray` of the server's capabilities, so a profile is
+//! only claimed when the configuration and the enabled features actually support it.
+
+use std::{collections::BTreeSet, str::FromStr};
+
+use crate::crypto::SecurityPolicy;
+
+use crate::server::config::ServerConfig;
+#[cfg(feature = "history")]
+use crate::server::historical::HistoryServerCapabilities;
+
+/// Nano Embedded Device Server Profile, the minimum that a server implements
+pub const NANO_EMBEDDED_DEVICE: &str =
+    "http://opcfoundation.org/UA-Profile/Server/NanoEmbeddedDevice";
+/// Micro Embedded Device Server Profile, which adds data change subscriptions
+pub const MICRO_EMBEDDED_DEVICE: &str =
+    "http://opcfoundation.org/UA-Profile/Server/MicroEmbeddedDevice";
+/// Embedded UA Server Profile, which adds security and the standard data change facet
+pub const EMBEDDED_UA: &str = "http://opcfoundation.org/UA-Profile/Server/EmbeddedUA";
+/// Base Server Behaviour Facet
+pub const BEHAVIOUR: &str = "http://opcfoundation.org/UA-Profile/Server/Behaviour";
+/// UA TCP UA SC UA Binary transport
+pub const UATCP_UASC_UABINARY: &str =
+    "http://opcfoundation.org/UA-Profile/Transport/uatcp-uasc-uabinary";
+/// Standard DataChange Subscription Server Facet
+pub const STANDARD_DATA_CHANGE_SUBSCRIPTION: &str =
+    "http://opcfoundation.org/UA-Profile/Server/StandardDataChangeSubscription";
+/// Standard Event Subscription Server Facet
+pub const STANDARD_EVENT_SUBSCRIPTION: &str =
+    "http://opcfoundation.org/UA-Profile/Server/StandardEventSubscription";
+/// Method Server Facet
+pub const METHODS: &str = "http://opcfoundation.org/UA-Profile/Server/Methods";
+/// Node Management Server Facet
+pub const NODE_MANAGEMENT: &str = "http://opcfoundation.org/UA-Profile/Server/NodeManagement";
+/// User Token - User Name Password Server Facet
+pub const USER_TOKEN_USER_NAME_PASSWORD: &str =
+    "http://opcfoundation.org/UA-Profile/Security/UserToken/Server/UserNamePassword";
+/// User Token - X509 Certificate Server Facet
+pub const USER_TOKEN_X509_CERTIFICATE: &str =
+    "http://opcfoundation.org/UA-Profile/Security/UserToken/Server/X509Certificate";
+/// Historical Raw Data Server Facet
+pub const HISTORICAL_RAW_DATA: &str =
+    "http://opcfoundation.org/UA-Profile/Server/HistoricalRawData";
+/// Historical Events Server Facet
+pub const HISTORICAL_EVENTS: &str = "http://opcfoundation.org/UA-Profile/Server/HistoricalEvents";
+
+/// Tests if a limit is at least the minimum that a facet requires, a limit of 0 meaning no limit
+fn at_least(limit: usize, minimum: usize) -> bool {
+    limit == 0 || limit >= minimum
+}
+
+/// The profiles and facets that the server claims to implement. They come from the server
+/// configuration, from the history capabilities that the server has set, and from any that the
+/// server implementation adds for facets of its own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerProfiles {
+    configured: BTreeSet<String>,
+    history: BTreeSet<String>,
+    added: BTreeSet<String>,
+}
+
+impl ServerProfiles {
+    /// Replaces the profiles implied by the server configuration, i.e. its endpoints, user tokens
+    /// and limits.
+    pub fn set_config(&mut self, config: &ServerConfig) {
+        let mut profiles = vec![BEHAVIOUR, UATCP_UASC_UABINARY, METHODS];
+
+        // Standard data change subscriptions need 2 subscriptions of 100 monitored items with
+        // queues of at least 2, while the micro embedded profile needs one subscription of 2 items
+        let limits = &config.limits;
+        let subscriptions = at_least(limits.max_monitored_items_per_sub, 2);
+        let standard_subscriptions = at_least(limits.max_subscriptions, 2)
+            && at_least(limits.max_monitored_items_per_sub, 100)
+            && at_least(limits.max_monitored_item_queue_size, 2);
+        if standard_subscriptions {
+            profiles.push(STANDARD_DATA_CHANGE_SUBSCRIPTION);
+            profiles.push(STANDARD_EVENT_SUBSCRIPTION);
+        }
+        if limits.clients_can_modify_address_space {
+            profiles.push(NODE_MANAGEMENT);
+        }
+
+        // Security policies and user tokens offered by the endpoints
+        let mut secure = false;
+        let mut security_policies = BTreeSet::new();
+        config.endpoints.values().for_each(|endpoint| {
+            let security_policy = SecurityPolicy::from_str(&endpoint.security_policy)
+                .unwrap_or(SecurityPolicy::Unknown);
+            if security_policy != SecurityPolicy::Unknown {
+                if security_policy != SecurityPolicy::None {
+                    secure = true;
+                }
+                security_policies.insert(security_policy.to_uri());
+            }
+            if endpoint.supports_user_pass(&config.user_tokens) {
+                profiles.push(USER_TOKEN_USER_NAME_PASSWORD);
+            }
+            if endpoint.supports_x509(&config.user_tokens) {
+                profiles.push(USER_TOKEN_X509_CERTIFICATE);
+            }
+        });
+        profiles.extend(security_policies);
+
+        profiles.push(if secure && standard_subscriptions {
+            EMBEDDED_UA
+        } else if subscriptions {
+            MICRO_EMBEDDED_DEVICE
+        } else {
+            NANO_EMBEDDED_DEVICE
+        });
+
+        self.configured = profiles.into_iter().map(String::from).collect();
+    }
+
+    /// Replaces the profiles implied by the history capabilities of the server
+    #[cfg(feature = "history")]
+    pub fn set_history(&mut self, capabilities: &HistoryServerCapabilities) {
+        self.history.clear();
+        if capabilities.access_history_data {
+            self.history.insert(HISTORICAL_RAW_DATA.to_string());
+        }
+        if capabilities.access_history_events {
+            self.history.insert(HISTORICAL_EVENTS.to_string());
+        }
+    }
+
+    /// Adds a profile or facet that the server implementation supports by itself, e.g. a companion
+    /// specification profile. Returns `false` if the profile was already claimed.
+    pub fn add(&mut self, profile_uri: &str) -> bool {
+        !self.contains(profile_uri) && self.added.insert(profile_uri.to_string())
+    }
+
+    /// Tests if the server claims the profile or facet
+    pub fn contains(&self, profile_uri: &str) -> bool {
+        self.configured.contains(profile_uri)
+            || self.history.contains(profile_uri)
+            || self.added.contains(profile_uri)
+    }
+
+    /// Returns the uris of all the profiles and facets that the server claims
+    pub fn uris(&self) -> Vec<String> {
+        self.configured
+            .iter()
+            .chain(self.history.iter())
+            .chain(self.added.iter())
+            .cloned()
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,214 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Provides the scheduler that runs the background tasks of a server, i.e. its polling actions
+//! and delayed actions, on the server's runtime.
+//!
+//! Tasks that are added before the server runs are started when it starts running. Every task
+//! stops when the server aborts, so none outlives the server, and each can be cancelled sooner
+//! through its [`TaskHandle`]. A task whose action panics does not take down the runtime thread,
+//! the panic is logged and a polling action carries on at its next interval.
+//!
+//! [`TaskHandle`]: ./struct.TaskHandle.html
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use tokio::{
+    runtime::Handle,
+    time::{interval_at, sleep, Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::sync::*;
+use crate::types::service_types::ServerState as ServerStateType;
+
+use crate::server::state::ServerState;
+
+/// A handle to a task of the scheduler, which can be used to cancel it. Dropping the handle does
+/// not cancel the task.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    token: CancellationToken,
+}
+
+impl TaskHandle {
+    /// Cancels the task. A polling action is not called again, and a delayed action that has not
+    /// yet been called will not be.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Tests if the task has finished, because it was cancelled, the server aborted or a delayed
+    /// action was called
+    pub fn is_finished(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+enum Task {
+    Polling {
+        interval_ms: u64,
+        action: Box<dyn Fn() + Send + Sync + 'static>,
+    },
+    Delayed {
+        delay_ms: u64,
+        /// In a mutex so the server is `Sync` while the action waits to start
+        action: Mutex<Box<dyn FnOnce() + Send + 'static>>,
+    },
+}
+
+/// Calls an action, logging instead of unwinding if it panics
+fn call_supervised<F>(action: F)
+where
+    F: FnOnce(),
+{
+    if panic::catch_unwind(AssertUnwindSafe(action)).is_err() {
+        error!("A scheduled action of the server panicked");
+    }
+}
+
+pub(crate) struct Scheduler {
+    server_state: Arc<RwLock<ServerState>>,
+    /// Cancelled when the server aborts, which stops every task
+    shutdown: CancellationToken,
+    /// Tasks that start when the server starts running
+    pending: Vec<(CancellationToken, Task)>,
+    /// The runtime of the server, once it is running
+    runtime: Option<Handle>,
+}
+
+impl Scheduler {
+    pub fn new(server_state: Arc<RwLock<ServerState>>) -> Scheduler {
+        Scheduler {
+            server_state,
+            shutdown: CancellationToken::new(),
+            pending: Vec::new(),
+            runtime: None,
+        }
+    }
+
+    /// Adds an action that is called repeatedly on an interval while the server is running
+    pub fn add_polling_action<F>(&mut self, interval_ms: u64, action: F) -> TaskHandle
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.add_task(Task::Polling {
+            interval_ms,
+            action: Box::new(action),
+        })
+    }
+
+    /// Adds an action that is called once after a delay, if the server is still running then
+    pub fn add_delayed_action<F>(&mut self, delay_ms: u64, action: F) -> TaskHandle
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.add_task(Task::Delayed {
+            delay_ms,
+            action: Mutex::new(Box::new(action)),
+        })
+    }
+
+    fn add_task(&mut self, task: Task) -> TaskHandle {
+        let token = self.shutdown.child_token();
+        if self.shutdown.is_cancelled() {
+            error!("Task added to the scheduler when server is aborting");
+        } else if let Some(ref runtime) = self.runtime {
+            Self::spawn(runtime, &self.server_state, token.clone(), task);
+        } else {
+            self.pending.push((token.clone(), task));
+        }
+        TaskHandle { token }
+    }
+
+    /// Starts the tasks that were added before the server was running. It must be called from
+    /// the runtime of the server, on which all tasks run from then on.
+    pub fn start(&mut self) {
+        let runtime = Handle::current();
+        let server_state = &self.server_state;
+        self.pending.drain(..).for_each(|(token, task)| {
+            Self::spawn(&runtime, server_state, token, task);
+        });
+        self.runtime = Some(runtime);
+    }
+
+    /// Returns a token that is cancelled when the scheduler stops its tasks, for tasks of the
+    /// server that it does not run itself
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.child_token()
+    }
+
+    /// Stops every task, e.g. because the server is aborting
+    pub fn shutdown(&self) {
+        if !self.shutdown.is_cancelled() {
+            debug!("Scheduler is stopping its tasks");
+            self.shutdown.cancel();
+        }
+    }
+
+    fn spawn(
+        runtime: &Handle,
+        server_state: &Arc<RwLock<ServerState>>,
+        token: CancellationToken,
+        task: Task,
+    ) {
+        let server_state = server_state.clone();
+        match task {
+            Task::Polling {
+                interval_ms,
+                action,
+            } => {
+                debug!("Starting a polling action at rate of {} ms", interval_ms);
+                runtime.spawn(async move {
+                    let mut timer = interval_at(Instant::now(), Duration::from_millis(interval_ms));
+                    loop {
+                        tokio::select! {
+                            _ = timer.tick() => {}
+                            _ = token.cancelled() => break,
+                        }
+                        match Self::server_status(&server_state) {
+                            Some(true) => {
+                                call_supervised(&action);
+                            }
+                            Some(false) => {}
+                            None => break,
+                        }
+                    }
+                    token.cancel();
+                    debug!("Polling action is finished");
+                });
+            }
+            Task::Delayed { delay_ms, action } => {
+                let action = action.into_inner();
+                runtime.spawn(async move {
+                    tokio::select! {
+                        _ = sleep(Duration::from_millis(delay_ms)) => {
+                            if let Some(true) = Self::server_status(&server_state) {
+                                call_supervised(action);
+                            }
+                        }
+                        _ = token.cancelled() => {}
+                    }
+                    token.cancel();
+                });
+            }
+        }
+    }
+
+    /// Returns whether the server is running, or `None` if it has aborted or failed and tasks
+    /// should stop
+    fn server_status(server_state: &Arc<RwLock<ServerState>>) -> Option<bool> {
+        let server_state = trace_read_lock!(server_state);
+        match server_state.state() {
+            ServerStateType::Failed
+            | ServerStateType::NoConfiguration
+            | ServerStateType::Shutdown => None,
+            _ if server_state.is_abort() => None,
+            _ => Some(server_state.is_running()),
+        }
+    }
+}
@@ -4,32 +4,46 @@
 
 //! Provides the [`Server`] type and functionality related to it.
 
-use std::{marker::Sync, net::SocketAddr, panic::AssertUnwindSafe, sync::Arc};
+use std::{marker::Sync, net::SocketAddr, sync::Arc};
 
 use tokio::{
     self,
+    io::{AsyncWriteExt, DuplexStream},
     net::{TcpListener, TcpStream, ToSocketAddrs},
     sync::oneshot::{self, Sender},
-    time::{interval_at, Duration, Instant},
+    time::{interval_at, timeout, Duration, Instant},
 };
 
-use crate::core::{config::Config, prelude::*};
+use crate::core::{
+    comms::{
+        local_socket::{LocalListener, LocalStream},
+        loopback::LoopbackListener,
+    },
+    config::Config,
+    prelude::*,
+};
 use crate::crypto::*;
 use crate::sync::*;
 use crate::types::service_types::ServerState as ServerStateType;
 
+#[cfg(feature = "diagnostics")]
+use crate::server::diagnostics::ServerDiagnostics;
+#[cfg(feature = "events")]
+use crate::server::events::audit::AuditLog;
+#[cfg(feature = "history")]
+use crate::server::historical::data_collection;
 use crate::server::{
     address_space::types::AddressSpace,
+    comms::connection_limiter::ConnectionLimiter,
     comms::tcp_transport::*,
     comms::transport::Transport,
     config::ServerConfig,
     constants,
-    diagnostics::ServerDiagnostics,
-    events::audit::AuditLog,
+    handle::{ServerHandle, UpdateQueue},
     metrics::ServerMetrics,
+    scheduler::{Scheduler, TaskHandle},
     session::SessionManager,
     state::{OperationalLimits, ServerState},
-    util::PollingAction,
 };
 
 pub type Connections = Vec<Arc<RwLock<TcpTransport>>>;
@@ -61,8 +75,12 @@ pub type Connections = Vec<Arc<RwLock<TcpTransport>>>;
 /// [`CertificateStore`]: ../../opcua_core/crypto/certificate_store/struct.CertificateStore.html
 ///
 pub struct Server {
-    /// List of pending polling actions to add to the server once run is called
-    pending_polling_actions: Vec<(u64, Box<dyn Fn() + Send + Sync + 'static>)>,
+    /// Scheduler of the polling and delayed actions, which start once run is called
+    scheduler: Scheduler,
+    /// Handle through which updates are queued from outside the server
+    handle: ServerHandle,
+    /// Queue of the updates made through server handles, until the server runs
+    update_queue: Option<UpdateQueue>,
     /// Certificate store for certs
     certificate_store: Arc<RwLock<CertificateStore>>,
     /// Server metrics - diagnostics and anything else that someone might be interested in that
@@ -105,10 +123,13 @@ impl Server {
             "opc.tcp://{}:{}",
             config.tcp_config.host, config.tcp_config.port
         );
+        let max_sessions = config.limits.max_sessions;
         let max_subscriptions = config.limits.max_subscriptions as usize;
         let max_monitored_items_per_sub = config.limits.max_monitored_items_per_sub as usize;
         let max_monitored_item_queue_size = config.limits.max_monitored_item_queue_size as usize;
+        let max_queued_notifications = config.limits.max_queued_notifications;
 
+        #[cfg(feature = "diagnostics")]
         let diagnostics = Arc::new(RwLock::new(ServerDiagnostics::default()));
         let min_publishing_interval_ms = config.limits.min_publishing_interval * 1000.0;
         let min_sampling_interval_ms = config.limits.min_sampling_interval * 1000.0;
@@ -122,13 +143,20 @@ impl Server {
             None
         };
         let (mut certificate_store, server_certificate, server_pkey) =
-            CertificateStore::new_with_x509_data(
-                &config.pki_dir,
-                false,
-                config.certificate_path.as_deref(),
-                config.private_key_path.as_deref(),
-                application_description,
-            );
+            if let Some(ref system_certificate_store) = config.system_certificate_store {
+                CertificateStore::new_with_system_store(
+                    &config.pki_dir,
+                    system_certificate_store.clone(),
+                )
+            } else {
+                CertificateStore::new_with_x509_data(
+                    &config.pki_dir,
+                    false,
+                    config.certificate_path.as_deref(),
+                    config.private_key_path.as_deref(),
+                    application_description,
+                )
+            };
         if server_certificate.is_none() || server_pkey.is_none() {
             error!("Server is missing its application instance certificate and/or its private key. Encrypted endpoints will not function correctly.")
         }
@@ -143,12 +171,20 @@ impl Server {
             certificate_store.set_trust_unknown_certs(true);
         }
         certificate_store.set_check_time(config.certificate_validation.check_time);
+        certificate_store.set_match_subject_key_identifier(
+            config.certificate_validation.match_subject_key_identifier,
+        );
+        let server_issuer_certificates = server_certificate
+            .as_ref()
+            .map(|server_certificate| certificate_store.issuer_certs(server_certificate))
+            .unwrap_or_default();
 
         let config = Arc::new(RwLock::new(config));
 
         // Set some values in the address space from the server state
         let address_space = Arc::new(RwLock::new(AddressSpace::new()));
 
+        #[cfg(feature = "events")]
         let audit_log = Arc::new(RwLock::new(AuditLog::new(address_space.clone())));
 
         let server_state = ServerState {
@@ -164,22 +200,30 @@ impl Server {
             start_time,
             config,
             server_certificate,
+            server_issuer_certificates,
             server_pkey,
             last_subscription_id: 0,
+            max_sessions,
             max_subscriptions,
             max_monitored_items_per_sub,
             max_monitored_item_queue_size,
+            max_queued_notifications,
             min_publishing_interval_ms,
             min_sampling_interval_ms,
             default_keep_alive_count: constants::DEFAULT_KEEP_ALIVE_COUNT,
             max_keep_alive_count: constants::MAX_KEEP_ALIVE_COUNT,
             max_lifetime_count: constants::MAX_KEEP_ALIVE_COUNT * 3,
+            #[cfg(feature = "diagnostics")]
             diagnostics,
             abort: false,
+            #[cfg(feature = "events")]
             audit_log,
             register_nodes_callback: None,
             unregister_nodes_callback: None,
+            user_access_levels: None,
+            #[cfg(feature = "history")]
             historical_data_provider: None,
+            #[cfg(feature = "history")]
             historical_event_provider: None,
             operational_limits: OperationalLimits::default(),
             send_buffer_size,
@@ -198,15 +242,24 @@ impl Server {
         // Cert store
         let certificate_store = Arc::new(RwLock::new(certificate_store));
 
-        let server = Server {
-            pending_polling_actions: Vec::new(),
+        let (handle, update_queue) = UpdateQueue::new();
+
+        let mut server = Server {
+            scheduler: Scheduler::new(server_state.clone()),
+            handle,
+            update_queue: Some(update_queue),
             server_state,
             server_metrics: server_metrics.clone(),
             address_space,
             certificate_store,
             connections: Arc::new(RwLock::new(Vec::new())),
-            session_manager: Arc::new(RwLock::new(SessionManager::default())),
+            session_manager: Arc::new(RwLock::new(SessionManager::new(max_sessions))),
         };
+        server.start_session_reaper();
+        #[cfg(feature = "events")]
+        server.start_alarm_unshelver();
+        #[cfg(feature = "history")]
+        server.start_history_collector();
 
         let mut server_metrics = trace_write_lock!(server_metrics);
         server_metrics.set_server_info(&server);
@@ -315,40 +368,28 @@ impl Server {
             }
         };
 
-        let (tx_abort, rx_abort) = oneshot::channel();
-
-        // Put the server into a running state
-        {
-            let mut server = trace_write_lock!(server);
-            // Running
-            {
-                let mut server_state = trace_write_lock!(server.server_state);
-                server_state.start_time = DateTime::now();
-                server_state.set_state(ServerStateType::Running);
-            }
-
-            // Start a timer that registers the server with a discovery server
-            if let Some(ref discovery_server_url) = discovery_server_url {
-                server.start_discovery_server_registration_timer(discovery_server_url);
-            } else {
-                info!("Server has not set a discovery server url, so no registration will happen");
-            }
-
-            // Start any pending polling action timers
-            server.start_pending_polling_actions();
-        }
+        let local_listener = Self::bind_local_socket(&server);
+        let mut connection_limiter = {
+            let server = trace_read_lock!(server);
+            let server_state = trace_read_lock!(server.server_state);
+            let config = trace_read_lock!(server_state.config);
+            ConnectionLimiter::new(
+                config.tcp_config.max_connection_rate,
+                config.tcp_config.max_unauthenticated_connections,
+            )
+        };
 
-        // Start a server abort task loop
-        Self::start_abort_poll(server.clone(), tx_abort);
+        let (tx_abort, rx_abort) = oneshot::channel();
+        Self::start_running(server.clone(), discovery_server_url, tx_abort);
 
-        // This isn't nice syntax, but basically there are two async actions
-        // going on, one of which has to complete - either the listener breaks out of its
+        // This isn't nice syntax, but basically there are three async actions
+        // going on, one of which has to complete - either a listener breaks out of its
         // loop, or the rx_abort receives an abort message.
         tokio::select! {
             _ = async {
                 loop {
                     match listener.accept().await {
-                        Ok((socket, _addr)) => {
+                        Ok((socket, addr)) => {
                             // Clear out dead sessions
                             info!("Handling new connection {:?}", socket);
                             // Check for abort
@@ -360,8 +401,13 @@ impl Server {
                             if is_abort {
                                 info!("Server is aborting so it will not accept new connections");
                                 break;
-                            } else {
-                                server.handle_connection(socket);
+                            }
+                            let unauthenticated_connections = server.unauthenticated_connections();
+                            match connection_limiter.admit(addr.ip(), unauthenticated_connections, Instant::now()) {
+                                Ok(()) => server.handle_connection(socket),
+                                Err(status_code) => {
+                                    tokio::spawn(Self::reject_connection(socket, status_code));
+                                }
                             }
                         }
                         Err(e) => {
@@ -372,6 +418,7 @@ impl Server {
                 // Help the rust type inferencer out
                 Ok::<_, tokio::io::Error>(())
             } => {}
+            _ = Self::local_socket_task(server.clone(), local_listener) => {}
             _ = rx_abort => {
                 info!("abort received");
             }
@@ -379,6 +426,159 @@ impl Server {
         info!("main server task is finished");
     }
 
+    /// Binds a listener to the local socket of the configuration, if it has one. The server
+    /// still runs on its TCP port if the listener cannot be bound.
+    fn bind_local_socket(server: &Arc<RwLock<Server>>) -> Option<LocalListener> {
+        let local_socket = {
+            let server = trace_read_lock!(server);
+            let server_state = trace_read_lock!(server.server_state);
+            let config = trace_read_lock!(server_state.config);
+            config.tcp_config.local_socket.clone()
+        }?;
+        match LocalListener::bind(&local_socket) {
+            Ok(listener) => Some(listener),
+            Err(err) => {
+                error!("Could not bind to local socket {}, {:?}", local_socket, err);
+                None
+            }
+        }
+    }
+
+    /// Accepts connections on the local socket until the server aborts. Without a listener it
+    /// never completes.
+    async fn local_socket_task(server: Arc<RwLock<Server>>, listener: Option<LocalListener>) {
+        let mut listener = match listener {
+            Some(listener) => listener,
+            None => return futures::future::pending().await,
+        };
+        loop {
+            match listener.accept().await {
+                Ok(stream) => {
+                    info!(
+                        "Handling new connection on local socket {}",
+                        listener.path()
+                    );
+                    let mut server = trace_write_lock!(server);
+                    let is_abort = {
+                        let server_state = trace_read_lock!(server.server_state);
+                        server_state.is_abort()
+                    };
+                    if is_abort {
+                        info!("Server is aborting so it will not accept new connections");
+                        break;
+                    } else {
+                        server.handle_local_connection(stream);
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "couldn't accept connection on local socket {}: {:?}",
+                        listener.path(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns a server task that behaves like the one from `new_server_task()` except that it
+    /// accepts in-process loopback connections instead of listening on a TCP socket. A client in
+    /// the same process that is built with `ClientBuilder::loopback(true)` and connects to an
+    /// endpoint with the host and port of the server's configuration is connected over an
+    /// in-memory stream. This is intended for integration
+    /// tests and does not register with a discovery server.
+    pub async fn new_loopback_server_task(server: Arc<RwLock<Server>>) {
+        let (host, port) = {
+            let server = trace_read_lock!(server);
+            server.log_endpoint_info();
+            let server_state = trace_read_lock!(server.server_state);
+            let config = trace_read_lock!(server_state.config);
+            (config.tcp_config.host.clone(), config.tcp_config.port)
+        };
+
+        let mut listener = match LoopbackListener::bind(&host, port) {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!(
+                    "Could not bind loopback listener to {}:{}, {}",
+                    host, port, err
+                );
+                return;
+            }
+        };
+
+        let (tx_abort, rx_abort) = oneshot::channel();
+        Self::start_running(server.clone(), None, tx_abort);
+
+        tokio::select! {
+            _ = async {
+                while let Some(stream) = listener.accept().await {
+                    let mut server = trace_write_lock!(server);
+                    let is_abort = {
+                        let server_state = trace_read_lock!(server.server_state);
+                        server_state.is_abort()
+                    };
+                    if is_abort {
+                        info!("Server is aborting so it will not accept new connections");
+                        break;
+                    } else {
+                        server.handle_loopback_connection(stream);
+                    }
+                }
+            } => {}
+            _ = rx_abort => {
+                info!("abort received");
+            }
+        }
+        info!("loopback server task is finished");
+    }
+
+    /// Puts the server into a running state, starts its timers and the task that polls for an
+    /// abort.
+    fn start_running(
+        server: Arc<RwLock<Server>>,
+        discovery_server_url: Option<String>,
+        tx_abort: Sender<()>,
+    ) {
+        {
+            let mut server = trace_write_lock!(server);
+            // Running
+            {
+                let mut server_state = trace_write_lock!(server.server_state);
+                server_state.start_time = DateTime::now();
+                server_state.set_state(ServerStateType::Running);
+            }
+
+            // Tell clients about nodes and references added or removed from now on
+            #[cfg(feature = "events")]
+            {
+                let mut address_space = trace_write_lock!(server.address_space);
+                address_space.set_model_change_events(true);
+            }
+
+            // Start a timer that registers the server with a discovery server
+            if let Some(ref discovery_server_url) = discovery_server_url {
+                server.start_discovery_server_registration_timer(discovery_server_url);
+            } else {
+                info!("Server has not set a discovery server url, so no registration will happen");
+            }
+
+            // Start any pending polling and delayed actions
+            server.scheduler.start();
+
+            // Apply the updates made through server handles
+            if let Some(update_queue) = server.update_queue.take() {
+                tokio::spawn(update_queue.run(
+                    server.address_space.clone(),
+                    server.scheduler.shutdown_token(),
+                ));
+            }
+        }
+
+        // Start a server abort task loop
+        Self::start_abort_poll(server, tx_abort);
+    }
+
     /// Returns the current [`ServerState`] for the server.
     ///
     /// [`ServerState`]: ../state/struct.ServerState.html
@@ -398,6 +598,12 @@ impl Server {
         self.address_space.clone()
     }
 
+    /// Returns a handle that other threads and tasks can use to set the values of variables and
+    /// raise events in the address space, without locking it themselves.
+    pub fn handle(&self) -> ServerHandle {
+        self.handle.clone()
+    }
+
     /// Returns the [`Connections`] for the server.
     ///
     /// [`Connections`]: ./type.Connections.html
@@ -420,11 +626,26 @@ impl Server {
     }
 
     /// Sets a flag telling the running server to abort. The abort will happen asynchronously after
-    /// all sessions have disconnected.
+    /// all sessions have disconnected. The subscriptions of every session are closed with a
+    /// `BadShutdown` status change notification which is sent to clients that still have publish
+    /// requests queued.
     pub fn abort(&mut self) {
         info!("Server has been instructed to abort");
-        let mut server_state = trace_write_lock!(self.server_state);
-        server_state.abort();
+        {
+            let now = clock::now();
+            let session_manager = trace_read_lock!(self.session_manager);
+            session_manager.sessions.values().for_each(|session| {
+                let mut session = trace_write_lock!(session);
+                session
+                    .subscriptions_mut()
+                    .close_all(&now, StatusCode::BadShutdown);
+            });
+        }
+        {
+            let mut server_state = trace_write_lock!(self.server_state);
+            server_state.abort();
+        }
+        self.scheduler.shutdown();
     }
 
     /// Strip out dead connections, i.e those which have disconnected. Returns `true` if there are
@@ -447,12 +668,70 @@ impl Server {
         !connections.is_empty()
     }
 
+    /// Starts the polling action that closes sessions which have timed out and frees what they
+    /// held. Sessions also time out when their client makes a request after the timeout, but a
+    /// client that has gone away makes no more requests.
+    fn start_session_reaper(&mut self) {
+        let park_subscriptions = {
+            let server_state = trace_read_lock!(self.server_state);
+            let config = trace_read_lock!(server_state.config);
+            !config.limits.delete_orphaned_subscriptions
+        };
+        let session_manager = self.session_manager.clone();
+        let address_space = self.address_space.clone();
+        let reap_sessions = move || {
+            let mut session_manager = trace_write_lock!(session_manager);
+            let _ =
+                session_manager.reap_sessions(&clock::now(), &address_space, park_subscriptions);
+        };
+        let _ = self
+            .scheduler
+            .add_polling_action(constants::SESSION_REAPER_INTERVAL_MS, reap_sessions);
+    }
+
+    /// Starts the polling action that unshelves the shelved alarms of the address space when
+    /// their shelving time has elapsed, and until then counts down their `UnshelveTime`.
+    #[cfg(feature = "events")]
+    fn start_alarm_unshelver(&mut self) {
+        let address_space = self.address_space.clone();
+        let unshelve_alarms = move || {
+            let has_shelved_alarms = {
+                let address_space = trace_read_lock!(address_space);
+                address_space.has_shelved_alarms_with_unshelve_time()
+            };
+            if has_shelved_alarms {
+                let mut address_space = trace_write_lock!(address_space);
+                address_space.update_shelved_alarms();
+            }
+        };
+        let _ = self
+            .scheduler
+            .add_polling_action(constants::ALARM_UNSHELVE_INTERVAL_MS, unshelve_alarms);
+    }
+
+    /// Starts the polling action that passes the values collected from historizing variables on
+    /// to the historical data provider, once the server has one.
+    #[cfg(feature = "history")]
+    fn start_history_collector(&mut self) {
+        let server_state = self.server_state.clone();
+        let address_space = self.address_space.clone();
+        let collect_history = move || {
+            data_collection::collect_history(&server_state, &address_space);
+        };
+        let _ = self
+            .scheduler
+            .add_polling_action(constants::HISTORY_COLLECTION_INTERVAL_MS, collect_history);
+    }
+
     /// Log information about the endpoints on this server
     fn log_endpoint_info(&self) {
         let server_state = trace_read_lock!(self.server_state);
         let config = trace_read_lock!(server_state.config);
         info!("OPC UA Server: {}", server_state.application_name);
         info!("Base url: {}", server_state.base_endpoint);
+        if let Some(ref local_socket) = config.tcp_config.local_socket {
+            info!("Local socket url: {}", local_socket_url(local_socket));
+        }
         info!("Supported endpoints:");
         for (id, endpoint) in &config.endpoints {
             let users: Vec<String> = endpoint.user_token_ids.iter().cloned().collect();
@@ -497,6 +776,7 @@ impl Server {
                             warn!("Abort called while there were still open connections");
                         }
                         info!("Server has aborted so, sending a command to break the listen loop");
+                        server.scheduler.shutdown();
                         tx_abort.send(()).unwrap();
                         break;
                     }
@@ -562,7 +842,7 @@ impl Server {
                     let server_state = server_state.clone();
                     let discovery_server_url = discovery_server_url.clone();
                     let _ = std::thread::spawn(move || {
-                        let _ = std::panic::catch_unwind(AssertUnwindSafe(move || {
+                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
                             let server_state = trace_read_lock!(server_state);
                             if server_state.is_running() {
                                 discovery::register_with_discovery_server(
@@ -585,42 +865,28 @@ impl Server {
     /// The function that is supplied does not take any arguments. It is expected that the
     /// implementation will move any variables into the function that are required to perform its
     /// action.
-    pub fn add_polling_action<F>(&mut self, interval_ms: u64, action: F)
+    ///
+    /// The action runs on the server's runtime and stops when the server aborts, or when it is
+    /// cancelled through the returned handle. If it panics, the panic is logged and the action is
+    /// called again at the next interval.
+    pub fn add_polling_action<F>(&mut self, interval_ms: u64, action: F) -> TaskHandle
     where
         F: Fn() + Send + Sync + 'static,
     {
         // If the server is not yet running, the action is queued and is started later
-        let server_state = trace_read_lock!(self.server_state);
-        if server_state.is_abort() {
-            error!("Polling action added when server is aborting");
-        // DO NOTHING
-        } else if !server_state.is_running() {
-            self.pending_polling_actions
-                .push((interval_ms, Box::new(action)));
-        } else {
-            // Start the action immediately
-            let _ = PollingAction::spawn(self.server_state.clone(), interval_ms, move || {
-                // Call the provided closure with the address space
-                action();
-            });
-        }
+        self.scheduler.add_polling_action(interval_ms, action)
     }
 
-    /// Starts any polling actions which were queued ready to start but not yet
-    fn start_pending_polling_actions(&mut self) {
-        let server_state = self.server_state.clone();
-        self.pending_polling_actions
-            .drain(..)
-            .for_each(|(interval_ms, action)| {
-                debug!(
-                    "Starting a pending polling action at rate of {} ms",
-                    interval_ms
-                );
-                let _ = PollingAction::spawn(server_state.clone(), interval_ms, move || {
-                    // Call the provided action
-                    action();
-                });
-            });
+    /// Creates an action that is called once after a delay in milliseconds, if the server is
+    /// still running by then. Like a polling action it runs on the server's runtime, and it is
+    /// not called if the server aborts or it is cancelled through the returned handle before the
+    /// delay is up. The delay of an action that is added before the server runs is counted from
+    /// when the server starts running.
+    pub fn add_delayed_action<F>(&mut self, delay_ms: u64, action: F) -> TaskHandle
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.scheduler.add_delayed_action(delay_ms, action)
     }
 
     /// Create a new transport.
@@ -636,7 +902,69 @@ impl Server {
     /// Handles the incoming request
     fn handle_connection(&mut self, socket: TcpStream) {
         trace!("Connection thread spawning");
+        {
+            let server_state = trace_read_lock!(self.server_state);
+            let config = trace_read_lock!(server_state.config);
+            if let Err(err) = config.tcp_config.socket_options.apply(&socket) {
+                warn!(
+                    "Could not set the options of socket {:?}, {:?}",
+                    socket, err
+                );
+            }
+        }
+        let (connection, looping_interval_ms) = self.add_connection();
+        // Run adds a session task to the tokio session
+        TcpTransport::run(connection, socket, looping_interval_ms);
+    }
 
+    /// Counts the TCP connections that are open without an activated session
+    fn unauthenticated_connections(&self) -> usize {
+        let connections = trace_read_lock!(self.connections);
+        connections
+            .iter()
+            .filter(|connection| {
+                let connection = trace_read_lock!(connection);
+                !connection.is_finished()
+                    && connection.client_address().is_some()
+                    && !connection.has_activated_session()
+            })
+            .count()
+    }
+
+    /// Sends a connection that is not admitted an error message and closes it, without waiting
+    /// for its hello or giving it a transport
+    async fn reject_connection(mut socket: TcpStream, status_code: StatusCode) {
+        let error = ErrorMessage::from_status_code(status_code).encode_to_vec();
+        let result = timeout(Duration::from_secs(1), async {
+            socket.write_all(&error).await?;
+            socket.shutdown().await
+        })
+        .await;
+        if !matches!(result, Ok(Ok(()))) {
+            debug!(
+                "Could not send an error to rejected connection {:?}",
+                socket
+            );
+        }
+    }
+
+    /// Handles an incoming connection on the local socket
+    fn handle_local_connection(&mut self, stream: Box<dyn LocalStream>) {
+        trace!("Local socket connection spawning");
+        let (connection, looping_interval_ms) = self.add_connection();
+        TcpTransport::run_stream(connection, stream, None, looping_interval_ms);
+    }
+
+    /// Handles an incoming loopback connection
+    fn handle_loopback_connection(&mut self, stream: DuplexStream) {
+        trace!("Loopback connection spawning");
+        let (connection, looping_interval_ms) = self.add_connection();
+        TcpTransport::run_stream(connection, stream, None, looping_interval_ms);
+    }
+
+    /// Creates a transport for a new connection and adds it to the connections list. Returns the
+    /// transport and the interval that its session loop should run at.
+    fn add_connection(&mut self) -> (Arc<RwLock<TcpTransport>>, f64) {
         // Spawn a task for the connection
         let connection = Arc::new(RwLock::new(self.new_transport()));
         {
@@ -654,7 +982,6 @@ impl Server {
             )
         };
 
-        // Run adds a session task to the tokio session
-        TcpTransport::run(connection, socket, looping_interval_ms);
+        (connection, looping_interval_ms)
     }
 }
@@ -12,15 +12,20 @@ use crate::{
 
 use crate::server::{
     address_space::{
-        node::{HasNodeId, NodeBase, NodeType},
+        data_access,
+        node::{NodeBase, NodeType},
         variable::Variable,
-        AddressSpace, UserAccessLevel,
+        AccessLevel, AddressSpace, UserAccessLevel,
     },
     services::Service,
     session::Session,
     state::ServerState,
 };
 
+#[cfg(feature = "history")]
+use crate::server::continuation_point::HistoryContinuationPoint;
+
+#[cfg(feature = "history")]
 enum ReadDetails {
     ReadEventDetails(ReadEventDetails),
     ReadRawModifiedDetails(ReadRawModifiedDetails),
@@ -28,6 +33,7 @@ enum ReadDetails {
     ReadAtTimeDetails(ReadAtTimeDetails),
 }
 
+#[cfg(feature = "history")]
 enum UpdateDetails {
     UpdateDataDetails(UpdateDataDetails),
     UpdateStructureDataDetails(UpdateStructureDataDetails),
@@ -88,6 +94,7 @@ impl AttributeService {
                     .iter()
                     .map(|node_to_read| {
                         Self::read_node_value(
+                            &server_state,
                             &session,
                             &address_space,
                             node_to_read,
@@ -112,10 +119,11 @@ impl AttributeService {
     }
 
     /// Used to read historical values
+    #[cfg(feature = "history")]
     pub fn history_read(
         &self,
         server_state: Arc<RwLock<ServerState>>,
-        _session: Arc<RwLock<Session>>,
+        session: Arc<RwLock<Session>>,
         address_space: Arc<RwLock<AddressSpace>>,
         request: &HistoryReadRequest,
     ) -> SupportedMessage {
@@ -129,6 +137,7 @@ impl AttributeService {
             match Self::do_history_read_details(
                 &decoding_options,
                 server_state,
+                session,
                 address_space,
                 request,
             ) {
@@ -171,7 +180,12 @@ impl AttributeService {
                 let results = nodes_to_write
                     .iter()
                     .map(|node_to_write| {
-                        Self::write_node_value(&session, &mut address_space, node_to_write)
+                        Self::write_node_value(
+                            &server_state,
+                            &session,
+                            &mut address_space,
+                            node_to_write,
+                        )
                     })
                     .collect();
 
@@ -193,6 +207,7 @@ impl AttributeService {
     }
 
     /// Used to update or update historical values
+    #[cfg(feature = "history")]
     pub fn history_update(
         &self,
         server_state: Arc<RwLock<ServerState>>,
@@ -235,11 +250,13 @@ impl AttributeService {
         }
     }
 
+    #[cfg(feature = "history")]
     fn node_id_to_action(node_id: &NodeId, actions: &[ObjectId]) -> Result<ObjectId, ()> {
         let object_id = node_id.as_object_id().map_err(|_| ())?;
         actions.iter().find(|v| object_id == **v).copied().ok_or(())
     }
 
+    #[cfg(feature = "history")]
     fn node_id_to_historical_read_action(node_id: &NodeId) -> Result<ObjectId, ()> {
         Self::node_id_to_action(
             node_id,
@@ -252,6 +269,7 @@ impl AttributeService {
         )
     }
 
+    #[cfg(feature = "history")]
     fn node_id_to_historical_update_action(node_id: &NodeId) -> Result<ObjectId, ()> {
         Self::node_id_to_action(
             node_id,
@@ -266,6 +284,7 @@ impl AttributeService {
         )
     }
 
+    #[cfg(feature = "history")]
     fn decode_history_read_details(
         history_read_details: &ExtensionObject,
         decoding_options: &DecodingOptions,
@@ -296,6 +315,7 @@ impl AttributeService {
         }
     }
 
+    #[cfg(feature = "history")]
     fn decode_history_update_details(
         history_update_details: &ExtensionObject,
         decoding_options: &DecodingOptions,
@@ -339,6 +359,7 @@ impl AttributeService {
         }
     }
 
+    #[cfg(feature = "history")]
     fn do_history_update_details(
         decoding_options: &DecodingOptions,
         server_state: Arc<RwLock<ServerState>>,
@@ -416,22 +437,62 @@ impl AttributeService {
         }
     }
 
+    #[cfg(feature = "history")]
     fn do_history_read_details(
         decoding_options: &DecodingOptions,
         server_state: Arc<RwLock<ServerState>>,
+        session: Arc<RwLock<Session>>,
         address_space: Arc<RwLock<AddressSpace>>,
         request: &HistoryReadRequest,
     ) -> Result<Vec<HistoryReadResult>, StatusCode> {
         // TODO enforce operation limits
 
         // Validate the action being performed
-        let nodes_to_read = &request.nodes_to_read.as_ref().unwrap();
+        let nodes_to_read = request.nodes_to_read.as_ref().unwrap();
         let timestamps_to_return = request.timestamps_to_return;
         let release_continuation_points = request.release_continuation_points;
         let read_details =
             Self::decode_history_read_details(&request.history_read_details, decoding_options)?;
 
         let server_state = trace_read_lock!(server_state);
+
+        // The history of a variable can only be read if its access levels allow it. Variables
+        // that fail the check are left out of the read and get the status code as their result.
+        // So are nodes whose continuation point is not one that the session holds for the node.
+        // The others are read with the continuation point of the provider in place of the one
+        // the client was given.
+        let is_event_read = matches!(read_details, ReadDetails::ReadEventDetails(_));
+        let access_results = {
+            let mut session = trace_write_lock!(session);
+            let address_space = trace_read_lock!(address_space);
+            nodes_to_read
+                .iter()
+                .map(|node_to_read| {
+                    if !is_event_read {
+                        if let Some(node) = address_space.find_node(&node_to_read.node_id) {
+                            Self::check_access_level(
+                                &server_state,
+                                &session,
+                                node,
+                                AttributeId::Value,
+                                AccessLevel::HISTORY_READ,
+                                StatusCode::BadNotReadable,
+                            )?;
+                        }
+                    }
+                    Self::find_history_continuation_point(&mut session, node_to_read)
+                })
+                .collect::<Vec<_>>()
+        };
+        if access_results.iter().all(|r| r.is_err()) {
+            return Ok(Self::history_read_results(access_results, Vec::new()));
+        }
+        let permitted_nodes = access_results
+            .iter()
+            .filter_map(|r| r.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+        let nodes_to_read = permitted_nodes.as_slice();
+
         let results = match read_details {
             ReadDetails::ReadEventDetails(details) => {
                 let historical_event_provider = server_state
@@ -486,7 +547,92 @@ impl AttributeService {
                 )?
             }
         };
-        Ok(results)
+
+        // The continuation points of the provider are kept by the session and the client is
+        // given their ids instead
+        let results = if release_continuation_points {
+            results
+        } else {
+            let mut session = trace_write_lock!(session);
+            results
+                .into_iter()
+                .zip(nodes_to_read.iter())
+                .map(|(result, node_to_read)| {
+                    Self::add_history_continuation_point(&mut session, result, node_to_read)
+                })
+                .collect()
+        };
+        Ok(Self::history_read_results(access_results, results))
+    }
+
+    /// Returns the node to read with the continuation point of the provider in place of the id
+    /// of it that the client holds, or `BadContinuationPointInvalid` if the session holds no such
+    /// continuation point for the node. The continuation point is removed from the session,
+    /// whether the read resumes or releases it.
+    #[cfg(feature = "history")]
+    fn find_history_continuation_point(
+        session: &mut Session,
+        node_to_read: &HistoryReadValueId,
+    ) -> Result<HistoryReadValueId, StatusCode> {
+        let mut node_to_read = node_to_read.clone();
+        if !node_to_read.continuation_point.is_null() {
+            node_to_read.continuation_point = session
+                .find_history_continuation_point(
+                    &node_to_read.continuation_point,
+                    &node_to_read.node_id,
+                )?
+                .continuation_point;
+        }
+        Ok(node_to_read)
+    }
+
+    /// Keeps the continuation point that the provider returned for a node in the session and
+    /// replaces it in the result with its id. The result is `BadNoContinuationPoints` if the
+    /// session has no continuation point to spare.
+    #[cfg(feature = "history")]
+    fn add_history_continuation_point(
+        session: &mut Session,
+        mut result: HistoryReadResult,
+        node_to_read: &HistoryReadValueId,
+    ) -> HistoryReadResult {
+        if !result.continuation_point.is_null() {
+            let continuation_point = HistoryContinuationPoint {
+                node_id: node_to_read.node_id.clone(),
+                continuation_point: result.continuation_point,
+            };
+            match session.add_history_continuation_point(continuation_point) {
+                Ok(id) => result.continuation_point = id,
+                Err(status_code) => {
+                    result = HistoryReadResult {
+                        status_code,
+                        continuation_point: ByteString::null(),
+                        history_data: ExtensionObject::null(),
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Merges the results of the nodes that were read with the status codes of the nodes that
+    /// were not permitted to be read, in the order of the request.
+    #[cfg(feature = "history")]
+    fn history_read_results<T>(
+        access_results: Vec<Result<T, StatusCode>>,
+        results: Vec<HistoryReadResult>,
+    ) -> Vec<HistoryReadResult> {
+        let mut results = results.into_iter();
+        access_results
+            .into_iter()
+            .filter_map(|r| match r {
+                Ok(_) => results.next(),
+                Err(status_code) => Some(HistoryReadResult {
+                    status_code,
+                    continuation_point: ByteString::null(),
+                    history_data: ExtensionObject::null(),
+                }),
+            })
+            .collect()
     }
 
     fn is_supported_data_encoding(data_encoding: &QualifiedName) -> bool {
@@ -498,6 +644,7 @@ impl AttributeService {
     }
 
     fn read_node_value(
+        server_state: &ServerState,
         session: &Session,
         address_space: &AddressSpace,
         node_to_read: &ReadValueId,
@@ -528,13 +675,20 @@ impl AttributeService {
                     }
                 };
 
-                if !Self::is_readable(session, node, attribute_id) {
+                if let Err(status_code) = Self::check_access_level(
+                    server_state,
+                    session,
+                    node,
+                    attribute_id,
+                    AccessLevel::CURRENT_READ,
+                    StatusCode::BadNotReadable,
+                ) {
                     // Can't read this node
                     debug!(
                         "read_node_value result for read node id {}, attribute {} is unreadable",
                         node_to_read.node_id, node_to_read.attribute_id
                     );
-                    result_value.status = Some(StatusCode::BadNotReadable);
+                    result_value.status = Some(status_code);
                 } else if attribute_id != AttributeId::Value && index_range != NumericRange::None {
                     // Can't supply an index range on a non-Value attribute
                     debug!(
@@ -546,7 +700,7 @@ impl AttributeService {
                     // Caller must request binary
                     debug!("read_node_value result for read node id {}, attribute {} is invalid data encoding", node_to_read.node_id, node_to_read.attribute_id);
                     result_value.status = Some(StatusCode::BadDataEncodingInvalid);
-                } else if let Some(attribute) = node.as_node().get_attribute_max_age(
+                } else if let Some(mut attribute) = node.as_node().get_attribute_max_age(
                     timestamps_to_return,
                     attribute_id,
                     index_range,
@@ -555,28 +709,35 @@ impl AttributeService {
                 ) {
                     // If caller was reading the user access level, this needs to be modified to
                     // take account of the effective level based on who is logged in.
-                    let value = if attribute_id == AttributeId::UserAccessLevel {
-                        if let Some(value) = attribute.value {
-                            if let Variant::Byte(value) = value {
-                                // The bits from the node are further modified by the session
-                                let user_access_level = UserAccessLevel::from_bits_truncate(value);
-                                let user_access_level = session.effective_user_access_level(
-                                    user_access_level,
-                                    &node.node_id(),
-                                    attribute_id,
-                                );
-                                Some(Variant::from(user_access_level.bits()))
-                            } else {
-                                Some(value)
-                            }
-                        } else {
-                            None
+                    let value = match node {
+                        NodeType::Variable(variable)
+                            if attribute_id == AttributeId::UserAccessLevel =>
+                        {
+                            // The bits from the node are further modified by the session
+                            let user_access_level =
+                                Self::user_access_level(server_state, session, variable);
+                            Some(Variant::from(user_access_level.bits()))
                         }
-                    } else {
-                        attribute.value.clone()
+                        _ if attribute_id == AttributeId::UserWriteMask => {
+                            let user_write_mask =
+                                Self::user_write_mask(server_state, session, node);
+                            Some(Variant::from(user_write_mask.bits()))
+                        }
+                        // The value is moved rather than copied, it is only the index range of a
+                        // large array
+                        _ => attribute.value.take(),
                     };
 
-                    // Result value is clone from the attribute
+                    // Human readable text is returned in the locale of the session
+                    let value = value.map(|mut value| {
+                        if let Some(locale_ids) = session.locale_ids() {
+                            address_space
+                                .localization()
+                                .localize_variant(&mut value, locale_ids);
+                        }
+                        value
+                    });
+
                     result_value.value = value;
                     result_value.status = attribute.status;
 
@@ -589,25 +750,16 @@ impl AttributeService {
                     // Timestamps to return only applies to variable value
                     if let NodeType::Variable(_) = node {
                         if attribute_id == AttributeId::Value {
-                            match timestamps_to_return {
-                                TimestampsToReturn::Source => {
-                                    result_value.source_timestamp = attribute.source_timestamp;
-                                    result_value.source_picoseconds = attribute.source_picoseconds;
-                                }
-                                TimestampsToReturn::Server => {
-                                    result_value.server_timestamp = attribute.server_timestamp;
-                                    result_value.server_picoseconds = attribute.server_picoseconds;
-                                }
-                                TimestampsToReturn::Both => {
-                                    result_value.source_timestamp = attribute.source_timestamp;
-                                    result_value.source_picoseconds = attribute.source_picoseconds;
-                                    result_value.server_timestamp = attribute.server_timestamp;
-                                    result_value.server_picoseconds = attribute.server_picoseconds;
-                                }
-                                TimestampsToReturn::Neither | TimestampsToReturn::Invalid => {
-                                    // Nothing needs to change
-                                }
+                            result_value.source_timestamp = attribute.source_timestamp;
+                            result_value.source_picoseconds = attribute.source_picoseconds;
+                            result_value.server_timestamp = attribute.server_timestamp;
+                            result_value.server_picoseconds = attribute.server_picoseconds;
+                            // A value without a server timestamp, e.g. from a getter, is stamped
+                            // with the time it was read
+                            if result_value.server_timestamp.is_none() {
+                                result_value.set_server_time(chrono::Utc::now());
                             }
+                            result_value.retain_timestamps(timestamps_to_return);
                         }
                     }
                 } else {
@@ -634,142 +786,262 @@ impl AttributeService {
         result_value
     }
 
+    /// Returns the user access level of a variable for the user of the session. It never exceeds
+    /// the access level of the variable.
     fn user_access_level(
+        server_state: &ServerState,
+        session: &Session,
+        variable: &Variable,
+    ) -> UserAccessLevel {
+        let user_access_level = session.effective_user_access_level(
+            variable.user_access_level(),
+            &variable.node_id(),
+            server_state,
+        );
+        user_access_level & UserAccessLevel::from_bits_truncate(variable.access_level().bits())
+    }
+
+    /// Checks that the value of a variable permits an access, e.g. `AccessLevel::CURRENT_READ`.
+    /// Fails with `not_permitted` if the access level of the variable does not permit it, or with
+    /// `BadUserAccessDenied` if the user access level does not. Other attributes and other kinds
+    /// of node are not governed by access levels.
+    fn check_access_level(
+        server_state: &ServerState,
         session: &Session,
         node: &NodeType,
         attribute_id: AttributeId,
-    ) -> UserAccessLevel {
-        let user_access_level = if let NodeType::Variable(ref node) = node {
-            node.user_access_level()
-        } else {
-            UserAccessLevel::CURRENT_READ
-        };
-        session.effective_user_access_level(user_access_level, &node.node_id(), attribute_id)
+        access: AccessLevel,
+        not_permitted: StatusCode,
+    ) -> Result<(), StatusCode> {
+        match node {
+            NodeType::Variable(variable) if attribute_id == AttributeId::Value => {
+                let user_access = UserAccessLevel::from_bits_truncate(access.bits());
+                if !variable.access_level().contains(access) {
+                    Err(not_permitted)
+                } else if !Self::user_access_level(server_state, session, variable)
+                    .contains(user_access)
+                {
+                    Err(StatusCode::BadUserAccessDenied)
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
     }
 
-    fn is_readable(session: &Session, node: &NodeType, attribute_id: AttributeId) -> bool {
-        // TODO session for current user
-        // Check for access level, user access level
-        Self::user_access_level(session, node, attribute_id).contains(UserAccessLevel::CURRENT_READ)
+    /// Returns the bit of the write mask that allows the attribute of a node to be written.
+    fn write_mask_bit(node: &NodeType, attribute_id: AttributeId) -> Option<WriteMask> {
+        match attribute_id {
+            AttributeId::Value => {
+                if let NodeType::VariableType(_) = node {
+                    Some(WriteMask::VALUE_FOR_VARIABLE_TYPE)
+                } else {
+                    None
+                }
+            }
+            AttributeId::NodeId => Some(WriteMask::NODE_ID),
+            AttributeId::NodeClass => Some(WriteMask::NODE_CLASS),
+            AttributeId::BrowseName => Some(WriteMask::BROWSE_NAME),
+            AttributeId::DisplayName => Some(WriteMask::DISPLAY_NAME),
+            AttributeId::Description => Some(WriteMask::DESCRIPTION),
+            AttributeId::WriteMask => Some(WriteMask::WRITE_MASK),
+            AttributeId::UserWriteMask => Some(WriteMask::USER_WRITE_MASK),
+            AttributeId::IsAbstract => Some(WriteMask::IS_ABSTRACT),
+            AttributeId::Symmetric => Some(WriteMask::SYMMETRIC),
+            AttributeId::InverseName => Some(WriteMask::INVERSE_NAME),
+            AttributeId::ContainsNoLoops => Some(WriteMask::CONTAINS_NO_LOOPS),
+            AttributeId::EventNotifier => Some(WriteMask::EVENT_NOTIFIER),
+            AttributeId::DataType => Some(WriteMask::DATA_TYPE),
+            AttributeId::ValueRank => Some(WriteMask::VALUE_RANK),
+            AttributeId::ArrayDimensions => Some(WriteMask::ARRAY_DIMENSIONS),
+            AttributeId::AccessLevel => Some(WriteMask::ACCESS_LEVEL),
+            AttributeId::UserAccessLevel => Some(WriteMask::USER_ACCESS_LEVEL),
+            AttributeId::MinimumSamplingInterval => Some(WriteMask::MINIMUM_SAMPLING_INTERVAL),
+            AttributeId::Historizing => Some(WriteMask::HISTORIZING),
+            AttributeId::Executable => Some(WriteMask::EXECUTABLE),
+            AttributeId::UserExecutable => Some(WriteMask::USER_EXECUTABLE),
+            AttributeId::DataTypeDefinition => Some(WriteMask::DATA_TYPE_DEFINITION),
+            AttributeId::RolePermissions => Some(WriteMask::ROLE_PERMISSIONS),
+            AttributeId::AccessRestrictions => Some(WriteMask::ACCESS_RESTRICTIONS),
+            AttributeId::AccessLevelEx => Some(WriteMask::ACCESS_LEVEL_EX),
+            AttributeId::UserRolePermissions => None, // Reserved
+        }
+    }
+
+    /// Returns the user write mask of a node for the user of the session. A node without a user
+    /// write mask gives its users the write mask, and the user write mask never exceeds it.
+    fn user_write_mask(
+        server_state: &ServerState,
+        session: &Session,
+        node: &NodeType,
+    ) -> WriteMask {
+        let node = node.as_node();
+        let write_mask = node.write_mask().unwrap_or_else(WriteMask::empty);
+        let user_write_mask = node.user_write_mask().unwrap_or(write_mask);
+        session.effective_user_write_mask(user_write_mask, &node.node_id(), server_state)
+            & write_mask
     }
 
-    fn is_writable(session: &Session, node: &NodeType, attribute_id: AttributeId) -> bool {
-        // TODO session for current user
-        // For a variable, the access level controls access to the variable
+    /// Checks that the write mask of a node permits the attribute to be written. Fails with
+    /// `BadNotWritable` if the write mask does not permit it, or with `BadUserAccessDenied` if the
+    /// user write mask does not.
+    fn check_write_mask(
+        server_state: &ServerState,
+        session: &Session,
+        node: &NodeType,
+        attribute_id: AttributeId,
+    ) -> Result<(), StatusCode> {
+        // For a variable, the access levels control access to the value and are checked separately
         if let NodeType::Variable(_) = node {
             if attribute_id == AttributeId::Value {
-                return Self::user_access_level(session, node, attribute_id)
-                    .contains(UserAccessLevel::CURRENT_WRITE);
+                return Ok(());
             }
         }
-
-        if let Some(write_mask) = node.as_node().write_mask() {
-            match attribute_id {
-                AttributeId::Value => {
-                    if let NodeType::VariableType(_) = node {
-                        write_mask.contains(WriteMask::VALUE_FOR_VARIABLE_TYPE)
-                    } else {
-                        false
-                    }
-                }
-                AttributeId::NodeId => write_mask.contains(WriteMask::NODE_ID),
-                AttributeId::NodeClass => write_mask.contains(WriteMask::NODE_CLASS),
-                AttributeId::BrowseName => write_mask.contains(WriteMask::BROWSE_NAME),
-                AttributeId::DisplayName => write_mask.contains(WriteMask::DISPLAY_NAME),
-                AttributeId::Description => write_mask.contains(WriteMask::DESCRIPTION),
-                AttributeId::WriteMask => write_mask.contains(WriteMask::WRITE_MASK),
-                AttributeId::UserWriteMask => write_mask.contains(WriteMask::USER_WRITE_MASK),
-                AttributeId::IsAbstract => write_mask.contains(WriteMask::IS_ABSTRACT),
-                AttributeId::Symmetric => write_mask.contains(WriteMask::SYMMETRIC),
-                AttributeId::InverseName => write_mask.contains(WriteMask::INVERSE_NAME),
-                AttributeId::ContainsNoLoops => write_mask.contains(WriteMask::CONTAINS_NO_LOOPS),
-                AttributeId::EventNotifier => write_mask.contains(WriteMask::EVENT_NOTIFIER),
-                AttributeId::DataType => write_mask.contains(WriteMask::DATA_TYPE),
-                AttributeId::ValueRank => write_mask.contains(WriteMask::VALUE_RANK),
-                AttributeId::ArrayDimensions => write_mask.contains(WriteMask::ARRAY_DIMENSIONS),
-                AttributeId::AccessLevel => write_mask.contains(WriteMask::ACCESS_LEVEL),
-                AttributeId::UserAccessLevel => write_mask.contains(WriteMask::USER_ACCESS_LEVEL),
-                AttributeId::MinimumSamplingInterval => {
-                    write_mask.contains(WriteMask::MINIMUM_SAMPLING_INTERVAL)
-                }
-                AttributeId::Historizing => write_mask.contains(WriteMask::HISTORIZING),
-                AttributeId::Executable => write_mask.contains(WriteMask::EXECUTABLE),
-                AttributeId::UserExecutable => write_mask.contains(WriteMask::USER_EXECUTABLE),
-                AttributeId::DataTypeDefinition => {
-                    write_mask.contains(WriteMask::DATA_TYPE_DEFINITION)
-                }
-                AttributeId::RolePermissions => write_mask.contains(WriteMask::ROLE_PERMISSIONS),
-                AttributeId::AccessRestrictions => {
-                    write_mask.contains(WriteMask::ACCESS_RESTRICTIONS)
-                }
-                AttributeId::AccessLevelEx => write_mask.contains(WriteMask::ACCESS_LEVEL_EX),
-                AttributeId::UserRolePermissions => false, // Reserved
-            }
+        let write_mask_bit =
+            Self::write_mask_bit(node, attribute_id).ok_or(StatusCode::BadNotWritable)?;
+        let write_mask = node.as_node().write_mask().unwrap_or_else(WriteMask::empty);
+        if !write_mask.contains(write_mask_bit) {
+            Err(StatusCode::BadNotWritable)
+        } else if !Self::user_write_mask(server_state, session, node).contains(write_mask_bit) {
+            Err(StatusCode::BadUserAccessDenied)
         } else {
-            false
+            Ok(())
         }
     }
 
-    /*
-    fn is_history_readable(session: &Session, node: &NodeType) -> bool {
-        Self::user_access_level(session, node, AttributeId::Value).contains(UserAccessLevel::HISTORY_READ)
+    /// Returns the data type of a scalar value. The data type of an extension object is found
+    /// from its encoding in the address space, or is `Structure` if the encoding is unknown.
+    fn scalar_data_type(address_space: &AddressSpace, value: &Variant) -> Option<NodeId> {
+        if let Variant::ExtensionObject(value) = value {
+            // The data type is the source of the inverse reference to the encoding
+            let data_type = address_space
+                .find_inverse_references(
+                    &value.node_id,
+                    Some((ReferenceTypeId::HasEncoding, false)),
+                )
+                .and_then(|references| references.first().map(|r| r.target_node.clone()));
+            data_type.or_else(|| Some(DataTypeId::Structure.into()))
+        } else {
+            value.scalar_data_type()
+        }
     }
 
-    fn is_history_updateable(session: &Session, node: &NodeType) -> bool {
-        Self::user_access_level(session, node, AttributeId::Value).contains(UserAccessLevel::HISTORY_WRITE)
+    /// Tests if a value of one data type can be written to a variable of another. Besides
+    /// subtypes, an enumeration may be written as its `Int32` value.
+    fn is_data_type_writable(
+        address_space: &AddressSpace,
+        value_data_type: &NodeId,
+        node_data_type: &NodeId,
+    ) -> bool {
+        address_space.is_subtype_of(value_data_type, node_data_type)
+            || (*value_data_type == DataTypeId::Int32.into()
+                && address_space.is_subtype_of(node_data_type, &DataTypeId::Enumeration.into()))
     }
-    */
 
-    /// Determine if the value is writable to a Variable node's data type
+    /// Validates a value to write against the data type, value rank and array dimensions of a
+    /// variable. A value written to an index range is only a part of the whole value, so only its
+    /// data type is validated. Fails with `BadTypeMismatch` if the value is of the wrong type or
+    /// rank, or with `BadOutOfRange` if it is larger than the array dimensions.
     fn validate_value_to_write(
         address_space: &AddressSpace,
         variable: &Variable,
         value: &Variant,
-    ) -> bool {
+        index_range: &NumericRange,
+    ) -> Result<(), StatusCode> {
         // Get the value rank and data type of the variable
         let value_rank = variable.value_rank();
         let node_data_type = variable.data_type();
 
-        let valid = if let Variant::Empty = value {
-            // Assigning an empty value is permissible
-            true
-        } else if let Some(value_data_type) = value.scalar_data_type() {
-            // Value is scalar. Check if the data type matches
-            let data_type_matches = address_space.is_subtype(&value_data_type, &node_data_type);
-            if !data_type_matches {
-                // Check if the value to write is a byte string and the receiving node type a byte array.
-                // This code is a mess just for some weird edge case in the spec that a write from
-                // a byte string to a byte array should succeed
-                match value {
-                    Variant::ByteString(_) => {
-                        if node_data_type == DataTypeId::Byte.into() {
-                            match value_rank {
-                                -2 | -3 | 1 => true,
-                                _ => false,
-                            }
-                        } else {
-                            false
-                        }
+        // The dimensions of the value, or None if it is a scalar
+        let dimensions = match value {
+            Variant::Empty => {
+                // Assigning an empty value is permissible
+                return Ok(());
+            }
+            Variant::ByteString(value)
+                if node_data_type == DataTypeId::Byte.into()
+                    && matches!(value_rank, -3 | -2 | 1) =>
+            {
+                // A byte string can be written to a byte array, an edge case in the spec
+                Some(vec![value.as_ref().len() as u32])
+            }
+            Variant::Array(array) => {
+                if let Some(value_data_type) = array
+                    .values
+                    .first()
+                    .and_then(|v| Self::scalar_data_type(address_space, v))
+                {
+                    if !Self::is_data_type_writable(
+                        address_space,
+                        &value_data_type,
+                        &node_data_type,
+                    ) {
+                        debug!("Variable value validation did not pass, check value {:?} against var {} data type of {}", value, variable.node_id(), node_data_type);
+                        return Err(StatusCode::BadTypeMismatch);
                     }
-                    _ => data_type_matches,
                 }
-            } else {
-                true
+                if array.dimensions.is_empty() {
+                    Some(vec![array.values.len() as u32])
+                } else {
+                    Some(array.dimensions.clone())
+                }
             }
-        } else if let Some(value_data_type) = value.array_data_type() {
-            // TODO check that value is array of same dimensions
-            address_space.is_subtype(&value_data_type, &node_data_type)
-        } else {
-            // Value should have a data type
-            false
+            value => {
+                let value_data_type = Self::scalar_data_type(address_space, value)
+                    .ok_or(StatusCode::BadTypeMismatch)?;
+                if !Self::is_data_type_writable(address_space, &value_data_type, &node_data_type) {
+                    debug!("Variable value validation did not pass, check value {:?} against var {} data type of {}", value, variable.node_id(), node_data_type);
+                    return Err(StatusCode::BadTypeMismatch);
+                }
+                None
+            }
+        };
+
+        if index_range.has_range() {
+            return Ok(());
+        }
+
+        // Check the value rank
+        let rank_matches = match dimensions {
+            None => (-3..=-1).contains(&value_rank),
+            Some(ref dimensions) => match value_rank {
+                -3 => dimensions.len() == 1,
+                -2 | 0 => true,
+                -1 => false,
+                value_rank => dimensions.len() == value_rank as usize,
+            },
         };
-        if !valid {
-            debug!("Variable value validation did not pass, check value {:?} against var {} data type of {}", value, variable.node_id(), node_data_type);
+        if !rank_matches {
+            debug!(
+                "Variable value validation did not pass, value {:?} does not match the value rank {} of var {}",
+                value, value_rank, variable.node_id()
+            );
+            return Err(StatusCode::BadTypeMismatch);
+        }
+
+        // Check the value fits the array dimensions, where a dimension of 0 has no limit
+        if let (Some(dimensions), Some(array_dimensions)) =
+            (dimensions, variable.array_dimensions())
+        {
+            if dimensions.len() == array_dimensions.len()
+                && dimensions
+                    .iter()
+                    .zip(array_dimensions.iter())
+                    .any(|(d, max)| *max > 0 && d > max)
+            {
+                debug!(
+                    "Variable value validation did not pass, value {:?} exceeds the array dimensions {:?} of var {}",
+                    value, array_dimensions, variable.node_id()
+                );
+                return Err(StatusCode::BadOutOfRange);
+            }
         }
-        valid
+        Ok(())
     }
 
     fn write_node_value(
+        server_state: &ServerState,
         session: &Session,
         address_space: &mut AddressSpace,
         node_to_write: &WriteValue,
@@ -778,8 +1050,19 @@ impl AttributeService {
             if let Ok(attribute_id) = AttributeId::from_u32(node_to_write.attribute_id) {
                 let index_range = node_to_write.index_range.as_ref().parse::<NumericRange>();
 
-                if !Self::is_writable(session, node, attribute_id) {
-                    StatusCode::BadNotWritable
+                if let Err(status_code) = Self::check_access_level(
+                    server_state,
+                    session,
+                    node,
+                    attribute_id,
+                    AccessLevel::CURRENT_WRITE,
+                    StatusCode::BadNotWritable,
+                ) {
+                    status_code
+                } else if let Err(status_code) =
+                    Self::check_write_mask(server_state, session, node, attribute_id)
+                {
+                    status_code
                 } else if attribute_id != AttributeId::Value && !node_to_write.index_range.is_null()
                 {
                     // Index ranges are not supported on anything other than a value attribute
@@ -794,51 +1077,101 @@ impl AttributeService {
                     StatusCode::BadIndexRangeInvalid
                 } else if let Some(ref value) = node_to_write.value.value {
                     let index_range = index_range.unwrap();
+                    let is_variable_value =
+                        attribute_id == AttributeId::Value && matches!(node, NodeType::Variable(_));
 
-                    // This is a band-aid for Variable::Value which should check if the data type
-                    // matches the written value. Note, that ALL attributes should check for subtypes
-                    // but they don't. There should be a general purpose fn attribute_type(attribute_id) helper
-                    // on the node impl that returns a datatype for the attribute regardless of node.
-                    let data_type_valid = if attribute_id == AttributeId::Value {
-                        match node {
-                            NodeType::Variable(ref variable) => {
-                                Self::validate_value_to_write(address_space, variable, value)
-                            }
-                            _ => true, // Other types don't have this attr but they will reject later during set
-                        }
+                    // The before write hooks may reject or transform the value of a variable
+                    let value = if is_variable_value {
+                        address_space.before_write(&node_to_write.node_id, value.clone(), session)
                     } else {
-                        true
+                        Ok(value.clone())
+                    };
+                    let value = match value {
+                        Ok(value) => value,
+                        Err(err) => {
+                            debug!(
+                                "Write to node {} was rejected by a hook, error = {}",
+                                node_to_write.node_id, err
+                            );
+                            return err;
+                        }
                     };
-                    if !data_type_valid {
-                        error!("Data type of value is invalid for writing to attribute");
-                        StatusCode::BadTypeMismatch
+
+                    // The value of a variable is validated against its data type, value rank,
+                    // array dimensions and the values of an enumeration. Other attributes are
+                    // validated as they are set.
+                    let validation = match address_space.find_node(&node_to_write.node_id) {
+                        Some(NodeType::Variable(ref variable)) if is_variable_value => {
+                            Self::validate_value_to_write(
+                                address_space,
+                                variable,
+                                &value,
+                                &index_range,
+                            )
+                            .and_then(|_| {
+                                data_access::validate_enum_value(
+                                    address_space,
+                                    &node_to_write.node_id,
+                                    &variable.data_type(),
+                                    &value,
+                                )
+                            })
+                        }
+                        _ => Ok(()),
+                    };
+                    if let Err(err) = validation {
+                        error!("Value is invalid for writing to attribute");
+                        err
                     } else {
+                        let notify_value_changed =
+                            is_variable_value && address_space.has_value_changed_hooks();
                         let node = address_space.find_node_mut(&node_to_write.node_id).unwrap();
                         let result = if attribute_id == AttributeId::Value {
                             match node {
-                                NodeType::Variable(ref mut variable) => variable
-                                    .set_value(index_range, value.clone())
-                                    .map_err(|err| {
-                                        error!(
-                                            "Value could not be set to node {} Value, error = {}",
-                                            node_to_write.node_id, err
-                                        );
-                                        err
-                                    }),
+                                NodeType::Variable(ref mut variable) => {
+                                    let old_value = if notify_value_changed {
+                                        Some(AddressSpace::current_value(variable))
+                                    } else {
+                                        None
+                                    };
+                                    variable
+                                        .set_value(index_range, value)
+                                        .map(|_| {
+                                            old_value.map(|old_value| {
+                                                (old_value, AddressSpace::current_value(variable))
+                                            })
+                                        })
+                                        .inspect_err(|err| {
+                                            error!(
+                                                "Value could not be set to node {} Value, error = {}",
+                                                node_to_write.node_id, err
+                                            );
+                                        })
+                                }
                                 _ => Err(StatusCode::BadAttributeIdInvalid),
                             }
                         } else {
                             let node = node.as_mut_node();
-                            node.set_attribute(attribute_id, value.clone())
-                                .map_err(|err| {
+                            node.set_attribute(attribute_id, value)
+                                .map(|_| None)
+                                .inspect_err(|err| {
                                     error!("Value could not be set to node {} attribute {:?}, error = {}", node_to_write.node_id, attribute_id, err);
-                                    err
                                 })
                         };
-                        if let Err(err) = result {
-                            err
-                        } else {
-                            StatusCode::Good
+                        if result.is_ok() && is_variable_value {
+                            data_access::value_written(address_space, &node_to_write.node_id);
+                        }
+                        match result {
+                            Err(err) => err,
+                            Ok(Some((old_value, new_value))) => {
+                                address_space.value_changed(
+                                    &node_to_write.node_id,
+                                    &old_value,
+                                    &new_value,
+                                );
+                                StatusCode::Good
+                            }
+                            Ok(None) => StatusCode::Good,
                         }
                     }
                 } else {
@@ -129,6 +129,7 @@ pub fn log_certificate_error(
     server_state: &ServerState,
     address_space: Arc<RwLock<AddressSpace>>,
     status_code: StatusCode,
+    certificate: &ByteString,
     request_header: &RequestHeader,
 ) {
     let node_id = next_node_id(address_space);
@@ -140,6 +141,12 @@ pub fn log_certificate_error(
                 .client_audit_entry_id(request_header.audit_entry_id.clone());
             let _ = server_state.raise_and_log(event);
         }
+        StatusCode::BadCertificateUntrusted => {
+            let event = AuditCertificateUntrustedEventType::new(node_id, now)
+                .certificate(certificate.clone())
+                .client_audit_entry_id(request_header.audit_entry_id.clone());
+            let _ = server_state.raise_and_log(event);
+        }
         _ => {
             // TODO client_id
             let event = AuditCertificateInvalidEventType::new(node_id, now)
@@ -0,0 +1,179 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Fills in the diagnostic infos of responses for clients that ask for them with the
+//! `return_diagnostics` of their request header.
+
+use crate::core::supported_message::SupportedMessage;
+use crate::types::{status_code::StatusCode, *};
+
+/// Makes the diagnostic infos for the status codes of the operations of a request. Returns `None`
+/// if every operation succeeded, since the list must then be empty.
+fn operation_diagnostic_infos<I>(
+    status_codes: I,
+    diagnostics: DiagnosticBits,
+    string_table: &mut StringTable,
+) -> Option<Vec<DiagnosticInfo>>
+where
+    I: Iterator<Item = StatusCode>,
+{
+    let diagnostic_infos = status_codes
+        .map(|status_code| {
+            if status_code.is_good() {
+                DiagnosticInfo::null()
+            } else {
+                DiagnosticInfo::from_status_code(status_code, diagnostics, string_table)
+            }
+        })
+        .collect::<Vec<_>>();
+    if diagnostic_infos.iter().all(|d| d.is_null()) {
+        None
+    } else {
+        Some(diagnostic_infos)
+    }
+}
+
+/// Adds the service and operation level diagnostics requested by the client to the response. The
+/// strings the diagnostics refer to are put into the string table of the response header.
+pub(crate) fn add_diagnostic_infos(
+    return_diagnostics: DiagnosticBits,
+    response: &mut SupportedMessage,
+) {
+    if return_diagnostics.is_empty() {
+        return;
+    }
+    let operation_level = return_diagnostics.operation_level();
+
+    // Every response with operation results gives their status codes and the place for their
+    // diagnostic infos
+    macro_rules! operation_results {
+        ($response: expr, $status_code: expr) => {{
+            let status_codes = $response.results.as_ref().map(|results| {
+                results
+                    .iter()
+                    .map($status_code)
+                    .collect::<Vec<StatusCode>>()
+            });
+            (
+                &mut $response.response_header,
+                vec![(status_codes, &mut $response.diagnostic_infos)],
+            )
+        }};
+    }
+
+    let (response_header, operations) = match response {
+        SupportedMessage::ServiceFault(response) => (&mut response.response_header, vec![]),
+        SupportedMessage::ActivateSessionResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        #[cfg(feature = "node-management")]
+        SupportedMessage::AddNodesResponse(response) => {
+            operation_results!(response, |r: &AddNodesResult| r.status_code)
+        }
+        #[cfg(feature = "node-management")]
+        SupportedMessage::AddReferencesResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        #[cfg(feature = "node-management")]
+        SupportedMessage::DeleteNodesResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        #[cfg(feature = "node-management")]
+        SupportedMessage::DeleteReferencesResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        SupportedMessage::BrowseResponse(response) => {
+            operation_results!(response, |r: &BrowseResult| r.status_code)
+        }
+        SupportedMessage::BrowseNextResponse(response) => {
+            operation_results!(response, |r: &BrowseResult| r.status_code)
+        }
+        SupportedMessage::TranslateBrowsePathsToNodeIdsResponse(response) => {
+            operation_results!(response, |r: &BrowsePathResult| r.status_code)
+        }
+        SupportedMessage::ReadResponse(response) => {
+            operation_results!(response, |r: &DataValue| r.status())
+        }
+        #[cfg(feature = "history")]
+        SupportedMessage::HistoryReadResponse(response) => {
+            operation_results!(response, |r: &HistoryReadResult| r.status_code)
+        }
+        SupportedMessage::WriteResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        #[cfg(feature = "history")]
+        SupportedMessage::HistoryUpdateResponse(response) => {
+            operation_results!(response, |r: &HistoryUpdateResult| r.status_code)
+        }
+        SupportedMessage::CallResponse(response) => {
+            operation_results!(response, |r: &CallMethodResult| r.status_code)
+        }
+        SupportedMessage::CreateMonitoredItemsResponse(response) => {
+            operation_results!(response, |r: &MonitoredItemCreateResult| r.status_code)
+        }
+        SupportedMessage::ModifyMonitoredItemsResponse(response) => {
+            operation_results!(response, |r: &MonitoredItemModifyResult| r.status_code)
+        }
+        SupportedMessage::SetMonitoringModeResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        SupportedMessage::DeleteMonitoredItemsResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        SupportedMessage::SetPublishingModeResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        SupportedMessage::DeleteSubscriptionsResponse(response) => {
+            operation_results!(response, |r: &StatusCode| *r)
+        }
+        SupportedMessage::TransferSubscriptionsResponse(response) => {
+            operation_results!(response, |r: &TransferResult| r.status_code)
+        }
+        SupportedMessage::SetTriggeringResponse(response) => (
+            &mut response.response_header,
+            vec![
+                (
+                    response.add_results.clone(),
+                    &mut response.add_diagnostic_infos,
+                ),
+                (
+                    response.remove_results.clone(),
+                    &mut response.remove_diagnostic_infos,
+                ),
+            ],
+        ),
+        _ => {
+            // Other responses have no operations and only a bad service result has diagnostics,
+            // which are sent in a service fault
+            return;
+        }
+    };
+
+    let mut string_table = StringTable::from(response_header.string_table.take());
+    let service_level = return_diagnostics.service_level();
+    if !service_level.is_empty()
+        && response_header.service_result.is_bad()
+        && response_header.service_diagnostics.is_null()
+    {
+        response_header.service_diagnostics = DiagnosticInfo::from_status_code(
+            response_header.service_result,
+            service_level,
+            &mut string_table,
+        );
+    }
+    if !operation_level.is_empty() {
+        for (status_codes, diagnostic_infos) in operations {
+            if let Some(status_codes) = status_codes {
+                if diagnostic_infos.is_none() {
+                    *diagnostic_infos = operation_diagnostic_infos(
+                        status_codes.into_iter(),
+                        operation_level,
+                        &mut string_table,
+                    );
+                }
+            }
+        }
+    }
+    response_header.string_table = string_table.into_strings();
+}
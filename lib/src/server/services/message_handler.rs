@@ -2,24 +2,28 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::sync::Arc;
-
-use chrono::Utc;
+use std::{net::SocketAddr, sync::Arc};
 
 use crate::core::comms::secure_channel::SecureChannel;
+#[cfg(any(feature = "metrics", feature = "tracing"))]
+use crate::core::record_metric;
 use crate::core::supported_message::SupportedMessage;
-use crate::crypto::CertificateStore;
+use crate::crypto::{CertificateStore, SecurityPolicy};
 use crate::sync::*;
 use crate::types::{status_code::StatusCode, *};
 
+#[cfg(feature = "node-management")]
+use crate::server::services::node_management::NodeManagementService;
+#[cfg(feature = "query")]
+use crate::server::services::query::QueryService;
 use crate::server::{
     address_space::AddressSpace,
     comms::tcp_transport::MessageSender,
+    config::QueueOverflowPolicy,
     services::{
-        attribute::AttributeService, discovery::DiscoveryService, method::MethodService,
-        monitored_item::MonitoredItemService, node_management::NodeManagementService,
-        query::QueryService, session::SessionService, subscription::SubscriptionService,
-        view::ViewService,
+        attribute::AttributeService, diagnostic_infos::add_diagnostic_infos,
+        discovery::DiscoveryService, method::MethodService, monitored_item::MonitoredItemService,
+        session::SessionService, subscription::SubscriptionService, view::ViewService,
     },
     session::{Session, SessionManager},
     session_diagnostics::*,
@@ -38,17 +42,21 @@ pub(crate) struct MessageHandler {
     address_space: Arc<RwLock<AddressSpace>>,
     /// Session state
     session_manager: Arc<RwLock<SessionManager>>,
+    /// Address of the client, recorded with its certificate if that is rejected
+    client_address: Option<SocketAddr>,
     /// Attribute service
     attribute_service: AttributeService,
     /// Discovery service
     discovery_service: DiscoveryService,
     /// Node Management service
+    #[cfg(feature = "node-management")]
     node_management_service: NodeManagementService,
     /// Method service
     method_service: MethodService,
     /// MonitoredItem service
     monitored_item_service: MonitoredItemService,
     /// Query service
+    #[cfg(feature = "query")]
     query_service: QueryService,
     /// Session service
     session_service: SessionService,
@@ -56,6 +64,12 @@ pub(crate) struct MessageHandler {
     subscription_service: SubscriptionService,
     /// View service
     view_service: ViewService,
+    /// Number of queued responses at which the connection overflows, 0 for no limit
+    max_queued_responses: usize,
+    /// What happens to requests while the connection overflows
+    queue_overflow_policy: QueueOverflowPolicy,
+    /// Only discovery requests are served over secure channels with security policy None
+    secure_sessions_only: bool,
 }
 
 impl MessageHandler {
@@ -66,24 +80,43 @@ impl MessageHandler {
         session_manager: Arc<RwLock<SessionManager>>,
         address_space: Arc<RwLock<AddressSpace>>,
     ) -> MessageHandler {
+        let (max_queued_responses, queue_overflow_policy, secure_sessions_only) = {
+            let server_state = trace_read_lock!(server_state);
+            let config = trace_read_lock!(server_state.config);
+            (
+                config.limits.max_queued_responses,
+                config.limits.queue_overflow_policy,
+                config.secure_sessions_only,
+            )
+        };
         MessageHandler {
             secure_channel,
             certificate_store,
             server_state,
             session_manager,
             address_space,
+            client_address: None,
             attribute_service: AttributeService::new(),
             discovery_service: DiscoveryService::new(),
             method_service: MethodService::new(),
             monitored_item_service: MonitoredItemService::new(),
+            #[cfg(feature = "node-management")]
             node_management_service: NodeManagementService::new(),
+            #[cfg(feature = "query")]
             query_service: QueryService::new(),
             session_service: SessionService::new(),
             view_service: ViewService::new(),
             subscription_service: SubscriptionService::new(),
+            max_queued_responses,
+            queue_overflow_policy,
+            secure_sessions_only,
         }
     }
 
+    pub fn set_client_address(&mut self, client_address: Option<SocketAddr>) {
+        self.client_address = client_address;
+    }
+
     pub fn handle_message(
         &mut self,
         request_id: u32,
@@ -99,6 +132,64 @@ impl MessageHandler {
         let server_state = self.server_state.clone();
         let address_space = self.address_space.clone();
 
+        #[cfg(any(feature = "metrics", feature = "tracing"))]
+        let started = std::time::Instant::now();
+
+        // The session id, status and duration are recorded on the span as they become known
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "service_request",
+            service = message.name(),
+            request_id,
+            request_handle = message.request_handle(),
+            session_id = tracing::field::Empty,
+            status = tracing::field::Empty,
+            duration_us = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        if self.is_overloaded(message, sender) {
+            if self.queue_overflow_policy == QueueOverflowPolicy::Disconnect {
+                warn!(
+                    "Connection is closed because {} responses are waiting to be written",
+                    sender.queue_depth()
+                );
+                return Err(StatusCode::BadTcpServerTooBusy);
+            }
+            warn!(
+                "Request {} is rejected because {} responses are waiting to be written",
+                message.request_handle(),
+                sender.queue_depth()
+            );
+            let response =
+                ServiceFault::new(message.request_header(), StatusCode::BadTcpServerTooBusy).into();
+            sender.send_message(request_id, response);
+            return Ok(());
+        }
+
+        if self.is_unsecured_non_discovery(message) {
+            warn!(
+                "Request {} is rejected because only discovery is served over security policy None",
+                message.request_handle()
+            );
+            let response = ServiceFault::new(
+                message.request_header(),
+                StatusCode::BadSecurityPolicyRejected,
+            )
+            .into();
+            sender.send_message(request_id, response);
+            return Ok(());
+        }
+
+        // The client stops waiting for the response once the timeout hint has elapsed
+        let deadline = if message.is_request() && message.request_header().timeout_hint > 0 {
+            let timeout_hint = message.request_header().timeout_hint as i64;
+            Some(clock::now() + chrono::Duration::milliseconds(timeout_hint))
+        } else {
+            None
+        };
+
         let response = match message {
             // Discovery Service Set, OPC UA Part 4, Section 5.4
             SupportedMessage::GetEndpointsRequest(request) => {
@@ -135,6 +226,8 @@ impl MessageHandler {
                         self.certificate_store.clone(),
                         server_state,
                         address_space,
+                        self.client_address,
+                        session_manager.len(),
                         request,
                     );
                     if let Some(session) = session {
@@ -177,6 +270,7 @@ impl MessageHandler {
             }
 
             // NodeManagement Service Set, OPC UA Part 4, Section 5.7
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddNodesRequest(request) => {
                 self.validate_service_request(message, ADD_NODES_COUNT, |session, _| {
                     Some(self.node_management_service.add_nodes(
@@ -188,6 +282,7 @@ impl MessageHandler {
                 })
             }
 
+            #[cfg(feature = "node-management")]
             SupportedMessage::AddReferencesRequest(request) => {
                 self.validate_service_request(message, ADD_REFERENCES_COUNT, |session, _| {
                     Some(self.node_management_service.add_references(
@@ -199,6 +294,7 @@ impl MessageHandler {
                 })
             }
 
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteNodesRequest(request) => {
                 self.validate_service_request(message, DELETE_NODES_COUNT, |session, _| {
                     Some(self.node_management_service.delete_nodes(
@@ -210,6 +306,7 @@ impl MessageHandler {
                 })
             }
 
+            #[cfg(feature = "node-management")]
             SupportedMessage::DeleteReferencesRequest(request) => {
                 self.validate_service_request(message, DELETE_REFERENCES_COUNT, |session, _| {
                     Some(self.node_management_service.delete_references(
@@ -268,6 +365,7 @@ impl MessageHandler {
             }
 
             // Query Service Set, OPC UA Part 4, Section 5.9
+            #[cfg(feature = "query")]
             SupportedMessage::QueryFirstRequest(request) => {
                 self.validate_service_request(message, READ_COUNT, |session, _| {
                     Some(self.query_service.query_first(
@@ -279,6 +377,7 @@ impl MessageHandler {
                 })
             }
 
+            #[cfg(feature = "query")]
             SupportedMessage::QueryNextRequest(request) => {
                 self.validate_service_request(message, READ_COUNT, |session, _| {
                     Some(self.query_service.query_next(
@@ -299,6 +398,7 @@ impl MessageHandler {
                     )
                 })
             }
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryReadRequest(request) => {
                 self.validate_service_request(message, HISTORY_READ_COUNT, |session, _| {
                     Some(self.attribute_service.history_read(
@@ -319,6 +419,7 @@ impl MessageHandler {
                     ))
                 })
             }
+            #[cfg(feature = "history")]
             SupportedMessage::HistoryUpdateRequest(request) => {
                 self.validate_service_request(message, HISTORY_UPDATE_COUNT, |session, _| {
                     Some(self.attribute_service.history_update(
@@ -423,12 +524,18 @@ impl MessageHandler {
                 })
             }
             SupportedMessage::TransferSubscriptionsRequest(request) => self
-                .validate_service_request(message, TRANSFER_SUBSCRIPTIONS_COUNT, |session, _| {
-                    Some(
-                        self.subscription_service
-                            .transfer_subscriptions(session, request),
-                    )
-                }),
+                .validate_service_request(
+                    message,
+                    TRANSFER_SUBSCRIPTIONS_COUNT,
+                    |session, session_manager| {
+                        Some(self.subscription_service.transfer_subscriptions(
+                            &clock::now(),
+                            session_manager,
+                            session,
+                            request,
+                        ))
+                    },
+                ),
             SupportedMessage::PublishRequest(request) => {
                 self.validate_service_request(message, "", |session, _| {
                     // TODO publish request diagnostics have to be done asynchronously too
@@ -437,7 +544,7 @@ impl MessageHandler {
                     // request is queued and the response will come back out of sequence some time in
                     // the future.
                     self.subscription_service.async_publish(
-                        &Utc::now(),
+                        &clock::now(),
                         session,
                         address_space,
                         request_id,
@@ -461,13 +568,66 @@ impl MessageHandler {
             }
         };
 
-        if let Some(response) = response {
-            let _ = sender.send_message(request_id, response);
+        if let Some(mut response) = response {
+            if message.is_request() {
+                add_diagnostic_infos(message.request_header().return_diagnostics, &mut response);
+            }
+            #[cfg(any(feature = "metrics", feature = "tracing"))]
+            {
+                let status = if let SupportedMessage::ServiceFault(ref fault) = response {
+                    fault.response_header.service_result
+                } else {
+                    StatusCode::Good
+                };
+                #[cfg(feature = "tracing")]
+                {
+                    span.record("status", tracing::field::display(status));
+                    span.record("duration_us", started.elapsed().as_micros() as u64);
+                }
+                record_metric!(on_service_call(message.name(), started.elapsed(), status));
+            }
+            sender.send_response(request_id, response, deadline);
         }
 
         Ok(())
     }
 
+    /// Tests if the request should be shed because too many responses on the connection are
+    /// waiting to be written. Requests that free up resources on the server are never shed, and
+    /// none are when the reader waits for the queue to have room instead.
+    fn is_overloaded(&self, message: &SupportedMessage, sender: &MessageSender) -> bool {
+        if self.max_queued_responses == 0
+            || self.queue_overflow_policy == QueueOverflowPolicy::Block
+            || !message.is_request()
+        {
+            false
+        } else {
+            match message {
+                SupportedMessage::CloseSessionRequest(_) | SupportedMessage::CancelRequest(_) => {
+                    false
+                }
+                _ => sender.queue_depth() >= self.max_queued_responses,
+            }
+        }
+    }
+
+    /// Tests if the request must be rejected because the server only creates sessions over
+    /// secure channels that are signed or encrypted, and serves nothing but discovery otherwise
+    fn is_unsecured_non_discovery(&self, message: &SupportedMessage) -> bool {
+        if !self.secure_sessions_only || !message.is_request() {
+            false
+        } else {
+            match message {
+                SupportedMessage::GetEndpointsRequest(_)
+                | SupportedMessage::FindServersRequest(_) => false,
+                _ => {
+                    let secure_channel = trace_read_lock!(self.secure_channel);
+                    secure_channel.security_policy() == SecurityPolicy::None
+                }
+            }
+        }
+    }
+
     /// Tests if this request should be rejected because of a session timeout
     fn is_session_timed_out(
         session: Arc<RwLock<Session>>,
@@ -523,7 +683,7 @@ impl MessageHandler {
     where
         F: FnOnce(Arc<RwLock<Session>>) -> SupportedMessage,
     {
-        let now = Utc::now();
+        let now = clock::now();
         let request_header = request.request_header();
 
         // Look up the session from a map to see if it exists
@@ -532,6 +692,12 @@ impl MessageHandler {
             session_manager.find_session_by_token(&request_header.authentication_token)
         };
         if let Some(session) = session {
+            #[cfg(feature = "tracing")]
+            {
+                let session = trace_read_lock!(session);
+                tracing::Span::current()
+                    .record("session_id", tracing::field::display(session.session_id()));
+            }
             let (response, authorized) = if let Err(response) =
                 Self::is_session_timed_out(session.clone(), request_header, now)
             {
@@ -563,7 +729,7 @@ impl MessageHandler {
     where
         F: FnOnce(Arc<RwLock<Session>>, Arc<RwLock<SessionManager>>) -> Option<SupportedMessage>,
     {
-        let now = Utc::now();
+        let now = clock::now();
         let request_header = request.request_header();
         // Look up the session from a map to see if it exists
         let session_manager = self.session_manager.clone();
@@ -572,6 +738,12 @@ impl MessageHandler {
             session_manager.find_session_by_token(&request_header.authentication_token)
         };
         if let Some(session) = session {
+            #[cfg(feature = "tracing")]
+            {
+                let session = trace_read_lock!(session);
+                tracing::Span::current()
+                    .record("session_id", tracing::field::display(session.session_id()));
+            }
             let (response, authorized) =
                 if let Err(response) = self.is_session_activated(session.clone(), request_header) {
                     (Some(response), false)
@@ -596,6 +768,7 @@ impl MessageHandler {
     }
 
     /// Increment count of request in session diagnostics
+    #[cfg(feature = "diagnostics")]
     fn diag_authorized_request(session_diagnostics: &mut SessionDiagnostics, authorized: bool) {
         if authorized {
             session_diagnostics.request();
@@ -605,6 +778,7 @@ impl MessageHandler {
     }
 
     /// Increment count of service call in session diagnostics
+    #[cfg(feature = "diagnostics")]
     fn diag_service_response(
         session: Arc<RwLock<Session>>,
         authorized: bool,
@@ -628,4 +802,14 @@ impl MessageHandler {
             }
         }
     }
+
+    /// Without diagnostics there are no service calls to count
+    #[cfg(not(feature = "diagnostics"))]
+    fn diag_service_response(
+        _session: Arc<RwLock<Session>>,
+        _authorized: bool,
+        _response: &SupportedMessage,
+        _diagnostic_key: &'static str,
+    ) {
+    }
 }
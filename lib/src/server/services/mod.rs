@@ -27,13 +27,17 @@ trait Service {
 }
 
 pub mod attribute;
+pub mod diagnostic_infos;
 pub mod discovery;
 pub mod method;
 pub mod monitored_item;
+#[cfg(feature = "node-management")]
 pub mod node_management;
+#[cfg(feature = "query")]
 pub mod query;
 pub mod session;
 pub mod subscription;
 pub mod view;
 
+#[cfg(feature = "events")]
 mod audit;
@@ -2,17 +2,24 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::core::supported_message::SupportedMessage;
 use crate::sync::*;
-use crate::types::{status_code::StatusCode, *};
+use crate::types::{node_ids::ReferenceTypeId, status_code::StatusCode, *};
 
 use crate::server::{
-    address_space::AddressSpace, services::Service, session::Session, state::ServerState,
+    address_space::{relative_path, AddressSpace},
+    continuation_point::QueryContinuationPoint,
+    events::event_filter,
+    services::{view, Service},
+    session::Session,
+    state::ServerState,
 };
 
-/// The view service. Allows the client to browse the address space of the server.
+/// The query service. Allows the client to find the nodes of the address space, or of a view, that
+/// are instances of certain types and match a filter.
 pub(crate) struct QueryService;
 
 impl Service for QueryService {
@@ -29,20 +36,306 @@ impl QueryService {
     pub fn query_first(
         &self,
         _server_state: Arc<RwLock<ServerState>>,
-        _session: Arc<RwLock<Session>>,
-        _address_space: Arc<RwLock<AddressSpace>>,
+        session: Arc<RwLock<Session>>,
+        address_space: Arc<RwLock<AddressSpace>>,
         request: &QueryFirstRequest,
     ) -> SupportedMessage {
-        self.service_fault(&request.request_header, StatusCode::BadNotSupported)
+        if is_empty_option_vec!(request.node_types) {
+            return self.service_fault(&request.request_header, StatusCode::BadNothingToDo);
+        }
+        let mut session = trace_write_lock!(session);
+        let address_space = trace_read_lock!(address_space);
+
+        let view_nodes = match view::nodes_in_view(&address_space, &request.view) {
+            Ok(view_nodes) => view_nodes,
+            Err(err) => {
+                info!(
+                    "Query request ignored because view {:?} is invalid, {}",
+                    request.view, err
+                );
+                return self.service_fault(&request.request_header, err);
+            }
+        };
+
+        // The filter and the node types must make sense before anything is queried
+        let filter_result =
+            match event_filter::validate_where_clause(&request.filter, &address_space) {
+                Ok(filter_result) => filter_result,
+                Err(err) => return self.service_fault(&request.request_header, err),
+            };
+        let filter_invalid = filter_result
+            .element_results
+            .as_ref()
+            .map(|r| r.iter().any(|r| r.status_code.is_bad()))
+            .unwrap_or(false);
+        let node_types = request.node_types.as_ref().unwrap();
+        let parsing_results = node_types
+            .iter()
+            .map(|node_type| Self::parse_node_type(&address_space, node_type))
+            .collect::<Vec<_>>();
+        let node_types_invalid = parsing_results.iter().any(|r| {
+            r.status_code.is_bad()
+                || r.data_status_codes
+                    .as_ref()
+                    .map(|s| s.iter().any(|s| s.is_bad()))
+                    .unwrap_or(false)
+        });
+        if filter_invalid || node_types_invalid {
+            let service_result = if filter_invalid {
+                StatusCode::BadContentFilterInvalid
+            } else {
+                StatusCode::BadInvalidArgument
+            };
+            return QueryFirstResponse {
+                response_header: ResponseHeader::new_service_result(
+                    &request.request_header,
+                    service_result,
+                ),
+                query_data_sets: None,
+                continuation_point: ByteString::null(),
+                parsing_results: if node_types_invalid {
+                    Some(parsing_results)
+                } else {
+                    None
+                },
+                diagnostic_infos: None,
+                filter_result,
+            }
+            .into();
+        }
+
+        // Find the matching nodes and make a data set for each of them
+        let mut query_data_sets = Vec::new();
+        let mut matched_nodes = HashSet::new();
+        for node_type in node_types {
+            let type_definition_id = &node_type.type_definition_node.node_id;
+            Self::instances_of(
+                &address_space,
+                type_definition_id,
+                node_type.include_sub_types,
+            )
+            .into_iter()
+            .filter(|(node_id, _)| {
+                view_nodes
+                    .as_ref()
+                    .map(|view_nodes| view_nodes.contains(node_id))
+                    .unwrap_or(true)
+            })
+            .filter(|(node_id, _)| {
+                matches!(
+                    event_filter::evaluate_where_clause(node_id, &request.filter, &address_space),
+                    Ok(Variant::Boolean(true))
+                )
+            })
+            .for_each(|(node_id, type_definition_id)| {
+                if matched_nodes.insert(node_id.clone()) {
+                    let values = node_type.data_to_return.as_ref().map(|data_to_return| {
+                        data_to_return
+                            .iter()
+                            .map(|d| Self::value_of(&address_space, &node_id, d))
+                            .collect()
+                    });
+                    query_data_sets.push(QueryDataSet {
+                        node_id: ExpandedNodeId::new(node_id),
+                        type_definition_node: ExpandedNodeId::new(type_definition_id),
+                        values,
+                    });
+                }
+            });
+        }
+
+        let (query_data_sets, continuation_point) = match Self::query_data_sets_to_return(
+            &mut session,
+            &address_space,
+            query_data_sets,
+            request.max_data_sets_to_return as usize,
+        ) {
+            Ok(result) => result,
+            Err(status_code) => return self.service_fault(&request.request_header, status_code),
+        };
+        QueryFirstResponse {
+            response_header: ResponseHeader::new_good(&request.request_header),
+            query_data_sets: Some(query_data_sets),
+            continuation_point,
+            parsing_results: None,
+            diagnostic_infos: None,
+            filter_result,
+        }
+        .into()
     }
 
     pub fn query_next(
         &self,
         _server_state: Arc<RwLock<ServerState>>,
-        _session: Arc<RwLock<Session>>,
-        _address_space: Arc<RwLock<AddressSpace>>,
+        session: Arc<RwLock<Session>>,
+        address_space: Arc<RwLock<AddressSpace>>,
         request: &QueryNextRequest,
     ) -> SupportedMessage {
-        self.service_fault(&request.request_header, StatusCode::BadNotSupported)
+        let mut session = trace_write_lock!(session);
+        let address_space = trace_read_lock!(address_space);
+        if request.release_continuation_point {
+            let status_code = session.release_query_continuation_point(&request.continuation_point);
+            return if status_code.is_good() {
+                QueryNextResponse {
+                    response_header: ResponseHeader::new_good(&request.request_header),
+                    query_data_sets: None,
+                    revised_continuation_point: ByteString::null(),
+                }
+                .into()
+            } else {
+                self.service_fault(&request.request_header, status_code)
+            };
+        }
+        let continuation_point =
+            session.find_query_continuation_point(&request.continuation_point, &address_space);
+        let result = continuation_point.and_then(|continuation_point| {
+            Self::query_data_sets_to_return(
+                &mut session,
+                &address_space,
+                continuation_point.query_data_sets,
+                continuation_point.max_data_sets_to_return,
+            )
+        });
+        match result {
+            Ok((query_data_sets, revised_continuation_point)) => QueryNextResponse {
+                response_header: ResponseHeader::new_good(&request.request_header),
+                query_data_sets: Some(query_data_sets),
+                revised_continuation_point,
+            }
+            .into(),
+            Err(status_code) => {
+                error!(
+                    "Query continuation point {} was invalid",
+                    request.continuation_point.as_base64()
+                );
+                self.service_fault(&request.request_header, status_code)
+            }
+        }
+    }
+
+    /// Checks that the node type is an object or variable type and that the data to return for it
+    /// is valid.
+    fn parse_node_type(
+        address_space: &AddressSpace,
+        node_type: &NodeTypeDescription,
+    ) -> ParsingResult {
+        let type_definition_id = &node_type.type_definition_node.node_id;
+        let status_code = match address_space.find_node(type_definition_id) {
+            Some(node)
+                if matches!(
+                    node.node_class(),
+                    NodeClass::ObjectType | NodeClass::VariableType
+                ) =>
+            {
+                StatusCode::Good
+            }
+            _ => StatusCode::BadTypeDefinitionInvalid,
+        };
+        let data_status_codes = node_type.data_to_return.as_ref().map(|data_to_return| {
+            data_to_return
+                .iter()
+                .map(|d| {
+                    if AttributeId::from_u32(d.attribute_id).is_err() {
+                        StatusCode::BadAttributeIdInvalid
+                    } else if d.index_range.as_ref().parse::<NumericRange>().is_err() {
+                        StatusCode::BadIndexRangeInvalid
+                    } else {
+                        StatusCode::Good
+                    }
+                })
+                .collect()
+        });
+        ParsingResult {
+            status_code,
+            data_status_codes,
+            data_diagnostic_infos: None,
+        }
+    }
+
+    /// Finds the instances of the type, or of its subtypes, with the type definition of each.
+    fn instances_of(
+        address_space: &AddressSpace,
+        type_definition_id: &NodeId,
+        include_sub_types: bool,
+    ) -> Vec<(NodeId, NodeId)> {
+        let mut type_definition_ids = vec![type_definition_id.clone()];
+        if include_sub_types {
+            type_definition_ids.append(&mut address_space.find_subtypes(type_definition_id));
+        }
+        type_definition_ids
+            .into_iter()
+            .flat_map(|type_definition_id| {
+                address_space
+                    .find_inverse_references(
+                        &type_definition_id,
+                        Some((ReferenceTypeId::HasTypeDefinition, false)),
+                    )
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |r| (r.target_node, type_definition_id.clone()))
+            })
+            .collect()
+    }
+
+    /// Reads the attribute of the node, or of the node found through the relative path from it.
+    fn value_of(
+        address_space: &AddressSpace,
+        node_id: &NodeId,
+        data_to_return: &QueryDataDescription,
+    ) -> Variant {
+        let target_node_id = if is_empty_option_vec!(data_to_return.relative_path.elements) {
+            Some(node_id.clone())
+        } else {
+            relative_path::find_nodes_relative_path(
+                address_space,
+                node_id,
+                &data_to_return.relative_path,
+            )
+            .ok()
+            .and_then(|node_ids| node_ids.into_iter().next())
+        };
+        let attribute_id = AttributeId::from_u32(data_to_return.attribute_id).ok();
+        let index_range = data_to_return
+            .index_range
+            .as_ref()
+            .parse::<NumericRange>()
+            .ok();
+        match (target_node_id, attribute_id, index_range) {
+            (Some(target_node_id), Some(attribute_id), Some(index_range)) => address_space
+                .find_node(&target_node_id)
+                .and_then(|node| {
+                    node.as_node().get_attribute(
+                        TimestampsToReturn::Neither,
+                        attribute_id,
+                        index_range,
+                        &QualifiedName::null(),
+                    )
+                })
+                .and_then(|data_value| data_value.value)
+                .unwrap_or(Variant::Empty),
+            _ => Variant::Empty,
+        }
+    }
+
+    /// Returns as many data sets as the client asked for and a continuation point for the rest,
+    /// or `BadNoContinuationPoints` if the session has no continuation point to spare.
+    fn query_data_sets_to_return(
+        session: &mut Session,
+        address_space: &AddressSpace,
+        mut query_data_sets: Vec<QueryDataSet>,
+        max_data_sets_to_return: usize,
+    ) -> Result<(Vec<QueryDataSet>, ByteString), StatusCode> {
+        if max_data_sets_to_return > 0 && query_data_sets.len() > max_data_sets_to_return {
+            let remaining = query_data_sets.split_off(max_data_sets_to_return);
+            let continuation_point =
+                session.add_query_continuation_point(QueryContinuationPoint {
+                    address_space_last_modified: address_space.last_modified(),
+                    max_data_sets_to_return,
+                    query_data_sets: remaining,
+                })?;
+            Ok((query_data_sets, continuation_point))
+        } else {
+            Ok((query_data_sets, ByteString::null()))
+        }
     }
 }
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use crate::core::comms::secure_channel::SecureChannel;
 use crate::core::supported_message::SupportedMessage;
@@ -10,11 +10,13 @@ use crate::crypto::{self as crypto, random, CertificateStore, SecurityPolicy};
 use crate::sync::*;
 use crate::types::{status_code::StatusCode, *};
 
+#[cfg(feature = "events")]
+use crate::server::services::audit;
 use crate::server::{
     address_space::address_space::AddressSpace,
     constants,
     identity_token::IdentityToken,
-    services::{audit, Service},
+    services::Service,
     session::{Session, SessionManager},
     state::ServerState,
 };
@@ -39,6 +41,8 @@ impl SessionService {
         certificate_store: Arc<RwLock<CertificateStore>>,
         server_state: Arc<RwLock<ServerState>>,
         address_space: Arc<RwLock<AddressSpace>>,
+        client_address: Option<SocketAddr>,
+        session_count: usize,
         request: &CreateSessionRequest,
     ) -> (Option<Session>, SupportedMessage) {
         let mut session = Session::new(server_state.clone());
@@ -48,6 +52,9 @@ impl SessionService {
 
         let endpoints = server_state.new_endpoint_descriptions(request.endpoint_url.as_ref());
 
+        let too_many_sessions =
+            server_state.max_sessions > 0 && session_count >= server_state.max_sessions;
+
         // Check the args
         let service_result = {
             // Validate the endpoint url
@@ -60,6 +67,12 @@ impl SessionService {
                 if endpoints.is_none() {
                     error!("Create session cannot find matching endpoints");
                     StatusCode::BadTcpEndpointUrlInvalid
+                } else if too_many_sessions {
+                    error!(
+                        "Create session rejected, the server already has {} sessions",
+                        server_state.max_sessions
+                    );
+                    StatusCode::BadTooManySessions
                 } else {
                     StatusCode::Good
                 }
@@ -67,8 +80,11 @@ impl SessionService {
         };
         if service_result.is_bad() {
             // Rejected
-            let mut diagnostics = trace_write_lock!(server_state.diagnostics);
-            diagnostics.on_rejected_session();
+            #[cfg(feature = "diagnostics")]
+            {
+                let mut diagnostics = trace_write_lock!(server_state.diagnostics);
+                diagnostics.on_rejected_session();
+            }
             (
                 None,
                 self.service_fault(&request.request_header, service_result),
@@ -76,9 +92,13 @@ impl SessionService {
         } else {
             let endpoints = endpoints.unwrap();
 
-            // Extract the client certificate if one is supplied
-            let client_certificate =
-                crypto::X509::from_byte_string(&request.client_certificate).ok();
+            // Extract the client certificate if one is supplied, followed by the certificates of
+            // the CAs that issued it
+            let client_certificate_chain =
+                crypto::X509::chain_from_byte_string(&request.client_certificate).ok();
+            let client_certificate = client_certificate_chain
+                .as_ref()
+                .and_then(|client_certificate_chain| client_certificate_chain.first().cloned());
 
             // Check the client's certificate for validity and acceptance
             let security_policy = {
@@ -87,12 +107,15 @@ impl SessionService {
             };
             let service_result = if security_policy != SecurityPolicy::None {
                 let certificate_store = trace_read_lock!(certificate_store);
-                let result = if let Some(ref client_certificate) = client_certificate {
-                    certificate_store.validate_or_reject_application_instance_cert(
-                        client_certificate,
+                let result = if let Some(ref client_certificate_chain) = client_certificate_chain {
+                    let client_address =
+                        client_address.map(|client_address| client_address.to_string());
+                    certificate_store.validate_or_reject_application_instance_cert_chain(
+                        client_certificate_chain,
                         security_policy,
                         None,
                         None,
+                        client_address.as_deref(),
                     )
                 } else {
                     warn!("Certificate supplied by client is invalid");
@@ -100,16 +123,21 @@ impl SessionService {
                 };
                 if result.is_bad() {
                     // Log an error
+                    #[cfg(feature = "events")]
                     audit::log_certificate_error(
                         &server_state,
                         address_space.clone(),
                         result,
+                        &request.client_certificate,
                         &request.request_header,
                     );
 
                     // Rejected for security reasons
-                    let mut diagnostics = trace_write_lock!(server_state.diagnostics);
-                    diagnostics.on_rejected_security_session();
+                    #[cfg(feature = "diagnostics")]
+                    {
+                        let mut diagnostics = trace_write_lock!(server_state.diagnostics);
+                        diagnostics.on_rejected_security_session();
+                    }
                 }
                 result
             } else {
@@ -118,6 +146,7 @@ impl SessionService {
 
             let secure_channel = trace_read_lock!(secure_channel);
             if service_result.is_bad() {
+                #[cfg(feature = "events")]
                 audit::log_create_session(
                     &server_state,
                     &secure_channel,
@@ -153,7 +182,7 @@ impl SessionService {
                 };
 
                 let authentication_token = NodeId::new(0, random::byte_string(32));
-                let server_nonce = security_policy.random_nonce();
+                let server_nonce = Self::new_session_nonce(security_policy);
                 let server_certificate = server_state.server_certificate_as_byte_string();
                 let server_endpoints = Some(endpoints);
 
@@ -169,6 +198,7 @@ impl SessionService {
                 session.set_session_nonce(server_nonce.clone());
                 session.set_session_name(request.session_name.clone());
 
+                #[cfg(feature = "events")]
                 audit::log_create_session(
                     &server_state,
                     &secure_channel,
@@ -222,7 +252,7 @@ impl SessionService {
             )
         };
 
-        let server_nonce = security_policy.random_nonce();
+        let server_nonce = Self::new_session_nonce(security_policy);
         let mut service_result = if !server_state.endpoint_exists(
             endpoint_url,
             security_policy,
@@ -248,8 +278,9 @@ impl SessionService {
             StatusCode::Good
         };
 
+        let mut user_token_id = None;
         if service_result.is_good() {
-            if let Err(err) = server_state.authenticate_endpoint(
+            match server_state.authenticate_endpoint(
                 request,
                 endpoint_url,
                 security_policy,
@@ -257,8 +288,11 @@ impl SessionService {
                 &request.user_identity_token,
                 session.session_nonce(),
             ) {
-                error!("activate_session, invalid endpoint");
-                service_result = err;
+                Ok(id) => user_token_id = Some(id),
+                Err(err) => {
+                    error!("activate_session, invalid endpoint");
+                    service_result = err;
+                }
             }
         }
 
@@ -296,10 +330,12 @@ impl SessionService {
                 &request.user_identity_token,
                 &server_state.decoding_options(),
             ));
+            session.set_user_token_id(user_token_id);
             session.set_locale_ids(request.locale_ids.clone());
 
             let diagnostic_infos = None;
 
+            #[cfg(feature = "events")]
             {
                 let secure_channel = trace_read_lock!(secure_channel);
                 audit::log_activate_session(
@@ -311,6 +347,9 @@ impl SessionService {
                     request,
                 );
             }
+            // The address space is only needed to raise the audit event
+            #[cfg(not(feature = "events"))]
+            let _ = address_space;
 
             ActivateSessionResponse {
                 response_header: ResponseHeader::new_good(&request.request_header),
@@ -351,6 +390,12 @@ impl SessionService {
                 let secure_channel = trace_read_lock!(secure_channel);
                 secure_channel.secure_channel_id()
             };
+            let park_subscriptions = {
+                let config = trace_read_lock!(server_state.config);
+                !config.limits.delete_orphaned_subscriptions
+            };
+            let mut client_user_id = UAString::null();
+            let mut orphaned_subscriptions = Vec::new();
 
             {
                 let mut session = trace_write_lock!(session);
@@ -367,14 +412,34 @@ impl SessionService {
                     );
                 }
 
+                // Subscriptions that the client does not delete are kept for transfer, and
+                // durable ones are kept even if orphaned subscriptions are deleted
+                if !request.delete_subscriptions {
+                    client_user_id = session.client_user_id();
+                    orphaned_subscriptions = session.subscriptions_mut().take_subscriptions();
+                    orphaned_subscriptions
+                        .retain(|subscription| park_subscriptions || subscription.is_durable());
+                }
+
                 session.set_authentication_token(NodeId::null());
                 session.set_user_identity(IdentityToken::None);
+                session.set_user_token_id(None);
                 session.set_activated(false);
+                #[cfg(feature = "events")]
                 audit::log_close_session(&server_state, &session, address_space, true, request);
+                #[cfg(not(feature = "events"))]
+                let _ = address_space;
             }
 
             {
                 let mut session_manager = trace_write_lock!(session_manager);
+                if !orphaned_subscriptions.is_empty() {
+                    let _ = session_manager.park_subscriptions(
+                        orphaned_subscriptions,
+                        client_user_id,
+                        &clock::now(),
+                    );
+                }
                 session_manager.deregister_session(session);
             }
 
@@ -394,17 +459,29 @@ impl SessionService {
     pub fn cancel(
         &self,
         _server_state: Arc<RwLock<ServerState>>,
-        _session: Arc<RwLock<Session>>,
+        session: Arc<RwLock<Session>>,
         request: &CancelRequest,
     ) -> SupportedMessage {
-        // This service call currently does nothing
+        // Other service calls are answered as soon as they are processed so only queued publish
+        // requests can be cancelled
+        let mut session = trace_write_lock!(session);
+        let cancel_count = session
+            .subscriptions_mut()
+            .cancel_publish_requests(request.request_handle);
         CancelResponse {
             response_header: ResponseHeader::new_good(&request.request_header),
-            cancel_count: 0,
+            cancel_count,
         }
         .into()
     }
 
+    /// Makes the nonce that the client signs, and encrypts its password with, on the next
+    /// activation. It is random even when the secure channel has no security, so that an
+    /// encrypted password cannot be replayed.
+    fn new_session_nonce(security_policy: SecurityPolicy) -> ByteString {
+        random::byte_string(security_policy.secure_channel_nonce_length())
+    }
+
     /// Verifies that the supplied client signature was produced by the session's client certificate
     /// from the server's certificate and nonce.
     fn verify_client_signature(
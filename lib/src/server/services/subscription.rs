@@ -9,7 +9,10 @@ use crate::sync::*;
 use crate::types::{status_code::StatusCode, *};
 
 use crate::server::{
-    address_space::AddressSpace, services::Service, session::Session, state::ServerState,
+    address_space::AddressSpace,
+    services::Service,
+    session::{Session, SessionManager},
+    state::ServerState,
     subscriptions::subscription::Subscription,
 };
 
@@ -59,6 +62,7 @@ impl SubscriptionService {
             // Create a new subscription
             let publishing_enabled = request.publishing_enabled;
             let subscription = Subscription::new(
+                #[cfg(feature = "diagnostics")]
                 server_state.diagnostics.clone(),
                 subscription_id,
                 publishing_enabled,
@@ -66,6 +70,7 @@ impl SubscriptionService {
                 revised_lifetime_count,
                 revised_max_keep_alive_count,
                 request.priority,
+                server_state.max_monitored_items_per_sub,
             );
             subscriptions.insert(subscription_id, subscription);
 
@@ -111,6 +116,10 @@ impl SubscriptionService {
                 );
 
             subscription.set_publishing_interval(revised_publishing_interval);
+            // A durable subscription keeps its lifetime in hours at the new publishing interval
+            let revised_lifetime_count = subscription
+                .durable_lifetime_count()
+                .unwrap_or(revised_lifetime_count);
             subscription.set_max_keep_alive_count(revised_max_keep_alive_count);
             subscription.set_max_lifetime_count(revised_lifetime_count);
             subscription.set_priority(request.priority);
@@ -164,10 +173,17 @@ impl SubscriptionService {
         }
     }
 
-    /// Handles a TransferSubscriptionsRequest
+    /// Handles a TransferSubscriptionsRequest. Subscriptions can be transferred from sessions
+    /// that have timed out or were closed without deleting them, or from another live session,
+    /// to a session of the same user. A live session is sent a status change notification that
+    /// the subscription was transferred. The notifications that were waiting to be acknowledged
+    /// on the old session are not carried over, so no sequence numbers are available for
+    /// republishing.
     pub fn transfer_subscriptions(
         &self,
-        _session: Arc<RwLock<Session>>,
+        now: &DateTimeUtc,
+        session_manager: Arc<RwLock<SessionManager>>,
+        session: Arc<RwLock<Session>>,
         request: &TransferSubscriptionsRequest,
     ) -> SupportedMessage {
         if is_empty_option_vec!(request.subscription_ids) {
@@ -175,13 +191,54 @@ impl SubscriptionService {
         } else {
             let subscription_ids = request.subscription_ids.as_ref().unwrap();
             let results = {
-                // TODO this is a stub. The real thing should look up subscriptions belonging to
-                //  other sessions and transfer them across to this one.
+                // The session manager is locked before the session, as the session reaper does
+                let mut session_manager = trace_write_lock!(session_manager);
+                let mut session = trace_write_lock!(session);
+                let client_user_id = session.client_user_id();
+                let session_id = session.session_id().clone();
                 let results = subscription_ids
                     .iter()
-                    .map(|_subscription_id| TransferResult {
-                        status_code: StatusCode::BadSubscriptionIdInvalid,
-                        available_sequence_numbers: None,
+                    .map(|subscription_id| {
+                        let status_code = if session.subscriptions().contains(*subscription_id) {
+                            StatusCode::Good
+                        } else if session.subscriptions().is_full() {
+                            StatusCode::BadTooManySubscriptions
+                        } else {
+                            let subscription = match session_manager
+                                .unpark_subscription(*subscription_id, &client_user_id)
+                            {
+                                Err(StatusCode::BadSubscriptionIdInvalid) => session_manager
+                                    .take_live_subscription(
+                                        *subscription_id,
+                                        &session_id,
+                                        &client_user_id,
+                                        now,
+                                    ),
+                                result => result,
+                            };
+                            match subscription {
+                                Ok(mut subscription) => {
+                                    debug!(
+                                        "Subscription {} is transferred to session {}",
+                                        subscription_id,
+                                        session.session_id()
+                                    );
+                                    if request.send_initial_values {
+                                        subscription.set_resend_data();
+                                    }
+                                    subscription.reset_lifetime_counter();
+                                    session
+                                        .subscriptions_mut()
+                                        .insert(*subscription_id, subscription);
+                                    StatusCode::Good
+                                }
+                                Err(status_code) => status_code,
+                            }
+                        };
+                        TransferResult {
+                            status_code,
+                            available_sequence_numbers: None,
+                        }
                     })
                     .collect::<Vec<TransferResult>>();
                 Some(results)
@@ -244,7 +301,7 @@ impl SubscriptionService {
     ) -> Option<SupportedMessage> {
         trace!("--> Receive a PublishRequest {:?}", request);
         let mut session = trace_write_lock!(session);
-        if session.subscriptions().is_empty() {
+        if session.subscriptions().is_empty() && !session.subscriptions().has_status_changes() {
             Some(self.service_fault(&request.request_header, StatusCode::BadNoSubscription))
         } else {
             let address_space = trace_read_lock!(address_space);
@@ -2,11 +2,11 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
+use std::collections::HashSet;
 use std::result::Result;
 use std::sync::Arc;
 
 use crate::core::supported_message::SupportedMessage;
-use crate::crypto::random;
 use crate::sync::*;
 use crate::types::{node_ids::ReferenceTypeId, status_code::StatusCode, *};
 
@@ -17,6 +17,36 @@ use crate::server::{
     session::Session,
     state::ServerState,
 };
+/// Returns the nodes in the view that a request asks to be restricted to, or `None` if the request
+/// is for the whole address space. Views have no history so a view can only be given by its
+/// current version.
+pub(crate) fn nodes_in_view(
+    address_space: &AddressSpace,
+    view: &ViewDescription,
+) -> Result<Option<HashSet<NodeId>>, StatusCode> {
+    if view.view_id.is_null() {
+        if !view.timestamp.is_null() || view.view_version != 0 {
+            Err(StatusCode::BadViewIdUnknown)
+        } else {
+            Ok(None)
+        }
+    } else if let Some(nodes) = address_space.nodes_in_view(&view.view_id) {
+        if !view.timestamp.is_null() && view.view_version != 0 {
+            Err(StatusCode::BadViewParameterMismatch)
+        } else if !view.timestamp.is_null() {
+            Err(StatusCode::BadViewTimestampInvalid)
+        } else if view.view_version != 0
+            && address_space.view_version(&view.view_id) != Some(view.view_version)
+        {
+            Err(StatusCode::BadViewVersionInvalid)
+        } else {
+            Ok(Some(nodes))
+        }
+    } else {
+        Err(StatusCode::BadViewIdUnknown)
+    }
+}
+
 /// The view service. Allows the client to browse the address space of the server.
 pub(crate) struct ViewService;
 
@@ -45,50 +75,59 @@ impl ViewService {
             let mut session = trace_write_lock!(session);
             let address_space = trace_read_lock!(address_space);
 
-            let view = &request.view;
-            if !view.view_id.is_null() || !view.timestamp.is_null() {
-                // Views are not supported
-                info!("Browse request ignored because view was specified (views not supported)");
-                self.service_fault(&request.request_header, StatusCode::BadViewIdUnknown)
-            } else {
-                // debug!("Browse request = {:#?}", request);
-                let nodes_to_browse = request.nodes_to_browse.as_ref().unwrap();
-                if nodes_to_browse.len() <= server_state.operational_limits.max_nodes_per_browse {
-                    // Max references per node. This should be server configurable but the constant
-                    // is generous. TODO this value needs to adapt for the max message size
-                    const DEFAULT_MAX_REFERENCES_PER_NODE: u32 = 255;
-                    let max_references_per_node = if request.requested_max_references_per_node == 0
-                    {
-                        // Client imposes no limit
-                        DEFAULT_MAX_REFERENCES_PER_NODE
-                    } else if request.requested_max_references_per_node
-                        > DEFAULT_MAX_REFERENCES_PER_NODE
+            match nodes_in_view(&address_space, &request.view) {
+                Err(err) => {
+                    info!(
+                        "Browse request ignored because view {:?} is invalid, {}",
+                        request.view, err
+                    );
+                    self.service_fault(&request.request_header, err)
+                }
+                Ok(view_nodes) => {
+                    // debug!("Browse request = {:#?}", request);
+                    let nodes_to_browse = request.nodes_to_browse.as_ref().unwrap();
+                    if nodes_to_browse.len() <= server_state.operational_limits.max_nodes_per_browse
                     {
-                        // Client limit exceeds default
-                        DEFAULT_MAX_REFERENCES_PER_NODE
+                        // Max references per node. This should be server configurable but the constant
+                        // is generous. TODO this value needs to adapt for the max message size
+                        const DEFAULT_MAX_REFERENCES_PER_NODE: u32 = 255;
+                        let max_references_per_node =
+                            if request.requested_max_references_per_node == 0 {
+                                // Client imposes no limit
+                                DEFAULT_MAX_REFERENCES_PER_NODE
+                            } else if request.requested_max_references_per_node
+                                > DEFAULT_MAX_REFERENCES_PER_NODE
+                            {
+                                // Client limit exceeds default
+                                DEFAULT_MAX_REFERENCES_PER_NODE
+                            } else {
+                                request.requested_max_references_per_node
+                            };
+                        // Browse the nodes
+                        let results = Some(Self::browse_nodes(
+                            &mut session,
+                            &address_space,
+                            view_nodes.as_ref(),
+                            nodes_to_browse,
+                            max_references_per_node as usize,
+                        ));
+                        let diagnostic_infos = None;
+                        BrowseResponse {
+                            response_header: ResponseHeader::new_good(&request.request_header),
+                            results,
+                            diagnostic_infos,
+                        }
+                        .into()
                     } else {
-                        request.requested_max_references_per_node
-                    };
-                    // Browse the nodes
-                    let results = Some(Self::browse_nodes(
-                        &mut session,
-                        &address_space,
-                        nodes_to_browse,
-                        max_references_per_node as usize,
-                    ));
-                    let diagnostic_infos = None;
-                    BrowseResponse {
-                        response_header: ResponseHeader::new_good(&request.request_header),
-                        results,
-                        diagnostic_infos,
+                        error!(
+                            "Browse request too many nodes to browse {}",
+                            nodes_to_browse.len()
+                        );
+                        self.service_fault(
+                            &request.request_header,
+                            StatusCode::BadTooManyOperations,
+                        )
                     }
-                    .into()
-                } else {
-                    error!(
-                        "Browse request too many nodes to browse {}",
-                        nodes_to_browse.len()
-                    );
-                    self.service_fault(&request.request_header, StatusCode::BadTooManyOperations)
                 }
             }
         }
@@ -108,8 +147,16 @@ impl ViewService {
 
             let continuation_points = request.continuation_points.as_ref().unwrap();
             let results = if request.release_continuation_points {
-                session.remove_browse_continuation_points(continuation_points);
-                None
+                // Each released continuation point has a result with no references
+                let results = continuation_points
+                    .iter()
+                    .map(|continuation_point| BrowseResult {
+                        status_code: session.release_browse_continuation_point(continuation_point),
+                        continuation_point: ByteString::null(),
+                        references: None,
+                    })
+                    .collect();
+                Some(results)
             } else {
                 // Iterate from the continuation point, assuming it is valid
                 session.remove_expired_browse_continuation_points(&address_space);
@@ -304,6 +351,7 @@ impl ViewService {
     fn browse_nodes(
         session: &mut Session,
         address_space: &AddressSpace,
+        view_nodes: Option<&HashSet<NodeId>>,
         nodes_to_browse: &[BrowseDescription],
         max_references_per_node: usize,
     ) -> Vec<BrowseResult> {
@@ -313,6 +361,7 @@ impl ViewService {
                 match Self::browse_node(
                     session,
                     address_space,
+                    view_nodes,
                     0,
                     node_to_browse,
                     max_references_per_node,
@@ -331,6 +380,7 @@ impl ViewService {
     fn browse_node(
         session: &mut Session,
         address_space: &AddressSpace,
+        view_nodes: Option<&HashSet<NodeId>>,
         starting_index: usize,
         node_to_browse: &BrowseDescription,
         max_references_per_node: usize,
@@ -339,18 +389,34 @@ impl ViewService {
         if node_to_browse.node_id.is_null() || !address_space.node_exists(&node_to_browse.node_id) {
             return Err(StatusCode::BadNodeIdUnknown);
         }
+        // A browse within a view must start from a node in the view
+        if let Some(view_nodes) = view_nodes {
+            if !view_nodes.contains(&node_to_browse.node_id) {
+                return Err(StatusCode::BadNodeNotInView);
+            }
+        }
 
         //debug!("Node to browse = {:?}", node_to_browse);
 
-        // Request may wish to filter by a kind of reference
+        if node_to_browse.browse_direction == BrowseDirection::Invalid {
+            return Err(StatusCode::BadBrowseDirectionInvalid);
+        }
+
+        // Request may wish to filter by a kind of reference. The reference type may be any
+        // reference type in the address space, not just the standard ones.
         let reference_type_id = if node_to_browse.reference_type_id.is_null() {
             None
-        } else if let Ok(reference_type_id) =
-            node_to_browse.reference_type_id.as_reference_type_id()
+        } else if address_space
+            .find_node(&node_to_browse.reference_type_id)
+            .map(|node| node.node_class() == NodeClass::ReferenceType)
+            .unwrap_or(false)
         {
-            Some((reference_type_id, node_to_browse.include_subtypes))
+            Some((
+                node_to_browse.reference_type_id.clone(),
+                node_to_browse.include_subtypes,
+            ))
         } else {
-            None
+            return Err(StatusCode::BadReferenceTypeIdInvalid);
         };
 
         // Fetch the references to / from the given node to browse
@@ -376,6 +442,12 @@ impl ViewService {
             if target_node_id.is_null() {
                 continue;
             }
+            // Skip target nodes outside of the view
+            if let Some(view_nodes) = view_nodes {
+                if !view_nodes.contains(&target_node_id) {
+                    continue;
+                }
+            }
             let target_node = address_space.find_node(&target_node_id);
             if target_node.is_none() {
                 continue;
@@ -392,7 +464,8 @@ impl ViewService {
                 }
             }
 
-            // Prepare the values to put into the struct according to the result mask
+            // Prepare the values to put into the struct according to the result mask. Fields that
+            // are not asked for are left as their defaults and are not looked up at all.
             let reference_type_id =
                 if result_mask.contains(BrowseDescriptionResultMask::RESULT_MASK_REFERENCE_TYPE) {
                     reference.reference_type.clone()
@@ -403,10 +476,9 @@ impl ViewService {
                 if result_mask.contains(BrowseDescriptionResultMask::RESULT_MASK_IS_FORWARD) {
                     idx < inverse_ref_idx
                 } else {
-                    true
+                    false
                 };
-
-            let target_node_class =
+            let node_class =
                 if result_mask.contains(BrowseDescriptionResultMask::RESULT_MASK_NODE_CLASS) {
                     target_node_class
                 } else {
@@ -420,7 +492,16 @@ impl ViewService {
                 };
             let display_name =
                 if result_mask.contains(BrowseDescriptionResultMask::RESULT_MASK_DISPLAY_NAME) {
-                    target_node.display_name().clone()
+                    let display_name = target_node.display_name();
+                    session
+                        .locale_ids()
+                        .as_ref()
+                        .and_then(|locale_ids| {
+                            address_space
+                                .localization()
+                                .localize(&display_name, locale_ids)
+                        })
+                        .unwrap_or(display_name)
                 } else {
                     LocalizedText::null()
                 };
@@ -430,17 +511,14 @@ impl ViewService {
                     // for the NodeClasses Object and Variable. For all other NodeClasses a null NodeId
                     // shall be returned.
                     match target_node_class {
-                        NodeClass::Object | NodeClass::Variable => {
-                            let type_defs = address_space.find_references(
-                                &target_node.node_id(),
+                        NodeClass::Object | NodeClass::Variable => address_space
+                            .find_references(
+                                &target_node_id,
                                 Some((ReferenceTypeId::HasTypeDefinition, false)),
-                            );
-                            if let Some(type_defs) = type_defs {
-                                ExpandedNodeId::new(type_defs[0].target_node.clone())
-                            } else {
-                                ExpandedNodeId::null()
-                            }
-                        }
+                            )
+                            .and_then(|type_defs| type_defs.into_iter().next())
+                            .map(|type_def| ExpandedNodeId::new(type_def.target_node))
+                            .unwrap_or_else(ExpandedNodeId::null),
                         _ => ExpandedNodeId::null(),
                     }
                 } else {
@@ -451,7 +529,7 @@ impl ViewService {
                 node_id: ExpandedNodeId::new(target_node_id),
                 reference_type_id,
                 is_forward,
-                node_class: target_node_class,
+                node_class,
                 browse_name,
                 display_name,
                 type_definition,
@@ -474,31 +552,34 @@ impl ViewService {
         continuation_point: &ByteString,
     ) -> BrowseResult {
         // Find the continuation point in the session
-        if let Some(continuation_point) = session.find_browse_continuation_point(continuation_point)
-        {
-            debug!(
-                "Browsing from continuation point {}",
-                continuation_point.id.as_base64()
-            );
-            let reference_descriptions = continuation_point.reference_descriptions.lock();
-            // Use the existing result. This may result in another continuation point being created
-            Self::reference_description_to_browse_result(
-                session,
-                address_space,
-                &reference_descriptions,
-                continuation_point.starting_index,
-                continuation_point.max_references_per_node,
-            )
-        } else {
-            // Not valid or missing
-            error!(
-                "Continuation point {} was invalid",
-                continuation_point.as_base64()
-            );
-            BrowseResult {
-                status_code: StatusCode::BadContinuationPointInvalid,
-                continuation_point: ByteString::null(),
-                references: None,
+        match session.find_browse_continuation_point(continuation_point) {
+            Ok(browse_continuation_point) => {
+                debug!(
+                    "Browsing from continuation point {}",
+                    continuation_point.as_base64()
+                );
+                let continuation_point = browse_continuation_point;
+                let reference_descriptions = continuation_point.reference_descriptions.lock();
+                // Use the existing result. This may result in another continuation point being created
+                Self::reference_description_to_browse_result(
+                    session,
+                    address_space,
+                    &reference_descriptions,
+                    continuation_point.starting_index,
+                    continuation_point.max_references_per_node,
+                )
+            }
+            Err(status_code) => {
+                // Not valid, expired or missing
+                error!(
+                    "Continuation point {} was invalid",
+                    continuation_point.as_base64()
+                );
+                BrowseResult {
+                    status_code,
+                    continuation_point: ByteString::null(),
+                    references: None,
+                }
             }
         }
     }
@@ -524,18 +605,27 @@ impl ViewService {
             //  be factored to allow for that
 
             // Create a continuation point for the remainder of the result. The point will hold the entire result
-            let continuation_point = random::byte_string(6);
+            let continuation_point =
+                session.add_browse_continuation_point(BrowseContinuationPoint {
+                    address_space_last_modified: address_space.last_modified(),
+                    max_references_per_node,
+                    starting_index: next_starting_index,
+                    reference_descriptions: Arc::new(Mutex::new(reference_descriptions.to_vec())),
+                });
+            let continuation_point = match continuation_point {
+                Ok(continuation_point) => continuation_point,
+                Err(status_code) => {
+                    // The client must release continuation points before browsing this node
+                    return BrowseResult {
+                        status_code,
+                        continuation_point: ByteString::null(),
+                        references: None,
+                    };
+                }
+            };
 
             debug!("References remaining {} exceeds max references {}, returning range {}..{} and creating new continuation point {}", references_remaining, max_references_per_node, starting_index, next_starting_index, continuation_point.as_base64());
 
-            session.add_browse_continuation_point(BrowseContinuationPoint {
-                id: continuation_point.clone(),
-                address_space_last_modified: address_space.last_modified(),
-                max_references_per_node,
-                starting_index: next_starting_index,
-                reference_descriptions: Arc::new(Mutex::new(reference_descriptions.to_vec())),
-            });
-
             (reference_descriptions_slice, continuation_point)
         } else {
             // Returns the remainder of the results
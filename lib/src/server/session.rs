@@ -2,29 +2,31 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::HashMap,
     sync::{
         atomic::{AtomicI32, Ordering},
         Arc,
     },
 };
 
-use chrono::Utc;
-
+use crate::core::record_metric;
 use crate::crypto::X509;
 use crate::sync::*;
 use crate::types::{service_types::PublishRequest, status_code::StatusCode, *};
 
 use crate::server::{
     address_space::{AddressSpace, UserAccessLevel},
-    continuation_point::BrowseContinuationPoint,
-    diagnostics::ServerDiagnostics,
+    continuation_point::{
+        BrowseContinuationPoint, ContinuationPoints, HistoryContinuationPoint,
+        QueryContinuationPoint,
+    },
     identity_token::IdentityToken,
-    session_diagnostics::SessionDiagnostics,
     state::ServerState,
-    subscriptions::subscription::TickReason,
+    subscriptions::subscription::{Subscription, TickReason},
     subscriptions::subscriptions::Subscriptions,
 };
+#[cfg(feature = "diagnostics")]
+use crate::server::{diagnostics::ServerDiagnostics, session_diagnostics::SessionDiagnostics};
 
 /// Session info holds information about a session created by CreateSession service
 #[derive(Clone)]
@@ -51,21 +53,62 @@ pub enum ServerUserIdentityToken {
     Invalid(ExtensionObject),
 }
 
+/// The resources that were freed by a pass of the session reaper
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub(crate) struct ReapedResources {
+    /// Sessions that were closed because they timed out
+    pub sessions: usize,
+    /// Publish requests of the sessions that were answered with `BadSessionClosed`
+    pub publish_requests: usize,
+    /// Browse and query continuation points of the sessions that were released
+    pub continuation_points: usize,
+    /// Subscriptions that were deleted, those of the sessions unless they were parked and the
+    /// parked ones whose lifetime ended without them being transferred
+    pub subscriptions_deleted: usize,
+    /// Subscriptions of the sessions that were parked so that another session can transfer them
+    pub subscriptions_parked: usize,
+}
+
+impl ReapedResources {
+    /// Tests if nothing was reaped
+    pub fn is_empty(&self) -> bool {
+        *self == ReapedResources::default()
+    }
+}
+
+/// A subscription whose session has closed without deleting it, which another session of the
+/// same user can transfer until the lifetime of the subscription ends
+pub(crate) struct ParkedSubscription {
+    subscription: Subscription,
+    /// The user of the session that the subscription belonged to
+    client_user_id: UAString,
+    expires_at: DateTimeUtc,
+}
+
 pub struct SessionManager {
     pub sessions: HashMap<NodeId, Arc<RwLock<Session>>>,
     pub sessions_terminated: bool,
+    /// Subscriptions of closed sessions that can be transferred, keyed by subscription id
+    parked_subscriptions: HashMap<u32, ParkedSubscription>,
 }
 
 impl Default for SessionManager {
     fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl SessionManager {
+    /// Creates a session manager for up to `max_sessions` sessions, 0 for no limit. The room for
+    /// a limited number of sessions is allocated up front, so the map never grows.
+    pub fn new(max_sessions: usize) -> Self {
         Self {
-            sessions: HashMap::new(),
+            sessions: HashMap::with_capacity(max_sessions),
             sessions_terminated: false,
+            parked_subscriptions: HashMap::new(),
         }
     }
-}
 
-impl SessionManager {
     pub fn len(&self) -> usize {
         self.sessions.len()
     }
@@ -83,10 +126,9 @@ impl SessionManager {
         for (_nodeid, session) in self.sessions.drain() {
             let mut session = trace_write_lock!(session);
             session.set_terminated();
-            let mut space = trace_write_lock!(address_space);
-            let diagnostics = trace_write_lock!(session.session_diagnostics);
-            diagnostics.deregister_session(&session, &mut space);
+            session.deregister_session(address_space.clone());
         }
+        self.parked_subscriptions.clear();
     }
 
     /// Find a session by its session id and return it.
@@ -146,6 +188,205 @@ impl SessionManager {
         self.sessions_terminated = self.sessions.is_empty();
         result
     }
+
+    /// Parks the subscriptions of a session that is closing without deleting them, so another
+    /// session of the same user can transfer them until their lifetime ends. Returns the number
+    /// of subscriptions parked.
+    pub(crate) fn park_subscriptions(
+        &mut self,
+        subscriptions: Vec<Subscription>,
+        client_user_id: UAString,
+        now: &DateTimeUtc,
+    ) -> usize {
+        Self::park(
+            &mut self.parked_subscriptions,
+            subscriptions,
+            client_user_id,
+            now,
+        )
+    }
+
+    fn park(
+        parked_subscriptions: &mut HashMap<u32, ParkedSubscription>,
+        subscriptions: Vec<Subscription>,
+        client_user_id: UAString,
+        now: &DateTimeUtc,
+    ) -> usize {
+        let park_count = subscriptions.len();
+        subscriptions.into_iter().for_each(|subscription| {
+            // The subscription lives for as many publishing intervals as its lifetime counter
+            // has left, as it would if its session were still there but not publishing
+            let lifetime_ms =
+                subscription.publishing_interval() * subscription.lifetime_counter() as f64;
+            let expires_at = *now + chrono::Duration::milliseconds(lifetime_ms as i64);
+            debug!(
+                "Parking subscription {} until {}",
+                subscription.subscription_id(),
+                expires_at
+            );
+            parked_subscriptions.insert(
+                subscription.subscription_id(),
+                ParkedSubscription {
+                    subscription,
+                    client_user_id: client_user_id.clone(),
+                    expires_at,
+                },
+            );
+        });
+        park_count
+    }
+
+    #[cfg(test)]
+    pub(crate) fn parked_subscriptions_len(&self) -> usize {
+        self.parked_subscriptions.len()
+    }
+
+    /// Takes a parked subscription out so it can be transferred to a session of the user. Fails
+    /// with `BadSubscriptionIdInvalid` if no subscription with the id is parked, or
+    /// `BadUserAccessDenied` if it belonged to a session of another user.
+    pub(crate) fn unpark_subscription(
+        &mut self,
+        subscription_id: u32,
+        client_user_id: &UAString,
+    ) -> Result<Subscription, StatusCode> {
+        match self.parked_subscriptions.get(&subscription_id) {
+            None => Err(StatusCode::BadSubscriptionIdInvalid),
+            Some(parked) if parked.client_user_id != *client_user_id => {
+                Err(StatusCode::BadUserAccessDenied)
+            }
+            Some(_) => Ok(self
+                .parked_subscriptions
+                .remove(&subscription_id)
+                .unwrap()
+                .subscription),
+        }
+    }
+
+    /// Takes a subscription out of another live session so it can be transferred to the session
+    /// with the supplied id. The old session is sent a status change notification to tell its
+    /// client that the subscription has gone. Fails with `BadSubscriptionIdInvalid` if no other
+    /// session has a subscription with the id, or `BadUserAccessDenied` if that session belongs
+    /// to another user.
+    ///
+    /// The caller must not hold a lock on any session other than the one it transfers to.
+    pub(crate) fn take_live_subscription(
+        &self,
+        subscription_id: u32,
+        session_id: &NodeId,
+        client_user_id: &UAString,
+        now: &DateTimeUtc,
+    ) -> Result<Subscription, StatusCode> {
+        let old_session = self
+            .sessions
+            .iter()
+            .filter(|(id, _)| *id != session_id)
+            .map(|(_, session)| session)
+            .find(|session| {
+                let session = trace_read_lock!(session);
+                !session.is_terminated() && session.subscriptions().contains(subscription_id)
+            })
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        let mut old_session = trace_write_lock!(old_session);
+        if old_session.client_user_id() != *client_user_id {
+            Err(StatusCode::BadUserAccessDenied)
+        } else {
+            old_session
+                .subscriptions_mut()
+                .transfer(subscription_id, now)
+                .ok_or(StatusCode::BadSubscriptionIdInvalid)
+        }
+    }
+
+    /// Closes the sessions that have timed out because their client has not made a service
+    /// request within the session timeout, and frees what they and earlier closed sessions held.
+    ///
+    /// The queued publish requests of a session are answered with `BadSessionClosed` and its
+    /// continuation points are released. Its subscriptions are parked for transfer, or deleted if
+    /// `park_subscriptions` is `false` and they are not durable. The session is terminated so that
+    /// no further requests can use it and is removed on the next pass, which gives its connection
+    /// time to send the publish responses. Parked subscriptions whose lifetime has ended are
+    /// deleted.
+    pub(crate) fn reap_sessions(
+        &mut self,
+        now: &DateTimeUtc,
+        address_space: &Arc<RwLock<AddressSpace>>,
+        park_subscriptions: bool,
+    ) -> ReapedResources {
+        let mut reaped = ReapedResources::default();
+
+        // Remove the sessions that were closed on the previous pass
+        let session_count = self.sessions.len();
+        self.sessions.retain(|_, session| {
+            let session = trace_read_lock!(session);
+            !session.is_terminated()
+        });
+        if self.sessions.len() < session_count {
+            self.sessions_terminated = self.sessions.is_empty();
+        }
+
+        let parked_count = self.parked_subscriptions.len();
+        self.parked_subscriptions.retain(|subscription_id, parked| {
+            let expired = parked.expires_at <= *now;
+            if expired {
+                debug!(
+                    "Parked subscription {} has expired without being transferred",
+                    subscription_id
+                );
+            }
+            !expired
+        });
+        reaped.subscriptions_deleted += parked_count - self.parked_subscriptions.len();
+
+        for session in self.sessions.values() {
+            let mut session = trace_write_lock!(session);
+            if !session.is_timed_out(now) {
+                continue;
+            }
+            info!(
+                "Session {} has timed out and is being closed",
+                session.session_id()
+            );
+            reaped.sessions += 1;
+            reaped.publish_requests += session
+                .subscriptions_mut()
+                .close_publish_requests(StatusCode::BadSessionClosed);
+            reaped.continuation_points += session.release_continuation_points();
+            // Durable subscriptions are parked even if orphaned subscriptions are deleted
+            let (subscriptions, deleted): (Vec<_>, Vec<_>) = session
+                .subscriptions_mut()
+                .take_subscriptions()
+                .into_iter()
+                .partition(|subscription| park_subscriptions || subscription.is_durable());
+            reaped.subscriptions_deleted += deleted.len();
+            reaped.subscriptions_parked += Self::park(
+                &mut self.parked_subscriptions,
+                subscriptions,
+                session.client_user_id(),
+                now,
+            );
+            session.set_authentication_token(NodeId::null());
+            session.set_activated(false);
+            session.set_terminated();
+            #[cfg(feature = "diagnostics")]
+            {
+                let mut diagnostics = trace_write_lock!(session.diagnostics);
+                diagnostics.on_session_timeout();
+            }
+            session.deregister_session(address_space.clone());
+        }
+
+        if !reaped.is_empty() {
+            info!("Session reaper freed {:?}", reaped);
+            record_metric!(on_resources_reaped(
+                reaped.sessions,
+                reaped.publish_requests,
+                reaped.continuation_points,
+                reaped.subscriptions_deleted,
+                reaped.subscriptions_parked
+            ));
+        }
+        reaped
+    }
 }
 
 /// The Session is any state maintained between the client and server
@@ -168,6 +409,9 @@ pub struct Session {
     session_timeout: f64,
     /// User identity token
     user_identity: IdentityToken,
+    /// The id of the server user token that authenticated the user identity, if the session is
+    /// activated
+    user_token_id: Option<String>,
     /// Session's preferred locale ids
     locale_ids: Option<Vec<UAString>>,
     /// Negotiated max request message size
@@ -176,13 +420,17 @@ pub struct Session {
     max_response_message_size: u32,
     /// Endpoint url for this session
     endpoint_url: UAString,
-    /// Maximum number of continuation points
-    max_browse_continuation_points: usize,
-    /// Browse continuation points (oldest to newest)
-    browse_continuation_points: VecDeque<BrowseContinuationPoint>,
+    /// Browse continuation points
+    browse_continuation_points: ContinuationPoints<BrowseContinuationPoint>,
+    /// Query continuation points
+    query_continuation_points: ContinuationPoints<QueryContinuationPoint>,
+    /// History read continuation points
+    history_continuation_points: ContinuationPoints<HistoryContinuationPoint>,
     /// Diagnostics associated with the server
+    #[cfg(feature = "diagnostics")]
     diagnostics: Arc<RwLock<ServerDiagnostics>>,
     /// Diagnostics associated with the session
+    #[cfg(feature = "diagnostics")]
     session_diagnostics: Arc<RwLock<SessionDiagnostics>>,
     /// Indicates if the session has received an ActivateSession
     activated: bool,
@@ -194,6 +442,7 @@ pub struct Session {
     terminated: bool,
     /// Flag indicating broadly if this session may modify the address space by adding or removing
     /// nodes or references to nodes.
+    #[cfg(feature = "node-management")]
     can_modify_address_space: bool,
     /// Timestamp of the last service request to have happened (only counts service requests while there is a session)
     last_service_request_timestamp: DateTimeUtc,
@@ -201,6 +450,7 @@ pub struct Session {
     subscriptions: Subscriptions,
 }
 
+#[cfg(feature = "diagnostics")]
 impl Drop for Session {
     fn drop(&mut self) {
         info!("Session is being dropped");
@@ -212,15 +462,18 @@ impl Drop for Session {
 impl Session {
     #[cfg(test)]
     pub fn new_no_certificate_store() -> Session {
-        let max_browse_continuation_points = super::constants::MAX_BROWSE_CONTINUATION_POINTS;
         let session = Session {
-            subscriptions: Subscriptions::new(100, PUBLISH_REQUEST_TIMEOUT),
+            subscriptions: Subscriptions::new(
+                100,
+                super::constants::DEFAULT_MAX_QUEUED_NOTIFICATIONS,
+                PUBLISH_REQUEST_TIMEOUT,
+            ),
             session_id: next_session_id(),
             secure_channel_id: 0,
             activated: false,
             terminate_session: false,
             terminated: false,
-            terminated_at: chrono::Utc::now(),
+            terminated_at: clock::now(),
             client_certificate: None,
             security_policy_uri: String::new(),
             authentication_token: NodeId::null(),
@@ -228,18 +481,33 @@ impl Session {
             session_name: UAString::null(),
             session_timeout: 0f64,
             user_identity: IdentityToken::None,
+            user_token_id: None,
             locale_ids: None,
             max_request_message_size: 0,
             max_response_message_size: 0,
             endpoint_url: UAString::null(),
-            max_browse_continuation_points,
-            browse_continuation_points: VecDeque::with_capacity(max_browse_continuation_points),
+            browse_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_BROWSE_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            query_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_QUERY_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            history_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_HISTORY_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            #[cfg(feature = "node-management")]
             can_modify_address_space: true,
+            #[cfg(feature = "diagnostics")]
             diagnostics: Arc::new(RwLock::new(ServerDiagnostics::default())),
+            #[cfg(feature = "diagnostics")]
             session_diagnostics: Arc::new(RwLock::new(SessionDiagnostics::default())),
-            last_service_request_timestamp: Utc::now(),
+            last_service_request_timestamp: clock::now(),
         };
 
+        #[cfg(feature = "diagnostics")]
         {
             let mut diagnostics = trace_write_lock!(session.diagnostics);
             diagnostics.on_create_session(&session);
@@ -249,24 +517,29 @@ impl Session {
 
     /// Create a `Session` from a `Server`
     pub fn new(server_state: Arc<RwLock<ServerState>>) -> Session {
-        let max_browse_continuation_points = super::constants::MAX_BROWSE_CONTINUATION_POINTS;
-
         let server_state = trace_read_lock!(server_state);
         let max_subscriptions = server_state.max_subscriptions;
+        let max_queued_notifications = server_state.max_queued_notifications;
+        #[cfg(feature = "diagnostics")]
         let diagnostics = server_state.diagnostics.clone();
+        #[cfg(feature = "node-management")]
         let can_modify_address_space = {
             let config = trace_read_lock!(server_state.config);
             config.limits.clients_can_modify_address_space
         };
 
         let session = Session {
-            subscriptions: Subscriptions::new(max_subscriptions, PUBLISH_REQUEST_TIMEOUT),
+            subscriptions: Subscriptions::new(
+                max_subscriptions,
+                max_queued_notifications,
+                PUBLISH_REQUEST_TIMEOUT,
+            ),
             session_id: next_session_id(),
             secure_channel_id: 0,
             activated: false,
             terminate_session: false,
             terminated: false,
-            terminated_at: chrono::Utc::now(),
+            terminated_at: clock::now(),
             client_certificate: None,
             security_policy_uri: String::new(),
             authentication_token: NodeId::null(),
@@ -274,17 +547,32 @@ impl Session {
             session_name: UAString::null(),
             session_timeout: 0f64,
             user_identity: IdentityToken::None,
+            user_token_id: None,
             locale_ids: None,
             max_request_message_size: 0,
             max_response_message_size: 0,
             endpoint_url: UAString::null(),
-            max_browse_continuation_points,
-            browse_continuation_points: VecDeque::with_capacity(max_browse_continuation_points),
+            browse_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_BROWSE_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            query_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_QUERY_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            history_continuation_points: ContinuationPoints::new(
+                super::constants::MAX_HISTORY_CONTINUATION_POINTS,
+                super::constants::CONTINUATION_POINT_TIMEOUT_MS,
+            ),
+            #[cfg(feature = "node-management")]
             can_modify_address_space,
+            #[cfg(feature = "diagnostics")]
             diagnostics,
+            #[cfg(feature = "diagnostics")]
             session_diagnostics: Arc::new(RwLock::new(SessionDiagnostics::default())),
-            last_service_request_timestamp: Utc::now(),
+            last_service_request_timestamp: clock::now(),
         };
+        #[cfg(feature = "diagnostics")]
         {
             let mut diagnostics = trace_write_lock!(session.diagnostics);
             diagnostics.on_create_session(&session);
@@ -315,7 +603,7 @@ impl Session {
     pub fn set_terminated(&mut self) {
         info!("Session being set to terminated");
         self.terminated = true;
-        self.terminated_at = chrono::Utc::now();
+        self.terminated_at = clock::now();
     }
 
     pub fn secure_channel_id(&self) -> u32 {
@@ -366,6 +654,16 @@ impl Session {
         self.user_identity = user_identity;
     }
 
+    /// Returns the id of the server user token that authenticated the user of the session, e.g.
+    /// `ANONYMOUS_USER_TOKEN_ID`, or `None` if the session is not activated.
+    pub fn user_token_id(&self) -> Option<&str> {
+        self.user_token_id.as_deref()
+    }
+
+    pub(crate) fn set_user_token_id(&mut self, user_token_id: Option<String>) {
+        self.user_token_id = user_token_id;
+    }
+
     pub fn last_service_request_timestamp(&self) -> DateTimeUtc {
         self.last_service_request_timestamp
     }
@@ -377,11 +675,22 @@ impl Session {
         self.last_service_request_timestamp = last_service_request_timestamp;
     }
 
+    /// Tests if the session has timed out, because the time since its last service request is
+    /// longer than its session timeout. A session that has been terminated has not timed out.
+    pub(crate) fn is_timed_out(&self, now: &DateTimeUtc) -> bool {
+        let elapsed = now.signed_duration_since(self.last_service_request_timestamp);
+        !self.terminated
+            && self.session_timeout > 0f64
+            && elapsed.num_milliseconds() as f64 > self.session_timeout
+    }
+
     pub fn locale_ids(&self) -> &Option<Vec<UAString>> {
         &self.locale_ids
     }
 
     pub fn set_locale_ids(&mut self, locale_ids: Option<Vec<UAString>>) {
+        self.subscriptions
+            .set_locale_ids(locale_ids.clone().unwrap_or_default());
         self.locale_ids = locale_ids;
     }
 
@@ -409,6 +718,7 @@ impl Session {
         self.session_name = session_name;
     }
 
+    #[cfg(feature = "diagnostics")]
     pub(crate) fn session_diagnostics(&self) -> Arc<RwLock<SessionDiagnostics>> {
         self.session_diagnostics.clone()
     }
@@ -455,42 +765,31 @@ impl Session {
         self.subscriptions.expire_stale_publish_requests(now);
     }
 
+    /// Adds a browse continuation point, returning its id, or `BadNoContinuationPoints` if the
+    /// session holds as many as it may.
     pub(crate) fn add_browse_continuation_point(
         &mut self,
         continuation_point: BrowseContinuationPoint,
-    ) {
-        // Remove excess browse continuation points
-        while self.browse_continuation_points.len() >= self.max_browse_continuation_points {
-            let continuation_point = self.browse_continuation_points.pop_front();
-            debug!(
-                "Removing old continuation point {} to make way for new one",
-                continuation_point.unwrap().id.as_base64()
-            );
-        }
+    ) -> Result<ByteString, StatusCode> {
         self.browse_continuation_points
-            .push_back(continuation_point);
+            .add(&clock::now(), continuation_point)
     }
 
-    /// Finds and REMOVES a continuation point by id.
+    /// Finds and REMOVES a browse continuation point by id, or returns
+    /// `BadContinuationPointInvalid` if there is no such continuation point or it has expired.
     pub(crate) fn find_browse_continuation_point(
         &mut self,
         id: &ByteString,
-    ) -> Option<BrowseContinuationPoint> {
-        if let Some(idx) = self
-            .browse_continuation_points
-            .iter()
-            .position(|continuation_point| continuation_point.id == *id)
-        {
-            self.browse_continuation_points.remove(idx)
-        } else {
-            None
-        }
+    ) -> Result<BrowseContinuationPoint, StatusCode> {
+        self.browse_continuation_points.take(&clock::now(), id)
     }
 
     pub(crate) fn remove_expired_browse_continuation_points(
         &mut self,
         address_space: &AddressSpace,
     ) {
+        self.browse_continuation_points
+            .remove_expired(&clock::now());
         self.browse_continuation_points.retain(|continuation_point| {
             let valid = continuation_point.is_valid_browse_continuation_point(address_space);
             if !valid {
@@ -500,33 +799,141 @@ impl Session {
         });
     }
 
-    /// Remove all the specified continuation points by id
-    pub(crate) fn remove_browse_continuation_points(&mut self, continuation_points: &[ByteString]) {
-        // Turn the supplied slice into a set
-        let continuation_points_set: HashSet<ByteString> =
-            continuation_points.iter().cloned().collect();
-        // Now remove any continuation points that are part of that set
-        self.browse_continuation_points
-            .retain(|continuation_point| !continuation_points_set.contains(&continuation_point.id));
+    /// Releases a browse continuation point by id, or returns `BadContinuationPointInvalid` if
+    /// there is no such continuation point.
+    pub(crate) fn release_browse_continuation_point(&mut self, id: &ByteString) -> StatusCode {
+        match self.browse_continuation_points.release(id) {
+            Ok(_) => StatusCode::Good,
+            Err(status_code) => status_code,
+        }
+    }
+
+    /// Releases every browse, query and history read continuation point, returning how many
+    /// there were
+    pub(crate) fn release_continuation_points(&mut self) -> usize {
+        self.browse_continuation_points.clear()
+            + self.query_continuation_points.clear()
+            + self.history_continuation_points.clear()
+    }
+
+    /// Adds a query continuation point, returning its id, or `BadNoContinuationPoints` if the
+    /// session holds as many as it may.
+    #[cfg(feature = "query")]
+    pub(crate) fn add_query_continuation_point(
+        &mut self,
+        continuation_point: QueryContinuationPoint,
+    ) -> Result<ByteString, StatusCode> {
+        self.query_continuation_points
+            .add(&clock::now(), continuation_point)
+    }
+
+    /// Finds and REMOVES a query continuation point by id. Returns `BadContinuationPointInvalid`
+    /// if there is no such continuation point, it has expired, or the address space has been
+    /// modified since it was made.
+    #[cfg(feature = "query")]
+    pub(crate) fn find_query_continuation_point(
+        &mut self,
+        id: &ByteString,
+        address_space: &AddressSpace,
+    ) -> Result<QueryContinuationPoint, StatusCode> {
+        self.query_continuation_points
+            .take(&clock::now(), id)
+            .and_then(|continuation_point| {
+                if continuation_point.is_valid_query_continuation_point(address_space) {
+                    Ok(continuation_point)
+                } else {
+                    Err(StatusCode::BadContinuationPointInvalid)
+                }
+            })
     }
 
+    /// Releases a query continuation point by id, or returns `BadContinuationPointInvalid` if
+    /// there is no such continuation point.
+    #[cfg(feature = "query")]
+    pub(crate) fn release_query_continuation_point(&mut self, id: &ByteString) -> StatusCode {
+        match self.query_continuation_points.release(id) {
+            Ok(_) => StatusCode::Good,
+            Err(status_code) => status_code,
+        }
+    }
+
+    /// Adds a history read continuation point, returning its id, or `BadNoContinuationPoints` if
+    /// the session holds as many as it may.
+    #[cfg(feature = "history")]
+    pub(crate) fn add_history_continuation_point(
+        &mut self,
+        continuation_point: HistoryContinuationPoint,
+    ) -> Result<ByteString, StatusCode> {
+        self.history_continuation_points
+            .add(&clock::now(), continuation_point)
+    }
+
+    /// Finds and REMOVES a history read continuation point by id. Returns
+    /// `BadContinuationPointInvalid` if there is no such continuation point, it has expired, or
+    /// it was made by reading another node.
+    #[cfg(feature = "history")]
+    pub(crate) fn find_history_continuation_point(
+        &mut self,
+        id: &ByteString,
+        node_id: &NodeId,
+    ) -> Result<HistoryContinuationPoint, StatusCode> {
+        self.history_continuation_points
+            .take(&clock::now(), id)
+            .and_then(|continuation_point| {
+                if continuation_point.node_id == *node_id {
+                    Ok(continuation_point)
+                } else {
+                    Err(StatusCode::BadContinuationPointInvalid)
+                }
+            })
+    }
+
+    #[cfg(feature = "node-management")]
     pub(crate) fn can_modify_address_space(&self) -> bool {
         self.can_modify_address_space
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, feature = "node-management"))]
     pub(crate) fn set_can_modify_address_space(&mut self, can_modify_address_space: bool) {
         self.can_modify_address_space = can_modify_address_space;
     }
 
+    /// Returns the user access level of a variable for the user of the session, which is the user
+    /// access level of the variable unless the server overrides it for the user.
     pub(crate) fn effective_user_access_level(
         &self,
         user_access_level: UserAccessLevel,
-        _node_id: &NodeId,
-        _attribute_id: AttributeId,
+        node_id: &NodeId,
+        server_state: &ServerState,
     ) -> UserAccessLevel {
-        // TODO session could modify the user_access_level further here via user / groups
-        user_access_level
+        match (
+            server_state.user_access_levels.as_ref(),
+            self.user_token_id.as_ref(),
+        ) {
+            (Some(user_access_levels), Some(user_token_id)) => {
+                user_access_levels.user_access_level(user_token_id, node_id, user_access_level)
+            }
+            _ => user_access_level,
+        }
+    }
+
+    /// Returns the user write mask of a node for the user of the session, which is the user write
+    /// mask of the node unless the server overrides it for the user.
+    pub(crate) fn effective_user_write_mask(
+        &self,
+        user_write_mask: WriteMask,
+        node_id: &NodeId,
+        server_state: &ServerState,
+    ) -> WriteMask {
+        match (
+            server_state.user_access_levels.as_ref(),
+            self.user_token_id.as_ref(),
+        ) {
+            (Some(user_access_levels), Some(user_token_id)) => {
+                user_access_levels.user_write_mask(user_token_id, node_id, user_write_mask)
+            }
+            _ => user_write_mask,
+        }
     }
 
     /// Helper function to return the client user id from the identity token or None of there is no user id
@@ -555,15 +962,24 @@ impl Session {
         self.terminate_session = true;
     }
 
+    #[cfg(feature = "diagnostics")]
     pub(crate) fn register_session(&self, address_space: Arc<RwLock<AddressSpace>>) {
         let session_diagnostics = trace_read_lock!(self.session_diagnostics);
         let mut address_space = trace_write_lock!(address_space);
         session_diagnostics.register_session(self, &mut address_space);
     }
 
+    #[cfg(feature = "diagnostics")]
     pub(crate) fn deregister_session(&self, address_space: Arc<RwLock<AddressSpace>>) {
         let session_diagnostics = trace_read_lock!(self.session_diagnostics);
         let mut address_space = trace_write_lock!(address_space);
         session_diagnostics.deregister_session(self, &mut address_space);
     }
+
+    /// Without diagnostics the session has no object in the address space
+    #[cfg(not(feature = "diagnostics"))]
+    pub(crate) fn register_session(&self, _address_space: Arc<RwLock<AddressSpace>>) {}
+
+    #[cfg(not(feature = "diagnostics"))]
+    pub(crate) fn deregister_session(&self, _address_space: Arc<RwLock<AddressSpace>>) {}
 }
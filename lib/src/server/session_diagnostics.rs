@@ -1,20 +1,24 @@
+#[cfg(feature = "diagnostics")]
 use std::collections::HashMap;
 
+#[cfg(feature = "diagnostics")]
 use crate::types::{node_ids::ObjectTypeId, service_types::ServiceCounterDataType};
 
+#[cfg(feature = "diagnostics")]
 use super::{
     address_space::{address_space::AddressSpace, object::ObjectBuilder},
     session::Session,
 };
 
 /// This object tracks session diagnostics for exposure through the address space
-
+#[cfg(feature = "diagnostics")]
 pub(crate) struct SessionDiagnostics {
     total_request_count: u32,
     unauthorized_request_count: u32,
     service_counters: HashMap<&'static str, ServiceCounterDataType>,
 }
 
+#[cfg(feature = "diagnostics")]
 impl Default for SessionDiagnostics {
     fn default() -> Self {
         Self {
@@ -25,6 +29,7 @@ impl Default for SessionDiagnostics {
     }
 }
 
+#[cfg(feature = "diagnostics")]
 impl SessionDiagnostics {
     /// Registers a session object
     pub(crate) fn register_session(&self, session: &Session, address_space: &mut AddressSpace) {
@@ -135,18 +140,6 @@ impl SessionDiagnostics {
         self.total_request_count += 1;
     }
 
-    /// Fetches a snapshot of the current service counter value
-    pub(crate) fn service_counter(
-        &mut self,
-        diagnostic_key: &'static str,
-    ) -> ServiceCounterDataType {
-        if let Some(counter) = self.service_counters.get_mut(diagnostic_key) {
-            counter.clone()
-        } else {
-            ServiceCounterDataType::default()
-        }
-    }
-
     /// Increments the service counter for a successful service call
     pub(crate) fn service_success(&mut self, diagnostic_key: &'static str) {
         if let Some(counter) = self.service_counters.get_mut(diagnostic_key) {
@@ -171,8 +164,10 @@ impl SessionDiagnostics {
 }
 
 pub(crate) const READ_COUNT: &str = "ReadCount";
+#[cfg(feature = "history")]
 pub(crate) const HISTORY_READ_COUNT: &str = "HistoryReadCount";
 pub(crate) const WRITE_COUNT: &str = "WriteCount";
+#[cfg(feature = "history")]
 pub(crate) const HISTORY_UPDATE_COUNT: &str = "HistoryUpdateCount";
 pub(crate) const CALL_COUNT: &str = "CallCount";
 pub(crate) const CREATE_MONITORED_ITEMS_COUNT: &str = "CreateMonitoredItemsCount";
@@ -187,9 +182,13 @@ pub(crate) const SET_PUBLISHING_MODE_COUNT: &str = "SetPublishingModeCount";
 pub(crate) const REPUBLISH_COUNT: &str = "RepublishCount";
 pub(crate) const TRANSFER_SUBSCRIPTIONS_COUNT: &str = "TransferSubscriptionsCount";
 pub(crate) const DELETE_SUBSCRIPTIONS_COUNT: &str = "DeleteSubscriptionsCount";
+#[cfg(feature = "node-management")]
 pub(crate) const ADD_NODES_COUNT: &str = "AddNodesCount";
+#[cfg(feature = "node-management")]
 pub(crate) const ADD_REFERENCES_COUNT: &str = "AddReferencesCount";
+#[cfg(feature = "node-management")]
 pub(crate) const DELETE_NODES_COUNT: &str = "DeleteNodesCount";
+#[cfg(feature = "node-management")]
 pub(crate) const DELETE_REFERENCES_COUNT: &str = "DeleteReferencesCount";
 pub(crate) const BROWSE_COUNT: &str = "BrowseCount";
 pub(crate) const BROWSE_NEXT_COUNT: &str = "BrowseNextCount";
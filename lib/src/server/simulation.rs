@@ -0,0 +1,357 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Provides generators of simulated values for variables, so that demo servers and load tests
+//! can produce changing data without timer code of their own.
+//!
+//! A [`Simulation`] attaches a generator to each of its variables with an update rate and sets
+//! the value of the variable from the generator with a polling action of the server. The
+//! generators that come with the module produce `Double` values, and any other kind of value can
+//! be produced by implementing [`ValueGenerator`].
+//!
+//! ```no_run
+//! use opcua::server::prelude::*;
+//!
+//! # fn example(server: &mut Server) {
+//! let ns = 2;
+//! let mut simulation = Simulation::new();
+//! simulation
+//!     .add(NodeId::new(ns, "Temperature"), 500, Sine::new(5.0, 60.0).offset(20.0))
+//!     .add(NodeId::new(ns, "Level"), 1000, RandomWalk::new(50.0, 1.0).limits(0.0, 100.0))
+//!     .add(NodeId::new(ns, "Pump"), 1000, Square::new(0.0, 1.0, 30.0));
+//! simulation.start(server);
+//! # }
+//! ```
+//!
+//! [`Simulation`]: ./struct.Simulation.html
+//! [`ValueGenerator`]: ./trait.ValueGenerator.html
+
+use std::{
+    f64::consts::PI,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::sync::*;
+use crate::types::{DateTime, NodeId, Variant};
+
+use crate::server::server::Server;
+
+/// Produces the values of a simulated variable. It is called on every update of the variable
+/// with the time that has elapsed since the simulation was started.
+pub trait ValueGenerator: Send {
+    /// Returns the next value of the variable
+    fn next_value(&mut self, elapsed: Duration) -> Variant;
+}
+
+/// The fraction of the period that has elapsed at the time, from 0 up to but excluding 1
+fn phase_of(elapsed: Duration, period: f64) -> f64 {
+    if period > 0.0 {
+        (elapsed.as_secs_f64() / period).fract()
+    } else {
+        0.0
+    }
+}
+
+/// A sine wave with an amplitude and a period in seconds, around an offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sine {
+    amplitude: f64,
+    period: f64,
+    offset: f64,
+    phase: f64,
+}
+
+impl Sine {
+    pub fn new(amplitude: f64, period: f64) -> Sine {
+        Sine {
+            amplitude,
+            period,
+            offset: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Sets the value around which the wave oscillates
+    pub fn offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the phase of the wave in radians, e.g. to avoid several waves being in step
+    pub fn phase(mut self, phase: f64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Returns the value of the wave at the time
+    pub fn value_at(&self, elapsed: Duration) -> f64 {
+        let angle = 2.0 * PI * phase_of(elapsed, self.period) + self.phase;
+        self.offset + self.amplitude * angle.sin()
+    }
+}
+
+impl ValueGenerator for Sine {
+    fn next_value(&mut self, elapsed: Duration) -> Variant {
+        Variant::Double(self.value_at(elapsed))
+    }
+}
+
+/// A sawtooth that rises from a minimum to a maximum over a period in seconds, and then starts
+/// again from the minimum
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ramp {
+    min: f64,
+    max: f64,
+    period: f64,
+}
+
+impl Ramp {
+    pub fn new(min: f64, max: f64, period: f64) -> Ramp {
+        Ramp { min, max, period }
+    }
+
+    /// Returns the value of the ramp at the time
+    pub fn value_at(&self, elapsed: Duration) -> f64 {
+        self.min + (self.max - self.min) * phase_of(elapsed, self.period)
+    }
+}
+
+impl ValueGenerator for Ramp {
+    fn next_value(&mut self, elapsed: Duration) -> Variant {
+        Variant::Double(self.value_at(elapsed))
+    }
+}
+
+/// A square wave that is high for the first half of each period in seconds and low for the other
+#[derive(Debug, Clone, PartialEq)]
+pub struct Square {
+    low: f64,
+    high: f64,
+    period: f64,
+}
+
+impl Square {
+    pub fn new(low: f64, high: f64, period: f64) -> Square {
+        Square { low, high, period }
+    }
+
+    /// Returns the value of the wave at the time
+    pub fn value_at(&self, elapsed: Duration) -> f64 {
+        if phase_of(elapsed, self.period) < 0.5 {
+            self.high
+        } else {
+            self.low
+        }
+    }
+}
+
+impl ValueGenerator for Square {
+    fn next_value(&mut self, elapsed: Duration) -> Variant {
+        Variant::Double(self.value_at(elapsed))
+    }
+}
+
+/// A value that moves up or down by a random amount of up to a step on each update, and stays
+/// within its limits. The random numbers are not cryptographically strong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomWalk {
+    value: f64,
+    step: f64,
+    min: f64,
+    max: f64,
+    /// State of the xorshift generator, which must never be 0
+    state: u64,
+}
+
+impl RandomWalk {
+    /// Creates a random walk that starts at the value and is seeded from the OS
+    pub fn new(start: f64, step: f64) -> RandomWalk {
+        let mut seed = [0u8; 8];
+        getrandom::getrandom(&mut seed).expect("The OS random number generator failed");
+        Self::with_seed(start, step, u64::from_le_bytes(seed))
+    }
+
+    /// Creates a random walk that starts at the value and always makes the same steps for the
+    /// same seed, e.g. so a load test is repeatable
+    pub fn with_seed(start: f64, step: f64, seed: u64) -> RandomWalk {
+        RandomWalk {
+            value: start,
+            step,
+            min: f64::MIN,
+            max: f64::MAX,
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    /// Sets the limits that the value stays within
+    pub fn limits(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self.value = self.value.max(min).min(max);
+        self
+    }
+
+    /// Returns a random number from 0 up to but excluding 1
+    fn random(&mut self) -> f64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let x = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Takes a step and returns the new value
+    pub fn step(&mut self) -> f64 {
+        let delta = (self.random() * 2.0 - 1.0) * self.step;
+        self.value = (self.value + delta).max(self.min).min(self.max);
+        self.value
+    }
+}
+
+impl ValueGenerator for RandomWalk {
+    fn next_value(&mut self, _elapsed: Duration) -> Variant {
+        Variant::Double(self.step())
+    }
+}
+
+/// Plays back the numbers in a column of a CSV file, one row on each update, starting again from
+/// the first row after the last. Rows whose column is not a number, such as a header, are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvPlayback {
+    values: Vec<f64>,
+    next: usize,
+}
+
+impl CsvPlayback {
+    /// Creates a playback of the values
+    pub fn new(values: Vec<f64>) -> CsvPlayback {
+        CsvPlayback { values, next: 0 }
+    }
+
+    /// Reads the column, numbered from 0, of a CSV file. Fails with `InvalidData` if the column
+    /// has no numbers.
+    pub fn from_file<P>(path: P, column: usize) -> io::Result<CsvPlayback>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(File::open(path)?, column)
+    }
+
+    /// Reads the column, numbered from 0, of CSV from the reader. Fails with `InvalidData` if the
+    /// column has no numbers.
+    pub fn from_reader<R>(reader: R, column: usize) -> io::Result<CsvPlayback>
+    where
+        R: Read,
+    {
+        let mut values = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if let Some(Ok(value)) = line.split(',').nth(column).map(|v| v.trim().parse()) {
+                values.push(value);
+            }
+        }
+        if values.is_empty() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Column {} of the CSV has no numbers", column),
+            ))
+        } else {
+            Ok(Self::new(values))
+        }
+    }
+
+    /// Returns the next value, or `None` if there are no values
+    pub fn next_sample(&mut self) -> Option<f64> {
+        let value = self.values.get(self.next).cloned();
+        if value.is_some() {
+            self.next = (self.next + 1) % self.values.len();
+        }
+        value
+    }
+}
+
+impl ValueGenerator for CsvPlayback {
+    fn next_value(&mut self, _elapsed: Duration) -> Variant {
+        self.next_sample().map_or(Variant::Empty, Variant::Double)
+    }
+}
+
+/// A variable whose value is set from a generator
+struct SimulatedVariable {
+    node_id: NodeId,
+    update_rate: u64,
+    generator: Box<dyn ValueGenerator>,
+}
+
+/// A set of simulated variables. The variables must already exist in the address space of the
+/// server, with a data type that suits the values of their generators.
+#[derive(Default)]
+pub struct Simulation {
+    variables: Vec<SimulatedVariable>,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation::default()
+    }
+
+    /// Adds a variable whose value is set from the generator every `update_rate` milliseconds
+    pub fn add<N, G>(&mut self, node_id: N, update_rate: u64, generator: G) -> &mut Self
+    where
+        N: Into<NodeId>,
+        G: ValueGenerator + 'static,
+    {
+        self.variables.push(SimulatedVariable {
+            node_id: node_id.into(),
+            update_rate,
+            generator: Box::new(generator),
+        });
+        self
+    }
+
+    /// Returns the number of simulated variables
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Tests if there are no simulated variables
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Starts updating the variables with polling actions of the server. The elapsed time that
+    /// generators are given is counted from now.
+    pub fn start(self, server: &mut Server) {
+        let started = Instant::now();
+        self.variables.into_iter().for_each(|variable| {
+            let SimulatedVariable {
+                node_id,
+                update_rate,
+                generator,
+            } = variable;
+            let address_space = server.address_space();
+            let generator = Arc::new(Mutex::new(generator));
+            server.add_polling_action(update_rate, move || {
+                let value = {
+                    let mut generator = trace_lock!(generator);
+                    generator.next_value(started.elapsed())
+                };
+                let now = DateTime::now();
+                let mut address_space = trace_write_lock!(address_space);
+                if !address_space.set_variable_value_by_ref(&node_id, value, &now, &now) {
+                    debug!("Simulated variable {} is not in the address space", node_id);
+                }
+            });
+        });
+    }
+}
@@ -12,29 +12,35 @@ use crate::sync::*;
 use crate::types::{
     profiles,
     service_types::{
-        ActivateSessionRequest, AnonymousIdentityToken, ApplicationDescription, ApplicationType,
-        EndpointDescription, RegisteredServer, ServerState as ServerStateType, SignatureData,
-        UserNameIdentityToken, UserTokenPolicy, UserTokenType, X509IdentityToken,
+        ActivateSessionRequest, ApplicationDescription, ApplicationType, EndpointDescription,
+        RegisteredServer, ServerState as ServerStateType, SignatureData, UserNameIdentityToken,
+        UserTokenPolicy, UserTokenType, X509IdentityToken,
     },
     status_code::StatusCode,
 };
 
+#[cfg(feature = "diagnostics")]
+use crate::server::diagnostics::ServerDiagnostics;
+#[cfg(feature = "events")]
+use crate::server::events::{
+    audit::{AuditEvent, AuditLog},
+    event::Event,
+};
+#[cfg(feature = "history")]
+use crate::server::historical::{HistoricalDataProvider, HistoricalEventProvider};
 use crate::server::{
-    callbacks::{RegisterNodes, UnregisterNodes},
+    callbacks::{RegisterNodes, UnregisterNodes, UserAccessLevels},
     config::{ServerConfig, ServerEndpoint},
     constants,
-    diagnostics::ServerDiagnostics,
-    events::{
-        audit::{AuditEvent, AuditLog},
-        event::Event,
-    },
-    historical::{HistoricalDataProvider, HistoricalEventProvider},
     identity_token::{
         IdentityToken, POLICY_ID_ANONYMOUS, POLICY_ID_USER_PASS_NONE, POLICY_ID_USER_PASS_RSA_15,
-        POLICY_ID_USER_PASS_RSA_OAEP, POLICY_ID_X509,
+        POLICY_ID_USER_PASS_RSA_OAEP, POLICY_ID_USER_PASS_RSA_OAEP_SHA256,
+        POLICY_ID_X509_RSA_PSS_SHA256, POLICY_ID_X509_RSA_SHA1, POLICY_ID_X509_RSA_SHA256,
     },
 };
 
+// Some limits are only reported in the server capabilities of the generated address space
+#[cfg_attr(not(feature = "generated-address-space"), allow(dead_code))]
 pub(crate) struct OperationalLimits {
     pub max_nodes_per_translate_browse_paths_to_node_ids: usize,
     pub max_nodes_per_read: usize,
@@ -89,17 +95,23 @@ pub struct ServerState {
     pub config: Arc<RwLock<ServerConfig>>,
     /// Server public certificate read from config location or null if there is none
     pub server_certificate: Option<X509>,
+    /// The certificates of the CAs that issued the server certificate, which are sent after it
+    pub server_issuer_certificates: Vec<X509>,
     /// Server private key
     pub server_pkey: Option<PrivateKey>,
     /// The next subscription id - subscriptions are shared across the whole server. Initial value
     /// is a random u32.
     pub last_subscription_id: u32,
+    /// Maximum number of sessions in the server, 0 means no limit
+    pub max_sessions: usize,
     /// Maximum number of subscriptions per session, 0 means no limit (danger)
     pub max_subscriptions: usize,
     /// Maximum number of monitored items per subscription, 0 means no limit (danger)
     pub max_monitored_items_per_sub: usize,
     /// Maximum number of queued values in a monitored item, 0 means no limit (danger)
     pub max_monitored_item_queue_size: usize,
+    /// Maximum number of notification messages that a late subscription holds, 0 means no limit
+    pub max_queued_notifications: usize,
     /// Minimum publishing interval (in millis)
     pub min_publishing_interval_ms: Duration,
     /// Minimum sampling interval (in millis)
@@ -117,17 +129,23 @@ pub struct ServerState {
     /// Sets the abort flag that terminates the associated server
     pub abort: bool,
     /// Audit log
+    #[cfg(feature = "events")]
     pub(crate) audit_log: Arc<RwLock<AuditLog>>,
     /// Diagnostic information
+    #[cfg(feature = "diagnostics")]
     pub(crate) diagnostics: Arc<RwLock<ServerDiagnostics>>,
     /// Callback for register nodes
     pub(crate) register_nodes_callback: Option<Box<dyn RegisterNodes + Send + Sync>>,
     /// Callback for unregister nodes
     pub(crate) unregister_nodes_callback: Option<Box<dyn UnregisterNodes + Send + Sync>>,
     /// Callback for historical data
+    #[cfg(feature = "history")]
     pub(crate) historical_data_provider: Option<Box<dyn HistoricalDataProvider + Send + Sync>>,
     /// Callback for historical events
+    #[cfg(feature = "history")]
     pub(crate) historical_event_provider: Option<Box<dyn HistoricalEventProvider + Send + Sync>>,
+    /// Callback for the user access levels of variables
+    pub(crate) user_access_levels: Option<Box<dyn UserAccessLevels + Send + Sync>>,
     /// Size of the send buffer in bytes
     pub send_buffer_size: usize,
     /// Size of the receive buffer in bytes
@@ -163,14 +181,16 @@ impl ServerState {
         }
 
         let config = trace_read_lock!(self.config);
-        if let Ok(hostname) = hostname_from_url(endpoint_url.as_ref()) {
-            if !hostname.eq_ignore_ascii_case(&config.tcp_config.host) {
+        if let Ok(url) = endpoint_url.as_ref().parse::<EndpointUrl>() {
+            if !url.host().eq_ignore_ascii_case(&config.tcp_config.host) {
                 debug!("Endpoint url \"{}\" hostname supplied by caller does not match server's hostname \"{}\"", endpoint_url, &config.tcp_config.host);
             }
+            let base_endpoint_url = config.base_endpoint_url_for(endpoint_url.as_ref());
             let endpoints = config
                 .endpoints
                 .iter()
-                .map(|(_, e)| self.new_endpoint_description(&config, e, true))
+                .filter(|(_, e)| config.is_advertised(e))
+                .map(|(_, e)| self.new_endpoint_description(&config, &base_endpoint_url, e, true))
                 .collect();
             Some(endpoints)
         } else {
@@ -178,8 +198,17 @@ impl ServerState {
                 "Endpoint url \"{}\" is unrecognized, using default",
                 endpoint_url
             );
-            if let Some(e) = config.default_endpoint() {
-                Some(vec![self.new_endpoint_description(&config, e, true)])
+            if let Some(e) = config
+                .default_endpoint()
+                .filter(|e| config.is_advertised(e))
+            {
+                let base_endpoint_url = config.base_endpoint_url();
+                Some(vec![self.new_endpoint_description(
+                    &config,
+                    &base_endpoint_url,
+                    e,
+                    true,
+                )])
             } else {
                 Some(vec![])
             }
@@ -207,7 +236,7 @@ impl ServerState {
     ) -> Option<Vec<EndpointDescription>> {
         debug!("find_endpoint, url = {}", endpoint_url);
         let config = trace_read_lock!(self.config);
-        let base_endpoint_url = config.base_endpoint_url();
+        let base_endpoint_url = config.base_endpoint_url_for(endpoint_url);
         let endpoints: Vec<EndpointDescription> = config
             .endpoints
             .iter()
@@ -215,7 +244,7 @@ impl ServerState {
                 // Test end point's security_policy_uri and matching url
                 url_matches_except_host(&e.endpoint_url(&base_endpoint_url), endpoint_url)
             })
-            .map(|(_, e)| self.new_endpoint_description(&config, e, false))
+            .map(|(_, e)| self.new_endpoint_description(&config, &base_endpoint_url, e, false))
             .collect();
         if endpoints.is_empty() {
             None
@@ -229,24 +258,55 @@ impl ServerState {
         match endpoint.password_security_policy() {
             SecurityPolicy::None => POLICY_ID_USER_PASS_NONE,
             SecurityPolicy::Basic128Rsa15 => POLICY_ID_USER_PASS_RSA_15,
-            SecurityPolicy::Basic256 | SecurityPolicy::Basic256Sha256 => {
-                POLICY_ID_USER_PASS_RSA_OAEP
+            SecurityPolicy::Basic256
+            | SecurityPolicy::Basic256Sha256
+            | SecurityPolicy::Aes128Sha256RsaOaep => POLICY_ID_USER_PASS_RSA_OAEP,
+            SecurityPolicy::Aes256Sha256RsaPss => POLICY_ID_USER_PASS_RSA_OAEP_SHA256,
+            SecurityPolicy::Unknown => {
+                panic!()
             }
-            // TODO this is a placeholder
-            SecurityPolicy::Aes128Sha256RsaOaep | SecurityPolicy::Aes256Sha256RsaPss => {
-                POLICY_ID_USER_PASS_RSA_OAEP
+        }
+        .into()
+    }
+
+    /// The security policy that clients encrypt passwords with is only sent when it differs
+    /// from the one of the endpoint, e.g. so passwords are encrypted even though the endpoint
+    /// has no security. Otherwise it is null, which means the same.
+    fn user_pass_security_policy_uri(endpoint: &ServerEndpoint) -> UAString {
+        let password_security_policy = endpoint.password_security_policy();
+        if password_security_policy == endpoint.security_policy() {
+            UAString::null()
+        } else {
+            UAString::from(password_security_policy.to_uri())
+        }
+    }
+
+    /// Determine the x509 policy id depending on the security policy that tokens are signed with.
+    /// Policies that sign the same way share an id, so the id identifies what the client has to do.
+    fn x509_security_policy_id(endpoint: &ServerEndpoint) -> UAString {
+        match endpoint.x509_security_policy() {
+            SecurityPolicy::Basic128Rsa15 | SecurityPolicy::Basic256 => POLICY_ID_X509_RSA_SHA1,
+            SecurityPolicy::Basic256Sha256 | SecurityPolicy::Aes128Sha256RsaOaep => {
+                POLICY_ID_X509_RSA_SHA256
             }
-            _ => {
+            SecurityPolicy::Aes256Sha256RsaPss => POLICY_ID_X509_RSA_PSS_SHA256,
+            SecurityPolicy::None | SecurityPolicy::Unknown => {
                 panic!()
             }
         }
         .into()
     }
 
-    fn user_pass_security_policy_uri(_endpoint: &ServerEndpoint) -> UAString {
-        // TODO we could force the security policy uri for passwords to be something other than the default
-        //  here to ensure they're secure even when the endpoint's security policy is None.
-        UAString::null()
+    /// The security policy of a user token policy, where null means that of the endpoint
+    fn user_token_security_policy(
+        endpoint: &ServerEndpoint,
+        user_token_policy: &UserTokenPolicy,
+    ) -> SecurityPolicy {
+        if user_token_policy.security_policy_uri.is_null() {
+            endpoint.security_policy()
+        } else {
+            SecurityPolicy::from_uri(user_token_policy.security_policy_uri.as_ref())
+        }
     }
 
     fn user_identity_tokens(
@@ -280,11 +340,11 @@ impl ServerState {
         // X509 policy
         if endpoint.supports_x509(&config.user_tokens) {
             user_identity_tokens.push(UserTokenPolicy {
-                policy_id: UAString::from(POLICY_ID_X509),
+                policy_id: Self::x509_security_policy_id(endpoint),
                 token_type: UserTokenType::Certificate,
                 issued_token_type: UAString::null(),
                 issuer_endpoint_url: UAString::null(),
-                security_policy_uri: UAString::from(SecurityPolicy::Basic128Rsa15.to_uri()),
+                security_policy_uri: UAString::from(endpoint.x509_security_policy().to_uri()),
             });
         }
 
@@ -302,11 +362,10 @@ impl ServerState {
     fn new_endpoint_description(
         &self,
         config: &ServerConfig,
+        base_endpoint_url: &str,
         endpoint: &ServerEndpoint,
         all_fields: bool,
     ) -> EndpointDescription {
-        let base_endpoint_url = config.base_endpoint_url();
-
         let user_identity_tokens = self.user_identity_tokens(config, endpoint);
 
         // CreateSession doesn't need all the endpoint description
@@ -341,7 +400,7 @@ impl ServerState {
         };
 
         EndpointDescription {
-            endpoint_url: endpoint.endpoint_url(&base_endpoint_url).into(),
+            endpoint_url: endpoint.endpoint_url(base_endpoint_url).into(),
             server,
             server_certificate,
             security_mode: endpoint.message_security_mode(),
@@ -391,9 +450,12 @@ impl ServerState {
         self.state == ServerStateType::Running
     }
 
+    /// Returns the server certificate followed by the certificates of the CAs that issued it
     pub fn server_certificate_as_byte_string(&self) -> ByteString {
         if let Some(ref server_certificate) = self.server_certificate {
-            server_certificate.as_byte_string()
+            let mut server_certificate_chain = vec![server_certificate.clone()];
+            server_certificate_chain.extend_from_slice(&self.server_issuer_certificates);
+            X509::chain_as_byte_string(&server_certificate_chain)
         } else {
             ByteString::null()
         }
@@ -444,31 +506,56 @@ impl ServerState {
         let config = trace_read_lock!(self.config);
 
         if let Some(endpoint) = config.find_endpoint(endpoint_url, security_policy, security_mode) {
-            // Now validate the user identity token
+            // The token is validated against the user token policy that the endpoint offers for it
+            let user_identity_tokens = self.user_identity_tokens(&config, endpoint);
             match IdentityToken::new(user_identity_token, &self.decoding_options()) {
                 IdentityToken::None => {
                     error!("User identity token type unsupported");
                     Err(StatusCode::BadIdentityTokenInvalid)
                 }
                 IdentityToken::AnonymousIdentityToken(token) => {
-                    Self::authenticate_anonymous_token(endpoint, &token)
+                    Self::find_user_token_policy(
+                        endpoint,
+                        &user_identity_tokens,
+                        UserTokenType::Anonymous,
+                        &token.policy_id,
+                    )?;
+                    debug!("Anonymous identity is authenticated");
+                    Ok(String::from(crate::server::config::ANONYMOUS_USER_TOKEN_ID))
                 }
-                IdentityToken::UserNameIdentityToken(token) => self
-                    .authenticate_username_identity_token(
+                IdentityToken::UserNameIdentityToken(token) => {
+                    let user_token_policy = Self::find_user_token_policy(
+                        endpoint,
+                        &user_identity_tokens,
+                        UserTokenType::UserName,
+                        &token.policy_id,
+                    )?;
+                    self.authenticate_username_identity_token(
                         &config,
                         endpoint,
+                        user_token_policy,
                         &token,
                         &self.server_pkey,
                         server_nonce,
-                    ),
-                IdentityToken::X509IdentityToken(token) => self.authenticate_x509_identity_token(
-                    &config,
-                    endpoint,
-                    &token,
-                    &request.user_token_signature,
-                    &self.server_certificate,
-                    server_nonce,
-                ),
+                    )
+                }
+                IdentityToken::X509IdentityToken(token) => {
+                    let user_token_policy = Self::find_user_token_policy(
+                        endpoint,
+                        &user_identity_tokens,
+                        UserTokenType::Certificate,
+                        &token.policy_id,
+                    )?;
+                    self.authenticate_x509_identity_token(
+                        &config,
+                        endpoint,
+                        user_token_policy,
+                        &token,
+                        &request.user_token_signature,
+                        &self.server_certificate,
+                        server_nonce,
+                    )
+                }
                 IdentityToken::Invalid(o) => {
                     error!("User identity token type {:?} is unsupported", o.node_id);
                     Err(StatusCode::BadIdentityTokenInvalid)
@@ -489,49 +576,63 @@ impl ServerState {
         self.unregister_nodes_callback = Some(unregister_nodes_callback);
     }
 
+    /// Sets the callback that overrides the user access levels of variables for the users of the
+    /// server.
+    pub fn set_user_access_levels(
+        &mut self,
+        user_access_levels: Box<dyn UserAccessLevels + Send + Sync>,
+    ) {
+        self.user_access_levels = Some(user_access_levels);
+    }
+
     /// Returns the decoding options of the server
     pub fn decoding_options(&self) -> DecodingOptions {
         let config = trace_read_lock!(self.config);
         config.decoding_options()
     }
 
-    /// Authenticates an anonymous token, i.e. does the endpoint support anonymous access or not
-    fn authenticate_anonymous_token(
+    /// Finds the user token policy that the endpoint offers for a token of the type and policy id.
+    /// A token is rejected if the endpoint offers no policy for its type and is invalid if it
+    /// does not possess the id of the policy that is offered.
+    fn find_user_token_policy<'a>(
         endpoint: &ServerEndpoint,
-        token: &AnonymousIdentityToken,
-    ) -> Result<String, StatusCode> {
-        if token.policy_id.as_ref() != POLICY_ID_ANONYMOUS {
-            error!("Token doesn't possess the correct policy id");
-            Err(StatusCode::BadIdentityTokenInvalid)
-        } else if !endpoint.supports_anonymous() {
+        user_identity_tokens: &'a [UserTokenPolicy],
+        token_type: UserTokenType,
+        policy_id: &UAString,
+    ) -> Result<&'a UserTokenPolicy, StatusCode> {
+        if !user_identity_tokens
+            .iter()
+            .any(|t| t.token_type == token_type)
+        {
             error!(
-                "Endpoint \"{}\" does not support anonymous authentication",
-                endpoint.path
+                "Endpoint \"{}\" does not support {:?} tokens",
+                endpoint.path, token_type
             );
             Err(StatusCode::BadIdentityTokenRejected)
+        } else if let Some(user_token_policy) = user_identity_tokens
+            .iter()
+            .find(|t| t.token_type == token_type && t.policy_id == *policy_id)
+        {
+            Ok(user_token_policy)
         } else {
-            debug!("Anonymous identity is authenticated");
-            Ok(String::from(crate::server::config::ANONYMOUS_USER_TOKEN_ID))
+            error!("Token doesn't possess the correct policy id");
+            Err(StatusCode::BadIdentityTokenInvalid)
         }
     }
 
-    /// Authenticates the username identity token with the supplied endpoint. The function returns the user token identifier
-    /// that matches the identity token.
+    /// Authenticates the username identity token with the supplied endpoint and the user token
+    /// policy that it conforms to. The function returns the user token identifier that matches
+    /// the identity token.
     fn authenticate_username_identity_token(
         &self,
         config: &ServerConfig,
         endpoint: &ServerEndpoint,
+        user_token_policy: &UserTokenPolicy,
         token: &UserNameIdentityToken,
         server_key: &Option<PrivateKey>,
         server_nonce: &ByteString,
     ) -> Result<String, StatusCode> {
-        if !endpoint.supports_user_pass(&config.user_tokens) {
-            error!("Endpoint doesn't support username password tokens");
-            Err(StatusCode::BadIdentityTokenRejected)
-        } else if token.policy_id != Self::user_pass_security_policy_id(endpoint) {
-            error!("Token doesn't possess the correct policy id");
-            Err(StatusCode::BadIdentityTokenInvalid)
-        } else if token.user_name.is_null() {
+        if token.user_name.is_null() {
             error!("User identify token supplies no user name");
             Err(StatusCode::BadIdentityTokenInvalid)
         } else {
@@ -540,6 +641,23 @@ impl ServerState {
                 token.policy_id.as_ref(),
                 token.encryption_algorithm.as_ref()
             );
+            // The password must be encrypted the way the user token policy says
+            let password_security_policy =
+                Self::user_token_security_policy(endpoint, user_token_policy);
+            let encryption_algorithm = if password_security_policy == SecurityPolicy::None {
+                ""
+            } else {
+                password_security_policy.asymmetric_encryption_algorithm()
+            };
+            if token.encryption_algorithm.as_ref() != encryption_algorithm {
+                error!(
+                    "Identity token password is encrypted with \"{}\" but endpoint \"{}\" expects \"{}\"",
+                    token.encryption_algorithm.as_ref(),
+                    endpoint.path,
+                    encryption_algorithm
+                );
+                return Err(StatusCode::BadIdentityTokenInvalid);
+            }
             let token_password = if !token.encryption_algorithm.is_null() {
                 if let Some(ref server_key) = server_key {
                     user_identity::decrypt_user_identity_token_password(
@@ -591,72 +709,61 @@ impl ServerState {
         }
     }
 
-    /// Authenticate the x509 token against the endpoint. The function returns the user token identifier
-    /// that matches the identity token.
+    /// Authenticate the x509 token against the endpoint and the user token policy that it conforms
+    /// to. The function returns the user token identifier that matches the identity token.
     fn authenticate_x509_identity_token(
         &self,
         config: &ServerConfig,
         endpoint: &ServerEndpoint,
+        user_token_policy: &UserTokenPolicy,
         token: &X509IdentityToken,
         user_token_signature: &SignatureData,
         server_certificate: &Option<X509>,
         server_nonce: &ByteString,
     ) -> Result<String, StatusCode> {
-        if !endpoint.supports_x509(&config.user_tokens) {
-            error!("Endpoint doesn't support x509 tokens");
-            Err(StatusCode::BadIdentityTokenRejected)
-        } else if token.policy_id.as_ref() != POLICY_ID_X509 {
-            error!("Token doesn't possess the correct policy id");
-            Err(StatusCode::BadIdentityTokenRejected)
-        } else {
-            let result = match server_certificate {
-                Some(ref server_certificate) => {
-                    // Find the security policy used for verifying tokens
-                    let user_identity_tokens = self.user_identity_tokens(config, endpoint);
-                    let security_policy = user_identity_tokens
-                        .iter()
-                        .find(|t| t.token_type == UserTokenType::Certificate)
-                        .map(|t| SecurityPolicy::from_uri(t.security_policy_uri.as_ref()))
-                        .unwrap_or_else(|| endpoint.security_policy());
-
-                    // The security policy has to be something that can encrypt
-                    match security_policy {
-                        SecurityPolicy::Unknown | SecurityPolicy::None => {
-                            Err(StatusCode::BadIdentityTokenInvalid)
-                        }
-                        security_policy => {
-                            // Verify token
-                            user_identity::verify_x509_identity_token(
-                                token,
-                                user_token_signature,
-                                security_policy,
-                                server_certificate,
-                                server_nonce.as_ref(),
-                            )
-                        }
+        let result = match server_certificate {
+            Some(ref server_certificate) => {
+                // The token is signed with the security policy of the user token policy
+                let security_policy = Self::user_token_security_policy(endpoint, user_token_policy);
+
+                // The security policy has to be something that can encrypt
+                match security_policy {
+                    SecurityPolicy::Unknown | SecurityPolicy::None => {
+                        Err(StatusCode::BadIdentityTokenInvalid)
+                    }
+                    security_policy => {
+                        // Verify token
+                        user_identity::verify_x509_identity_token(
+                            token,
+                            user_token_signature,
+                            security_policy,
+                            server_certificate,
+                            server_nonce.as_ref(),
+                        )
                     }
                 }
-                None => Err(StatusCode::BadIdentityTokenInvalid),
-            };
-            result.and_then(|_| {
-                // Check the endpoint to see if this token is supported
-                let signing_cert = X509::from_byte_string(&token.certificate_data)?;
-                let signing_thumbprint = signing_cert.thumbprint();
-                for user_token_id in &endpoint.user_token_ids {
-                    if let Some(server_user_token) = config.user_tokens.get(user_token_id) {
-                        if let Some(ref user_thumbprint) = server_user_token.thumbprint {
-                            // The signing cert matches a user's identity, so it is valid
-                            if *user_thumbprint == signing_thumbprint {
-                                return Ok(user_token_id.clone());
-                            }
+            }
+            None => Err(StatusCode::BadIdentityTokenInvalid),
+        };
+        result.and_then(|_| {
+            // Check the endpoint to see if this token is supported
+            let signing_cert = X509::from_byte_string(&token.certificate_data)?;
+            let signing_thumbprint = signing_cert.thumbprint();
+            for user_token_id in &endpoint.user_token_ids {
+                if let Some(server_user_token) = config.user_tokens.get(user_token_id) {
+                    if let Some(ref user_thumbprint) = server_user_token.thumbprint {
+                        // The signing cert matches a user's identity, so it is valid
+                        if *user_thumbprint == signing_thumbprint {
+                            return Ok(user_token_id.clone());
                         }
                     }
                 }
-                Err(StatusCode::BadIdentityTokenInvalid)
-            })
-        }
+            }
+            Err(StatusCode::BadIdentityTokenInvalid)
+        })
     }
 
+    #[cfg(feature = "history")]
     pub fn set_historical_data_provider(
         &mut self,
         historical_data_provider: Box<dyn HistoricalDataProvider + Send + Sync>,
@@ -664,6 +771,7 @@ impl ServerState {
         self.historical_data_provider = Some(historical_data_provider);
     }
 
+    #[cfg(feature = "history")]
     pub fn set_historical_event_provider(
         &mut self,
         historical_event_provider: Box<dyn HistoricalEventProvider + Send + Sync>,
@@ -671,6 +779,7 @@ impl ServerState {
         self.historical_event_provider = Some(historical_event_provider);
     }
 
+    #[cfg(feature = "events")]
     pub(crate) fn raise_and_log<T>(&self, event: T) -> Result<NodeId, ()>
     where
         T: AuditEvent + Event,
@@ -25,6 +25,18 @@ pub struct PublishResponseEntry {
     pub response: SupportedMessage,
 }
 
+impl PublishResponseEntry {
+    /// Drops the notifications of the response, e.g. because the connection is too backed up to
+    /// send them, and marks it `GoodOverload` so the client can tell. The notification message
+    /// stays in the retransmission queue, so the client can still republish it.
+    pub(crate) fn drop_notifications(&mut self) {
+        if let SupportedMessage::PublishResponse(ref mut response) = self.response {
+            response.notification_message.notification_data = None;
+            response.response_header.service_result = StatusCode::GoodOverload;
+        }
+    }
+}
+
 /// This converts an OPC UA Duration into a time duration used for testing for interval elapsed
 fn duration_from_ms(d: f64) -> time::Duration {
     // Duration is a floating point number in millis so turn to microseconds for greater accuracy
@@ -5,25 +5,33 @@
 use std::collections::{BTreeSet, VecDeque};
 use std::result::Result;
 
+use crate::core::record_metric;
+#[cfg(feature = "events")]
+use crate::types::service_types::{EventFieldList, EventFilter};
 use crate::types::{
     node_ids::ObjectId,
     service_types::{
-        DataChangeFilter, EventFieldList, EventFilter, MonitoredItemCreateRequest,
-        MonitoredItemModifyRequest, MonitoredItemNotification, ReadValueId, TimestampsToReturn,
+        DataChangeFilter, MonitoredItemCreateRequest, MonitoredItemModifyRequest,
+        MonitoredItemNotification, ReadValueId, TimestampsToReturn,
     },
     status_code::StatusCode,
     *,
 };
 
+#[cfg(feature = "events")]
 use crate::server::{
-    address_space::{node::Node, AddressSpace, EventNotifier},
+    address_space::{node::Node, EventNotifier},
     events::event_filter,
+};
+use crate::server::{
+    address_space::{node::NodeType, AddressSpace},
     state::ServerState,
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Notification {
     MonitoredItemNotification(MonitoredItemNotification),
+    #[cfg(feature = "events")]
     Event(EventFieldList),
 }
 
@@ -33,6 +41,7 @@ impl From<MonitoredItemNotification> for Notification {
     }
 }
 
+#[cfg(feature = "events")]
 impl From<EventFieldList> for Notification {
     fn from(v: EventFieldList) -> Self {
         Notification::Event(v)
@@ -43,6 +52,7 @@ impl From<EventFieldList> for Notification {
 pub(crate) enum FilterType {
     None,
     DataChangeFilter(DataChangeFilter),
+    #[cfg(feature = "events")]
     EventFilter(EventFilter),
 }
 
@@ -63,9 +73,15 @@ impl FilterType {
                         filter.decode_inner::<DataChangeFilter>(decoding_options)?,
                     ))
                 }
+                #[cfg(feature = "events")]
                 ObjectId::EventFilter_Encoding_DefaultBinary => Ok(FilterType::EventFilter(
                     filter.decode_inner::<EventFilter>(decoding_options)?,
                 )),
+                #[cfg(not(feature = "events"))]
+                ObjectId::EventFilter_Encoding_DefaultBinary => {
+                    error!("Event filters are not supported without the events feature");
+                    Err(StatusCode::BadMonitoredItemFilterUnsupported)
+                }
                 _ => {
                     error!(
                         "Requested data filter type is not supported, {:?}",
@@ -104,6 +120,22 @@ pub(crate) struct MonitoredItem {
     timestamps_to_return: TimestampsToReturn,
     last_sample_time: DateTimeUtc,
     last_data_value: Option<DataValue>,
+    // Set when a notification is queued and cleared when the triggered items are reported, so a
+    // change between two publishes still triggers the items on the next publish.
+    trigger_pending: bool,
+    // Set when this item is linked to a triggering item. The last value is reported on the next
+    // trigger even if it has not changed since.
+    report_last_value_on_trigger: bool,
+    // Set when a client calls ConditionRefresh. The retained conditions are queued on the next
+    // sample between a RefreshStartEvent and a RefreshEndEvent.
+    #[cfg(feature = "events")]
+    condition_refresh: bool,
+    // The number of updates of the monitored value when it was last sampled
+    last_value_updates: Option<u64>,
+    // Updates of the value that were replaced by a later update before they were sampled
+    coalesced_updates: u64,
+    // Notifications that were discarded because the queue was full
+    discarded_notifications: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -148,6 +180,13 @@ impl MonitoredItem {
             timestamps_to_return,
             last_sample_time: *now,
             last_data_value: None,
+            trigger_pending: false,
+            report_last_value_on_trigger: false,
+            #[cfg(feature = "events")]
+            condition_refresh: false,
+            last_value_updates: None,
+            coalesced_updates: 0,
+            discarded_notifications: 0,
             queue_size,
             notification_queue: VecDeque::with_capacity(queue_size),
             queue_overflow: false,
@@ -207,22 +246,65 @@ impl MonitoredItem {
         });
     }
 
+    /// Tests if the item has queued a notification since it last triggered its triggered items
+    /// and clears the flag. A disabled item never triggers.
+    pub fn take_trigger(&mut self) -> bool {
+        let trigger_pending = self.trigger_pending;
+        self.trigger_pending = false;
+        trigger_pending
+            && self.monitoring_mode != MonitoringMode::Disabled
+            && !self.triggered_items.is_empty()
+    }
+
+    /// Called when the item is linked to a triggering item, so it reports its last value when
+    /// it is next triggered.
+    pub fn set_linked_to_trigger(&mut self) {
+        self.report_last_value_on_trigger = true;
+    }
+
+    /// Retrieves the notifications to report when a triggering item triggers this item. From
+    /// Part 4, only an item in sampling mode reports when triggered. It reports its queued
+    /// notifications, or if it has none and was just linked, its last sampled value.
+    pub fn triggered_notifications(&mut self) -> Option<Vec<Notification>> {
+        if self.monitoring_mode != MonitoringMode::Sampling {
+            return None;
+        }
+        if self.report_last_value_on_trigger && self.notification_queue.is_empty() {
+            if let Some(ref last_data_value) = self.last_data_value {
+                let mut value = last_data_value.clone();
+                value.retain_timestamps(self.timestamps_to_return);
+                self.notification_queue
+                    .push_back(Notification::from(MonitoredItemNotification {
+                        client_handle: self.client_handle,
+                        value,
+                    }));
+            }
+        }
+        self.report_last_value_on_trigger = false;
+        self.all_notifications()
+    }
+
     /// Validates the filter associated with the monitored item and returns the filter result
     /// encoded in an extension object.
     pub fn validate_filter(
         &self,
         address_space: &AddressSpace,
     ) -> Result<ExtensionObject, StatusCode> {
-        // Event filter must be validated
-        let filter_result = if let FilterType::EventFilter(ref event_filter) = self.filter {
-            let filter_result = event_filter::validate(event_filter, address_space)?;
-            ExtensionObject::from_encodable(
-                ObjectId::EventFilterResult_Encoding_DefaultBinary,
-                &filter_result,
-            )
-        } else {
+        // Only an event filter is validated against the address space
+        #[cfg(not(feature = "events"))]
+        let _ = address_space;
+        let filter_result = match self.filter {
+            // Event filter must be validated
+            #[cfg(feature = "events")]
+            FilterType::EventFilter(ref event_filter) => {
+                let filter_result = event_filter::validate(event_filter, address_space)?;
+                ExtensionObject::from_encodable(
+                    ObjectId::EventFilterResult_Encoding_DefaultBinary,
+                    &filter_result,
+                )
+            }
             // DataChangeFilter has no result
-            ExtensionObject::null()
+            _ => ExtensionObject::null(),
         };
         Ok(filter_result)
     }
@@ -245,8 +327,8 @@ impl MonitoredItem {
         if self.monitoring_mode == MonitoringMode::Disabled {
             TickResult::NoChange
         } else {
-            let check_value = if resend_data {
-                // Always check for resend_data flag
+            let check_value = if resend_data || self.condition_refresh() {
+                // Always check for resend_data flag or a condition refresh
                 true
             } else if self.sampling_interval < 0f64 {
                 // -1 means use the subscription publishing interval so if the publishing interval elapsed,
@@ -284,6 +366,7 @@ impl MonitoredItem {
     }
 
     /// Gets the event notifier bits for a node, or empty if there are no bits
+    #[cfg(feature = "events")]
     fn get_event_notifier(node: &dyn Node) -> EventNotifier {
         if let Some(v) = node.get_attribute(
             TimestampsToReturn::Neither,
@@ -302,6 +385,7 @@ impl MonitoredItem {
     }
 
     /// Check for
+    #[cfg(feature = "events")]
     fn check_for_events(
         &mut self,
         address_space: &AddressSpace,
@@ -313,19 +397,29 @@ impl MonitoredItem {
                 // Node has to allow subscribe to events
                 if Self::get_event_notifier(node).contains(EventNotifier::SUBSCRIBE_TO_EVENTS) {
                     let object_id = node.node_id();
-                    if let Some(events) = event_filter::evaluate(
+                    let events = event_filter::evaluate(
                         &object_id,
                         filter,
                         address_space,
                         happened_since,
                         self.client_handle,
-                    ) {
+                    );
+                    // The retained conditions of a condition refresh are queued ahead of new events
+                    let refreshed = if self.condition_refresh {
+                        let filter = filter.clone();
+                        self.condition_refresh = false;
+                        self.enqueue_retained_conditions(&filter, &object_id, address_space);
+                        true
+                    } else {
+                        false
+                    };
+                    if let Some(events) = events {
                         events
                             .into_iter()
                             .for_each(|event| self.enqueue_notification_message(event));
                         true
                     } else {
-                        false
+                        refreshed
                     }
                 } else {
                     false
@@ -335,19 +429,60 @@ impl MonitoredItem {
         }
     }
 
+    /// Queues the retained conditions of the object between a refresh start and a refresh end event.
+    /// The start and end events are queued whatever the where clause of the filter.
+    #[cfg(feature = "events")]
+    fn enqueue_retained_conditions(
+        &mut self,
+        filter: &EventFilter,
+        object_id: &NodeId,
+        address_space: &AddressSpace,
+    ) {
+        let now = DateTime::now();
+        self.enqueue_notification_message(event_filter::refresh_start_event(
+            filter,
+            self.client_handle,
+            &now,
+        ));
+        if let Some(conditions) = event_filter::evaluate_retained_conditions(
+            object_id,
+            filter,
+            address_space,
+            self.client_handle,
+        ) {
+            conditions
+                .into_iter()
+                .for_each(|condition| self.enqueue_notification_message(condition));
+        }
+        self.enqueue_notification_message(event_filter::refresh_end_event(
+            filter,
+            self.client_handle,
+            &now,
+        ));
+    }
+
     fn check_for_data_change(
         &mut self,
         _address_space: &AddressSpace,
+        now: &DateTimeUtc,
         resend_data: bool,
         attribute_id: AttributeId,
-        node: &dyn Node,
+        node: &NodeType,
     ) -> bool {
-        let data_value = node.get_attribute(
-            TimestampsToReturn::Neither,
-            attribute_id,
-            NumericRange::None,
-            &QualifiedName::null(),
-        );
+        // The value is sampled with both timestamps, since a filter may compare them. The getter
+        // of a value is only called if it polls.
+        let data_value = match node {
+            NodeType::Variable(variable) if attribute_id == AttributeId::Value => {
+                self.count_coalesced_updates(variable.value_updates());
+                Some(variable.sample_value())
+            }
+            _ => node.as_node().get_attribute(
+                TimestampsToReturn::Both,
+                attribute_id,
+                NumericRange::None,
+                &QualifiedName::null(),
+            ),
+        };
         if let Some(mut data_value) = data_value {
             // Test for data change
             let data_change = if resend_data {
@@ -361,6 +496,7 @@ impl MonitoredItem {
                     FilterType::DataChangeFilter(ref filter) => {
                         !filter.compare(&data_value, last_data_value, None)
                     }
+                    #[cfg(feature = "events")]
                     _ => {
                         // Unrecognized filter
                         false
@@ -384,26 +520,10 @@ impl MonitoredItem {
                 // Store current data value to compare against on the next tick
                 self.last_data_value = Some(data_value.clone());
 
-                // Strip out timestamps that subscriber is not interested in
-                match self.timestamps_to_return {
-                    TimestampsToReturn::Neither | TimestampsToReturn::Invalid => {
-                        data_value.source_timestamp = None;
-                        data_value.source_picoseconds = None;
-                        data_value.server_timestamp = None;
-                        data_value.server_picoseconds = None
-                    }
-                    TimestampsToReturn::Server => {
-                        data_value.source_timestamp = None;
-                        data_value.source_picoseconds = None;
-                    }
-                    TimestampsToReturn::Source => {
-                        data_value.server_timestamp = None;
-                        data_value.server_picoseconds = None
-                    }
-                    TimestampsToReturn::Both => {
-                        // DO NOTHING
-                    }
-                }
+                // The server timestamp is the time that the value was sampled, and timestamps
+                // that the subscriber is not interested in are stripped out
+                data_value.set_server_time(*now);
+                data_value.retain_timestamps(self.timestamps_to_return);
 
                 // Enqueue notification message
                 let client_handle = self.client_handle;
@@ -425,10 +545,16 @@ impl MonitoredItem {
         }
     }
 
-    fn is_event_filter(&self) -> bool {
+    #[cfg(feature = "events")]
+    pub(crate) fn is_event_filter(&self) -> bool {
         matches!(self.filter, FilterType::EventFilter(_))
     }
 
+    #[cfg(not(feature = "events"))]
+    pub(crate) fn is_event_filter(&self) -> bool {
+        false
+    }
+
     /// Fetches the most recent value of the monitored item from the source and compares
     /// it to the last value. If the value has changed according to a filter / equality
     /// check, the latest value and its timestamps will be stored in the monitored item.
@@ -446,19 +572,24 @@ impl MonitoredItem {
         let changed = if let Some(node) = address_space.find_node(&self.item_to_monitor.node_id) {
             match AttributeId::from_u32(self.item_to_monitor.attribute_id) {
                 Ok(attribute_id) => {
-                    let node = node.as_node();
                     match self.filter {
+                        #[cfg(feature = "events")]
                         FilterType::EventFilter(_) => {
                             // EventFilter is only relevant on the EventNotifier attribute
                             if attribute_id == AttributeId::EventNotifier {
                                 let happened_since = self.last_sample_time;
-                                self.check_for_events(address_space, &happened_since, node)
+                                self.check_for_events(
+                                    address_space,
+                                    &happened_since,
+                                    node.as_node(),
+                                )
                             } else {
                                 false
                             }
                         }
                         _ => self.check_for_data_change(
                             address_space,
+                            now,
                             resend_data,
                             attribute_id,
                             node,
@@ -485,11 +616,39 @@ impl MonitoredItem {
         changed
     }
 
+    /// Counts the updates of a pushed value since the last sample, other than the latest, as
+    /// coalesced. The sample only sees the latest value, however fast the application pushes.
+    fn count_coalesced_updates(&mut self, value_updates: u64) {
+        if let Some(last_value_updates) = self.last_value_updates {
+            let coalesced = value_updates.saturating_sub(last_value_updates + 1);
+            if coalesced > 0 {
+                self.coalesced_updates += coalesced;
+                record_metric!(on_updates_coalesced(coalesced as usize));
+            }
+        }
+        self.last_value_updates = Some(value_updates);
+    }
+
+    /// Counts notifications that were discarded from, or never made it into, a full queue.
+    fn count_discarded_notifications(&mut self, discarded: u64) {
+        self.discarded_notifications += discarded;
+        record_metric!(on_notifications_discarded(discarded as usize));
+    }
+
     /// Enqueues a notification message for the monitored item
     pub fn enqueue_notification_message<T>(&mut self, notification: T)
     where
         T: Into<Notification>,
     {
+        let notification = notification.into();
+        #[cfg(feature = "events")]
+        if self.notification_queue.len() == self.queue_size && self.queue_size > 1 {
+            if let Notification::Event(_) = notification {
+                self.enqueue_overflowing_event(notification);
+                self.trigger_pending = true;
+                return;
+            }
+        }
         // test for overflow
         let overflow = if self.notification_queue.len() == self.queue_size {
             trace!(
@@ -497,6 +656,7 @@ impl MonitoredItem {
                 self.item_to_monitor.node_id
             );
             // Overflow behaviour
+            self.count_discarded_notifications(1);
             if self.discard_oldest {
                 // Throw away oldest item (the one at the start) to make space at the end
                 let _ = self.notification_queue.pop_front();
@@ -509,16 +669,69 @@ impl MonitoredItem {
         } else {
             false
         };
-        let mut notification = notification.into();
+        let mut notification = notification;
         if overflow {
-            if let Notification::MonitoredItemNotification(ref mut notification) = notification {
-                // Set the overflow bit on the data value's status
-                notification.value.status =
-                    Some(notification.value.status() | StatusCode::OVERFLOW);
+            match notification {
+                Notification::MonitoredItemNotification(ref mut notification) => {
+                    // Set the overflow bit on the data value's status
+                    notification.value.status =
+                        Some(notification.value.status() | StatusCode::OVERFLOW);
+                }
+                #[cfg(feature = "events")]
+                Notification::Event(_) => {}
             }
             self.queue_overflow = true;
         }
         self.notification_queue.push_back(notification);
+        self.trigger_pending = true;
+    }
+
+    /// Enqueues an event when the queue is full. The first time the queue overflows, an event of
+    /// type `EventQueueOverflowEventType` takes the place of a discarded event so the client can
+    /// tell that events were lost. It goes at the start of the queue when the oldest events are
+    /// discarded, or at the end otherwise, and is never discarded itself.
+    #[cfg(feature = "events")]
+    fn enqueue_overflowing_event(&mut self, event: Notification) {
+        trace!(
+            "Event queue overflow, node {:?}",
+            self.item_to_monitor.node_id
+        );
+        let overflow_event = if self.queue_overflow {
+            None
+        } else if let FilterType::EventFilter(ref event_filter) = self.filter {
+            Some(Notification::Event(event_filter::overflow_event(
+                event_filter,
+                self.client_handle,
+                &DateTime::now(),
+            )))
+        } else {
+            panic!("Event queued on a monitored item without an event filter")
+        };
+        let discarded = if self.discard_oldest {
+            let discarded = if let Some(overflow_event) = overflow_event {
+                // The two oldest events make way for the overflow event and the new event
+                let _ = self.notification_queue.pop_front();
+                let _ = self.notification_queue.pop_front();
+                self.notification_queue.push_front(overflow_event);
+                2
+            } else {
+                // Discard the oldest event after the overflow event
+                let _ = self.notification_queue.remove(1);
+                1
+            };
+            self.notification_queue.push_back(event);
+            discarded
+        } else if let Some(overflow_event) = overflow_event {
+            // The overflow event replaces the latest event and the new event is discarded
+            let _ = self.notification_queue.pop_back();
+            self.notification_queue.push_back(overflow_event);
+            2
+        } else {
+            // The new event is discarded
+            1
+        };
+        self.count_discarded_notifications(discarded);
+        self.queue_overflow = true;
     }
 
     /// Gets the oldest notification message from the notification queue
@@ -553,7 +766,8 @@ impl MonitoredItem {
             // From spec "any negative number is interpreted as -1"
             // -1 means monitored item's sampling interval defaults to the subscription's publishing interval
             -1.0
-        } else if requested_sampling_interval == 0.0
+        } else if requested_sampling_interval.is_nan()
+            || requested_sampling_interval == 0.0
             || requested_sampling_interval < server_state.min_sampling_interval_ms
         {
             server_state.min_sampling_interval_ms
@@ -562,6 +776,35 @@ impl MonitoredItem {
         }
     }
 
+    /// Raises the sampling interval to the `MinimumSamplingInterval` of the monitored variable so a
+    /// value is not sampled faster than its source can provide it. An item that samples at the
+    /// publishing interval is given the minimum instead if the publishing interval is faster.
+    /// A minimum of 0 means the value may be sampled as fast as the server allows.
+    pub fn clamp_to_minimum_sampling_interval(
+        &mut self,
+        address_space: &AddressSpace,
+        publishing_interval: Duration,
+    ) {
+        if self.item_to_monitor.attribute_id != AttributeId::Value as u32 {
+            return;
+        }
+        let minimum_sampling_interval = match address_space.find_node(&self.item_to_monitor.node_id)
+        {
+            Some(NodeType::Variable(variable)) => variable.minimum_sampling_interval(),
+            _ => None,
+        };
+        if let Some(minimum_sampling_interval) = minimum_sampling_interval {
+            let sampling_interval = if self.sampling_interval < 0f64 {
+                publishing_interval
+            } else {
+                self.sampling_interval
+            };
+            if minimum_sampling_interval > 0f64 && sampling_interval < minimum_sampling_interval {
+                self.sampling_interval = minimum_sampling_interval;
+            }
+        }
+    }
+
     /// Takes the requested queue size and ensures it is within the range supported by the server
     fn sanitize_queue_size(server_state: &ServerState, requested_queue_size: usize) -> usize {
         if requested_queue_size == 0 || requested_queue_size == 1 {
@@ -598,14 +841,23 @@ impl MonitoredItem {
         self.monitoring_mode = monitoring_mode;
     }
 
-    pub fn monitoring_mode(&self) -> MonitoringMode {
-        self.monitoring_mode
-    }
-
     pub fn queue_size(&self) -> usize {
         self.queue_size
     }
 
+    /// Returns the number of updates of the value that were coalesced, i.e. replaced by a later
+    /// update before the item sampled them.
+    #[cfg(test)]
+    pub fn coalesced_updates(&self) -> u64 {
+        self.coalesced_updates
+    }
+
+    /// Returns the number of notifications that were discarded because the queue was full.
+    #[cfg(test)]
+    pub fn discarded_notifications(&self) -> u64 {
+        self.discarded_notifications
+    }
+
     #[cfg(test)]
     pub fn queue_overflow(&self) -> bool {
         self.queue_overflow
@@ -616,6 +868,22 @@ impl MonitoredItem {
         &self.notification_queue
     }
 
+    /// Requests the retained conditions to be queued on the next sample of an item for events.
+    #[cfg(all(feature = "events", feature = "generated-address-space"))]
+    pub(crate) fn set_condition_refresh(&mut self) {
+        self.condition_refresh = true;
+    }
+
+    #[cfg(feature = "events")]
+    pub(crate) fn condition_refresh(&self) -> bool {
+        self.condition_refresh
+    }
+
+    #[cfg(not(feature = "events"))]
+    pub(crate) fn condition_refresh(&self) -> bool {
+        false
+    }
+
     #[cfg(test)]
     pub(crate) fn set_discard_oldest(&mut self, discard_oldest: bool) {
         self.discard_oldest = discard_oldest;
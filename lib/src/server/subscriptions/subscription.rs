@@ -3,10 +3,13 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 use std::collections::{BTreeSet, HashMap, VecDeque};
+#[cfg(feature = "diagnostics")]
 use std::sync::Arc;
 
+#[cfg(feature = "diagnostics")]
 use crate::sync::*;
 use crate::types::{
+    clock,
     service_types::{
         MonitoredItemCreateRequest, MonitoredItemCreateResult, MonitoredItemModifyRequest,
         MonitoredItemModifyResult, NotificationMessage, TimestampsToReturn,
@@ -17,10 +20,11 @@ use crate::types::{
 
 use crate::core::handle::Handle;
 
+#[cfg(feature = "diagnostics")]
+use crate::server::diagnostics::ServerDiagnostics;
 use crate::server::{
     address_space::AddressSpace,
     constants,
-    diagnostics::ServerDiagnostics,
     state::ServerState,
     subscriptions::monitored_item::{MonitoredItem, Notification, TickResult},
 };
@@ -84,6 +88,8 @@ pub(crate) enum HandledState {
 /// This is for debugging purposes. It allows the caller to validate the output state if required.
 #[derive(Debug)]
 pub(crate) struct UpdateStateResult {
+    // Only the tests look at which state was handled
+    #[allow(dead_code)]
     pub handled_state: HandledState,
     pub update_state_action: UpdateStateAction,
 }
@@ -140,6 +146,9 @@ pub struct Subscription {
     /// A flag that tells the subscription to send the latest value of every monitored item on the
     /// next publish request.
     resend_data: bool,
+    /// The lifetime in hours of a durable subscription, which is parked when its session goes
+    /// away even if orphaned subscriptions are deleted
+    durable_lifetime_in_hours: Option<u32>,
     /// The next sequence number to be sent
     sequence_number: Handle,
     /// Last notification's sequence number. This is a sanity check since sequence numbers should start from
@@ -154,13 +163,19 @@ pub struct Subscription {
     #[serde(skip)]
     notifications: VecDeque<NotificationMessage>,
     /// Server diagnostics to track creation / destruction / modification of the subscription
+    #[cfg(feature = "diagnostics")]
     #[serde(skip)]
     diagnostics: Arc<RwLock<ServerDiagnostics>>,
     /// Stops the subscription calling diagnostics on drop
+    #[cfg(feature = "diagnostics")]
     #[serde(skip)]
     diagnostics_on_drop: bool,
+    /// The preferred locales of the session for human readable text in notifications
+    #[serde(skip)]
+    locale_ids: Vec<UAString>,
 }
 
+#[cfg(feature = "diagnostics")]
 impl Drop for Subscription {
     fn drop(&mut self) {
         if self.diagnostics_on_drop {
@@ -171,20 +186,29 @@ impl Drop for Subscription {
 }
 
 impl Subscription {
+    /// Creates a subscription for up to `max_monitored_items` monitored items, 0 for no limit. The
+    /// room for a limited number of monitored items is allocated up front, so the map never grows.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        diagnostics: Arc<RwLock<ServerDiagnostics>>,
+        #[cfg(feature = "diagnostics")] diagnostics: Arc<RwLock<ServerDiagnostics>>,
         subscription_id: u32,
         publishing_enabled: bool,
         publishing_interval: Duration,
         lifetime_counter: u32,
         keep_alive_counter: u32,
         priority: u8,
+        max_monitored_items: usize,
     ) -> Subscription {
+        let monitored_items_capacity = if max_monitored_items > 0 {
+            max_monitored_items
+        } else {
+            constants::DEFAULT_MONITORED_ITEM_CAPACITY
+        };
         let subscription = Subscription {
             subscription_id,
             publishing_interval,
             priority,
-            monitored_items: HashMap::with_capacity(constants::DEFAULT_MONITORED_ITEM_CAPACITY),
+            monitored_items: HashMap::with_capacity(monitored_items_capacity),
             max_lifetime_counter: lifetime_counter,
             max_keep_alive_counter: keep_alive_counter,
             // State variables
@@ -194,15 +218,20 @@ impl Subscription {
             first_message_sent: false,
             publishing_enabled,
             resend_data: false,
+            durable_lifetime_in_hours: None,
             // Counters for new items
             sequence_number: Handle::new(1),
             last_sequence_number: 0,
             next_monitored_item_id: 1,
-            last_time_publishing_interval_elapsed: chrono::Utc::now(),
+            last_time_publishing_interval_elapsed: clock::now(),
             notifications: VecDeque::with_capacity(100),
+            #[cfg(feature = "diagnostics")]
             diagnostics,
+            #[cfg(feature = "diagnostics")]
             diagnostics_on_drop: true,
+            locale_ids: Vec::new(),
         };
+        #[cfg(feature = "diagnostics")]
         {
             let mut diagnostics = trace_write_lock!(subscription.diagnostics);
             diagnostics.on_create_subscription(&subscription);
@@ -259,12 +288,19 @@ impl Subscription {
                         server_state,
                         item_to_create,
                     ) {
-                        Ok(monitored_item) => {
+                        Ok(mut monitored_item) => {
+                            monitored_item.clamp_to_minimum_sampling_interval(
+                                address_space,
+                                self.publishing_interval,
+                            );
                             if server_state.max_monitored_items_per_sub == 0
                                 || self.monitored_items.len()
-                                    <= server_state.max_monitored_items_per_sub
+                                    < server_state.max_monitored_items_per_sub
                             {
-                                let revised_sampling_interval = monitored_item.sampling_interval();
+                                let revised_sampling_interval = Self::revised_sampling_interval(
+                                    &monitored_item,
+                                    self.publishing_interval,
+                                );
                                 let revised_queue_size = monitored_item.queue_size() as u32;
                                 // Validate the filter before registering the item
                                 match monitored_item.validate_filter(address_space) {
@@ -299,6 +335,19 @@ impl Subscription {
             .collect()
     }
 
+    /// Returns the sampling interval that the monitored item actually samples at, which is the
+    /// publishing interval of the subscription if the client asked for -1.
+    fn revised_sampling_interval(
+        monitored_item: &MonitoredItem,
+        publishing_interval: Duration,
+    ) -> Duration {
+        if monitored_item.sampling_interval() < 0f64 {
+            publishing_interval
+        } else {
+            monitored_item.sampling_interval()
+        }
+    }
+
     /// Modify the specified monitored items, returning a result for each
     pub fn modify_monitored_items(
         &mut self,
@@ -308,6 +357,7 @@ impl Subscription {
         items_to_modify: &[MonitoredItemModifyRequest],
     ) -> Vec<MonitoredItemModifyResult> {
         self.reset_lifetime_counter();
+        let publishing_interval = self.publishing_interval;
         items_to_modify
             .iter()
             .map(|item_to_modify| {
@@ -323,10 +373,15 @@ impl Subscription {
                             timestamps_to_return,
                             item_to_modify,
                         );
+                        monitored_item
+                            .clamp_to_minimum_sampling_interval(address_space, publishing_interval);
                         match modify_result {
                             Ok(filter_result) => MonitoredItemModifyResult {
                                 status_code: StatusCode::Good,
-                                revised_sampling_interval: monitored_item.sampling_interval(),
+                                revised_sampling_interval: Self::revised_sampling_interval(
+                                    monitored_item,
+                                    publishing_interval,
+                                ),
                                 revised_queue_size: monitored_item.queue_size() as u32,
                                 filter_result,
                             },
@@ -400,6 +455,41 @@ impl Subscription {
         self.resend_data = true;
     }
 
+    /// Requests a refresh of the retained conditions of every monitored item for events, or of a
+    /// single item if its id is supplied. The conditions are queued on the next sample of each item.
+    /// A refresh fails with `BadRefreshInProgress` while a refresh of the same item is pending.
+    #[cfg(all(feature = "events", feature = "generated-address-space"))]
+    pub(crate) fn set_condition_refresh(
+        &mut self,
+        monitored_item_id: Option<u32>,
+    ) -> Result<(), StatusCode> {
+        if let Some(monitored_item_id) = monitored_item_id {
+            match self.monitored_items.get_mut(&monitored_item_id) {
+                Some(monitored_item) if monitored_item.is_event_filter() => {
+                    if monitored_item.condition_refresh() {
+                        Err(StatusCode::BadRefreshInProgress)
+                    } else {
+                        monitored_item.set_condition_refresh();
+                        Ok(())
+                    }
+                }
+                _ => Err(StatusCode::BadMonitoredItemIdInvalid),
+            }
+        } else if self
+            .monitored_items
+            .values()
+            .any(|monitored_item| monitored_item.condition_refresh())
+        {
+            Err(StatusCode::BadRefreshInProgress)
+        } else {
+            self.monitored_items
+                .values_mut()
+                .filter(|monitored_item| monitored_item.is_event_filter())
+                .for_each(|monitored_item| monitored_item.set_condition_refresh());
+            Ok(())
+        }
+    }
+
     /// Tests if the publishing interval has elapsed since the last time this function in which case
     /// it returns `true` and updates its internal state.
     fn test_and_set_publishing_interval_elapsed(&mut self, now: &DateTimeUtc) -> bool {
@@ -495,7 +585,10 @@ impl Subscription {
             );
         }
         // debug!("Enqueuing notification {:?}", notification);
-        self.last_sequence_number = notification.sequence_number;
+        if notification.notification_data.is_some() {
+            // A keep alive does not use up its sequence number
+            self.last_sequence_number = notification.sequence_number;
+        }
         self.notifications.push_back(notification);
     }
 
@@ -508,7 +601,15 @@ impl Subscription {
         // Now act on the state's action
         match update_state_result.update_state_action {
             UpdateStateAction::None => {
-                if let Some(ref notification) = notification {
+                if self.state == SubscriptionState::Late {
+                    // There was no publish request for the notification, so it waits for one
+                    // instead of being dropped. When requests are scarce, the subscriptions of
+                    // the session are given them in order of priority, and the session discards
+                    // the oldest if too many are waiting.
+                    if let Some(notification) = notification {
+                        self.enqueue_notification(notification);
+                    }
+                } else if let Some(ref notification) = notification {
                     // Reset the next sequence number to the discarded notification
                     let notification_sequence_number = notification.sequence_number;
                     self.sequence_number.set_next(notification_sequence_number);
@@ -523,12 +624,13 @@ impl Subscription {
                     self.sequence_number.set_next(notification_sequence_number);
                     debug!("Notification message nr {} was being ignored for a keep alive, update state was {:?}", notification_sequence_number, update_state_result);
                 }
-                // Send a keep alive
+                // Send a keep alive. It holds the sequence number of the next notification
+                // message without using it up.
                 debug!("Sending keep alive response");
-                let notification = NotificationMessage::keep_alive(
-                    self.sequence_number.next(),
-                    DateTime::from(*now),
-                );
+                let sequence_number = self.sequence_number.next();
+                self.sequence_number.set_next(sequence_number);
+                let notification =
+                    NotificationMessage::keep_alive(sequence_number, DateTime::from(*now));
                 self.enqueue_notification(notification);
             }
             UpdateStateAction::ReturnNotifications => {
@@ -551,21 +653,52 @@ impl Subscription {
                 }
                 // Delete the monitored items, issue a status change for the subscription
                 debug!("Subscription status change to closed / timeout");
-                self.monitored_items.clear();
-                let notification = NotificationMessage::status_change(
-                    self.sequence_number.next(),
-                    DateTime::from(*now),
-                    StatusCode::BadTimeout,
-                );
-                self.enqueue_notification(notification);
+                self.close(now, StatusCode::BadTimeout);
             }
         }
     }
 
+    /// Closes the subscription, deleting its monitored items and queuing a status change
+    /// notification with the supplied status for the client. The subscription is removed once
+    /// the notification has been sent.
+    pub(crate) fn close(&mut self, now: &DateTimeUtc, status: StatusCode) {
+        self.state = SubscriptionState::Closed;
+        self.monitored_items.clear();
+        let notification = NotificationMessage::status_change(
+            self.sequence_number.next(),
+            DateTime::from(*now),
+            status,
+        );
+        self.enqueue_notification(notification);
+    }
+
+    /// Creates the status change notification which tells the session that the subscription
+    /// belonged to that it has been transferred to another session. The notification uses up the
+    /// next sequence number of the subscription.
+    pub(crate) fn transferred_notification(&mut self, now: &DateTimeUtc) -> NotificationMessage {
+        let sequence_number = self.sequence_number.next();
+        self.last_sequence_number = sequence_number;
+        NotificationMessage::status_change(
+            sequence_number,
+            DateTime::from(*now),
+            StatusCode::GoodSubscriptionTransferred,
+        )
+    }
+
     pub(crate) fn take_notification(&mut self) -> Option<NotificationMessage> {
         self.notifications.pop_front()
     }
 
+    /// Returns the number of notifications waiting for a publish request
+    pub(crate) fn queued_notification_count(&self) -> usize {
+        self.notifications.len()
+    }
+
+    /// Returns the publish time of the oldest notification waiting for a publish request
+    pub(crate) fn oldest_notification_time(&self) -> Option<DateTime> {
+        self.notifications.front().map(|n| n.publish_time)
+    }
+
     // See OPC UA Part 4 5.13.1.2 State Table
     //
     // This function implements the main guts of updating the subscription's state according to
@@ -632,15 +765,15 @@ impl Subscription {
 
         // more state tests that match on more than one state
         match self.state {
-            SubscriptionState::Normal | SubscriptionState::Late | SubscriptionState::KeepAlive => {
-                if self.lifetime_counter == 1 {
-                    // State #27
-                    self.state = SubscriptionState::Closed;
-                    return UpdateStateResult::new(
-                        HandledState::Closed27,
-                        UpdateStateAction::SubscriptionExpired,
-                    );
-                }
+            SubscriptionState::Normal | SubscriptionState::Late | SubscriptionState::KeepAlive
+                if p.publishing_timer_expired && self.lifetime_counter == 1 =>
+            {
+                // State #27
+                self.state = SubscriptionState::Closed;
+                return UpdateStateResult::new(
+                    HandledState::Closed27,
+                    UpdateStateAction::SubscriptionExpired,
+                );
             }
             _ => {
                 // DO NOTHING
@@ -734,6 +867,7 @@ impl Subscription {
             }
             SubscriptionState::Late => {
                 if tick_reason == TickReason::ReceivePublishRequest
+                    && p.publishing_req_queued
                     && self.publishing_enabled
                     && (p.notifications_available || p.more_notifications)
                 {
@@ -746,6 +880,7 @@ impl Subscription {
                         UpdateStateAction::ReturnNotifications,
                     );
                 } else if tick_reason == TickReason::ReceivePublishRequest
+                    && p.publishing_req_queued
                     && (!self.publishing_enabled
                         || (self.publishing_enabled
                             && !p.notifications_available
@@ -778,6 +913,8 @@ impl Subscription {
                     && p.publishing_req_queued
                 {
                     // State #14
+                    self.reset_lifetime_counter();
+                    self.start_publishing_timer();
                     self.first_message_sent = true;
                     self.state = SubscriptionState::Normal;
                     return UpdateStateResult::new(
@@ -788,9 +925,10 @@ impl Subscription {
                     && p.publishing_req_queued
                     && self.keep_alive_counter == 1
                     && (!self.publishing_enabled
-                        || (self.publishing_enabled && p.notifications_available))
+                        || (self.publishing_enabled && !p.notifications_available))
                 {
                     // State #15
+                    self.reset_lifetime_counter();
                     self.start_publishing_timer();
                     self.reset_keep_alive_counter();
                     return UpdateStateResult::new(
@@ -851,85 +989,34 @@ impl Subscription {
         let mut monitored_item_notifications = Vec::with_capacity(self.monitored_items.len() * 2);
 
         for monitored_item in self.monitored_items.values_mut() {
-            // If this returns true then the monitored item wants to report its notification
-            let monitoring_mode = monitored_item.monitoring_mode();
-            match monitored_item.tick(now, address_space, publishing_interval_elapsed, resend_data)
-            {
-                TickResult::ReportValueChanged => {
-                    if publishing_interval_elapsed {
-                        // If this monitored item has triggered items, then they need to be handled
-                        match monitoring_mode {
-                            MonitoringMode::Reporting => {
-                                // From triggering docs
-                                // If the monitoring mode of the triggering item is REPORTING, then it is reported when the
-                                // triggering item triggers the items to report.
-                                monitored_item.triggered_items().iter().for_each(|i| {
-                                    triggered_items.insert(*i);
-                                })
-                            }
-                            _ => {
-                                // Sampling should have gone in the other branch. Disabled shouldn't do anything.
-                                panic!("How can there be changes to report when monitored item is in this monitoring mode {:?}", monitoring_mode);
-                            }
-                        }
-                        // Take some / all of the monitored item's pending notifications
-                        if let Some(mut item_notification_messages) =
-                            monitored_item.all_notifications()
-                        {
-                            monitored_item_notifications.append(&mut item_notification_messages);
-                        }
-                    }
+            let tick_result =
+                monitored_item.tick(now, address_space, publishing_interval_elapsed, resend_data);
+            if publishing_interval_elapsed {
+                // From triggering docs
+                // An item in reporting or sampling mode triggers its triggered items when it has
+                // queued a notification since the last publish. Only an item in reporting mode is
+                // reported itself.
+                if monitored_item.take_trigger() {
+                    triggered_items.extend(monitored_item.triggered_items().iter());
                 }
-                TickResult::ValueChanged => {
-                    // The monitored item doesn't have changes to report but its value did change so it
-                    // is still necessary to check its triggered items.
-                    if publishing_interval_elapsed {
-                        match monitoring_mode {
-                            MonitoringMode::Sampling => {
-                                // If the monitoring mode of the triggering item is SAMPLING, then it is not reported when the
-                                // triggering item triggers the items to report.
-                                monitored_item.triggered_items().iter().for_each(|i| {
-                                    triggered_items.insert(*i);
-                                })
-                            }
-                            _ => {
-                                // Reporting should have gone in the other branch. Disabled shouldn't do anything.
-                                panic!("How can there be a value change when the mode is not sampling?");
-                            }
-                        }
+                if tick_result == TickResult::ReportValueChanged {
+                    // Take some / all of the monitored item's pending notifications
+                    if let Some(mut item_notification_messages) = monitored_item.all_notifications()
+                    {
+                        monitored_item_notifications.append(&mut item_notification_messages);
                     }
                 }
-                TickResult::NoChange => {
-                    // Ignore
-                }
             }
         }
 
-        // Are there any triggered items to force a change on?
+        // Are there any triggered items to report?
         triggered_items.iter().for_each(|i| {
             if let Some(ref mut monitored_item) = self.monitored_items.get_mut(i) {
-                // Check the monitoring mode of the item to report
-                match monitored_item.monitoring_mode() {
-                    MonitoringMode::Sampling => {
-                        // If the monitoring mode of the item to report is SAMPLING, then it is reported when the
-                        // triggering item triggers the i tems to report.
-                        //
-                        // Call with the resend_data flag as true to force the monitored item to
-                        monitored_item.check_value(address_space, now, true);
-                        if let Some(mut notifications) = monitored_item.all_notifications() {
-                            monitored_item_notifications.append(&mut notifications);
-                        }
-                    }
-                    MonitoringMode::Reporting => {
-                        // If the monitoring mode of the item to report is REPORTING, this effectively causes the
-                        // triggering item to be ignored. All notifications of the items to report are sent after the
-                        // publishing interval expires.
-                        //
-                        // DO NOTHING
-                    }
-                    MonitoringMode::Disabled => {
-                        // DO NOTHING
-                    }
+                // If the monitoring mode of the item to report is SAMPLING, then it is reported when the
+                // triggering item triggers the items to report. If it is REPORTING, this effectively causes the
+                // triggering item to be ignored. A DISABLED item does nothing.
+                if let Some(mut notifications) = monitored_item.triggered_notifications() {
+                    monitored_item_notifications.append(&mut notifications);
                 }
             } else {
                 // It is possible that a monitored item contains a triggered id which has been deleted, so silently
@@ -937,6 +1024,22 @@ impl Subscription {
             }
         });
 
+        // Human readable text is sent in the locale of the session
+        let localization = address_space.localization();
+        if !self.locale_ids.is_empty() && !localization.is_empty() {
+            monitored_item_notifications
+                .iter_mut()
+                .for_each(|notification| match notification {
+                    Notification::MonitoredItemNotification(notification) => {
+                        localization.localize_data_change(notification, &self.locale_ids)
+                    }
+                    #[cfg(feature = "events")]
+                    Notification::Event(event) => {
+                        localization.localize_event(event, &self.locale_ids)
+                    }
+                });
+        }
+
         // Produce a data change notification
         if !monitored_item_notifications.is_empty() {
             let next_sequence_number = self.sequence_number.next();
@@ -950,17 +1053,15 @@ impl Subscription {
             // Collect all datachange notifications
             let data_change_notifications = monitored_item_notifications
                 .iter()
-                .filter(|v| matches!(v, Notification::MonitoredItemNotification(_)))
-                .map(|v| {
-                    if let Notification::MonitoredItemNotification(v) = v {
-                        v.clone()
-                    } else {
-                        panic!()
-                    }
+                .filter_map(|v| match v {
+                    Notification::MonitoredItemNotification(v) => Some(v.clone()),
+                    #[cfg(feature = "events")]
+                    _ => None,
                 })
                 .collect();
 
             // Collect event notifications
+            #[cfg(feature = "events")]
             let event_notifications = monitored_item_notifications
                 .iter()
                 .filter(|v| matches!(v, Notification::Event(_)))
@@ -972,6 +1073,8 @@ impl Subscription {
                     }
                 })
                 .collect();
+            #[cfg(not(feature = "events"))]
+            let event_notifications = Vec::new();
 
             // Make a notification
             let notification = NotificationMessage::data_change(
@@ -1027,7 +1130,6 @@ impl Subscription {
         self.keep_alive_counter = keep_alive_counter;
     }
 
-    #[cfg(test)]
     pub(crate) fn state(&self) -> SubscriptionState {
         self.state
     }
@@ -1071,6 +1173,37 @@ impl Subscription {
         self.max_lifetime_counter = max_lifetime_count;
     }
 
+    /// Tests if the subscription has been made durable by `SetSubscriptionDurable`.
+    pub fn is_durable(&self) -> bool {
+        self.durable_lifetime_in_hours.is_some()
+    }
+
+    /// Makes the subscription durable with a lifetime in hours, which sets its lifetime count
+    /// for the publishing interval. A subscription can only be made durable before it has
+    /// monitored items, otherwise the call fails with `BadInvalidState`.
+    #[cfg(feature = "generated-address-space")]
+    pub(crate) fn set_durable(&mut self, lifetime_in_hours: u32) -> Result<(), StatusCode> {
+        if !self.monitored_items.is_empty() {
+            return Err(StatusCode::BadInvalidState);
+        }
+        self.durable_lifetime_in_hours = Some(lifetime_in_hours);
+        if let Some(lifetime_count) = self.durable_lifetime_count() {
+            self.set_max_lifetime_count(lifetime_count);
+            self.reset_lifetime_counter();
+        }
+        Ok(())
+    }
+
+    /// Returns the lifetime count that gives a durable subscription its lifetime at the
+    /// publishing interval, or `None` if the subscription is not durable.
+    pub(crate) fn durable_lifetime_count(&self) -> Option<u32> {
+        self.durable_lifetime_in_hours.map(|lifetime_in_hours| {
+            let lifetime_ms = lifetime_in_hours as f64 * 3_600_000f64;
+            let lifetime_count = (lifetime_ms / self.publishing_interval).ceil();
+            lifetime_count.max(1f64).min(u32::MAX as f64) as u32
+        })
+    }
+
     pub fn priority(&self) -> u8 {
         self.priority
     }
@@ -1084,6 +1217,11 @@ impl Subscription {
         self.reset_lifetime_counter();
     }
 
+    pub(crate) fn set_locale_ids(&mut self, locale_ids: Vec<UAString>) {
+        self.locale_ids = locale_ids;
+    }
+
+    #[cfg(feature = "diagnostics")]
     pub(crate) fn set_diagnostics_on_drop(&mut self, diagnostics_on_drop: bool) {
         self.diagnostics_on_drop = diagnostics_on_drop;
     }
@@ -1133,11 +1271,18 @@ impl Subscription {
         if let Some(ref mut monitored_item) = self.monitored_items.get_mut(&monitored_item_id) {
             // Set the triggering monitored items
             monitored_item.set_triggering(items_to_add.as_slice(), items_to_remove.as_slice());
-
-            Ok((add_results, remove_results))
         } else {
             // This monitored item is unrecognized
-            Err(StatusCode::BadMonitoredItemIdInvalid)
+            return Err(StatusCode::BadMonitoredItemIdInvalid);
         }
+
+        // Linked items report their last value on the next trigger
+        items_to_add.iter().for_each(|i| {
+            if let Some(monitored_item) = self.monitored_items.get_mut(i) {
+                monitored_item.set_linked_to_trigger();
+            }
+        });
+
+        Ok((add_results, remove_results))
     }
 }
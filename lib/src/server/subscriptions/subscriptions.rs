@@ -2,8 +2,12 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::collections::{BTreeMap, VecDeque};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, VecDeque},
+};
 
+use crate::core::record_metric;
 use crate::types::{
     service_types::{NotificationMessage, PublishRequest, PublishResponse, ServiceFault},
     status_code::StatusCode,
@@ -13,7 +17,7 @@ use crate::types::{
 use crate::server::{
     address_space::types::AddressSpace,
     subscriptions::{
-        subscription::{Subscription, TickReason},
+        subscription::{Subscription, SubscriptionState, TickReason},
         PublishRequestEntry, PublishResponseEntry,
     },
 };
@@ -34,13 +38,23 @@ pub(crate) struct Subscriptions {
     publish_response_queue: VecDeque<PublishResponseEntry>,
     // Timeout period for requests in ms
     publish_request_timeout: i64,
+    /// Maximum number of notifications that a subscription holds while waiting for publish
+    /// requests, 0 for no limit
+    max_queued_notifications: usize,
+    /// Maximum number of subscriptions of the session, 0 for no limit
+    max_subscriptions: usize,
     /// Subscriptions associated with the session
-    subscriptions: BTreeMap<u32, Subscription>,
+    subscriptions: HashMap<u32, Subscription>,
     // Notifications waiting to be sent - Value is subscription id and notification message.
     transmission_queue: VecDeque<(u32, PublishRequestEntry, NotificationMessage)>,
     // Notifications that have been sent but have yet to be acknowledged (retransmission queue).
     // Key is (subscription_id, sequence_number). Value is notification message.
     retransmission_queue: BTreeMap<(u32, u32), NotificationMessage>,
+    /// The preferred locales of the session for human readable text in notifications
+    locale_ids: Vec<UAString>,
+    // Status change notifications of subscriptions which have been transferred to another
+    // session, waiting to be sent. Value is subscription id and notification message.
+    status_changes: VecDeque<(u32, NotificationMessage)>,
 }
 
 #[derive(Serialize)]
@@ -53,7 +67,11 @@ pub struct Metrics {
 }
 
 impl Subscriptions {
-    pub fn new(max_subscriptions: usize, publish_request_timeout: i64) -> Subscriptions {
+    pub fn new(
+        max_subscriptions: usize,
+        max_queued_notifications: usize,
+        publish_request_timeout: i64,
+    ) -> Subscriptions {
         let max_publish_requests = if max_subscriptions > 0 {
             2 * max_subscriptions
         } else {
@@ -63,9 +81,14 @@ impl Subscriptions {
             publish_request_queue: VecDeque::with_capacity(max_publish_requests),
             publish_response_queue: VecDeque::with_capacity(max_publish_requests),
             publish_request_timeout,
-            subscriptions: BTreeMap::new(),
+            max_queued_notifications,
+            max_subscriptions,
+            // The room for a limited number of subscriptions is allocated up front
+            subscriptions: HashMap::with_capacity(max_subscriptions),
             transmission_queue: VecDeque::with_capacity(max_publish_requests),
             retransmission_queue: BTreeMap::new(),
+            locale_ids: Vec::new(),
+            status_changes: VecDeque::new(),
         }
     }
 
@@ -75,7 +98,9 @@ impl Subscriptions {
             .subscriptions()
             .iter()
             .map(|subscription_pair| {
+                #[cfg_attr(not(feature = "diagnostics"), allow(unused_mut))]
                 let mut subscription = subscription_pair.1.clone();
+                #[cfg(feature = "diagnostics")]
                 subscription.set_diagnostics_on_drop(false);
                 subscription
             })
@@ -121,8 +146,9 @@ impl Subscriptions {
 
     /// Returns the number of maxmimum publish requests allowable for the current number of subscriptions
     pub fn max_publish_requests(&self) -> usize {
-        // Allow for two requests per subscription
-        self.subscriptions.len() * 2
+        // Allow for two requests per subscription, including the transferred subscriptions whose
+        // status changes are still to be sent
+        (self.subscriptions.len() + self.status_changes.len()) * 2
     }
 
     /// Places a new publish request onto the queue of publish requests.
@@ -159,6 +185,7 @@ impl Subscriptions {
                 request,
                 results,
             });
+            record_metric!(on_publish_queue_depth(self.publish_request_queue.len()));
             // Tick to trigger publish
             self.tick(now, address_space, TickReason::ReceivePublishRequest)
         }
@@ -169,13 +196,24 @@ impl Subscriptions {
         self.subscriptions.is_empty()
     }
 
+    /// Tests if there are status change notifications of transferred subscriptions waiting to
+    /// be sent.
+    pub(crate) fn has_status_changes(&self) -> bool {
+        !self.status_changes.is_empty()
+    }
+
     /// Returns the length of subscriptions.
     pub fn len(&self) -> usize {
         self.subscriptions.len()
     }
 
+    /// Tests if the session has as many subscriptions as it is allowed to have
+    pub(crate) fn is_full(&self) -> bool {
+        self.max_subscriptions > 0 && self.subscriptions.len() >= self.max_subscriptions
+    }
+
     /// Returns a reference to the collection holding the subscriptions.
-    pub fn subscriptions(&self) -> &BTreeMap<u32, Subscription> {
+    pub fn subscriptions(&self) -> &HashMap<u32, Subscription> {
         &self.subscriptions
     }
 
@@ -184,10 +222,20 @@ impl Subscriptions {
         self.subscriptions.contains_key(&subscription_id)
     }
 
-    pub fn insert(&mut self, subscription_id: u32, subscription: Subscription) {
+    pub fn insert(&mut self, subscription_id: u32, mut subscription: Subscription) {
+        subscription.set_locale_ids(self.locale_ids.clone());
         self.subscriptions.insert(subscription_id, subscription);
     }
 
+    /// Sets the preferred locales of the session, which apply to the notifications of every
+    /// subscription.
+    pub(crate) fn set_locale_ids(&mut self, locale_ids: Vec<UAString>) {
+        self.subscriptions
+            .values_mut()
+            .for_each(|s| s.set_locale_ids(locale_ids.clone()));
+        self.locale_ids = locale_ids;
+    }
+
     pub fn remove(&mut self, subscription_id: u32) -> Option<Subscription> {
         self.subscriptions.remove(&subscription_id)
     }
@@ -196,8 +244,32 @@ impl Subscriptions {
         self.subscriptions.get_mut(&subscription_id)
     }
 
+    /// Removes the subscription so it can be transferred to another session. A status change
+    /// notification is queued so the client learns that the subscription has gone from this session.
+    pub(crate) fn transfer(
+        &mut self,
+        subscription_id: u32,
+        now: &DateTimeUtc,
+    ) -> Option<Subscription> {
+        let mut subscription = self.subscriptions.remove(&subscription_id)?;
+        let notification = subscription.transferred_notification(now);
+        self.status_changes
+            .push_back((subscription_id, notification));
+        Some(subscription)
+    }
+
+    /// Closes every subscription, e.g. because the server is shutting down. Each subscription
+    /// sends a status change notification with the supplied status before it is removed.
+    /// Subscriptions which are already closed keep the status change they have.
+    pub(crate) fn close_all(&mut self, now: &DateTimeUtc, status: StatusCode) {
+        self.subscriptions
+            .values_mut()
+            .filter(|s| s.state() != SubscriptionState::Closed)
+            .for_each(|s| s.close(now, status));
+    }
+
     /// The tick causes the subscription manager to iterate through individual subscriptions calling tick
-    /// on each in order of priority. In each case this could generate data change notifications. Data change
+    /// on each in order of priority, highest first. In each case this could generate data change notifications. Data change
     /// notifications will be attached to the next available publish response and queued for sending
     /// to the client.
     pub(crate) fn tick(
@@ -207,19 +279,43 @@ impl Subscriptions {
         tick_reason: TickReason,
     ) -> Result<(), StatusCode> {
         let subscription_ids = {
-            // Sort subscriptions by priority
-            let mut subscription_priority: Vec<(u32, u8)> = self
+            // Sort subscriptions by priority, highest first, so that when publish requests are
+            // scarce the notifications of the highest priority subscription are sent first. Among
+            // subscriptions of the same priority, the one whose notifications have waited the
+            // longest goes first so that none of them is starved, and then the one with the
+            // lowest id.
+            let mut subscription_priority: Vec<(u32, u8, Option<i64>)> = self
                 .subscriptions
                 .values()
-                .map(|v| (v.subscription_id(), v.priority()))
+                .map(|v| {
+                    (
+                        v.subscription_id(),
+                        v.priority(),
+                        v.oldest_notification_time().map(|t| t.checked_ticks()),
+                    )
+                })
                 .collect();
-            subscription_priority.sort_by(|s1, s2| s1.1.cmp(&s2.1));
+            subscription_priority.sort_by_key(|s| (Reverse(s.1), s.2.is_none(), s.2, s.0));
             subscription_priority
                 .iter()
                 .map(|s| s.0)
                 .collect::<Vec<u32>>()
         };
 
+        // Status changes of transferred subscriptions are sent first
+        while !self.publish_request_queue.is_empty() {
+            if let Some((subscription_id, notification_message)) = self.status_changes.pop_front() {
+                let publish_request = self.publish_request_queue.pop_back().unwrap();
+                self.transmission_queue.push_front((
+                    subscription_id,
+                    publish_request,
+                    notification_message,
+                ));
+            } else {
+                break;
+            }
+        }
+
         // Iterate through all subscriptions. If there is a publish request it will be used to
         // acknowledge notifications and the response to return new notifications.
 
@@ -252,6 +348,22 @@ impl Subscriptions {
                 }
             }
 
+            // A client that stops publishing must not make a late subscription hold notifications
+            // without bound, so the oldest are discarded. Their sequence numbers are gone for good,
+            // so nothing may be left under them to republish or acknowledge.
+            if self.max_queued_notifications > 0 {
+                while subscription.queued_notification_count() > self.max_queued_notifications {
+                    if let Some(discarded) = subscription.take_notification() {
+                        self.retransmission_queue
+                            .remove(&(subscription_id, discarded.sequence_number));
+                        debug!(
+                            "Notification message nr {} of late subscription {} was discarded because too many are waiting",
+                            discarded.sequence_number, subscription_id
+                        );
+                    }
+                }
+            }
+
             // Remove the subscription if it is done
             if subscription.ready_to_remove() {
                 self.subscriptions.remove(&subscription_id);
@@ -271,11 +383,14 @@ impl Subscriptions {
             // Get a list of available sequence numbers
             let available_sequence_numbers = self.available_sequence_numbers(subscription_id);
 
-            // The notification to be sent is now put into the retransmission queue
-            self.retransmission_queue.insert(
-                (subscription_id, notification_message.sequence_number),
-                notification_message.clone(),
-            );
+            // The notification to be sent is now put into the retransmission queue, unless it is a
+            // keep alive which is never acknowledged or republished
+            if notification_message.notification_data.is_some() {
+                self.retransmission_queue.insert(
+                    (subscription_id, notification_message.sequence_number),
+                    notification_message.clone(),
+                );
+            }
 
             // Enqueue a publish response
             let response = self.make_publish_response(
@@ -336,6 +451,70 @@ impl Subscriptions {
             .append(&mut expired_publish_responses);
     }
 
+    /// Cancels the queued publish requests with the request handle, answering each with a
+    /// `BadRequestCancelledByClient` service fault. Returns the number of requests cancelled.
+    pub fn cancel_publish_requests(&mut self, request_handle: u32) -> u32 {
+        let mut cancelled_publish_responses = VecDeque::new();
+        self.publish_request_queue.retain(|request| {
+            let request_header = &request.request.request_header;
+            if request_header.request_handle == request_handle {
+                debug!("Publish request {} has been cancelled", request_handle);
+                cancelled_publish_responses.push_front(PublishResponseEntry {
+                    request_id: request.request_id,
+                    response: ServiceFault {
+                        response_header: ResponseHeader::new_timestamped_service_result(
+                            DateTime::now(),
+                            request_header,
+                            StatusCode::BadRequestCancelledByClient,
+                        ),
+                    }
+                    .into(),
+                });
+                false
+            } else {
+                true
+            }
+        });
+        let cancel_count = cancelled_publish_responses.len() as u32;
+        self.publish_response_queue
+            .append(&mut cancelled_publish_responses);
+        cancel_count
+    }
+
+    /// Answers every queued publish request with a service fault of the status, e.g. because the
+    /// session is closing. Returns the number of requests answered.
+    pub(crate) fn close_publish_requests(&mut self, status_code: StatusCode) -> usize {
+        let now = DateTime::now();
+        let closed_publish_responses = self
+            .publish_request_queue
+            .drain(..)
+            .rev()
+            .map(|request| PublishResponseEntry {
+                request_id: request.request_id,
+                response: ServiceFault {
+                    response_header: ResponseHeader::new_timestamped_service_result(
+                        now,
+                        &request.request.request_header,
+                        status_code,
+                    ),
+                }
+                .into(),
+            })
+            .collect::<Vec<_>>();
+        let close_count = closed_publish_responses.len();
+        self.publish_response_queue.extend(closed_publish_responses);
+        close_count
+    }
+
+    /// Takes every subscription out, e.g. to delete them or transfer them to another session
+    pub(crate) fn take_subscriptions(&mut self) -> Vec<Subscription> {
+        // Draining keeps the room allocated for the subscriptions
+        self.subscriptions
+            .drain()
+            .map(|(_, subscription)| subscription)
+            .collect()
+    }
+
     /// Deletes the acknowledged notifications, returning a list of status code for each according
     /// to whether it was found or not.
     ///
@@ -442,7 +621,7 @@ impl Subscriptions {
         sequence_number: u32,
     ) -> Result<NotificationMessage, StatusCode> {
         // Look for the subscription
-        if self.subscriptions.get(&subscription_id).is_some() {
+        if self.subscriptions.contains_key(&subscription_id) {
             // Look for the sequence number
             if let Some(notification_message) = self
                 .retransmission_queue
@@ -48,6 +48,43 @@ fn namespaces() {
     assert_eq!(address_space.register_namespace("foo").unwrap(), 2u16);
 }
 
+#[test]
+fn import_namespaces() {
+    let mut address_space = AddressSpace::new();
+    let ns = address_space.register_namespace("urn:test").unwrap();
+
+    // The node set shares one namespace with the address space and brings one of its own
+    let node_set = NamespaceTable::from_uris(vec![
+        "http://opcfoundation.org/UA/",
+        "urn:machines",
+        "urn:test",
+    ]);
+    let map = address_space.import_namespaces(&node_set).unwrap();
+    assert_eq!(map.index(0), Some(0));
+    assert_eq!(map.index(1), Some(ns + 1));
+    assert_eq!(map.index(2), Some(ns));
+    assert_eq!(address_space.namespace_uri(ns + 1), Some("urn:machines"));
+    // Importing does not change the default namespace
+    assert_eq!(address_space.default_namespace(), ns);
+
+    // The namespace array has the new namespace
+    let namespace_array = address_space
+        .find_variable(VariableId::Server_NamespaceArray)
+        .unwrap()
+        .value(
+            TimestampsToReturn::Neither,
+            NumericRange::None,
+            &QualifiedName::null(),
+            0.0,
+        )
+        .value
+        .unwrap();
+    assert_eq!(
+        NamespaceTable::from_variant(&namespace_array).as_ref(),
+        Some(address_space.namespaces())
+    );
+}
+
 #[test]
 fn find_root_folder() {
     let address_space = AddressSpace::new();
@@ -915,6 +952,228 @@ fn is_subtype() {
     });
 }
 
+#[test]
+fn type_hierarchy() {
+    let mut address_space = AddressSpace::new();
+
+    // Supertypes are nearest first
+    let supertypes = address_space.find_supertypes(&ObjectTypeId::AuditSessionEventType.into());
+    let expected: Vec<NodeId> = vec![
+        ObjectTypeId::AuditSecurityEventType.into(),
+        ObjectTypeId::AuditEventType.into(),
+        ObjectTypeId::BaseEventType.into(),
+        ObjectTypeId::BaseObjectType.into(),
+    ];
+    assert_eq!(supertypes, expected);
+    assert!(address_space
+        .find_supertypes(&ObjectTypeId::BaseObjectType.into())
+        .is_empty());
+
+    // Subtypes include indirect ones
+    let subtypes = address_space.find_subtypes(&ObjectTypeId::AuditEventType.into());
+    assert!(subtypes.contains(&ObjectTypeId::AuditSecurityEventType.into()));
+    assert!(subtypes.contains(&ObjectTypeId::AuditSessionEventType.into()));
+    assert!(!subtypes.contains(&ObjectTypeId::AuditEventType.into()));
+    assert!(!subtypes.contains(&ObjectTypeId::SystemEventType.into()));
+
+    // Add a type and move it around the hierarchy
+    let type_id = NodeId::new(1, "MyEventType");
+    ObjectTypeBuilder::new(&type_id, "MyEventType", "MyEventType")
+        .subtype_of(ObjectTypeId::SystemEventType)
+        .insert(&mut address_space);
+    assert!(address_space.is_subtype_of(&type_id, &ObjectTypeId::BaseEventType.into()));
+    assert!(address_space
+        .find_subtypes(&ObjectTypeId::BaseEventType.into())
+        .contains(&type_id));
+
+    assert!(address_space.delete_reference(
+        &ObjectTypeId::SystemEventType.into(),
+        &type_id,
+        ReferenceTypeId::HasSubtype
+    ));
+    assert!(!address_space.is_subtype_of(&type_id, &ObjectTypeId::BaseEventType.into()));
+    assert!(address_space.find_supertypes(&type_id).is_empty());
+
+    address_space.insert_reference(
+        &ObjectTypeId::ProgressEventType.into(),
+        &type_id,
+        ReferenceTypeId::HasSubtype,
+    );
+    assert!(address_space.is_subtype_of(&type_id, &ObjectTypeId::ProgressEventType.into()));
+    assert!(!address_space.is_subtype_of(&type_id, &ObjectTypeId::SystemEventType.into()));
+
+    // Deleting the supertype forgets it
+    let supertype_id = NodeId::new(1, "MyBaseEventType");
+    ObjectTypeBuilder::new(&supertype_id, "MyBaseEventType", "MyBaseEventType")
+        .subtype_of(ObjectTypeId::BaseEventType)
+        .insert(&mut address_space);
+    address_space.delete_reference(
+        &ObjectTypeId::ProgressEventType.into(),
+        &type_id,
+        ReferenceTypeId::HasSubtype,
+    );
+    address_space.insert_reference(&supertype_id, &type_id, ReferenceTypeId::HasSubtype);
+    assert!(address_space.is_subtype_of(&type_id, &supertype_id));
+    address_space.delete(&supertype_id, true);
+    assert!(!address_space.is_subtype_of(&type_id, &ObjectTypeId::BaseEventType.into()));
+}
+
+/// Gets the changes of every model change event in the address space
+fn model_changes(address_space: &AddressSpace) -> Vec<Vec<ModelChangeStructureDataType>> {
+    let decoding_options = DecodingOptions::test();
+    address_space
+        .find_objects_by_type(ObjectTypeId::GeneralModelChangeEventType, false)
+        .unwrap_or_default()
+        .iter()
+        .map(|event_id| {
+            let changes =
+                find_node_from_browse_path(address_space, event_id, &["Changes".into()]).unwrap();
+            let changes = changes
+                .as_node()
+                .get_attribute(
+                    TimestampsToReturn::Neither,
+                    AttributeId::Value,
+                    NumericRange::None,
+                    &QualifiedName::null(),
+                )
+                .unwrap()
+                .value
+                .unwrap();
+            if let Variant::Array(array) = changes {
+                array
+                    .values
+                    .iter()
+                    .map(|v| {
+                        if let Variant::ExtensionObject(v) = v {
+                            v.decode_inner::<ModelChangeStructureDataType>(&decoding_options)
+                                .unwrap()
+                        } else {
+                            panic!("Change is not an extension object");
+                        }
+                    })
+                    .collect()
+            } else {
+                panic!("Changes is not an array");
+            }
+        })
+        .collect()
+}
+
+fn node_version(address_space: &AddressSpace, node_id: &NodeId) -> Variant {
+    let node_version =
+        find_node_from_browse_path(address_space, node_id, &["NodeVersion".into()]).unwrap();
+    node_version
+        .as_node()
+        .get_attribute(
+            TimestampsToReturn::Neither,
+            AttributeId::Value,
+            NumericRange::None,
+            &QualifiedName::null(),
+        )
+        .unwrap()
+        .value
+        .unwrap()
+}
+
+#[test]
+fn model_change_events() {
+    let mut address_space = AddressSpace::new();
+
+    // Nothing is raised until model change events are turned on
+    let machine_id = NodeId::new(1, "Machine");
+    ObjectBuilder::new(&machine_id, "Machine", "Machine")
+        .organized_by(ObjectId::ObjectsFolder)
+        .has_type_definition(ObjectTypeId::BaseObjectType)
+        .insert(&mut address_space);
+    VariableBuilder::new(&NodeId::new(1, "NodeVersion"), "NodeVersion", "NodeVersion")
+        .property_of(machine_id.clone())
+        .has_type_definition(VariableTypeId::PropertyType)
+        .data_type(DataTypeId::String)
+        .value(UAString::from("0"))
+        .insert(&mut address_space);
+    assert!(model_changes(&address_space).is_empty());
+
+    address_space.set_model_change_events(true);
+
+    // Adding a node changes the node and the node it references
+    let speed_id = NodeId::new(1, "Speed");
+    VariableBuilder::new(&speed_id, "Speed", "Speed")
+        .component_of(machine_id.clone())
+        .has_type_definition(VariableTypeId::BaseDataVariableType)
+        .data_type(DataTypeId::Double)
+        .value(0f64)
+        .insert(&mut address_space);
+    let changes = model_changes(&address_space);
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].contains(&ModelChangeStructureDataType {
+        affected: speed_id.clone(),
+        affected_type: VariableTypeId::BaseDataVariableType.into(),
+        verb: ModelChangeStructureVerbMask::NodeAdded as u8,
+    }));
+    assert!(changes[0].contains(&ModelChangeStructureDataType {
+        affected: machine_id.clone(),
+        affected_type: ObjectTypeId::BaseObjectType.into(),
+        verb: ModelChangeStructureVerbMask::ReferenceAdded as u8,
+    }));
+    assert_eq!(
+        node_version(&address_space, &machine_id),
+        UAString::from("1").into()
+    );
+
+    // Deleting it again
+    assert!(address_space.delete(&speed_id, true));
+    let changes = model_changes(&address_space);
+    assert_eq!(changes.len(), 2);
+    let deleted = changes
+        .iter()
+        .find(|changes| {
+            changes
+                .iter()
+                .any(|c| c.verb == ModelChangeStructureVerbMask::NodeDeleted as u8)
+        })
+        .unwrap();
+    assert!(deleted.contains(&ModelChangeStructureDataType {
+        affected: machine_id.clone(),
+        affected_type: ObjectTypeId::BaseObjectType.into(),
+        verb: ModelChangeStructureVerbMask::ReferenceDeleted as u8,
+    }));
+    assert_eq!(
+        node_version(&address_space, &machine_id),
+        UAString::from("2").into()
+    );
+
+    // References
+    address_space.insert_reference(
+        &machine_id,
+        &ObjectId::Server.into(),
+        ReferenceTypeId::Organizes,
+    );
+    assert_eq!(model_changes(&address_space).len(), 3);
+    assert!(address_space.delete_reference(
+        &machine_id,
+        &ObjectId::Server.into(),
+        ReferenceTypeId::Organizes
+    ));
+    assert_eq!(model_changes(&address_space).len(), 4);
+    assert_eq!(
+        node_version(&address_space, &machine_id),
+        UAString::from("4").into()
+    );
+
+    // Raising and purging events are not model changes
+    let mut event = BaseEventType::new_now(
+        NodeId::new(1, "Event"),
+        ObjectTypeId::BaseEventType,
+        "Event",
+        "Event",
+        NodeId::null(),
+    )
+    .source_node(machine_id.clone());
+    assert!(event.raise(&mut address_space).is_ok());
+    assert!(address_space.delete(&NodeId::new(1, "Event"), true));
+    assert_eq!(model_changes(&address_space).len(), 4);
+}
+
 #[test]
 fn hierarchical_references() {
     let address_space = AddressSpace::new();
@@ -952,3 +1211,805 @@ fn hierarchical_references() {
     assert!(refs.contains(&ObjectId::Server_ServerCapabilities_AggregateFunctions.into()));
     assert!(refs.contains(&ObjectId::HistoryServerCapabilities.into()));
 }
+
+fn server_profiles(address_space: &AddressSpace) -> Vec<String> {
+    let v = address_space
+        .find_variable(VariableId::Server_ServerCapabilities_ServerProfileArray)
+        .unwrap();
+    match v
+        .value(
+            TimestampsToReturn::Neither,
+            NumericRange::None,
+            &QualifiedName::null(),
+            0.0,
+        )
+        .value
+        .unwrap()
+    {
+        Variant::Array(array) => array.values.iter().map(|v| v.to_string()).collect(),
+        v => panic!("ServerProfileArray is not an array, {:?}", v),
+    }
+}
+
+#[test]
+fn server_capabilities() {
+    use crate::server::profiles;
+
+    // The sample server has secure endpoints with user name and x509 users
+    let server = ServerBuilder::new_sample().server().unwrap();
+    let address_space = server.address_space();
+    let mut address_space = address_space.write();
+    let uris = server_profiles(&address_space);
+    assert!(uris.contains(&profiles::EMBEDDED_UA.to_string()));
+    assert!(uris.contains(&profiles::STANDARD_DATA_CHANGE_SUBSCRIPTION.to_string()));
+    assert!(uris.contains(&profiles::USER_TOKEN_USER_NAME_PASSWORD.to_string()));
+    assert!(uris.contains(&profiles::USER_TOKEN_X509_CERTIFICATE.to_string()));
+    assert!(uris.contains(&SecurityPolicy::Basic256Sha256.to_uri().to_string()));
+    assert!(!uris.contains(&profiles::NODE_MANAGEMENT.to_string()));
+    assert!(!uris.contains(&profiles::HISTORICAL_RAW_DATA.to_string()));
+
+    // Sample rate is in milliseconds
+    let v = address_space
+        .find_variable(VariableId::Server_ServerCapabilities_MinSupportedSampleRate)
+        .unwrap();
+    let min_sample_rate = v.value(
+        TimestampsToReturn::Neither,
+        NumericRange::None,
+        &QualifiedName::null(),
+        0.0,
+    );
+    assert_eq!(
+        min_sample_rate.value.unwrap(),
+        Variant::from(crate::server::constants::SUBSCRIPTION_TIMER_RATE_MS as f64)
+    );
+
+    // Enabling history adds its facets
+    address_space.set_history_server_capabilities(&HistoryServerCapabilities {
+        access_history_data: true,
+        access_history_events: false,
+        max_return_data: 100,
+        max_return_events: 100,
+        insert_data: false,
+        replace_data: false,
+        update_data: false,
+        delete_raw: false,
+        delete_at_time: false,
+        insert_event: false,
+        replace_event: false,
+        update_event: false,
+        delete_event: false,
+        insert_annotation: false,
+        aggregate_functions: Vec::new(),
+    });
+    let uris = server_profiles(&address_space);
+    assert!(uris.contains(&profiles::HISTORICAL_RAW_DATA.to_string()));
+    assert!(!uris.contains(&profiles::HISTORICAL_EVENTS.to_string()));
+
+    // The server implementation can claim its own facets
+    address_space.add_server_profile("urn:test:profile");
+    assert!(server_profiles(&address_space).contains(&"urn:test:profile".to_string()));
+    assert!(address_space.server_profiles().contains("urn:test:profile"));
+
+    // An anonymous server with no security that lets clients modify the address space
+    let server = ServerBuilder::new_anonymous("foo")
+        .clients_can_modify_address_space()
+        .server()
+        .unwrap();
+    let address_space = server.address_space();
+    let address_space = address_space.read();
+    let uris = server_profiles(&address_space);
+    assert!(uris.contains(&profiles::MICRO_EMBEDDED_DEVICE.to_string()));
+    assert!(uris.contains(&profiles::NODE_MANAGEMENT.to_string()));
+    assert!(uris.contains(&SecurityPolicy::None.to_uri().to_string()));
+    assert!(!uris.contains(&profiles::EMBEDDED_UA.to_string()));
+    assert!(!uris.contains(&profiles::USER_TOKEN_USER_NAME_PASSWORD.to_string()));
+}
+
+fn server_redundancy_type(address_space: &AddressSpace) -> NodeId {
+    let references = address_space
+        .find_references(
+            &ObjectId::Server_ServerRedundancy.into(),
+            Some((ReferenceTypeId::HasTypeDefinition, false)),
+        )
+        .unwrap();
+    assert_eq!(references.len(), 1);
+    references[0].target_node.clone()
+}
+
+fn redundancy_value(address_space: &AddressSpace, variable_id: VariableId) -> Option<Variant> {
+    address_space.find_variable(variable_id).map(|v| {
+        v.value(
+            TimestampsToReturn::Neither,
+            NumericRange::None,
+            &QualifiedName::null(),
+            0.0,
+        )
+        .value
+        .unwrap()
+    })
+}
+
+#[test]
+fn server_redundancy() {
+    // A server is not redundant by default
+    let server = ServerBuilder::new_anonymous("foo").server().unwrap();
+    let address_space = server.address_space();
+    let address_space = address_space.read();
+    assert_eq!(
+        redundancy_value(
+            &address_space,
+            VariableId::Server_ServerRedundancy_RedundancySupport
+        ),
+        Some(Variant::Int32(RedundancySupport::None as i32))
+    );
+    assert_eq!(
+        server_redundancy_type(&address_space),
+        ObjectTypeId::ServerRedundancyType.into()
+    );
+    assert!(redundancy_value(
+        &address_space,
+        VariableId::Server_ServerRedundancy_ServerUriArray
+    )
+    .is_none());
+
+    // A member of a redundant set advertises the set, including itself
+    let server = ServerBuilder::new_anonymous("foo")
+        .application_uri("urn:foo")
+        .redundancy(Redundancy {
+            mode: RedundancyMode::Hot,
+            server_uris: vec!["urn:bar".into()],
+            server_network_groups: Vec::new(),
+        })
+        .server()
+        .unwrap();
+    let address_space = server.address_space();
+    let address_space = address_space.read();
+    assert_eq!(
+        redundancy_value(
+            &address_space,
+            VariableId::Server_ServerRedundancy_RedundancySupport
+        ),
+        Some(Variant::Int32(RedundancySupport::Hot as i32))
+    );
+    assert_eq!(
+        server_redundancy_type(&address_space),
+        ObjectTypeId::NonTransparentRedundancyType.into()
+    );
+    assert_eq!(
+        redundancy_value(
+            &address_space,
+            VariableId::Server_ServerRedundancy_ServerUriArray
+        ),
+        Some(Variant::from((
+            VariantTypeId::String,
+            vec![
+                Variant::from(UAString::from("urn:foo")),
+                Variant::from(UAString::from("urn:bar"))
+            ]
+        )))
+    );
+    assert!(redundancy_value(
+        &address_space,
+        VariableId::Server_ServerRedundancy_ServerNetworkGroups
+    )
+    .is_none());
+
+    // Network groups give the network redundancy type
+    let server = ServerBuilder::new_anonymous("foo")
+        .application_uri("urn:foo")
+        .redundancy(Redundancy {
+            mode: RedundancyMode::Warm,
+            server_uris: vec!["urn:foo".into(), "urn:bar".into()],
+            server_network_groups: vec![ServerNetworkGroup {
+                server_uri: "urn:bar".into(),
+                network_paths: vec![
+                    vec!["opc.tcp://bar-a:4855/".into()],
+                    vec!["opc.tcp://bar-b:4855/".into()],
+                ],
+            }],
+        })
+        .server()
+        .unwrap();
+    let address_space = server.address_space();
+    let address_space = address_space.read();
+    assert_eq!(
+        server_redundancy_type(&address_space),
+        ObjectTypeId::NonTransparentNetworkRedundancyType.into()
+    );
+    let server_uris = redundancy_value(
+        &address_space,
+        VariableId::Server_ServerRedundancy_ServerUriArray,
+    );
+    match server_uris {
+        Some(Variant::Array(array)) => assert_eq!(array.values.len(), 2),
+        v => panic!("Unexpected server uris {:?}", v),
+    }
+    let network_groups = match redundancy_value(
+        &address_space,
+        VariableId::Server_ServerRedundancy_ServerNetworkGroups,
+    ) {
+        Some(Variant::Array(array)) => array.values,
+        v => panic!("Unexpected network groups {:?}", v),
+    };
+    assert_eq!(network_groups.len(), 1);
+    let network_group = match network_groups[0] {
+        Variant::ExtensionObject(ref o) => o
+            .decode_inner::<NetworkGroupDataType>(&DecodingOptions::test())
+            .unwrap(),
+        ref v => panic!("Unexpected network group {:?}", v),
+    };
+    assert_eq!(network_group.server_uri.as_ref(), "urn:bar");
+    let network_paths = network_group.network_paths.unwrap();
+    assert_eq!(network_paths.len(), 2);
+    assert_eq!(
+        network_paths[1].endpoint_url_list,
+        Some(vec![UAString::from("opc.tcp://bar-b:4855/")])
+    );
+}
+
+#[test]
+fn localization() {
+    let mut localization = Localization::new();
+    localization.set_default_locale("en");
+    localization.add_translations(
+        "Temperature",
+        vec![
+            ("de", "Temperatur"),
+            ("fr-CA", "Température"),
+            ("fr-FR", "Temp."),
+        ],
+    );
+
+    let locale_ids = |ids: &[&str]| ids.iter().map(|id| UAString::from(*id)).collect::<Vec<_>>();
+    let text = LocalizedText::from("Temperature");
+
+    // Exact match
+    assert_eq!(
+        localization.localize(&text, &locale_ids(&["fr-CA"])),
+        Some(LocalizedText::new("fr-CA", "Température"))
+    );
+    // Falls back to the language of the locale
+    assert_eq!(
+        localization.localize(&text, &locale_ids(&["de-CH"])),
+        Some(LocalizedText::new("de", "Temperatur"))
+    );
+    assert_eq!(
+        localization.localize(&text, &locale_ids(&["FR"])),
+        Some(LocalizedText::new("fr-CA", "Température"))
+    );
+    // Locales are tried in order of preference
+    assert_eq!(
+        localization.localize(&text, &locale_ids(&["ja", "de"])),
+        Some(LocalizedText::new("de", "Temperatur"))
+    );
+    // Text without a locale is in the default locale, so it is returned as it is
+    assert_eq!(
+        localization.localize(&text, &locale_ids(&["en-GB", "de"])),
+        None
+    );
+    assert_eq!(localization.localize(&text, &locale_ids(&["ja"])), None);
+    assert_eq!(localization.localize(&text, &[]), None);
+    // Text without a translation is returned as it is
+    assert_eq!(
+        localization.localize(&LocalizedText::from("Pressure"), &locale_ids(&["de"])),
+        None
+    );
+
+    // Arrays of text, e.g. enum strings, are localized element by element
+    localization.add_translation("Open", "de", "Offen");
+    let mut value = Variant::from((
+        VariantTypeId::LocalizedText,
+        vec![
+            Variant::from(LocalizedText::from("Open")),
+            Variant::from(LocalizedText::from("Closed")),
+        ],
+    ));
+    localization.localize_variant(&mut value, &locale_ids(&["de"]));
+    assert_eq!(
+        value,
+        Variant::from((
+            VariantTypeId::LocalizedText,
+            vec![
+                Variant::from(LocalizedText::new("de", "Offen")),
+                Variant::from(LocalizedText::from("Closed")),
+            ],
+        ))
+    );
+    // As are the fields of events, e.g. their message
+    let mut event = EventFieldList {
+        client_handle: 1,
+        event_fields: Some(vec![
+            Variant::from(LocalizedText::from("Open")),
+            Variant::from(1i32),
+        ]),
+    };
+    localization.localize_event(&mut event, &locale_ids(&["de"]));
+    assert_eq!(
+        event.event_fields.unwrap(),
+        vec![
+            Variant::from(LocalizedText::new("de", "Offen")),
+            Variant::from(1i32)
+        ]
+    );
+}
+
+/// Finds the value of a property of a node by its browse name
+fn property_value(address_space: &AddressSpace, node_id: &NodeId, browse_name: &str) -> Variant {
+    let references = address_space
+        .find_references(node_id, Some((ReferenceTypeId::HasProperty, false)))
+        .unwrap();
+    let property = references
+        .iter()
+        .map(|r| address_space.find_variable_by_ref(&r.target_node).unwrap())
+        .find(|v| v.browse_name() == QualifiedName::from(browse_name))
+        .unwrap();
+    let type_definition = address_space
+        .find_references(
+            &property.node_id(),
+            Some((ReferenceTypeId::HasTypeDefinition, false)),
+        )
+        .unwrap();
+    assert_eq!(
+        type_definition[0].target_node,
+        VariableTypeId::PropertyType.into()
+    );
+    property
+        .value(
+            TimestampsToReturn::Neither,
+            NumericRange::None,
+            &QualifiedName::null(),
+            0.0,
+        )
+        .value
+        .unwrap()
+}
+
+fn type_definition(address_space: &AddressSpace, node_id: &NodeId) -> NodeId {
+    address_space
+        .find_references(node_id, Some((ReferenceTypeId::HasTypeDefinition, false)))
+        .unwrap()[0]
+        .target_node
+        .clone()
+}
+
+#[test]
+fn data_access_variables() {
+    let mut address_space = AddressSpace::new();
+    let decoding_options = DecodingOptions::default();
+
+    // Analog item
+    let analog_id = AnalogItemBuilder::new(&NodeId::new(1, "analog"), "Analog", "Analog")
+        .data_type(DataTypeId::Double)
+        .value(21.5)
+        .eu_range(-40.0, 120.0)
+        .instrument_range(-50.0, 150.0)
+        .engineering_units(EUInformation {
+            namespace_uri: UAString::from("http://www.opcfoundation.org/UA/units/un/cefact"),
+            unit_id: 4408652,
+            display_name: LocalizedText::from("°C"),
+            description: LocalizedText::from("degree Celsius"),
+        })
+        .organized_by(ObjectId::ObjectsFolder)
+        .insert(&mut address_space);
+    assert_eq!(
+        type_definition(&address_space, &analog_id),
+        VariableTypeId::AnalogItemType.into()
+    );
+    let range = |value: Variant| {
+        if let Variant::ExtensionObject(value) = value {
+            value.decode_inner::<Range>(&decoding_options).unwrap()
+        } else {
+            panic!("Not an extension object");
+        }
+    };
+    assert_eq!(
+        range(property_value(&address_space, &analog_id, "EURange")),
+        Range {
+            low: -40.0,
+            high: 120.0
+        }
+    );
+    assert_eq!(
+        range(property_value(
+            &address_space,
+            &analog_id,
+            "InstrumentRange"
+        )),
+        Range {
+            low: -50.0,
+            high: 150.0
+        }
+    );
+    if let Variant::ExtensionObject(units) =
+        property_value(&address_space, &analog_id, "EngineeringUnits")
+    {
+        let units = units
+            .decode_inner::<EUInformation>(&decoding_options)
+            .unwrap();
+        assert_eq!(units.unit_id, 4408652);
+    } else {
+        panic!("Not an extension object");
+    }
+
+    // Two state discrete
+    let two_state_id =
+        TwoStateDiscreteBuilder::new(&NodeId::new(1, "two_state"), "TwoState", "TwoState")
+            .true_state("On")
+            .false_state("Off")
+            .value(true)
+            .insert(&mut address_space);
+    assert_eq!(
+        type_definition(&address_space, &two_state_id),
+        VariableTypeId::TwoStateDiscreteType.into()
+    );
+    let two_state = address_space.find_variable_by_ref(&two_state_id).unwrap();
+    assert_eq!(two_state.data_type(), DataTypeId::Boolean.into());
+    assert_eq!(
+        property_value(&address_space, &two_state_id, "TrueState"),
+        Variant::from(LocalizedText::from("On"))
+    );
+    assert_eq!(
+        property_value(&address_space, &two_state_id, "FalseState"),
+        Variant::from(LocalizedText::from("Off"))
+    );
+
+    // Multi state discrete
+    let multi_state_id =
+        MultiStateDiscreteBuilder::new(&NodeId::new(1, "multi_state"), "MultiState", "MultiState")
+            .enum_strings(vec!["Off", "Low", "High"])
+            .value(1u32)
+            .insert(&mut address_space);
+    assert_eq!(
+        type_definition(&address_space, &multi_state_id),
+        VariableTypeId::MultiStateDiscreteType.into()
+    );
+    assert_eq!(
+        property_value(&address_space, &multi_state_id, "EnumStrings"),
+        Variant::from((
+            VariantTypeId::LocalizedText,
+            vec![
+                Variant::from(LocalizedText::from("Off")),
+                Variant::from(LocalizedText::from("Low")),
+                Variant::from(LocalizedText::from("High")),
+            ]
+        ))
+    );
+
+    // Multi state value discrete, whose value as text follows the value
+    let multi_state_value_id = MultiStateValueDiscreteBuilder::new(
+        &NodeId::new(1, "multi_state_value"),
+        "MultiStateValue",
+        "MultiStateValue",
+    )
+    .enum_value(1, "Low")
+    .enum_value(10, "High")
+    .value(10i32)
+    .insert(&mut address_space);
+    let value_as_text = |address_space: &AddressSpace| {
+        if let Variant::LocalizedText(text) =
+            property_value(address_space, &multi_state_value_id, "ValueAsText")
+        {
+            text.text
+        } else {
+            panic!("Not a localized text");
+        }
+    };
+    assert_eq!(
+        type_definition(&address_space, &multi_state_value_id),
+        VariableTypeId::MultiStateValueDiscreteType.into()
+    );
+    if let Variant::Array(enum_values) =
+        property_value(&address_space, &multi_state_value_id, "EnumValues")
+    {
+        assert_eq!(enum_values.values.len(), 2);
+    } else {
+        panic!("Not an array");
+    }
+    assert_eq!(value_as_text(&address_space), UAString::from("High"));
+    let now = DateTime::now();
+    assert!(address_space.set_multi_state_value(&multi_state_value_id, 1i32, &now, &now));
+    assert_eq!(value_as_text(&address_space), UAString::from("Low"));
+    // A value that has no text
+    assert!(address_space.set_multi_state_value(&multi_state_value_id, 5i32, &now, &now));
+    assert!(value_as_text(&address_space).is_null());
+}
+
+#[test]
+#[should_panic]
+fn analog_item_without_eu_range() {
+    let mut address_space = AddressSpace::new();
+    AnalogItemBuilder::new(&NodeId::new(1, "analog"), "Analog", "Analog")
+        .data_type(DataTypeId::Double)
+        .value(21.5)
+        .insert(&mut address_space);
+}
+
+#[test]
+fn set_variable_timestamps() {
+    let address_space = make_sample_address_space();
+    let mut address_space = trace_write_lock!(address_space);
+    let ns = address_space.namespace_index("urn:test").unwrap();
+    let node_id = NodeId::new(ns, "v1");
+
+    // The source and server timestamps are kept apart
+    let source_timestamp = DateTime::ymd_hms(2022, 1, 1, 12, 0, 0);
+    let server_timestamp = DateTime::ymd_hms(2022, 1, 1, 12, 0, 1);
+    assert!(address_space.set_variable_value(
+        node_id.clone(),
+        1i32,
+        &source_timestamp,
+        &server_timestamp
+    ));
+    let value = address_space.get_variable_value(node_id.clone()).unwrap();
+    assert_eq!(value.source_timestamp, Some(source_timestamp));
+    assert_eq!(value.server_timestamp, Some(server_timestamp));
+
+    // A data value keeps its status and source timestamp and is given a server timestamp
+    let before = DateTime::now();
+    let mut data_value = DataValue::value_only(2i32);
+    data_value.status = Some(StatusCode::UncertainLastUsableValue);
+    data_value.source_timestamp = Some(source_timestamp);
+    data_value.source_picoseconds = Some(500);
+    assert!(address_space.set_variable_data_value(node_id.clone(), data_value));
+    let value = address_space.get_variable_value(node_id.clone()).unwrap();
+    assert_eq!(value.value, Some(Variant::Int32(2)));
+    assert_eq!(value.status, Some(StatusCode::UncertainLastUsableValue));
+    assert_eq!(value.source_timestamp, Some(source_timestamp));
+    assert_eq!(value.source_picoseconds, Some(500));
+    assert!(value.server_timestamp.unwrap() >= before);
+
+    // A data value without timestamps or status is sampled now and good
+    assert!(address_space.set_variable_data_value(node_id.clone(), DataValue::value_only(3i32)));
+    let value = address_space.get_variable_value(node_id.clone()).unwrap();
+    assert_eq!(value.status, Some(StatusCode::Good));
+    assert_eq!(value.source_timestamp, value.server_timestamp);
+
+    assert!(!address_space.set_variable_data_value(NodeId::new(ns, "none"), DataValue::null()));
+}
+
+/// Inserts a `DeviceType` with a mandatory `SerialNumber` property, an optional `Model` property
+/// and a mandatory `Reset` method, and a `PumpType` subtype of it with a mandatory `Motor` of a
+/// `MotorType` that has a mandatory `Speed` variable, and an optional placeholder for sensors.
+fn add_device_types(address_space: &mut AddressSpace) -> (NodeId, NodeId) {
+    let device_type_id = NodeId::new(1, "DeviceType");
+    ObjectTypeBuilder::new(&device_type_id, "DeviceType", "DeviceType")
+        .is_abstract(true)
+        .subtype_of(ObjectTypeId::BaseObjectType)
+        .insert(address_space);
+    VariableBuilder::new(
+        &NodeId::new(1, "DeviceType.SerialNumber"),
+        "SerialNumber",
+        "SerialNumber",
+    )
+    .data_type(DataTypeId::String)
+    .value("None")
+    .has_type_definition(VariableTypeId::PropertyType)
+    .has_modelling_rule(ObjectId::ModellingRule_Mandatory)
+    .property_of(device_type_id.clone())
+    .insert(address_space);
+    VariableBuilder::new(&NodeId::new(1, "DeviceType.Model"), "Model", "Model")
+        .data_type(DataTypeId::String)
+        .has_type_definition(VariableTypeId::PropertyType)
+        .has_modelling_rule(ObjectId::ModellingRule_Optional)
+        .property_of(device_type_id.clone())
+        .insert(address_space);
+    MethodBuilder::new(&NodeId::new(1, "DeviceType.Reset"), "Reset", "Reset")
+        .has_modelling_rule(ModellingRule::Mandatory)
+        .component_of(device_type_id.clone())
+        .callback(Box::new(HelloWorld))
+        .insert(address_space);
+
+    let motor_type_id = NodeId::new(1, "MotorType");
+    ObjectTypeBuilder::new(&motor_type_id, "MotorType", "MotorType")
+        .subtype_of(ObjectTypeId::BaseObjectType)
+        .insert(address_space);
+    VariableBuilder::new(&NodeId::new(1, "MotorType.Speed"), "Speed", "Speed")
+        .data_type(DataTypeId::Double)
+        .value(0.0)
+        .has_type_definition(VariableTypeId::BaseDataVariableType)
+        .has_modelling_rule(ObjectId::ModellingRule_Mandatory)
+        .component_of(motor_type_id.clone())
+        .insert(address_space);
+
+    let pump_type_id = NodeId::new(1, "PumpType");
+    ObjectTypeBuilder::new(&pump_type_id, "PumpType", "PumpType")
+        .subtype_of(device_type_id.clone())
+        .insert(address_space);
+    ObjectBuilder::new(&NodeId::new(1, "PumpType.Motor"), "Motor", "Motor")
+        .has_type_definition(motor_type_id)
+        .has_modelling_rule(ModellingRule::Mandatory)
+        .component_of(pump_type_id.clone())
+        .insert(address_space);
+    ObjectBuilder::new(&NodeId::new(1, "PumpType.Sensor"), "<Sensor>", "<Sensor>")
+        .has_type_definition(ObjectTypeId::BaseObjectType)
+        .has_modelling_rule(ModellingRule::OptionalPlaceholder)
+        .component_of(pump_type_id.clone())
+        .insert(address_space);
+
+    (device_type_id, pump_type_id)
+}
+
+#[test]
+fn instantiate_object_type() {
+    let mut address_space = AddressSpace::new();
+    let (_, pump_type_id) = add_device_types(&mut address_space);
+
+    let pump_id = address_space
+        .instantiate(&pump_type_id, "Pump1", &ObjectId::ObjectsFolder.into())
+        .unwrap();
+    // The objects folder organizes the pump
+    assert!(address_space.has_reference(
+        &ObjectId::ObjectsFolder.into(),
+        &pump_id,
+        ReferenceTypeId::Organizes
+    ));
+    assert!(address_space.has_reference(
+        &pump_id,
+        &pump_type_id,
+        ReferenceTypeId::HasTypeDefinition
+    ));
+
+    // The mandatory property of the supertype is copied with its value
+    let serial_number =
+        find_node_from_browse_path(&address_space, &pump_id, &["SerialNumber".into()]).unwrap();
+    let serial_number_id = serial_number.as_node().node_id();
+    assert_ne!(serial_number_id, NodeId::new(1, "DeviceType.SerialNumber"));
+    assert!(address_space.has_reference(&pump_id, &serial_number_id, ReferenceTypeId::HasProperty));
+    assert!(address_space.has_reference(
+        &serial_number_id,
+        &VariableTypeId::PropertyType.into(),
+        ReferenceTypeId::HasTypeDefinition
+    ));
+    // The copy is an instance, not an instance declaration
+    assert!(!address_space.has_reference(
+        &serial_number_id,
+        &ObjectId::ModellingRule_Mandatory.into(),
+        ReferenceTypeId::HasModellingRule
+    ));
+    assert_eq!(
+        address_space
+            .get_variable_value(serial_number_id)
+            .unwrap()
+            .value,
+        Some(Variant::from("None"))
+    );
+
+    // Neither the optional property nor the placeholder are copied
+    assert!(find_node_from_browse_path(&address_space, &pump_id, &["Model".into()]).is_err());
+    assert!(find_node_from_browse_path(&address_space, &pump_id, &["<Sensor>".into()]).is_err());
+
+    // The method is referenced rather than copied
+    assert!(address_space.has_reference(
+        &pump_id,
+        &NodeId::new(1, "DeviceType.Reset"),
+        ReferenceTypeId::HasComponent
+    ));
+
+    // The mandatory declarations of the motor's type are copied into the motor
+    let speed =
+        find_node_from_browse_path(&address_space, &pump_id, &["Motor".into(), "Speed".into()])
+            .unwrap();
+    assert_eq!(speed.node_class(), NodeClass::Variable);
+}
+
+#[test]
+fn instantiate_variable_type() {
+    let mut address_space = AddressSpace::new();
+    let (_, pump_type_id) = add_device_types(&mut address_space);
+    let pump_id = address_space
+        .instantiate(&pump_type_id, "Pump1", &ObjectId::ObjectsFolder.into())
+        .unwrap();
+
+    let level_id = address_space
+        .instantiate(
+            &VariableTypeId::BaseDataVariableType.into(),
+            "Level",
+            &pump_id,
+        )
+        .unwrap();
+    assert!(address_space.find_variable_by_ref(&level_id).is_some());
+    assert!(address_space.has_reference(&pump_id, &level_id, ReferenceTypeId::HasComponent));
+
+    let property_id = address_space
+        .instantiate(&VariableTypeId::PropertyType.into(), "Rating", &pump_id)
+        .unwrap();
+    assert!(address_space.has_reference(&pump_id, &property_id, ReferenceTypeId::HasProperty));
+}
+
+#[test]
+fn instantiate_errors() {
+    let mut address_space = AddressSpace::new();
+    let (device_type_id, pump_type_id) = add_device_types(&mut address_space);
+    let objects_folder_id: NodeId = ObjectId::ObjectsFolder.into();
+
+    // An abstract type cannot be instantiated
+    assert_eq!(
+        address_space.instantiate(&device_type_id, "Device1", &objects_folder_id),
+        Err(StatusCode::BadTypeDefinitionInvalid)
+    );
+    // Nor can a node that is not a type
+    assert_eq!(
+        address_space.instantiate(&objects_folder_id, "Device1", &objects_folder_id),
+        Err(StatusCode::BadTypeDefinitionInvalid)
+    );
+    assert_eq!(
+        address_space.instantiate(&pump_type_id, "Pump1", &NodeId::new(1, "NoSuchParent")),
+        Err(StatusCode::BadParentNodeIdInvalid)
+    );
+    assert!(address_space
+        .instantiate(&pump_type_id, "Pump1", &objects_folder_id)
+        .is_ok());
+    assert_eq!(
+        address_space.instantiate(&pump_type_id, "Pump1", &objects_folder_id),
+        Err(StatusCode::BadBrowseNameDuplicated)
+    );
+}
+
+#[test]
+fn instantiate_with_optional() {
+    let mut address_space = AddressSpace::new();
+    let (_, pump_type_id) = add_device_types(&mut address_space);
+
+    let pump_id = address_space
+        .instantiate_with_optional(
+            &pump_type_id,
+            "Pump1",
+            &ObjectId::ObjectsFolder.into(),
+            &["Model".into(), "<Sensor>".into()],
+        )
+        .unwrap();
+    assert!(find_node_from_browse_path(&address_space, &pump_id, &["Model".into()]).is_ok());
+    assert!(find_node_from_browse_path(&address_space, &pump_id, &["SerialNumber".into()]).is_ok());
+    // A placeholder is never copied
+    assert!(find_node_from_browse_path(&address_space, &pump_id, &["<Sensor>".into()]).is_err());
+}
+
+#[test]
+fn modelling_rules() {
+    let mut address_space = AddressSpace::new();
+    let (device_type_id, pump_type_id) = add_device_types(&mut address_space);
+
+    assert_eq!(
+        address_space.modelling_rule(&NodeId::new(1, "DeviceType.SerialNumber")),
+        Some(ModellingRule::Mandatory)
+    );
+    assert_eq!(
+        address_space.modelling_rule(&NodeId::new(1, "DeviceType.Model")),
+        Some(ModellingRule::Optional)
+    );
+    assert_eq!(
+        address_space.modelling_rule(&NodeId::new(1, "PumpType.Sensor")),
+        Some(ModellingRule::OptionalPlaceholder)
+    );
+    assert!(address_space.is_instance_declaration(&NodeId::new(1, "DeviceType.Reset")));
+    assert!(!address_space.is_instance_declaration(&device_type_id));
+
+    // The declarations of the pump type include those of the device type
+    let mut declarations = address_space.find_instance_declarations(&pump_type_id);
+    declarations.sort_by_key(|(node_id, _)| node_id.to_string());
+    assert_eq!(
+        declarations,
+        vec![
+            (NodeId::new(1, "DeviceType.Model"), ModellingRule::Optional),
+            (NodeId::new(1, "DeviceType.Reset"), ModellingRule::Mandatory),
+            (
+                NodeId::new(1, "DeviceType.SerialNumber"),
+                ModellingRule::Mandatory
+            ),
+            (NodeId::new(1, "PumpType.Motor"), ModellingRule::Mandatory),
+            (
+                NodeId::new(1, "PumpType.Sensor"),
+                ModellingRule::OptionalPlaceholder
+            ),
+        ]
+    );
+
+    // The nodes of an instance are not instance declarations
+    let pump_id = address_space
+        .instantiate(&pump_type_id, "Pump1", &ObjectId::ObjectsFolder.into())
+        .unwrap();
+    let motor = find_node_from_browse_path(&address_space, &pump_id, &["Motor".into()]).unwrap();
+    let motor_id = motor.as_node().node_id();
+    assert_eq!(address_space.modelling_rule(&motor_id), None);
+    assert!(!address_space.is_instance_declaration(&pump_id));
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use tempdir::TempDir;
+
+use crate::client::prelude::*;
+use crate::server::{builder::ServerBuilder, server::Server};
+use crate::sync::*;
+
+const CONFORMANCE_HOST: &str = "conformance-test";
+const CONFORMANCE_PORT: u16 = 4855;
+
+#[test]
+fn conformance_embedded_profile() {
+    let pki_dir = TempDir::new("conformance").unwrap();
+
+    let server = ServerBuilder::new_anonymous("Conformance Test Server")
+        .application_uri("urn:conformance-test-server")
+        .host_and_port(CONFORMANCE_HOST, CONFORMANCE_PORT)
+        .pki_dir(pki_dir.path().join("server"))
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .server()
+        .unwrap();
+    let server = Arc::new(RwLock::new(server));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server_task = runtime.spawn(Server::new_loopback_server_task(server.clone()));
+
+    // Wait for the server to bind its listener
+    let endpoint_url = format!("opc.tcp://{}:{}/", CONFORMANCE_HOST, CONFORMANCE_PORT);
+    while !crate::core::comms::loopback::is_bound(&endpoint_url, CONFORMANCE_PORT) {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut client = ClientBuilder::new()
+        .loopback(true)
+        .application_name("Conformance Test Client")
+        .application_uri("urn:conformance-test-client")
+        .pki_dir(pki_dir.path().join("client"))
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .client()
+        .unwrap();
+
+    let session = client
+        .connect_to_endpoint(
+            (
+                endpoint_url.as_ref(),
+                SecurityPolicy::None.to_str(),
+                MessageSecurityMode::None,
+                UserTokenPolicy::anonymous(),
+            ),
+            IdentityToken::Anonymous,
+        )
+        .unwrap();
+
+    let report = ConformanceTest::new(session.clone(), ConformanceProfile::Embedded).run();
+    println!("{}", report);
+    assert_eq!(report.profile, ConformanceProfile::Embedded);
+    assert_eq!(report.results.len(), 10);
+    assert!(report.passed());
+
+    let nano_report = ConformanceTest::new(session.clone(), ConformanceProfile::Nano).run();
+    assert!(nano_report
+        .results
+        .iter()
+        .all(|r| r.profile == ConformanceProfile::Nano));
+
+    {
+        let session = trace_read_lock!(session);
+        session.disconnect();
+    }
+    {
+        let mut server = trace_write_lock!(server);
+        server.abort();
+    }
+    runtime.block_on(server_task).unwrap();
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::{Cursor, Read, Write},
+    net::{IpAddr, Ipv4Addr, TcpStream},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tempdir::TempDir;
+
+use crate::core::comms::tcp_types::{ErrorMessage, HelloMessage};
+use crate::sync::*;
+use crate::types::{status_code::StatusCode, BinaryEncoder, DecodingOptions};
+
+use crate::server::{
+    builder::ServerBuilder, comms::connection_limiter::ConnectionLimiter, config::ServerConfig,
+    server::Server,
+};
+
+#[test]
+fn connection_limiter_limits_rate_per_address() {
+    let mut limiter = ConnectionLimiter::new(2, 0);
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let start = tokio::time::Instant::now();
+
+    assert!(limiter.admit(a, 0, start).is_ok());
+    assert!(limiter.admit(a, 0, start + Duration::from_secs(1)).is_ok());
+    assert_eq!(
+        limiter.admit(a, 0, start + Duration::from_secs(2)),
+        Err(StatusCode::BadTcpServerTooBusy)
+    );
+    // Another address has a rate of its own
+    assert!(limiter.admit(b, 0, start + Duration::from_secs(2)).is_ok());
+    // The rejected connection did not count, so the first drops out of the minute on time
+    assert!(limiter.admit(a, 0, start + Duration::from_secs(60)).is_ok());
+    assert_eq!(
+        limiter.admit(a, 0, start + Duration::from_secs(60)),
+        Err(StatusCode::BadTcpServerTooBusy)
+    );
+}
+
+#[test]
+fn connection_limiter_limits_unauthenticated_connections() {
+    let mut limiter = ConnectionLimiter::new(0, 3);
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let now = tokio::time::Instant::now();
+    assert!(limiter.admit(a, 2, now).is_ok());
+    assert_eq!(
+        limiter.admit(a, 3, now),
+        Err(StatusCode::BadTcpServerTooBusy)
+    );
+
+    // No limits
+    let mut limiter = ConnectionLimiter::new(0, 0);
+    for _ in 0..100 {
+        assert!(limiter.admit(a, 1000, now).is_ok());
+    }
+}
+
+/// Runs a server on a free port with the configuration that the function makes, until the test
+/// function returns
+fn with_server<F, T>(configure: F, test: T)
+where
+    F: FnOnce(&mut ServerConfig),
+    T: FnOnce(u16),
+{
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let pki_dir = TempDir::new("connection_limits").unwrap();
+    let mut config = ServerBuilder::new_anonymous("Connection Limits Test Server")
+        .application_uri("urn:connection-limits-test-server")
+        .host_and_port("127.0.0.1", port)
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .config();
+    configure(&mut config);
+    let server = Arc::new(RwLock::new(Server::new(config)));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server_task = runtime.spawn(Server::new_server_task(server.clone()));
+    let started = Instant::now();
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(started.elapsed() < Duration::from_secs(10));
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    test(port);
+
+    {
+        let mut server = trace_write_lock!(server);
+        server.abort();
+    }
+    runtime.block_on(server_task).unwrap();
+}
+
+/// Reads what the server sends until it closes the connection, which must be an error message
+fn read_error(socket: &mut TcpStream) -> StatusCode {
+    socket
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let mut bytes = Vec::new();
+    socket.read_to_end(&mut bytes).unwrap();
+    let error = ErrorMessage::decode(&mut Cursor::new(bytes), &DecodingOptions::test()).unwrap();
+    StatusCode::from_u32(error.error).unwrap()
+}
+
+fn send_hello(socket: &mut TcpStream, port: u16) {
+    let hello = HelloMessage::new(
+        &format!("opc.tcp://127.0.0.1:{}/", port),
+        65535,
+        65535,
+        0,
+        0,
+    );
+    socket.write_all(&hello.encode_to_vec()).unwrap();
+}
+
+#[test]
+fn connection_without_hello_is_closed_with_error() {
+    with_server(
+        |config| config.tcp_config.hello_timeout = 1,
+        |port| {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let started = Instant::now();
+            assert_eq!(read_error(&mut socket), StatusCode::BadTimeout);
+            assert!(started.elapsed() < Duration::from_secs(5));
+
+            // A hello that is followed by nothing is closed at the same deadline
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            send_hello(&mut socket, port);
+            socket
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            let mut bytes = Vec::new();
+            socket.read_to_end(&mut bytes).unwrap();
+            // The acknowledge is followed by the error
+            assert_eq!(&bytes[..4], b"ACKF");
+            let ack_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+            let error = ErrorMessage::decode(
+                &mut Cursor::new(&bytes[ack_size..]),
+                &DecodingOptions::test(),
+            )
+            .unwrap();
+            assert_eq!(error.error, StatusCode::BadTimeout.bits());
+        },
+    );
+}
+
+#[test]
+fn invalid_hello_is_rejected_with_error() {
+    with_server(
+        |_| {},
+        |port| {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let mut hello = HelloMessage::new(
+                &format!("opc.tcp://127.0.0.1:{}/", port),
+                65535,
+                65535,
+                0,
+                0,
+            );
+            hello.protocol_version = 100;
+            socket.write_all(&hello.encode_to_vec()).unwrap();
+            assert_eq!(
+                read_error(&mut socket),
+                StatusCode::BadProtocolVersionUnsupported
+            );
+        },
+    );
+}
+
+#[test]
+fn unauthenticated_connections_are_limited() {
+    with_server(
+        |config| config.tcp_config.max_unauthenticated_connections = 2,
+        |port| {
+            // The connection that waits for the server to start counts too, until it is noticed
+            // that it is closed, so the test waits for the server to forget it
+            std::thread::sleep(Duration::from_millis(500));
+            let _first = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let _second = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            let mut third = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            assert_eq!(read_error(&mut third), StatusCode::BadTcpServerTooBusy);
+        },
+    );
+}
+
+#[test]
+fn connection_rate_is_limited() {
+    with_server(
+        |config| config.tcp_config.max_connection_rate = 3,
+        |port| {
+            // The server was already connected to once to see that it is running
+            let _second = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let _third = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let mut fourth = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            assert_eq!(read_error(&mut fourth), StatusCode::BadTcpServerTooBusy);
+        },
+    );
+}
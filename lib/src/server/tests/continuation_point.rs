@@ -0,0 +1,80 @@
+use chrono::{Duration, Utc};
+
+use crate::server::continuation_point::ContinuationPoints;
+use crate::types::{status_code::StatusCode, ByteString};
+
+#[test]
+fn add_and_take() {
+    let now = Utc::now();
+    let mut continuation_points = ContinuationPoints::new(10, 1000);
+    let id1 = continuation_points.add(&now, 1).unwrap();
+    let id2 = continuation_points.add(&now, 2).unwrap();
+    assert_ne!(id1, id2);
+    assert_eq!(continuation_points.len(), 2);
+
+    // A continuation point can only be taken once
+    assert_eq!(continuation_points.take(&now, &id2), Ok(2));
+    assert_eq!(
+        continuation_points.take(&now, &id2),
+        Err(StatusCode::BadContinuationPointInvalid)
+    );
+    assert_eq!(
+        continuation_points.take(&now, &ByteString::from(vec![1u8, 2, 3])),
+        Err(StatusCode::BadContinuationPointInvalid)
+    );
+    assert_eq!(continuation_points.take(&now, &id1), Ok(1));
+    assert!(continuation_points.is_empty());
+}
+
+#[test]
+fn limit() {
+    let now = Utc::now();
+    let mut continuation_points = ContinuationPoints::new(2, 1000);
+    let id1 = continuation_points.add(&now, 1).unwrap();
+    let _ = continuation_points.add(&now, 2).unwrap();
+
+    // The oldest continuation point is not evicted to make way for another one
+    assert_eq!(
+        continuation_points.add(&now, 3),
+        Err(StatusCode::BadNoContinuationPoints)
+    );
+    assert_eq!(continuation_points.len(), 2);
+
+    // Releasing one makes room
+    assert_eq!(continuation_points.release(&id1), Ok(1));
+    assert_eq!(
+        continuation_points.release(&id1),
+        Err(StatusCode::BadContinuationPointInvalid)
+    );
+    assert!(continuation_points.add(&now, 3).is_ok());
+    assert_eq!(continuation_points.clear(), 2);
+    assert!(continuation_points.is_empty());
+}
+
+#[test]
+fn expiry() {
+    let now = Utc::now();
+    let mut continuation_points = ContinuationPoints::new(2, 1000);
+    let id1 = continuation_points.add(&now, 1).unwrap();
+    let id2 = continuation_points
+        .add(&(now + Duration::milliseconds(500)), 2)
+        .unwrap();
+
+    // An expired continuation point is invalid
+    let later = now + Duration::milliseconds(1000);
+    assert_eq!(
+        continuation_points.take(&later, &id1),
+        Err(StatusCode::BadContinuationPointInvalid)
+    );
+    assert_eq!(continuation_points.len(), 1);
+
+    // Expired continuation points make room for new ones
+    let _ = continuation_points.add(&later, 3).unwrap();
+    let later = now + Duration::milliseconds(1500);
+    assert_eq!(continuation_points.remove_expired(&later), 1);
+    assert_eq!(
+        continuation_points.take(&later, &id2),
+        Err(StatusCode::BadContinuationPointInvalid)
+    );
+    assert_eq!(continuation_points.len(), 1);
+}
@@ -4,14 +4,21 @@ use crate::types::{
     node_ids::ReferenceTypeId,
     operand::{ContentFilterBuilder, Operand},
     service_types::ContentFilterElement,
-    AttributeId, DataTypeId, LocalizedText, NodeId, ObjectId, ObjectTypeId, QualifiedName,
-    UAString, VariableTypeId, Variant,
+    status_code::StatusCode,
+    AttributeId, DataTypeId, DateTime, LocalizedText, NodeId, NumericRange, ObjectId, ObjectTypeId,
+    QualifiedName, TimestampsToReturn, UAString, VariableTypeId, Variant,
 };
 
 use crate::server::{
-    address_space::{object_type::ObjectTypeBuilder, variable::VariableBuilder, AddressSpace},
-    events::event::{BaseEventType, Event},
+    address_space::{
+        object_type::ObjectTypeBuilder, relative_path::find_node_from_browse_path,
+        state_machine::FiniteStateMachineBuilder, variable::VariableBuilder, AddressSpace,
+    },
+    events::alarm::ShelvedState,
+    events::event::{filter_events, retained_conditions_for_object, BaseEventType, Event},
     events::event_filter,
+    events::limit_alarm::{LimitAlarmKind, LimitAlarmType},
+    events::off_normal_alarm::OffNormalAlarmType,
     events::operator,
     tests::*,
 };
@@ -788,6 +795,42 @@ fn test_bitwise_and() {
     })
 }
 
+#[test]
+fn test_of_type() {
+    do_operator_test(|address_space, object_id, used_elements, elements| {
+        // The event's own type, its supertype and an unrelated type
+        [
+            (TestEventType::event_type_id(), true),
+            (ObjectTypeId::BaseEventType.into(), true),
+            (ObjectTypeId::FolderType.into(), false),
+        ]
+        .iter()
+        .for_each(|(type_id, expected)| {
+            let operands = &[Operand::literal(Variant::from(type_id.clone()))];
+            let result = operator::of_type(
+                object_id,
+                &operands[..],
+                used_elements,
+                elements,
+                address_space,
+            )
+            .unwrap();
+            assert_eq!(result, Variant::Boolean(*expected));
+        });
+
+        // Operand must be a node id
+        let operands = &[Operand::literal(10)];
+        let result = operator::of_type(
+            object_id,
+            &operands[..],
+            used_elements,
+            elements,
+            address_space,
+        );
+        assert!(result.is_err());
+    })
+}
+
 #[test]
 fn test_where_clause() {
     crate::console_logging::init();
@@ -863,3 +906,503 @@ fn test_where_clause() {
             assert_eq!(result.unwrap(), expected.into());
         });
 }
+
+/// Returns the value of a variable of an alarm
+fn alarm_value(
+    address_space: &AddressSpace,
+    alarm_id: &NodeId,
+    browse_path: &[&'static str],
+) -> Variant {
+    let browse_path = browse_path
+        .iter()
+        .map(|name| QualifiedName::from(*name))
+        .collect::<Vec<_>>();
+    let node = find_node_from_browse_path(address_space, alarm_id, &browse_path).unwrap();
+    let node_id = node.as_node().node_id();
+    address_space
+        .find_variable_by_ref(&node_id)
+        .and_then(|variable| {
+            variable
+                .value(
+                    TimestampsToReturn::Neither,
+                    NumericRange::None,
+                    &QualifiedName::null(),
+                    0.0,
+                )
+                .value
+        })
+        .unwrap()
+}
+
+fn limit_alarm_address_space() -> (AddressSpace, u16) {
+    let mut address_space = AddressSpace::new();
+    // The alarm's properties have numeric ids so it goes in a namespace of its own
+    let ns = address_space.register_namespace("urn:alarms").unwrap();
+    VariableBuilder::new(
+        &NodeId::new(ns, "Temperature"),
+        "Temperature",
+        "Temperature",
+    )
+    .organized_by(ObjectId::ObjectsFolder)
+    .data_type(DataTypeId::Double)
+    .value(20f64)
+    .insert(&mut address_space);
+    VariableBuilder::new(&NodeId::new(ns, "Setpoint"), "Setpoint", "Setpoint")
+        .organized_by(ObjectId::ObjectsFolder)
+        .data_type(DataTypeId::Double)
+        .value(20f64)
+        .insert(&mut address_space);
+    (address_space, ns)
+}
+
+fn set_temperature(address_space: &mut AddressSpace, ns: u16, name: &'static str, value: f64) {
+    let now = DateTime::now();
+    assert!(address_space.set_variable_value_by_ref(&NodeId::new(ns, name), value, &now, &now));
+}
+
+#[test]
+fn exclusive_limit_alarm() {
+    crate::console_logging::init();
+
+    let (mut address_space, ns) = limit_alarm_address_space();
+    let alarm_id = NodeId::new(ns, "TemperatureAlarm");
+    let mut alarm = LimitAlarmType::new(
+        &alarm_id,
+        "TemperatureAlarm",
+        "TemperatureAlarm",
+        NodeId::new(ns, "Temperature"),
+        LimitAlarmKind::ExclusiveLimit,
+    )
+    .high_high_limit(90.0)
+    .high_limit(70.0)
+    .low_limit(10.0);
+    assert!(alarm.raise(&mut address_space).is_ok());
+
+    // The alarm is a condition of the server and within its limits
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(false)
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["HighHighLimit"]),
+        Variant::from(90f64)
+    );
+    assert!(retained_conditions_for_object(ObjectId::Server, &address_space).is_none());
+
+    // Crossing the high limit activates the alarm and fires an event
+    let event_id = alarm_value(&address_space, &alarm_id, &["EventId"]);
+    set_temperature(&mut address_space, ns, "Temperature", 75.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(true)
+    );
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["LimitState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::from(&ObjectId::ExclusiveLimitStateMachineType_High))
+    );
+    assert_ne!(
+        alarm_value(&address_space, &alarm_id, &["EventId"]),
+        event_id
+    );
+    assert_eq!(
+        retained_conditions_for_object(ObjectId::Server, &address_space),
+        Some(vec![alarm_id.clone()])
+    );
+
+    // An exclusive alarm is only in the state of the furthest limit
+    set_temperature(&mut address_space, ns, "Temperature", 95.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["LimitState", "CurrentState"]),
+        Variant::from(LocalizedText::from("HighHigh"))
+    );
+
+    // A value that stays in the same state fires no event
+    let event_id = alarm_value(&address_space, &alarm_id, &["EventId"]);
+    set_temperature(&mut address_space, ns, "Temperature", 96.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["EventId"]),
+        event_id
+    );
+
+    set_temperature(&mut address_space, ns, "Temperature", 5.0);
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["LimitState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::from(&ObjectId::ExclusiveLimitStateMachineType_Low))
+    );
+
+    // Returning within the limits deactivates the alarm which is no longer retained
+    set_temperature(&mut address_space, ns, "Temperature", 20.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(false)
+    );
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["LimitState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::null())
+    );
+    assert!(retained_conditions_for_object(ObjectId::Server, &address_space).is_none());
+
+    // A deleted alarm is no longer evaluated
+    assert!(address_space.delete(&alarm_id, true));
+    set_temperature(&mut address_space, ns, "Temperature", 95.0);
+    assert!(address_space.find_node(&alarm_id).is_none());
+}
+
+#[test]
+fn non_exclusive_limit_alarm() {
+    crate::console_logging::init();
+
+    let (mut address_space, ns) = limit_alarm_address_space();
+    let alarm_id = NodeId::new(ns, "TemperatureAlarm");
+    let mut alarm = LimitAlarmType::new(
+        &alarm_id,
+        "TemperatureAlarm",
+        "TemperatureAlarm",
+        NodeId::new(ns, "Temperature"),
+        LimitAlarmKind::NonExclusiveLimit,
+    )
+    .source_node(ObjectId::ObjectsFolder)
+    .high_high_limit(90.0)
+    .high_limit(70.0);
+    assert!(alarm.raise(&mut address_space).is_ok());
+
+    // A non-exclusive alarm is in every state the value lies beyond
+    set_temperature(&mut address_space, ns, "Temperature", 95.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["HighHighState", "Id"]),
+        Variant::from(true)
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["HighState", "Id"]),
+        Variant::from(true)
+    );
+    assert_eq!(
+        retained_conditions_for_object(ObjectId::ObjectsFolder, &address_space),
+        Some(vec![alarm_id.clone()])
+    );
+
+    set_temperature(&mut address_space, ns, "Temperature", 80.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["HighHighState", "Id"]),
+        Variant::from(false)
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["HighState"]),
+        Variant::from(LocalizedText::from("Active"))
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(true)
+    );
+}
+
+#[test]
+fn deviation_alarm() {
+    crate::console_logging::init();
+
+    let (mut address_space, ns) = limit_alarm_address_space();
+    let alarm_id = NodeId::new(ns, "TemperatureAlarm");
+    let mut alarm = LimitAlarmType::new(
+        &alarm_id,
+        "TemperatureAlarm",
+        "TemperatureAlarm",
+        NodeId::new(ns, "Temperature"),
+        LimitAlarmKind::ExclusiveDeviation(NodeId::new(ns, "Setpoint")),
+    )
+    .high_limit(5.0)
+    .low_limit(-5.0);
+    assert!(alarm.raise(&mut address_space).is_ok());
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["SetpointNode"]),
+        Variant::from(NodeId::new(ns, "Setpoint"))
+    );
+
+    // The alarm compares the limits with the deviation of the input from the setpoint
+    set_temperature(&mut address_space, ns, "Temperature", 24.0);
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(false)
+    );
+    set_temperature(&mut address_space, ns, "Setpoint", 30.0);
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["LimitState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::from(&ObjectId::ExclusiveLimitStateMachineType_Low))
+    );
+    set_temperature(&mut address_space, ns, "Temperature", 36.0);
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["LimitState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::from(&ObjectId::ExclusiveLimitStateMachineType_High))
+    );
+}
+
+#[test]
+fn off_normal_alarm() {
+    crate::console_logging::init();
+
+    let mut address_space = AddressSpace::new();
+    let ns = address_space.register_namespace("urn:alarms").unwrap();
+    let mode_id = NodeId::new(ns, "Mode");
+    let normal_mode_id = NodeId::new(ns, "NormalMode");
+    VariableBuilder::new(&mode_id, "Mode", "Mode")
+        .organized_by(ObjectId::ObjectsFolder)
+        .data_type(DataTypeId::Int32)
+        .value(1i32)
+        .insert(&mut address_space);
+    VariableBuilder::new(&normal_mode_id, "NormalMode", "NormalMode")
+        .organized_by(ObjectId::ObjectsFolder)
+        .data_type(DataTypeId::UInt32)
+        .value(1u32)
+        .insert(&mut address_space);
+    let set_value = |address_space: &mut AddressSpace, node_id: &NodeId, value: Variant| {
+        let now = DateTime::now();
+        assert!(address_space.set_variable_value_by_ref(node_id, value, &now, &now));
+    };
+
+    let alarm_id = NodeId::new(ns, "ModeAlarm");
+    let mut alarm = OffNormalAlarmType::new(
+        &alarm_id,
+        "ModeAlarm",
+        "ModeAlarm",
+        mode_id.clone(),
+        normal_mode_id.clone(),
+    );
+    assert!(alarm.raise(&mut address_space).is_ok());
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["NormalState"]),
+        Variant::from(normal_mode_id.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(false)
+    );
+
+    // The alarm is active while the value differs from the normal one
+    set_value(&mut address_space, &mode_id, 2i32.into());
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(true)
+    );
+    assert_eq!(
+        retained_conditions_for_object(ObjectId::Server, &address_space),
+        Some(vec![alarm_id.clone()])
+    );
+    set_value(&mut address_space, &normal_mode_id, 2u32.into());
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(false)
+    );
+
+    // A suppressed alarm changes its state without firing an event
+    assert!(!address_space.set_alarm_suppressed(&mode_id, true));
+    assert!(address_space.set_alarm_suppressed(&alarm_id, true));
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["SuppressedOrShelved"]),
+        Variant::from(true)
+    );
+    let event_id = alarm_value(&address_space, &alarm_id, &["EventId"]);
+    set_value(&mut address_space, &mode_id, 3i32.into());
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["ActiveState", "Id"]),
+        Variant::from(true)
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["EventId"]),
+        event_id
+    );
+    assert!(address_space.set_alarm_suppressed(&alarm_id, false));
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["SuppressedState", "Id"]),
+        Variant::from(false)
+    );
+
+    // A one shot shelved alarm is unshelved when it becomes inactive
+    assert!(address_space
+        .set_alarm_shelved_state(&alarm_id, ShelvedState::OneShotShelved)
+        .is_ok());
+    let event_id = alarm_value(&address_space, &alarm_id, &["EventId"]);
+    set_value(&mut address_space, &mode_id, 2i32.into());
+    assert_ne!(
+        alarm_value(&address_space, &alarm_id, &["EventId"]),
+        event_id
+    );
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["ShelvingState", "CurrentState"]
+        ),
+        Variant::from(LocalizedText::from("Unshelved"))
+    );
+    assert_eq!(
+        alarm_value(&address_space, &alarm_id, &["SuppressedOrShelved"]),
+        Variant::from(false)
+    );
+
+    // A timed shelved alarm is unshelved when its time has elapsed
+    assert!(address_space
+        .set_alarm_shelved_state(&alarm_id, ShelvedState::TimedShelved(1f64))
+        .is_ok());
+    assert!(address_space.has_shelved_alarms_with_unshelve_time());
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    address_space.update_shelved_alarms();
+    assert!(!address_space.has_shelved_alarms_with_unshelve_time());
+    assert_eq!(
+        alarm_value(
+            &address_space,
+            &alarm_id,
+            &["ShelvingState", "CurrentState", "Id"]
+        ),
+        Variant::from(NodeId::from(&ObjectId::ShelvedStateMachineType_Unshelved))
+    );
+    assert_eq!(
+        address_space.set_alarm_shelved_state(&alarm_id, ShelvedState::Unshelved),
+        Err(StatusCode::BadConditionNotShelved)
+    );
+}
+
+#[test]
+fn finite_state_machine() {
+    crate::console_logging::init();
+
+    let mut address_space = AddressSpace::new();
+    let ns = address_space
+        .register_namespace("urn:statemachines")
+        .unwrap();
+    let door_id = NodeId::new(ns, "Door");
+    let (closed, open) = (NodeId::new(ns, "Closed"), NodeId::new(ns, "Open"));
+    let (opening, closing) = (NodeId::new(ns, "Opening"), NodeId::new(ns, "Closing"));
+    FiniteStateMachineBuilder::new(&door_id, "Door", "Door")
+        .state(closed.clone(), "Closed", 1)
+        .state(open.clone(), "Open", 2)
+        .transition(opening.clone(), "Opening", 1, closed.clone(), open.clone())
+        .transition(closing.clone(), "Closing", 2, open.clone(), closed.clone())
+        .organized_by(ObjectId::ObjectsFolder)
+        .insert(&mut address_space);
+
+    // The states and transitions are components of the state machine
+    assert!(address_space.has_reference(&door_id, &closed, ReferenceTypeId::HasComponent));
+    assert!(address_space.has_reference(&opening, &open, ReferenceTypeId::ToState));
+    assert_eq!(
+        alarm_value(&address_space, &closed, &["StateNumber"]),
+        Variant::from(1u32)
+    );
+
+    // The state machine starts in its first state
+    assert_eq!(
+        address_space.state_machine_current_state(&door_id),
+        Some(closed.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["CurrentState"]),
+        Variant::from(LocalizedText::from("Closed"))
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["CurrentState", "Id"]),
+        Variant::from(closed.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["LastTransition", "Id"]),
+        Variant::from(NodeId::null())
+    );
+    assert!(filter_events(
+        &door_id,
+        ObjectTypeId::TransitionEventType,
+        &address_space,
+        |_| true
+    )
+    .is_none());
+
+    // A transition updates the variables and fires an event
+    assert!(address_space
+        .fire_state_machine_transition(&door_id, opening.clone())
+        .is_ok());
+    assert_eq!(
+        address_space.state_machine_current_state(&door_id),
+        Some(open.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["CurrentState"]),
+        Variant::from(LocalizedText::from("Open"))
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["CurrentState", "Number"]),
+        Variant::from(2u32)
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["LastTransition"]),
+        Variant::from(LocalizedText::from("Opening"))
+    );
+    assert_eq!(
+        alarm_value(&address_space, &door_id, &["LastTransition", "Id"]),
+        Variant::from(opening.clone())
+    );
+    let events = filter_events(
+        &door_id,
+        ObjectTypeId::TransitionEventType,
+        &address_space,
+        |_| true,
+    )
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        alarm_value(&address_space, &events[0], &["Transition", "Id"]),
+        Variant::from(opening.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &events[0], &["FromState", "Id"]),
+        Variant::from(closed.clone())
+    );
+    assert_eq!(
+        alarm_value(&address_space, &events[0], &["ToState"]),
+        Variant::from(LocalizedText::from("Open"))
+    );
+
+    // A transition must leave the current state and be one of the state machine
+    assert_eq!(
+        address_space.fire_state_machine_transition(&door_id, opening.clone()),
+        Err(StatusCode::BadInvalidState)
+    );
+    assert_eq!(
+        address_space.fire_state_machine_transition(&door_id, NodeId::new(ns, "Locking")),
+        Err(StatusCode::BadInvalidArgument)
+    );
+    assert_eq!(
+        address_space.fire_state_machine_transition(&NodeId::new(ns, "Window"), closing.clone()),
+        Err(StatusCode::BadNodeIdUnknown)
+    );
+    assert!(address_space
+        .fire_state_machine_transition(&door_id, closing)
+        .is_ok());
+    assert_eq!(
+        address_space.state_machine_current_state(&door_id),
+        Some(closed)
+    );
+
+    // A deleted state machine takes no more transitions
+    assert!(address_space.delete(&door_id, true));
+    assert_eq!(address_space.state_machine_current_state(&door_id), None);
+    assert_eq!(
+        address_space.fire_state_machine_transition(&door_id, opening),
+        Err(StatusCode::BadNodeIdUnknown)
+    );
+}
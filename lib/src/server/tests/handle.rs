@@ -0,0 +1,104 @@
+use std::thread;
+
+use crate::server::{
+    events::event::{BaseEventType, Event},
+    handle::UpdateQueue,
+};
+
+use super::*;
+
+#[test]
+fn handle_set_value() {
+    let address_space = make_sample_address_space();
+    let ns = trace_read_lock!(address_space)
+        .namespace_index("urn:test")
+        .unwrap();
+    let (handle, mut update_queue) = UpdateQueue::new();
+
+    // Nothing changes until the queue is applied
+    let sampled = DateTime::ymd_hms(2022, 1, 1, 12, 0, 0);
+    assert!(handle.set_value(NodeId::new(ns, "v1"), 1i32, &sampled));
+    assert!(handle.set_value(NodeId::new(ns, "v1"), 2i32, &sampled));
+    assert!(handle.set_value(NodeId::new(ns, "v4"), 1.5f64, &sampled));
+    assert!(handle.set_value(NodeId::new(ns, "nothing"), 1i32, &sampled));
+    {
+        let address_space = trace_read_lock!(address_space);
+        let value = address_space
+            .get_variable_value(NodeId::new(ns, "v1"))
+            .unwrap();
+        assert_eq!(value.value, Some(Variant::Int32(30)));
+    }
+
+    assert_eq!(update_queue.apply_queued(&address_space, None), 4);
+    assert_eq!(update_queue.apply_queued(&address_space, None), 0);
+    {
+        let address_space = trace_read_lock!(address_space);
+        // Updates are applied in order, with the source timestamp of the handle
+        let value = address_space
+            .get_variable_value(NodeId::new(ns, "v1"))
+            .unwrap();
+        assert_eq!(value.value, Some(Variant::Int32(2)));
+        assert_eq!(value.source_timestamp, Some(sampled));
+        let value = address_space
+            .get_variable_value(NodeId::new(ns, "v4"))
+            .unwrap();
+        assert_eq!(value.value, Some(Variant::Double(1.5)));
+    }
+
+    // Handles stop queueing when the queue is gone
+    drop(update_queue);
+    assert!(handle.is_closed());
+    assert!(!handle.set_value(NodeId::new(ns, "v1"), 3i32, &sampled));
+}
+
+#[test]
+fn handle_from_threads() {
+    let address_space = make_sample_address_space();
+    let ns = trace_read_lock!(address_space)
+        .namespace_index("urn:test")
+        .unwrap();
+    let (handle, mut update_queue) = UpdateQueue::new();
+
+    let threads = (0..4)
+        .map(|i| {
+            let handle = handle.clone();
+            thread::spawn(move || {
+                (0..100).for_each(|j| {
+                    let _ = handle.set_value(NodeId::new(ns, "v1"), i * 100 + j, &DateTime::now());
+                });
+            })
+        })
+        .collect::<Vec<_>>();
+    threads.into_iter().for_each(|t| t.join().unwrap());
+
+    // The updates are applied in batches of up to 1000
+    assert_eq!(update_queue.apply_queued(&address_space, None), 400);
+}
+
+#[test]
+fn handle_raise_event() {
+    let address_space = make_sample_address_space();
+    let ns = trace_read_lock!(address_space)
+        .namespace_index("urn:test")
+        .unwrap();
+    let (handle, mut update_queue) = UpdateQueue::new();
+
+    let event_id = NodeId::new(ns, "Event1");
+    let event = BaseEventType::new_now(
+        event_id.clone(),
+        ObjectTypeId::BaseEventType,
+        "Event1",
+        "Event1",
+        NodeId::objects_folder_id(),
+    );
+    assert!(event.is_valid());
+    assert!(handle.raise_event(event));
+    assert!(trace_read_lock!(address_space)
+        .find_node(&event_id)
+        .is_none());
+
+    assert_eq!(update_queue.apply_queued(&address_space, None), 1);
+    assert!(trace_read_lock!(address_space)
+        .find_node(&event_id)
+        .is_some());
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use tempdir::TempDir;
+
+use crate::client::prelude::*;
+use crate::core::comms::url::local_socket_url;
+use crate::server::{builder::ServerBuilder, server::Server};
+use crate::sync::*;
+
+#[test]
+fn client_connects_through_local_socket() {
+    let pki_dir = TempDir::new("local_socket").unwrap();
+    let socket_path = pki_dir.path().join("server.sock");
+    let socket_path = socket_path.to_str().unwrap();
+
+    // The TCP port is picked by the OS, the client only uses the socket
+    let server = ServerBuilder::new_anonymous("Local Socket Test Server")
+        .application_uri("urn:local-socket-test-server")
+        .host_and_port("127.0.0.1", 0)
+        .local_socket(socket_path)
+        .pki_dir(pki_dir.path().join("server"))
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .server()
+        .unwrap();
+    let server = Arc::new(RwLock::new(server));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server_task = runtime.spawn(Server::new_server_task(server.clone()));
+    while !std::path::Path::new(socket_path).exists() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut client = ClientBuilder::new()
+        .application_name("Local Socket Test Client")
+        .application_uri("urn:local-socket-test-client")
+        .pki_dir(pki_dir.path().join("client"))
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .client()
+        .unwrap();
+
+    // The server advertises its endpoints with the url of the socket
+    let endpoint_url = format!("{}/", local_socket_url(socket_path));
+    let endpoints = client
+        .get_server_endpoints_from_url(endpoint_url.as_str())
+        .unwrap();
+    assert!(!endpoints.is_empty());
+    assert!(endpoints
+        .iter()
+        .all(|e| e.endpoint_url.as_ref() == endpoint_url));
+
+    let session = client
+        .connect_to_endpoint(
+            (
+                endpoint_url.as_ref(),
+                SecurityPolicy::None.to_str(),
+                MessageSecurityMode::None,
+                UserTokenPolicy::anonymous(),
+            ),
+            IdentityToken::Anonymous,
+        )
+        .unwrap();
+    {
+        let session = trace_read_lock!(session);
+        let results = session
+            .read(
+                &[ReadValueId::from(NodeId::from(
+                    &VariableId::Server_ServerStatus_State,
+                ))],
+                TimestampsToReturn::Neither,
+                0f64,
+            )
+            .unwrap();
+        assert_eq!(
+            results[0].value,
+            Some(Variant::from(ServerState::Running as i32))
+        );
+        session.disconnect();
+    }
+
+    {
+        let mut server = trace_write_lock!(server);
+        server.abort();
+    }
+    runtime.block_on(server_task).unwrap();
+}
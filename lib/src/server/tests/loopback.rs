@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use tempdir::TempDir;
+
+use crate::client::prelude::*;
+use crate::server::{builder::ServerBuilder, server::Server};
+use crate::sync::*;
+
+const LOOPBACK_HOST: &str = "loopback-test";
+const LOOPBACK_PORT: u16 = 4855;
+
+/// Runs a server on the loopback transport with the host name and calls the function with a
+/// session connected to it
+fn do_loopback_test<F>(host: &str, f: F)
+where
+    F: FnOnce(Arc<RwLock<Session>>),
+{
+    let pki_dir = TempDir::new("loopback").unwrap();
+
+    let server = ServerBuilder::new_anonymous("Loopback Test Server")
+        .application_uri("urn:loopback-test-server")
+        .host_and_port(host, LOOPBACK_PORT)
+        .pki_dir(pki_dir.path().join("server"))
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .server()
+        .unwrap();
+    let server = Arc::new(RwLock::new(server));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server_task = runtime.spawn(Server::new_loopback_server_task(server.clone()));
+
+    // Wait for the server to bind its listener
+    let endpoint_url = format!("opc.tcp://{}:{}/", host, LOOPBACK_PORT);
+    while !crate::core::comms::loopback::is_bound(&endpoint_url, LOOPBACK_PORT) {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut client = ClientBuilder::new()
+        .loopback(true)
+        .application_name("Loopback Test Client")
+        .application_uri("urn:loopback-test-client")
+        .pki_dir(pki_dir.path().join("client"))
+        .create_sample_keypair(false)
+        .trust_server_certs(true)
+        .session_retry_limit(0)
+        .client()
+        .unwrap();
+
+    let session = client
+        .connect_to_endpoint(
+            (
+                endpoint_url.as_ref(),
+                SecurityPolicy::None.to_str(),
+                MessageSecurityMode::None,
+                UserTokenPolicy::anonymous(),
+            ),
+            IdentityToken::Anonymous,
+        )
+        .unwrap();
+
+    f(session.clone());
+
+    {
+        let session = trace_read_lock!(session);
+        session.disconnect();
+    }
+
+    {
+        let mut server = trace_write_lock!(server);
+        server.abort();
+    }
+    runtime.block_on(server_task).unwrap();
+}
+
+#[test]
+fn loopback_client_reads_from_server() {
+    do_loopback_test(LOOPBACK_HOST, |session| {
+        let session = trace_read_lock!(session);
+        let results = session
+            .read(
+                &[ReadValueId::from(NodeId::from(
+                    &VariableId::Server_ServerStatus_State,
+                ))],
+                TimestampsToReturn::Neither,
+                0f64,
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].value,
+            Some(Variant::from(ServerState::Running as i32))
+        );
+    });
+}
+
+#[test]
+fn loopback_client_receives_diagnostics() {
+    do_loopback_test("diagnostics-test", |session| {
+        let mut session = trace_write_lock!(session);
+
+        // No diagnostics unless they are asked for
+        let nodes_to_read = [
+            ReadValueId::from(NodeId::from(&VariableId::Server_ServerStatus_State)),
+            ReadValueId::from(NodeId::new(2, "DoesNotExist")),
+        ];
+        let _ = session
+            .read(&nodes_to_read, TimestampsToReturn::Neither, 0f64)
+            .unwrap();
+        assert!(session.last_response_diagnostics().is_none());
+
+        session.set_return_diagnostics(DiagnosticBits::all());
+        let results = session
+            .read(&nodes_to_read, TimestampsToReturn::Neither, 0f64)
+            .unwrap();
+        assert_eq!(results[1].status(), StatusCode::BadNodeIdUnknown);
+
+        let diagnostics = session.last_response_diagnostics().unwrap();
+        assert!(diagnostics.service_diagnostics.is_none());
+        assert_eq!(diagnostics.operation_diagnostics.len(), 2);
+        assert!(diagnostics.operation_diagnostics[0].is_none());
+        let diagnostic_info = diagnostics.operation_diagnostics[1].as_ref().unwrap();
+        assert_eq!(
+            diagnostic_info.symbolic_id.as_deref(),
+            Some("BadNodeIdUnknown")
+        );
+        assert_eq!(
+            diagnostic_info.localized_text.as_deref(),
+            Some(StatusCode::BadNodeIdUnknown.description())
+        );
+        assert_eq!(
+            diagnostic_info.inner_status_code,
+            Some(StatusCode::BadNodeIdUnknown)
+        );
+    });
+}
@@ -4,20 +4,33 @@ use chrono;
 use time;
 
 use crate::core::{config::Config, supported_message::SupportedMessage};
+use crate::crypto::{SystemCertificateStore, SystemStoreLocation};
 use crate::sync::*;
 use crate::types::{status_code::StatusCode, *};
 
 use crate::server::{
     address_space::{address_space::*, variable::*},
     builder::ServerBuilder,
-    config::ServerConfig,
+    config::{QueueOverflowPolicy, ServerConfig},
     session::*,
     subscriptions::*,
 };
 
 mod address_space;
+#[cfg(feature = "client")]
+mod conformance;
+mod connection_limits;
+mod continuation_point;
+#[cfg(feature = "events")]
 mod events;
+mod handle;
+#[cfg(all(feature = "client", unix))]
+mod local_socket;
+#[cfg(feature = "client")]
+mod loopback;
+mod scheduler;
 mod services;
+mod simulation;
 mod subscriptions;
 
 fn make_test_file(filename: &str) -> PathBuf {
@@ -81,6 +94,28 @@ pub fn server_config_save() {
     }
 }
 
+#[test]
+pub fn server_config_bounded_memory() {
+    let config = ServerBuilder::new_sample()
+        .clients_can_modify_address_space()
+        .bounded_memory()
+        .config();
+    assert!(config.is_valid());
+    assert!(config.limits.clients_can_modify_address_space);
+    assert_eq!(config.limits.max_sessions, 4);
+    assert_eq!(
+        config.limits.queue_overflow_policy,
+        QueueOverflowPolicy::Block
+    );
+    // The limits can still be changed after the preset is applied
+    let config = ServerBuilder::new_sample()
+        .bounded_memory()
+        .max_sessions(1)
+        .config();
+    assert_eq!(config.limits.max_sessions, 1);
+    assert!(!config.limits.clients_can_modify_address_space);
+}
+
 #[test]
 pub fn server_config_invalid() {
     // Remove the endpoint
@@ -98,6 +133,54 @@ pub fn server_config_invalid() {
         .user_token_ids
         .insert("hello".to_string());
     assert_eq!(config.is_valid(), false);
+
+    // X509 tokens cannot be signed without security
+    config = ServerBuilder::new_anonymous("foo").config();
+    config
+        .endpoints
+        .get_mut("none")
+        .unwrap()
+        .x509_security_policy = Some("None".to_string());
+    assert!(!config.is_valid());
+
+    // Only secure sessions without a secure endpoint
+    config = ServerBuilder::new_anonymous("foo")
+        .secure_sessions_only(true)
+        .config();
+    assert!(!config.is_valid());
+    assert!(ServerBuilder::new_sample()
+        .secure_sessions_only(true)
+        .config()
+        .is_valid());
+
+    // The subscription timer cannot tick without a minimum interval
+    assert!(!ServerBuilder::new_anonymous("foo")
+        .min_sampling_interval(0.0)
+        .config()
+        .is_valid());
+    assert!(!ServerBuilder::new_anonymous("foo")
+        .min_publishing_interval(-1.0)
+        .config()
+        .is_valid());
+
+    // The store of the operating system is only supported on Windows, and needs store names
+    let system_certificate_store = SystemCertificateStore {
+        location: SystemStoreLocation::LocalMachine,
+        own_store: "My".to_string(),
+        own_thumbprint: "f5baa2ed4c1b0e1fb4d12f6a587a3b1d21593e7c".to_string(),
+        trusted_store: "TrustedPeople".to_string(),
+    };
+    config = ServerBuilder::new_sample()
+        .system_certificate_store(system_certificate_store.clone())
+        .config();
+    assert_eq!(config.is_valid(), SystemCertificateStore::is_supported());
+    config = ServerBuilder::new_sample()
+        .system_certificate_store(SystemCertificateStore {
+            trusted_store: String::new(),
+            ..system_certificate_store
+        })
+        .config();
+    assert!(!config.is_valid());
 }
 
 #[test]
@@ -0,0 +1,162 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use tempdir::TempDir;
+
+use crate::server::{builder::ServerBuilder, scheduler::Scheduler, state::ServerState};
+use crate::sync::*;
+use crate::types::service_types::ServerState as ServerStateType;
+
+/// Calls the function with a scheduler for a server that is running, and a runtime that the
+/// scheduler has been started on
+fn do_scheduler_test<F>(f: F)
+where
+    F: FnOnce(&mut Scheduler, Arc<RwLock<ServerState>>, &tokio::runtime::Runtime),
+{
+    let pki_dir = TempDir::new("scheduler").unwrap();
+    let server = ServerBuilder::new_anonymous("Scheduler Test Server")
+        .pki_dir(pki_dir.path())
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .server()
+        .unwrap();
+    let server_state = server.server_state();
+    {
+        let mut server_state = trace_write_lock!(server_state);
+        server_state.set_state(ServerStateType::Running);
+    }
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut scheduler = Scheduler::new(server_state.clone());
+    f(&mut scheduler, server_state, &runtime);
+}
+
+fn counter() -> (Arc<AtomicUsize>, impl Fn() + Send + Sync + 'static) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let count2 = count.clone();
+    (count, move || {
+        count2.fetch_add(1, Ordering::SeqCst);
+    })
+}
+
+fn sleep_ms(ms: u64) {
+    thread::sleep(Duration::from_millis(ms));
+}
+
+#[test]
+fn polling_actions() {
+    do_scheduler_test(|scheduler, _, runtime| {
+        // An action added before the scheduler starts waits for it
+        let (count, action) = counter();
+        let handle = scheduler.add_polling_action(10, action);
+        sleep_ms(50);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        runtime.block_on(async { scheduler.start() });
+        sleep_ms(200);
+        assert!(count.load(Ordering::SeqCst) > 2);
+        assert!(!handle.is_finished());
+
+        // A cancelled action is not called again
+        handle.cancel();
+        sleep_ms(50);
+        let cancelled_count = count.load(Ordering::SeqCst);
+        sleep_ms(100);
+        assert_eq!(count.load(Ordering::SeqCst), cancelled_count);
+        assert!(handle.is_finished());
+
+        // Actions added once the scheduler has started run straight away, even if added from
+        // outside the runtime
+        let (count, action) = counter();
+        let _handle = scheduler.add_polling_action(10, action);
+        sleep_ms(200);
+        assert!(count.load(Ordering::SeqCst) > 2);
+    });
+}
+
+#[test]
+fn polling_action_panics() {
+    do_scheduler_test(|scheduler, _, runtime| {
+        runtime.block_on(async { scheduler.start() });
+        let count = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let count = count.clone();
+            scheduler.add_polling_action(10, move || {
+                let count = count.fetch_add(1, Ordering::SeqCst);
+                if count == 1 {
+                    panic!("Polling action failed");
+                }
+            })
+        };
+        sleep_ms(200);
+        // The action carries on after it panicked
+        assert!(count.load(Ordering::SeqCst) > 2);
+        assert!(!handle.is_finished());
+    });
+}
+
+#[test]
+fn delayed_actions() {
+    do_scheduler_test(|scheduler, server_state, runtime| {
+        runtime.block_on(async { scheduler.start() });
+
+        let (count, action) = counter();
+        let handle = scheduler.add_delayed_action(50, action);
+        sleep_ms(10);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        sleep_ms(150);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(handle.is_finished());
+
+        // A cancelled action is never called
+        let (count, action) = counter();
+        let handle = scheduler.add_delayed_action(50, action);
+        handle.cancel();
+        sleep_ms(150);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        // Nor is an action whose delay is up when the server is not running
+        {
+            let mut server_state = trace_write_lock!(server_state);
+            server_state.set_state(ServerStateType::Suspended);
+        }
+        let (count, action) = counter();
+        let _handle = scheduler.add_delayed_action(10, action);
+        sleep_ms(100);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+    });
+}
+
+#[test]
+fn shutdown() {
+    do_scheduler_test(|scheduler, _, runtime| {
+        runtime.block_on(async { scheduler.start() });
+        let (polling_count, action) = counter();
+        let polling = scheduler.add_polling_action(10, action);
+        let (delayed_count, action) = counter();
+        let delayed = scheduler.add_delayed_action(100, action);
+        sleep_ms(50);
+
+        // Every task stops when the server shuts down the scheduler
+        scheduler.shutdown();
+        assert!(polling.is_finished());
+        assert!(delayed.is_finished());
+        sleep_ms(50);
+        let count = polling_count.load(Ordering::SeqCst);
+        sleep_ms(150);
+        assert_eq!(polling_count.load(Ordering::SeqCst), count);
+        assert_eq!(delayed_count.load(Ordering::SeqCst), 0);
+
+        // Tasks added afterwards never start
+        let (count, action) = counter();
+        let handle = scheduler.add_polling_action(10, action);
+        sleep_ms(50);
+        assert!(handle.is_finished());
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+    });
+}
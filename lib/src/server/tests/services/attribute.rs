@@ -1,9 +1,19 @@
 use chrono::Duration;
 
-use crate::server::{address_space::AccessLevel, services::attribute::AttributeService};
+use crate::server::{
+    address_space::{relative_path::find_node_from_browse_path, AccessLevel},
+    historical::data_collection,
+    services::attribute::AttributeService,
+};
 use crate::supported_message_as;
 use crate::sync::*;
-use crate::types::{Variant, WriteMask};
+use crate::types::{
+    service_types::{
+        AccessLevelExType, AccessRestrictionType, EnumDefinition, EnumField, PermissionType,
+        RolePermissionType,
+    },
+    Variant, WriteMask,
+};
 
 use super::*;
 
@@ -49,7 +59,7 @@ fn node_ids(address_space: Arc<RwLock<AddressSpace>>) -> Vec<NodeId> {
     let node = address_space.find_node_mut(&node_ids[3]).unwrap();
     let r = node
         .as_mut_node()
-        .set_attribute(AttributeId::UserAccessLevel, Variant::from(0u8));
+        .set_attribute(AttributeId::AccessLevel, Variant::from(0u8));
     assert!(r.is_ok());
     node_ids
 }
@@ -166,6 +176,215 @@ fn read() {
     });
 }
 
+#[test]
+fn read_index_range() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        // A large array and a large byte string
+        let array_id = NodeId::next_numeric(2);
+        let bytes_id = NodeId::next_numeric(2);
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            let _ = VariableBuilder::new(&array_id, var_name(0), "")
+                .data_type(DataTypeId::UInt32)
+                .value_rank(1)
+                .value((0..10000u32).collect::<Vec<_>>())
+                .organized_by(ObjectId::RootFolder)
+                .insert(&mut address_space);
+            let _ = VariableBuilder::new(&bytes_id, var_name(1), "")
+                .data_type(DataTypeId::ByteString)
+                .value(ByteString::from(vec![7u8; 10000]))
+                .organized_by(ObjectId::RootFolder)
+                .insert(&mut address_space);
+        }
+
+        let nodes_to_read = vec![
+            read_value_range(&array_id, AttributeId::Value, UAString::from("100:102")),
+            read_value_range(&array_id, AttributeId::Value, UAString::from("9998:10100")),
+            read_value_range(&array_id, AttributeId::Value, UAString::from("10000:10100")),
+            read_value_range(&bytes_id, AttributeId::Value, UAString::from("0:9")),
+        ];
+        let request = ReadRequest {
+            request_header: make_request_header(),
+            max_age: 0f64,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: Some(nodes_to_read),
+        };
+        let response = ats.read(server_state, session, address_space, &request);
+        let response: ReadResponse = supported_message_as!(response, ReadResponse);
+        let results = response.results.unwrap();
+
+        // Only the range is returned
+        assert_eq!(
+            results[0].value,
+            Some(Variant::from(vec![100u32, 101, 102]))
+        );
+        assert_eq!(results[1].value, Some(Variant::from(vec![9998u32, 9999])));
+        assert_eq!(results[2].status, Some(StatusCode::BadIndexRangeNoData));
+        assert!(results[2].value.is_none());
+        assert_eq!(
+            results[3].value,
+            Some(Variant::from(ByteString::from(vec![7u8; 10])))
+        );
+    });
+}
+
+#[test]
+fn read_localized_text() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let node_id = NodeId::new(1, "temperature");
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            VariableBuilder::new(&node_id, "Temperature", "Temperature")
+                .description("The temperature")
+                .data_type(DataTypeId::Int32)
+                .value(20i32)
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut address_space);
+            let localization = address_space.localization_mut();
+            localization.add_translation("Temperature", "de", "Temperatur");
+            localization.add_translation("The temperature", "de", "Die Temperatur");
+        }
+
+        let read = |locale_ids: Option<Vec<UAString>>| {
+            trace_write_lock!(session).set_locale_ids(locale_ids);
+            let request = ReadRequest {
+                request_header: make_request_header(),
+                max_age: 0f64,
+                timestamps_to_return: TimestampsToReturn::Neither,
+                nodes_to_read: Some(vec![
+                    read_value(&node_id, AttributeId::DisplayName),
+                    read_value(&node_id, AttributeId::Description),
+                ]),
+            };
+            let response = ats.read(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &request,
+            );
+            let response: ReadResponse = supported_message_as!(response, ReadResponse);
+            response
+                .results
+                .unwrap()
+                .into_iter()
+                .map(|r| r.value.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // The session asked for German
+        let results = read(Some(vec![UAString::from("de-AT")]));
+        assert_eq!(
+            results,
+            vec![
+                Variant::from(LocalizedText::new("de", "Temperatur")),
+                Variant::from(LocalizedText::new("de", "Die Temperatur")),
+            ]
+        );
+
+        // No locale ids or an unknown locale return the text as it is
+        let results = read(None);
+        assert_eq!(
+            results[0],
+            Variant::from(LocalizedText::from("Temperature"))
+        );
+        let results = read(Some(vec![UAString::from("ja")]));
+        assert_eq!(
+            results[1],
+            Variant::from(LocalizedText::from("The temperature"))
+        );
+    });
+}
+
+#[test]
+fn read_1_04_attributes() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let variable_id = NodeId::new(1, "variable");
+        let data_type_id = NodeId::new(1, "Colour");
+        let role_permissions = vec![RolePermissionType {
+            role_id: ObjectId::WellKnownRole_Operator.into(),
+            permissions: PermissionType::Browse | PermissionType::Read,
+        }];
+        let enum_definition = EnumDefinition {
+            fields: Some(vec![EnumField {
+                value: 0,
+                display_name: LocalizedText::new("", "Red"),
+                description: LocalizedText::new("", ""),
+                name: UAString::from("Red"),
+            }]),
+        };
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            VariableBuilder::new(&variable_id, "Variable", "Variable")
+                .data_type(DataTypeId::Int32)
+                .value(1i32)
+                .access_level_ex(AccessLevelExType::CurrentRead | AccessLevelExType::NonatomicRead)
+                .role_permissions(role_permissions.clone())
+                .access_restrictions(AccessRestrictionType::EncryptionRequired)
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut address_space);
+            DataTypeBuilder::new(&data_type_id, "Colour", "Colour")
+                .subtype_of(DataTypeId::Enumeration)
+                .data_type_definition(enum_definition.clone())
+                .insert(&mut address_space);
+        }
+
+        let request = ReadRequest {
+            request_header: make_request_header(),
+            max_age: 0f64,
+            timestamps_to_return: TimestampsToReturn::Neither,
+            nodes_to_read: Some(vec![
+                read_value(&variable_id, AttributeId::AccessLevelEx),
+                read_value(&variable_id, AttributeId::AccessLevel),
+                read_value(&variable_id, AttributeId::RolePermissions),
+                read_value(&variable_id, AttributeId::AccessRestrictions),
+                read_value(&data_type_id, AttributeId::DataTypeDefinition),
+                // Optional attributes that were not set
+                read_value(&variable_id, AttributeId::UserRolePermissions),
+                read_value(&variable_id, AttributeId::DataTypeDefinition),
+            ]),
+        };
+        let response = ats.read(server_state, session, address_space, &request);
+        let response: ReadResponse = supported_message_as!(response, ReadResponse);
+        let results = response.results.unwrap();
+
+        assert_eq!(results[0].value, Some(Variant::UInt32(0x101)));
+        assert_eq!(results[1].value, Some(Variant::Byte(1)));
+
+        let decoding_options = DecodingOptions::default();
+        let values = match results[2].value.as_ref().unwrap() {
+            Variant::Array(array) => array.values.clone(),
+            v => panic!("Expected an array, got {:?}", v),
+        };
+        let values = values
+            .iter()
+            .map(|v| match v {
+                Variant::ExtensionObject(v) => v
+                    .decode_inner::<RolePermissionType>(&decoding_options)
+                    .unwrap(),
+                v => panic!("Expected an extension object, got {:?}", v),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, role_permissions);
+
+        assert_eq!(results[3].value, Some(Variant::UInt16(2)));
+
+        match results[4].value.as_ref().unwrap() {
+            Variant::ExtensionObject(v) => {
+                assert_eq!(
+                    v.object_id().unwrap(),
+                    ObjectId::EnumDefinition_Encoding_DefaultBinary
+                );
+                let v = v.decode_inner::<EnumDefinition>(&decoding_options).unwrap();
+                assert_eq!(v.fields.unwrap()[0].name, UAString::from("Red"));
+            }
+            v => panic!("Expected an extension object, got {:?}", v),
+        }
+
+        assert_eq!(results[5].status, Some(StatusCode::BadAttributeIdInvalid));
+        assert_eq!(results[6].status, Some(StatusCode::BadAttributeIdInvalid));
+    });
+}
+
 #[test]
 fn read_invalid_timestamps() {
     // The TimestampsToReturnEnum will be set to Invalid to simulate a decoding error.
@@ -194,6 +413,89 @@ fn read_invalid_timestamps() {
     });
 }
 
+#[test]
+fn read_timestamps() {
+    // Test that the timestamps of a value are returned as asked, and that a value from a getter
+    // without a server timestamp is stamped with the time it was read
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let source_timestamp = DateTime::ymd_hms(2022, 1, 1, 12, 0, 0);
+        let server_timestamp = DateTime::ymd_hms(2022, 1, 1, 12, 0, 1);
+        let (stored_id, getter_id) = {
+            let node_ids = node_ids(address_space.clone());
+            let mut address_space = trace_write_lock!(address_space);
+            let _ = address_space.set_variable_value(
+                node_ids[0].clone(),
+                1i32,
+                &source_timestamp,
+                &server_timestamp,
+            );
+            let getter = AttrFnGetter::new(move |_, _, _, _, _, _| {
+                Ok(Some(DataValue {
+                    value: Some(Variant::Int32(2)),
+                    status: Some(StatusCode::Good),
+                    source_timestamp: Some(source_timestamp),
+                    source_picoseconds: None,
+                    server_timestamp: None,
+                    server_picoseconds: None,
+                }))
+            });
+            let variable = address_space.find_variable_mut(&node_ids[1]).unwrap();
+            variable.set_value_getter(Arc::new(Mutex::new(getter)));
+            (node_ids[0].clone(), node_ids[1].clone())
+        };
+
+        let read = |timestamps_to_return| {
+            let request = ReadRequest {
+                request_header: make_request_header(),
+                max_age: 0f64,
+                timestamps_to_return,
+                nodes_to_read: Some(vec![
+                    read_value(&stored_id, AttributeId::Value),
+                    read_value(&getter_id, AttributeId::Value),
+                    read_value(&stored_id, AttributeId::DisplayName),
+                ]),
+            };
+            let before = DateTime::now();
+            let response = ats.read(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &request,
+            );
+            let response = supported_message_as!(response, ReadResponse);
+            (before, response.results.unwrap())
+        };
+
+        let (before, results) = read(TimestampsToReturn::Both);
+        assert_eq!(results[0].source_timestamp, Some(source_timestamp));
+        assert_eq!(results[0].server_timestamp, Some(server_timestamp));
+        assert_eq!(results[1].source_timestamp, Some(source_timestamp));
+        assert!(results[1].server_timestamp.unwrap() >= before);
+        // Timestamps are only returned for values
+        assert!(results[2].source_timestamp.is_none());
+        assert!(results[2].server_timestamp.is_none());
+
+        let (_, results) = read(TimestampsToReturn::Source);
+        assert_eq!(results[0].source_timestamp, Some(source_timestamp));
+        assert!(results[0].server_timestamp.is_none());
+        assert!(results[1].server_timestamp.is_none());
+
+        let (_, results) = read(TimestampsToReturn::Server);
+        assert!(results[0].source_timestamp.is_none());
+        assert_eq!(results[0].server_timestamp, Some(server_timestamp));
+        assert!(results[1].source_timestamp.is_none());
+        assert!(results[1].server_timestamp.is_some());
+
+        let (_, results) = read(TimestampsToReturn::Neither);
+        results.iter().for_each(|result| {
+            assert!(result.source_timestamp.is_none());
+            assert!(result.source_picoseconds.is_none());
+            assert!(result.server_timestamp.is_none());
+            assert!(result.server_picoseconds.is_none());
+        });
+    });
+}
+
 fn write_value(node_id: &NodeId, attribute_id: AttributeId, value: DataValue) -> WriteValue {
     WriteValue {
         node_id: node_id.clone(),
@@ -553,6 +855,621 @@ fn write_index_range() {
     });
 }
 
+#[test]
+fn write_validation() {
+    // Test that written values are validated against the data type, value rank and array
+    // dimensions of the variable
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let variable = |i: usize, data_type: DataTypeId, value_rank: i32, value: Variant| {
+            let node_id = NodeId::new(2, format!("validated{}", i));
+            let mut address_space = trace_write_lock!(address_space);
+            let mut builder = VariableBuilder::new(&node_id, var_name(i), "")
+                .data_type(data_type)
+                .value_rank(value_rank)
+                .value(value)
+                .organized_by(ObjectId::RootFolder)
+                .writable();
+            if value_rank == 1 {
+                builder = builder.array_dimensions(&[3]);
+            }
+            builder.insert(&mut address_space);
+            node_id
+        };
+        let scalar_id = variable(0, DataTypeId::Int32, -1, Variant::from(0i32));
+        let number_id = variable(1, DataTypeId::Number, -1, Variant::from(0i32));
+        let array_id = variable(2, DataTypeId::Int32, 1, Variant::from(vec![0i32; 3]));
+        let enum_id = variable(3, DataTypeId::ServerState, -1, Variant::from(0i32));
+        let range_id = variable(
+            4,
+            DataTypeId::Range,
+            -1,
+            Variant::from(ExtensionObject::from_encodable(
+                ObjectId::Range_Encoding_DefaultBinary,
+                &Range {
+                    low: 0.0,
+                    high: 1.0,
+                },
+            )),
+        );
+
+        let range = ExtensionObject::from_encodable(
+            ObjectId::Range_Encoding_DefaultBinary,
+            &Range {
+                low: 1.0,
+                high: 2.0,
+            },
+        );
+        let not_range = ExtensionObject::from_encodable(
+            ObjectId::EUInformation_Encoding_DefaultBinary,
+            &EUInformation {
+                namespace_uri: UAString::null(),
+                unit_id: 0,
+                display_name: LocalizedText::null(),
+                description: LocalizedText::null(),
+            },
+        );
+        let mut nodes_to_write = vec![
+            // 1. wrong data type
+            write_value(&scalar_id, AttributeId::Value, DataValue::new_now(1.5f64)),
+            // 2. an array to a scalar
+            write_value(
+                &scalar_id,
+                AttributeId::Value,
+                DataValue::new_now(vec![1i32, 2i32]),
+            ),
+            // 3. a subtype of the data type
+            write_value(&number_id, AttributeId::Value, DataValue::new_now(5i16)),
+            // 4. an array within the array dimensions
+            write_value(
+                &array_id,
+                AttributeId::Value,
+                DataValue::new_now(vec![1i32, 2i32]),
+            ),
+            // 5. an array larger than the array dimensions
+            write_value(
+                &array_id,
+                AttributeId::Value,
+                DataValue::new_now(vec![1i32, 2i32, 3i32, 4i32]),
+            ),
+            // 6. a scalar to an array
+            write_value(&array_id, AttributeId::Value, DataValue::new_now(1i32)),
+            // 7. an array of the wrong number of dimensions
+            write_value(
+                &array_id,
+                AttributeId::Value,
+                DataValue::new_now(Variant::from((
+                    VariantTypeId::Int32,
+                    vec![Variant::from(1i32); 4],
+                    vec![2u32, 2u32],
+                ))),
+            ),
+            // 8. an enumeration as its Int32 value
+            write_value(&enum_id, AttributeId::Value, DataValue::new_now(1i32)),
+            // 9. a structure of the data type
+            write_value(&range_id, AttributeId::Value, DataValue::new_now(range)),
+            // 10. a structure of another data type
+            write_value(&range_id, AttributeId::Value, DataValue::new_now(not_range)),
+            // 11. an index range with the wrong number of elements
+            write_value_index_range(
+                &array_id,
+                AttributeId::Value,
+                "0:1",
+                DataValue::new_now(vec![1i32, 2i32, 3i32]),
+            ),
+            // 12. an index range outside the array
+            write_value_index_range(
+                &array_id,
+                AttributeId::Value,
+                "1:5",
+                DataValue::new_now(vec![1i32; 5]),
+            ),
+            // 13. an index range of the wrong data type
+            write_value_index_range(
+                &array_id,
+                AttributeId::Value,
+                "1",
+                DataValue::new_now(vec![1.5f64]),
+            ),
+        ];
+        // The writes are split to stay within the operation limits
+        let index_range_writes = nodes_to_write.split_off(10);
+        let mut results = Vec::new();
+        for nodes_to_write in [nodes_to_write, index_range_writes] {
+            let response = write_request(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                ats,
+                nodes_to_write,
+            );
+            results.extend(response.results.unwrap());
+        }
+        assert_eq!(
+            results,
+            vec![
+                StatusCode::BadTypeMismatch,
+                StatusCode::BadTypeMismatch,
+                StatusCode::Good,
+                StatusCode::Good,
+                StatusCode::BadOutOfRange,
+                StatusCode::BadTypeMismatch,
+                StatusCode::BadTypeMismatch,
+                StatusCode::Good,
+                StatusCode::Good,
+                StatusCode::BadTypeMismatch,
+                StatusCode::BadIndexRangeInvalid,
+                StatusCode::BadIndexRangeNoData,
+                StatusCode::BadTypeMismatch,
+            ]
+        );
+
+        // Rejected values are not stored
+        validate_variable_value(address_space.clone(), &scalar_id, |value| {
+            assert_eq!(*value, Variant::from(0i32));
+        });
+        validate_variable_value(address_space, &array_id, |value| {
+            assert_eq!(*value, Variant::from(vec![1i32, 2i32]));
+        });
+    });
+}
+
+#[derive(Copy, Clone)]
+enum Valve {
+    Closed = 0,
+    Open = 1,
+    Fault = 5,
+}
+
+impl UaEnum for Valve {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (Valve::Closed as i64, "Closed"),
+            (Valve::Open as i64, "Open"),
+            (Valve::Fault as i64, "Fault"),
+        ]
+    }
+}
+
+#[test]
+fn write_enum_validation() {
+    // Test that values written to enumerated variables must be one of the enum values
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let (multi_state_id, multi_state_value_id, enum_id) = {
+            let mut address_space = trace_write_lock!(address_space);
+            let multi_state_id =
+                MultiStateDiscreteBuilder::new(&NodeId::new(2, "multi_state"), "MultiState", "")
+                    .enum_strings(vec!["Off", "Low", "High"])
+                    .value(0u32)
+                    .writable()
+                    .organized_by(ObjectId::RootFolder)
+                    .insert(&mut address_space);
+            let multi_state_value_id = MultiStateValueDiscreteBuilder::new(
+                &NodeId::new(2, "multi_state_value"),
+                "MultiStateValue",
+                "",
+            )
+            .enum_type::<Valve>()
+            .value(Valve::Closed as i32)
+            .writable()
+            .organized_by(ObjectId::RootFolder)
+            .insert(&mut address_space);
+            // An enumeration data type with the values of the enum
+            let data_type_id = NodeId::new(2, "Valve");
+            DataTypeBuilder::new(&data_type_id, "Valve", "Valve")
+                .subtype_of(DataTypeId::Enumeration)
+                .insert(&mut address_space);
+            VariableBuilder::new(
+                &NodeId::new(2, "Valve.EnumValues"),
+                "EnumValues",
+                "EnumValues",
+            )
+            .property_of(data_type_id.clone())
+            .has_type_definition(VariableTypeId::PropertyType)
+            .data_type(DataTypeId::EnumValueType)
+            .value(Enumeration::from_enum::<Valve>().enum_values_variant())
+            .insert(&mut address_space);
+            let enum_id = NodeId::new(2, "enum");
+            VariableBuilder::new(&enum_id, "Enum", "")
+                .data_type(data_type_id)
+                .value(0i32)
+                .writable()
+                .organized_by(ObjectId::RootFolder)
+                .insert(&mut address_space);
+            (multi_state_id, multi_state_value_id, enum_id)
+        };
+
+        let nodes_to_write = vec![
+            // 1. an index into the enum strings
+            write_value(
+                &multi_state_id,
+                AttributeId::Value,
+                DataValue::new_now(2u32),
+            ),
+            // 2. past the enum strings
+            write_value(
+                &multi_state_id,
+                AttributeId::Value,
+                DataValue::new_now(3u32),
+            ),
+            // 3. one of the enum values
+            write_value(
+                &multi_state_value_id,
+                AttributeId::Value,
+                DataValue::new_now(Valve::Fault as i32),
+            ),
+            // 4. in between the enum values
+            write_value(
+                &multi_state_value_id,
+                AttributeId::Value,
+                DataValue::new_now(3i32),
+            ),
+            // 5. a value of the enumeration data type
+            write_value(&enum_id, AttributeId::Value, DataValue::new_now(1i32)),
+            // 6. not a value of the enumeration data type
+            write_value(&enum_id, AttributeId::Value, DataValue::new_now(99i32)),
+        ];
+        let response = write_request(
+            server_state,
+            session,
+            address_space.clone(),
+            ats,
+            nodes_to_write,
+        );
+        assert_eq!(
+            response.results.unwrap(),
+            vec![
+                StatusCode::Good,
+                StatusCode::BadOutOfRange,
+                StatusCode::Good,
+                StatusCode::BadOutOfRange,
+                StatusCode::Good,
+                StatusCode::BadOutOfRange,
+            ]
+        );
+
+        // The value as text follows the written value
+        let address_space = trace_read_lock!(address_space);
+        let value_as_text = find_node_from_browse_path(
+            &address_space,
+            &multi_state_value_id,
+            &["ValueAsText".into()],
+        )
+        .unwrap();
+        let value_as_text = address_space
+            .get_variable_value(value_as_text.as_node().node_id())
+            .unwrap();
+        if let Some(Variant::LocalizedText(value_as_text)) = value_as_text.value {
+            assert_eq!(value_as_text.text, UAString::from("Fault"));
+        } else {
+            panic!("Not a localized text");
+        }
+    });
+}
+
+#[test]
+fn write_hooks() {
+    // Test that the before write hooks can reject and transform values and that the value changed
+    // hooks see the old and new values
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let (_, node_ids) = add_many_vars_to_address_space(address_space.clone(), 3);
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            node_ids.iter().for_each(|node_id| {
+                let variable = address_space.find_variable_mut(node_id).unwrap();
+                variable.set_writable(true);
+                variable.set_user_access_level(UserAccessLevel::CURRENT_WRITE);
+            });
+
+            // Negative values are rejected and the third variable is clamped to 10
+            let clamped_id = node_ids[2].clone();
+            address_space.add_before_write_hook(move |node_id, value, _session| match value {
+                Variant::Int32(v) if v < 0 => Err(StatusCode::BadOutOfRange),
+                Variant::Int32(v) if *node_id == clamped_id => Ok(Variant::Int32(v.min(10))),
+                value => Ok(value),
+            });
+            let changes = changes.clone();
+            address_space.add_value_changed_hook(move |node_id, old_value, new_value| {
+                let mut changes = trace_lock!(changes);
+                changes.push((
+                    node_id.clone(),
+                    old_value.value.clone(),
+                    new_value.value.clone(),
+                ));
+            });
+        }
+
+        let nodes_to_write = vec![
+            // 1. an accepted value
+            write_value(&node_ids[0], AttributeId::Value, DataValue::new_now(5i32)),
+            // 2. a rejected value
+            write_value(&node_ids[1], AttributeId::Value, DataValue::new_now(-1i32)),
+            // 3. a transformed value
+            write_value(&node_ids[2], AttributeId::Value, DataValue::new_now(20i32)),
+            // 4. the same value again, which is not a change
+            write_value(&node_ids[0], AttributeId::Value, DataValue::new_now(5i32)),
+        ];
+        let response = write_request(
+            server_state,
+            session,
+            address_space.clone(),
+            ats,
+            nodes_to_write,
+        );
+        let results = response.results.unwrap();
+        assert_eq!(
+            results,
+            vec![
+                StatusCode::Good,
+                StatusCode::BadOutOfRange,
+                StatusCode::Good,
+                StatusCode::Good
+            ]
+        );
+
+        validate_variable_value(address_space.clone(), &node_ids[1], |v| {
+            assert_eq!(*v, Variant::Int32(1));
+        });
+        validate_variable_value(address_space.clone(), &node_ids[2], |v| {
+            assert_eq!(*v, Variant::Int32(10));
+        });
+
+        // Values set by the server are seen by the value changed hooks too
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            let now = DateTime::now();
+            address_space.set_variable_value(node_ids[1].clone(), 7i32, &now, &now);
+        }
+
+        let changes = trace_lock!(changes);
+        assert_eq!(
+            *changes,
+            vec![
+                (
+                    node_ids[0].clone(),
+                    Some(Variant::Int32(0)),
+                    Some(Variant::Int32(5))
+                ),
+                (
+                    node_ids[2].clone(),
+                    Some(Variant::Int32(2)),
+                    Some(Variant::Int32(10))
+                ),
+                (
+                    node_ids[1].clone(),
+                    Some(Variant::Int32(1)),
+                    Some(Variant::Int32(7))
+                ),
+            ]
+        );
+    });
+}
+
+struct OperatorAccessLevels;
+
+impl UserAccessLevels for OperatorAccessLevels {
+    fn user_access_level(
+        &self,
+        user_token_id: &str,
+        _node_id: &NodeId,
+        user_access_level: UserAccessLevel,
+    ) -> UserAccessLevel {
+        if user_token_id == "operator" {
+            user_access_level | UserAccessLevel::CURRENT_WRITE
+        } else {
+            user_access_level
+        }
+    }
+
+    fn user_write_mask(
+        &self,
+        user_token_id: &str,
+        _node_id: &NodeId,
+        user_write_mask: WriteMask,
+    ) -> WriteMask {
+        if user_token_id == "operator" {
+            user_write_mask | WriteMask::DESCRIPTION | WriteMask::BROWSE_NAME
+        } else {
+            user_write_mask
+        }
+    }
+}
+
+#[test]
+fn access_levels() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let node_ids = {
+            let (_, node_ids) = add_many_vars_to_address_space(address_space.clone(), 3);
+            let mut address_space = trace_write_lock!(address_space);
+            // [0] is writable but the user may only read it
+            let v = address_space.find_variable_mut(&node_ids[0]).unwrap();
+            v.set_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+            v.set_user_access_level(UserAccessLevel::CURRENT_READ);
+            // [1] is read only but the user access level claims more
+            let v = address_space.find_variable_mut(&node_ids[1]).unwrap();
+            v.set_access_level(AccessLevel::CURRENT_READ);
+            v.set_user_access_level(UserAccessLevel::CURRENT_READ | UserAccessLevel::CURRENT_WRITE);
+            // [2] is readable but not by the user
+            let v = address_space.find_variable_mut(&node_ids[2]).unwrap();
+            v.set_user_access_level(UserAccessLevel::empty());
+            node_ids
+        };
+
+        let read = |server_state, session, address_space| {
+            let request = ReadRequest {
+                request_header: make_request_header(),
+                max_age: 0f64,
+                timestamps_to_return: TimestampsToReturn::Neither,
+                nodes_to_read: Some(vec![
+                    read_value(&node_ids[0], AttributeId::Value),
+                    read_value(&node_ids[1], AttributeId::UserAccessLevel),
+                    read_value(&node_ids[2], AttributeId::Value),
+                    read_value(&node_ids[2], AttributeId::DisplayName),
+                ]),
+            };
+            let response = ats.read(server_state, session, address_space, &request);
+            supported_message_as!(response, ReadResponse)
+                .results
+                .unwrap()
+        };
+        let write = |server_state, session, address_space| {
+            write_request(
+                server_state,
+                session,
+                address_space,
+                ats,
+                vec![
+                    write_value(&node_ids[0], AttributeId::Value, DataValue::new_now(10i32)),
+                    write_value(&node_ids[1], AttributeId::Value, DataValue::new_now(11i32)),
+                ],
+            )
+            .results
+            .unwrap()
+        };
+
+        let results = read(server_state.clone(), session.clone(), address_space.clone());
+        assert_eq!(results[0].status, Some(StatusCode::Good));
+        // The user access level is limited by the access level
+        assert_eq!(
+            results[1].value,
+            Some(Variant::from(UserAccessLevel::CURRENT_READ.bits()))
+        );
+        assert_eq!(results[2].status, Some(StatusCode::BadUserAccessDenied));
+        // Other attributes are not governed by the access levels
+        assert!(results[3].value.is_some());
+
+        let results = write(server_state.clone(), session.clone(), address_space.clone());
+        assert_eq!(results[0], StatusCode::BadUserAccessDenied);
+        assert_eq!(results[1], StatusCode::BadNotWritable);
+
+        // An override can give a user access that the access level permits, but no more
+        {
+            let mut server_state = trace_write_lock!(server_state);
+            server_state.set_user_access_levels(Box::new(OperatorAccessLevels));
+        }
+        {
+            let mut session = trace_write_lock!(session);
+            session.set_user_token_id(Some("operator".into()));
+        }
+        let results = write(server_state.clone(), session.clone(), address_space.clone());
+        assert_eq!(results[0], StatusCode::Good);
+        assert_eq!(results[1], StatusCode::BadNotWritable);
+
+        // The override applies to the user of the session only
+        {
+            let mut session = trace_write_lock!(session);
+            session.set_user_token_id(Some(ANONYMOUS_USER_TOKEN_ID.into()));
+        }
+        let results = write(server_state, session, address_space);
+        assert_eq!(results[0], StatusCode::BadUserAccessDenied);
+    });
+}
+
+#[test]
+fn write_masks() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let object_id = NodeId::new(1, "object");
+        let other_object_id = NodeId::new(1, "other");
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            // The user may write the display name but not the description of this object
+            ObjectBuilder::new(&object_id, "Object", "Object")
+                .write_mask(WriteMask::DISPLAY_NAME | WriteMask::DESCRIPTION)
+                .user_write_mask(WriteMask::DISPLAY_NAME)
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut address_space);
+            // A node without a user write mask gives its users the write mask
+            ObjectBuilder::new(&other_object_id, "Other", "Other")
+                .write_mask(WriteMask::DESCRIPTION)
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut address_space);
+        }
+        let nodes_to_write = || {
+            vec![
+                write_value(
+                    &object_id,
+                    AttributeId::DisplayName,
+                    DataValue::new_now(LocalizedText::from("Renamed")),
+                ),
+                write_value(
+                    &object_id,
+                    AttributeId::Description,
+                    DataValue::new_now(LocalizedText::from("Described")),
+                ),
+                write_value(
+                    &object_id,
+                    AttributeId::BrowseName,
+                    DataValue::new_now(QualifiedName::from("Renamed")),
+                ),
+                write_value(
+                    &other_object_id,
+                    AttributeId::Description,
+                    DataValue::new_now(LocalizedText::from("Described")),
+                ),
+            ]
+        };
+
+        let response = write_request(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            ats,
+            nodes_to_write(),
+        );
+        let results = response.results.unwrap();
+        assert_eq!(results[0], StatusCode::Good);
+        assert_eq!(results[1], StatusCode::BadUserAccessDenied);
+        assert_eq!(results[2], StatusCode::BadNotWritable);
+        assert_eq!(results[3], StatusCode::Good);
+        {
+            let address_space = trace_read_lock!(address_space);
+            let object = address_space.find_node(&object_id).unwrap().as_node();
+            assert_eq!(object.display_name(), LocalizedText::from("Renamed"));
+            assert!(object.description().is_none());
+        }
+
+        // The user write mask that is read is the one of the user
+        let request = ReadRequest {
+            request_header: make_request_header(),
+            max_age: 0f64,
+            timestamps_to_return: TimestampsToReturn::Neither,
+            nodes_to_read: Some(vec![read_value(&object_id, AttributeId::UserWriteMask)]),
+        };
+        let read = |server_state, session, address_space| {
+            let response = ats.read(server_state, session, address_space, &request);
+            supported_message_as!(response, ReadResponse)
+                .results
+                .unwrap()[0]
+                .value
+                .clone()
+        };
+        assert_eq!(
+            read(server_state.clone(), session.clone(), address_space.clone()),
+            Some(Variant::from(WriteMask::DISPLAY_NAME.bits()))
+        );
+
+        // An override can give a user more of the write mask, but no more
+        {
+            let mut server_state = trace_write_lock!(server_state);
+            server_state.set_user_access_levels(Box::new(OperatorAccessLevels));
+        }
+        {
+            let mut session = trace_write_lock!(session);
+            session.set_user_token_id(Some("operator".into()));
+        }
+        assert_eq!(
+            read(server_state.clone(), session.clone(), address_space.clone()),
+            Some(Variant::from(
+                (WriteMask::DISPLAY_NAME | WriteMask::DESCRIPTION).bits()
+            ))
+        );
+        let response = write_request(server_state, session, address_space, ats, nodes_to_write());
+        let results = response.results.unwrap();
+        assert_eq!(results[1], StatusCode::Good);
+        assert_eq!(results[2], StatusCode::BadNotWritable);
+    });
+}
+
 // #[test] fn write_null_value() { /* Write an empty variant to a value and see that it is allowed */}
 
 struct DataProvider;
@@ -695,6 +1612,155 @@ fn history_read_nothing_data_provider() {
     });
 }
 
+#[test]
+fn history_read_access_level() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        {
+            let mut server_state = server_state.write();
+            server_state.set_historical_data_provider(Box::new(DataProvider));
+        }
+        // A variable without history read access is left out of the read
+        let node_ids = {
+            let (_, node_ids) = add_many_vars_to_address_space(address_space.clone(), 1);
+            node_ids
+        };
+        let mut nodes_to_read = nodes_to_read();
+        nodes_to_read.insert(
+            0,
+            HistoryReadValueId {
+                node_id: node_ids[0].clone(),
+                index_range: UAString::null(),
+                data_encoding: QualifiedName::null(),
+                continuation_point: ByteString::null(),
+            },
+        );
+        let history_read_details = ExtensionObject::from_encodable(
+            ObjectId::ReadRawModifiedDetails_Encoding_DefaultBinary,
+            &read_raw_modified_details(),
+        );
+        let request = HistoryReadRequest {
+            request_header: make_request_header(),
+            history_read_details,
+            timestamps_to_return: TimestampsToReturn::Both,
+            release_continuation_points: true,
+            nodes_to_read: Some(nodes_to_read),
+        };
+        let response: HistoryReadResponse = supported_message_as!(
+            ats.history_read(server_state, session, address_space, &request),
+            HistoryReadResponse
+        );
+        let results = response.results.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status_code, StatusCode::BadNotReadable);
+        assert_eq!(results[1], DataProvider::historical_read_result()[0]);
+    });
+}
+
+/// Reads history in two pages, remembering the continuation points it is given
+struct PagingDataProvider {
+    continuation_points: Arc<Mutex<Vec<ByteString>>>,
+}
+
+impl HistoricalDataProvider for PagingDataProvider {
+    fn read_raw_modified_details(
+        &self,
+        _address_space: Arc<RwLock<AddressSpace>>,
+        _request: ReadRawModifiedDetails,
+        _timestamps_to_return: TimestampsToReturn,
+        _release_continuation_points: bool,
+        nodes_to_read: &[HistoryReadValueId],
+    ) -> Result<Vec<HistoryReadResult>, StatusCode> {
+        let results = nodes_to_read
+            .iter()
+            .map(|node_to_read| {
+                self.continuation_points
+                    .lock()
+                    .push(node_to_read.continuation_point.clone());
+                let continuation_point = if node_to_read.continuation_point.is_null() {
+                    ByteString::from(b"page 2")
+                } else {
+                    ByteString::null()
+                };
+                HistoryReadResult {
+                    status_code: StatusCode::Good,
+                    continuation_point,
+                    history_data: ExtensionObject::null(),
+                }
+            })
+            .collect();
+        Ok(results)
+    }
+}
+
+#[test]
+fn history_read_continuation_point() {
+    do_attribute_service_test(|server_state, session, address_space, ats| {
+        let continuation_points = Arc::new(Mutex::new(Vec::new()));
+        {
+            let mut server_state = server_state.write();
+            server_state.set_historical_data_provider(Box::new(PagingDataProvider {
+                continuation_points: continuation_points.clone(),
+            }));
+        }
+        let history_read = |continuation_point: &ByteString, release_continuation_points| {
+            let mut nodes_to_read = nodes_to_read();
+            nodes_to_read[0].continuation_point = continuation_point.clone();
+            let history_read_details = ExtensionObject::from_encodable(
+                ObjectId::ReadRawModifiedDetails_Encoding_DefaultBinary,
+                &read_raw_modified_details(),
+            );
+            let request = HistoryReadRequest {
+                request_header: make_request_header(),
+                history_read_details,
+                timestamps_to_return: TimestampsToReturn::Both,
+                release_continuation_points,
+                nodes_to_read: Some(nodes_to_read),
+            };
+            let response: HistoryReadResponse = supported_message_as!(
+                ats.history_read(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                HistoryReadResponse
+            );
+            response.results.unwrap().remove(0)
+        };
+
+        // The client is given an id for the continuation point of the provider
+        let result = history_read(&ByteString::null(), false);
+        assert_eq!(result.status_code, StatusCode::Good);
+        assert!(!result.continuation_point.is_null());
+        assert_ne!(result.continuation_point, ByteString::from(b"page 2"));
+
+        // Resuming passes the continuation point of the provider back to it
+        let id = result.continuation_point;
+        let result = history_read(&id, false);
+        assert_eq!(result.status_code, StatusCode::Good);
+        assert!(result.continuation_point.is_null());
+        assert_eq!(
+            *continuation_points.lock(),
+            vec![ByteString::null(), ByteString::from(b"page 2")]
+        );
+
+        // The continuation point can't be resumed twice, and the provider doesn't see the id
+        let result = history_read(&id, false);
+        assert_eq!(result.status_code, StatusCode::BadContinuationPointInvalid);
+        assert_eq!(continuation_points.lock().len(), 2);
+
+        // A released continuation point can't be resumed
+        let id = history_read(&ByteString::null(), false).continuation_point;
+        let result = history_read(&id, true);
+        assert_eq!(result.status_code, StatusCode::Good);
+        assert!(result.continuation_point.is_null());
+        let result = history_read(&id, false);
+        assert_eq!(result.status_code, StatusCode::BadContinuationPointInvalid);
+        let result = history_read(&ByteString::from(b"page 2"), false);
+        assert_eq!(result.status_code, StatusCode::BadContinuationPointInvalid);
+    });
+}
+
 fn delete_raw_modified_details() -> DeleteRawModifiedDetails {
     let now = chrono::Utc::now();
     let start_time = (now - Duration::days(5)).into();
@@ -827,3 +1893,247 @@ fn history_update_data_provider() {
         assert_eq!(result1.status_code, StatusCode::Good);
     });
 }
+
+/// A historical data provider that keeps the values that the server collects
+struct CollectingDataProvider {
+    values: Arc<Mutex<Vec<(NodeId, DataValue)>>>,
+}
+
+impl HistoricalDataProvider for CollectingDataProvider {
+    fn collect_values(&self, values: &[(NodeId, DataValue)]) -> Result<(), StatusCode> {
+        self.values.lock().extend_from_slice(values);
+        Ok(())
+    }
+}
+
+#[test]
+fn history_collection() {
+    do_attribute_service_test(|server_state, _session, address_space, _ats| {
+        let (level_id, other_id) = {
+            let mut address_space = trace_write_lock!(address_space);
+            let ns = address_space.register_namespace("urn:history").unwrap();
+            let (level_id, other_id) = (NodeId::new(ns, "Level"), NodeId::new(ns, "Other"));
+            VariableBuilder::new(&level_id, "Level", "Level")
+                .organized_by(ObjectId::ObjectsFolder)
+                .data_type(DataTypeId::Double)
+                .value(0f64)
+                .historizing(true)
+                .insert(&mut address_space);
+            VariableBuilder::new(&other_id, "Other", "Other")
+                .organized_by(ObjectId::ObjectsFolder)
+                .data_type(DataTypeId::Double)
+                .value(0f64)
+                .insert(&mut address_space);
+            (level_id, other_id)
+        };
+        let set_value = |node_id: &NodeId, value: f64| {
+            let mut address_space = trace_write_lock!(address_space);
+            let now = DateTime::now();
+            assert!(address_space.set_variable_value(node_id.clone(), value, &now, &now));
+        };
+        let values = Arc::new(Mutex::new(Vec::new()));
+        let collected_values = || {
+            values
+                .lock()
+                .drain(..)
+                .map(|(node_id, data_value): (NodeId, DataValue)| {
+                    (node_id, data_value.value.unwrap().as_f64().unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Nothing is collected without a provider
+        set_value(&level_id, 1f64);
+        data_collection::collect_history(&server_state, &address_space);
+        assert!(!trace_read_lock!(address_space).is_history_collection_enabled());
+
+        // Every change of a historizing variable is collected once there is a provider
+        {
+            let mut server_state = server_state.write();
+            server_state.set_historical_data_provider(Box::new(CollectingDataProvider {
+                values: values.clone(),
+            }));
+        }
+        data_collection::collect_history(&server_state, &address_space);
+        set_value(&level_id, 2f64);
+        set_value(&other_id, 2f64);
+        set_value(&level_id, 3f64);
+        data_collection::collect_history(&server_state, &address_space);
+        assert_eq!(
+            collected_values(),
+            vec![(level_id.clone(), 2f64), (level_id.clone(), 3f64)]
+        );
+
+        // The configuration of the variable is in its HA Configuration
+        let configuration = HistoricalDataConfiguration {
+            max_time_interval: 1000f64,
+            exception_deviation: 0.5f64,
+            ..Default::default()
+        };
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            assert!(!address_space.set_historical_data_configuration(
+                &NodeId::new(1, "Unknown"),
+                configuration.clone()
+            ));
+            assert!(address_space.set_historical_data_configuration(&level_id, configuration));
+            let max_time_interval_id = find_node_from_browse_path(
+                &address_space,
+                &level_id,
+                &["HA Configuration".into(), "MaxTimeInterval".into()],
+            )
+            .unwrap()
+            .as_node()
+            .node_id();
+            assert_eq!(
+                address_space
+                    .get_variable_value(max_time_interval_id)
+                    .unwrap()
+                    .value,
+                Some(Variant::from(1000f64))
+            );
+        }
+
+        // A change that deviates too little from the last value is not collected, and the value
+        // is collected again once its maximum time interval has elapsed
+        set_value(&level_id, 3.2f64);
+        set_value(&level_id, 4f64);
+        let collected = {
+            let mut address_space = trace_write_lock!(address_space);
+            assert_eq!(address_space.collect_history(&chrono::Utc::now()).len(), 1);
+            let later = chrono::Utc::now() + Duration::seconds(2);
+            assert!(address_space.has_history_to_collect(&later));
+            address_space.collect_history(&later)
+        };
+        assert_eq!(collected.len(), 1);
+        assert_eq!(collected[0].0, level_id);
+        assert_eq!(collected[0].1.value, Some(Variant::from(4f64)));
+    });
+}
+
+/// A historical data provider that declares what it can do
+struct CapableDataProvider;
+
+impl HistoricalDataProvider for CapableDataProvider {
+    fn capabilities(&self) -> Option<HistoryServerCapabilities> {
+        Some(HistoryServerCapabilities {
+            access_history_data: true,
+            max_return_data: 500,
+            insert_data: true,
+            delete_raw: true,
+            aggregate_functions: vec![
+                ObjectId::AggregateFunction_Average.into(),
+                ObjectId::AggregateFunction_Minimum.into(),
+            ],
+            ..Default::default()
+        })
+    }
+}
+
+fn organized_aggregate_functions(address_space: &AddressSpace, folder_id: &NodeId) -> Vec<NodeId> {
+    let mut aggregate_functions: Vec<NodeId> = address_space
+        .find_references(folder_id, Some((ReferenceTypeId::Organizes, false)))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| r.target_node)
+        .collect();
+    aggregate_functions.sort_by_key(|node_id| node_id.to_string());
+    aggregate_functions
+}
+
+#[test]
+fn history_capabilities() {
+    do_attribute_service_test(|server_state, _session, address_space, _ats| {
+        let level_id = {
+            let mut address_space = trace_write_lock!(address_space);
+            let ns = address_space.register_namespace("urn:history").unwrap();
+            let level_id = NodeId::new(ns, "Level");
+            VariableBuilder::new(&level_id, "Level", "Level")
+                .organized_by(ObjectId::ObjectsFolder)
+                .data_type(DataTypeId::Double)
+                .value(0f64)
+                .historizing(true)
+                .insert(&mut address_space);
+            level_id
+        };
+        let capabilities_id: NodeId = ObjectId::HistoryServerCapabilities_AggregateFunctions.into();
+        let variable_aggregate_functions = |address_space: &AddressSpace| {
+            let folder_id = find_node_from_browse_path(
+                address_space,
+                &level_id,
+                &["HA Configuration".into(), "AggregateFunctions".into()],
+            )
+            .unwrap()
+            .as_node()
+            .node_id();
+            organized_aggregate_functions(address_space, &folder_id)
+        };
+
+        // The capabilities that the provider declares are set once it is the provider
+        {
+            let mut server_state = server_state.write();
+            server_state.set_historical_data_provider(Box::new(CapableDataProvider));
+        }
+        data_collection::collect_history(&server_state, &address_space);
+        let expected: Vec<NodeId> = vec![
+            ObjectId::AggregateFunction_Average.into(),
+            ObjectId::AggregateFunction_Minimum.into(),
+        ];
+        {
+            let address_space = trace_read_lock!(address_space);
+            let capability = |variable_id: VariableId| {
+                address_space.get_variable_value(variable_id).unwrap().value
+            };
+            assert_eq!(
+                capability(VariableId::HistoryServerCapabilities_AccessHistoryDataCapability),
+                Some(Variant::from(true))
+            );
+            assert_eq!(
+                capability(VariableId::HistoryServerCapabilities_InsertDataCapability),
+                Some(Variant::from(true))
+            );
+            assert_eq!(
+                capability(VariableId::HistoryServerCapabilities_ReplaceDataCapability),
+                Some(Variant::from(false))
+            );
+            assert_eq!(
+                capability(VariableId::HistoryServerCapabilities_DeleteRawCapability),
+                Some(Variant::from(true))
+            );
+            assert_eq!(
+                capability(VariableId::HistoryServerCapabilities_MaxReturnDataValues),
+                Some(Variant::from(500u32))
+            );
+            assert_eq!(
+                organized_aggregate_functions(&address_space, &capabilities_id),
+                expected
+            );
+
+            // The historizing variable has an HA Configuration with the aggregate functions
+            assert_eq!(variable_aggregate_functions(&address_space), expected);
+        }
+
+        // Capabilities that are set later replace the aggregate functions everywhere
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            address_space.set_history_server_capabilities(&HistoryServerCapabilities {
+                access_history_data: true,
+                aggregate_functions: vec![ObjectId::AggregateFunction_Maximum.into()],
+                ..Default::default()
+            });
+            let expected: Vec<NodeId> = vec![ObjectId::AggregateFunction_Maximum.into()];
+            assert_eq!(
+                organized_aggregate_functions(&address_space, &capabilities_id),
+                expected
+            );
+            assert_eq!(variable_aggregate_functions(&address_space), expected);
+
+            // A variable that is configured afterwards has them too
+            assert!(address_space.set_historical_data_configuration(
+                &level_id,
+                HistoricalDataConfiguration::default()
+            ));
+            assert_eq!(variable_aggregate_functions(&address_space), expected);
+        }
+    });
+}
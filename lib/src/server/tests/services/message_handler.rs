@@ -0,0 +1,405 @@
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::core::comms::secure_channel::SecureChannel;
+use crate::crypto::SecurityPolicy;
+use crate::server::{
+    comms::tcp_transport::{Message, MessageSender},
+    services::message_handler::MessageHandler,
+    subscriptions::PublishResponseEntry,
+};
+use crate::supported_message_as;
+use crate::sync::*;
+use crate::types::UAString;
+
+use super::*;
+
+fn do_message_handler_test<F>(server_builder: ServerBuilder, f: F)
+where
+    F: FnOnce(MessageHandler, MessageSender, UnboundedReceiver<Message>),
+{
+    do_message_handler_test_with_policy(server_builder, SecurityPolicy::None, f)
+}
+
+/// Runs the test with a message handler whose secure channel has the security policy
+fn do_message_handler_test_with_policy<F>(
+    server_builder: ServerBuilder,
+    security_policy: SecurityPolicy,
+    f: F,
+) where
+    F: FnOnce(MessageHandler, MessageSender, UnboundedReceiver<Message>),
+{
+    let server = server_builder.server().unwrap();
+    let mut secure_channel = SecureChannel::new_no_certificate_store();
+    secure_channel.set_security_policy(security_policy);
+    let message_handler = MessageHandler::new(
+        Arc::new(RwLock::new(secure_channel)),
+        server.certificate_store(),
+        server.server_state(),
+        Arc::new(RwLock::new(SessionManager::default())),
+        server.address_space(),
+    );
+    let (tx, rx) = unbounded_channel();
+    f(message_handler, MessageSender::new(tx), rx);
+}
+
+fn get_endpoints_request(request_handle: u32, timeout_hint: u32) -> SupportedMessage {
+    GetEndpointsRequest {
+        request_header: RequestHeader {
+            request_handle,
+            timeout_hint,
+            ..make_request_header()
+        },
+        endpoint_url: UAString::from("opc.tcp://localhost:4855/"),
+        locale_ids: None,
+        profile_uris: None,
+    }
+    .into()
+}
+
+#[test]
+fn shed_requests_when_overloaded() {
+    let server_builder = ServerBuilder::new_sample().max_queued_responses(2);
+    do_message_handler_test(server_builder, |mut message_handler, sender, mut rx| {
+        // Nothing takes the responses from the queue, so the third request is shed
+        for request_handle in 1..=3 {
+            let request = get_endpoints_request(request_handle, 0);
+            message_handler
+                .handle_message(request_handle, &request, &sender)
+                .unwrap();
+        }
+        assert_eq!(sender.queue_depth(), 3);
+
+        let mut responses = Vec::new();
+        while let Ok(Message::Message(_, response, _)) = rx.try_recv() {
+            responses.push(response);
+        }
+        assert_eq!(responses.len(), 3);
+        let _ = supported_message_as!(responses[0].clone(), GetEndpointsResponse);
+        let _ = supported_message_as!(responses[1].clone(), GetEndpointsResponse);
+        let response = supported_message_as!(responses[2].clone(), ServiceFault);
+        assert_eq!(response.response_header.request_handle, 3);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadTcpServerTooBusy
+        );
+    });
+}
+
+#[test]
+fn response_deadline_from_timeout_hint() {
+    do_message_handler_test(
+        ServerBuilder::new_sample(),
+        |mut message_handler, sender, mut rx| {
+            let started = clock::now();
+            message_handler
+                .handle_message(1, &get_endpoints_request(1, 5000), &sender)
+                .unwrap();
+            message_handler
+                .handle_message(2, &get_endpoints_request(2, 0), &sender)
+                .unwrap();
+
+            // The response must be written within the timeout hint to be of use
+            match rx.try_recv() {
+                Ok(Message::Message(1, _, Some(deadline))) => {
+                    let deadline_ms = deadline.signed_duration_since(started).num_milliseconds();
+                    assert!(
+                        (5000..6000).contains(&deadline_ms),
+                        "deadline = {}ms",
+                        deadline_ms
+                    );
+                }
+                message => panic!("Unexpected message {:?}", message),
+            }
+            // Without a hint the response is always written
+            match rx.try_recv() {
+                Ok(Message::Message(2, _, None)) => {}
+                message => panic!("Unexpected message {:?}", message),
+            }
+        },
+    );
+}
+
+#[test]
+fn block_policy_does_not_shed_requests() {
+    let server_builder = ServerBuilder::new_sample()
+        .max_queued_responses(1)
+        .queue_overflow_policy(QueueOverflowPolicy::Block);
+    do_message_handler_test(server_builder, |mut message_handler, sender, mut rx| {
+        // The reader waits for room before handling requests, so the handler answers them all
+        for request_handle in 1..=3 {
+            let request = get_endpoints_request(request_handle, 0);
+            message_handler
+                .handle_message(request_handle, &request, &sender)
+                .unwrap();
+        }
+        while let Ok(Message::Message(_, response, _)) = rx.try_recv() {
+            let _ = supported_message_as!(response, GetEndpointsResponse);
+        }
+    });
+}
+
+#[test]
+fn disconnect_when_overloaded() {
+    let server_builder = ServerBuilder::new_sample()
+        .max_queued_responses(1)
+        .queue_overflow_policy(QueueOverflowPolicy::Disconnect);
+    do_message_handler_test(server_builder, |mut message_handler, sender, _rx| {
+        message_handler
+            .handle_message(1, &get_endpoints_request(1, 0), &sender)
+            .unwrap();
+        assert_eq!(
+            message_handler
+                .handle_message(2, &get_endpoints_request(2, 0), &sender)
+                .unwrap_err(),
+            StatusCode::BadTcpServerTooBusy
+        );
+        assert_eq!(sender.queue_depth(), 1);
+    });
+}
+
+fn browse_request(request_handle: u32) -> SupportedMessage {
+    BrowseRequest {
+        request_header: RequestHeader {
+            request_handle,
+            ..make_request_header()
+        },
+        view: ViewDescription {
+            view_id: NodeId::null(),
+            timestamp: DateTime::null(),
+            view_version: 0,
+        },
+        requested_max_references_per_node: 0,
+        nodes_to_browse: Some(vec![BrowseDescription {
+            node_id: NodeId::objects_folder_id(),
+            browse_direction: BrowseDirection::Forward,
+            reference_type_id: NodeId::null(),
+            include_subtypes: true,
+            node_class_mask: 0,
+            result_mask: BrowseDescriptionResultMask::all().bits(),
+        }]),
+    }
+    .into()
+}
+
+fn next_service_result(rx: &mut UnboundedReceiver<Message>) -> StatusCode {
+    match rx.try_recv() {
+        Ok(Message::Message(_, SupportedMessage::ServiceFault(fault), _)) => {
+            fault.response_header.service_result
+        }
+        Ok(Message::Message(_, response, _)) => response.response_header().service_result,
+        message => panic!("Unexpected message {:?}", message),
+    }
+}
+
+#[test]
+fn secure_sessions_only_serves_discovery_over_security_policy_none() {
+    let server_builder = ServerBuilder::new_sample().secure_sessions_only(true);
+    do_message_handler_test(server_builder, |mut message_handler, sender, mut rx| {
+        message_handler
+            .handle_message(1, &get_endpoints_request(1, 0), &sender)
+            .unwrap();
+        match rx.try_recv() {
+            Ok(Message::Message(1, SupportedMessage::GetEndpointsResponse(response), _)) => {
+                // The unsecured endpoint is not advertised
+                let endpoints = response.endpoints.unwrap();
+                assert!(!endpoints.is_empty());
+                assert!(endpoints
+                    .iter()
+                    .all(|e| e.security_mode != MessageSecurityMode::None));
+            }
+            message => panic!("Unexpected message {:?}", message),
+        }
+
+        let create_session = CreateSessionRequest {
+            request_header: make_request_header(),
+            client_description: ApplicationDescription::default(),
+            server_uri: UAString::null(),
+            endpoint_url: UAString::from("opc.tcp://localhost:4855/"),
+            session_name: UAString::from("x"),
+            client_nonce: ByteString::null(),
+            client_certificate: ByteString::null(),
+            requested_session_timeout: 0f64,
+            max_response_message_size: 0,
+        }
+        .into();
+        message_handler
+            .handle_message(2, &create_session, &sender)
+            .unwrap();
+        assert_eq!(
+            next_service_result(&mut rx),
+            StatusCode::BadSecurityPolicyRejected
+        );
+        message_handler
+            .handle_message(3, &browse_request(3), &sender)
+            .unwrap();
+        assert_eq!(
+            next_service_result(&mut rx),
+            StatusCode::BadSecurityPolicyRejected
+        );
+    });
+}
+
+fn create_session_request(client_certificate: ByteString) -> SupportedMessage {
+    CreateSessionRequest {
+        request_header: make_request_header(),
+        client_description: ApplicationDescription::default(),
+        server_uri: UAString::null(),
+        endpoint_url: UAString::from("opc.tcp://localhost:4855/"),
+        session_name: UAString::from("x"),
+        client_nonce: ByteString::null(),
+        client_certificate,
+        requested_session_timeout: 0f64,
+        max_response_message_size: 0,
+    }
+    .into()
+}
+
+#[test]
+fn create_session_with_empty_client_certificate() {
+    // An empty certificate is ignored when the security policy is none
+    do_message_handler_test(
+        ServerBuilder::new_sample(),
+        |mut message_handler, sender, mut rx| {
+            message_handler
+                .handle_message(
+                    1,
+                    &create_session_request(ByteString::from(vec![])),
+                    &sender,
+                )
+                .unwrap();
+            assert_eq!(next_service_result(&mut rx), StatusCode::Good);
+        },
+    );
+    // Otherwise it is rejected as invalid
+    do_message_handler_test_with_policy(
+        ServerBuilder::new_sample(),
+        SecurityPolicy::Basic256Sha256,
+        |mut message_handler, sender, mut rx| {
+            message_handler
+                .handle_message(
+                    1,
+                    &create_session_request(ByteString::from(vec![])),
+                    &sender,
+                )
+                .unwrap();
+            assert_eq!(
+                next_service_result(&mut rx),
+                StatusCode::BadCertificateInvalid
+            );
+        },
+    );
+}
+
+#[test]
+fn max_sessions_across_connections() {
+    let server_builder = ServerBuilder::new_sample().max_sessions(2);
+    let server = server_builder.server().unwrap();
+    let create_session = CreateSessionRequest {
+        request_header: make_request_header(),
+        client_description: ApplicationDescription::default(),
+        server_uri: UAString::null(),
+        endpoint_url: UAString::from("opc.tcp://localhost:4855/"),
+        session_name: UAString::from("x"),
+        client_nonce: ByteString::null(),
+        client_certificate: ByteString::null(),
+        requested_session_timeout: 0f64,
+        max_response_message_size: 0,
+    }
+    .into();
+    // Each session is created on a connection of its own, and the connections share the session
+    // manager of the server, so only the limit of the server applies
+    let (tx, mut rx) = unbounded_channel();
+    let sender = MessageSender::new(tx);
+    let session_manager = Arc::new(RwLock::new(SessionManager::new(2)));
+    let mut message_handlers: Vec<MessageHandler> = (0..3)
+        .map(|_| {
+            MessageHandler::new(
+                Arc::new(RwLock::new(SecureChannel::new_no_certificate_store())),
+                server.certificate_store(),
+                server.server_state(),
+                session_manager.clone(),
+                server.address_space(),
+            )
+        })
+        .collect();
+    for (i, message_handler) in message_handlers.iter_mut().enumerate() {
+        message_handler
+            .handle_message(i as u32, &create_session, &sender)
+            .unwrap();
+    }
+    assert_eq!(next_service_result(&mut rx), StatusCode::Good);
+    assert_eq!(next_service_result(&mut rx), StatusCode::Good);
+    assert_eq!(next_service_result(&mut rx), StatusCode::BadTooManySessions);
+}
+
+#[test]
+fn secure_sessions_only_serves_secure_channels() {
+    let server_builder = ServerBuilder::new_sample().secure_sessions_only(true);
+    do_message_handler_test_with_policy(
+        server_builder,
+        SecurityPolicy::Basic256Sha256,
+        |mut message_handler, sender, mut rx| {
+            // The request gets as far as finding that there is no session
+            message_handler
+                .handle_message(1, &browse_request(1), &sender)
+                .unwrap();
+            assert_eq!(
+                next_service_result(&mut rx),
+                StatusCode::BadSessionIdInvalid
+            );
+        },
+    );
+}
+
+#[test]
+fn unsecured_sessions_are_served_by_default() {
+    do_message_handler_test(
+        ServerBuilder::new_sample(),
+        |mut message_handler, sender, mut rx| {
+            message_handler
+                .handle_message(1, &browse_request(1), &sender)
+                .unwrap();
+            assert_eq!(
+                next_service_result(&mut rx),
+                StatusCode::BadSessionIdInvalid
+            );
+        },
+    );
+}
+
+#[test]
+fn drop_notifications_of_publish_response() {
+    let notification_message = NotificationMessage::data_change(
+        7,
+        DateTime::now(),
+        vec![MonitoredItemNotification {
+            client_handle: 1,
+            value: DataValue::from(1.0f64),
+        }],
+        vec![],
+    );
+    let mut publish_response = PublishResponseEntry {
+        request_id: 1,
+        response: PublishResponse {
+            response_header: ResponseHeader::new_good(&make_request_header()),
+            subscription_id: 1,
+            available_sequence_numbers: Some(vec![7]),
+            more_notifications: false,
+            notification_message,
+            results: None,
+            diagnostic_infos: None,
+        }
+        .into(),
+    };
+    publish_response.drop_notifications();
+
+    // The client still gets its sequence number, so it can republish the message
+    let response = supported_message_as!(publish_response.response, PublishResponse);
+    assert_eq!(
+        response.response_header.service_result,
+        StatusCode::GoodOverload
+    );
+    assert_eq!(response.notification_message.sequence_number, 7);
+    assert!(response.notification_message.notification_data.is_none());
+    assert_eq!(response.available_sequence_numbers, Some(vec![7]));
+}
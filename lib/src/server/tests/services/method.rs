@@ -6,8 +6,16 @@ use crate::types::{
     status_code::StatusCode,
 };
 
-use crate::server::services::{
-    method::MethodService, monitored_item::MonitoredItemService, subscription::SubscriptionService,
+use crate::server::{
+    address_space::{
+        relative_path::find_node_from_browse_path, state_machine::ProgramStateMachineBuilder,
+    },
+    callbacks::Program,
+    events::{event::filter_events, off_normal_alarm::OffNormalAlarmType},
+    services::{
+        method::MethodService, monitored_item::MonitoredItemService,
+        subscription::SubscriptionService,
+    },
 };
 
 use super::*;
@@ -390,3 +398,614 @@ fn call_resend_data() {
         }
     });
 }
+
+#[test]
+fn call_set_subscription_durable() {
+    do_method_service_test(|server_state, session_manager, session, address_space, s| {
+        let call = |args: Vec<Variant>| {
+            let request = new_call_method_request(
+                ObjectId::Server,
+                MethodId::Server_SetSubscriptionDurable,
+                Some(args),
+            );
+            call_single(
+                s,
+                server_state.clone(),
+                session_manager.clone(),
+                session.clone(),
+                address_space.clone(),
+                request,
+            )
+            .unwrap()
+        };
+        let max_lifetime_in_hours = {
+            let server_state = trace_read_lock!(server_state);
+            let config = trace_read_lock!(server_state.config);
+            config.limits.max_durable_subscription_lifetime_hours
+        };
+
+        // Call with invalid subscription id
+        let response = call(vec![100u32.into(), 1u32.into()]);
+        assert_eq!(response.status_code, StatusCode::BadSubscriptionIdInvalid);
+
+        let ss = SubscriptionService::new();
+        let mis = MonitoredItemService::new();
+        let subscription_id = {
+            let request = create_subscription_request();
+            let response: CreateSubscriptionResponse = supported_message_as!(
+                ss.create_subscription(server_state.clone(), session.clone(), &request),
+                CreateSubscriptionResponse
+            );
+            response.subscription_id
+        };
+
+        // The lifetime is revised to the server's maximum if the client asks for none or more
+        for (lifetime_in_hours, revised_lifetime_in_hours) in &[
+            (0, max_lifetime_in_hours),
+            (max_lifetime_in_hours + 1, max_lifetime_in_hours),
+            (1, 1),
+        ] {
+            let response = call(vec![subscription_id.into(), (*lifetime_in_hours).into()]);
+            assert_eq!(response.status_code, StatusCode::Good);
+            assert_eq!(
+                response.output_arguments,
+                Some(vec![Variant::from(*revised_lifetime_in_hours)])
+            );
+        }
+        {
+            // The lifetime count of the subscription lasts an hour at its publishing interval
+            let session = trace_read_lock!(session);
+            let subscription = session
+                .subscriptions()
+                .subscriptions()
+                .get(&subscription_id)
+                .unwrap();
+            assert!(subscription.is_durable());
+            let lifetime_ms =
+                subscription.publishing_interval() * subscription.max_lifetime_count() as f64;
+            assert_eq!(lifetime_ms, 3_600_000f64);
+        }
+
+        // A subscription with monitored items can't be made durable
+        let request =
+            create_monitored_items_request(subscription_id, 1, VariableId::Server_ServiceLevel);
+        let _: CreateMonitoredItemsResponse = supported_message_as!(
+            mis.create_monitored_items(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &request
+            ),
+            CreateMonitoredItemsResponse
+        );
+        let response = call(vec![subscription_id.into(), 1u32.into()]);
+        assert_eq!(response.status_code, StatusCode::BadInvalidState);
+
+        // Call with too few arguments
+        let response = call(vec![subscription_id.into()]);
+        assert_eq!(response.status_code, StatusCode::BadArgumentsMissing);
+    });
+}
+
+#[test]
+fn call_condition_refresh() {
+    do_method_service_test(|server_state, session_manager, session, address_space, s| {
+        let call = |request| {
+            call_single(
+                s,
+                server_state.clone(),
+                session_manager.clone(),
+                session.clone(),
+                address_space.clone(),
+                request,
+            )
+            .unwrap()
+            .status_code
+        };
+        let condition_refresh = |subscription_id: u32| {
+            new_call_method_request(
+                ObjectTypeId::ConditionType,
+                MethodId::ConditionType_ConditionRefresh,
+                Some(vec![subscription_id.into()]),
+            )
+        };
+        let condition_refresh2 = |subscription_id: u32, monitored_item_id: u32| {
+            new_call_method_request(
+                ObjectTypeId::ConditionType,
+                MethodId::ConditionType_ConditionRefresh2,
+                Some(vec![subscription_id.into(), monitored_item_id.into()]),
+            )
+        };
+
+        // Call with invalid subscription id
+        assert_eq!(
+            call(condition_refresh(100)),
+            StatusCode::BadSubscriptionIdInvalid
+        );
+
+        // Create a subscription with a monitored item for the events of the server
+        let ss = SubscriptionService::new();
+        let mis = MonitoredItemService::new();
+        let subscription_id = {
+            let request = create_subscription_request();
+            let response: CreateSubscriptionResponse = supported_message_as!(
+                ss.create_subscription(server_state.clone(), session.clone(), &request),
+                CreateSubscriptionResponse
+            );
+            response.subscription_id
+        };
+        let monitored_item_id = {
+            let mut request =
+                create_monitored_items_request(subscription_id, 999, ObjectId::Server);
+            let item_to_create = &mut request.items_to_create.as_mut().unwrap()[0];
+            item_to_create.item_to_monitor.attribute_id = AttributeId::EventNotifier as u32;
+            item_to_create.requested_parameters.filter = ExtensionObject::from_encodable(
+                ObjectId::EventFilter_Encoding_DefaultBinary,
+                &EventFilter {
+                    where_clause: ContentFilter { elements: None },
+                    select_clauses: Some(vec![SimpleAttributeOperand::new(
+                        ObjectTypeId::BaseEventType,
+                        "EventType",
+                        AttributeId::Value,
+                        UAString::null(),
+                    )]),
+                },
+            );
+            let response: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+            let result = &response.results.unwrap()[0];
+            assert_eq!(result.status_code, StatusCode::Good);
+            result.monitored_item_id
+        };
+
+        // A refresh is pending until the monitored item is next sampled
+        assert_eq!(call(condition_refresh(subscription_id)), StatusCode::Good);
+        assert_eq!(
+            call(condition_refresh(subscription_id)),
+            StatusCode::BadRefreshInProgress
+        );
+        assert_eq!(
+            call(condition_refresh2(subscription_id, monitored_item_id)),
+            StatusCode::BadRefreshInProgress
+        );
+
+        // ConditionRefresh2 needs a monitored item of the subscription
+        assert_eq!(
+            call(condition_refresh2(subscription_id, monitored_item_id + 1)),
+            StatusCode::BadMonitoredItemIdInvalid
+        );
+
+        // The method is on the ConditionType
+        let mut request = condition_refresh(subscription_id);
+        request.object_id = ObjectId::Server.into();
+        assert_eq!(call(request), StatusCode::BadMethodInvalid);
+    });
+}
+
+/// Raises an off normal alarm for a door in a namespace of its own
+fn raise_door_alarm(
+    address_space: &Arc<RwLock<AddressSpace>>,
+    max_time_shelved: Option<f64>,
+) -> NodeId {
+    let mut address_space = trace_write_lock!(address_space);
+    let ns = address_space.register_namespace("urn:alarms").unwrap();
+    let door_id = NodeId::new(ns, "DoorOpen");
+    let normal_id = NodeId::new(ns, "DoorOpenNormal");
+    VariableBuilder::new(&door_id, "DoorOpen", "DoorOpen")
+        .organized_by(ObjectId::ObjectsFolder)
+        .data_type(DataTypeId::Boolean)
+        .value(false)
+        .insert(&mut address_space);
+    VariableBuilder::new(&normal_id, "DoorOpenNormal", "DoorOpenNormal")
+        .organized_by(ObjectId::ObjectsFolder)
+        .data_type(DataTypeId::Boolean)
+        .value(false)
+        .insert(&mut address_space);
+    let alarm_id = NodeId::new(ns, "DoorAlarm");
+    let alarm = OffNormalAlarmType::new(&alarm_id, "DoorAlarm", "DoorAlarm", door_id, normal_id);
+    let mut alarm = match max_time_shelved {
+        Some(max_time_shelved) => alarm.max_time_shelved(max_time_shelved),
+        None => alarm,
+    };
+    assert!(alarm.raise(&mut address_space).is_ok());
+    alarm_id
+}
+
+#[test]
+fn call_shelve_alarm() {
+    do_method_service_test(|server_state, session_manager, session, address_space, s| {
+        let alarm_id = raise_door_alarm(&address_space, None);
+        let shelving_state_id = {
+            let address_space = trace_read_lock!(address_space);
+            find_node_from_browse_path(&address_space, &alarm_id, &["ShelvingState".into()])
+                .unwrap()
+                .as_node()
+                .node_id()
+        };
+        let call = |method_id: MethodId, input_arguments: Option<Vec<Variant>>| {
+            call_single(
+                s,
+                server_state.clone(),
+                session_manager.clone(),
+                session.clone(),
+                address_space.clone(),
+                new_call_method_request(&shelving_state_id, method_id, input_arguments),
+            )
+            .unwrap()
+            .status_code
+        };
+        let current_state_id = || {
+            let address_space = trace_read_lock!(address_space);
+            let node = find_node_from_browse_path(
+                &address_space,
+                &alarm_id,
+                &["ShelvingState".into(), "CurrentState".into(), "Id".into()],
+            )
+            .unwrap();
+            let variable_id = node.as_node().node_id();
+            address_space
+                .get_variable_value(variable_id)
+                .unwrap()
+                .value
+                .unwrap()
+        };
+
+        // Unshelve an alarm that is not shelved
+        assert_eq!(
+            call(MethodId::ShelvedStateMachineType_Unshelve, None),
+            StatusCode::BadConditionNotShelved
+        );
+
+        // Shelve the alarm once
+        assert_eq!(
+            call(MethodId::ShelvedStateMachineType_OneShotShelve, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            current_state_id(),
+            Variant::from(NodeId::from(
+                &ObjectId::ShelvedStateMachineType_OneShotShelved
+            ))
+        );
+        assert_eq!(
+            call(MethodId::ShelvedStateMachineType_OneShotShelve, None),
+            StatusCode::BadConditionAlreadyShelved
+        );
+
+        // Shelve the alarm for a time, which must be positive
+        assert_eq!(
+            call(
+                MethodId::ShelvedStateMachineType_TimedShelve,
+                Some(vec![0f64.into()])
+            ),
+            StatusCode::BadShelvingTimeOutOfRange
+        );
+        assert_eq!(
+            call(MethodId::ShelvedStateMachineType_TimedShelve, None),
+            StatusCode::BadArgumentsMissing
+        );
+        assert_eq!(
+            call(
+                MethodId::ShelvedStateMachineType_TimedShelve,
+                Some(vec![60000f64.into()])
+            ),
+            StatusCode::Good
+        );
+        assert_eq!(
+            current_state_id(),
+            Variant::from(NodeId::from(
+                &ObjectId::ShelvedStateMachineType_TimedShelved
+            ))
+        );
+
+        assert_eq!(
+            call(MethodId::ShelvedStateMachineType_Unshelve, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            current_state_id(),
+            Variant::from(NodeId::from(&ObjectId::ShelvedStateMachineType_Unshelved))
+        );
+    });
+}
+
+#[test]
+fn call_suppress_alarm() {
+    do_method_service_test(|server_state, session_manager, session, address_space, s| {
+        let alarm_id = raise_door_alarm(&address_space, Some(60000f64));
+        let shelving_state_id = {
+            let address_space = trace_read_lock!(address_space);
+            find_node_from_browse_path(&address_space, &alarm_id, &["ShelvingState".into()])
+                .unwrap()
+                .as_node()
+                .node_id()
+        };
+        let call = |object_id: &NodeId, method_id: MethodId, input_arguments| {
+            call_single(
+                s,
+                server_state.clone(),
+                session_manager.clone(),
+                session.clone(),
+                address_space.clone(),
+                new_call_method_request(object_id, method_id, input_arguments),
+            )
+            .unwrap()
+            .status_code
+        };
+        let alarm_value = |browse_path: &[&'static str]| {
+            let address_space = trace_read_lock!(address_space);
+            let browse_path = browse_path
+                .iter()
+                .map(|name| QualifiedName::from(*name))
+                .collect::<Vec<_>>();
+            let node = find_node_from_browse_path(&address_space, &alarm_id, &browse_path).unwrap();
+            let variable_id = node.as_node().node_id();
+            address_space
+                .get_variable_value(variable_id)
+                .unwrap()
+                .value
+                .unwrap()
+        };
+
+        // Suppress and unsuppress the alarm through the methods of the alarm
+        assert_eq!(
+            call(&alarm_id, MethodId::AlarmConditionType_Suppress, None),
+            StatusCode::Good
+        );
+        assert_eq!(alarm_value(&["SuppressedState", "Id"]), Variant::from(true));
+        assert_eq!(alarm_value(&["SuppressedOrShelved"]), Variant::from(true));
+        assert_eq!(
+            call(&alarm_id, MethodId::AlarmConditionType_Unsuppress, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            alarm_value(&["SuppressedState", "Id"]),
+            Variant::from(false)
+        );
+
+        // The methods are on the alarm, not its shelving state
+        assert_eq!(
+            call(
+                &shelving_state_id,
+                MethodId::AlarmConditionType_Suppress,
+                None
+            ),
+            StatusCode::BadMethodInvalid
+        );
+        assert_eq!(
+            call(
+                &alarm_id,
+                MethodId::AlarmConditionType_Suppress,
+                Some(vec![true.into()])
+            ),
+            StatusCode::BadTooManyArguments
+        );
+
+        // The shelving methods are called by the ids of the alarm type too, and the shelving time
+        // may not exceed the maximum
+        assert_eq!(alarm_value(&["MaxTimeShelved"]), Variant::from(60000f64));
+        assert_eq!(
+            call(
+                &shelving_state_id,
+                MethodId::AlarmConditionType_ShelvingState_TimedShelve,
+                Some(vec![60001f64.into()])
+            ),
+            StatusCode::BadShelvingTimeOutOfRange
+        );
+        assert_eq!(
+            call(
+                &shelving_state_id,
+                MethodId::AlarmConditionType_ShelvingState_TimedShelve,
+                Some(vec![30000f64.into()])
+            ),
+            StatusCode::Good
+        );
+        assert_eq!(
+            alarm_value(&["ShelvingState", "UnshelveTime"]),
+            Variant::from(30000f64)
+        );
+
+        // A one shot shelved alarm is shelved for the maximum time at most
+        assert_eq!(
+            call(
+                &shelving_state_id,
+                MethodId::AlarmConditionType_ShelvingState_OneShotShelve,
+                None
+            ),
+            StatusCode::Good
+        );
+        assert_eq!(
+            alarm_value(&["ShelvingState", "UnshelveTime"]),
+            Variant::from(60000f64)
+        );
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            assert!(address_space.has_shelved_alarms_with_unshelve_time());
+            address_space.update_shelved_alarms();
+        }
+        let unshelve_time = alarm_value(&["ShelvingState", "UnshelveTime"])
+            .as_f64()
+            .unwrap();
+        assert!(unshelve_time > 0f64 && unshelve_time <= 60000f64);
+        assert_eq!(
+            call(
+                &shelving_state_id,
+                MethodId::AlarmConditionType_ShelvingState_Unshelve,
+                None
+            ),
+            StatusCode::Good
+        );
+        assert_eq!(
+            alarm_value(&["ShelvingState", "UnshelveTime"]),
+            Variant::from(0f64)
+        );
+    });
+}
+
+/// A call to a program with its input arguments
+type ProgramCall = (&'static str, Vec<Variant>);
+
+/// A program that records its calls, and refuses to resume
+struct BatchProgram {
+    calls: Arc<Mutex<Vec<ProgramCall>>>,
+}
+
+impl BatchProgram {
+    fn record(&self, call: &'static str, input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        self.calls.lock().push((call, input_arguments.to_vec()));
+        Ok(())
+    }
+}
+
+impl Program for BatchProgram {
+    fn start(&mut self, input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        self.record("start", input_arguments)
+    }
+
+    fn suspend(&mut self, input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        self.record("suspend", input_arguments)
+    }
+
+    fn resume(&mut self, _input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        Err(StatusCode::BadUserAccessDenied)
+    }
+
+    fn halt(&mut self, input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        self.record("halt", input_arguments)
+    }
+
+    fn reset(&mut self, input_arguments: &[Variant]) -> Result<(), StatusCode> {
+        self.record("reset", input_arguments)
+    }
+}
+
+#[test]
+fn call_program_methods() {
+    do_method_service_test(|server_state, session_manager, session, address_space, s| {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let program_id = {
+            let mut address_space = trace_write_lock!(address_space);
+            let ns = address_space.register_namespace("urn:programs").unwrap();
+            let program = BatchProgram {
+                calls: calls.clone(),
+            };
+            ProgramStateMachineBuilder::new(
+                &NodeId::new(ns, "Batch"),
+                "Batch",
+                "Batch",
+                Box::new(program),
+            )
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space)
+        };
+        let call = |method_id: MethodId, input_arguments: Option<Vec<Variant>>| {
+            call_single(
+                s,
+                server_state.clone(),
+                session_manager.clone(),
+                session.clone(),
+                address_space.clone(),
+                new_call_method_request(&program_id, method_id, input_arguments),
+            )
+            .unwrap()
+            .status_code
+        };
+        let current_state = || {
+            let address_space = trace_read_lock!(address_space);
+            address_space
+                .state_machine_current_state(&program_id)
+                .unwrap()
+        };
+        let program_value = |name: &'static str| {
+            let address_space = trace_read_lock!(address_space);
+            let node =
+                find_node_from_browse_path(&address_space, &program_id, &[name.into()]).unwrap();
+            address_space
+                .get_variable_value(node.as_node().node_id())
+                .unwrap()
+                .value
+                .unwrap()
+        };
+
+        // The program is ready to start, with the arguments of the call
+        assert_eq!(
+            current_state(),
+            ObjectId::ProgramStateMachineType_Ready.into()
+        );
+        assert_eq!(
+            call(MethodId::ProgramStateMachineType_Suspend, None),
+            StatusCode::BadInvalidState
+        );
+        assert_eq!(
+            call(
+                MethodId::ProgramStateMachineType_Start,
+                Some(vec![Variant::from(5u32)])
+            ),
+            StatusCode::Good
+        );
+        assert_eq!(
+            current_state(),
+            ObjectId::ProgramStateMachineType_Running.into()
+        );
+        assert_eq!(
+            program_value("CurrentState"),
+            Variant::from(LocalizedText::from("Running"))
+        );
+
+        // The program refuses to resume so it stays suspended
+        assert_eq!(
+            call(MethodId::ProgramStateMachineType_Suspend, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            call(MethodId::ProgramStateMachineType_Resume, None),
+            StatusCode::BadUserAccessDenied
+        );
+        assert_eq!(
+            current_state(),
+            ObjectId::ProgramStateMachineType_Suspended.into()
+        );
+
+        // Halting and resetting the program makes it ready again
+        assert_eq!(
+            call(MethodId::ProgramStateMachineType_Halt, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            call(MethodId::ProgramStateMachineType_Reset, None),
+            StatusCode::Good
+        );
+        assert_eq!(
+            current_state(),
+            ObjectId::ProgramStateMachineType_Ready.into()
+        );
+        assert_eq!(program_value("RecycleCount"), Variant::from(1i32));
+        assert_eq!(
+            calls
+                .lock()
+                .iter()
+                .map(|(call, _)| *call)
+                .collect::<Vec<_>>(),
+            vec!["start", "suspend", "halt", "reset"]
+        );
+        assert_eq!(calls.lock()[0].1, vec![Variant::from(5u32)]);
+
+        // The program fires a program transition event for each transition
+        let address_space = trace_read_lock!(address_space);
+        let events = filter_events(
+            &program_id,
+            ObjectTypeId::ProgramTransitionEventType,
+            &address_space,
+            |_| true,
+        )
+        .unwrap();
+        assert_eq!(events.len(), 4);
+    });
+}
@@ -168,9 +168,13 @@ where
 
 pub mod attribute;
 pub mod discovery;
+pub mod message_handler;
 pub mod method;
 pub mod monitored_item;
+#[cfg(feature = "node-management")]
 pub mod node_management;
+#[cfg(feature = "query")]
+pub mod query;
 pub mod session;
 pub mod subscription;
 pub mod view;
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use std::ops::Add;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use chrono::{self, Utc};
 
@@ -519,6 +520,250 @@ fn monitored_item_data_change_filter() {
     )
 }
 
+#[test]
+fn monitored_item_value_source() {
+    do_subscription_service_test(
+        |server_state,
+         _session,
+         _address_space,
+         _ss: SubscriptionService,
+         _mis: MonitoredItemService| {
+            let mut address_space = make_address_space();
+            let server_state = trace_read_lock!(server_state);
+
+            // Variable 1 has a getter that polls, variable 2 one whose value is pushed
+            let poll_calls = Arc::new(AtomicUsize::new(0));
+            let push_calls = Arc::new(AtomicUsize::new(0));
+            let counting_getter = |calls: Arc<AtomicUsize>| {
+                move |_: &NodeId,
+                      _: TimestampsToReturn,
+                      _: AttributeId,
+                      _: NumericRange,
+                      _: &QualifiedName,
+                      _: f64|
+                      -> Result<Option<DataValue>, StatusCode> {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(Some(DataValue::new_now(5u32)))
+                }
+            };
+            {
+                let v = address_space.find_variable_mut(NodeId::new(1, 1)).unwrap();
+                v.set_value_getter(AttrFnGetter::new_boxed(counting_getter(poll_calls.clone())));
+                assert_eq!(v.value_source(), ValueSource::Poll);
+                let v = address_space.find_variable_mut(NodeId::new(1, 2)).unwrap();
+                v.set_value_getter(AttrFnGetter::new_push_boxed(counting_getter(
+                    push_calls.clone(),
+                )));
+                assert_eq!(v.value_source(), ValueSource::Push);
+            }
+
+            let mut monitored_items = (1..=2)
+                .map(|i| {
+                    MonitoredItem::new(
+                        &chrono::Utc::now(),
+                        i,
+                        TimestampsToReturn::Both,
+                        &server_state,
+                        &make_create_request(
+                            -1f64,
+                            5,
+                            NodeId::new(1, i),
+                            AttributeId::Value,
+                            ExtensionObject::null(),
+                        ),
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>();
+            fn tick(
+                monitored_items: &mut [MonitoredItem],
+                address_space: &AddressSpace,
+                publishing_interval_elapsed: bool,
+            ) -> Vec<TickResult> {
+                let now = Utc::now();
+                monitored_items
+                    .iter_mut()
+                    .map(|m| m.tick(&now, address_space, publishing_interval_elapsed, false))
+                    .collect()
+            }
+
+            // Only the getter that polls is called, and only when the items sample
+            assert_eq!(
+                tick(&mut monitored_items, &address_space, true),
+                vec![
+                    TickResult::ReportValueChanged,
+                    TickResult::ReportValueChanged
+                ]
+            );
+            monitored_items.iter_mut().for_each(|m| {
+                let _ = m.all_notifications();
+            });
+            assert_eq!(
+                tick(&mut monitored_items, &address_space, false),
+                vec![TickResult::NoChange, TickResult::NoChange]
+            );
+            assert_eq!(
+                tick(&mut monitored_items, &address_space, true),
+                vec![TickResult::NoChange, TickResult::NoChange]
+            );
+            assert_eq!(poll_calls.load(Ordering::SeqCst), 2);
+            assert_eq!(push_calls.load(Ordering::SeqCst), 0);
+
+            // A pushed value is sampled when the application sets it
+            let _ = address_space.set_variable_value(
+                NodeId::new(1, 2),
+                7u32,
+                &DateTime::now(),
+                &DateTime::now(),
+            );
+            assert_eq!(
+                tick(&mut monitored_items, &address_space, true),
+                vec![TickResult::NoChange, TickResult::ReportValueChanged]
+            );
+            assert_eq!(push_calls.load(Ordering::SeqCst), 0);
+            let notification = monitored_items[1]
+                .all_notifications()
+                .unwrap()
+                .pop()
+                .unwrap();
+            if let Notification::MonitoredItemNotification(notification) = notification {
+                assert_eq!(notification.value.value.unwrap(), Variant::UInt32(7));
+            } else {
+                panic!();
+            }
+
+            // A read still calls the getter of a pushed value
+            let v = address_space.find_variable(NodeId::new(1, 2)).unwrap();
+            let _ = v.value(
+                TimestampsToReturn::Both,
+                NumericRange::None,
+                &QualifiedName::null(),
+                0.0,
+            );
+            assert_eq!(push_calls.load(Ordering::SeqCst), 1);
+        },
+    )
+}
+
+#[test]
+fn monitored_item_coalesced_updates() {
+    do_subscription_service_test(
+        |server_state,
+         _session,
+         _address_space,
+         _ss: SubscriptionService,
+         _mis: MonitoredItemService| {
+            let mut address_space = make_address_space();
+            let server_state = trace_read_lock!(server_state);
+
+            // An item with a queue of 2 that samples at the publishing interval
+            let mut monitored_item = MonitoredItem::new(
+                &chrono::Utc::now(),
+                1,
+                TimestampsToReturn::Both,
+                &server_state,
+                &make_create_request(
+                    -1f64,
+                    2,
+                    test_var_node_id(),
+                    AttributeId::Value,
+                    ExtensionObject::null(),
+                ),
+            )
+            .unwrap();
+            let push = |address_space: &mut AddressSpace, values: std::ops::Range<u32>| {
+                values.for_each(|v| {
+                    let now = DateTime::now();
+                    let _ = address_space.set_variable_value(test_var_node_id(), v, &now, &now);
+                });
+            };
+
+            // Values pushed between samples are coalesced into the latest
+            let now = Utc::now();
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::ReportValueChanged
+            );
+            push(&mut address_space, 1..6);
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::ReportValueChanged
+            );
+            assert_eq!(monitored_item.coalesced_updates(), 4);
+            assert_eq!(monitored_item.discarded_notifications(), 0);
+            assert_eq!(monitored_item.notification_queue().len(), 2);
+
+            // A single update is sampled, and overflows the queue
+            push(&mut address_space, 6..7);
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::ReportValueChanged
+            );
+            assert_eq!(monitored_item.coalesced_updates(), 4);
+            assert_eq!(monitored_item.discarded_notifications(), 1);
+            let notification = monitored_item.oldest_notification_message().unwrap();
+            if let Notification::MonitoredItemNotification(notification) = notification {
+                assert_eq!(notification.value.value.unwrap(), Variant::UInt32(5));
+            } else {
+                panic!();
+            }
+        },
+    )
+}
+
+#[test]
+fn monitored_item_timestamps_to_return() {
+    // Test that notifications have the timestamps that were asked for, with the server timestamp
+    // being the time that the value was sampled
+    do_subscription_service_test(
+        |server_state,
+         _session,
+         _address_space,
+         _ss: SubscriptionService,
+         _mis: MonitoredItemService| {
+            let mut address_space = make_address_space();
+            let source_timestamp = DateTime::ymd_hms(2022, 1, 1, 12, 0, 0);
+            let _ = address_space.set_variable_value(
+                test_var_node_id(),
+                1u32,
+                &source_timestamp,
+                &source_timestamp,
+            );
+            let server_state = trace_read_lock!(server_state);
+
+            let sampled = Utc::now();
+            let timestamps = |timestamps_to_return| {
+                let mut monitored_item = MonitoredItem::new(
+                    &chrono::Utc::now(),
+                    1,
+                    timestamps_to_return,
+                    &server_state,
+                    &make_create_request_data_change_filter(-1f64, 5),
+                )
+                .unwrap();
+                assert_eq!(
+                    monitored_item.tick(&sampled, &address_space, true, false),
+                    TickResult::ReportValueChanged
+                );
+                match monitored_item.oldest_notification_message().unwrap() {
+                    Notification::MonitoredItemNotification(notification) => (
+                        notification.value.source_time(),
+                        notification.value.server_time(),
+                    ),
+                    _ => panic!(),
+                }
+            };
+
+            let source = Some(source_timestamp.as_chrono());
+            let server = Some(sampled);
+            assert_eq!(timestamps(TimestampsToReturn::Both), (source, server));
+            assert_eq!(timestamps(TimestampsToReturn::Source), (source, None));
+            assert_eq!(timestamps(TimestampsToReturn::Server), (None, server));
+            assert_eq!(timestamps(TimestampsToReturn::Neither), (None, None));
+        },
+    )
+}
+
 #[test]
 fn monitored_item_event_filter() {
     // create an address space
@@ -679,6 +924,500 @@ fn unknown_node_id() {
     );
 }
 
+#[test]
+fn too_many_monitored_items() {
+    do_subscription_service_test(
+        |server_state,
+         session,
+         address_space,
+         ss: SubscriptionService,
+         mis: MonitoredItemService| {
+            // The subscription holds no more monitored items than the limit
+            {
+                let mut server_state = trace_write_lock!(server_state);
+                server_state.max_monitored_items_per_sub = 2;
+            }
+            let subscription_id = {
+                let request = create_subscription_request(0, 0);
+                let response: CreateSubscriptionResponse = supported_message_as!(
+                    ss.create_subscription(server_state.clone(), session.clone(), &request),
+                    CreateSubscriptionResponse
+                );
+                response.subscription_id
+            };
+
+            let request = create_monitored_items_request(
+                subscription_id,
+                (1..=3)
+                    .map(|i| NodeId::new(1, var_name(i)))
+                    .collect::<Vec<_>>(),
+            );
+            let response: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+            let status_codes = response
+                .results
+                .unwrap()
+                .iter()
+                .map(|result| result.status_code)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                status_codes,
+                vec![
+                    StatusCode::Good,
+                    StatusCode::Good,
+                    StatusCode::BadTooManyMonitoredItems
+                ]
+            );
+        },
+    );
+}
+
+#[test]
+fn revised_intervals() {
+    do_subscription_service_test(
+        |server_state,
+         session,
+         address_space,
+         ss: SubscriptionService,
+         mis: MonitoredItemService| {
+            let (min_publishing_interval, min_sampling_interval) = {
+                let server_state = trace_read_lock!(server_state);
+                (
+                    server_state.min_publishing_interval_ms,
+                    server_state.min_sampling_interval_ms,
+                )
+            };
+
+            // A publishing interval faster than the minimum is revised to the minimum
+            let create_subscription = |publishing_interval| {
+                let request = CreateSubscriptionRequest {
+                    requested_publishing_interval: publishing_interval,
+                    ..create_subscription_request(0, 0)
+                };
+                let response: CreateSubscriptionResponse = supported_message_as!(
+                    ss.create_subscription(server_state.clone(), session.clone(), &request),
+                    CreateSubscriptionResponse
+                );
+                response
+            };
+            assert_eq!(
+                create_subscription(1.0).revised_publishing_interval,
+                min_publishing_interval
+            );
+            assert_eq!(
+                create_subscription(f64::NAN).revised_publishing_interval,
+                min_publishing_interval
+            );
+            let response = create_subscription(1000.0);
+            assert_eq!(response.revised_publishing_interval, 1000.0);
+            let subscription_id = response.subscription_id;
+
+            // -1 samples at the publishing interval, intervals faster than the minimum are
+            // revised to the minimum
+            let sampling_intervals = [-1.0, 0.0, 1.0, f64::NAN, 500.0];
+            let request = CreateMonitoredItemsRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                timestamps_to_return: TimestampsToReturn::Both,
+                items_to_create: Some(
+                    sampling_intervals
+                        .iter()
+                        .map(|sampling_interval| {
+                            make_create_request(
+                                *sampling_interval,
+                                1,
+                                NodeId::new(1, var_name(1)),
+                                AttributeId::Value,
+                                ExtensionObject::null(),
+                            )
+                        })
+                        .collect(),
+                ),
+            };
+            let response: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+            let results = response.results.unwrap();
+            assert_eq!(
+                results
+                    .iter()
+                    .map(|r| r.revised_sampling_interval)
+                    .collect::<Vec<_>>(),
+                vec![
+                    1000.0,
+                    min_sampling_interval,
+                    min_sampling_interval,
+                    min_sampling_interval,
+                    500.0
+                ]
+            );
+
+            // Modifying an item to -1 also reports the publishing interval
+            let request = ModifyMonitoredItemsRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                timestamps_to_return: TimestampsToReturn::Both,
+                items_to_modify: Some(vec![MonitoredItemModifyRequest {
+                    monitored_item_id: results[4].monitored_item_id,
+                    requested_parameters: MonitoringParameters {
+                        sampling_interval: -1.0,
+                        ..request.items_to_create.as_ref().unwrap()[4]
+                            .requested_parameters
+                            .clone()
+                    },
+                }]),
+            };
+            let response: ModifyMonitoredItemsResponse = supported_message_as!(
+                mis.modify_monitored_items(server_state, session, address_space, &request),
+                ModifyMonitoredItemsResponse
+            );
+            let results = response.results.unwrap();
+            assert_eq!(results[0].status_code, StatusCode::Good);
+            assert_eq!(results[0].revised_sampling_interval, 1000.0);
+        },
+    );
+}
+
+#[test]
+fn minimum_sampling_interval() {
+    do_subscription_service_test(
+        |server_state,
+         session,
+         address_space,
+         ss: SubscriptionService,
+         mis: MonitoredItemService| {
+            // A variable whose value cannot be sampled faster than every 2s
+            let slow_node_id = NodeId::new(1, "slow");
+            {
+                let mut address_space = trace_write_lock!(address_space);
+                VariableBuilder::new(&slow_node_id, "slow", "slow")
+                    .data_type(DataTypeId::UInt32)
+                    .value(0u32)
+                    .minimum_sampling_interval(2000.0)
+                    .organized_by(ObjectId::ObjectsFolder)
+                    .insert(&mut address_space);
+            }
+
+            let request = CreateSubscriptionRequest {
+                requested_publishing_interval: 1000.0,
+                ..create_subscription_request(0, 0)
+            };
+            let response: CreateSubscriptionResponse = supported_message_as!(
+                ss.create_subscription(server_state.clone(), session.clone(), &request),
+                CreateSubscriptionResponse
+            );
+            let subscription_id = response.subscription_id;
+
+            // Intervals faster than the minimum, including the publishing interval, are revised
+            // to the minimum. The minimum only applies to the value of the variable.
+            let items_to_create = vec![
+                (-1.0, slow_node_id.clone(), AttributeId::Value),
+                (100.0, slow_node_id.clone(), AttributeId::Value),
+                (5000.0, slow_node_id.clone(), AttributeId::Value),
+                (100.0, slow_node_id.clone(), AttributeId::DisplayName),
+                (100.0, NodeId::new(1, var_name(1)), AttributeId::Value),
+            ];
+            let request = CreateMonitoredItemsRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                timestamps_to_return: TimestampsToReturn::Both,
+                items_to_create: Some(
+                    items_to_create
+                        .into_iter()
+                        .map(|(sampling_interval, node_id, attribute_id)| {
+                            make_create_request(
+                                sampling_interval,
+                                1,
+                                node_id,
+                                attribute_id,
+                                ExtensionObject::null(),
+                            )
+                        })
+                        .collect(),
+                ),
+            };
+            let response: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+            let results = response.results.unwrap();
+            assert_eq!(
+                results
+                    .iter()
+                    .map(|r| r.revised_sampling_interval)
+                    .collect::<Vec<_>>(),
+                vec![2000.0, 2000.0, 5000.0, 100.0, 100.0]
+            );
+
+            // Modifying an item is revised the same way
+            let request = ModifyMonitoredItemsRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                timestamps_to_return: TimestampsToReturn::Both,
+                items_to_modify: Some(vec![MonitoredItemModifyRequest {
+                    monitored_item_id: results[2].monitored_item_id,
+                    requested_parameters: MonitoringParameters {
+                        sampling_interval: 500.0,
+                        ..request.items_to_create.as_ref().unwrap()[2]
+                            .requested_parameters
+                            .clone()
+                    },
+                }]),
+            };
+            let response: ModifyMonitoredItemsResponse = supported_message_as!(
+                mis.modify_monitored_items(server_state, session, address_space, &request),
+                ModifyMonitoredItemsResponse
+            );
+            let results = response.results.unwrap();
+            assert_eq!(results[0].status_code, StatusCode::Good);
+            assert_eq!(results[0].revised_sampling_interval, 2000.0);
+        },
+    );
+}
+
+#[test]
+fn monitored_item_resend_data() {
+    do_subscription_service_test(
+        |server_state,
+         session,
+         address_space,
+         ss: SubscriptionService,
+         mis: MonitoredItemService| {
+            let subscription_id = {
+                let request = create_subscription_request(0, 0);
+                let response: CreateSubscriptionResponse = supported_message_as!(
+                    ss.create_subscription(server_state.clone(), session.clone(), &request),
+                    CreateSubscriptionResponse
+                );
+                response.subscription_id
+            };
+            {
+                let mut session = trace_write_lock!(session);
+                session
+                    .subscriptions_mut()
+                    .get_mut(subscription_id)
+                    .unwrap()
+                    .set_state(SubscriptionState::Normal);
+            }
+            let request = create_monitored_items_request(
+                subscription_id,
+                vec![NodeId::new(1, var_name(0)), NodeId::new(1, var_name(1))],
+            );
+            let _: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+            let expect_both_items = |response: PublishResponse| {
+                let (notifications, _) = response
+                    .notification_message
+                    .notifications(&DecodingOptions::test())
+                    .unwrap();
+                assert_eq!(notifications.len(), 1);
+                let monitored_items = notifications[0].monitored_items.as_ref().unwrap();
+                let client_handles: HashSet<u32> = monitored_items
+                    .iter()
+                    .map(|min| min.client_handle)
+                    .collect();
+                assert_eq!(client_handles, [0, 1].iter().cloned().collect());
+            };
+
+            // The first publish has the initial values, after which nothing changes
+            let now = publish_tick_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                Utc::now(),
+                chrono::Duration::seconds(2),
+                expect_both_items,
+            );
+            let now = publish_tick_no_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                now,
+                chrono::Duration::seconds(2),
+            );
+
+            // ResendData sends the unchanged values once more
+            {
+                let mut session = trace_write_lock!(session);
+                session
+                    .subscriptions_mut()
+                    .get_mut(subscription_id)
+                    .unwrap()
+                    .set_resend_data();
+            }
+            let now = publish_tick_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                now,
+                chrono::Duration::seconds(2),
+                expect_both_items,
+            );
+            let _ = publish_tick_no_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                now,
+                chrono::Duration::seconds(2),
+            );
+        },
+    );
+}
+
+#[test]
+fn late_subscription_notifications_are_capped() {
+    do_subscription_service_test(
+        |server_state,
+         session,
+         address_space,
+         ss: SubscriptionService,
+         mis: MonitoredItemService| {
+            let subscription_id = {
+                let request = create_subscription_request(0, 10000);
+                let response: CreateSubscriptionResponse = supported_message_as!(
+                    ss.create_subscription(server_state.clone(), session.clone(), &request),
+                    CreateSubscriptionResponse
+                );
+                response.subscription_id
+            };
+            let request =
+                create_monitored_items_request(subscription_id, vec![NodeId::new(1, var_name(0))]);
+            let _: CreateMonitoredItemsResponse = supported_message_as!(
+                mis.create_monitored_items(
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &request
+                ),
+                CreateMonitoredItemsResponse
+            );
+
+            // The client stops sending publish requests while every publishing interval has a
+            // notification for it
+            let max = trace_read_lock!(server_state).max_queued_notifications;
+            let ticks = max + 10;
+            let mut now = Utc::now();
+            {
+                let mut session = trace_write_lock!(session);
+                let address_space = trace_read_lock!(address_space);
+                session
+                    .subscriptions_mut()
+                    .get_mut(subscription_id)
+                    .unwrap()
+                    .set_state(SubscriptionState::Late);
+                // Something stale is held under a sequence number that is discarded
+                let notification = NotificationMessage::data_change(
+                    1,
+                    DateTime::now(),
+                    vec![MonitoredItemNotification {
+                        client_handle: 1,
+                        value: Variant::Empty.into(),
+                    }],
+                    vec![],
+                );
+                session
+                    .subscriptions_mut()
+                    .retransmission_queue()
+                    .insert((subscription_id, 1), notification);
+                for _ in 0..ticks {
+                    session
+                        .subscriptions_mut()
+                        .get_mut(subscription_id)
+                        .unwrap()
+                        .set_resend_data();
+                    now += chrono::Duration::seconds(2);
+                    let _ = session.tick_subscriptions(
+                        &now,
+                        &address_space,
+                        TickReason::TickTimerFired,
+                    );
+                }
+                assert!(session
+                    .subscriptions_mut()
+                    .retransmission_queue()
+                    .is_empty());
+            }
+
+            // A discarded sequence number cannot be republished or acknowledged
+            let request = RepublishRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                retransmit_sequence_number: 1,
+            };
+            let response: ServiceFault =
+                supported_message_as!(ss.republish(session.clone(), &request), ServiceFault);
+            assert_eq!(
+                response.response_header.service_result,
+                StatusCode::BadMessageNotAvailable
+            );
+            let request = PublishRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_acknowledgements: Some(vec![SubscriptionAcknowledgement {
+                    subscription_id,
+                    sequence_number: 1,
+                }]),
+            };
+            let _ = ss.async_publish(&now, session.clone(), address_space.clone(), 1001, &request);
+
+            // The publish request takes the oldest notification still waiting, which can be
+            // republished
+            let response = publish_response(session.clone());
+            assert_eq!(
+                response.results,
+                Some(vec![StatusCode::BadSequenceNumberUnknown])
+            );
+            let oldest = (ticks - max + 1) as u32;
+            assert_eq!(response.notification_message.sequence_number, oldest);
+            let request = RepublishRequest {
+                request_header: RequestHeader::dummy(),
+                subscription_id,
+                retransmit_sequence_number: oldest,
+            };
+            let response: RepublishResponse =
+                supported_message_as!(ss.republish(session.clone(), &request), RepublishResponse);
+            assert_eq!(response.notification_message.sequence_number, oldest);
+
+            // Only the newest notifications are still waiting
+            let mut session = trace_write_lock!(session);
+            let subscription = session
+                .subscriptions_mut()
+                .get_mut(subscription_id)
+                .unwrap();
+            let mut sequence_numbers = Vec::new();
+            while let Some(notification) = subscription.take_notification() {
+                sequence_numbers.push(notification.sequence_number);
+            }
+            let expected = (oldest + 1..=ticks as u32).collect::<Vec<_>>();
+            assert_eq!(sequence_numbers, expected);
+        },
+    );
+}
+
 #[test]
 fn monitored_item_triggers() {
     do_subscription_service_test(
@@ -931,6 +1670,33 @@ fn monitored_item_triggers() {
                 );
             }
 
+            // the sampling triggered item has no new sample and already reported its last value,
+            // so nothing is reported
+            let now = publish_tick_no_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                now,
+                chrono::Duration::seconds(2),
+            );
+
+            // change the value of the triggering item and the sampling triggered item
+            {
+                let mut address_space = trace_write_lock!(address_space);
+                let _ = address_space.set_variable_value(
+                    triggering_node.clone(),
+                    3,
+                    &DateTime::from(now),
+                    &DateTime::from(now),
+                );
+                let _ = address_space.set_variable_value(
+                    NodeId::new(1, var_name(2)),
+                    10,
+                    &DateTime::from(now),
+                    &DateTime::from(now),
+                );
+            }
+
             // do a publish on the monitored item,
             let now = publish_tick_response(
                 session.clone(),
@@ -956,6 +1722,25 @@ fn monitored_item_triggers() {
                 },
             );
 
+            // change only the value of the sampling triggered item. It is not reported because
+            // the triggering item has not changed
+            {
+                let mut address_space = trace_write_lock!(address_space);
+                let _ = address_space.set_variable_value(
+                    NodeId::new(1, var_name(2)),
+                    11,
+                    &DateTime::from(now),
+                    &DateTime::from(now),
+                );
+            }
+            let now = publish_tick_no_response(
+                session.clone(),
+                &ss,
+                address_space.clone(),
+                now,
+                chrono::Duration::seconds(2),
+            );
+
             // change monitoring mode of triggering item to disable
             set_monitoring_mode(
                 session.clone(),
@@ -968,9 +1753,15 @@ fn monitored_item_triggers() {
                 let mut address_space = trace_write_lock!(address_space);
                 let _ = address_space.set_variable_value(
                     triggering_node.clone(),
-                    3,
-                    &DateTime::from(now.clone()),
-                    &DateTime::from(now.clone()),
+                    4,
+                    &DateTime::from(now),
+                    &DateTime::from(now),
+                );
+                let _ = address_space.set_variable_value(
+                    NodeId::new(1, var_name(2)),
+                    12,
+                    &DateTime::from(now),
+                    &DateTime::from(now),
                 );
             }
 
@@ -1002,6 +1793,7 @@ fn monitored_item_queue_discard_oldest() {
             // discard_oldest = true
             {
                 let mut monitored_item = populate_monitored_item(&server_state, true);
+                assert_eq!(monitored_item.discarded_notifications(), 1);
                 assert_first_notification_is_i32(&mut monitored_item, 1);
                 assert_first_notification_is_i32(&mut monitored_item, 2);
                 assert_first_notification_is_i32(&mut monitored_item, 3);
@@ -1012,6 +1804,7 @@ fn monitored_item_queue_discard_oldest() {
             // discard_oldest = false
             {
                 let mut monitored_item = populate_monitored_item(&server_state, false);
+                assert_eq!(monitored_item.discarded_notifications(), 1);
                 assert_first_notification_is_i32(&mut monitored_item, 0);
                 assert_first_notification_is_i32(&mut monitored_item, 1);
                 assert_first_notification_is_i32(&mut monitored_item, 2);
@@ -1021,3 +1814,204 @@ fn monitored_item_queue_discard_oldest() {
         },
     );
 }
+
+#[test]
+fn monitored_item_event_queue_overflow() {
+    // When the queue of an event monitored item overflows an EventQueueOverflowEventType event
+    // takes the place of a discarded event
+    do_subscription_service_test(
+        |server_state,
+         _session,
+         _address_space,
+         _ss: SubscriptionService,
+         _mis: MonitoredItemService| {
+            let server_state = trace_read_lock!(server_state);
+
+            let queued_events = |discard_oldest| {
+                let mut monitored_item = MonitoredItem::new(
+                    &chrono::Utc::now(),
+                    1,
+                    TimestampsToReturn::Both,
+                    &server_state,
+                    &make_create_request_event_filter(-1f64, 3),
+                )
+                .unwrap();
+                monitored_item.set_discard_oldest(discard_oldest);
+                for i in 0..5 {
+                    monitored_item.enqueue_notification_message(EventFieldList {
+                        client_handle: 999,
+                        event_fields: Some(vec![Variant::Int32(i)]),
+                    });
+                }
+                assert!(monitored_item.queue_overflow());
+                monitored_item
+                    .all_notifications()
+                    .unwrap()
+                    .into_iter()
+                    .map(|n| match n {
+                        Notification::Event(event) => {
+                            assert_eq!(event.client_handle, 999);
+                            event.event_fields.unwrap()
+                        }
+                        _ => panic!(),
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let is_overflow_event = |fields: &Vec<Variant>| {
+                // The select clauses are the EventId and SourceNode of the event
+                fields.len() == 2
+                    && matches!(fields[0], Variant::ByteString(_))
+                    && fields[1] == Variant::from(NodeId::from(&ObjectId::Server))
+            };
+
+            // The overflow event goes first and the oldest events after it are discarded
+            let events = queued_events(true);
+            assert_eq!(events.len(), 3);
+            assert!(is_overflow_event(&events[0]));
+            assert_eq!(events[1], vec![Variant::Int32(3)]);
+            assert_eq!(events[2], vec![Variant::Int32(4)]);
+
+            // The overflow event replaces the latest event and newer events are discarded
+            let events = queued_events(false);
+            assert_eq!(events.len(), 3);
+            assert_eq!(events[0], vec![Variant::Int32(0)]);
+            assert_eq!(events[1], vec![Variant::Int32(1)]);
+            assert!(is_overflow_event(&events[2]));
+        },
+    );
+}
+
+#[test]
+fn monitored_item_condition_refresh() {
+    // A condition refresh replays the retained conditions of the object between a refresh start
+    // and a refresh end event
+    do_subscription_service_test(
+        |server_state,
+         _session,
+         _address_space,
+         _ss: SubscriptionService,
+         _mis: MonitoredItemService| {
+            let mut address_space = make_address_space();
+            let server_state = trace_read_lock!(server_state);
+
+            // The test variables have numeric ids in namespace 1 and so do the properties of the
+            // events, so the events go in a namespace of their own
+            let _ = address_space.register_namespace("urn:test").unwrap();
+            let ns = address_space.register_namespace("urn:conditions").unwrap();
+
+            let filter = ExtensionObject::from_encodable(
+                ObjectId::EventFilter_Encoding_DefaultBinary,
+                &EventFilter {
+                    where_clause: ContentFilter { elements: None },
+                    select_clauses: Some(vec![
+                        SimpleAttributeOperand::new(
+                            ObjectTypeId::BaseEventType,
+                            "EventType",
+                            AttributeId::Value,
+                            UAString::null(),
+                        ),
+                        SimpleAttributeOperand::new(
+                            ObjectTypeId::BaseEventType,
+                            "Message",
+                            AttributeId::Value,
+                            UAString::null(),
+                        ),
+                    ]),
+                },
+            );
+            let mut monitored_item = MonitoredItem::new(
+                &chrono::Utc::now(),
+                1,
+                TimestampsToReturn::Both,
+                &server_state,
+                &make_create_request(
+                    -1f64,
+                    10,
+                    test_object_node_id(),
+                    AttributeId::EventNotifier,
+                    filter,
+                ),
+            )
+            .unwrap();
+
+            // Raise conditions that are retained or not, and a plain event
+            let now = Utc::now() - chrono::Duration::seconds(10);
+            let mut raise =
+                |name: &'static str, event_type_id: ObjectTypeId, retain: Option<bool>| {
+                    let event_id = NodeId::new(ns, name);
+                    let mut event = BaseEventType::new(
+                        &event_id,
+                        event_type_id,
+                        name,
+                        "",
+                        NodeId::objects_folder_id(),
+                        DateTime::from(now),
+                    )
+                    .source_node(test_object_node_id())
+                    .message(name);
+                    assert!(event.raise(&mut address_space).is_ok());
+                    if let Some(retain) = retain {
+                        event.add_property(
+                            &event_id,
+                            NodeId::next_numeric(ns),
+                            "Retain",
+                            "Retain",
+                            DataTypeId::Boolean,
+                            retain,
+                            &mut address_space,
+                        );
+                    }
+                };
+            raise("Alarm1", ObjectTypeId::AlarmConditionType, Some(true));
+            raise("Alarm2", ObjectTypeId::AlarmConditionType, Some(false));
+            raise("Condition1", ObjectTypeId::ConditionType, None);
+            raise("Event1", ObjectTypeId::BaseEventType, None);
+
+            // The events happened before the item was created so nothing is reported
+            let now = Utc::now() + chrono::Duration::milliseconds(100);
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::NoChange
+            );
+
+            monitored_item.set_condition_refresh();
+            let now = now + chrono::Duration::milliseconds(100);
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::ReportValueChanged
+            );
+            assert!(!monitored_item.condition_refresh());
+
+            let events = monitored_item
+                .all_notifications()
+                .unwrap()
+                .into_iter()
+                .map(|n| match n {
+                    Notification::Event(event) => event.event_fields.unwrap(),
+                    _ => panic!(),
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(events.len(), 3);
+            assert_eq!(
+                events[0][0],
+                Variant::from(NodeId::from(&ObjectTypeId::RefreshStartEventType))
+            );
+            assert_eq!(
+                events[1][0],
+                Variant::from(NodeId::from(&ObjectTypeId::AlarmConditionType))
+            );
+            assert_eq!(events[1][1], Variant::from(LocalizedText::from("Alarm1")));
+            assert_eq!(
+                events[2][0],
+                Variant::from(NodeId::from(&ObjectTypeId::RefreshEndEventType))
+            );
+
+            // The refresh only happens once
+            let now = now + chrono::Duration::milliseconds(100);
+            assert_eq!(
+                monitored_item.tick(&now, &address_space, true, false),
+                TickResult::NoChange
+            );
+        },
+    );
+}
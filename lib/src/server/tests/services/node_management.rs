@@ -466,6 +466,43 @@ fn add_nodes_valid() {
     );
 }
 
+#[test]
+fn add_nodes_model_change_event() {
+    // Adding a node while the server is running raises a model change event
+    do_node_management_service_test(true, |server_state, session, address_space, nms| {
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            address_space.set_model_change_events(true);
+        }
+        let response = nms.add_nodes(
+            server_state,
+            session,
+            address_space.clone(),
+            &AddNodesRequest {
+                request_header: RequestHeader::dummy(),
+                nodes_to_add: Some(vec![AddNodesItem {
+                    parent_node_id: ObjectId::ObjectsFolder.into(),
+                    reference_type_id: ReferenceTypeId::Organizes.into(),
+                    requested_new_node_id: ExpandedNodeId::null(),
+                    browse_name: QualifiedName::from("boo"),
+                    node_class: NodeClass::Object,
+                    node_attributes: object_attributes("foo"),
+                    type_definition: ObjectTypeId::BaseObjectType.into(),
+                }]),
+            },
+        );
+        let response: AddNodesResponse = supported_message_as!(response, AddNodesResponse);
+        let results = response.results.unwrap();
+        assert!(results[0].status_code.is_good());
+
+        let address_space = trace_read_lock!(address_space);
+        let events = address_space
+            .find_objects_by_type(ObjectTypeId::GeneralModelChangeEventType, false)
+            .unwrap();
+        assert!(!events.is_empty());
+    });
+}
+
 #[test]
 fn add_nodes_user_access_denied() {
     // Add a node without permission
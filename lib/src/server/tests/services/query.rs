@@ -0,0 +1,400 @@
+use crate::server::services::query::QueryService;
+use crate::supported_message_as;
+use crate::sync::*;
+use crate::types::operand::{ContentFilterBuilder, Operand};
+
+use super::*;
+
+// Query service tests
+
+fn pump_type_id() -> NodeId {
+    NodeId::new(1, "PumpType")
+}
+
+fn big_pump_type_id() -> NodeId {
+    NodeId::new(1, "BigPumpType")
+}
+
+fn pump_id(idx: usize) -> NodeId {
+    NodeId::new(1, format!("Pump{}", idx))
+}
+
+/// Adds a pump type and a subtype of it, each with a speed, and pumps of both types. The pumps
+/// have speeds of 10, 20, 30 and 40 and all but the first are in the returned view.
+fn add_pumps_to_address_space(address_space: Arc<RwLock<AddressSpace>>) -> NodeId {
+    let mut address_space = trace_write_lock!(address_space);
+    ObjectTypeBuilder::new(&pump_type_id(), "PumpType", "PumpType")
+        .subtype_of(ObjectTypeId::BaseObjectType)
+        .insert(&mut address_space);
+    VariableBuilder::new(&NodeId::new(1, "PumpType.Speed"), "Speed", "Speed")
+        .data_type(DataTypeId::Int32)
+        .value(0i32)
+        .component_of(pump_type_id())
+        .insert(&mut address_space);
+    ObjectTypeBuilder::new(&big_pump_type_id(), "BigPumpType", "BigPumpType")
+        .subtype_of(pump_type_id())
+        .insert(&mut address_space);
+
+    let folder_id = address_space
+        .add_folder("Pumps", "Pumps", &NodeId::objects_folder_id())
+        .unwrap();
+    (1..=4).for_each(|idx| {
+        let type_definition_id = if idx % 2 == 0 {
+            big_pump_type_id()
+        } else {
+            pump_type_id()
+        };
+        let name = format!("Pump{}", idx);
+        ObjectBuilder::new(&pump_id(idx), name.as_str(), name.as_str())
+            .has_type_definition(type_definition_id)
+            .organized_by(folder_id.clone())
+            .insert(&mut address_space);
+        VariableBuilder::new(
+            &NodeId::new(1, format!("Pump{}.Speed", idx)),
+            "Speed",
+            "Speed",
+        )
+        .data_type(DataTypeId::Int32)
+        .value(idx as i32 * 10)
+        .component_of(pump_id(idx))
+        .insert(&mut address_space);
+    });
+
+    let view_id = address_space.add_view("Pumps", "Pumps").unwrap();
+    (2..=4).for_each(|idx| {
+        address_space.add_to_view(&view_id, &pump_id(idx));
+    });
+    view_id
+}
+
+fn node_type(type_definition_id: NodeId, include_sub_types: bool) -> NodeTypeDescription {
+    NodeTypeDescription {
+        type_definition_node: ExpandedNodeId::new(type_definition_id),
+        include_sub_types,
+        data_to_return: Some(vec![
+            QueryDataDescription {
+                relative_path: RelativePath { elements: None },
+                attribute_id: AttributeId::BrowseName as u32,
+                index_range: UAString::null(),
+            },
+            QueryDataDescription {
+                relative_path: RelativePath::from_str(
+                    "/Speed",
+                    &RelativePathElement::default_node_resolver,
+                )
+                .unwrap(),
+                attribute_id: AttributeId::Value as u32,
+                index_range: UAString::null(),
+            },
+        ]),
+    }
+}
+
+fn query_first_request(
+    view_id: NodeId,
+    node_types: Vec<NodeTypeDescription>,
+    filter: ContentFilter,
+    max_data_sets_to_return: u32,
+) -> QueryFirstRequest {
+    QueryFirstRequest {
+        request_header: make_request_header(),
+        view: ViewDescription {
+            view_id,
+            timestamp: DateTime::null(),
+            view_version: 0,
+        },
+        node_types: Some(node_types),
+        filter,
+        max_data_sets_to_return,
+        max_references_to_return: 0,
+    }
+}
+
+fn do_query_service_test<F>(f: F)
+where
+    F: FnOnce(
+        Arc<RwLock<ServerState>>,
+        Arc<RwLock<Session>>,
+        Arc<RwLock<AddressSpace>>,
+        &QueryService,
+    ),
+{
+    let st = ServiceTest::new();
+    f(
+        st.server_state.clone(),
+        st.session.clone(),
+        st.address_space.clone(),
+        &QueryService::new(),
+    );
+}
+
+/// Returns the browse name and speed of each pump in the data sets, sorted by browse name
+fn pumps(query_data_sets: &[QueryDataSet]) -> Vec<(String, Variant)> {
+    let mut pumps = query_data_sets
+        .iter()
+        .map(|d| {
+            let values = d.values.as_ref().unwrap();
+            let browse_name = match &values[0] {
+                Variant::QualifiedName(v) => v.name.as_ref().to_string(),
+                v => panic!("Expected a browse name, got {:?}", v),
+            };
+            (browse_name, values[1].clone())
+        })
+        .collect::<Vec<_>>();
+    pumps.sort_by(|a, b| a.0.cmp(&b.0));
+    pumps
+}
+
+#[test]
+fn query_first() {
+    do_query_service_test(|server_state, session, address_space, qs| {
+        add_pumps_to_address_space(address_space.clone());
+
+        // The pumps of a type without its subtypes
+        let request = query_first_request(
+            NodeId::null(),
+            vec![node_type(pump_type_id(), false)],
+            ContentFilter { elements: None },
+            0,
+        );
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, QueryFirstResponse);
+        assert!(response.continuation_point.is_null());
+        let query_data_sets = response.query_data_sets.unwrap();
+        assert_eq!(
+            pumps(&query_data_sets),
+            vec![
+                ("Pump1".to_string(), Variant::Int32(10)),
+                ("Pump3".to_string(), Variant::Int32(30)),
+            ]
+        );
+        assert!(query_data_sets
+            .iter()
+            .all(|d| d.type_definition_node.node_id == pump_type_id()));
+
+        // The pumps of a type and its subtypes whose speed is over 15
+        let filter = ContentFilterBuilder::new()
+            .gt(
+                Operand::simple_attribute(
+                    pump_type_id(),
+                    "Speed",
+                    AttributeId::Value,
+                    UAString::null(),
+                ),
+                Operand::literal(15),
+            )
+            .build();
+        let request = query_first_request(
+            NodeId::null(),
+            vec![node_type(pump_type_id(), true)],
+            filter,
+            0,
+        );
+        let response = qs.query_first(server_state, session, address_space, &request);
+        let response = supported_message_as!(response, QueryFirstResponse);
+        let query_data_sets = response.query_data_sets.unwrap();
+        assert_eq!(
+            pumps(&query_data_sets),
+            vec![
+                ("Pump2".to_string(), Variant::Int32(20)),
+                ("Pump3".to_string(), Variant::Int32(30)),
+                ("Pump4".to_string(), Variant::Int32(40)),
+            ]
+        );
+    });
+}
+
+#[test]
+fn query_first_view() {
+    do_query_service_test(|server_state, session, address_space, qs| {
+        let view_id = add_pumps_to_address_space(address_space.clone());
+
+        // Only the pumps in the view are found
+        let request = query_first_request(
+            view_id,
+            vec![node_type(pump_type_id(), true)],
+            ContentFilter { elements: None },
+            0,
+        );
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, QueryFirstResponse);
+        let pumps = pumps(&response.query_data_sets.unwrap());
+        assert_eq!(
+            pumps.iter().map(|p| p.0.as_str()).collect::<Vec<_>>(),
+            vec!["Pump2", "Pump3", "Pump4"]
+        );
+
+        // An unknown view is rejected
+        let request = query_first_request(
+            NodeId::new(1, "FakeView"),
+            vec![node_type(pump_type_id(), true)],
+            ContentFilter { elements: None },
+            0,
+        );
+        let response = qs.query_first(server_state, session, address_space, &request);
+        let response = supported_message_as!(response, ServiceFault);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadViewIdUnknown
+        );
+    });
+}
+
+#[test]
+fn query_first_invalid() {
+    do_query_service_test(|server_state, session, address_space, qs| {
+        add_pumps_to_address_space(address_space.clone());
+
+        // Nothing to do
+        let mut request =
+            query_first_request(NodeId::null(), vec![], ContentFilter { elements: None }, 0);
+        request.node_types = None;
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, ServiceFault);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadNothingToDo
+        );
+
+        // A node type which is not a type, and an invalid attribute to return
+        let mut invalid_attribute = node_type(pump_type_id(), false);
+        invalid_attribute.data_to_return.as_mut().unwrap()[0].attribute_id = 999;
+        let request = query_first_request(
+            NodeId::null(),
+            vec![node_type(pump_id(1), false), invalid_attribute],
+            ContentFilter { elements: None },
+            0,
+        );
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, QueryFirstResponse);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadInvalidArgument
+        );
+        assert!(response.query_data_sets.is_none());
+        let parsing_results = response.parsing_results.unwrap();
+        assert_eq!(
+            parsing_results[0].status_code,
+            StatusCode::BadTypeDefinitionInvalid
+        );
+        assert_eq!(parsing_results[1].status_code, StatusCode::Good);
+        assert_eq!(
+            parsing_results[1].data_status_codes,
+            Some(vec![StatusCode::BadAttributeIdInvalid, StatusCode::Good])
+        );
+
+        // A filter with too few operands
+        let request = query_first_request(
+            NodeId::null(),
+            vec![node_type(pump_type_id(), false)],
+            ContentFilter {
+                elements: Some(vec![ContentFilterElement {
+                    filter_operator: FilterOperator::Equals,
+                    filter_operands: Some(vec![]),
+                }]),
+            },
+            0,
+        );
+        let response = qs.query_first(server_state, session, address_space, &request);
+        let response = supported_message_as!(response, QueryFirstResponse);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadContentFilterInvalid
+        );
+        assert_eq!(
+            response.filter_result.element_results.unwrap()[0].status_code,
+            StatusCode::BadFilterOperandCountMismatch
+        );
+    });
+}
+
+#[test]
+fn query_next() {
+    do_query_service_test(|server_state, session, address_space, qs| {
+        add_pumps_to_address_space(address_space.clone());
+
+        // Three of the four pumps are returned at a time
+        let request = query_first_request(
+            NodeId::null(),
+            vec![node_type(pump_type_id(), true)],
+            ContentFilter { elements: None },
+            3,
+        );
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, QueryFirstResponse);
+        let mut query_data_sets = response.query_data_sets.unwrap();
+        assert_eq!(query_data_sets.len(), 3);
+        assert!(!response.continuation_point.is_null());
+
+        let query_next = |continuation_point: &ByteString, release_continuation_point: bool| {
+            let request = QueryNextRequest {
+                request_header: make_request_header(),
+                release_continuation_point,
+                continuation_point: continuation_point.clone(),
+            };
+            qs.query_next(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &request,
+            )
+        };
+
+        let response = query_next(&response.continuation_point, false);
+        let response = supported_message_as!(response, QueryNextResponse);
+        query_data_sets.extend(response.query_data_sets.unwrap());
+        assert!(response.revised_continuation_point.is_null());
+        assert_eq!(
+            pumps(&query_data_sets)
+                .iter()
+                .map(|p| p.0.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Pump1", "Pump2", "Pump3", "Pump4"]
+        );
+
+        // A released continuation point cannot be used again
+        let response = qs.query_first(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let continuation_point =
+            supported_message_as!(response, QueryFirstResponse).continuation_point;
+        let response = query_next(&continuation_point, true);
+        let response = supported_message_as!(response, QueryNextResponse);
+        assert!(response.query_data_sets.is_none());
+        assert!(response.revised_continuation_point.is_null());
+        let response = query_next(&continuation_point, false);
+        let response = supported_message_as!(response, ServiceFault);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadContinuationPointInvalid
+        );
+    });
+}
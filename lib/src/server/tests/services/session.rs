@@ -3,8 +3,11 @@ use crate::types::{ActivateSessionRequest, RequestHeader, SignatureData};
 
 use crate::server::{
     builder::ServerBuilder,
+    config::ANONYMOUS_USER_TOKEN_ID,
     identity_token::{
         POLICY_ID_USER_PASS_NONE, POLICY_ID_USER_PASS_RSA_15, POLICY_ID_USER_PASS_RSA_OAEP,
+        POLICY_ID_USER_PASS_RSA_OAEP_SHA256, POLICY_ID_X509_RSA_PSS_SHA256,
+        POLICY_ID_X509_RSA_SHA256,
     },
     services::session::SessionService,
     state::ServerState,
@@ -302,7 +305,7 @@ fn user_name_pass_token() {
             // Invalid password (encrypted)
             let token = make_encrypted_user_name_identity_token(
                 POLICY_ID_USER_PASS_RSA_OAEP,
-                SecurityPolicy::Basic128Rsa15,
+                SecurityPolicy::Basic256Sha256,
                 &server_nonce,
                 &server_cert,
                 "sample1",
@@ -320,3 +323,272 @@ fn user_name_pass_token() {
         },
     );
 }
+
+#[test]
+fn user_name_pass_token_encrypted_on_unsecured_endpoint() {
+    do_session_service_test(
+        Some("./pki_user_name_pass_token"),
+        |server_state, _session_service| {
+            const ENDPOINT_URL: &str = "opc.tcp://localhost:4855/";
+            let server_nonce = random::byte_string(32);
+            let request = dummy_activate_session_request();
+
+            for (password_security_policy, policy_id) in [
+                (SecurityPolicy::Basic128Rsa15, POLICY_ID_USER_PASS_RSA_15),
+                (SecurityPolicy::Basic256, POLICY_ID_USER_PASS_RSA_OAEP),
+                (SecurityPolicy::Basic256Sha256, POLICY_ID_USER_PASS_RSA_OAEP),
+                (
+                    SecurityPolicy::Aes128Sha256RsaOaep,
+                    POLICY_ID_USER_PASS_RSA_OAEP,
+                ),
+                (
+                    SecurityPolicy::Aes256Sha256RsaPss,
+                    POLICY_ID_USER_PASS_RSA_OAEP_SHA256,
+                ),
+            ] {
+                {
+                    let server_state = server_state.read();
+                    let mut config = server_state.config.write();
+                    config
+                        .endpoints
+                        .get_mut("none")
+                        .unwrap()
+                        .password_security_policy = Some(password_security_policy.to_string());
+                }
+                let server_state = server_state.read();
+                let server_cert = server_state.server_certificate.clone();
+
+                // The unsecured endpoint tells clients to encrypt passwords anyway
+                let endpoints = server_state
+                    .endpoints(&UAString::from(ENDPOINT_URL), &None)
+                    .unwrap();
+                let endpoint = endpoints
+                    .iter()
+                    .find(|e| {
+                        e.security_mode == MessageSecurityMode::None
+                            && !e.endpoint_url.as_ref().ends_with("/noaccess")
+                    })
+                    .unwrap();
+                let user_token_policy = endpoint.find_policy(UserTokenType::UserName).unwrap();
+                assert_eq!(user_token_policy.policy_id.as_ref(), policy_id);
+                assert_eq!(
+                    user_token_policy.security_policy_uri.as_ref(),
+                    password_security_policy.to_uri()
+                );
+
+                // A client encrypts the password with the policy even though the channel has
+                // no security
+                let token = make_user_name_identity_token(
+                    SecurityPolicy::None,
+                    user_token_policy,
+                    server_nonce.as_ref(),
+                    &server_cert,
+                    "sample1",
+                    "sample1pwd",
+                )
+                .unwrap();
+                let token = ExtensionObject::from_encodable(
+                    ObjectId::UserNameIdentityToken_Encoding_DefaultBinary,
+                    &token,
+                );
+                let result = server_state.authenticate_endpoint(
+                    &request,
+                    ENDPOINT_URL,
+                    SecurityPolicy::None,
+                    MessageSecurityMode::None,
+                    &token,
+                    &server_nonce,
+                );
+                assert!(result.is_ok());
+
+                // The password does not decrypt with another nonce, e.g. when it is replayed
+                let result = server_state.authenticate_endpoint(
+                    &request,
+                    ENDPOINT_URL,
+                    SecurityPolicy::None,
+                    MessageSecurityMode::None,
+                    &token,
+                    &random::byte_string(32),
+                );
+                assert_eq!(result.unwrap_err(), StatusCode::BadDecodingError);
+
+                // A password in plain text is rejected
+                let token = UserNameIdentityToken {
+                    policy_id: UAString::from(policy_id),
+                    user_name: UAString::from("sample1"),
+                    password: ByteString::from(b"sample1pwd"),
+                    encryption_algorithm: UAString::null(),
+                };
+                let token = ExtensionObject::from_encodable(
+                    ObjectId::UserNameIdentityToken_Encoding_DefaultBinary,
+                    &token,
+                );
+                let result = server_state.authenticate_endpoint(
+                    &request,
+                    ENDPOINT_URL,
+                    SecurityPolicy::None,
+                    MessageSecurityMode::None,
+                    &token,
+                    &server_nonce,
+                );
+                assert_eq!(result.unwrap_err(), StatusCode::BadIdentityTokenInvalid);
+            }
+        },
+    );
+}
+
+#[test]
+fn user_token_policies_per_endpoint() {
+    do_session_service_test(
+        Some("./pki_user_token_policies_per_endpoint"),
+        |server_state, _session_service| {
+            const ENDPOINT_URL: &str = "opc.tcp://localhost:4855/";
+            let server_nonce = random::byte_string(32);
+            let request = dummy_activate_session_request();
+
+            // Anonymous only on the unsecured endpoint, user name and X509 on a secure one
+            {
+                let server_state = server_state.read();
+                let mut config = server_state.config.write();
+                let endpoint = config.endpoints.get_mut("none").unwrap();
+                endpoint.user_token_ids = [ANONYMOUS_USER_TOKEN_ID.to_string()]
+                    .iter()
+                    .cloned()
+                    .collect();
+                let endpoint = config
+                    .endpoints
+                    .get_mut("basic256sha256_sign_encrypt")
+                    .unwrap();
+                endpoint.user_token_ids = ["sample_password_user", "sample_x509_user"]
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect();
+                endpoint.x509_security_policy =
+                    Some(SecurityPolicy::Aes256Sha256RsaPss.to_string());
+            }
+            let server_state = server_state.read();
+            let server_cert = server_state.server_certificate.clone();
+
+            let endpoints = server_state
+                .endpoints(&UAString::from(ENDPOINT_URL), &None)
+                .unwrap();
+            let find_endpoint = |security_policy: SecurityPolicy, security_mode| {
+                endpoints
+                    .iter()
+                    .find(|e| {
+                        e.security_policy_uri.as_ref() == security_policy.to_uri()
+                            && e.security_mode == security_mode
+                            && !e.endpoint_url.as_ref().ends_with("/noaccess")
+                    })
+                    .unwrap()
+            };
+            let none_endpoint = find_endpoint(SecurityPolicy::None, MessageSecurityMode::None);
+            let token_types = none_endpoint
+                .user_identity_tokens
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|t| t.token_type)
+                .collect::<Vec<_>>();
+            assert_eq!(token_types, vec![UserTokenType::Anonymous]);
+
+            let secure_endpoint = find_endpoint(
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+            );
+            assert!(secure_endpoint
+                .find_policy(UserTokenType::Anonymous)
+                .is_none());
+            let user_name_policy = secure_endpoint
+                .find_policy(UserTokenType::UserName)
+                .unwrap();
+            assert_eq!(
+                user_name_policy.policy_id.as_ref(),
+                POLICY_ID_USER_PASS_RSA_OAEP
+            );
+            let x509_policy = secure_endpoint
+                .find_policy(UserTokenType::Certificate)
+                .unwrap();
+            assert_eq!(
+                x509_policy.policy_id.as_ref(),
+                POLICY_ID_X509_RSA_PSS_SHA256
+            );
+            assert_eq!(
+                x509_policy.security_policy_uri.as_ref(),
+                SecurityPolicy::Aes256Sha256RsaPss.to_uri()
+            );
+            // Other endpoints still sign X509 tokens with their own policy
+            let x509_policy =
+                find_endpoint(SecurityPolicy::Basic256Sha256, MessageSecurityMode::Sign)
+                    .find_policy(UserTokenType::Certificate)
+                    .unwrap();
+            assert_eq!(x509_policy.policy_id.as_ref(), POLICY_ID_X509_RSA_SHA256);
+
+            // Anonymous is rejected on the secure endpoint
+            let token = ExtensionObject::from_encodable(
+                ObjectId::AnonymousIdentityToken_Encoding_DefaultBinary,
+                &AnonymousIdentityToken {
+                    policy_id: UAString::from("anonymous"),
+                },
+            );
+            let result = server_state.authenticate_endpoint(
+                &request,
+                ENDPOINT_URL,
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+                &token,
+                &server_nonce,
+            );
+            assert_eq!(result.unwrap_err(), StatusCode::BadIdentityTokenRejected);
+
+            // A user name is rejected on the unsecured endpoint
+            let token = make_unencrypted_user_name_identity_token("sample1", "sample1pwd");
+            let result = server_state.authenticate_endpoint(
+                &request,
+                ENDPOINT_URL,
+                SecurityPolicy::None,
+                MessageSecurityMode::None,
+                &token,
+                &server_nonce,
+            );
+            assert_eq!(result.unwrap_err(), StatusCode::BadIdentityTokenRejected);
+
+            // A user name token must possess the id of the user name policy
+            let token = make_encrypted_user_name_identity_token(
+                POLICY_ID_X509_RSA_PSS_SHA256,
+                SecurityPolicy::Basic256Sha256,
+                &server_nonce,
+                &server_cert,
+                "sample1",
+                "sample1pwd",
+            );
+            let result = server_state.authenticate_endpoint(
+                &request,
+                ENDPOINT_URL,
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+                &token,
+                &server_nonce,
+            );
+            assert_eq!(result.unwrap_err(), StatusCode::BadIdentityTokenInvalid);
+
+            let token = make_encrypted_user_name_identity_token(
+                user_name_policy.policy_id.as_ref(),
+                SecurityPolicy::Basic256Sha256,
+                &server_nonce,
+                &server_cert,
+                "sample1",
+                "sample1pwd",
+            );
+            let result = server_state.authenticate_endpoint(
+                &request,
+                ENDPOINT_URL,
+                SecurityPolicy::Basic256Sha256,
+                MessageSecurityMode::SignAndEncrypt,
+                &token,
+                &server_nonce,
+            );
+            assert_eq!(result.unwrap(), "sample_password_user");
+        },
+    );
+}
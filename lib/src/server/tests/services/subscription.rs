@@ -3,8 +3,14 @@ use std::ops::Add;
 use chrono::Utc;
 
 use crate::server::{
+    continuation_point::BrowseContinuationPoint,
+    identity_token::IdentityToken,
     prelude::*,
-    services::{monitored_item::MonitoredItemService, subscription::SubscriptionService},
+    services::{
+        monitored_item::MonitoredItemService, session::SessionService,
+        subscription::SubscriptionService,
+    },
+    session::ReapedResources,
     state::ServerState,
     subscriptions::subscription::*,
 };
@@ -378,6 +384,78 @@ fn multiple_publish_response_subscription() {
     })
 }
 
+#[test]
+fn publish_by_subscription_priority() {
+    do_subscription_service_test(|server_state, session, address_space, ss, mis| {
+        // A low priority subscription is created before a high priority one
+        let subscription_ids = [0u8, 200u8]
+            .iter()
+            .map(|priority| {
+                let request = CreateSubscriptionRequest {
+                    priority: *priority,
+                    ..create_subscription_request(0, 0)
+                };
+                let response: CreateSubscriptionResponse = supported_message_as!(
+                    ss.create_subscription(server_state.clone(), session.clone(), &request),
+                    CreateSubscriptionResponse
+                );
+                let subscription_id = response.subscription_id;
+                create_monitored_item(
+                    subscription_id,
+                    VariableId::Server_ServerStatus_StartTime,
+                    server_state.clone(),
+                    session.clone(),
+                    address_space.clone(),
+                    &mis,
+                );
+                let mut session = trace_write_lock!(session);
+                session
+                    .subscriptions_mut()
+                    .get_mut(subscription_id)
+                    .unwrap()
+                    .set_state(SubscriptionState::Normal);
+                subscription_id
+            })
+            .collect::<Vec<u32>>();
+
+        // Both subscriptions have notifications but there is no publish request to send them
+        let now = Utc::now().add(chrono::Duration::seconds(2));
+        {
+            let mut session = trace_write_lock!(session);
+            let address_space = trace_read_lock!(address_space);
+            let _ = session.tick_subscriptions(&now, &address_space, TickReason::TickTimerFired);
+            assert!(session
+                .subscriptions_mut()
+                .publish_response_queue()
+                .is_empty());
+        }
+
+        // Each publish request goes to the subscription with the highest priority that is waiting
+        let publish_subscription_id = |request_id| {
+            let response = ss.async_publish(
+                &now,
+                session.clone(),
+                address_space.clone(),
+                request_id,
+                &publish_request(None),
+            );
+            assert!(response.is_none());
+            let mut session = trace_write_lock!(session);
+            let response = session
+                .subscriptions_mut()
+                .publish_response_queue()
+                .pop_front()
+                .unwrap()
+                .response;
+            let response: PublishResponse = supported_message_as!(response, PublishResponse);
+            assert!(response.notification_message.notification_data.is_some());
+            response.subscription_id
+        };
+        assert_eq!(publish_subscription_id(1001), subscription_ids[1]);
+        assert_eq!(publish_subscription_id(1002), subscription_ids[0]);
+    })
+}
+
 #[test]
 fn acknowledge_unknown_sequence_nr() {
     do_subscription_service_test(|server_state, session, address_space, ss, _mis| {
@@ -458,3 +536,423 @@ fn republish() {
         );
     })
 }
+
+#[test]
+fn cancel_publish_request() {
+    do_subscription_service_test(|server_state, session, address_space, ss, _| {
+        let _ = create_subscription(server_state.clone(), session.clone(), &ss);
+
+        // Queue a publish request
+        let mut request = publish_request(None);
+        request.request_header.request_handle = 1234;
+        let response = ss.async_publish(
+            &Utc::now(),
+            session.clone(),
+            address_space.clone(),
+            1001,
+            &request,
+        );
+        assert!(response.is_none());
+
+        // Cancelling a different request handle cancels nothing
+        let request = CancelRequest {
+            request_header: RequestHeader::dummy(),
+            request_handle: 4321,
+        };
+        let response =
+            SessionService::new().cancel(server_state.clone(), session.clone(), &request);
+        let response: CancelResponse = supported_message_as!(response, CancelResponse);
+        assert_eq!(response.cancel_count, 0);
+
+        // Cancel the queued publish request
+        let request = CancelRequest {
+            request_header: RequestHeader::dummy(),
+            request_handle: 1234,
+        };
+        let response =
+            SessionService::new().cancel(server_state.clone(), session.clone(), &request);
+        let response: CancelResponse = supported_message_as!(response, CancelResponse);
+        assert_eq!(response.cancel_count, 1);
+
+        // The publish request is answered with a fault
+        let mut session = trace_write_lock!(session);
+        assert!(session
+            .subscriptions_mut()
+            .publish_request_queue()
+            .is_empty());
+        let response = session
+            .subscriptions_mut()
+            .publish_response_queue()
+            .pop_back()
+            .unwrap();
+        assert_eq!(response.request_id, 1001);
+        let response: ServiceFault = supported_message_as!(response.response, ServiceFault);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadRequestCancelledByClient
+        );
+    })
+}
+
+/// Makes the session time out by backdating its last service request past its timeout
+fn time_out_session(session: &Arc<RwLock<Session>>, now: &DateTimeUtc) {
+    let mut session = trace_write_lock!(session);
+    session.set_session_timeout(1000f64);
+    session.set_last_service_request_timestamp(*now - chrono::Duration::seconds(2));
+}
+
+fn transfer_subscriptions_request(subscription_ids: Vec<u32>) -> TransferSubscriptionsRequest {
+    TransferSubscriptionsRequest {
+        request_header: RequestHeader::dummy(),
+        subscription_ids: Some(subscription_ids),
+        send_initial_values: true,
+    }
+}
+
+#[test]
+fn reap_timed_out_session() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+    let subscription_id = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+
+    // Queue a publish request and hold a continuation point
+    let response = ss.async_publish(
+        &now,
+        st.session.clone(),
+        st.address_space.clone(),
+        1001,
+        &publish_request(None),
+    );
+    assert!(response.is_none());
+    {
+        let mut session = trace_write_lock!(st.session);
+        session
+            .add_browse_continuation_point(BrowseContinuationPoint {
+                address_space_last_modified: now,
+                max_references_per_node: 1,
+                starting_index: 0,
+                reference_descriptions: Arc::new(Mutex::new(Vec::new())),
+            })
+            .unwrap();
+    }
+
+    // Nothing is reaped while the session is within its timeout
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        assert!(session_manager
+            .reap_sessions(&now, &st.address_space, true)
+            .is_empty());
+    }
+
+    time_out_session(&st.session, &now);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        let reaped = session_manager.reap_sessions(&now, &st.address_space, true);
+        assert_eq!(
+            reaped,
+            ReapedResources {
+                sessions: 1,
+                publish_requests: 1,
+                continuation_points: 1,
+                subscriptions_deleted: 0,
+                subscriptions_parked: 1,
+            }
+        );
+        assert_eq!(session_manager.parked_subscriptions_len(), 1);
+        // The session stays until the next pass so its publish responses can be sent
+        assert_eq!(session_manager.len(), 1);
+    }
+    {
+        let mut session = trace_write_lock!(st.session);
+        assert!(session.is_terminated());
+        assert!(session.subscriptions().is_empty());
+        let response = session
+            .subscriptions_mut()
+            .take_publish_responses()
+            .unwrap()
+            .pop_front()
+            .unwrap();
+        assert_eq!(response.request_id, 1001);
+        let response: ServiceFault = supported_message_as!(response.response, ServiceFault);
+        assert_eq!(
+            response.response_header.service_result,
+            StatusCode::BadSessionClosed
+        );
+    }
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        assert!(session_manager
+            .reap_sessions(&now, &st.address_space, true)
+            .is_empty());
+        assert_eq!(session_manager.len(), 0);
+    }
+
+    // Another session of the same user transfers the parked subscription
+    let session = Arc::new(RwLock::new(Session::new(st.server_state.clone())));
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        session_manager.register_session(session.clone());
+    }
+    let request = transfer_subscriptions_request(vec![subscription_id, subscription_id + 100]);
+    let response: TransferSubscriptionsResponse = supported_message_as!(
+        ss.transfer_subscriptions(&now, st.session_manager.clone(), session.clone(), &request),
+        TransferSubscriptionsResponse
+    );
+    let results = response.results.unwrap();
+    assert_eq!(results[0].status_code, StatusCode::Good);
+    assert_eq!(results[1].status_code, StatusCode::BadSubscriptionIdInvalid);
+    assert!(trace_read_lock!(session)
+        .subscriptions()
+        .contains(subscription_id));
+    assert_eq!(
+        trace_read_lock!(st.session_manager).parked_subscriptions_len(),
+        0
+    );
+}
+
+#[test]
+fn reap_orphaned_subscriptions() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+
+    // Subscriptions are deleted instead of parked when configured to
+    let _ = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+    time_out_session(&st.session, &now);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        let reaped = session_manager.reap_sessions(&now, &st.address_space, false);
+        assert_eq!(reaped.sessions, 1);
+        assert_eq!(reaped.subscriptions_deleted, 1);
+        assert_eq!(reaped.subscriptions_parked, 0);
+        assert_eq!(session_manager.parked_subscriptions_len(), 0);
+    }
+
+    // Parked subscriptions can only be transferred by the same user
+    let session = Arc::new(RwLock::new(Session::new(st.server_state.clone())));
+    let subscription_id = create_subscription(st.server_state.clone(), session.clone(), &ss);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        session_manager.register_session(session.clone());
+    }
+    time_out_session(&session, &now);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        let reaped = session_manager.reap_sessions(&now, &st.address_space, true);
+        assert_eq!(reaped.subscriptions_parked, 1);
+    }
+    let session = Arc::new(RwLock::new(Session::new(st.server_state.clone())));
+    {
+        let mut session = trace_write_lock!(session);
+        session.set_user_identity(IdentityToken::UserNameIdentityToken(
+            UserNameIdentityToken {
+                policy_id: UAString::from("userpass"),
+                user_name: UAString::from("sample1"),
+                password: ByteString::null(),
+                encryption_algorithm: UAString::null(),
+            },
+        ));
+    }
+    let request = transfer_subscriptions_request(vec![subscription_id]);
+    let response: TransferSubscriptionsResponse = supported_message_as!(
+        ss.transfer_subscriptions(&now, st.session_manager.clone(), session, &request),
+        TransferSubscriptionsResponse
+    );
+    assert_eq!(
+        response.results.unwrap()[0].status_code,
+        StatusCode::BadUserAccessDenied
+    );
+
+    // A parked subscription is deleted when its lifetime ends
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        let reaped = session_manager.reap_sessions(
+            &(now + chrono::Duration::days(1)),
+            &st.address_space,
+            true,
+        );
+        assert_eq!(reaped.subscriptions_deleted, 1);
+        assert_eq!(session_manager.parked_subscriptions_len(), 0);
+    }
+}
+
+#[test]
+fn reap_durable_subscription() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+
+    // A durable subscription is parked for its lifetime even when orphaned subscriptions are
+    // deleted
+    let _ = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+    let subscription_id = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+    {
+        let mut session = trace_write_lock!(st.session);
+        let subscription = session
+            .subscriptions_mut()
+            .get_mut(subscription_id)
+            .unwrap();
+        subscription.set_durable(2).unwrap();
+        assert!(subscription.is_durable());
+    }
+    time_out_session(&st.session, &now);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        let reaped = session_manager.reap_sessions(&now, &st.address_space, false);
+        assert_eq!(reaped.subscriptions_deleted, 1);
+        assert_eq!(reaped.subscriptions_parked, 1);
+
+        let reaped = session_manager.reap_sessions(
+            &(now + chrono::Duration::hours(1)),
+            &st.address_space,
+            false,
+        );
+        assert_eq!(reaped.subscriptions_deleted, 0);
+        assert_eq!(session_manager.parked_subscriptions_len(), 1);
+
+        let reaped = session_manager.reap_sessions(
+            &(now + chrono::Duration::hours(3)),
+            &st.address_space,
+            false,
+        );
+        assert_eq!(reaped.subscriptions_deleted, 1);
+        assert_eq!(session_manager.parked_subscriptions_len(), 0);
+    }
+}
+
+/// Sends a publish request on the session and returns the status of the status change
+/// notification in the response.
+fn publish_status_change(
+    now: &DateTimeUtc,
+    st: &ServiceTest,
+    session: Arc<RwLock<Session>>,
+    ss: &SubscriptionService,
+) -> (u32, StatusCode) {
+    let response = ss.async_publish(
+        now,
+        session.clone(),
+        st.address_space.clone(),
+        1001,
+        &publish_request(None),
+    );
+    assert!(response.is_none());
+    let mut session = trace_write_lock!(session);
+    let response = session
+        .subscriptions_mut()
+        .publish_response_queue()
+        .pop_front()
+        .unwrap()
+        .response;
+    let response: PublishResponse = supported_message_as!(response, PublishResponse);
+    let status_change = response
+        .notification_message
+        .status_change_notification(&DecodingOptions::test())
+        .unwrap();
+    (response.subscription_id, status_change.status)
+}
+
+#[test]
+fn transfer_live_subscription() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+
+    let subscription_id = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+
+    // Another session of the same user transfers the subscription from the live session
+    let session = Arc::new(RwLock::new(Session::new(st.server_state.clone())));
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        session_manager.register_session(session.clone());
+    }
+    let request = transfer_subscriptions_request(vec![subscription_id]);
+    let response: TransferSubscriptionsResponse = supported_message_as!(
+        ss.transfer_subscriptions(&now, st.session_manager.clone(), session.clone(), &request),
+        TransferSubscriptionsResponse
+    );
+    assert_eq!(response.results.unwrap()[0].status_code, StatusCode::Good);
+    assert!(trace_read_lock!(session)
+        .subscriptions()
+        .contains(subscription_id));
+    assert!(trace_read_lock!(st.session).subscriptions().is_empty());
+
+    // The old session is told that the subscription was transferred
+    assert_eq!(
+        publish_status_change(&now, &st, st.session.clone(), &ss),
+        (subscription_id, StatusCode::GoodSubscriptionTransferred)
+    );
+
+    // After that the old session has nothing left to publish
+    let response: ServiceFault = supported_message_as!(
+        ss.async_publish(
+            &now,
+            st.session.clone(),
+            st.address_space.clone(),
+            1002,
+            &publish_request(None),
+        )
+        .unwrap(),
+        ServiceFault
+    );
+    assert_eq!(
+        response.response_header.service_result,
+        StatusCode::BadNoSubscription
+    );
+}
+
+#[test]
+fn transfer_subscription_to_full_session() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+
+    let subscription_id = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+
+    // The other session already has as many subscriptions as it is allowed to
+    {
+        let mut server_state = trace_write_lock!(st.server_state);
+        server_state.max_subscriptions = 1;
+    }
+    let session = Arc::new(RwLock::new(Session::new(st.server_state.clone())));
+    let _ = create_subscription(st.server_state.clone(), session.clone(), &ss);
+    {
+        let mut session_manager = trace_write_lock!(st.session_manager);
+        session_manager.register_session(session.clone());
+    }
+    let request = transfer_subscriptions_request(vec![subscription_id]);
+    let response: TransferSubscriptionsResponse = supported_message_as!(
+        ss.transfer_subscriptions(&now, st.session_manager.clone(), session.clone(), &request),
+        TransferSubscriptionsResponse
+    );
+    assert_eq!(
+        response.results.unwrap()[0].status_code,
+        StatusCode::BadTooManySubscriptions
+    );
+    assert_eq!(trace_read_lock!(session).subscriptions().len(), 1);
+    assert!(trace_read_lock!(st.session)
+        .subscriptions()
+        .contains(subscription_id));
+}
+
+#[test]
+fn close_subscriptions_on_shutdown() {
+    let st = ServiceTest::new();
+    let ss = SubscriptionService::new();
+    let now = Utc::now();
+
+    let subscription_id = create_subscription(st.server_state.clone(), st.session.clone(), &ss);
+    {
+        let mut session = trace_write_lock!(st.session);
+        session
+            .subscriptions_mut()
+            .close_all(&now, StatusCode::BadShutdown);
+    }
+
+    // The subscription is removed once its status change has been published
+    assert_eq!(
+        publish_status_change(&now, &st, st.session.clone(), &ss),
+        (subscription_id, StatusCode::BadShutdown)
+    );
+    assert!(trace_read_lock!(st.session).subscriptions().is_empty());
+}
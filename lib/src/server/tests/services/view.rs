@@ -159,7 +159,7 @@ fn browse() {
     });
 }
 
-// Test the response of supplying an unsupported view to the browse request
+// Test the response of supplying an unknown view to the browse request
 #[test]
 fn browse_non_null_view() {
     do_view_service_test(|server_state, session, address_space, vs| {
@@ -198,6 +198,268 @@ fn browse_non_null_view() {
     });
 }
 
+// Test browsing within a view, which only sees the nodes in the view
+#[test]
+fn browse_view() {
+    do_view_service_test(|server_state, session, address_space, vs| {
+        let (view_id, folder_id, other_folder_id) = {
+            let mut address_space = trace_write_lock!(address_space);
+            let folder_id = address_space
+                .add_folder("InView", "InView", &NodeId::objects_folder_id())
+                .unwrap();
+            let other_folder_id = address_space
+                .add_folder("NotInView", "NotInView", &NodeId::objects_folder_id())
+                .unwrap();
+            address_space.add_variables(
+                vec![
+                    Variable::new(&NodeId::new(1, "v1"), "v1", "v1", 1i32),
+                    Variable::new(&NodeId::new(1, "v2"), "v2", "v2", 2i32),
+                ],
+                &folder_id,
+            );
+            address_space.add_variables(
+                vec![Variable::new(&NodeId::new(1, "v3"), "v3", "v3", 3i32)],
+                &other_folder_id,
+            );
+            let view_id = address_space.add_view("View", "View").unwrap();
+            assert_eq!(address_space.view_version(&view_id), Some(0));
+            assert!(address_space.add_to_view(&view_id, &folder_id));
+            assert_eq!(address_space.view_version(&view_id), Some(1));
+            assert!(!address_space.add_to_view(&folder_id, &other_folder_id));
+            (view_id, folder_id, other_folder_id)
+        };
+
+        let browse = |nodes: &[NodeId], view: ViewDescription| {
+            let mut request = make_browse_request(
+                nodes,
+                NodeClassMask::empty(),
+                1000,
+                BrowseDirection::Forward,
+                ReferenceTypeId::Organizes,
+            );
+            request.view = view;
+            vs.browse(
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &request,
+            )
+        };
+        let view = |view_version: u32, timestamp: DateTime| ViewDescription {
+            view_id: view_id.clone(),
+            timestamp,
+            view_version,
+        };
+
+        let response = browse(
+            &[
+                view_id.clone(),
+                folder_id.clone(),
+                other_folder_id.clone(),
+                ObjectId::ObjectsFolder.into(),
+            ],
+            view(0, DateTime::null()),
+        );
+        let results = supported_message_as!(response, BrowseResponse)
+            .results
+            .unwrap();
+        let browse_names = |result: &BrowseResult| {
+            result
+                .references
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|r| r.browse_name.name.as_ref().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(browse_names(&results[0]), vec!["InView"]);
+        assert_eq!(browse_names(&results[1]), vec!["v1", "v2"]);
+        assert_eq!(results[2].status_code, StatusCode::BadNodeNotInView);
+        assert_eq!(results[3].status_code, StatusCode::BadNodeNotInView);
+
+        // The current version of the view may be given, but not a timestamp
+        let response = browse(std::slice::from_ref(&folder_id), view(1, DateTime::null()));
+        let results = supported_message_as!(response, BrowseResponse)
+            .results
+            .unwrap();
+        assert_eq!(results[0].status_code, StatusCode::Good);
+        for (view, status_code) in [
+            (view(2, DateTime::null()), StatusCode::BadViewVersionInvalid),
+            (
+                view(0, DateTime::now()),
+                StatusCode::BadViewTimestampInvalid,
+            ),
+            (
+                view(1, DateTime::now()),
+                StatusCode::BadViewParameterMismatch,
+            ),
+        ] {
+            let response = browse(std::slice::from_ref(&folder_id), view);
+            let response = supported_message_as!(response, ServiceFault);
+            assert_eq!(response.response_header.service_result, status_code);
+        }
+
+        // Removing the folder from the view takes its variables with it
+        {
+            let mut address_space = trace_write_lock!(address_space);
+            assert!(address_space.remove_from_view(&view_id, &folder_id));
+            assert!(!address_space.remove_from_view(&view_id, &folder_id));
+            assert_eq!(address_space.view_version(&view_id), Some(2));
+        }
+        let response = browse(std::slice::from_ref(&folder_id), view(0, DateTime::null()));
+        let results = supported_message_as!(response, BrowseResponse)
+            .results
+            .unwrap();
+        assert_eq!(results[0].status_code, StatusCode::BadNodeNotInView);
+    });
+}
+
+// This test applies a result mask to the browse so only the fields in the mask should come back
+#[test]
+fn browse_result_mask() {
+    do_view_service_test(|server_state, session, address_space, vs| {
+        let nodes: Vec<NodeId> = vec![ObjectId::Server.into()];
+        let mut request = make_browse_request(
+            &nodes,
+            NodeClassMask::OBJECT,
+            1000,
+            BrowseDirection::Forward,
+            ReferenceTypeId::HasComponent,
+        );
+        request.nodes_to_browse.as_mut().unwrap()[0].result_mask =
+            BrowseDescriptionResultMask::RESULT_MASK_TYPE_DEFINITION.bits();
+
+        let response = vs.browse(server_state, session, address_space, &request);
+        let response = supported_message_as!(response, BrowseResponse);
+        let results = response.results.unwrap();
+        let references = results[0].references.as_ref().unwrap();
+
+        // The type definition is resolved even though the node class was not asked for
+        assert!(!references.is_empty());
+        references.iter().for_each(|r| {
+            assert!(!r.node_id.is_null());
+            assert!(!r.type_definition.is_null());
+            assert!(r.reference_type_id.is_null());
+            assert!(!r.is_forward);
+            assert_eq!(r.node_class, NodeClass::Unspecified);
+            assert!(r.browse_name.is_null());
+            assert_eq!(r.display_name, LocalizedText::null());
+        });
+        let server_status = references
+            .iter()
+            .find(|r| r.node_id.node_id == VariableId::Server_ServerStatus.into());
+        assert!(server_status.is_none());
+        let capabilities = references
+            .iter()
+            .find(|r| r.node_id.node_id == ObjectId::Server_ServerCapabilities.into())
+            .unwrap();
+        assert_eq!(
+            capabilities.type_definition.node_id,
+            ObjectTypeId::ServerCapabilitiesType.into()
+        );
+    });
+}
+
+// This test browses with reference types that are not standard or do not exist
+#[test]
+fn browse_reference_type() {
+    do_view_service_test(|server_state, session, address_space, vs| {
+        let reference_type_id = NodeId::new(1, "HasWidget");
+        let widget_id = NodeId::new(1, "Widget");
+        {
+            let mut address_space = address_space.write();
+            ReferenceTypeBuilder::new(&reference_type_id, "HasWidget", "HasWidget")
+                .subtype_of(ReferenceTypeId::HasComponent)
+                .insert(&mut address_space);
+            ObjectBuilder::new(&widget_id, "Widget", "Widget")
+                .organized_by(ObjectId::ObjectsFolder)
+                .insert(&mut address_space);
+            address_space.insert_reference(
+                &ObjectId::Server.into(),
+                &widget_id,
+                reference_type_id.clone(),
+            );
+        }
+
+        // A custom reference type is honoured, as are its supertypes
+        let nodes: Vec<NodeId> = vec![ObjectId::Server.into()];
+        let request = make_browse_request(
+            &nodes,
+            NodeClassMask::empty(),
+            1000,
+            BrowseDirection::Forward,
+            reference_type_id.clone(),
+        );
+        let response = vs.browse(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, BrowseResponse);
+        let results = response.results.unwrap();
+        let references = results[0].references.as_ref().unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].node_id.node_id, widget_id);
+        assert_eq!(references[0].reference_type_id, reference_type_id);
+
+        let request = make_browse_request(
+            &nodes,
+            NodeClassMask::empty(),
+            1000,
+            BrowseDirection::Forward,
+            ReferenceTypeId::HasComponent,
+        );
+        let response = vs.browse(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, BrowseResponse);
+        let results = response.results.unwrap();
+        let references = results[0].references.as_ref().unwrap();
+        assert!(references.iter().any(|r| r.node_id.node_id == widget_id));
+
+        // A reference type that is not a reference type is an error
+        let request = make_browse_request(
+            &nodes,
+            NodeClassMask::empty(),
+            1000,
+            BrowseDirection::Forward,
+            ObjectId::ObjectsFolder,
+        );
+        let response = vs.browse(
+            server_state.clone(),
+            session.clone(),
+            address_space.clone(),
+            &request,
+        );
+        let response = supported_message_as!(response, BrowseResponse);
+        let results = response.results.unwrap();
+        assert_eq!(
+            results[0].status_code,
+            StatusCode::BadReferenceTypeIdInvalid
+        );
+
+        // So is an invalid browse direction
+        let request = make_browse_request(
+            &nodes,
+            NodeClassMask::empty(),
+            1000,
+            BrowseDirection::Invalid,
+            ReferenceTypeId::HasComponent,
+        );
+        let response = vs.browse(server_state, session, address_space, &request);
+        let response = supported_message_as!(response, BrowseResponse);
+        let results = response.results.unwrap();
+        assert_eq!(
+            results[0].status_code,
+            StatusCode::BadBrowseDirectionInvalid
+        );
+    });
+}
+
 // This test applies a class mask to the browse so only nodes of types in the mask should come back
 #[test]
 fn browse_node_class_mask() {
@@ -731,7 +993,8 @@ fn browse_next_release_cp() {
         let _references = r1.references.as_ref().unwrap();
         assert!(!r1.continuation_point.is_null());
 
-        // Browse next and release the previous continuation points, expect Null result
+        // Browse next and release the previous continuation points, expect a result with no
+        // references
         let response = do_browse_next(
             &vs,
             session.clone(),
@@ -739,7 +1002,25 @@ fn browse_next_release_cp() {
             &r1.continuation_point,
             true,
         );
-        assert!(response.results.is_none());
+        let results = response.results.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status_code, StatusCode::Good);
+        assert!(results[0].continuation_point.is_null());
+        assert!(results[0].references.is_none());
+
+        // Releasing it again is invalid
+        let response = do_browse_next(
+            vs,
+            session.clone(),
+            address_space.clone(),
+            &r1.continuation_point,
+            true,
+        );
+        let results = response.results.unwrap();
+        assert_eq!(
+            results[0].status_code,
+            StatusCode::BadContinuationPointInvalid
+        );
 
         // Browse next again with same continuation point, expect BadContinuationPointInvalid
         let response = do_browse_next(
@@ -754,6 +1035,56 @@ fn browse_next_release_cp() {
     });
 }
 
+#[test]
+fn browse_no_continuation_points() {
+    // Browse until the session holds as many continuation points as it may
+    do_view_service_test(|server_state, session, address_space, vs| {
+        let parent_node_id = add_many_vars_to_address_space(address_space.clone(), 10).0;
+        let nodes = vec![parent_node_id.clone()];
+        let browse = || {
+            let response = do_browse(
+                vs,
+                server_state.clone(),
+                session.clone(),
+                address_space.clone(),
+                &nodes,
+                5,
+                BrowseDirection::Forward,
+            );
+            response.results.unwrap().remove(0)
+        };
+        let continuation_points = (0..crate::server::constants::MAX_BROWSE_CONTINUATION_POINTS)
+            .map(|_| {
+                let r = browse();
+                assert_eq!(r.status_code, StatusCode::Good);
+                assert!(!r.continuation_point.is_null());
+                r.continuation_point
+            })
+            .collect::<Vec<_>>();
+
+        // The next browse fails rather than evicting a continuation point the client holds
+        let r = browse();
+        assert_eq!(r.status_code, StatusCode::BadNoContinuationPoints);
+        assert!(r.continuation_point.is_null());
+        assert!(r.references.is_none());
+
+        // Finishing a browse frees its continuation point for another
+        let response = do_browse_next(
+            vs,
+            session.clone(),
+            address_space.clone(),
+            &continuation_points[0],
+            false,
+        );
+        let r1 = &response.results.unwrap()[0];
+        assert_eq!(r1.status_code, StatusCode::Good);
+        assert!(r1.continuation_point.is_null());
+        let r = browse();
+        assert_eq!(r.status_code, StatusCode::Good);
+        assert!(!r.continuation_point.is_null());
+    });
+}
+
 #[test]
 fn browse_next_multiple_cps() {
     // Browse multiple times with multiple continuation points
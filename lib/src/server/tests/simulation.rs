@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use crate::server::simulation::*;
+use crate::types::{NodeId, Variant};
+
+fn secs(secs: f64) -> Duration {
+    Duration::from_secs_f64(secs)
+}
+
+fn assert_near(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} is not near {}", a, b);
+}
+
+#[test]
+fn sine() {
+    let sine = Sine::new(2.0, 4.0).offset(10.0);
+    assert_near(sine.value_at(secs(0.0)), 10.0);
+    assert_near(sine.value_at(secs(1.0)), 12.0);
+    assert_near(sine.value_at(secs(3.0)), 8.0);
+    // The wave repeats every period
+    assert_near(sine.value_at(secs(5.0)), 12.0);
+
+    let mut sine = Sine::new(1.0, 4.0).phase(std::f64::consts::FRAC_PI_2);
+    assert_eq!(sine.next_value(secs(0.0)), Variant::Double(1.0));
+}
+
+#[test]
+fn ramp() {
+    let ramp = Ramp::new(-10.0, 10.0, 10.0);
+    assert_near(ramp.value_at(secs(0.0)), -10.0);
+    assert_near(ramp.value_at(secs(2.5)), -5.0);
+    assert_near(ramp.value_at(secs(7.5)), 5.0);
+    assert_near(ramp.value_at(secs(10.0)), -10.0);
+}
+
+#[test]
+fn square() {
+    let square = Square::new(0.0, 1.0, 2.0);
+    assert_eq!(square.value_at(secs(0.0)), 1.0);
+    assert_eq!(square.value_at(secs(0.9)), 1.0);
+    assert_eq!(square.value_at(secs(1.0)), 0.0);
+    assert_eq!(square.value_at(secs(2.5)), 1.0);
+    // A period of 0 is just high
+    assert_eq!(Square::new(0.0, 1.0, 0.0).value_at(secs(1.0)), 1.0);
+}
+
+#[test]
+fn random_walk() {
+    let mut walk = RandomWalk::with_seed(5.0, 1.0, 1234).limits(0.0, 10.0);
+    let mut last = 5.0;
+    (0..1000).for_each(|_| {
+        let value = walk.step();
+        assert!((value - last).abs() <= 1.0);
+        assert!((0.0..=10.0).contains(&value));
+        last = value;
+    });
+
+    // The same seed makes the same walk
+    let mut walk1 = RandomWalk::with_seed(0.0, 1.0, 99);
+    let mut walk2 = RandomWalk::with_seed(0.0, 1.0, 99);
+    (0..10).for_each(|_| assert_eq!(walk1.step(), walk2.step()));
+
+    // The start is kept within the limits
+    let mut walk = RandomWalk::with_seed(50.0, 0.0, 1).limits(0.0, 10.0);
+    assert_eq!(walk.step(), 10.0);
+}
+
+#[test]
+fn csv_playback() {
+    let csv = "time,flow,pressure\n0,1.5,100\n1,2.5,bad\n2,3.5,102\n";
+    let mut flow = CsvPlayback::from_reader(csv.as_bytes(), 1).unwrap();
+    assert_eq!(flow.next_sample(), Some(1.5));
+    assert_eq!(flow.next_sample(), Some(2.5));
+    assert_eq!(flow.next_value(secs(0.0)), Variant::Double(3.5));
+    // Playback starts again from the first row
+    assert_eq!(flow.next_sample(), Some(1.5));
+
+    // Rows that are not numbers are skipped
+    let mut pressure = CsvPlayback::from_reader(csv.as_bytes(), 2).unwrap();
+    assert_eq!(pressure.next_sample(), Some(100.0));
+    assert_eq!(pressure.next_sample(), Some(102.0));
+
+    // A column without numbers is an error
+    assert!(CsvPlayback::from_reader(csv.as_bytes(), 3).is_err());
+    assert!(CsvPlayback::from_file("no_such_file.csv", 0).is_err());
+
+    assert_eq!(CsvPlayback::new(Vec::new()).next_sample(), None);
+}
+
+#[test]
+fn simulation_variables() {
+    let mut simulation = Simulation::new();
+    assert!(simulation.is_empty());
+    simulation
+        .add(NodeId::new(2, "sine"), 100, Sine::new(1.0, 1.0))
+        .add(NodeId::new(2, "walk"), 100, RandomWalk::new(0.0, 1.0));
+    assert_eq!(simulation.len(), 2);
+}
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
+use chrono::Utc;
+
 use crate::server::{
+    address_space::AddressSpace,
     diagnostics::ServerDiagnostics,
     subscriptions::subscription::{
         HandledState, Subscription, SubscriptionState, SubscriptionStateParams, TickReason,
@@ -8,6 +11,7 @@ use crate::server::{
     },
 };
 use crate::sync::*;
+use crate::types::{service_types::StatusChangeNotification, status_code::StatusCode, *};
 
 const DEFAULT_LIFETIME_COUNT: u32 = 300;
 const DEFAULT_KEEPALIVE_COUNT: u32 = 100;
@@ -22,6 +26,7 @@ fn make_subscription(state: SubscriptionState) -> Subscription {
         DEFAULT_LIFETIME_COUNT,
         DEFAULT_KEEPALIVE_COUNT,
         0,
+        0,
     );
     result.set_state(state);
     result
@@ -37,6 +42,7 @@ fn basic_subscription() {
         DEFAULT_LIFETIME_COUNT,
         DEFAULT_KEEPALIVE_COUNT,
         0,
+        0,
     );
     assert_eq!(s.state(), SubscriptionState::Creating);
 }
@@ -401,6 +407,8 @@ fn update_state_14() {
         UpdateStateAction::ReturnNotifications
     );
     assert_eq!(s.state(), SubscriptionState::Normal);
+    // The lifetime counter is reset and the publishing timer restarted
+    assert_eq!(s.lifetime_counter(), DEFAULT_LIFETIME_COUNT - 1);
 }
 
 #[test]
@@ -483,22 +491,18 @@ fn update_state_17() {
 #[test]
 fn update_state_27() {
     // Test #27
-    // Queue a publish request, publishing on, more notifications, lifetime of 1
+    // Publishing timer expires without a publish request, publishing on, lifetime of 1
 
     // Ensure subscription is closed, update action to close expired subscription
 
     let mut s = make_subscription(SubscriptionState::Normal);
 
-    // queue publish request
-    // set publish enabled true
-    // set more notifications true
-
-    let tick_reason = TickReason::ReceivePublishRequest;
+    let tick_reason = TickReason::TickTimerFired;
     let p = SubscriptionStateParams {
         notifications_available: true,
         more_notifications: true,
-        publishing_req_queued: true,
-        publishing_timer_expired: false,
+        publishing_req_queued: false,
+        publishing_timer_expired: true,
     };
 
     s.set_publishing_enabled(true);
@@ -515,3 +519,116 @@ fn update_state_27() {
     assert_eq!(s.lifetime_counter(), 1);
     assert_eq!(s.message_sent(), false);
 }
+
+#[test]
+fn update_state_10_no_publish_request() {
+    // A publish request was received but another subscription took it, so the subscription stays
+    // late
+    let mut s = make_subscription(SubscriptionState::Late);
+
+    let tick_reason = TickReason::ReceivePublishRequest;
+    let p = SubscriptionStateParams {
+        notifications_available: true,
+        more_notifications: false,
+        publishing_req_queued: false,
+        publishing_timer_expired: false,
+    };
+
+    s.set_publishing_enabled(true);
+
+    let update_state_result = s.update_state(tick_reason, p);
+
+    assert_eq!(update_state_result.handled_state, HandledState::None0);
+    assert_eq!(
+        update_state_result.update_state_action,
+        UpdateStateAction::None
+    );
+    assert_eq!(s.state(), SubscriptionState::Late);
+}
+
+#[test]
+fn update_state_15_publishing_enabled() {
+    // A keep alive is sent when publishing is enabled and there is nothing to send, and the
+    // publish request it is sent with counts as client activity
+    let mut s = make_subscription(SubscriptionState::KeepAlive);
+
+    let tick_reason = TickReason::TickTimerFired;
+    let p = SubscriptionStateParams {
+        notifications_available: false,
+        more_notifications: false,
+        publishing_req_queued: true,
+        publishing_timer_expired: true,
+    };
+
+    s.set_keep_alive_counter(1);
+    s.set_current_lifetime_count(2);
+    s.set_publishing_enabled(true);
+
+    let update_state_result = s.update_state(tick_reason, p);
+
+    assert_eq!(update_state_result.handled_state, HandledState::KeepAlive15);
+    assert_eq!(
+        update_state_result.update_state_action,
+        UpdateStateAction::ReturnKeepAlive
+    );
+    assert_eq!(s.state(), SubscriptionState::KeepAlive);
+    assert_eq!(s.keep_alive_counter(), s.max_keep_alive_count());
+    assert_eq!(s.lifetime_counter(), DEFAULT_LIFETIME_COUNT - 1);
+}
+
+#[test]
+fn update_state_27_publish_request() {
+    // A publish request that arrives when the lifetime is about to expire keeps the subscription
+    // alive
+    let mut s = make_subscription(SubscriptionState::Normal);
+
+    let tick_reason = TickReason::ReceivePublishRequest;
+    let p = SubscriptionStateParams {
+        notifications_available: true,
+        more_notifications: true,
+        publishing_req_queued: true,
+        publishing_timer_expired: false,
+    };
+
+    s.set_publishing_enabled(true);
+    s.set_current_lifetime_count(1);
+
+    let update_state_result = s.update_state(tick_reason, p);
+
+    assert_eq!(update_state_result.handled_state, HandledState::Normal5);
+    assert_eq!(s.state(), SubscriptionState::Normal);
+    assert_eq!(s.lifetime_counter(), DEFAULT_LIFETIME_COUNT);
+}
+
+#[test]
+fn keep_alive_and_lifetime_expiry() {
+    let address_space = AddressSpace::new();
+    let mut s = make_subscription(SubscriptionState::Normal);
+    s.set_publishing_enabled(true);
+
+    // The first publishing interval with nothing to send returns a keep alive
+    let now = Utc::now() + chrono::Duration::seconds(2);
+    s.tick(&now, &address_space, TickReason::TickTimerFired, true);
+    let keep_alive = s.take_notification().unwrap();
+    assert_eq!(keep_alive.sequence_number, 1);
+    assert!(keep_alive.notification_data.is_none());
+    assert!(s.take_notification().is_none());
+
+    // The lifetime expires because no publish request arrives. The status change gets the
+    // sequence number that the keep alive announced.
+    s.set_current_lifetime_count(1);
+    let now = now + chrono::Duration::seconds(2);
+    s.tick(&now, &address_space, TickReason::TickTimerFired, false);
+    assert_eq!(s.state(), SubscriptionState::Closed);
+    assert!(!s.ready_to_remove());
+
+    let status_change = s.take_notification().unwrap();
+    assert_eq!(status_change.sequence_number, 1);
+    let notification_data = status_change.notification_data.unwrap();
+    assert_eq!(notification_data.len(), 1);
+    let status_change = notification_data[0]
+        .decode_inner::<StatusChangeNotification>(&DecodingOptions::test())
+        .unwrap();
+    assert_eq!(status_change.status, StatusCode::BadTimeout);
+    assert!(s.ready_to_remove());
+}
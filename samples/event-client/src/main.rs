@@ -108,20 +108,24 @@ fn subscribe_to_events(
 
     let event_fields: Vec<String> = event_fields.split(',').map(|s| s.into()).collect();
 
+    // Select the requested fields of every event
+    let event_filter = event_fields
+        .iter()
+        .fold(EventFilterBuilder::new(), |builder, event_field| {
+            builder.select(ObjectTypeId::BaseEventType, event_field)
+        })
+        .build();
+
     let event_callback = {
         let event_fields = event_fields.clone();
-        EventCallback::new(move |events| {
+        EventFieldsCallback::new(&event_filter, move |_client_handle, fields| {
             // Handle events
             println!("Event from server:");
-            if let Some(ref events) = events.events {
-                events.iter().for_each(|e| {
-                    if let Some(ref event_values) = e.event_fields {
-                        event_values.iter().enumerate().for_each(|(idx, field)| {
-                            println!("  {}: {}", event_fields[idx], field);
-                        });
-                    }
-                });
-            }
+            event_fields.iter().for_each(|event_field| {
+                if let Some(field) = fields.get(event_field) {
+                    println!("  {}: {}", event_field, field);
+                }
+            });
         })
     };
 
@@ -138,27 +142,6 @@ fn subscribe_to_events(
         event_source
     );
 
-    // The where clause is looking for events that are change events
-    let where_clause = ContentFilter { elements: None };
-
-    // Select clauses
-    let select_clauses = Some(
-        event_fields
-            .iter()
-            .map(|s| SimpleAttributeOperand {
-                type_definition_id: ObjectTypeId::BaseEventType.into(),
-                browse_path: Some(vec![QualifiedName::from(s)]),
-                attribute_id: AttributeId::Value as u32,
-                index_range: UAString::null(),
-            })
-            .collect(),
-    );
-
-    let event_filter = EventFilter {
-        where_clause,
-        select_clauses,
-    };
-
     let mut item_to_create: MonitoredItemCreateRequest = event_source.into();
     item_to_create.item_to_monitor.attribute_id = AttributeId::EventNotifier as u32;
     item_to_create.requested_parameters.sampling_interval = 100.0;
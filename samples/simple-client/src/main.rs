@@ -126,7 +126,7 @@ fn print_value(item: &MonitoredItem) {
         println!(
             "Item \"{}\", Value not found, error: {}",
             node_id,
-            data_value.status.as_ref().unwrap()
+            data_value.status()
         );
     }
 }
@@ -0,0 +1,95 @@
+use opcua::core::comms::capture::{replay, CaptureDirection, CaptureReader, CaptureRecord};
+use opcua::types::DecodingOptions;
+
+fn main() {
+    let args = match Args::parse_args() {
+        Ok(args) if !args.help && args.capture_file.is_some() => args,
+        _ => {
+            Args::usage();
+            return;
+        }
+    };
+    let verbose = args.verbose;
+    let capture_file = args.capture_file.unwrap();
+
+    let reader = match CaptureReader::open(&capture_file) {
+        Ok(reader) => reader,
+        Err(err) => {
+            eprintln!("Cannot open capture file {}, error = {}", capture_file, err);
+            return;
+        }
+    };
+
+    // Read every record up to the end or the first one that is corrupt
+    let mut records: Vec<CaptureRecord> = Vec::new();
+    for record in reader {
+        match record {
+            Ok(record) => records.push(record),
+            Err(err) => {
+                eprintln!(
+                    "Capture is corrupt after {} records, error = {}",
+                    records.len(),
+                    err
+                );
+                break;
+            }
+        }
+    }
+    println!("Read {} records from {}", records.len(), capture_file);
+
+    replay(records, DecodingOptions::default())
+        .into_iter()
+        .for_each(|replayed| {
+            let direction = match replayed.direction {
+                CaptureDirection::Received => "<-",
+                CaptureDirection::Sent => "->",
+            };
+            println!(
+                "{} {} channel {} request {}",
+                replayed.timestamp, direction, replayed.secure_channel_id, replayed.request_id
+            );
+            match replayed.message {
+                Ok(message) => {
+                    if verbose {
+                        println!("{:#?}", message);
+                    } else {
+                        println!("  {}", message.name());
+                    }
+                }
+                Err(err) => println!("  Cannot decode message, error = {}", err),
+            }
+        });
+}
+
+struct Args {
+    help: bool,
+    verbose: bool,
+    capture_file: Option<String>,
+}
+
+impl Args {
+    pub fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+        let mut args = pico_args::Arguments::from_env();
+        Ok(Args {
+            help: args.contains(["-h", "--help"]),
+            verbose: args.contains(["-v", "--verbose"]),
+            capture_file: args.opt_free_from_str()?,
+        })
+    }
+
+    pub fn usage() {
+        println!(
+            r#"OPC UA Capture Replay
+
+This reads a capture file recorded by a client or server and feeds its chunks back into the
+decoder, printing out the messages they contain. Chunks of a secure channel can only be decoded
+if the capture was made with decrypted chunks included or the channel has a security policy of None.
+Each direction is replayed from its decrypted chunks if there are any, otherwise from its raw ones.
+
+Usage:
+  -h, --help            Show help.
+  -v, --verbose         Print the contents of each message, not just its name.
+  capture-file          The capture file to replay."#
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Hands the data changes of the monitored nodes to every client of `/events` and `/ws`.
+
+use std::collections::HashMap;
+
+use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+
+/// Holds the subscribers to data changes and the last change of each node, so a new subscriber
+/// starts off with the current values rather than waiting for them to change.
+#[derive(Default)]
+pub struct Changes {
+    last_changes: HashMap<String, String>,
+    subscribers: Vec<UnboundedSender<String>>,
+}
+
+impl Changes {
+    /// Sends the change of the named node as JSON to every subscriber. Subscribers that have gone
+    /// away are dropped.
+    pub fn publish(&mut self, name: &str, change: String) {
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(change.clone()).is_ok());
+        self.last_changes.insert(name.to_string(), change);
+    }
+
+    /// Returns a stream of changes, beginning with the last change of each node
+    pub fn subscribe(&mut self) -> UnboundedReceiver<String> {
+        let (tx, rx) = unbounded();
+        self.last_changes.values().for_each(|change| {
+            let _ = tx.unbounded_send(change.clone());
+        });
+        self.subscribers.push(tx);
+        rx
+    }
+}
@@ -0,0 +1,111 @@
+//! The configuration of the gateway, i.e. the server it connects to and the nodes it exposes.
+
+use std::{collections::HashSet, str::FromStr};
+
+use opcua::{client::prelude::*, core::config::Config};
+
+/// A node that the gateway exposes under a name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeConfig {
+    /// The name of the node in the urls of the gateway, e.g. `/nodes/temperature`
+    pub name: String,
+    /// The node id in its string form, e.g. `ns=2;s=Device.Temp`
+    pub node_id: String,
+    /// Whether clients of the gateway may write the value of the node
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl NodeConfig {
+    /// Returns the node id of the node. Only call this on a valid config.
+    pub fn node_id(&self) -> NodeId {
+        NodeId::from_str(&self.node_id).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    pub application_name: String,
+    pub application_uri: String,
+    pub product_uri: String,
+    /// The address that the HTTP server binds to, e.g. `127.0.0.1:8686`
+    pub http_address: String,
+    /// The url of the OPC UA server
+    pub server_url: String,
+    pub security_policy: String,
+    pub security_mode: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub pki_dir: String,
+    /// Publishing and sampling interval in milliseconds of the subscription to the nodes
+    pub publishing_interval: f64,
+    pub nodes: Vec<NodeConfig>,
+}
+
+impl Config for GatewayConfig {
+    fn is_valid(&self) -> bool {
+        let mut valid = true;
+        if self.application_name.is_empty() {
+            error!("Application name is empty");
+            valid = false;
+        }
+        if self.application_uri.is_empty() {
+            error!("Application uri is empty");
+            valid = false;
+        }
+        if SecurityPolicy::from_str(&self.security_policy).is_err() {
+            error!("Security policy {} is not recognized", self.security_policy);
+            valid = false;
+        }
+        if MessageSecurityMode::from(self.security_mode.as_str()) == MessageSecurityMode::Invalid {
+            error!("Security mode {} is not recognized", self.security_mode);
+            valid = false;
+        }
+        if self.user.is_some() != self.password.is_some() {
+            error!("User and password must be set together");
+            valid = false;
+        }
+        if self.publishing_interval <= 0.0 {
+            error!("Publishing interval must be greater than 0");
+            valid = false;
+        }
+        let mut names = HashSet::new();
+        self.nodes.iter().for_each(|node| {
+            if node.name.is_empty() || node.name.contains('/') {
+                error!("Node name \"{}\" is empty or contains a /", node.name);
+                valid = false;
+            } else if !names.insert(node.name.as_str()) {
+                error!("Node name {} is used more than once", node.name);
+                valid = false;
+            }
+            if NodeId::from_str(&node.node_id).is_err() {
+                error!("Node {} has an invalid node id {}", node.name, node.node_id);
+                valid = false;
+            }
+        });
+        valid
+    }
+
+    fn application_name(&self) -> UAString {
+        UAString::from(&self.application_name)
+    }
+
+    fn application_uri(&self) -> UAString {
+        UAString::from(&self.application_uri)
+    }
+
+    fn product_uri(&self) -> UAString {
+        UAString::from(&self.product_uri)
+    }
+
+    fn application_type(&self) -> ApplicationType {
+        ApplicationType::Client
+    }
+}
+
+impl GatewayConfig {
+    /// Finds the node with the name
+    pub fn node(&self, name: &str) -> Option<&NodeConfig> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+}
@@ -0,0 +1,232 @@
+//! The HTTP side of the gateway. Nodes are addressed by the name they have in the configuration.
+//!
+//! * `GET /nodes` - lists the configured nodes
+//! * `GET /nodes/{name}` - reads the value of a node
+//! * `PUT /nodes/{name}` - writes the value of a writable node, the body being the JSON value
+//! * `GET /events` - streams data changes as server-sent events
+//! * `GET /ws` - streams data changes over a WebSocket
+//!
+//! Values are in the non-reversible OPC UA JSON form, see `json.rs`.
+
+use std::{sync::Arc, time::Duration};
+
+use actix_web::{
+    actix::{Actor, ActorContext, AsyncContext, Handler, Message, StreamHandler},
+    dev::HttpResponseBuilder,
+    error, http,
+    server::HttpServer,
+    ws, App, HttpRequest, HttpResponse, Json, Path, State,
+};
+use bytes::Bytes;
+use futures::Stream;
+use serde_json::{json, Value};
+
+use opcua::client::prelude::*;
+use opcua::sync::{Mutex, RwLock};
+
+use crate::{changes::Changes, config::GatewayConfig, json};
+
+/// The state shared by every request
+#[derive(Clone)]
+pub struct GatewayState {
+    pub config: Arc<GatewayConfig>,
+    pub session: Arc<RwLock<Session>>,
+    pub changes: Arc<Mutex<Changes>>,
+}
+
+/// Runs the HTTP server until the process is stopped
+pub fn run_server(state: GatewayState) -> Result<(), String> {
+    let address = state.config.http_address.clone();
+    HttpServer::new(move || {
+        App::with_state(state.clone())
+            .resource("/nodes", |r| r.method(http::Method::GET).with(list_nodes))
+            .resource("/nodes/{name}", |r| {
+                r.method(http::Method::GET).with(read_node);
+                r.method(http::Method::PUT).with(write_node);
+            })
+            .resource("/events", |r| r.method(http::Method::GET).f(events))
+            .resource("/ws", |r| r.method(http::Method::GET).f(ws_create_request))
+    })
+    .bind(&address)
+    .map_err(|err| format!("Cannot bind to {}, error = {}", address, err))?
+    .run();
+    Ok(())
+}
+
+fn error_response(mut response: HttpResponseBuilder, message: String) -> HttpResponse {
+    response.json(json!({ "error": message }))
+}
+
+fn list_nodes(state: State<GatewayState>) -> HttpResponse {
+    let nodes = state
+        .config
+        .nodes
+        .iter()
+        .map(|node| {
+            json!({
+                "name": node.name,
+                "node_id": node.node_id,
+                "writable": node.writable,
+            })
+        })
+        .collect::<Vec<Value>>();
+    HttpResponse::Ok().json(nodes)
+}
+
+/// Reads the current value of the node from the server
+fn read_value(state: &GatewayState, node_id: &NodeId) -> Result<DataValue, HttpResponse> {
+    state
+        .session
+        .read()
+        .read(&[node_id.into()], TimestampsToReturn::Both, 0.0)
+        .map_err(|err| {
+            error_response(
+                HttpResponse::BadGateway(),
+                format!("Cannot read {}, error = {}", node_id, err),
+            )
+        })
+        .and_then(|mut values| {
+            values.pop().ok_or_else(|| {
+                error_response(
+                    HttpResponse::BadGateway(),
+                    format!("Server returned no value for {}", node_id),
+                )
+            })
+        })
+}
+
+fn read_node((state, name): (State<GatewayState>, Path<String>)) -> HttpResponse {
+    let node = match state.config.node(&name) {
+        Some(node) => node,
+        None => return error_response(HttpResponse::NotFound(), format!("No node named {}", name)),
+    };
+    match read_value(&state, &node.node_id()) {
+        Ok(value) => HttpResponse::Ok().json(json::data_value(&value)),
+        Err(response) => response,
+    }
+}
+
+fn write_node(
+    (state, name, body): (State<GatewayState>, Path<String>, Json<Value>),
+) -> HttpResponse {
+    let node = match state.config.node(&name) {
+        Some(node) => node,
+        None => return error_response(HttpResponse::NotFound(), format!("No node named {}", name)),
+    };
+    if !node.writable {
+        return error_response(
+            HttpResponse::Forbidden(),
+            format!("Node {} is not writable", name),
+        );
+    }
+    let node_id = node.node_id();
+
+    // The body is either the value itself or an object with a Value, like a read returns
+    let value = match body.into_inner() {
+        Value::Object(mut object) if object.contains_key("Value") => {
+            object.remove("Value").unwrap()
+        }
+        value => value,
+    };
+
+    // The value is written as the type of the value that the node already has
+    let variant_type = match read_value(&state, &node_id) {
+        Ok(DataValue {
+            value: Some(ref current),
+            ..
+        }) if *current != Variant::Empty => current.type_id(),
+        Ok(_) => {
+            return error_response(
+                HttpResponse::Conflict(),
+                format!("Node {} has no value to take the data type from", name),
+            )
+        }
+        Err(response) => return response,
+    };
+    let value = match json::to_variant(&value, variant_type) {
+        Ok(value) => value,
+        Err(err) => return error_response(HttpResponse::BadRequest(), err),
+    };
+
+    let result = state.session.read().write(&[WriteValue {
+        node_id: node_id.clone(),
+        attribute_id: AttributeId::Value as u32,
+        index_range: UAString::null(),
+        value: value.into(),
+    }]);
+    match result {
+        Ok(results) => match results.first() {
+            Some(status_code) if status_code.is_good() => HttpResponse::NoContent().finish(),
+            Some(status_code) => HttpResponse::Conflict()
+                .json(json!({ "StatusCode": json::status_code(*status_code) })),
+            None => error_response(
+                HttpResponse::BadGateway(),
+                format!("Server returned no result for {}", node_id),
+            ),
+        },
+        Err(err) => error_response(
+            HttpResponse::BadGateway(),
+            format!("Cannot write {}, error = {}", node_id, err),
+        ),
+    }
+}
+
+/// Streams data changes as server-sent events, one JSON change per event
+fn events(req: &HttpRequest<GatewayState>) -> HttpResponse {
+    let changes = req.state().changes.lock().subscribe();
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(http::header::CACHE_CONTROL, "no-cache")
+        // Compression would buffer the events
+        .content_encoding(http::ContentEncoding::Identity)
+        .streaming(
+            changes
+                .map(|change| Bytes::from(format!("data: {}\n\n", change)))
+                .map_err(|_| error::ErrorInternalServerError("Change stream has closed")),
+        )
+}
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A data change to send on a WebSocket
+struct Change(String);
+
+impl Message for Change {
+    type Result = ();
+}
+
+/// An actor for a WebSocket that data changes are sent to as text messages
+struct ChangesSocket;
+
+impl Actor for ChangesSocket {
+    type Context = ws::WebsocketContext<Self, GatewayState>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let changes = ctx.state().changes.lock().subscribe();
+        ctx.add_message_stream(changes.map(Change));
+        ctx.run_interval(HEARTBEAT_INTERVAL, |_, ctx| ctx.ping(""));
+    }
+}
+
+impl Handler<Change> for ChangesSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: Change, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
+}
+
+impl StreamHandler<ws::Message, ws::ProtocolError> for ChangesSocket {
+    fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
+        match msg {
+            ws::Message::Ping(msg) => ctx.pong(&msg),
+            ws::Message::Close(_) => ctx.stop(),
+            // Anything that the client sends is ignored
+            _ => {}
+        }
+    }
+}
+
+fn ws_create_request(req: &HttpRequest<GatewayState>) -> Result<HttpResponse, actix_web::Error> {
+    ws::start(req, ChangesSocket)
+}
@@ -0,0 +1,270 @@
+//! Maps values to and from JSON using the non-reversible form of the OPC UA JSON encoding
+//! described in OPC UA Part 6 5.4, which is meant for consumers such as web pages that do not
+//! need to turn the JSON back into the exact OPC UA type.
+
+use std::str::FromStr;
+
+use opcua::client::prelude::*;
+use serde_json::{json, Map, Value};
+
+/// Encodes a data value as a JSON object with `Value`, `StatusCode`, `SourceTimestamp` and
+/// `ServerTimestamp` fields. Fields that are not set, and a status code of `Good`, are omitted.
+pub fn data_value(value: &DataValue) -> Value {
+    let mut object = Map::new();
+    if let Some(ref v) = value.value {
+        object.insert("Value".to_string(), variant(v));
+    }
+    if let Some(status) = value.status {
+        if status != StatusCode::Good {
+            object.insert("StatusCode".to_string(), status_code(status));
+        }
+    }
+    if let Some(ref timestamp) = value.source_timestamp {
+        object.insert("SourceTimestamp".to_string(), date_time(timestamp));
+    }
+    if let Some(ref timestamp) = value.server_timestamp {
+        object.insert("ServerTimestamp".to_string(), date_time(timestamp));
+    }
+    Value::Object(object)
+}
+
+/// Encodes a status code as an object holding its code and its name.
+pub fn status_code(status: StatusCode) -> Value {
+    json!({
+        "Code": status.bits(),
+        "Symbol": status.to_string(),
+    })
+}
+
+fn date_time(value: &DateTime) -> Value {
+    Value::String(value.to_string())
+}
+
+fn float(value: f64) -> Value {
+    // Special values have no JSON number so they are strings
+    if value.is_nan() {
+        json!("NaN")
+    } else if value == f64::INFINITY {
+        json!("Infinity")
+    } else if value == f64::NEG_INFINITY {
+        json!("-Infinity")
+    } else {
+        json!(value)
+    }
+}
+
+/// Encodes a variant as the JSON value of whatever it holds. Int64 and UInt64 values are strings
+/// since JSON numbers cannot hold them without losing precision. Anything without a natural JSON
+/// form is its string form.
+pub fn variant(value: &Variant) -> Value {
+    match value {
+        Variant::Empty => Value::Null,
+        Variant::Boolean(v) => json!(v),
+        Variant::SByte(v) => json!(v),
+        Variant::Byte(v) => json!(v),
+        Variant::Int16(v) => json!(v),
+        Variant::UInt16(v) => json!(v),
+        Variant::Int32(v) => json!(v),
+        Variant::UInt32(v) => json!(v),
+        Variant::Int64(v) => json!(v.to_string()),
+        Variant::UInt64(v) => json!(v.to_string()),
+        Variant::Float(v) => float(*v as f64),
+        Variant::Double(v) => float(*v),
+        Variant::String(v) if v.is_null() => Value::Null,
+        Variant::String(v) => json!(v.as_ref()),
+        Variant::DateTime(v) => date_time(v),
+        Variant::ByteString(v) if v.is_null() => Value::Null,
+        Variant::ByteString(v) => json!(v.as_base64()),
+        Variant::StatusCode(v) => status_code(*v),
+        Variant::QualifiedName(v) => {
+            if v.namespace_index == 0 {
+                json!({ "Name": v.name.as_ref() })
+            } else {
+                json!({ "Name": v.name.as_ref(), "Uri": v.namespace_index })
+            }
+        }
+        Variant::LocalizedText(v) => json!(v.text.as_ref()),
+        Variant::DataValue(v) => data_value(v),
+        Variant::Variant(v) => variant(v),
+        Variant::Array(array) => Value::Array(array.values.iter().map(variant).collect()),
+        value => Value::String(value.to_string()),
+    }
+}
+
+/// Decodes a JSON value into a variant of the type, e.g. the type of the value that a node
+/// already has. Numbers must fit into the type, and an Int64 or UInt64 may also be a string.
+/// A string is converted to any type that has a string form, such as a Guid or a NodeId.
+pub fn to_variant(value: &Value, variant_type: VariantTypeId) -> Result<Variant, String> {
+    let invalid = || format!("{} is not a valid {:?}", value, variant_type);
+    let result = match (variant_type, value) {
+        (VariantTypeId::Boolean, Value::Bool(v)) => Variant::from(*v),
+        (VariantTypeId::Float, Value::Number(v)) => {
+            Variant::from(v.as_f64().ok_or_else(invalid)? as f32)
+        }
+        (VariantTypeId::Double, Value::Number(v)) => Variant::from(v.as_f64().ok_or_else(invalid)?),
+        (VariantTypeId::String, Value::String(v)) => Variant::from(v.as_str()),
+        (VariantTypeId::String, Value::Null) => Variant::from(UAString::null()),
+        (variant_type, Value::Number(v)) => {
+            // Integers are cast through a 64-bit value, which fails if they are out of range
+            let v = if let Some(v) = v.as_i64() {
+                Variant::from(v)
+            } else if let Some(v) = v.as_u64() {
+                Variant::from(v)
+            } else {
+                return Err(invalid());
+            };
+            match v.convert(variant_type) {
+                Variant::Empty if is_integer(variant_type) => {
+                    i64_cast(&v, variant_type).ok_or_else(invalid)?
+                }
+                Variant::Empty => return Err(invalid()),
+                v => v,
+            }
+        }
+        (variant_type, Value::String(v)) => match variant_type {
+            VariantTypeId::DateTime => DateTime::from_str(v)
+                .map(Variant::from)
+                .map_err(|_| invalid())?,
+            VariantTypeId::ByteString => ByteString::from_base64(v)
+                .map(Variant::from)
+                .ok_or_else(invalid)?,
+            variant_type => match Variant::from(v.as_str()).cast(variant_type) {
+                Variant::Empty => return Err(invalid()),
+                v => v,
+            },
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(result)
+}
+
+fn is_integer(variant_type: VariantTypeId) -> bool {
+    matches!(
+        variant_type,
+        VariantTypeId::SByte
+            | VariantTypeId::Byte
+            | VariantTypeId::Int16
+            | VariantTypeId::UInt16
+            | VariantTypeId::Int32
+            | VariantTypeId::UInt32
+            | VariantTypeId::Int64
+            | VariantTypeId::UInt64
+    )
+}
+
+/// Narrows a 64-bit integer into a smaller integer type, or returns `None` if it does not fit.
+/// The implicit conversions of a variant only widen so they cannot do this.
+fn i64_cast(value: &Variant, variant_type: VariantTypeId) -> Option<Variant> {
+    use std::convert::TryFrom;
+    let v = match value {
+        Variant::Int64(v) => *v as i128,
+        Variant::UInt64(v) => *v as i128,
+        _ => return None,
+    };
+    Some(match variant_type {
+        VariantTypeId::SByte => Variant::from(i8::try_from(v).ok()?),
+        VariantTypeId::Byte => Variant::from(u8::try_from(v).ok()?),
+        VariantTypeId::Int16 => Variant::from(i16::try_from(v).ok()?),
+        VariantTypeId::UInt16 => Variant::from(u16::try_from(v).ok()?),
+        VariantTypeId::Int32 => Variant::from(i32::try_from(v).ok()?),
+        VariantTypeId::UInt32 => Variant::from(u32::try_from(v).ok()?),
+        VariantTypeId::Int64 => Variant::from(i64::try_from(v).ok()?),
+        VariantTypeId::UInt64 => Variant::from(u64::try_from(v).ok()?),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_variant() {
+        assert_eq!(variant(&Variant::from(true)), json!(true));
+        assert_eq!(variant(&Variant::from(-5i32)), json!(-5));
+        assert_eq!(
+            variant(&Variant::from(i64::MAX)),
+            json!("9223372036854775807")
+        );
+        assert_eq!(variant(&Variant::from(1.5f64)), json!(1.5));
+        assert_eq!(variant(&Variant::from(f64::NAN)), json!("NaN"));
+        assert_eq!(
+            variant(&Variant::from(f32::NEG_INFINITY)),
+            json!("-Infinity")
+        );
+        assert_eq!(variant(&Variant::from("hello")), json!("hello"));
+        assert_eq!(variant(&Variant::from(UAString::null())), Value::Null);
+        assert_eq!(variant(&Variant::Empty), Value::Null);
+        assert_eq!(
+            variant(&Variant::from(ByteString::from(b"hello"))),
+            json!("aGVsbG8=")
+        );
+        assert_eq!(
+            variant(&Variant::from(NodeId::new(2, "Device.Temp"))),
+            json!("ns=2;s=Device.Temp")
+        );
+        assert_eq!(variant(&Variant::from(vec![1i32, 2, 3])), json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn encode_data_value() {
+        let mut value = DataValue::value_only(10u16);
+        assert_eq!(data_value(&value), json!({ "Value": 10 }));
+        value.status = Some(StatusCode::Good);
+        assert_eq!(data_value(&value), json!({ "Value": 10 }));
+        value.status = Some(StatusCode::BadNodeIdUnknown);
+        value.value = None;
+        assert_eq!(
+            data_value(&value),
+            json!({ "StatusCode": { "Code": StatusCode::BadNodeIdUnknown.bits(), "Symbol": "BadNodeIdUnknown" } })
+        );
+    }
+
+    #[test]
+    fn decode_variant() {
+        assert_eq!(
+            to_variant(&json!(true), VariantTypeId::Boolean).unwrap(),
+            Variant::from(true)
+        );
+        assert_eq!(
+            to_variant(&json!(42), VariantTypeId::Byte).unwrap(),
+            Variant::from(42u8)
+        );
+        assert_eq!(
+            to_variant(&json!(-42), VariantTypeId::Int16).unwrap(),
+            Variant::from(-42i16)
+        );
+        assert_eq!(
+            to_variant(&json!(42), VariantTypeId::Double).unwrap(),
+            Variant::from(42f64)
+        );
+        assert_eq!(
+            to_variant(&json!("9223372036854775807"), VariantTypeId::Int64).unwrap(),
+            Variant::from(i64::MAX)
+        );
+        assert_eq!(
+            to_variant(&json!(u64::MAX), VariantTypeId::UInt64).unwrap(),
+            Variant::from(u64::MAX)
+        );
+        assert_eq!(
+            to_variant(&json!("hello"), VariantTypeId::String).unwrap(),
+            Variant::from("hello")
+        );
+        assert_eq!(
+            to_variant(&json!("aGVsbG8="), VariantTypeId::ByteString).unwrap(),
+            Variant::from(ByteString::from(b"hello"))
+        );
+        assert_eq!(
+            to_variant(&json!("ns=2;s=Device.Temp"), VariantTypeId::NodeId).unwrap(),
+            Variant::from(NodeId::new(2, "Device.Temp"))
+        );
+
+        // Values that do not fit the type
+        assert!(to_variant(&json!(256), VariantTypeId::Byte).is_err());
+        assert!(to_variant(&json!(-1), VariantTypeId::UInt32).is_err());
+        assert!(to_variant(&json!(1.5), VariantTypeId::Int32).is_err());
+        assert!(to_variant(&json!("x"), VariantTypeId::Int32).is_err());
+        assert!(to_variant(&json!(1), VariantTypeId::Boolean).is_err());
+        assert!(to_variant(&json!([1]), VariantTypeId::Int32).is_err());
+    }
+}
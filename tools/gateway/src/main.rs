@@ -0,0 +1,191 @@
+//! A gateway that exposes the values of configured nodes on an OPC UA server over HTTP. Values
+//! can be read and written with REST calls, and their changes are streamed to clients as
+//! server-sent events or over a WebSocket. See `http.rs` for the routes.
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use opcua::client::prelude::*;
+use opcua::core::config::Config;
+use opcua::sync::{Mutex, RwLock};
+use serde_json::Value;
+
+use crate::{changes::Changes, config::GatewayConfig, http::GatewayState};
+
+mod changes;
+mod config;
+mod http;
+mod json;
+
+struct Args {
+    help: bool,
+    config: String,
+}
+
+impl Args {
+    pub fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+        let mut args = pico_args::Arguments::from_env();
+        Ok(Args {
+            help: args.contains(["-h", "--help"]),
+            config: args
+                .opt_value_from_str("--config")?
+                .unwrap_or_else(|| String::from(DEFAULT_CONFIG)),
+        })
+    }
+
+    pub fn usage() {
+        println!(
+            r#"OPC UA Gateway
+Usage:
+  -h, --help   Show help
+  --config     Path to the gateway configuration (default: {})"#,
+            DEFAULT_CONFIG
+        );
+    }
+}
+
+const DEFAULT_CONFIG: &str = "gateway.conf";
+
+fn main() {
+    let args = match Args::parse_args() {
+        Ok(args) if !args.help => args,
+        Ok(_) => {
+            Args::usage();
+            return;
+        }
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            Args::usage();
+            std::process::exit(2);
+        }
+    };
+    opcua::console_logging::init();
+    if let Err(err) = run(&args) {
+        eprintln!("ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let config: GatewayConfig = GatewayConfig::load(&PathBuf::from(&args.config))
+        .map_err(|_| format!("Cannot load the configuration from {}", args.config))?;
+    if !config.is_valid() {
+        return Err(format!("The configuration in {} is invalid", args.config));
+    }
+    let config = Arc::new(config);
+    let session = connect(&config)?;
+    let changes = Arc::new(Mutex::new(Changes::default()));
+    subscribe(&config, &session, &changes)?;
+
+    // The session runs on its own thread for as long as the sender is held
+    let _session_tx = Session::run_async(session.clone());
+
+    info!("Gateway is listening on http://{}", config.http_address);
+    http::run_server(GatewayState {
+        config,
+        session,
+        changes,
+    })
+}
+
+fn connect(config: &GatewayConfig) -> Result<Arc<RwLock<Session>>, String> {
+    let security_policy = SecurityPolicy::from_str(&config.security_policy).unwrap();
+    let security_mode = MessageSecurityMode::from(config.security_mode.as_str());
+    let identity_token = match (&config.user, &config.password) {
+        (Some(user), Some(password)) => IdentityToken::UserName(user.clone(), password.clone()),
+        _ => IdentityToken::Anonymous,
+    };
+    let mut client = ClientBuilder::new()
+        .application_name(config.application_name.as_str())
+        .application_uri(config.application_uri.as_str())
+        .product_uri(config.product_uri.as_str())
+        .pki_dir(config.pki_dir.as_str())
+        .trust_server_certs(true)
+        .create_sample_keypair(true)
+        .client()
+        .ok_or_else(|| String::from("Cannot create a client"))?;
+    client
+        .connect_to_endpoint(
+            (
+                config.server_url.as_str(),
+                security_policy.to_str(),
+                security_mode,
+                UserTokenPolicy::anonymous(),
+            ),
+            identity_token,
+        )
+        .map_err(|err| format!("Cannot connect to {}, error = {}", config.server_url, err))
+}
+
+/// Monitors every configured node and publishes its changes as JSON objects holding the name and
+/// node id of the node together with its data value
+fn subscribe(
+    config: &Arc<GatewayConfig>,
+    session: &Arc<RwLock<Session>>,
+    changes: &Arc<Mutex<Changes>>,
+) -> Result<(), String> {
+    if config.nodes.is_empty() {
+        return Ok(());
+    }
+    let session = session.read();
+    let callback_config = config.clone();
+    let callback_changes = changes.clone();
+    let subscription_id = session
+        .create_subscription(
+            config.publishing_interval,
+            30,
+            10,
+            0,
+            0,
+            true,
+            DataChangeCallback::new(move |changed_monitored_items| {
+                let mut changes = callback_changes.lock();
+                changed_monitored_items.iter().for_each(|item| {
+                    let node_id = &item.item_to_monitor().node_id;
+                    let node = callback_config
+                        .nodes
+                        .iter()
+                        .find(|node| node.node_id() == *node_id);
+                    if let Some(node) = node {
+                        let mut change = json::data_value(item.last_value());
+                        if let Value::Object(ref mut object) = change {
+                            object.insert("name".to_string(), Value::from(node.name.as_str()));
+                            object
+                                .insert("node_id".to_string(), Value::from(node.node_id.as_str()));
+                        }
+                        changes.publish(&node.name, change.to_string());
+                    }
+                });
+            }),
+        )
+        .map_err(|err| format!("Cannot create subscription, error = {}", err))?;
+    let items_to_create = config
+        .nodes
+        .iter()
+        .map(|node| {
+            let mut item: MonitoredItemCreateRequest = node.node_id().into();
+            item.requested_parameters.sampling_interval = config.publishing_interval;
+            item
+        })
+        .collect::<Vec<MonitoredItemCreateRequest>>();
+    let results = session
+        .create_monitored_items(subscription_id, TimestampsToReturn::Both, &items_to_create)
+        .map_err(|err| format!("Cannot create monitored items, error = {}", err))?;
+    // A node that cannot be monitored can still be read and written
+    config
+        .nodes
+        .iter()
+        .zip(results.iter())
+        .filter(|(_, result)| result.status_code.is_bad())
+        .for_each(|(node, result)| {
+            warn!(
+                "Cannot monitor node {}, error = {}",
+                node.name, result.status_code
+            )
+        });
+    Ok(())
+}
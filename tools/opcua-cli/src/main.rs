@@ -0,0 +1,446 @@
+use std::{collections::HashSet, str::FromStr, sync::Arc};
+
+use opcua::client::prelude::*;
+use opcua::sync::RwLock;
+
+use crate::output::Output;
+
+mod output;
+
+fn main() {
+    let args = match Args::parse_args() {
+        Ok(args) if !args.help && args.command.is_some() => args,
+        Ok(_) => {
+            Args::usage();
+            return;
+        }
+        Err(err) => {
+            eprintln!("ERROR: {}", err);
+            Args::usage();
+            std::process::exit(2);
+        }
+    };
+    if args.verbose {
+        opcua::console_logging::init();
+    }
+
+    let output = Output::new(args.json);
+    let result = match args.command.as_deref().unwrap() {
+        "endpoints" => endpoints(&args, &output),
+        "browse" => connect(&args).and_then(|session| browse(&args, &output, &session)),
+        "read" => connect(&args).and_then(|session| read(&args, &output, &session)),
+        "write" => connect(&args).and_then(|session| write(&args, &output, &session)),
+        "call" => connect(&args).and_then(|session| call(&args, &output, &session)),
+        "subscribe" => connect(&args).and_then(|session| subscribe(&args, output, session)),
+        command => Err(format!("Unknown command {}", command)),
+    };
+    if let Err(err) = result {
+        eprintln!("ERROR: {}", err);
+        std::process::exit(1);
+    }
+}
+
+struct Args {
+    help: bool,
+    verbose: bool,
+    json: bool,
+    command: Option<String>,
+    url: String,
+    security_policy: String,
+    security_mode: String,
+    user: Option<String>,
+    password: Option<String>,
+    pki_dir: String,
+    nodes: Vec<String>,
+    depth: usize,
+    value: Option<String>,
+    data_type: Option<String>,
+    object: Option<String>,
+    method: Option<String>,
+    call_args: Vec<String>,
+    interval: f64,
+}
+
+impl Args {
+    pub fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+        let mut args = pico_args::Arguments::from_env();
+        let args = Args {
+            help: args.contains(["-h", "--help"]),
+            verbose: args.contains(["-v", "--verbose"]),
+            json: args.contains("--json"),
+            url: args
+                .opt_value_from_str("--url")?
+                .unwrap_or_else(|| String::from(DEFAULT_URL)),
+            security_policy: args
+                .opt_value_from_str("--security-policy")?
+                .unwrap_or_else(|| String::from(SecurityPolicy::None.to_str())),
+            security_mode: args
+                .opt_value_from_str("--security-mode")?
+                .unwrap_or_else(|| String::from(MessageSecurityMode::None)),
+            user: args.opt_value_from_str("--user")?,
+            password: args.opt_value_from_str("--password")?,
+            pki_dir: args
+                .opt_value_from_str("--pki-dir")?
+                .unwrap_or_else(|| String::from(DEFAULT_PKI_DIR)),
+            nodes: args.values_from_str("--node")?,
+            depth: args.opt_value_from_str("--depth")?.unwrap_or(DEFAULT_DEPTH),
+            value: args.opt_value_from_str("--value")?,
+            data_type: args.opt_value_from_str("--type")?,
+            object: args.opt_value_from_str("--object")?,
+            method: args.opt_value_from_str("--method")?,
+            call_args: args.values_from_str("--arg")?,
+            interval: args
+                .opt_value_from_str("--interval")?
+                .unwrap_or(DEFAULT_INTERVAL),
+            command: args.opt_free_from_str()?,
+        };
+        Ok(args)
+    }
+
+    pub fn usage() {
+        println!(
+            r#"OPC UA CLI
+
+A command-line client for commissioning and debugging OPC UA servers. Node ids are written in
+the string form of OPC UA Part 6, e.g. "ns=2;s=Device.Temp" or "i=2258". Values are written
+as strings and converted to the data type of the node, or the type given with --type.
+
+Usage:
+  opcua-cli [options] <command>
+
+Commands:
+  endpoints                 List the endpoints of the server.
+  browse                    Browse the address space as a tree from --node (default: {}).
+  read                      Read the value of each --node.
+  write                     Write --value to --node.
+  call                      Call --method on --object with each --arg.
+  subscribe                 Subscribe to each --node and print value changes until interrupted.
+
+Options:
+  -h, --help                Show help.
+  -v, --verbose             Log what the client is doing to the console.
+  --json                    Print one JSON object per line instead of text.
+  --url [url]               Url of the server (default: {}).
+  --security-policy [name]  Security policy, e.g. None or Basic256Sha256 (default: None).
+  --security-mode [mode]    Security mode, None, Sign or SignAndEncrypt (default: None).
+  --user [name]             User name, which must be given with --password.
+  --password [password]     Password of the user.
+  --pki-dir [path]          Directory of the client certificate and trusted server certificates
+                            (default: {}).
+  --node [node-id]          A node, which may be given more than once for read and subscribe.
+  --depth [n]               How many levels deep to browse (default: {}).
+  --value [value]           The value to write.
+  --type [type]             Data type of the value, e.g. Int32, Double, Boolean or String.
+  --object [node-id]        The object that the method is called on.
+  --method [node-id]        The method to call.
+  --arg [type:value]        An input argument of the method, e.g. Int32:5, in order.
+  --interval [ms]           Publishing and sampling interval of a subscription (default: {})."#,
+            DEFAULT_BROWSE_NODE, DEFAULT_URL, DEFAULT_PKI_DIR, DEFAULT_DEPTH, DEFAULT_INTERVAL
+        );
+    }
+}
+
+const DEFAULT_URL: &str = "opc.tcp://localhost:4855";
+const DEFAULT_PKI_DIR: &str = "./pki";
+const DEFAULT_BROWSE_NODE: &str = "i=85";
+const DEFAULT_DEPTH: usize = 2;
+const DEFAULT_INTERVAL: f64 = 1000.0;
+
+fn make_client(args: &Args) -> Result<Client, String> {
+    ClientBuilder::new()
+        .application_name("OPC UA CLI")
+        .application_uri("urn:OpcUaCli")
+        .product_uri("urn:OpcUaCli")
+        .pki_dir(args.pki_dir.as_str())
+        .trust_server_certs(true)
+        .create_sample_keypair(true)
+        .session_retry_limit(0)
+        .client()
+        .ok_or_else(|| String::from("Cannot create a client"))
+}
+
+fn endpoints(args: &Args, output: &Output) -> Result<(), String> {
+    let endpoints = make_client(args)?
+        .get_server_endpoints_from_url(args.url.as_str())
+        .map_err(|err| format!("Cannot get endpoints from {}, error = {}", args.url, err))?;
+    endpoints
+        .iter()
+        .for_each(|endpoint| output.endpoint(endpoint));
+    Ok(())
+}
+
+fn connect(args: &Args) -> Result<Arc<RwLock<Session>>, String> {
+    let security_policy = SecurityPolicy::from_str(&args.security_policy)
+        .map_err(|_| format!("Unknown security policy {}", args.security_policy))?;
+    let security_mode = MessageSecurityMode::from(args.security_mode.as_str());
+    if security_mode == MessageSecurityMode::Invalid {
+        return Err(format!("Unknown security mode {}", args.security_mode));
+    }
+    let identity_token = match (&args.user, &args.password) {
+        (Some(user), Some(password)) => IdentityToken::UserName(user.clone(), password.clone()),
+        (None, None) => IdentityToken::Anonymous,
+        _ => return Err(String::from("--user and --password must be given together")),
+    };
+    make_client(args)?
+        .connect_to_endpoint(
+            (
+                args.url.as_str(),
+                security_policy.to_str(),
+                security_mode,
+                UserTokenPolicy::anonymous(),
+            ),
+            identity_token,
+        )
+        .map_err(|err| format!("Cannot connect to {}, error = {}", args.url, err))
+}
+
+fn parse_node_id(node_id: &str) -> Result<NodeId, String> {
+    NodeId::from_str(node_id).map_err(|_| format!("Invalid node id {}", node_id))
+}
+
+fn parse_nodes(args: &Args) -> Result<Vec<NodeId>, String> {
+    if args.nodes.is_empty() {
+        Err(String::from("No --node given"))
+    } else {
+        args.nodes.iter().map(|n| parse_node_id(n)).collect()
+    }
+}
+
+fn parse_data_type(name: &str) -> Result<VariantTypeId, String> {
+    Ok(match name {
+        "Boolean" => VariantTypeId::Boolean,
+        "SByte" => VariantTypeId::SByte,
+        "Byte" => VariantTypeId::Byte,
+        "Int16" => VariantTypeId::Int16,
+        "UInt16" => VariantTypeId::UInt16,
+        "Int32" => VariantTypeId::Int32,
+        "UInt32" => VariantTypeId::UInt32,
+        "Int64" => VariantTypeId::Int64,
+        "UInt64" => VariantTypeId::UInt64,
+        "Float" => VariantTypeId::Float,
+        "Double" => VariantTypeId::Double,
+        "String" => VariantTypeId::String,
+        "Guid" => VariantTypeId::Guid,
+        "NodeId" => VariantTypeId::NodeId,
+        "ExpandedNodeId" => VariantTypeId::ExpandedNodeId,
+        _ => return Err(format!("Unsupported data type {}", name)),
+    })
+}
+
+/// Converts a value from the command line into a variant of the type
+fn parse_value(value: &str, data_type: VariantTypeId) -> Result<Variant, String> {
+    match Variant::from(value).cast(data_type) {
+        Variant::Empty => Err(format!("Cannot convert {} to {:?}", value, data_type)),
+        value => Ok(value),
+    }
+}
+
+fn browse(args: &Args, output: &Output, session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let node_id = match args.nodes.first() {
+        Some(node_id) => parse_node_id(node_id)?,
+        None => parse_node_id(DEFAULT_BROWSE_NODE)?,
+    };
+    let session = session.read();
+    let mut visited = HashSet::new();
+    visited.insert(node_id.clone());
+    browse_tree(&session, output, &node_id, 0, args.depth, &mut visited)
+}
+
+fn browse_tree(
+    session: &Session,
+    output: &Output,
+    node_id: &NodeId,
+    level: usize,
+    depth: usize,
+    visited: &mut HashSet<NodeId>,
+) -> Result<(), String> {
+    if level >= depth {
+        return Ok(());
+    }
+    for reference in browse_references(session, node_id)? {
+        output.reference(level, &reference);
+        // Only follow references to local nodes that have not been seen, in case of a loop
+        if let Some(child) = reference.node_id.as_local_node_id() {
+            if visited.insert(child.clone()) {
+                browse_tree(session, output, child, level + 1, depth, visited)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Browses the hierarchical references of a node, following continuation points until the
+/// server has returned every reference
+fn browse_references(
+    session: &Session,
+    node_id: &NodeId,
+) -> Result<Vec<ReferenceDescription>, String> {
+    let browse_description = BrowseDescription {
+        node_id: node_id.clone(),
+        browse_direction: BrowseDirection::Forward,
+        reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+        include_subtypes: true,
+        node_class_mask: 0,
+        result_mask: BrowseDescriptionResultMask::all().bits(),
+    };
+    let mut result = session
+        .browse(&[browse_description])
+        .map_err(|err| format!("Cannot browse {}, error = {}", node_id, err))?
+        .and_then(|mut results| results.pop())
+        .ok_or_else(|| format!("Cannot browse {}, no result", node_id))?;
+    let mut references = Vec::new();
+    loop {
+        if result.status_code.is_bad() {
+            return Err(format!(
+                "Cannot browse {}, error = {}",
+                node_id, result.status_code
+            ));
+        }
+        references.extend(result.references.take().unwrap_or_default());
+        if result.continuation_point.is_null_or_empty() {
+            break;
+        }
+        result = session
+            .browse_next(false, &[result.continuation_point])
+            .map_err(|err| format!("Cannot browse {}, error = {}", node_id, err))?
+            .and_then(|mut results| results.pop())
+            .ok_or_else(|| format!("Cannot browse {}, no result", node_id))?;
+    }
+    Ok(references)
+}
+
+fn read(args: &Args, output: &Output, session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let node_ids = parse_nodes(args)?;
+    let nodes_to_read = node_ids
+        .iter()
+        .map(|node_id| node_id.into())
+        .collect::<Vec<ReadValueId>>();
+    let values = session
+        .read()
+        .read(&nodes_to_read, TimestampsToReturn::Both, 0.0)
+        .map_err(|err| format!("Cannot read, error = {}", err))?;
+    node_ids
+        .iter()
+        .zip(values.iter())
+        .for_each(|(node_id, value)| output.value(node_id, value));
+    Ok(())
+}
+
+fn write(args: &Args, output: &Output, session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let node_id = match args.nodes.as_slice() {
+        [node_id] => parse_node_id(node_id)?,
+        _ => return Err(String::from("Write needs exactly one --node")),
+    };
+    let value = args
+        .value
+        .as_ref()
+        .ok_or_else(|| String::from("Write needs a --value"))?;
+    let session = session.read();
+    let data_type = match args.data_type {
+        Some(ref data_type) => parse_data_type(data_type)?,
+        None => {
+            // Write the value as the type of the value the node already has
+            let current = session
+                .read(&[(&node_id).into()], TimestampsToReturn::Neither, 0.0)
+                .map_err(|err| format!("Cannot read {}, error = {}", node_id, err))?;
+            match current.first().and_then(|v| v.value.as_ref()) {
+                Some(Variant::Empty) | None => {
+                    return Err(format!(
+                        "{} has no value to take the data type from, use --type",
+                        node_id
+                    ))
+                }
+                Some(current) => current.type_id(),
+            }
+        }
+    };
+    let value = parse_value(value, data_type)?;
+    let results = session
+        .write(&[WriteValue {
+            node_id: node_id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            value: value.into(),
+        }])
+        .map_err(|err| format!("Cannot write {}, error = {}", node_id, err))?;
+    let status_code = results
+        .first()
+        .copied()
+        .unwrap_or(StatusCode::BadUnexpectedError);
+    output.status(&node_id, status_code);
+    Ok(())
+}
+
+fn call(args: &Args, output: &Output, session: &Arc<RwLock<Session>>) -> Result<(), String> {
+    let object_id = args
+        .object
+        .as_ref()
+        .ok_or_else(|| String::from("Call needs an --object"))
+        .and_then(|object_id| parse_node_id(object_id))?;
+    let method_id = args
+        .method
+        .as_ref()
+        .ok_or_else(|| String::from("Call needs a --method"))
+        .and_then(|method_id| parse_node_id(method_id))?;
+    let input_arguments = args
+        .call_args
+        .iter()
+        .map(|arg| {
+            let mut parts = arg.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(data_type), Some(value)) => parse_value(value, parse_data_type(data_type)?),
+                _ => Err(format!("Argument {} is not in the form type:value", arg)),
+            }
+        })
+        .collect::<Result<Vec<Variant>, String>>()?;
+    let result = session
+        .read()
+        .call((object_id, method_id.clone(), Some(input_arguments)))
+        .map_err(|err| format!("Cannot call {}, error = {}", method_id, err))?;
+    output.call_result(&method_id, &result);
+    Ok(())
+}
+
+fn subscribe(args: &Args, output: Output, session: Arc<RwLock<Session>>) -> Result<(), String> {
+    let node_ids = parse_nodes(args)?;
+    {
+        let session = session.read();
+        let subscription_id = session
+            .create_subscription(
+                args.interval,
+                30,
+                10,
+                0,
+                0,
+                true,
+                DataChangeCallback::new(move |changed_monitored_items| {
+                    changed_monitored_items.iter().for_each(|item| {
+                        output.value(&item.item_to_monitor().node_id, item.last_value())
+                    });
+                }),
+            )
+            .map_err(|err| format!("Cannot create subscription, error = {}", err))?;
+        let items_to_create = node_ids
+            .into_iter()
+            .map(|node_id| {
+                let mut item: MonitoredItemCreateRequest = node_id.into();
+                item.requested_parameters.sampling_interval = args.interval;
+                item
+            })
+            .collect::<Vec<MonitoredItemCreateRequest>>();
+        let results = session
+            .create_monitored_items(subscription_id, TimestampsToReturn::Both, &items_to_create)
+            .map_err(|err| format!("Cannot create monitored items, error = {}", err))?;
+        for (item, result) in items_to_create.iter().zip(results.iter()) {
+            if result.status_code.is_bad() {
+                return Err(format!(
+                    "Cannot monitor {}, error = {}",
+                    item.item_to_monitor.node_id, result.status_code
+                ));
+            }
+        }
+    }
+    // Runs until the session is disconnected, the callback prints changes as they arrive
+    Session::run(session);
+    Ok(())
+}
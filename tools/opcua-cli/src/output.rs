@@ -0,0 +1,161 @@
+//! Prints the results of commands, either as text for a person to read or as one JSON object per
+//! line for a script to consume.
+
+use opcua::client::prelude::*;
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy)]
+pub struct Output {
+    json: bool,
+}
+
+impl Output {
+    pub fn new(json: bool) -> Output {
+        Output { json }
+    }
+
+    pub fn endpoint(&self, endpoint: &EndpointDescription) {
+        let user_tokens = endpoint
+            .user_identity_tokens
+            .as_ref()
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|token| format!("{:?}", token.token_type))
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        if self.json {
+            self.print(json!({
+                "endpoint_url": endpoint.endpoint_url.as_ref(),
+                "security_policy_uri": endpoint.security_policy_uri.as_ref(),
+                "security_mode": endpoint.security_mode.to_string(),
+                "security_level": endpoint.security_level,
+                "user_identity_tokens": user_tokens,
+            }));
+        } else {
+            println!(
+                "{} - {} / {} (level {}), user tokens {}",
+                endpoint.endpoint_url,
+                endpoint.security_policy_uri,
+                endpoint.security_mode,
+                endpoint.security_level,
+                user_tokens.join(", ")
+            );
+        }
+    }
+
+    pub fn reference(&self, level: usize, reference: &ReferenceDescription) {
+        let browse_name = &reference.browse_name;
+        let browse_name = if browse_name.namespace_index == 0 {
+            browse_name.name.to_string()
+        } else {
+            format!("{}:{}", browse_name.namespace_index, browse_name.name)
+        };
+        if self.json {
+            self.print(json!({
+                "level": level,
+                "node_id": node_id_text(&reference.node_id),
+                "browse_name": browse_name,
+                "display_name": reference.display_name.text.as_ref(),
+                "node_class": format!("{:?}", reference.node_class),
+            }));
+        } else {
+            println!(
+                "{}{} ({:?}) {}",
+                "  ".repeat(level),
+                browse_name,
+                reference.node_class,
+                node_id_text(&reference.node_id)
+            );
+        }
+    }
+
+    pub fn value(&self, node_id: &NodeId, value: &DataValue) {
+        let status = value.status.unwrap_or(StatusCode::Good);
+        if self.json {
+            self.print(json!({
+                "node_id": node_id.to_string(),
+                "value": value.value.as_ref().map(json_value).unwrap_or(Value::Null),
+                "status": status.to_string(),
+                "source_timestamp": value.source_timestamp.as_ref().map(|t| t.to_string()),
+                "server_timestamp": value.server_timestamp.as_ref().map(|t| t.to_string()),
+            }));
+        } else {
+            let text = match value.value {
+                Some(ref value) => value.to_string(),
+                None => String::from("(no value)"),
+            };
+            match value.source_timestamp {
+                Some(ref timestamp) => {
+                    println!("{} = {} [{}] {}", node_id, text, status, timestamp)
+                }
+                None => println!("{} = {} [{}]", node_id, text, status),
+            }
+        }
+    }
+
+    pub fn status(&self, node_id: &NodeId, status: StatusCode) {
+        if self.json {
+            self.print(json!({
+                "node_id": node_id.to_string(),
+                "status": status.to_string(),
+            }));
+        } else {
+            println!("{} [{}]", node_id, status);
+        }
+    }
+
+    pub fn call_result(&self, method_id: &NodeId, result: &CallMethodResult) {
+        let output_arguments = result.output_arguments.as_deref().unwrap_or(&[]);
+        if self.json {
+            self.print(json!({
+                "method_id": method_id.to_string(),
+                "status": result.status_code.to_string(),
+                "output_arguments": output_arguments.iter().map(json_value).collect::<Vec<Value>>(),
+            }));
+        } else {
+            println!("{} [{}]", method_id, result.status_code);
+            output_arguments
+                .iter()
+                .enumerate()
+                .for_each(|(i, argument)| println!("  {} = {}", i, argument));
+        }
+    }
+
+    fn print(&self, value: Value) {
+        println!("{}", value);
+    }
+}
+
+/// Node ids on this server are printed without the server index that an expanded node id
+/// always has in its string form
+fn node_id_text(node_id: &ExpandedNodeId) -> String {
+    match node_id.as_local_node_id() {
+        Some(node_id) => node_id.to_string(),
+        None => node_id.to_string(),
+    }
+}
+
+/// Turns a variant into the closest JSON value, so numbers, booleans and arrays can be consumed
+/// directly. Anything without a JSON equivalent is a string.
+fn json_value(value: &Variant) -> Value {
+    match value {
+        Variant::Empty => Value::Null,
+        Variant::Boolean(v) => json!(v),
+        Variant::SByte(v) => json!(v),
+        Variant::Byte(v) => json!(v),
+        Variant::Int16(v) => json!(v),
+        Variant::UInt16(v) => json!(v),
+        Variant::Int32(v) => json!(v),
+        Variant::UInt32(v) => json!(v),
+        Variant::Int64(v) => json!(v),
+        Variant::UInt64(v) => json!(v),
+        // NaN and infinity have no JSON form and become null
+        Variant::Float(v) => json!(v),
+        Variant::Double(v) => json!(v),
+        Variant::String(v) if v.is_null() => Value::Null,
+        Variant::Array(array) => Value::Array(array.values.iter().map(json_value).collect()),
+        value => Value::String(value.to_string()),
+    }
+}
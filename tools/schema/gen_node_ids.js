@@ -16,9 +16,9 @@ rs_out.write(`// OPCUA for Rust
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
-use crate::types::{
+use crate::{
     node_id::{NodeId, ExpandedNodeId},
     string::UAString,
 };
@@ -66,16 +66,16 @@ impl<'a> From<&'a ${key}Id> for NodeId {
     }
 }
 
-impl Into<NodeId> for ${key}Id {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<${key}Id> for NodeId {
+    fn from(r: ${key}Id) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for ${key}Id {
-    fn into(self) -> ExpandedNodeId {
+impl From<${key}Id> for ExpandedNodeId {
+    fn from(r: ${key}Id) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
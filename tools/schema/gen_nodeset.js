@@ -14,6 +14,8 @@ let argv = require("yargs")
     .describe('outputdir', "The directory to generate Rust modules into")
     .default('outputdir', ".")
     .describe('ns', "Rewrites any non-zero namespace index to this new value")
+    .describe('types', "Also generates a module of typed wrappers for the object types, e.g. foo_types.rs")
+    .boolean('types')
     .argv;
 
 let xml_file = argv.nodeset;
@@ -26,6 +28,7 @@ let config = {
     autogenerated_by: "tools/schema/gen_nodeset.js",
     trace: trace,
     namespace_index: namespace_index,
+    typed_wrappers: argv.types,
     opcua_server_crate: true,
     copyright: false
 }
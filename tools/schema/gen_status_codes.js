@@ -38,7 +38,7 @@ fs.createReadStream(status_code_csv)
 #![allow(non_upper_case_globals)]
 #![allow(unused_attributes)]
 
-use std::str::FromStr;
+use core::str::FromStr;
 
 // This file contains the machine-generated values of StatusCode. Refer to status_code.rs for the hand implemented
 // components.
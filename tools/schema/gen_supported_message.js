@@ -5,6 +5,28 @@ var xml2js = require("xml2js");
 var settings = require("./settings");
 let util = require("./util");
 
+// The service sets that are only compiled with a feature, by the messages of their services
+const message_features = {
+    "AddNodes": "node-management",
+    "AddReferences": "node-management",
+    "DeleteNodes": "node-management",
+    "DeleteReferences": "node-management",
+    "QueryFirst": "query",
+    "QueryNext": "query",
+    "HistoryRead": "history",
+    "HistoryUpdate": "history",
+};
+
+// Returns the cfg attribute of a message whose service set is only compiled with a feature
+function cfg_attribute(message_type, indent) {
+    let service = message_type.replace(/(Request|Response)$/, "");
+    if (service in message_features) {
+        return `${indent}#[cfg(feature = "${message_features[service]}")]
+`;
+    }
+    return "";
+}
+
 function generate_supported_message(message_types) {
     var file_name = "supported_message.rs";
     var file_path = `${settings.rs_supported_message_dir}/${file_name}`;
@@ -17,10 +39,9 @@ function generate_supported_message(message_types) {
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 
-use std::io::{Read, Write};
-
 use crate::types::{
     encoding::*,
+    io::{Read, Write},
     node_id::NodeId,
     node_ids::ObjectId,
     request_header::RequestHeader,
@@ -28,14 +49,14 @@ use crate::types::{
     service_types::*,
 };
 
-pub use crate::server::comms::tcp_types::AcknowledgeMessage;
+pub use crate::core::comms::tcp_types::AcknowledgeMessage;
 
 /// This macro helps avoid tedious repetition as new messages are added
 /// The first form just handles the trailing comma after the last entry to save some pointless
 /// editing when new messages are added to the list.
 macro_rules! supported_messages_enum {
-    [ $( $x:ident, ) * ] => (supported_messages_enum![ $( $x ),* ];);
-    [ $( $x:ident ), * ] => {
+    [ $( $(#[$meta:meta])* $x:ident, ) * ] => (supported_messages_enum![ $( $(#[$meta])* $x ),* ];);
+    [ $( $(#[$meta:meta])* $x:ident ), * ] => {
         #[derive(Debug, PartialEq, Clone)]
         pub enum SupportedMessage {
             /// An invalid request / response of some form
@@ -43,7 +64,7 @@ macro_rules! supported_messages_enum {
             /// Acknowledge message
             AcknowledgeMessage(Box<AcknowledgeMessage>),
             /// Other messages
-            $( $x(Box<$x>), )*
+            $( $(#[$meta])* $x(Box<$x>), )*
         }
 
         impl BinaryEncoder <SupportedMessage> for SupportedMessage {
@@ -53,7 +74,7 @@ macro_rules! supported_messages_enum {
                         panic!("Unsupported message byte_len {:?}", object_id);
                     },
                     SupportedMessage::AcknowledgeMessage(value) => value.byte_len(),
-                    $( SupportedMessage::$x(value) => value.byte_len(), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.byte_len(), )*
                 }
             }
 
@@ -63,7 +84,7 @@ macro_rules! supported_messages_enum {
                         panic!("Unsupported message encode {:?}", object_id);
                     },
                     SupportedMessage::AcknowledgeMessage(value) => value.encode(stream),
-                    $( SupportedMessage::$x(value) => value.encode(stream), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.encode(stream), )*
                 }
             }
 
@@ -78,6 +99,7 @@ macro_rules! supported_messages_enum {
         }
 
         $(
+        $(#[$meta])*
         impl Into<SupportedMessage> for $x {
             fn into(self) -> SupportedMessage { SupportedMessage::$x(Box::new(self)) }
         }
@@ -92,7 +114,16 @@ macro_rules! supported_messages_enum {
                     SupportedMessage::AcknowledgeMessage(value) => {
                         panic!("Unsupported message node_id {:?}", value);
                     },
-                    $( SupportedMessage::$x(value) => value.object_id().into(), )*
+                    $( $(#[$meta])* SupportedMessage::$x(value) => value.object_id().into(), )*
+                }
+            }
+
+            /// Returns the name of the message, e.g. "ReadRequest"
+            pub fn name(&self) -> &'static str {
+                match self {
+                    SupportedMessage::Invalid(_) => "Invalid",
+                    SupportedMessage::AcknowledgeMessage(_) => "AcknowledgeMessage",
+                    $( $(#[$meta])* SupportedMessage::$x(_) => stringify!($x), )*
                 }
             }
         }
@@ -115,7 +146,7 @@ impl SupportedMessage {
 `;
     _.each(message_types, message_type => {
         if (message_type.endsWith("Request")) {
-            contents += `            SupportedMessage::${message_type}(_) => true,
+            contents += `${cfg_attribute(message_type, "            ")}            SupportedMessage::${message_type}(_) => true,
 `;
         }
     });
@@ -128,7 +159,7 @@ impl SupportedMessage {
 `;
     _.each(message_types, message_type => {
         if (message_type.endsWith("Request")) {
-            contents += `            SupportedMessage::${message_type}(r) => &r.request_header,
+            contents += `${cfg_attribute(message_type, "            ")}            SupportedMessage::${message_type}(r) => &r.request_header,
 `;
         }
     });
@@ -141,7 +172,7 @@ impl SupportedMessage {
 `;
     _.each(message_types, message_type => {
         if (message_type.endsWith("Response") || message_type === "ServiceFault") {
-            contents += `            SupportedMessage::${message_type}(_) => true,
+            contents += `${cfg_attribute(message_type, "            ")}            SupportedMessage::${message_type}(_) => true,
 `;
         }
     });
@@ -154,7 +185,7 @@ impl SupportedMessage {
 `;
     _.each(message_types, message_type => {
         if (message_type.endsWith("Response") || message_type === "ServiceFault") {
-            contents += `            SupportedMessage::${message_type}(r) => &r.response_header,
+            contents += `${cfg_attribute(message_type, "            ")}            SupportedMessage::${message_type}(r) => &r.response_header,
 `;
         }
     });
@@ -162,13 +193,22 @@ impl SupportedMessage {
         }
     }
 
+    /// The object ids of the binary encodings of the messages that \`decode_by_object_id()\` decodes
+    pub const OBJECT_IDS: &[ObjectId] = &[
+`;
+    _.each(message_types, message_type => {
+        contents += `${cfg_attribute(message_type, "        ")}        ObjectId::${message_type}_Encoding_DefaultBinary,
+`;
+    });
+    contents += `    ];
+
     pub fn decode_by_object_id<S: Read>(stream: &mut S, object_id: ObjectId, decoding_options: &DecodingOptions) -> EncodingResult<Self> {
         trace!("decoding object_id {:?}", object_id);
         let decoded_message = match object_id {
 `;
 
     _.each(message_types, message_type => {
-        contents += `            ObjectId::${message_type}_Encoding_DefaultBinary => {
+        contents += `${cfg_attribute(message_type, "            ")}            ObjectId::${message_type}_Encoding_DefaultBinary => {
                 ${message_type}::decode(stream, decoding_options)?.into()
             }
 `;
@@ -188,7 +228,7 @@ supported_messages_enum![
 `;
 
     _.each(message_types, message_type => {
-        contents += `    ${message_type},
+        contents += `${cfg_attribute(message_type, "    ")}    ${message_type},
 `;
     });
 
@@ -88,6 +88,9 @@ exports.generate = (xml_file, xml_data, rs_module, config) => {
             node_start += max_nodes_per_file;
         }
     }
+    if (config.typed_wrappers) {
+        modules.push(generate_typed_wrappers_file(xml_file, rs_module, nodes, alias_map, config));
+    }
     return modules;
 }
 
@@ -358,12 +361,15 @@ function insert_node(fn_name, node_type, node, alias_map, config) {
 
     // Process other references
     if (_.has(node, "References")) {
-        node_references = node_references.concat(get_node_references(node["References"][0], config));
+        node_references = node_references.concat(get_node_references(node["References"][0], alias_map, config));
     }
     if (node_references.length > 0) {
+        // The references share one type, so when any reference type is a node id they all are
+        let any_node_id = node_references.some(r => r.is_node_id);
         contents += `${indent}let _ = address_space.insert(node, Some(&[\n`;
         _.each(node_references, r => {
-            contents += `${indent}    (&${r.node_other}, &${r.reference_type}, ${r.reference_direction}),\n`;
+            let reference_type = (any_node_id && !r.is_node_id) ? `NodeId::from(&${r.reference_type})` : r.reference_type;
+            contents += `${indent}    (&${r.node_other}, &${reference_type}, ${r.reference_direction}),\n`;
         });
         contents += `${indent}]));\n`;
     } else {
@@ -382,7 +388,7 @@ function insert_node(fn_name, node_type, node, alias_map, config) {
     return contents;
 }
 
-function get_node_references(reference_element, config) {
+function get_node_references(reference_element, alias_map, config) {
     let node_references = [];
     if (_.has(reference_element, "Reference")) {
         _.each(reference_element["Reference"], reference => {
@@ -393,17 +399,333 @@ function get_node_references(reference_element, config) {
             let reference_type = reference["$"]["ReferenceType"];
             let reference_direction = is_forward ? "ReferenceDirection::Forward" : "ReferenceDirection::Inverse";
 
-            let node_id = util.parse_node_id(reference_type);
-            if (node_id) {
-                // TODO
+            // A reference type may be a node id, e.g. i=37 for HasModellingRule, or an alias of one. Aliases of standard
+            // reference types are the names of the standard reference types.
+            let aliased_reference_type = _.has(alias_map, reference_type) ? alias_map[reference_type] : reference_type;
+            let node_id = util.parse_node_id(aliased_reference_type);
+            if (node_id && (aliased_reference_type === reference_type || node_id.ns > 0)) {
+                node_references.push({
+                    node_other: node_other,
+                    reference_type: node_id_ctor(aliased_reference_type, config),
+                    reference_direction: reference_direction,
+                    is_node_id: true
+                })
             } else {
                 node_references.push({
                     node_other: node_other,
                     reference_type: `ReferenceTypeId::${reference_type}`,
-                    reference_direction: reference_direction
+                    reference_direction: reference_direction,
+                    is_node_id: false
                 })
             }
         });
     }
     return node_references;
 }
+
+// The typed wrappers are generated from the standard references and modelling rule
+const HAS_COMPONENT = "i=47";
+const HAS_PROPERTY = "i=46";
+const HAS_SUBTYPE = "i=45";
+const HAS_TYPE_DEFINITION = "i=40";
+const HAS_MODELLING_RULE = "i=37";
+const MODELLING_RULE_MANDATORY = "i=78";
+const PROPERTY_TYPE = "i=68";
+const BASE_DATA_VARIABLE_TYPE = "i=63";
+
+function resolve_alias(alias_map, snippet) {
+    return _.has(alias_map, snippet) ? alias_map[snippet] : snippet;
+}
+
+function raw_node_references(node, alias_map) {
+    // Returns the references of a node with aliases resolved, e.g. HasComponent becomes i=47
+    let references = [];
+    if (_.has(node, "References")) {
+        _.each(node["References"][0]["Reference"], reference => {
+            references.push({
+                reference_type: resolve_alias(alias_map, reference["$"]["ReferenceType"]),
+                target: resolve_alias(alias_map, reference["_"].trim()),
+                is_forward: !_.has(reference["$"], "IsForward") || reference["$"]["IsForward"] === "true"
+            });
+        });
+    }
+    return references;
+}
+
+function qualified_name_ctor(browse_name, config) {
+    // This turns a browse name like "1:SerialNumber" into a qualified name
+    let idx = browse_name.indexOf(":");
+    let ns = 0;
+    let name = browse_name;
+    if (idx > 0 && /^[0-9]+$/.test(browse_name.substr(0, idx))) {
+        ns = parseInt(browse_name.substr(0, idx));
+        name = browse_name.substr(idx + 1);
+        if (ns > 0 && config.namespace_index !== -1) {
+            ns = config.namespace_index;
+        }
+    }
+    return {ns: ns, name: name, ctor: `QualifiedName::new(${ns}, "${name}")`};
+}
+
+function wrapper_struct_name(browse_name) {
+    let name = qualified_name_ctor(browse_name, {namespace_index: -1}).name;
+    return _.upperFirst(_.camelCase(name));
+}
+
+function find_mandatory_children(type_id, node_map, alias_map) {
+    // Finds the mandatory components and properties of a type, including those of its supertypes that are in the
+    // same node set. A child of a subtype replaces the child of a supertype with the same browse name.
+    let supertypes = [];
+    let visited = {};
+    let current_id = type_id;
+    while (current_id && _.has(node_map, current_id) && !_.has(visited, current_id)) {
+        visited[current_id] = true;
+        supertypes.unshift(current_id);
+        let supertype = _.find(raw_node_references(node_map[current_id][1], alias_map), r => r.reference_type === HAS_SUBTYPE && !r.is_forward);
+        current_id = supertype ? supertype.target : undefined;
+    }
+
+    let children = [];
+    _.each(supertypes, parent_id => {
+        let child_ids = [];
+        _.each(raw_node_references(node_map[parent_id][1], alias_map), r => {
+            if (r.is_forward && (r.reference_type === HAS_COMPONENT || r.reference_type === HAS_PROPERTY)) {
+                child_ids.push({id: r.target, reference_type: r.reference_type});
+            }
+        });
+        _.each(node_map, (tuple, child_id) => {
+            _.each(raw_node_references(tuple[1], alias_map), r => {
+                if (!r.is_forward && r.target === parent_id && (r.reference_type === HAS_COMPONENT || r.reference_type === HAS_PROPERTY)) {
+                    if (!_.find(child_ids, c => c.id === child_id)) {
+                        child_ids.push({id: child_id, reference_type: r.reference_type});
+                    }
+                }
+            });
+        });
+        _.each(child_ids, child => {
+            if (!_.has(node_map, child.id)) {
+                return;
+            }
+            let tuple = node_map[child.id];
+            let references = raw_node_references(tuple[1], alias_map);
+            let is_mandatory = _.find(references, r => r.is_forward && r.reference_type === HAS_MODELLING_RULE && r.target === MODELLING_RULE_MANDATORY);
+            if (!is_mandatory) {
+                return;
+            }
+            let type_definition = _.find(references, r => r.is_forward && r.reference_type === HAS_TYPE_DEFINITION);
+            let browse_name = tuple[1]["$"]["BrowseName"];
+            _.remove(children, c => c.browse_name === browse_name);
+            children.push({
+                id: child.id,
+                node_type: tuple[0],
+                node: tuple[1],
+                browse_name: browse_name,
+                reference_type: child.reference_type,
+                type_definition: type_definition ? type_definition.target : undefined
+            });
+        });
+    });
+    return children;
+}
+
+function generate_typed_wrappers_file(xml_name, rs_name, nodes, alias_map, config) {
+    let module_name = `${rs_name}_types`;
+    let file_name = `${module_name}.rs`;
+
+    let node_map = {};
+    _.each(nodes, tuple => {
+        node_map[tuple[1]["$"]["NodeId"]] = tuple;
+    });
+
+    // Each object type of the node set is wrapped by a struct of the same name
+    let object_types = _.filter(nodes, tuple => tuple[0] === "ObjectType");
+    let wrappers = {};
+    _.each(object_types, tuple => {
+        wrappers[tuple[1]["$"]["NodeId"]] = wrapper_struct_name(tuple[1]["$"]["BrowseName"]);
+    });
+
+    let contents = "";
+    if (config.copyright) {
+        contents = `// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+`;
+    }
+
+    contents += `// This file was autogenerated from ${xml_name} by ${config.autogenerated_by}
+// DO NOT EDIT THIS FILE
+
+#[allow(unused_imports)]
+use std::{convert::TryFrom, str::FromStr};
+
+#[allow(unused_imports)]
+use crate::{
+    server::address_space::{relative_path::find_node_from_browse_path, types::*},
+    types::{
+        DataTypeId, LocalizedText, NodeId, ObjectTypeId, QualifiedName, ReferenceTypeId, VariableTypeId,
+    },
+};
+`;
+
+    _.each(object_types, tuple => {
+        contents += generate_typed_wrapper(tuple[1], node_map, wrappers, alias_map, config);
+    });
+
+    util.write_to_file(`${config.destination_dir}/${file_name}`, contents);
+    return module_name;
+}
+
+function generate_typed_wrapper(type_node, node_map, wrappers, alias_map, config) {
+    let type_id = type_node["$"]["NodeId"];
+    let type_browse_name = qualified_name_ctor(type_node["$"]["BrowseName"], config).name;
+    let struct_name = wrappers[type_id];
+    let is_abstract = _.has(type_node["$"], "IsAbstract") && type_node["$"]["IsAbstract"] === "true";
+    let children = find_mandatory_children(type_id, node_map, alias_map);
+
+    let contents = `
+/// An object of the \`${type_browse_name}\` object type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ${struct_name} {
+    node_id: NodeId,
+}
+
+impl ${struct_name} {
+    /// Returns the node id of the object type.
+    pub fn type_id() -> NodeId {
+        ${node_id_ctor(type_id, config)}
+    }
+
+    /// Wraps an object in the address space, or returns \`None\` if it is not an object of the type.
+    pub fn from_node_id(address_space: &AddressSpace, node_id: &NodeId) -> Option<Self> {
+        if address_space.has_reference(node_id, &Self::type_id(), ReferenceTypeId::HasTypeDefinition) {
+            Some(Self { node_id: node_id.clone() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the node id of the object.
+    pub fn node_id(&self) -> &NodeId {
+        &self.node_id
+    }
+`;
+
+    if (!is_abstract) {
+        contents += `
+    /// Inserts an object of the type into the address space, together with its mandatory components and properties.
+    pub fn insert<R, S>(address_space: &mut AddressSpace, node_id: &NodeId, browse_name: R, display_name: S, parent_id: &NodeId) -> Self
+    where
+        R: Into<QualifiedName>,
+        S: Into<LocalizedText>,
+    {
+        ObjectBuilder::new(node_id, browse_name, display_name)
+            .has_type_definition(Self::type_id())
+            .organized_by(parent_id.clone())
+            .insert(address_space);
+        Self::insert_children(address_space, node_id);
+        Self { node_id: node_id.clone() }
+    }
+`;
+    }
+
+    contents += `
+    #[allow(dead_code, unused_variables)]
+    fn insert_children(address_space: &mut AddressSpace, node_id: &NodeId) {`;
+    _.each(children, child => {
+        let browse_name = qualified_name_ctor(child.browse_name, config);
+        let display_name = _.has(child.node, "DisplayName") ? child.node["DisplayName"][0] : browse_name.name;
+        if (_.isObject(display_name)) {
+            display_name = display_name["_"];
+        }
+        let reference = child.reference_type === HAS_PROPERTY ? "property_of" : "component_of";
+        if (child.node_type === "Variable") {
+            let data_type = _.has(child.node["$"], "DataType") ? data_type_node_id(alias_map, child.node["$"]["DataType"], config) : "DataTypeId::BaseDataType";
+            let type_definition = child.type_definition || (child.reference_type === HAS_PROPERTY ? PROPERTY_TYPE : BASE_DATA_VARIABLE_TYPE);
+            contents += `
+        VariableBuilder::new(&NodeId::next_numeric(node_id.namespace), ${browse_name.ctor}, "${display_name}")
+            .data_type(${data_type})`;
+            if (_.has(child.node["$"], "ValueRank")) {
+                contents += `
+            .value_rank(${child.node["$"]["ValueRank"]})`;
+            }
+            contents += `
+            .has_type_definition(${node_id_ctor(type_definition, config)})
+            .${reference}(node_id.clone())
+            .insert(address_space);`;
+        } else if (child.node_type === "Object") {
+            let child_wrapper = child.type_definition ? wrappers[child.type_definition] : undefined;
+            contents += `
+        let child_id = NodeId::next_numeric(node_id.namespace);
+        ObjectBuilder::new(&child_id, ${browse_name.ctor}, "${display_name}")`;
+            if (child.type_definition) {
+                contents += `
+            .has_type_definition(${node_id_ctor(child.type_definition, config)})`;
+            }
+            contents += `
+            .${reference}(node_id.clone())
+            .insert(address_space);`;
+            if (child_wrapper) {
+                contents += `
+        ${child_wrapper}::insert_children(address_space, &child_id);`;
+            }
+        } else if (child.node_type === "Method") {
+            // Methods are not copied, the instance references the method of the type
+            contents += `
+        address_space.insert_reference(node_id, &${node_id_ctor(child.id, config)}, ReferenceTypeId::HasComponent);`;
+        }
+    });
+    contents += `
+    }
+`;
+
+    _.each(children, child => {
+        let browse_name = qualified_name_ctor(child.browse_name, config);
+        let fn_name = _.snakeCase(browse_name.name);
+        let kind = child.reference_type === HAS_PROPERTY ? "property" : "component";
+        if (child.node_type === "Variable") {
+            contents += `
+    /// Returns the \`${browse_name.name}\` ${kind}.
+    pub fn ${fn_name}<'a>(&self, address_space: &'a AddressSpace) -> Option<&'a Variable> {
+        match find_node_from_browse_path(address_space, &self.node_id, &[${browse_name.ctor}]) {
+            Ok(NodeType::Variable(variable)) => Some(variable),
+            _ => None,
+        }
+    }
+`;
+        } else if (child.node_type === "Object") {
+            let child_wrapper = child.type_definition ? wrappers[child.type_definition] : undefined;
+            if (child_wrapper) {
+                contents += `
+    /// Returns the \`${browse_name.name}\` ${kind}.
+    pub fn ${fn_name}(&self, address_space: &AddressSpace) -> Option<${child_wrapper}> {
+        match find_node_from_browse_path(address_space, &self.node_id, &[${browse_name.ctor}]) {
+            Ok(NodeType::Object(object)) => Some(${child_wrapper} { node_id: object.node_id() }),
+            _ => None,
+        }
+    }
+`;
+            } else {
+                contents += `
+    /// Returns the \`${browse_name.name}\` ${kind}.
+    pub fn ${fn_name}<'a>(&self, address_space: &'a AddressSpace) -> Option<&'a Object> {
+        match find_node_from_browse_path(address_space, &self.node_id, &[${browse_name.ctor}]) {
+            Ok(NodeType::Object(object)) => Some(object),
+            _ => None,
+        }
+    }
+`;
+            }
+        } else if (child.node_type === "Method") {
+            contents += `
+    /// Returns the node id of the \`${browse_name.name}\` method.
+    pub fn ${fn_name}_method_id() -> NodeId {
+        ${node_id_ctor(child.id, config)}
+    }
+`;
+        }
+    });
+
+    contents += `}
+`;
+    return contents;
+}
@@ -1,7 +1,7 @@
 exports.schema_dir = `${__dirname}/schemas/1.0.4`;
-exports.rs_types_dir = `${__dirname}/../../lib/src/types/service_types`;
-exports.rs_status_codes_dir = `${__dirname}/../../lib/src/types/`;
-exports.rs_node_ids_dir = `${__dirname}/../../lib/src/types/`;
+exports.rs_types_dir = `${__dirname}/../../types/src/service_types`;
+exports.rs_status_codes_dir = `${__dirname}/../../types/src/`;
+exports.rs_node_ids_dir = `${__dirname}/../../types/src/`;
 exports.rs_supported_message_dir = `${__dirname}/../../lib/src/core/`;
 exports.rs_address_space_dir = `${__dirname}/../../lib/src/server/address_space/generated`;
 
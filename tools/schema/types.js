@@ -87,13 +87,14 @@ function convertFieldName(name) {
     return _.snakeCase(name);
 }
 
-function errorResponseForEnum(name) {
+// Some enums decode an invalid value as their Invalid variant rather than failing
+function invalidValueForEnum(name) {
     switch (name) {
         case "BrowseDirection":
         case "TimestampsToReturn":
-            return "Ok(Self::Invalid)";
+            return "Self::Invalid";
         default:
-            return "Err(StatusCode::BadUnexpectedError)";
+            return null;
     }
 }
 exports.from_xml = (bsd_file, rs_module) => {
@@ -192,7 +193,7 @@ exports.from_xml = (bsd_file, rs_module) => {
                 }
 
                 // The error code is what to return if the value does not match the value expected by
-                enum_type.error_code = errorResponseForEnum(enum_type.name);
+                enum_type.invalid_value = invalidValueForEnum(enum_type.name);
 
                 if (_.has(element, "opc:Documentation")) {
                     enum_type.documentation = element["opc:Documentation"];
@@ -287,6 +288,43 @@ impl BinaryEncoder<${enum_type.name}> for ${enum_type.name} {
         Ok(${enum_type.name}::from_bits_truncate(${enum_type.type}::decode(stream, decoding_options)?))
     }
 }
+
+impl UaOptionSet for ${enum_type.name} {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[`;
+        // Only the options with a bit are listed, e.g. not None
+        // Signed bits are cast through the unsigned type of the same size to keep the top bit
+        let unsigned_type = enum_type.type.replace("i", "u");
+        let to_u64 = unsigned_type === enum_type.type ? "self.bits as u64" : `self.bits as ${unsigned_type} as u64`;
+        let from_u64 = unsigned_type === enum_type.type ? `bits as ${enum_type.type}` : `bits as ${unsigned_type} as ${enum_type.type}`;
+        _.each(enum_type.values, (value) => {
+            if (value.value > 0) {
+                contents += `
+            (${Math.log2(value.value)}, "${value.name}"),`;
+            }
+        });
+        contents += `
+        ]
+    }
+
+    fn size() -> usize {
+        ${enum_type.size}
+    }
+
+    fn to_u64(&self) -> u64 {
+        ${to_u64}
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        ${enum_type.name}::from_bits_truncate(${from_u64})
+    }
+}
+
+impl From<${enum_type.name}> for Variant {
+    fn from(v: ${enum_type.name}) -> Self {
+        Variant::from(v.bits)
+    }
+}
 `
 
     return contents;
@@ -302,10 +340,13 @@ function generate_enum_types(bsd_file, enums, rs_types_dir) {
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 #![allow(non_upper_case_globals)]
-use std::io::{Read, Write};
-use crate::types::{
+use core::convert::TryFrom;
+use crate::{
     encoding::*,
+    enumeration::{UaEnum, UaOptionSet},
+    io::{Read, Write},
     status_codes::StatusCode,
+    variant::Variant,
 };
 use bitflags;
 `;
@@ -321,7 +362,7 @@ use bitflags;
         } else {
             let derivations = "Debug, Copy, Clone, PartialEq"
             if (_.includes(serde_supported_types, enum_type.name)) {
-                derivations += ", Serialize";
+                derivations += ", Serialize, Deserialize";
             }
             contents += `
 #[derive(${derivations})]
@@ -334,6 +375,37 @@ pub enum ${enum_type.name} {`;
         contents += `
 }
 
+impl TryFrom<${enum_type.type}> for ${enum_type.name} {
+    type Error = StatusCode;
+
+    fn try_from(value: ${enum_type.type}) -> Result<Self, StatusCode> {
+        match value {`;
+
+        _.each(enum_type.values, (value) => {
+            contents += `
+            ${value.value} => Ok(Self::${value.name}),`;
+        });
+
+        contents += `
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for ${enum_type.name} {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[`;
+
+        _.each(enum_type.values, (value) => {
+            contents += `
+            (${value.value}, "${value.name}"),`;
+        });
+
+        contents += `
+        ]
+    }
+}
+
 impl BinaryEncoder<${enum_type.name}> for ${enum_type.name} {
     fn byte_len(&self) -> usize {
         ${enum_type.size}
@@ -344,20 +416,23 @@ impl BinaryEncoder<${enum_type.name}> for ${enum_type.name} {
     }
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_${enum_type.type}(stream)?;
-        match value {`;
+        let value = read_${enum_type.type}(stream)?;`;
 
-        _.each(enum_type.values, (value) => {
-            contents += `
-            ${value.value} => Ok(Self::${value.name}),`;
-        });
-
-        contents += `
-            v => {
-                error!("Invalid value {} for enum ${enum_type.name}", v);
-                ${enum_type.error_code}
+            if (enum_type.invalid_value) {
+                contents += `
+        Ok(Self::try_from(value).unwrap_or_else(|_| {
+            error!("Invalid value {} for enum ${enum_type.name}", value);
+            ${enum_type.invalid_value}
+        }))`;
+            } else {
+                contents += `
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum ${enum_type.name}", value);
+            StatusCode::BadDecodingError
+        })`;
             }
-        }
+
+            contents += `
     }
 }`
         }
@@ -373,7 +448,7 @@ impl BinaryEncoder<${enum_type.name}> for ${enum_type.name} {
 
 function generate_type_imports(structured_types, fields_to_add, fields_to_hide, has_message_info) {
     let imports = `#[allow(unused_imports)]
-use crate::types::{
+use crate::{
     encoding::*,
     basic_types::*,
 `;
@@ -449,8 +524,11 @@ function generate_structured_type_file(bsd_file, structured_types, structured_ty
 //
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
-use std::io::{Read, Write};
 `;
+    if (_.some(structured_type.fields_to_add, field => _.has(field, 'is_array'))) {
+        contents += `use alloc::vec::Vec;\n`;
+    }
+    contents += `use crate::io::{Read, Write};\n`;
     contents += generate_type_imports(structured_types, structured_type.fields_to_add, structured_type.fields_to_hide, has_message_info);
     contents += "\n";
 
@@ -460,7 +538,7 @@ use std::io::{Read, Write};
 
     let derivations = "Debug, Clone, PartialEq";
     if (_.includes(serde_supported_types, structured_type.name)) {
-        derivations += ", Serialize";
+        derivations += ", Serialize, Deserialize";
     }
 
     contents += `#[derive(${derivations})]
@@ -0,0 +1,82 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Benchmarks of the binary encoding and decoding of Variant arrays, which make up the bulk of
+//! the reads, writes and data change notifications of most servers.
+
+use std::io::Cursor;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use opcua_types::*;
+
+/// The numbers of elements of the arrays that are encoded
+const ARRAY_LENGTHS: [usize; 2] = [100, 10000];
+
+fn sample_arrays(length: usize) -> Vec<(&'static str, Variant)> {
+    vec![
+        (
+            "Int32",
+            Variant::from((0..length as i32).collect::<Vec<i32>>()),
+        ),
+        (
+            "Double",
+            Variant::from((0..length).map(|i| i as f64 * 0.5).collect::<Vec<f64>>()),
+        ),
+        (
+            "String",
+            Variant::from(
+                (0..length)
+                    .map(|i| format!("Value {}", i))
+                    .collect::<Vec<String>>(),
+            ),
+        ),
+    ]
+}
+
+fn decoding_options() -> DecodingOptions {
+    DecodingOptions {
+        max_array_length: ARRAY_LENGTHS[ARRAY_LENGTHS.len() - 1],
+        ..Default::default()
+    }
+}
+
+fn variant_array_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("variant_array_encode");
+    for length in ARRAY_LENGTHS {
+        for (name, variant) in sample_arrays(length) {
+            let byte_len = variant.byte_len();
+            group.throughput(Throughput::Bytes(byte_len as u64));
+            group.bench_with_input(BenchmarkId::new(name, length), &variant, |b, variant| {
+                let mut stream = Cursor::new(vec![0u8; byte_len]);
+                b.iter(|| {
+                    stream.set_position(0);
+                    variant.encode(&mut stream).unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn variant_array_decode(c: &mut Criterion) {
+    let decoding_options = decoding_options();
+    let mut group = c.benchmark_group("variant_array_decode");
+    for length in ARRAY_LENGTHS {
+        for (name, variant) in sample_arrays(length) {
+            let bytes = variant.encode_to_vec();
+            group.throughput(Throughput::Bytes(bytes.len() as u64));
+            group.bench_with_input(BenchmarkId::new(name, length), &bytes, |b, bytes| {
+                b.iter(|| {
+                    let mut stream = Cursor::new(black_box(bytes.as_slice()));
+                    Variant::decode(&mut stream, &decoding_options).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, variant_array_encode, variant_array_decode);
+criterion_main!(benches);
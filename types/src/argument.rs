@@ -2,10 +2,14 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::io::{Read, Write};
+use alloc::vec::Vec;
 
-use crate::types::{
-    encoding::*, localized_text::LocalizedText, node_id::NodeId, status_codes::StatusCode,
+use crate::{
+    encoding::*,
+    io::{Read, Write},
+    localized_text::LocalizedText,
+    node_id::NodeId,
+    status_codes::StatusCode,
     string::UAString,
 };
 
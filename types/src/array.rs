@@ -1,5 +1,7 @@
-use crate::types::variant::*;
-use crate::types::StatusCode;
+use alloc::vec::Vec;
+
+use crate::variant::*;
+use crate::StatusCode;
 
 /// An array is a vector of values with an optional number of dimensions.
 /// It is expected that the multi-dimensional array is valid, or it might not be encoded or decoded
@@ -6,7 +6,7 @@
 
 // Attributes sometimes required and sometimes optional
 
-use std::{error::Error, fmt};
+use core::{error::Error, fmt};
 
 #[derive(Debug)]
 pub struct AttributeIdError;
@@ -19,7 +19,7 @@ impl fmt::Display for AttributeIdError {
 
 impl Error for AttributeIdError {}
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum AttributeId {
     NodeId = 1,
     NodeClass = 2,
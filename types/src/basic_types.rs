@@ -3,9 +3,10 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 //! Contains definitions of the simple OPC UA scalar types.
-use std::io::{Read, Write};
-
-use crate::types::encoding::*;
+use crate::{
+    encoding::*,
+    io::{Read, Write},
+};
 
 // OPC UA Part 6 - Mappings 1.03 Specification
 
@@ -4,14 +4,17 @@
 
 //! Contains the implementation of `ByteString`.
 
-use std::convert::TryFrom;
-use std::io::{Read, Write};
+use alloc::{format, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::{fs::File, path::Path};
 
-use crate::types::{
+use crate::{
     encoding::{
         process_decode_io_result, process_encode_io_result, write_i32, BinaryEncoder,
         DecodingOptions, EncodingResult,
     },
+    io::{Read, Write},
     status_codes::StatusCode,
     Guid,
 };
@@ -24,35 +27,26 @@ pub struct ByteString {
 
 impl AsRef<[u8]> for ByteString {
     fn as_ref(&self) -> &[u8] {
-        if self.value.is_none() {
-            &[]
-        } else {
-            self.value.as_ref().unwrap()
-        }
+        self.value.as_deref().unwrap_or(&[])
     }
 }
 
 impl BinaryEncoder<ByteString> for ByteString {
     fn byte_len(&self) -> usize {
         // Length plus the actual length of bytes (if not null)
-        4 + if self.value.is_none() {
-            0
-        } else {
-            self.value.as_ref().unwrap().len()
-        }
+        4 + self.value.as_ref().map_or(0, |v| v.len())
     }
 
     fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
         // Strings are uncoded as UTF8 chars preceded by an Int32 length. A -1 indicates a null string
-        if self.value.is_none() {
-            write_i32(stream, -1)
-        } else {
+        if let Some(ref value) = self.value {
             let mut size: usize = 0;
-            let value = self.value.as_ref().unwrap();
             size += write_i32(stream, value.len() as i32)?;
             size += process_encode_io_result(stream.write(value))?;
             assert_eq!(size, self.byte_len());
             Ok(size)
+        } else {
+            write_i32(stream, -1)
         }
     }
 
@@ -95,6 +89,13 @@ impl From<Vec<u8>> for ByteString {
     }
 }
 
+impl From<ByteString> for Vec<u8> {
+    fn from(value: ByteString) -> Self {
+        // A null byte string becomes empty bytes
+        value.value.unwrap_or_default()
+    }
+}
+
 impl From<Guid> for ByteString {
     fn from(value: Guid) -> Self {
         ByteString::from(value.as_bytes().to_vec())
@@ -120,9 +121,9 @@ impl TryFrom<&ByteString> for Guid {
     }
 }
 
-impl Into<String> for ByteString {
-    fn into(self) -> String {
-        self.as_base64()
+impl From<ByteString> for String {
+    fn from(value: ByteString) -> Self {
+        value.as_base64()
     }
 }
 
@@ -176,10 +177,68 @@ impl ByteString {
         }
     }
 
+    /// Creates a byte string from a string of hexadecimal values for each byte, e.g. `"0a1B"`.
+    /// Returns `None` if the string has an odd length or anything other than hex digits.
+    pub fn from_hex(data: &str) -> Option<ByteString> {
+        if !data.len().is_multiple_of(2) || !data.bytes().all(|b| b.is_ascii_hexdigit()) {
+            None
+        } else {
+            (0..data.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&data[i..i + 2], 16).ok())
+                .collect::<Option<Vec<u8>>>()
+                .map(Self::from)
+        }
+    }
+
+    /// Returns the byte string as a string using lowercase hexadecimal values for each byte. A
+    /// null byte string is an empty string.
+    pub fn as_hex_string(&self) -> String {
+        let bytes = self.as_ref();
+        let mut hex_string = String::with_capacity(bytes.len() * 2);
+        for b in bytes {
+            hex_string.push_str(&format!("{:02x}", b))
+        }
+        hex_string
+    }
+
+    /// Creates a byte string by reading a reader to the end. Fails with an `InvalidData` error
+    /// rather than allocating more if the reader holds more than `max_length` bytes.
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: std::io::Read>(
+        reader: R,
+        max_length: usize,
+    ) -> std::io::Result<ByteString> {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut reader.take(max_length as u64 + 1), &mut bytes)?;
+        if bytes.len() > max_length {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("ByteString length exceeds limit {}", max_length),
+            ))
+        } else {
+            Ok(Self::from(bytes))
+        }
+    }
+
+    /// Creates a byte string from the contents of a file, e.g. a certificate or a file being
+    /// transferred. Fails if the file holds more than `max_length` bytes.
+    #[cfg(feature = "std")]
+    pub fn from_file<P: AsRef<Path>>(path: P, max_length: usize) -> std::io::Result<ByteString> {
+        Self::from_reader(File::open(path)?, max_length)
+    }
+
+    /// Writes the bytes of the byte string to a writer. A null byte string writes nothing.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        std::io::Write::write_all(&mut writer, self.as_ref())
+    }
+
     /// This function is meant for use with NumericRange. It creates a substring from this string
     /// from min up to and inclusive of max. Note that min must have an index within the string
     /// but max is allowed to be beyond the end in which case the remainder of the string is
     /// returned (see docs for NumericRange).
+    #[allow(clippy::result_unit_err)]
     pub fn substring(&self, min: usize, max: usize) -> Result<ByteString, ()> {
         if let Some(ref v) = self.value {
             if min >= v.len() {
@@ -237,3 +296,52 @@ fn bytestring_substring() {
     assert!(v.substring(4, 10000).is_err());
     assert!(ByteString::null().substring(0, 0).is_err());
 }
+
+#[test]
+fn bytestring_hex() {
+    let v = ByteString::from_hex("00ff0A1b").unwrap();
+    assert_eq!(v.as_ref(), &[0x00, 0xff, 0x0a, 0x1b]);
+    assert_eq!(v.as_hex_string(), "00ff0a1b");
+    assert_eq!(ByteString::from_hex("").unwrap(), ByteString::from(&[]));
+    assert_eq!(ByteString::null().as_hex_string(), "");
+    assert!(ByteString::from_hex("0").is_none());
+    assert!(ByteString::from_hex("0g").is_none());
+    assert!(ByteString::from_hex("+f").is_none());
+    assert!(ByteString::from_hex("\u{e9}").is_none());
+}
+
+#[test]
+fn bytestring_base64() {
+    let v = ByteString::from(b"hello world");
+    assert_eq!(v.as_base64(), "aGVsbG8gd29ybGQ=");
+    assert_eq!(ByteString::from_base64("aGVsbG8gd29ybGQ=").unwrap(), v);
+    assert!(ByteString::from_base64("not base64!").is_none());
+}
+
+#[test]
+fn bytestring_reader() {
+    let a = [0x1u8, 0x2u8, 0x3u8, 0x4u8];
+    let v = ByteString::from_reader(&a[..], 4).unwrap();
+    assert_eq!(v.as_ref(), &a);
+    let err = ByteString::from_reader(&a[..], 3).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut out = Vec::new();
+    v.write_to(&mut out).unwrap();
+    assert_eq!(out, a);
+    assert_eq!(Vec::<u8>::from(v), a);
+    assert!(Vec::<u8>::from(ByteString::null()).is_empty());
+}
+
+#[test]
+fn bytestring_file() {
+    let dir = tempdir::TempDir::new("bytestring").unwrap();
+    let path = dir.path().join("bytes.bin");
+    ByteString::from(b"file contents")
+        .write_to(File::create(&path).unwrap())
+        .unwrap();
+    let v = ByteString::from_file(&path, 1024).unwrap();
+    assert_eq!(v.as_ref(), b"file contents");
+    assert!(ByteString::from_file(&path, 4).is_err());
+    assert!(ByteString::from_file(dir.path().join("missing.bin"), 1024).is_err());
+}
@@ -0,0 +1,118 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`Clock`] that supplies the current time to time dependent logic.
+//!
+//! `DateTime::now()`, session timeouts, subscription publishing intervals and secure channel token
+//! lifetimes all obtain the time from the installed clock. Normally this is the [`SystemClock`]
+//! but tests can install a [`MockClock`] with [`set_clock`] and move it forwards to exercise timeouts
+//! and subscription behaviour without real sleeps. The clock is shared by everything in the
+//! process.
+//!
+//! Without the `std` feature there is no system time to read, so the clock stands still at the
+//! null date time until the application installs a clock of its own, e.g. one that reads a real
+//! time clock.
+//!
+//! [`Clock`]: ./trait.Clock.html
+//! [`SystemClock`]: ./struct.SystemClock.html
+//! [`MockClock`]: ./struct.MockClock.html
+//! [`set_clock`]: ./fn.set_clock.html
+
+use alloc::sync::Arc;
+
+use chrono::Duration;
+#[cfg(feature = "std")]
+use chrono::Utc;
+
+use crate::{
+    date_time::DateTimeUtc,
+    sync::{Mutex, RwLock},
+};
+
+/// A source of the current time
+pub trait Clock: Send + Sync {
+    /// Returns the current time
+    fn now(&self) -> DateTimeUtc;
+}
+
+/// A clock that returns the time of the system
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTimeUtc {
+        Utc::now()
+    }
+}
+
+/// A clock for tests that only moves when it is told to
+pub struct MockClock {
+    now: Mutex<DateTimeUtc>,
+}
+
+#[cfg(feature = "std")]
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTimeUtc {
+        *self.now.lock()
+    }
+}
+
+impl MockClock {
+    /// Creates a clock that is stopped at the supplied time
+    pub fn new(now: DateTimeUtc) -> MockClock {
+        MockClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Sets the time of the clock
+    pub fn set(&self, now: DateTimeUtc) {
+        *self.now.lock() = now;
+    }
+
+    /// Moves the clock forwards, or backwards if the duration is negative
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock();
+        *now += duration;
+    }
+}
+
+lazy_static! {
+    static ref CLOCK: RwLock<Arc<dyn Clock>> = RwLock::new(default_clock());
+}
+
+#[cfg(feature = "std")]
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[cfg(not(feature = "std"))]
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(MockClock::new(
+        crate::date_time::DateTime::null().as_chrono(),
+    ))
+}
+
+/// Installs the clock that supplies the current time, replacing the previous one.
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    *CLOCK.write() = clock;
+}
+
+/// Restores the system clock, or the stopped clock without std.
+pub fn reset_clock() {
+    set_clock(default_clock());
+}
+
+/// Returns the current time from the installed clock.
+pub fn now() -> DateTimeUtc {
+    CLOCK.read().now()
+}
@@ -4,12 +4,21 @@
 
 //! Contains the implementation of `DataValue`.
 
-use std::io::{Read, Write};
-
-use crate::types::{
-    byte_string::ByteString, date_time::*, encoding::*, guid::Guid, localized_text::LocalizedText,
-    node_id::NodeId, qualified_name::QualifiedName, service_types::TimestampsToReturn,
-    status_codes::StatusCode, string::UAString, variant::Variant,
+use alloc::string::String;
+
+use crate::{
+    byte_string::ByteString,
+    date_time::*,
+    encoding::*,
+    guid::Guid,
+    io::{Read, Write},
+    localized_text::LocalizedText,
+    node_id::NodeId,
+    qualified_name::QualifiedName,
+    service_types::TimestampsToReturn,
+    status_codes::StatusCode,
+    string::UAString,
+    variant::Variant,
 };
 
 bitflags! {
@@ -382,17 +391,30 @@ impl DataValue {
     where
         V: Into<Variant>,
     {
-        let now = DateTime::now();
+        Self::new_at(value, DateTime::now())
+    }
+
+    /// Creates a `DataValue` from the supplied value with the source and server timestamps both
+    /// set to the supplied time.
+    pub fn new_at<V>(value: V, time: DateTime) -> DataValue
+    where
+        V: Into<Variant>,
+    {
         DataValue {
             value: Some(value.into()),
             status: Some(StatusCode::Good),
-            source_timestamp: Some(now),
+            source_timestamp: Some(time),
             source_picoseconds: Some(0),
-            server_timestamp: Some(now),
+            server_timestamp: Some(time),
             server_picoseconds: Some(0),
         }
     }
 
+    /// Returns a builder for constructing a `DataValue` field by field
+    pub fn builder() -> DataValueBuilder {
+        DataValueBuilder::new()
+    }
+
     /// Creates an empty DataValue
     pub fn null() -> DataValue {
         DataValue {
@@ -421,6 +443,34 @@ impl DataValue {
         self.server_picoseconds = Some(0);
     }
 
+    /// Returns the source timestamp, with the source picoseconds added
+    pub fn source_time(&self) -> Option<DateTimeUtc> {
+        self.source_timestamp.map(|timestamp| {
+            timestamp.as_chrono_with_picoseconds(self.source_picoseconds.unwrap_or(0))
+        })
+    }
+
+    /// Sets the source timestamp and picoseconds from a time that is more precise than a tick
+    pub fn set_source_time(&mut self, time: DateTimeUtc) {
+        let (timestamp, picoseconds) = DateTime::from_chrono_with_picoseconds(time);
+        self.source_timestamp = Some(timestamp);
+        self.source_picoseconds = Some(picoseconds);
+    }
+
+    /// Returns the server timestamp, with the server picoseconds added
+    pub fn server_time(&self) -> Option<DateTimeUtc> {
+        self.server_timestamp.map(|timestamp| {
+            timestamp.as_chrono_with_picoseconds(self.server_picoseconds.unwrap_or(0))
+        })
+    }
+
+    /// Sets the server timestamp and picoseconds from a time that is more precise than a tick
+    pub fn set_server_time(&mut self, time: DateTimeUtc) {
+        let (timestamp, picoseconds) = DateTime::from_chrono_with_picoseconds(time);
+        self.server_timestamp = Some(timestamp);
+        self.server_picoseconds = Some(picoseconds);
+    }
+
     /// Sets the timestamps of the data value based on supplied timestamps to return
     pub fn set_timestamps(
         &mut self,
@@ -457,6 +507,25 @@ impl DataValue {
         }
     }
 
+    /// Removes the timestamps that are not to be returned, leaving those that are, together with
+    /// their picoseconds, as they were
+    pub fn retain_timestamps(&mut self, timestamps_to_return: TimestampsToReturn) {
+        let (source, server) = match timestamps_to_return {
+            TimestampsToReturn::Source => (true, false),
+            TimestampsToReturn::Server => (false, true),
+            TimestampsToReturn::Both => (true, true),
+            _ => (false, false),
+        };
+        if !source {
+            self.source_timestamp = None;
+            self.source_picoseconds = None;
+        }
+        if !server {
+            self.server_timestamp = None;
+            self.server_picoseconds = None;
+        }
+    }
+
     /// Returns the status code or Good if there is no code on the value
     pub fn status(&self) -> StatusCode {
         self.status.map_or(StatusCode::Good, |s| s)
@@ -468,26 +537,90 @@ impl DataValue {
         self.status().status().is_good()
     }
 
+    /// Computes the encoding mask. Fields which hold their default value (a Good status, a null
+    /// timestamp or zero picoseconds) are left out of the mask since a decoder infers those values
+    /// anyway, so they cost nothing on the wire.
     fn encoding_mask(&self) -> DataValueFlags {
         let mut encoding_mask = DataValueFlags::empty();
         if self.value.is_some() {
             encoding_mask |= DataValueFlags::HAS_VALUE;
         }
-        if self.status.is_some() {
+        if matches!(self.status, Some(status) if status != StatusCode::Good) {
             encoding_mask |= DataValueFlags::HAS_STATUS;
         }
-        if self.source_timestamp.is_some() {
+        if matches!(self.source_timestamp, Some(timestamp) if !timestamp.is_null()) {
             encoding_mask |= DataValueFlags::HAS_SOURCE_TIMESTAMP;
-            if self.source_picoseconds.is_some() {
+            if matches!(self.source_picoseconds, Some(picoseconds) if picoseconds != 0) {
                 encoding_mask |= DataValueFlags::HAS_SOURCE_PICOSECONDS;
             }
         }
-        if self.server_timestamp.is_some() {
+        if matches!(self.server_timestamp, Some(timestamp) if !timestamp.is_null()) {
             encoding_mask |= DataValueFlags::HAS_SERVER_TIMESTAMP;
-            if self.server_picoseconds.is_some() {
+            if matches!(self.server_picoseconds, Some(picoseconds) if picoseconds != 0) {
                 encoding_mask |= DataValueFlags::HAS_SERVER_PICOSECONDS;
             }
         }
         encoding_mask
     }
 }
+
+/// Builds a `DataValue` field by field, e.g.
+///
+/// ```
+/// use opcua_types::{DataValueBuilder, DateTime, StatusCode};
+///
+/// let data_value = DataValueBuilder::new()
+///     .value(100i32)
+///     .status(StatusCode::GoodClamped)
+///     .source_timestamp(DateTime::now())
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DataValueBuilder {
+    data_value: DataValue,
+}
+
+impl DataValueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value<V>(mut self, value: V) -> Self
+    where
+        V: Into<Variant>,
+    {
+        self.data_value.value = Some(value.into());
+        self
+    }
+
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.data_value.status = Some(status);
+        self
+    }
+
+    pub fn source_timestamp(mut self, source_timestamp: DateTime) -> Self {
+        self.data_value.source_timestamp = Some(source_timestamp);
+        self
+    }
+
+    /// Sets the source timestamp and picoseconds from a time that is more precise than a tick
+    pub fn source_time(mut self, time: DateTimeUtc) -> Self {
+        self.data_value.set_source_time(time);
+        self
+    }
+
+    pub fn server_timestamp(mut self, server_timestamp: DateTime) -> Self {
+        self.data_value.server_timestamp = Some(server_timestamp);
+        self
+    }
+
+    /// Sets the server timestamp and picoseconds from a time that is more precise than a tick
+    pub fn server_time(mut self, time: DateTimeUtc) -> Self {
+        self.data_value.set_server_time(time);
+        self
+    }
+
+    pub fn build(self) -> DataValue {
+        self.data_value
+    }
+}
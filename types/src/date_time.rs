@@ -4,22 +4,30 @@
 
 //! Contains the implementation of `DataTime`.
 
-use std::{
+use core::{
     cmp::Ordering,
     fmt,
-    io::{Read, Write},
-    ops::{Add, Sub},
+    ops::{Add, AddAssign, Sub, SubAssign},
     str::FromStr,
 };
+#[cfg(feature = "std")]
+use std::time::SystemTime;
 
 use chrono::{Datelike, Duration, TimeZone, Timelike, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::types::encoding::*;
+use crate::{
+    clock,
+    encoding::*,
+    io::{Read, Write},
+};
 
 const NANOS_PER_SECOND: i64 = 1_000_000_000;
 const NANOS_PER_TICK: i64 = 100;
 const TICKS_PER_SECOND: i64 = NANOS_PER_SECOND / NANOS_PER_TICK;
+/// The picoseconds of a DataValue count intervals of 10 picoseconds
+const PICOSECOND_INTERVALS_PER_NANO: i64 = 100;
+const MAX_PICOSECONDS: i16 = 9999;
 
 const MIN_YEAR: u16 = 1601;
 const MAX_YEAR: u16 = 9999;
@@ -87,6 +95,12 @@ impl Add<Duration> for DateTime {
     }
 }
 
+impl AddAssign<Duration> for DateTime {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
 impl Sub<DateTime> for DateTime {
     type Output = Duration;
 
@@ -103,6 +117,12 @@ impl Sub<Duration> for DateTime {
     }
 }
 
+impl SubAssign<Duration> for DateTime {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
 impl PartialOrd for DateTime {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.date_time.cmp(&other.date_time))
@@ -121,10 +141,10 @@ impl From<(u16, u16, u16, u16, u16, u16)> for DateTime {
 impl From<(u16, u16, u16, u16, u16, u16, u32)> for DateTime {
     fn from(dt: (u16, u16, u16, u16, u16, u16, u32)) -> Self {
         let (year, month, day, hour, minute, second, nanos) = dt;
-        if month < 1 || month > 12 {
+        if !(1..=12).contains(&month) {
             panic!("Invalid month");
         }
-        if day < 1 || day > 31 {
+        if !(1..=31).contains(&day) {
             panic!("Invalid day");
         }
         if hour > 23 {
@@ -168,7 +188,7 @@ impl From<DateTimeUtc> for DateTime {
 
 impl From<i64> for DateTime {
     fn from(value: i64) -> Self {
-        if value == i64::max_value() {
+        if value == i64::MAX {
             // Max signifies end times
             Self::endtimes()
         } else {
@@ -180,15 +200,29 @@ impl From<i64> for DateTime {
     }
 }
 
-impl Into<i64> for DateTime {
-    fn into(self) -> i64 {
-        self.checked_ticks()
+impl From<DateTime> for i64 {
+    fn from(value: DateTime) -> Self {
+        value.checked_ticks()
+    }
+}
+
+impl From<DateTime> for DateTimeUtc {
+    fn from(value: DateTime) -> Self {
+        value.as_chrono()
     }
 }
 
-impl Into<DateTimeUtc> for DateTime {
-    fn into(self) -> DateTimeUtc {
-        self.as_chrono()
+#[cfg(feature = "std")]
+impl From<SystemTime> for DateTime {
+    fn from(system_time: SystemTime) -> Self {
+        DateTime::from(DateTimeUtc::from(system_time))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<DateTime> for SystemTime {
+    fn from(date_time: DateTime) -> Self {
+        SystemTime::from(date_time.as_chrono())
     }
 }
 
@@ -209,14 +243,14 @@ impl FromStr for DateTime {
 }
 
 impl DateTime {
-    /// Constructs from the current time
+    /// Constructs from the current time of the installed [`Clock`](../clock/trait.Clock.html)
     pub fn now() -> DateTime {
-        DateTime::from(Utc::now())
+        DateTime::from(clock::now())
     }
 
     /// Constructs from the current time with an offset
     pub fn now_with_offset(offset: Duration) -> DateTime {
-        DateTime::from(Utc::now() + offset)
+        DateTime::from(clock::now() + offset)
     }
 
     /// Creates a null date time (i.e. the epoch)
@@ -281,23 +315,72 @@ impl DateTime {
     }
 
     /// To checked ticks. Function returns 0 or MAX_INT64
-    /// if date exceeds valid OPC UA range
+    /// if date exceeds valid OPC UA range. As Part 6 requires, the endtimes itself is MAX_INT64
     pub fn checked_ticks(&self) -> i64 {
         let nanos = self.ticks();
         if nanos < 0 {
             return 0;
         }
-        if nanos > Self::endtimes_ticks() {
-            return i64::max_value();
+        if nanos >= Self::endtimes_ticks() {
+            return i64::MAX;
         }
         nanos
     }
 
+    /// Tests if the date time is the endtimes, i.e. it is encoded as the maximum tick value
+    pub fn is_endtimes(&self) -> bool {
+        self.checked_ticks() == i64::MAX
+    }
+
+    /// Returns the date time clamped to the range that OPC UA can encode, i.e. a date before the
+    /// epoch becomes the epoch and a date after the endtimes becomes the endtimes. This is the
+    /// value the other end gets once the date time is encoded.
+    pub fn clamped(&self) -> DateTime {
+        if self.date_time < Self::epoch_chrono() {
+            Self::epoch()
+        } else if self.date_time > Self::endtimes_chrono() {
+            Self::endtimes()
+        } else {
+            *self
+        }
+    }
+
+    /// Adds the duration, returning `None` if the result is out of range
+    pub fn checked_add(&self, duration: Duration) -> Option<DateTime> {
+        self.date_time
+            .checked_add_signed(duration)
+            .map(DateTime::from)
+    }
+
+    /// Subtracts the duration, returning `None` if the result is out of range
+    pub fn checked_sub(&self, duration: Duration) -> Option<DateTime> {
+        self.date_time
+            .checked_sub_signed(duration)
+            .map(DateTime::from)
+    }
+
     /// Time as chrono
     pub fn as_chrono(&self) -> DateTimeUtc {
         self.date_time
     }
 
+    /// Splits a time into a date time, which is only precise to a tick of 100 nanoseconds, and
+    /// the rest of the time in the 10 picosecond intervals that a `DataValue` holds in its
+    /// picoseconds fields.
+    pub fn from_chrono_with_picoseconds(date_time: DateTimeUtc) -> (DateTime, i16) {
+        let nanos_below_tick = date_time.nanosecond() as i64 % NANOS_PER_TICK;
+        let picoseconds = (nanos_below_tick * PICOSECOND_INTERVALS_PER_NANO) as i16;
+        (DateTime::from(date_time), picoseconds)
+    }
+
+    /// Returns the time of the date time with picoseconds of a `DataValue` added to it. Chrono
+    /// is precise to a nanosecond so anything finer is lost. Picoseconds outside the valid range
+    /// of 0 to 9999 are clamped to it.
+    pub fn as_chrono_with_picoseconds(&self, picoseconds: i16) -> DateTimeUtc {
+        let picoseconds = picoseconds.clamp(0, MAX_PICOSECONDS) as i64;
+        self.date_time + Duration::nanoseconds(picoseconds / PICOSECOND_INTERVALS_PER_NANO)
+    }
+
     /// The OPC UA epoch - Jan 1 1601 00:00:00
     fn epoch_chrono() -> DateTimeUtc {
         Utc.ymd(MIN_YEAR as i32, 1, 1).and_hms(0, 0, 0)
@@ -316,7 +399,10 @@ impl DateTime {
         let seconds_part = Duration::seconds(duration.num_seconds());
         let seconds = seconds_part.num_seconds();
         let nanos = (duration - seconds_part).num_nanoseconds().unwrap();
-        // Put it back together in ticks
-        seconds * TICKS_PER_SECOND + nanos / NANOS_PER_TICK
+        // Put it back together in ticks, saturating for a date time too far from the epoch to
+        // fit, e.g. a decoded one that has the client offset applied on top
+        seconds
+            .saturating_mul(TICKS_PER_SECOND)
+            .saturating_add(nanos / NANOS_PER_TICK)
     }
 }
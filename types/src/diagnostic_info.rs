@@ -4,9 +4,19 @@
 
 //! Contains the implementation of `DiagnosticInfo`.
 
-use std::io::{Read, Write};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
 
-use crate::types::{encoding::*, status_codes::StatusCode, string::UAString};
+use crate::{
+    encoding::*,
+    io::{Read, Write},
+    response_header::ResponseHeader,
+    status_codes::StatusCode,
+    string::UAString,
+};
 
 bitflags! {
     pub struct DiagnosticInfoMask: u8 {
@@ -45,6 +55,67 @@ bitflags! {
     }
 }
 
+impl DiagnosticBits {
+    /// Returns the service level diagnostics that are requested.
+    pub fn service_level(&self) -> DiagnosticBits {
+        DiagnosticBits::from_bits_truncate(self.bits & 0x0000_001f)
+    }
+
+    /// Returns the operation level diagnostics that are requested, shifted onto the equivalent
+    /// service level bits so diagnostic infos for operations are made the same way as for the
+    /// service.
+    pub fn operation_level(&self) -> DiagnosticBits {
+        DiagnosticBits::from_bits_truncate((self.bits >> 5) & 0x0000_001f)
+    }
+}
+
+/// The namespace of the symbolic ids of the standard status codes
+const STATUS_CODE_NAMESPACE: &str = "http://opcfoundation.org/UA/";
+
+/// Builds the string table of a response, which holds the strings that the diagnostic infos in
+/// the response refer to by index.
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    strings: Vec<UAString>,
+}
+
+impl From<Option<Vec<UAString>>> for StringTable {
+    fn from(strings: Option<Vec<UAString>>) -> Self {
+        StringTable {
+            strings: strings.unwrap_or_default(),
+        }
+    }
+}
+
+impl StringTable {
+    pub fn new() -> StringTable {
+        StringTable::default()
+    }
+
+    /// Returns the index of the string in the table, adding it if it isn't there already.
+    pub fn index_of(&mut self, value: &str) -> i32 {
+        if let Some(idx) = self.strings.iter().position(|s| s.as_ref() == value) {
+            idx as i32
+        } else {
+            self.strings.push(UAString::from(value));
+            (self.strings.len() - 1) as i32
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns the strings for the string table of a response header.
+    pub fn into_strings(self) -> Option<Vec<UAString>> {
+        if self.strings.is_empty() {
+            None
+        } else {
+            Some(self.strings)
+        }
+    }
+}
+
 /// Diagnostic information.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct DiagnosticInfo {
@@ -163,7 +234,8 @@ impl BinaryEncoder<DiagnosticInfo> for DiagnosticInfo {
             diagnostic_info.inner_status_code = Some(StatusCode::decode(stream, decoding_options)?);
         }
         if encoding_mask.contains(DiagnosticInfoMask::HAS_INNER_DIAGNOSTIC_INFO) {
-            // Read inner diagnostic info
+            // Read inner diagnostic info. It is depth checked to prevent deep recursion
+            let _depth_lock = decoding_options.depth_lock()?;
             diagnostic_info.inner_diagnostic_info =
                 Some(Box::new(DiagnosticInfo::decode(stream, decoding_options)?));
         }
@@ -190,6 +262,58 @@ impl DiagnosticInfo {
         }
     }
 
+    /// Makes the diagnostic info describing a status code. The diagnostics determine which parts
+    /// are filled in and should be the service level bits, or the operation level bits returned
+    /// by `DiagnosticBits::operation_level()`. Strings are added to the string table.
+    pub fn from_status_code(
+        status_code: StatusCode,
+        diagnostics: DiagnosticBits,
+        string_table: &mut StringTable,
+    ) -> DiagnosticInfo {
+        let mut diagnostic_info = DiagnosticInfo::null();
+        if diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_SYMBOLIC_ID) {
+            diagnostic_info.symbolic_id = Some(string_table.index_of(status_code.name()));
+            diagnostic_info.namespace_uri = Some(string_table.index_of(STATUS_CODE_NAMESPACE));
+        }
+        if diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_LOCALIZED_TEXT) {
+            diagnostic_info.localized_text = Some(string_table.index_of(status_code.description()));
+        }
+        if diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_LOCALIZED_INNER_STATUS_CODE) {
+            diagnostic_info.inner_status_code = Some(status_code);
+        }
+        diagnostic_info
+    }
+
+    /// Tests if the diagnostic info holds no information.
+    pub fn is_null(&self) -> bool {
+        self.encoding_mask().is_empty()
+    }
+
+    /// Looks up the strings that the diagnostic info refers to in the string table of the
+    /// response that it came from.
+    pub fn resolve(&self, string_table: &[UAString]) -> ResolvedDiagnosticInfo {
+        let lookup = |idx: Option<i32>| {
+            idx.filter(|idx| *idx >= 0)
+                .and_then(|idx| string_table.get(idx as usize))
+                .map(|s| s.as_ref().to_string())
+        };
+        ResolvedDiagnosticInfo {
+            symbolic_id: lookup(self.symbolic_id),
+            namespace_uri: lookup(self.namespace_uri),
+            locale: lookup(self.locale),
+            localized_text: lookup(self.localized_text),
+            additional_info: self
+                .additional_info
+                .as_ref()
+                .map(|s| s.as_ref().to_string()),
+            inner_status_code: self.inner_status_code,
+            inner_diagnostic_info: self
+                .inner_diagnostic_info
+                .as_ref()
+                .map(|d| Box::new(d.resolve(string_table))),
+        }
+    }
+
     pub fn encoding_mask(&self) -> DiagnosticInfoMask {
         let mut encoding_mask = DiagnosticInfoMask::empty();
         if self.symbolic_id.is_some() {
@@ -216,3 +340,62 @@ impl DiagnosticInfo {
         encoding_mask
     }
 }
+
+/// A diagnostic info with the strings that it refers to looked up in the string table.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ResolvedDiagnosticInfo {
+    /// A symbolic name for the status code.
+    pub symbolic_id: Option<String>,
+    /// A namespace that qualifies the symbolic id.
+    pub namespace_uri: Option<String>,
+    /// The locale used for the localized text.
+    pub locale: Option<String>,
+    /// A human readable summary of the status code.
+    pub localized_text: Option<String>,
+    /// Detailed application specific diagnostic information.
+    pub additional_info: Option<String>,
+    /// A status code provided by an underlying system.
+    pub inner_status_code: Option<StatusCode>,
+    /// Diagnostic info associated with the inner status code.
+    pub inner_diagnostic_info: Option<Box<ResolvedDiagnosticInfo>>,
+}
+
+/// The diagnostics that a server returned in a response.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ResponseDiagnostics {
+    /// Diagnostics for the service result.
+    pub service_diagnostics: Option<ResolvedDiagnosticInfo>,
+    /// Diagnostics for each operation of the request, in the same order as the results. An
+    /// operation without diagnostics has `None`.
+    pub operation_diagnostics: Vec<Option<ResolvedDiagnosticInfo>>,
+}
+
+impl ResponseDiagnostics {
+    /// Resolves the service diagnostics of the response header and the operation diagnostics of
+    /// the response. Returns `None` if the response holds no diagnostics.
+    pub fn new(
+        response_header: &ResponseHeader,
+        diagnostic_infos: Option<&[DiagnosticInfo]>,
+    ) -> Option<ResponseDiagnostics> {
+        let string_table = response_header.string_table.as_deref().unwrap_or(&[]);
+        let resolve = |diagnostic_info: &DiagnosticInfo| {
+            if diagnostic_info.is_null() {
+                None
+            } else {
+                Some(diagnostic_info.resolve(string_table))
+            }
+        };
+        let service_diagnostics = resolve(&response_header.service_diagnostics);
+        let operation_diagnostics = diagnostic_infos
+            .map(|d| d.iter().map(resolve).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if service_diagnostics.is_none() && operation_diagnostics.iter().all(|d| d.is_none()) {
+            None
+        } else {
+            Some(ResponseDiagnostics {
+                service_diagnostics,
+                operation_diagnostics,
+            })
+        }
+    }
+}
@@ -5,21 +5,22 @@
 //! Contains the `BinaryEncoder` trait and helpers for reading and writing of scalar values and
 //! other primitives.
 
-use std::{
-    fmt::Debug,
-    io::{Cursor, Read, Result, Write},
-    sync::Arc,
-};
+use alloc::{sync::Arc, vec::Vec};
+use core::fmt::Debug;
 
-use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian};
 use chrono::Duration;
 
 use crate::{
+    constants,
+    extension_object::TypeRegistry,
+    io::{Read, Result, Write},
+    namespaces::NamespaceTable,
+    status_codes::StatusCode,
     sync::Mutex,
-    types::{constants, status_codes::StatusCode},
 };
 
-pub type EncodingResult<T> = std::result::Result<T, StatusCode>;
+pub type EncodingResult<T> = core::result::Result<T, StatusCode>;
 
 /// Depth lock holds a reference on the depth gauge. The drop ensures impl that the reference is
 /// decremented even if there is a panic unwind.
@@ -30,7 +31,7 @@ pub struct DepthLock {
 
 impl Drop for DepthLock {
     fn drop(&mut self) {
-        let mut dg = trace_lock!(self.depth_gauge);
+        let mut dg = self.depth_gauge.lock();
         if dg.current_depth > 0 {
             dg.current_depth -= 1;
         }
@@ -45,7 +46,7 @@ impl DepthLock {
     pub fn obtain(
         depth_gauge: Arc<Mutex<DepthGauge>>,
     ) -> core::result::Result<DepthLock, StatusCode> {
-        let mut dg = trace_lock!(depth_gauge);
+        let mut dg = depth_gauge.lock();
         if dg.current_depth >= dg.max_depth {
             warn!("Decoding in stream aborted due maximum recursion depth being reached");
             Err(StatusCode::BadDecodingError)
@@ -108,6 +109,11 @@ pub struct DecodingOptions {
     pub max_array_length: usize,
     /// Decoding depth gauge is used to check for recursion
     pub decoding_depth_gauge: Arc<Mutex<DepthGauge>>,
+    /// The namespace table of the other end, which the namespace indices of decoded node ids
+    /// belong to
+    pub namespaces: Arc<NamespaceTable>,
+    /// The custom types that extension objects can be decoded into
+    pub type_registry: Arc<TypeRegistry>,
 }
 
 impl Default for DecodingOptions {
@@ -120,6 +126,8 @@ impl Default for DecodingOptions {
             max_byte_string_length: constants::MAX_BYTE_STRING_LENGTH,
             max_array_length: constants::MAX_ARRAY_LENGTH,
             decoding_depth_gauge: Arc::new(Mutex::new(DepthGauge::default())),
+            namespaces: Arc::new(NamespaceTable::new()),
+            type_registry: Arc::new(TypeRegistry::new()),
         }
     }
 }
@@ -138,7 +146,7 @@ impl DecodingOptions {
     }
 
     /// For test only. Having a separate function makes it easier to control calls to DecodingOptions::default().
-    #[cfg(test)]
+    #[doc(hidden)]
     pub fn test() -> Self {
         Self::default()
     }
@@ -165,9 +173,9 @@ pub trait BinaryEncoder<T> {
     // Convenience method for encoding a message straight into an array of bytes. It is preferable to reuse buffers than
     // to call this so it should be reserved for tests and trivial code.
     fn encode_to_vec(&self) -> Vec<u8> {
-        let mut buffer = Cursor::new(Vec::with_capacity(self.byte_len()));
+        let mut buffer = Vec::with_capacity(self.byte_len());
         let _ = self.encode(&mut buffer);
-        buffer.into_inner()
+        buffer
     }
 }
 
@@ -245,9 +253,7 @@ pub fn read_array<S: Read, T: BinaryEncoder<T>>(
 /// Writes a series of identical bytes to the stream
 pub fn write_bytes(stream: &mut dyn Write, value: u8, count: usize) -> EncodingResult<usize> {
     for _ in 0..count {
-        let _ = stream
-            .write_u8(value)
-            .map_err(|_| StatusCode::BadEncodingError)?;
+        write_u8(stream, value)?;
     }
     Ok(count)
 }
@@ -0,0 +1,248 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains [`UaEnum`], which describes a Rust enum as an OPC UA enumeration, [`Enumeration`],
+//! which maps the values of an enumeration to their text, and [`UaOptionSet`], which describes
+//! bitflags as an OPC UA option set.
+//!
+//! [`UaEnum`]: ./trait.UaEnum.html
+//! [`Enumeration`]: ./struct.Enumeration.html
+//! [`UaOptionSet`]: ./trait.UaOptionSet.html
+
+use alloc::vec::Vec;
+
+use crate::{
+    encoding::DecodingOptions,
+    extension_object::ExtensionObject,
+    localized_text::LocalizedText,
+    node_ids::ObjectId,
+    service_types::{EnumValueType, OptionSet},
+    variant::{Variant, VariantTypeId},
+};
+
+/// Implemented by a Rust enum that is exposed as an OPC UA enumeration, e.g. as the states of a
+/// `MultiStateDiscreteType` or `MultiStateValueDiscreteType` variable.
+///
+/// ```
+/// use opcua_types::UaEnum;
+///
+/// #[derive(Copy, Clone)]
+/// enum Valve {
+///     Closed = 0,
+///     Open = 1,
+///     Fault = 5,
+/// }
+///
+/// impl UaEnum for Valve {
+///     fn variants() -> &'static [(i64, &'static str)] {
+///         &[(0, "Closed"), (1, "Open"), (5, "Fault")]
+///     }
+/// }
+/// ```
+pub trait UaEnum {
+    /// Returns the value and name of each variant, in order of value.
+    fn variants() -> &'static [(i64, &'static str)];
+}
+
+/// The values of an enumeration with their text, as held by its `EnumStrings` or `EnumValues`
+/// property. `EnumStrings` hold the text of the values from 0 upwards, while `EnumValues` may have
+/// gaps between values.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Enumeration {
+    enum_values: Vec<EnumValueType>,
+}
+
+impl Enumeration {
+    /// Creates the enumeration of a Rust enum, naming each value after its variant.
+    pub fn from_enum<E>() -> Enumeration
+    where
+        E: UaEnum,
+    {
+        Enumeration {
+            enum_values: E::variants()
+                .iter()
+                .map(|(value, name)| EnumValueType {
+                    value: *value,
+                    display_name: LocalizedText::from(*name),
+                    description: LocalizedText::null(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates the enumeration from `EnumStrings`, where the first is the text of the value 0.
+    pub fn from_enum_strings(enum_strings: Vec<LocalizedText>) -> Enumeration {
+        Enumeration {
+            enum_values: enum_strings
+                .into_iter()
+                .enumerate()
+                .map(|(value, display_name)| EnumValueType {
+                    value: value as i64,
+                    display_name,
+                    description: LocalizedText::null(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates the enumeration from `EnumValues`.
+    pub fn from_enum_values(enum_values: Vec<EnumValueType>) -> Enumeration {
+        Enumeration { enum_values }
+    }
+
+    /// Creates the enumeration from the value of an `EnumStrings` property, i.e. an array of
+    /// localized text, or of an `EnumValues` property, i.e. an array of `EnumValueType`. Returns
+    /// `None` if the value is neither.
+    pub fn from_property_value(value: &Variant) -> Option<Enumeration> {
+        let array = if let Variant::Array(array) = value {
+            array
+        } else {
+            return None;
+        };
+        match array.value_type {
+            VariantTypeId::LocalizedText => {
+                let enum_strings = array
+                    .values
+                    .iter()
+                    .map(|v| match v {
+                        Variant::LocalizedText(v) => Some(*v.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Self::from_enum_strings(enum_strings))
+            }
+            VariantTypeId::ExtensionObject => {
+                let decoding_options = DecodingOptions::default();
+                let enum_values = array
+                    .values
+                    .iter()
+                    .map(|v| match v {
+                        Variant::ExtensionObject(v) => {
+                            v.decode_inner::<EnumValueType>(&decoding_options).ok()
+                        }
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Self::from_enum_values(enum_values))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the values with their text.
+    pub fn enum_values(&self) -> &[EnumValueType] {
+        &self.enum_values
+    }
+
+    /// Returns the text of the values as `EnumStrings`, or `None` if the values do not run from 0
+    /// upwards without gaps, which `EnumStrings` cannot express.
+    pub fn enum_strings(&self) -> Option<Vec<LocalizedText>> {
+        let mut enum_values = self.enum_values.iter().collect::<Vec<_>>();
+        enum_values.sort_by_key(|v| v.value);
+        if enum_values
+            .iter()
+            .enumerate()
+            .all(|(i, v)| v.value == i as i64)
+        {
+            Some(
+                enum_values
+                    .into_iter()
+                    .map(|v| v.display_name.clone())
+                    .collect(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `EnumValues` as the value of a property, i.e. an array of extension objects.
+    pub fn enum_values_variant(&self) -> Variant {
+        let values = self
+            .enum_values
+            .iter()
+            .map(|v| {
+                ExtensionObject::from_encodable(ObjectId::EnumValueType_Encoding_DefaultBinary, v)
+                    .into()
+            })
+            .collect::<Vec<Variant>>();
+        Variant::from((VariantTypeId::ExtensionObject, values))
+    }
+
+    /// Tests if the value is one of the values of the enumeration.
+    pub fn contains(&self, value: i64) -> bool {
+        self.enum_values.iter().any(|v| v.value == value)
+    }
+
+    /// Returns the text of the value, or `None` if it is not one of the values.
+    pub fn text(&self, value: i64) -> Option<&LocalizedText> {
+        self.enum_values
+            .iter()
+            .find(|v| v.value == value)
+            .map(|v| &v.display_name)
+    }
+
+    /// Returns the text of a value read from a variable, which may be of any integer type, or
+    /// `None` if it is not a number or not one of the values.
+    pub fn text_of(&self, value: &Variant) -> Option<&LocalizedText> {
+        if let Variant::Int64(value) = value.convert(VariantTypeId::Int64) {
+            self.text(value)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value with the text, or `None` if no value has it.
+    pub fn value_of(&self, text: &str) -> Option<i64> {
+        self.enum_values
+            .iter()
+            .find(|v| v.display_name.text.as_ref() == text)
+            .map(|v| v.value)
+    }
+}
+
+/// Implemented by the bitflags of an option set data type, e.g. the generated `AccessLevelType`.
+/// An option set that is a subtype of an integer is passed as the integer, while the `OptionSet`
+/// structure passes the bits of the value together with the bits that are valid.
+pub trait UaOptionSet: Sized {
+    /// Returns the number and name of each bit, in order of bit number. An option without a bit,
+    /// such as `None`, is not in the list.
+    fn options() -> &'static [(u32, &'static str)];
+
+    /// Returns the size of the option set in bytes.
+    fn size() -> usize;
+
+    /// Returns the bits of the option set.
+    fn to_u64(&self) -> u64;
+
+    /// Creates the option set from bits, dropping any bits that are not an option.
+    fn from_u64(bits: u64) -> Self;
+
+    /// Returns the bits of all the options.
+    fn valid_bits() -> u64 {
+        Self::options()
+            .iter()
+            .fold(0, |valid_bits, (bit, _)| valid_bits | 1 << bit)
+    }
+
+    /// Reads the option set from a value, which is either the integer of the option set or an
+    /// `OptionSet` structure in an extension object. Returns `None` if it is neither.
+    fn from_variant(value: &Variant) -> Option<Self> {
+        match value {
+            Variant::ExtensionObject(extension_object) => extension_object
+                .decode_inner::<OptionSet>(&DecodingOptions::default())
+                .ok()
+                .map(|option_set| option_set.to_option_set()),
+            // Signed values keep their bits, so a negative value sets the top bit
+            Variant::SByte(v) => Some(Self::from_u64(*v as u8 as u64)),
+            Variant::Byte(v) => Some(Self::from_u64(*v as u64)),
+            Variant::Int16(v) => Some(Self::from_u64(*v as u16 as u64)),
+            Variant::UInt16(v) => Some(Self::from_u64(*v as u64)),
+            Variant::Int32(v) => Some(Self::from_u64(*v as u32 as u64)),
+            Variant::UInt32(v) => Some(Self::from_u64(*v as u64)),
+            Variant::Int64(v) => Some(Self::from_u64(*v as u64)),
+            Variant::UInt64(v) => Some(Self::from_u64(*v)),
+            _ => None,
+        }
+    }
+}
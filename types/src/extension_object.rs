@@ -0,0 +1,333 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the implementation of `ExtensionObject`.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::Any, error::Error, fmt};
+
+use super::{
+    byte_string::ByteString,
+    encoding::*,
+    io::{Read, Write},
+    namespaces::NamespaceTable,
+    node_id::{ExpandedNodeId, NodeId},
+    node_ids::ObjectId,
+    status_codes::StatusCode,
+    string::XmlElement,
+};
+
+#[derive(Debug)]
+pub struct ExtensionObjectError;
+
+impl fmt::Display for ExtensionObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExtensionObjectError")
+    }
+}
+
+impl Error for ExtensionObjectError {}
+
+/// Enumeration that holds the kinds of encoding that an ExtensionObject data may be encoded with.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum ExtensionObjectEncoding {
+    /// For an extension object with nothing encoded with it
+    None,
+    /// For an extension object with data encoded in a ByteString
+    ByteString(ByteString),
+    /// For an extension object with data encoded in an XML string
+    XmlElement(XmlElement),
+}
+
+/// Implemented by the types that can be the body of an extension object so that
+/// [`ExtensionObject::try_decode`] can tell if an extension object holds the type before
+/// decoding it.
+///
+/// Every structure in `service_types` implements it. A custom structure should implement it with
+/// the node id of its binary encoding given by namespace URI, since the namespace index differs
+/// from one server to the next.
+///
+/// [`ExtensionObject::try_decode`]: ./struct.ExtensionObject.html#method.try_decode
+pub trait ExtensionObjectBody: BinaryEncoder<Self> + Sized {
+    /// Returns the node id of the default binary encoding of the type
+    fn binary_encoding_id() -> ExpandedNodeId;
+}
+
+/// A decoded extension object body of a type registered with a [`TypeRegistry`]
+///
+/// [`TypeRegistry`]: ./struct.TypeRegistry.html
+pub type DecodedBody = Box<dyn Any + Send + Sync>;
+
+type DecodeFn = fn(&ExtensionObject, &DecodingOptions) -> EncodingResult<DecodedBody>;
+
+/// A registry of the custom types that extension objects may hold. The registry is part of the
+/// `DecodingOptions`, so with the namespace table held alongside it an extension object can be
+/// decoded into whatever registered type its node id identifies, see
+/// [`ExtensionObject::decode_registered`].
+///
+/// [`ExtensionObject::decode_registered`]: ./struct.ExtensionObject.html#method.decode_registered
+#[derive(Default, Clone)]
+pub struct TypeRegistry {
+    types: Vec<(ExpandedNodeId, DecodeFn)>,
+}
+
+impl fmt::Debug for TypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.types.iter().map(|(id, _)| id))
+            .finish()
+    }
+}
+
+impl TypeRegistry {
+    pub fn new() -> TypeRegistry {
+        TypeRegistry::default()
+    }
+
+    /// Registers a type. Registering the same type twice has no effect.
+    pub fn register<T>(&mut self)
+    where
+        T: ExtensionObjectBody + Send + Sync + 'static,
+    {
+        let encoding_id = T::binary_encoding_id();
+        if !self.types.iter().any(|(id, _)| *id == encoding_id) {
+            self.types
+                .push((encoding_id, |extension_object, decoding_options| {
+                    extension_object
+                        .decode_inner::<T>(decoding_options)
+                        .map(|value| Box::new(value) as DecodedBody)
+                }));
+        }
+    }
+
+    /// Returns the number of registered types
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Returns true if no type is registered
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn find(&self, node_id: &NodeId, namespaces: &NamespaceTable) -> Option<DecodeFn> {
+        self.types
+            .iter()
+            .find(|(id, _)| namespaces.resolve(id).as_ref() == Some(node_id))
+            .map(|(_, decode)| *decode)
+    }
+}
+
+/// An extension object holds a serialized object identified by its node id.
+///
+/// The body is kept in its encoded form and is only decoded when asked for, so an extension
+/// object holding a type that this crate does not know about is encoded again exactly as it was
+/// received.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionObject {
+    pub node_id: NodeId,
+    pub body: ExtensionObjectEncoding,
+}
+
+impl Default for ExtensionObject {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl BinaryEncoder<ExtensionObject> for ExtensionObject {
+    fn byte_len(&self) -> usize {
+        let mut size = self.node_id.byte_len();
+        size += match self.body {
+            ExtensionObjectEncoding::None => 1,
+            ExtensionObjectEncoding::ByteString(ref value) => {
+                // Encoding mask + data
+                1 + value.byte_len()
+            }
+            ExtensionObjectEncoding::XmlElement(ref value) => {
+                // Encoding mask + data
+                1 + value.byte_len()
+            }
+        };
+        size
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        let mut size = 0;
+        size += self.node_id.encode(stream)?;
+        match self.body {
+            ExtensionObjectEncoding::None => {
+                size += write_u8(stream, 0x0)?;
+            }
+            ExtensionObjectEncoding::ByteString(ref value) => {
+                // Encoding mask + data
+                size += write_u8(stream, 0x1)?;
+                size += value.encode(stream)?;
+            }
+            ExtensionObjectEncoding::XmlElement(ref value) => {
+                // Encoding mask + data
+                size += write_u8(stream, 0x2)?;
+                size += value.encode(stream)?;
+            }
+        }
+        assert_eq!(size, self.byte_len());
+        Ok(size)
+    }
+
+    fn decode<S: Read>(stream: &mut S, decoding_options: &DecodingOptions) -> EncodingResult<Self> {
+        // Extension object is depth checked to prevent deep recursion
+        let _depth_lock = decoding_options.depth_lock()?;
+        let node_id = NodeId::decode(stream, decoding_options)?;
+        let encoding_type = u8::decode(stream, decoding_options)?;
+        let body = match encoding_type {
+            0x0 => ExtensionObjectEncoding::None,
+            0x1 => {
+                ExtensionObjectEncoding::ByteString(ByteString::decode(stream, decoding_options)?)
+            }
+            0x2 => {
+                ExtensionObjectEncoding::XmlElement(XmlElement::decode(stream, decoding_options)?)
+            }
+            _ => {
+                error!("Invalid encoding type {} in stream", encoding_type);
+                return Err(StatusCode::BadDecodingError);
+            }
+        };
+        Ok(ExtensionObject { node_id, body })
+    }
+}
+
+impl ExtensionObject {
+    /// Creates a null extension object, i.e. one with no value or payload
+    pub fn null() -> ExtensionObject {
+        ExtensionObject {
+            node_id: NodeId::null(),
+            body: ExtensionObjectEncoding::None,
+        }
+    }
+
+    /// Tests for null node id.
+    pub fn is_null(&self) -> bool {
+        self.node_id.is_null()
+    }
+
+    /// Tests for empty body.
+    pub fn is_empty(&self) -> bool {
+        self.is_null() || matches!(self.body, ExtensionObjectEncoding::None)
+    }
+
+    /// Returns the object id of the thing this extension object contains, assuming the
+    /// object id can be recognised from the node id.
+    pub fn object_id(&self) -> Result<ObjectId, ExtensionObjectError> {
+        self.node_id
+            .as_object_id()
+            .map_err(|_| ExtensionObjectError)
+    }
+
+    /// Creates an extension object with the specified node id and the encodable object as its payload.
+    /// The body is set to a byte string containing the encoded struct.
+    pub fn from_encodable<N, T>(node_id: N, encodable: &T) -> ExtensionObject
+    where
+        N: Into<NodeId>,
+        T: BinaryEncoder<T>,
+    {
+        // Serialize to extension object
+        ExtensionObject {
+            node_id: node_id.into(),
+            body: ExtensionObjectEncoding::ByteString(ByteString::from(encodable.encode_to_vec())),
+        }
+    }
+
+    /// Decodes the inner content of the extension object and returns it. The node id is ignored
+    /// for decoding. The caller supplies the binary encoder impl that should be used to extract
+    /// the data. Errors result in a decoding error.
+    pub fn decode_inner<T>(&self, decoding_options: &DecodingOptions) -> EncodingResult<T>
+    where
+        T: BinaryEncoder<T>,
+    {
+        match self.body {
+            ExtensionObjectEncoding::ByteString(ref byte_string) => {
+                if let Some(ref value) = byte_string.value {
+                    let mut stream = value.as_slice();
+                    T::decode(&mut stream, decoding_options)
+                } else {
+                    Err(StatusCode::BadDecodingError)
+                }
+            }
+            _ => {
+                error!("decode_inner called on an unsupported ExtensionObject type");
+                Err(StatusCode::BadDecodingError)
+            }
+        }
+    }
+
+    /// Creates an extension object holding the value, with the node id of its binary encoding
+    /// resolved against the namespace table. Returns `None` if the namespace of the type is not
+    /// in the table.
+    pub fn from_body<T>(value: &T, namespaces: &NamespaceTable) -> Option<ExtensionObject>
+    where
+        T: ExtensionObjectBody,
+    {
+        namespaces
+            .resolve(&T::binary_encoding_id())
+            .map(|node_id| Self::from_encodable(node_id, value))
+    }
+
+    /// Tests if the extension object holds the type, i.e. its node id is the binary encoding
+    /// of the type once resolved against the namespace table.
+    pub fn holds<T>(&self, namespaces: &NamespaceTable) -> bool
+    where
+        T: ExtensionObjectBody,
+    {
+        namespaces
+            .resolve(&T::binary_encoding_id())
+            .map(|node_id| node_id == self.node_id)
+            .unwrap_or(false)
+    }
+
+    /// Decodes the body of the extension object if it holds the type. The node id of the
+    /// extension object is resolved against the namespace table of the decoding options, which
+    /// must be that of the server the value came from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(T))` - the decoded body
+    /// * `Ok(None)` - the extension object holds some other type or nothing at all
+    /// * `Err(StatusCode)` - `BadDecodingError` if the body is malformed or XML encoded
+    ///
+    pub fn try_decode<T>(&self, decoding_options: &DecodingOptions) -> EncodingResult<Option<T>>
+    where
+        T: ExtensionObjectBody,
+    {
+        if self.is_empty() || !self.holds::<T>(&decoding_options.namespaces) {
+            Ok(None)
+        } else {
+            self.decode_inner::<T>(decoding_options).map(Some)
+        }
+    }
+
+    /// Decodes the body of the extension object into the type that its node id identifies in the
+    /// type registry of the decoding options. The caller downcasts the result to the type.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DecodedBody))` - the decoded body
+    /// * `Ok(None)` - the extension object holds a type that is not registered or nothing at all
+    /// * `Err(StatusCode)` - `BadDecodingError` if the body is malformed or XML encoded
+    ///
+    pub fn decode_registered(
+        &self,
+        decoding_options: &DecodingOptions,
+    ) -> EncodingResult<Option<DecodedBody>> {
+        if self.is_empty() {
+            Ok(None)
+        } else if let Some(decode) = decoding_options
+            .type_registry
+            .find(&self.node_id, &decoding_options.namespaces)
+        {
+            decode(self, decoding_options).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the implementation of `Guid`.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::{fmt, str::FromStr};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    encoding::*,
+    io::{Read, Write},
+};
+
+/// A Guid is a 16 byte Globally Unique Identifier as described by RFC 4122.
+///
+/// The bytes are held in the order of the RFC, which is also the order of the string form. The
+/// OPC UA binary encoding is mixed-endian and writes the first three fields little-endian, so a
+/// Guid does not appear on the wire in the order of its bytes.
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct Guid {
+    bytes: [u8; 16],
+}
+
+impl Serialize for Guid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Guid {
+    fn deserialize<D>(deserializer: D) -> Result<Guid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let result = String::deserialize(deserializer);
+        match result {
+            Ok(guid) => Guid::from_str(&guid).map_err(|_| D::Error::custom("Invalid guid")),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, b) in self.bytes.iter().enumerate() {
+            if i == 4 || i == 6 || i == 8 || i == 10 {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl BinaryEncoder<Guid> for Guid {
+    fn byte_len(&self) -> usize {
+        16
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        let (data1, data2, data3, data4) = self.as_fields();
+        let mut size: usize = 0;
+        size += write_u32(stream, data1)?;
+        size += write_u16(stream, data2)?;
+        size += write_u16(stream, data3)?;
+        size += process_encode_io_result(stream.write(&data4))?;
+        Ok(size)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let data1 = read_u32(stream)?;
+        let data2 = read_u16(stream)?;
+        let data3 = read_u16(stream)?;
+        let mut data4 = [0u8; 8];
+        process_decode_io_result(stream.read_exact(&mut data4))?;
+        Ok(Guid::from_fields(data1, data2, data3, data4))
+    }
+}
+
+impl FromStr for Guid {
+    type Err = ();
+
+    /// Parses a Guid from any of the standard forms, i.e. hyphenated
+    /// `72962b91-fa75-4ae6-8d28-b404dc7daf63`, simple `72962b91fa754ae68d28b404dc7daf63`, braced
+    /// `{72962b91-fa75-4ae6-8d28-b404dc7daf63}` or URN `urn:uuid:72962b91-fa75-4ae6-8d28-b404dc7daf63`.
+    /// Hex digits may be upper or lower case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = match s.get(..9) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("urn:uuid:") => &s[9..],
+            _ if s.starts_with('{') && s.ends_with('}') => &s[1..s.len() - 1],
+            _ => s,
+        };
+        let digits =
+            if hex.len() == 36 && [8, 13, 18, 23].iter().all(|&i| hex.as_bytes()[i] == b'-') {
+                hex.replace('-', "")
+            } else {
+                hex.to_string()
+            };
+        if digits.len() != 32 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            error!("Guid cannot be parsed from string {}", s);
+            return Err(());
+        }
+        let mut bytes = [0u8; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| ())?;
+        }
+        Ok(Guid { bytes })
+    }
+}
+
+impl Default for Guid {
+    fn default() -> Self {
+        Guid::null()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Guid {
+    fn from(v: uuid::Uuid) -> Self {
+        Guid {
+            bytes: *v.as_bytes(),
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Guid> for uuid::Uuid {
+    fn from(v: Guid) -> Self {
+        uuid::Uuid::from_bytes(v.bytes)
+    }
+}
+
+impl Guid {
+    /// Return a null guid, i.e. 00000000-0000-0000-0000-000000000000
+    pub fn null() -> Guid {
+        Guid { bytes: [0u8; 16] }
+    }
+
+    /// Creates a random (version 4) Guid from the random number generator of the OS
+    #[cfg(feature = "std")]
+    pub fn new() -> Guid {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).expect("The OS random number generator failed");
+        // Set the version to 4 and the variant to RFC 4122
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Guid { bytes }
+    }
+
+    /// Tests if the Guid is the null guid
+    pub fn is_null(&self) -> bool {
+        self.bytes == [0u8; 16]
+    }
+
+    /// Returns the version of the Guid, e.g. 4 for a random Guid
+    pub fn version(&self) -> u8 {
+        self.bytes[6] >> 4
+    }
+
+    /// Returns the bytes of the Guid in RFC 4122 order
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.bytes
+    }
+
+    /// Creates a guid from bytes in RFC 4122 order
+    pub fn from_bytes(bytes: [u8; 16]) -> Guid {
+        Guid { bytes }
+    }
+
+    /// Creates a guid from its fields. These are the `Data1` to `Data4` fields of the OPC UA
+    /// definition, e.g. `72962b91-fa75-4ae6-8d28-b404dc7daf63` has `Data1` 0x72962b91.
+    pub fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Guid {
+        let mut bytes = [0u8; 16];
+        bytes[0..4].copy_from_slice(&data1.to_be_bytes());
+        bytes[4..6].copy_from_slice(&data2.to_be_bytes());
+        bytes[6..8].copy_from_slice(&data3.to_be_bytes());
+        bytes[8..16].copy_from_slice(&data4);
+        Guid { bytes }
+    }
+
+    /// Returns the `Data1` to `Data4` fields of the guid
+    pub fn as_fields(&self) -> (u32, u16, u16, [u8; 8]) {
+        let b = &self.bytes;
+        let mut data4 = [0u8; 8];
+        data4.copy_from_slice(&b[8..16]);
+        (
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_be_bytes([b[4], b[5]]),
+            u16::from_be_bytes([b[6], b[7]]),
+            data4,
+        )
+    }
+
+    /// Returns the Guid as 32 hex digits without hyphens
+    pub fn to_simple_string(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Returns the Guid in braces, e.g. `{72962b91-fa75-4ae6-8d28-b404dc7daf63}`
+    pub fn to_braced_string(&self) -> String {
+        format!("{{{}}}", self)
+    }
+
+    /// Returns the Guid as a URN, e.g. `urn:uuid:72962b91-fa75-4ae6-8d28-b404dc7daf63`
+    pub fn to_urn_string(&self) -> String {
+        format!("urn:uuid:{}", self)
+    }
+}
@@ -0,0 +1,170 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`Read`] and [`Write`] traits of the streams that values are decoded from and
+//! encoded to.
+//!
+//! With the `std` feature every `std::io::Read` is a [`Read`] and every `std::io::Write` is a
+//! [`Write`], so cursors, files and sockets can be used as they are. Without it the traits are
+//! implemented for byte slices, mutable byte slices and vectors, and an application can implement
+//! them for its own buffers.
+//!
+//! [`Read`]: ./trait.Read.html
+//! [`Write`]: ./trait.Write.html
+
+use core::fmt;
+
+/// An error reading from or writing to a stream
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The stream ended before the buffer could be filled
+    UnexpectedEof,
+    /// The stream would not take any more bytes
+    WriteZero,
+    /// Any other error of a `std::io` stream
+    #[cfg(feature = "std")]
+    Std(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of stream"),
+            Error::WriteZero => write!(f, "stream is full"),
+            #[cfg(feature = "std")]
+            Error::Std(err) => err.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            std::io::ErrorKind::WriteZero => Error::WriteZero,
+            _ => Error::Std(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::UnexpectedEof => std::io::ErrorKind::UnexpectedEof.into(),
+            Error::WriteZero => std::io::ErrorKind::WriteZero.into(),
+            Error::Std(err) => err,
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A stream of bytes that values are decoded from
+pub trait Read {
+    /// Reads bytes into the buffer, returning how many were read. Zero means the stream has ended
+    /// or the buffer is empty.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Reads exactly enough bytes to fill the buffer, failing with `UnexpectedEof` if the stream
+    /// ends first.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::UnexpectedEof),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A stream of bytes that values are encoded to
+pub trait Write {
+    /// Writes bytes from the buffer, returning how many were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Writes the whole buffer, failing with `WriteZero` if the stream will not take all of it.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::WriteZero),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + ?Sized> Read for R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        std::io::Read::read(self, buf).map_err(Error::from)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        std::io::Read::read_exact(self, buf).map_err(Error::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> Write for W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        std::io::Write::write(self, buf).map_err(Error::from)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        std::io::Write::write_all(self, buf).map_err(Error::from)
+    }
+}
+
+// Without std the same streams as std::io provides for bytes in memory
+
+#[cfg(not(feature = "std"))]
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = core::cmp::min(buf.len(), self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for &mut [u8] {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = core::cmp::min(buf.len(), self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(n);
+        head.copy_from_slice(&buf[..n]);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for alloc::vec::Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-//! The OPC UA Types module contains data types and enumerations for OPC UA.
+//! The OPC UA Types crate contains data types and enumerations for OPC UA.
 //!
 //! This includes:
 //!
@@ -11,6 +11,32 @@
 //! 3. Autogenerated data types and request / responses as described in OPC Part 4.
 //!
 //! For the built-in data types, the module provides functions
+//!
+//! The crate has none of the networking, crypto or async dependencies of the `opcua` crate, which
+//! re-exports it as `opcua::types`, so the types and their encoding can be used on their own.
+//!
+//! Without the default `std` feature the crate is `no_std` and only needs `alloc`, e.g. for
+//! embedded devices. Values are encoded to and decoded from the [`io::Read`] and [`io::Write`]
+//! traits of the crate, which every `std::io` stream implements when `std` is on.
+//!
+//! [`io::Read`]: ./io/trait.Read.html
+//! [`io::Write`]: ./io/trait.Write.html
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate bitflags;
+#[macro_use]
+extern crate serde_derive;
+#[cfg(test)]
+extern crate serde_json;
+#[cfg(test)]
+extern crate tempdir;
 
 ///Contains constants recognized by OPC UA clients and servers to describe various protocols and
 /// profiles used during communication and encryption.
@@ -195,18 +221,23 @@ pub mod array;
 pub mod attribute;
 pub mod basic_types;
 pub mod byte_string;
+pub mod clock;
 pub mod data_types;
 pub mod data_value;
 pub mod date_time;
 pub mod diagnostic_info;
 pub mod encoding;
+pub mod enumeration;
 pub mod extension_object;
 pub mod guid;
+pub mod io;
 pub mod localized_text;
+pub mod namespaces;
 pub mod node_id;
 pub mod notification_message;
 pub mod numeric_range;
 pub mod operand;
+mod pattern;
 pub mod qualified_name;
 pub mod relative_path;
 pub mod request_header;
@@ -218,11 +249,20 @@ pub mod status_code;
 pub mod string;
 pub mod variant;
 
-pub use crate::types::{
-    argument::*, array::*, attribute::*, basic_types::*, byte_string::*, data_types::*,
-    data_value::*, date_time::*, diagnostic_info::*, encoding::*, extension_object::*, guid::*,
-    localized_text::*, node_id::*, node_ids::*, numeric_range::*, operand::*, qualified_name::*,
-    request_header::*, response_header::*, service_types::*, status_code::*, string::*, variant::*,
+/// The locks of the crate, which spin when there is no std to block with
+pub mod sync {
+    #[cfg(feature = "std")]
+    pub use parking_lot::{Mutex, RwLock};
+    #[cfg(not(feature = "std"))]
+    pub use spin::{Mutex, RwLock};
+}
+
+pub use crate::{
+    array::*, attribute::*, byte_string::*, data_types::*, data_value::*, date_time::*,
+    diagnostic_info::*, encoding::*, enumeration::*, extension_object::*, guid::*,
+    localized_text::*, namespaces::*, node_id::*, node_ids::*, numeric_range::*, operand::*,
+    qualified_name::*, request_header::*, response_header::*, service_types::*, status_code::*,
+    string::*, variant::*,
 };
 
 #[cfg(test)]
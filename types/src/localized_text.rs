@@ -3,13 +3,15 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 //! Contains the definition of `LocalizedText`.
-use std::{
-    fmt,
+use alloc::string::String;
+use core::fmt;
+
+use crate::{
+    encoding::*,
     io::{Read, Write},
+    string::*,
 };
 
-use crate::types::{encoding::*, string::*};
-
 /// A human readable text with an optional locale identifier.
 #[derive(PartialEq, Default, Debug, Clone, Serialize, Deserialize)]
 pub struct LocalizedText {
@@ -103,6 +105,11 @@ impl BinaryEncoder<LocalizedText> for LocalizedText {
     }
 }
 
+/// Returns the language part of a locale id, e.g. `en` for `en-US`
+pub fn locale_language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
 impl LocalizedText {
     pub fn new(locale: &str, text: &str) -> LocalizedText {
         LocalizedText {
@@ -117,4 +124,53 @@ impl LocalizedText {
             text: UAString::null(),
         }
     }
+
+    /// Creates a text that has no locale, i.e. is in the invariant locale.
+    pub fn invariant<T>(text: T) -> LocalizedText
+    where
+        T: Into<UAString>,
+    {
+        LocalizedText {
+            locale: UAString::null(),
+            text: text.into(),
+        }
+    }
+
+    /// Tests if the text is in the locale. Locale ids are compared case insensitively.
+    pub fn is_locale(&self, locale_id: &str) -> bool {
+        self.locale.as_ref().eq_ignore_ascii_case(locale_id)
+    }
+
+    /// Tests if the text is in the language of the locale, e.g. a text in `de` or `de-AT` is in
+    /// the language of `de-CH`.
+    pub fn is_language_of(&self, locale_id: &str) -> bool {
+        !self.locale.is_empty()
+            && locale_language(self.locale.as_ref())
+                .eq_ignore_ascii_case(locale_language(locale_id))
+    }
+
+    /// Selects the text that best suits the locale ids, which are in order of preference. For
+    /// each locale id a text in the locale is chosen over a text in its language. If no locale
+    /// id can be satisfied, the first text without a locale is chosen and failing that the first
+    /// text.
+    pub fn select<'a>(
+        texts: &'a [LocalizedText],
+        locale_ids: &[UAString],
+    ) -> Option<&'a LocalizedText> {
+        locale_ids
+            .iter()
+            .filter(|locale_id| !locale_id.is_empty())
+            .find_map(|locale_id| {
+                texts
+                    .iter()
+                    .find(|text| text.is_locale(locale_id.as_ref()))
+                    .or_else(|| {
+                        texts
+                            .iter()
+                            .find(|text| text.is_language_of(locale_id.as_ref()))
+                    })
+            })
+            .or_else(|| texts.iter().find(|text| text.locale.is_empty()))
+            .or_else(|| texts.first())
+    }
 }
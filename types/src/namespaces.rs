@@ -0,0 +1,331 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains the [`NamespaceTable`] that maps namespace URIs to the namespace indices used by
+//! `NodeId` and `QualifiedName`, the [`NamespaceMap`] that translates indices from one table
+//! to another, and the [`ServerTable`] that maps the server indices of an `ExpandedNodeId` to
+//! server URIs.
+//!
+//! [`NamespaceTable`]: ./struct.NamespaceTable.html
+//! [`NamespaceMap`]: ./struct.NamespaceMap.html
+//! [`ServerTable`]: ./struct.ServerTable.html
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    node_id::{ExpandedNodeId, NodeId},
+    qualified_name::QualifiedName,
+    status_code::StatusCode,
+    variant::Variant,
+};
+
+/// The URI of the OPC UA namespace, which is always namespace index 0
+pub const OPC_UA_NAMESPACE_URI: &str = "http://opcfoundation.org/UA/";
+
+/// A table of namespace URIs where the position of a URI in the table is its namespace index,
+/// i.e. the contents of a server's `NamespaceArray` variable.
+///
+/// Indices are stable: registering a namespace appends it to the table so the index of every
+/// namespace already in the table never changes. Namespace index 0 is always the OPC UA
+/// namespace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceTable {
+    uris: Vec<String>,
+}
+
+impl Default for NamespaceTable {
+    fn default() -> Self {
+        NamespaceTable {
+            uris: vec![OPC_UA_NAMESPACE_URI.to_string()],
+        }
+    }
+}
+
+impl NamespaceTable {
+    /// Creates a table that only holds the OPC UA namespace.
+    pub fn new() -> NamespaceTable {
+        NamespaceTable::default()
+    }
+
+    /// Creates a table from namespace URIs in index order, e.g. as read from the
+    /// `NamespaceArray` of a server.
+    pub fn from_uris<T>(uris: Vec<T>) -> NamespaceTable
+    where
+        T: Into<String>,
+    {
+        NamespaceTable {
+            uris: uris.into_iter().map(|uri| uri.into()).collect(),
+        }
+    }
+
+    /// Creates a table from the value of a `NamespaceArray` variable, which is an array of
+    /// strings. Returns `None` if the value is anything else.
+    pub fn from_variant(value: &Variant) -> Option<NamespaceTable> {
+        uris_from_variant(value).map(|uris| NamespaceTable { uris })
+    }
+
+    /// Returns the namespace URIs in index order.
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    /// Returns the number of namespaces in the table.
+    pub fn len(&self) -> usize {
+        self.uris.len()
+    }
+
+    /// Returns true if the table has no namespaces.
+    pub fn is_empty(&self) -> bool {
+        self.uris.is_empty()
+    }
+
+    /// Finds the index of a namespace URI.
+    pub fn index_of(&self, uri: &str) -> Option<u16> {
+        self.uris
+            .iter()
+            .position(|u| u == uri)
+            .map(|index| index as u16)
+    }
+
+    /// Finds the namespace URI of an index.
+    pub fn uri(&self, index: u16) -> Option<&str> {
+        self.uris.get(index as usize).map(|uri| uri.as_str())
+    }
+
+    /// Registers a namespace URI and returns its index. A URI that is already in the table keeps its
+    /// index. Fails with `BadInvalidArgument` if the URI is empty or `BadTooManyOperations` if the
+    /// table is full.
+    pub fn register(&mut self, uri: &str) -> Result<u16, StatusCode> {
+        if uri.is_empty() {
+            Err(StatusCode::BadInvalidArgument)
+        } else if let Some(index) = self.index_of(uri) {
+            Ok(index)
+        } else if self.uris.len() >= NamespaceMap::UNMAPPED as usize {
+            Err(StatusCode::BadTooManyOperations)
+        } else {
+            self.uris.push(uri.to_string());
+            Ok((self.uris.len() - 1) as u16)
+        }
+    }
+
+    /// Registers every namespace of another table, e.g. the namespaces of a node set being
+    /// imported, and returns the map that translates the indices of the other table into indices
+    /// of this one. Note that the `NamespaceUris` of a UANodeSet file omit the OPC UA namespace,
+    /// so it must be put first. Fails like [`register`] on the first namespace that cannot be
+    /// registered.
+    ///
+    /// [`register`]: #method.register
+    pub fn import(&mut self, other: &NamespaceTable) -> Result<NamespaceMap, StatusCode> {
+        let indices = other
+            .uris
+            .iter()
+            .map(|uri| self.register(uri))
+            .collect::<Result<Vec<u16>, StatusCode>>()?;
+        Ok(NamespaceMap { indices })
+    }
+
+    /// Makes a map that translates the indices of another table into indices of this one without
+    /// registering anything. Indices of namespaces that this table does not have are unmapped.
+    pub fn map_from(&self, other: &NamespaceTable) -> NamespaceMap {
+        let indices = other
+            .uris
+            .iter()
+            .map(|uri| self.index_of(uri).unwrap_or(NamespaceMap::UNMAPPED))
+            .collect();
+        NamespaceMap { indices }
+    }
+
+    /// Resolves the namespace URI of an expanded node id into an index, returning the node id
+    /// with that index. An expanded node id without a URI is returned as it is. A node id on
+    /// another server cannot be resolved against this table, see [`ServerTable::resolve`].
+    ///
+    /// [`ServerTable::resolve`]: ./struct.ServerTable.html#method.resolve
+    pub fn resolve(&self, node_id: &ExpandedNodeId) -> Option<NodeId> {
+        if !node_id.is_local() {
+            None
+        } else if node_id.namespace_uri.is_null() {
+            Some(node_id.node_id.clone())
+        } else {
+            self.index_of(node_id.namespace_uri.as_ref())
+                .map(|namespace| NodeId {
+                    namespace,
+                    identifier: node_id.node_id.identifier.clone(),
+                })
+        }
+    }
+
+    /// Turns a node id into an expanded node id that holds the namespace URI instead of the index,
+    /// so it can be resolved against another table.
+    pub fn expand(&self, node_id: &NodeId) -> Option<ExpandedNodeId> {
+        self.uri(node_id.namespace)
+            .map(|uri| ExpandedNodeId::with_namespace_uri(uri, node_id.identifier.clone()))
+    }
+}
+
+impl From<&NamespaceTable> for Variant {
+    fn from(v: &NamespaceTable) -> Self {
+        Variant::from(&v.uris)
+    }
+}
+
+/// Translates the namespace indices of one [`NamespaceTable`] into the indices of another, e.g.
+/// from the indices used in a node set file into the indices of the server it is imported into.
+///
+/// [`NamespaceTable`]: ./struct.NamespaceTable.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamespaceMap {
+    indices: Vec<u16>,
+}
+
+impl NamespaceMap {
+    const UNMAPPED: u16 = u16::MAX;
+
+    /// Translates a namespace index. Returns `None` for an index the map does not know.
+    pub fn index(&self, index: u16) -> Option<u16> {
+        match self.indices.get(index as usize) {
+            Some(&Self::UNMAPPED) | None => None,
+            Some(index) => Some(*index),
+        }
+    }
+
+    /// Translates the namespace of a node id.
+    pub fn node_id(&self, node_id: &NodeId) -> Option<NodeId> {
+        self.index(node_id.namespace).map(|namespace| NodeId {
+            namespace,
+            identifier: node_id.identifier.clone(),
+        })
+    }
+
+    /// Translates the namespace of a qualified name.
+    pub fn qualified_name(&self, name: &QualifiedName) -> Option<QualifiedName> {
+        self.index(name.namespace_index)
+            .map(|namespace_index| QualifiedName {
+                namespace_index,
+                name: name.name.clone(),
+            })
+    }
+}
+
+/// A table of server URIs where the position of a URI in the table is the server index used by
+/// `ExpandedNodeId`, i.e. the contents of a server's `ServerArray` variable. Server index 0 is
+/// always the server that the table belongs to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerTable {
+    uris: Vec<String>,
+}
+
+/// An expanded node id that has been resolved against the tables of the server it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedNodeId {
+    /// The node is on the server the tables belong to and this is its node id there.
+    Local(NodeId),
+    /// The node is on another server. The node id has a server index of 0 and any namespace
+    /// index it holds is an index of the other server, so it must be resolved against the
+    /// namespace table of that server.
+    Remote {
+        server_uri: String,
+        node_id: ExpandedNodeId,
+    },
+}
+
+impl ServerTable {
+    /// Creates a table that only holds the server itself.
+    pub fn new(server_uri: &str) -> ServerTable {
+        ServerTable {
+            uris: vec![server_uri.to_string()],
+        }
+    }
+
+    /// Creates a table from server URIs in index order.
+    pub fn from_uris<T>(uris: Vec<T>) -> ServerTable
+    where
+        T: Into<String>,
+    {
+        ServerTable {
+            uris: uris.into_iter().map(|uri| uri.into()).collect(),
+        }
+    }
+
+    /// Creates a table from the value of a `ServerArray` variable, which is an array of strings.
+    /// Returns `None` if the value is anything else.
+    pub fn from_variant(value: &Variant) -> Option<ServerTable> {
+        uris_from_variant(value).map(|uris| ServerTable { uris })
+    }
+
+    /// Returns the server URIs in index order.
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    /// Finds the index of a server URI.
+    pub fn index_of(&self, uri: &str) -> Option<u32> {
+        self.uris
+            .iter()
+            .position(|u| u == uri)
+            .map(|index| index as u32)
+    }
+
+    /// Finds the server URI of an index.
+    pub fn uri(&self, index: u32) -> Option<&str> {
+        self.uris.get(index as usize).map(|uri| uri.as_str())
+    }
+
+    /// Resolves an expanded node id. A node id on this server has its namespace URI resolved
+    /// against the namespace table. A node id on another server has its server index replaced
+    /// with the URI of that server. Returns `None` if the server index or the namespace URI is
+    /// not in the tables.
+    pub fn resolve(
+        &self,
+        node_id: &ExpandedNodeId,
+        namespaces: &NamespaceTable,
+    ) -> Option<ResolvedNodeId> {
+        if node_id.is_local() {
+            namespaces.resolve(node_id).map(ResolvedNodeId::Local)
+        } else {
+            self.uri(node_id.server_index)
+                .map(|server_uri| ResolvedNodeId::Remote {
+                    server_uri: server_uri.to_string(),
+                    node_id: ExpandedNodeId {
+                        server_index: 0,
+                        ..node_id.clone()
+                    },
+                })
+        }
+    }
+
+    /// Points an expanded node id at the server with the URI, e.g. to describe a reference to a
+    /// node on another server. Returns `None` if the server is not in the table.
+    pub fn expand(&self, server_uri: &str, node_id: &ExpandedNodeId) -> Option<ExpandedNodeId> {
+        self.index_of(server_uri)
+            .map(|server_index| ExpandedNodeId {
+                server_index,
+                ..node_id.clone()
+            })
+    }
+}
+
+impl From<&ServerTable> for Variant {
+    fn from(v: &ServerTable) -> Self {
+        Variant::from(&v.uris)
+    }
+}
+
+fn uris_from_variant(value: &Variant) -> Option<Vec<String>> {
+    if let Variant::Array(array) = value {
+        array
+            .values
+            .iter()
+            .map(|v| match v {
+                Variant::String(uri) if !uri.is_null() => Some(uri.as_ref().to_string()),
+                _ => None,
+            })
+            .collect()
+    } else {
+        None
+    }
+}
@@ -4,20 +4,19 @@
 
 //! Contains the implementation of `NodeId` and `ExpandedNodeId`.
 
-use std::{
-    self,
+use alloc::string::{String, ToString};
+use core::{
     convert::TryFrom,
     fmt,
-    io::{Read, Write},
     str::FromStr,
     sync::atomic::{AtomicUsize, Ordering},
-    u16, u32,
 };
 
-use crate::types::{
+use crate::{
     byte_string::ByteString,
     encoding::*,
     guid::Guid,
+    io::{Read, Write},
     node_ids::{ObjectId, ReferenceTypeId},
     status_codes::StatusCode,
     string::*,
@@ -37,7 +36,7 @@ impl fmt::Display for Identifier {
         match self {
             Identifier::Numeric(v) => write!(f, "i={}", *v),
             Identifier::String(v) => write!(f, "s={}", v),
-            Identifier::Guid(v) => write!(f, "g={:?}", v),
+            Identifier::Guid(v) => write!(f, "g={}", v),
             Identifier::ByteString(v) => write!(f, "b={}", v.as_base64()),
         }
     }
@@ -71,7 +70,7 @@ impl From<i32> for Identifier {
 
 impl From<u32> for Identifier {
     fn from(v: u32) -> Self {
-        Identifier::Numeric(v as u32)
+        Identifier::Numeric(v)
     }
 }
 
@@ -120,9 +119,15 @@ impl fmt::Display for NodeIdError {
     }
 }
 
-impl std::error::Error for NodeIdError {}
+impl core::error::Error for NodeIdError {}
 
 /// An identifier for a node in the address space of an OPC UA Server.
+///
+/// A node id can be parsed from and displayed as the string form of OPC UA Part 6, e.g.
+/// `ns=2;s=Device.Temp`, `i=2258`, `ns=1;g=72962b91-fa75-4ae6-8d28-b404dc7daf63` or
+/// `ns=1;b=M/RbKBsRVkePCePcx24oRA==`, where the namespace is omitted when it is 0. Displaying a
+/// node id and parsing the result gives back the same node id, except for null or empty string and
+/// byte string identifiers which have no string form.
 #[derive(PartialEq, Eq, Clone, Debug, Hash, Serialize, Deserialize)]
 pub struct NodeId {
     /// The index for a namespace
@@ -246,8 +251,8 @@ impl BinaryEncoder<NodeId> for NodeId {
 
 impl FromStr for NodeId {
     type Err = StatusCode;
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        use regex::Regex;
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        use crate::pattern::Regex;
 
         // Parses a node from a string using the format specified in 5.3.1.10 part 6
         //
@@ -262,8 +267,9 @@ impl FromStr for NodeId {
         // If namespace == 0, the ns=0; will be omitted
 
         lazy_static! {
-            // Contains capture groups "ns" and "t" for namespace and type respectively
-            static ref RE: Regex = Regex::new(r"^(ns=(?P<ns>[0-9]+);)?(?P<t>[isgb]=.+)$").unwrap();
+            // Contains capture groups "ns" and "t" for namespace and type respectively. The value
+            // may span lines since a string identifier can hold any character.
+            static ref RE: Regex = Regex::new(r"(?s)^(ns=(?P<ns>[0-9]+);)?(?P<t>[isgb]=.+)$").unwrap();
         }
 
         let captures = RE.captures(s).ok_or(StatusCode::BadNodeIdInvalid)?;
@@ -291,9 +297,9 @@ impl From<&NodeId> for NodeId {
     }
 }
 
-impl Into<String> for NodeId {
-    fn into(self) -> String {
-        self.to_string()
+impl From<NodeId> for String {
+    fn from(value: NodeId) -> Self {
+        value.to_string()
     }
 }
 
@@ -387,7 +393,7 @@ impl NodeId {
     }
 
     /// Extracts an ObjectId from a node id, providing the node id holds an object id
-    pub fn as_object_id(&self) -> std::result::Result<ObjectId, NodeIdError> {
+    pub fn as_object_id(&self) -> core::result::Result<ObjectId, NodeIdError> {
         match self.identifier {
             Identifier::Numeric(id) if self.namespace == 0 => {
                 ObjectId::try_from(id).map_err(|_| NodeIdError)
@@ -396,7 +402,7 @@ impl NodeId {
         }
     }
 
-    pub fn as_reference_type_id(&self) -> std::result::Result<ReferenceTypeId, NodeIdError> {
+    pub fn as_reference_type_id(&self) -> core::result::Result<ReferenceTypeId, NodeIdError> {
         // TODO this function should not exist - filter code should work with non ns 0 reference
         // types
         if self.is_null() {
@@ -567,9 +573,9 @@ impl BinaryEncoder<ExpandedNodeId> for ExpandedNodeId {
     }
 }
 
-impl<'a> Into<ExpandedNodeId> for &'a NodeId {
-    fn into(self) -> ExpandedNodeId {
-        self.clone().into()
+impl From<&NodeId> for ExpandedNodeId {
+    fn from(value: &NodeId) -> Self {
+        value.clone().into()
     }
 }
 
@@ -588,7 +594,14 @@ impl fmt::Display for ExpandedNodeId {
         // Formatted depending on the namespace uri being empty or not.
         if self.namespace_uri.is_empty() {
             // svr=<serverindex>;ns=<namespaceindex>;<type>=<value>
-            write!(f, "svr={};{}", self.server_index, self.node_id)
+            //
+            // Unlike a node id, the namespace is written even when it is 0 because parsing
+            // requires it.
+            write!(
+                f,
+                "svr={};ns={};{}",
+                self.server_index, self.node_id.namespace, self.node_id.identifier
+            )
         } else {
             // The % and ; chars have to be escaped out in the uri
             let namespace_uri = String::from(self.namespace_uri.as_ref())
@@ -606,8 +619,8 @@ impl fmt::Display for ExpandedNodeId {
 
 impl FromStr for ExpandedNodeId {
     type Err = StatusCode;
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        use regex::Regex;
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        use crate::pattern::Regex;
 
         // Parses a node from a string using the format specified in 5.3.1.11 part 6
         //
@@ -617,7 +630,7 @@ impl FromStr for ExpandedNodeId {
 
         lazy_static! {
             // Contains capture groups "svr", either "ns" or "nsu" and then "t" for type
-            static ref RE: Regex = Regex::new(r"^svr=(?P<svr>[0-9]+);(ns=(?P<ns>[0-9]+)|nsu=(?P<nsu>[^;]+));(?P<t>[isgb]=.+)$").unwrap();
+            static ref RE: Regex = Regex::new(r"(?s)^svr=(?P<svr>[0-9]+);(ns=(?P<ns>[0-9]+)|nsu=(?P<nsu>[^;]+));(?P<t>[isgb]=.+)$").unwrap();
         }
 
         let captures = RE.captures(s).ok_or(StatusCode::BadNodeIdInvalid)?;
@@ -677,7 +690,37 @@ impl ExpandedNodeId {
         Self::new(NodeId::null())
     }
 
+    /// Creates an expanded node id that identifies its namespace by URI rather than by index.
+    pub fn with_namespace_uri<T>(namespace_uri: &str, identifier: T) -> ExpandedNodeId
+    where
+        T: Into<Identifier>,
+    {
+        ExpandedNodeId {
+            node_id: NodeId {
+                namespace: 0,
+                identifier: identifier.into(),
+            },
+            namespace_uri: UAString::from(namespace_uri),
+            server_index: 0,
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         self.node_id.is_null()
     }
+
+    /// Test if the node is on the local server, i.e. the server index is 0
+    pub fn is_local(&self) -> bool {
+        self.server_index == 0
+    }
+
+    /// Returns the node id if it can be used without being resolved first, i.e. the node is on
+    /// the local server and its namespace is given by an index rather than a URI.
+    pub fn as_local_node_id(&self) -> Option<&NodeId> {
+        if self.is_local() && self.namespace_uri.is_null() {
+            Some(&self.node_id)
+        } else {
+            None
+        }
+    }
 }
@@ -6,9 +6,9 @@
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
-use crate::types::{
+use crate::{
     node_id::{NodeId, ExpandedNodeId},
     string::UAString,
 };
@@ -420,16 +420,16 @@ impl<'a> From<&'a DataTypeId> for NodeId {
     }
 }
 
-impl Into<NodeId> for DataTypeId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<DataTypeId> for NodeId {
+    fn from(r: DataTypeId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for DataTypeId {
-    fn into(self) -> ExpandedNodeId {
+impl From<DataTypeId> for ExpandedNodeId {
+    fn from(r: DataTypeId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -899,16 +899,16 @@ impl<'a> From<&'a ReferenceTypeId> for NodeId {
     }
 }
 
-impl Into<NodeId> for ReferenceTypeId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<ReferenceTypeId> for NodeId {
+    fn from(r: ReferenceTypeId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for ReferenceTypeId {
-    fn into(self) -> ExpandedNodeId {
+impl From<ReferenceTypeId> for ExpandedNodeId {
+    fn from(r: ReferenceTypeId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -1201,16 +1201,16 @@ impl<'a> From<&'a ObjectTypeId> for NodeId {
     }
 }
 
-impl Into<NodeId> for ObjectTypeId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<ObjectTypeId> for NodeId {
+    fn from(r: ObjectTypeId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for ObjectTypeId {
-    fn into(self) -> ExpandedNodeId {
+impl From<ObjectTypeId> for ExpandedNodeId {
+    fn from(r: ObjectTypeId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -1516,16 +1516,16 @@ impl<'a> From<&'a VariableTypeId> for NodeId {
     }
 }
 
-impl Into<NodeId> for VariableTypeId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<VariableTypeId> for NodeId {
+    fn from(r: VariableTypeId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for VariableTypeId {
-    fn into(self) -> ExpandedNodeId {
+impl From<VariableTypeId> for ExpandedNodeId {
+    fn from(r: VariableTypeId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -2674,16 +2674,16 @@ impl<'a> From<&'a ObjectId> for NodeId {
     }
 }
 
-impl Into<NodeId> for ObjectId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<ObjectId> for NodeId {
+    fn from(r: ObjectId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for ObjectId {
-    fn into(self) -> ExpandedNodeId {
+impl From<ObjectId> for ExpandedNodeId {
+    fn from(r: ObjectId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -14517,16 +14517,16 @@ impl<'a> From<&'a VariableId> for NodeId {
     }
 }
 
-impl Into<NodeId> for VariableId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<VariableId> for NodeId {
+    fn from(r: VariableId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for VariableId {
-    fn into(self) -> ExpandedNodeId {
+impl From<VariableId> for ExpandedNodeId {
+    fn from(r: VariableId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
@@ -26468,16 +26468,16 @@ impl<'a> From<&'a MethodId> for NodeId {
     }
 }
 
-impl Into<NodeId> for MethodId {
-    fn into(self) -> NodeId {
-        NodeId::new(0, self as u32)
+impl From<MethodId> for NodeId {
+    fn from(r: MethodId) -> Self {
+        NodeId::new(0, r as u32)
     }
 }
 
-impl Into<ExpandedNodeId> for MethodId {
-    fn into(self) -> ExpandedNodeId {
+impl From<MethodId> for ExpandedNodeId {
+    fn from(r: MethodId) -> Self {
         ExpandedNodeId {
-            node_id: NodeId::new(0, self as u32),
+            node_id: NodeId::new(0, r as u32),
             namespace_uri: UAString::null(),
             server_index: 0,
         }
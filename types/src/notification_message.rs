@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-///! Helpers for NotificationMessage types
-use crate::types::{
+//! Helpers for NotificationMessage types
+use alloc::{vec, vec::Vec};
+
+use crate::{
     date_time::DateTime,
     diagnostic_info::DiagnosticInfo,
     encoding::DecodingOptions,
@@ -139,4 +141,23 @@ impl NotificationMessage {
             None
         }
     }
+
+    /// Extract the status change notification from the message, which tells the client that the
+    /// state of the subscription has changed, e.g. because it timed out or was transferred to
+    /// another session. If there is none, or it cannot be parsed, the function will return `None`.
+    pub fn status_change_notification(
+        &self,
+        decoding_options: &DecodingOptions,
+    ) -> Option<StatusChangeNotification> {
+        self.notification_data
+            .as_ref()?
+            .iter()
+            .filter(|n| {
+                n.node_id == ObjectId::StatusChangeNotification_Encoding_DefaultBinary.into()
+            })
+            .find_map(|n| {
+                n.decode_inner::<StatusChangeNotification>(decoding_options)
+                    .ok()
+            })
+    }
 }
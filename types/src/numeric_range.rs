@@ -4,9 +4,12 @@
 
 //! Contains the implementation of `NumericRange`.
 
-use std::{fmt, str::FromStr};
+use alloc::{format, string::String, vec::Vec};
+use core::{fmt, str::FromStr};
 
-use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::pattern::Regex;
 
 #[derive(Debug)]
 pub struct NumericRangeError;
@@ -17,7 +20,7 @@ impl fmt::Display for NumericRangeError {
     }
 }
 
-impl std::error::Error for NumericRangeError {}
+impl core::error::Error for NumericRangeError {}
 
 /// Numeric range describes a range within an array. See OPCUA Part 4 7.22
 ///
@@ -187,6 +190,27 @@ impl FromStr for NumericRange {
     }
 }
 
+impl Serialize for NumericRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.as_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NumericRange {
+    fn deserialize<D>(deserializer: D) -> Result<NumericRange, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let range = String::deserialize(deserializer)?;
+        NumericRange::from_str(&range).map_err(|_| D::Error::custom("Invalid numeric range"))
+    }
+}
+
 impl NumericRange {
     pub fn new<T>(s: T) -> Result<Self, NumericRangeError>
     where
@@ -2,14 +2,15 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::convert::TryFrom;
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
 
-use crate::types::{
+use crate::{
     attribute::AttributeId,
-    node_ids::ObjectId,
+    node_ids::{ObjectId, ObjectTypeId},
     service_types::{
-        AttributeOperand, ContentFilter, ContentFilterElement, ElementOperand, FilterOperator,
-        LiteralOperand, SimpleAttributeOperand,
+        AttributeOperand, ContentFilter, ContentFilterElement, ElementOperand, EventFieldList,
+        EventFilter, FilterOperator, LiteralOperand, SimpleAttributeOperand,
     },
     status_code::StatusCode,
     DecodingOptions, ExtensionObject, NodeId, QualifiedName, UAString, Variant,
@@ -407,6 +408,16 @@ impl ContentFilterBuilder {
         self.add_element(FilterOperator::BitwiseOr, vec![o1.into(), o2.into()])
     }
 
+    pub fn of_type<T>(self, type_definition_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.add_element(
+            FilterOperator::OfType,
+            vec![Operand::literal(Variant::from(type_definition_id.into()))],
+        )
+    }
+
     pub fn build(self) -> ContentFilter {
         ContentFilter {
             elements: Some(self.elements),
@@ -442,4 +453,188 @@ impl SimpleAttributeOperand {
             index_range,
         }
     }
+    /// Returns the browse path as a string using / as a separator, escaping any / within a browse
+    /// name as `\/`, i.e. the form of browse path that `SimpleAttributeOperand::new()` takes. An
+    /// operand with no browse path that selects the `NodeId` attribute is named `ConditionId`, since
+    /// that is how a select clause asks for the condition id of a condition.
+    pub fn field_name(&self) -> String {
+        match self.browse_path {
+            Some(ref browse_path) if !browse_path.is_empty() => browse_path
+                .iter()
+                .map(|browse_name| browse_name.name.as_ref().replace('/', r"\/"))
+                .collect::<Vec<_>>()
+                .join("/"),
+            _ if self.attribute_id == AttributeId::NodeId as u32 => "ConditionId".into(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// This is a convenience for building a [`SimpleAttributeOperand`]. Unlike `SimpleAttributeOperand::new()`
+/// the browse path can be built from browse names in any namespace. By default the operand selects
+/// the `Value` attribute of a field of `BaseEventType` and the browse path is empty.
+pub struct SimpleAttributeOperandBuilder {
+    type_definition_id: NodeId,
+    browse_path: Vec<QualifiedName>,
+    attribute_id: AttributeId,
+    index_range: UAString,
+}
+
+impl Default for SimpleAttributeOperandBuilder {
+    fn default() -> Self {
+        SimpleAttributeOperandBuilder {
+            type_definition_id: ObjectTypeId::BaseEventType.into(),
+            browse_path: Vec::new(),
+            attribute_id: AttributeId::Value,
+            index_range: UAString::null(),
+        }
+    }
+}
+
+impl SimpleAttributeOperandBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn type_definition_id<T>(mut self, type_definition_id: T) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.type_definition_id = type_definition_id.into();
+        self
+    }
+
+    /// Sets the browse path from a string of browse names using / as a separator. Each browse
+    /// name may be prefixed with its namespace index, e.g. `2:Tank/2:Level`, see
+    /// `QualifiedName::parse()`, otherwise it is in namespace 0.
+    pub fn browse_path(mut self, browse_path: &str) -> Self {
+        let operand = SimpleAttributeOperand::new(
+            NodeId::null(),
+            browse_path,
+            AttributeId::Value,
+            UAString::null(),
+        );
+        self.browse_path = operand
+            .browse_path
+            .unwrap_or_default()
+            .into_iter()
+            .map(|browse_name| {
+                QualifiedName::parse(browse_name.name.as_ref()).unwrap_or(browse_name)
+            })
+            .collect();
+        self
+    }
+
+    /// Appends a browse name to the browse path.
+    pub fn browse_name<T>(mut self, browse_name: T) -> Self
+    where
+        T: Into<QualifiedName>,
+    {
+        self.browse_path.push(browse_name.into());
+        self
+    }
+
+    pub fn attribute_id(mut self, attribute_id: AttributeId) -> Self {
+        self.attribute_id = attribute_id;
+        self
+    }
+
+    pub fn index_range<T>(mut self, index_range: T) -> Self
+    where
+        T: Into<UAString>,
+    {
+        self.index_range = index_range.into();
+        self
+    }
+
+    pub fn build(self) -> SimpleAttributeOperand {
+        SimpleAttributeOperand {
+            type_definition_id: self.type_definition_id,
+            browse_path: Some(self.browse_path),
+            attribute_id: self.attribute_id as u32,
+            index_range: self.index_range,
+        }
+    }
+}
+
+/// This is a convenience for building an [`EventFilter`] from select clauses and a where clause.
+/// The where clause can be built with a [`ContentFilterBuilder`]. Without a where clause the filter
+/// matches every event.
+///
+/// The fields of the resulting events are in the order of the select clauses, and
+/// `EventFilter::event_fields()` pairs them back up with the clauses.
+pub struct EventFilterBuilder {
+    select_clauses: Vec<SimpleAttributeOperand>,
+    where_clause: ContentFilter,
+}
+
+impl Default for EventFilterBuilder {
+    fn default() -> Self {
+        EventFilterBuilder {
+            select_clauses: Vec::new(),
+            where_clause: ContentFilter { elements: None },
+        }
+    }
+}
+
+impl EventFilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the value of a field of an event type. The browse path is the browse name using
+    /// / as a separator.
+    pub fn select<T>(self, type_definition_id: T, browse_path: &str) -> Self
+    where
+        T: Into<NodeId>,
+    {
+        self.select_operand(SimpleAttributeOperand::new(
+            type_definition_id,
+            browse_path,
+            AttributeId::Value,
+            UAString::null(),
+        ))
+    }
+
+    /// Selects the condition id of events that are conditions.
+    pub fn select_condition_id(self) -> Self {
+        self.select_operand(
+            SimpleAttributeOperandBuilder::new()
+                .type_definition_id(ObjectTypeId::ConditionType)
+                .attribute_id(AttributeId::NodeId)
+                .build(),
+        )
+    }
+
+    pub fn select_operand(mut self, select_clause: SimpleAttributeOperand) -> Self {
+        self.select_clauses.push(select_clause);
+        self
+    }
+
+    pub fn where_clause(mut self, where_clause: ContentFilter) -> Self {
+        self.where_clause = where_clause;
+        self
+    }
+
+    pub fn build(self) -> EventFilter {
+        EventFilter {
+            select_clauses: Some(self.select_clauses),
+            where_clause: self.where_clause,
+        }
+    }
+}
+
+impl EventFilter {
+    /// Pairs the fields of an event with the select clauses of this filter and returns them keyed
+    /// by the field name of each clause. Clauses that the event has no field for are left out.
+    pub fn event_fields(&self, event: &EventFieldList) -> BTreeMap<String, Variant> {
+        match (&self.select_clauses, &event.event_fields) {
+            (Some(select_clauses), Some(event_fields)) => select_clauses
+                .iter()
+                .zip(event_fields.iter())
+                .map(|(select_clause, field)| (select_clause.field_name(), field.clone()))
+                .collect(),
+            _ => BTreeMap::new(),
+        }
+    }
 }
@@ -0,0 +1,56 @@
+// OPCUA for Rust
+// SPDX-License-Identifier: MPL-2.0
+// Copyright (C) 2017-2022 Adam Lock
+
+//! Contains a small regular expression type for the parsers of node ids, numeric ranges and
+//! relative paths. It wraps the meta engine of `regex-automata`, which works without std unlike
+//! the `regex` crate, behind the part of the `regex` API that the parsers use.
+
+use alloc::boxed::Box;
+
+use regex_automata::{meta, util::captures};
+
+pub(crate) struct Regex(meta::Regex);
+
+impl Regex {
+    /// Compiles the pattern
+    pub fn new(pattern: &str) -> Result<Regex, Box<meta::BuildError>> {
+        meta::Regex::new(pattern).map(Regex).map_err(Box::new)
+    }
+
+    /// Finds the leftmost match in the haystack and returns its capture groups
+    pub fn captures<'h>(&self, haystack: &'h str) -> Option<Captures<'h>> {
+        let mut captures = self.0.create_captures();
+        self.0.captures(haystack, &mut captures);
+        if captures.is_match() {
+            Some(Captures {
+                haystack,
+                captures,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) struct Captures<'h> {
+    haystack: &'h str,
+    captures: captures::Captures,
+}
+
+impl<'h> Captures<'h> {
+    /// Returns the text of the named group, or `None` if the group did not take part in the match
+    pub fn name(&self, name: &str) -> Option<Match<'h>> {
+        self.captures
+            .get_group_by_name(name)
+            .map(|span| Match(&self.haystack[span.range()]))
+    }
+}
+
+pub(crate) struct Match<'h>(&'h str);
+
+impl<'h> Match<'h> {
+    pub fn as_str(&self) -> &'h str {
+        self.0
+    }
+}
@@ -3,14 +3,22 @@
 // Copyright (C) 2017-2022 Adam Lock
 
 //! Contains the definition of `QualifiedName`.
-use std::io::{Read, Write};
+use alloc::string::String;
+use core::{fmt, str::FromStr};
 
-use crate::types::{encoding::*, string::*};
+use crate::{
+    encoding::*,
+    io::{Read, Write},
+    status_codes::StatusCode,
+    string::*,
+};
 
 /// An identifier for a error or condition that is associated with a value or an operation.
 ///
-/// A name qualified by a namespace.
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A name qualified by a namespace. Two qualified names are equal only if their namespace indexes
+/// are equal and their names are equal, compared case sensitively, as the specification requires
+/// for browse names.
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Serialize, Deserialize)]
 pub struct QualifiedName {
     /// The namespace index.
     pub namespace_index: u16,
@@ -45,6 +53,26 @@ impl From<String> for QualifiedName {
     }
 }
 
+impl fmt::Display for QualifiedName {
+    /// Writes the name in the form that `QualifiedName::parse()` reads, i.e. `nsidx:name`, with
+    /// the namespace index omitted when it is 0.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.namespace_index == 0 {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}:{}", self.namespace_index, self.name)
+        }
+    }
+}
+
+impl FromStr for QualifiedName {
+    type Err = StatusCode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        QualifiedName::parse(s)
+    }
+}
+
 impl BinaryEncoder<QualifiedName> for QualifiedName {
     fn byte_len(&self) -> usize {
         let mut size: usize = 0;
@@ -92,4 +120,24 @@ impl QualifiedName {
     pub fn is_null(&self) -> bool {
         self.namespace_index == 0 && self.name.is_null()
     }
+
+    /// Parses a qualified name from a string of the form `nsidx:name`, e.g. `2:MyName`, or just
+    /// `name` for a name in namespace 0. Only a prefix of digits is taken to be a namespace index,
+    /// so a name such as `Server:Status` is a namespace 0 name containing a colon. A namespace
+    /// index that does not fit in a `u16` is an error.
+    pub fn parse(s: &str) -> Result<QualifiedName, StatusCode> {
+        match s.split_once(':') {
+            Some((namespace_index, name))
+                if !namespace_index.is_empty()
+                    && namespace_index.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                let namespace_index = namespace_index.parse::<u16>().map_err(|_| {
+                    error!("Namespace index {} is out of range", namespace_index);
+                    StatusCode::BadBrowseNameInvalid
+                })?;
+                Ok(QualifiedName::new(namespace_index, name))
+            }
+            _ => Ok(QualifiedName::new(0, s)),
+        }
+    }
 }
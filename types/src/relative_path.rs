@@ -7,29 +7,22 @@
 //! Functions are implemented on the `RelativePath` and `RelativePathElement` structs where
 //! there are most useful.
 //!
-use std::{error::Error, fmt};
 
-use regex::Regex;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 
-use crate::types::{
+use crate::{
     node_id::{Identifier, NodeId},
     node_ids::*,
+    pattern::Regex,
     qualified_name::QualifiedName,
     service_types::{RelativePath, RelativePathElement},
     string::UAString,
 };
 
-#[derive(Debug)]
-struct RelativePathError;
-
-impl fmt::Display for RelativePathError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "RelativePathError")
-    }
-}
-
-impl Error for RelativePathError {}
-
 impl RelativePath {
     /// The maximum size in chars of any path element.
     const MAX_TOKEN_LEN: usize = 256;
@@ -39,6 +32,7 @@ impl RelativePath {
     /// Converts a string into a relative path. Caller must supply a `node_resolver` which will
     /// be used to look up nodes from their browse name. The function will reject strings
     /// that look unusually long or contain too many elements.
+    #[allow(clippy::result_unit_err)]
     pub fn from_str<CB>(path: &str, node_resolver: &CB) -> Result<RelativePath, ()>
     where
         CB: Fn(u16, &str) -> Option<NodeId>,
@@ -61,15 +55,13 @@ impl RelativePath {
                         // The next character is escaped and part of the token
                         escaped_char = true;
                     }
-                    '/' | '.' | '<' => {
+                    '/' | '.' | '<' if !token.is_empty() => {
                         // We have reached the start of a token and need to process the previous one
-                        if !token.is_empty() {
-                            if elements.len() == Self::MAX_ELEMENTS {
-                                break;
-                            }
-                            elements.push(RelativePathElement::from_str(&token, node_resolver)?);
-                            token.clear();
+                        if elements.len() == Self::MAX_ELEMENTS {
+                            break;
                         }
+                        elements.push(RelativePathElement::from_str(&token, node_resolver)?);
+                        token.clear();
                     }
                     _ => {}
                 }
@@ -237,6 +229,7 @@ impl RelativePathElement {
     /// * `<!NonHierarchicalReferences>foo`
     /// * `<#!2:MyReftype>2:blah`
     ///
+    #[allow(clippy::result_unit_err)]
     pub fn from_str<CB>(path: &str, node_resolver: &CB) -> Result<RelativePathElement, ()>
     where
         CB: Fn(u16, &str) -> Option<NodeId>,
@@ -392,37 +385,17 @@ fn unescape_browse_name(name: &str) -> String {
 /// * bar
 ///
 fn target_name(target_name: &str) -> Result<QualifiedName, ()> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"((?P<nsidx>[0-9+]):)?(?P<name>.*)").unwrap();
-    }
-    if let Some(captures) = RE.captures(target_name) {
-        let namespace = if let Some(namespace) = captures.name("nsidx") {
-            if let Ok(namespace) = namespace.as_str().parse::<u16>() {
-                namespace
-            } else {
-                error!(
-                    "Namespace {} for target name is out of range",
-                    namespace.as_str()
-                );
-                return Err(());
-            }
-        } else {
-            0
-        };
-        let name = if let Some(name) = captures.name("name") {
-            let name = name.as_str();
-            if name.is_empty() {
-                UAString::null()
-            } else {
-                UAString::from(unescape_browse_name(name))
-            }
-        } else {
-            UAString::null()
-        };
-        Ok(QualifiedName::new(namespace, name))
+    // Reserved chars are escaped with `&`, so an escaped `:` never separates the namespace index
+    let QualifiedName {
+        namespace_index,
+        name,
+    } = QualifiedName::parse(target_name).map_err(|_| ())?;
+    let name = if name.is_empty() {
+        UAString::null()
     } else {
-        Ok(QualifiedName::null())
-    }
+        UAString::from(unescape_browse_name(name.as_ref()))
+    };
+    Ok(QualifiedName::new(namespace_index, name))
 }
 
 /// Test that escaping of browse names works as expected in each direction
@@ -452,7 +425,7 @@ fn test_escape_browse_name() {
 /// and a RelativePathElement type
 #[test]
 fn test_relative_path_element() {
-    use crate::types::qualified_name::QualifiedName;
+    use crate::qualified_name::QualifiedName;
 
     [
         (
@@ -531,7 +504,7 @@ fn test_relative_path_element() {
 /// and a RelativePath type.
 #[test]
 fn test_relative_path() {
-    use crate::types::qualified_name::QualifiedName;
+    use crate::qualified_name::QualifiedName;
 
     // Samples are from OPC UA Part 4 Appendix A
     let tests = vec![
@@ -2,14 +2,15 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::{
-    self,
+use crate::{
+    data_types::*,
+    date_time::DateTime,
+    diagnostic_info::DiagnosticBits,
+    encoding::*,
+    extension_object::ExtensionObject,
     io::{Read, Write},
-};
-
-use crate::types::{
-    data_types::*, date_time::DateTime, diagnostic_info::DiagnosticBits, encoding::*,
-    extension_object::ExtensionObject, node_id::NodeId, string::UAString,
+    node_id::NodeId,
+    string::UAString,
 };
 
 /// The `RequestHeader` contains information common to every request from a client to the server.
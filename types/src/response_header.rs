@@ -2,14 +2,17 @@
 // SPDX-License-Identifier: MPL-2.0
 // Copyright (C) 2017-2022 Adam Lock
 
-use std::{
-    self,
-    io::{Read, Write},
-};
+use alloc::vec::Vec;
 
-use crate::types::{
-    data_types::*, date_time::DateTime, diagnostic_info::DiagnosticInfo, encoding::*,
-    extension_object::ExtensionObject, request_header::RequestHeader, status_codes::StatusCode,
+use crate::{
+    data_types::*,
+    date_time::DateTime,
+    diagnostic_info::DiagnosticInfo,
+    encoding::*,
+    extension_object::ExtensionObject,
+    io::{Read, Write},
+    request_header::RequestHeader,
+    status_codes::StatusCode,
     string::UAString,
 };
 
@@ -6,9 +6,9 @@
 //
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
-use std::io::{Read, Write};
+use crate::io::{Read, Write};
 #[allow(unused_imports)]
-use crate::types::{
+use crate::{
     encoding::*,
     basic_types::*,
     string::UAString,
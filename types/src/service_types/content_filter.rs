@@ -6,9 +6,10 @@
 //
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
-use std::io::{Read, Write};
+use alloc::vec::Vec;
+use crate::io::{Read, Write};
 #[allow(unused_imports)]
-use crate::types::{
+use crate::{
     encoding::*,
     basic_types::*,
     service_types::impls::MessageInfo,
@@ -16,7 +17,7 @@ use crate::types::{
     service_types::ContentFilterElement,
 };
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContentFilter {
     pub elements: Option<Vec<ContentFilterElement>>,
 }
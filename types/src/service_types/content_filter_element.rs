@@ -6,9 +6,10 @@
 //
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
-use std::io::{Read, Write};
+use alloc::vec::Vec;
+use crate::io::{Read, Write};
 #[allow(unused_imports)]
-use crate::types::{
+use crate::{
     encoding::*,
     basic_types::*,
     service_types::impls::MessageInfo,
@@ -17,7 +18,7 @@ use crate::types::{
     extension_object::ExtensionObject,
 };
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContentFilterElement {
     pub filter_operator: FilterOperator,
     pub filter_operands: Option<Vec<ExtensionObject>>,
@@ -6,15 +6,15 @@
 //
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
-use std::io::{Read, Write};
+use crate::io::{Read, Write};
 #[allow(unused_imports)]
-use crate::types::{
+use crate::{
     encoding::*,
     basic_types::*,
     service_types::enums::DataChangeTrigger,
 };
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataChangeFilter {
     pub trigger: DataChangeTrigger,
     pub deadband_type: u32,
@@ -7,10 +7,13 @@
 // DO NOT EDIT THIS FILE
 #![allow(unused_attributes)]
 #![allow(non_upper_case_globals)]
-use std::io::{Read, Write};
-use crate::types::{
+use core::convert::TryFrom;
+use crate::{
     encoding::*,
+    enumeration::{UaEnum, UaOptionSet},
+    io::{Read, Write},
     status_codes::StatusCode,
+    variant::Variant,
 };
 use bitflags;
 
@@ -25,6 +28,35 @@ pub enum NodeIdType {
     ByteString = 5,
 }
 
+impl TryFrom<u8> for NodeIdType {
+    type Error = StatusCode;
+
+    fn try_from(value: u8) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::TwoByte),
+            1 => Ok(Self::FourByte),
+            2 => Ok(Self::Numeric),
+            3 => Ok(Self::String),
+            4 => Ok(Self::Guid),
+            5 => Ok(Self::ByteString),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for NodeIdType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "TwoByte"),
+            (1, "FourByte"),
+            (2, "Numeric"),
+            (3, "String"),
+            (4, "Guid"),
+            (5, "ByteString"),
+        ]
+    }
+}
+
 impl BinaryEncoder<NodeIdType> for NodeIdType {
     fn byte_len(&self) -> usize {
         1
@@ -36,18 +68,10 @@ impl BinaryEncoder<NodeIdType> for NodeIdType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_u8(stream)?;
-        match value {
-            0 => Ok(Self::TwoByte),
-            1 => Ok(Self::FourByte),
-            2 => Ok(Self::Numeric),
-            3 => Ok(Self::String),
-            4 => Ok(Self::Guid),
-            5 => Ok(Self::ByteString),
-            v => {
-                error!("Invalid value {} for enum NodeIdType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum NodeIdType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -58,6 +82,29 @@ pub enum NamingRuleType {
     Constraint = 3,
 }
 
+impl TryFrom<i32> for NamingRuleType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::Mandatory),
+            2 => Ok(Self::Optional),
+            3 => Ok(Self::Constraint),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for NamingRuleType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "Mandatory"),
+            (2, "Optional"),
+            (3, "Constraint"),
+        ]
+    }
+}
+
 impl BinaryEncoder<NamingRuleType> for NamingRuleType {
     fn byte_len(&self) -> usize {
         4
@@ -69,15 +116,10 @@ impl BinaryEncoder<NamingRuleType> for NamingRuleType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::Mandatory),
-            2 => Ok(Self::Optional),
-            3 => Ok(Self::Constraint),
-            v => {
-                error!("Invalid value {} for enum NamingRuleType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum NamingRuleType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -89,6 +131,31 @@ pub enum OpenFileMode {
     Append = 8,
 }
 
+impl TryFrom<i32> for OpenFileMode {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::Read),
+            2 => Ok(Self::Write),
+            4 => Ok(Self::EraseExisting),
+            8 => Ok(Self::Append),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for OpenFileMode {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "Read"),
+            (2, "Write"),
+            (4, "EraseExisting"),
+            (8, "Append"),
+        ]
+    }
+}
+
 impl BinaryEncoder<OpenFileMode> for OpenFileMode {
     fn byte_len(&self) -> usize {
         4
@@ -100,16 +167,10 @@ impl BinaryEncoder<OpenFileMode> for OpenFileMode {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::Read),
-            2 => Ok(Self::Write),
-            4 => Ok(Self::EraseExisting),
-            8 => Ok(Self::Append),
-            v => {
-                error!("Invalid value {} for enum OpenFileMode", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum OpenFileMode", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -123,6 +184,35 @@ pub enum IdentityCriteriaType {
     AuthenticatedUser = 6,
 }
 
+impl TryFrom<i32> for IdentityCriteriaType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::UserName),
+            2 => Ok(Self::Thumbprint),
+            3 => Ok(Self::Role),
+            4 => Ok(Self::GroupId),
+            5 => Ok(Self::Anonymous),
+            6 => Ok(Self::AuthenticatedUser),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for IdentityCriteriaType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "UserName"),
+            (2, "Thumbprint"),
+            (3, "Role"),
+            (4, "GroupId"),
+            (5, "Anonymous"),
+            (6, "AuthenticatedUser"),
+        ]
+    }
+}
+
 impl BinaryEncoder<IdentityCriteriaType> for IdentityCriteriaType {
     fn byte_len(&self) -> usize {
         4
@@ -134,18 +224,10 @@ impl BinaryEncoder<IdentityCriteriaType> for IdentityCriteriaType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::UserName),
-            2 => Ok(Self::Thumbprint),
-            3 => Ok(Self::Role),
-            4 => Ok(Self::GroupId),
-            5 => Ok(Self::Anonymous),
-            6 => Ok(Self::AuthenticatedUser),
-            v => {
-                error!("Invalid value {} for enum IdentityCriteriaType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum IdentityCriteriaType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -159,6 +241,35 @@ pub enum TrustListMasks {
     All = 15,
 }
 
+impl TryFrom<i32> for TrustListMasks {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::TrustedCertificates),
+            2 => Ok(Self::TrustedCrls),
+            4 => Ok(Self::IssuerCertificates),
+            8 => Ok(Self::IssuerCrls),
+            15 => Ok(Self::All),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for TrustListMasks {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "None"),
+            (1, "TrustedCertificates"),
+            (2, "TrustedCrls"),
+            (4, "IssuerCertificates"),
+            (8, "IssuerCrls"),
+            (15, "All"),
+        ]
+    }
+}
+
 impl BinaryEncoder<TrustListMasks> for TrustListMasks {
     fn byte_len(&self) -> usize {
         4
@@ -170,18 +281,10 @@ impl BinaryEncoder<TrustListMasks> for TrustListMasks {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::None),
-            1 => Ok(Self::TrustedCertificates),
-            2 => Ok(Self::TrustedCrls),
-            4 => Ok(Self::IssuerCertificates),
-            8 => Ok(Self::IssuerCrls),
-            15 => Ok(Self::All),
-            v => {
-                error!("Invalid value {} for enum TrustListMasks", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum TrustListMasks", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -193,6 +296,31 @@ pub enum PubSubState {
     Error = 3,
 }
 
+impl TryFrom<i32> for PubSubState {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Disabled),
+            1 => Ok(Self::Paused),
+            2 => Ok(Self::Operational),
+            3 => Ok(Self::Error),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for PubSubState {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Disabled"),
+            (1, "Paused"),
+            (2, "Operational"),
+            (3, "Error"),
+        ]
+    }
+}
+
 impl BinaryEncoder<PubSubState> for PubSubState {
     fn byte_len(&self) -> usize {
         4
@@ -204,16 +332,10 @@ impl BinaryEncoder<PubSubState> for PubSubState {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Disabled),
-            1 => Ok(Self::Paused),
-            2 => Ok(Self::Operational),
-            3 => Ok(Self::Error),
-            v => {
-                error!("Invalid value {} for enum PubSubState", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum PubSubState", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -238,6 +360,32 @@ impl BinaryEncoder<DataSetFieldFlags> for DataSetFieldFlags {
     }
 }
 
+impl UaOptionSet for DataSetFieldFlags {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "PromotedField"),
+        ]
+    }
+
+    fn size() -> usize {
+        2
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u16 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        DataSetFieldFlags::from_bits_truncate(bits as u16 as i16)
+    }
+}
+
+impl From<DataSetFieldFlags> for Variant {
+    fn from(v: DataSetFieldFlags) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct DataSetFieldContentMask: i32 {
@@ -265,6 +413,37 @@ impl BinaryEncoder<DataSetFieldContentMask> for DataSetFieldContentMask {
     }
 }
 
+impl UaOptionSet for DataSetFieldContentMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "StatusCode"),
+            (1, "SourceTimestamp"),
+            (2, "ServerTimestamp"),
+            (3, "SourcePicoSeconds"),
+            (4, "ServerPicoSeconds"),
+            (5, "RawData"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        DataSetFieldContentMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<DataSetFieldContentMask> for Variant {
+    fn from(v: DataSetFieldContentMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum OverrideValueHandling {
@@ -273,6 +452,29 @@ pub enum OverrideValueHandling {
     OverrideValue = 2,
 }
 
+impl TryFrom<i32> for OverrideValueHandling {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Disabled),
+            1 => Ok(Self::LastUsableValue),
+            2 => Ok(Self::OverrideValue),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for OverrideValueHandling {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Disabled"),
+            (1, "LastUsableValue"),
+            (2, "OverrideValue"),
+        ]
+    }
+}
+
 impl BinaryEncoder<OverrideValueHandling> for OverrideValueHandling {
     fn byte_len(&self) -> usize {
         4
@@ -284,15 +486,10 @@ impl BinaryEncoder<OverrideValueHandling> for OverrideValueHandling {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Disabled),
-            1 => Ok(Self::LastUsableValue),
-            2 => Ok(Self::OverrideValue),
-            v => {
-                error!("Invalid value {} for enum OverrideValueHandling", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum OverrideValueHandling", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -303,6 +500,29 @@ pub enum DataSetOrderingType {
     AscendingWriterIdSingle = 2,
 }
 
+impl TryFrom<i32> for DataSetOrderingType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Undefined),
+            1 => Ok(Self::AscendingWriterId),
+            2 => Ok(Self::AscendingWriterIdSingle),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for DataSetOrderingType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Undefined"),
+            (1, "AscendingWriterId"),
+            (2, "AscendingWriterIdSingle"),
+        ]
+    }
+}
+
 impl BinaryEncoder<DataSetOrderingType> for DataSetOrderingType {
     fn byte_len(&self) -> usize {
         4
@@ -314,15 +534,10 @@ impl BinaryEncoder<DataSetOrderingType> for DataSetOrderingType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Undefined),
-            1 => Ok(Self::AscendingWriterId),
-            2 => Ok(Self::AscendingWriterIdSingle),
-            v => {
-                error!("Invalid value {} for enum DataSetOrderingType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum DataSetOrderingType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -357,6 +572,42 @@ impl BinaryEncoder<UadpNetworkMessageContentMask> for UadpNetworkMessageContentM
     }
 }
 
+impl UaOptionSet for UadpNetworkMessageContentMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "PublisherId"),
+            (1, "GroupHeader"),
+            (2, "WriterGroupId"),
+            (3, "GroupVersion"),
+            (4, "NetworkMessageNumber"),
+            (5, "SequenceNumber"),
+            (6, "PayloadHeader"),
+            (7, "Timestamp"),
+            (8, "PicoSeconds"),
+            (9, "DataSetClassId"),
+            (10, "PromotedFields"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        UadpNetworkMessageContentMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<UadpNetworkMessageContentMask> for Variant {
+    fn from(v: UadpNetworkMessageContentMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct UadpDataSetMessageContentMask: i32 {
@@ -384,6 +635,37 @@ impl BinaryEncoder<UadpDataSetMessageContentMask> for UadpDataSetMessageContentM
     }
 }
 
+impl UaOptionSet for UadpDataSetMessageContentMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "Timestamp"),
+            (1, "PicoSeconds"),
+            (2, "Status"),
+            (3, "MajorVersion"),
+            (4, "MinorVersion"),
+            (5, "SequenceNumber"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        UadpDataSetMessageContentMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<UadpDataSetMessageContentMask> for Variant {
+    fn from(v: UadpDataSetMessageContentMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct JsonNetworkMessageContentMask: i32 {
@@ -411,6 +693,37 @@ impl BinaryEncoder<JsonNetworkMessageContentMask> for JsonNetworkMessageContentM
     }
 }
 
+impl UaOptionSet for JsonNetworkMessageContentMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "NetworkMessageHeader"),
+            (1, "DataSetMessageHeader"),
+            (2, "SingleDataSetMessage"),
+            (3, "PublisherId"),
+            (4, "DataSetClassId"),
+            (5, "ReplyTo"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        JsonNetworkMessageContentMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<JsonNetworkMessageContentMask> for Variant {
+    fn from(v: JsonNetworkMessageContentMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct JsonDataSetMessageContentMask: i32 {
@@ -437,6 +750,36 @@ impl BinaryEncoder<JsonDataSetMessageContentMask> for JsonDataSetMessageContentM
     }
 }
 
+impl UaOptionSet for JsonDataSetMessageContentMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "DataSetWriterId"),
+            (1, "MetaDataVersion"),
+            (2, "SequenceNumber"),
+            (3, "Timestamp"),
+            (4, "Status"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        JsonDataSetMessageContentMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<JsonDataSetMessageContentMask> for Variant {
+    fn from(v: JsonDataSetMessageContentMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BrokerTransportQualityOfService {
@@ -447,41 +790,34 @@ pub enum BrokerTransportQualityOfService {
     ExactlyOnce = 4,
 }
 
-impl BinaryEncoder<BrokerTransportQualityOfService> for BrokerTransportQualityOfService {
-    fn byte_len(&self) -> usize {
-        4
-    }
-
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
+impl TryFrom<i32> for BrokerTransportQualityOfService {
+    type Error = StatusCode;
 
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::NotSpecified),
             1 => Ok(Self::BestEffort),
             2 => Ok(Self::AtLeastOnce),
             3 => Ok(Self::AtMostOnce),
             4 => Ok(Self::ExactlyOnce),
-            v => {
-                error!("Invalid value {} for enum BrokerTransportQualityOfService", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum DiagnosticsLevel {
-    Basic = 0,
-    Advanced = 1,
-    Info = 2,
-    Log = 3,
-    Debug = 4,
+impl UaEnum for BrokerTransportQualityOfService {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "NotSpecified"),
+            (1, "BestEffort"),
+            (2, "AtLeastOnce"),
+            (3, "AtMostOnce"),
+            (4, "ExactlyOnce"),
+        ]
+    }
 }
 
-impl BinaryEncoder<DiagnosticsLevel> for DiagnosticsLevel {
+impl BinaryEncoder<BrokerTransportQualityOfService> for BrokerTransportQualityOfService {
     fn byte_len(&self) -> usize {
         4
     }
@@ -492,26 +828,94 @@ impl BinaryEncoder<DiagnosticsLevel> for DiagnosticsLevel {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum BrokerTransportQualityOfService", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DiagnosticsLevel {
+    Basic = 0,
+    Advanced = 1,
+    Info = 2,
+    Log = 3,
+    Debug = 4,
+}
+
+impl TryFrom<i32> for DiagnosticsLevel {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::Basic),
             1 => Ok(Self::Advanced),
             2 => Ok(Self::Info),
             3 => Ok(Self::Log),
             4 => Ok(Self::Debug),
-            v => {
-                error!("Invalid value {} for enum DiagnosticsLevel", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
+impl UaEnum for DiagnosticsLevel {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Basic"),
+            (1, "Advanced"),
+            (2, "Info"),
+            (3, "Log"),
+            (4, "Debug"),
+        ]
+    }
+}
+
+impl BinaryEncoder<DiagnosticsLevel> for DiagnosticsLevel {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum DiagnosticsLevel", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PubSubDiagnosticsCounterClassification {
     Information = 0,
     Error = 1,
 }
 
+impl TryFrom<i32> for PubSubDiagnosticsCounterClassification {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Information),
+            1 => Ok(Self::Error),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for PubSubDiagnosticsCounterClassification {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Information"),
+            (1, "Error"),
+        ]
+    }
+}
+
 impl BinaryEncoder<PubSubDiagnosticsCounterClassification> for PubSubDiagnosticsCounterClassification {
     fn byte_len(&self) -> usize {
         4
@@ -523,14 +927,10 @@ impl BinaryEncoder<PubSubDiagnosticsCounterClassification> for PubSubDiagnostics
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Information),
-            1 => Ok(Self::Error),
-            v => {
-                error!("Invalid value {} for enum PubSubDiagnosticsCounterClassification", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum PubSubDiagnosticsCounterClassification", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -542,6 +942,31 @@ pub enum IdType {
     Opaque = 3,
 }
 
+impl TryFrom<i32> for IdType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Numeric),
+            1 => Ok(Self::String),
+            2 => Ok(Self::Guid),
+            3 => Ok(Self::Opaque),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for IdType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Numeric"),
+            (1, "String"),
+            (2, "Guid"),
+            (3, "Opaque"),
+        ]
+    }
+}
+
 impl BinaryEncoder<IdType> for IdType {
     fn byte_len(&self) -> usize {
         4
@@ -553,16 +978,10 @@ impl BinaryEncoder<IdType> for IdType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Numeric),
-            1 => Ok(Self::String),
-            2 => Ok(Self::Guid),
-            3 => Ok(Self::Opaque),
-            v => {
-                error!("Invalid value {} for enum IdType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum IdType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -579,17 +998,10 @@ pub enum NodeClass {
     View = 128,
 }
 
-impl BinaryEncoder<NodeClass> for NodeClass {
-    fn byte_len(&self) -> usize {
-        4
-    }
-
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
+impl TryFrom<i32> for NodeClass {
+    type Error = StatusCode;
 
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::Unspecified),
             1 => Ok(Self::Object),
@@ -600,14 +1012,45 @@ impl BinaryEncoder<NodeClass> for NodeClass {
             32 => Ok(Self::ReferenceType),
             64 => Ok(Self::DataType),
             128 => Ok(Self::View),
-            v => {
-                error!("Invalid value {} for enum NodeClass", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
+impl UaEnum for NodeClass {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Unspecified"),
+            (1, "Object"),
+            (2, "Variable"),
+            (4, "Method"),
+            (8, "ObjectType"),
+            (16, "VariableType"),
+            (32, "ReferenceType"),
+            (64, "DataType"),
+            (128, "View"),
+        ]
+    }
+}
+
+impl BinaryEncoder<NodeClass> for NodeClass {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum NodeClass", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
 bitflags! {
     pub struct PermissionType: i32 {
         const None = 0;
@@ -645,6 +1088,48 @@ impl BinaryEncoder<PermissionType> for PermissionType {
     }
 }
 
+impl UaOptionSet for PermissionType {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "Browse"),
+            (1, "ReadRolePermissions"),
+            (2, "WriteAttribute"),
+            (3, "WriteRolePermissions"),
+            (4, "WriteHistorizing"),
+            (5, "Read"),
+            (6, "Write"),
+            (7, "ReadHistory"),
+            (8, "InsertHistory"),
+            (9, "ModifyHistory"),
+            (10, "DeleteHistory"),
+            (11, "ReceiveEvents"),
+            (12, "Call"),
+            (13, "AddReference"),
+            (14, "RemoveReference"),
+            (15, "DeleteNode"),
+            (16, "AddNode"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        PermissionType::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<PermissionType> for Variant {
+    fn from(v: PermissionType) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct AccessLevelType: u8 {
@@ -673,6 +1158,38 @@ impl BinaryEncoder<AccessLevelType> for AccessLevelType {
     }
 }
 
+impl UaOptionSet for AccessLevelType {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "CurrentRead"),
+            (1, "CurrentWrite"),
+            (2, "HistoryRead"),
+            (3, "HistoryWrite"),
+            (4, "SemanticChange"),
+            (5, "StatusWrite"),
+            (6, "TimestampWrite"),
+        ]
+    }
+
+    fn size() -> usize {
+        1
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        AccessLevelType::from_bits_truncate(bits as u8)
+    }
+}
+
+impl From<AccessLevelType> for Variant {
+    fn from(v: AccessLevelType) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct AccessLevelExType: i32 {
@@ -705,6 +1222,42 @@ impl BinaryEncoder<AccessLevelExType> for AccessLevelExType {
     }
 }
 
+impl UaOptionSet for AccessLevelExType {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "CurrentRead"),
+            (1, "CurrentWrite"),
+            (2, "HistoryRead"),
+            (3, "HistoryWrite"),
+            (4, "SemanticChange"),
+            (5, "StatusWrite"),
+            (6, "TimestampWrite"),
+            (8, "NonatomicRead"),
+            (9, "NonatomicWrite"),
+            (10, "WriteFullArrayOnly"),
+            (11, "NoSubDataTypes"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        AccessLevelExType::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<AccessLevelExType> for Variant {
+    fn from(v: AccessLevelExType) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct EventNotifierType: u8 {
@@ -729,6 +1282,34 @@ impl BinaryEncoder<EventNotifierType> for EventNotifierType {
     }
 }
 
+impl UaOptionSet for EventNotifierType {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "SubscribeToEvents"),
+            (2, "HistoryRead"),
+            (3, "HistoryWrite"),
+        ]
+    }
+
+    fn size() -> usize {
+        1
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        EventNotifierType::from_bits_truncate(bits as u8)
+    }
+}
+
+impl From<EventNotifierType> for Variant {
+    fn from(v: EventNotifierType) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 bitflags! {
     pub struct AccessRestrictionType: i16 {
@@ -754,6 +1335,35 @@ impl BinaryEncoder<AccessRestrictionType> for AccessRestrictionType {
     }
 }
 
+impl UaOptionSet for AccessRestrictionType {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "SigningRequired"),
+            (1, "EncryptionRequired"),
+            (2, "SessionRequired"),
+            (3, "ApplyRestrictionsToBrowse"),
+        ]
+    }
+
+    fn size() -> usize {
+        2
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u16 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        AccessRestrictionType::from_bits_truncate(bits as u16 as i16)
+    }
+}
+
+impl From<AccessRestrictionType> for Variant {
+    fn from(v: AccessRestrictionType) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum StructureType {
@@ -762,6 +1372,29 @@ pub enum StructureType {
     Union = 2,
 }
 
+impl TryFrom<i32> for StructureType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Structure),
+            1 => Ok(Self::StructureWithOptionalFields),
+            2 => Ok(Self::Union),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for StructureType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Structure"),
+            (1, "StructureWithOptionalFields"),
+            (2, "Union"),
+        ]
+    }
+}
+
 impl BinaryEncoder<StructureType> for StructureType {
     fn byte_len(&self) -> usize {
         4
@@ -773,15 +1406,10 @@ impl BinaryEncoder<StructureType> for StructureType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Structure),
-            1 => Ok(Self::StructureWithOptionalFields),
-            2 => Ok(Self::Union),
-            v => {
-                error!("Invalid value {} for enum StructureType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum StructureType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -793,6 +1421,31 @@ pub enum ApplicationType {
     DiscoveryServer = 3,
 }
 
+impl TryFrom<i32> for ApplicationType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Server),
+            1 => Ok(Self::Client),
+            2 => Ok(Self::ClientAndServer),
+            3 => Ok(Self::DiscoveryServer),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for ApplicationType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Server"),
+            (1, "Client"),
+            (2, "ClientAndServer"),
+            (3, "DiscoveryServer"),
+        ]
+    }
+}
+
 impl BinaryEncoder<ApplicationType> for ApplicationType {
     fn byte_len(&self) -> usize {
         4
@@ -804,16 +1457,10 @@ impl BinaryEncoder<ApplicationType> for ApplicationType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Server),
-            1 => Ok(Self::Client),
-            2 => Ok(Self::ClientAndServer),
-            3 => Ok(Self::DiscoveryServer),
-            v => {
-                error!("Invalid value {} for enum ApplicationType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum ApplicationType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -825,6 +1472,31 @@ pub enum MessageSecurityMode {
     SignAndEncrypt = 3,
 }
 
+impl TryFrom<i32> for MessageSecurityMode {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Invalid),
+            1 => Ok(Self::None),
+            2 => Ok(Self::Sign),
+            3 => Ok(Self::SignAndEncrypt),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for MessageSecurityMode {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Invalid"),
+            (1, "None"),
+            (2, "Sign"),
+            (3, "SignAndEncrypt"),
+        ]
+    }
+}
+
 impl BinaryEncoder<MessageSecurityMode> for MessageSecurityMode {
     fn byte_len(&self) -> usize {
         4
@@ -836,16 +1508,10 @@ impl BinaryEncoder<MessageSecurityMode> for MessageSecurityMode {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Invalid),
-            1 => Ok(Self::None),
-            2 => Ok(Self::Sign),
-            3 => Ok(Self::SignAndEncrypt),
-            v => {
-                error!("Invalid value {} for enum MessageSecurityMode", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum MessageSecurityMode", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -857,6 +1523,31 @@ pub enum UserTokenType {
     IssuedToken = 3,
 }
 
+impl TryFrom<i32> for UserTokenType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Anonymous),
+            1 => Ok(Self::UserName),
+            2 => Ok(Self::Certificate),
+            3 => Ok(Self::IssuedToken),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for UserTokenType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Anonymous"),
+            (1, "UserName"),
+            (2, "Certificate"),
+            (3, "IssuedToken"),
+        ]
+    }
+}
+
 impl BinaryEncoder<UserTokenType> for UserTokenType {
     fn byte_len(&self) -> usize {
         4
@@ -868,16 +1559,10 @@ impl BinaryEncoder<UserTokenType> for UserTokenType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Anonymous),
-            1 => Ok(Self::UserName),
-            2 => Ok(Self::Certificate),
-            3 => Ok(Self::IssuedToken),
-            v => {
-                error!("Invalid value {} for enum UserTokenType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum UserTokenType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -887,6 +1572,27 @@ pub enum SecurityTokenRequestType {
     Renew = 1,
 }
 
+impl TryFrom<i32> for SecurityTokenRequestType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Issue),
+            1 => Ok(Self::Renew),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for SecurityTokenRequestType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Issue"),
+            (1, "Renew"),
+        ]
+    }
+}
+
 impl BinaryEncoder<SecurityTokenRequestType> for SecurityTokenRequestType {
     fn byte_len(&self) -> usize {
         4
@@ -898,14 +1604,10 @@ impl BinaryEncoder<SecurityTokenRequestType> for SecurityTokenRequestType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Issue),
-            1 => Ok(Self::Renew),
-            v => {
-                error!("Invalid value {} for enum SecurityTokenRequestType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum SecurityTokenRequestType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -948,17 +1650,10 @@ pub enum NodeAttributesMask {
     View = 26501356,
 }
 
-impl BinaryEncoder<NodeAttributesMask> for NodeAttributesMask {
-    fn byte_len(&self) -> usize {
-        4
-    }
-
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
+impl TryFrom<i32> for NodeAttributesMask {
+    type Error = StatusCode;
 
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::None),
             1 => Ok(Self::AccessLevel),
@@ -995,17 +1690,74 @@ impl BinaryEncoder<NodeAttributesMask> for NodeAttributesMask {
             26632548 => Ok(Self::Method),
             26537060 => Ok(Self::ReferenceType),
             26501356 => Ok(Self::View),
-            v => {
-                error!("Invalid value {} for enum NodeAttributesMask", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
-bitflags! {
-    pub struct AttributeWriteMask: i32 {
-        const None = 0;
+impl UaEnum for NodeAttributesMask {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "None"),
+            (1, "AccessLevel"),
+            (2, "ArrayDimensions"),
+            (4, "BrowseName"),
+            (8, "ContainsNoLoops"),
+            (16, "DataType"),
+            (32, "Description"),
+            (64, "DisplayName"),
+            (128, "EventNotifier"),
+            (256, "Executable"),
+            (512, "Historizing"),
+            (1024, "InverseName"),
+            (2048, "IsAbstract"),
+            (4096, "MinimumSamplingInterval"),
+            (8192, "NodeClass"),
+            (16384, "NodeId"),
+            (32768, "Symmetric"),
+            (65536, "UserAccessLevel"),
+            (131072, "UserExecutable"),
+            (262144, "UserWriteMask"),
+            (524288, "ValueRank"),
+            (1048576, "WriteMask"),
+            (2097152, "Value"),
+            (4194304, "DataTypeDefinition"),
+            (8388608, "RolePermissions"),
+            (16777216, "AccessRestrictions"),
+            (33554431, "All"),
+            (26501220, "BaseNode"),
+            (26501348, "Object"),
+            (26503268, "ObjectType"),
+            (26571383, "Variable"),
+            (28600438, "VariableType"),
+            (26632548, "Method"),
+            (26537060, "ReferenceType"),
+            (26501356, "View"),
+        ]
+    }
+}
+
+impl BinaryEncoder<NodeAttributesMask> for NodeAttributesMask {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum NodeAttributesMask", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
+bitflags! {
+    pub struct AttributeWriteMask: i32 {
+        const None = 0;
         const AccessLevel = 1;
         const ArrayDimensions = 2;
         const BrowseName = 4;
@@ -1049,6 +1801,57 @@ impl BinaryEncoder<AttributeWriteMask> for AttributeWriteMask {
     }
 }
 
+impl UaOptionSet for AttributeWriteMask {
+    fn options() -> &'static [(u32, &'static str)] {
+        &[
+            (0, "AccessLevel"),
+            (1, "ArrayDimensions"),
+            (2, "BrowseName"),
+            (3, "ContainsNoLoops"),
+            (4, "DataType"),
+            (5, "Description"),
+            (6, "DisplayName"),
+            (7, "EventNotifier"),
+            (8, "Executable"),
+            (9, "Historizing"),
+            (10, "InverseName"),
+            (11, "IsAbstract"),
+            (12, "MinimumSamplingInterval"),
+            (13, "NodeClass"),
+            (14, "NodeId"),
+            (15, "Symmetric"),
+            (16, "UserAccessLevel"),
+            (17, "UserExecutable"),
+            (18, "UserWriteMask"),
+            (19, "ValueRank"),
+            (20, "WriteMask"),
+            (21, "ValueForVariableType"),
+            (22, "DataTypeDefinition"),
+            (23, "RolePermissions"),
+            (24, "AccessRestrictions"),
+            (25, "AccessLevelEx"),
+        ]
+    }
+
+    fn size() -> usize {
+        4
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.bits as u32 as u64
+    }
+
+    fn from_u64(bits: u64) -> Self {
+        AttributeWriteMask::from_bits_truncate(bits as u32 as i32)
+    }
+}
+
+impl From<AttributeWriteMask> for Variant {
+    fn from(v: AttributeWriteMask) -> Self {
+        Variant::from(v.bits)
+    }
+}
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BrowseDirection {
@@ -1058,6 +1861,31 @@ pub enum BrowseDirection {
     Invalid = 3,
 }
 
+impl TryFrom<i32> for BrowseDirection {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Forward),
+            1 => Ok(Self::Inverse),
+            2 => Ok(Self::Both),
+            3 => Ok(Self::Invalid),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for BrowseDirection {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Forward"),
+            (1, "Inverse"),
+            (2, "Both"),
+            (3, "Invalid"),
+        ]
+    }
+}
+
 impl BinaryEncoder<BrowseDirection> for BrowseDirection {
     fn byte_len(&self) -> usize {
         4
@@ -1069,16 +1897,10 @@ impl BinaryEncoder<BrowseDirection> for BrowseDirection {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Forward),
-            1 => Ok(Self::Inverse),
-            2 => Ok(Self::Both),
-            3 => Ok(Self::Invalid),
-            v => {
-                error!("Invalid value {} for enum BrowseDirection", v);
-                Ok(Self::Invalid)
-            }
-        }
+        Ok(Self::try_from(value).unwrap_or_else(|_| {
+            error!("Invalid value {} for enum BrowseDirection", value);
+            Self::Invalid
+        }))
     }
 }
 
@@ -1096,17 +1918,10 @@ pub enum BrowseResultMask {
     TargetInfo = 60,
 }
 
-impl BinaryEncoder<BrowseResultMask> for BrowseResultMask {
-    fn byte_len(&self) -> usize {
-        4
-    }
-
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
+impl TryFrom<i32> for BrowseResultMask {
+    type Error = StatusCode;
 
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::None),
             1 => Ok(Self::ReferenceTypeId),
@@ -1118,15 +1933,47 @@ impl BinaryEncoder<BrowseResultMask> for BrowseResultMask {
             63 => Ok(Self::All),
             3 => Ok(Self::ReferenceTypeInfo),
             60 => Ok(Self::TargetInfo),
-            v => {
-                error!("Invalid value {} for enum BrowseResultMask", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+impl UaEnum for BrowseResultMask {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "None"),
+            (1, "ReferenceTypeId"),
+            (2, "IsForward"),
+            (4, "NodeClass"),
+            (8, "BrowseName"),
+            (16, "DisplayName"),
+            (32, "TypeDefinition"),
+            (63, "All"),
+            (3, "ReferenceTypeInfo"),
+            (60, "TargetInfo"),
+        ]
+    }
+}
+
+impl BinaryEncoder<BrowseResultMask> for BrowseResultMask {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum BrowseResultMask", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FilterOperator {
     Equals = 0,
     IsNull = 1,
@@ -1148,17 +1995,10 @@ pub enum FilterOperator {
     BitwiseOr = 17,
 }
 
-impl BinaryEncoder<FilterOperator> for FilterOperator {
-    fn byte_len(&self) -> usize {
-        4
-    }
-
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
+impl TryFrom<i32> for FilterOperator {
+    type Error = StatusCode;
 
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::Equals),
             1 => Ok(Self::IsNull),
@@ -1178,24 +2018,37 @@ impl BinaryEncoder<FilterOperator> for FilterOperator {
             15 => Ok(Self::RelatedTo),
             16 => Ok(Self::BitwiseAnd),
             17 => Ok(Self::BitwiseOr),
-            v => {
-                error!("Invalid value {} for enum FilterOperator", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
-pub enum TimestampsToReturn {
-    Source = 0,
-    Server = 1,
-    Both = 2,
-    Neither = 3,
-    Invalid = 4,
+impl UaEnum for FilterOperator {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Equals"),
+            (1, "IsNull"),
+            (2, "GreaterThan"),
+            (3, "LessThan"),
+            (4, "GreaterThanOrEqual"),
+            (5, "LessThanOrEqual"),
+            (6, "Like"),
+            (7, "Not"),
+            (8, "Between"),
+            (9, "InList"),
+            (10, "And"),
+            (11, "Or"),
+            (12, "Cast"),
+            (13, "InView"),
+            (14, "OfType"),
+            (15, "RelatedTo"),
+            (16, "BitwiseAnd"),
+            (17, "BitwiseOr"),
+        ]
+    }
 }
 
-impl BinaryEncoder<TimestampsToReturn> for TimestampsToReturn {
+impl BinaryEncoder<FilterOperator> for FilterOperator {
     fn byte_len(&self) -> usize {
         4
     }
@@ -1206,20 +2059,67 @@ impl BinaryEncoder<TimestampsToReturn> for TimestampsToReturn {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum FilterOperator", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimestampsToReturn {
+    Source = 0,
+    Server = 1,
+    Both = 2,
+    Neither = 3,
+    Invalid = 4,
+}
+
+impl TryFrom<i32> for TimestampsToReturn {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::Source),
             1 => Ok(Self::Server),
             2 => Ok(Self::Both),
             3 => Ok(Self::Neither),
             4 => Ok(Self::Invalid),
-            v => {
-                error!("Invalid value {} for enum TimestampsToReturn", v);
-                Ok(Self::Invalid)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
+impl UaEnum for TimestampsToReturn {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Source"),
+            (1, "Server"),
+            (2, "Both"),
+            (3, "Neither"),
+            (4, "Invalid"),
+        ]
+    }
+}
+
+impl BinaryEncoder<TimestampsToReturn> for TimestampsToReturn {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Ok(Self::try_from(value).unwrap_or_else(|_| {
+            error!("Invalid value {} for enum TimestampsToReturn", value);
+            Self::Invalid
+        }))
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum HistoryUpdateType {
     Insert = 1,
@@ -1228,6 +2128,31 @@ pub enum HistoryUpdateType {
     Delete = 4,
 }
 
+impl TryFrom<i32> for HistoryUpdateType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::Insert),
+            2 => Ok(Self::Replace),
+            3 => Ok(Self::Update),
+            4 => Ok(Self::Delete),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for HistoryUpdateType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "Insert"),
+            (2, "Replace"),
+            (3, "Update"),
+            (4, "Delete"),
+        ]
+    }
+}
+
 impl BinaryEncoder<HistoryUpdateType> for HistoryUpdateType {
     fn byte_len(&self) -> usize {
         4
@@ -1239,16 +2164,10 @@ impl BinaryEncoder<HistoryUpdateType> for HistoryUpdateType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::Insert),
-            2 => Ok(Self::Replace),
-            3 => Ok(Self::Update),
-            4 => Ok(Self::Delete),
-            v => {
-                error!("Invalid value {} for enum HistoryUpdateType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum HistoryUpdateType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1260,6 +2179,31 @@ pub enum PerformUpdateType {
     Remove = 4,
 }
 
+impl TryFrom<i32> for PerformUpdateType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::Insert),
+            2 => Ok(Self::Replace),
+            3 => Ok(Self::Update),
+            4 => Ok(Self::Remove),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for PerformUpdateType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "Insert"),
+            (2, "Replace"),
+            (3, "Update"),
+            (4, "Remove"),
+        ]
+    }
+}
+
 impl BinaryEncoder<PerformUpdateType> for PerformUpdateType {
     fn byte_len(&self) -> usize {
         4
@@ -1271,26 +2215,43 @@ impl BinaryEncoder<PerformUpdateType> for PerformUpdateType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::Insert),
-            2 => Ok(Self::Replace),
-            3 => Ok(Self::Update),
-            4 => Ok(Self::Remove),
-            v => {
-                error!("Invalid value {} for enum PerformUpdateType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum PerformUpdateType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MonitoringMode {
     Disabled = 0,
     Sampling = 1,
     Reporting = 2,
 }
 
+impl TryFrom<i32> for MonitoringMode {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Disabled),
+            1 => Ok(Self::Sampling),
+            2 => Ok(Self::Reporting),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for MonitoringMode {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Disabled"),
+            (1, "Sampling"),
+            (2, "Reporting"),
+        ]
+    }
+}
+
 impl BinaryEncoder<MonitoringMode> for MonitoringMode {
     fn byte_len(&self) -> usize {
         4
@@ -1302,25 +2263,43 @@ impl BinaryEncoder<MonitoringMode> for MonitoringMode {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Disabled),
-            1 => Ok(Self::Sampling),
-            2 => Ok(Self::Reporting),
-            v => {
-                error!("Invalid value {} for enum MonitoringMode", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum MonitoringMode", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DataChangeTrigger {
     Status = 0,
     StatusValue = 1,
     StatusValueTimestamp = 2,
 }
 
+impl TryFrom<i32> for DataChangeTrigger {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Status),
+            1 => Ok(Self::StatusValue),
+            2 => Ok(Self::StatusValueTimestamp),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for DataChangeTrigger {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Status"),
+            (1, "StatusValue"),
+            (2, "StatusValueTimestamp"),
+        ]
+    }
+}
+
 impl BinaryEncoder<DataChangeTrigger> for DataChangeTrigger {
     fn byte_len(&self) -> usize {
         4
@@ -1332,15 +2311,10 @@ impl BinaryEncoder<DataChangeTrigger> for DataChangeTrigger {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Status),
-            1 => Ok(Self::StatusValue),
-            2 => Ok(Self::StatusValueTimestamp),
-            v => {
-                error!("Invalid value {} for enum DataChangeTrigger", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum DataChangeTrigger", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1351,6 +2325,29 @@ pub enum DeadbandType {
     Percent = 2,
 }
 
+impl TryFrom<i32> for DeadbandType {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Absolute),
+            2 => Ok(Self::Percent),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for DeadbandType {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "None"),
+            (1, "Absolute"),
+            (2, "Percent"),
+        ]
+    }
+}
+
 impl BinaryEncoder<DeadbandType> for DeadbandType {
     fn byte_len(&self) -> usize {
         4
@@ -1362,15 +2359,10 @@ impl BinaryEncoder<DeadbandType> for DeadbandType {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::None),
-            1 => Ok(Self::Absolute),
-            2 => Ok(Self::Percent),
-            v => {
-                error!("Invalid value {} for enum DeadbandType", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum DeadbandType", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1384,6 +2376,35 @@ pub enum RedundancySupport {
     HotAndMirrored = 5,
 }
 
+impl TryFrom<i32> for RedundancySupport {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Cold),
+            2 => Ok(Self::Warm),
+            3 => Ok(Self::Hot),
+            4 => Ok(Self::Transparent),
+            5 => Ok(Self::HotAndMirrored),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for RedundancySupport {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "None"),
+            (1, "Cold"),
+            (2, "Warm"),
+            (3, "Hot"),
+            (4, "Transparent"),
+            (5, "HotAndMirrored"),
+        ]
+    }
+}
+
 impl BinaryEncoder<RedundancySupport> for RedundancySupport {
     fn byte_len(&self) -> usize {
         4
@@ -1395,18 +2416,10 @@ impl BinaryEncoder<RedundancySupport> for RedundancySupport {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::None),
-            1 => Ok(Self::Cold),
-            2 => Ok(Self::Warm),
-            3 => Ok(Self::Hot),
-            4 => Ok(Self::Transparent),
-            5 => Ok(Self::HotAndMirrored),
-            v => {
-                error!("Invalid value {} for enum RedundancySupport", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum RedundancySupport", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1422,17 +2435,10 @@ pub enum ServerState {
     Unknown = 7,
 }
 
-impl BinaryEncoder<ServerState> for ServerState {
-    fn byte_len(&self) -> usize {
-        4
-    }
+impl TryFrom<i32> for ServerState {
+    type Error = StatusCode;
 
-    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
-        write_i32(stream, *self as i32)
-    }
-
-    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
-        let value = read_i32(stream)?;
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
         match value {
             0 => Ok(Self::Running),
             1 => Ok(Self::Failed),
@@ -1442,14 +2448,44 @@ impl BinaryEncoder<ServerState> for ServerState {
             5 => Ok(Self::Test),
             6 => Ok(Self::CommunicationFault),
             7 => Ok(Self::Unknown),
-            v => {
-                error!("Invalid value {} for enum ServerState", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
+            _ => Err(StatusCode::BadDecodingError),
         }
     }
 }
 
+impl UaEnum for ServerState {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Running"),
+            (1, "Failed"),
+            (2, "NoConfiguration"),
+            (3, "Suspended"),
+            (4, "Shutdown"),
+            (5, "Test"),
+            (6, "CommunicationFault"),
+            (7, "Unknown"),
+        ]
+    }
+}
+
+impl BinaryEncoder<ServerState> for ServerState {
+    fn byte_len(&self) -> usize {
+        4
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        write_i32(stream, *self as i32)
+    }
+
+    fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
+        let value = read_i32(stream)?;
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum ServerState", value);
+            StatusCode::BadDecodingError
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ModelChangeStructureVerbMask {
     NodeAdded = 1,
@@ -1459,6 +2495,33 @@ pub enum ModelChangeStructureVerbMask {
     DataTypeChanged = 16,
 }
 
+impl TryFrom<i32> for ModelChangeStructureVerbMask {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            1 => Ok(Self::NodeAdded),
+            2 => Ok(Self::NodeDeleted),
+            4 => Ok(Self::ReferenceAdded),
+            8 => Ok(Self::ReferenceDeleted),
+            16 => Ok(Self::DataTypeChanged),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for ModelChangeStructureVerbMask {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (1, "NodeAdded"),
+            (2, "NodeDeleted"),
+            (4, "ReferenceAdded"),
+            (8, "ReferenceDeleted"),
+            (16, "DataTypeChanged"),
+        ]
+    }
+}
+
 impl BinaryEncoder<ModelChangeStructureVerbMask> for ModelChangeStructureVerbMask {
     fn byte_len(&self) -> usize {
         4
@@ -1470,17 +2533,10 @@ impl BinaryEncoder<ModelChangeStructureVerbMask> for ModelChangeStructureVerbMas
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            1 => Ok(Self::NodeAdded),
-            2 => Ok(Self::NodeDeleted),
-            4 => Ok(Self::ReferenceAdded),
-            8 => Ok(Self::ReferenceDeleted),
-            16 => Ok(Self::DataTypeChanged),
-            v => {
-                error!("Invalid value {} for enum ModelChangeStructureVerbMask", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum ModelChangeStructureVerbMask", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1491,6 +2547,29 @@ pub enum AxisScaleEnumeration {
     Ln = 2,
 }
 
+impl TryFrom<i32> for AxisScaleEnumeration {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::Linear),
+            1 => Ok(Self::Log),
+            2 => Ok(Self::Ln),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for AxisScaleEnumeration {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "Linear"),
+            (1, "Log"),
+            (2, "Ln"),
+        ]
+    }
+}
+
 impl BinaryEncoder<AxisScaleEnumeration> for AxisScaleEnumeration {
     fn byte_len(&self) -> usize {
         4
@@ -1502,15 +2581,10 @@ impl BinaryEncoder<AxisScaleEnumeration> for AxisScaleEnumeration {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::Linear),
-            1 => Ok(Self::Log),
-            2 => Ok(Self::Ln),
-            v => {
-                error!("Invalid value {} for enum AxisScaleEnumeration", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum AxisScaleEnumeration", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
 
@@ -1523,6 +2597,33 @@ pub enum ExceptionDeviationFormat {
     Unknown = 4,
 }
 
+impl TryFrom<i32> for ExceptionDeviationFormat {
+    type Error = StatusCode;
+
+    fn try_from(value: i32) -> Result<Self, StatusCode> {
+        match value {
+            0 => Ok(Self::AbsoluteValue),
+            1 => Ok(Self::PercentOfValue),
+            2 => Ok(Self::PercentOfRange),
+            3 => Ok(Self::PercentOfEURange),
+            4 => Ok(Self::Unknown),
+            _ => Err(StatusCode::BadDecodingError),
+        }
+    }
+}
+
+impl UaEnum for ExceptionDeviationFormat {
+    fn variants() -> &'static [(i64, &'static str)] {
+        &[
+            (0, "AbsoluteValue"),
+            (1, "PercentOfValue"),
+            (2, "PercentOfRange"),
+            (3, "PercentOfEURange"),
+            (4, "Unknown"),
+        ]
+    }
+}
+
 impl BinaryEncoder<ExceptionDeviationFormat> for ExceptionDeviationFormat {
     fn byte_len(&self) -> usize {
         4
@@ -1534,16 +2635,9 @@ impl BinaryEncoder<ExceptionDeviationFormat> for ExceptionDeviationFormat {
 
     fn decode<S: Read>(stream: &mut S, _: &DecodingOptions) -> EncodingResult<Self> {
         let value = read_i32(stream)?;
-        match value {
-            0 => Ok(Self::AbsoluteValue),
-            1 => Ok(Self::PercentOfValue),
-            2 => Ok(Self::PercentOfRange),
-            3 => Ok(Self::PercentOfEURange),
-            4 => Ok(Self::Unknown),
-            v => {
-                error!("Invalid value {} for enum ExceptionDeviationFormat", v);
-                Err(StatusCode::BadUnexpectedError)
-            }
-        }
+        Self::try_from(value).map_err(|_| {
+            error!("Invalid value {} for enum ExceptionDeviationFormat", value);
+            StatusCode::BadDecodingError
+        })
     }
 }
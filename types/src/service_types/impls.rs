@@ -0,0 +1,853 @@
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+use crate::{
+    attribute::AttributeId,
+    byte_string::ByteString,
+    constants,
+    data_value::DataValue,
+    enumeration::UaOptionSet,
+    extension_object::{ExtensionObject, ExtensionObjectBody},
+    localized_text::LocalizedText,
+    node_id::{ExpandedNodeId, NodeId},
+    node_ids::{DataTypeId, ObjectId},
+    profiles,
+    qualified_name::QualifiedName,
+    request_header::RequestHeader,
+    response_header::ResponseHeader,
+    service_types::{
+        enums::DeadbandType, AnonymousIdentityToken, ApplicationDescription, ApplicationType,
+        Argument, CallMethodRequest, DataChangeFilter, DataChangeTrigger, EndpointDescription,
+        MessageSecurityMode, MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters,
+        OptionSet, ReadValueId, ServerDiagnosticsSummaryDataType, ServiceCounterDataType,
+        ServiceFault, SignatureData, UserNameIdentityToken, UserTokenPolicy, UserTokenType,
+    },
+    status_codes::StatusCode,
+    string::UAString,
+    variant::Variant,
+};
+
+/// Implemented by messages
+pub trait MessageInfo {
+    /// The object id associated with the message
+    fn object_id(&self) -> ObjectId;
+}
+
+impl ServiceFault {
+    pub fn new(request_header: &RequestHeader, service_result: StatusCode) -> ServiceFault {
+        ServiceFault {
+            response_header: ResponseHeader::new_service_result(request_header, service_result),
+        }
+    }
+}
+
+impl UserTokenPolicy {
+    pub fn anonymous() -> UserTokenPolicy {
+        UserTokenPolicy {
+            policy_id: UAString::from("anonymous"),
+            token_type: UserTokenType::Anonymous,
+            issued_token_type: UAString::null(),
+            issuer_endpoint_url: UAString::null(),
+            security_policy_uri: UAString::null(),
+        }
+    }
+}
+
+impl DataChangeFilter {
+    /// Compares one data value to another and returns true if they differ, according to their trigger
+    /// type of status, status/value or status/value/timestamp
+    pub fn compare(&self, v1: &DataValue, v2: &DataValue, eu_range: Option<(f64, f64)>) -> bool {
+        match self.trigger {
+            DataChangeTrigger::Status => v1.status == v2.status,
+            DataChangeTrigger::StatusValue => {
+                v1.status == v2.status && self.compare_value_option(&v1.value, &v2.value, eu_range)
+            }
+            DataChangeTrigger::StatusValueTimestamp => {
+                v1.status == v2.status
+                    && self.compare_value_option(&v1.value, &v2.value, eu_range)
+                    && v1.server_timestamp == v2.server_timestamp
+            }
+        }
+    }
+
+    /// Compares two variant values to each other. Returns true if they are considered the "same".
+    pub fn compare_value_option(
+        &self,
+        v1: &Option<Variant>,
+        v2: &Option<Variant>,
+        eu_range: Option<(f64, f64)>,
+    ) -> bool {
+        match (v1, v2) {
+            (Some(_), None) | (None, Some(_)) => false,
+            (None, None) => {
+                // If it's always none then it hasn't changed
+                true
+            }
+            (Some(v1), Some(v2)) => {
+                // Otherwise test the filter
+                self.compare_value(v1, v2, eu_range).unwrap_or(true)
+            }
+        }
+    }
+
+    /// Compares two values, either a straight value compare or a numeric comparison against the
+    /// deadband settings. If deadband is asked for and the values are not convertible into a numeric
+    /// value, the result is false. The value is true if the values are the same within the limits
+    /// set.
+    ///
+    /// The eu_range is the engineering unit range and represents the range that the value should
+    /// typically operate between. It's used for percentage change operations and ignored otherwise.
+    ///
+    /// # Errors
+    ///
+    /// BadDeadbandFilterInvalid indicates the deadband settings were invalid, e.g. an invalid
+    /// type, or the args were invalid. A (low, high) range must be supplied for a percentage deadband compare.
+    pub fn compare_value(
+        &self,
+        v1: &Variant,
+        v2: &Variant,
+        eu_range: Option<(f64, f64)>,
+    ) -> core::result::Result<bool, StatusCode> {
+        // TODO be able to compare arrays of numbers
+        if self.deadband_type == DeadbandType::None as u32 {
+            // Straight comparison of values
+            Ok(v1 == v2)
+        } else {
+            // Absolute
+            match (v1.as_f64(), v2.as_f64()) {
+                (None, _) | (_, None) => Ok(false),
+                (Some(v1), Some(v2)) => {
+                    if self.deadband_value < 0f64 {
+                        Err(StatusCode::BadDeadbandFilterInvalid)
+                    } else if self.deadband_type == DeadbandType::Absolute as u32 {
+                        Ok(DataChangeFilter::abs_compare(v1, v2, self.deadband_value))
+                    } else if self.deadband_type == DeadbandType::Percent as u32 {
+                        match eu_range {
+                            None => Err(StatusCode::BadDeadbandFilterInvalid),
+                            Some((low, high)) => {
+                                if low >= high {
+                                    Err(StatusCode::BadDeadbandFilterInvalid)
+                                } else {
+                                    Ok(DataChangeFilter::pct_compare(
+                                        v1,
+                                        v2,
+                                        low,
+                                        high,
+                                        self.deadband_value,
+                                    ))
+                                }
+                            }
+                        }
+                    } else {
+                        // Type is not recognized
+                        Err(StatusCode::BadDeadbandFilterInvalid)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compares the difference between v1 and v2 to the threshold. The two values are considered equal
+    /// if their difference is less than or equal to the threshold.
+    pub fn abs_compare(v1: f64, v2: f64, threshold_diff: f64) -> bool {
+        let diff = (v1 - v2).abs();
+        diff <= threshold_diff
+    }
+
+    /// Compares the percentage difference between v1 and v2 using the low-high range as the comparison.
+    /// The two values are considered equal if their perentage difference is less than or equal to the
+    /// threshold.
+    pub fn pct_compare(v1: f64, v2: f64, low: f64, high: f64, threshold_pct_change: f64) -> bool {
+        let v1_pct = 100f64 * (v1 - low) / (high - low);
+        let v2_pct = 100f64 * (v2 - low) / (high - low);
+        let pct_change = (v1_pct - v2_pct).abs();
+        // Comparison is equal if the % change of v1 - v2 < the threshold
+        pct_change <= threshold_pct_change
+    }
+}
+
+impl EndpointDescription {
+    /// Returns a reference to a policy that matches the supplied token type, otherwise None
+    pub fn find_policy(&self, token_type: UserTokenType) -> Option<&UserTokenPolicy> {
+        if let Some(ref policies) = self.user_identity_tokens {
+            policies.iter().find(|t| t.token_type == token_type)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to a policy that matches the supplied policy id
+    pub fn find_policy_by_id(&self, policy_id: &str) -> Option<&UserTokenPolicy> {
+        if let Some(ref policies) = self.user_identity_tokens {
+            policies.iter().find(|t| t.policy_id.as_ref() == policy_id)
+        } else {
+            None
+        }
+    }
+}
+
+impl UserNameIdentityToken {
+    /// Ensures the token is valid
+    pub fn is_valid(&self) -> bool {
+        !self.user_name.is_null() && !self.password.is_null()
+    }
+
+    // Get the plaintext password as a string, if possible.
+    pub fn plaintext_password(&self) -> Result<String, StatusCode> {
+        if !self.encryption_algorithm.is_empty() {
+            // Should not be calling this function at all encryption is applied
+            panic!();
+        }
+        String::from_utf8(self.password.as_ref().to_vec()).map_err(|_| StatusCode::BadDecodingError)
+    }
+
+    /// Authenticates the token against the supplied username and password.
+    pub fn authenticate(&self, username: &str, password: &[u8]) -> Result<(), StatusCode> {
+        // No comparison will be made unless user and pass are explicitly set to something in the token
+        // Even if someone has a blank password, client should pass an empty string, not null.
+        let valid = if self.is_valid() {
+            // Plaintext encryption
+            if self.encryption_algorithm.is_null() {
+                // Password shall be a UTF-8 encoded string
+                let id_user = self.user_name.as_ref();
+                let id_pass = self.password.value.as_ref().unwrap();
+                if username == id_user {
+                    if password == id_pass.as_slice() {
+                        true
+                    } else {
+                        error!("Authentication error: User name {} supplied by client is recognised but password is not", username);
+                        false
+                    }
+                } else {
+                    error!("Authentication error: User name supplied by client is unrecognised");
+                    false
+                }
+            } else {
+                // TODO See 7.36.3. UserTokenPolicy and SecurityPolicy should be used to provide
+                //  a means to encrypt a password and not send it plain text. Sending a plaintext
+                //  password over unsecured network is a bad thing!!!
+                error!(
+                    "Authentication error: Unsupported encryption algorithm {}",
+                    self.encryption_algorithm.as_ref()
+                );
+                false
+            }
+        } else {
+            error!("Authentication error: User / pass credentials not supplied in token");
+            false
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(StatusCode::BadIdentityTokenRejected)
+        }
+    }
+}
+
+impl<'a> From<&'a NodeId> for ReadValueId {
+    fn from(node_id: &'a NodeId) -> Self {
+        Self::from(node_id.clone())
+    }
+}
+
+impl From<NodeId> for ReadValueId {
+    fn from(node_id: NodeId) -> Self {
+        ReadValueId {
+            node_id,
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            data_encoding: QualifiedName::null(),
+        }
+    }
+}
+
+impl<'a> From<(u16, &'a str)> for ReadValueId {
+    fn from(v: (u16, &'a str)) -> Self {
+        Self::from(NodeId::from(v))
+    }
+}
+
+impl Default for AnonymousIdentityToken {
+    fn default() -> Self {
+        AnonymousIdentityToken {
+            policy_id: UAString::from(profiles::SECURITY_USER_TOKEN_POLICY_ANONYMOUS),
+        }
+    }
+}
+
+impl SignatureData {
+    pub fn null() -> SignatureData {
+        SignatureData {
+            algorithm: UAString::null(),
+            signature: ByteString::null(),
+        }
+    }
+}
+
+impl From<NodeId> for MonitoredItemCreateRequest {
+    fn from(value: NodeId) -> Self {
+        MonitoredItemCreateRequest::new(
+            value.into(),
+            MonitoringMode::Reporting,
+            MonitoringParameters::default(),
+        )
+    }
+}
+
+impl MonitoredItemCreateRequest {
+    /// Adds an item to monitor to the subscription
+    pub fn new(
+        item_to_monitor: ReadValueId,
+        monitoring_mode: MonitoringMode,
+        requested_parameters: MonitoringParameters,
+    ) -> MonitoredItemCreateRequest {
+        MonitoredItemCreateRequest {
+            item_to_monitor,
+            monitoring_mode,
+            requested_parameters,
+        }
+    }
+}
+
+impl Default for ApplicationDescription {
+    fn default() -> Self {
+        Self {
+            application_uri: UAString::null(),
+            product_uri: UAString::null(),
+            application_name: LocalizedText::null(),
+            application_type: ApplicationType::Server,
+            gateway_server_uri: UAString::null(),
+            discovery_profile_uri: UAString::null(),
+            discovery_urls: None,
+        }
+    }
+}
+
+impl Default for MonitoringParameters {
+    fn default() -> Self {
+        MonitoringParameters {
+            client_handle: 0,
+            sampling_interval: -1f64,
+            filter: ExtensionObject::null(),
+            queue_size: 1,
+            discard_oldest: true,
+        }
+    }
+}
+
+impl From<(NodeId, NodeId, Option<Vec<Variant>>)> for CallMethodRequest {
+    fn from(value: (NodeId, NodeId, Option<Vec<Variant>>)) -> Self {
+        CallMethodRequest {
+            object_id: value.0,
+            method_id: value.1,
+            input_arguments: value.2,
+        }
+    }
+}
+
+// The struct is generated without a Default derive
+#[allow(clippy::derivable_impls)]
+impl Default for ServerDiagnosticsSummaryDataType {
+    fn default() -> Self {
+        ServerDiagnosticsSummaryDataType {
+            server_view_count: 0,
+            current_session_count: 0,
+            cumulated_session_count: 0,
+            security_rejected_session_count: 0,
+            rejected_session_count: 0,
+            session_timeout_count: 0,
+            session_abort_count: 0,
+            current_subscription_count: 0,
+            cumulated_subscription_count: 0,
+            publishing_interval_count: 0,
+            security_rejected_requests_count: 0,
+            rejected_requests_count: 0,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for EndpointDescription {
+    fn from(v: &'a str) -> Self {
+        EndpointDescription::from((
+            v,
+            constants::SECURITY_POLICY_NONE_URI,
+            MessageSecurityMode::None,
+        ))
+    }
+}
+
+impl<'a> From<(&'a str, &'a str, MessageSecurityMode)> for EndpointDescription {
+    fn from(v: (&'a str, &'a str, MessageSecurityMode)) -> Self {
+        EndpointDescription::from((v.0, v.1, v.2, None))
+    }
+}
+
+impl<'a> From<(&'a str, &'a str, MessageSecurityMode, UserTokenPolicy)> for EndpointDescription {
+    fn from(v: (&'a str, &'a str, MessageSecurityMode, UserTokenPolicy)) -> Self {
+        EndpointDescription::from((v.0, v.1, v.2, Some(vec![v.3])))
+    }
+}
+
+impl<'a> From<(&'a str, &'a str, MessageSecurityMode, Vec<UserTokenPolicy>)>
+    for EndpointDescription
+{
+    fn from(v: (&'a str, &'a str, MessageSecurityMode, Vec<UserTokenPolicy>)) -> Self {
+        EndpointDescription::from((v.0, v.1, v.2, Some(v.3)))
+    }
+}
+
+impl<'a>
+    From<(
+        &'a str,
+        &'a str,
+        MessageSecurityMode,
+        Option<Vec<UserTokenPolicy>>,
+    )> for EndpointDescription
+{
+    fn from(
+        v: (
+            &'a str,
+            &'a str,
+            MessageSecurityMode,
+            Option<Vec<UserTokenPolicy>>,
+        ),
+    ) -> Self {
+        EndpointDescription {
+            endpoint_url: UAString::from(v.0),
+            security_policy_uri: UAString::from(v.1),
+            security_mode: v.2,
+            server: ApplicationDescription::default(),
+            security_level: 0,
+            server_certificate: ByteString::null(),
+            transport_profile_uri: UAString::null(),
+            user_identity_tokens: v.3,
+        }
+    }
+}
+
+const MESSAGE_SECURITY_MODE_NONE: &str = "None";
+const MESSAGE_SECURITY_MODE_SIGN: &str = "Sign";
+const MESSAGE_SECURITY_MODE_SIGN_AND_ENCRYPT: &str = "SignAndEncrypt";
+
+impl fmt::Display for MessageSecurityMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MessageSecurityMode::None => MESSAGE_SECURITY_MODE_NONE,
+            MessageSecurityMode::Sign => MESSAGE_SECURITY_MODE_SIGN,
+            MessageSecurityMode::SignAndEncrypt => MESSAGE_SECURITY_MODE_SIGN_AND_ENCRYPT,
+            _ => "",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<MessageSecurityMode> for String {
+    fn from(security_mode: MessageSecurityMode) -> Self {
+        String::from(match security_mode {
+            MessageSecurityMode::None => MESSAGE_SECURITY_MODE_NONE,
+            MessageSecurityMode::Sign => MESSAGE_SECURITY_MODE_SIGN,
+            MessageSecurityMode::SignAndEncrypt => MESSAGE_SECURITY_MODE_SIGN_AND_ENCRYPT,
+            _ => "",
+        })
+    }
+}
+
+impl<'a> From<&'a str> for MessageSecurityMode {
+    fn from(str: &'a str) -> Self {
+        match str {
+            MESSAGE_SECURITY_MODE_NONE => MessageSecurityMode::None,
+            MESSAGE_SECURITY_MODE_SIGN => MessageSecurityMode::Sign,
+            MESSAGE_SECURITY_MODE_SIGN_AND_ENCRYPT => MessageSecurityMode::SignAndEncrypt,
+            _ => {
+                error!("Specified security mode \"{}\" is not recognized", str);
+                MessageSecurityMode::Invalid
+            }
+        }
+    }
+}
+
+impl From<(&str, DataTypeId)> for Argument {
+    fn from(v: (&str, DataTypeId)) -> Self {
+        Argument {
+            name: UAString::from(v.0),
+            data_type: v.1.into(),
+            value_rank: -1,
+            array_dimensions: None,
+            description: LocalizedText::new("", ""),
+        }
+    }
+}
+
+// The struct is generated without a Default derive
+#[allow(clippy::derivable_impls)]
+impl Default for ServiceCounterDataType {
+    fn default() -> Self {
+        Self {
+            total_count: 0,
+            error_count: 0,
+        }
+    }
+}
+
+impl ServiceCounterDataType {
+    pub fn success(&mut self) {
+        self.total_count += 1;
+    }
+
+    pub fn error(&mut self) {
+        self.total_count += 1;
+        self.error_count += 1;
+    }
+}
+
+macro_rules! extension_object_body_impl {
+    ($($t: ident => $id: ident),* $(,)?) => {
+        $(
+            impl ExtensionObjectBody for crate::service_types::$t {
+                fn binary_encoding_id() -> ExpandedNodeId {
+                    ObjectId::$id.into()
+                }
+            }
+        )*
+    };
+}
+
+extension_object_body_impl! {
+    ActivateSessionRequest => ActivateSessionRequest_Encoding_DefaultBinary,
+    ActivateSessionResponse => ActivateSessionResponse_Encoding_DefaultBinary,
+    AddNodesItem => AddNodesItem_Encoding_DefaultBinary,
+    AddNodesRequest => AddNodesRequest_Encoding_DefaultBinary,
+    AddNodesResponse => AddNodesResponse_Encoding_DefaultBinary,
+    AddNodesResult => AddNodesResult_Encoding_DefaultBinary,
+    AddReferencesItem => AddReferencesItem_Encoding_DefaultBinary,
+    AddReferencesRequest => AddReferencesRequest_Encoding_DefaultBinary,
+    AddReferencesResponse => AddReferencesResponse_Encoding_DefaultBinary,
+    AdditionalParametersType => AdditionalParametersType_Encoding_DefaultBinary,
+    AggregateConfiguration => AggregateConfiguration_Encoding_DefaultBinary,
+    AggregateFilter => AggregateFilter_Encoding_DefaultBinary,
+    AggregateFilterResult => AggregateFilterResult_Encoding_DefaultBinary,
+    AliasNameDataType => AliasNameDataType_Encoding_DefaultBinary,
+    Annotation => Annotation_Encoding_DefaultBinary,
+    AnonymousIdentityToken => AnonymousIdentityToken_Encoding_DefaultBinary,
+    ApplicationDescription => ApplicationDescription_Encoding_DefaultBinary,
+    Argument => Argument_Encoding_DefaultBinary,
+    AttributeOperand => AttributeOperand_Encoding_DefaultBinary,
+    AxisInformation => AxisInformation_Encoding_DefaultBinary,
+    BrokerConnectionTransportDataType => BrokerConnectionTransportDataType_Encoding_DefaultBinary,
+    BrokerDataSetReaderTransportDataType => BrokerDataSetReaderTransportDataType_Encoding_DefaultBinary,
+    BrokerDataSetWriterTransportDataType => BrokerDataSetWriterTransportDataType_Encoding_DefaultBinary,
+    BrokerWriterGroupTransportDataType => BrokerWriterGroupTransportDataType_Encoding_DefaultBinary,
+    BrowseDescription => BrowseDescription_Encoding_DefaultBinary,
+    BrowseNextRequest => BrowseNextRequest_Encoding_DefaultBinary,
+    BrowseNextResponse => BrowseNextResponse_Encoding_DefaultBinary,
+    BrowsePath => BrowsePath_Encoding_DefaultBinary,
+    BrowsePathResult => BrowsePathResult_Encoding_DefaultBinary,
+    BrowsePathTarget => BrowsePathTarget_Encoding_DefaultBinary,
+    BrowseRequest => BrowseRequest_Encoding_DefaultBinary,
+    BrowseResponse => BrowseResponse_Encoding_DefaultBinary,
+    BrowseResult => BrowseResult_Encoding_DefaultBinary,
+    BuildInfo => BuildInfo_Encoding_DefaultBinary,
+    CallMethodRequest => CallMethodRequest_Encoding_DefaultBinary,
+    CallMethodResult => CallMethodResult_Encoding_DefaultBinary,
+    CallRequest => CallRequest_Encoding_DefaultBinary,
+    CallResponse => CallResponse_Encoding_DefaultBinary,
+    CancelRequest => CancelRequest_Encoding_DefaultBinary,
+    CancelResponse => CancelResponse_Encoding_DefaultBinary,
+    CartesianCoordinates => CartesianCoordinates_Encoding_DefaultBinary,
+    ChannelSecurityToken => ChannelSecurityToken_Encoding_DefaultBinary,
+    CloseSecureChannelRequest => CloseSecureChannelRequest_Encoding_DefaultBinary,
+    CloseSecureChannelResponse => CloseSecureChannelResponse_Encoding_DefaultBinary,
+    CloseSessionRequest => CloseSessionRequest_Encoding_DefaultBinary,
+    CloseSessionResponse => CloseSessionResponse_Encoding_DefaultBinary,
+    ComplexNumberType => ComplexNumberType_Encoding_DefaultBinary,
+    ConfigurationVersionDataType => ConfigurationVersionDataType_Encoding_DefaultBinary,
+    ConnectionTransportDataType => ConnectionTransportDataType_Encoding_DefaultBinary,
+    ContentFilter => ContentFilter_Encoding_DefaultBinary,
+    ContentFilterElement => ContentFilterElement_Encoding_DefaultBinary,
+    ContentFilterElementResult => ContentFilterElementResult_Encoding_DefaultBinary,
+    ContentFilterResult => ContentFilterResult_Encoding_DefaultBinary,
+    CreateMonitoredItemsRequest => CreateMonitoredItemsRequest_Encoding_DefaultBinary,
+    CreateMonitoredItemsResponse => CreateMonitoredItemsResponse_Encoding_DefaultBinary,
+    CreateSessionRequest => CreateSessionRequest_Encoding_DefaultBinary,
+    CreateSessionResponse => CreateSessionResponse_Encoding_DefaultBinary,
+    CreateSubscriptionRequest => CreateSubscriptionRequest_Encoding_DefaultBinary,
+    CreateSubscriptionResponse => CreateSubscriptionResponse_Encoding_DefaultBinary,
+    CurrencyUnitType => CurrencyUnitType_Encoding_DefaultBinary,
+    DataChangeFilter => DataChangeFilter_Encoding_DefaultBinary,
+    DataChangeNotification => DataChangeNotification_Encoding_DefaultBinary,
+    DataSetMetaDataType => DataSetMetaDataType_Encoding_DefaultBinary,
+    DataSetReaderDataType => DataSetReaderDataType_Encoding_DefaultBinary,
+    DataSetReaderMessageDataType => DataSetReaderMessageDataType_Encoding_DefaultBinary,
+    DataSetReaderTransportDataType => DataSetReaderTransportDataType_Encoding_DefaultBinary,
+    DataSetWriterDataType => DataSetWriterDataType_Encoding_DefaultBinary,
+    DataSetWriterMessageDataType => DataSetWriterMessageDataType_Encoding_DefaultBinary,
+    DataSetWriterTransportDataType => DataSetWriterTransportDataType_Encoding_DefaultBinary,
+    DataTypeAttributes => DataTypeAttributes_Encoding_DefaultBinary,
+    DataTypeDescription => DataTypeDescription_Encoding_DefaultBinary,
+    DataTypeSchemaHeader => DataTypeSchemaHeader_Encoding_DefaultBinary,
+    DatagramConnectionTransportDataType => DatagramConnectionTransportDataType_Encoding_DefaultBinary,
+    DatagramWriterGroupTransportDataType => DatagramWriterGroupTransportDataType_Encoding_DefaultBinary,
+    DecimalDataType => DecimalDataType_Encoding_DefaultBinary,
+    DeleteAtTimeDetails => DeleteAtTimeDetails_Encoding_DefaultBinary,
+    DeleteEventDetails => DeleteEventDetails_Encoding_DefaultBinary,
+    DeleteMonitoredItemsRequest => DeleteMonitoredItemsRequest_Encoding_DefaultBinary,
+    DeleteMonitoredItemsResponse => DeleteMonitoredItemsResponse_Encoding_DefaultBinary,
+    DeleteNodesItem => DeleteNodesItem_Encoding_DefaultBinary,
+    DeleteNodesRequest => DeleteNodesRequest_Encoding_DefaultBinary,
+    DeleteNodesResponse => DeleteNodesResponse_Encoding_DefaultBinary,
+    DeleteRawModifiedDetails => DeleteRawModifiedDetails_Encoding_DefaultBinary,
+    DeleteReferencesItem => DeleteReferencesItem_Encoding_DefaultBinary,
+    DeleteReferencesRequest => DeleteReferencesRequest_Encoding_DefaultBinary,
+    DeleteReferencesResponse => DeleteReferencesResponse_Encoding_DefaultBinary,
+    DeleteSubscriptionsRequest => DeleteSubscriptionsRequest_Encoding_DefaultBinary,
+    DeleteSubscriptionsResponse => DeleteSubscriptionsResponse_Encoding_DefaultBinary,
+    DiscoveryConfiguration => DiscoveryConfiguration_Encoding_DefaultBinary,
+    DoubleComplexNumberType => DoubleComplexNumberType_Encoding_DefaultBinary,
+    EUInformation => EUInformation_Encoding_DefaultBinary,
+    ElementOperand => ElementOperand_Encoding_DefaultBinary,
+    EndpointConfiguration => EndpointConfiguration_Encoding_DefaultBinary,
+    EndpointDescription => EndpointDescription_Encoding_DefaultBinary,
+    EndpointType => EndpointType_Encoding_DefaultBinary,
+    EndpointUrlListDataType => EndpointUrlListDataType_Encoding_DefaultBinary,
+    EnumDefinition => EnumDefinition_Encoding_DefaultBinary,
+    EnumDescription => EnumDescription_Encoding_DefaultBinary,
+    EnumField => EnumField_Encoding_DefaultBinary,
+    EnumValueType => EnumValueType_Encoding_DefaultBinary,
+    EphemeralKeyType => EphemeralKeyType_Encoding_DefaultBinary,
+    EventFieldList => EventFieldList_Encoding_DefaultBinary,
+    EventFilter => EventFilter_Encoding_DefaultBinary,
+    EventFilterResult => EventFilterResult_Encoding_DefaultBinary,
+    EventNotificationList => EventNotificationList_Encoding_DefaultBinary,
+    FieldMetaData => FieldMetaData_Encoding_DefaultBinary,
+    FieldTargetDataType => FieldTargetDataType_Encoding_DefaultBinary,
+    FilterOperand => FilterOperand_Encoding_DefaultBinary,
+    FindServersOnNetworkRequest => FindServersOnNetworkRequest_Encoding_DefaultBinary,
+    FindServersOnNetworkResponse => FindServersOnNetworkResponse_Encoding_DefaultBinary,
+    FindServersRequest => FindServersRequest_Encoding_DefaultBinary,
+    FindServersResponse => FindServersResponse_Encoding_DefaultBinary,
+    Frame => Frame_Encoding_DefaultBinary,
+    GenericAttributeValue => GenericAttributeValue_Encoding_DefaultBinary,
+    GenericAttributes => GenericAttributes_Encoding_DefaultBinary,
+    GetEndpointsRequest => GetEndpointsRequest_Encoding_DefaultBinary,
+    GetEndpointsResponse => GetEndpointsResponse_Encoding_DefaultBinary,
+    HistoryData => HistoryData_Encoding_DefaultBinary,
+    HistoryEvent => HistoryEvent_Encoding_DefaultBinary,
+    HistoryEventFieldList => HistoryEventFieldList_Encoding_DefaultBinary,
+    HistoryModifiedData => HistoryModifiedData_Encoding_DefaultBinary,
+    HistoryReadDetails => HistoryReadDetails_Encoding_DefaultBinary,
+    HistoryReadRequest => HistoryReadRequest_Encoding_DefaultBinary,
+    HistoryReadResponse => HistoryReadResponse_Encoding_DefaultBinary,
+    HistoryReadResult => HistoryReadResult_Encoding_DefaultBinary,
+    HistoryReadValueId => HistoryReadValueId_Encoding_DefaultBinary,
+    HistoryUpdateDetails => HistoryUpdateDetails_Encoding_DefaultBinary,
+    HistoryUpdateRequest => HistoryUpdateRequest_Encoding_DefaultBinary,
+    HistoryUpdateResponse => HistoryUpdateResponse_Encoding_DefaultBinary,
+    HistoryUpdateResult => HistoryUpdateResult_Encoding_DefaultBinary,
+    IdentityMappingRuleType => IdentityMappingRuleType_Encoding_DefaultBinary,
+    IssuedIdentityToken => IssuedIdentityToken_Encoding_DefaultBinary,
+    JsonDataSetReaderMessageDataType => JsonDataSetReaderMessageDataType_Encoding_DefaultBinary,
+    JsonDataSetWriterMessageDataType => JsonDataSetWriterMessageDataType_Encoding_DefaultBinary,
+    JsonWriterGroupMessageDataType => JsonWriterGroupMessageDataType_Encoding_DefaultBinary,
+    KeyValuePair => KeyValuePair_Encoding_DefaultBinary,
+    LiteralOperand => LiteralOperand_Encoding_DefaultBinary,
+    MdnsDiscoveryConfiguration => MdnsDiscoveryConfiguration_Encoding_DefaultBinary,
+    MethodAttributes => MethodAttributes_Encoding_DefaultBinary,
+    ModelChangeStructureDataType => ModelChangeStructureDataType_Encoding_DefaultBinary,
+    ModificationInfo => ModificationInfo_Encoding_DefaultBinary,
+    ModifyMonitoredItemsRequest => ModifyMonitoredItemsRequest_Encoding_DefaultBinary,
+    ModifyMonitoredItemsResponse => ModifyMonitoredItemsResponse_Encoding_DefaultBinary,
+    ModifySubscriptionRequest => ModifySubscriptionRequest_Encoding_DefaultBinary,
+    ModifySubscriptionResponse => ModifySubscriptionResponse_Encoding_DefaultBinary,
+    MonitoredItemCreateRequest => MonitoredItemCreateRequest_Encoding_DefaultBinary,
+    MonitoredItemCreateResult => MonitoredItemCreateResult_Encoding_DefaultBinary,
+    MonitoredItemModifyRequest => MonitoredItemModifyRequest_Encoding_DefaultBinary,
+    MonitoredItemModifyResult => MonitoredItemModifyResult_Encoding_DefaultBinary,
+    MonitoredItemNotification => MonitoredItemNotification_Encoding_DefaultBinary,
+    MonitoringFilter => MonitoringFilter_Encoding_DefaultBinary,
+    MonitoringFilterResult => MonitoringFilterResult_Encoding_DefaultBinary,
+    MonitoringParameters => MonitoringParameters_Encoding_DefaultBinary,
+    NetworkAddressDataType => NetworkAddressDataType_Encoding_DefaultBinary,
+    NetworkAddressUrlDataType => NetworkAddressUrlDataType_Encoding_DefaultBinary,
+    NetworkGroupDataType => NetworkGroupDataType_Encoding_DefaultBinary,
+    NodeAttributes => NodeAttributes_Encoding_DefaultBinary,
+    NodeReference => NodeReference_Encoding_DefaultBinary,
+    NodeTypeDescription => NodeTypeDescription_Encoding_DefaultBinary,
+    NotificationData => NotificationData_Encoding_DefaultBinary,
+    NotificationMessage => NotificationMessage_Encoding_DefaultBinary,
+    ObjectAttributes => ObjectAttributes_Encoding_DefaultBinary,
+    ObjectTypeAttributes => ObjectTypeAttributes_Encoding_DefaultBinary,
+    OpenSecureChannelRequest => OpenSecureChannelRequest_Encoding_DefaultBinary,
+    OpenSecureChannelResponse => OpenSecureChannelResponse_Encoding_DefaultBinary,
+    OptionSet => OptionSet_Encoding_DefaultBinary,
+    Orientation => Orientation_Encoding_DefaultBinary,
+    ParsingResult => ParsingResult_Encoding_DefaultBinary,
+    ProgramDiagnostic2DataType => ProgramDiagnostic2DataType_Encoding_DefaultBinary,
+    ProgramDiagnosticDataType => ProgramDiagnosticDataType_Encoding_DefaultBinary,
+    PubSubConfigurationDataType => PubSubConfigurationDataType_Encoding_DefaultBinary,
+    PubSubConnectionDataType => PubSubConnectionDataType_Encoding_DefaultBinary,
+    PubSubGroupDataType => PubSubGroupDataType_Encoding_DefaultBinary,
+    PublishRequest => PublishRequest_Encoding_DefaultBinary,
+    PublishResponse => PublishResponse_Encoding_DefaultBinary,
+    PublishedDataItemsDataType => PublishedDataItemsDataType_Encoding_DefaultBinary,
+    PublishedDataSetDataType => PublishedDataSetDataType_Encoding_DefaultBinary,
+    PublishedDataSetSourceDataType => PublishedDataSetSourceDataType_Encoding_DefaultBinary,
+    PublishedEventsDataType => PublishedEventsDataType_Encoding_DefaultBinary,
+    PublishedVariableDataType => PublishedVariableDataType_Encoding_DefaultBinary,
+    QueryDataDescription => QueryDataDescription_Encoding_DefaultBinary,
+    QueryDataSet => QueryDataSet_Encoding_DefaultBinary,
+    QueryFirstRequest => QueryFirstRequest_Encoding_DefaultBinary,
+    QueryFirstResponse => QueryFirstResponse_Encoding_DefaultBinary,
+    QueryNextRequest => QueryNextRequest_Encoding_DefaultBinary,
+    QueryNextResponse => QueryNextResponse_Encoding_DefaultBinary,
+    Range => Range_Encoding_DefaultBinary,
+    RationalNumber => RationalNumber_Encoding_DefaultBinary,
+    ReadAnnotationDataDetails => ReadAnnotationDataDetails_Encoding_DefaultBinary,
+    ReadAtTimeDetails => ReadAtTimeDetails_Encoding_DefaultBinary,
+    ReadEventDetails => ReadEventDetails_Encoding_DefaultBinary,
+    ReadProcessedDetails => ReadProcessedDetails_Encoding_DefaultBinary,
+    ReadRawModifiedDetails => ReadRawModifiedDetails_Encoding_DefaultBinary,
+    ReadRequest => ReadRequest_Encoding_DefaultBinary,
+    ReadResponse => ReadResponse_Encoding_DefaultBinary,
+    ReadValueId => ReadValueId_Encoding_DefaultBinary,
+    ReaderGroupDataType => ReaderGroupDataType_Encoding_DefaultBinary,
+    ReaderGroupMessageDataType => ReaderGroupMessageDataType_Encoding_DefaultBinary,
+    ReaderGroupTransportDataType => ReaderGroupTransportDataType_Encoding_DefaultBinary,
+    RedundantServerDataType => RedundantServerDataType_Encoding_DefaultBinary,
+    ReferenceDescription => ReferenceDescription_Encoding_DefaultBinary,
+    ReferenceTypeAttributes => ReferenceTypeAttributes_Encoding_DefaultBinary,
+    RegisterNodesRequest => RegisterNodesRequest_Encoding_DefaultBinary,
+    RegisterNodesResponse => RegisterNodesResponse_Encoding_DefaultBinary,
+    RegisterServer2Request => RegisterServer2Request_Encoding_DefaultBinary,
+    RegisterServer2Response => RegisterServer2Response_Encoding_DefaultBinary,
+    RegisterServerRequest => RegisterServerRequest_Encoding_DefaultBinary,
+    RegisterServerResponse => RegisterServerResponse_Encoding_DefaultBinary,
+    RegisteredServer => RegisteredServer_Encoding_DefaultBinary,
+    RelativePath => RelativePath_Encoding_DefaultBinary,
+    RelativePathElement => RelativePathElement_Encoding_DefaultBinary,
+    RepublishRequest => RepublishRequest_Encoding_DefaultBinary,
+    RepublishResponse => RepublishResponse_Encoding_DefaultBinary,
+    RolePermissionType => RolePermissionType_Encoding_DefaultBinary,
+    SamplingIntervalDiagnosticsDataType => SamplingIntervalDiagnosticsDataType_Encoding_DefaultBinary,
+    SemanticChangeStructureDataType => SemanticChangeStructureDataType_Encoding_DefaultBinary,
+    ServerDiagnosticsSummaryDataType => ServerDiagnosticsSummaryDataType_Encoding_DefaultBinary,
+    ServerOnNetwork => ServerOnNetwork_Encoding_DefaultBinary,
+    ServerStatusDataType => ServerStatusDataType_Encoding_DefaultBinary,
+    ServiceCounterDataType => ServiceCounterDataType_Encoding_DefaultBinary,
+    ServiceFault => ServiceFault_Encoding_DefaultBinary,
+    SessionDiagnosticsDataType => SessionDiagnosticsDataType_Encoding_DefaultBinary,
+    SessionSecurityDiagnosticsDataType => SessionSecurityDiagnosticsDataType_Encoding_DefaultBinary,
+    SessionlessInvokeRequestType => SessionlessInvokeRequestType_Encoding_DefaultBinary,
+    SessionlessInvokeResponseType => SessionlessInvokeResponseType_Encoding_DefaultBinary,
+    SetMonitoringModeRequest => SetMonitoringModeRequest_Encoding_DefaultBinary,
+    SetMonitoringModeResponse => SetMonitoringModeResponse_Encoding_DefaultBinary,
+    SetPublishingModeRequest => SetPublishingModeRequest_Encoding_DefaultBinary,
+    SetPublishingModeResponse => SetPublishingModeResponse_Encoding_DefaultBinary,
+    SetTriggeringRequest => SetTriggeringRequest_Encoding_DefaultBinary,
+    SetTriggeringResponse => SetTriggeringResponse_Encoding_DefaultBinary,
+    SignatureData => SignatureData_Encoding_DefaultBinary,
+    SignedSoftwareCertificate => SignedSoftwareCertificate_Encoding_DefaultBinary,
+    SimpleAttributeOperand => SimpleAttributeOperand_Encoding_DefaultBinary,
+    SimpleTypeDescription => SimpleTypeDescription_Encoding_DefaultBinary,
+    StatusChangeNotification => StatusChangeNotification_Encoding_DefaultBinary,
+    StatusResult => StatusResult_Encoding_DefaultBinary,
+    StructureDefinition => StructureDefinition_Encoding_DefaultBinary,
+    StructureDescription => StructureDescription_Encoding_DefaultBinary,
+    StructureField => StructureField_Encoding_DefaultBinary,
+    SubscribedDataSetDataType => SubscribedDataSetDataType_Encoding_DefaultBinary,
+    SubscribedDataSetMirrorDataType => SubscribedDataSetMirrorDataType_Encoding_DefaultBinary,
+    SubscriptionAcknowledgement => SubscriptionAcknowledgement_Encoding_DefaultBinary,
+    SubscriptionDiagnosticsDataType => SubscriptionDiagnosticsDataType_Encoding_DefaultBinary,
+    TargetVariablesDataType => TargetVariablesDataType_Encoding_DefaultBinary,
+    ThreeDCartesianCoordinates => ThreeDCartesianCoordinates_Encoding_DefaultBinary,
+    ThreeDFrame => ThreeDFrame_Encoding_DefaultBinary,
+    ThreeDOrientation => ThreeDOrientation_Encoding_DefaultBinary,
+    ThreeDVector => ThreeDVector_Encoding_DefaultBinary,
+    TimeZoneDataType => TimeZoneDataType_Encoding_DefaultBinary,
+    TransferResult => TransferResult_Encoding_DefaultBinary,
+    TransferSubscriptionsRequest => TransferSubscriptionsRequest_Encoding_DefaultBinary,
+    TransferSubscriptionsResponse => TransferSubscriptionsResponse_Encoding_DefaultBinary,
+    TranslateBrowsePathsToNodeIdsRequest => TranslateBrowsePathsToNodeIdsRequest_Encoding_DefaultBinary,
+    TranslateBrowsePathsToNodeIdsResponse => TranslateBrowsePathsToNodeIdsResponse_Encoding_DefaultBinary,
+    TrustListDataType => TrustListDataType_Encoding_DefaultBinary,
+    UABinaryFileDataType => UABinaryFileDataType_Encoding_DefaultBinary,
+    UadpDataSetReaderMessageDataType => UadpDataSetReaderMessageDataType_Encoding_DefaultBinary,
+    UadpDataSetWriterMessageDataType => UadpDataSetWriterMessageDataType_Encoding_DefaultBinary,
+    UadpWriterGroupMessageDataType => UadpWriterGroupMessageDataType_Encoding_DefaultBinary,
+    UnregisterNodesRequest => UnregisterNodesRequest_Encoding_DefaultBinary,
+    UnregisterNodesResponse => UnregisterNodesResponse_Encoding_DefaultBinary,
+    UpdateDataDetails => UpdateDataDetails_Encoding_DefaultBinary,
+    UpdateEventDetails => UpdateEventDetails_Encoding_DefaultBinary,
+    UpdateStructureDataDetails => UpdateStructureDataDetails_Encoding_DefaultBinary,
+    UserIdentityToken => UserIdentityToken_Encoding_DefaultBinary,
+    UserNameIdentityToken => UserNameIdentityToken_Encoding_DefaultBinary,
+    UserTokenPolicy => UserTokenPolicy_Encoding_DefaultBinary,
+    VariableAttributes => VariableAttributes_Encoding_DefaultBinary,
+    VariableTypeAttributes => VariableTypeAttributes_Encoding_DefaultBinary,
+    Vector => Vector_Encoding_DefaultBinary,
+    ViewAttributes => ViewAttributes_Encoding_DefaultBinary,
+    ViewDescription => ViewDescription_Encoding_DefaultBinary,
+    WriteRequest => WriteRequest_Encoding_DefaultBinary,
+    WriteResponse => WriteResponse_Encoding_DefaultBinary,
+    WriteValue => WriteValue_Encoding_DefaultBinary,
+    WriterGroupDataType => WriterGroupDataType_Encoding_DefaultBinary,
+    WriterGroupMessageDataType => WriterGroupMessageDataType_Encoding_DefaultBinary,
+    WriterGroupTransportDataType => WriterGroupTransportDataType_Encoding_DefaultBinary,
+    X509IdentityToken => X509IdentityToken_Encoding_DefaultBinary,
+    XVType => XVType_Encoding_DefaultBinary,
+}
+
+impl OptionSet {
+    /// Creates the structure of an option set, whose valid bits are the bits of its options.
+    pub fn from_option_set<T>(option_set: &T) -> OptionSet
+    where
+        T: UaOptionSet,
+    {
+        let to_bytes = |bits: u64| ByteString::from(bits.to_le_bytes()[..T::size()].to_vec());
+        OptionSet {
+            value: to_bytes(option_set.to_u64()),
+            valid_bits: to_bytes(T::valid_bits()),
+        }
+    }
+
+    /// Returns the bits of the value that are valid as an option set, ignoring any others.
+    pub fn to_option_set<T>(&self) -> T
+    where
+        T: UaOptionSet,
+    {
+        let from_bytes = |bytes: &ByteString| {
+            bytes
+                .as_ref()
+                .iter()
+                .take(8)
+                .enumerate()
+                .fold(0u64, |bits, (i, byte)| bits | (*byte as u64) << (i * 8))
+        };
+        let value = from_bytes(&self.value);
+        // Without valid bits, every bit of the value is taken to be valid
+        let valid_bits = if self.valid_bits.is_null_or_empty() {
+            u64::MAX
+        } else {
+            from_bytes(&self.valid_bits)
+        };
+        T::from_u64(value & valid_bits)
+    }
+
+    /// Tests if a bit of the value is set and valid.
+    pub fn is_set(&self, bit: usize) -> bool {
+        let is_set = |bytes: &ByteString| {
+            bytes
+                .as_ref()
+                .get(bit / 8)
+                .map(|byte| byte & (1 << (bit % 8)) != 0)
+                .unwrap_or(false)
+        };
+        is_set(&self.value) && (self.valid_bits.is_null_or_empty() || is_set(&self.valid_bits))
+    }
+}
+
+impl From<&OptionSet> for Variant {
+    fn from(option_set: &OptionSet) -> Self {
+        ExtensionObject::from_encodable(ObjectId::OptionSet_Encoding_DefaultBinary, option_set)
+            .into()
+    }
+}
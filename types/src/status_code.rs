@@ -5,19 +5,16 @@
 //! Contains the hand implemented part of the StatusCode type. The other file, `status_codes.rs` contains
 //! the machine generated part.
 
-use std::{
-    error::Error,
-    fmt,
-    fmt::Formatter,
-    io::{self, Read, Write},
-};
+use core::{convert::TryFrom, error::Error, fmt, fmt::Formatter};
 
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
-pub use crate::types::{encoding::*, status_codes::StatusCode};
+use crate::io::{Read, Write};
+
+pub use crate::{encoding::*, status_codes::StatusCode};
 
 // The bitflags! macro implements Debug for StatusCode but it fouls the display because status
 // codes are a combination of bits and unique values.
@@ -95,9 +92,10 @@ impl fmt::Debug for StatusCode {
 
 // Serialize / Deserialize are manually implemented because bitflags! doesn't do it.
 
-impl From<StatusCode> for io::Error {
-    fn from(e: StatusCode) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, format!("StatusCode {}", e))
+#[cfg(feature = "std")]
+impl From<StatusCode> for std::io::Error {
+    fn from(e: StatusCode) -> std::io::Error {
+        std::io::Error::other(format!("StatusCode {}", e))
     }
 }
 
@@ -125,6 +123,21 @@ impl<'de> Visitor<'de> for StatusCodeVisitor {
     {
         Ok(value)
     }
+
+    // Self describing formats such as JSON hand over any integer as a 64-bit value
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        u32::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        u32::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
 }
 
 impl<'de> Deserialize<'de> for StatusCode {
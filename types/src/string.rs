@@ -4,16 +4,18 @@
 
 //! Contains the implementation of `UAString`.
 
-use std::{
-    fmt,
-    io::{Read, Write},
+use alloc::{
+    string::{String, ToString},
+    vec,
 };
+use core::fmt;
 
-use crate::types::{
+use crate::{
     encoding::{
         process_decode_io_result, process_encode_io_result, write_i32, BinaryEncoder,
         DecodingOptions, EncodingResult,
     },
+    io::{Read, Write},
     status_codes::StatusCode,
 };
 
@@ -41,25 +43,20 @@ impl fmt::Display for UAString {
 impl BinaryEncoder<UAString> for UAString {
     fn byte_len(&self) -> usize {
         // Length plus the actual string length in bytes for a non-null string.
-        4 + if self.value.is_none() {
-            0
-        } else {
-            self.value.as_ref().unwrap().len()
-        }
+        4 + self.value.as_ref().map_or(0, |v| v.len())
     }
 
     fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
         // Strings are encoded as UTF8 chars preceded by an Int32 length. A -1 indicates a null string
-        if self.value.is_none() {
-            write_i32(stream, -1)
-        } else {
-            let value = self.value.as_ref().unwrap();
+        if let Some(ref value) = self.value {
             let mut size: usize = 0;
             size += write_i32(stream, value.len() as i32)?;
             let buf = value.as_bytes();
             size += process_encode_io_result(stream.write(buf))?;
             assert_eq!(size, self.byte_len());
             Ok(size)
+        } else {
+            write_i32(stream, -1)
         }
     }
 
@@ -132,7 +129,7 @@ impl Default for UAString {
     }
 }
 
-impl<'a, 'b> PartialEq<str> for UAString {
+impl PartialEq<str> for UAString {
     fn eq(&self, other: &str) -> bool {
         match self.value {
             None => false,
@@ -152,19 +149,17 @@ impl UAString {
 
     /// Returns true if the string is null or empty, false otherwise
     pub fn is_empty(&self) -> bool {
-        if self.value.is_none() {
-            true
-        } else {
-            self.value.as_ref().unwrap().is_empty()
+        match self.value {
+            None => true,
+            Some(ref v) => v.is_empty(),
         }
     }
 
     /// Returns the length of the string in bytes or -1 for null.
     pub fn len(&self) -> isize {
-        if self.value.is_none() {
-            -1
-        } else {
-            self.value.as_ref().unwrap().len() as isize
+        match self.value {
+            None => -1,
+            Some(ref v) => v.len() as isize,
         }
     }
 
@@ -182,6 +177,7 @@ impl UAString {
     /// from min up to and inclusive of max. Note that min must have an index within the string
     /// but max is allowed to be beyond the end in which case the remainder of the string is
     /// returned (see docs for NumericRange).
+    #[allow(clippy::result_unit_err)]
     pub fn substring(&self, min: usize, max: usize) -> Result<UAString, ()> {
         if let Some(ref v) = self.value() {
             if min >= v.len() {
@@ -222,6 +218,7 @@ fn string_value() {
 }
 
 #[test]
+#[allow(clippy::comparison_to_empty)]
 fn string_eq() {
     let s = UAString::null();
     assert!(!s.eq(""));
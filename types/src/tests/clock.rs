@@ -0,0 +1,31 @@
+use chrono::{Duration, TimeZone, Utc};
+
+use crate::clock::*;
+
+#[test]
+fn mock_clock() {
+    let start = Utc.ymd(2022, 3, 14).and_hms(12, 0, 0);
+    let clock = MockClock::new(start);
+    assert_eq!(clock.now(), start);
+
+    // The clock does not move by itself
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(clock.now(), start);
+
+    clock.advance(Duration::seconds(30));
+    assert_eq!(clock.now(), start + Duration::seconds(30));
+    clock.advance(Duration::seconds(-10));
+    assert_eq!(clock.now(), start + Duration::seconds(20));
+
+    let later = Utc.ymd(2022, 3, 15).and_hms(0, 0, 0);
+    clock.set(later);
+    assert_eq!(clock.now(), later);
+}
+
+#[test]
+fn system_clock() {
+    let before = Utc::now();
+    let now = SystemClock.now();
+    let after = Utc::now();
+    assert!(before <= now && now <= after);
+}
@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use crate::*;
+
+#[test]
+fn null() {
+    assert_eq!(DateTime::null().checked_ticks(), 0i64);
+}
+
+#[test]
+fn epoch() {
+    let epoch = DateTime::ymd_hms_nano(1601, 1, 1, 0, 0, 0, 0);
+    assert_eq!(epoch.ticks(), 0);
+    assert_eq!(epoch.checked_ticks(), 0);
+
+    let epoch = DateTime::epoch();
+    assert_eq!(epoch.ticks(), 0);
+    assert_eq!(epoch.checked_ticks(), 0);
+}
+
+#[test]
+fn before_epoch() {
+    let epoch = DateTime::ymd_hms_nano(1600, 12, 31, 23, 59, 59, 999_999);
+    assert_eq!(epoch.checked_ticks(), 0);
+}
+
+#[test]
+fn epoch_plus_1tick() {
+    let epoch = DateTime::ymd_hms_nano(1601, 1, 1, 0, 0, 0, 100);
+    assert_eq!(epoch.ticks(), 1);
+}
+
+#[test]
+fn endtimes() {
+    let endtimes = DateTime::ymd_hms_nano(9999, 12, 31, 23, 59, 59, 999_999);
+    assert_eq!(endtimes.checked_ticks(), i64::MAX);
+
+    let endtimes = DateTime::ymd_hms_nano(10000, 1, 1, 0, 0, 0, 0);
+    assert_eq!(endtimes.checked_ticks(), i64::MAX);
+}
+
+#[test]
+fn time() {
+    use chrono::Datelike;
+    let now = DateTime::now();
+    let now = now.as_chrono();
+    assert!(now.year() > 2000 && now.year() < 2050);
+    assert!(now.month() >= 1 && now.month() <= 12);
+}
+
+#[test]
+fn string() {
+    let now = DateTime::now();
+    let now_s = format!("{}", now);
+    let now2 = DateTime::from_str(&now_s).unwrap();
+    // Note: This parsing is potentially lossy so now != now2 and will be off by a small amount
+    // so this code may have to change to compare an interval delta
+    assert_eq!(now, now2);
+}
+
+#[test]
+fn system_time() {
+    use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+    let unix_epoch = DateTime::from(UNIX_EPOCH);
+    assert_eq!(unix_epoch, DateTime::ymd(1970, 1, 1));
+    assert_eq!(SystemTime::from(unix_epoch), UNIX_EPOCH);
+
+    // Only ticks of 100ns survive
+    let system_time = UNIX_EPOCH + StdDuration::from_nanos(1_000_000_123);
+    let dt = DateTime::from(system_time);
+    assert_eq!(
+        SystemTime::from(dt),
+        UNIX_EPOCH + StdDuration::from_nanos(1_000_000_100)
+    );
+}
+
+#[test]
+fn duration_arithmetic() {
+    let mut dt = DateTime::ymd_hms(2020, 2, 28, 12, 0, 0);
+    dt += chrono::Duration::days(1);
+    assert_eq!(dt, DateTime::ymd_hms(2020, 2, 29, 12, 0, 0));
+    dt -= chrono::Duration::hours(12);
+    assert_eq!(dt, DateTime::ymd(2020, 2, 29));
+    assert_eq!(dt - DateTime::ymd(2020, 2, 28), chrono::Duration::days(1));
+
+    assert_eq!(
+        dt.checked_add(chrono::Duration::milliseconds(1)),
+        Some(DateTime::ymd_hms_nano(2020, 2, 29, 0, 0, 0, 1_000_000))
+    );
+    assert!(dt.checked_add(chrono::Duration::max_value()).is_none());
+    assert!(dt.checked_sub(chrono::Duration::max_value()).is_none());
+}
+
+#[test]
+fn clamped() {
+    let before_epoch = DateTime::ymd(1500, 1, 1);
+    assert_eq!(before_epoch.clamped(), DateTime::epoch());
+    assert!(!before_epoch.is_endtimes());
+
+    let after_endtimes = DateTime::ymd(10000, 6, 1);
+    assert_eq!(after_endtimes.clamped(), DateTime::endtimes());
+    assert!(after_endtimes.is_endtimes());
+    assert!(DateTime::endtimes().is_endtimes());
+
+    let dt = DateTime::ymd(2000, 1, 1);
+    assert_eq!(dt.clamped(), dt);
+    assert!(!dt.is_endtimes());
+}
+
+#[test]
+fn picoseconds() {
+    use chrono::{TimeZone, Utc};
+
+    let time = Utc.ymd(2020, 1, 1).and_hms_nano(0, 0, 0, 1_234_567);
+    let (dt, picoseconds) = DateTime::from_chrono_with_picoseconds(time);
+    assert_eq!(dt, DateTime::ymd_hms_nano(2020, 1, 1, 0, 0, 0, 1_234_500));
+    // 67ns is 6700 intervals of 10ps
+    assert_eq!(picoseconds, 6700);
+    assert_eq!(dt.as_chrono_with_picoseconds(picoseconds), time);
+    // Out of range picoseconds are clamped
+    assert_eq!(
+        dt.as_chrono_with_picoseconds(20000),
+        Utc.ymd(2020, 1, 1).and_hms_nano(0, 0, 0, 1_234_599)
+    );
+
+    let mut data_value = DataValue::value_only(1);
+    assert!(data_value.source_time().is_none());
+    data_value.set_source_time(time);
+    assert_eq!(data_value.source_timestamp, Some(dt));
+    assert_eq!(data_value.source_picoseconds, Some(6700));
+    assert_eq!(data_value.source_time(), Some(time));
+    data_value.set_server_time(time);
+    assert_eq!(data_value.server_picoseconds, Some(6700));
+    assert_eq!(data_value.server_time(), Some(time));
+
+    // The picoseconds survive encoding
+    let data_value = crate::tests::serialize_test_and_return(data_value);
+    assert_eq!(data_value.source_time(), Some(time));
+}
@@ -2,7 +2,12 @@ use parking_lot::Mutex;
 use std::sync::Arc;
 use std::{io::Cursor, str::FromStr};
 
-use crate::types::{encoding::DecodingOptions, string::UAString, tests::*};
+use crate::{
+    encoding::DecodingOptions,
+    io::{Read, Write},
+    string::UAString,
+    tests::*,
+};
 
 #[test]
 fn encoding_bool() {
@@ -12,72 +17,72 @@ fn encoding_bool() {
 
 #[test]
 fn encoding_sbyte() {
-    serialize_test(0 as i8);
-    serialize_test(100 as i8);
-    serialize_test(-90 as i8);
+    serialize_test(0_i8);
+    serialize_test(100_i8);
+    serialize_test(-90_i8);
 }
 
 #[test]
 fn encoding_byte() {
-    serialize_test(0 as u8);
-    serialize_test(255 as u8);
-    serialize_test(90 as u8);
+    serialize_test(0_u8);
+    serialize_test(255_u8);
+    serialize_test(90_u8);
 }
 
 #[test]
 fn encoding_int16() {
-    serialize_test(0 as i16);
-    serialize_test(-17000 as i16);
-    serialize_test(32000 as i16);
+    serialize_test(0_i16);
+    serialize_test(-17000_i16);
+    serialize_test(32000_i16);
 }
 
 #[test]
 fn encoding_uint16() {
-    serialize_test(0 as u16);
-    serialize_test(57000 as u16);
-    serialize_test(32000 as u16);
+    serialize_test(0_u16);
+    serialize_test(57000_u16);
+    serialize_test(32000_u16);
 }
 
 #[test]
 fn encoding_int32() {
-    serialize_test(0 as i32);
-    serialize_test(-17444000 as i32);
-    serialize_test(32004440 as i32);
+    serialize_test(0_i32);
+    serialize_test(-17444000_i32);
+    serialize_test(32004440_i32);
 }
 
 #[test]
 fn encoding_uint32() {
-    serialize_test(0 as u32);
-    serialize_test(57055500 as u32);
-    serialize_test(32555000 as u32);
+    serialize_test(0_u32);
+    serialize_test(57055500_u32);
+    serialize_test(32555000_u32);
 }
 
 #[test]
 fn encoding_int64() {
-    serialize_test(0 as i64);
-    serialize_test(-17442224000 as i64);
-    serialize_test(32022204440 as i64);
+    serialize_test(0_i64);
+    serialize_test(-17442224000_i64);
+    serialize_test(32022204440_i64);
 }
 
 #[test]
 fn encoding_uint64() {
-    serialize_test(0 as u64);
-    serialize_test(57054445500 as u64);
-    serialize_test(34442555000 as u64);
+    serialize_test(0_u64);
+    serialize_test(57054445500_u64);
+    serialize_test(34442555000_u64);
 }
 
 #[test]
 fn encoding_f32() {
     serialize_test(0 as f32);
-    serialize_test(12.4342 as f32);
-    serialize_test(5686.222 as f32);
+    serialize_test(12.4342_f32);
+    serialize_test(5686.222_f32);
 }
 
 #[test]
 fn encoding_f64() {
     serialize_test(0 as f64);
-    serialize_test(12.43424324234 as f64);
-    serialize_test(5686.222342342 as f64);
+    serialize_test(12.43424324234_f64);
+    serialize_test(5686.222342342_f64);
 }
 
 #[test]
@@ -153,14 +158,14 @@ fn encode_guid_5226() {
         0x91, 0x2B, 0x96, 0x72, 0x75, 0xFA, 0xE6, 0x4A, 0x8D, 0x28, 0xB4, 0x04, 0xDC, 0x7D, 0xAF,
         0x63,
     ];
-    let guid = Guid::from_str("912b9672-75fa-e64a-8D28-B404DC7DAF63").unwrap();
+    let guid = Guid::from_str("72962B91-FA75-4AE6-8D28-B404DC7DAF63").unwrap();
     serialize_and_compare(guid, &expected_bytes);
 }
 
 #[test]
 fn node_id_2byte_numeric() {
     // Sample from OPCUA Part 6 - 5.2.2.9
-    let node_id = NodeId::new(0, 0x72 as u32);
+    let node_id = NodeId::new(0, 0x72_u32);
     let expected_bytes = [0x0, 0x72];
     serialize_and_compare(node_id.clone(), &expected_bytes);
 
@@ -194,7 +199,7 @@ fn node_id_large_namespace() {
 
 #[test]
 fn node_id_large_id() {
-    let node_id = NodeId::new(1, 0xdeadbeef as u32);
+    let node_id = NodeId::new(1, 0xdeadbeef_u32);
     assert!(node_id.is_numeric());
 
     let expected_bytes = [0x2, 0x1, 0x0, 0xef, 0xbe, 0xad, 0xde];
@@ -219,7 +224,7 @@ fn node_id_string_part_6_5229() {
 
 #[test]
 fn node_id_guid() {
-    let guid = Guid::from_str("912b9672-75fa-e64a-8D28-B404DC7DAF63").unwrap();
+    let guid = Guid::from_str("72962B91-FA75-4AE6-8D28-B404DC7DAF63").unwrap();
     let node_id = NodeId::new(1, guid);
     assert!(node_id.is_guid());
     serialize_test(node_id);
@@ -251,6 +256,154 @@ fn extension_object() {
     serialize_test(eo);
 }
 
+#[derive(Debug, PartialEq)]
+struct CustomStruct {
+    value: i32,
+}
+
+impl BinaryEncoder<CustomStruct> for CustomStruct {
+    fn byte_len(&self) -> usize {
+        self.value.byte_len()
+    }
+
+    fn encode<S: Write>(&self, stream: &mut S) -> EncodingResult<usize> {
+        self.value.encode(stream)
+    }
+
+    fn decode<S: Read>(stream: &mut S, decoding_options: &DecodingOptions) -> EncodingResult<Self> {
+        let value = i32::decode(stream, decoding_options)?;
+        Ok(CustomStruct { value })
+    }
+}
+
+impl ExtensionObjectBody for CustomStruct {
+    fn binary_encoding_id() -> ExpandedNodeId {
+        ExpandedNodeId::with_namespace_uri("urn:custom", 5001)
+    }
+}
+
+#[test]
+fn extension_object_try_decode() {
+    let namespaces =
+        NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:other", "urn:custom"]);
+    let decoding_options = DecodingOptions {
+        namespaces: Arc::new(namespaces.clone()),
+        ..DecodingOptions::test()
+    };
+
+    // A standard type
+    let read_value_id = ReadValueId::from(NodeId::new(1, "Hello"));
+    let eo = ExtensionObject::from_body(&read_value_id, &namespaces).unwrap();
+    assert_eq!(
+        eo.node_id,
+        ObjectId::ReadValueId_Encoding_DefaultBinary.into()
+    );
+    assert!(eo.holds::<ReadValueId>(&namespaces));
+    assert!(!eo.holds::<CustomStruct>(&namespaces));
+    assert_eq!(
+        eo.try_decode::<ReadValueId>(&decoding_options).unwrap(),
+        Some(read_value_id)
+    );
+    assert_eq!(
+        eo.try_decode::<CustomStruct>(&decoding_options).unwrap(),
+        None
+    );
+
+    // A custom type gets the index of its namespace
+    let custom = CustomStruct { value: 42 };
+    let eo = ExtensionObject::from_body(&custom, &namespaces).unwrap();
+    assert_eq!(eo.node_id, NodeId::new(2, 5001));
+    assert_eq!(
+        eo.try_decode::<CustomStruct>(&decoding_options).unwrap(),
+        Some(CustomStruct { value: 42 })
+    );
+    assert!(ExtensionObject::from_body(&custom, &NamespaceTable::new()).is_none());
+
+    // The same node id means something else against another table
+    let other = DecodingOptions {
+        namespaces: Arc::new(NamespaceTable::from_uris(vec![
+            OPC_UA_NAMESPACE_URI,
+            "urn:custom",
+        ])),
+        ..DecodingOptions::test()
+    };
+    assert_eq!(eo.try_decode::<CustomStruct>(&other).unwrap(), None);
+
+    // A malformed body is an error, a null one is not
+    let eo = ExtensionObject {
+        node_id: NodeId::new(2, 5001),
+        body: ExtensionObjectEncoding::ByteString(ByteString::from(b"ab")),
+    };
+    assert_eq!(
+        eo.try_decode::<CustomStruct>(&decoding_options)
+            .unwrap_err(),
+        StatusCode::BadDecodingError
+    );
+    assert_eq!(
+        ExtensionObject::null()
+            .try_decode::<CustomStruct>(&decoding_options)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn extension_object_decode_registered() {
+    let mut type_registry = TypeRegistry::new();
+    type_registry.register::<CustomStruct>();
+    type_registry.register::<CustomStruct>();
+    assert_eq!(type_registry.len(), 1);
+
+    let namespaces = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:custom"]);
+    let decoding_options = DecodingOptions {
+        namespaces: Arc::new(namespaces.clone()),
+        type_registry: Arc::new(type_registry),
+        ..DecodingOptions::test()
+    };
+
+    // The extension object is decoded as part of a variant and its body into the registered type
+    let eo = ExtensionObject::from_body(&CustomStruct { value: 7 }, &namespaces).unwrap();
+    let value = Variant::from(eo).encode_to_vec();
+    let value = Variant::decode(&mut Cursor::new(value), &decoding_options).unwrap();
+    let body = match value {
+        Variant::ExtensionObject(eo) => eo.decode_registered(&decoding_options).unwrap(),
+        _ => panic!(),
+    };
+    assert_eq!(
+        body.unwrap().downcast_ref::<CustomStruct>(),
+        Some(&CustomStruct { value: 7 })
+    );
+
+    // Unregistered types and types in namespaces the table does not have are left alone
+    let eo =
+        ExtensionObject::from_body(&ReadValueId::from(NodeId::new(1, 1)), &namespaces).unwrap();
+    assert!(eo.decode_registered(&decoding_options).unwrap().is_none());
+    let eo = ExtensionObject::from_body(&CustomStruct { value: 7 }, &namespaces).unwrap();
+    let decoding_options = DecodingOptions {
+        namespaces: Arc::new(NamespaceTable::new()),
+        ..decoding_options
+    };
+    assert!(eo.decode_registered(&decoding_options).unwrap().is_none());
+}
+
+#[test]
+fn extension_object_unknown_body() {
+    // A body nothing knows how to decode is encoded again byte for byte
+    let eo = ExtensionObject {
+        node_id: NodeId::new(7, "UnknownType"),
+        body: ExtensionObjectEncoding::ByteString(ByteString::from(b"\x01\x02\x03 unknown")),
+    };
+    let value = Variant::from((
+        VariantTypeId::ExtensionObject,
+        vec![Variant::from(eo.clone())],
+    ));
+    let encoded = value.encode_to_vec();
+    let decoded =
+        Variant::decode(&mut Cursor::new(encoded.clone()), &DecodingOptions::test()).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(decoded.encode_to_vec(), encoded);
+}
+
 #[test]
 fn localized_text() {
     let t = LocalizedText {
@@ -559,12 +712,104 @@ fn deep_encoding() {
     assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
 }
 
+#[test]
+fn deep_encoding_data_value() {
+    let decoding_options = DecodingOptions {
+        decoding_depth_gauge: Arc::new(Mutex::new(DepthGauge {
+            max_depth: 2,
+            current_depth: 0,
+        })),
+        ..Default::default()
+    };
+
+    let d3 = Variant::from(DataValue::from(Variant::from(1)));
+    let d2 = Variant::from(DataValue::from(d3));
+
+    // This should decode
+    let mut stream = serialize_as_stream(d2.clone());
+    assert_eq!(Variant::decode(&mut stream, &decoding_options).unwrap(), d2);
+
+    // This should not decode, too deep
+    let d1 = Variant::from(DataValue::from(d2));
+    let mut stream = serialize_as_stream(d1);
+    let res = Variant::decode(&mut stream, &decoding_options);
+    assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
+
+    // A stream of data values nested in variants without end should not overflow the stack
+    let data: Vec<u8> = [EncodingMask::DATA_VALUE, 0x1].repeat(100000);
+    let res = Variant::decode(&mut Cursor::new(data), &DecodingOptions::test());
+    assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
+}
+
+#[test]
+fn deep_encoding_diagnostic_info() {
+    let decoding_options = DecodingOptions {
+        decoding_depth_gauge: Arc::new(Mutex::new(DepthGauge {
+            max_depth: 2,
+            current_depth: 0,
+        })),
+        ..Default::default()
+    };
+
+    let d3 = DiagnosticInfo {
+        inner_diagnostic_info: Some(Box::new(DiagnosticInfo::null())),
+        ..DiagnosticInfo::null()
+    };
+    let d2 = DiagnosticInfo {
+        inner_diagnostic_info: Some(Box::new(d3)),
+        ..DiagnosticInfo::null()
+    };
+
+    // This should decode
+    let mut stream = serialize_as_stream(d2.clone());
+    assert_eq!(
+        DiagnosticInfo::decode(&mut stream, &decoding_options).unwrap(),
+        d2
+    );
+
+    // This should not decode, too deep
+    let d1 = DiagnosticInfo {
+        inner_diagnostic_info: Some(Box::new(d2)),
+        ..DiagnosticInfo::null()
+    };
+    let mut stream = serialize_as_stream(d1);
+    let res = DiagnosticInfo::decode(&mut stream, &decoding_options);
+    assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
+
+    // Inner diagnostic infos without end should not overflow the stack
+    let data = vec![DiagnosticInfoMask::HAS_INNER_DIAGNOSTIC_INFO.bits(); 100000];
+    let res = DiagnosticInfo::decode(&mut Cursor::new(data), &DecodingOptions::test());
+    assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
+}
+
+#[test]
+fn decode_extreme_date_times() {
+    // Ticks outside of the range of a date time decode without overflowing, even with a client
+    // offset applied on top of them
+    for client_offset in [
+        chrono::Duration::zero(),
+        chrono::Duration::days(1),
+        chrono::Duration::days(-1),
+    ] {
+        let decoding_options = DecodingOptions {
+            client_offset,
+            ..Default::default()
+        };
+        for ticks in [i64::MIN, i64::MIN + 1, i64::MAX - 1] {
+            let data = ticks.encode_to_vec();
+            let date_time = DateTime::decode(&mut Cursor::new(data), &decoding_options).unwrap();
+            let checked_ticks = date_time.checked_ticks();
+            assert!(checked_ticks == 0 || checked_ticks == i64::MAX);
+        }
+    }
+}
+
 #[test]
 fn depth_gauge() {
     let dg = Arc::new(Mutex::new(DepthGauge::default()));
 
     let max_depth = {
-        let dg = trace_lock!(dg);
+        let dg = dg.lock();
         dg.max_depth()
     };
     assert_eq!(max_depth, constants::MAX_DECODING_DEPTH);
@@ -578,7 +823,7 @@ fn depth_gauge() {
 
         // Depth should now be MAX_DECODING_DEPTH
         {
-            let dg = trace_lock!(dg);
+            let dg = dg.lock();
             assert_eq!(dg.current_depth(), max_depth);
         }
 
@@ -593,7 +838,65 @@ fn depth_gauge() {
 
     // Depth should be zero
     {
-        let dg = trace_lock!(dg);
+        let dg = dg.lock();
         assert_eq!(dg.current_depth(), 0);
     }
 }
+
+#[test]
+fn decode_unknown_enum_value() {
+    use std::convert::TryFrom;
+
+    // A discriminant the enum does not know about should be a decoding error, not a panic
+    assert_eq!(MessageSecurityMode::try_from(99), Err(StatusCode::BadDecodingError));
+    let data = 99i32.to_le_bytes().to_vec();
+    let res = MessageSecurityMode::decode(&mut Cursor::new(data), &DecodingOptions::test());
+    assert_eq!(res.unwrap_err(), StatusCode::BadDecodingError);
+
+    // Some enums map unknown values onto their invalid variant instead
+    let data = 99i32.to_le_bytes().to_vec();
+    let res = TimestampsToReturn::decode(&mut Cursor::new(data), &DecodingOptions::test());
+    assert_eq!(res.unwrap(), TimestampsToReturn::Invalid);
+
+    // Known values still round trip
+    serialize_test(MessageSecurityMode::SignAndEncrypt);
+}
+
+#[test]
+fn data_value_encoding_mask() {
+    // Default fields are left out of the encoding
+    let v = DataValue {
+        value: Some(Variant::from(1i32)),
+        status: Some(StatusCode::Good),
+        source_timestamp: Some(DateTime::null()),
+        source_picoseconds: Some(0),
+        server_timestamp: None,
+        server_picoseconds: None,
+    };
+    assert_eq!(v.byte_len(), DataValue::value_only(1i32).byte_len());
+    let decoded = serialize_test_and_return_expected(v, DataValue::value_only(1i32));
+    assert_eq!(decoded.status(), StatusCode::Good);
+
+    // What the builder sets is encoded as it is
+    let now = DateTime::now();
+    let v = DataValue::builder()
+        .value(1i32)
+        .status(StatusCode::GoodClamped)
+        .source_timestamp(now)
+        .server_time(now.as_chrono() + chrono::Duration::nanoseconds(5))
+        .build();
+    assert_eq!(v.source_picoseconds, None);
+    assert_eq!(v.server_picoseconds, Some(500));
+    serialize_test(v);
+
+    // Zero picoseconds are skipped
+    let v = DataValueBuilder::new()
+        .value(1i32)
+        .server_time(now.as_chrono())
+        .build();
+    let expected = DataValue {
+        server_picoseconds: None,
+        ..v.clone()
+    };
+    serialize_test_and_return_expected(v, expected);
+}
@@ -0,0 +1,69 @@
+use crate::*;
+
+#[test]
+fn generated_enum() {
+    let enumeration = Enumeration::from_enum::<MessageSecurityMode>();
+    assert_eq!(
+        enumeration.text(MessageSecurityMode::SignAndEncrypt as i64),
+        Some(&LocalizedText::from("SignAndEncrypt"))
+    );
+    assert_eq!(enumeration.value_of("Sign"), Some(2));
+    assert!(!enumeration.contains(4));
+    // The values run from 0 upwards so they can be enum strings
+    assert_eq!(
+        enumeration.enum_strings().unwrap(),
+        vec![
+            LocalizedText::from("Invalid"),
+            LocalizedText::from("None"),
+            LocalizedText::from("Sign"),
+            LocalizedText::from("SignAndEncrypt"),
+        ]
+    );
+    // These do not start at 0
+    assert!(Enumeration::from_enum::<NamingRuleType>()
+        .enum_strings()
+        .is_none());
+}
+
+#[test]
+fn from_property_value() {
+    // Enum strings
+    let enum_strings = Variant::from((
+        VariantTypeId::LocalizedText,
+        vec![
+            Variant::from(LocalizedText::from("Off")),
+            Variant::from(LocalizedText::from("On")),
+        ],
+    ));
+    let enumeration = Enumeration::from_property_value(&enum_strings).unwrap();
+    assert_eq!(
+        enumeration.text_of(&Variant::from(1u32)),
+        Some(&LocalizedText::from("On"))
+    );
+    assert_eq!(enumeration.text_of(&Variant::from(2u32)), None);
+    assert_eq!(enumeration.text_of(&Variant::from("On")), None);
+
+    // Enum values survive being encoded into a property
+    let enum_values = Enumeration::from_enum_values(vec![
+        EnumValueType {
+            value: -1,
+            display_name: LocalizedText::from("Unknown"),
+            description: LocalizedText::null(),
+        },
+        EnumValueType {
+            value: 10,
+            display_name: LocalizedText::from("High"),
+            description: LocalizedText::null(),
+        },
+    ]);
+    let enumeration = Enumeration::from_property_value(&enum_values.enum_values_variant()).unwrap();
+    assert_eq!(
+        enumeration.text_of(&Variant::from(10i16)).unwrap().text,
+        UAString::from("High")
+    );
+    assert_eq!(enumeration.value_of("Unknown"), Some(-1));
+
+    // Anything else is not an enumeration
+    assert!(Enumeration::from_property_value(&Variant::from(1i32)).is_none());
+    assert!(Enumeration::from_property_value(&Variant::from(vec![1i32, 2i32])).is_none());
+}
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use crate::*;
+
+#[test]
+fn parse_forms() {
+    let expected = Guid::from_fields(
+        0x72962b91,
+        0xfa75,
+        0x4ae6,
+        [0x8d, 0x28, 0xb4, 0x04, 0xdc, 0x7d, 0xaf, 0x63],
+    );
+    [
+        "72962b91-fa75-4ae6-8d28-b404dc7daf63",
+        "72962B91-FA75-4AE6-8D28-B404DC7DAF63",
+        "72962b91fa754ae68d28b404dc7daf63",
+        "{72962b91-fa75-4ae6-8d28-b404dc7daf63}",
+        "urn:uuid:72962b91-fa75-4ae6-8d28-b404dc7daf63",
+        "URN:UUID:72962b91fa754ae68d28b404dc7daf63",
+    ]
+    .iter()
+    .for_each(|s| assert_eq!(Guid::from_str(s).unwrap(), expected, "{}", s));
+
+    [
+        "",
+        "72962b91-fa75-4ae6-8d28-b404dc7daf6",
+        "72962b91-fa75-4ae6-8d28-b404dc7daf633",
+        "72962b91+fa75-4ae6-8d28-b404dc7daf63",
+        "72962b91-fa75-4ae6-8d28-b404dc7daf6g",
+        "+2962b91fa754ae68d28b404dc7daf63",
+        "{72962b91-fa75-4ae6-8d28-b404dc7daf63",
+        "72962b91-fa75-4ae6-8d28-b404dc7d\u{e9}f",
+    ]
+    .iter()
+    .for_each(|s| assert!(Guid::from_str(s).is_err(), "{}", s));
+}
+
+#[test]
+fn format_forms() {
+    let guid = Guid::from_str("72962B91-FA75-4AE6-8D28-B404DC7DAF63").unwrap();
+    assert_eq!(guid.to_string(), "72962b91-fa75-4ae6-8d28-b404dc7daf63");
+    assert_eq!(
+        format!("{:?}", guid),
+        "72962b91-fa75-4ae6-8d28-b404dc7daf63"
+    );
+    assert_eq!(guid.to_simple_string(), "72962b91fa754ae68d28b404dc7daf63");
+    assert_eq!(
+        guid.to_braced_string(),
+        "{72962b91-fa75-4ae6-8d28-b404dc7daf63}"
+    );
+    assert_eq!(
+        guid.to_urn_string(),
+        "urn:uuid:72962b91-fa75-4ae6-8d28-b404dc7daf63"
+    );
+    assert_eq!(
+        Guid::null().to_string(),
+        "00000000-0000-0000-0000-000000000000"
+    );
+}
+
+#[test]
+fn fields() {
+    let guid = Guid::from_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap();
+    assert_eq!(
+        guid.as_fields(),
+        (
+            0x72962b91,
+            0xfa75,
+            0x4ae6,
+            [0x8d, 0x28, 0xb4, 0x04, 0xdc, 0x7d, 0xaf, 0x63]
+        )
+    );
+    assert_eq!(
+        guid.as_bytes(),
+        &[
+            0x72, 0x96, 0x2b, 0x91, 0xfa, 0x75, 0x4a, 0xe6, 0x8d, 0x28, 0xb4, 0x04, 0xdc, 0x7d,
+            0xaf, 0x63
+        ]
+    );
+}
+
+#[test]
+fn random() {
+    let guid = Guid::new();
+    assert!(!guid.is_null());
+    assert_eq!(guid.version(), 4);
+    // The variant is RFC 4122
+    assert_eq!(guid.as_bytes()[8] & 0xc0, 0x80);
+    assert_ne!(guid, Guid::new());
+    assert!(Guid::null().is_null());
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid() {
+    let uuid = uuid::Uuid::parse_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap();
+    let guid = Guid::from(uuid);
+    assert_eq!(guid.to_string(), uuid.to_string());
+    assert_eq!(uuid::Uuid::from(guid), uuid);
+}
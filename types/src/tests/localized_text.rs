@@ -0,0 +1,50 @@
+use crate::*;
+
+#[test]
+fn locale_matching() {
+    let text = LocalizedText::new("de-AT", "Grüß Gott");
+    assert!(text.is_locale("de-AT"));
+    assert!(text.is_locale("DE-at"));
+    assert!(!text.is_locale("de-CH"));
+    assert!(text.is_language_of("de-CH"));
+    assert!(text.is_language_of("de"));
+    assert!(!text.is_language_of("en"));
+    assert!(!LocalizedText::invariant("Hello").is_language_of("en"));
+}
+
+#[test]
+fn select() {
+    let texts = vec![
+        LocalizedText::invariant("Hello"),
+        LocalizedText::new("de", "Hallo"),
+        LocalizedText::new("de-AT", "Servus"),
+        LocalizedText::new("fr-FR", "Bonjour"),
+    ];
+    let select = |locale_ids: &[&str]| {
+        let locale_ids = locale_ids
+            .iter()
+            .map(|l| UAString::from(*l))
+            .collect::<Vec<_>>();
+        LocalizedText::select(&texts, &locale_ids)
+            .map(|text| text.text.as_ref().to_string())
+            .unwrap()
+    };
+    // Exact locale is preferred over the language
+    assert_eq!(select(&["de-AT"]), "Servus");
+    // Language fallback
+    assert_eq!(select(&["de-CH"]), "Hallo");
+    assert_eq!(select(&["fr"]), "Bonjour");
+    // Locale ids are tried in order of preference
+    assert_eq!(select(&["es", "fr-CA", "de"]), "Bonjour");
+    // Otherwise the invariant text
+    assert_eq!(select(&["es"]), "Hello");
+    assert_eq!(select(&[]), "Hello");
+
+    // Otherwise the first text
+    let texts = vec![LocalizedText::new("de", "Hallo")];
+    assert_eq!(
+        LocalizedText::select(&texts, &[UAString::from("en")]),
+        Some(&texts[0])
+    );
+    assert_eq!(LocalizedText::select(&[], &[UAString::from("en")]), None);
+}
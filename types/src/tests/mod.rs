@@ -1,6 +1,13 @@
+mod clock;
 mod date_time;
 mod encoding;
+mod enumeration;
+mod guid;
+mod localized_text;
+mod namespaces;
 mod node_id;
+mod option_set;
+mod qualified_name;
 mod serde;
 mod variant;
 
@@ -8,7 +15,7 @@ use std::cmp::PartialEq;
 use std::fmt::Debug;
 use std::io::Cursor;
 
-use crate::types::{argument::Argument, status_codes::StatusCode, *};
+use crate::{argument::Argument, status_codes::StatusCode, *};
 
 pub fn serialize_test_and_return<T>(value: T) -> T
 where
@@ -0,0 +1,109 @@
+use crate::*;
+
+#[test]
+fn namespace_table_register() {
+    let mut namespaces = NamespaceTable::new();
+    assert_eq!(namespaces.len(), 1);
+    assert_eq!(namespaces.index_of(OPC_UA_NAMESPACE_URI), Some(0));
+
+    // Indices are handed out in order and never change
+    assert_eq!(namespaces.register("urn:a").unwrap(), 1);
+    assert_eq!(namespaces.register("urn:b").unwrap(), 2);
+    assert_eq!(namespaces.register("urn:a").unwrap(), 1);
+    assert_eq!(namespaces.uri(2), Some("urn:b"));
+    assert_eq!(namespaces.uri(3), None);
+    assert_eq!(
+        namespaces.register("").unwrap_err(),
+        StatusCode::BadInvalidArgument
+    );
+
+    let value = Variant::from(&namespaces);
+    assert_eq!(NamespaceTable::from_variant(&value), Some(namespaces));
+    assert_eq!(NamespaceTable::from_variant(&Variant::from(1)), None);
+}
+
+#[test]
+fn namespace_table_import() {
+    let mut namespaces = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:a", "urn:b"]);
+
+    // A node set that uses namespace 1 for urn:b and namespace 2 for urn:c
+    let node_set = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:b", "urn:c"]);
+    let map = namespaces.import(&node_set).unwrap();
+    assert_eq!(namespaces.index_of("urn:c"), Some(3));
+    assert_eq!(map.index(0), Some(0));
+    assert_eq!(map.index(1), Some(2));
+    assert_eq!(map.index(2), Some(3));
+    assert_eq!(map.index(3), None);
+    assert_eq!(
+        map.node_id(&NodeId::new(1, "Pump")),
+        Some(NodeId::new(2, "Pump"))
+    );
+    assert_eq!(
+        map.qualified_name(&QualifiedName::new(2, "Valve")),
+        Some(QualifiedName::new(3, "Valve"))
+    );
+
+    // Mapping without registering leaves unknown namespaces unmapped
+    let other = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:d", "urn:a"]);
+    let map = namespaces.map_from(&other);
+    assert_eq!(map.index(1), None);
+    assert_eq!(map.index(2), Some(1));
+    assert_eq!(namespaces.len(), 4);
+}
+
+#[test]
+fn namespace_table_resolve() {
+    let namespaces = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:a"]);
+    let expanded = namespaces.expand(&NodeId::new(1, 100)).unwrap();
+    assert_eq!(expanded.namespace_uri.as_ref(), "urn:a");
+    assert_eq!(namespaces.resolve(&expanded), Some(NodeId::new(1, 100)));
+
+    let other = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:b", "urn:a"]);
+    assert_eq!(other.resolve(&expanded), Some(NodeId::new(2, 100)));
+    assert_eq!(NamespaceTable::new().resolve(&expanded), None);
+
+    // An expanded node id without a uri already has its index
+    let expanded = ExpandedNodeId::new(NodeId::new(1, 100));
+    assert_eq!(
+        NamespaceTable::new().resolve(&expanded),
+        Some(NodeId::new(1, 100))
+    );
+}
+
+#[test]
+fn server_table_resolve() {
+    let namespaces = NamespaceTable::from_uris(vec![OPC_UA_NAMESPACE_URI, "urn:a"]);
+    let servers = ServerTable::from_uris(vec!["urn:local-server", "urn:remote-server"]);
+    assert_eq!(servers.index_of("urn:remote-server"), Some(1));
+    assert_eq!(servers.uri(0), Some("urn:local-server"));
+
+    // A local node id is resolved against the namespace table
+    let node_id = ExpandedNodeId::with_namespace_uri("urn:a", 100);
+    assert_eq!(
+        servers.resolve(&node_id, &namespaces),
+        Some(ResolvedNodeId::Local(NodeId::new(1, 100)))
+    );
+
+    // A remote node id gets the uri of its server and keeps its namespace uri
+    let remote = servers.expand("urn:remote-server", &node_id).unwrap();
+    assert_eq!(remote.server_index, 1);
+    assert_eq!(namespaces.resolve(&remote), None);
+    assert_eq!(
+        servers.resolve(&remote, &namespaces),
+        Some(ResolvedNodeId::Remote {
+            server_uri: "urn:remote-server".to_string(),
+            node_id,
+        })
+    );
+
+    // Unknown servers
+    let mut unknown = remote.clone();
+    unknown.server_index = 2;
+    assert_eq!(servers.resolve(&unknown, &namespaces), None);
+    assert!(servers.expand("urn:unknown", &remote).is_none());
+
+    // The server array holds strings
+    let value = Variant::from(&servers);
+    assert_eq!(ServerTable::from_variant(&value), Some(servers));
+    assert_eq!(ServerTable::from_variant(&Variant::from(1i32)), None);
+}
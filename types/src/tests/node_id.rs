@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::types::*;
+use crate::*;
 
 #[test]
 fn parse_invalid_node_id() {
@@ -145,3 +145,82 @@ fn expanded_node_id() {
         node_id
     );
 }
+
+#[test]
+fn expanded_node_id_local() {
+    let node_id = ExpandedNodeId::new(NodeId::new(1, "Hello"));
+    assert!(node_id.is_local());
+    assert_eq!(node_id.as_local_node_id(), Some(&NodeId::new(1, "Hello")));
+
+    // A namespace uri must be resolved before the node id can be used
+    let node_id = ExpandedNodeId::with_namespace_uri("urn:a", "Hello");
+    assert!(node_id.is_local());
+    assert_eq!(node_id.namespace_uri.as_ref(), "urn:a");
+    assert_eq!(node_id.as_local_node_id(), None);
+    assert_eq!(format!("{}", node_id), "svr=0;nsu=urn:a;s=Hello");
+
+    // So must a node on another server
+    let node_id = ExpandedNodeId {
+        server_index: 2,
+        ..ExpandedNodeId::new(NodeId::new(1, "Hello"))
+    };
+    assert!(!node_id.is_local());
+    assert_eq!(node_id.as_local_node_id(), None);
+}
+
+#[test]
+fn node_id_string_round_trip() {
+    [
+        NodeId::new(0, 2258u32),
+        NodeId::new(2, "Device.Temp"),
+        NodeId::new(u16::MAX, u32::MAX),
+        NodeId::new(1, "a;b=c"),
+        NodeId::new(0, "ns=1;s=x"),
+        NodeId::new(3, "lines\nof\ntext"),
+        NodeId::new(3, " spaces "),
+        NodeId::new(4, "\u{e9}t\u{e9}"),
+        NodeId::new(5, Guid::new()),
+        NodeId::new(6, ByteString::from(&[0u8, 1, 2, 255])),
+    ]
+    .iter()
+    .for_each(|node_id| {
+        let s = node_id.to_string();
+        assert_eq!(&NodeId::from_str(&s).unwrap(), node_id, "{}", s);
+    });
+
+    assert_eq!(
+        NodeId::new(2, "Device.Temp").to_string(),
+        "ns=2;s=Device.Temp"
+    );
+    assert_eq!(NodeId::new(0, 2258u32).to_string(), "i=2258");
+    assert_eq!(
+        NodeId::from_str("ns=0;i=2258").unwrap().to_string(),
+        "i=2258"
+    );
+    assert_eq!(
+        "ns=2;s=Device.Temp".parse::<NodeId>().unwrap(),
+        NodeId::new(2, "Device.Temp")
+    );
+}
+
+#[test]
+fn expanded_node_id_string_round_trip() {
+    [
+        ExpandedNodeId::new(NodeId::new(0, 2258u32)),
+        ExpandedNodeId {
+            server_index: 3,
+            ..ExpandedNodeId::new(NodeId::new(2, "lines\nof\ntext"))
+        },
+        ExpandedNodeId::with_namespace_uri("urn:a;b%c", "Device.Temp"),
+    ]
+    .iter()
+    .for_each(|node_id| {
+        let s = node_id.to_string();
+        assert_eq!(&ExpandedNodeId::from_str(&s).unwrap(), node_id, "{}", s);
+    });
+
+    assert_eq!(
+        ExpandedNodeId::new(NodeId::new(0, 2258u32)).to_string(),
+        "svr=0;ns=0;i=2258"
+    );
+}
@@ -0,0 +1,89 @@
+use crate::{tests::*, *};
+
+#[test]
+fn generated_option_set() {
+    assert_eq!(AccessLevelType::size(), 1);
+    assert_eq!(AccessLevelType::options()[1], (1, "CurrentWrite"));
+    assert_eq!(AccessLevelType::valid_bits(), 0x7f);
+    assert_eq!(AccessRestrictionType::size(), 2);
+    assert_eq!(AccessRestrictionType::valid_bits(), 0xf);
+
+    let access_level = AccessLevelType::CurrentRead | AccessLevelType::HistoryRead;
+    assert_eq!(access_level.to_u64(), 0x5);
+    // Bits that are not options are dropped
+    assert_eq!(AccessLevelType::from_u64(0xff05), access_level);
+
+    // An option set that is an integer becomes its integer
+    assert_eq!(Variant::from(access_level), Variant::Byte(0x5));
+    assert_eq!(
+        Variant::from(AccessRestrictionType::SigningRequired),
+        Variant::Int16(1)
+    );
+}
+
+#[test]
+fn option_set_from_variant() {
+    let access_level = AccessLevelType::CurrentRead | AccessLevelType::CurrentWrite;
+    assert_eq!(
+        AccessLevelType::from_variant(&Variant::from(access_level)),
+        Some(access_level)
+    );
+    // Any integer will do
+    assert_eq!(
+        AccessLevelType::from_variant(&Variant::UInt32(3)),
+        Some(access_level)
+    );
+    assert_eq!(
+        PermissionType::from_variant(&Variant::Int32(-1)),
+        Some(PermissionType::all())
+    );
+    assert_eq!(AccessLevelType::from_variant(&Variant::Double(3.0)), None);
+    assert_eq!(AccessLevelType::from_variant(&Variant::from("3")), None);
+
+    let option_set = OptionSet::from_option_set(&access_level);
+    assert_eq!(
+        AccessLevelType::from_variant(&Variant::from(&option_set)),
+        Some(access_level)
+    );
+}
+
+#[test]
+fn option_set_structure() {
+    let access_restriction =
+        AccessRestrictionType::SigningRequired | AccessRestrictionType::SessionRequired;
+    let option_set = OptionSet::from_option_set(&access_restriction);
+    assert_eq!(option_set.value, ByteString::from(vec![0x5u8, 0x0]));
+    assert_eq!(option_set.valid_bits, ByteString::from(vec![0xfu8, 0x0]));
+    assert!(option_set.is_set(0));
+    assert!(!option_set.is_set(1));
+    assert!(option_set.is_set(2));
+    assert!(!option_set.is_set(16));
+    assert_eq!(
+        option_set.to_option_set::<AccessRestrictionType>(),
+        access_restriction
+    );
+    serialize_test(option_set);
+
+    // A value bit is ignored unless it is also a valid bit
+    let option_set = OptionSet {
+        value: ByteString::from(vec![0x3u8]),
+        valid_bits: ByteString::from(vec![0x2u8]),
+    };
+    assert!(!option_set.is_set(0));
+    assert!(option_set.is_set(1));
+    assert_eq!(
+        option_set.to_option_set::<AccessLevelType>(),
+        AccessLevelType::CurrentWrite
+    );
+
+    // Without valid bits, every bit of the value is valid
+    let option_set = OptionSet {
+        value: ByteString::from(vec![0x3u8]),
+        valid_bits: ByteString::null(),
+    };
+    assert!(option_set.is_set(0));
+    assert_eq!(
+        option_set.to_option_set::<AccessLevelType>(),
+        AccessLevelType::CurrentRead | AccessLevelType::CurrentWrite
+    );
+}
@@ -0,0 +1,53 @@
+use std::str::FromStr;
+
+use crate::*;
+
+#[test]
+fn parse() {
+    [
+        ("2:MyName", QualifiedName::new(2, "MyName")),
+        ("MyName", QualifiedName::new(0, "MyName")),
+        ("0:MyName", QualifiedName::new(0, "MyName")),
+        ("65535:", QualifiedName::new(65535, "")),
+        // Only a numeric prefix is a namespace index
+        ("Server:Status", QualifiedName::new(0, "Server:Status")),
+        (":MyName", QualifiedName::new(0, ":MyName")),
+        ("1:2:MyName", QualifiedName::new(1, "2:MyName")),
+    ]
+    .iter()
+    .for_each(|(s, expected)| {
+        assert_eq!(&QualifiedName::parse(s).unwrap(), expected);
+        assert_eq!(&QualifiedName::from_str(s).unwrap(), expected);
+    });
+
+    assert_eq!(
+        QualifiedName::parse("65536:MyName").unwrap_err(),
+        StatusCode::BadBrowseNameInvalid
+    );
+}
+
+#[test]
+fn display() {
+    [
+        QualifiedName::new(2, "MyName"),
+        QualifiedName::new(0, "MyName"),
+    ]
+    .iter()
+    .for_each(|name| {
+        assert_eq!(&QualifiedName::parse(&name.to_string()).unwrap(), name);
+    });
+    assert_eq!(QualifiedName::new(0, "MyName").to_string(), "MyName");
+    assert_eq!(QualifiedName::new(3, "MyName").to_string(), "3:MyName");
+}
+
+#[test]
+fn case_sensitive() {
+    assert_ne!(
+        QualifiedName::new(2, "MyName"),
+        QualifiedName::new(2, "myname")
+    );
+    assert_ne!(
+        QualifiedName::new(2, "MyName"),
+        QualifiedName::new(1, "MyName")
+    );
+}
@@ -0,0 +1,149 @@
+use std::str::FromStr;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    data_value::DataValue, date_time::DateTime, guid::Guid, status_codes::StatusCode,
+    variant::Variant, *,
+};
+
+fn round_trip<T>(value: T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let json = serde_json::to_string(&value).unwrap();
+    println!("json = {}", json);
+    assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+}
+
+#[test]
+fn serialize_variant() {
+    let v = Variant::from("Hello");
+    let vs = serde_json::to_string(&v).unwrap();
+    println!("v = {}", vs);
+    assert_eq!(vs, r#"{"String":{"value":"Hello"}}"#);
+
+    let guid = Guid::new();
+    let guid_str = guid.to_string();
+    let v = Variant::from(guid);
+    let vs = serde_json::to_string(&v).unwrap();
+    println!("v = {}", vs);
+    assert_eq!(vs, format!("{{\"Guid\":\"{}\"}}", guid_str));
+
+    let dt = DateTime::now();
+    let ticks = dt.checked_ticks();
+    let v = Variant::from(dt);
+    let vs = serde_json::to_string(&v).unwrap();
+    println!("v = {}", vs);
+    assert_eq!(vs, format!("{{\"DateTime\":{}}}", ticks));
+}
+
+#[test]
+fn serialize_deserialize_date_time() {
+    let dt1 = DateTime::now();
+    let vs = serde_json::to_string(&dt1).unwrap();
+    println!("date_time = {}", vs);
+    let dt2 = serde_json::from_str::<DateTime>(&vs).unwrap();
+    assert_eq!(dt1, dt2);
+}
+
+#[test]
+fn serialize_deserialize_guid() {
+    let g1 = Guid::new();
+    let vs = serde_json::to_string(&g1).unwrap();
+    println!("guid = {}", vs);
+    let g2: Guid = serde_json::from_str(&vs).unwrap();
+    assert_eq!(g1, g2);
+}
+
+#[test]
+fn serialize_data_value() {
+    let source_timestamp = DateTime::now();
+    let server_timestamp = DateTime::now();
+    let dv = DataValue {
+        value: Some(Variant::from(100u16)),
+        status: Some(StatusCode::BadAggregateListMismatch),
+        source_timestamp: Some(source_timestamp),
+        source_picoseconds: Some(123),
+        server_timestamp: Some(server_timestamp),
+        server_picoseconds: Some(456),
+    };
+    let dvs = serde_json::to_string(&dv).unwrap();
+    println!("dv = {}", dvs);
+
+    assert_eq!(dvs, format!("{{\"value\":{{\"UInt16\":100}},\"status\":2161377280,\"source_timestamp\":{},\"source_picoseconds\":123,\"server_timestamp\":{},\"server_picoseconds\":456}}", source_timestamp.checked_ticks(), server_timestamp.checked_ticks()));
+}
+
+#[test]
+fn serialize_deserialize_node_ids() {
+    round_trip(NodeId::new(0, 2253));
+    round_trip(NodeId::new(2, "Hello"));
+    round_trip(NodeId::new(3, Guid::new()));
+    round_trip(NodeId::new(4, ByteString::from(b"abc")));
+    round_trip(ExpandedNodeId::with_namespace_uri("urn:a", 100));
+    round_trip(ExpandedNodeId {
+        server_index: 3,
+        ..ExpandedNodeId::new(NodeId::new(1, 1))
+    });
+}
+
+#[test]
+fn serialize_deserialize_names() {
+    round_trip(QualifiedName::new(2, "Pump"));
+    round_trip(QualifiedName::null());
+    round_trip(LocalizedText::new("en", "Pump"));
+    round_trip(LocalizedText::null());
+}
+
+#[test]
+fn serialize_deserialize_variants() {
+    round_trip(Variant::Empty);
+    round_trip(Variant::from(true));
+    round_trip(Variant::from(-5i64));
+    round_trip(Variant::from(1.5f64));
+    round_trip(Variant::from("Hello"));
+    round_trip(Variant::from(StatusCode::BadNodeIdUnknown));
+    round_trip(Variant::from(NodeId::new(2, "Hello")));
+    round_trip(Variant::from(QualifiedName::new(2, "Pump")));
+    round_trip(Variant::from(LocalizedText::new("en", "Pump")));
+    round_trip(Variant::from(vec![1u32, 2, 3]));
+    round_trip(Variant::from(ExtensionObject::from_encodable(
+        ObjectId::ReadValueId_Encoding_DefaultBinary,
+        &ReadValueId::from(NodeId::new(1, 1)),
+    )));
+}
+
+#[test]
+fn serialize_deserialize_data_value() {
+    round_trip(DataValue {
+        value: Some(Variant::from(100u16)),
+        status: Some(StatusCode::UncertainInitialValue),
+        source_timestamp: Some(DateTime::now()),
+        source_picoseconds: Some(123),
+        server_timestamp: Some(DateTime::now()),
+        server_picoseconds: None,
+    });
+    round_trip(DataValue::null());
+}
+
+#[test]
+fn serialize_deserialize_service_types() {
+    let mut read_value_id = ReadValueId::from(NodeId::new(2, "Pump"));
+    read_value_id.index_range = UAString::from("1:2");
+    round_trip(read_value_id);
+    round_trip(AttributeId::Value);
+    round_trip(TimestampsToReturn::Both);
+    round_trip(NamespaceTable::from_uris(vec![
+        OPC_UA_NAMESPACE_URI,
+        "urn:a",
+    ]));
+}
+
+#[test]
+fn serialize_deserialize_numeric_range() {
+    let range = NumericRange::from_str("1:2,5").unwrap();
+    assert_eq!(serde_json::to_string(&range).unwrap(), r#""1:2,5""#);
+    round_trip(range);
+    round_trip(NumericRange::None);
+    assert!(serde_json::from_str::<NumericRange>(r#""2:1""#).is_err());
+}
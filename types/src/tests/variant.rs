@@ -1,7 +1,7 @@
 use std::convert::TryFrom;
 use std::str::FromStr;
 
-use crate::types::{
+use crate::{
     numeric_range::NumericRange,
     status_code::StatusCode,
     variant::{Variant, VariantTypeId},
@@ -22,8 +22,8 @@ fn is_numeric() {
     assert!(Variant::from(10f32).is_numeric());
     assert!(Variant::from(10f64).is_numeric());
 
-    assert_eq!(Variant::from("foo").is_numeric(), false);
-    assert_eq!(Variant::from(true).is_numeric(), false);
+    assert!(!Variant::from("foo").is_numeric());
+    assert!(!Variant::from(true).is_numeric());
 }
 
 #[test]
@@ -38,7 +38,7 @@ fn size() {
 
 #[test]
 fn variant_type_id() {
-    use crate::types::{
+    use crate::{
         status_codes::StatusCode, ByteString, DateTime, ExpandedNodeId, ExtensionObject, Guid,
         LocalizedText, NodeId, QualifiedName, UAString, XmlElement,
     };
@@ -110,8 +110,7 @@ fn variant_u32_array() {
         Variant::Array(array) => {
             let values = array.values;
             assert_eq!(values.len(), 3);
-            let mut i = 1u32;
-            for v in values {
+            for (i, v) in (1u32..).zip(values) {
                 assert!(v.is_numeric());
                 match v {
                     Variant::UInt32(v) => {
@@ -119,7 +118,6 @@ fn variant_u32_array() {
                     }
                     _ => panic!("Not the expected type"),
                 }
-                i += 1;
             }
         }
         _ => panic!("Not an array"),
@@ -150,8 +148,7 @@ fn variant_i32_array() {
         Variant::Array(array) => {
             let values = array.values;
             assert_eq!(values.len(), 3);
-            let mut i = 1;
-            for v in values {
+            for (i, v) in (1..).zip(values) {
                 assert!(v.is_numeric());
                 match v {
                     Variant::Int32(v) => {
@@ -159,7 +156,6 @@ fn variant_i32_array() {
                     }
                     _ => panic!("Not the expected type"),
                 }
-                i += 1;
             }
         }
         _ => panic!("Not an array"),
@@ -1039,7 +1035,7 @@ fn variant_cast_uint32() {
         | StatusCode::HISTORICAL_RAW
         | StatusCode::SEMANTICS_CHANGED;
     assert_eq!(
-        Variant::from(status_code.bits() as u32).cast(VariantTypeId::StatusCode),
+        Variant::from(status_code.bits()).cast(VariantTypeId::StatusCode),
         Variant::from(status_code)
     );
     // String
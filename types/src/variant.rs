@@ -4,21 +4,17 @@
 
 //! Contains the implementation of `Variant`.
 
-use std::{
-    convert::TryFrom,
-    fmt,
-    io::{Read, Write},
-    str::FromStr,
-    {i16, i32, i64, i8, u16, u32, u64, u8},
-};
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+use core::{convert::TryFrom, fmt, str::FromStr};
 
-use crate::types::{
+use crate::{
     array::*,
     byte_string::ByteString,
     date_time::DateTime,
     encoding::*,
     extension_object::ExtensionObject,
     guid::Guid,
+    io::{Read, Write},
     localized_text::LocalizedText,
     node_id::{ExpandedNodeId, Identifier, NodeId},
     node_ids::DataTypeId,
@@ -72,9 +68,10 @@ impl EncodingMask {
 /// As variants may be passed around a lot on the stack, Boxes are used for more complex types to
 /// keep the size of this type down a bit, especially when used in arrays.
 ///
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
 pub enum Variant {
     /// Empty type has no value. It is equivalent to a Null value (part 6 5.1.6)
+    #[default]
     Empty,
     /// Boolean
     Boolean(bool),
@@ -805,7 +802,7 @@ impl BinaryEncoder<Variant> for Variant {
             let value_type_id = VariantTypeId::from_encoding_mask(element_encoding_mask)?;
             if encoding_mask & EncodingMask::ARRAY_DIMENSIONS_BIT != 0 {
                 if let Some(dimensions) = read_array(stream, decoding_options)? {
-                    if dimensions.iter().any(|d| *d == 0) {
+                    if dimensions.contains(&0) {
                         error!("Invalid array dimensions");
                         Err(StatusCode::BadDecodingError)
                     } else {
@@ -849,12 +846,6 @@ impl BinaryEncoder<Variant> for Variant {
     }
 }
 
-impl Default for Variant {
-    fn default() -> Self {
-        Variant::Empty
-    }
-}
-
 /// This implementation is mainly for debugging / convenience purposes, to eliminate some of the
 /// noise in common types from using the Debug trait.
 impl fmt::Display for Variant {
@@ -885,7 +876,7 @@ impl fmt::Display for Variant {
 impl Variant {
     /// Test the flag (convenience method)
     pub fn test_encoding_flag(encoding_mask: u8, flag: u8) -> bool {
-        encoding_mask == flag as u8
+        encoding_mask == flag
     }
 
     /// Returns the length of just the value, not the encoding flag
@@ -1019,8 +1010,11 @@ impl Variant {
             let _depth_lock = decoding_options.depth_lock()?;
             Variant::Variant(Box::new(Variant::decode(stream, decoding_options)?))
         } else if Self::test_encoding_flag(encoding_mask, EncodingMask::DATA_VALUE) {
+            // Data value holds a variant so it is depth checked to prevent deep recursion
+            let _depth_lock = decoding_options.depth_lock()?;
             Self::from(DataValue::decode(stream, decoding_options)?)
         } else if Self::test_encoding_flag(encoding_mask, EncodingMask::DIAGNOSTIC) {
+            // Diagnostic info internally does depth checking to prevent deep recursion
             Self::from(DiagnosticInfo::decode(stream, decoding_options)?)
         } else {
             Variant::Empty
@@ -1047,7 +1041,7 @@ impl Variant {
                 },
                 Variant::Double(v) => {
                     // Truncated value used in integer conversions
-                    let vt = f64::trunc(v + 0.5);
+                    let vt = libm::trunc(v + 0.5);
                     match target_type {
                         VariantTypeId::Boolean => cast_to_bool!(v as i64),
                         VariantTypeId::Byte => cast_to_integer!(vt, f64, u8),
@@ -1078,7 +1072,7 @@ impl Variant {
                     _ => Variant::Empty,
                 },
                 Variant::Float(v) => {
-                    let vt = f32::trunc(v + 0.5);
+                    let vt = libm::truncf(v + 0.5);
                     match target_type {
                         VariantTypeId::Boolean => cast_to_bool!(v as i64),
                         VariantTypeId::Byte => cast_to_integer!(vt, f32, u8),
@@ -1358,7 +1352,7 @@ impl Variant {
             Variant::StatusCode(v) => match target_type {
                 VariantTypeId::Int32 => (v.bits() as i32).into(),
                 VariantTypeId::Int64 => (v.bits() as i64).into(),
-                VariantTypeId::UInt32 => (v.bits() as u32).into(),
+                VariantTypeId::UInt32 => v.bits().into(),
                 VariantTypeId::UInt64 => (v.bits() as u64).into(),
                 _ => Variant::Empty,
             },
@@ -1732,8 +1726,11 @@ impl Variant {
                     NumericRange::None => Err(StatusCode::BadIndexRangeNoData),
                     NumericRange::Index(idx) => {
                         let idx = idx as usize;
-                        if idx >= values.len() || other_values.is_empty() {
+                        if idx >= values.len() {
                             Err(StatusCode::BadIndexRangeNoData)
+                        } else if other_values.len() != 1 {
+                            // The value must have exactly the elements of the range
+                            Err(StatusCode::BadIndexRangeInvalid)
                         } else {
                             values[idx] = other_values[0].clone();
                             Ok(())
@@ -1741,17 +1738,13 @@ impl Variant {
                     }
                     NumericRange::Range(min, max) => {
                         let (min, max) = (min as usize, max as usize);
-                        if min >= values.len() {
+                        if max >= values.len() {
                             Err(StatusCode::BadIndexRangeNoData)
+                        } else if other_values.len() != max - min + 1 {
+                            // The value must have exactly the elements of the range
+                            Err(StatusCode::BadIndexRangeInvalid)
                         } else {
-                            // Possibly this could splice or something but it's trying to copy elements
-                            // until either the source or destination array is finished.
-                            let mut idx = min;
-                            while idx < values.len() && idx <= max && idx - min < other_values.len()
-                            {
-                                values[idx] = other_values[idx - min].clone();
-                                idx += 1;
-                            }
+                            values[min..=max].clone_from_slice(other_values);
                             Ok(())
                         }
                     }
@@ -1806,7 +1799,7 @@ impl Variant {
                             } else {
                                 max
                             };
-                            let values = &values[min as usize..=max];
+                            let values = &values[min..=max];
                             let values: Vec<Variant> = values.to_vec();
                             Ok(Variant::from((array.value_type, values)))
                         }